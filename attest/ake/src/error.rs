@@ -39,6 +39,8 @@ pub enum Error {
     EncryptError(CipherError),
     #[fail(display = "The message could not be decrypted: {}", _0)]
     DecryptError(CipherError),
+    #[fail(display = "The remote handshake message did not carry our AKE protocol version")]
+    ProtocolVersionMismatch,
     #[fail(display = "Unknown error while initiating a new AKE")]
     Unknown,
 }
@@ -6,11 +6,12 @@ use crate::{
     error::Error,
     event::{AuthRequestOutput, AuthResponse, AuthSuccess, ClientInitiate, NodeInitiate},
     mealy::Transition,
+    shared::{unversioned_payload, versioned_payload, ProtocolVersion},
     state::{AuthPending, Ready, Start},
 };
 use aead::{AeadMut, NewAead};
 use alloc::{string::String, vec::Vec};
-use attest::{Measurement, QuoteSignType, ReportDataMask, VerificationReport};
+use attest::{QuoteSignType, ReportDataMask, VerificationReport, Verifier};
 use core::convert::TryFrom;
 use digest::{BlockInput, Digest, FixedOutput, Input, Reset};
 use keys::Kex;
@@ -24,11 +25,9 @@ use rand_core::{CryptoRng, RngCore};
 /// Helper function to create the output for an initiate
 fn parse_handshake_output<Handshake, KexAlgo, Cipher, DigestType>(
     output: HandshakeOutput<KexAlgo, Cipher, DigestType>,
-    expected_measurement: Measurement,
-    expected_product_id: u16,
-    expected_minimum_svn: u16,
-    allow_debug: bool,
+    verifier: Verifier,
     trust_anchors: Option<Vec<String>>,
+    protocol_version: ProtocolVersion,
 ) -> Result<
     (
         AuthPending<KexAlgo, Cipher, DigestType>,
@@ -44,14 +43,7 @@ where
 {
     match output.status {
         HandshakeStatus::InProgress(state) => Ok((
-            AuthPending::new(
-                state,
-                expected_measurement,
-                expected_product_id,
-                expected_minimum_svn,
-                allow_debug,
-                trust_anchors,
-            ),
+            AuthPending::new(state, verifier, trust_anchors, protocol_version),
             AuthRequestOutput::<Handshake, KexAlgo, Cipher, DigestType>::from(output.payload),
         )),
         HandshakeStatus::Complete(_output) => Err(Error::EarlyHandshakeComplete),
@@ -97,13 +89,11 @@ where
 
         parse_handshake_output(
             handshake_state
-                .write_message(csprng, &[])
+                .write_message(csprng, &versioned_payload(self.protocol_version, &[]))
                 .map_err(Error::HandshakeWrite)?,
-            self.expected_measurement,
-            self.expected_product_id,
-            self.expected_minimum_svn,
-            self.allow_debug,
+            self.verifier,
             self.trust_anchors,
+            self.protocol_version,
         )
     }
 }
@@ -151,13 +141,14 @@ where
 
         parse_handshake_output(
             handshake_state
-                .write_message(csprng, &serialized_report)
+                .write_message(
+                    csprng,
+                    &versioned_payload(self.protocol_version, &serialized_report),
+                )
                 .map_err(Error::HandshakeWrite)?,
-            self.expected_measurement,
-            self.expected_product_id,
-            self.expected_minimum_svn,
-            self.allow_debug,
+            self.verifier,
             self.trust_anchors,
+            self.protocol_version,
         )
     }
 }
@@ -184,18 +175,23 @@ where
         match output.status {
             HandshakeStatus::InProgress(_state) => Err(Error::HandshakeNotComplete),
             HandshakeStatus::Complete(result) => {
+                // Responders answer in the version they were offered, or in the legacy protocol
+                // if they predate versioning, but never in a version we did not offer.
+                let (protocol_version, payload) = unversioned_payload(&output.payload)?;
+                if protocol_version == ProtocolVersion::Current
+                    && self.protocol_version == ProtocolVersion::Legacy
+                {
+                    return Err(Error::ProtocolVersionMismatch);
+                }
+
+                // The report must attest to the static key the responder proved it holds in this
+                // handshake, so a report replayed from another enclave is rejected here.
                 let remote_report: VerificationReport =
-                    deserialize(&output.payload).map_err(|_e| Error::ReportDeserialization)?;
-                remote_report.verify(
+                    deserialize(payload).map_err(|_e| Error::ReportDeserialization)?;
+                self.verifier.verify(
+                    &remote_report,
                     self.trust_anchors,
-                    None,
-                    None,
-                    None,
                     QuoteSignType::Linkable,
-                    self.allow_debug,
-                    &self.expected_measurement,
-                    self.expected_product_id,
-                    self.expected_minimum_svn,
                     &ReportDataMask::try_from(
                         result
                             .remote_identity
@@ -210,6 +206,7 @@ where
                         writer: result.initiator_cipher,
                         reader: result.responder_cipher,
                         binding: result.channel_binding,
+                        protocol_version,
                     },
                     (),
                 ))
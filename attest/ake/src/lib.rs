@@ -25,6 +25,7 @@ pub use self::{
         NodeInitiate, Plaintext,
     },
     mealy::Transition,
+    shared::ProtocolVersion,
     state::{AuthPending, Ready, Start},
 };
 
@@ -39,7 +40,7 @@ mod test {
     use super::*;
     use aes_gcm::Aes256Gcm;
     use alloc::string::String;
-    use attest::{Quote, IAS_SIM_ROOT_ANCHORS};
+    use attest::{Quote, VerificationReport, IAS_SIM_ROOT_ANCHORS};
     use attest_net::{Client, RaClient};
     use core::convert::TryFrom;
     use keys::{FromRandom, X25519Private, X25519Public, X25519};
@@ -52,30 +53,20 @@ mod test {
     const PRODUCT_ID: u16 = 0u16;
     const MIN_SVN: u16 = 0u16;
 
-    #[test]
-    fn ix_handshake() {
+    /// Create an initiator and responder which trust the simulated IAS, and a simulated IAS report
+    /// attesting to the given identity.
+    fn start_pair(identity: &X25519Private) -> (Start, Start, VerificationReport) {
         // Read an existing, valid quote
         let data = include_str!("../test_data/ok_quote.txt");
         let quote = Quote::from_base64(data.trim()).expect("Could not parse quote");
 
-        // Create a new identity pubkey for our "enclave"
-        let mut csprng = Hc128Rng::seed_from_u64(0);
-        let identity = X25519Private::from_random(&mut csprng);
-        let pubkey = X25519Public::from(&identity);
-
-        // Get the bytes from our quote
-        let mut quote_data = quote.to_x64_vec();
-
         // Overwrite the cached quote's report_data contents with our pubkey
+        let pubkey = X25519Public::from(identity);
+        let mut quote_data = quote.to_x64_vec();
         (&mut quote_data[368..400]).copy_from_slice(pubkey.as_ref());
-
-        // Re-assemble a quote from the munged version
         let quote = Quote::try_from(quote_data.as_ref())
             .expect("Could not parse quote from modified bytes");
 
-        std::println!("new quote = {:?}", &quote);
-
-        // Sign the forged quote with the sim client
         let ra_client = Client::new("").expect("Could not create sim client");
         let ias_report = ra_client
             .verify_quote(&quote, None)
@@ -86,6 +77,7 @@ mod test {
             .expect("Could not retrieve report body from cached report")
             .mr_signer();
 
+        let trust_anchors = Some(vec![String::from(IAS_SIM_ROOT_ANCHORS)]);
         let mut initiator = Start::new(
             RESPONDER_ID_STR.into(),
             mr_signer,
@@ -93,7 +85,7 @@ mod test {
             MIN_SVN,
             true,
         );
-
+        initiator.trust_anchors = trust_anchors.clone();
         let mut responder = Start::new(
             RESPONDER_ID_STR.into(),
             mr_signer,
@@ -101,11 +93,18 @@ mod test {
             MIN_SVN,
             true,
         );
-
-        let trust_anchors = Some(vec![String::from(IAS_SIM_ROOT_ANCHORS)]);
-        initiator.trust_anchors = trust_anchors.clone();
         responder.trust_anchors = trust_anchors;
 
+        (initiator, responder, ias_report)
+    }
+
+    #[test]
+    fn ix_handshake() {
+        // Create a new identity pubkey for our "enclave"
+        let mut csprng = Hc128Rng::seed_from_u64(0);
+        let identity = X25519Private::from_random(&mut csprng);
+        let (initiator, responder, ias_report) = start_pair(&identity);
+
         let node_init =
             NodeInitiate::<X25519, Aes256Gcm, Sha512>::new(identity.clone(), ias_report.clone());
         let (initiator, auth_request_output) = initiator
@@ -163,4 +162,61 @@ mod test {
 
         assert_eq!(plaintext2.as_slice(), response.as_bytes());
     }
+
+    #[test]
+    fn ix_handshake_rejects_report_for_another_identity() {
+        let mut csprng = Hc128Rng::seed_from_u64(0);
+        let identity = X25519Private::from_random(&mut csprng);
+        let (initiator, responder, ias_report) = start_pair(&identity);
+
+        let node_init =
+            NodeInitiate::<X25519, Aes256Gcm, Sha512>::new(identity, ias_report.clone());
+        let (initiator, auth_request_output) = initiator
+            .try_next(&mut csprng, node_init)
+            .expect("Initiator could not be initiated");
+
+        // The responder replays a valid report, but handshakes with a key the report does not
+        // attest to.
+        let other_identity = X25519Private::from_random(&mut csprng);
+        let auth_request_input =
+            AuthRequestInput::new(auth_request_output, other_identity, ias_report);
+        let (_responder, auth_response) = responder
+            .try_next(&mut csprng, auth_request_input)
+            .expect("Responder could not process auth request");
+
+        assert!(initiator.try_next(&mut csprng, auth_response).is_err());
+    }
+
+    #[test]
+    fn ix_handshake_with_legacy_initiator() {
+        let mut csprng = Hc128Rng::seed_from_u64(0);
+        let identity = X25519Private::from_random(&mut csprng);
+        let (initiator, responder, ias_report) = start_pair(&identity);
+        let initiator = initiator.with_protocol_version(ProtocolVersion::Legacy);
+
+        let node_init =
+            NodeInitiate::<X25519, Aes256Gcm, Sha512>::new(identity.clone(), ias_report.clone());
+        let (initiator, auth_request_output) = initiator
+            .try_next(&mut csprng, node_init)
+            .expect("Initiator could not be initiated");
+        let auth_request_input = AuthRequestInput::new(auth_request_output, identity, ias_report);
+        let (responder, auth_response) = responder
+            .try_next(&mut csprng, auth_request_input)
+            .expect("Responder could not process auth request");
+        let (initiator, _) = initiator
+            .try_next(&mut csprng, auth_response)
+            .expect("Initiator not process auth response");
+
+        // The responder answers in the version it was offered.
+        assert_eq!(initiator.protocol_version(), ProtocolVersion::Legacy);
+        assert_eq!(responder.protocol_version(), ProtocolVersion::Legacy);
+
+        let (_, ciphertext) = initiator
+            .try_next(&mut csprng, Plaintext::new(b"aad", b"hello"))
+            .expect("Could not encrypt payload");
+        let (_, plaintext) = responder
+            .try_next(&mut csprng, Ciphertext::new(b"aad", &ciphertext))
+            .expect("Could not decrypt payload");
+        assert_eq!(plaintext.as_slice(), b"hello");
+    }
 }
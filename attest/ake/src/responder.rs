@@ -5,6 +5,7 @@ use crate::{
     error::Error,
     event::{AuthRequestInput, AuthResponse},
     mealy::Transition,
+    shared::{unversioned_payload, versioned_payload, ProtocolVersion},
     state::{Ready, Start},
 };
 use aead::{AeadMut, NewAead};
@@ -27,7 +28,14 @@ trait ResponderTransitionMixin {
         &self,
         data: &[u8],
         local_identity: KexAlgo::Private,
-    ) -> Result<(HandshakeState<KexAlgo, Cipher, DigestType>, Vec<u8>), Error>
+    ) -> Result<
+        (
+            HandshakeState<KexAlgo, Cipher, DigestType>,
+            ProtocolVersion,
+            Vec<u8>,
+        ),
+        Error,
+    >
     where
         Handshake: HandshakePattern,
         KexAlgo: Kex,
@@ -38,6 +46,7 @@ trait ResponderTransitionMixin {
     fn handle_response<KexAlgo, Cipher, DigestType>(
         csprng: &mut (impl CryptoRng + RngCore),
         handshake_state: HandshakeState<KexAlgo, Cipher, DigestType>,
+        protocol_version: ProtocolVersion,
         ias_report: VerificationReport,
     ) -> Result<(Ready<Cipher>, AuthResponse), Error>
     where
@@ -51,7 +60,14 @@ impl ResponderTransitionMixin for Start {
         &self,
         data: &[u8],
         local_identity: KexAlgo::Private,
-    ) -> Result<(HandshakeState<KexAlgo, Cipher, DigestType>, Vec<u8>), Error>
+    ) -> Result<
+        (
+            HandshakeState<KexAlgo, Cipher, DigestType>,
+            ProtocolVersion,
+            Vec<u8>,
+        ),
+        Error,
+    >
     where
         Handshake: HandshakePattern,
         KexAlgo: Kex,
@@ -76,7 +92,11 @@ impl ResponderTransitionMixin for Start {
             .map_err(Error::HandshakeWrite)?;
 
         match output.status {
-            HandshakeStatus::InProgress(new_state) => Ok((new_state, output.payload)),
+            HandshakeStatus::InProgress(new_state) => {
+                // Answer in whichever version the initiator offered.
+                let (protocol_version, payload) = unversioned_payload(&output.payload)?;
+                Ok((new_state, protocol_version, payload.to_vec()))
+            }
             HandshakeStatus::Complete(_v) => Err(Error::EarlyHandshakeComplete),
         }
     }
@@ -84,6 +104,7 @@ impl ResponderTransitionMixin for Start {
     fn handle_response<KexAlgo, Cipher, DigestType>(
         csprng: &mut (impl CryptoRng + RngCore),
         handshake_state: HandshakeState<KexAlgo, Cipher, DigestType>,
+        protocol_version: ProtocolVersion,
         ias_report: VerificationReport,
     ) -> Result<(Ready<Cipher>, AuthResponse), Error>
     where
@@ -95,7 +116,7 @@ impl ResponderTransitionMixin for Start {
         let local_report = serialize(&ias_report).map_err(|_e| Error::ReportSerialization)?;
 
         let output = handshake_state
-            .write_message(csprng, &local_report)
+            .write_message(csprng, &versioned_payload(protocol_version, &local_report))
             .map_err(Error::HandshakeWrite)?;
 
         match output.status {
@@ -105,6 +126,7 @@ impl ResponderTransitionMixin for Start {
                     writer: result.responder_cipher,
                     reader: result.initiator_cipher,
                     binding: result.channel_binding,
+                    protocol_version,
                 },
                 AuthResponse::from(output.payload),
             )),
@@ -136,7 +158,7 @@ where
         input: AuthRequestInput<HandshakeIX, KexAlgo, Cipher, DigestType>,
     ) -> Result<(Ready<Cipher>, AuthResponse), Error> {
         // Read the request and return the payload and state
-        let (handshake_state, payload) = self
+        let (handshake_state, protocol_version, payload) = self
             .handle_request::<HandshakeIX, KexAlgo, Cipher, DigestType>(
                 &input.data.data,
                 input.local_identity,
@@ -145,16 +167,10 @@ where
         // Parse and verify the received IAS report
         let remote_report: VerificationReport =
             deserialize(&payload).map_err(|_e| Error::ReportDeserialization)?;
-        remote_report.verify(
+        self.verifier.verify(
+            &remote_report,
             self.trust_anchors,
-            None,
-            None,
-            None,
             QuoteSignType::Linkable,
-            self.allow_debug,
-            &self.expected_measurement,
-            self.expected_product_id,
-            self.expected_minimum_svn,
             &ReportDataMask::try_from(
                 handshake_state
                     .remote_identity()
@@ -164,7 +180,7 @@ where
             .map_err(|_e| Error::BadRemoteIdentity)?,
         )?;
 
-        Self::handle_response(csprng, handshake_state, input.ias_report)
+        Self::handle_response(csprng, handshake_state, protocol_version, input.ias_report)
     }
 }
 
@@ -188,11 +204,11 @@ where
         csprng: &mut (impl CryptoRng + RngCore),
         input: AuthRequestInput<HandshakeNX, KexAlgo, Cipher, DigestType>,
     ) -> Result<(Ready<Cipher>, AuthResponse), Error> {
-        let (handshake_state, _payload) = self
+        let (handshake_state, protocol_version, _payload) = self
             .handle_request::<HandshakeNX, KexAlgo, Cipher, DigestType>(
                 &input.data.data,
                 input.local_identity,
             )?;
-        Self::handle_response(csprng, handshake_state, input.ias_report)
+        Self::handle_response(csprng, handshake_state, protocol_version, input.ias_report)
     }
 }
@@ -3,6 +3,7 @@
 //! Common transitions between initiator and responder.
 
 use crate::{
+    error::Error,
     event::{Ciphertext, Plaintext},
     mealy::Transition,
     state::Ready,
@@ -12,6 +13,59 @@ use alloc::vec::Vec;
 use mcnoise::{CipherError, NoiseCipher};
 use rand_core::{CryptoRng, RngCore};
 
+/// The version of the AKE protocol spoken by this crate.
+///
+/// Every handshake payload is prefixed with this string. Because handshake payloads are mixed into
+/// the noise transcript, a peer which strips or rewrites it (e.g. to force an older protocol) will
+/// cause the handshake to fail rather than silently negotiating a weaker channel. For the same
+/// reason, the report carried in a payload is bound to the handshake it was sent in: it is
+/// authenticated by the transcript hash, and verified against the static key that same transcript
+/// authenticated.
+const AKE_PROTOCOL_VERSION: &[u8] = b"mc-ake/2";
+
+/// The prefix every version of `AKE_PROTOCOL_VERSION` starts with.
+const AKE_PROTOCOL_PREFIX: &[u8] = b"mc-ake/";
+
+/// The protocol version a peer speaks, as negotiated during the handshake.
+///
+/// Peers which predate versioned payloads send them without any prefix. They are accepted until
+/// the next release, and spoken to in their own, unversioned, protocol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProtocolVersion {
+    /// Unversioned payloads, and channel messages without sequence numbers.
+    Legacy,
+    /// `AKE_PROTOCOL_VERSION`.
+    Current,
+}
+
+/// Prefix a handshake payload with the given protocol version.
+pub(crate) fn versioned_payload(version: ProtocolVersion, payload: &[u8]) -> Vec<u8> {
+    let prefix: &[u8] = match version {
+        ProtocolVersion::Legacy => &[],
+        ProtocolVersion::Current => AKE_PROTOCOL_VERSION,
+    };
+    let mut retval = Vec::with_capacity(prefix.len() + payload.len());
+    retval.extend_from_slice(prefix);
+    retval.extend_from_slice(payload);
+    retval
+}
+
+/// Check the protocol version on a handshake payload received from a remote peer, and return the
+/// version along with the payload without it. Payloads from other versions of the protocol are
+/// rejected.
+pub(crate) fn unversioned_payload(payload: &[u8]) -> Result<(ProtocolVersion, &[u8]), Error> {
+    if payload.starts_with(AKE_PROTOCOL_VERSION) {
+        Ok((
+            ProtocolVersion::Current,
+            &payload[AKE_PROTOCOL_VERSION.len()..],
+        ))
+    } else if payload.starts_with(AKE_PROTOCOL_PREFIX) {
+        Err(Error::ProtocolVersionMismatch)
+    } else {
+        Ok((ProtocolVersion::Legacy, payload))
+    }
+}
+
 /// Ready + Ciphertext => Ready + Vec-of-plaintext
 impl<Cipher> Transition<Ready<Cipher>, Ciphertext<'_, '_>, Vec<u8>> for Ready<Cipher>
 where
@@ -47,3 +101,32 @@ where
         Ok((retval, ciphertext))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn versioned_payload_round_trip() {
+        let payload = versioned_payload(ProtocolVersion::Current, b"report");
+        assert_eq!(
+            unversioned_payload(&payload).expect("Could not read versioned payload"),
+            (ProtocolVersion::Current, &b"report"[..])
+        );
+
+        let payload = versioned_payload(ProtocolVersion::Legacy, b"report");
+        assert_eq!(payload, b"report".to_vec());
+        assert_eq!(
+            unversioned_payload(&payload).expect("Could not read legacy payload"),
+            (ProtocolVersion::Legacy, &b"report"[..])
+        );
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        assert_eq!(
+            unversioned_payload(b"mc-ake/3report"),
+            Err(Error::ProtocolVersionMismatch)
+        );
+    }
+}
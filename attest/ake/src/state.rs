@@ -2,10 +2,10 @@
 
 //! Transducer states used by initiators and/or responders.
 
-use crate::mealy::State;
+use crate::{mealy::State, shared::ProtocolVersion};
 use aead::{AeadMut, NewAead};
 use alloc::{string::String, vec::Vec};
-use attest::Measurement;
+use attest::{EnclaveIdentity, Measurement, Verifier};
 use digest::{BlockInput, FixedOutput, Input, Reset};
 use keys::Kex;
 use mcnoise::{CipherError, CipherState, HandshakeState, NoiseCipher};
@@ -16,20 +16,14 @@ pub struct Start {
     /// For responder's unique ID (hostname)
     pub(crate) responder_id: String,
 
-    /// The measurement we expect from authenticated counterparties
-    pub(crate) expected_measurement: Measurement,
-
-    /// The product ID remote enclaves must be running
-    pub(crate) expected_product_id: u16,
-
-    /// The minimum security version remote enclaves must be running
-    pub(crate) expected_minimum_svn: u16,
-
-    /// Whether or not to allow remote enclaves to run in debug
-    pub(crate) allow_debug: bool,
+    /// The enclaves we accept from authenticated counterparties
+    pub(crate) verifier: Verifier,
 
     /// An optional value used to inject specific trust anchors during validation (used for testing)
     pub(crate) trust_anchors: Option<Vec<String>>,
+
+    /// The protocol version an initiator offers. Responders speak whichever the initiator offers.
+    pub(crate) protocol_version: ProtocolVersion,
 }
 
 impl Start {
@@ -42,15 +36,36 @@ impl Start {
         expected_minimum_svn: u16,
         allow_debug: bool,
     ) -> Self {
+        Self::new_with_verifier(
+            responder_id,
+            Verifier::new(
+                EnclaveIdentity::new(
+                    expected_measurement,
+                    expected_product_id,
+                    expected_minimum_svn,
+                ),
+                allow_debug,
+            ),
+        )
+    }
+
+    /// Begin a Mealy machine which accepts any of the enclaves the given verifier accepts.
+    pub fn new_with_verifier(responder_id: String, verifier: Verifier) -> Self {
         Self {
             responder_id,
-            expected_measurement: expected_measurement.into(),
-            expected_product_id,
-            expected_minimum_svn,
-            allow_debug,
+            verifier,
             trust_anchors: None,
+            protocol_version: ProtocolVersion::Current,
         }
     }
+
+    /// Offer the given protocol version when initiating, rather than the current one. Until every
+    /// responder has been upgraded, initiators must offer `ProtocolVersion::Legacy` to nodes that
+    /// have not been, since those cannot parse versioned payloads.
+    pub fn with_protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = protocol_version;
+        self
+    }
 }
 
 impl State for Start {}
@@ -65,17 +80,14 @@ where
 {
     /// The handshake state
     pub(crate) state: HandshakeState<KexAlgo, Cipher, DigestType>,
-    /// The enclave measurement we expect in the AuthResponse
-    pub(crate) expected_measurement: Measurement,
-    /// The product ID remote enclaves must be running
-    pub(crate) expected_product_id: u16,
-    /// The minimum security version remote enclaves must be running
-    pub(crate) expected_minimum_svn: u16,
-    /// Whether or not to allow remote enclaves to run in debug
-    pub(crate) allow_debug: bool,
+    /// The enclaves we accept in the AuthResponse
+    pub(crate) verifier: Verifier,
 
     /// An optional value used to inject specific trust anchors during validation (used for testing)
     pub(crate) trust_anchors: Option<Vec<String>>,
+
+    /// The protocol version offered in the auth request.
+    pub(crate) protocol_version: ProtocolVersion,
 }
 
 impl<KexAlgo, Cipher, DigestType> State for AuthPending<KexAlgo, Cipher, DigestType>
@@ -94,25 +106,30 @@ where
 {
     pub(crate) fn new(
         state: HandshakeState<KexAlgo, Cipher, DigestType>,
-        expected_measurement: Measurement,
-        expected_product_id: u16,
-        expected_minimum_svn: u16,
-        allow_debug: bool,
+        verifier: Verifier,
         trust_anchors: Option<Vec<String>>,
+        protocol_version: ProtocolVersion,
     ) -> Self {
         Self {
             state,
-            expected_measurement,
-            expected_product_id,
-            expected_minimum_svn,
-            allow_debug,
+            verifier,
             trust_anchors,
+            protocol_version,
         }
     }
 }
 
+/// The length of the explicit sequence number which prefixes each message on an established
+/// channel.
+const SEQUENCE_NUMBER_LEN: usize = 8;
+
 /// The state after an auth response has been sent by a responder/received by
 /// an initiator.
+///
+/// Each message sent over the channel is prefixed by its big-endian sequence number. The sequence
+/// number is also the AEAD nonce, so it is authenticated along with the message, and lets the
+/// reader distinguish replayed messages from ones which were dropped or re-ordered in transit.
+/// Channels with legacy peers send messages without sequence numbers.
 pub struct Ready<Cipher>
 where
     Cipher: AeadMut + NewAead + NoiseCipher + Sized,
@@ -120,6 +137,7 @@ where
     pub(crate) writer: CipherState<Cipher>,
     pub(crate) reader: CipherState<Cipher>,
     pub(crate) binding: Vec<u8>,
+    pub(crate) protocol_version: ProtocolVersion,
 }
 
 impl<Cipher> Ready<Cipher>
@@ -131,15 +149,181 @@ where
         self.binding.as_ref()
     }
 
+    /// The protocol version negotiated with the remote peer.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
     /// Using the writer cipher, encrypt the given plaintext.
     pub fn encrypt(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CipherError> {
-        self.writer.encrypt_with_ad(aad, plaintext)
+        if self.protocol_version == ProtocolVersion::Legacy {
+            return self.writer.encrypt_with_ad(aad, plaintext);
+        }
+
+        let sequence_number = self.writer.nonce();
+        let ciphertext = self.writer.encrypt_with_ad(aad, plaintext)?;
+
+        let mut retval = Vec::with_capacity(SEQUENCE_NUMBER_LEN + ciphertext.len());
+        retval.extend_from_slice(&sequence_number.to_be_bytes());
+        retval.extend_from_slice(&ciphertext);
+        Ok(retval)
     }
 
     /// Using the reader cipher, decrypt the provided ciphertext.
+    ///
+    /// Messages must be decrypted in the order they were encrypted, and each exactly once.
     pub fn decrypt(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, CipherError> {
+        if self.protocol_version == ProtocolVersion::Legacy {
+            return self.reader.decrypt_with_ad(aad, ciphertext);
+        }
+
+        if ciphertext.len() < SEQUENCE_NUMBER_LEN {
+            return Err(CipherError::Truncated);
+        }
+        let (sequence_bytes, ciphertext) = ciphertext.split_at(SEQUENCE_NUMBER_LEN);
+        let mut sequence_number = [0u8; SEQUENCE_NUMBER_LEN];
+        sequence_number.copy_from_slice(sequence_bytes);
+        let sequence_number = u64::from_be_bytes(sequence_number);
+
+        let expected = self.reader.nonce();
+        if sequence_number < expected {
+            return Err(CipherError::Replayed(sequence_number, expected));
+        }
+        if sequence_number > expected {
+            return Err(CipherError::OutOfOrder(sequence_number, expected));
+        }
+
         self.reader.decrypt_with_ad(aad, ciphertext)
     }
 }
 
 impl<Cipher> State for Ready<Cipher> where Cipher: AeadMut + NewAead + NoiseCipher + Sized {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aead::{generic_array::typenum::Unsigned, Aead};
+    use aes_gcm::Aes256Gcm;
+    use alloc::vec;
+
+    /// Create a pair of connected channels.
+    fn ready_pair() -> (Ready<Aes256Gcm>, Ready<Aes256Gcm>) {
+        ready_pair_with_version(ProtocolVersion::Current)
+    }
+
+    /// Create a pair of connected channels speaking the given protocol version.
+    fn ready_pair_with_version(
+        protocol_version: ProtocolVersion,
+    ) -> (Ready<Aes256Gcm>, Ready<Aes256Gcm>) {
+        let key_len = <Aes256Gcm as NewAead>::KeySize::to_usize();
+        let cipher = |key: u8| {
+            let mut state = CipherState::<Aes256Gcm>::default();
+            state
+                .initialize_key(Some(vec![key; key_len]))
+                .expect("Could not initialize key");
+            state
+        };
+
+        (
+            Ready {
+                writer: cipher(1),
+                reader: cipher(2),
+                binding: vec![3u8; 32],
+                protocol_version,
+            },
+            Ready {
+                writer: cipher(2),
+                reader: cipher(1),
+                binding: vec![3u8; 32],
+                protocol_version,
+            },
+        )
+    }
+
+    #[test]
+    fn encrypt_decrypt_in_order() {
+        let (mut initiator, mut responder) = ready_pair();
+
+        for i in 0..3u8 {
+            let ciphertext = initiator.encrypt(b"aad", &[i]).expect("Could not encrypt");
+            assert_eq!(&ciphertext[..8], &u64::from(i).to_be_bytes());
+            let plaintext = responder
+                .decrypt(b"aad", &ciphertext)
+                .expect("Could not decrypt");
+            assert_eq!(plaintext, vec![i]);
+        }
+    }
+
+    #[test]
+    fn replayed_message_is_rejected() {
+        let (mut initiator, mut responder) = ready_pair();
+
+        let ciphertext = initiator.encrypt(&[], b"hello").expect("Could not encrypt");
+        responder
+            .decrypt(&[], &ciphertext)
+            .expect("Could not decrypt");
+        assert_eq!(
+            responder.decrypt(&[], &ciphertext),
+            Err(CipherError::Replayed(0, 1))
+        );
+    }
+
+    #[test]
+    fn out_of_order_message_is_rejected() {
+        let (mut initiator, mut responder) = ready_pair();
+
+        let first = initiator.encrypt(&[], b"first").expect("Could not encrypt");
+        let second = initiator
+            .encrypt(&[], b"second")
+            .expect("Could not encrypt");
+        assert_eq!(
+            responder.decrypt(&[], &second),
+            Err(CipherError::OutOfOrder(1, 0))
+        );
+
+        // The channel is still usable once the missing message arrives.
+        assert_eq!(
+            responder.decrypt(&[], &first).expect("Could not decrypt"),
+            b"first".to_vec()
+        );
+        assert_eq!(
+            responder.decrypt(&[], &second).expect("Could not decrypt"),
+            b"second".to_vec()
+        );
+    }
+
+    #[test]
+    fn tampered_sequence_number_is_rejected() {
+        let (mut initiator, mut responder) = ready_pair();
+
+        let _first = initiator.encrypt(&[], b"first").expect("Could not encrypt");
+        let mut second = initiator
+            .encrypt(&[], b"second")
+            .expect("Could not encrypt");
+
+        // Claiming the second message is the first one must fail authentication.
+        second[7] = 0;
+        assert_eq!(responder.decrypt(&[], &second), Err(CipherError::Aead));
+        assert_eq!(
+            responder.decrypt(&[], &[0u8; 4]),
+            Err(CipherError::Truncated)
+        );
+    }
+
+    #[test]
+    fn legacy_channel_has_no_sequence_numbers() {
+        let (mut initiator, mut responder) = ready_pair_with_version(ProtocolVersion::Legacy);
+
+        let ciphertext = initiator
+            .encrypt(b"aad", b"hello")
+            .expect("Could not encrypt");
+        let tag_len = <Aes256Gcm as Aead>::TagSize::to_usize();
+        assert_eq!(ciphertext.len(), b"hello".len() + tag_len);
+        assert_eq!(
+            responder
+                .decrypt(b"aad", &ciphertext)
+                .expect("Could not decrypt"),
+            b"hello".to_vec()
+        );
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
         epid_group_id::EpidGroupId, measurement::Measurement, pib::PlatformInfoBlob,
         report_data::ReportDataMask,
     },
+    verifier::AdvisoryPolicy,
     IAS_SIGNING_ROOT_CERT_PEMS, IAS_VERSION,
 };
 use alloc::{
@@ -158,6 +159,23 @@ impl VerificationReportData {
         expected_version: f64,
         expected_ias_nonce: Option<&IasNonce>,
         expected_pse_manifest_hash: Option<&[u8]>,
+    ) -> Result<(), VerifyError> {
+        self.verify_data_with_policy(
+            expected_version,
+            expected_ias_nonce,
+            expected_pse_manifest_hash,
+            &AdvisoryPolicy::default(),
+        )
+    }
+
+    /// Verify our contents, but not the quote, tolerating the platform advisories allowed by
+    /// `advisory_policy`.
+    pub fn verify_data_with_policy(
+        &self,
+        expected_version: f64,
+        expected_ias_nonce: Option<&IasNonce>,
+        expected_pse_manifest_hash: Option<&[u8]>,
+        advisory_policy: &AdvisoryPolicy,
     ) -> Result<(), VerifyError> {
         // Dumbest. Possible. Timeline.
         if unsafe { fabsf64(expected_version - self.version) } > EPSILON {
@@ -187,18 +205,33 @@ impl VerificationReportData {
         }
 
         // Result<Option<Result<(), PseManifestError>>, IasQuoteError>
-        match &self.quote_status {
-            Ok(optional_pse_result)
-            | Err(IasQuoteError::SwHardeningNeeded(optional_pse_result)) => {
-                match optional_pse_result {
-                    Some(pse_result) => match pse_result {
-                        Ok(()) => Ok(()),
-                        Err(e) => Err(e.clone().into()),
-                    },
-                    None => Ok(()),
-                }
+        let optional_pse_result = match &self.quote_status {
+            Ok(optional_pse_result) => optional_pse_result,
+            Err(IasQuoteError::SwHardeningNeeded(optional_pse_result))
+                if advisory_policy.allow_sw_hardening_needed =>
+            {
+                optional_pse_result
+            }
+            Err(IasQuoteError::ConfigurationNeeded(optional_pse_result, _))
+                if advisory_policy.allow_configuration_needed =>
+            {
+                optional_pse_result
             }
-            Err(e) => Err(e.clone().into()),
+            Err(IasQuoteError::ConfigurationAndSwHardeningNeeded(optional_pse_result, _))
+                if advisory_policy.allow_configuration_needed
+                    && advisory_policy.allow_sw_hardening_needed =>
+            {
+                optional_pse_result
+            }
+            Err(e) => return Err(e.clone().into()),
+        };
+
+        match optional_pse_result {
+            Some(pse_result) => match pse_result {
+                Ok(()) => Ok(()),
+                Err(e) => Err(e.clone().into()),
+            },
+            None => Ok(()),
         }
     }
 
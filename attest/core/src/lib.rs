@@ -20,6 +20,7 @@ mod seal;
 mod sigrl;
 mod traits;
 mod types;
+mod verifier;
 
 pub use crate::{
     error::{
@@ -55,6 +56,7 @@ pub use crate::{
         update_info::*,
         ConfigSecurityVersion, MiscSelect, ProductId, SecurityVersion,
     },
+    verifier::{AdvisoryPolicy, EnclaveIdentity, Verifier, VerifierBuilder},
 };
 
 #[cfg(feature = "sgx-sim")]
@@ -0,0 +1,300 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Verification of attestation reports against a set of acceptable enclave identities.
+//!
+//! During a staged enclave upgrade, nodes running the old and the new enclave coexist, and clients
+//! and peers need to accept both. A `Verifier` accepts a report if it matches any one of its
+//! identities.
+
+use crate::{
+    error::VerifyError,
+    ias::verify::{VerificationReport, VerificationReportData},
+    quote::QuoteSignType,
+    types::{measurement::Measurement, report_data::ReportDataMask, ProductId, SecurityVersion},
+    IAS_VERSION,
+};
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+use serde::{Deserialize, Serialize};
+
+/// The platform advisories IAS may report which are tolerated.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct AdvisoryPolicy {
+    /// Accept platforms that need software mitigations, which the enclave is built with.
+    pub allow_sw_hardening_needed: bool,
+
+    /// Accept platforms that need BIOS configuration changes.
+    pub allow_configuration_needed: bool,
+}
+
+impl Default for AdvisoryPolicy {
+    fn default() -> Self {
+        Self {
+            allow_sw_hardening_needed: true,
+            allow_configuration_needed: false,
+        }
+    }
+}
+
+/// An enclave a `Verifier` accepts.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct EnclaveIdentity {
+    /// The MRENCLAVE or MRSIGNER the enclave must have.
+    pub measurement: Measurement,
+
+    /// The product ID the enclave must have.
+    pub product_id: ProductId,
+
+    /// The minimum security version the enclave must have.
+    pub minimum_security_version: SecurityVersion,
+
+    /// The platform advisories tolerated for this enclave.
+    pub advisory_policy: AdvisoryPolicy,
+}
+
+impl EnclaveIdentity {
+    /// An identity tolerating the default platform advisories.
+    pub fn new(
+        measurement: impl Into<Measurement>,
+        product_id: ProductId,
+        minimum_security_version: SecurityVersion,
+    ) -> Self {
+        Self {
+            measurement: measurement.into(),
+            product_id,
+            minimum_security_version,
+            advisory_policy: AdvisoryPolicy::default(),
+        }
+    }
+}
+
+/// Verifies attestation reports, accepting any of several enclave identities.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Verifier {
+    identities: Vec<EnclaveIdentity>,
+    allow_debug: bool,
+}
+
+impl Verifier {
+    /// A verifier accepting a single enclave identity.
+    pub fn new(identity: EnclaveIdentity, allow_debug: bool) -> Self {
+        Self {
+            identities: vec![identity],
+            allow_debug,
+        }
+    }
+
+    /// The enclave identities this verifier accepts.
+    pub fn identities(&self) -> &[EnclaveIdentity] {
+        &self.identities
+    }
+
+    /// Checks the signature of a verification report, then verifies its contents.
+    pub fn verify(
+        &self,
+        report: &VerificationReport,
+        trust_anchors: Option<Vec<String>>,
+        expected_type: QuoteSignType,
+        expected_data: &ReportDataMask,
+    ) -> Result<VerificationReportData, VerifyError> {
+        report.verify_signature(trust_anchors)?;
+        let report_data = VerificationReportData::try_from(report)?;
+        self.verify_data(&report_data, expected_type, expected_data)?;
+        Ok(report_data)
+    }
+
+    /// Verifies the contents of an authenticated report. Succeeds if any of the accepted
+    /// identities matches, otherwise returns the error for the last identity tried.
+    pub fn verify_data(
+        &self,
+        report_data: &VerificationReportData,
+        expected_type: QuoteSignType,
+        expected_data: &ReportDataMask,
+    ) -> Result<(), VerifyError> {
+        let mut result = Err(VerifyError::Unknown);
+        for identity in &self.identities {
+            result = report_data
+                .verify_data_with_policy(IAS_VERSION, None, None, &identity.advisory_policy)
+                .and_then(|()| {
+                    Ok(report_data.quote.verify(
+                        None,
+                        expected_type,
+                        self.allow_debug,
+                        &identity.measurement,
+                        identity.product_id,
+                        identity.minimum_security_version,
+                        expected_data,
+                    )?)
+                });
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+}
+
+/// Builds a `Verifier` accepting any of a set of enclave identities.
+#[derive(Clone, Debug, Default)]
+pub struct VerifierBuilder {
+    identities: Vec<EnclaveIdentity>,
+    allow_debug: bool,
+}
+
+impl VerifierBuilder {
+    /// Accept the given enclave identity, in addition to any previously added ones.
+    pub fn identity(&mut self, identity: EnclaveIdentity) -> &mut Self {
+        self.identities.push(identity);
+        self
+    }
+
+    /// Accept enclaves running in debug mode (default: false).
+    pub fn allow_debug(&mut self, allow_debug: bool) -> &mut Self {
+        self.allow_debug = allow_debug;
+        self
+    }
+
+    /// Create the verifier. Returns `None` if no identities were added, since such a verifier
+    /// would reject everything.
+    pub fn build(&self) -> Option<Verifier> {
+        if self.identities.is_empty() {
+            return None;
+        }
+        Some(Verifier {
+            identities: self.identities.clone(),
+            allow_debug: self.allow_debug,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{error::IasQuoteError, ias::verify::VerificationSignature, MrEnclave, MrSigner};
+
+    const IAS_WITH_PIB: &str = include_str!("../data/test/ias_with_pib.json");
+
+    /// A parsed report, whose quote status has been replaced with `quote_status`.
+    fn report_data(
+        quote_status: impl FnOnce(&VerificationReportData) -> crate::IasQuoteResult,
+    ) -> VerificationReportData {
+        let report = VerificationReport {
+            sig: VerificationSignature::default(),
+            chain: Vec::default(),
+            http_body: String::from(IAS_WITH_PIB),
+        };
+        let mut report_data = VerificationReportData::try_from(&report)
+            .expect("Could not parse IAS verification report");
+        report_data.quote_status = quote_status(&report_data);
+        report_data
+    }
+
+    /// The identity of the enclave which produced the test report.
+    fn identities(report_data: &VerificationReportData) -> (EnclaveIdentity, EnclaveIdentity) {
+        let report_body = report_data
+            .quote
+            .report_body()
+            .expect("Could not parse report body");
+        (
+            EnclaveIdentity::new(
+                report_body.mr_enclave(),
+                report_body.product_id(),
+                report_body.security_version(),
+            ),
+            EnclaveIdentity::new(
+                report_body.mr_signer(),
+                report_body.product_id(),
+                report_body.security_version(),
+            ),
+        )
+    }
+
+    #[test]
+    // A report should be accepted if it matches any of the identities.
+    fn test_verify_data_accepts_any_identity() {
+        let report_data = report_data(|_| Ok(None));
+        let sign_type = report_data.quote.sign_type().unwrap();
+        let (mr_enclave_identity, mr_signer_identity) = identities(&report_data);
+        let other_identity = EnclaveIdentity::new(MrEnclave::default(), 0, 0);
+
+        let old_and_new = VerifierBuilder::default()
+            .identity(other_identity)
+            .identity(mr_enclave_identity)
+            .allow_debug(true)
+            .build()
+            .unwrap();
+        assert!(old_and_new
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_ok());
+
+        let mr_signer_only = Verifier::new(mr_signer_identity, true);
+        assert!(mr_signer_only
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_ok());
+
+        let others_only = VerifierBuilder::default()
+            .identity(other_identity)
+            .identity(EnclaveIdentity::new(MrSigner::default(), 0, 0))
+            .allow_debug(true)
+            .build()
+            .unwrap();
+        assert!(others_only
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_err());
+
+        // A newer minimum security version than the enclave has.
+        let mut too_old = mr_enclave_identity;
+        too_old.minimum_security_version += 1;
+        assert!(Verifier::new(too_old, true)
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_err());
+    }
+
+    #[test]
+    // Platform advisories should be tolerated per identity.
+    fn test_verify_data_advisory_policy() {
+        let report_data = report_data(|report_data| {
+            Err(IasQuoteError::ConfigurationNeeded(
+                None,
+                report_data.platform_info_blob.clone().unwrap(),
+            ))
+        });
+        let sign_type = report_data.quote.sign_type().unwrap();
+        let (strict, mut lenient) = identities(&report_data);
+        lenient.advisory_policy.allow_configuration_needed = true;
+
+        let verifier = Verifier::new(strict, true);
+        assert!(verifier
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_err());
+
+        let verifier = VerifierBuilder::default()
+            .identity(strict)
+            .identity(lenient)
+            .allow_debug(true)
+            .build()
+            .unwrap();
+        assert!(verifier
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_ok());
+
+        // Out of date platforms are never accepted.
+        let report_data = report_data_with_group_out_of_date();
+        assert!(verifier
+            .verify_data(&report_data, sign_type, &ReportDataMask::default())
+            .is_err());
+    }
+
+    /// The test report, with the quote status IAS gave it.
+    fn report_data_with_group_out_of_date() -> VerificationReportData {
+        report_data(|report_data| report_data.quote_status.clone())
+    }
+
+    #[test]
+    fn test_builder_requires_an_identity() {
+        assert!(VerifierBuilder::default()
+            .allow_debug(true)
+            .build()
+            .is_none());
+    }
+}
@@ -6,6 +6,9 @@
 
 extern crate alloc;
 
+#[cfg(all(feature = "std", not(feature = "log")))]
+extern crate std;
+
 use sha3::Digest;
 
 mod hasher_builder;
@@ -15,6 +18,11 @@ mod responder_id;
 pub mod lru;
 pub use lru::LruCache;
 
+#[cfg(feature = "std")]
+mod sync_lru;
+#[cfg(feature = "std")]
+pub use sync_lru::SyncLruCache;
+
 pub use node_id::NodeID;
 pub use responder_id::{ResponderId, ResponderIdParseError};
 
@@ -11,12 +11,12 @@
 //! [logstash](https://www.elastic.co/products/logstash).
 //! - MC_LOG_UDP_JSON - When set to host:port, enables logging JSON log messages into a UDP socket.
 //! Suitable for use with [filebeat](https://www.elastic.co/products/beats/filebeat).
+//! - MC_LOG_SINKS - Sends log messages to additional sinks (name=argument, separated by comma),
+//! built by the factories registered with `register_log_sink_factory`. See the `sinks` module.
 //! - MC_LOG_EXTRA_CONTEXT - Adds an extra logging context (key=val, separated by comma).
 
-/// Sets chan_size for stdout, gelf, and UDP loggers
+/// Sets chan_size for the stdout logger
 const STDOUT_CHANNEL_SIZE: usize = 100_000;
-const GELF_CHANNEL_SIZE: usize = 100_000;
-const UDP_CHANNEL_SIZE: usize = 100_000;
 
 /// Wrap calls to assert! macros to record an error message before panic
 #[macro_export]
@@ -98,16 +98,17 @@ pub use slog::{o, FnValue, Logger, PushFnValue};
 pub use slog_scope;
 
 mod sentry_logger;
+pub mod sinks;
 /// Internal modules/imports.
 mod udp_writer;
 
+pub use sinks::{add_log_sink, register_log_sink_factory, LogSink, LogSinkFactory};
+
 use build_info;
 use chrono;
 use lazy_static::lazy_static;
 use sentry_logger::SentryLogger;
 use slog::Drain;
-use slog_gelf;
-use slog_json;
 use std::{env, io, sync::Mutex, time::Instant};
 
 /// Custom timestamp function for use with slog-term
@@ -134,64 +135,25 @@ fn create_stdout_logger() -> slog::Fuse<slog_async::Async> {
 /// Create a GELF (https://docs.graylog.org/en/3.0/pages/gelf.html) logger.
 fn create_gelf_logger() -> Option<slog::Fuse<slog_async::Async>> {
     env::var("MC_LOG_GELF").ok().map(|remote_host_port| {
-        let local_hostname = hostname::get_hostname().unwrap();
-
-        let drain = slog_envlogger::new(
-            slog_gelf::Gelf::new(&local_hostname, &remote_host_port[..])
-                .expect("failed creating Gelf logger for")
-                .fuse(),
-        );
-
-        slog_async::Async::new(drain)
-            .thread_name("slog-gelf".into())
-            .chan_size(GELF_CHANNEL_SIZE)
-            .build()
-            .fuse()
+        let sink = sinks::create_gelf_sink(&remote_host_port)
+            .unwrap_or_else(|err| panic!("failed creating Gelf logger: {}", err));
+        sinks::spawn_sink(sink, "slog-gelf")
     })
 }
 
 /// Create a UDP JSON logger.
 fn create_udp_json_logger() -> Option<slog::Fuse<slog_async::Async>> {
     env::var("MC_LOG_UDP_JSON").ok().map(|remote_host_port| {
-        let drain = slog_json::Json::new(udp_writer::UdpWriter::new(remote_host_port))
-            .set_newlines(false)
-            .set_flush(true)
-            .add_key_value(o!(
-                    "ts" => PushFnValue(move |_, ser| {
-                        ser.emit(chrono::Local::now().to_rfc3339())
-                    }),
-                    "level_str" => FnValue(move |record| {
-                        record.level().as_short_str()
-                    }),
-                    "level"  => FnValue(move |record| {
-                        record.level().as_usize()
-                    }),
-                    "message" => PushFnValue(move |record, ser| {
-                        // Cap message at 65000 bytes to increase chances of it fitting in a UDP
-                        // packet.
-                        let mut msg = format!("{}", record.msg());
-                        if msg.len() > 65000 {
-                            msg = format!("{}... <trimmed>", &msg[0..65000]);
-                        }
-                        ser.emit(msg)
-                    }),
-            ))
-            .build()
-            .fuse();
-
-        let drain = slog_envlogger::new(drain);
-
-        slog_async::Async::new(drain)
-            .thread_name("slog-udp".into())
-            .chan_size(UDP_CHANNEL_SIZE)
-            .build()
-            .fuse()
+        let sink = sinks::create_udp_json_sink(&remote_host_port)
+            .unwrap_or_else(|err| panic!("failed creating UDP JSON logger: {}", err));
+        sinks::spawn_sink(sink, "slog-udp")
     })
 }
 
 /// Create the root logger, which logs to stdout and optionally a GELF endpoint
 /// (if the `MC_LOG_GELF` environment variable is set) or a UDP JSON endpoint (if the
-/// `MC_LOG_UDP_JSON` environment variable is set).
+/// `MC_LOG_UDP_JSON` environment variable is set), along with the sinks listed in `MC_LOG_SINKS`
+/// or added with `add_log_sink`.
 pub fn create_root_logger() -> Logger {
     // Support MC_LOG in addition to RUST_LOG. This makes allows us to not affect cargo's logs when
     // doing stuff like MC_LOG=trace cargo test -p ...
@@ -205,12 +167,13 @@ pub fn create_root_logger() -> Logger {
     }
 
     // Create our loggers.
-    let network_logger = match (create_gelf_logger(), create_udp_json_logger()) {
-        (None, None) => None,
-        (Some(gelf), None) => Some(gelf),
-        (None, Some(udp_json)) => Some(udp_json),
+    let mut sink_loggers = match (create_gelf_logger(), create_udp_json_logger()) {
+        (None, None) => vec![],
+        (Some(gelf), None) => vec![gelf],
+        (None, Some(udp_json)) => vec![udp_json],
         (Some(_), Some(_)) => panic!("MC_LOG_GELF and MC_LOG_UDP_JSON are mutually exclusive!"),
     };
+    sink_loggers.extend(sinks::create_sinks());
     let stdout_logger = create_stdout_logger();
 
     // Extra context that always gets added to each log message.
@@ -220,13 +183,13 @@ pub fn create_root_logger() -> Logger {
     );
 
     // Create root logger.
-    let mut root_logger = if let Some(network_logger) = network_logger {
+    let mut root_logger = if sink_loggers.is_empty() {
+        Logger::root(stdout_logger, extra_kv)
+    } else {
         Logger::root(
-            slog::Duplicate(stdout_logger, network_logger).fuse(),
+            slog::Duplicate(stdout_logger, sinks::Fanout(sink_loggers)).fuse(),
             extra_kv,
         )
-    } else {
-        Logger::root(stdout_logger, extra_kv)
     };
 
     // Add extra context if it is available.
@@ -0,0 +1,224 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Pluggable log sinks.
+//!
+//! Besides stdout, the root logger sends every record to the sinks listed in `MC_LOG_SINKS`, as
+//! comma-separated `name=argument` pairs, e.g. `MC_LOG_SINKS=gelf=graylog:12201`. The name picks
+//! a factory, which builds the sink from the argument. `gelf` and `udp_json` are built in, and
+//! binaries may register factories of their own with `register_log_sink_factory`, or add sinks
+//! they built themselves with `add_log_sink`, before creating their logger.
+
+use super::udp_writer::UdpWriter;
+use slog::{o, Drain, FnValue, Level, Never, OwnedKVList, PushFnValue, Record};
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Arc, Mutex},
+};
+
+/// A log sink.
+pub type LogSink = Arc<dyn slog::SendSyncRefUnwindSafeDrain<Ok = (), Err = Never>>;
+
+/// Builds a sink from the argument given to it in `MC_LOG_SINKS`.
+pub type LogSinkFactory = fn(&str) -> Result<LogSink, String>;
+
+/// Sets chan_size for sink loggers.
+const SINK_CHANNEL_SIZE: usize = 100_000;
+
+lazy_static::lazy_static! {
+    static ref LOG_SINK_FACTORIES: Mutex<HashMap<String, LogSinkFactory>> = {
+        let mut factories = HashMap::new();
+        factories.insert("gelf".to_string(), create_gelf_sink as LogSinkFactory);
+        factories.insert("udp_json".to_string(), create_udp_json_sink as LogSinkFactory);
+        Mutex::new(factories)
+    };
+
+    static ref LOG_SINKS: Mutex<Vec<LogSink>> = Mutex::new(Vec::new());
+}
+
+/// Register a factory that `MC_LOG_SINKS` can refer to by name, replacing the factory of the same
+/// name, if any.
+pub fn register_log_sink_factory(name: &str, factory: LogSinkFactory) {
+    LOG_SINK_FACTORIES
+        .lock()
+        .expect("mutex poisoned")
+        .insert(name.to_string(), factory);
+}
+
+/// Add a sink that receives the records of every root logger created from now on.
+pub fn add_log_sink(sink: LogSink) {
+    LOG_SINKS.lock().expect("mutex poisoned").push(sink);
+}
+
+/// Parses comma-separated `name=argument` pairs, such as those of `MC_LOG_SINKS`. A trailing comma
+/// is allowed.
+pub fn parse_sink_specs(specs: &str) -> Result<Vec<(String, String)>, String> {
+    specs
+        .split(',')
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| {
+            let mut name_arg = spec.splitn(2, '=');
+            match (name_arg.next(), name_arg.next()) {
+                (Some(name), Some(arg)) if !name.is_empty() => {
+                    Ok((name.to_string(), arg.to_string()))
+                }
+                _ => Err(format!("invalid sink entry: {}", spec)),
+            }
+        })
+        .collect()
+}
+
+/// Builds the sinks listed in `MC_LOG_SINKS`, followed by the ones added with `add_log_sink`.
+/// Each sink runs on a thread of its own, so that a slow sink does not hold back the others.
+pub(crate) fn create_sinks() -> Vec<slog::Fuse<slog_async::Async>> {
+    let mut sinks = Vec::new();
+
+    if let Ok(specs) = env::var("MC_LOG_SINKS") {
+        let factories = LOG_SINK_FACTORIES.lock().expect("mutex poisoned");
+        for (name, arg) in
+            parse_sink_specs(&specs).unwrap_or_else(|err| panic!("MC_LOG_SINKS: {}", err))
+        {
+            let factory = factories
+                .get(&name)
+                .unwrap_or_else(|| panic!("unknown log sink: {}", name));
+            let sink = factory(&arg)
+                .unwrap_or_else(|err| panic!("failed creating log sink {}: {}", name, err));
+            sinks.push(sink);
+        }
+    }
+    sinks.extend(LOG_SINKS.lock().expect("mutex poisoned").iter().cloned());
+
+    sinks
+        .into_iter()
+        .map(|sink| spawn_sink(sink, "slog-sink"))
+        .collect()
+}
+
+/// Runs a sink on a thread of its own, behind the `MC_LOG` filter.
+pub(crate) fn spawn_sink(sink: LogSink, thread_name: &str) -> slog::Fuse<slog_async::Async> {
+    slog_async::Async::new(slog_envlogger::new(sink))
+        .thread_name(thread_name.into())
+        .chan_size(SINK_CHANNEL_SIZE)
+        .build()
+        .fuse()
+}
+
+/// Sends every record to all of its sinks.
+pub(crate) struct Fanout(pub Vec<slog::Fuse<slog_async::Async>>);
+
+impl Drain for Fanout {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> Result<(), Never> {
+        for sink in &self.0 {
+            sink.log(record, values)?;
+        }
+        Ok(())
+    }
+
+    fn is_enabled(&self, level: Level) -> bool {
+        self.0.iter().any(|sink| sink.is_enabled(level))
+    }
+}
+
+/// Create a GELF (https://docs.graylog.org/en/3.0/pages/gelf.html) sink.
+pub(crate) fn create_gelf_sink(remote_host_port: &str) -> Result<LogSink, String> {
+    let local_hostname = hostname::get_hostname().ok_or("failed getting hostname")?;
+    let drain = slog_gelf::Gelf::new(&local_hostname, remote_host_port)
+        .map_err(|err| err.to_string())?
+        .fuse();
+    Ok(Arc::new(Mutex::new(drain).fuse()))
+}
+
+/// Create a UDP JSON sink.
+pub(crate) fn create_udp_json_sink(remote_host_port: &str) -> Result<LogSink, String> {
+    let drain = slog_json::Json::new(UdpWriter::new(remote_host_port.to_string()))
+        .set_newlines(false)
+        .set_flush(true)
+        .add_key_value(o!(
+                "ts" => PushFnValue(move |_, ser| {
+                    ser.emit(chrono::Local::now().to_rfc3339())
+                }),
+                "level_str" => FnValue(move |record| {
+                    record.level().as_short_str()
+                }),
+                "level"  => FnValue(move |record| {
+                    record.level().as_usize()
+                }),
+                "message" => PushFnValue(move |record, ser| {
+                    // Cap message at 65000 bytes to increase chances of it fitting in a UDP
+                    // packet.
+                    let mut msg = format!("{}", record.msg());
+                    if msg.len() > 65000 {
+                        msg = format!("{}... <trimmed>", &msg[0..65000]);
+                    }
+                    ser.emit(msg)
+                }),
+        ))
+        .build()
+        .fuse();
+    Ok(Arc::new(Mutex::new(drain).fuse()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use slog::Logger;
+
+    #[test]
+    fn test_parse_sink_specs() {
+        assert_eq!(
+            parse_sink_specs("gelf=graylog:12201,otlp=http://collector:4317,").unwrap(),
+            vec![
+                ("gelf".to_string(), "graylog:12201".to_string()),
+                ("otlp".to_string(), "http://collector:4317".to_string()),
+            ]
+        );
+        assert_eq!(parse_sink_specs("").unwrap(), vec![]);
+        assert!(parse_sink_specs("gelf").is_err());
+        assert!(parse_sink_specs("=graylog:12201").is_err());
+    }
+
+    /// Counts the records it receives.
+    struct CountingSink(Mutex<usize>);
+
+    impl Drain for CountingSink {
+        type Ok = ();
+        type Err = Never;
+
+        fn log(&self, _record: &Record, _values: &OwnedKVList) -> Result<(), Never> {
+            *self.0.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fanout() {
+        let sinks: Vec<Arc<CountingSink>> = (0..2)
+            .map(|_| Arc::new(CountingSink(Mutex::new(0))))
+            .collect();
+        let fanout = Fanout(
+            sinks
+                .iter()
+                .map(|sink| {
+                    slog_async::Async::new(sink.clone())
+                        .chan_size(SINK_CHANNEL_SIZE)
+                        .build()
+                        .fuse()
+                })
+                .collect(),
+        );
+
+        {
+            let logger = Logger::root(fanout, o!());
+            slog::info!(logger, "first");
+            slog::error!(logger, "second");
+        }
+
+        // Dropping the logger flushes the sinks.
+        for sink in sinks {
+            assert_eq!(*sink.0.lock().unwrap(), 2);
+        }
+    }
+}
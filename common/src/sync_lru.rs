@@ -0,0 +1,223 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A bounded LRU cache that can be shared between threads.
+//!
+//! Long-running services keep a number of maps keyed by data received from the network (peers,
+//! transactions, ...). Backing those maps with a `SyncLruCache` guarantees that their memory usage
+//! stays bounded, regardless of how many distinct keys are observed over the lifetime of the
+//! process.
+
+use crate::lru::LruCache;
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt, hash::Hash};
+use std::sync::{Mutex, MutexGuard};
+
+/// A thread-safe wrapper around `LruCache`.
+pub struct SyncLruCache<K: Hash + Eq, V> {
+    inner: Mutex<LruCache<K, V>>,
+}
+
+impl<K: Hash + Eq, V> SyncLruCache<K, V> {
+    /// Creates a new cache that holds at most `cap` items.
+    pub fn new(cap: usize) -> Self {
+        Self {
+            inner: Mutex::new(LruCache::new(cap)),
+        }
+    }
+
+    /// Locks the cache, allowing multiple operations to be performed atomically.
+    pub fn lock(&self) -> MutexGuard<LruCache<K, V>> {
+        self.inner.lock().expect("lock poisoned")
+    }
+
+    /// Puts a key-value pair into the cache, evicting the least recently used entry if the cache
+    /// is full. Returns the previous value if the key was already present.
+    pub fn put(&self, k: K, v: V) -> Option<V> {
+        self.lock().put(k, v)
+    }
+
+    /// Returns a copy of the value associated with a key, marking it as most recently used.
+    pub fn get<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.lock().get(k).cloned()
+    }
+
+    /// Returns a copy of the value associated with a key, without updating its position.
+    pub fn peek<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.lock().peek(k).cloned()
+    }
+
+    /// Returns true if the cache contains the given key, without updating its position.
+    pub fn contains<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().contains(k)
+    }
+
+    /// Removes a key from the cache, returning its value if it was present.
+    pub fn pop<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock().pop(k)
+    }
+
+    /// Retains only the entries for which `f` returns true. Returns the removed keys.
+    pub fn retain<F>(&self, mut f: F) -> Vec<K>
+    where
+        K: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut cache = self.lock();
+        let removed: Vec<K> = cache
+            .iter()
+            .filter(|(k, v)| !f(k, v))
+            .map(|(k, _v)| k.clone())
+            .collect();
+        for k in removed.iter() {
+            cache.pop(k);
+        }
+        removed
+    }
+
+    /// Returns a snapshot of the cache's entries, from most to least recently used.
+    pub fn entries(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.lock()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns the maximum number of entries the cache can hold.
+    pub fn cap(&self) -> usize {
+        self.lock().cap()
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Clone for SyncLruCache<K, V> {
+    fn clone(&self) -> Self {
+        let cache = self.lock();
+        let mut copy = LruCache::new(cache.cap());
+        // Insert from least to most recently used so that the copy preserves the ordering.
+        let entries: Vec<(&K, &V)> = cache.iter().collect();
+        for (k, v) in entries.into_iter().rev() {
+            copy.put(k.clone(), v.clone());
+        }
+        Self {
+            inner: Mutex::new(copy),
+        }
+    }
+}
+
+impl<K: Hash + Eq + fmt::Debug, V: fmt::Debug> fmt::Debug for SyncLruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.lock().iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{sync::Arc, thread, vec};
+
+    #[test]
+    // Inserting beyond capacity should evict the least recently used entry.
+    fn test_evicts_least_recently_used() {
+        let cache = SyncLruCache::new(2);
+        cache.put("apple", 1);
+        cache.put("banana", 2);
+
+        // Touch "apple" so that "banana" becomes the least recently used entry.
+        assert_eq!(cache.get(&"apple"), Some(1));
+        cache.put("pear", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&"apple"));
+        assert!(!cache.contains(&"banana"));
+        assert!(cache.contains(&"pear"));
+    }
+
+    #[test]
+    fn test_retain() {
+        let cache = SyncLruCache::new(10);
+        for i in 0..10u64 {
+            cache.put(i, i * 10);
+        }
+
+        let mut removed = cache.retain(|_k, v| *v >= 50);
+        removed.sort();
+        assert_eq!(removed, vec![0, 1, 2, 3, 4]);
+        assert_eq!(cache.len(), 5);
+        assert_eq!(cache.peek(&7), Some(70));
+        assert_eq!(cache.peek(&3), None);
+    }
+
+    #[test]
+    // Cloning should preserve both the contents and the recency ordering.
+    fn test_clone_preserves_order() {
+        let cache = SyncLruCache::new(3);
+        cache.put(1, "a");
+        cache.put(2, "b");
+        cache.put(3, "c");
+        cache.get(&1);
+
+        let copy = cache.clone();
+        assert_eq!(copy.cap(), 3);
+        assert_eq!(copy.entries(), cache.entries());
+
+        // The copy is independent of the original.
+        copy.put(4, "d");
+        assert!(!copy.contains(&2));
+        assert!(cache.contains(&2));
+    }
+
+    #[test]
+    fn test_concurrent_puts_stay_bounded() {
+        let cache = Arc::new(SyncLruCache::new(100));
+        let handles: Vec<_> = (0..4u64)
+            .map(|t| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for i in 0..1000u64 {
+                        cache.put(t * 1000 + i, i);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(cache.len(), 100);
+    }
+}
@@ -22,6 +22,7 @@ use std::{
 };
 use transaction::{
     amount::Amount,
+    dust::{DustPolicy, DustRules},
     encrypted_fog_hint::EncryptedFogHint,
     range::Range,
     ring_signature::{
@@ -31,7 +32,8 @@ use transaction::{
     tx,
     tx::{TxOutMembershipElement, TxOutMembershipHash, TxOutMembershipProof},
     validation::TransactionValidationError,
-    BlockContents, BlockSignature, CompressedCommitment, RedactedTx,
+    ArchiveManifest, BlockContents, BlockSignature, CompressedCommitment, RedactedTx,
+    SignedArchiveManifest,
 };
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -899,6 +901,67 @@ impl TryFrom<&blockchain::BlockSignature> for BlockSignature {
     }
 }
 
+/// Convert SignedArchiveManifest --> blockchain::SignedArchiveManifest.
+impl From<&SignedArchiveManifest> for blockchain::SignedArchiveManifest {
+    fn from(src: &SignedArchiveManifest) -> Self {
+        let mut manifest = blockchain::ArchiveManifest::new();
+        manifest.set_first_block_index(src.manifest().first_block_index);
+        manifest.set_num_blocks(src.manifest().num_blocks);
+        manifest.set_merkle_root(src.manifest().merkle_root.to_vec());
+
+        let mut dst = blockchain::SignedArchiveManifest::new();
+        dst.set_manifest(manifest);
+        dst.set_signature(external::Ed25519Signature::from(src.signature()));
+        dst.set_signer(external::Ed25519Public::from(src.signer()));
+        dst
+    }
+}
+
+/// Convert blockchain::SignedArchiveManifest --> SignedArchiveManifest.
+impl TryFrom<&blockchain::SignedArchiveManifest> for SignedArchiveManifest {
+    type Error = ConversionError;
+
+    fn try_from(source: &blockchain::SignedArchiveManifest) -> Result<Self, Self::Error> {
+        let manifest = ArchiveManifest {
+            first_block_index: source.get_manifest().get_first_block_index(),
+            num_blocks: source.get_manifest().get_num_blocks(),
+            merkle_root: <[u8; 32]>::try_from(source.get_manifest().get_merkle_root())?,
+        };
+        let signature = Ed25519Signature::try_from(source.get_signature())?;
+        let signer = Ed25519Public::try_from(source.get_signer())?;
+        Ok(SignedArchiveManifest::new(manifest, signature, signer))
+    }
+}
+
+/// Convert DustPolicy --> blockchain::DustPolicy.
+impl From<DustPolicy> for blockchain::DustPolicy {
+    fn from(src: DustPolicy) -> Self {
+        match src {
+            DustPolicy::AllowedAlways => Self::AllowedAlways,
+            DustPolicy::AllowedAsChange => Self::AllowedAsChange,
+            DustPolicy::Rejected => Self::Rejected,
+        }
+    }
+}
+
+/// Convert blockchain::DustPolicy --> DustPolicy.
+impl From<blockchain::DustPolicy> for DustPolicy {
+    fn from(src: blockchain::DustPolicy) -> Self {
+        match src {
+            blockchain::DustPolicy::AllowedAlways => Self::AllowedAlways,
+            blockchain::DustPolicy::AllowedAsChange => Self::AllowedAsChange,
+            blockchain::DustPolicy::Rejected => Self::Rejected,
+        }
+    }
+}
+
+/// Convert blockchain::LastBlockInfoResponse --> DustRules.
+impl From<&blockchain::LastBlockInfoResponse> for DustRules {
+    fn from(src: &blockchain::LastBlockInfoResponse) -> Self {
+        DustRules::new(src.get_dust_policy().into(), src.get_minimum_output_value())
+    }
+}
+
 /// Helper method for getting the suggested path/filename for a given block index.
 pub fn block_num_to_s3block_path(block_index: transaction::BlockIndex) -> PathBuf {
     let filename = format!("{:016x}.pb", block_index);
@@ -910,6 +973,14 @@ pub fn block_num_to_s3block_path(block_index: transaction::BlockIndex) -> PathBu
     path
 }
 
+/// Helper method for getting the suggested path/filename for the manifest of the range of blocks
+/// starting at a given block index.
+pub fn archive_manifest_path(first_block_index: transaction::BlockIndex) -> PathBuf {
+    let mut path = PathBuf::from("manifests");
+    path.push(format!("{:016x}.pb", first_block_index));
+    path
+}
+
 #[cfg(test)]
 mod conversion_tests {
     extern crate rand;
@@ -1325,4 +1396,12 @@ mod conversion_tests {
             PathBuf::from("1a/2b/3c/4e/5a/6b/7c/1a2b3c4e5a6b7c8d.pb"),
         );
     }
+
+    #[test]
+    fn test_archive_manifest_path() {
+        assert_eq!(
+            archive_manifest_path(0x3e8),
+            PathBuf::from("manifests/00000000000003e8.pb"),
+        );
+    }
 }
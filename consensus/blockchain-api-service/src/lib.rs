@@ -1,19 +1,27 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 //! Serves blockchain-related API requests.
+//!
+//! Shared by the consensus service and the read-only consensus mirror, which both serve the
+//! client-facing `BlockchainApi` out of a ledger.
 
 use common::logger::{log, Logger};
-use grpc_util::{rpc_logger, send_result};
+use grpc_util::{rpc_database_err, rpc_logger, send_result};
 use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
 use ledger_db::Ledger;
 use metrics::{self, SVC_COUNTERS};
 use mobilecoin_api::{
-    blockchain::{self, BlocksRequest, BlocksResponse, LastBlockInfoResponse},
+    blockchain::{
+        self, BlockIdsResponse, BlocksRequest, BlocksResponse, LastBlockInfoResponse,
+        TxOutsRequest, TxOutsResponse,
+    },
     blockchain_grpc::BlockchainApi,
     empty::Empty,
+    external,
 };
 use protobuf::RepeatedField;
 use std::{cmp, convert::From};
+use transaction::{constants::BASE_FEE, dust::DustRules};
 
 #[derive(Clone)]
 pub struct BlockchainApiService<L: Ledger + Clone> {
@@ -23,6 +31,12 @@ pub struct BlockchainApiService<L: Ledger + Clone> {
     /// Maximal number of results to return in API calls that return multiple results.
     max_page_size: u16,
 
+    /// Dust rules advertised to clients.
+    dust_rules: DustRules,
+
+    /// Minimum transaction fee advertised to clients.
+    minimum_fee: u64,
+
     /// Logger.
     logger: Logger,
 }
@@ -32,21 +46,35 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         BlockchainApiService {
             ledger,
             max_page_size: 2000,
+            dust_rules: DustRules::default(),
+            minimum_fee: BASE_FEE,
             logger,
         }
     }
 
     // Set the maximum number of items returned for a single request.
-    #[allow(dead_code)]
     pub fn set_max_page_size(&mut self, max_page_size: u16) {
         self.max_page_size = max_page_size;
     }
 
+    /// Set the dust rules advertised to clients.
+    pub fn set_dust_rules(&mut self, dust_rules: DustRules) {
+        self.dust_rules = dust_rules;
+    }
+
+    /// Set the minimum transaction fee advertised to clients.
+    pub fn set_minimum_fee(&mut self, minimum_fee: u64) {
+        self.minimum_fee = minimum_fee;
+    }
+
     /// Returns information about the last block.
     fn get_last_block_info_helper(&mut self) -> Result<LastBlockInfoResponse, ledger_db::Error> {
         let num_blocks = self.ledger.num_blocks()?;
         let mut resp = LastBlockInfoResponse::new();
         resp.set_index(num_blocks - 1);
+        resp.set_dust_policy(self.dust_rules.policy.into());
+        resp.set_minimum_output_value(self.dust_rules.minimum_output_value);
+        resp.set_minimum_fee(self.minimum_fee);
 
         Ok(resp)
     }
@@ -92,6 +120,51 @@ impl<L: Ledger + Clone> BlockchainApiService<L> {
         response.set_blocks(RepeatedField::from_vec(blocks));
         Ok(response)
     }
+
+    /// Returns the IDs of the blocks in the range [offset, offset + limit), clamped the same way
+    /// `get_blocks_helper` clamps the range.
+    fn get_block_ids_helper(&mut self, offset: u64, limit: u32) -> Result<BlockIdsResponse, ()> {
+        let block_ids: Vec<Vec<u8>> = self
+            .get_blocks_helper(offset, limit)?
+            .take_blocks()
+            .into_iter()
+            .map(|mut block| block.take_id())
+            .collect();
+
+        let mut response = BlockIdsResponse::new();
+        response.set_block_ids(RepeatedField::from_vec(block_ids));
+        Ok(response)
+    }
+
+    /// Returns TxOuts in the range [offset, offset + limit), by global TxOut index.
+    ///
+    /// If `limit` exceeds `max_page_size`, then only [offset, offset + max_page_size) is returned.
+    /// If `limit` exceeds the number of TxOuts in the database, then only [offset, num_txos) is returned.
+    fn get_tx_outs_helper(
+        &mut self,
+        offset: u64,
+        limit: u32,
+    ) -> Result<TxOutsResponse, ledger_db::Error> {
+        let num_txos = self.ledger.num_txos()?;
+        let start_index = cmp::min(offset, num_txos);
+        let end_index = cmp::min(
+            offset.saturating_add(cmp::min(limit, self.max_page_size as u32) as u64),
+            num_txos,
+        );
+
+        let tx_outs = (start_index..end_index)
+            .map(|index| {
+                self.ledger
+                    .get_tx_out_by_index(index)
+                    .map(|tx_out| external::TxOut::from(&tx_out))
+            })
+            .collect::<Result<Vec<external::TxOut>, ledger_db::Error>>()?;
+
+        let mut response = TxOutsResponse::new();
+        response.set_tx_outs(RepeatedField::from_vec(tx_outs));
+        response.set_num_tx_outs(num_txos);
+        Ok(response)
+    }
 }
 
 impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
@@ -135,6 +208,54 @@ impl<L: Ledger + Clone> BlockchainApi for BlockchainApiService<L> {
             send_result(ctx, sink, resp, &logger);
         });
     }
+
+    /// Gets the IDs of a range [offset, offset+limit) of Blocks.
+    fn get_block_ids(
+        &mut self,
+        ctx: RpcContext,
+        request: BlocksRequest,
+        sink: UnarySink<BlockIdsResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            log::trace!(
+                logger,
+                "Received GetBlockIds for offset {} and limit {})",
+                request.offset,
+                request.limit
+            );
+
+            let resp = self
+                .get_block_ids_helper(request.offset, request.limit)
+                .map_err(|_| RpcStatus::new(RpcStatusCode::INTERNAL, None));
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
+
+    /// Gets a range [offset, offset+limit) of TxOuts.
+    fn get_tx_outs(
+        &mut self,
+        ctx: RpcContext,
+        request: TxOutsRequest,
+        sink: UnarySink<TxOutsResponse>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            log::trace!(
+                logger,
+                "Received TxOutsRequest for offset {} and limit {})",
+                request.offset,
+                request.limit
+            );
+
+            let resp = self
+                .get_tx_outs_helper(request.offset, request.limit)
+                .map_err(|err| rpc_database_err(err, &logger));
+            send_result(ctx, sink, resp, &logger);
+        });
+    }
 }
 
 #[cfg(test)]
@@ -142,25 +263,23 @@ mod tests {
     use super::*;
     use common::logger::test_with_logger;
     use keys::{FromRandom, RistrettoPrivate};
-    use ledger_db::LedgerDB;
+    use ledger_db::InMemoryLedger;
     use rand::{rngs::StdRng, SeedableRng};
-    use tempdir::TempDir;
-    use transaction::{account_keys::AccountKey, tx::TxOut, Block, BlockContents, BLOCK_VERSION};
-
-    /// Creates a LedgerDB instance.
-    fn create_db() -> LedgerDB {
-        let temp_dir = TempDir::new("test").unwrap();
-        let path = temp_dir.path().to_path_buf();
-        LedgerDB::create(path.clone()).unwrap();
-        LedgerDB::open(path).unwrap()
+    use transaction::{
+        account_keys::AccountKey, dust::DustPolicy, tx::TxOut, Block, BlockContents, BLOCK_VERSION,
+    };
+
+    /// Creates an empty ledger.
+    fn create_db() -> InMemoryLedger {
+        InMemoryLedger::default()
     }
 
-    /// Populates the LedgerDB with initial data, and returns the Block entities that were written.
+    /// Populates the ledger with initial data, and returns the Block entities that were written.
     ///
     /// # Arguments
     /// * `n_blocks` - number of blocks of transactions to write to `db`.
     ///
-    fn populate_db(db: &mut LedgerDB, n_blocks: u64) -> Vec<Block> {
+    fn populate_db(db: &mut InMemoryLedger, n_blocks: u64) -> Vec<Block> {
         let initial_amount: u64 = 5_000 * 1_000_000_000_000;
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
 
@@ -227,6 +346,7 @@ mod tests {
 
         let mut expected_response = LastBlockInfoResponse::new();
         expected_response.set_index(block_entities.last().unwrap().index);
+        expected_response.set_minimum_fee(BASE_FEE);
         assert_eq!(
             block_entities.last().unwrap().index,
             ledger_db.num_blocks().unwrap() - 1
@@ -238,6 +358,38 @@ mod tests {
         assert_eq!(block_response, expected_response);
     }
 
+    #[test_with_logger]
+    // `get_last_block_info` should advertise the configured dust rules.
+    fn test_get_last_block_info_dust_rules(logger: Logger) {
+        let mut ledger_db = create_db();
+        let _blocks = populate_db(&mut ledger_db, 2);
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        blockchain_api_service.set_dust_rules(DustRules::new(DustPolicy::AllowedAsChange, 1000));
+
+        let response = blockchain_api_service.get_last_block_info_helper().unwrap();
+        assert_eq!(
+            response.get_dust_policy(),
+            blockchain::DustPolicy::AllowedAsChange
+        );
+        assert_eq!(response.get_minimum_output_value(), 1000);
+    }
+
+    #[test_with_logger]
+    // `get_last_block_info` should advertise the configured minimum fee.
+    fn test_get_last_block_info_minimum_fee(logger: Logger) {
+        let mut ledger_db = create_db();
+        let _blocks = populate_db(&mut ledger_db, 2);
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        let response = blockchain_api_service.get_last_block_info_helper().unwrap();
+        assert_eq!(response.get_minimum_fee(), BASE_FEE);
+
+        blockchain_api_service.set_minimum_fee(BASE_FEE * 10);
+        let response = blockchain_api_service.get_last_block_info_helper().unwrap();
+        assert_eq!(response.get_minimum_fee(), BASE_FEE * 10);
+    }
+
     #[test_with_logger]
     // `get_blocks` should returns the correct range of blocks.
     fn test_get_blocks_response_range(logger: Logger) {
@@ -310,4 +462,63 @@ mod tests {
         assert_eq!(expected_blocks.get(0).unwrap(), blocks.get(0).unwrap());
         assert_eq!(expected_blocks.get(4).unwrap(), blocks.get(4).unwrap());
     }
+
+    #[test_with_logger]
+    // `get_block_ids` should return the IDs of the same blocks `get_blocks` returns.
+    fn test_get_block_ids(logger: Logger) {
+        let mut ledger_db = create_db();
+        let block_entities = populate_db(&mut ledger_db, 20);
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db, logger);
+        blockchain_api_service.set_max_page_size(5);
+
+        let response = blockchain_api_service.get_block_ids_helper(3, 100).unwrap();
+        let expected_block_ids: Vec<Vec<u8>> = block_entities[3..8]
+            .iter()
+            .map(|block| block.id.as_ref().to_vec())
+            .collect();
+        assert_eq!(response.block_ids.into_vec(), expected_block_ids);
+
+        // Requests past the end of the ledger return the available block IDs.
+        let response = blockchain_api_service.get_block_ids_helper(18, 5).unwrap();
+        assert_eq!(response.block_ids.len(), 2);
+    }
+
+    #[test_with_logger]
+    // `get_tx_outs` should return the requested range of TxOuts, clamped to the ledger and page size.
+    fn test_get_tx_outs(logger: Logger) {
+        let mut ledger_db = create_db();
+        let _blocks = populate_db(&mut ledger_db, 20);
+        let num_txos = ledger_db.num_txos().unwrap();
+        assert_eq!(num_txos, 20);
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger_db.clone(), logger);
+        blockchain_api_service.set_max_page_size(5);
+
+        {
+            // The range [3, 5) should return two TxOuts.
+            let response = blockchain_api_service.get_tx_outs_helper(3, 2).unwrap();
+            assert_eq!(response.num_tx_outs, num_txos);
+            assert_eq!(response.tx_outs.len(), 2);
+            for (i, tx_out) in response.tx_outs.iter().enumerate() {
+                let expected = ledger_db.get_tx_out_by_index(3 + i as u64).unwrap();
+                assert_eq!(*tx_out, external::TxOut::from(&expected));
+            }
+        }
+
+        {
+            // Requests are limited to max_page_size.
+            let response = blockchain_api_service.get_tx_outs_helper(0, 100).unwrap();
+            assert_eq!(response.tx_outs.len(), 5);
+        }
+
+        {
+            // Requests past the end of the ledger return the available TxOuts.
+            let response = blockchain_api_service.get_tx_outs_helper(18, 5).unwrap();
+            assert_eq!(response.tx_outs.len(), 2);
+
+            let response = blockchain_api_service.get_tx_outs_helper(100, 5).unwrap();
+            assert_eq!(response.tx_outs.len(), 0);
+        }
+    }
 }
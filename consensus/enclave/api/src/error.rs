@@ -14,7 +14,7 @@ use mcserial::{
 use message_cipher::CipherError as MessageCipherError;
 use serde::{Deserialize, Serialize};
 use sgx_compat::sync::PoisonError;
-use transaction::validation::TransactionValidationError;
+use transaction::{fee_split::FeeSplitError, validation::TransactionValidationError};
 
 /// An enumeration of errors which can occur inside a consensus enclave.
 #[derive(Clone, Debug, Deserialize, Fail, PartialEq, PartialOrd, Serialize)]
@@ -54,6 +54,14 @@ pub enum Error {
     /// Signature error
     #[fail(display = "Signature error")]
     Signature,
+
+    /// The fee split provided by the local system is invalid.
+    #[fail(display = "Invalid fee split: {}", _0)]
+    FeeSplit(FeeSplitError),
+
+    /// The fees of the transactions in a block overflow.
+    #[fail(display = "The fees of a block overflow")]
+    FeeOverflow,
 }
 
 impl From<MessageCipherError> for Error {
@@ -110,6 +118,12 @@ impl From<AttestEnclaveError> for Error {
     }
 }
 
+impl From<FeeSplitError> for Error {
+    fn from(src: FeeSplitError) -> Error {
+        Error::FeeSplit(src)
+    }
+}
+
 impl From<Ed25519SignatureError> for Error {
     fn from(_src: Ed25519SignatureError) -> Error {
         Error::Signature
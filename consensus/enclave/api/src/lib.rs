@@ -20,10 +20,12 @@ use attest_enclave_api::{
 use common::ResponderId;
 use core::{hash::Hash, result::Result as StdResult};
 use keys::{Ed25519Public, X25519Public};
+use prost::Message;
 use serde::{Deserialize, Serialize};
 use transaction::{
+    fee_split::FeeSplit,
     ring_signature::KeyImage,
-    tx::{Tx, TxHash, TxOutMembershipProof},
+    tx::{Tx, TxHash, TxOut, TxOutMembershipProof},
     Block, BlockContents, BlockSignature,
 };
 
@@ -105,16 +107,67 @@ pub struct TxContext {
 
 pub type SealedBlockSigningKey = Vec<u8>;
 
+/// A client's request for TxOut membership proofs, sent over an attested channel.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize, Message)]
+pub struct TxOutProofsRequest {
+    /// Global indices of the TxOuts the client wants membership proofs for.
+    #[prost(uint64, repeated, tag = "1")]
+    pub indices: Vec<u64>,
+
+    /// Number of additional randomly selected TxOuts to return, for use as mixins.
+    #[prost(uint64, tag = "2")]
+    pub num_mixins: u64,
+}
+
+/// The response to a `TxOutProofsRequest`, encrypted for the requesting client.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize, Message)]
+pub struct TxOutProofsResponse {
+    /// The requested TxOuts, in request order.
+    #[prost(message, repeated, tag = "1")]
+    pub tx_outs: Vec<TxOut>,
+
+    /// Membership proofs for `tx_outs`.
+    #[prost(message, repeated, tag = "2")]
+    pub proofs: Vec<TxOutMembershipProof>,
+
+    /// Mixin candidates.
+    #[prost(message, repeated, tag = "3")]
+    pub mixins: Vec<TxOut>,
+
+    /// Membership proofs for `mixins`.
+    #[prost(message, repeated, tag = "4")]
+    pub mixin_proofs: Vec<TxOutMembershipProof>,
+}
+
+/// A client's question which of the key images it wants to spend are already spent by a pending
+/// transaction, sent over an attested channel.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize, Message)]
+pub struct PendingKeyImagesRequest {
+    /// The key images the client wants to spend.
+    #[prost(message, repeated, tag = "1")]
+    pub key_images: Vec<KeyImage>,
+}
+
+/// The response to a `PendingKeyImagesRequest`, encrypted for the requesting client.
+#[derive(Clone, Deserialize, Eq, PartialEq, Serialize, Message)]
+pub struct PendingKeyImagesResponse {
+    /// The requested key images that a transaction waiting to be externalized spends.
+    #[prost(message, repeated, tag = "1")]
+    pub pending_key_images: Vec<KeyImage>,
+}
+
 /// The API for interacting with a consensus node's enclave.
 pub trait ConsensusEnclave {
     // UTILITY METHODS
 
-    /// Perform one-time initialization upon enclave startup.
+    /// Perform one-time initialization upon enclave startup. `fee_split` is how the fees of the
+    /// blocks formed by the enclave are split between fee recipients.
     fn enclave_init(
         &self,
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        fee_split: &FeeSplit,
     ) -> Result<SealedBlockSigningKey>;
 
     /// Retrieve the public identity of the enclave.
@@ -169,6 +222,34 @@ pub trait ConsensusEnclave {
     /// sync with the client.
     fn client_discard_message(&self, msg: EnclaveMessage<ClientSession>) -> Result<()>;
 
+    /// Decrypts a client's request for TxOut membership proofs, so that untrusted can look up the
+    /// requested data in the ledger.
+    fn client_tx_out_proofs_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<TxOutProofsRequest>;
+
+    /// Encrypts the TxOuts and membership proofs gathered by untrusted for the requesting client.
+    fn client_tx_out_proofs_response(
+        &self,
+        channel_id: &ClientSession,
+        response: TxOutProofsResponse,
+    ) -> Result<EnclaveMessage<ClientSession>>;
+
+    /// Decrypts a client's request for the key images spent by pending transactions, so that
+    /// untrusted can look them up among the transactions it holds.
+    fn client_pending_key_images_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<PendingKeyImagesRequest>;
+
+    /// Encrypts the pending key images found by untrusted for the requesting client.
+    fn client_pending_key_images_response(
+        &self,
+        channel_id: &ClientSession,
+        response: PendingKeyImagesResponse,
+    ) -> Result<EnclaveMessage<ClientSession>>;
+
     // NODE-FACING METHODS
 
     /// Start a new outbound connection.
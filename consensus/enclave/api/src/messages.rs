@@ -2,7 +2,10 @@
 
 //! The message types used by the consensus_enclave_api.
 
-use crate::{LocallyEncryptedTx, ResponderId, SealedBlockSigningKey, WellFormedEncryptedTx};
+use crate::{
+    LocallyEncryptedTx, PendingKeyImagesResponse, ResponderId, SealedBlockSigningKey,
+    TxOutProofsResponse, WellFormedEncryptedTx,
+};
 use alloc::vec::Vec;
 use attest::{Quote, Report, TargetInfo, VerificationReport};
 use attest_enclave_api::{
@@ -10,13 +13,18 @@ use attest_enclave_api::{
     PeerSession,
 };
 use serde::{Deserialize, Serialize};
-use transaction::{tx::TxOutMembershipProof, Block};
+use transaction::{fee_split::FeeSplit, tx::TxOutMembershipProof, Block};
 
 /// An enumeration of API calls and their arguments for use across serialization boundaries.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum EnclaveCall {
     /// The [ConsensusEnclave::enclave_init()] method.
-    EnclaveInit(ResponderId, ResponderId, Option<SealedBlockSigningKey>),
+    EnclaveInit(
+        ResponderId,
+        ResponderId,
+        Option<SealedBlockSigningKey>,
+        FeeSplit,
+    ),
 
     /// The [PeerableEnclave::peer_init()] method.
     ///
@@ -91,6 +99,26 @@ pub enum EnclaveCall {
     /// Decrypts an incoming message and discard the data.
     ClientDiscardMessage(EnclaveMessage<ClientSession>),
 
+    /// The [ConsensusEnclave::client_tx_out_proofs_request()] method.
+    ///
+    /// Decrypts a client's request for TxOut membership proofs.
+    ClientTxOutProofsRequest(EnclaveMessage<ClientSession>),
+
+    /// The [ConsensusEnclave::client_tx_out_proofs_response()] method.
+    ///
+    /// Encrypts the requested TxOuts and membership proofs for a client.
+    ClientTxOutProofsResponse(ClientSession, TxOutProofsResponse),
+
+    /// The [ConsensusEnclave::client_pending_key_images_request()] method.
+    ///
+    /// Decrypts a client's request for the key images spent by pending transactions.
+    ClientPendingKeyImagesRequest(EnclaveMessage<ClientSession>),
+
+    /// The [ConsensusEnclave::client_pending_key_images_response()] method.
+    ///
+    /// Encrypts the pending key images for a client.
+    ClientPendingKeyImagesResponse(ClientSession, PendingKeyImagesResponse),
+
     /// The [ConsensusEnclave::client_tx_propose()] method.
     ///
     /// Start a new transaction proposal given the encrypted message from a peer.
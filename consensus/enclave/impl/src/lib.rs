@@ -15,7 +15,7 @@ extern crate alloc;
 mod identity;
 
 use ake_enclave::AkeEnclaveState;
-use alloc::{collections::BTreeSet, format, vec::Vec};
+use alloc::{collections::BTreeSet, format, string::String, vec::Vec};
 use attest::{IasNonce, IntelSealed, Quote, QuoteNonce, Report, TargetInfo, VerificationReport};
 use attest_enclave_api::{
     ClientAuthRequest, ClientAuthResponse, ClientSession, EnclaveMessage,
@@ -24,33 +24,25 @@ use attest_enclave_api::{
 use attest_trusted::SealAlgo;
 use common::ResponderId;
 use consensus_enclave_api::{
-    ConsensusEnclave, Error, LocallyEncryptedTx, Result, SealedBlockSigningKey, TxContext,
+    ConsensusEnclave, Error, LocallyEncryptedTx, PendingKeyImagesRequest, PendingKeyImagesResponse,
+    Result, SealedBlockSigningKey, TxContext, TxOutProofsRequest, TxOutProofsResponse,
     WellFormedEncryptedTx, WellFormedTxContext,
 };
-use core::convert::{TryFrom, TryInto};
-use digest::Input;
-use digestible::Digestible;
+use core::convert::TryFrom;
 use identity::Ed25519Identity;
-use keys::{Ed25519Pair, Ed25519Public, RistrettoPrivate, RistrettoPublic, X25519Public};
+use keys::{Ed25519Pair, Ed25519Public, X25519Public};
 use mcrand::McRng;
 use message_cipher::{AesMessageCipher, MessageCipher};
 use prost::Message;
 use rand_core::{CryptoRng, RngCore};
 use sgx_compat::sync::Mutex;
 use transaction::{
-    account_keys::PublicAddress,
-    amount::Amount,
-    blake2b_256::Blake2b256,
-    constants::{FEE_SPEND_PUBLIC_KEY, FEE_VIEW_PUBLIC_KEY},
-    onetime_keys::{compute_shared_secret, compute_tx_pubkey, create_onetime_public_key},
-    ring_signature::{Blinding, KeyImage, Scalar},
+    fee_split::FeeSplit,
+    ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutMembershipProof},
     Block, BlockContents, BlockSignature, RedactedTx, BLOCK_VERSION,
 };
 
-/// The prefix used when constructing the fees output blinding.
-const BLINDING_HASH_PREFIX: &[u8] = b"BLINDING_1";
-
 /// A well-formed transaction.
 #[derive(Clone, Eq, PartialEq, Message)]
 pub struct WellFormedTx {
@@ -100,6 +92,9 @@ pub struct SgxConsensusEnclave {
 
     /// Cipher used to encrypt well-formed-encrypted transactions.
     well_formed_encrypted_tx_cipher: Mutex<AesMessageCipher>,
+
+    /// How the fees of each block are split between fee recipients.
+    fee_split: Mutex<FeeSplit>,
 }
 
 impl core::default::Default for SgxConsensusEnclave {
@@ -110,6 +105,7 @@ impl core::default::Default for SgxConsensusEnclave {
             well_formed_encrypted_tx_cipher: Mutex::new(AesMessageCipher::new(
                 &mut McRng::default(),
             )),
+            fee_split: Mutex::new(FeeSplit::default()),
         }
     }
 }
@@ -143,9 +139,16 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         peer_self_id: &ResponderId,
         client_self_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        fee_split: &FeeSplit,
     ) -> Result<SealedBlockSigningKey> {
-        self.ake
-            .init(peer_self_id.clone(), client_self_id.clone())?;
+        // The fee split comes from the untrusted system.
+        fee_split.validate()?;
+        *self.fee_split.lock()? = fee_split.clone();
+
+        self.ake.init(
+            peer_responder_id(peer_self_id, fee_split),
+            client_self_id.clone(),
+        )?;
 
         // if we were passed a sealed key, unseal it and overwrite the private key
 
@@ -205,8 +208,49 @@ impl ConsensusEnclave for SgxConsensusEnclave {
         Ok(())
     }
 
+    fn client_tx_out_proofs_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<TxOutProofsRequest> {
+        let request_bytes = self.ake.client_decrypt(msg)?;
+        Ok(mcserial::decode(&request_bytes)?)
+    }
+
+    fn client_tx_out_proofs_response(
+        &self,
+        channel_id: &ClientSession,
+        response: TxOutProofsResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        if response.tx_outs.len() != response.proofs.len()
+            || response.mixins.len() != response.mixin_proofs.len()
+        {
+            return Err(Error::InvalidLocalMembershipProof);
+        }
+
+        let response_bytes = mcserial::encode(&response);
+        Ok(self.ake.client_encrypt(channel_id, &[], &response_bytes)?)
+    }
+
+    fn client_pending_key_images_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<PendingKeyImagesRequest> {
+        let request_bytes = self.ake.client_decrypt(msg)?;
+        Ok(mcserial::decode(&request_bytes)?)
+    }
+
+    fn client_pending_key_images_response(
+        &self,
+        channel_id: &ClientSession,
+        response: PendingKeyImagesResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        let response_bytes = mcserial::encode(&response);
+        Ok(self.ake.client_encrypt(channel_id, &[], &response_bytes)?)
+    }
+
     fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
-        Ok(self.ake.peer_init(peer_id)?)
+        let peer_id = peer_responder_id(peer_id, &self.fee_split.lock()?);
+        Ok(self.ake.peer_init(&peer_id)?)
     }
 
     fn peer_accept(&self, req: PeerAuthRequest) -> Result<(PeerAuthResponse, PeerSession)> {
@@ -214,7 +258,8 @@ impl ConsensusEnclave for SgxConsensusEnclave {
     }
 
     fn peer_connect(&self, peer_id: &ResponderId, msg: PeerAuthResponse) -> Result<PeerSession> {
-        Ok(self.ake.peer_connect(peer_id, msg)?)
+        let peer_id = peer_responder_id(peer_id, &self.fee_split.lock()?);
+        Ok(self.ake.peer_connect(&peer_id, msg)?)
     }
 
     fn peer_close(&self, session_id: &PeerSession) -> Result<()> {
@@ -425,42 +470,12 @@ impl ConsensusEnclave for SgxConsensusEnclave {
             }
         }
 
-        // Create an aggregate fee output.
-        let fee_tx_private_key = {
-            let hash_value: [u8; 32] = {
-                let mut hasher = Blake2b256::new();
-                transactions.digest(&mut hasher);
-                hasher
-                    .result()
-                    .as_slice()
-                    .try_into()
-                    .expect("Wrong length.")
-            };
-
-            // This private key is generated from the hash of all transactions in this block.
-            // This ensures that all nodes generate the same fee output transaction.
-            RistrettoPrivate::from(Scalar::from_bytes_mod_order(hash_value))
-        };
-
-        let blinding = {
-            let hash_value: [u8; 32] = {
-                let mut hasher = Blake2b256::new();
-                hasher.input(BLINDING_HASH_PREFIX);
-                transactions.digest(&mut hasher);
-                hasher
-                    .result()
-                    .as_slice()
-                    .try_into()
-                    .expect("Wrong length.")
-            };
-
-            // This private key is generated from the hash of all transactions in this block.
-            // This ensures that all nodes generate the same fee output transaction.
-            Blinding::from_bytes_mod_order(hash_value)
-        };
-
-        let total_fee: u64 = transactions.iter().map(|tx| tx.prefix.fee).sum();
-        let fee_output = mint_aggregate_fee(&fee_tx_private_key, total_fee, blinding)?;
+        // Split the fees of the block between the fee recipients, with one output each.
+        let total_fee = transactions
+            .iter()
+            .try_fold(0u64, |total_fee, tx| total_fee.checked_add(tx.prefix.fee))
+            .ok_or(Error::FeeOverflow)?;
+        let fee_split = self.fee_split.lock()?.clone();
 
         let mut outputs: Vec<TxOut> = Vec::new();
         let mut key_images: Vec<KeyImage> = Vec::new();
@@ -468,7 +483,10 @@ impl ConsensusEnclave for SgxConsensusEnclave {
             outputs.extend(tx.prefix.outputs.iter().cloned());
             key_images.extend(tx.key_images().iter().cloned());
         }
-        outputs.push(fee_output);
+        let fee_outputs = fee_split
+            .fee_outputs(&key_images, &outputs, total_fee)
+            .map_err(|e| Error::RedactTxs(format!("AmountError: {:?}", e)))?;
+        outputs.extend(fee_outputs);
 
         // TODO: sort outputs and key images.
         let block_contents = BlockContents::new(key_images, outputs);
@@ -490,57 +508,38 @@ impl ConsensusEnclave for SgxConsensusEnclave {
     }
 }
 
-/// Creates a single output belonging to the fee recipient account.
-///
-/// # Arguments:
-/// * `tx_private_key` - Transaction key used to output the aggregate fee.
-/// * `total_fee` - The sum of all fees in the block.
-/// * `blinding` - The` Blinding` value to use for constructing the Amount.
-fn mint_aggregate_fee(
-    tx_private_key: &RistrettoPrivate,
-    total_fee: u64,
-    blinding: Blinding,
-) -> Result<TxOut> {
-    let fee_recipient = PublicAddress::new(
-        &RistrettoPublic::try_from(&FEE_SPEND_PUBLIC_KEY).unwrap(),
-        &RistrettoPublic::try_from(&FEE_VIEW_PUBLIC_KEY).unwrap(),
-    );
-
-    // Create a single TxOut
-    let fee_output: TxOut = {
-        let target_key = create_onetime_public_key(&fee_recipient, tx_private_key).into();
-        let public_key =
-            compute_tx_pubkey(&tx_private_key, fee_recipient.spend_public_key()).into();
-        let amount = {
-            let shared_secret =
-                compute_shared_secret(fee_recipient.view_public_key(), tx_private_key);
-            // The fee view key is publicly known, so there is no need for a blinding.
-            Amount::new(total_fee, blinding, &shared_secret)
-                .map_err(|e| Error::RedactTxs(format!("AmountError: {:?}", e)))?
-        };
-
-        TxOut {
-            amount,
-            target_key,
-            public_key,
-            e_account_hint: Default::default(),
-        }
-    };
+/// The responder ID the enclaves of two peers authenticate each other with. Unless fees are split
+/// the default way, it includes the fingerprint of the fee split, so that the enclaves of nodes
+/// that split fees differently fail to establish a peer session.
+fn peer_responder_id(responder_id: &ResponderId, fee_split: &FeeSplit) -> ResponderId {
+    if *fee_split == FeeSplit::default() {
+        return responder_id.clone();
+    }
 
-    Ok(fee_output)
+    let fingerprint: String = fee_split
+        .fingerprint()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    ResponderId(format!("{}#fee-split-{}", responder_id, fingerprint))
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use keys::{RistrettoPrivate, RistrettoPublic};
     use ledger_db::Ledger;
     use rand_core::SeedableRng;
     use rand_hc::Hc128Rng;
     use transaction::{
-        account_keys::AccountKey, constants::FEE_VIEW_PRIVATE_KEY,
-        onetime_keys::view_key_matches_output, tx::TxOutMembershipHash,
-        validation::TransactionValidationError, view_key::ViewKey,
+        account_keys::{AccountKey, PublicAddress},
+        constants::{FEE_SPEND_PUBLIC_KEY, FEE_VIEW_PRIVATE_KEY},
+        fee_split::FeeRecipient,
+        onetime_keys::{compute_shared_secret, view_key_matches_output},
+        tx::TxOutMembershipHash,
+        validation::TransactionValidationError,
+        view_key::ViewKey,
     };
     use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
 
@@ -827,6 +826,98 @@ mod tests {
         assert_eq!(value, total_fee);
     }
 
+    #[test]
+    // form_block should mint one fee output per fee recipient, each holding its share of the fees.
+    fn test_form_block_splits_fees() {
+        let mut rng = Hc128Rng::from_seed([77u8; 32]);
+        let enclave = SgxConsensusEnclave::default();
+
+        let foundation = AccountKey::random(&mut rng);
+        let operator = AccountKey::random(&mut rng);
+        let fee_split = FeeSplit::new(vec![
+            FeeRecipient::new(foundation.default_subaddress(), 3),
+            FeeRecipient::new(operator.default_subaddress(), 1),
+        ])
+        .unwrap();
+        *enclave.fee_split.lock().unwrap() = fee_split.clone();
+
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        let n_blocks = 1;
+        initialize_ledger(&mut ledger, n_blocks, &sender, &mut rng);
+
+        // Spend outputs from the origin block.
+        let origin_block_contents = ledger.get_block_contents(0).unwrap();
+        let input_transactions: Vec<Tx> = (0..3)
+            .map(|i| {
+                create_transaction(
+                    &mut ledger,
+                    &origin_block_contents.outputs[i],
+                    &sender,
+                    &recipient.default_subaddress(),
+                    n_blocks + 1,
+                    &mut rng,
+                )
+            })
+            .collect();
+        let total_fee: u64 = input_transactions.iter().map(|tx| tx.prefix.fee).sum();
+
+        let well_formed_encrypted_txs_with_proofs: Vec<_> = input_transactions
+            .iter()
+            .map(|tx| {
+                let well_formed_tx = WellFormedTx::from(tx.clone());
+                let encrypted_tx = enclave
+                    .encrypt_well_formed_tx(&well_formed_tx, &mut rng)
+                    .unwrap();
+                let highest_indices = well_formed_tx.tx.get_membership_proof_highest_indices();
+                let membership_proofs = ledger
+                    .get_tx_out_proof_of_memberships(&highest_indices)
+                    .expect("failed getting proof");
+                (encrypted_tx, membership_proofs)
+            })
+            .collect();
+
+        let parent_block = ledger.get_block(ledger.num_blocks().unwrap() - 1).unwrap();
+        let (_block, block_contents, _signature) = enclave
+            .form_block(&parent_block, &well_formed_encrypted_txs_with_proofs)
+            .unwrap();
+
+        // `block_contents` should include a fee output per recipient.
+        let num_outputs: usize = input_transactions
+            .iter()
+            .map(|tx| tx.prefix.outputs.len())
+            .sum();
+        assert_eq!(num_outputs + 2, block_contents.outputs.len());
+
+        // Each recipient should own exactly one output, holding its share of the fees.
+        let expected_fees = fee_split.split(total_fee);
+        assert_eq!(expected_fees.iter().sum::<u64>(), total_fee);
+        for (account_key, expected_fee) in [foundation, operator].iter().zip(expected_fees) {
+            let view_key = account_key.view_key();
+            let fee_outputs: Vec<&TxOut> = block_contents
+                .outputs
+                .iter()
+                .filter(|output| {
+                    let output_public_key = RistrettoPublic::try_from(&output.public_key).unwrap();
+                    let output_target_key = RistrettoPublic::try_from(&output.target_key).unwrap();
+                    view_key_matches_output(&view_key, &output_target_key, &output_public_key)
+                })
+                .collect();
+            assert_eq!(fee_outputs.len(), 1);
+
+            let fee_output_public_key =
+                RistrettoPublic::try_from(&fee_outputs[0].public_key).unwrap();
+            let shared_secret =
+                compute_shared_secret(&fee_output_public_key, account_key.view_private_key());
+            let (value, _blinding) = fee_outputs[0].amount.get_value(&shared_secret).unwrap();
+            assert_eq!(value, expected_fee);
+        }
+
+        // Anyone who knows the split can check the fee outputs.
+        assert_eq!(fee_split.verify_fee_outputs(&block_contents), Ok(total_fee));
+    }
+
     #[test]
     /// form_block should return an error if the input transactions contain a double-spend.
     fn test_form_block_prevents_duplicate_spend() {
@@ -992,4 +1083,39 @@ mod tests {
         ));
         assert_eq!(form_block_result, expected);
     }
+
+    #[test]
+    // Peers should only agree on a responder ID if they split fees the same way.
+    fn test_peer_responder_id() {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+        let responder_id = ResponderId("peer1.example.com:8443".into());
+
+        // Nodes that split fees the default way keep their responder ID.
+        assert_eq!(
+            peer_responder_id(&responder_id, &FeeSplit::default()),
+            responder_id
+        );
+
+        let mut split = |weights: &[u32]| {
+            FeeSplit::new(
+                weights
+                    .iter()
+                    .map(|weight| {
+                        FeeRecipient::new(
+                            AccountKey::random(&mut rng).default_subaddress(),
+                            *weight,
+                        )
+                    })
+                    .collect(),
+            )
+            .unwrap()
+        };
+        let fee_split = split(&[3, 1]);
+        let other_fee_split = split(&[3, 1]);
+
+        let bound_id = peer_responder_id(&responder_id, &fee_split);
+        assert_ne!(bound_id, responder_id);
+        assert_eq!(bound_id, peer_responder_id(&responder_id, &fee_split));
+        assert_ne!(bound_id, peer_responder_id(&responder_id, &other_fee_split));
+    }
 }
@@ -13,6 +13,12 @@ pub const CONSENSUS_ENCLAVE_SECURITY_VERSION: u16 = 1;
 pub const CONSENSUS_ENCLAVE_NAME: &str = "consensus-enclave";
 pub const CONSENSUS_ENCLAVE_DIR: &str = "../trusted";
 
+// Enclave sizing. These values are part of the enclave's signed measurement (MRENCLAVE), so they
+// cannot be changed when a node starts: raising them requires a new enclave build and signature.
+pub const CONSENSUS_ENCLAVE_TCS_NUM: usize = 32;
+pub const CONSENSUS_ENCLAVE_STACK_MAX_SIZE: usize = 256 * 1024;
+pub const CONSENSUS_ENCLAVE_HEAP_MAX_SIZE: usize = 128 * 1024 * 1024;
+
 fn main() {
     let env = Environment::default();
     let sgx = SgxEnvironment::new(&env).expect("Could not read SGX environment");
@@ -78,11 +84,11 @@ fn main() {
         )
         .prod_id(CONSENSUS_ENCLAVE_PRODUCT_ID)
         .isv_security_version(CONSENSUS_ENCLAVE_SECURITY_VERSION)
-        .tcs_num(32)
+        .tcs_num(CONSENSUS_ENCLAVE_TCS_NUM)
         .tcs_min_pool(1)
         .tcs_policy(TcsPolicy::Unbound)
-        .stack_max_size(256 * 1024)
-        .heap_max_size(128 * 1024 * 1024);
+        .stack_max_size(CONSENSUS_ENCLAVE_STACK_MAX_SIZE)
+        .heap_max_size(CONSENSUS_ENCLAVE_HEAP_MAX_SIZE);
 
     let _sig = builder
         .build()
@@ -3,8 +3,9 @@
 //! Mock enclave, used for tests
 
 pub use consensus_enclave_api::{
-    ConsensusEnclave, ConsensusEnclaveProxy, Error, LocallyEncryptedTx, Result,
-    SealedBlockSigningKey, TxContext, WellFormedEncryptedTx, WellFormedTxContext,
+    ConsensusEnclave, ConsensusEnclaveProxy, Error, LocallyEncryptedTx, PendingKeyImagesRequest,
+    PendingKeyImagesResponse, Result, SealedBlockSigningKey, TxContext, TxOutProofsRequest,
+    TxOutProofsResponse, WellFormedEncryptedTx, WellFormedTxContext,
 };
 
 use attest::{IasNonce, Quote, QuoteNonce, Report, TargetInfo, VerificationReport};
@@ -19,6 +20,7 @@ use rand_core::SeedableRng;
 use rand_hc::Hc128Rng;
 use std::sync::Arc;
 use transaction::{
+    fee_split::FeeSplit,
     ring_signature::KeyImage,
     tx::{Tx, TxOut, TxOutMembershipProof},
     Block, BlockContents, BlockSignature, BLOCK_VERSION,
@@ -60,6 +62,7 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
         _self_peer_id: &ResponderId,
         _self_client_id: &ResponderId,
         _sealed_key: &Option<SealedBlockSigningKey>,
+        _fee_split: &FeeSplit,
     ) -> Result<SealedBlockSigningKey> {
         Ok(vec![])
     }
@@ -105,6 +108,45 @@ impl ConsensusEnclave for ConsensusServiceMockEnclave {
         Ok(())
     }
 
+    fn client_tx_out_proofs_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<TxOutProofsRequest> {
+        // The mock enclave does not encrypt client traffic, the payload is just serialized.
+        Ok(mcserial::decode(&msg.data)?)
+    }
+
+    fn client_tx_out_proofs_response(
+        &self,
+        channel_id: &ClientSession,
+        response: TxOutProofsResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        Ok(EnclaveMessage {
+            aad: Vec::new(),
+            channel_id: channel_id.clone(),
+            data: mcserial::encode(&response),
+        })
+    }
+
+    fn client_pending_key_images_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<PendingKeyImagesRequest> {
+        Ok(mcserial::decode(&msg.data)?)
+    }
+
+    fn client_pending_key_images_response(
+        &self,
+        channel_id: &ClientSession,
+        response: PendingKeyImagesResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        Ok(EnclaveMessage {
+            aad: Vec::new(),
+            channel_id: channel_id.clone(),
+            data: mcserial::encode(&response),
+        })
+    }
+
     fn peer_init(&self, _node_id: &ResponderId) -> Result<PeerAuthRequest> {
         Ok(vec![].into())
     }
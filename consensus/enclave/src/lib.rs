@@ -3,8 +3,9 @@
 //! The Consensus Service SGX Enclave Proxy
 
 pub use consensus_enclave_api::{
-    ConsensusEnclave, ConsensusEnclaveProxy, EnclaveCall, Error, LocallyEncryptedTx, Result,
-    TxContext, WellFormedEncryptedTx, WellFormedTxContext,
+    ConsensusEnclave, ConsensusEnclaveProxy, EnclaveCall, Error, LocallyEncryptedTx,
+    PendingKeyImagesRequest, PendingKeyImagesResponse, Result, TxContext, TxOutProofsRequest,
+    TxOutProofsResponse, WellFormedEncryptedTx, WellFormedTxContext,
 };
 
 use attest::{
@@ -20,7 +21,9 @@ use keys::{Ed25519Public, X25519Public};
 use sgx_types::{sgx_enclave_id_t, sgx_status_t, *};
 use sgx_urts::SgxEnclave;
 use std::{path, result::Result as StdResult, sync::Arc};
-use transaction::{tx::TxOutMembershipProof, Block, BlockContents, BlockSignature};
+use transaction::{
+    fee_split::FeeSplit, tx::TxOutMembershipProof, Block, BlockContents, BlockSignature,
+};
 
 #[allow(unused_imports)]
 use sgx_slog;
@@ -41,6 +44,7 @@ impl ConsensusServiceSgxEnclave {
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        fee_split: &FeeSplit,
     ) -> (ConsensusServiceSgxEnclave, SealedBlockSigningKey) {
         let mut launch_token: sgx_launch_token_t = [0; 1024];
         let mut launch_token_updated: i32 = 0;
@@ -68,7 +72,7 @@ impl ConsensusServiceSgxEnclave {
         };
 
         let sealed_key = sgx_enclave
-            .enclave_init(self_peer_id, self_client_id, &sealed_key)
+            .enclave_init(self_peer_id, self_client_id, &sealed_key, fee_split)
             .expect("enclave_init failed");
 
         (sgx_enclave, sealed_key)
@@ -93,11 +97,13 @@ impl ConsensusEnclave for ConsensusServiceSgxEnclave {
         self_peer_id: &ResponderId,
         self_client_id: &ResponderId,
         sealed_key: &Option<SealedBlockSigningKey>,
+        fee_split: &FeeSplit,
     ) -> Result<SealedBlockSigningKey> {
         let inbuf = mcserial::serialize(&EnclaveCall::EnclaveInit(
             self_peer_id.clone(),
             self_client_id.clone(),
             sealed_key.clone(),
+            fee_split.clone(),
         ))?;
         let outbuf = self.enclave_call(&inbuf)?;
         mcserial::deserialize(&outbuf[..])?
@@ -157,6 +163,50 @@ impl ConsensusEnclave for ConsensusServiceSgxEnclave {
         mcserial::deserialize(&outbuf[..])?
     }
 
+    fn client_tx_out_proofs_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<TxOutProofsRequest> {
+        let inbuf = mcserial::serialize(&EnclaveCall::ClientTxOutProofsRequest(msg))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn client_tx_out_proofs_response(
+        &self,
+        channel_id: &ClientSession,
+        response: TxOutProofsResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        let inbuf = mcserial::serialize(&EnclaveCall::ClientTxOutProofsResponse(
+            channel_id.clone(),
+            response,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn client_pending_key_images_request(
+        &self,
+        msg: EnclaveMessage<ClientSession>,
+    ) -> Result<PendingKeyImagesRequest> {
+        let inbuf = mcserial::serialize(&EnclaveCall::ClientPendingKeyImagesRequest(msg))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn client_pending_key_images_response(
+        &self,
+        channel_id: &ClientSession,
+        response: PendingKeyImagesResponse,
+    ) -> Result<EnclaveMessage<ClientSession>> {
+        let inbuf = mcserial::serialize(&EnclaveCall::ClientPendingKeyImagesResponse(
+            channel_id.clone(),
+            response,
+        ))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
     fn peer_init(&self, peer_id: &ResponderId) -> Result<PeerAuthRequest> {
         let inbuf = mcserial::serialize(&EnclaveCall::PeerInit(peer_id.clone()))?;
         let outbuf = self.enclave_call(&inbuf)?;
@@ -33,10 +33,10 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
     // And actually do it
     let outdata = match call_details {
         // Utility methods
-        EnclaveCall::EnclaveInit(peer_self_id, client_self_id, sealed_key) => {
-            serialize(&ENCLAVE.enclave_init(&peer_self_id, &client_self_id, &sealed_key))
-                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
-        }
+        EnclaveCall::EnclaveInit(peer_self_id, client_self_id, sealed_key, fee_split) => serialize(
+            &ENCLAVE.enclave_init(&peer_self_id, &client_self_id, &sealed_key, &fee_split),
+        )
+        .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
         // Node-to-Node Attestation
         EnclaveCall::PeerInit(node_id) => {
             serialize(&ENCLAVE.peer_init(&node_id)).or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
@@ -57,6 +57,22 @@ pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
             .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
         EnclaveCall::ClientDiscardMessage(msg) => serialize(&ENCLAVE.client_discard_message(msg))
             .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?,
+        EnclaveCall::ClientTxOutProofsRequest(msg) => {
+            serialize(&ENCLAVE.client_tx_out_proofs_request(msg))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+        EnclaveCall::ClientTxOutProofsResponse(channel_id, response) => {
+            serialize(&ENCLAVE.client_tx_out_proofs_response(&channel_id, response))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+        EnclaveCall::ClientPendingKeyImagesRequest(msg) => {
+            serialize(&ENCLAVE.client_pending_key_images_request(msg))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+        EnclaveCall::ClientPendingKeyImagesResponse(channel_id, response) => {
+            serialize(&ENCLAVE.client_pending_key_images_response(&channel_id, response))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
         // Report Caching
         EnclaveCall::GetIdentity => {
             serialize(&ENCLAVE.get_identity()).or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
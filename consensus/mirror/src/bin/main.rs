@@ -0,0 +1,115 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Entrypoint for the read-only consensus mirror.
+
+use attest::MrSigner;
+use common::logger::{create_app_logger, log, o, Logger};
+use consensus_enclave_measurement::sigstruct;
+use consensus_mirror::{config::Config, mirror_service::MirrorService};
+use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
+use std::{
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use structopt::StructOpt;
+
+fn main() {
+    common::setup_panic_handler();
+    let _sentry_guard = common::sentry::init();
+
+    let config = Config::from_args();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    // Stop gracefully on SIGINT and SIGTERM, so that the ledger is not left mid-append.
+    let shutdown_requested = Arc::new(AtomicBool::new(false));
+    for signal in &[signal_hook::SIGINT, signal_hook::SIGTERM] {
+        signal_hook::flag::register(*signal, shutdown_requested.clone())
+            .expect("Could not register signal handler");
+    }
+
+    // Create peer manager.
+    let peer_manager = config.create_peer_manager(
+        MrSigner::try_from(&sigstruct().mrsigner()[..])
+            .expect("Could not parse validator node MRSIGNER"),
+        &logger,
+    );
+
+    // Create network state, transactions fetcher and ledger sync.
+    let network_state =
+        PollingNetworkState::new(config.quorum_set(), peer_manager.clone(), logger.clone());
+
+    let transactions_fetcher =
+        ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
+            .expect("Failed creating ReqwestTransactionsFetcher");
+
+    let ledger_db = create_or_open_ledger_db(&config, &transactions_fetcher, &logger);
+
+    let _ledger_sync_service_thread = LedgerSyncServiceThread::new(
+        ledger_db.clone(),
+        peer_manager,
+        network_state,
+        transactions_fetcher,
+        config.poll_interval,
+        logger.clone(),
+    );
+
+    let _mirror_service = MirrorService::start(
+        ledger_db,
+        &config.client_listen_uri,
+        config.dust_rules(),
+        logger,
+    );
+
+    wait_for_shutdown(&shutdown_requested, &logger);
+}
+
+/// Block until a shutdown was requested. Services are stopped as they are dropped.
+fn wait_for_shutdown(shutdown_requested: &AtomicBool, logger: &Logger) {
+    while !shutdown_requested.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    log::info!(logger, "Shutting down");
+}
+
+/// Opens the mirrored ledger, bootstrapping it with the origin block if it is empty.
+fn create_or_open_ledger_db(
+    config: &Config,
+    transactions_fetcher: &ReqwestTransactionsFetcher,
+    logger: &Logger,
+) -> LedgerDB {
+    let mut ledger_db = match LedgerDB::open(config.ledger_path.clone()) {
+        Ok(ledger_db) => ledger_db,
+        Err(_) => {
+            log::info!(
+                logger,
+                "Ledger DB {:?} does not exist, creating it",
+                config.ledger_path
+            );
+            std::fs::create_dir_all(config.ledger_path.clone())
+                .expect("Could not create ledger dir");
+            LedgerDB::create(config.ledger_path.clone()).expect("Could not create ledger_db");
+            LedgerDB::open(config.ledger_path.clone()).expect("Could not open ledger_db")
+        }
+    };
+
+    if ledger_db.num_blocks().expect("Could not read ledger_db") == 0 {
+        log::info!(
+            logger,
+            "Ledger DB {:?} is empty, bootstrapping from tx source",
+            config.ledger_path
+        );
+        let (block, block_contents) = transactions_fetcher
+            .get_origin_block_and_transactions()
+            .expect("Failed to download origin block");
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .expect("Failed to append origin block");
+    }
+
+    ledger_db
+}
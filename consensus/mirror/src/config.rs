@@ -0,0 +1,124 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Configuration parameters for the consensus mirror.
+
+use attest::Measurement;
+use common::{logger::Logger, ResponderId};
+use mcconnection::{ConnectionManager, ThickClient};
+use mcuri::{ConnectionUri, ConsensusClientUri};
+use scp::QuorumSet;
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use structopt::StructOpt;
+use transaction::dust::{DustPolicy, DustRules};
+
+#[derive(Clone, Debug, StructOpt)]
+#[structopt(
+    name = "consensus_mirror",
+    about = "A read-only mirror of the MobileCoin consensus client APIs."
+)]
+pub struct Config {
+    /// Path to the mirrored ledger db (lmdb).
+    #[structopt(long, parse(from_os_str))]
+    pub ledger_path: PathBuf,
+
+    /// Validator nodes to sync blocks from.
+    #[structopt(long = "peer", required = true, min_values = 1)]
+    pub peers: Vec<ConsensusClientUri>,
+
+    /// Quorum set for ledger syncing. By default, the quorum set would include all peers.
+    ///
+    /// The quorum set is represented in JSON. For example:
+    /// {"threshold":1,"members":[{"type":"Node","args":"node2.test.mobilecoin.com:443"},{"type":"Node","args":"node3.test.mobilecoin.com:443"}]}
+    #[structopt(long, parse(try_from_str=parse_quorum_set_from_json))]
+    quorum_set: Option<QuorumSet<ResponderId>>,
+
+    /// URLs to use for transaction data.
+    ///
+    /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
+    #[structopt(long = "tx-source-url", required = true, min_values = 1)]
+    pub tx_source_urls: Vec<String>,
+
+    /// How many seconds to wait between polling.
+    #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
+    pub poll_interval: Duration,
+
+    /// URI to serve read-only client requests on.
+    #[structopt(long, default_value = "insecure-mc://0.0.0.0:3223/")]
+    pub client_listen_uri: ConsensusClientUri,
+
+    /// How the mirrored network treats zero-value and sub-minimum outputs:
+    /// rejected, allowed-as-change or allowed-always.
+    #[structopt(long, default_value = "allowed-always")]
+    pub dust_policy: DustPolicy,
+
+    /// The smallest output value, in picoMOB, the mirrored network does not consider dust.
+    #[structopt(long, default_value = "0")]
+    pub minimum_output_value: u64,
+}
+
+fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_secs(u64::from_str(src)?))
+}
+
+fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
+    Ok(serde_json::from_str(src)
+        .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?)
+}
+
+impl Config {
+    pub fn dust_rules(&self) -> DustRules {
+        DustRules::new(self.dust_policy, self.minimum_output_value)
+    }
+
+    pub fn quorum_set(&self) -> QuorumSet<ResponderId> {
+        // If we have an explicit quorum set, use that.
+        if let Some(quorum_set) = &self.quorum_set {
+            return quorum_set.clone();
+        }
+
+        // Otherwise create a quorum set that includes all of the peers we know about.
+        let node_ids = self
+            .peers
+            .iter()
+            .map(|p| {
+                p.responder_id().unwrap_or_else(|e| {
+                    panic!(
+                        "Could not get responder_id from uri {}: {:?}",
+                        p.to_string(),
+                        e
+                    )
+                })
+            })
+            .collect::<Vec<ResponderId>>();
+        QuorumSet::new_with_node_ids(node_ids.len() as u32, node_ids)
+    }
+
+    pub fn create_peer_manager(
+        &self,
+        measurement: impl Into<Measurement>,
+        logger: &Logger,
+    ) -> ConnectionManager<ThickClient> {
+        let measurement = measurement.into();
+        let grpc_env = Arc::new(
+            grpcio::EnvBuilder::new()
+                .name_prefix("RPC".to_string())
+                .build(),
+        );
+
+        let peers = self
+            .peers
+            .iter()
+            .map(|client_uri| {
+                ThickClient::new(
+                    client_uri.clone(),
+                    measurement,
+                    grpc_env.clone(),
+                    logger.clone(),
+                )
+                .expect("Could not create thick client.")
+            })
+            .collect();
+
+        ConnectionManager::new(peers, logger.clone())
+    }
+}
@@ -0,0 +1,9 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A read-only mirror of the consensus client-facing APIs.
+//!
+//! The mirror keeps a local ledger in sync with the network and serves block and TxOut queries
+//! from it, without participating in consensus.
+
+pub mod config;
+pub mod mirror_service;
@@ -0,0 +1,59 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Serves the read-only client-facing API out of a local ledger.
+
+use blockchain_api_service::BlockchainApiService;
+use common::logger::{log, Logger};
+use grpc_util::HealthService;
+use ledger_db::Ledger;
+use mcconnection::ConnectionUriGrpcioServer;
+use mcuri::{ConnectionUri, ConsensusClientUri};
+use mobilecoin_api::blockchain_grpc;
+use std::sync::Arc;
+use transaction::dust::DustRules;
+
+pub struct MirrorService {
+    /// GRPC server.
+    _server: grpcio::Server,
+}
+
+impl MirrorService {
+    /// Start serving read-only queries for `ledger` on `listen_uri`.
+    pub fn start<L: Ledger + 'static>(
+        ledger: L,
+        listen_uri: &ConsensusClientUri,
+        dust_rules: DustRules,
+        logger: Logger,
+    ) -> Self {
+        log::info!(
+            logger,
+            "Starting mirror rpc server on {}...",
+            listen_uri.addr(),
+        );
+
+        let mut blockchain_api_service = BlockchainApiService::new(ledger, logger.clone());
+        blockchain_api_service.set_dust_rules(dust_rules);
+        let blockchain_service = blockchain_grpc::create_blockchain_api(blockchain_api_service);
+        let health_service = HealthService::new(None, logger.clone()).into_service();
+
+        let env = Arc::new(
+            grpcio::EnvBuilder::new()
+                .name_prefix("Mirror-RPC".to_string())
+                .build(),
+        );
+
+        let mut server = grpcio::ServerBuilder::new(env)
+            .register_service(blockchain_service)
+            .register_service(health_service)
+            .bind_using_uri(listen_uri)
+            .build()
+            .expect("Failed building mirror grpc server");
+        server.start();
+
+        for (host, port) in server.bind_addrs() {
+            log::info!(logger, "Mirror GRPC API listening on {}:{}", host, port);
+        }
+
+        Self { _server: server }
+    }
+}
@@ -122,6 +122,36 @@ impl<
         (0, 1)
     }
 
+    /// Checks whether the given nodes form a blocking set, i.e. intersect every quorum slice.
+    pub fn is_blocking_set(&self, nodes: &HashSet<ID>) -> bool {
+        if self.members.is_empty() {
+            return false;
+        }
+        let needed = (self.members.len() as u32).saturating_sub(self.threshold) + 1;
+        self.num_members_satisfying(nodes, Self::is_blocking_set) >= needed
+    }
+
+    /// Checks whether the given nodes satisfy the threshold of this quorum set, i.e. contain a
+    /// quorum slice.
+    pub fn is_quorum_slice(&self, nodes: &HashSet<ID>) -> bool {
+        self.num_members_satisfying(nodes, Self::is_quorum_slice) >= self.threshold
+    }
+
+    /// Counts the members that are in `nodes`, or, for inner sets, satisfy `inner_set_pred`.
+    fn num_members_satisfying(
+        &self,
+        nodes: &HashSet<ID>,
+        inner_set_pred: fn(&Self, &HashSet<ID>) -> bool,
+    ) -> u32 {
+        self.members
+            .iter()
+            .filter(|member| match member {
+                QuorumSetMember::Node(node_id) => nodes.contains(node_id),
+                QuorumSetMember::InnerSet(qs) => inner_set_pred(qs, nodes),
+            })
+            .count() as u32
+    }
+
     /// Attempts to find a blocking set matching a given predicate `predicate`.
     ///
     /// # Arguments
@@ -767,4 +797,44 @@ mod quorum_set_tests {
             ])
         );
     }
+
+    #[test]
+    fn test_is_blocking_set_and_quorum_slice() {
+        // A 2-of-{3-node inner set, 3-node inner set} quorum set.
+        let quorum_set: QuorumSet = QuorumSet::new_with_inner_sets(
+            2,
+            vec![
+                QuorumSet::new_with_node_ids(
+                    2,
+                    vec![test_node_id(2), test_node_id(3), test_node_id(4)],
+                ),
+                QuorumSet::new_with_node_ids(
+                    2,
+                    vec![test_node_id(5), test_node_id(6), test_node_id(7)],
+                ),
+            ],
+        );
+
+        let nodes =
+            |ids: &[u32]| -> HashSet<NodeID> { ids.iter().map(|id| test_node_id(*id)).collect() };
+
+        assert!(!quorum_set.is_blocking_set(&nodes(&[])));
+        assert!(!quorum_set.is_quorum_slice(&nodes(&[])));
+
+        // Two nodes from one inner set block that inner set, and so block the outer set.
+        assert!(quorum_set.is_blocking_set(&nodes(&[2, 3])));
+        assert!(!quorum_set.is_quorum_slice(&nodes(&[2, 3])));
+
+        // One node from each inner set blocks neither.
+        assert!(!quorum_set.is_blocking_set(&nodes(&[2, 5])));
+
+        // Two nodes from each inner set satisfy both.
+        assert!(quorum_set.is_blocking_set(&nodes(&[2, 3, 5, 6])));
+        assert!(quorum_set.is_quorum_slice(&nodes(&[2, 3, 5, 6])));
+
+        // An empty quorum set is trivially satisfied but can never be blocked.
+        let empty_quorum_set: QuorumSet = QuorumSet::empty();
+        assert!(empty_quorum_set.is_quorum_slice(&nodes(&[])));
+        assert!(!empty_quorum_set.is_blocking_set(&nodes(&[2])));
+    }
 }
@@ -29,6 +29,7 @@ fn main() -> Result<(), ConsensusServiceError> {
     let (logger, _global_logger_guard) = create_app_logger(o!(
         "mc.local_node_id" => local_node_id.responder_id.to_string(),
     ));
+    let _metrics_reporter = metrics::MetricsReporter::start_from_env(logger.clone());
 
     // load the sealed block signing key fron storage
     let cached_key = match File::open(&config.sealed_block_signing_key) {
@@ -54,6 +55,7 @@ fn main() -> Result<(), ConsensusServiceError> {
         &config.peer_responder_id,
         &config.client_responder_id,
         &cached_key,
+        &config.network().fee_split(),
     );
 
     // write the sealed block signing key
@@ -6,6 +6,7 @@
 
 use crate::{
     counters,
+    pending_values::{PendingValueOrder, PendingValues},
     tx_manager::{TxManager, TxManagerError, UntrustedInterfaces},
 };
 use common::{
@@ -27,7 +28,7 @@ use retry::delay::Fibonacci;
 use scp::{scp_log::LoggingScpNode, slot::Phase, Msg, Node, QuorumSet, ScpNode, SlotIndex};
 use std::{
     cmp::min,
-    collections::{btree_map::Entry::Vacant, BTreeMap, BTreeSet},
+    collections::BTreeSet,
     iter::FromIterator,
     path::PathBuf,
     sync::{
@@ -37,7 +38,7 @@ use std::{
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
-use transaction::{tx::TxHash, BlockID};
+use transaction::{fee_split::FeeSplit, tx::TxHash, BlockID};
 
 /// Time we're allowed to stay behind before we initiate catchup.
 /// This reduces the amount of unnecessary catchups due to minor network issues.
@@ -85,6 +86,8 @@ impl ByzantineLedger {
         msg_signer_key: Arc<Ed25519Pair>,
         tx_source_urls: Vec<String>,
         opt_scp_debug_dump_dir: Option<PathBuf>,
+        pending_value_order: PendingValueOrder,
+        fee_split: FeeSplit,
         logger: Logger,
     ) -> Self {
         let (sender, receiver) =
@@ -180,6 +183,8 @@ impl ByzantineLedger {
                         tx_manager,
                         broadcaster,
                         tx_source_urls,
+                        pending_value_order,
+                        fee_split,
                         logger,
                     );
                 })
@@ -289,17 +294,8 @@ struct ByzantineLedgerThread<
     // Map of slot index -> pending scp messages we need to process.
     pending_consensus_msgs: HashMap<SlotIndex, Vec<(VerifiedConsensusMsg, ResponderId)>>,
 
-    // Pending values we're trying to push. We need to store them as a vec so we can process values
-    // on a first-come first-served basis. However, we want to be able to:
-    // 1) Efficiently see if we already have a given value and ignore duplicates
-    // 2) Track how long each value took to externalize.
-    // To accomplish both of this goals we store, in addition to the queue of pending values, a
-    // BTreeMap that maps a value to when we first encountered it. Note that we only store a
-    // timestamp for values that were handed to us directly from a client. We skip tracking
-    // processing times for relayed values since we want to track the time from when the network
-    // first saw a value, and not when a specific node saw it.
-    pending_values: Vec<TxHash>,
-    pending_values_map: BTreeMap<TxHash, Option<Instant>>,
+    // Pending values we're trying to push, and the order in which we nominate them.
+    pending_values: PendingValues,
 
     // Do we need to nominate anything?
     need_nominate: bool,
@@ -334,6 +330,8 @@ impl<
         tx_manager: TxManager<E, L, UI>,
         broadcaster: Arc<Mutex<ThreadedBroadcaster>>,
         tx_source_urls: Vec<String>,
+        pending_value_order: PendingValueOrder,
+        fee_split: FeeSplit,
         logger: Logger,
     ) {
         let cur_slot = ledger.num_blocks().unwrap();
@@ -342,12 +340,13 @@ impl<
         let transactions_fetcher = ReqwestTransactionsFetcher::new(tx_source_urls, logger.clone())
             .unwrap_or_else(|e| panic!("Failed creating transaction fetcher: {:?}", e));
 
-        let ledger_sync_service = LedgerSyncService::new(
+        let mut ledger_sync_service = LedgerSyncService::new(
             ledger.clone(),
             peer_manager.clone(),
             transactions_fetcher,
             logger.clone(),
         );
+        ledger_sync_service.set_fee_split(Some(fee_split));
 
         let network_state = SCPNetworkState::new(node_id, quorum_set, logger.clone());
 
@@ -365,8 +364,7 @@ impl<
             cur_slot,
             prev_block_id,
             pending_consensus_msgs: HashMap::default(),
-            pending_values: Vec::new(),
-            pending_values_map: BTreeMap::default(),
+            pending_values: PendingValues::new(pending_value_order),
             need_nominate: false,
             network_state,
             ledger_sync_service,
@@ -385,9 +383,6 @@ impl<
     // The place where all the consensus work is actually done.
     // Returns true until stop is requested.
     fn tick(&mut self) -> bool {
-        // Sanity check.
-        assert_eq!(self.pending_values.len(), self.pending_values_map.len());
-
         // Process external requests sent to us through the interface channel.
         if !self.process_external_requests() {
             return false;
@@ -507,16 +502,6 @@ impl<
                 self.pending_values
                     .retain(|tx_hash| tx_manager.validate_tx_by_hash(tx_hash).is_ok());
 
-                // Re-construct the BTreeMap with the remaining values, using the old timestamps.
-                let mut new_pending_values_map = BTreeMap::new();
-                for val in self.pending_values.iter() {
-                    new_pending_values_map.insert(
-                        val.clone(),
-                        self.pending_values_map.get(val).unwrap().clone(),
-                    );
-                }
-                self.pending_values_map = new_pending_values_map;
-
                 // Nominate if needed.
                 if !self.pending_values.is_empty() {
                     self.need_nominate = true;
@@ -569,11 +554,9 @@ impl<
                 ByzantineLedgerTaskMessage::Values(timestamp, new_values) => {
                     // Collect.
                     for value in new_values {
-                        // IF we don't already know of this value, add it to the pending list and
-                        // map.
-                        if let Vacant(entry) = self.pending_values_map.entry(value.clone()) {
-                            entry.insert(timestamp);
-                            self.pending_values.push(value);
+                        // IF we don't already know of this value, add it to the pending values.
+                        let fee = self.tx_manager.get_fee_by_hash(&value).unwrap_or(0);
+                        if self.pending_values.insert(value, fee, timestamp) {
                             self.need_nominate = true;
                         }
                     }
@@ -620,9 +603,7 @@ impl<
                 self.cur_slot,
                 BTreeSet::from_iter(
                     self.pending_values
-                        .iter()
-                        .take(MAX_PENDING_VALUES_TO_NOMINATE)
-                        .cloned(),
+                        .to_nominate(MAX_PENDING_VALUES_TO_NOMINATE),
                 ),
             )
             .expect("nominate failed");
@@ -700,8 +681,8 @@ impl<
 
         // Update pending value processing time metrics.
         for ext_val in ext_vals.iter() {
-            if let Some(Some(timestamp)) = self.pending_values_map.get(ext_val) {
-                let duration = Instant::now().saturating_duration_since(*timestamp);
+            if let Some(timestamp) = self.pending_values.submitted_at(ext_val) {
+                let duration = Instant::now().saturating_duration_since(timestamp);
                 counters::PENDING_VALUE_PROCESSING_TIME.observe(duration.as_secs_f64());
             }
         }
@@ -768,16 +749,6 @@ impl<
             !purged_hashes.contains(tx_hash) && tx_manager.validate_tx_by_hash(tx_hash).is_ok()
         });
 
-        // Re-construct the BTreeMap with the remaining values, using the old timestamps.
-        let mut new_pending_values_map = BTreeMap::new();
-        for val in self.pending_values.iter() {
-            new_pending_values_map.insert(
-                val.clone(),
-                self.pending_values_map.get(val).unwrap().clone(),
-            );
-        }
-        self.pending_values_map = new_pending_values_map;
-
         log::info!(
             self.logger,
             "number of pending values post cleanup: {} ({} expired)",
@@ -869,6 +840,13 @@ impl<
                         move |(tx_manager, logger), tx_context| {
                             match tx_manager.insert_proposed_tx(tx_context) {
                                 Ok(_) | Err(TxManagerError::AlreadyInCache) => {}
+                                Err(TxManagerError::CacheFull) => {
+                                    log::error!(
+                                        logger,
+                                        "Dropped transaction from node {}, the tx cache is full",
+                                        from_responder_id,
+                                    );
+                                }
                                 Err(err) => {
                                     // Not currently logging the malformed transaction to save a
                                     // `.clone()`. We'll see if this ever happens.
@@ -1067,6 +1045,8 @@ mod tests {
             local_signer_key.clone(),
             Vec::new(),
             None,
+            PendingValueOrder::Arrival,
+            FeeSplit::default(),
             logger.clone(),
         );
 
@@ -9,21 +9,32 @@ use crate::{
     tx_manager::{TxManager, TxManagerError},
 };
 use attest_api::attest::Message;
-use common::logger::{log, Logger};
-use consensus_enclave::ConsensusEnclaveProxy;
+use attest_enclave_api::{ClientSession, EnclaveMessage};
+use common::{
+    logger::{log, Logger},
+    HashSet,
+};
+use consensus_enclave::{ConsensusEnclaveProxy, PendingKeyImagesResponse, TxOutProofsResponse};
 use grpc_util::{rpc_logger, send_result};
-use grpcio::{RpcContext, UnarySink};
+use grpcio::{RpcContext, RpcStatus, UnarySink};
 use ledger_db::Ledger;
 use metrics::{self, SVC_COUNTERS};
 use mobilecoin_api::{
     consensus_client_grpc::ConsensusClientApi, consensus_common::ProposeTxResponse,
 };
+use rand::Rng;
 use std::sync::Arc;
-use transaction::validation::TransactionValidationError;
+use transaction::{tx::TxOut, validation::TransactionValidationError};
 
 /// Maximum number of pending values for consensus service before rejecting add_transaction requests.
 const PENDING_LIMIT: i64 = 500;
 
+/// Maximum number of TxOuts (requested and mixins) returned by a single `get_tx_out_proofs` call.
+const MAX_TX_OUT_PROOFS_PER_REQUEST: u64 = 1000;
+
+/// Maximum number of key images checked by a single `get_pending_key_images` call.
+const MAX_KEY_IMAGES_PER_PENDING_REQUEST: usize = 1000;
+
 #[derive(Clone)]
 pub struct ClientApiService<E: ConsensusEnclaveProxy, L: Ledger + Clone> {
     enclave: E,
@@ -129,6 +140,118 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ClientApiService<E, L> {
             }
         }
     }
+
+    fn real_get_tx_out_proofs(
+        &mut self,
+        request: Message,
+        logger: &Logger,
+    ) -> Result<Message, ConsensusGrpcError> {
+        let msg: EnclaveMessage<ClientSession> = request.into();
+        let channel_id = msg.channel_id.clone();
+        let proofs_request = self.enclave.client_tx_out_proofs_request(msg)?;
+
+        let num_requested = proofs_request.indices.len() as u64;
+        if num_requested.saturating_add(proofs_request.num_mixins) > MAX_TX_OUT_PROOFS_PER_REQUEST {
+            return Err(ConsensusGrpcError::InvalidArgument(format!(
+                "At most {} TxOuts may be requested at once",
+                MAX_TX_OUT_PROOFS_PER_REQUEST
+            )));
+        }
+
+        let num_txos = self.ledger.num_txos()?;
+        if let Some(index) = proofs_request
+            .indices
+            .iter()
+            .find(|index| **index >= num_txos)
+        {
+            return Err(ConsensusGrpcError::InvalidArgument(format!(
+                "TxOut index {} out of range, ledger contains {} TxOuts",
+                index, num_txos
+            )));
+        }
+
+        // Pick distinct mixins uniformly at random, excluding the requested TxOuts.
+        let excluded: HashSet<u64> = proofs_request.indices.iter().cloned().collect();
+        let num_mixins = std::cmp::min(
+            proofs_request.num_mixins,
+            num_txos.saturating_sub(excluded.len() as u64),
+        );
+        let mut rng = rand::thread_rng();
+        let mut mixin_indices: HashSet<u64> = HashSet::default();
+        while (mixin_indices.len() as u64) < num_mixins {
+            let index = rng.gen_range(0, num_txos);
+            if !excluded.contains(&index) {
+                mixin_indices.insert(index);
+            }
+        }
+        let mut mixin_indices: Vec<u64> = mixin_indices.into_iter().collect();
+        mixin_indices.sort();
+
+        let get_tx_outs = |indices: &[u64]| -> Result<Vec<TxOut>, ConsensusGrpcError> {
+            indices
+                .iter()
+                .map(|index| Ok(self.ledger.get_tx_out_by_index(*index)?))
+                .collect()
+        };
+
+        let response = TxOutProofsResponse {
+            tx_outs: get_tx_outs(&proofs_request.indices)?,
+            proofs: self
+                .ledger
+                .get_tx_out_proof_of_memberships(&proofs_request.indices)?,
+            mixins: get_tx_outs(&mixin_indices)?,
+            mixin_proofs: self
+                .ledger
+                .get_tx_out_proof_of_memberships(&mixin_indices)?,
+        };
+
+        log::trace!(
+            logger,
+            "Serving {} TxOut proofs and {} mixins",
+            response.tx_outs.len(),
+            response.mixins.len()
+        );
+
+        Ok(self
+            .enclave
+            .client_tx_out_proofs_response(&channel_id, response)?
+            .into())
+    }
+
+    fn real_get_pending_key_images(
+        &mut self,
+        request: Message,
+        logger: &Logger,
+    ) -> Result<Message, ConsensusGrpcError> {
+        let msg: EnclaveMessage<ClientSession> = request.into();
+        let channel_id = msg.channel_id.clone();
+        let pending_request = self.enclave.client_pending_key_images_request(msg)?;
+
+        if pending_request.key_images.len() > MAX_KEY_IMAGES_PER_PENDING_REQUEST {
+            return Err(ConsensusGrpcError::InvalidArgument(format!(
+                "At most {} key images may be checked at once",
+                MAX_KEY_IMAGES_PER_PENDING_REQUEST
+            )));
+        }
+
+        let response = PendingKeyImagesResponse {
+            pending_key_images: self
+                .tx_manager
+                .pending_key_images(&pending_request.key_images)?,
+        };
+
+        log::trace!(
+            logger,
+            "{} of {} key images are pending",
+            response.pending_key_images.len(),
+            pending_request.key_images.len()
+        );
+
+        Ok(self
+            .enclave
+            .client_pending_key_images_response(&channel_id, response)?
+            .into())
+    }
 }
 
 impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ConsensusClientApi for ClientApiService<E, L> {
@@ -155,4 +278,211 @@ impl<E: ConsensusEnclaveProxy, L: Ledger + Clone> ConsensusClientApi for ClientA
             )
         });
     }
+
+    fn get_tx_out_proofs(&mut self, ctx: RpcContext, request: Message, sink: UnarySink<Message>) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            send_result(
+                ctx,
+                sink,
+                self.real_get_tx_out_proofs(request, &logger)
+                    .map_err(RpcStatus::from),
+                &logger,
+            )
+        });
+    }
+
+    fn get_pending_key_images(
+        &mut self,
+        ctx: RpcContext,
+        request: Message,
+        sink: UnarySink<Message>,
+    ) {
+        let _timer = SVC_COUNTERS.req(&ctx);
+        common::logger::scoped_global_logger(&rpc_logger(&ctx, &self.logger), |logger| {
+            send_result(
+                ctx,
+                sink,
+                self.real_get_pending_key_images(request, &logger)
+                    .map_err(RpcStatus::from),
+                &logger,
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validators::DefaultTxManagerUntrustedInterfaces;
+    use common::logger::test_with_logger;
+    use consensus_enclave_mock::{ConsensusServiceMockEnclave, TxOutProofsRequest};
+    use ledger_db::LedgerDB;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+    use transaction_test_utils::{create_ledger, initialize_ledger};
+
+    /// A client API service backed by the mock enclave and a ledger with a few blocks.
+    fn create_service(
+        logger: Logger,
+    ) -> (
+        ClientApiService<ConsensusServiceMockEnclave, LedgerDB>,
+        LedgerDB,
+    ) {
+        let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let mut ledger = create_ledger();
+        initialize_ledger(&mut ledger, 5, &sender, &mut rng);
+
+        let enclave = ConsensusServiceMockEnclave::default();
+        let tx_manager = TxManager::new(
+            enclave.clone(),
+            ledger.clone(),
+            DefaultTxManagerUntrustedInterfaces::new(ledger.clone()),
+            logger.clone(),
+        );
+        let service = ClientApiService::new(
+            enclave,
+            Arc::new(|_, _, _| {}),
+            ledger.clone(),
+            tx_manager,
+            Arc::new(|| true),
+            logger,
+        );
+
+        (service, ledger)
+    }
+
+    /// The request as the client would send it, unencrypted as the mock enclave expects.
+    fn proofs_request(indices: Vec<u64>, num_mixins: u64) -> Message {
+        EnclaveMessage {
+            aad: Vec::new(),
+            channel_id: ClientSession::default(),
+            data: mcserial::encode(&TxOutProofsRequest {
+                indices,
+                num_mixins,
+            }),
+        }
+        .into()
+    }
+
+    fn decode_response(response: Message) -> TxOutProofsResponse {
+        let msg: EnclaveMessage<ClientSession> = response.into();
+        mcserial::decode(&msg.data).unwrap()
+    }
+
+    #[test_with_logger]
+    // Requested TxOuts are returned in request order, with their proofs.
+    fn test_get_tx_out_proofs(logger: Logger) {
+        let (mut service, ledger) = create_service(logger.clone());
+
+        let indices = vec![3, 0, 2];
+        let response = decode_response(
+            service
+                .real_get_tx_out_proofs(proofs_request(indices.clone(), 0), &logger)
+                .unwrap(),
+        );
+
+        let expected_tx_outs: Vec<TxOut> = indices
+            .iter()
+            .map(|index| ledger.get_tx_out_by_index(*index).unwrap())
+            .collect();
+        assert_eq!(response.tx_outs, expected_tx_outs);
+        assert_eq!(
+            response.proofs,
+            ledger.get_tx_out_proof_of_memberships(&indices).unwrap()
+        );
+        assert!(response.mixins.is_empty());
+        assert!(response.mixin_proofs.is_empty());
+    }
+
+    #[test_with_logger]
+    // Requests for more than MAX_TX_OUT_PROOFS_PER_REQUEST TxOuts, mixins included, are rejected.
+    fn test_get_tx_out_proofs_rejects_too_many(logger: Logger) {
+        let (mut service, _ledger) = create_service(logger.clone());
+
+        // Exactly at the limit is fine, mixins are capped by what the ledger holds.
+        assert!(service
+            .real_get_tx_out_proofs(
+                proofs_request(vec![0], MAX_TX_OUT_PROOFS_PER_REQUEST - 1),
+                &logger
+            )
+            .is_ok());
+
+        match service.real_get_tx_out_proofs(
+            proofs_request(vec![0], MAX_TX_OUT_PROOFS_PER_REQUEST),
+            &logger,
+        ) {
+            Err(ConsensusGrpcError::InvalidArgument(_)) => {}
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
+        }
+
+        let indices: Vec<u64> = (0..=MAX_TX_OUT_PROOFS_PER_REQUEST).map(|_| 0).collect();
+        match service.real_get_tx_out_proofs(proofs_request(indices, 0), &logger) {
+            Err(ConsensusGrpcError::InvalidArgument(_)) => {}
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
+        }
+
+        // The counts must not overflow past the check.
+        match service.real_get_tx_out_proofs(proofs_request(vec![0], u64::max_value()), &logger) {
+            Err(ConsensusGrpcError::InvalidArgument(_)) => {}
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test_with_logger]
+    // Indices past the end of the ledger are rejected.
+    fn test_get_tx_out_proofs_rejects_out_of_range(logger: Logger) {
+        let (mut service, ledger) = create_service(logger.clone());
+        let num_txos = ledger.num_txos().unwrap();
+
+        assert!(service
+            .real_get_tx_out_proofs(proofs_request(vec![num_txos - 1], 0), &logger)
+            .is_ok());
+
+        match service.real_get_tx_out_proofs(proofs_request(vec![0, num_txos], 0), &logger) {
+            Err(ConsensusGrpcError::InvalidArgument(_)) => {}
+            result => panic!("Unexpected result {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test_with_logger]
+    // Mixins are distinct, never one of the requested TxOuts, and capped by what is left.
+    fn test_get_tx_out_proofs_mixins_exclude_requested(logger: Logger) {
+        let (mut service, ledger) = create_service(logger.clone());
+        let num_txos = ledger.num_txos().unwrap();
+        assert!(num_txos > 3);
+
+        // Request all but two TxOuts, and more mixins than are left.
+        let indices: Vec<u64> = (2..num_txos).collect();
+        let response = decode_response(
+            service
+                .real_get_tx_out_proofs(proofs_request(indices.clone(), 10), &logger)
+                .unwrap(),
+        );
+
+        let expected_mixins: Vec<TxOut> = (0..2)
+            .map(|index| ledger.get_tx_out_by_index(index).unwrap())
+            .collect();
+        assert_eq!(response.mixins, expected_mixins);
+        assert_eq!(
+            response.mixin_proofs,
+            ledger.get_tx_out_proof_of_memberships(&[0, 1]).unwrap()
+        );
+
+        // With room to choose, mixins are still distinct and exclude the requested TxOuts.
+        let indices = vec![0, num_txos - 1];
+        for _ in 0..10 {
+            let response = decode_response(
+                service
+                    .real_get_tx_out_proofs(proofs_request(indices.clone(), 2), &logger)
+                    .unwrap(),
+            );
+            assert_eq!(response.mixins.len(), 2);
+            assert_ne!(response.mixins[0], response.mixins[1]);
+            for mixin in response.mixins.iter() {
+                assert!(!response.tx_outs.contains(mixin));
+            }
+        }
+    }
 }
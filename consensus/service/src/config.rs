@@ -2,15 +2,24 @@
 
 //! Configuration parameters for the Consensus Service application.
 
+use crate::pending_values::PendingValueOrder;
 use attest::ProviderId;
 use base64;
 use common::{HashMap, HashSet, NodeID, ResponderId};
-use keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private};
+use keys::{DistinguishedEncoding, Ed25519Pair, Ed25519Private, RistrettoPublic};
 use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri, ConsensusPeerUri as PeerUri};
 use scp::{QuorumSet, QuorumSetMember};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, fs, iter::FromIterator, path::PathBuf, string::String, sync::Arc};
+use std::{
+    convert::TryFrom, fmt::Debug, fs, iter::FromIterator, path::PathBuf, str::FromStr,
+    string::String, sync::Arc, time::Duration,
+};
 use structopt::StructOpt;
+use transaction::{
+    account_keys::PublicAddress,
+    dust::DustRules,
+    fee_split::{FeeRecipient, FeeSplit},
+};
 
 #[derive(Clone, Debug, StructOpt)]
 #[structopt(
@@ -78,6 +87,21 @@ pub struct Config {
     /// Path to the sealed block signing key
     #[structopt(long, parse(from_os_str))]
     pub sealed_block_signing_key: PathBuf,
+
+    /// Nominate pending transactions highest fee first, instead of in arrival order. This only
+    /// affects what this node proposes: combining values does not depend on fees, so that nodes
+    /// configured differently still agree.
+    #[structopt(long)]
+    pub fee_priority: bool,
+
+    /// With --fee-priority, the number of seconds after which a pending transaction is nominated
+    /// ahead of newer ones regardless of its fee.
+    #[structopt(long, default_value = "30", parse(try_from_str=parse_duration_in_seconds))]
+    pub fee_priority_max_wait: Duration,
+}
+
+fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
 fn keypair_from_base64(src: &str) -> Result<Arc<Ed25519Pair>, String> {
@@ -102,6 +126,44 @@ pub struct NetworkConfig {
 
     /// Optional list of peers we are aware of.
     pub known_peers: Option<Vec<PeerUri>>,
+
+    /// Rules for zero-value and sub-minimum outputs, advertised to clients.
+    #[serde(default)]
+    pub dust_rules: DustRules,
+
+    /// Recipients of the fees of each block. Empty to pay all fees to the network's fee recipient
+    /// account. Every node must list the same recipients, in the same order.
+    #[serde(default)]
+    pub fee_recipients: Vec<FeeRecipientConfig>,
+}
+
+/// A recipient of a share of the fees of each block.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
+pub struct FeeRecipientConfig {
+    /// Hex-encoded spend public key of the recipient's address.
+    pub spend_public_key: String,
+
+    /// Hex-encoded view public key of the recipient's address.
+    pub view_public_key: String,
+
+    /// The recipient's share of the fees, relative to the weights of the other recipients.
+    pub weight: u32,
+}
+
+impl FeeRecipientConfig {
+    fn fee_recipient(&self) -> FeeRecipient {
+        let public_key = |hex_key: &str| {
+            let bytes = hex::decode(hex_key)
+                .unwrap_or_else(|e| panic!("invalid fee recipient key {}: {:?}", hex_key, e));
+            RistrettoPublic::try_from(&bytes[..])
+                .unwrap_or_else(|e| panic!("invalid fee recipient key {}: {:?}", hex_key, e))
+        };
+        let address = PublicAddress::new(
+            &public_key(&self.spend_public_key),
+            &public_key(&self.view_public_key),
+        );
+        FeeRecipient::new(address, self.weight)
+    }
 }
 
 impl NetworkConfig {
@@ -140,6 +202,21 @@ impl NetworkConfig {
         self.broadcast_peers.clone()
     }
 
+    /// How the fees of each block are split between recipients.
+    pub fn fee_split(&self) -> FeeSplit {
+        if self.fee_recipients.is_empty() {
+            return FeeSplit::default();
+        }
+
+        FeeSplit::new(
+            self.fee_recipients
+                .iter()
+                .map(FeeRecipientConfig::fee_recipient)
+                .collect(),
+        )
+        .unwrap_or_else(|e| panic!("invalid fee recipients: {}", e))
+    }
+
     // Convert a QuorumSet<ResponderId> -> QuorumSet<NodeID> based on a
     // ResponderID -> NodeID map.
     fn resolve_quorum_set(
@@ -176,6 +253,17 @@ impl Config {
         }
     }
 
+    /// The order in which pending values are nominated.
+    pub(crate) fn pending_value_order(&self) -> PendingValueOrder {
+        if self.fee_priority {
+            PendingValueOrder::FeePriority {
+                max_wait: self.fee_priority_max_wait,
+            }
+        } else {
+            PendingValueOrder::Arrival
+        }
+    }
+
     /// Get the network configuration by loading the network.toml/json file.
     pub fn network(&self) -> NetworkConfig {
         // Read configuration file.
@@ -236,6 +324,9 @@ impl Config {
             panic!("Network configuration is missing tx_source_urls");
         }
 
+        // Sanity test: Fees can be split between the fee recipients.
+        network.fee_split();
+
         // Success.
         network
     }
@@ -245,8 +336,10 @@ impl Config {
 mod tests {
     use super::*;
     use keys::Ed25519Public;
+    use rand::{rngs::StdRng, SeedableRng};
     use scp::QuorumSetMember;
     use std::str::FromStr;
+    use transaction::{account_keys::AccountKey, dust::DustPolicy};
 
     #[test]
     fn test_network_config_parsing() {
@@ -275,6 +368,61 @@ mod tests {
             assert_eq!(network.quorum_set.members.len(), 0);
             assert_eq!(network.broadcast_peers.len(), 0);
             assert!(network.known_peers.is_none());
+            assert_eq!(network.dust_rules, DustRules::default());
+            assert_eq!(network.fee_split(), FeeSplit::default());
+        }
+
+        // Dust rules.
+        {
+            let input_toml: &str = r#"
+                broadcast_peers = []
+                tx_source_urls = []
+                quorum_set = { threshold = 2, members = [] }
+                dust_rules = { policy = "allowed-as-change", minimum_output_value = 1000 }
+            "#;
+            let network: NetworkConfig = toml::from_str(input_toml).expect("failed parsing toml");
+            assert_eq!(
+                network.dust_rules,
+                DustRules::new(DustPolicy::AllowedAsChange, 1000)
+            );
+        }
+
+        // Fee recipients.
+        {
+            let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+            let foundation = AccountKey::random(&mut rng).default_subaddress();
+            let operators = AccountKey::random(&mut rng).default_subaddress();
+
+            let input_toml = format!(
+                r#"
+                broadcast_peers = []
+                tx_source_urls = []
+                quorum_set = {{ threshold = 2, members = [] }}
+
+                [[fee_recipients]]
+                spend_public_key = "{}"
+                view_public_key = "{}"
+                weight = 3
+
+                [[fee_recipients]]
+                spend_public_key = "{}"
+                view_public_key = "{}"
+                weight = 1
+                "#,
+                hex::encode(foundation.spend_public_key().to_bytes()),
+                hex::encode(foundation.view_public_key().to_bytes()),
+                hex::encode(operators.spend_public_key().to_bytes()),
+                hex::encode(operators.view_public_key().to_bytes()),
+            );
+            let network: NetworkConfig = toml::from_str(&input_toml).expect("failed parsing toml");
+            assert_eq!(
+                network.fee_split(),
+                FeeSplit::new(vec![
+                    FeeRecipient::new(foundation, 3),
+                    FeeRecipient::new(operators, 1),
+                ])
+                .unwrap()
+            );
         }
 
         // Real world configuration.
@@ -400,6 +548,8 @@ mod tests {
             scp_debug_dump: None,
             origin_block_path: None,
             sealed_block_signing_key: PathBuf::default(),
+            fee_priority: false,
+            fee_priority_max_wait: Duration::from_secs(30),
         };
 
         assert_eq!(
@@ -449,6 +599,8 @@ mod tests {
             scp_debug_dump: None,
             origin_block_path: None,
             sealed_block_signing_key: PathBuf::default(),
+            fee_priority: false,
+            fee_priority_max_wait: Duration::from_secs(30),
         };
 
         assert_eq!(
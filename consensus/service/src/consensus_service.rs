@@ -4,8 +4,8 @@
 
 use crate::{
     attested_api_service::AttestedApiService, background_work_queue::BackgroundWorkQueue,
-    blockchain_api_service, byzantine_ledger::ByzantineLedger, client_api_service, config::Config,
-    counters, management::ManagementServer, peer_api_service, peer_keepalive::PeerKeepalive,
+    byzantine_ledger::ByzantineLedger, client_api_service, config::Config, counters,
+    management::ManagementServer, peer_api_service, peer_keepalive::PeerKeepalive,
     tx_manager::TxManager, validators::DefaultTxManagerUntrustedInterfaces,
 };
 use attest::{
@@ -16,6 +16,7 @@ use attest_api::attest_grpc::create_attested_api;
 use attest_enclave_api::{ClientSession, Error as AttestEnclaveError, PeerSession};
 use attest_net::{Error as RaError, RaClient};
 use attest_untrusted::QuotingEnclave;
+use blockchain_api_service::BlockchainApiService;
 use common::{
     logger::{log, Logger},
     NodeID, ResponderId,
@@ -152,6 +153,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             BackgroundWorkQueue::new(&counters::CONSENSUS_MSGS_FROM_NETWORK_QUEUE_SIZE);
 
         let local_node_id = config.node_id();
+        let fee_split_fingerprint = config.network().fee_split().fingerprint();
 
         // Peers
         let peers: Vec<PeerConnection<E>> = config
@@ -162,6 +164,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 PeerConnection::new(
                     enclave.clone(),
                     local_node_id.clone(),
+                    fee_split_fingerprint,
                     peer_uri,
                     env.clone(),
                     logger.clone(),
@@ -429,12 +432,10 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             self.logger.clone(),
         ));
 
-        let blockchain_service = blockchain_grpc::create_blockchain_api(
-            blockchain_api_service::BlockchainApiService::new(
-                self.ledger_db.clone(),
-                self.logger.clone(),
-            ),
-        );
+        let mut blockchain_api_service =
+            BlockchainApiService::new(self.ledger_db.clone(), self.logger.clone());
+        blockchain_api_service.set_dust_rules(self.config.network().dust_rules);
+        let blockchain_service = blockchain_grpc::create_blockchain_api(blockchain_api_service);
 
         let is_serving_user_requests = self.create_is_serving_user_requests_fn();
         let health_check_callback: Arc<dyn Fn(&str) -> HealthCheckStatus + Sync + Send> =
@@ -492,12 +493,10 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 .and_then(|byzantine_ledger| byzantine_ledger.get_highest_scp_message())
         });
 
-        let blockchain_service = blockchain_grpc::create_blockchain_api(
-            blockchain_api_service::BlockchainApiService::new(
-                self.ledger_db.clone(),
-                self.logger.clone(),
-            ),
-        );
+        let mut blockchain_api_service =
+            BlockchainApiService::new(self.ledger_db.clone(), self.logger.clone());
+        blockchain_api_service.set_dust_rules(self.config.network().dust_rules);
+        let blockchain_service = blockchain_grpc::create_blockchain_api(blockchain_api_service);
 
         let peer_service =
             consensus_peer_grpc::create_consensus_peer_api(peer_api_service::PeerApiService::new(
@@ -508,6 +507,7 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
                 self.tx_manager.clone(),
                 get_highest_scp_message_fn,
                 self.peer_manager.responder_ids(),
+                self.config.network().fee_split().fingerprint(),
                 self.logger.clone(),
             ));
 
@@ -552,6 +552,8 @@ impl<E: ConsensusEnclaveProxy, R: RaClient + Send + Sync + 'static> ConsensusSer
             self.config.msg_signer_key.clone(),
             self.config.network().tx_source_urls,
             self.config.scp_debug_dump.clone(),
+            self.config.pending_value_order(),
+            self.config.network().fee_split(),
             self.logger.clone(),
         ));
 
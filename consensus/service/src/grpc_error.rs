@@ -77,6 +77,7 @@ impl From<TxManagerError> for ConsensusGrpcError {
             TxManagerError::Enclave(err) => Self::from(err),
             TxManagerError::TransactionValidation(err) => Self::from(err),
             TxManagerError::LedgerDb(err) => Self::from(err),
+            TxManagerError::CacheFull => Self::OverCapacity,
             _ => Self::Other(format!("tx manager error: {}", src)),
         }
     }
@@ -105,6 +106,9 @@ impl From<ConsensusGrpcError> for RpcStatus {
                     Some("Unauthenticated".into()),
                 )
             }
+            ConsensusGrpcError::InvalidArgument(err) => {
+                RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(err))
+            }
             ConsensusGrpcError::Other(err) => RpcStatus::new(RpcStatusCode::INTERNAL, Some(err)),
             ConsensusGrpcError::TransactionValidation(err) => {
                 global_log::error!("Attempting to convert a ConsensusGrpcError::TransactionValidation into RpcStatus, this should not happen! Error is: {}", err);
@@ -14,7 +14,6 @@ pub mod validators; // Public so that it can be benchmarked by the `benchmarks`
 
 mod attested_api_service;
 mod background_work_queue;
-mod blockchain_api_service;
 mod byzantine_ledger;
 mod client_api_service;
 mod counters;
@@ -22,6 +21,7 @@ mod grpc_error;
 mod management;
 mod peer_api_service;
 mod peer_keepalive;
+mod pending_values;
 
 #[cfg(test)]
 mod test_utils;
@@ -32,7 +32,7 @@ use std::{
     convert::{TryFrom, TryInto},
     sync::Arc,
 };
-use transaction::tx::TxHash;
+use transaction::{fee_split::FeeSplit, tx::TxHash};
 
 // Callback method for returning the latest SCP message issued by the local node, used to
 // implement the `fetch_latest_msg` RPC call.
@@ -64,6 +64,10 @@ pub struct PeerApiService<E: ConsensusEnclaveProxy, L: Ledger> {
     /// not on this list, we won't be able to reach out to it to ask for the transaction contents.
     known_responder_ids: Vec<ResponderId>,
 
+    /// Fingerprint of the local fee split. Peers that split fees differently would form different
+    /// blocks, so their messages are rejected.
+    fee_split_fingerprint: [u8; 32],
+
     /// Logger.
     logger: Logger,
 }
@@ -77,6 +81,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
         tx_manager: TxManager<E, L>,
         fetch_latest_msg_fn: FetchLatestMsgFn,
         known_responder_ids: Vec<ResponderId>,
+        fee_split_fingerprint: [u8; 32],
         logger: Logger,
     ) -> Self {
         Self {
@@ -87,6 +92,7 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> PeerApiService<E, L> {
             tx_manager,
             fetch_latest_msg_fn,
             known_responder_ids,
+            fee_split_fingerprint,
             logger,
         }
     }
@@ -271,6 +277,32 @@ impl<E: ConsensusEnclaveProxy, L: Ledger> ConsensusPeerApi for PeerApiService<E,
                 return;
             }
 
+            // See if this peer splits fees the same way. Peers that do not send a fingerprint split
+            // fees the default way. The enclaves also refuse to attest peers that split fees
+            // differently, so this only rejects such messages early, with a clear error.
+            let peer_fee_split_fingerprint = match request.get_fee_split_fingerprint() {
+                [] => FeeSplit::default().fingerprint().to_vec(),
+                fingerprint => fingerprint.to_vec(),
+            };
+            if peer_fee_split_fingerprint[..] != self.fee_split_fingerprint[..] {
+                send_result(
+                    ctx,
+                    sink,
+                    Err(rpc_invalid_arg_error(
+                        "send_consensus_msg",
+                        format!("Fee split mismatch with {}", from_responder_id),
+                        &logger,
+                    )),
+                    &logger,
+                );
+                log::warn!(
+                    logger,
+                    "Rejecting consensus message from {}, which splits fees differently",
+                    from_responder_id
+                );
+                return;
+            }
+
             // Validate message signature
             // FIXME: Additional verification for quorum set members that public key matches expected
             let consensus_msg: peers::VerifiedConsensusMsg = match unverified_consensus_msg
@@ -0,0 +1,269 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The values a node is trying to get externalized, and the order in which it nominates them.
+
+use std::{
+    cmp::Ordering,
+    collections::{btree_map::Entry::Vacant, BTreeMap},
+    time::{Duration, Instant},
+};
+use transaction::tx::TxHash;
+
+/// The order in which pending values are nominated.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PendingValueOrder {
+    /// First come, first served.
+    Arrival,
+
+    /// Highest fee first. Values that have been pending for at least `max_wait` are nominated
+    /// ahead of all others, oldest first, so that low-fee values are not starved under load.
+    FeePriority { max_wait: Duration },
+}
+
+impl Default for PendingValueOrder {
+    fn default() -> Self {
+        PendingValueOrder::Arrival
+    }
+}
+
+struct PendingValue {
+    /// The fee paid by the transaction.
+    fee: u64,
+
+    /// When this node first saw the value.
+    received_at: Instant,
+
+    /// When the value was handed to us by a client. We skip tracking this for relayed values
+    /// since we want to track the time from when the network first saw a value, and not when a
+    /// specific node saw it.
+    submitted_at: Option<Instant>,
+}
+
+/// Pending values we're trying to push. We need to store them in arrival order so we can process
+/// values on a first-come first-served basis or break ties between equal fees. However, we want to
+/// be able to:
+/// 1) Efficiently see if we already have a given value and ignore duplicates
+/// 2) Track how long each value took to externalize.
+/// To accomplish both of these goals we store, in addition to the queue of pending values, a
+/// BTreeMap that maps a value to what we know about it.
+pub struct PendingValues {
+    order: PendingValueOrder,
+    values: Vec<TxHash>,
+    entries: BTreeMap<TxHash, PendingValue>,
+}
+
+impl PendingValues {
+    pub fn new(order: PendingValueOrder) -> Self {
+        Self {
+            order,
+            values: Vec::new(),
+            entries: BTreeMap::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Add a value, unless we already have it. Returns true if the value was added.
+    ///
+    /// # Arguments
+    /// * `tx_hash` - The value.
+    /// * `fee` - The fee paid by the transaction.
+    /// * `submitted_at` - When a client handed us the value, or None if it was relayed by a peer.
+    pub fn insert(&mut self, tx_hash: TxHash, fee: u64, submitted_at: Option<Instant>) -> bool {
+        self.insert_at(tx_hash, fee, submitted_at, Instant::now())
+    }
+
+    fn insert_at(
+        &mut self,
+        tx_hash: TxHash,
+        fee: u64,
+        submitted_at: Option<Instant>,
+        received_at: Instant,
+    ) -> bool {
+        if let Vacant(entry) = self.entries.entry(tx_hash) {
+            entry.insert(PendingValue {
+                fee,
+                received_at,
+                submitted_at,
+            });
+            self.values.push(tx_hash);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// When a client handed us the given value, if it did.
+    pub fn submitted_at(&self, tx_hash: &TxHash) -> Option<Instant> {
+        self.entries
+            .get(tx_hash)
+            .and_then(|entry| entry.submitted_at)
+    }
+
+    /// Keep only the values for which `f` returns true.
+    pub fn retain<F: FnMut(&TxHash) -> bool>(&mut self, mut f: F) {
+        let entries = &mut self.entries;
+        self.values.retain(|tx_hash| {
+            if f(tx_hash) {
+                true
+            } else {
+                entries.remove(tx_hash);
+                false
+            }
+        });
+    }
+
+    /// The values to nominate, in priority order.
+    ///
+    /// # Arguments
+    /// * `max_values` - The maximal number of values to return.
+    pub fn to_nominate(&self, max_values: usize) -> Vec<TxHash> {
+        self.to_nominate_at(max_values, Instant::now())
+    }
+
+    fn to_nominate_at(&self, max_values: usize, now: Instant) -> Vec<TxHash> {
+        let max_wait = match self.order {
+            PendingValueOrder::Arrival => {
+                return self.values.iter().take(max_values).cloned().collect();
+            }
+            PendingValueOrder::FeePriority { max_wait } => max_wait,
+        };
+
+        // Values are in arrival order, so sorting by their position breaks ties by age.
+        let mut candidates: Vec<(usize, &TxHash, bool, u64)> = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(position, tx_hash)| {
+                let entry = &self.entries[tx_hash];
+                let is_starving = now.saturating_duration_since(entry.received_at) >= max_wait;
+                (position, tx_hash, is_starving, entry.fee)
+            })
+            .collect();
+
+        candidates.sort_by(
+            |(position_a, _, starving_a, fee_a), (position_b, _, starving_b, fee_b)| {
+                starving_b
+                    .cmp(starving_a)
+                    .then_with(|| {
+                        if *starving_a && *starving_b {
+                            Ordering::Equal
+                        } else {
+                            fee_b.cmp(fee_a)
+                        }
+                    })
+                    .then_with(|| position_a.cmp(position_b))
+            },
+        );
+
+        candidates
+            .into_iter()
+            .take(max_values)
+            .map(|(_, tx_hash, _, _)| *tx_hash)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx_hash(byte: u8) -> TxHash {
+        TxHash::from([byte; 32])
+    }
+
+    #[test]
+    // Duplicates should be ignored, and retain should drop values from both the queue and the map.
+    fn test_insert_and_retain() {
+        let mut pending_values = PendingValues::new(PendingValueOrder::Arrival);
+        let submitted_at = Instant::now();
+
+        assert!(pending_values.insert(tx_hash(1), 10, Some(submitted_at)));
+        assert!(pending_values.insert(tx_hash(2), 20, None));
+        assert!(!pending_values.insert(tx_hash(1), 30, None));
+        assert_eq!(pending_values.len(), 2);
+        assert_eq!(pending_values.submitted_at(&tx_hash(1)), Some(submitted_at));
+        assert_eq!(pending_values.submitted_at(&tx_hash(2)), None);
+
+        pending_values.retain(|tx_hash| *tx_hash != self::tx_hash(1));
+        assert_eq!(pending_values.len(), 1);
+        assert_eq!(pending_values.submitted_at(&tx_hash(1)), None);
+        assert_eq!(pending_values.to_nominate(10), vec![tx_hash(2)]);
+
+        // A value that was dropped can be added again.
+        assert!(pending_values.insert(tx_hash(1), 10, None));
+        assert_eq!(pending_values.to_nominate(10), vec![tx_hash(2), tx_hash(1)]);
+    }
+
+    #[test]
+    // Arrival order should nominate the oldest values, regardless of fee.
+    fn test_arrival_order() {
+        let mut pending_values = PendingValues::new(PendingValueOrder::Arrival);
+        pending_values.insert(tx_hash(1), 10, None);
+        pending_values.insert(tx_hash(2), 30, None);
+        pending_values.insert(tx_hash(3), 20, None);
+
+        assert_eq!(
+            pending_values.to_nominate(10),
+            vec![tx_hash(1), tx_hash(2), tx_hash(3)]
+        );
+        assert_eq!(pending_values.to_nominate(2), vec![tx_hash(1), tx_hash(2)]);
+    }
+
+    #[test]
+    // Fee priority should nominate the highest fees first, and ties in arrival order.
+    fn test_fee_priority_order() {
+        let mut pending_values = PendingValues::new(PendingValueOrder::FeePriority {
+            max_wait: Duration::from_secs(60),
+        });
+        let now = Instant::now();
+        pending_values.insert_at(tx_hash(1), 10, None, now);
+        pending_values.insert_at(tx_hash(2), 30, None, now);
+        pending_values.insert_at(tx_hash(3), 20, None, now);
+        pending_values.insert_at(tx_hash(4), 30, None, now);
+
+        assert_eq!(
+            pending_values.to_nominate_at(10, now),
+            vec![tx_hash(2), tx_hash(4), tx_hash(3), tx_hash(1)]
+        );
+        assert_eq!(
+            pending_values.to_nominate_at(2, now),
+            vec![tx_hash(2), tx_hash(4)]
+        );
+    }
+
+    #[test]
+    // Values pending for longer than `max_wait` should be nominated first, oldest first.
+    fn test_fee_priority_anti_starvation() {
+        let max_wait = Duration::from_secs(60);
+        let mut pending_values = PendingValues::new(PendingValueOrder::FeePriority { max_wait });
+        let start = Instant::now();
+        pending_values.insert_at(tx_hash(1), 1, None, start);
+        pending_values.insert_at(tx_hash(2), 2, None, start + Duration::from_secs(10));
+        pending_values.insert_at(tx_hash(3), 100, None, start + Duration::from_secs(20));
+
+        // Nothing is starving yet.
+        assert_eq!(
+            pending_values.to_nominate_at(1, start + Duration::from_secs(30)),
+            vec![tx_hash(3)]
+        );
+
+        // The first value has waited long enough.
+        assert_eq!(
+            pending_values.to_nominate_at(3, start + max_wait),
+            vec![tx_hash(1), tx_hash(3), tx_hash(2)]
+        );
+
+        // Both low-fee values have waited long enough.
+        assert_eq!(
+            pending_values.to_nominate_at(3, start + max_wait + Duration::from_secs(10)),
+            vec![tx_hash(1), tx_hash(2), tx_hash(3)]
+        );
+    }
+}
@@ -41,6 +41,9 @@ pub enum TxManagerError {
     #[fail(display = "Tx not in cache ({})", _0)]
     NotInCache(TxHash),
 
+    #[fail(display = "Tx cache is full")]
+    CacheFull,
+
     #[fail(display = "Ledger error: {}", _0)]
     LedgerDb(LedgerDbError),
 }
@@ -69,6 +72,10 @@ impl From<LedgerDbError> for TxManagerError {
 
 pub type TxManagerResult<T> = Result<T, TxManagerError>;
 
+/// Maximum number of well-formed transactions held in the cache. Once full, transactions that can
+/// no longer be appended to the ledger are evicted, and new ones are rejected until there is room.
+const MAX_CACHE_ENTRIES: usize = 100_000;
+
 struct CacheEntry {
     encrypted_tx: WellFormedEncryptedTx,
 
@@ -135,7 +142,8 @@ pub struct TxManager<
     /// Logger.
     logger: Logger,
 
-    /// Map of tx hashes to data we hold for each tx.
+    /// Map of tx hashes to data we hold for each tx, bounded by `MAX_CACHE_ENTRIES`. A tx that
+    /// can still be appended to the ledger is never evicted, since it may still be voted on.
     cache: Arc<Mutex<HashMap<TxHash, CacheEntry>>>,
 }
 
@@ -195,6 +203,12 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         // Store in our cache.
         {
             let mut cache = self.lock_cache();
+            if cache.len() >= MAX_CACHE_ENTRIES {
+                self.evict_invalid(&mut cache);
+                if cache.len() >= MAX_CACHE_ENTRIES {
+                    return Err(TxManagerError::CacheFull);
+                }
+            }
             cache.insert(
                 *well_formed_tx_context.tx_hash(),
                 CacheEntry {
@@ -237,6 +251,23 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
         purged_hashes
     }
 
+    /// Evict the transactions that can no longer be appended to the ledger, because their tombstone
+    /// block has passed or a key image they spend was externalized. Pending values referencing
+    /// them are dropped once they fail `validate_tx_by_hash`.
+    fn evict_invalid(&self, cache: &mut HashMap<TxHash, CacheEntry>) {
+        let num_entries_before = cache.len();
+        let untrusted = &self.untrusted;
+        cache.retain(|_tx_hash, entry| untrusted.is_valid(entry.context()).is_ok());
+
+        log::debug!(
+            self.logger,
+            "cache full, evicted {} invalid txs, left with {}",
+            num_entries_before - cache.len(),
+            cache.len(),
+        );
+        counters::TX_CACHE_NUM_ENTRIES.set(cache.len() as i64);
+    }
+
     /// Returns the list of hashes inside `tx_hashes` that are not inside the cache.
     pub fn missing_hashes(&self, tx_hashes: &BTreeSet<TxHash>) -> Vec<TxHash> {
         let mut missing = Vec::new();
@@ -350,6 +381,38 @@ impl<E: ConsensusEnclaveProxy, L: Ledger, UI: UntrustedInterfaces> TxManager<E,
             .map(|entry| entry.encrypted_tx().clone())
     }
 
+    /// The fee paid by a cached transaction.
+    pub fn get_fee_by_hash(&self, tx_hash: &TxHash) -> Option<u64> {
+        self.lock_cache()
+            .get(tx_hash)
+            .map(|entry| entry.context().fee())
+    }
+
+    /// Returns the key images inside `key_images` that are spent by a cached transaction, but
+    /// are not yet spent in the ledger.
+    pub fn pending_key_images(&self, key_images: &[KeyImage]) -> TxManagerResult<Vec<KeyImage>> {
+        let cached: Vec<KeyImage> = {
+            let cache = self.lock_cache();
+            key_images
+                .iter()
+                .filter(|key_image| {
+                    cache
+                        .iter()
+                        .any(|(_tx_hash, entry)| entry.context().key_images().contains(key_image))
+                })
+                .cloned()
+                .collect()
+        };
+
+        let mut pending = Vec::new();
+        for key_image in cached {
+            if !self.ledger.contains_key_image(&key_image)? {
+                pending.push(key_image);
+            }
+        }
+        Ok(pending)
+    }
+
     pub fn num_entries(&self) -> usize {
         self.lock_cache().len()
     }
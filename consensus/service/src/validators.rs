@@ -105,6 +105,10 @@ impl<L: Ledger> TxManagerUntrustedInterfaces for DefaultTxManagerUntrustedInterf
     /// formed and safe to append to the ledger individually.
     /// ASSUMES VALUES ARE SORTED.
     ///
+    /// Candidates are taken in the order they are given, regardless of the fees they pay, so that
+    /// the result is the same on every node. Fee priority only applies to what a node nominates,
+    /// see `PendingValues`.
+    ///
     /// # Arguments
     /// * `tx_contexts` - "Candidate" transactions. Each is assumed to be individually valid.
     /// * `max_elements` - Maximum number of elements to return.
@@ -906,4 +910,79 @@ mod combine_tests {
         assert_eq!(combined_transactions.len(), 2);
         assert!(combined_transactions.contains(third_client_tx.tx_hash()));
     }
+
+    /// Creates transactions that each spend the same TxOut, paying the given fees.
+    fn conflicting_tx_contexts_with_fees(fees: &[u64]) -> Vec<WellFormedTxContext> {
+        let mut rng = Hc128Rng::from_seed([1u8; 32]);
+
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+
+        // Create a TxOut that was sent to Alice.
+        let tx_out = TxOut::new(
+            123,
+            &alice.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap();
+
+        // Alice creates InputCredentials to spend her tx_out.
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&tx_out.public_key).unwrap(),
+            alice.view_private_key(),
+            &alice.default_subaddress_spend_key(),
+        );
+
+        // Create transactions that send the value of `tx_out` to bob, paying various fees.
+        fees.iter()
+            .map(|fee| {
+                let ring = vec![tx_out.clone()];
+                let membership_proofs: Vec<TxOutMembershipProof> = ring
+                    .iter()
+                    .map(|_tx_out| {
+                        // TODO: provide valid proofs for each tx_out.
+                        TxOutMembershipProof::new(0, 0, HashMap::default())
+                    })
+                    .collect();
+
+                let input_credentials = InputCredentials::new(
+                    ring,
+                    membership_proofs,
+                    0,
+                    onetime_private_key,
+                    *alice.view_private_key(),
+                    &mut rng,
+                )
+                .unwrap();
+
+                let mut transaction_builder = TransactionBuilder::new();
+                transaction_builder.add_input(input_credentials);
+                transaction_builder.set_fee(*fee);
+                transaction_builder
+                    .add_output(123 - fee, &bob.default_subaddress(), None, &mut rng)
+                    .unwrap();
+
+                let tx = transaction_builder.build(&mut rng).unwrap();
+                WellFormedTxContext::from(&tx)
+            })
+            .collect()
+    }
+
+    #[test]
+    // `combine` should keep the first of two transactions that use the same key image, regardless
+    // of fee, so that nodes nominating by fee still agree on the result.
+    fn combine_ignores_fees() {
+        let client_txs = conflicting_tx_contexts_with_fees(&[0, 10, 5]);
+
+        for rotation in 0..client_txs.len() {
+            let mut transaction_set = client_txs.clone();
+            transaction_set.rotate_left(rotation);
+
+            let combined_transactions = combine(transaction_set.clone(), 10);
+            assert_eq!(combined_transactions.len(), 1);
+            assert!(combined_transactions.contains(transaction_set[0].tx_hash()));
+        }
+    }
 }
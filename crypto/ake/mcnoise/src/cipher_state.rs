@@ -25,6 +25,15 @@ pub enum CipherError {
     NoKey,
     #[fail(display = "Authenticated encryption error")]
     Aead,
+    #[fail(display = "The message was too short to contain a sequence number")]
+    Truncated,
+    #[fail(display = "Message {} was replayed, expected message {}", _0, _1)]
+    Replayed(u64, u64),
+    #[fail(
+        display = "Message {} arrived out of order, expected message {}",
+        _0, _1
+    )]
+    OutOfOrder(u64, u64),
 }
 
 impl From<AeadError> for CipherError {
@@ -158,7 +167,11 @@ impl<Cipher: AeadMut + NewAead + Sized + NoiseCipher> CipherState<Cipher> {
     /// This will irrevocably override the current nonce value.
     pub fn set_nonce(&mut self, nonce: u64) {
         self.nonce = nonce;
-        // TODO: return current nonce? We don't provide any access otherwise...
+    }
+
+    /// Retrieve the nonce which will be used for the next encrypt or decrypt operation.
+    pub fn nonce(&self) -> u64 {
+        self.nonce
     }
 
     /// The noise protocol `EncryptWithAd()` operation.
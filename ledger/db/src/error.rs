@@ -40,11 +40,26 @@ pub enum Error {
     #[fail(display = "IndexOutOfBounds: {}", _0)]
     IndexOutOfBounds(u64),
 
+    /// The contents of the block were discarded by pruning the ledger.
+    #[fail(display = "BlockPruned: {}", _0)]
+    BlockPruned(u64),
+
     #[fail(display = "LmdbError")]
     LmdbError(lmdb::Error),
 
     #[fail(display = "RangeError")]
     RangeError,
+
+    /// The ledger was opened read-only, see `LedgerDB::open_read_only`.
+    #[fail(display = "ReadOnly")]
+    ReadOnly,
+
+    /// A snapshot is corrupted, or of an unsupported version.
+    #[fail(display = "InvalidSnapshot")]
+    InvalidSnapshot,
+
+    #[fail(display = "Io: {:?}", _0)]
+    Io(std::io::ErrorKind),
 }
 
 impl From<lmdb::Error> for Error {
@@ -72,6 +87,12 @@ impl From<mcserial::encode::Error> for Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(io_error: std::io::Error) -> Self {
+        Error::Io(io_error.kind())
+    }
+}
+
 impl From<transaction::range::RangeError> for Error {
     fn from(_: transaction::range::RangeError) -> Self {
         Error::RangeError
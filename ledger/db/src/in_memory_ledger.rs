@@ -0,0 +1,364 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A `Ledger` kept in memory.
+//!
+//! `InMemoryLedger` validates appended blocks and computes membership proofs exactly like
+//! `LedgerDB`, so that it can stand in for it wherever a ledger is not meant to outlive the
+//! process, e.g. in unit tests, fuzzing and load tests. Clones share the same blocks.
+
+use crate::{
+    tx_out_store::{merkle_hashes_containing, merkle_proof_of_membership},
+    validate_append_block, Error, Ledger,
+};
+use common::{Hash, HashMap};
+use keys::CompressedRistrettoPublic;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use transaction::{
+    range::Range,
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipProof},
+    Block, BlockContents, BlockSignature,
+};
+
+#[derive(Default)]
+struct Inner {
+    /// Blocks by block number.
+    blocks: Vec<Block>,
+
+    /// Block contents by block number.
+    block_contents: Vec<BlockContents>,
+
+    /// Block signatures by block number.
+    block_signatures: HashMap<u64, BlockSignature>,
+
+    /// Block number of each spent key image.
+    key_images: HashMap<KeyImage, u64>,
+
+    /// TxOuts by index.
+    tx_outs: Vec<TxOut>,
+
+    /// `tx_out.hash() -> index`
+    tx_out_index_by_hash: HashMap<Hash, u64>,
+
+    /// `tx_out.public_key -> index`
+    tx_out_index_by_public_key: HashMap<CompressedRistrettoPublic, u64>,
+
+    /// Block number of each TxOut, by TxOut index.
+    block_number_by_tx_out_index: Vec<u64>,
+
+    /// Merkle hashes of subtrees, see `tx_out_store`.
+    merkle_hashes: HashMap<Range, [u8; 32]>,
+}
+
+impl Inner {
+    fn get_merkle_hash(&self, range: &Range) -> Result<[u8; 32], Error> {
+        self.merkle_hashes
+            .get(range)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    /// Update Merkle hashes to include the TxOut with the given index.
+    fn update_merkle_hashes(&mut self, index: u64) -> Result<(), Error> {
+        let num_tx_outs = self.tx_outs.len() as u64;
+        let tx_out = self
+            .tx_outs
+            .get(index as usize)
+            .ok_or(Error::IndexOutOfBounds(index))?;
+        let hashes = merkle_hashes_containing(index, num_tx_outs, tx_out, |range| {
+            self.get_merkle_hash(range)
+        })?;
+        self.merkle_hashes.extend(hashes);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryLedger {
+    inner: Arc<RwLock<Inner>>,
+}
+
+impl InMemoryLedger {
+    fn read(&self) -> RwLockReadGuard<Inner> {
+        self.inner.read().expect("lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<Inner> {
+        self.inner.write().expect("lock poisoned")
+    }
+
+    /// Removes all blocks from index `num_blocks` onwards, see `LedgerDB::truncate`.
+    pub fn truncate(&mut self, num_blocks: u64) -> Result<(), Error> {
+        let mut inner = self.write();
+        let num_blocks_before = inner.blocks.len() as u64;
+        if num_blocks == 0 || num_blocks > num_blocks_before {
+            return Err(Error::IndexOutOfBounds(num_blocks));
+        }
+
+        let mut num_tx_outs = inner.tx_outs.len() as u64;
+        for block_contents in inner.block_contents.split_off(num_blocks as usize) {
+            for key_image in &block_contents.key_images {
+                inner.key_images.remove(key_image);
+            }
+            for tx_out in &block_contents.outputs {
+                inner.tx_out_index_by_hash.remove(&tx_out.hash());
+            }
+            num_tx_outs -= block_contents.outputs.len() as u64;
+        }
+        for index in num_tx_outs..inner.tx_outs.len() as u64 {
+            let public_key = inner.tx_outs[index as usize].public_key;
+            // Only the latest TxOut with a given public key is indexed.
+            if inner.tx_out_index_by_public_key.get(&public_key) == Some(&index) {
+                inner.tx_out_index_by_public_key.remove(&public_key);
+            }
+        }
+        inner
+            .block_number_by_tx_out_index
+            .truncate(num_tx_outs as usize);
+        inner.blocks.truncate(num_blocks as usize);
+        inner
+            .block_signatures
+            .retain(|block_number, _| *block_number < num_blocks);
+
+        inner.tx_outs.truncate(num_tx_outs as usize);
+        inner
+            .merkle_hashes
+            .retain(|range, _| range.from < num_tx_outs);
+        // Every subtree that lost TxOuts contains the last remaining one.
+        if num_tx_outs > 0 {
+            inner.update_merkle_hashes(num_tx_outs - 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Ledger for InMemoryLedger {
+    fn append_block(
+        &mut self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+    ) -> Result<(), Error> {
+        validate_append_block(self, block, block_contents)?;
+
+        let mut inner = self.write();
+        for key_image in &block_contents.key_images {
+            inner.key_images.insert(*key_image, block.index);
+        }
+        for tx_out in &block_contents.outputs {
+            let index = inner.tx_outs.len() as u64;
+            inner.tx_out_index_by_hash.insert(tx_out.hash(), index);
+            inner
+                .tx_out_index_by_public_key
+                .insert(tx_out.public_key, index);
+            inner.block_number_by_tx_out_index.push(block.index);
+            inner.tx_outs.push(tx_out.clone());
+            inner.update_merkle_hashes(index)?;
+        }
+        if let Some(signature) = signature {
+            inner
+                .block_signatures
+                .insert(block.index, signature.clone());
+        }
+        inner.blocks.push(block.clone());
+        inner.block_contents.push(block_contents.clone());
+        Ok(())
+    }
+
+    fn num_blocks(&self) -> Result<u64, Error> {
+        Ok(self.read().blocks.len() as u64)
+    }
+
+    fn get_block(&self, block_number: u64) -> Result<Block, Error> {
+        self.read()
+            .blocks
+            .get(block_number as usize)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_block_contents(&self, block_number: u64) -> Result<BlockContents, Error> {
+        self.read()
+            .block_contents
+            .get(block_number as usize)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_block_signature(&self, block_number: u64) -> Result<BlockSignature, Error> {
+        self.read()
+            .block_signatures
+            .get(&block_number)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn num_txos(&self) -> Result<u64, Error> {
+        Ok(self.read().tx_outs.len() as u64)
+    }
+
+    fn get_tx_out_index_by_hash(&self, tx_out_hash: &Hash) -> Result<u64, Error> {
+        self.read()
+            .tx_out_index_by_hash
+            .get(tx_out_hash)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        self.read()
+            .tx_out_index_by_public_key
+            .get(tx_out_public_key)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error> {
+        self.read()
+            .tx_outs
+            .get(index as usize)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<u64, Error> {
+        self.read()
+            .block_number_by_tx_out_index
+            .get(tx_out_index as usize)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    fn get_tx_out_proof_of_memberships(
+        &self,
+        indexes: &[u64],
+    ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let inner = self.read();
+        let num_tx_outs = inner.tx_outs.len() as u64;
+        indexes
+            .iter()
+            .map(|index| {
+                merkle_proof_of_membership(*index, num_tx_outs, |range| {
+                    inner.get_merkle_hash(range)
+                })
+            })
+            .collect()
+    }
+
+    fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<u64>, Error> {
+        Ok(self.read().key_images.get(key_image).cloned())
+    }
+
+    fn get_key_images_by_block(&self, block_number: u64) -> Result<Vec<KeyImage>, Error> {
+        self.read()
+            .block_contents
+            .get(block_number as usize)
+            .map(|block_contents| block_contents.key_images.clone())
+            .ok_or(Error::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        test_utils::conformance::{populate, run_conformance_tests},
+        LedgerDB,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    /// Creates a LedgerDB, along with the directory that must outlive it.
+    fn create_ledger_db() -> (LedgerDB, TempDir) {
+        let temp_dir = TempDir::new("in_memory_ledger_test").unwrap();
+        let path = temp_dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        (LedgerDB::open(path).unwrap(), temp_dir)
+    }
+
+    #[test]
+    fn test_conformance() {
+        run_conformance_tests(InMemoryLedger::default);
+    }
+
+    #[test]
+    // Both ledgers should produce the same membership proofs for the same blocks, also after being
+    // truncated and extended again.
+    fn test_membership_proofs_match_ledger_db() {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let mut in_memory_ledger = InMemoryLedger::default();
+        let blocks = populate(&mut in_memory_ledger, 7, &mut rng);
+        let (mut ledger_db, _temp_dir) = create_ledger_db();
+        for (block, block_contents, signature) in &blocks {
+            ledger_db
+                .append_block(block, block_contents, signature.as_ref())
+                .unwrap();
+        }
+
+        let check_proofs = |in_memory_ledger: &InMemoryLedger, ledger_db: &LedgerDB| {
+            let num_txos = ledger_db.num_txos().unwrap();
+            assert_eq!(in_memory_ledger.num_txos().unwrap(), num_txos);
+            let indexes: Vec<u64> = (0..num_txos).collect();
+            assert_eq!(
+                in_memory_ledger
+                    .get_tx_out_proof_of_memberships(&indexes)
+                    .unwrap(),
+                ledger_db.get_tx_out_proof_of_memberships(&indexes).unwrap()
+            );
+        };
+        check_proofs(&in_memory_ledger, &ledger_db);
+
+        // Truncating removes everything past the remaining blocks.
+        in_memory_ledger.truncate(3).unwrap();
+        ledger_db.truncate(3).unwrap();
+        check_proofs(&in_memory_ledger, &ledger_db);
+        assert_eq!(in_memory_ledger.num_blocks().unwrap(), 3);
+        assert_eq!(in_memory_ledger.get_block(3), Err(Error::NotFound));
+        assert_eq!(
+            in_memory_ledger.get_block_signature(4),
+            Err(Error::NotFound)
+        );
+        let (_, removed_contents, _) = &blocks[3];
+        assert_eq!(
+            in_memory_ledger.check_key_image(&removed_contents.key_images[0]),
+            Ok(None)
+        );
+        assert_eq!(
+            in_memory_ledger.get_tx_out_index_by_hash(&removed_contents.outputs[0].hash()),
+            Err(Error::NotFound)
+        );
+        assert_eq!(
+            in_memory_ledger.truncate(0),
+            Err(Error::IndexOutOfBounds(0))
+        );
+        assert_eq!(
+            in_memory_ledger.truncate(4),
+            Err(Error::IndexOutOfBounds(4))
+        );
+
+        // The removed blocks can be appended again.
+        for (block, block_contents, signature) in &blocks[3..] {
+            in_memory_ledger
+                .append_block(block, block_contents, signature.as_ref())
+                .unwrap();
+            ledger_db
+                .append_block(block, block_contents, signature.as_ref())
+                .unwrap();
+        }
+        check_proofs(&in_memory_ledger, &ledger_db);
+    }
+
+    #[test]
+    // Clones should share the same blocks.
+    fn test_clones_share_blocks() {
+        let mut rng: StdRng = SeedableRng::from_seed([5u8; 32]);
+        let ledger = InMemoryLedger::default();
+        let mut clone = ledger.clone();
+        populate(&mut clone, 2, &mut rng);
+        assert_eq!(ledger.num_blocks().unwrap(), 2);
+    }
+}
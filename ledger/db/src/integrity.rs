@@ -0,0 +1,65 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The outcome of checking a ledger for corruption, see `LedgerDB::verify_integrity`.
+
+use transaction::BlockIndex;
+
+/// An inconsistency found in a ledger.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IntegrityProblem {
+    /// The block stored under this index holds another index.
+    BlockIndexMismatch(BlockIndex),
+
+    /// The ID of the block does not match its other fields.
+    InvalidBlockID(BlockIndex),
+
+    /// The parent ID of the block is not the ID of the previous block.
+    ParentMismatch(BlockIndex),
+
+    /// The contents of the block do not match its contents hash.
+    ContentsHashMismatch(BlockIndex),
+
+    /// The key images of the block are missing, or not recorded as spent in it.
+    KeyImageMismatch(BlockIndex),
+
+    /// The TxOuts of the block are missing, differ from its contents, or are not indexed.
+    TxOutMismatch(BlockIndex),
+
+    /// The signature stored for the block is not a valid signature of it.
+    InvalidSignature(BlockIndex),
+
+    /// The number of TxOuts in the ledger differs from the number of TxOuts in its blocks.
+    TxOutCountMismatch {
+        /// The number of TxOuts in the blocks.
+        expected: u64,
+        /// The number of TxOuts in the ledger.
+        actual: u64,
+    },
+
+    /// The stored root hash of the TxOut Merkle tree differs from the one computed from the
+    /// TxOuts, so membership proofs would be wrong.
+    MerkleRootMismatch,
+}
+
+/// What `LedgerDB::verify_integrity` checked, and the problems it found.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntegrityReport {
+    /// Number of blocks checked.
+    pub num_blocks: u64,
+
+    /// Number of checked blocks whose contents were pruned, which are only checked partially.
+    pub num_pruned_blocks: u64,
+
+    /// Number of TxOuts checked.
+    pub num_tx_outs: u64,
+
+    /// The problems found, in increasing order of block index.
+    pub problems: Vec<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    /// Whether no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Metrics about the storage of a ledger, see `LedgerDB::metrics`.
+
+use lmdb::Environment;
+use metrics::{Histogram, IntCounter, IntGauge, OpMetrics};
+
+/// Counters, gauges and histograms of a `LedgerDB`, shared by its clones.
+#[derive(Clone)]
+pub struct LedgerMetrics {
+    /// The collector holding all of the metrics below, which can be registered with Prometheus.
+    pub op_metrics: OpMetrics,
+
+    /// Time it takes to append blocks, for each write transaction.
+    pub append_blocks_time: Histogram,
+
+    /// Blocks appended since the ledger was opened.
+    pub blocks_appended_count: IntCounter,
+
+    /// Time it takes to read a block, its contents or signature, a TxOut, a proof of membership
+    /// or a key image.
+    pub read_time: Histogram,
+
+    /// Size of the data in the LMDB environment, in bytes.
+    pub db_size: IntGauge,
+
+    /// Number of LMDB reader slots used, by any process sharing the ledger.
+    pub num_readers: IntGauge,
+}
+
+impl LedgerMetrics {
+    /// Creates metrics that are not registered with Prometheus.
+    pub fn new() -> Self {
+        let op_metrics = OpMetrics::new("ledger_db");
+        Self {
+            append_blocks_time: op_metrics.histogram("append_blocks"),
+            blocks_appended_count: op_metrics.counter("blocks_appended_count"),
+            read_time: op_metrics.histogram("read"),
+            db_size: op_metrics.gauge("db_size"),
+            num_readers: op_metrics.gauge("num_readers"),
+            op_metrics,
+        }
+    }
+
+    /// Updates the gauges that are read from the LMDB environment.
+    pub(crate) fn update_env_gauges(&self, env: &Environment) -> Result<(), lmdb::Error> {
+        // The lmdb crate does not wrap `mdb_env_info`.
+        // Safe since `env` is open for as long as it is borrowed, and both calls only write to
+        // the given structs.
+        let (stat, info) = unsafe {
+            let mut stat: lmdb_sys::MDB_stat = std::mem::zeroed();
+            lmdb_result(lmdb_sys::mdb_env_stat(env.env(), &mut stat))?;
+            let mut info: lmdb_sys::MDB_envinfo = std::mem::zeroed();
+            lmdb_result(lmdb_sys::mdb_env_info(env.env(), &mut info))?;
+            (stat, info)
+        };
+
+        // Pages are numbered from 0.
+        let num_pages = info.me_last_pgno as i64 + 1;
+        self.db_size.set(num_pages * stat.ms_psize as i64);
+        self.num_readers.set(info.me_numreaders as i64);
+        Ok(())
+    }
+}
+
+impl Default for LedgerMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lmdb_result(err_code: std::os::raw::c_int) -> Result<(), lmdb::Error> {
+    if err_code == lmdb_sys::MDB_SUCCESS {
+        Ok(())
+    } else {
+        Err(lmdb::Error::from_err_code(err_code))
+    }
+}
@@ -2,6 +2,8 @@
 
 use crate::Error;
 use common::Hash;
+use keys::CompressedRistrettoPublic;
+use std::ops::Range;
 use transaction::{
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
@@ -17,6 +19,19 @@ pub trait Ledger: Clone + Send {
         signature: Option<&BlockSignature>,
     ) -> Result<(), Error>;
 
+    /// Appends consecutive blocks along with their transactions, stopping at the first block that
+    /// cannot be appended. Ledgers may append all blocks at once, in which case none is appended
+    /// if one cannot be.
+    fn append_blocks(
+        &mut self,
+        blocks: &[(Block, BlockContents, Option<BlockSignature>)],
+    ) -> Result<(), Error> {
+        for (block, block_contents, signature) in blocks {
+            self.append_block(block, block_contents, signature.as_ref())?;
+        }
+        Ok(())
+    }
+
     /// Get the total number of blocks in the ledger.
     fn num_blocks(&self) -> Result<u64, Error>;
 
@@ -35,9 +50,44 @@ pub trait Ledger: Clone + Send {
     /// Returns the index of the TxOut with the given hash.
     fn get_tx_out_index_by_hash(&self, tx_out_hash: &Hash) -> Result<u64, Error>;
 
+    /// Returns the index of the TxOut with the given public key.
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error>;
+
     /// Gets a TxOut by its index in the ledger.
     fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error>;
 
+    /// Returns the number of the block that contains the TxOut with the given index.
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<u64, Error>;
+
+    /// Returns the number of the block that contains the TxOut with the given public key.
+    fn get_block_index_by_tx_out_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        let tx_out_index = self.get_tx_out_index_by_public_key(tx_out_public_key)?;
+        self.get_block_index_by_tx_out_index(tx_out_index)
+    }
+
+    /// Gets the TxOuts of the blocks with numbers in `block_range`, in the order they were
+    /// appended in. Returns `IndexOutOfBounds` if a block is not in the ledger.
+    fn get_tx_outs_by_block_range(&self, block_range: Range<u64>) -> Result<Vec<TxOut>, Error> {
+        if block_range.start >= block_range.end {
+            return Ok(Vec::new());
+        }
+        if block_range.end > self.num_blocks()? {
+            return Err(Error::IndexOutOfBounds(block_range.end - 1));
+        }
+
+        let mut tx_outs = Vec::new();
+        for block_number in block_range {
+            tx_outs.extend(self.get_block_contents(block_number)?.outputs);
+        }
+        Ok(tx_outs)
+    }
+
     /// Gets a proof of memberships for TxOuts with indexes `indexes`.
     fn get_tx_out_proof_of_memberships(
         &self,
@@ -54,6 +104,14 @@ pub trait Ledger: Clone + Send {
     /// Ok(None) is returned when the key image is not in the ledger.
     fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<u64>, Error>;
 
+    /// Gets the block in which the given key image was spent.
+    fn get_block_by_key_image(&self, key_image: &KeyImage) -> Result<Block, Error> {
+        match self.check_key_image(key_image)? {
+            Some(block_number) => self.get_block(block_number),
+            None => Err(Error::NotFound),
+        }
+    }
+
     /// Gets the key images used by transactions in a single block.
     fn get_key_images_by_block(&self, block_number: u64) -> Result<Vec<KeyImage>, Error>;
 }
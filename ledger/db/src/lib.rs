@@ -8,23 +8,36 @@
 extern crate test;
 
 use core::convert::TryInto;
+use keys::CompressedRistrettoPublic;
 use lmdb::{
     Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
     Transaction, WriteFlags,
 };
 use mcserial::{deserialize, serialize};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use transaction::{Block, BlockContents, BlockID, BlockSignature, BLOCK_VERSION};
 
 mod error;
+mod in_memory_ledger;
+mod integrity;
+mod ledger_metrics;
 mod ledger_trait;
+pub mod snapshot;
 pub mod tx_out_store;
 
 #[cfg(any(test, feature = "test_utils"))]
 pub mod test_utils;
 
 pub use error::Error;
+pub use in_memory_ledger::InMemoryLedger;
+pub use integrity::{IntegrityProblem, IntegrityReport};
+pub use ledger_metrics::LedgerMetrics;
 pub use ledger_trait::Ledger;
+use snapshot::{SnapshotReader, SnapshotWriter};
 use transaction::{
     ring_signature::KeyImage,
     tx::{TxOut, TxOutMembershipProof},
@@ -40,9 +53,11 @@ pub const BLOCK_CONTENTS_DB_NAME: &str = "ledger_db:block_contents";
 pub const BLOCK_SIGNATURES_DB_NAME: &str = "ledger_db:block_signatures";
 pub const KEY_IMAGES_DB_NAME: &str = "ledger_db:key_images";
 pub const KEY_IMAGES_BY_BLOCK_DB_NAME: &str = "ledger_db:key_images_by_block";
+pub const BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME: &str = "ledger_db:block_number_by_tx_out_index";
 
 // Keys used by the `counts` database.
 const NUM_BLOCKS_KEY: &str = "num_blocks";
+const NUM_PRUNED_BLOCKS_KEY: &str = "num_pruned_blocks";
 
 #[derive(Clone)]
 pub struct LedgerDB {
@@ -50,6 +65,8 @@ pub struct LedgerDB {
 
     /// Aggregate counts about the ledger.
     /// * `NUM_BLOCKS_KEY` --> number of blocks in the ledger.
+    /// * `NUM_PRUNED_BLOCKS_KEY` --> number of blocks whose contents were discarded by `prune`.
+    ///   Ledgers that were never pruned have no entry.
     counts: Database,
 
     /// Blocks by block number. `block number -> Block`
@@ -67,11 +84,20 @@ pub struct LedgerDB {
     /// Key Images by Block
     key_images_by_block: Database,
 
+    /// Block number of each TxOut. `TxOut index -> block number`
+    block_number_by_tx_out_index: Database,
+
     /// Storage abstraction for TxOuts.
     tx_out_store: TxOutStore,
 
     /// Location on filesystem.
     path: PathBuf,
+
+    /// Whether the ledger was opened with `open_read_only`.
+    read_only: bool,
+
+    /// Metrics about the storage of the ledger.
+    metrics: LedgerMetrics,
 }
 
 /// LedgerDB is an append-only log (or chain) of blocks of transactions.
@@ -88,19 +114,42 @@ impl Ledger for LedgerDB {
         block_contents: &BlockContents,
         signature: Option<&BlockSignature>,
     ) -> Result<(), Error> {
-        // Note: This function must update every LMDB database managed by LedgerDB.
-        let mut db_transaction = self.env.begin_rw_txn()?;
-
-        self.validate_append_block(block, block_contents)?;
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
 
-        self.write_key_images(block.index, &block_contents.key_images, &mut db_transaction)?;
+        let _timer = self.metrics.append_blocks_time.start_timer();
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        self.append_block_in_txn(block, block_contents, signature, &mut db_transaction)?;
+        db_transaction.commit()?;
+        self.metrics.blocks_appended_count.inc();
+        Ok(())
+    }
 
-        for tx_out in &block_contents.outputs {
-            self.tx_out_store.push(tx_out, &mut db_transaction)?;
+    /// Appends consecutive blocks in a single write transaction, which is much faster than
+    /// appending them one at a time. None is appended if one cannot be.
+    fn append_blocks(
+        &mut self,
+        blocks: &[(Block, BlockContents, Option<BlockSignature>)],
+    ) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
         }
 
-        self.write_block(block, block_contents, signature, &mut db_transaction)?;
+        let _timer = self.metrics.append_blocks_time.start_timer();
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        for (block, block_contents, signature) in blocks {
+            self.append_block_in_txn(
+                block,
+                block_contents,
+                signature.as_ref(),
+                &mut db_transaction,
+            )?;
+        }
         db_transaction.commit()?;
+        self.metrics
+            .blocks_appended_count
+            .inc_by(blocks.len() as i64);
         Ok(())
     }
 
@@ -120,6 +169,7 @@ impl Ledger for LedgerDB {
 
     /// Gets a Block by its index in the blockchain.
     fn get_block(&self, block_number: u64) -> Result<Block, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
         let key = u64_to_key_bytes(block_number);
         let block_bytes = db_transaction.get(self.blocks, &key)?;
@@ -129,7 +179,11 @@ impl Ledger for LedgerDB {
 
     /// Get the contents of a block.
     fn get_block_contents(&self, block_number: u64) -> Result<BlockContents, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
+        if block_number < self.get_num_pruned_blocks(&db_transaction)? {
+            return Err(Error::BlockPruned(block_number));
+        }
         let key = u64_to_key_bytes(block_number);
         let bytes = db_transaction.get(self.block_contents, &key)?;
         let block_contents = deserialize(&bytes)?;
@@ -138,6 +192,7 @@ impl Ledger for LedgerDB {
 
     /// Gets a block signature by its index in the blockchain.
     fn get_block_signature(&self, block_number: u64) -> Result<BlockSignature, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
         let key = u64_to_key_bytes(block_number);
         let signature_bytes = db_transaction.get(self.block_signatures, &key)?;
@@ -152,15 +207,61 @@ impl Ledger for LedgerDB {
             .get_tx_out_index_by_hash(tx_out_hash, &db_transaction)
     }
 
+    /// Returns the index of the TxOut with the given public key.
+    fn get_tx_out_index_by_public_key(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.tx_out_store
+            .get_tx_out_index_by_public_key(tx_out_public_key, &db_transaction)
+    }
+
     /// Gets a TxOut by its index in the ledger.
     fn get_tx_out_by_index(&self, index: u64) -> Result<TxOut, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
         self.tx_out_store
             .get_tx_out_by_index(index, &db_transaction)
     }
 
+    /// Returns the number of the block that contains the TxOut with the given index.
+    fn get_block_index_by_tx_out_index(&self, tx_out_index: u64) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        let block_number_bytes = db_transaction.get(
+            self.block_number_by_tx_out_index,
+            &u64_to_key_bytes(tx_out_index),
+        )?;
+        Ok(key_bytes_to_u64(block_number_bytes))
+    }
+
+    /// Gets the TxOuts of the blocks with numbers in `block_range`, including pruned blocks.
+    fn get_tx_outs_by_block_range(&self, block_range: Range<u64>) -> Result<Vec<TxOut>, Error> {
+        let _timer = self.metrics.read_time.start_timer();
+        if block_range.start >= block_range.end {
+            return Ok(Vec::new());
+        }
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        if block_range.end > num_blocks {
+            return Err(Error::IndexOutOfBounds(block_range.end - 1));
+        }
+
+        let first_tx_out_index =
+            self.get_first_tx_out_index_of_block(block_range.start, &db_transaction)?;
+        let end_tx_out_index =
+            self.get_first_tx_out_index_of_block(block_range.end, &db_transaction)?;
+        (first_tx_out_index..end_tx_out_index)
+            .map(|index| {
+                self.tx_out_store
+                    .get_tx_out_by_index(index, &db_transaction)
+            })
+            .collect()
+    }
+
     /// Returns true if the Ledger contains the given KeyImage.
     fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<u64>, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
         match db_transaction.get(self.key_images, &key_image) {
             Ok(db_bytes) => {
@@ -188,6 +289,7 @@ impl Ledger for LedgerDB {
         &self,
         indexes: &[u64],
     ) -> Result<Vec<TxOutMembershipProof>, Error> {
+        let _timer = self.metrics.read_time.start_timer();
         let db_transaction = self.env.begin_ro_txn()?;
         indexes
             .iter()
@@ -202,11 +304,28 @@ impl Ledger for LedgerDB {
 impl LedgerDB {
     /// Opens an existing Ledger Database in the given path.
     pub fn open(path: PathBuf) -> Result<LedgerDB, Error> {
+        Self::open_with_mode(path, false)
+    }
+
+    /// Opens an existing Ledger Database in the given path without write access, so that other
+    /// processes can read a ledger while a single one appends to it. Methods modifying the ledger
+    /// fail with `Error::ReadOnly`. Ledgers created by earlier releases need to be opened with
+    /// `open` once, so that they are indexed, or this fails with `Error::ReadOnly` as well.
+    pub fn open_read_only(path: PathBuf) -> Result<LedgerDB, Error> {
+        Self::open_with_mode(path, true)
+    }
+
+    fn open_with_mode(path: PathBuf, read_only: bool) -> Result<LedgerDB, Error> {
+        let flags = if read_only {
+            EnvironmentFlags::READ_ONLY
+        } else {
+            // TODO - needed because currently our test cloud machines have slow disks.
+            EnvironmentFlags::NO_SYNC
+        };
         let env = Environment::new()
             .set_max_dbs(20)
             .set_map_size(MAX_LMDB_FILE_SIZE)
-            // TODO - needed because currently our test cloud machines have slow disks.
-            .set_flags(EnvironmentFlags::NO_SYNC)
+            .set_flags(flags)
             .open(&path)?;
 
         let counts = env.open_db(Some(COUNTS_DB_NAME))?;
@@ -215,10 +334,23 @@ impl LedgerDB {
         let block_signatures = env.open_db(Some(BLOCK_SIGNATURES_DB_NAME))?;
         let key_images = env.open_db(Some(KEY_IMAGES_DB_NAME))?;
         let key_images_by_block = env.open_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME))?;
+        let (block_number_by_tx_out_index, needs_indexing) =
+            match env.open_db(Some(BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME)) {
+                Ok(db) => (db, false),
+                Err(lmdb::Error::NotFound) if read_only => return Err(Error::ReadOnly),
+                Err(lmdb::Error::NotFound) => (
+                    env.create_db(
+                        Some(BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME),
+                        DatabaseFlags::empty(),
+                    )?,
+                    true,
+                ),
+                Err(err) => return Err(err.into()),
+            };
 
-        let tx_out_store = TxOutStore::new(&env)?;
+        let tx_out_store = TxOutStore::new(&env, read_only)?;
 
-        Ok(LedgerDB {
+        let ledger_db = LedgerDB {
             env: Arc::new(env),
             path,
             counts,
@@ -227,8 +359,18 @@ impl LedgerDB {
             block_signatures,
             key_images,
             key_images_by_block,
+            block_number_by_tx_out_index,
             tx_out_store,
-        })
+            read_only,
+            metrics: LedgerMetrics::new(),
+        };
+
+        // Ledgers created before TxOuts were indexed by block are indexed when first opened.
+        if needs_indexing {
+            ledger_db.index_tx_out_block_numbers()?;
+        }
+
+        Ok(ledger_db)
     }
 
     /// Creates a fresh Ledger Database in the given path.
@@ -250,6 +392,10 @@ impl LedgerDB {
         env.create_db(Some(BLOCK_SIGNATURES_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(KEY_IMAGES_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(KEY_IMAGES_BY_BLOCK_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(BLOCK_NUMBER_BY_TX_OUT_INDEX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
 
         TxOutStore::create(&env)?;
 
@@ -266,6 +412,434 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Removes all blocks from index `num_blocks` onwards, along with their contents, signatures,
+    /// key images and TxOuts, so that they can be appended again. The origin block is never
+    /// removed.
+    pub fn truncate(&mut self, num_blocks: u64) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let num_blocks_before = self.num_blocks()?;
+        if num_blocks == 0 || num_blocks > num_blocks_before {
+            return Err(Error::IndexOutOfBounds(num_blocks));
+        }
+
+        // Note: This function must update every LMDB database managed by LedgerDB.
+        let mut db_transaction = self.env.begin_rw_txn()?;
+
+        // The contents of the removed blocks are needed to find their key images and TxOuts.
+        if num_blocks < self.get_num_pruned_blocks(&db_transaction)? {
+            return Err(Error::BlockPruned(num_blocks));
+        }
+
+        let num_tx_outs_before = self.tx_out_store.num_tx_outs(&db_transaction)?;
+        let mut num_tx_outs = num_tx_outs_before;
+        for block_index in (num_blocks..num_blocks_before).rev() {
+            let key = u64_to_key_bytes(block_index);
+
+            let block_contents: BlockContents =
+                deserialize(db_transaction.get(self.block_contents, &key)?)?;
+            for key_image in &block_contents.key_images {
+                db_transaction.del(self.key_images, &key_image, None)?;
+            }
+            num_tx_outs -= block_contents.outputs.len() as u64;
+
+            db_transaction.del(self.key_images_by_block, &key, None)?;
+            db_transaction.del(self.block_contents, &key, None)?;
+            db_transaction.del(self.blocks, &key, None)?;
+            match db_transaction.del(self.block_signatures, &key, None) {
+                Ok(()) | Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        for tx_out_index in num_tx_outs..num_tx_outs_before {
+            db_transaction.del(
+                self.block_number_by_tx_out_index,
+                &u64_to_key_bytes(tx_out_index),
+                None,
+            )?;
+        }
+        self.tx_out_store
+            .truncate(num_tx_outs, &mut db_transaction)?;
+
+        db_transaction.put(
+            self.counts,
+            &NUM_BLOCKS_KEY,
+            &u64_to_key_bytes(num_blocks),
+            WriteFlags::empty(),
+        )?;
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    /// Discards the contents of the blocks before index `num_blocks`, to save space. Their headers,
+    /// signatures, key images and TxOuts are kept, so that blocks can still be validated and
+    /// appended, key images checked and membership proofs of any TxOut computed, but
+    /// `get_block_contents` fails with `BlockPruned` for them. Blocks that were already pruned
+    /// are skipped.
+    pub fn prune(&mut self, num_blocks: u64) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        if num_blocks > key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?) {
+            return Err(Error::IndexOutOfBounds(num_blocks));
+        }
+
+        let num_pruned_blocks = self.get_num_pruned_blocks(&db_transaction)?;
+        if num_blocks <= num_pruned_blocks {
+            return Ok(());
+        }
+        for block_index in num_pruned_blocks..num_blocks {
+            db_transaction.del(self.block_contents, &u64_to_key_bytes(block_index), None)?;
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_PRUNED_BLOCKS_KEY,
+            &u64_to_key_bytes(num_blocks),
+            WriteFlags::empty(),
+        )?;
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
+    /// The number of blocks, from the origin block onwards, whose contents were discarded by
+    /// `prune`.
+    pub fn num_pruned_blocks(&self) -> Result<u64, Error> {
+        let db_transaction = self.env.begin_ro_txn()?;
+        self.get_num_pruned_blocks(&db_transaction)
+    }
+
+    fn get_num_pruned_blocks(&self, db_transaction: &impl Transaction) -> Result<u64, Error> {
+        match db_transaction.get(self.counts, &NUM_PRUNED_BLOCKS_KEY) {
+            Ok(bytes) => Ok(key_bytes_to_u64(bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the blocks up to and including `up_to_block`, along with their contents and
+    /// signatures, to a snapshot file at `path`, see `snapshot`. The blocks must not be pruned.
+    pub fn export_snapshot(&self, path: &Path, up_to_block: u64) -> Result<(), Error> {
+        // Blocks are read in a single transaction, so that they are consistent.
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        if up_to_block >= num_blocks {
+            return Err(Error::IndexOutOfBounds(up_to_block));
+        }
+        // Snapshots start from the origin block.
+        if self.get_num_pruned_blocks(&db_transaction)? > 0 {
+            return Err(Error::BlockPruned(0));
+        }
+
+        let mut writer = SnapshotWriter::create(path, up_to_block + 1)?;
+        for block_index in 0..=up_to_block {
+            let key = u64_to_key_bytes(block_index);
+            let block: Block = deserialize(db_transaction.get(self.blocks, &key)?)?;
+            let block_contents: BlockContents =
+                deserialize(db_transaction.get(self.block_contents, &key)?)?;
+            let signature: Option<BlockSignature> =
+                match db_transaction.get(self.block_signatures, &key) {
+                    Ok(bytes) => Some(deserialize(bytes)?),
+                    Err(lmdb::Error::NotFound) => None,
+                    Err(err) => return Err(err.into()),
+                };
+            writer.write_block(&(block, block_contents, signature))?;
+        }
+        writer.finish()
+    }
+
+    /// Appends the blocks of a snapshot file at `path` to the ledger, validating them like any
+    /// appended block. Blocks the ledger already has are skipped, as long as they match the
+    /// snapshot. Nothing is appended if the snapshot is corrupted. Returns the number of blocks
+    /// in the snapshot.
+    pub fn import_snapshot(&mut self, path: &Path) -> Result<u64, Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly);
+        }
+        let reader = SnapshotReader::open(path)?;
+        let num_snapshot_blocks = reader.num_blocks();
+
+        let num_blocks = self.num_blocks()?;
+        for snapshot_block in reader {
+            let (block, block_contents, signature) = snapshot_block?;
+            if block.index < num_blocks {
+                if self.get_block(block.index)? != block {
+                    return Err(Error::InvalidBlock);
+                }
+                continue;
+            }
+            self.append_block(&block, &block_contents, signature.as_ref())?;
+        }
+
+        Ok(num_snapshot_blocks)
+    }
+
+    /// Metrics about the storage of the ledger, shared by the clones of this `LedgerDB`, with the
+    /// size of the ledger and its number of readers read from LMDB.
+    pub fn metrics(&self) -> Result<&LedgerMetrics, Error> {
+        self.metrics.update_env_gauges(&self.env)?;
+        Ok(&self.metrics)
+    }
+
+    /// Checks the ledger for corruption. The ID of every block is recomputed and checked to link to
+    /// the previous block, the contents of blocks that were not pruned are checked against the
+    /// blocks, the key images and TxOuts of every block against the indexes, and the stored
+    /// signatures against the blocks. The number of TxOuts and the root of the TxOut Merkle tree
+    /// are recomputed as well. Returns the problems found, or an error if the ledger could not be
+    /// read.
+    pub fn verify_integrity(&self) -> Result<IntegrityReport, Error> {
+        // Everything is read in a single transaction, so that appended blocks are not seen midway.
+        let db_transaction = self.env.begin_ro_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        let num_pruned_blocks = self.get_num_pruned_blocks(&db_transaction)?;
+        let num_tx_outs = self.tx_out_store.num_tx_outs(&db_transaction)?;
+
+        let mut problems = Vec::new();
+        let mut parent_id = BlockID::default();
+        let mut num_block_tx_outs = 0;
+        for block_index in 0..num_blocks {
+            let key = u64_to_key_bytes(block_index);
+            let block: Block = deserialize(db_transaction.get(self.blocks, &key)?)?;
+            if block.index != block_index {
+                problems.push(IntegrityProblem::BlockIndexMismatch(block_index));
+            }
+            if !block.is_block_id_valid() {
+                problems.push(IntegrityProblem::InvalidBlockID(block_index));
+            }
+            if block.parent_id != parent_id {
+                problems.push(IntegrityProblem::ParentMismatch(block_index));
+            }
+            parent_id = block.id.clone();
+
+            // The key images and TxOuts of pruned blocks are only checked against the indexes.
+            let block_contents: Option<BlockContents> = if block_index < num_pruned_blocks {
+                None
+            } else {
+                let block_contents: BlockContents =
+                    deserialize(db_transaction.get(self.block_contents, &key)?)?;
+                if block_contents.hash() != block.contents_hash {
+                    problems.push(IntegrityProblem::ContentsHashMismatch(block_index));
+                }
+                Some(block_contents)
+            };
+
+            if !self.are_key_images_consistent(
+                block_index,
+                block_contents.as_ref(),
+                &db_transaction,
+            )? {
+                problems.push(IntegrityProblem::KeyImageMismatch(block_index));
+            }
+
+            let (tx_outs_consistent, num_tx_outs_in_block) = self.check_block_tx_outs(
+                block_index,
+                num_block_tx_outs,
+                block_contents.as_ref(),
+                &db_transaction,
+            )?;
+            if !tx_outs_consistent {
+                problems.push(IntegrityProblem::TxOutMismatch(block_index));
+            }
+            num_block_tx_outs += num_tx_outs_in_block;
+
+            match db_transaction.get(self.block_signatures, &key) {
+                Ok(bytes) => {
+                    let signature: BlockSignature = deserialize(bytes)?;
+                    if signature.verify(&block).is_err() {
+                        problems.push(IntegrityProblem::InvalidSignature(block_index));
+                    }
+                }
+                Err(lmdb::Error::NotFound) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if num_block_tx_outs != num_tx_outs {
+            problems.push(IntegrityProblem::TxOutCountMismatch {
+                expected: num_block_tx_outs,
+                actual: num_tx_outs,
+            });
+        } else {
+            // All TxOuts were found, so the root can be computed.
+            let root_hash = self
+                .tx_out_store
+                .compute_root_merkle_hash(&db_transaction)?;
+            match self.tx_out_store.get_root_merkle_hash(&db_transaction) {
+                Ok(stored_root_hash) if stored_root_hash == root_hash => {}
+                Ok(_) | Err(Error::NotFound) => problems.push(IntegrityProblem::MerkleRootMismatch),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(IntegrityReport {
+            num_blocks,
+            num_pruned_blocks: num_pruned_blocks.min(num_blocks),
+            num_tx_outs,
+            problems,
+        })
+    }
+
+    /// Checks that the key images of a block are recorded, match its contents if they were not
+    /// pruned, and are spent in the block.
+    fn are_key_images_consistent(
+        &self,
+        block_index: u64,
+        block_contents: Option<&BlockContents>,
+        db_transaction: &impl Transaction,
+    ) -> Result<bool, Error> {
+        let key_images: Vec<KeyImage> =
+            match db_transaction.get(self.key_images_by_block, &u64_to_key_bytes(block_index)) {
+                Ok(bytes) => deserialize(bytes)?,
+                Err(lmdb::Error::NotFound) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            };
+        if let Some(block_contents) = block_contents {
+            if block_contents.key_images != key_images {
+                return Ok(false);
+            }
+        }
+
+        for key_image in &key_images {
+            match db_transaction.get(self.key_images, key_image) {
+                Ok(bytes) if bytes == &block_index.to_le_bytes()[..] => {}
+                Ok(_) | Err(lmdb::Error::NotFound) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Checks that the TxOuts of a block, from index `first_tx_out_index` on, are recorded as
+    /// being in the block, match its contents if they were not pruned, and are indexed. The TxOuts
+    /// of a pruned block are the ones recorded as being in it. Returns whether they are consistent,
+    /// and the number of TxOuts in the block.
+    fn check_block_tx_outs(
+        &self,
+        block_index: u64,
+        first_tx_out_index: u64,
+        block_contents: Option<&BlockContents>,
+        db_transaction: &impl Transaction,
+    ) -> Result<(bool, u64), Error> {
+        let mut consistent = true;
+        let mut tx_out_index = first_tx_out_index;
+        loop {
+            let expected_tx_out = match block_contents {
+                Some(block_contents) => {
+                    match block_contents
+                        .outputs
+                        .get((tx_out_index - first_tx_out_index) as usize)
+                    {
+                        Some(tx_out) => Some(tx_out),
+                        None => break,
+                    }
+                }
+                None => None,
+            };
+
+            let tx_out_block_index = match db_transaction.get(
+                self.block_number_by_tx_out_index,
+                &u64_to_key_bytes(tx_out_index),
+            ) {
+                Ok(bytes) => Some(key_bytes_to_u64(bytes)),
+                Err(lmdb::Error::NotFound) => None,
+                Err(err) => return Err(err.into()),
+            };
+            if tx_out_block_index != Some(block_index) {
+                if block_contents.is_none() {
+                    // The first TxOut of the next block.
+                    break;
+                }
+                consistent = false;
+            }
+
+            match self
+                .tx_out_store
+                .get_tx_out_by_index(tx_out_index, db_transaction)
+            {
+                Ok(tx_out) => {
+                    if let Some(expected_tx_out) = expected_tx_out {
+                        if *expected_tx_out != tx_out {
+                            consistent = false;
+                        }
+                    }
+                    let index_by_hash = self
+                        .tx_out_store
+                        .get_tx_out_index_by_hash(&tx_out.hash(), db_transaction);
+                    let index_by_public_key = self
+                        .tx_out_store
+                        .get_tx_out_index_by_public_key(&tx_out.public_key, db_transaction);
+                    for index in [index_by_hash, index_by_public_key].iter() {
+                        match index {
+                            Ok(index) if *index == tx_out_index => {}
+                            Ok(_) | Err(Error::NotFound) => consistent = false,
+                            Err(err) => return Err(*err),
+                        }
+                    }
+                }
+                Err(Error::NotFound) => consistent = false,
+                Err(err) => return Err(err),
+            }
+
+            tx_out_index += 1;
+        }
+
+        Ok((consistent, tx_out_index - first_tx_out_index))
+    }
+
+    /// Returns the index of the first TxOut of a block, or of the TxOut after the last one if the
+    /// block is not in the ledger. TxOuts are in increasing order of block number, so the index is
+    /// found by binary search.
+    fn get_first_tx_out_index_of_block(
+        &self,
+        block_number: u64,
+        db_transaction: &impl Transaction,
+    ) -> Result<u64, Error> {
+        let mut low = 0;
+        let mut high = self.tx_out_store.num_tx_outs(db_transaction)?;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let middle_block_number = key_bytes_to_u64(
+                db_transaction.get(self.block_number_by_tx_out_index, &u64_to_key_bytes(middle))?,
+            );
+            if middle_block_number < block_number {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Records the block number of every TxOut in the ledger.
+    fn index_tx_out_block_numbers(&self) -> Result<(), Error> {
+        let mut db_transaction = self.env.begin_rw_txn()?;
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+
+        let mut tx_out_index = 0;
+        for block_index in 0..num_blocks {
+            let block_contents: BlockContents = deserialize(
+                db_transaction.get(self.block_contents, &u64_to_key_bytes(block_index))?,
+            )?;
+            for _ in &block_contents.outputs {
+                db_transaction.put(
+                    self.block_number_by_tx_out_index,
+                    &u64_to_key_bytes(tx_out_index),
+                    &u64_to_key_bytes(block_index),
+                    WriteFlags::empty(),
+                )?;
+                tx_out_index += 1;
+            }
+        }
+
+        db_transaction.commit()?;
+        Ok(())
+    }
+
     /// Write a `Block`.
     fn write_block(
         &self,
@@ -314,6 +888,57 @@ impl LedgerDB {
         Ok(())
     }
 
+    /// Validates a block against the blocks in `db_transaction`, which include the blocks it
+    /// appended, and appends it.
+    fn append_block_in_txn(
+        &self,
+        block: &Block,
+        block_contents: &BlockContents,
+        signature: Option<&BlockSignature>,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        // Note: This function must update every LMDB database managed by LedgerDB.
+        let num_blocks = key_bytes_to_u64(&db_transaction.get(self.counts, &NUM_BLOCKS_KEY)?);
+        let last_block: Option<Block> = if num_blocks == 0 {
+            None
+        } else {
+            Some(deserialize(
+                db_transaction.get(self.blocks, &u64_to_key_bytes(num_blocks - 1))?,
+            )?)
+        };
+        validate_block(block, block_contents, last_block.as_ref(), |key_image| {
+            self.is_key_image_in_txn(key_image, &*db_transaction)
+        })?;
+
+        self.write_key_images(block.index, &block_contents.key_images, db_transaction)?;
+
+        for tx_out in &block_contents.outputs {
+            let index = self.tx_out_store.push(tx_out, db_transaction)?;
+            db_transaction.put(
+                self.block_number_by_tx_out_index,
+                &u64_to_key_bytes(index),
+                &u64_to_key_bytes(block.index),
+                WriteFlags::empty(),
+            )?;
+        }
+
+        self.write_block(block, block_contents, signature, db_transaction)?;
+        Ok(())
+    }
+
+    /// Whether a key image is spent, according to `db_transaction`.
+    fn is_key_image_in_txn(
+        &self,
+        key_image: &KeyImage,
+        db_transaction: &impl Transaction,
+    ) -> Result<bool, Error> {
+        match db_transaction.get(self.key_images, key_image) {
+            Ok(_) => Ok(true),
+            Err(lmdb::Error::NotFound) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     fn write_key_images(
         &self,
         block_index: u64,
@@ -322,7 +947,7 @@ impl LedgerDB {
     ) -> Result<(), Error> {
         // Update Key Images
         for key_image in key_images {
-            if self.contains_key_image(key_image)? {
+            if self.is_key_image_in_txn(key_image, &*db_transaction)? {
                 return Err(Error::KeyImageAlreadySpent);
             }
             db_transaction.put(
@@ -340,65 +965,88 @@ impl LedgerDB {
         )?;
         Ok(())
     }
+}
 
-    /// Checks if a block can be appended to the db.
-    fn validate_append_block(
-        &self,
-        block: &Block,
-        block_contents: &BlockContents,
-    ) -> Result<(), Error> {
-        // Check that version is correct
-        if block.version != BLOCK_VERSION {
-            return Err(Error::InvalidBlock);
-        }
+/// Checks if a block can be appended to a ledger.
+fn validate_append_block<L: Ledger>(
+    ledger: &L,
+    block: &Block,
+    block_contents: &BlockContents,
+) -> Result<(), Error> {
+    let num_blocks = ledger.num_blocks()?;
+    let last_block = if num_blocks == 0 {
+        None
+    } else {
+        Some(ledger.get_block(num_blocks - 1)?)
+    };
+    validate_block(block, block_contents, last_block.as_ref(), |key_image| {
+        ledger.contains_key_image(key_image)
+    })
+}
 
-        // A block must have outputs.
-        if block_contents.outputs.is_empty() {
-            // TODO: better error type.
-            return Err(Error::InvalidBlock);
-        }
+/// Checks if a block can be appended after `last_block`, the last block of a ledger, which is
+/// None if the ledger is empty.
+///
+/// # Arguments
+/// * `is_spent` - Whether a key image is spent in the ledger.
+fn validate_block(
+    block: &Block,
+    block_contents: &BlockContents,
+    last_block: Option<&Block>,
+    is_spent: impl Fn(&KeyImage) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    // Check that version is correct. Blocks formed before the latest version remain valid.
+    if block.version > BLOCK_VERSION {
+        return Err(Error::InvalidBlock);
+    }
 
-        // TODO: enable this.
-        // // Non-origin blocks must have key images.
-        // if block.index == 0 && block_contents.key_images.is_empty() {
-        //     return Err(Error::InvalidBlock);
-        // }
+    // A block must have outputs.
+    if block_contents.outputs.is_empty() {
+        // TODO: better error type.
+        return Err(Error::InvalidBlock);
+    }
 
-        // Check if block is being appended at the correct place.
-        let num_blocks = self.num_blocks()?;
-        if num_blocks == 0 {
+    // TODO: enable this.
+    // // Non-origin blocks must have key images.
+    // if block.index == 0 && block_contents.key_images.is_empty() {
+    //     return Err(Error::InvalidBlock);
+    // }
+
+    // Check if block is being appended at the correct place.
+    match last_block {
+        None => {
             // This must be an origin block.
             if block.index != 0 || block.parent_id != BlockID::default() {
                 return Err(Error::InvalidBlock);
             }
-        } else {
+        }
+        Some(last_block) => {
             // The block must have the correct index and parent.
-            let last_block = self.get_block(num_blocks - 1)?;
-            if block.index != num_blocks || block.parent_id != last_block.id {
+            if block.index != last_block.index + 1 || block.parent_id != last_block.id {
                 return Err(Error::InvalidBlock);
             }
         }
+    }
 
-        // Check that the block contents match the hash.
-        if block.contents_hash != block_contents.hash() {
-            return Err(Error::InvalidBlockContents);
-        }
-
-        // Check that none of the key images were previously spent.
-        for key_image in &block_contents.key_images {
-            if self.contains_key_image(key_image)? {
-                return Err(Error::KeyImageAlreadySpent);
-            }
-        }
+    // Check that the block contents match the hash.
+    if block.contents_hash != block_contents.hash() {
+        return Err(Error::InvalidBlockContents);
+    }
 
-        // Validate block id.
-        if !block.is_block_id_valid() {
-            return Err(Error::InvalidBlockID);
+    // Check that none of the key images were previously spent.
+    for key_image in &block_contents.key_images {
+        if is_spent(key_image)? {
+            return Err(Error::KeyImageAlreadySpent);
         }
+    }
 
-        // All good
-        Ok(())
+    // Validate block id.
+    if !block.is_block_id_valid() {
+        return Err(Error::InvalidBlockID);
     }
+
+    // All good
+    Ok(())
 }
 
 // Specifies how we serialize the u64 chunk number in lmdb
@@ -491,6 +1139,11 @@ mod ledger_db_test {
         blocks
     }
 
+    #[test]
+    fn test_conformance() {
+        test_utils::conformance::run_conformance_tests(create_db);
+    }
+
     #[test]
     // Test initial conditions of a new LedgerDB instance.
     fn test_ledger_db_initialization() {
@@ -713,6 +1366,396 @@ mod ledger_db_test {
         }
     }
 
+    #[test]
+    // `truncate` should remove blocks along with their key images and TxOuts, so that they can be
+    // appended again.
+    fn test_truncate() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut ledger_db = create_db();
+
+        let origin_account_key = AccountKey::random(&mut rng);
+        let (origin_block, origin_block_contents) =
+            get_origin_block_and_contents(&origin_account_key);
+        ledger_db
+            .append_block(&origin_block, &origin_block_contents, None)
+            .unwrap();
+
+        // Write the next block, containing several key images.
+        let account_key = AccountKey::random(&mut rng);
+        let key_images: Vec<KeyImage> = (0..3)
+            .map(|_i| KeyImage::from(RistrettoPoint::random(&mut rng)))
+            .collect();
+        let tx_out = TxOut::new(
+            10,
+            &account_key.default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap();
+        let block_contents = BlockContents::new(key_images.clone(), vec![tx_out.clone()]);
+        let block = Block::new(
+            BLOCK_VERSION,
+            &origin_block.id,
+            1,
+            &Default::default(),
+            &block_contents,
+        );
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+
+        // The origin block cannot be removed, and blocks cannot be added by truncating.
+        assert_eq!(ledger_db.truncate(0), Err(Error::IndexOutOfBounds(0)));
+        assert_eq!(ledger_db.truncate(3), Err(Error::IndexOutOfBounds(3)));
+
+        ledger_db.truncate(1).unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 1);
+        assert_eq!(ledger_db.num_txos().unwrap(), 1);
+        assert_eq!(ledger_db.get_block(1), Err(Error::NotFound));
+        assert_eq!(
+            ledger_db.get_tx_out_index_by_hash(&tx_out.hash()),
+            Err(Error::NotFound)
+        );
+        assert_eq!(
+            ledger_db.get_tx_out_index_by_public_key(&tx_out.public_key),
+            Err(Error::NotFound)
+        );
+        assert_eq!(
+            ledger_db.get_block_index_by_tx_out_index(1),
+            Err(Error::NotFound)
+        );
+        for key_image in &key_images {
+            assert!(!ledger_db.contains_key_image(&key_image).unwrap());
+        }
+        assert_eq!(ledger_db.get_block(0).unwrap(), origin_block);
+
+        // The removed block can be appended again.
+        ledger_db
+            .append_block(&block, &block_contents, None)
+            .unwrap();
+        assert_eq!(ledger_db.num_blocks().unwrap(), 2);
+        assert_eq!(ledger_db.get_tx_out_index_by_hash(&tx_out.hash()), Ok(1));
+    }
+
+    #[test]
+    // `prune` should discard the contents of old blocks, and keep everything else.
+    fn test_prune() {
+        let mut rng: StdRng = SeedableRng::from_seed([5u8; 32]);
+        let mut ledger_db = create_db();
+        let blocks = test_utils::conformance::populate(&mut ledger_db, 5, &mut rng);
+        assert_eq!(ledger_db.num_pruned_blocks(), Ok(0));
+
+        // Blocks cannot be pruned before they are appended.
+        assert_eq!(ledger_db.prune(6), Err(Error::IndexOutOfBounds(6)));
+
+        ledger_db.prune(3).unwrap();
+        assert_eq!(ledger_db.num_pruned_blocks(), Ok(3));
+        for (block, block_contents, _) in &blocks {
+            if block.index < 3 {
+                assert_eq!(
+                    ledger_db.get_block_contents(block.index),
+                    Err(Error::BlockPruned(block.index))
+                );
+            } else {
+                assert_eq!(
+                    ledger_db.get_block_contents(block.index).as_ref(),
+                    Ok(block_contents)
+                );
+            }
+
+            // Headers, key images and TxOuts are kept.
+            assert_eq!(ledger_db.get_block(block.index).as_ref(), Ok(block));
+            assert_eq!(
+                ledger_db.get_key_images_by_block(block.index).as_ref(),
+                Ok(&block_contents.key_images)
+            );
+            for key_image in &block_contents.key_images {
+                assert_eq!(ledger_db.check_key_image(key_image), Ok(Some(block.index)));
+            }
+            for tx_out in &block_contents.outputs {
+                let index = ledger_db.get_tx_out_index_by_hash(&tx_out.hash()).unwrap();
+                assert_eq!(ledger_db.get_tx_out_by_index(index).as_ref(), Ok(tx_out));
+                assert_eq!(
+                    ledger_db.get_block_index_by_tx_out_index(index),
+                    Ok(block.index)
+                );
+                assert!(ledger_db.get_tx_out_proof_of_memberships(&[index]).is_ok());
+            }
+        }
+
+        // The TxOuts of pruned blocks can still be listed.
+        let tx_outs: Vec<TxOut> = blocks
+            .iter()
+            .flat_map(|(_, block_contents, _)| block_contents.outputs.clone())
+            .collect();
+        assert_eq!(ledger_db.get_tx_outs_by_block_range(0..5), Ok(tx_outs));
+
+        // Pruning fewer blocks than were already pruned does nothing.
+        ledger_db.prune(2).unwrap();
+        assert_eq!(ledger_db.num_pruned_blocks(), Ok(3));
+
+        // Pruned blocks cannot be removed, but the others can.
+        assert_eq!(ledger_db.truncate(2), Err(Error::BlockPruned(2)));
+        ledger_db.truncate(4).unwrap();
+        assert_eq!(ledger_db.num_blocks(), Ok(4));
+
+        // Blocks can still be appended after pruning.
+        let (block, block_contents, signature) = &blocks[4];
+        ledger_db
+            .append_block(block, block_contents, signature.as_ref())
+            .unwrap();
+        assert_eq!(ledger_db.num_blocks(), Ok(5));
+    }
+
+    #[test]
+    // A snapshot should hold everything needed to recreate a ledger, and be refused if corrupted.
+    fn test_export_and_import_snapshot() {
+        let mut rng: StdRng = SeedableRng::from_seed([6u8; 32]);
+        let mut ledger_db = create_db();
+        let blocks = test_utils::conformance::populate(&mut ledger_db, 5, &mut rng);
+
+        let temp_dir = TempDir::new("snapshot").unwrap();
+        let path = temp_dir.path().join("ledger.snapshot");
+        assert_eq!(
+            ledger_db.export_snapshot(&path, 5),
+            Err(Error::IndexOutOfBounds(5))
+        );
+        ledger_db.export_snapshot(&path, 3).unwrap();
+
+        // Importing into an empty ledger recreates the exported blocks.
+        let mut imported_ledger_db = create_db();
+        assert_eq!(imported_ledger_db.import_snapshot(&path), Ok(4));
+        assert_eq!(imported_ledger_db.num_blocks(), Ok(4));
+        for (block, block_contents, signature) in &blocks[..4] {
+            assert_eq!(
+                imported_ledger_db.get_block(block.index).as_ref(),
+                Ok(block)
+            );
+            assert_eq!(
+                imported_ledger_db.get_block_contents(block.index).as_ref(),
+                Ok(block_contents)
+            );
+            assert_eq!(
+                imported_ledger_db.get_block_signature(block.index).ok(),
+                *signature
+            );
+        }
+
+        // Blocks the ledger already has are skipped.
+        assert_eq!(imported_ledger_db.import_snapshot(&path), Ok(4));
+        assert_eq!(imported_ledger_db.num_blocks(), Ok(4));
+
+        // Pruned blocks cannot be exported.
+        ledger_db.prune(1).unwrap();
+        assert_eq!(
+            ledger_db.export_snapshot(&path, 3),
+            Err(Error::BlockPruned(0))
+        );
+
+        // A corrupted snapshot is refused, and nothing is appended.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 40;
+        bytes[last] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let mut empty_ledger_db = create_db();
+        assert_eq!(
+            empty_ledger_db.import_snapshot(&path),
+            Err(Error::InvalidSnapshot)
+        );
+        assert_eq!(empty_ledger_db.num_blocks(), Ok(0));
+    }
+
+    #[test]
+    // A read-only ledger should serve the blocks of a ledger, and refuse modifications.
+    fn test_open_read_only() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        let mut ledger_db = LedgerDB::open(path.clone()).unwrap();
+        let blocks = test_utils::conformance::populate(&mut ledger_db, 5, &mut rng);
+        ledger_db.truncate(4).unwrap();
+        // LMDB environments must not be opened twice in a process.
+        drop(ledger_db);
+
+        let mut read_only_ledger_db = LedgerDB::open_read_only(path).unwrap();
+        assert_eq!(read_only_ledger_db.num_blocks(), Ok(4));
+        for (block, block_contents, _) in &blocks[..4] {
+            assert_eq!(
+                read_only_ledger_db.get_block(block.index).as_ref(),
+                Ok(block)
+            );
+            assert_eq!(
+                read_only_ledger_db.get_block_contents(block.index).as_ref(),
+                Ok(block_contents)
+            );
+        }
+
+        let (block, block_contents, signature) = &blocks[4];
+        assert_eq!(
+            read_only_ledger_db.append_block(block, block_contents, signature.as_ref()),
+            Err(Error::ReadOnly)
+        );
+        assert_eq!(read_only_ledger_db.truncate(2), Err(Error::ReadOnly));
+        assert_eq!(read_only_ledger_db.prune(2), Err(Error::ReadOnly));
+        assert_eq!(read_only_ledger_db.num_blocks(), Ok(4));
+    }
+
+    #[test]
+    // `append_blocks` should append either all blocks or none of them.
+    fn test_append_blocks_atomically() {
+        let mut rng: StdRng = SeedableRng::from_seed([9u8; 32]);
+        let mut source_ledger_db = create_db();
+        let mut blocks = test_utils::conformance::populate(&mut source_ledger_db, 5, &mut rng);
+
+        let mut ledger_db = create_db();
+        ledger_db.append_blocks(&blocks[..2]).unwrap();
+
+        // The last block does not match its contents.
+        let (_, block_contents, _) = blocks[2].clone();
+        blocks[4].1 = block_contents;
+        assert_eq!(
+            ledger_db.append_blocks(&blocks[2..]),
+            Err(Error::InvalidBlockContents)
+        );
+        assert_eq!(ledger_db.num_blocks(), Ok(2));
+        assert_eq!(
+            ledger_db.num_txos(),
+            Ok((blocks[0].1.outputs.len() + blocks[1].1.outputs.len()) as u64)
+        );
+        for key_image in &blocks[2].1.key_images {
+            assert_eq!(ledger_db.contains_key_image(key_image), Ok(false));
+        }
+
+        ledger_db.append_blocks(&blocks[2..4]).unwrap();
+        assert_eq!(ledger_db.num_blocks(), Ok(4));
+        assert!(ledger_db.verify_integrity().unwrap().is_ok());
+    }
+
+    #[test]
+    // Appending and reading blocks should be reflected in the metrics.
+    fn test_metrics() {
+        let mut rng: StdRng = SeedableRng::from_seed([10u8; 32]);
+        let mut source_ledger_db = create_db();
+        let blocks = test_utils::conformance::populate(&mut source_ledger_db, 5, &mut rng);
+
+        let mut ledger_db = create_db();
+        let metrics = ledger_db.metrics.clone();
+        assert_eq!(metrics.blocks_appended_count.get(), 0);
+        assert_eq!(metrics.append_blocks_time.get_sample_count(), 0);
+
+        for (block, block_contents, signature) in &blocks[..3] {
+            ledger_db
+                .append_block(block, block_contents, signature.as_ref())
+                .unwrap();
+        }
+        ledger_db.append_blocks(&blocks[3..]).unwrap();
+        assert_eq!(metrics.blocks_appended_count.get(), 5);
+        assert_eq!(metrics.append_blocks_time.get_sample_count(), 4);
+
+        let num_reads = metrics.read_time.get_sample_count();
+        ledger_db.get_block(2).unwrap();
+        ledger_db.get_tx_out_by_index(0).unwrap();
+        assert_eq!(metrics.read_time.get_sample_count(), num_reads + 2);
+
+        // Clones share the metrics.
+        ledger_db.clone().get_block(0).unwrap();
+        assert_eq!(metrics.read_time.get_sample_count(), num_reads + 3);
+
+        // The gauges are read from LMDB.
+        assert_eq!(metrics.db_size.get(), 0);
+        ledger_db.metrics().unwrap();
+        assert!(metrics.db_size.get() > 0);
+        assert!(metrics.num_readers.get() > 0);
+    }
+
+    #[test]
+    // `verify_integrity` should accept a consistent ledger, and report corrupted blocks.
+    fn test_verify_integrity() {
+        let mut rng: StdRng = SeedableRng::from_seed([8u8; 32]);
+        let mut ledger_db = create_db();
+        let blocks = test_utils::conformance::populate(&mut ledger_db, 5, &mut rng);
+        let num_tx_outs = ledger_db.num_txos().unwrap();
+
+        let report = ledger_db.verify_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.num_blocks, 5);
+        assert_eq!(report.num_pruned_blocks, 0);
+        assert_eq!(report.num_tx_outs, num_tx_outs);
+
+        // Pruned blocks are checked against the indexes.
+        ledger_db.prune(2).unwrap();
+        let report = ledger_db.verify_integrity().unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.num_pruned_blocks, 2);
+
+        // Replace the contents of block 4 with those of block 1, which has as many TxOuts.
+        let mut db_transaction = ledger_db.env.begin_rw_txn().unwrap();
+        db_transaction
+            .put(
+                ledger_db.block_contents,
+                &u64_to_key_bytes(4),
+                &serialize(&blocks[1].1).unwrap(),
+                WriteFlags::empty(),
+            )
+            .unwrap();
+        // Forget that a key image of block 1 was spent.
+        db_transaction
+            .del(ledger_db.key_images, &blocks[1].1.key_images[0], None)
+            .unwrap();
+        db_transaction.commit().unwrap();
+
+        let report = ledger_db.verify_integrity().unwrap();
+        assert_eq!(
+            report.problems,
+            vec![
+                IntegrityProblem::KeyImageMismatch(1),
+                IntegrityProblem::ContentsHashMismatch(4),
+                IntegrityProblem::KeyImageMismatch(4),
+                IntegrityProblem::TxOutMismatch(4),
+            ]
+        );
+    }
+
+    #[test]
+    // Ledgers created before TxOuts were indexed by block are indexed when opened.
+    fn test_open_indexes_tx_out_block_numbers() {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let temp_dir = TempDir::new("test").unwrap();
+        let path = temp_dir.path().to_path_buf();
+        LedgerDB::create(path.clone()).unwrap();
+        let mut ledger_db = LedgerDB::open(path.clone()).unwrap();
+        let blocks = test_utils::conformance::populate(&mut ledger_db, 4, &mut rng);
+
+        let mut db_transaction = ledger_db.env.begin_rw_txn().unwrap();
+        unsafe {
+            db_transaction
+                .drop_db(ledger_db.block_number_by_tx_out_index)
+                .unwrap();
+        }
+        db_transaction.commit().unwrap();
+        drop(ledger_db);
+
+        // Indexing needs write access.
+        assert_eq!(
+            LedgerDB::open_read_only(path.clone()).err(),
+            Some(Error::ReadOnly)
+        );
+
+        let ledger_db = LedgerDB::open(path).unwrap();
+        let mut tx_out_index = 0;
+        for (block, block_contents, _) in &blocks {
+            for _ in &block_contents.outputs {
+                assert_eq!(
+                    ledger_db.get_block_index_by_tx_out_index(tx_out_index),
+                    Ok(block.index)
+                );
+                tx_out_index += 1;
+            }
+        }
+    }
+
     #[test]
     // `get_key_images_by_block` should return the correct set of key images used in a single block.
     fn test_get_key_images_by_block() {
@@ -0,0 +1,179 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Ledger snapshots.
+//!
+//! A snapshot holds the blocks of a ledger, from the origin block onwards, along with their
+//! contents and signatures, in a single file. Unlike a copy of the LMDB file, it holds none of the
+//! indexes and free pages of the database, which are rebuilt as the blocks are appended on
+//! import.
+//!
+//! A snapshot starts with `SNAPSHOT_MAGIC`, the format version and the number of blocks, as
+//! little-endian u32 and u64. Each block follows as its serialized `SnapshotBlock`, prefixed by
+//! its length as a little-endian u64. The SHA3-256 digest of everything before it ends the file.
+//! The digest only detects snapshots that were truncated or corrupted, it does not establish
+//! where a snapshot came from. Imported blocks are validated like any appended block.
+
+use crate::Error;
+use mcserial::{deserialize, serialize};
+use sha3::{Digest, Sha3_256};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+use transaction::{Block, BlockContents, BlockSignature};
+
+/// Identifies ledger snapshots.
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"MCLEDGER";
+
+/// The version of the snapshots this crate exports. Snapshots of other versions are refused.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Max size of a serialized block, which keeps a corrupted length from exhausting memory.
+const MAX_SNAPSHOT_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Size of the SHA3-256 digest that ends a snapshot.
+const DIGEST_SIZE: u64 = 32;
+
+/// A block, as stored in a snapshot.
+pub type SnapshotBlock = (Block, BlockContents, Option<BlockSignature>);
+
+/// Writes a snapshot of a given number of blocks.
+pub(crate) struct SnapshotWriter {
+    writer: BufWriter<File>,
+    hasher: Sha3_256,
+    num_blocks_left: u64,
+}
+
+impl SnapshotWriter {
+    pub fn create(path: &Path, num_blocks: u64) -> Result<Self, Error> {
+        let mut snapshot_writer = Self {
+            writer: BufWriter::new(File::create(path)?),
+            hasher: Sha3_256::new(),
+            num_blocks_left: num_blocks,
+        };
+        snapshot_writer.write(SNAPSHOT_MAGIC)?;
+        snapshot_writer.write(&SNAPSHOT_VERSION.to_le_bytes())?;
+        snapshot_writer.write(&num_blocks.to_le_bytes())?;
+        Ok(snapshot_writer)
+    }
+
+    pub fn write_block(&mut self, block: &SnapshotBlock) -> Result<(), Error> {
+        assert!(self.num_blocks_left > 0, "wrote more blocks than announced");
+        self.num_blocks_left -= 1;
+
+        let bytes = serialize(block)?;
+        self.write(&(bytes.len() as u64).to_le_bytes())?;
+        self.write(&bytes)
+    }
+
+    /// Writes the digest, and flushes the snapshot to disk.
+    pub fn finish(mut self) -> Result<(), Error> {
+        assert_eq!(self.num_blocks_left, 0, "wrote fewer blocks than announced");
+
+        let digest = self.hasher.result();
+        self.writer.write_all(&digest)?;
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.hasher.input(bytes);
+        self.writer.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+/// Reads the blocks of a snapshot, once its digest was checked.
+pub(crate) struct SnapshotReader {
+    reader: BufReader<File>,
+    num_blocks: u64,
+    num_blocks_left: u64,
+}
+
+impl SnapshotReader {
+    /// Opens a snapshot, checking its format version and digest.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        verify_digest(path)?;
+
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != *SNAPSHOT_MAGIC {
+            return Err(Error::InvalidSnapshot);
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != SNAPSHOT_VERSION {
+            return Err(Error::InvalidSnapshot);
+        }
+        let num_blocks = read_u64(&mut reader)?;
+
+        Ok(Self {
+            reader,
+            num_blocks,
+            num_blocks_left: num_blocks,
+        })
+    }
+
+    /// The number of blocks in the snapshot.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    fn read_block(&mut self) -> Result<SnapshotBlock, Error> {
+        let size = read_u64(&mut self.reader)?;
+        if size > MAX_SNAPSHOT_BLOCK_SIZE {
+            return Err(Error::InvalidSnapshot);
+        }
+        let mut bytes = vec![0u8; size as usize];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(deserialize(&bytes)?)
+    }
+}
+
+impl Iterator for SnapshotReader {
+    type Item = Result<SnapshotBlock, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.num_blocks_left == 0 {
+            return None;
+        }
+        self.num_blocks_left -= 1;
+        Some(self.read_block())
+    }
+}
+
+/// Checks that a snapshot ends with the digest of the rest of it.
+fn verify_digest(path: &Path) -> Result<(), Error> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < DIGEST_SIZE {
+        return Err(Error::InvalidSnapshot);
+    }
+
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha3_256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut remaining = len - DIGEST_SIZE;
+    while remaining > 0 {
+        let chunk_len = remaining.min(buf.len() as u64) as usize;
+        reader.read_exact(&mut buf[..chunk_len])?;
+        hasher.input(&buf[..chunk_len]);
+        remaining -= chunk_len as u64;
+    }
+
+    let mut digest = [0u8; DIGEST_SIZE as usize];
+    reader.read_exact(&mut digest)?;
+    if hasher.result()[..] != digest[..] {
+        return Err(Error::InvalidSnapshot);
+    }
+    Ok(())
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
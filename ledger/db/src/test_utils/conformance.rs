@@ -0,0 +1,411 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Conformance tests for `Ledger` implementations.
+//!
+//! Every implementation of `Ledger` should pass `run_conformance_tests`, so that ledgers used in
+//! tests behave like the ones used in production.
+
+use crate::{Error, Ledger};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use keys::{CompressedRistrettoPublic, Ed25519Pair, FromRandom, RistrettoPrivate};
+use rand::{rngs::StdRng, SeedableRng};
+use transaction::{
+    account_keys::AccountKey,
+    membership_proofs::is_membership_proof_valid,
+    ring_signature::KeyImage,
+    tx::{TxOut, TxOutMembershipProof},
+    Block, BlockContents, BlockID, BlockSignature, BLOCK_VERSION,
+};
+
+/// Runs all conformance tests, each against a new, empty ledger.
+///
+/// # Arguments
+/// * `create_ledger` - Creates an empty ledger.
+pub fn run_conformance_tests<L: Ledger>(create_ledger: impl Fn() -> L) {
+    check_append_and_get(create_ledger());
+    check_append_block_validation(create_ledger());
+    check_append_blocks(create_ledger());
+    check_membership_proofs(create_ledger());
+    check_key_images(create_ledger());
+    check_tx_out_lookups(create_ledger());
+    check_block_queries(create_ledger());
+}
+
+/// Creates a block with `num_outputs` outputs and `num_key_images` key images on top of `parent`,
+/// or an origin block.
+pub fn next_block(
+    parent: Option<&Block>,
+    num_outputs: usize,
+    num_key_images: usize,
+    rng: &mut StdRng,
+) -> (Block, BlockContents) {
+    let recipient = AccountKey::random(rng).default_subaddress();
+    let outputs: Vec<TxOut> = (0..num_outputs)
+        .map(|_| {
+            TxOut::new(
+                1000,
+                &recipient,
+                &RistrettoPrivate::from_random(rng),
+                Default::default(),
+                rng,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    match parent {
+        None => (
+            Block::new_origin_block(&outputs),
+            BlockContents::new(vec![], outputs),
+        ),
+        Some(parent) => {
+            let key_images = (0..num_key_images)
+                .map(|_| KeyImage::from(RistrettoPoint::random(rng)))
+                .collect();
+            let block_contents = BlockContents::new(key_images, outputs);
+            let block = Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                parent.index + 1,
+                &Default::default(),
+                &block_contents,
+            );
+            (block, block_contents)
+        }
+    }
+}
+
+/// Appends `num_blocks` blocks with a varying number of outputs, signing every other one.
+pub fn populate<L: Ledger>(
+    ledger: &mut L,
+    num_blocks: usize,
+    rng: &mut StdRng,
+) -> Vec<(Block, BlockContents, Option<BlockSignature>)> {
+    let signer = Ed25519Pair::from_random(rng);
+    let mut blocks: Vec<(Block, BlockContents, Option<BlockSignature>)> = Vec::new();
+    for block_index in 0..num_blocks {
+        let parent = blocks.last().map(|(block, _, _)| block);
+        let (block, block_contents) = next_block(parent, 1 + block_index % 3, 2, rng);
+        let signature = if block_index % 2 == 0 {
+            Some(BlockSignature::from_block_and_keypair(&block, &signer).unwrap())
+        } else {
+            None
+        };
+        ledger
+            .append_block(&block, &block_contents, signature.as_ref())
+            .unwrap();
+        blocks.push((block, block_contents, signature));
+    }
+    blocks
+}
+
+/// Blocks, their contents, signatures and TxOuts can be read back after being appended.
+pub fn check_append_and_get<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+    assert_eq!(ledger.num_blocks().unwrap(), 0);
+    assert_eq!(ledger.num_txos().unwrap(), 0);
+    assert_eq!(ledger.get_block(0), Err(Error::NotFound));
+
+    let blocks = populate(&mut ledger, 5, &mut rng);
+    assert_eq!(ledger.num_blocks().unwrap(), 5);
+
+    let mut tx_out_index = 0;
+    for (block, block_contents, signature) in &blocks {
+        assert_eq!(&ledger.get_block(block.index).unwrap(), block);
+        assert_eq!(
+            &ledger.get_block_contents(block.index).unwrap(),
+            block_contents
+        );
+        match signature {
+            Some(signature) => {
+                assert_eq!(&ledger.get_block_signature(block.index).unwrap(), signature)
+            }
+            None => assert_eq!(
+                ledger.get_block_signature(block.index),
+                Err(Error::NotFound)
+            ),
+        }
+
+        for tx_out in &block_contents.outputs {
+            assert_eq!(&ledger.get_tx_out_by_index(tx_out_index).unwrap(), tx_out);
+            assert_eq!(
+                ledger.get_tx_out_index_by_hash(&tx_out.hash()),
+                Ok(tx_out_index)
+            );
+            tx_out_index += 1;
+        }
+    }
+    assert_eq!(ledger.num_txos().unwrap(), tx_out_index);
+
+    // Things not in the ledger are not found.
+    assert_eq!(ledger.get_block(5), Err(Error::NotFound));
+    assert_eq!(ledger.get_block_contents(5), Err(Error::NotFound));
+    assert_eq!(ledger.get_block_signature(5), Err(Error::NotFound));
+    assert_eq!(
+        ledger.get_tx_out_by_index(tx_out_index),
+        Err(Error::NotFound)
+    );
+    assert_eq!(
+        ledger.get_tx_out_index_by_hash(&[7u8; 32]),
+        Err(Error::NotFound)
+    );
+}
+
+/// Invalid blocks are rejected and leave the ledger untouched.
+pub fn check_append_block_validation<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([3u8; 32]);
+
+    // The first block must be an origin block.
+    let (origin_block, origin_contents) = next_block(None, 2, 0, &mut rng);
+    let (block_one, contents_one) = next_block(Some(&origin_block), 2, 2, &mut rng);
+    assert_eq!(
+        ledger.append_block(&block_one, &contents_one, None),
+        Err(Error::InvalidBlock)
+    );
+    ledger
+        .append_block(&origin_block, &origin_contents, None)
+        .unwrap();
+
+    // Blocks must extend the last block.
+    assert_eq!(
+        ledger.append_block(&origin_block, &origin_contents, None),
+        Err(Error::InvalidBlock)
+    );
+    let mut orphan_block = block_one.clone();
+    orphan_block.parent_id = BlockID::default();
+    assert_eq!(
+        ledger.append_block(&orphan_block, &contents_one, None),
+        Err(Error::InvalidBlock)
+    );
+
+    // Contents must match the block.
+    assert_eq!(
+        ledger.append_block(&block_one, &origin_contents, None),
+        Err(Error::InvalidBlockContents)
+    );
+
+    // Blocks must have outputs.
+    let empty_contents = BlockContents::new(contents_one.key_images.clone(), vec![]);
+    let empty_block = Block::new(
+        BLOCK_VERSION,
+        &origin_block.id,
+        1,
+        &Default::default(),
+        &empty_contents,
+    );
+    assert_eq!(
+        ledger.append_block(&empty_block, &empty_contents, None),
+        Err(Error::InvalidBlock)
+    );
+
+    // Rejected blocks leave the ledger untouched.
+    assert_eq!(ledger.num_blocks().unwrap(), 1);
+    assert_eq!(ledger.num_txos().unwrap(), 2);
+
+    // Key images cannot be spent twice.
+    ledger
+        .append_block(&block_one, &contents_one, None)
+        .unwrap();
+    let (_, mut contents_two) = next_block(Some(&block_one), 1, 0, &mut rng);
+    contents_two.key_images = vec![contents_one.key_images[0]];
+    let block_two = Block::new(
+        BLOCK_VERSION,
+        &block_one.id,
+        2,
+        &Default::default(),
+        &contents_two,
+    );
+    assert_eq!(
+        ledger.append_block(&block_two, &contents_two, None),
+        Err(Error::KeyImageAlreadySpent)
+    );
+}
+
+/// Blocks appended together can be read back, and are validated against each other.
+pub fn check_append_blocks<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+    let mut blocks: Vec<(Block, BlockContents, Option<BlockSignature>)> = Vec::new();
+    for block_index in 0..4 {
+        let parent = blocks.last().map(|(block, _, _)| block);
+        let (block, block_contents) = next_block(parent, 1 + block_index % 3, 2, &mut rng);
+        blocks.push((block, block_contents, None));
+    }
+
+    ledger.append_blocks(&blocks[..1]).unwrap();
+    ledger.append_blocks(&blocks[1..]).unwrap();
+    assert_eq!(ledger.num_blocks().unwrap(), 4);
+    for (block, block_contents, _) in &blocks {
+        assert_eq!(&ledger.get_block(block.index).unwrap(), block);
+        assert_eq!(
+            &ledger.get_block_contents(block.index).unwrap(),
+            block_contents
+        );
+    }
+
+    // A block cannot spend a key image spent by a block appended along with it.
+    let (block_four, contents_four) = next_block(Some(&blocks[3].0), 1, 1, &mut rng);
+    let (_, mut contents_five) = next_block(Some(&block_four), 1, 0, &mut rng);
+    contents_five.key_images = contents_four.key_images.clone();
+    let block_five = Block::new(
+        BLOCK_VERSION,
+        &block_four.id,
+        5,
+        &Default::default(),
+        &contents_five,
+    );
+    assert_eq!(
+        ledger.append_blocks(&[
+            (block_four, contents_four, None),
+            (block_five, contents_five, None)
+        ]),
+        Err(Error::KeyImageAlreadySpent)
+    );
+}
+
+/// The root hash of the Merkle tree a proof was computed against.
+fn root_hash(proof: &TxOutMembershipProof) -> [u8; 32] {
+    let root = proof
+        .elements
+        .iter()
+        .filter(|element| element.range.from == 0)
+        .max_by_key(|element| element.range.to)
+        .expect("proof has no elements");
+    *root.hash.as_ref()
+}
+
+/// Proofs of membership are valid for every TxOut, against the same root hash, and remain so
+/// against the new root hash once more blocks are appended.
+pub fn check_membership_proofs<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+
+    let check_proofs = |ledger: &L| {
+        let num_txos = ledger.num_txos().unwrap();
+        let indexes: Vec<u64> = (0..num_txos).collect();
+        let proofs = ledger.get_tx_out_proof_of_memberships(&indexes).unwrap();
+        assert_eq!(proofs.len(), indexes.len());
+
+        let known_root_hash = root_hash(&proofs[0]);
+        for (index, proof) in indexes.iter().zip(proofs.iter()) {
+            assert_eq!(proof.index, *index);
+            assert_eq!(proof.highest_index, num_txos - 1);
+            let tx_out = ledger.get_tx_out_by_index(*index).unwrap();
+            assert!(is_membership_proof_valid(&tx_out, proof, &known_root_hash).unwrap());
+        }
+
+        // Proofs for TxOuts not in the ledger fail, even alongside valid indexes.
+        assert_eq!(
+            ledger.get_tx_out_proof_of_memberships(&[0, num_txos]),
+            Err(Error::IndexOutOfBounds(num_txos))
+        );
+    };
+
+    let blocks = populate(&mut ledger, 1, &mut rng);
+    check_proofs(&ledger);
+
+    // A ledger with a number of TxOuts that is not a power of two.
+    let (parent, _, _) = blocks.last().unwrap();
+    let (block, block_contents) = next_block(Some(parent), 3, 1, &mut rng);
+    ledger.append_block(&block, &block_contents, None).unwrap();
+    check_proofs(&ledger);
+
+    let (next, next_contents) = next_block(Some(&block), 5, 1, &mut rng);
+    ledger.append_block(&next, &next_contents, None).unwrap();
+    check_proofs(&ledger);
+}
+
+/// Key images are recorded with the block that spent them.
+pub fn check_key_images<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([5u8; 32]);
+    let blocks = populate(&mut ledger, 4, &mut rng);
+
+    for (block, block_contents, _) in &blocks {
+        assert_eq!(
+            ledger.get_key_images_by_block(block.index).unwrap(),
+            block_contents.key_images
+        );
+        for key_image in &block_contents.key_images {
+            assert_eq!(ledger.check_key_image(key_image), Ok(Some(block.index)));
+            assert_eq!(ledger.contains_key_image(key_image), Ok(true));
+        }
+    }
+
+    let unknown_key_image = KeyImage::from(RistrettoPoint::random(&mut rng));
+    assert_eq!(ledger.check_key_image(&unknown_key_image), Ok(None));
+    assert_eq!(ledger.contains_key_image(&unknown_key_image), Ok(false));
+    assert_eq!(ledger.get_key_images_by_block(4), Err(Error::NotFound));
+}
+
+/// TxOuts can be looked up by public key, and the block that contains them by their index.
+pub fn check_tx_out_lookups<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([6u8; 32]);
+    let blocks = populate(&mut ledger, 5, &mut rng);
+
+    let mut tx_out_index = 0;
+    for (block, block_contents, _) in &blocks {
+        for tx_out in &block_contents.outputs {
+            assert_eq!(
+                ledger.get_tx_out_index_by_public_key(&tx_out.public_key),
+                Ok(tx_out_index)
+            );
+            assert_eq!(
+                ledger.get_block_index_by_tx_out_index(tx_out_index),
+                Ok(block.index)
+            );
+            tx_out_index += 1;
+        }
+    }
+
+    let unknown_public_key: CompressedRistrettoPublic = RistrettoPoint::random(&mut rng).into();
+    assert_eq!(
+        ledger.get_tx_out_index_by_public_key(&unknown_public_key),
+        Err(Error::NotFound)
+    );
+    assert_eq!(
+        ledger.get_block_index_by_tx_out_index(tx_out_index),
+        Err(Error::NotFound)
+    );
+}
+
+/// Blocks can be looked up by the TxOuts and key images in them, and TxOuts by block.
+pub fn check_block_queries<L: Ledger>(mut ledger: L) {
+    let mut rng: StdRng = SeedableRng::from_seed([8u8; 32]);
+    let blocks = populate(&mut ledger, 5, &mut rng);
+
+    for (block, block_contents, _) in &blocks {
+        for tx_out in &block_contents.outputs {
+            assert_eq!(
+                ledger.get_block_index_by_tx_out_public_key(&tx_out.public_key),
+                Ok(block.index)
+            );
+        }
+        for key_image in &block_contents.key_images {
+            assert_eq!(ledger.get_block_by_key_image(key_image).as_ref(), Ok(block));
+        }
+    }
+
+    let tx_outs = |range: std::ops::Range<usize>| -> Vec<TxOut> {
+        blocks[range]
+            .iter()
+            .flat_map(|(_, block_contents, _)| block_contents.outputs.clone())
+            .collect()
+    };
+    assert_eq!(ledger.get_tx_outs_by_block_range(0..5), Ok(tx_outs(0..5)));
+    assert_eq!(ledger.get_tx_outs_by_block_range(2..4), Ok(tx_outs(2..4)));
+    assert_eq!(ledger.get_tx_outs_by_block_range(4..5), Ok(tx_outs(4..5)));
+    assert_eq!(ledger.get_tx_outs_by_block_range(3..3), Ok(Vec::new()));
+    assert_eq!(
+        ledger.get_tx_outs_by_block_range(3..6),
+        Err(Error::IndexOutOfBounds(5))
+    );
+
+    let unknown_public_key: CompressedRistrettoPublic = RistrettoPoint::random(&mut rng).into();
+    assert_eq!(
+        ledger.get_block_index_by_tx_out_public_key(&unknown_public_key),
+        Err(Error::NotFound)
+    );
+    let unknown_key_image = KeyImage::from(RistrettoPoint::random(&mut rng));
+    assert_eq!(
+        ledger.get_block_by_key_image(&unknown_key_image),
+        Err(Error::NotFound)
+    );
+}
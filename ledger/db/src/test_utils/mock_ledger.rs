@@ -3,7 +3,7 @@
 use crate::{Error, Ledger};
 use common::{HashMap, HashSet};
 use curve25519_dalek::ristretto::RistrettoPoint;
-use keys::{FromRandom, RistrettoPrivate};
+use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate};
 use rand::{rngs::StdRng, SeedableRng};
 use std::{
     iter::FromIterator,
@@ -129,11 +129,24 @@ impl Ledger for MockLedger {
         unimplemented!()
     }
 
+    fn get_tx_out_index_by_public_key(
+        &self,
+        _tx_out_public_key: &CompressedRistrettoPublic,
+    ) -> Result<u64, Error> {
+        // Unused for these tests.
+        unimplemented!()
+    }
+
     fn get_tx_out_by_index(&self, _: u64) -> Result<TxOut, Error> {
         // Unused for these tests.
         unimplemented!()
     }
 
+    fn get_block_index_by_tx_out_index(&self, _tx_out_index: u64) -> Result<u64, Error> {
+        // Unused for these tests.
+        unimplemented!()
+    }
+
     fn check_key_image(&self, key_image: &KeyImage) -> Result<Option<u64>, Error> {
         // Unused for these tests.
         Ok(self.lock().key_images.get(key_image).cloned())
@@ -1,4 +1,5 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
+pub mod conformance;
 pub mod mock_ledger;
 pub use mock_ledger::{get_mock_ledger, get_test_ledger_blocks, MockLedger};
@@ -25,6 +25,7 @@
 
 use crate::{key_bytes_to_u64, u64_to_key_bytes, Error};
 use common::{Hash, HashMap};
+use keys::CompressedRistrettoPublic;
 use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
 use mcserial::{deserialize, serialize};
 use transaction::{
@@ -36,6 +37,7 @@ use transaction::{
 // LMDB Database names.
 const COUNTS_DB_NAME: &str = "tx_out_store:counts";
 const TX_OUT_INDEX_BY_HASH_DB_NAME: &str = "tx_out_store:tx_out_index_by_hash";
+const TX_OUT_INDEX_BY_PUBLIC_KEY_DB_NAME: &str = "tx_out_store:tx_out_index_by_public_key";
 const TX_OUT_BY_INDEX_DB_NAME: &str = "tx_out_store:tx_out_by_index";
 const MERKLE_HASH_BY_RANGE_DB_NAME: &str = "tx_out_store:merkle_hash_by_range";
 
@@ -54,6 +56,9 @@ pub struct TxOutStore {
     /// `tx_out.hash() -> u64_to_key_bytes(index)`
     tx_out_index_by_hash: Database,
 
+    /// `tx_out.public_key -> u64_to_key_bytes(index)`
+    tx_out_index_by_public_key: Database,
+
     /// Merkle hashes of subtrees. Range -> Merkle Hash of subtree containing TxOuts with indices in `[range.from, range.to]`.
     /// range.to_key_bytes --> [u8; 32]
     merkle_hashes: Database,
@@ -61,19 +66,58 @@ pub struct TxOutStore {
 
 impl TxOutStore {
     /// Opens an existing TxOutStore.
-    pub fn new(env: &Environment) -> Result<Self, Error> {
-        Ok(TxOutStore {
+    ///
+    /// Stores created before TxOuts were indexed by public key are indexed when they are first
+    /// opened, which fails with `Error::ReadOnly` if `read_only` is set.
+    pub fn new(env: &Environment, read_only: bool) -> Result<Self, Error> {
+        let (tx_out_index_by_public_key, needs_indexing) =
+            match env.open_db(Some(TX_OUT_INDEX_BY_PUBLIC_KEY_DB_NAME)) {
+                Ok(db) => (db, false),
+                Err(lmdb::Error::NotFound) if read_only => return Err(Error::ReadOnly),
+                Err(lmdb::Error::NotFound) => (
+                    env.create_db(
+                        Some(TX_OUT_INDEX_BY_PUBLIC_KEY_DB_NAME),
+                        DatabaseFlags::empty(),
+                    )?,
+                    true,
+                ),
+                Err(err) => return Err(err.into()),
+            };
+
+        let tx_out_store = TxOutStore {
             counts: env.open_db(Some(COUNTS_DB_NAME))?,
             tx_out_index_by_hash: env.open_db(Some(TX_OUT_INDEX_BY_HASH_DB_NAME))?,
+            tx_out_index_by_public_key,
             tx_out_by_index: env.open_db(Some(TX_OUT_BY_INDEX_DB_NAME))?,
             merkle_hashes: env.open_db(Some(MERKLE_HASH_BY_RANGE_DB_NAME))?,
-        })
+        };
+
+        if needs_indexing {
+            let mut db_transaction = env.begin_rw_txn()?;
+            let num_tx_outs = tx_out_store.num_tx_outs(&db_transaction)?;
+            for index in 0..num_tx_outs {
+                let tx_out = tx_out_store.get_tx_out_by_index(index, &db_transaction)?;
+                db_transaction.put(
+                    tx_out_store.tx_out_index_by_public_key,
+                    &tx_out.public_key,
+                    &u64_to_key_bytes(index),
+                    WriteFlags::empty(),
+                )?;
+            }
+            db_transaction.commit()?;
+        }
+
+        Ok(tx_out_store)
     }
 
     // Creates a fresh TxOutStore on disk.
     pub fn create(env: &Environment) -> Result<(), Error> {
         let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(TX_OUT_INDEX_BY_HASH_DB_NAME), DatabaseFlags::empty())?;
+        env.create_db(
+            Some(TX_OUT_INDEX_BY_PUBLIC_KEY_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
         env.create_db(Some(TX_OUT_BY_INDEX_DB_NAME), DatabaseFlags::empty())?;
         env.create_db(Some(MERKLE_HASH_BY_RANGE_DB_NAME), DatabaseFlags::empty())?;
 
@@ -111,6 +155,13 @@ impl TxOutStore {
             WriteFlags::empty(),
         )?;
 
+        db_transaction.put(
+            self.tx_out_index_by_public_key,
+            &tx_out.public_key,
+            &u64_to_key_bytes(index),
+            WriteFlags::empty(),
+        )?;
+
         let tx_out_bytes: Vec<u8> = serialize(tx_out)?;
 
         db_transaction.put(
@@ -125,6 +176,51 @@ impl TxOutStore {
         Ok(index)
     }
 
+    /// Removes all TxOuts from index `num_tx_outs` onwards, and updates the Merkle hashes of the
+    /// remaining ones.
+    pub fn truncate(
+        &self,
+        num_tx_outs: u64,
+        db_transaction: &mut RwTransaction,
+    ) -> Result<(), Error> {
+        let num_tx_outs_before = self.num_tx_outs(db_transaction)?;
+        if num_tx_outs > num_tx_outs_before {
+            return Err(Error::IndexOutOfBounds(num_tx_outs));
+        }
+
+        for index in num_tx_outs..num_tx_outs_before {
+            let tx_out = self.get_tx_out_by_index(index, db_transaction)?;
+            db_transaction.del(self.tx_out_index_by_hash, &tx_out.hash(), None)?;
+            // Public keys are not required to be unique; only the latest TxOut with a given public
+            // key is indexed.
+            if self.get_tx_out_index_by_public_key(&tx_out.public_key, db_transaction) == Ok(index)
+            {
+                db_transaction.del(self.tx_out_index_by_public_key, &tx_out.public_key, None)?;
+            }
+            db_transaction.del(self.tx_out_by_index, &u64_to_key_bytes(index), None)?;
+            db_transaction.del(
+                self.merkle_hashes,
+                &range_to_key_bytes(&Range::new(index, index)?),
+                None,
+            )?;
+        }
+
+        db_transaction.put(
+            self.counts,
+            &NUM_TX_OUTS_KEY,
+            &u64_to_key_bytes(num_tx_outs),
+            WriteFlags::empty(),
+        )?;
+
+        // Every subtree that lost TxOuts contains the last remaining one. Hashes of subtrees that
+        // lie entirely past it are never read.
+        if num_tx_outs > 0 {
+            self.update_merkle_hashes(num_tx_outs - 1, db_transaction)?;
+        }
+
+        Ok(())
+    }
+
     /// Get the total number of TxOuts in the ledger.
     pub fn num_tx_outs<T: Transaction>(&self, db_transaction: &T) -> Result<u64, Error> {
         Ok(key_bytes_to_u64(
@@ -142,6 +238,16 @@ impl TxOutStore {
         Ok(key_bytes_to_u64(index_bytes))
     }
 
+    /// Returns the index of the TxOut with the given public key.
+    pub fn get_tx_out_index_by_public_key<T: Transaction>(
+        &self,
+        tx_out_public_key: &CompressedRistrettoPublic,
+        db_transaction: &T,
+    ) -> Result<u64, Error> {
+        let index_bytes = db_transaction.get(self.tx_out_index_by_public_key, tx_out_public_key)?;
+        Ok(key_bytes_to_u64(index_bytes))
+    }
+
     /// Gets a TxOut by its index in the ledger.
     pub fn get_tx_out_by_index<T: Transaction>(
         &self,
@@ -154,7 +260,6 @@ impl TxOutStore {
     }
 
     /// Get the root hash of the Merkle Tree
-    #[allow(dead_code)]
     pub fn get_root_merkle_hash<T: Transaction>(
         &self,
         db_transaction: &T,
@@ -174,6 +279,57 @@ impl TxOutStore {
             Err(Error::CapacityExceeded)
         }
     }
+
+    /// Computes the root hash of the Merkle Tree from the TxOuts, ignoring the stored Merkle
+    /// hashes, e.g. to check them.
+    pub fn compute_root_merkle_hash<T: Transaction>(
+        &self,
+        db_transaction: &T,
+    ) -> Result<[u8; 32], Error> {
+        let num_tx_outs = self.num_tx_outs(db_transaction)?;
+
+        if num_tx_outs == 0 {
+            return Ok(nil_hash_fn());
+        }
+
+        match num_tx_outs.checked_next_power_of_two() {
+            Some(num_leaves_full_tree) => {
+                self.compute_merkle_hash(0, num_leaves_full_tree - 1, num_tx_outs, db_transaction)
+            }
+            // Overflow.
+            None => Err(Error::CapacityExceeded),
+        }
+    }
+
+    /// Computes the Merkle hash of the non-empty range `[low, high]` from the TxOuts in it.
+    fn compute_merkle_hash<T: Transaction>(
+        &self,
+        low: u64,
+        high: u64,
+        num_tx_outs: u64,
+        db_transaction: &T,
+    ) -> Result<[u8; 32], Error> {
+        if low == high {
+            let tx_out = self.get_tx_out_by_index(low, db_transaction)?;
+            let tx_out_bytes: Vec<u8> = serialize(&tx_out)?;
+            return Ok(leaf_hash_fn(&tx_out_bytes));
+        }
+
+        let mid: u64 = (low + high) / 2;
+        let left_child_hash = self.compute_merkle_hash(low, mid, num_tx_outs, db_transaction)?;
+        let right_child_hash = if mid + 1 >= num_tx_outs {
+            // The right subtree contains no TxOuts, so use the nil hash.
+            nil_hash_fn()
+        } else {
+            self.compute_merkle_hash(mid + 1, high, num_tx_outs, db_transaction)?
+        };
+
+        let left_slice: &[u8] = &left_child_hash;
+        let right_slice: &[u8] = &right_child_hash;
+        let concatenated_slices: &[u8] = &[left_slice, right_slice].concat();
+        Ok(internal_hash_fn(&concatenated_slices))
+    }
+
     /// Writes the Merkle hash value for a node spanning the given range.
     fn write_merkle_hash(
         &self,
@@ -225,45 +381,16 @@ impl TxOutStore {
             return Err(Error::IndexOutOfBounds(index));
         }
 
-        let ranges = containing_ranges(index, num_tx_outs)?;
-
-        for (low, high) in ranges {
-            if low == high {
-                // Leaf.
-                let tx_out = self.get_tx_out_by_index(index, db_transaction)?;
-                let tx_out_bytes: Vec<u8> = serialize(&tx_out)?;
-                let hash = leaf_hash_fn(&tx_out_bytes);
-                let range = Range::new(low, low)?;
-                self.write_merkle_hash(&range, &hash, db_transaction)?;
-            } else {
-                // Internal node.
-                let mid: u64 = (low + high) / 2;
-
-                // Left child.
-                let left_child_hash = {
-                    let left_child_range = Range::new(low, mid)?;
-                    self.get_merkle_hash(&left_child_range, db_transaction)?
-                };
-
-                // Right child.
-                let right_child_hash = if mid + 1 >= num_tx_outs {
-                    // The right subtree contains no TxOuts, so use the nil hash.
-                    nil_hash_fn()
-                } else {
-                    // The right subtree contains some TxOuts, so look up the child's hash.
-                    let right_child_range = Range::new(mid + 1, high)?;
-                    self.get_merkle_hash(&right_child_range, db_transaction)?
-                };
-
-                // This node.
-                let left_slice: &[u8] = &left_child_hash;
-                let right_slice: &[u8] = &right_child_hash;
-                let concatenated_slices: &[u8] = &[left_slice, right_slice].concat();
+        let tx_out = self.get_tx_out_by_index(index, db_transaction)?;
+        let hashes = {
+            let db_transaction: &RwTransaction = db_transaction;
+            merkle_hashes_containing(index, num_tx_outs, &tx_out, |range| {
+                self.get_merkle_hash(range, db_transaction)
+            })?
+        };
 
-                let hash = internal_hash_fn(&concatenated_slices);
-                let range = Range::new(low, high)?;
-                self.write_merkle_hash(&range, &hash, db_transaction)?;
-            }
+        for (range, hash) in hashes {
+            self.write_merkle_hash(&range, &hash, db_transaction)?;
         }
 
         Ok(())
@@ -277,47 +404,126 @@ impl TxOutStore {
         db_transaction: &T,
     ) -> Result<TxOutMembershipProof, Error> {
         let num_tx_outs = self.num_tx_outs(db_transaction)?;
-        if index >= num_tx_outs {
-            return Err(Error::IndexOutOfBounds(index));
-        }
-
-        let ranges = containing_ranges(index, num_tx_outs)?;
+        merkle_proof_of_membership(index, num_tx_outs, |range| {
+            self.get_merkle_hash(range, db_transaction)
+        })
+    }
+}
 
-        // For each non-leaf node, compute the range of the "other" child.
-        let internal_ranges: Vec<(u64, u64)> = ranges.iter().skip(1).cloned().collect();
-        let mut other_ranges: Vec<(u64, u64)> = Vec::new();
-        for (low, high) in internal_ranges {
+/// Computes the Merkle hashes of the ranges containing a TxOut.
+///
+/// # Arguments
+/// * `index` - The index of the TxOut.
+/// * `num_tx_outs` - The number of TxOuts in the tree, this one included.
+/// * `tx_out` - The TxOut.
+/// * `get_merkle_hash` - Gets the hash of a non-empty range that does not contain the TxOut.
+///
+/// Returns the hash of each range containing the TxOut, from the leaf up to the root.
+pub fn merkle_hashes_containing<F>(
+    index: u64,
+    num_tx_outs: u64,
+    tx_out: &TxOut,
+    get_merkle_hash: F,
+) -> Result<Vec<(Range, [u8; 32])>, Error>
+where
+    F: Fn(&Range) -> Result<[u8; 32], Error>,
+{
+    let ranges = containing_ranges(index, num_tx_outs)?;
+
+    let mut hashes: Vec<(Range, [u8; 32])> = Vec::with_capacity(ranges.len());
+    // Hash of the range containing the TxOut one level down.
+    let mut child_hash = [0u8; 32];
+    for (low, high) in ranges {
+        let hash = if low == high {
+            // Leaf.
+            let tx_out_bytes: Vec<u8> = serialize(tx_out)?;
+            leaf_hash_fn(&tx_out_bytes)
+        } else {
+            // Internal node.
             let mid: u64 = (low + high) / 2;
-            if index <= mid {
-                // "other" child in the higher half-range.
-                other_ranges.push((mid + 1, high));
+
+            let left_child_hash = if index <= mid {
+                child_hash
             } else {
-                // "other" child in the lower half-range.
-                other_ranges.push((low, mid));
-            }
-        }
+                get_merkle_hash(&Range::new(low, mid)?)?
+            };
 
-        // Get Merkle hashes for containing ranges and "other" ranges.
-        let mut range_to_hash: HashMap<Range, [u8; 32]> = HashMap::default();
-        for &(low, high) in ranges.iter().chain(other_ranges.iter()) {
-            let range = Range::new(low, high)?;
-            let hash = if low >= num_tx_outs {
-                // Supply the nil hash if the range contains no data.
-                // Note: Nil hashes could probably be omitted as an optimization if validation
-                // knows that it must supply them for any range where `low >= num_tx_outs`.
+            let right_child_hash = if index > mid {
+                child_hash
+            } else if mid + 1 >= num_tx_outs {
+                // The right subtree contains no TxOuts, so use the nil hash.
                 nil_hash_fn()
             } else {
-                self.get_merkle_hash(&range, db_transaction)?
+                get_merkle_hash(&Range::new(mid + 1, high)?)?
             };
-            range_to_hash.insert(range, hash);
+
+            let left_slice: &[u8] = &left_child_hash;
+            let right_slice: &[u8] = &right_child_hash;
+            let concatenated_slices: &[u8] = &[left_slice, right_slice].concat();
+            internal_hash_fn(&concatenated_slices)
+        };
+
+        hashes.push((Range::new(low, high)?, hash));
+        child_hash = hash;
+    }
+
+    Ok(hashes)
+}
+
+/// Merkle proof-of-membership for the TxOut with the given index.
+///
+/// # Arguments
+/// * `index` - The index of the TxOut.
+/// * `num_tx_outs` - The number of TxOuts in the tree.
+/// * `get_merkle_hash` - Gets the hash of a non-empty range.
+pub fn merkle_proof_of_membership<F>(
+    index: u64,
+    num_tx_outs: u64,
+    get_merkle_hash: F,
+) -> Result<TxOutMembershipProof, Error>
+where
+    F: Fn(&Range) -> Result<[u8; 32], Error>,
+{
+    if index >= num_tx_outs {
+        return Err(Error::IndexOutOfBounds(index));
+    }
+
+    let ranges = containing_ranges(index, num_tx_outs)?;
+
+    // For each non-leaf node, compute the range of the "other" child.
+    let internal_ranges: Vec<(u64, u64)> = ranges.iter().skip(1).cloned().collect();
+    let mut other_ranges: Vec<(u64, u64)> = Vec::new();
+    for (low, high) in internal_ranges {
+        let mid: u64 = (low + high) / 2;
+        if index <= mid {
+            // "other" child in the higher half-range.
+            other_ranges.push((mid + 1, high));
+        } else {
+            // "other" child in the lower half-range.
+            other_ranges.push((low, mid));
         }
+    }
 
-        Ok(TxOutMembershipProof::new(
-            index,
-            num_tx_outs - 1,
-            range_to_hash,
-        ))
+    // Get Merkle hashes for containing ranges and "other" ranges.
+    let mut range_to_hash: HashMap<Range, [u8; 32]> = HashMap::default();
+    for &(low, high) in ranges.iter().chain(other_ranges.iter()) {
+        let range = Range::new(low, high)?;
+        let hash = if low >= num_tx_outs {
+            // Supply the nil hash if the range contains no data.
+            // Note: Nil hashes could probably be omitted as an optimization if validation
+            // knows that it must supply them for any range where `low >= num_tx_outs`.
+            nil_hash_fn()
+        } else {
+            get_merkle_hash(&range)?
+        };
+        range_to_hash.insert(range, hash);
     }
+
+    Ok(TxOutMembershipProof::new(
+        index,
+        num_tx_outs - 1,
+        range_to_hash,
+    ))
 }
 
 /// Converts this Range to bytes for use as an LMDB key.
@@ -747,7 +953,7 @@ pub mod tx_out_store_tests {
     pub fn init_tx_out_store() -> (TxOutStore, Environment) {
         let env = get_env();
         TxOutStore::create(&env).unwrap();
-        let tx_out_store: TxOutStore = TxOutStore::new(&env).unwrap();
+        let tx_out_store: TxOutStore = TxOutStore::new(&env, false).unwrap();
         (tx_out_store, env)
     }
 
@@ -832,6 +1038,61 @@ pub mod tx_out_store_tests {
         }
     }
 
+    #[test]
+    // `get_tx_out_index_by_public_key` should return the correct index, also for a store that was
+    // created before TxOuts were indexed by public key.
+    fn test_get_tx_out_index_by_public_key() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let tx_outs: Vec<TxOut> = get_tx_outs(20)
+            .into_iter()
+            .map(|mut tx_out| {
+                // TxOuts sent to the same recipient by the same transaction share a public key.
+                tx_out.public_key = RistrettoPublic::from_random(&mut rng).into();
+                tx_out
+            })
+            .collect();
+
+        {
+            let mut rw_transaction: RwTransaction = env.begin_rw_txn().unwrap();
+            for tx_out in &tx_outs {
+                tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+            }
+            rw_transaction.commit().unwrap();
+        }
+
+        let check_indexes = |tx_out_store: &TxOutStore| {
+            let ro_transaction: RoTransaction = env.begin_ro_txn().unwrap();
+            for (index, tx_out) in tx_outs.iter().enumerate() {
+                assert_eq!(
+                    Ok(index as u64),
+                    tx_out_store
+                        .get_tx_out_index_by_public_key(&tx_out.public_key, &ro_transaction)
+                );
+            }
+            let unrecognized_public_key = RistrettoPublic::from_random(&mut rng.clone()).into();
+            assert_eq!(
+                Err(Error::NotFound),
+                tx_out_store
+                    .get_tx_out_index_by_public_key(&unrecognized_public_key, &ro_transaction)
+            );
+        };
+        check_indexes(&tx_out_store);
+
+        // Drop the index, as if the store predated it. Opening the store rebuilds it.
+        {
+            let mut rw_transaction: RwTransaction = env.begin_rw_txn().unwrap();
+            unsafe {
+                rw_transaction
+                    .drop_db(tx_out_store.tx_out_index_by_public_key)
+                    .unwrap();
+            }
+            rw_transaction.commit().unwrap();
+        }
+        let reopened_tx_out_store = TxOutStore::new(&env, false).unwrap();
+        check_indexes(&reopened_tx_out_store);
+    }
+
     #[test]
     // `get_tx_out_by_index` should return the correct TxOut, or Error::NotFound.
     fn test_get_tx_out_by_index() {
@@ -903,6 +1164,51 @@ pub mod tx_out_store_tests {
         rw_transaction.commit().unwrap();
     }
 
+    #[test]
+    // `truncate` should remove TxOuts, and leave the Merkle tree as if they were never pushed.
+    fn test_truncate() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let tx_outs = get_tx_outs(13);
+
+        let mut rw_transaction: RwTransaction = env.begin_rw_txn().unwrap();
+        let mut root_hashes = Vec::new();
+        for tx_out in &tx_outs {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+            root_hashes.push(tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap());
+        }
+
+        tx_out_store.truncate(5, &mut rw_transaction).unwrap();
+        assert_eq!(5, tx_out_store.num_tx_outs(&rw_transaction).unwrap());
+        assert_eq!(
+            root_hashes[4],
+            tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap()
+        );
+        assert_eq!(
+            Err(Error::NotFound),
+            tx_out_store.get_tx_out_by_index(5, &rw_transaction)
+        );
+        assert_eq!(
+            Err(Error::NotFound),
+            tx_out_store.get_tx_out_index_by_hash(&tx_outs[7].hash(), &rw_transaction)
+        );
+
+        // TxOuts cannot be added by truncating.
+        assert_eq!(
+            Err(Error::IndexOutOfBounds(6)),
+            tx_out_store.truncate(6, &mut rw_transaction)
+        );
+
+        // Pushing the removed TxOuts again restores the tree.
+        for tx_out in &tx_outs[5..] {
+            tx_out_store.push(tx_out, &mut rw_transaction).unwrap();
+        }
+        assert_eq!(
+            root_hashes[12],
+            tx_out_store.get_root_merkle_hash(&rw_transaction).unwrap()
+        );
+        rw_transaction.commit().unwrap();
+    }
+
     #[test]
     fn test_containing_range() {
         // The subtree of size 2^0 containing leaf 5 contains leaves [5,5].
@@ -969,6 +1275,26 @@ pub mod tx_out_store_tests {
         assert_eq!(hash, retrieved_hash);
     }
 
+    #[test]
+    // The root hash computed from the TxOuts should match the stored one, whatever the number of
+    // TxOuts.
+    fn test_compute_root_merkle_hash() {
+        let (tx_out_store, env) = init_tx_out_store();
+        let mut rw_transaction: RwTransaction = env.begin_rw_txn().unwrap();
+        assert_eq!(
+            tx_out_store.compute_root_merkle_hash(&rw_transaction),
+            Ok(nil_hash_fn())
+        );
+
+        for tx_out in get_tx_outs(13) {
+            tx_out_store.push(&tx_out, &mut rw_transaction).unwrap();
+            assert_eq!(
+                tx_out_store.compute_root_merkle_hash(&rw_transaction),
+                tx_out_store.get_root_merkle_hash(&rw_transaction)
+            );
+        }
+    }
+
     #[test]
     fn test_get_root_merkle_hash() {
         let (tx_out_store, env) = init_tx_out_store();
@@ -7,15 +7,21 @@ pub mod uri;
 
 use crate::uri::{Destination, Uri};
 use common::logger::{create_app_logger, log, o, Logger};
+use keys::{Ed25519Pair, Ed25519Private};
 use ledger_db::{Error as LedgerDbError, Ledger, LedgerDB};
-use mobilecoin_api::{blockchain, conversions::block_num_to_s3block_path};
+use mobilecoin_api::{
+    blockchain,
+    conversions::{archive_manifest_path, block_num_to_s3block_path},
+};
 use protobuf::Message;
 use rusoto_core::{Region, RusotoError};
 use rusoto_s3::{PutObjectError, PutObjectRequest, S3Client, S3};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr, sync::Arc};
 use structopt::StructOpt;
-use transaction::{Block, BlockContents, BlockIndex, BlockSignature};
+use transaction::{
+    ArchiveManifest, Block, BlockContents, BlockIndex, BlockSignature, SignedArchiveManifest,
+};
 
 pub trait BlockHandler {
     fn handle_block(
@@ -24,6 +30,8 @@ pub trait BlockHandler {
         block_contents: &BlockContents,
         signature: &Option<BlockSignature>,
     );
+
+    fn handle_manifest(&mut self, manifest: &SignedArchiveManifest);
 }
 
 /// Block to start syncing from.
@@ -73,6 +81,25 @@ pub struct Config {
     /// State file, defaults to ~/.mc-ledger-distribution-state
     #[structopt(long)]
     pub state_file: Option<PathBuf>,
+
+    /// Key used to sign archive manifests, as a base64 DER-encoded Ed25519 private key. No
+    /// manifests are published if this is not set.
+    #[structopt(long, parse(try_from_str=keypair_from_base64))]
+    pub manifest_signer_key: Option<Arc<Ed25519Pair>>,
+
+    /// Number of blocks covered by each archive manifest. A manifest is published once every
+    /// block of a range [n * interval, (n + 1) * interval) has been written.
+    #[structopt(long, default_value = "1000")]
+    pub manifest_interval: u64,
+}
+
+fn keypair_from_base64(src: &str) -> Result<Arc<Ed25519Pair>, String> {
+    let privkey_bytes = base64::decode_config(src, base64::STANDARD)
+        .map_err(|err| format!("Could not decode private key from base64 {:?}", err))?;
+
+    let secret_key = Ed25519Private::try_from_der(privkey_bytes.as_slice())
+        .map_err(|err| format!("Could not get Ed25519Private from der {:?}", err))?;
+    Ok(Arc::new(Ed25519Pair::from(secret_key)))
 }
 
 /// State file contents.
@@ -178,6 +205,31 @@ impl BlockHandler for S3BlockWriter {
                 .expect("failed to serialize S3Block"),
         );
     }
+
+    fn handle_manifest(&mut self, manifest: &SignedArchiveManifest) {
+        log::info!(
+            self.logger,
+            "S3: Handling manifest for blocks {}-{}",
+            manifest.manifest().first_block_index,
+            manifest.manifest().end_block_index() - 1,
+        );
+
+        let dest = self
+            .path
+            .as_path()
+            .join(archive_manifest_path(manifest.manifest().first_block_index));
+
+        let dir = dest.as_path().parent().expect("failed getting parent");
+        let filename = dest.file_name().unwrap();
+
+        self.write_bytes_to_s3(
+            dir.to_str().unwrap(),
+            filename.to_str().unwrap(),
+            &blockchain::SignedArchiveManifest::from(manifest)
+                .write_to_bytes()
+                .expect("failed to serialize SignedArchiveManifest"),
+        );
+    }
 }
 
 /// Local directory block writer.
@@ -230,6 +282,53 @@ impl BlockHandler for LocalBlockWriter {
         fs::write(&dest, bytes)
             .unwrap_or_else(|_| panic!("failed writing block #{} to {:?}", block.index, dest));
     }
+
+    fn handle_manifest(&mut self, manifest: &SignedArchiveManifest) {
+        let first_block_index = manifest.manifest().first_block_index;
+        log::info!(
+            self.logger,
+            "Local: Handling manifest for blocks {}-{}",
+            first_block_index,
+            manifest.manifest().end_block_index() - 1,
+        );
+
+        let bytes = blockchain::SignedArchiveManifest::from(manifest)
+            .write_to_bytes()
+            .expect("failed to serialize SignedArchiveManifest");
+
+        let dest = self
+            .path
+            .as_path()
+            .join(archive_manifest_path(first_block_index));
+        let dir = dest.as_path().parent().expect("failed getting parent");
+
+        fs::create_dir_all(dir)
+            .unwrap_or_else(|e| panic!("failed creating directory {:?}: {:?}", dir, e));
+        fs::write(&dest, bytes).unwrap_or_else(|_| {
+            panic!(
+                "failed writing manifest for block #{} to {:?}",
+                first_block_index, dest
+            )
+        });
+    }
+}
+
+/// Creates the signed manifest for the range of `num_blocks` blocks ending just before
+/// `end_block_index`.
+fn sign_archive_manifest(
+    ledger_db: &LedgerDB,
+    end_block_index: BlockIndex,
+    num_blocks: u64,
+    keypair: &Ed25519Pair,
+) -> Result<SignedArchiveManifest, LedgerDbError> {
+    let blocks = (end_block_index - num_blocks..end_block_index)
+        .map(|block_index| ledger_db.get_block(block_index))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(
+        SignedArchiveManifest::from_manifest_and_keypair(ArchiveManifest::new(&blocks), keypair)
+            .expect("failed signing archive manifest"),
+    )
 }
 
 // Implements the ledger db polling loop
@@ -275,6 +374,11 @@ fn main() {
             .expect("Failed getting number of blocks in ledger"),
     };
 
+    assert!(
+        config.manifest_interval > 0,
+        "--manifest-interval must be positive"
+    );
+
     // Create block handler
     let mut block_handler: Box<dyn BlockHandler> = match config.destination.destination {
         Destination::S3 { path, region } => {
@@ -320,6 +424,26 @@ fn main() {
             block_handler.handle_block(&block, &block_contents, &signature);
             next_block_num += 1;
 
+            // Publish a manifest once the last block of a range has been written.
+            if let Some(keypair) = config.manifest_signer_key.as_ref() {
+                if next_block_num % config.manifest_interval == 0 {
+                    match sign_archive_manifest(
+                        &ledger_db,
+                        next_block_num,
+                        config.manifest_interval,
+                        keypair,
+                    ) {
+                        Ok(manifest) => block_handler.handle_manifest(&manifest),
+                        Err(err) => log::error!(
+                            logger,
+                            "Failed creating manifest for blocks ending at #{}: {:?}",
+                            next_block_num,
+                            err
+                        ),
+                    }
+                }
+            }
+
             let state = StateData {
                 next_block: next_block_num,
             };
@@ -20,6 +20,45 @@ lazy_static::lazy_static! {
     // Number of txouts in the ledger (by querying ledger)
     pub static ref TXO_IN_LEDGER: IntGauge = OP_COUNTERS.gauge("num_txos");
 
-    // Time it takes to perform append_block
+    // Time it takes to append a batch of blocks
     pub static ref APPEND_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("append_block");
+
+    // Blocks whose contents were fetched since this node started.
+    pub static ref BLOCKS_FETCHED_COUNT: IntCounter = OP_COUNTERS.counter("blocks_fetched_count");
+
+    // Time it takes to fetch the contents of a block, including retries
+    pub static ref FETCH_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("fetch_block");
+
+    // Number of blocks waiting to be fetched or being fetched
+    pub static ref FETCH_QUEUE_SIZE: IntGauge = OP_COUNTERS.gauge("fetch_queue_size");
+
+    // Blocks found to be safe since this node started.
+    pub static ref BLOCKS_VERIFIED_COUNT: IntCounter = OP_COUNTERS.counter("blocks_verified_count");
+
+    // Time it takes to check whether a block is safe
+    pub static ref VERIFY_BLOCK_TIME: Histogram = OP_COUNTERS.histogram("verify_block");
+
+    // Number of fetched blocks waiting for earlier blocks to be verified
+    pub static ref VERIFY_QUEUE_SIZE: IntGauge = OP_COUNTERS.gauge("verify_queue_size");
+
+    // Number of safe blocks waiting to be appended
+    pub static ref APPEND_QUEUE_SIZE: IntGauge = OP_COUNTERS.gauge("append_queue_size");
+
+    // Number of requests to a transaction source retried after a transient error.
+    pub static ref FETCH_RETRY_COUNT: IntCounter = OP_COUNTERS.counter("fetch_retry_count");
+
+    // Number of times a transaction source was banned for serving invalid blocks.
+    pub static ref SOURCE_BANNED_COUNT: IntCounter = OP_COUNTERS.counter("source_banned_count");
+
+    // Number of times a node was caught signing two different blocks for the same index.
+    pub static ref EQUIVOCATIONS_DETECTED_COUNT: IntCounter = OP_COUNTERS.counter("equivocations_detected_count");
+
+    // Number of sync attempts in which peers disagreed on a block.
+    pub static ref FORKS_DETECTED_COUNT: IntCounter = OP_COUNTERS.counter("forks_detected_count");
+
+    // Number of block signatures not stored because they were invalid, or not by a configured signer.
+    pub static ref BLOCK_SIGNATURES_REJECTED_COUNT: IntCounter = OP_COUNTERS.counter("block_signatures_rejected_count");
+
+    // Number of sync attempts in which the local ledger was found to diverge from the network.
+    pub static ref DIVERGENCES_DETECTED_COUNT: IntCounter = OP_COUNTERS.counter("divergences_detected_count");
 }
@@ -0,0 +1,308 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Detects nodes that sign two different blocks for the same block index.
+//!
+//! Each node signs the blocks it externalizes, and publishes them along with the signature. An
+//! honest node never signs two different blocks at the same index, so two such signatures by the
+//! same key are proof that the node misbehaved (or that its signing key was compromised).
+
+use crate::counters;
+use common::{
+    logger::{log, Logger},
+    LruCache,
+};
+use failure::Fail;
+use keys::Ed25519Public;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf, sync::Mutex};
+use transaction::{Block, BlockIndex, BlockSignature};
+use url::Url;
+
+/// Maximum number of (signer, block index) pairs remembered. Once full, the least recently
+/// observed pairs are forgotten.
+const MAX_OBSERVATIONS: usize = 100_000;
+
+#[derive(Debug, Fail)]
+pub enum EquivocationAlertError {
+    #[fail(display = "IO error on {:?}: {}", _0, _1)]
+    IO(PathBuf, std::io::Error),
+
+    #[fail(display = "Serialization error: {}", _0)]
+    Serialization(serde_json::Error),
+
+    #[fail(display = "Webhook error on {}: {}", _0, _1)]
+    Webhook(String, reqwest::Error),
+}
+
+impl From<serde_json::Error> for EquivocationAlertError {
+    fn from(src: serde_json::Error) -> Self {
+        EquivocationAlertError::Serialization(src)
+    }
+}
+
+/// A signed block, and where it was obtained from.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedBlockObservation {
+    /// Where the block was obtained from, e.g. the URL of an archived block.
+    pub source: String,
+
+    /// The block.
+    pub block: Block,
+
+    /// The signature over `block`.
+    pub signature: BlockSignature,
+}
+
+/// Evidence that a node signed two different blocks for the same block index.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Equivocation {
+    /// The block index both blocks claim.
+    pub block_index: BlockIndex,
+
+    /// The first signed block observed.
+    pub first: SignedBlockObservation,
+
+    /// A conflicting signed block by the same signer.
+    pub second: SignedBlockObservation,
+}
+
+/// Compares the signed blocks it is shown, and raises an alert when the same key signed two
+/// different blocks for the same block index.
+pub struct EquivocationDetector {
+    /// The first signed block observed for each (signer, block index).
+    observations: Mutex<LruCache<(Ed25519Public, BlockIndex), SignedBlockObservation>>,
+
+    /// Equivocations detected so far.
+    evidence: Mutex<Vec<Equivocation>>,
+
+    /// An optional directory where evidence is written to, one JSON file per equivocation.
+    evidence_dir: Option<PathBuf>,
+
+    /// An optional URL evidence is POSTed to as JSON.
+    webhook_url: Option<Url>,
+
+    client: reqwest::Client,
+    logger: Logger,
+}
+
+impl EquivocationDetector {
+    pub fn new(logger: Logger) -> Self {
+        Self {
+            observations: Mutex::new(LruCache::new(MAX_OBSERVATIONS)),
+            evidence: Mutex::new(Vec::new()),
+            evidence_dir: None,
+            webhook_url: None,
+            client: reqwest::Client::new(),
+            logger,
+        }
+    }
+
+    /// Sets a directory where evidence of equivocation is written to.
+    pub fn set_evidence_dir(&mut self, evidence_dir: PathBuf) {
+        self.evidence_dir = Some(evidence_dir);
+    }
+
+    /// Sets a URL evidence of equivocation is POSTed to.
+    pub fn set_webhook_url(&mut self, webhook_url: Url) {
+        self.webhook_url = Some(webhook_url);
+    }
+
+    /// Equivocations detected so far.
+    pub fn evidence(&self) -> Vec<Equivocation> {
+        self.evidence.lock().expect("mutex poisoned").clone()
+    }
+
+    /// Records a signed block, and raises an alert if its signer previously signed a different
+    /// block for the same index. The signature is assumed to have been verified.
+    ///
+    /// # Arguments
+    /// * `source` - Where the block was obtained from.
+    /// * `block` - The block.
+    /// * `signature` - A valid signature over `block`.
+    ///
+    /// Returns the evidence if this observation conflicts with an earlier one.
+    pub fn observe(
+        &self,
+        source: &str,
+        block: &Block,
+        signature: &BlockSignature,
+    ) -> Option<Equivocation> {
+        let key = (*signature.signer(), block.index);
+        let observation = SignedBlockObservation {
+            source: source.to_string(),
+            block: block.clone(),
+            signature: signature.clone(),
+        };
+
+        let equivocation = {
+            let mut observations = self.observations.lock().expect("mutex poisoned");
+            match observations.get(&key) {
+                None => {
+                    observations.put(key, observation);
+                    return None;
+                }
+                Some(first) if first.block.id == block.id => return None,
+                Some(first) => Equivocation {
+                    block_index: block.index,
+                    first: first.clone(),
+                    second: observation,
+                },
+            }
+        };
+
+        // Only alert once per pair of conflicting blocks, even if they are fetched again.
+        {
+            let mut evidence = self.evidence.lock().expect("mutex poisoned");
+            if evidence.iter().any(|known| {
+                known.first.signature.signer() == equivocation.first.signature.signer()
+                    && known.block_index == equivocation.block_index
+                    && known.second.block.id == equivocation.second.block.id
+            }) {
+                return None;
+            }
+            evidence.push(equivocation.clone());
+        }
+
+        self.alert(&equivocation);
+        Some(equivocation)
+    }
+
+    /// Logs the equivocation, counts it, and delivers the evidence to the configured
+    /// destinations.
+    fn alert(&self, equivocation: &Equivocation) {
+        log::crit!(
+            self.logger,
+            "Equivocation detected: {:?} signed block {:?} (from {}) and block {:?} (from {}) for block index {}",
+            equivocation.first.signature.signer(),
+            equivocation.first.block.id,
+            equivocation.first.source,
+            equivocation.second.block.id,
+            equivocation.second.source,
+            equivocation.block_index,
+        );
+        counters::EQUIVOCATIONS_DETECTED_COUNT.inc();
+
+        if let Err(err) = self.write_evidence(equivocation) {
+            log::error!(self.logger, "Failed writing equivocation evidence: {}", err);
+        }
+        if let Err(err) = self.post_evidence(equivocation) {
+            log::error!(self.logger, "Failed posting equivocation evidence: {}", err);
+        }
+    }
+
+    fn write_evidence(&self, equivocation: &Equivocation) -> Result<(), EquivocationAlertError> {
+        let evidence_dir = match &self.evidence_dir {
+            Some(evidence_dir) => evidence_dir,
+            None => return Ok(()),
+        };
+
+        let signer: &[u8] = equivocation.first.signature.signer().as_ref();
+        let signer_hex: String = signer.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let path = evidence_dir.join(format!(
+            "equivocation-{}-{}.json",
+            equivocation.block_index, signer_hex
+        ));
+
+        let json = serde_json::to_vec_pretty(equivocation)?;
+        fs::write(&path, json).map_err(|err| EquivocationAlertError::IO(path, err))
+    }
+
+    fn post_evidence(&self, equivocation: &Equivocation) -> Result<(), EquivocationAlertError> {
+        let webhook_url = match &self.webhook_url {
+            Some(webhook_url) => webhook_url,
+            None => return Ok(()),
+        };
+
+        self.client
+            .post(webhook_url.as_str())
+            .json(equivocation)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| EquivocationAlertError::Webhook(webhook_url.to_string(), err))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::test_with_logger;
+    use keys::{Ed25519Pair, FromRandom};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::{tx::TxOutMembershipElement, BlockContents, BLOCK_VERSION};
+
+    /// Two different blocks at index 1.
+    fn conflicting_blocks() -> (Block, Block) {
+        let origin = Block::new_origin_block(&[]);
+        let block = |key_image_byte: u8| {
+            let block_contents = BlockContents::new(vec![[key_image_byte; 32].into()], Vec::new());
+            Block::new(
+                BLOCK_VERSION,
+                &origin.id,
+                1,
+                &TxOutMembershipElement::default(),
+                &block_contents,
+            )
+        };
+        (block(1), block(2))
+    }
+
+    #[test_with_logger]
+    // Two different blocks signed by the same key for the same index should be reported.
+    fn test_detects_equivocation(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let other_signer = Ed25519Pair::from_random(&mut rng);
+        let (block_a, block_b) = conflicting_blocks();
+        let sign = |block: &Block, signer: &Ed25519Pair| {
+            BlockSignature::from_block_and_keypair(block, signer).unwrap()
+        };
+
+        let evidence_dir = TempDir::new("equivocation").unwrap();
+        let mut detector = EquivocationDetector::new(logger);
+        detector.set_evidence_dir(evidence_dir.path().to_path_buf());
+
+        // The same block from several sources, and a different block by another signer, are fine.
+        assert_eq!(
+            detector.observe("a", &block_a, &sign(&block_a, &signer)),
+            None
+        );
+        assert_eq!(
+            detector.observe("b", &block_a, &sign(&block_a, &signer)),
+            None
+        );
+        assert_eq!(
+            detector.observe("c", &block_b, &sign(&block_b, &other_signer)),
+            None
+        );
+        assert!(detector.evidence().is_empty());
+
+        // The same signer signing a different block is not.
+        let equivocation = detector
+            .observe("d", &block_b, &sign(&block_b, &signer))
+            .expect("Equivocation not detected");
+        assert_eq!(equivocation.block_index, 1);
+        assert_eq!(equivocation.first.source, "a");
+        assert_eq!(equivocation.first.block, block_a);
+        assert_eq!(equivocation.second.source, "d");
+        assert_eq!(equivocation.second.block, block_b);
+        assert_eq!(detector.evidence(), vec![equivocation.clone()]);
+
+        // Fetching the conflicting block again should not raise another alert.
+        assert_eq!(
+            detector.observe("e", &block_b, &sign(&block_b, &signer)),
+            None
+        );
+        assert_eq!(detector.evidence().len(), 1);
+
+        // The evidence should have been written to disk.
+        let files: Vec<_> = fs::read_dir(evidence_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let written: Equivocation = serde_json::from_slice(&fs::read(&files[0]).unwrap()).unwrap();
+        assert_eq!(written, equivocation);
+    }
+}
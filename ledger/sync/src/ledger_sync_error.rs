@@ -7,6 +7,7 @@ use failure::Fail;
 use ledger_db::Error as LedgerDbError;
 use mcconnection::Error as ConnectionError;
 use retry::Error as RetryError;
+use transaction::{BlockID, BlockIndex};
 
 #[derive(Debug, Fail)]
 pub enum LedgerSyncError {
@@ -37,6 +38,16 @@ pub enum LedgerSyncError {
 
     #[fail(display = "No transaction data.")]
     NoTransactionData,
+
+    #[fail(
+        display = "Local block {} is {:?}, but the network agrees on {:?}.",
+        block_index, local_block_id, network_block_id
+    )]
+    Divergence {
+        block_index: BlockIndex,
+        local_block_id: BlockID,
+        network_block_id: BlockID,
+    },
 }
 
 impl<TFE: TransactionFetcherError + 'static> From<TFE> for LedgerSyncError {
@@ -5,13 +5,17 @@
 //! (`TransactionsFetcher`) for fetching actual transaction data.
 
 use crate::{
-    counters, ledger_sync_error::LedgerSyncError, network_state_trait::NetworkState,
+    counters,
+    ledger_sync_error::LedgerSyncError,
+    network_state_trait::NetworkState,
+    sync_pipeline::{SafeBlock, SyncPipeline, SyncPipelineConfig},
     transactions_fetcher_trait::TransactionsFetcher,
 };
 use common::{
     logger::{log, Logger},
     HashMap, HashSet, ResponderId,
 };
+use keys::Ed25519Public;
 use ledger_db::Ledger;
 use mcconnection::{
     BlockchainConnection, Connection, ConnectionManager, RetryableBlockchainConnection,
@@ -19,14 +23,16 @@ use mcconnection::{
 use mcuri::ConnectionUri;
 use retry::delay::Fibonacci;
 use std::{
+    cmp::max,
     collections::BTreeMap,
     iter::FromIterator,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::{Duration, Instant},
+    time::Duration,
 };
 use transaction::{
-    compute_block_id, ring_signature::KeyImage, Block, BlockContents, BlockID, BlockIndex,
+    compute_block_id, fee_split::FeeSplit, ring_signature::KeyImage, Block, BlockContents, BlockID,
+    BlockIndex, BlockSignature,
 };
 
 /// Maximal amount to allow for getting block and transaction data.
@@ -47,12 +53,27 @@ pub struct LedgerSyncService<L: Ledger, BC: BlockchainConnection, TF: Transactio
     get_blocks_timeout: Duration,
     get_transactions_timeout: Duration,
 
+    /// Whether to establish the consensus tip from peers' block IDs before fetching any blocks.
+    header_first: bool,
+
+    /// Queue depths and parallelism of fetching, verifying and appending blocks.
+    pipeline_config: SyncPipelineConfig,
+
+    /// Signers whose block signatures are stored along with the blocks. Any signer if empty.
+    block_signers: Vec<Ed25519Public>,
+
+    /// How the network splits block fees. Fee outputs are not checked if None.
+    fee_split: Option<FeeSplit>,
+
     /// Logger.
     logger: Logger,
 }
 
-impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 'static>
-    LedgerSyncService<L, BC, TF>
+impl<
+        L: Ledger + 'static,
+        BC: BlockchainConnection + 'static,
+        TF: TransactionsFetcher + 'static,
+    > LedgerSyncService<L, BC, TF>
 {
     /// Creates a new SyncService.
     pub fn new(
@@ -74,10 +95,43 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             transactions_fetcher: Arc::new(transactions_fetcher),
             get_blocks_timeout: DEFAULT_GET_BLOCKS_TIMEOUT,
             get_transactions_timeout: DEFAULT_GET_TRANSACTIONS_TIMEOUT,
+            header_first: false,
+            pipeline_config: SyncPipelineConfig::default(),
+            block_signers: Vec::new(),
+            fee_split: None,
             logger,
         }
     }
 
+    /// Fetch only block IDs from every peer to establish the consensus tip, and then fetch the
+    /// blocks up to it from a single peer that agrees on it, instead of fetching blocks from every
+    /// peer and comparing them.
+    pub fn set_header_first(&mut self, header_first: bool) {
+        self.header_first = header_first;
+    }
+
+    /// Set the queue depths and parallelism of fetching, verifying and appending blocks.
+    pub fn set_pipeline_config(&mut self, pipeline_config: SyncPipelineConfig) {
+        self.pipeline_config = pipeline_config;
+    }
+
+    /// The queue depths and parallelism of fetching, verifying and appending blocks.
+    pub fn pipeline_config(&self) -> &SyncPipelineConfig {
+        &self.pipeline_config
+    }
+
+    /// Only store the signatures of appended blocks that were made by one of `block_signers`.
+    /// Signatures by any signer are stored if empty, as long as they are valid.
+    pub fn set_block_signers(&mut self, block_signers: Vec<Ed25519Public>) {
+        self.block_signers = block_signers;
+    }
+
+    /// Only append blocks whose fee outputs follow `fee_split`, starting with the first block
+    /// version whose fee outputs can be checked.
+    pub fn set_fee_split(&mut self, fee_split: Option<FeeSplit>) {
+        self.fee_split = fee_split;
+    }
+
     /// Check if our ledger is currently behind.
     pub fn is_behind<NS: NetworkState>(&self, network_state: &NS) -> bool {
         let num_blocks: u64 = self
@@ -104,6 +158,13 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
     ///     * None of the key images in the block have appeared before.
     /// 5. Append safe blocks to the ledger.
     ///
+    /// Steps 3 to 5 overlap: blocks are streamed through a bounded pipeline, so that blocks are
+    /// appended while later ones are still being downloaded (see `SyncPipelineConfig`).
+    ///
+    /// While the network is further ahead than the blocks synced by this attempt, safe blocks are
+    /// appended in batches of `SyncPipelineConfig::append_batch_size` blocks, each written at once.
+    /// Otherwise, each block is appended as soon as it is safe.
+    ///
     /// # Arguments
     /// * `network_state` - Current state of the network, used to determine if we're behind.
     /// * `limit` - Maximum number of blocks to add to the ledger.
@@ -112,76 +173,60 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         network_state: &impl NetworkState,
         limit: u32,
     ) -> Result<(), LedgerSyncError> {
-        let (responder_ids, _, potentially_safe_blocks) = self
-            .get_potentially_safe_blocks(network_state, limit)
-            .ok_or(LedgerSyncError::NoSafeBlocks)?;
+        let (responder_ids, sync_to_block_index, potentially_safe_blocks) =
+            match self.get_potentially_safe_blocks(network_state, limit) {
+                Some(potentially_safe_blocks) => potentially_safe_blocks,
+                None => {
+                    // Peers serve no blocks that extend the local ledger if it diverged from
+                    // theirs, so tell this apart from the network being unavailable.
+                    self.check_for_divergence(network_state, limit)?;
+                    return Err(LedgerSyncError::NoSafeBlocks);
+                }
+            };
 
         if potentially_safe_blocks.is_empty() {
             return Err(LedgerSyncError::EmptyBlockVec);
         }
 
-        let num_potentially_safe_blocks = potentially_safe_blocks.len();
-
-        // Get transactions.
-        let block_index_to_opt_transactions: BTreeMap<BlockIndex, Option<BlockContents>> =
-            get_block_contents(
-                self.transactions_fetcher.clone(),
-                &responder_ids,
-                &potentially_safe_blocks,
-                self.get_transactions_timeout,
-                &self.logger,
-            );
-
-        let mut blocks_and_contents: Vec<(Block, BlockContents)> = Vec::new();
-
-        {
-            // Populate `blocks_with_transactions`. This just returns all (block, transactions) until
-            // it reaches a None.
-            let mut block_index_to_transactions: BTreeMap<BlockIndex, BlockContents> =
-                block_index_to_opt_transactions
-                    .into_iter()
-                    .take_while(|(_block_index, transactions_opt)| transactions_opt.is_some())
-                    .map(|(block_index, transactions_opt)| (block_index, transactions_opt.unwrap()))
-                    .collect();
+        // Fetch, verify and append blocks until one cannot be fetched or is not safe.
+        let pipeline = SyncPipeline::start(
+            &self.pipeline_config,
+            self.ledger.clone(),
+            self.transactions_fetcher.clone(),
+            &responder_ids,
+            potentially_safe_blocks,
+            self.fee_split.clone(),
+            self.get_transactions_timeout,
+            &self.logger,
+        );
 
-            let block_index_to_block: BTreeMap<BlockIndex, Block> = potentially_safe_blocks
-                .into_iter()
-                .map(|block| (block.index, block))
-                .collect();
-
-            // Join blocks and transactions, allowing for the possibility that transactions
-            // may not be available for some blocks due to failed network requests for transactions.
-            for (block_index, block) in block_index_to_block {
-                if let Some(block_contents) = block_index_to_transactions.remove(&block_index) {
-                    blocks_and_contents.push((block, block_contents));
-                } else {
-                    log::error!(self.logger, "No transactions for block {:?}", block);
-                    break;
-                }
+        let append_batch_size = if network_state.is_behind(sync_to_block_index) {
+            max(self.pipeline_config.append_batch_size, 1)
+        } else {
+            1
+        };
+        let mut num_appended_blocks = 0;
+        let mut safe_blocks: Vec<SafeBlock> = Vec::with_capacity(append_batch_size);
+        while let Some(safe_block) = pipeline.next_safe_block() {
+            safe_blocks.push(safe_block);
+            if safe_blocks.len() >= append_batch_size {
+                num_appended_blocks += self.append_safe_blocks(safe_blocks.split_off(0))?;
             }
         }
-
-        if blocks_with_transactions.is_empty() {
-            log::error!(
-                self.logger,
-                "Identified {} safe blocks but was unable to get transaction data",
-                num_potentially_safe_blocks,
-            );
-            return Err(LedgerSyncError::NoTransactionData);
+        if !safe_blocks.is_empty() {
+            num_appended_blocks += self.append_safe_blocks(safe_blocks)?;
         }
+        pipeline.finish()?;
 
-        // Process safe blocks.
-        log::trace!(
-            &self.logger,
-            "Identifying safe blocks out of {} blocks",
-            blocks_and_contents.len()
-        );
-        if let Ok(safe_blocks) =
-            identify_safe_blocks(&self.ledger, &blocks_and_contents, &self.logger)
-        {
-            self.append_safe_blocks(&safe_blocks)?;
-        } else {
+        if num_appended_blocks == 0 {
             log::info!(self.logger, "No safe blocks.");
+        } else {
+            log::info!(
+                self.logger,
+                "Appended {} blocks to ledger, which now has {} blocks",
+                num_appended_blocks,
+                self.ledger.num_blocks()?,
+            );
         }
 
         Ok(())
@@ -194,8 +239,9 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
     /// be fully "safe", we must verify the block hashes from the full set of transactions
     /// corresponding to that block.
     ///
-    /// This method is potentially costly: it queries blocks from all peers. It should not be
-    /// performed unless it has been determined that the local ledger is out of sync.
+    /// This method is potentially costly: it queries blocks from all peers, or only their block
+    /// IDs in header-first mode. It should not be performed unless it has been determined that the
+    /// local ledger is out of sync.
     ///
     /// # Arguments
     /// * `network_state` - Current state of the network, used to determine if we're behind.
@@ -217,6 +263,10 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
             next_block_index + BlockIndex::from(limit)
         );
 
+        if self.header_first {
+            return self.get_potentially_safe_blocks_header_first(network_state, last_block, limit);
+        }
+
         let node_to_blocks: HashMap<ResponderId, Vec<Block>> = get_blocks(
             &self.manager,
             last_block,
@@ -236,35 +286,14 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
 
         let grouping: BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>> =
             group_by_block(&node_to_blocks);
-
-        // If sync_target is Some, it indicates that the local ledger should attempt to be synced
-        // from the given nodes, up to and including the Block with the given BlockID in BlockIndex.
-        let mut sync_target: Option<(BlockIndex, BlockID, Vec<ResponderId>)> = None;
-
-        // Iterate over groupings, starting with the highest block index.
-        // Starting with the highest block index is a greedy strategy, and should be more efficient
-        // in the normal case where all nodes agree, or when a new ledger must download a large
-        // number of blocks.
-        'outer: for (block_index, block_id_to_nodes) in grouping.iter().rev() {
-            for (block_id, responder_ids) in block_id_to_nodes.iter() {
-                if network_state.is_blocking_and_quorum(&responder_ids) {
-                    // It should be possible to sync with these nodes up to `block_id` at `block_index`.
-                    //
-                    // Note: in the event of a network fork, there may be multiple distinct sets of
-                    // nodes that could be chosen here. Arbitrarily, we take the first such set of nodes.
-                    let node_vec: Vec<ResponderId> = responder_ids.iter().cloned().collect();
-                    sync_target = Some((*block_index, block_id.clone(), node_vec));
-                    break 'outer;
-                }
-            }
-        }
+        report_fork(&grouping, &self.logger);
 
         // Return None if no blocks are available.
-        sync_target.as_ref()?;
+        let (sync_to_block_index, _block_id, responder_ids) =
+            find_sync_target(&grouping, network_state)?;
 
         // All nodes in `responder_ids` should have the same blocks up to `sync_to_block_index`.
         // Copy those blocks from one of the nodes.
-        let (sync_to_block_index, _block_id, responder_ids) = sync_target.unwrap();
         if let Some(responder_id) = responder_ids.get(0) {
             if let Some(ref blocks) = node_to_blocks.get(responder_id) {
                 let blocks_to_sync: Vec<Block> = blocks
@@ -287,41 +316,153 @@ impl<L: Ledger, BC: BlockchainConnection + 'static, TF: TransactionsFetcher + 's
         }
     }
 
-    /// Append safe blocks to the local ledger.
-    fn append_safe_blocks(
+    /// Identifies potentially safe blocks by first fetching block IDs from all peers, and then
+    /// fetching the blocks up to the consensus tip from one of the peers that agree on it.
+    ///
+    /// Since each block ID commits to its parent's ID, a chain of valid blocks that ends in the
+    /// agreed upon tip is the chain the peers agree on.
+    fn get_potentially_safe_blocks_header_first(
         &mut self,
-        blocks_and_contents: &[(Block, BlockContents)],
-    ) -> Result<(), LedgerSyncError> {
-        log::info!(
-            self.logger,
-            "Appending {} blocks to ledger, which currently has {} blocks",
-            blocks_and_contents.len(),
-            self.ledger
-                .num_blocks()
-                .expect("failed getting number of blocks"),
-        );
-        common::trace_time!(
-            self.logger,
-            "Appended {} blocks to ledger",
-            blocks_and_contents.len()
+        network_state: &impl NetworkState,
+        last_block: Block,
+        limit: u32,
+    ) -> Option<(Vec<ResponderId>, BlockIndex, Vec<Block>)> {
+        let first_block_index = last_block.index + 1;
+        let node_to_block_ids: HashMap<ResponderId, Vec<BlockID>> = get_block_ids(
+            &self.manager,
+            first_block_index,
+            limit,
+            self.get_blocks_timeout,
+            &self.logger,
         );
 
-        for (block, contents) in blocks_and_contents {
+        let grouping = group_by_block_id(first_block_index, &node_to_block_ids);
+        report_fork(&grouping, &self.logger);
+
+        let (sync_to_block_index, tip_block_id, responder_ids) =
+            find_sync_target(&grouping, network_state)?;
+
+        for responder_id in &responder_ids {
+            let conn = match self.manager.conn(responder_id) {
+                Some(conn) => conn,
+                None => continue,
+            };
+
+            match conn
+                .fetch_blocks(
+                    first_block_index..sync_to_block_index + 1,
+                    Fibonacci::from_millis(10).take(5),
+                )
+                .map_err(LedgerSyncError::Consensus)
+                .and_then(|blocks| verify_block_ids(blocks, &last_block))
             {
-                let _timer = counters::APPEND_BLOCK_TIME.start_timer();
-                self.ledger.append_block(block, contents, None)?;
+                Ok(blocks) => {
+                    if blocks.last().map(|block| &block.id) == Some(&tip_block_id) {
+                        return Some((responder_ids.clone(), sync_to_block_index, blocks));
+                    }
+                    log::warn!(
+                        self.logger,
+                        "{} did not serve the blocks up to {} it reported",
+                        conn,
+                        sync_to_block_index
+                    );
+                }
+                Err(err) => {
+                    log::warn!(
+                        self.logger,
+                        "Failed to retrieve blocks from {}: {:?}",
+                        conn,
+                        err
+                    );
+                }
             }
+        }
+
+        log::error!(
+            self.logger,
+            "None of {:?} served blocks up to {}",
+            responder_ids,
+            sync_to_block_index
+        );
+        None
+    }
+
+    /// Checks whether the most recent blocks of the local ledger differ from the blocks that a
+    /// sufficient set of peers agree on.
+    ///
+    /// # Arguments
+    /// * `network_state` - Current state of the network.
+    /// * `limit` - number of the most recent local blocks to check.
+    ///
+    /// Returns `LedgerSyncError::Divergence` for the lowest such block, if any.
+    fn check_for_divergence(
+        &self,
+        network_state: &impl NetworkState,
+        limit: u32,
+    ) -> Result<(), LedgerSyncError> {
+        let num_blocks = self.ledger.num_blocks()?;
+        let first_block_index = num_blocks.saturating_sub(u64::from(limit));
+        let node_to_block_ids: HashMap<ResponderId, Vec<BlockID>> = get_block_ids(
+            &self.manager,
+            first_block_index,
+            (num_blocks - first_block_index) as u32,
+            self.get_blocks_timeout,
+            &self.logger,
+        );
+        let grouping = group_by_block_id(first_block_index, &node_to_block_ids);
+
+        if let Some((block_index, local_block_id, network_block_id)) =
+            find_divergence(&self.ledger, &grouping, network_state)?
+        {
+            log::error!(
+                self.logger,
+                "Local ledger diverged from the network at block {}: local block {:?}, network block {:?}",
+                block_index,
+                local_block_id,
+                network_block_id
+            );
+            counters::DIVERGENCES_DETECTED_COUNT.inc();
+            return Err(LedgerSyncError::Divergence {
+                block_index,
+                local_block_id,
+                network_block_id,
+            });
+        }
+
+        Ok(())
+    }
 
-            // FIXME: MC-365 Move ledger counters into ledger_db
+    /// Append consecutive safe blocks to the local ledger at once, along with their signatures if
+    /// they are accepted. Returns the number of appended blocks.
+    fn append_safe_blocks(
+        &mut self,
+        safe_blocks: Vec<SafeBlock>,
+    ) -> Result<usize, LedgerSyncError> {
+        let safe_blocks: Vec<SafeBlock> = safe_blocks
+            .into_iter()
+            .map(|(block, block_contents, signature)| {
+                let signature = signature.filter(|signature| {
+                    is_accepted_signature(&block, signature, &self.block_signers, &self.logger)
+                });
+                (block, block_contents, signature)
+            })
+            .collect();
+        {
+            let _timer = counters::APPEND_BLOCK_TIME.start_timer();
+            self.ledger.append_blocks(&safe_blocks)?;
+        }
+
+        // FIXME: MC-365 Move ledger counters into ledger_db
+        for (_block, block_contents, _signature) in &safe_blocks {
             counters::BLOCKS_WRITTEN_COUNT.inc();
-            counters::BLOCKS_IN_LEDGER.set(self.ledger.num_blocks()? as i64);
-            for _output in &contents.outputs {
+            for _output in &block_contents.outputs {
                 counters::TXO_WRITTEN_COUNT.inc();
             }
-            counters::TXO_IN_LEDGER.set(self.ledger.num_txos()? as i64);
         }
+        counters::BLOCKS_IN_LEDGER.set(self.ledger.num_blocks()? as i64);
+        counters::TXO_IN_LEDGER.set(self.ledger.num_txos()? as i64);
 
-        Ok(())
+        Ok(safe_blocks.len())
     }
 }
 
@@ -357,7 +498,7 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
             .spawn(move || {
                 let &(ref lock, ref condvar) = &*thread_results_and_condvar;
 
-                // Perform call to get the blocks from the peer. Blocks are later verified by `identify_safe_blocks`.
+                // Perform call to get the blocks from the peer. Blocks are later verified by `SafeBlockChecker`.
                 let start = thread_append_after_block.index + 1;
                 let end = start + u64::from(limit);
                 let mut blocks_result = Vec::new();
@@ -417,6 +558,92 @@ fn get_blocks<BC: BlockchainConnection + 'static>(
     )
 }
 
+/// Gets the IDs of the blocks that could potentially be appended, starting at
+/// `first_block_index`, from each peer.
+///
+/// # Arguments
+/// * `manager` - Manager instance.
+/// * `first_block_index` - Index of the first block to get the ID of.
+/// * `limit` - Maximal number of block IDs to fetch.
+/// * `timeout` - Overall request timeout.
+///
+/// Peers are queried concurrently, and any successful responses collected before a timeout occurs are returned.
+fn get_block_ids<BC: BlockchainConnection + 'static>(
+    manager: &ConnectionManager<BC>,
+    first_block_index: BlockIndex,
+    limit: u32,
+    timeout: Duration,
+    logger: &Logger,
+) -> HashMap<ResponderId, Vec<BlockID>> {
+    type ResultsMap = HashMap<ResponderId, Vec<BlockID>>;
+    let results_and_condvar = Arc::new((Mutex::new(ResultsMap::default()), Condvar::new()));
+
+    for conn in manager.conns().into_iter() {
+        let responder_id = match conn.uri().responder_id() {
+            Ok(responder_id) => responder_id,
+            Err(err) => {
+                log::warn!(logger, "Could not get responder id of {}: {:?}", conn, err);
+                continue;
+            }
+        };
+
+        let thread_results_and_condvar = results_and_condvar.clone();
+        let logger = logger.clone();
+        thread::Builder::new()
+            .name(format!("GetBlockIds:{}", conn))
+            .spawn(move || {
+                let &(ref lock, ref condvar) = &*thread_results_and_condvar;
+
+                let end = first_block_index + u64::from(limit);
+                let block_ids = match conn
+                    .fetch_block_ids(first_block_index..end, Fibonacci::from_millis(10).take(5))
+                {
+                    Ok(block_ids) => {
+                        log::debug!(
+                            logger,
+                            "Received {} block ids from {}",
+                            block_ids.len(),
+                            conn
+                        );
+                        block_ids
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            logger,
+                            "Failed to retrieve block ids from {}: {:?}",
+                            conn,
+                            err
+                        );
+                        Vec::new()
+                    }
+                };
+
+                // Always insert a result, so that waiting below ends as soon as every peer
+                // responded.
+                let mut results = lock.lock().expect("mutex poisoned");
+                results.insert(responder_id, block_ids);
+                condvar.notify_one();
+            })
+            .expect("Failed spawning GetBlockIds thread!");
+    }
+
+    // Wait until either we get all results, or a timeout happens.
+    let &(ref lock, ref condvar) = &*results_and_condvar;
+    let (worker_results, _wait_timeout_result) = condvar
+        .wait_timeout_until(lock.lock().unwrap(), timeout, |ref mut results| {
+            results.len() == manager.len()
+        })
+        .expect("waiting on condvar failed");
+
+    // Filter out results with no block ids
+    HashMap::from_iter(
+        worker_results
+            .clone()
+            .into_iter()
+            .filter(|(_responder_id, block_ids)| !block_ids.is_empty()),
+    )
+}
+
 fn verify_block_ids(
     blocks: Vec<Block>,
     append_after_block: &Block,
@@ -471,244 +698,207 @@ fn group_by_block(
     block_index_to_grouping
 }
 
-/// Gets all transactions for each block in a list of Blocks.
-///
-/// It is assumed that all peers have identical Block IDs for the given blocks, so it is
-/// sufficient to obtain each transaction from a single peer. Specifically, this is expected to
-/// be used in conjunction with `group_by_block` which identifies peers who have identical blocks.
+/// For each block index, group nodes according to the ID of the block they externalized (if any).
 ///
 /// # Arguments
-/// * `transactions_fetcher` - The mechanism used for fetching transaction contents for a given
-/// block.
-/// * `safe_responder_ids` - ResponderIds that have been identified as agreeing with eachother on the
-///                     `blocks` we want to fetch.
-/// * `blocks` - List of blocks to fetch transactions for.
-/// * `timeout` - Overall request timeout.
-///
-/// Peers are queried concurrently. Currently, this method will run indefinitely until all
-/// transactions have been retrieved.
-fn get_block_contents<TF: TransactionsFetcher + 'static>(
-    transactions_fetcher: Arc<TF>,
-    safe_responder_ids: &[ResponderId],
-    blocks: &[Block],
-    timeout: Duration,
-    logger: &Logger,
-) -> BTreeMap<BlockIndex, Option<BlockContents>> {
-    type ResultsMap = BTreeMap<BlockIndex, Option<BlockContents>>;
-
-    enum Msg {
-        ProcessBlock {
-            // Block we are trying to fetch transactions for.
-            block: Block,
-
-            // How many attempts have we made so far (this is used for calculating retry delays).
-            num_attempts: u64,
-        },
-        Stop,
-    }
+/// * `first_block_index` - Index of the block the first ID of each node is for.
+/// * `node_to_block_ids` - mapping from ResponderId to the IDs of consecutive Blocks externalized
+///   by that node.
+fn group_by_block_id(
+    first_block_index: BlockIndex,
+    node_to_block_ids: &HashMap<ResponderId, Vec<BlockID>>,
+) -> BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>> {
+    let mut block_index_to_grouping: BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>> =
+        BTreeMap::new();
 
-    // The channel is going to hold the list of pending blocks we still need to get transactions
-    // for.
-    let (sender, receiver) = crossbeam_channel::bounded(blocks.len());
-    for block in blocks.iter().cloned() {
-        sender
-            .send(Msg::ProcessBlock {
-                block,
-                num_attempts: 0,
-            })
-            .expect("failed sending to channel");
+    for (responder_id, block_ids) in node_to_block_ids {
+        for (block_index, block_id) in (first_block_index..).zip(block_ids.iter()) {
+            block_index_to_grouping
+                .entry(block_index)
+                .or_insert_with(HashMap::default)
+                .entry(block_id.clone())
+                .or_insert_with(HashSet::default)
+                .insert(responder_id.clone());
+        }
     }
+    block_index_to_grouping
+}
 
-    let results_and_condvar = Arc::new((Mutex::new(ResultsMap::new()), Condvar::new()));
-    let deadline = Instant::now() + timeout;
-
-    // Spawn worker threads.
-    let mut thread_handles = Vec::new();
+/// Finds the highest block that a blocking set of peers, which forms a quorum with the local
+/// node, agree on.
+///
+/// Returns the index and ID of that block, and the peers that agree on it, or None if there is
+/// no such block.
+fn find_sync_target(
+    grouping: &BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>>,
+    network_state: &impl NetworkState,
+) -> Option<(BlockIndex, BlockID, Vec<ResponderId>)> {
+    // Iterate over groupings, starting with the highest block index.
+    // Starting with the highest block index is a greedy strategy, and should be more efficient
+    // in the normal case where all nodes agree, or when a new ledger must download a large
+    // number of blocks.
+    for (block_index, block_id_to_nodes) in grouping.iter().rev() {
+        for (block_id, responder_ids) in block_id_to_nodes.iter() {
+            if network_state.is_blocking_and_quorum(&responder_ids) {
+                // It should be possible to sync with these nodes up to `block_id` at `block_index`.
+                //
+                // Note: in the event of a network fork, there may be multiple distinct sets of
+                // nodes that could be chosen here. Arbitrarily, we take the first such set of nodes.
+                let node_vec: Vec<ResponderId> = responder_ids.iter().cloned().collect();
+                return Some((*block_index, block_id.clone(), node_vec));
+            }
+        }
+    }
+    None
+}
 
-    // TODO: hardcoded limit
-    let num_workers = std::cmp::min(5, blocks.len());
-    for worker_num in 0..num_workers {
-        let thread_results_and_condvar = results_and_condvar.clone();
-        let thread_sender = sender.clone();
-        let thread_receiver = receiver.clone();
-        let thread_logger = logger.clone();
-        let thread_transactions_fetcher = transactions_fetcher.clone();
-        let thread_safe_responder_ids = safe_responder_ids.to_owned();
-
-        let thread_handle = thread::Builder::new()
-            .name(format!("GetTxs:{}", worker_num))
-            .spawn(move || {
-                let &(ref lock, ref condvar) = &*thread_results_and_condvar;
+/// Finds the lowest block index at which a blocking set of peers, which forms a quorum with the
+/// local node, agree on a block that differs from the one in the local ledger.
+///
+/// Returns the index, the ID of the local block and the ID of the block the peers agree on, or
+/// None if the local ledger agrees with the peers on every block in `grouping`.
+fn find_divergence<L: Ledger>(
+    ledger: &L,
+    grouping: &BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>>,
+    network_state: &impl NetworkState,
+) -> Result<Option<(BlockIndex, BlockID, BlockID)>, LedgerSyncError> {
+    let num_blocks = ledger.num_blocks()?;
+    for (block_index, block_id_to_nodes) in grouping.range(..num_blocks) {
+        let local_block_id = ledger.get_block(*block_index)?.id;
+        for (block_id, responder_ids) in block_id_to_nodes.iter() {
+            if *block_id != local_block_id && network_state.is_blocking_and_quorum(responder_ids) {
+                return Ok(Some((*block_index, local_block_id, block_id.clone())));
+            }
+        }
+    }
+    Ok(None)
+}
 
-                for msg in thread_receiver.iter() {
-                    match msg {
-                        Msg::ProcessBlock {
-                            block,
-                            num_attempts,
-                        } => {
-                            // Check for timeout.
-                            if Instant::now() > deadline {
-                                log::error!(
-                                    thread_logger,
-                                    "Worker {} giving up on block {}: deadline exceeded",
-                                    worker_num,
-                                    block.index,
-                                );
-
-                                let mut results = lock.lock().expect("mutex poisoned");
-                                results.insert(block.index, None);
-                                condvar.notify_one();
-                                continue;
-                            }
-
-                            // Try and get contents of this block.
-                            log::trace!(
-                                thread_logger,
-                                "Worker {} attempting block {}",
-                                worker_num,
-                                block.index
-                            );
-
-                            match thread_transactions_fetcher
-                                .get_block_contents(thread_safe_responder_ids.as_slice(), &block)
-                                .map_err(LedgerSyncError::from)
-                                .and_then(|block_contents| {
-                                    let contents_hash = block_contents.hash();
-                                    if contents_hash != block.contents_hash {
-                                        log::debug!(
-                                            thread_logger,
-                                            "Contents and block mismatch: {:02x?} vs {:02x?}",
-                                            contents_hash,
-                                            block.contents_hash,
-                                        );
-                                        Err(LedgerSyncError::TransactionsAndBlockMismatch)
-                                    } else {
-                                        Ok(block_contents)
-                                    }
-                                }) {
-                                Ok(block_contents) => {
-                                    // Log
-                                    log::trace!(
-                                        thread_logger,
-                                        "Worker {} got contents for block {}",
-                                        worker_num,
-                                        block.index
-                                    );
-
-                                    // passing the actual block and not just a block index.
-                                    let mut results = lock.lock().expect("mutex poisoned");
-                                    let old_result =
-                                        results.insert(block.index, Some(block_contents));
-
-                                    // We should encounter each block index only once.
-                                    assert!(old_result.is_none());
-
-                                    // Signal condition variable to check if maybe we're done.
-                                    condvar.notify_one();
-                                }
-
-                                Err(err) => {
-                                    // Log
-                                    log::error!(
-                                        thread_logger,
-                                        "Worker {} failed getting transactions for block {}: {}",
-                                        worker_num,
-                                        block.index,
-                                        err
-                                    );
-
-                                    // Sleep, with a linearly increasing delay. This prevents endless retries
-                                    // as long as the deadline is not exceeded.
-                                    thread::sleep(Duration::from_secs(num_attempts + 1));
-
-                                    // Put back to queue for a retry
-                                    thread_sender
-                                        .send(Msg::ProcessBlock {
-                                            block,
-                                            num_attempts: num_attempts + 1,
-                                        })
-                                        .expect("failed sending to channel");
-                                }
-                            }
-                        }
-                        Msg::Stop => {
-                            return;
-                        }
-                    }
-                }
-            })
-            .expect("Failed spawning GetBlocks thread!");
+/// Reports the lowest block index at which peers disagree on the block, if any. Past that index
+/// peers are on different forks, and only the fork a quorum agrees on can be synced.
+fn report_fork(
+    grouping: &BTreeMap<BlockIndex, HashMap<BlockID, HashSet<ResponderId>>>,
+    logger: &Logger,
+) -> Option<BlockIndex> {
+    let (block_index, block_id_to_nodes) = grouping
+        .iter()
+        .find(|(_block_index, block_id_to_nodes)| block_id_to_nodes.len() > 1)?;
+
+    log::warn!(
+        logger,
+        "Peers disagree on block {}: {:?}",
+        block_index,
+        block_id_to_nodes
+    );
+    counters::FORKS_DETECTED_COUNT.inc();
+    Some(*block_index)
+}
 
-        thread_handles.push(thread_handle);
+/// Checks whether the signature of a block is valid, and made by one of `block_signers`, or by
+/// anyone if `block_signers` is empty.
+fn is_accepted_signature(
+    block: &Block,
+    signature: &BlockSignature,
+    block_signers: &[Ed25519Public],
+    logger: &Logger,
+) -> bool {
+    if !block_signers.is_empty() && !block_signers.contains(signature.signer()) {
+        log::warn!(
+            logger,
+            "Not storing the signature of block {}: {:?} is not a configured signer",
+            block.index,
+            signature.signer()
+        );
+        counters::BLOCK_SIGNATURES_REJECTED_COUNT.inc();
+        return false;
     }
 
-    // Wait until we get all results, or we timeout. Note that timeout checking is handled inside
-    // the worker threads.
-    log::trace!(logger, "Waiting on {} results", blocks.len());
-    let &(ref lock, ref condvar) = &*results_and_condvar;
-    let results = condvar
-        .wait_until(lock.lock().unwrap(), |ref mut results| {
-            results.len() == blocks.len()
-        })
-        .expect("waiting on condvar failed");
+    if let Err(err) = signature.verify(block) {
+        log::warn!(
+            logger,
+            "Not storing the signature of block {}: {:?}",
+            block.index,
+            err
+        );
+        counters::BLOCK_SIGNATURES_REJECTED_COUNT.inc();
+        return false;
+    }
 
-    // Sanity - we will only get here when results.len() == blocks.len(), which only happens when
-    // everything in the queue was proceesed.
-    assert!(receiver.is_empty());
+    true
+}
 
-    // Tell all threads to stop.
-    for _ in 0..blocks.len() {
-        sender.send(Msg::Stop).expect("failed sending to channel");
-    }
+/// Fetches the contents of a block from the given peers, and checks that they match the block.
+/// The signature of the block, if any was served, is returned unchecked.
+pub(crate) fn fetch_block_contents<TF: TransactionsFetcher>(
+    transactions_fetcher: &TF,
+    safe_responder_ids: &[ResponderId],
+    block: &Block,
+    logger: &Logger,
+) -> Result<(BlockContents, Option<BlockSignature>), LedgerSyncError> {
+    let (block_contents, signature) =
+        transactions_fetcher.get_block_contents_and_signature(safe_responder_ids, block)?;
 
-    // Wait for all threads to stop
-    log::trace!(logger, "Joining worker threads...");
-    for thread_handle in thread_handles.into_iter() {
-        if let Err(err) = thread_handle.join() {
-            log::error!(
-                logger,
-                "Failed joining get_transactions worker thread: {:?}",
-                err
-            );
-        }
+    let contents_hash = block_contents.hash();
+    if contents_hash != block.contents_hash {
+        log::debug!(
+            logger,
+            "Contents and block mismatch: {:02x?} vs {:02x?}",
+            contents_hash,
+            block.contents_hash,
+        );
+        return Err(LedgerSyncError::TransactionsAndBlockMismatch);
     }
 
-    // Return results.
-    results.clone()
+    Ok((block_contents, signature))
 }
 
-/// Identify a sequence of blocks that are safe to append to the local node's ledger.
+/// Checks, one block at a time, whether a sequence of blocks is safe to append to the local node's
+/// ledger.
 ///
 /// A "safe" block satisfies:
 ///     1. A sufficient set of peers have externalized the block (aka "potentially safe"),
 ///     2. The block is part of a chain of safe blocks, rooted at the highest block in the local node’s ledger,
 ///     3. The block’s ID agrees with the merkle hash of its transactions,
-///     4. None of the key images in the block have appeared before.
-///
-/// # Arguments
-/// * `ledger` - The local node's ledger.
-/// * `blocks_and_contents` - A sequence of Blocks with their associated transactions, in increasing order of block number.
-fn identify_safe_blocks<L: Ledger>(
-    ledger: &L,
-    blocks_and_contents: &[(Block, BlockContents)],
-    logger: &Logger,
-) -> Result<Vec<(Block, BlockContents)>, ()> {
-    // The highest block externalized by the local node.
-    let highest_local_block = ledger
-        .num_blocks()
-        .and_then(|num_blocks| ledger.get_block(num_blocks - 1))
-        .expect("Failed getting highest local block");
+///     4. None of the key images in the block have appeared before,
+///     5. The block pays its fees according to the network's fee split, if known.
+pub(crate) struct SafeBlockChecker {
+    /// The last block found to be safe, or the highest block in the local ledger.
+    last_safe_block: Block,
+
+    /// KeyImages used by new, safe blocks.
+    /// They are not necessarily in the ledger yet, but may not be used again.
+    additional_key_images: HashSet<KeyImage>,
+
+    /// How the network splits block fees, if known.
+    fee_split: Option<FeeSplit>,
+}
 
-    let mut safe_blocks_and_contents: Vec<(Block, BlockContents)> = Vec::new();
-    let mut last_safe_block: Block = highest_local_block;
+impl SafeBlockChecker {
+    pub fn new<L: Ledger>(ledger: &L, fee_split: Option<FeeSplit>) -> Self {
+        // The highest block externalized by the local node.
+        let highest_local_block = ledger
+            .num_blocks()
+            .and_then(|num_blocks| ledger.get_block(num_blocks - 1))
+            .expect("Failed getting highest local block");
 
-    // KeyImages used by new, safe blocks.
-    // They are not yet in the ledger, but may not be used again.
-    let mut additional_key_images: HashSet<KeyImage> = HashSet::default();
+        Self {
+            last_safe_block: highest_local_block,
+            additional_key_images: HashSet::default(),
+            fee_split,
+        }
+    }
 
-    'block_loop: for (block, block_contents) in blocks_and_contents {
+    /// Checks whether the next block in the sequence is safe. Blocks must be checked in increasing
+    /// order of block number, and no further blocks should be checked once one was not safe.
+    pub fn is_safe<L: Ledger>(
+        &mut self,
+        ledger: &L,
+        block: &Block,
+        block_contents: &BlockContents,
+        logger: &Logger,
+    ) -> bool {
         // The block must be part of a chain of safe blocks.
-        if block.parent_id != last_safe_block.id {
+        if block.parent_id != self.last_safe_block.id {
             log::error!(
                 logger,
                 "The block's parent_id must be the last safe block in the chain."
@@ -717,9 +907,9 @@ fn identify_safe_blocks<L: Ledger>(
                 logger,
                 "block: {:?}, expected parent_id: {:?}",
                 block,
-                last_safe_block.id
+                self.last_safe_block.id
             );
-            break;
+            return false;
         }
 
         let derived_block_id = compute_block_id(
@@ -738,7 +928,7 @@ fn identify_safe_blocks<L: Ledger>(
                 block,
                 derived_block_id
             );
-            break;
+            return false;
         }
 
         // No key images in the block may have been previously seen.
@@ -753,7 +943,7 @@ fn identify_safe_blocks<L: Ledger>(
                             key_image,
                             block
                         );
-                        break 'block_loop;
+                        return false;
                     }
                 }
                 Err(e) => {
@@ -763,40 +953,99 @@ fn identify_safe_blocks<L: Ledger>(
                         key_image,
                         e
                     );
-                    break 'block_loop;
+                    return false;
                 }
             }
 
             // Check if the key image was used by another potentially safe block.
-            if additional_key_images.contains(key_image) {
+            if self.additional_key_images.contains(key_image) {
                 log::error!(
                     logger,
                     "Previously used KeyImage {:?} in block {:?}",
                     key_image,
                     block
                 );
-                break 'block_loop;
+                return false;
+            }
+            self.additional_key_images.insert(key_image.clone());
+        }
+
+        // The block's fee outputs must follow the fee split.
+        if let Some(fee_split) = self.fee_split.as_ref() {
+            if FeeSplit::has_verifiable_fee_outputs(block) {
+                if let Err(e) = fee_split.verify_fee_outputs(block_contents) {
+                    log::error!(logger, "Invalid fee outputs in block {:?}: {}", block, e);
+                    return false;
+                }
             }
-            additional_key_images.insert(key_image.clone());
         }
 
         // This block is safe.
-        last_safe_block = block.clone();
-        safe_blocks_and_contents.push((block.clone(), block_contents.clone()));
+        self.last_safe_block = block.clone();
+        true
     }
-
-    Ok(safe_blocks_and_contents)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{test_utils::MockTransactionsFetcher, SCPNetworkState};
+    use crate::{sync_pipeline::FetchStage, test_utils::MockTransactionsFetcher, SCPNetworkState};
     use common::{logger::test_with_logger, NodeID};
+    use keys::{Ed25519Pair, FromRandom};
     use ledger_db::test_utils::{get_mock_ledger, get_test_ledger_blocks};
     use peers_tests::{test_node_id, test_peer_uri, MockPeerConnection};
+    use rand::{rngs::StdRng, SeedableRng};
     use scp::{core_types::Ballot, msg::*, *};
     use std::convert::TryFrom;
+    use transaction::BLOCK_VERSION;
+
+    /// Gets the contents of each block in a list of Blocks, or None for blocks whose contents
+    /// could not be fetched before the timeout.
+    fn get_block_contents<TF: TransactionsFetcher + 'static>(
+        transactions_fetcher: Arc<TF>,
+        safe_responder_ids: &[ResponderId],
+        blocks: &[Block],
+        timeout: Duration,
+        logger: &Logger,
+    ) -> BTreeMap<BlockIndex, Option<BlockContents>> {
+        let fetch_stage = FetchStage::start(
+            transactions_fetcher,
+            safe_responder_ids,
+            std::cmp::min(5, blocks.len()),
+            blocks.len(),
+            timeout,
+            logger,
+        );
+        for block in blocks {
+            fetch_stage.fetch(block.clone());
+        }
+
+        (0..blocks.len())
+            .map(|_| {
+                let (block, fetched_opt) = fetch_stage.recv().expect("fetch stage stopped");
+                (
+                    block.index,
+                    fetched_opt.map(|(block_contents, _signature)| block_contents),
+                )
+            })
+            .collect()
+    }
+
+    /// Identify the leading sequence of blocks that are safe to append to the local node's ledger.
+    fn identify_safe_blocks<L: Ledger>(
+        ledger: &L,
+        blocks_and_contents: &[(Block, BlockContents)],
+        logger: &Logger,
+    ) -> Result<Vec<(Block, BlockContents)>, ()> {
+        let mut checker = SafeBlockChecker::new(ledger, None);
+        Ok(blocks_and_contents
+            .iter()
+            .take_while(|(block, block_contents)| {
+                checker.is_safe(ledger, block, block_contents, logger)
+            })
+            .cloned()
+            .collect())
+    }
 
     #[test_with_logger]
     // A node with the trivial quorum set should never be "behind".
@@ -1282,6 +1531,219 @@ mod tests {
         }
     }
 
+    #[test_with_logger]
+    // In header-first mode, blocks should be synced up to the highest block peers agree on, and
+    // the block at which they diverge should be reported.
+    fn test_get_potentially_safe_blocks_header_first(logger: Logger) {
+        let trivial_quorum_set = QuorumSet::empty();
+
+        let node_a_uri = test_peer_uri(22);
+        let node_a = (test_node_id(22), trivial_quorum_set.clone());
+
+        let node_b_uri = test_peer_uri(33);
+        let node_b = (test_node_id(33), trivial_quorum_set);
+
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            2,
+            vec![node_a.0.clone().responder_id, node_b.0.clone().responder_id],
+        );
+
+        let network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+
+        // Peer A and Peer B agree on the first 10 blocks, and then fork.
+        let ledger_a = get_mock_ledger(25);
+        let mut ledger_b = get_mock_ledger(10);
+        for block_index in 10..20 {
+            let parent = ledger_b.get_block(block_index - 1).unwrap();
+            let block_contents = BlockContents::new(Vec::new(), Vec::new());
+            let block = Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                block_index,
+                &Default::default(),
+                &block_contents,
+            );
+            ledger_b.set_block(&block, &block_contents);
+        }
+
+        let peer_conns = vec![
+            MockPeerConnection::new(node_a_uri, local_node_id.clone(), ledger_a, 50),
+            MockPeerConnection::new(node_b_uri, local_node_id, ledger_b, 50),
+        ];
+
+        let ledger = get_mock_ledger(5);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(ledger.clone());
+        let mut sync_service = LedgerSyncService::new(
+            ledger,
+            conn_manager.clone(),
+            transactions_fetcher,
+            logger.clone(),
+        );
+        sync_service.set_header_first(true);
+
+        let (responder_ids, block_index, blocks) = sync_service
+            .get_potentially_safe_blocks(&network_state, 100)
+            .unwrap();
+        assert_eq!(responder_ids.len(), 2);
+        assert_eq!(block_index, 9);
+        assert_eq!(
+            blocks.iter().map(|block| block.index).collect::<Vec<_>>(),
+            vec![5, 6, 7, 8, 9]
+        );
+
+        let node_to_block_ids =
+            get_block_ids(&conn_manager, 5, 100, Duration::from_secs(10), &logger);
+        let grouping = group_by_block_id(5, &node_to_block_ids);
+        assert_eq!(report_fork(&grouping, &logger), Some(10));
+        assert_eq!(grouping[&9].len(), 1);
+        assert_eq!(grouping[&19].len(), 2);
+        assert_eq!(grouping[&24].len(), 1);
+    }
+
+    #[test_with_logger]
+    // If the local ledger diverged from the chain peers agree on, syncing should fail with the
+    // lowest diverging block instead of making no progress.
+    fn test_attempt_ledger_sync_divergence(logger: Logger) {
+        let trivial_quorum_set = QuorumSet::empty();
+
+        let node_a_uri = test_peer_uri(22);
+        let node_a = (test_node_id(22), trivial_quorum_set.clone());
+
+        let node_b_uri = test_peer_uri(33);
+        let node_b = (test_node_id(33), trivial_quorum_set);
+
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            2,
+            vec![node_a.0.clone().responder_id, node_b.0.clone().responder_id],
+        );
+
+        let network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+
+        // Both peers have the same 25 blocks.
+        let peer_ledger = get_mock_ledger(25);
+        let peer_conns = vec![
+            MockPeerConnection::new(node_a_uri, local_node_id.clone(), peer_ledger.clone(), 50),
+            MockPeerConnection::new(node_b_uri, local_node_id, peer_ledger.clone(), 50),
+        ];
+
+        // The local ledger agrees with the peers on the first 5 blocks, and then diverges.
+        let mut ledger = get_mock_ledger(5);
+        for block_index in 5..10 {
+            let parent = ledger.get_block(block_index - 1).unwrap();
+            let block_contents = BlockContents::new(Vec::new(), Vec::new());
+            let block = Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                block_index,
+                &Default::default(),
+                &block_contents,
+            );
+            ledger.set_block(&block, &block_contents);
+        }
+
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(peer_ledger.clone());
+        let mut sync_service = LedgerSyncService::new(
+            ledger.clone(),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        );
+
+        match sync_service.attempt_ledger_sync(&network_state, 100) {
+            Err(LedgerSyncError::Divergence {
+                block_index,
+                local_block_id,
+                network_block_id,
+            }) => {
+                assert_eq!(block_index, 5);
+                assert_eq!(local_block_id, ledger.get_block(5).unwrap().id);
+                assert_eq!(network_block_id, peer_ledger.get_block(5).unwrap().id);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(ledger.num_blocks().unwrap(), 10);
+    }
+
+    #[test_with_logger]
+    // Syncing while the network is further ahead should append the safe blocks in batches, ending
+    // with a partial batch.
+    fn test_attempt_ledger_sync_appends_batches(logger: Logger) {
+        let trivial_quorum_set = QuorumSet::empty();
+
+        let node_a_uri = test_peer_uri(22);
+        let node_a = (test_node_id(22), trivial_quorum_set.clone());
+
+        let node_b_uri = test_peer_uri(33);
+        let node_b = (test_node_id(33), trivial_quorum_set);
+
+        let local_node_id = test_node_id(11);
+        let local_quorum_set: QuorumSet<ResponderId> = QuorumSet::new_with_node_ids(
+            2,
+            vec![node_a.0.clone().responder_id, node_b.0.clone().responder_id],
+        );
+
+        // Both peers externalized a slot beyond the blocks synced by the attempt.
+        let mut network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id.clone(),
+            local_quorum_set,
+            logger.clone(),
+        );
+        for (node_id, quorum_set) in vec![node_a, node_b] {
+            network_state.push(Msg::new(
+                node_id.responder_id,
+                quorum_set,
+                30,
+                Topic::Externalize(ExternalizePayload {
+                    C: Ballot::new(100, &["foo"]),
+                    HN: 4,
+                }),
+            ));
+        }
+
+        let peer_ledger = get_mock_ledger(25);
+        let peer_conns = vec![
+            MockPeerConnection::new(node_a_uri, local_node_id.clone(), peer_ledger.clone(), 50),
+            MockPeerConnection::new(node_b_uri, local_node_id, peer_ledger.clone(), 50),
+        ];
+
+        let ledger = get_mock_ledger(5);
+        let conn_manager = ConnectionManager::new(peer_conns, logger.clone());
+        let transactions_fetcher = MockTransactionsFetcher::new(peer_ledger.clone());
+        let mut sync_service = LedgerSyncService::new(
+            ledger.clone(),
+            conn_manager,
+            transactions_fetcher,
+            logger.clone(),
+        );
+        sync_service.set_pipeline_config(SyncPipelineConfig {
+            append_batch_size: 3,
+            ..Default::default()
+        });
+
+        sync_service
+            .attempt_ledger_sync(&network_state, 10)
+            .unwrap();
+        assert_eq!(ledger.num_blocks().unwrap(), 15);
+        for block_index in 0..15 {
+            assert_eq!(
+                ledger.get_block(block_index).unwrap(),
+                peer_ledger.get_block(block_index).unwrap()
+            );
+        }
+    }
+
     #[test]
     #[ignore]
     fn test_get_potentially_safe_blocks_network_fork() {
@@ -1441,6 +1903,44 @@ mod tests {
         assert_eq!(safe_blocks.len(), 0);
     }
 
+    #[test_with_logger]
+    // A block whose fee outputs do not follow the fee split is not safe.
+    fn test_identify_safe_blocks_wrong_fee_outputs(logger: Logger) {
+        // The local node's ledger must contain the origin block.
+        let local_ledger = get_mock_ledger(1);
+        let origin_block = local_ledger.get_block(0).unwrap();
+
+        // The test blocks do not have fee outputs.
+        let blocks_and_contents = get_test_ledger_blocks(2);
+        let (block_one, contents_one) = blocks_and_contents.get(1).unwrap().clone();
+
+        let fee_split = FeeSplit::default();
+        let mut checker = SafeBlockChecker::new(&local_ledger, Some(fee_split.clone()));
+        assert!(!checker.is_safe(&local_ledger, &block_one, &contents_one, &logger));
+
+        // Fee outputs are not checked if the fee split is unknown.
+        let mut checker = SafeBlockChecker::new(&local_ledger, None);
+        assert!(checker.is_safe(&local_ledger, &block_one, &contents_one, &logger));
+
+        // The same block, with fee outputs following the split, is safe.
+        let mut outputs = contents_one.outputs.clone();
+        outputs.extend(
+            fee_split
+                .fee_outputs(&contents_one.key_images, &contents_one.outputs, 30)
+                .unwrap(),
+        );
+        let contents = BlockContents::new(contents_one.key_images.clone(), outputs);
+        let block = Block::new(
+            BLOCK_VERSION,
+            &origin_block.id,
+            1,
+            &block_one.root_element,
+            &contents,
+        );
+        let mut checker = SafeBlockChecker::new(&local_ledger, Some(fee_split));
+        assert!(checker.is_safe(&local_ledger, &block, &contents, &logger));
+    }
+
     #[test]
     // Without a fork, nodes contain subsets of the longest blockchain. For each slot (aka, block index),
     // `group_by_block` should return a single group of nodes who have externalized a block for that slot.
@@ -1511,4 +2011,36 @@ mod tests {
             assert!(nodes.contains(&test_peer_uri(2).responder_id().unwrap()));
         }
     }
+
+    #[test_with_logger]
+    // Only valid signatures by a configured signer, or by anyone if none is configured, are stored.
+    fn test_is_accepted_signature(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let other_signer = Ed25519Pair::from_random(&mut rng);
+
+        let blocks_and_transactions = get_test_ledger_blocks(2);
+        let (block, _) = &blocks_and_transactions[1];
+        let signature = BlockSignature::from_block_and_keypair(block, &signer).unwrap();
+        let other_signature = BlockSignature::from_block_and_keypair(block, &other_signer).unwrap();
+
+        assert!(is_accepted_signature(block, &signature, &[], &logger));
+        assert!(is_accepted_signature(
+            block,
+            &signature,
+            &[signer.public_key()],
+            &logger
+        ));
+        assert!(!is_accepted_signature(
+            block,
+            &other_signature,
+            &[signer.public_key()],
+            &logger
+        ));
+
+        // A signature of another block is refused.
+        let (other_block, _) = &blocks_and_transactions[0];
+        let signature = BlockSignature::from_block_and_keypair(other_block, &signer).unwrap();
+        assert!(!is_accepted_signature(block, &signature, &[], &logger));
+    }
 }
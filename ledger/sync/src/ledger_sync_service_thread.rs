@@ -3,11 +3,12 @@
 //! An integration between `PollingNetworkState` and `LedgerSyncService` that performs the sync in
 //! a background thread.
 
-use crate::{LedgerSyncService, PollingNetworkState, TransactionsFetcher};
+use crate::{LedgerSyncError, LedgerSyncService, PollingNetworkState, TransactionsFetcher};
 use common::logger::{log, Logger};
 use ledger_db::Ledger;
 use mcconnection::{BlockchainConnection, ConnectionManager};
 use std::{
+    cmp::max,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -16,7 +17,8 @@ use std::{
     time::Duration,
 };
 
-/// Maximal number of blocks to attempt to sync at each loop iteration.
+/// Maximal number of blocks to attempt to sync at each loop iteration, unless more are appended
+/// at once while catching up.
 const MAX_BLOCKS_PER_SYNC_ITERATION: u32 = 10;
 
 pub struct LedgerSyncServiceThread {
@@ -45,6 +47,27 @@ impl LedgerSyncServiceThread {
             logger.clone(),
         );
 
+        Self::from_service(
+            ledger,
+            ledger_sync_service,
+            network_state,
+            poll_interval,
+            logger,
+        )
+    }
+
+    /// Performs the sync with an already configured `LedgerSyncService`.
+    pub fn from_service<
+        L: Ledger + 'static,
+        BC: BlockchainConnection + 'static,
+        TF: TransactionsFetcher + 'static,
+    >(
+        ledger: L,
+        ledger_sync_service: LedgerSyncService<L, BC, TF>,
+        network_state: PollingNetworkState<BC>,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
         let currently_behind = Arc::new(AtomicBool::new(false));
         let stop_requested = Arc::new(AtomicBool::new(false));
 
@@ -100,6 +123,12 @@ impl LedgerSyncServiceThread {
     ) {
         log::debug!(logger, "LedgerSyncServiceThread has started.");
 
+        // Attempts must sync enough blocks to fill a batch.
+        let blocks_per_sync_iteration = max(
+            MAX_BLOCKS_PER_SYNC_ITERATION,
+            ledger_sync_service.pipeline_config().append_batch_size as u32,
+        );
+
         loop {
             if stop_requested.load(Ordering::SeqCst) {
                 log::debug!(logger, "LedgerSyncServiceThread stop requested.");
@@ -126,8 +155,19 @@ impl LedgerSyncServiceThread {
 
             // Maybe sync, maybe wait and check again.
             if is_behind {
-                let _ = ledger_sync_service
-                    .attempt_ledger_sync(&network_state, MAX_BLOCKS_PER_SYNC_ITERATION);
+                // Syncing cannot make progress once the ledger diverged from the network, and
+                // requires removing the diverged blocks from the ledger.
+                if let Err(LedgerSyncError::Divergence { block_index, .. }) = ledger_sync_service
+                    .attempt_ledger_sync(&network_state, blocks_per_sync_iteration)
+                {
+                    log::crit!(
+                        logger,
+                        "Ledger diverged from the network at block {}, stopping ledger sync. Remove blocks from {} onwards to resync them.",
+                        block_index,
+                        block_index
+                    );
+                    break;
+                }
             } else if !stop_requested.load(Ordering::SeqCst) {
                 log::trace!(
                     logger,
@@ -4,22 +4,40 @@
 #![feature(wait_until)]
 
 mod counters;
+mod equivocation_detector;
 mod ledger_sync_error;
 mod ledger_sync_service;
 mod ledger_sync_service_thread;
+mod local_block_fetcher;
+mod multi_source_transactions_fetcher;
 mod network_state_trait;
 mod polling_network_state;
 mod reqwest_transactions_fetcher;
+mod s3_transactions_fetcher;
 mod scp_network_state;
+mod sync_pipeline;
 mod transactions_fetcher_trait;
 
+pub use equivocation_detector::{
+    Equivocation, EquivocationAlertError, EquivocationDetector, SignedBlockObservation,
+};
 pub use ledger_sync_error::LedgerSyncError;
 pub use ledger_sync_service::LedgerSyncService;
 pub use ledger_sync_service_thread::LedgerSyncServiceThread;
+pub use local_block_fetcher::{LocalBlockFetcher, LocalBlockFetcherError};
+pub use multi_source_transactions_fetcher::{
+    MultiSourceTransactionsFetcher, SourceHealth, SourceStatus,
+};
 pub use network_state_trait::NetworkState;
 pub use polling_network_state::PollingNetworkState;
-pub use reqwest_transactions_fetcher::ReqwestTransactionsFetcher;
+pub use reqwest_transactions_fetcher::{
+    ReqwestTransactionsFetcher, ReqwestTransactionsFetcherError, RetryPolicy, S3BlockData,
+};
+pub use s3_transactions_fetcher::{
+    S3ArchiveLocation, S3Credentials, S3TransactionsFetcher, S3TransactionsFetcherError,
+};
 pub use scp_network_state::SCPNetworkState;
+pub use sync_pipeline::SyncPipelineConfig;
 pub use transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
 
 #[cfg(any(test, feature = "test_utils"))]
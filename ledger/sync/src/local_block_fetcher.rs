@@ -0,0 +1,207 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Implementation of the `TransactionsFetcher` trait that reads blocks from a local directory laid
+//! out like the archive ledger-distribution writes, e.g. a copy of an archive, or a network file
+//! system. This allows syncing without any network access.
+
+use crate::{
+    equivocation_detector::EquivocationDetector,
+    reqwest_transactions_fetcher::S3BlockData,
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use failure::Fail;
+use mobilecoin_api::conversions::block_num_to_s3block_path;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use transaction::{Block, BlockContents, BlockIndex, BlockSignature};
+use url::Url;
+
+#[derive(Debug, Fail)]
+pub enum LocalBlockFetcherError {
+    #[fail(display = "Not a local directory: {}", _0)]
+    NotALocalDirectory(String),
+
+    #[fail(display = "IO error on {:?}: {:?}", _0, _1)]
+    IO(PathBuf, std::io::Error),
+
+    #[fail(display = "Read an invalid block from {:?}: {}", _0, _1)]
+    InvalidBlockReceived(PathBuf, String),
+}
+
+impl TransactionFetcherError for LocalBlockFetcherError {}
+
+pub struct LocalBlockFetcher {
+    /// The directory the archive is in.
+    pub path: PathBuf,
+    logger: Logger,
+
+    /// An optional detector every signed block read is reported to.
+    equivocation_detector: Option<Arc<EquivocationDetector>>,
+}
+
+impl LocalBlockFetcher {
+    pub fn new(path: PathBuf, logger: Logger) -> Self {
+        Self {
+            path,
+            logger,
+            equivocation_detector: None,
+        }
+    }
+
+    /// Creates a fetcher for the directory a `file://` URL points to.
+    pub fn from_url(url: &Url, logger: Logger) -> Result<Self, LocalBlockFetcherError> {
+        if url.scheme() != "file" {
+            return Err(LocalBlockFetcherError::NotALocalDirectory(url.to_string()));
+        }
+
+        let path = url
+            .to_file_path()
+            .map_err(|_| LocalBlockFetcherError::NotALocalDirectory(url.to_string()))?;
+        Ok(Self::new(path, logger))
+    }
+
+    /// Sets a detector every signed block read is reported to.
+    pub fn set_equivocation_detector(&mut self, equivocation_detector: Arc<EquivocationDetector>) {
+        self.equivocation_detector = Some(equivocation_detector);
+    }
+
+    /// Reads a file of the archive.
+    ///
+    /// # Arguments
+    /// * `relative_path` - Path of the file, relative to the directory of the archive.
+    pub fn read(&self, relative_path: &Path) -> Result<Vec<u8>, LocalBlockFetcherError> {
+        let path = self.path.join(relative_path);
+        fs::read(&path).map_err(|err| LocalBlockFetcherError::IO(path, err))
+    }
+
+    /// Reads the block at `block_index`, along with its contents and signature.
+    pub fn get_block_data(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<S3BlockData, LocalBlockFetcherError> {
+        let relative_path = block_num_to_s3block_path(block_index);
+        let path = self.path.join(&relative_path);
+        log::debug!(
+            self.logger,
+            "Attempting to read block {} from {:?}",
+            block_index,
+            path
+        );
+
+        let bytes = self.read(&relative_path)?;
+        let s3_block_data = S3BlockData::from_bytes(&bytes)
+            .map_err(|err| LocalBlockFetcherError::InvalidBlockReceived(path.clone(), err))?;
+
+        if s3_block_data.block.index != block_index || !s3_block_data.block.is_block_id_valid() {
+            return Err(LocalBlockFetcherError::InvalidBlockReceived(
+                path,
+                format!("invalid block {:?}", s3_block_data.block),
+            ));
+        }
+
+        if let Some(signature) = s3_block_data.signature.as_ref() {
+            if let Some(equivocation_detector) = self.equivocation_detector.as_ref() {
+                equivocation_detector.observe(
+                    &path.to_string_lossy(),
+                    &s3_block_data.block,
+                    signature,
+                );
+            }
+        }
+
+        Ok(s3_block_data)
+    }
+
+    pub fn get_origin_block_and_transactions(
+        &self,
+    ) -> Result<(Block, BlockContents), LocalBlockFetcherError> {
+        let s3_block_data = self.get_block_data(0)?;
+        Ok((s3_block_data.block, s3_block_data.block_contents))
+    }
+}
+
+impl TransactionsFetcher for LocalBlockFetcher {
+    type Error = LocalBlockFetcherError;
+
+    fn get_block_contents(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<BlockContents, Self::Error> {
+        let (block_contents, _signature) =
+            self.get_block_contents_and_signature(safe_responder_ids, block)?;
+        Ok(block_contents)
+    }
+
+    fn get_block_contents_and_signature(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
+        let s3_block_data = self.get_block_data(block.index)?;
+
+        // Check that we read data for the block we actually asked about.
+        if *block != s3_block_data.block {
+            return Err(LocalBlockFetcherError::InvalidBlockReceived(
+                self.path.join(block_num_to_s3block_path(block.index)),
+                "block data mismatch".to_string(),
+            ));
+        }
+
+        Ok((s3_block_data.block_contents, s3_block_data.signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_blocks, write_block};
+    use common::logger::test_with_logger;
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    // Blocks should be read from the directory a file:// URL points to, and checked.
+    fn test_get_block_contents(logger: Logger) {
+        let blocks = get_blocks(3);
+        let dir = TempDir::new("archive").unwrap();
+        for block in &blocks[..2] {
+            write_block(dir.path(), block);
+        }
+
+        let url = Url::from_directory_path(dir.path()).unwrap();
+        let fetcher = LocalBlockFetcher::from_url(&url, logger.clone()).unwrap();
+        assert_eq!(fetcher.path, dir.path());
+
+        for block in &blocks[..2] {
+            let block_contents = fetcher.get_block_contents(&[], block).unwrap();
+            assert_eq!(block_contents, BlockContents::new(Vec::new(), Vec::new()));
+        }
+        match fetcher.get_block_contents(&[], &blocks[2]) {
+            Err(LocalBlockFetcherError::IO(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // A block stored under the wrong index is rejected.
+        let path = dir.path().join(block_num_to_s3block_path(2));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::copy(dir.path().join(block_num_to_s3block_path(1)), &path).unwrap();
+        match fetcher.get_block_contents(&[], &blocks[2]) {
+            Err(LocalBlockFetcherError::InvalidBlockReceived(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let https_url = Url::parse("https://example.com/archive/").unwrap();
+        match LocalBlockFetcher::from_url(&https_url, logger) {
+            Err(LocalBlockFetcherError::NotALocalDirectory(_)) => {}
+            Err(err) => panic!("unexpected error {:?}", err),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+}
@@ -0,0 +1,640 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Implementation of the `TransactionsFetcher` trait that spreads fetching across several
+//! transaction sources. Different block ranges are downloaded from different sources in parallel,
+//! and a source that is lagging behind or serving corrupt blocks is failed over from.
+
+use crate::{
+    equivocation_detector::EquivocationDetector,
+    reqwest_transactions_fetcher::{
+        ReqwestTransactionsFetcher, ReqwestTransactionsFetcherError, S3BlockData, SourceBans,
+    },
+    transactions_fetcher_trait::TransactionsFetcher,
+};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use keys::Ed25519Public;
+use std::{
+    cmp::min,
+    fmt,
+    ops::Range,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::{Block, BlockContents, BlockIndex, BlockSignature};
+use url::Url;
+
+/// Number of consecutive blocks fetched from the same source.
+pub const DEFAULT_RANGE_SIZE: u64 = 10;
+
+/// How long a source that is missing a block is not asked for that block, or later ones.
+pub const DEFAULT_LAG_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How often the health of every source is logged while fetching blocks one at a time.
+pub const DEFAULT_HEALTH_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether a source is currently used for fetching.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceStatus {
+    /// The source is used for all blocks.
+    Healthy,
+
+    /// The source was missing the given block, and is not used for it or later blocks.
+    Lagging(BlockIndex),
+
+    /// The source served an invalid block, and is not used at all.
+    Banned,
+}
+
+impl fmt::Display for SourceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SourceStatus::Healthy => write!(f, "healthy"),
+            SourceStatus::Lagging(block_index) => write!(f, "lagging at block {}", block_index),
+            SourceStatus::Banned => write!(f, "banned"),
+        }
+    }
+}
+
+/// The health of a transaction source, as observed while fetching from it.
+#[derive(Clone, Debug)]
+pub struct SourceHealth {
+    pub source_url: Url,
+    pub status: SourceStatus,
+
+    /// Number of valid blocks fetched from the source.
+    pub num_blocks_fetched: u64,
+
+    /// Number of times the source was found to be missing a block.
+    pub num_times_lagging: u64,
+
+    /// Number of invalid blocks, or manifests, served by the source.
+    pub num_corrupt_blocks: u64,
+
+    /// The last error encountered while fetching from the source.
+    pub last_error: Option<String>,
+}
+
+/// Outcomes of fetching from a source.
+#[derive(Default)]
+struct SourceState {
+    num_blocks_fetched: u64,
+    num_times_lagging: u64,
+    num_corrupt_blocks: u64,
+    last_error: Option<String>,
+
+    /// The source was missing this block, and is not asked for it or later blocks until the
+    /// given time.
+    lagging: Option<(BlockIndex, Instant)>,
+}
+
+/// A single transaction source, and what was observed of it.
+struct Source {
+    /// Fetches from this source only, and bans it when it serves invalid blocks. Bans are shared
+    /// with the fetchers of the other sources.
+    fetcher: ReqwestTransactionsFetcher,
+
+    state: Mutex<SourceState>,
+}
+
+impl Source {
+    fn url(&self) -> &Url {
+        &self.fetcher.source_urls[0]
+    }
+
+    fn status(&self) -> SourceStatus {
+        if self.fetcher.is_source_banned(self.url()) {
+            return SourceStatus::Banned;
+        }
+
+        let now = Instant::now();
+        match self.state.lock().expect("mutex poisoned").lagging {
+            Some((block_index, lagging_until)) if lagging_until > now => {
+                SourceStatus::Lagging(block_index)
+            }
+            _ => SourceStatus::Healthy,
+        }
+    }
+
+    /// Returns true if the source should be asked for the given block.
+    fn is_available(&self, block_index: BlockIndex) -> bool {
+        match self.status() {
+            SourceStatus::Healthy => true,
+            SourceStatus::Lagging(lagging_block_index) => block_index < lagging_block_index,
+            SourceStatus::Banned => false,
+        }
+    }
+
+    fn health(&self) -> SourceHealth {
+        let status = self.status();
+        let state = self.state.lock().expect("mutex poisoned");
+        SourceHealth {
+            source_url: self.url().clone(),
+            status,
+            num_blocks_fetched: state.num_blocks_fetched,
+            num_times_lagging: state.num_times_lagging,
+            num_corrupt_blocks: state.num_corrupt_blocks,
+            last_error: state.last_error.clone(),
+        }
+    }
+
+    /// Fetches a block, and records whether the source served it, was missing it, or served an
+    /// invalid block.
+    ///
+    /// # Arguments
+    /// * `block_index` - The index of the block to fetch.
+    /// * `expected_block` - If known, the block the source must serve for `block_index`.
+    /// * `lag_cooldown` - How long the source is not used if it is missing the block.
+    /// * `logger` - Logger.
+    fn fetch(
+        &self,
+        block_index: BlockIndex,
+        expected_block: Option<&Block>,
+        lag_cooldown: Duration,
+        logger: &Logger,
+    ) -> Result<S3BlockData, ReqwestTransactionsFetcherError> {
+        let result = self
+            .fetcher
+            .get_block_data(block_index)
+            .and_then(|s3_block_data| match expected_block {
+                Some(block) if *block != s3_block_data.block => {
+                    let err = ReqwestTransactionsFetcherError::MismatchedDataReceived(
+                        self.url().to_string(),
+                        format!("block data mismatch for block {}", block_index),
+                    );
+                    self.fetcher.record_offense(self.url(), &err);
+                    Err(err)
+                }
+                _ => Ok(s3_block_data),
+            });
+
+        let mut state = self.state.lock().expect("mutex poisoned");
+        match &result {
+            Ok(_) => {
+                state.num_blocks_fetched += 1;
+                // A source that serves a block it was missing has caught up.
+                if let Some((lagging_block_index, _)) = state.lagging {
+                    if block_index >= lagging_block_index {
+                        state.lagging = None;
+                    }
+                }
+            }
+
+            // The source is banned, and was not contacted.
+            Err(ReqwestTransactionsFetcherError::NoAvailableSources) => {}
+
+            // The source served invalid data, and may have been banned by `fetcher`.
+            Err(err) if is_invalid_block_error(err) => {
+                state.num_corrupt_blocks += 1;
+                state.last_error = Some(err.to_string());
+            }
+
+            Err(err) => {
+                log::warn!(
+                    logger,
+                    "Transaction source {} is lagging, missing block {}: {}",
+                    self.url(),
+                    block_index,
+                    err
+                );
+                state.num_times_lagging += 1;
+                state.last_error = Some(err.to_string());
+                state.lagging = Some((block_index, Instant::now() + lag_cooldown));
+            }
+        }
+
+        result
+    }
+}
+
+fn is_invalid_block_error(err: &ReqwestTransactionsFetcherError) -> bool {
+    match err {
+        ReqwestTransactionsFetcherError::InvalidBlockReceived(_, _)
+        | ReqwestTransactionsFetcherError::InvalidManifestReceived(_, _)
+        | ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _) => true,
+        _ => false,
+    }
+}
+
+pub struct MultiSourceTransactionsFetcher {
+    sources: Vec<Arc<Source>>,
+
+    /// Number of consecutive blocks fetched from the same source.
+    range_size: u64,
+
+    /// How long a source that is missing a block is not asked for it, or later blocks.
+    lag_cooldown: Duration,
+
+    /// How often the health of every source is logged while fetching blocks one at a time.
+    health_log_interval: Duration,
+
+    /// When the health of every source was last logged.
+    last_health_log: Mutex<Instant>,
+
+    logger: Logger,
+}
+
+impl MultiSourceTransactionsFetcher {
+    pub fn new(
+        source_urls: Vec<String>,
+        logger: Logger,
+    ) -> Result<Self, ReqwestTransactionsFetcherError> {
+        Self::new_with_client(source_urls, reqwest::Client::new(), logger)
+    }
+
+    pub fn new_with_client(
+        source_urls: Vec<String>,
+        client: reqwest::Client,
+        logger: Logger,
+    ) -> Result<Self, ReqwestTransactionsFetcherError> {
+        let fetchers: Result<Vec<ReqwestTransactionsFetcher>, ReqwestTransactionsFetcherError> =
+            source_urls
+                .into_iter()
+                .map(|source_url| {
+                    ReqwestTransactionsFetcher::new_with_client(
+                        vec![source_url],
+                        client.clone(),
+                        logger.clone(),
+                    )
+                })
+                .collect();
+        let fetchers = fetchers?;
+
+        let source_bans = Arc::new(SourceBans::new(
+            fetchers
+                .iter()
+                .map(|fetcher| fetcher.source_urls[0].clone())
+                .collect(),
+        ));
+        let sources = fetchers
+            .into_iter()
+            .map(|mut fetcher| {
+                fetcher.set_source_bans(source_bans.clone());
+                Arc::new(Source {
+                    fetcher,
+                    state: Mutex::new(SourceState::default()),
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            sources,
+            range_size: DEFAULT_RANGE_SIZE,
+            lag_cooldown: DEFAULT_LAG_COOLDOWN,
+            health_log_interval: DEFAULT_HEALTH_LOG_INTERVAL,
+            last_health_log: Mutex::new(Instant::now()),
+            logger,
+        })
+    }
+
+    /// Sets the number of consecutive blocks fetched from the same source.
+    pub fn set_range_size(&mut self, range_size: u64) {
+        self.range_size = range_size.max(1);
+    }
+
+    /// Sets how long a source that is missing a block is not asked for it, or later blocks.
+    pub fn set_lag_cooldown(&mut self, lag_cooldown: Duration) {
+        self.lag_cooldown = lag_cooldown;
+    }
+
+    /// Sets how often the health of every source is logged while fetching blocks one at a time.
+    pub fn set_health_log_interval(&mut self, health_log_interval: Duration) {
+        self.health_log_interval = health_log_interval;
+    }
+
+    /// Sets how long a source that served an invalid block is excluded from fetching.
+    pub fn set_source_ban_duration(&mut self, source_ban_duration: Duration) {
+        for source in self.sources_mut() {
+            source.fetcher.set_source_ban_duration(source_ban_duration);
+        }
+    }
+
+    /// Sets a detector every signed block fetched is reported to.
+    pub fn set_equivocation_detector(&mut self, equivocation_detector: Arc<EquivocationDetector>) {
+        for source in self.sources_mut() {
+            source
+                .fetcher
+                .set_equivocation_detector(equivocation_detector.clone());
+        }
+    }
+
+    /// Sets the key archive manifests must be signed with to be trusted.
+    pub fn set_manifest_signer(&mut self, manifest_signer: Ed25519Public) {
+        for source in self.sources_mut() {
+            source.fetcher.set_manifest_signer(manifest_signer);
+        }
+    }
+
+    fn sources_mut(&mut self) -> impl Iterator<Item = &mut Source> {
+        // Sources are only shared with threads while fetching, which does not outlive `&self`.
+        self.sources
+            .iter_mut()
+            .map(|source| Arc::get_mut(source).expect("source shared outside of fetching"))
+    }
+
+    /// The health of every source, in the order the sources were given.
+    pub fn source_health(&self) -> Vec<SourceHealth> {
+        self.sources.iter().map(|source| source.health()).collect()
+    }
+
+    /// Logs the health of every source.
+    pub fn log_source_health(&self) {
+        for health in self.source_health() {
+            log::info!(
+                self.logger,
+                "Transaction source {} is {}: {} blocks fetched, lagging {} time(s), {} corrupt block(s), last error: {:?}",
+                health.source_url,
+                health.status,
+                health.num_blocks_fetched,
+                health.num_times_lagging,
+                health.num_corrupt_blocks,
+                health.last_error,
+            );
+        }
+        *self.last_health_log.lock().expect("mutex poisoned") = Instant::now();
+    }
+
+    /// Logs the health of every source if it was not logged recently.
+    fn maybe_log_source_health(&self) {
+        let last_health_log = *self.last_health_log.lock().expect("mutex poisoned");
+        if last_health_log.elapsed() >= self.health_log_interval {
+            self.log_source_health();
+        }
+    }
+
+    /// Indices of the sources to try for a block, starting with the one its range is assigned to.
+    fn source_indices_for_block(
+        &self,
+        block_index: BlockIndex,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let first_source_index = (block_index / self.range_size) as usize;
+        (0..self.sources.len())
+            .map(move |offset| (first_source_index + offset) % self.sources.len())
+            .filter(move |source_index| self.sources[*source_index].is_available(block_index))
+    }
+
+    /// Fetches a range of blocks, along with their contents and signatures.
+    ///
+    /// The range is split into ranges of `range_size` blocks that are spread across the sources,
+    /// and all sources are fetched from in parallel. Whatever a source fails to serve, because it
+    /// is lagging behind or serves an invalid block, is fetched from the other sources.
+    pub fn get_blocks(
+        &self,
+        block_range: Range<BlockIndex>,
+    ) -> Result<Vec<S3BlockData>, ReqwestTransactionsFetcherError> {
+        let mut fetched: Vec<Option<S3BlockData>> =
+            vec![None; block_range.end.saturating_sub(block_range.start) as usize];
+
+        let mut pending_ranges: Vec<Range<BlockIndex>> = block_range
+            .clone()
+            .step_by(self.range_size as usize)
+            .map(|start| start..min(start + self.range_size, block_range.end))
+            .collect();
+
+        while !pending_ranges.is_empty() {
+            // Assign every pending range to a source that can serve it.
+            let mut ranges_by_source: Vec<Vec<Range<BlockIndex>>> =
+                vec![Vec::new(); self.sources.len()];
+            for range in pending_ranges.drain(..) {
+                let source_index = self
+                    .source_indices_for_block(range.start)
+                    .next()
+                    .ok_or(ReqwestTransactionsFetcherError::NoAvailableSources)?;
+                ranges_by_source[source_index].push(range);
+            }
+
+            // Fetch from every source in parallel. Each source stops fetching a range at the
+            // first block it fails to serve, and returns the rest of the range.
+            let join_handles: Vec<_> = ranges_by_source
+                .into_iter()
+                .enumerate()
+                .filter(|(_source_index, ranges)| !ranges.is_empty())
+                .map(|(source_index, ranges)| {
+                    let source = self.sources[source_index].clone();
+                    let lag_cooldown = self.lag_cooldown;
+                    let logger = self.logger.clone();
+
+                    thread::Builder::new()
+                        .name(format!("FetchRange:{}", source_index))
+                        .spawn(move || {
+                            let mut s3_blocks = Vec::new();
+                            let mut unfetched_ranges = Vec::new();
+                            for range in ranges {
+                                for block_index in range.clone() {
+                                    let result = if source.is_available(block_index) {
+                                        source.fetch(block_index, None, lag_cooldown, &logger)
+                                    } else {
+                                        Err(ReqwestTransactionsFetcherError::NoAvailableSources)
+                                    };
+
+                                    match result {
+                                        Ok(s3_block_data) => s3_blocks.push(s3_block_data),
+                                        Err(_) => {
+                                            unfetched_ranges.push(block_index..range.end);
+                                            break;
+                                        }
+                                    }
+                                }
+                            }
+                            (s3_blocks, unfetched_ranges)
+                        })
+                        .expect("Failed spawning FetchRange thread!")
+                })
+                .collect();
+
+            for join_handle in join_handles {
+                let (s3_blocks, unfetched_ranges) =
+                    join_handle.join().expect("FetchRange thread panicked");
+                for s3_block_data in s3_blocks {
+                    let offset = (s3_block_data.block.index - block_range.start) as usize;
+                    fetched[offset] = Some(s3_block_data);
+                }
+                pending_ranges.extend(unfetched_ranges);
+            }
+        }
+
+        self.log_source_health();
+
+        Ok(fetched
+            .into_iter()
+            .map(|s3_block_data| s3_block_data.expect("all blocks fetched"))
+            .collect())
+    }
+
+    /// Fetches the origin block and its contents from the first source that serves it.
+    pub fn get_origin_block_and_transactions(
+        &self,
+    ) -> Result<(Block, BlockContents), ReqwestTransactionsFetcherError> {
+        let s3_block_data = self.get_blocks(0..1)?.remove(0);
+        Ok((s3_block_data.block, s3_block_data.block_contents))
+    }
+}
+
+impl TransactionsFetcher for MultiSourceTransactionsFetcher {
+    type Error = ReqwestTransactionsFetcherError;
+
+    fn get_block_contents(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<BlockContents, Self::Error> {
+        let (block_contents, _signature) =
+            self.get_block_contents_and_signature(safe_responder_ids, block)?;
+        Ok(block_contents)
+    }
+
+    fn get_block_contents_and_signature(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
+        self.maybe_log_source_health();
+
+        // Fail over to the next source until one serves the block.
+        let mut last_err = ReqwestTransactionsFetcherError::NoAvailableSources;
+        for source_index in self.source_indices_for_block(block.index) {
+            match self.sources[source_index].fetch(
+                block.index,
+                Some(block),
+                self.lag_cooldown,
+                &self.logger,
+            ) {
+                Ok(s3_block_data) => {
+                    return Ok((s3_block_data.block_contents, s3_block_data.signature))
+                }
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reqwest_transactions_fetcher::MAX_CONSECUTIVE_INVALID_RESPONSES,
+        test_utils::{get_blocks, write_block},
+    };
+    use common::logger::test_with_logger;
+    use mobilecoin_api::conversions::block_num_to_s3block_path;
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn file_url(dir: &TempDir) -> String {
+        format!("file://{}", dir.path().to_str().unwrap())
+    }
+
+    #[test_with_logger]
+    // Blocks a source lags behind on, or keeps serving corrupt, should be fetched from the other
+    // sources.
+    fn test_get_blocks_fails_over(logger: Logger) {
+        let blocks = get_blocks(30);
+
+        // A source with every block.
+        let good_dir = TempDir::new("good_source").unwrap();
+        for block in &blocks {
+            write_block(good_dir.path(), block);
+        }
+
+        // A source that only has the first 15 blocks.
+        let lagging_dir = TempDir::new("lagging_source").unwrap();
+        for block in &blocks[..15] {
+            write_block(lagging_dir.path(), block);
+        }
+
+        // A source that serves garbage for block 25.
+        let corrupt_dir = TempDir::new("corrupt_source").unwrap();
+        for block in &blocks {
+            write_block(corrupt_dir.path(), block);
+        }
+        let corrupt_path = corrupt_dir
+            .path()
+            .join(block_num_to_s3block_path(blocks[25].index));
+        fs::write(&corrupt_path, b"not a block").unwrap();
+
+        let mut fetcher = MultiSourceTransactionsFetcher::new(
+            vec![
+                file_url(&good_dir),
+                file_url(&lagging_dir),
+                file_url(&corrupt_dir),
+            ],
+            logger,
+        )
+        .unwrap();
+        fetcher.set_range_size(5);
+
+        let fetched: Vec<Block> = fetcher
+            .get_blocks(0..30)
+            .unwrap()
+            .into_iter()
+            .map(|s3_block_data| s3_block_data.block)
+            .collect();
+        assert_eq!(fetched, blocks);
+
+        // Every source served some of the blocks.
+        let health = fetcher.source_health();
+        assert!(health.iter().all(|health| health.num_blocks_fetched > 0));
+        assert_eq!(health.iter().map(|h| h.num_blocks_fetched).sum::<u64>(), 30);
+
+        assert_eq!(health[0].status, SourceStatus::Healthy);
+        assert_eq!(health[1].status, SourceStatus::Lagging(20));
+        assert_eq!(health[1].num_times_lagging, 1);
+        assert_eq!(health[2].status, SourceStatus::Banned);
+        assert_eq!(
+            health[2].num_corrupt_blocks,
+            MAX_CONSECUTIVE_INVALID_RESPONSES
+        );
+    }
+
+    #[test_with_logger]
+    // Fetching block contents should fail over from a lagging source, and fail once no source
+    // serves the block.
+    fn test_get_block_contents_fails_over(logger: Logger) {
+        let blocks = get_blocks(3);
+
+        let good_dir = TempDir::new("good_source").unwrap();
+        for block in &blocks[..2] {
+            write_block(good_dir.path(), block);
+        }
+        let lagging_dir = TempDir::new("lagging_source").unwrap();
+        write_block(lagging_dir.path(), &blocks[0]);
+
+        let mut fetcher = MultiSourceTransactionsFetcher::new(
+            vec![file_url(&good_dir), file_url(&lagging_dir)],
+            logger,
+        )
+        .unwrap();
+        fetcher.set_range_size(1);
+
+        // Block 1 is assigned to the lagging source, which is missing it.
+        for block in &blocks[..2] {
+            fetcher.get_block_contents(&[], block).unwrap();
+        }
+        assert_eq!(fetcher.source_health()[1].status, SourceStatus::Lagging(1));
+
+        // Nobody has block 2.
+        match fetcher.get_block_contents(&[], &blocks[2]) {
+            Err(ReqwestTransactionsFetcherError::IO(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match fetcher.get_block_contents(&[], &blocks[2]) {
+            Err(ReqwestTransactionsFetcherError::NoAvailableSources) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Earlier blocks are still served.
+        for block in &blocks[..2] {
+            fetcher.get_block_contents(&[], block).unwrap();
+        }
+        let health = fetcher.source_health();
+        assert_eq!(health[0].status, SourceStatus::Lagging(2));
+        assert_eq!(health[0].num_blocks_fetched, 4);
+        assert_eq!(health[1].status, SourceStatus::Lagging(1));
+        assert_eq!(health[1].num_blocks_fetched, 1);
+    }
+}
@@ -3,21 +3,39 @@
 //! Implementation of the `TransactionsFetcher` trait that fetches transactions data over http(s)
 //! using the `reqwest` library. It can be used, for example, to get transaction data from S3.
 
-use crate::transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher};
+use crate::{
+    counters,
+    equivocation_detector::EquivocationDetector,
+    local_block_fetcher::{LocalBlockFetcher, LocalBlockFetcherError},
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
 use common::{
     logger::{log, Logger},
-    ResponderId,
+    HashMap, ResponderId,
 };
 use failure::Fail;
-use mobilecoin_api::{blockchain, conversions::block_num_to_s3block_path};
-use reqwest::Error as ReqwestError;
+use keys::Ed25519Public;
+use mobilecoin_api::{
+    blockchain,
+    conversions::{archive_manifest_path, block_num_to_s3block_path},
+};
+use rand::Rng;
+use reqwest::{
+    header::{self, HeaderValue},
+    Error as ReqwestError, StatusCode,
+};
 use serde::{Deserialize, Serialize};
 use std::{
     convert::TryFrom,
     fs,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
 };
-use transaction::{Block, BlockContents, BlockSignature};
+use transaction::{Block, BlockContents, BlockIndex, BlockSignature, SignedArchiveManifest};
 use url::Url;
 
 #[derive(Debug, Fail)]
@@ -33,6 +51,24 @@ pub enum ReqwestTransactionsFetcherError {
 
     #[fail(display = "Received an invalid block from {}: {}", _0, _1)]
     InvalidBlockReceived(String, String),
+
+    #[fail(display = "Received an invalid manifest from {}: {}", _0, _1)]
+    InvalidManifestReceived(String, String),
+
+    #[fail(
+        display = "Received data from {} that does not match the request: {}",
+        _0, _1
+    )]
+    MismatchedDataReceived(String, String),
+
+    #[fail(display = "No trusted manifest signer is configured")]
+    NoManifestSigner,
+
+    #[fail(display = "All transaction sources are temporarily banned")]
+    NoAvailableSources,
+
+    #[fail(display = "Not a local directory: {}", _0)]
+    NotALocalDirectory(String),
 }
 
 impl From<ReqwestError> for ReqwestTransactionsFetcherError {
@@ -44,8 +80,132 @@ impl From<ReqwestError> for ReqwestTransactionsFetcherError {
     }
 }
 
+impl From<LocalBlockFetcherError> for ReqwestTransactionsFetcherError {
+    fn from(src: LocalBlockFetcherError) -> Self {
+        match src {
+            LocalBlockFetcherError::NotALocalDirectory(url) => {
+                ReqwestTransactionsFetcherError::NotALocalDirectory(url)
+            }
+            LocalBlockFetcherError::IO(path, err) => {
+                ReqwestTransactionsFetcherError::IO(path.to_string_lossy().into_owned(), err)
+            }
+            LocalBlockFetcherError::InvalidBlockReceived(path, msg) => {
+                ReqwestTransactionsFetcherError::InvalidBlockReceived(
+                    path.to_string_lossy().into_owned(),
+                    msg,
+                )
+            }
+        }
+    }
+}
+
 impl TransactionFetcherError for ReqwestTransactionsFetcherError {}
 
+/// How long a source that served an invalid block is excluded from fetching.
+pub const DEFAULT_SOURCE_BAN_DURATION: Duration = Duration::from_secs(600);
+
+/// Number of consecutive responses a source may fail to parse before it is banned. Data that does
+/// not parse may have been truncated or corrupted in transit, so it is retried before the source
+/// is blamed for it.
+pub const MAX_CONSECUTIVE_INVALID_RESPONSES: u64 = 3;
+
+/// Offenses recorded against a source that served invalid data.
+#[derive(Clone, Debug, Default)]
+struct SourceOffenses {
+    /// Total number of invalid responses served by this source.
+    num_offenses: u64,
+
+    /// Number of invalid responses served by this source since it last served a valid one.
+    num_consecutive_offenses: u64,
+
+    /// The source is excluded from fetching until this time.
+    banned_until: Option<Instant>,
+}
+
+/// Offenses recorded against a set of sources. This is shared by the fetchers of sources that
+/// fail over to one another, so that the last of them that is not banned never is.
+pub(crate) struct SourceBans {
+    source_urls: Vec<Url>,
+    offenses: Mutex<HashMap<Url, SourceOffenses>>,
+}
+
+impl SourceBans {
+    pub(crate) fn new(source_urls: Vec<Url>) -> Self {
+        Self {
+            source_urls,
+            offenses: Mutex::new(HashMap::default()),
+        }
+    }
+
+    fn is_banned(&self, source_url: &Url) -> bool {
+        let now = Instant::now();
+        self.offenses
+            .lock()
+            .expect("mutex poisoned")
+            .get(source_url)
+            .and_then(|offenses| offenses.banned_until)
+            .map_or(false, |banned_until| banned_until > now)
+    }
+}
+
+/// How requests to a source that fail with a transient error, such as a timeout or a server
+/// error, are retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Maximal number of attempts of a request, including the first one.
+    pub max_attempts: usize,
+
+    /// Delay before the first retry. The delay doubles with every retry, and is randomized by up
+    /// to half of it so that requests failing together are not retried together.
+    pub initial_backoff: Duration,
+
+    /// Maximal delay between two attempts.
+    pub max_backoff: Duration,
+
+    /// How long a single attempt may take.
+    pub request_timeout: Duration,
+}
+
+impl RetryPolicy {
+    /// The delay after a given number of failed attempts.
+    pub fn backoff(&self, num_failed_attempts: usize) -> Duration {
+        let exponent = num_failed_attempts.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        let half_backoff_nanos = (backoff.as_nanos() / 2) as u64;
+        let jitter = rand::thread_rng().gen_range(0, half_backoff_nanos + 1);
+        backoff - Duration::from_nanos(jitter)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            // The default timeout of reqwest clients.
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Returns true if a request that failed with the given error may succeed when retried.
+fn is_transient(err: &ReqwestTransactionsFetcherError) -> bool {
+    match err {
+        // Errors without a status are connection errors, timeouts, or interrupted downloads.
+        ReqwestTransactionsFetcherError::ReqwestError(_, err) => match err.status() {
+            Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            None => !err.is_redirect() && !err.is_serialization(),
+        },
+        _ => false,
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct S3BlockData {
     pub block: Block,
@@ -53,11 +213,71 @@ pub struct S3BlockData {
     pub signature: Option<BlockSignature>,
 }
 
+impl S3BlockData {
+    /// Parses a block written by ledger-distribution, and checks that its signature, if any, and
+    /// its contents match the block.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let s3_block: blockchain::S3Block = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| format!("protobuf parse failed: {:?}", err))?;
+
+        let block = Block::try_from(s3_block.get_block())
+            .map_err(|err| format!("Block conversion failed: {:?}", err))?;
+
+        let block_contents = BlockContents::try_from(s3_block.get_block_contents())
+            .map_err(|err| format!("Block contents conversion failed: {:?}", err))?;
+
+        let signature = s3_block
+            .signature
+            .into_option()
+            .as_ref()
+            .map(BlockSignature::try_from)
+            .transpose()
+            .map_err(|err| format!("Invalid block signature: {:?}", err))?;
+
+        if let Some(signature) = signature.as_ref() {
+            signature
+                .verify(&block)
+                .map_err(|err| format!("Unable to verify block signature: {:?}", err))?;
+        }
+
+        if block.contents_hash != block_contents.hash() {
+            return Err(format!(
+                "Invalid block contents hash. Block: {:?}, BlockContents: {:?}",
+                block, block_contents
+            ));
+        }
+
+        Ok(Self {
+            block,
+            block_contents,
+            signature,
+        })
+    }
+}
+
 pub struct ReqwestTransactionsFetcher {
     pub source_urls: Vec<Url>,
     client: reqwest::Client,
     logger: Logger,
     source_index_counter: AtomicU64,
+
+    /// How long a misbehaving source is excluded for.
+    source_ban_duration: Duration,
+
+    /// Offenses recorded against misbehaving sources.
+    source_bans: Arc<SourceBans>,
+
+    /// An optional detector every signed block fetched is reported to.
+    equivocation_detector: Option<Arc<EquivocationDetector>>,
+
+    /// The key archive manifests must be signed with to be trusted.
+    manifest_signer: Option<Ed25519Public>,
+
+    /// Blocks of `file://` sources are read from the local directory they point to.
+    local_block_fetchers: HashMap<Url, LocalBlockFetcher>,
+
+    /// How failed requests are retried.
+    retry_policy: RetryPolicy,
 }
 
 impl ReqwestTransactionsFetcher {
@@ -89,96 +309,485 @@ impl ReqwestTransactionsFetcher {
             })
             .collect();
 
+        let source_urls = source_urls?;
+
+        let mut local_block_fetchers = HashMap::default();
+        for source_url in source_urls.iter().filter(|url| url.scheme() == "file") {
+            local_block_fetchers.insert(
+                source_url.clone(),
+                LocalBlockFetcher::from_url(source_url, logger.clone())?,
+            );
+        }
+
         Ok(Self {
-            source_urls: source_urls?,
+            source_bans: Arc::new(SourceBans::new(source_urls.clone())),
+            source_urls,
             client,
             logger,
             source_index_counter: AtomicU64::new(0),
+            source_ban_duration: DEFAULT_SOURCE_BAN_DURATION,
+            equivocation_detector: None,
+            manifest_signer: None,
+            local_block_fetchers,
+            retry_policy: RetryPolicy::default(),
         })
     }
 
+    /// Sets how long a source that served an invalid block is excluded from fetching.
+    pub fn set_source_ban_duration(&mut self, source_ban_duration: Duration) {
+        self.source_ban_duration = source_ban_duration;
+    }
+
+    /// Shares the offenses recorded against sources with other fetchers, which must cover this
+    /// fetcher's sources.
+    pub(crate) fn set_source_bans(&mut self, source_bans: Arc<SourceBans>) {
+        self.source_bans = source_bans;
+    }
+
+    /// Sets how failed requests are retried. This replaces the client with one that times out
+    /// requests after `retry_policy.request_timeout`.
+    pub fn set_retry_policy(
+        &mut self,
+        retry_policy: RetryPolicy,
+    ) -> Result<(), ReqwestTransactionsFetcherError> {
+        self.client = reqwest::Client::builder()
+            .timeout(retry_policy.request_timeout)
+            .build()?;
+        self.retry_policy = retry_policy;
+        Ok(())
+    }
+
+    /// Sets a detector every signed block fetched is reported to.
+    pub fn set_equivocation_detector(&mut self, equivocation_detector: Arc<EquivocationDetector>) {
+        for local_block_fetcher in self.local_block_fetchers.values_mut() {
+            local_block_fetcher.set_equivocation_detector(equivocation_detector.clone());
+        }
+        self.equivocation_detector = Some(equivocation_detector);
+    }
+
+    /// Sets the key archive manifests must be signed with to be trusted.
+    pub fn set_manifest_signer(&mut self, manifest_signer: Ed25519Public) {
+        self.manifest_signer = Some(manifest_signer);
+    }
+
+    /// Returns true if the given source is currently excluded from fetching.
+    pub fn is_source_banned(&self, source_url: &Url) -> bool {
+        self.source_bans.is_banned(source_url)
+    }
+
+    /// Records that a source served valid data.
+    fn record_success(&self, source_url: &Url) {
+        if let Some(offenses) = self
+            .source_bans
+            .offenses
+            .lock()
+            .expect("mutex poisoned")
+            .get_mut(source_url)
+        {
+            offenses.num_consecutive_offenses = 0;
+        }
+    }
+
+    /// Records an offense against a source that served invalid data, and excludes it from fetching
+    /// for a cooldown period if:
+    /// * it served data that parses, but does not match what was requested, or
+    /// * it served data that does not parse `MAX_CONSECUTIVE_INVALID_RESPONSES` times in a row.
+    ///
+    /// The last source that is not banned is never banned, so that syncing can still make progress
+    /// if it was only unlucky. Other errors, such as a source being unreachable, are not offenses.
+    pub(crate) fn record_offense(&self, source_url: &Url, err: &ReqwestTransactionsFetcherError) {
+        let is_mismatch = match err {
+            ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _) => true,
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(_, _)
+            | ReqwestTransactionsFetcherError::InvalidManifestReceived(_, _) => false,
+            _ => return,
+        };
+
+        let now = Instant::now();
+        let mut source_offenses = self.source_bans.offenses.lock().expect("mutex poisoned");
+        let is_last_available_source = self.source_bans.source_urls.iter().all(|other_url| {
+            other_url == source_url
+                || source_offenses
+                    .get(other_url)
+                    .and_then(|offenses| offenses.banned_until)
+                    .map_or(false, |banned_until| banned_until > now)
+        });
+
+        let offenses = source_offenses.entry(source_url.clone()).or_default();
+        offenses.num_offenses += 1;
+        offenses.num_consecutive_offenses += 1;
+
+        if !is_mismatch && offenses.num_consecutive_offenses < MAX_CONSECUTIVE_INVALID_RESPONSES {
+            log::warn!(
+                self.logger,
+                "Transaction source {} served invalid data ({} time(s) in a row): {}",
+                source_url,
+                offenses.num_consecutive_offenses,
+                err
+            );
+            return;
+        }
+
+        if is_last_available_source {
+            log::warn!(
+                self.logger,
+                "Not banning transaction source {}, the last one available, after {} offense(s): {}",
+                source_url,
+                offenses.num_offenses,
+                err
+            );
+            return;
+        }
+
+        offenses.banned_until = Some(now + self.source_ban_duration);
+        offenses.num_consecutive_offenses = 0;
+        log::error!(
+            self.logger,
+            "Banning transaction source {} for {:?} after {} offense(s): {}",
+            source_url,
+            self.source_ban_duration,
+            offenses.num_offenses,
+            err
+        );
+        counters::SOURCE_BANNED_COUNT.inc();
+    }
+
+    /// Picks the next source to fetch from, skipping over banned sources.
+    fn next_source_url(&self) -> Result<&Url, ReqwestTransactionsFetcherError> {
+        for _ in 0..self.source_urls.len() {
+            let source_index_counter =
+                self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
+            let source_url = &self.source_urls[source_index_counter % self.source_urls.len()];
+            if !self.is_source_banned(source_url) {
+                return Ok(source_url);
+            }
+        }
+
+        Err(ReqwestTransactionsFetcherError::NoAvailableSources)
+    }
+
+    fn bytes_from_url(&self, url: &Url) -> Result<Vec<u8>, ReqwestTransactionsFetcherError> {
+        // Special treatment for file:// to read from a local directory.
+        if url.scheme() == "file" {
+            let path = &url[url::Position::BeforeHost..url::Position::AfterPath];
+            return fs::read(path)
+                .map_err(|err| ReqwestTransactionsFetcherError::IO(path.to_string(), err));
+        }
+
+        let mut bytes = Vec::new();
+        let mut etag = None;
+        let mut num_attempts = 0;
+        loop {
+            num_attempts += 1;
+            match self.download_into(url, &mut bytes, &mut etag) {
+                Ok(()) => return Ok(bytes),
+                Err(err) if num_attempts < self.retry_policy.max_attempts && is_transient(&err) => {
+                    let backoff = self.retry_policy.backoff(num_attempts);
+                    log::warn!(
+                        self.logger,
+                        "Attempt {} to fetch {} failed, retrying in {:?}: {}",
+                        num_attempts,
+                        url,
+                        backoff,
+                        err
+                    );
+                    counters::FETCH_RETRY_COUNT.inc();
+                    thread::sleep(backoff);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Downloads an object, appending it to `bytes`. If `bytes` holds the beginning of the object
+    /// from an interrupted download, and `etag` identifies the version of the object it came from,
+    /// only the rest of that version of the object is requested. `etag` is set to the ETag of the
+    /// object downloaded.
+    fn download_into(
+        &self,
+        url: &Url,
+        bytes: &mut Vec<u8>,
+        etag: &mut Option<HeaderValue>,
+    ) -> Result<(), ReqwestTransactionsFetcherError> {
+        let mut request = self.client.get(url.as_str());
+        match etag.as_ref() {
+            // If the object changed since, the server ignores the range and sends all of it, so
+            // the beginning of one version is never stitched to the rest of another.
+            Some(etag) if !bytes.is_empty() => {
+                request = request
+                    .header(header::RANGE, format!("bytes={}-", bytes.len()))
+                    .header(header::IF_RANGE, etag.clone());
+            }
+            // Without an ETag there is no telling which version the beginning came from.
+            _ => bytes.clear(),
+        }
+
+        let response = request
+            .send()
+            .map_err(|err| ReqwestTransactionsFetcherError::ReqwestError(url.to_string(), err))?;
+
+        // A missing block is reported as an error, rather than parsed as an invalid block.
+        let mut response = response.error_for_status()?;
+
+        // A server that does not support ranges, or whose object changed, sends all of it again.
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            bytes.clear();
+        }
+        *etag = response.headers().get(header::ETAG).cloned();
+
+        response.copy_to(bytes)?;
+        Ok(())
+    }
+
     pub fn block_from_url(
         &self,
         url: &Url,
     ) -> Result<S3BlockData, ReqwestTransactionsFetcherError> {
-        // Special treatment for file:// to read from a local directory.
-        let bytes: Vec<u8> = if url.scheme() == "file" {
-            let path = &url[url::Position::BeforeHost..url::Position::AfterPath];
-            fs::read(path)
-                .map_err(|err| ReqwestTransactionsFetcherError::IO(path.to_string(), err))?
-                .to_vec()
-        } else {
-            let mut response = self.client.get(url.as_str()).send().map_err(|err| {
-                ReqwestTransactionsFetcherError::ReqwestError(url.to_string(), err)
-            })?;
-
-            let mut bytes = Vec::new();
-            response.copy_to(&mut bytes)?;
-            bytes
-        };
+        let bytes = self.bytes_from_url(url)?;
 
-        let s3_block: blockchain::S3Block = protobuf::parse_from_bytes(&bytes).map_err(|err| {
-            ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                format!("prorobuf parse failed: {:?}", err),
-            )
+        let s3_block_data = S3BlockData::from_bytes(&bytes).map_err(|err| {
+            ReqwestTransactionsFetcherError::InvalidBlockReceived(url.to_string(), err)
         })?;
 
-        let block = Block::try_from(s3_block.get_block()).map_err(|err| {
-            ReqwestTransactionsFetcherError::InvalidBlockReceived(
+        if let Some(signature) = s3_block_data.signature.as_ref() {
+            if let Some(equivocation_detector) = self.equivocation_detector.as_ref() {
+                equivocation_detector.observe(url.as_str(), &s3_block_data.block, signature);
+            }
+        }
+
+        Ok(s3_block_data)
+    }
+
+    /// Fetches a manifest and checks that it is signed by the trusted manifest signer.
+    pub fn manifest_from_url(
+        &self,
+        url: &Url,
+    ) -> Result<SignedArchiveManifest, ReqwestTransactionsFetcherError> {
+        let manifest_signer = self
+            .manifest_signer
+            .as_ref()
+            .ok_or(ReqwestTransactionsFetcherError::NoManifestSigner)?;
+
+        let bytes = self.bytes_from_url(url)?;
+
+        let invalid_manifest = |msg: String| {
+            ReqwestTransactionsFetcherError::InvalidManifestReceived(url.to_string(), msg)
+        };
+
+        let bc_manifest: blockchain::SignedArchiveManifest = protobuf::parse_from_bytes(&bytes)
+            .map_err(|err| invalid_manifest(format!("protobuf parse failed: {:?}", err)))?;
+
+        let manifest = SignedArchiveManifest::try_from(&bc_manifest)
+            .map_err(|err| invalid_manifest(format!("Manifest conversion failed: {:?}", err)))?;
+
+        if manifest.signer() != manifest_signer {
+            return Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(
                 url.to_string(),
-                format!("Block conversion failed: {:?}", err),
-            )
+                format!("Manifest signed by untrusted key {:?}", manifest.signer()),
+            ));
+        }
+
+        manifest.verify().map_err(|err| {
+            invalid_manifest(format!("Unable to verify manifest signature: {:?}", err))
         })?;
 
-        let block_contents =
-            BlockContents::try_from(s3_block.get_block_contents()).map_err(|err| {
-                ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    format!("Block contents conversion failed: {:?}", err),
-                )
-            })?;
+        Ok(manifest)
+    }
 
-        let signature = s3_block
-            .signature
-            .into_option()
-            .as_ref()
-            .map(BlockSignature::try_from)
-            .transpose()
-            .map_err(|err| {
-                ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    format!("Invalid block signature: {:?}", err),
-                )
-            })?;
+    /// Fetches the range of blocks covered by the manifest starting at `first_block_index`, and
+    /// checks all of them against the manifest before returning them. This lets a large range be
+    /// validated in bulk, instead of trusting each block as it is appended.
+    ///
+    /// The manifest and all of its blocks are fetched from a single source, which is banned if it
+    /// serves a manifest or blocks that do not check out. See `record_offense`.
+    pub fn get_verified_blocks(
+        &self,
+        first_block_index: BlockIndex,
+    ) -> Result<Vec<S3BlockData>, ReqwestTransactionsFetcherError> {
+        let source_url = self.next_source_url()?;
 
-        if let Some(signature) = signature.as_ref() {
-            signature.verify(&block).map_err(|err| {
-                ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                    url.to_string(),
-                    format!("Unable to verify block signature: {:?}", err),
-                )
-            })?;
+        let result = self.get_verified_blocks_from_source(source_url, first_block_index);
+
+        match &result {
+            Ok(_) => self.record_success(source_url),
+            Err(err) => self.record_offense(source_url, err),
         }
 
-        if block.contents_hash != block_contents.hash() {
-            return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
+        result
+    }
+
+    fn get_verified_blocks_from_source(
+        &self,
+        source_url: &Url,
+        first_block_index: BlockIndex,
+    ) -> Result<Vec<S3BlockData>, ReqwestTransactionsFetcherError> {
+        let join = |filename: String| {
+            source_url
+                .join(&filename)
+                .map_err(|err| ReqwestTransactionsFetcherError::UrlParse(filename, err))
+        };
+
+        let manifest_url = join(
+            archive_manifest_path(first_block_index)
+                .into_os_string()
+                .into_string()
+                .unwrap(),
+        )?;
+        log::debug!(
+            self.logger,
+            "Attempting to fetch manifest for block {} from {}",
+            first_block_index,
+            manifest_url
+        );
+        let manifest = self.manifest_from_url(&manifest_url)?;
+        let manifest = manifest.manifest();
+
+        if manifest.first_block_index != first_block_index {
+            return Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(
+                manifest_url.to_string(),
                 format!(
-                    "Invalid block contents hash. Block: {:?}, BlockContents: {:?}",
-                    block, block_contents
+                    "Manifest starts at block {}, expected {}",
+                    manifest.first_block_index, first_block_index
                 ),
             ));
         }
 
-        let s3_block_data = S3BlockData {
-            block,
-            block_contents,
-            signature,
+        let mut s3_blocks = Vec::new();
+        for block_index in first_block_index..manifest.end_block_index() {
+            let url = join(
+                block_num_to_s3block_path(block_index)
+                    .into_os_string()
+                    .into_string()
+                    .unwrap(),
+            )?;
+            s3_blocks.push(self.block_from_url(&url)?);
+        }
+
+        let blocks: Vec<Block> = s3_blocks
+            .iter()
+            .map(|s3_block_data| s3_block_data.block.clone())
+            .collect();
+        manifest.verify_blocks(&blocks).map_err(|err| {
+            ReqwestTransactionsFetcherError::MismatchedDataReceived(
+                source_url.to_string(),
+                format!(
+                    "Blocks {}-{} do not match the manifest: {}",
+                    first_block_index,
+                    manifest.end_block_index() - 1,
+                    err
+                ),
+            )
+        })?;
+
+        Ok(s3_blocks)
+    }
+
+    /// Fetches the block at `block_index`, along with its contents and signature, from the next
+    /// source that is not banned.
+    pub fn get_block_data(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<S3BlockData, ReqwestTransactionsFetcherError> {
+        let source_url = self.next_source_url()?;
+        self.get_block_data_from_source(source_url, block_index)
+    }
+
+    fn get_block_data_from_source(
+        &self,
+        source_url: &Url,
+        block_index: BlockIndex,
+    ) -> Result<S3BlockData, ReqwestTransactionsFetcherError> {
+        let result = match self.local_block_fetchers.get(source_url) {
+            Some(local_block_fetcher) => local_block_fetcher
+                .get_block_data(block_index)
+                .map_err(ReqwestTransactionsFetcherError::from),
+            None => self.get_remote_block_data(source_url, block_index),
         };
+
+        // A source serving invalid data may be misbehaving, as opposed to being unreachable. Stop
+        // using it for a while so that we continue syncing from the other sources.
+        match &result {
+            Ok(_) => self.record_success(source_url),
+            Err(err) => self.record_offense(source_url, err),
+        }
+
+        result
+    }
+
+    fn get_remote_block_data(
+        &self,
+        source_url: &Url,
+        block_index: BlockIndex,
+    ) -> Result<S3BlockData, ReqwestTransactionsFetcherError> {
+        // Construct URL for the block we are trying to fetch.
+        let filename = block_num_to_s3block_path(block_index)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        let url = source_url
+            .join(&filename)
+            .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))?;
+
+        // Try and get the block.
+        log::debug!(
+            self.logger,
+            "Attempting to fetch block {} from {}",
+            block_index,
+            url
+        );
+
+        let s3_block_data = self.block_from_url(&url)?;
+        if s3_block_data.block.index != block_index || !s3_block_data.block.is_block_id_valid() {
+            return Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(
+                url.to_string(),
+                format!("invalid block {:?}", s3_block_data.block),
+            ));
+        }
+
         Ok(s3_block_data)
     }
 
+    /// Fetches a block from every source that is not banned, and returns the valid signatures
+    /// they serve for it. Sources that serve a different block, or no signature, are skipped.
+    pub fn get_block_signatures(&self, block: &Block) -> Vec<BlockSignature> {
+        let filename = block_num_to_s3block_path(block.index)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+
+        self.source_urls
+            .iter()
+            .filter(|source_url| !self.is_source_banned(source_url))
+            .filter_map(|source_url| {
+                let url = source_url.join(&filename).ok()?;
+                match self.block_from_url(&url) {
+                    Ok(s3_block_data) if s3_block_data.block == *block => s3_block_data.signature,
+                    Ok(s3_block_data) => {
+                        log::debug!(
+                            self.logger,
+                            "{} serves block {:?} for index {}, expected {:?}",
+                            url,
+                            s3_block_data.block.id,
+                            block.index,
+                            block.id,
+                        );
+                        None
+                    }
+                    Err(err) => {
+                        log::debug!(
+                            self.logger,
+                            "Failed fetching block {} from {}: {}",
+                            block.index,
+                            url,
+                            err
+                        );
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
     pub fn get_origin_block_and_transactions(
         &self,
     ) -> Result<(Block, BlockContents), ReqwestTransactionsFetcherError> {
@@ -198,42 +807,278 @@ impl TransactionsFetcher for ReqwestTransactionsFetcher {
 
     fn get_block_contents(
         &self,
-        _safe_responder_ids: &[ResponderId],
+        safe_responder_ids: &[ResponderId],
         block: &Block,
     ) -> Result<BlockContents, Self::Error> {
+        let (block_contents, _signature) =
+            self.get_block_contents_and_signature(safe_responder_ids, block)?;
+        Ok(block_contents)
+    }
+
+    fn get_block_contents_and_signature(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
         // Get the source to fetch from.
-        let source_index_counter =
-            self.source_index_counter.fetch_add(1, Ordering::SeqCst) as usize;
-        let source_url = &self.source_urls[source_index_counter % self.source_urls.len()];
+        let source_url = self.next_source_url()?;
 
-        // Construct URL for the block we are trying to fetch.
-        let filename = block_num_to_s3block_path(block.index)
-            .into_os_string()
-            .into_string()
-            .unwrap();
-        let url = source_url
-            .join(&filename)
-            .map_err(|e| ReqwestTransactionsFetcherError::UrlParse(filename, e))?;
+        let s3_block_data = self.get_block_data_from_source(source_url, block.index)?;
 
-        // Try and get the block.
-        log::debug!(
-            self.logger,
-            "Attempting to fetch block {} from {}",
+        // Check that we received data for the block we actually asked about.
+        if *block != s3_block_data.block {
+            let err = ReqwestTransactionsFetcherError::MismatchedDataReceived(
+                source_url.to_string(),
+                format!("block data mismatch for block {}", block.index),
+            );
+            self.record_offense(source_url, &err);
+            return Err(err);
+        }
+
+        Ok((s3_block_data.block_contents, s3_block_data.signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{get_blocks, write_block};
+    use common::logger::test_with_logger;
+    use keys::{Ed25519Pair, FromRandom};
+    use protobuf::Message;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::path::Path;
+    use tempdir::TempDir;
+    use transaction::{ArchiveManifest, BLOCK_VERSION};
+
+    /// Writes blocks, and a manifest covering all of them, the way ledger-distribution does.
+    fn write_archive(dir: &Path, blocks: &[Block], keypair: &Ed25519Pair) {
+        for block in blocks {
+            write_block(dir, block);
+        }
+
+        let manifest =
+            SignedArchiveManifest::from_manifest_and_keypair(ArchiveManifest::new(blocks), keypair)
+                .unwrap();
+        let path = dir.join(archive_manifest_path(blocks[0].index));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            blockchain::SignedArchiveManifest::from(&manifest)
+                .write_to_bytes()
+                .unwrap(),
+        )
+        .unwrap();
+    }
+
+    /// A valid block, with empty contents, at the same index as `block` but with another parent.
+    fn other_origin_block(block: &Block) -> Block {
+        Block::new(
+            BLOCK_VERSION,
+            &block.id,
             block.index,
-            url
+            &Default::default(),
+            &BlockContents::new(Vec::new(), Vec::new()),
+        )
+    }
+
+    #[test_with_logger]
+    // A source that keeps serving a block that does not parse should be skipped until its ban
+    // expires.
+    fn test_invalid_block_bans_source(logger: Logger) {
+        let block = Block::new_origin_block(&[]);
+        let filename = block_num_to_s3block_path(block.index);
+
+        // The "bad" source serves garbage, the "good" source does not have the block at all.
+        let bad_dir = TempDir::new("bad_source").unwrap();
+        let bad_path = bad_dir.path().join(&filename);
+        fs::create_dir_all(bad_path.parent().unwrap()).unwrap();
+        fs::write(&bad_path, b"not a block").unwrap();
+        let good_dir = TempDir::new("good_source").unwrap();
+
+        let bad_url = format!("file://{}", bad_dir.path().to_str().unwrap());
+        let good_url = format!("file://{}", good_dir.path().to_str().unwrap());
+        let mut fetcher = ReqwestTransactionsFetcher::new(vec![bad_url, good_url], logger).unwrap();
+        fetcher.set_source_ban_duration(Duration::from_secs(60));
+        let bad_source_url = fetcher.source_urls[0].clone();
+        let good_source_url = fetcher.source_urls[1].clone();
+
+        // Attempts alternate between the sources. The bad source is only banned once it served
+        // garbage enough times in a row.
+        for num_attempts in 1..=MAX_CONSECUTIVE_INVALID_RESPONSES {
+            assert!(!fetcher.is_source_banned(&bad_source_url));
+            match fetcher.get_block_contents(&[], &block) {
+                Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(_, _)) => {}
+                result => panic!("unexpected result {:?}", result),
+            }
+            if num_attempts < MAX_CONSECUTIVE_INVALID_RESPONSES {
+                match fetcher.get_block_contents(&[], &block) {
+                    Err(ReqwestTransactionsFetcherError::IO(_, _)) => {}
+                    result => panic!("unexpected result {:?}", result),
+                }
+            }
+        }
+        assert!(fetcher.is_source_banned(&bad_source_url));
+        assert!(!fetcher.is_source_banned(&good_source_url));
+
+        // Subsequent attempts only go to the good source, which does not ban it.
+        for _ in 0..4 {
+            match fetcher.get_block_contents(&[], &block) {
+                Err(ReqwestTransactionsFetcherError::IO(_, _)) => {}
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+        assert!(!fetcher.is_source_banned(&good_source_url));
+    }
+
+    #[test_with_logger]
+    // A source serving a valid block other than the one requested should be banned right away.
+    fn test_mismatched_block_bans_source(logger: Logger) {
+        let block = Block::new_origin_block(&[]);
+        let other_block = other_origin_block(&block);
+
+        let other_dir = TempDir::new("other_source").unwrap();
+        write_block(other_dir.path(), &other_block);
+        let good_dir = TempDir::new("good_source").unwrap();
+
+        let other_url = format!("file://{}", other_dir.path().to_str().unwrap());
+        let good_url = format!("file://{}", good_dir.path().to_str().unwrap());
+        let fetcher = ReqwestTransactionsFetcher::new(vec![other_url, good_url], logger).unwrap();
+        let other_source_url = fetcher.source_urls[0].clone();
+
+        match fetcher.get_block_contents(&[], &block) {
+            Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(fetcher.is_source_banned(&other_source_url));
+    }
+
+    #[test_with_logger]
+    // The last source that is not banned should keep being fetched from, however it misbehaves.
+    fn test_last_source_is_not_banned(logger: Logger) {
+        let block = Block::new_origin_block(&[]);
+        let other_block = other_origin_block(&block);
+
+        let dirs: Vec<TempDir> = (0..2)
+            .map(|_| {
+                let dir = TempDir::new("other_source").unwrap();
+                write_block(dir.path(), &other_block);
+                dir
+            })
+            .collect();
+        let urls = dirs
+            .iter()
+            .map(|dir| format!("file://{}", dir.path().to_str().unwrap()))
+            .collect();
+        let fetcher = ReqwestTransactionsFetcher::new(urls, logger).unwrap();
+
+        for _ in 0..4 {
+            match fetcher.get_block_contents(&[], &block) {
+                Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _)) => {}
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+        assert!(fetcher.is_source_banned(&fetcher.source_urls[0]));
+        assert!(!fetcher.is_source_banned(&fetcher.source_urls[1]));
+    }
+
+    #[test_with_logger]
+    // A range of blocks matching a manifest signed by the trusted key should be returned.
+    fn test_get_verified_blocks(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let keypair = Ed25519Pair::from_random(&mut rng);
+        let blocks = get_blocks(10);
+
+        let dir = TempDir::new("archive").unwrap();
+        write_archive(dir.path(), &blocks[5..10], &keypair);
+
+        let url = format!("file://{}", dir.path().to_str().unwrap());
+        let mut fetcher = ReqwestTransactionsFetcher::new(vec![url], logger).unwrap();
+
+        // Without a trusted signer, manifests cannot be checked.
+        match fetcher.get_verified_blocks(5) {
+            Err(ReqwestTransactionsFetcherError::NoManifestSigner) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        fetcher.set_manifest_signer(keypair.public_key());
+        let fetched: Vec<Block> = fetcher
+            .get_verified_blocks(5)
+            .unwrap()
+            .into_iter()
+            .map(|s3_block_data| s3_block_data.block)
+            .collect();
+        assert_eq!(fetched, blocks[5..10].to_vec());
+    }
+
+    #[test_with_logger]
+    // A source serving blocks that do not match its manifest, or a manifest signed by another
+    // key, should be banned, unless it is the last source available.
+    fn test_get_verified_blocks_rejects_mismatches(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let keypair = Ed25519Pair::from_random(&mut rng);
+        let other_keypair = Ed25519Pair::from_random(&mut rng);
+        let blocks = get_blocks(4);
+
+        // A source whose manifest covers blocks other than the ones it serves.
+        let tampered_dir = TempDir::new("tampered_source").unwrap();
+        write_archive(tampered_dir.path(), &blocks, &keypair);
+        let other_block = Block::new(
+            BLOCK_VERSION,
+            &blocks[0].id,
+            2,
+            &Default::default(),
+            &BlockContents::new(Vec::new(), Vec::new()),
         );
+        write_block(tampered_dir.path(), &other_block);
 
-        let s3_block_data = self.block_from_url(&url)?;
+        // A source whose manifest is signed by an untrusted key.
+        let untrusted_dir = TempDir::new("untrusted_source").unwrap();
+        write_archive(untrusted_dir.path(), &blocks, &other_keypair);
 
-        // Check that we received data for the block we actually asked about.
-        if *block != s3_block_data.block {
-            return Err(ReqwestTransactionsFetcherError::InvalidBlockReceived(
-                url.to_string(),
-                "block data mismatch".to_string(),
-            ));
+        let tampered_url = format!("file://{}", tampered_dir.path().to_str().unwrap());
+        let untrusted_url = format!("file://{}", untrusted_dir.path().to_str().unwrap());
+        let mut fetcher =
+            ReqwestTransactionsFetcher::new(vec![tampered_url, untrusted_url], logger).unwrap();
+        fetcher.set_manifest_signer(keypair.public_key());
+        let tampered_source_url = fetcher.source_urls[0].clone();
+        let untrusted_source_url = fetcher.source_urls[1].clone();
+
+        match fetcher.get_verified_blocks(0) {
+            Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(fetcher.is_source_banned(&tampered_source_url));
+
+        match fetcher.get_verified_blocks(0) {
+            Err(ReqwestTransactionsFetcherError::MismatchedDataReceived(_, _)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(!fetcher.is_source_banned(&untrusted_source_url));
+    }
+
+    #[test]
+    // Delays between attempts should double up to the maximal backoff, minus up to half of the
+    // delay of jitter.
+    fn test_retry_policy_backoff() {
+        let retry_policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+            request_timeout: Duration::from_secs(1),
+        };
+
+        let expected_backoffs_ms = [100, 200, 400, 800, 1000, 1000];
+        for (num_failed_attempts, expected_backoff_ms) in (1..).zip(expected_backoffs_ms.iter()) {
+            let expected_backoff = Duration::from_millis(*expected_backoff_ms);
+            for _ in 0..10 {
+                let backoff = retry_policy.backoff(num_failed_attempts);
+                assert!(backoff <= expected_backoff);
+                assert!(backoff >= expected_backoff / 2);
+            }
         }
 
-        // Got what we wanted!
-        Ok(s3_block_data.block_contents)
+        // Large numbers of attempts do not overflow.
+        assert!(retry_policy.backoff(1000) <= retry_policy.max_backoff);
     }
 }
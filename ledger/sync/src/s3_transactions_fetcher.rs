@@ -0,0 +1,363 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Implementation of the `TransactionsFetcher` trait that fetches transactions data through the S3
+//! API, with credentials. This allows syncing from private buckets, on S3 or on services that
+//! support the S3 API such as Google Cloud Storage.
+
+use crate::{
+    equivocation_detector::EquivocationDetector,
+    reqwest_transactions_fetcher::S3BlockData,
+    transactions_fetcher_trait::{TransactionFetcherError, TransactionsFetcher},
+};
+use common::{
+    logger::{log, Logger},
+    ResponderId,
+};
+use failure::Fail;
+use mobilecoin_api::conversions::block_num_to_s3block_path;
+use rusoto_core::{
+    credential::{DefaultCredentialsProvider, StaticProvider},
+    region::ParseRegionError,
+    HttpClient, Region, RusotoError,
+};
+use rusoto_s3::{GetObjectError, GetObjectRequest, S3Client, S3};
+use std::{io::Read, str::FromStr, sync::Arc};
+use transaction::{Block, BlockContents, BlockIndex, BlockSignature};
+use url::Url;
+
+/// The endpoint of the Google Cloud Storage API that is compatible with S3.
+pub const GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+
+#[derive(Debug, Fail)]
+pub enum S3TransactionsFetcherError {
+    #[fail(display = "Url parse error on {}: {}", _0, _1)]
+    UrlParse(String, url::ParseError),
+
+    #[fail(display = "Unknown scheme: {}", _0)]
+    UnknownScheme(String),
+
+    #[fail(display = "Missing bucket in {}", _0)]
+    MissingBucket(String),
+
+    #[fail(display = "Invalid S3 region: {}", _0)]
+    InvalidRegion(ParseRegionError),
+
+    #[fail(display = "Failed creating S3 client: {}", _0)]
+    Client(String),
+
+    #[fail(display = "S3 error on {}: {}", _0, _1)]
+    GetObject(String, RusotoError<GetObjectError>),
+
+    #[fail(display = "IO error on {}: {:?}", _0, _1)]
+    IO(String, std::io::Error),
+
+    #[fail(display = "Received an invalid block from {}: {}", _0, _1)]
+    InvalidBlockReceived(String, String),
+}
+
+impl TransactionFetcherError for S3TransactionsFetcherError {}
+
+/// A bucket holding blocks written by ledger-distribution, and the region it is in.
+///
+/// Parsed from `s3://bucket/prefix?region=us-west-1` for S3, or `gs://bucket/prefix` for Google
+/// Cloud Storage. Other services supporting the S3 API can be reached with
+/// `s3://bucket/prefix?endpoint=https://...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct S3ArchiveLocation {
+    pub bucket: String,
+
+    /// Path of the archive inside the bucket, without leading or trailing slashes.
+    pub prefix: String,
+
+    pub region: Region,
+}
+
+impl S3ArchiveLocation {
+    /// The key of the object holding a block.
+    fn block_key(&self, block_index: BlockIndex) -> String {
+        let path = block_num_to_s3block_path(block_index)
+            .into_os_string()
+            .into_string()
+            .unwrap();
+        if self.prefix.is_empty() {
+            path
+        } else {
+            format!("{}/{}", self.prefix, path)
+        }
+    }
+}
+
+impl FromStr for S3ArchiveLocation {
+    type Err = S3TransactionsFetcherError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(src)
+            .map_err(|err| S3TransactionsFetcherError::UrlParse(src.to_string(), err))?;
+
+        let bucket = url.host_str().unwrap_or("").to_string();
+        if bucket.is_empty() {
+            return Err(S3TransactionsFetcherError::MissingBucket(src.to_string()));
+        }
+        let prefix = url.path().trim_matches('/').to_string();
+
+        let query_param = |name: &str| {
+            url.query_pairs()
+                .find(|(k, v)| k == name && !v.is_empty())
+                .map(|(_k, v)| v.to_string())
+        };
+
+        let region = match url.scheme() {
+            "s3" => match (query_param("region"), query_param("endpoint")) {
+                (region, Some(endpoint)) => Region::Custom {
+                    name: region.unwrap_or_else(|| Region::default().name().to_string()),
+                    endpoint,
+                },
+                (Some(region), None) => {
+                    Region::from_str(&region).map_err(S3TransactionsFetcherError::InvalidRegion)?
+                }
+                (None, None) => Region::default(),
+            },
+
+            // GCS ignores the region, but it is part of the request signature.
+            "gs" => Region::Custom {
+                name: "auto".to_string(),
+                endpoint: GCS_ENDPOINT.to_string(),
+            },
+
+            scheme => {
+                return Err(S3TransactionsFetcherError::UnknownScheme(
+                    scheme.to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            bucket,
+            prefix,
+            region,
+        })
+    }
+}
+
+/// Keys for accessing a private bucket. On Google Cloud Storage, these are HMAC keys.
+#[derive(Clone)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+pub struct S3TransactionsFetcher {
+    pub location: S3ArchiveLocation,
+    s3_client: S3Client,
+    logger: Logger,
+
+    /// An optional detector every signed block fetched is reported to.
+    equivocation_detector: Option<Arc<EquivocationDetector>>,
+}
+
+impl S3TransactionsFetcher {
+    /// Creates a fetcher for the given bucket.
+    ///
+    /// # Arguments
+    /// * `location` - The bucket, and prefix inside it, to fetch blocks from.
+    /// * `credentials` - Keys for accessing the bucket. When not provided, they are looked up the
+    ///   way AWS tools do: in the environment, in `~/.aws/credentials`, or from instance metadata.
+    /// * `logger` - Logger.
+    pub fn new(
+        location: S3ArchiveLocation,
+        credentials: Option<S3Credentials>,
+        logger: Logger,
+    ) -> Result<Self, S3TransactionsFetcherError> {
+        let http_client =
+            HttpClient::new().map_err(|err| S3TransactionsFetcherError::Client(err.to_string()))?;
+
+        let s3_client = match credentials {
+            Some(credentials) => S3Client::new_with(
+                http_client,
+                StaticProvider::new_minimal(
+                    credentials.access_key_id,
+                    credentials.secret_access_key,
+                ),
+                location.region.clone(),
+            ),
+            None => S3Client::new_with(
+                http_client,
+                DefaultCredentialsProvider::new()
+                    .map_err(|err| S3TransactionsFetcherError::Client(err.to_string()))?,
+                location.region.clone(),
+            ),
+        };
+
+        Ok(Self {
+            location,
+            s3_client,
+            logger,
+            equivocation_detector: None,
+        })
+    }
+
+    /// Sets a detector every signed block fetched is reported to.
+    pub fn set_equivocation_detector(&mut self, equivocation_detector: Arc<EquivocationDetector>) {
+        self.equivocation_detector = Some(equivocation_detector);
+    }
+
+    fn bytes_from_key(&self, key: &str) -> Result<Vec<u8>, S3TransactionsFetcherError> {
+        let request = GetObjectRequest {
+            bucket: self.location.bucket.clone(),
+            key: key.to_string(),
+            ..Default::default()
+        };
+
+        let output = self
+            .s3_client
+            .get_object(request)
+            .sync()
+            .map_err(|err| S3TransactionsFetcherError::GetObject(key.to_string(), err))?;
+
+        let mut bytes = Vec::new();
+        if let Some(body) = output.body {
+            body.into_blocking_read()
+                .read_to_end(&mut bytes)
+                .map_err(|err| S3TransactionsFetcherError::IO(key.to_string(), err))?;
+        }
+        Ok(bytes)
+    }
+
+    /// Fetches the block at `block_index`, along with its contents and signature.
+    pub fn get_block_data(
+        &self,
+        block_index: BlockIndex,
+    ) -> Result<S3BlockData, S3TransactionsFetcherError> {
+        let key = self.location.block_key(block_index);
+        log::debug!(
+            self.logger,
+            "Attempting to fetch block {} from s3://{}/{}",
+            block_index,
+            self.location.bucket,
+            key
+        );
+
+        let bytes = self.bytes_from_key(&key)?;
+        let s3_block_data = S3BlockData::from_bytes(&bytes)
+            .map_err(|err| S3TransactionsFetcherError::InvalidBlockReceived(key.clone(), err))?;
+
+        if s3_block_data.block.index != block_index || !s3_block_data.block.is_block_id_valid() {
+            return Err(S3TransactionsFetcherError::InvalidBlockReceived(
+                key,
+                format!("invalid block {:?}", s3_block_data.block),
+            ));
+        }
+
+        if let Some(signature) = s3_block_data.signature.as_ref() {
+            if let Some(equivocation_detector) = self.equivocation_detector.as_ref() {
+                equivocation_detector.observe(
+                    &format!("s3://{}/{}", self.location.bucket, key),
+                    &s3_block_data.block,
+                    signature,
+                );
+            }
+        }
+
+        Ok(s3_block_data)
+    }
+
+    pub fn get_origin_block_and_transactions(
+        &self,
+    ) -> Result<(Block, BlockContents), S3TransactionsFetcherError> {
+        let s3_block_data = self.get_block_data(0)?;
+        Ok((s3_block_data.block, s3_block_data.block_contents))
+    }
+}
+
+impl TransactionsFetcher for S3TransactionsFetcher {
+    type Error = S3TransactionsFetcherError;
+
+    fn get_block_contents(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<BlockContents, Self::Error> {
+        let (block_contents, _signature) =
+            self.get_block_contents_and_signature(safe_responder_ids, block)?;
+        Ok(block_contents)
+    }
+
+    fn get_block_contents_and_signature(
+        &self,
+        _safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
+        let s3_block_data = self.get_block_data(block.index)?;
+
+        // Check that we received data for the block we actually asked about.
+        if *block != s3_block_data.block {
+            return Err(S3TransactionsFetcherError::InvalidBlockReceived(
+                self.location.block_key(block.index),
+                "block data mismatch".to_string(),
+            ));
+        }
+
+        Ok((s3_block_data.block_contents, s3_block_data.signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Archive locations should be parsed for S3, GCS and other services supporting the S3 API.
+    fn test_parse_archive_location() {
+        let location =
+            S3ArchiveLocation::from_str("s3://bucket/path/to/archive/?region=us-west-1").unwrap();
+        assert_eq!(location.bucket, "bucket");
+        assert_eq!(location.prefix, "path/to/archive");
+        assert_eq!(location.region, Region::UsWest1);
+        assert_eq!(
+            location.block_key(1),
+            format!(
+                "path/to/archive/{}",
+                block_num_to_s3block_path(1).to_str().unwrap()
+            )
+        );
+
+        let location = S3ArchiveLocation::from_str("s3://bucket").unwrap();
+        assert_eq!(location.prefix, "");
+        assert_eq!(
+            location.block_key(1),
+            block_num_to_s3block_path(1).to_str().unwrap()
+        );
+
+        let location = S3ArchiveLocation::from_str("gs://bucket/archive").unwrap();
+        assert_eq!(location.bucket, "bucket");
+        assert_eq!(location.prefix, "archive");
+        assert_eq!(
+            location.region,
+            Region::Custom {
+                name: "auto".to_string(),
+                endpoint: GCS_ENDPOINT.to_string(),
+            }
+        );
+
+        let location = S3ArchiveLocation::from_str(
+            "s3://bucket/archive?region=eu-1&endpoint=https://minio.example.com",
+        )
+        .unwrap();
+        assert_eq!(
+            location.region,
+            Region::Custom {
+                name: "eu-1".to_string(),
+                endpoint: "https://minio.example.com".to_string(),
+            }
+        );
+
+        match S3ArchiveLocation::from_str("https://bucket/archive") {
+            Err(S3TransactionsFetcherError::UnknownScheme(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match S3ArchiveLocation::from_str("s3://bucket?region=nowhere") {
+            Err(S3TransactionsFetcherError::InvalidRegion(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
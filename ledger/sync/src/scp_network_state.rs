@@ -5,7 +5,7 @@
 use crate::network_state_trait::NetworkState;
 use common::{
     logger::{log, Logger},
-    HashMap, HashSet, NodeID, ResponderId,
+    HashMap, HashSet, NodeID, ResponderId, SyncLruCache,
 };
 use scp::{
     core_types::Ballot, msg::ExternalizePayload, predicates::FuncPredicate, GenericNodeId, Msg,
@@ -15,6 +15,9 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::{hash::Hash, iter::FromIterator};
 use transaction::BlockIndex;
 
+/// Maximum number of nodes whose highest externalized slot we keep track of.
+const MAX_TRACKED_NODES: usize = 1024;
+
 #[derive(Clone)]
 pub struct SCPNetworkState<ID: GenericNodeId = NodeID> {
     // The local node ID.
@@ -24,7 +27,7 @@ pub struct SCPNetworkState<ID: GenericNodeId = NodeID> {
     local_quorum_set: QuorumSet<ID>,
 
     // Highest slot that a given node has externalized.
-    id_to_current_slot: HashMap<ID, SlotIndex>,
+    id_to_current_slot: SyncLruCache<ID, SlotIndex>,
 
     // Logger.
     logger: Logger,
@@ -35,7 +38,7 @@ impl<ID: GenericNodeId + Clone + Eq + PartialEq + Hash> SCPNetworkState<ID> {
         Self {
             local_node_id,
             local_quorum_set,
-            id_to_current_slot: HashMap::default(),
+            id_to_current_slot: SyncLruCache::new(MAX_TRACKED_NODES),
             logger,
         }
     }
@@ -52,21 +55,25 @@ impl<ID: GenericNodeId + Clone + Eq + PartialEq + Hash> SCPNetworkState<ID> {
         };
 
         // "Upsert"
-        let entry_exists = self.id_to_current_slot.contains_key(&sender_id);
-        let entry_is_older = entry_exists && self.id_to_current_slot[&sender_id] < new_slot_index;
-        if !entry_exists || entry_is_older {
+        let mut id_to_current_slot = self.id_to_current_slot.lock();
+        let entry_is_older = match id_to_current_slot.get(&sender_id) {
+            Some(current_slot_index) => *current_slot_index < new_slot_index,
+            None => true,
+        };
+        if entry_is_older {
             log::trace!(
                 self.logger,
                 "Recording slot index {} from {}",
                 new_slot_index,
                 sender_id
             );
-            self.id_to_current_slot.insert(sender_id, new_slot_index);
+            id_to_current_slot.put(sender_id, new_slot_index);
         }
     }
 
-    pub fn peer_to_current_slot(&self) -> &HashMap<ID, SlotIndex> {
-        &self.id_to_current_slot
+    /// A snapshot of the highest slot each tracked node has externalized.
+    pub fn peer_to_current_slot(&self) -> HashMap<ID, SlotIndex> {
+        HashMap::from_iter(self.id_to_current_slot.entries())
     }
 }
 
@@ -145,7 +152,7 @@ mod tests {
     use common::logger::test_with_logger;
     use peers_tests::test_node_id;
     use scp::{core_types::Ballot, msg::*};
-    use std::collections::BTreeSet;
+    use std::{collections::BTreeSet, str::FromStr};
 
     #[test_with_logger]
     fn test_new(logger: Logger) {
@@ -190,7 +197,7 @@ mod tests {
             ));
             assert_eq!(network_state.peer_to_current_slot().len(), 1);
             assert_eq!(
-                *network_state.id_to_current_slot.get(&sender_id).unwrap(),
+                network_state.id_to_current_slot.get(&sender_id).unwrap(),
                 4 as SlotIndex
             );
         }
@@ -211,7 +218,7 @@ mod tests {
             ));
             assert_eq!(network_state.peer_to_current_slot().len(), 1);
             assert_eq!(
-                *network_state.id_to_current_slot.get(&sender_id).unwrap(),
+                network_state.id_to_current_slot.get(&sender_id).unwrap(),
                 4 as SlotIndex
             );
         }
@@ -231,7 +238,7 @@ mod tests {
             ));
             assert_eq!(network_state.peer_to_current_slot().len(), 1);
             assert_eq!(
-                *network_state.id_to_current_slot.get(&sender_id).unwrap(),
+                network_state.id_to_current_slot.get(&sender_id).unwrap(),
                 4 as SlotIndex
             );
         }
@@ -265,7 +272,7 @@ mod tests {
             ));
             assert_eq!(network_state.peer_to_current_slot().len(), 1);
             assert_eq!(
-                *network_state.id_to_current_slot.get(&sender_id).unwrap(),
+                network_state.id_to_current_slot.get(&sender_id).unwrap(),
                 5 as SlotIndex
             );
         }
@@ -284,9 +291,7 @@ mod tests {
         );
 
         let sender_id = test_node_id(11).responder_id;
-        network_state
-            .id_to_current_slot
-            .insert(sender_id.clone(), 8);
+        network_state.id_to_current_slot.put(sender_id.clone(), 8);
 
         // Push a "stale" Externalize message for slot 5.
         {
@@ -307,7 +312,7 @@ mod tests {
         // NetworkState should still think that node 11 has externalized slot 8.
         assert_eq!(network_state.peer_to_current_slot().len(), 1);
         assert_eq!(
-            *network_state.id_to_current_slot.get(&sender_id).unwrap(),
+            network_state.id_to_current_slot.get(&sender_id).unwrap(),
             8 as SlotIndex
         );
     }
@@ -344,7 +349,7 @@ mod tests {
         ));
         assert_eq!(network_state.peer_to_current_slot().len(), 1);
         assert_eq!(
-            *network_state.id_to_current_slot.get(&sender_a_id).unwrap(),
+            network_state.id_to_current_slot.get(&sender_a_id).unwrap(),
             4 as SlotIndex
         );
 
@@ -366,7 +371,7 @@ mod tests {
         assert_eq!(network_state.peer_to_current_slot().len(), 2);
 
         assert_eq!(
-            *network_state.id_to_current_slot.get(&sender_b_id).unwrap(),
+            network_state.id_to_current_slot.get(&sender_b_id).unwrap(),
             5 as SlotIndex
         );
     }
@@ -493,4 +498,37 @@ mod tests {
             true
         );
     }
+
+    #[test_with_logger]
+    // The number of tracked nodes should not grow past MAX_TRACKED_NODES.
+    fn test_push_is_bounded(logger: Logger) {
+        let local_node_id = test_node_id(1);
+        let mut network_state = SCPNetworkState::<ResponderId>::new(
+            local_node_id.responder_id,
+            QuorumSet::<ResponderId>::empty(),
+            logger,
+        );
+
+        for i in 0..(MAX_TRACKED_NODES + 10) {
+            let sender_id = ResponderId::from_str(&format!("node{}.test.com:8443", i)).unwrap();
+            network_state.push(Msg::new(
+                sender_id,
+                QuorumSet::<ResponderId>::empty(),
+                5,
+                Topic::Externalize(ExternalizePayload {
+                    C: Ballot::new(1, &["foo"]),
+                    HN: 1,
+                }),
+            ));
+        }
+
+        assert_eq!(
+            network_state.peer_to_current_slot().len(),
+            MAX_TRACKED_NODES
+        );
+
+        // The oldest senders should have been evicted.
+        let first_sender_id = ResponderId::from_str("node0.test.com:8443").unwrap();
+        assert!(!network_state.id_to_current_slot.contains(&first_sender_id));
+    }
 }
@@ -0,0 +1,553 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A bounded fetch -> verify -> append pipeline for the blocks a sync attempt appends.
+//!
+//! A pool of worker threads fetches block contents, a verifier thread checks the fetched blocks
+//! in order, and the caller appends the verified blocks to its ledger. Each stage hands blocks to
+//! the next one through a bounded queue, so that a slow ledger makes the earlier stages wait
+//! instead of buffering downloaded blocks without limit.
+
+use crate::{
+    counters,
+    ledger_sync_error::LedgerSyncError,
+    ledger_sync_service::{fetch_block_contents, SafeBlockChecker},
+    transactions_fetcher_trait::TransactionsFetcher,
+};
+use common::{
+    logger::{log, Logger},
+    HashMap, ResponderId,
+};
+use crossbeam_channel::{Receiver, Sender};
+use ledger_db::Ledger;
+use std::{
+    cmp::{max, min},
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::{fee_split::FeeSplit, Block, BlockContents, BlockIndex, BlockSignature};
+
+/// Queue depths and parallelism of the sync pipeline.
+#[derive(Clone, Debug)]
+pub struct SyncPipelineConfig {
+    /// Number of threads fetching block contents concurrently.
+    pub num_fetch_workers: usize,
+
+    /// Maximal number of blocks that are being fetched, or were fetched and wait to be verified.
+    pub fetch_queue_depth: usize,
+
+    /// Maximal number of verified blocks waiting to be appended to the ledger.
+    pub append_queue_depth: usize,
+
+    /// Maximal number of blocks appended to the ledger at once while catching up with the
+    /// network. Blocks near the network's tip are appended one at a time.
+    pub append_batch_size: usize,
+}
+
+/// The contents of a fetched block, along with the signature of the block, if one was served.
+type FetchedContents = (BlockContents, Option<BlockSignature>);
+
+/// A safe block, along with its contents and the signature it was fetched with, if any.
+pub(crate) type SafeBlock = (Block, BlockContents, Option<BlockSignature>);
+
+impl Default for SyncPipelineConfig {
+    fn default() -> Self {
+        Self {
+            num_fetch_workers: 5,
+            fetch_queue_depth: 50,
+            append_queue_depth: 10,
+            append_batch_size: 100,
+        }
+    }
+}
+
+/// The fetch stage: worker threads fetching the contents of blocks.
+pub(crate) struct FetchStage {
+    /// Blocks waiting for a worker.
+    block_sender: Option<Sender<Block>>,
+
+    /// Fetched blocks, in the order in which fetching them finished, along with their unchecked
+    /// signatures. The contents are None if they could not be fetched before the timeout.
+    result_receiver: Receiver<(Block, Option<FetchedContents>)>,
+
+    /// Stop trigger, used to signal the workers to stop fetching.
+    stop_requested: Arc<AtomicBool>,
+
+    /// The worker thread handles.
+    join_handles: Vec<thread::JoinHandle<()>>,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl FetchStage {
+    /// Starts the worker threads.
+    ///
+    /// # Arguments
+    /// * `transactions_fetcher` - The mechanism used for fetching block contents.
+    /// * `safe_responder_ids` - ResponderIds that agree with eachother on the blocks to fetch.
+    /// * `num_workers` - Number of worker threads.
+    /// * `queue_depth` - Maximal number of blocks that can be waiting to be fetched, or have been
+    ///   fetched but not received yet. Callers must not exceed it, or `fetch` blocks.
+    /// * `timeout` - How long to keep retrying a block before giving up on it.
+    pub fn start<TF: TransactionsFetcher + 'static>(
+        transactions_fetcher: Arc<TF>,
+        safe_responder_ids: &[ResponderId],
+        num_workers: usize,
+        queue_depth: usize,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Self {
+        let (block_sender, block_receiver) = crossbeam_channel::bounded(max(queue_depth, 1));
+        let (result_sender, result_receiver) = crossbeam_channel::bounded(max(queue_depth, 1));
+        let stop_requested = Arc::new(AtomicBool::new(false));
+
+        let join_handles = (0..max(num_workers, 1))
+            .map(|worker_num| {
+                let thread_block_receiver: Receiver<Block> = block_receiver.clone();
+                let thread_result_sender = result_sender.clone();
+                let thread_stop_requested = stop_requested.clone();
+                let thread_transactions_fetcher = transactions_fetcher.clone();
+                let thread_safe_responder_ids = safe_responder_ids.to_owned();
+                let thread_logger = logger.clone();
+
+                thread::Builder::new()
+                    .name(format!("GetTxs:{}", worker_num))
+                    .spawn(move || {
+                        for block in thread_block_receiver.iter() {
+                            if thread_stop_requested.load(Ordering::SeqCst) {
+                                break;
+                            }
+
+                            let fetched_contents = fetch_with_retries(
+                                &*thread_transactions_fetcher,
+                                &thread_safe_responder_ids,
+                                &block,
+                                Instant::now() + timeout,
+                                &thread_stop_requested,
+                                worker_num,
+                                &thread_logger,
+                            );
+
+                            if thread_result_sender
+                                .send((block, fetched_contents))
+                                .is_err()
+                            {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("Failed spawning GetTxs thread!")
+            })
+            .collect();
+
+        Self {
+            block_sender: Some(block_sender),
+            result_receiver,
+            stop_requested,
+            join_handles,
+            logger: logger.clone(),
+        }
+    }
+
+    /// Queues a block to be fetched.
+    pub fn fetch(&self, block: Block) {
+        if let Some(block_sender) = self.block_sender.as_ref() {
+            block_sender.send(block).expect("failed sending to channel");
+        }
+    }
+
+    /// Waits for the next fetched block. Returns None if all workers stopped.
+    pub fn recv(&self) -> Option<(Block, Option<FetchedContents>)> {
+        self.result_receiver.recv().ok()
+    }
+
+    /// Stops the workers, abandoning blocks that were not fetched yet.
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        self.block_sender = None;
+        for join_handle in self.join_handles.drain(..) {
+            if let Err(err) = join_handle.join() {
+                log::error!(self.logger, "Failed joining GetTxs thread: {:?}", err);
+            }
+        }
+    }
+}
+
+impl Drop for FetchStage {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Fetches the contents of a block, retrying with a linearly increasing delay until `deadline`.
+fn fetch_with_retries<TF: TransactionsFetcher>(
+    transactions_fetcher: &TF,
+    safe_responder_ids: &[ResponderId],
+    block: &Block,
+    deadline: Instant,
+    stop_requested: &AtomicBool,
+    worker_num: usize,
+    logger: &Logger,
+) -> Option<FetchedContents> {
+    let _timer = counters::FETCH_BLOCK_TIME.start_timer();
+
+    let mut num_attempts: u64 = 0;
+    loop {
+        if Instant::now() > deadline {
+            log::error!(
+                logger,
+                "Worker {} giving up on block {}: deadline exceeded",
+                worker_num,
+                block.index,
+            );
+            return None;
+        }
+
+        log::trace!(
+            logger,
+            "Worker {} attempting block {}",
+            worker_num,
+            block.index
+        );
+        match fetch_block_contents(transactions_fetcher, safe_responder_ids, block, logger) {
+            Ok(fetched_contents) => {
+                log::trace!(
+                    logger,
+                    "Worker {} got contents for block {}",
+                    worker_num,
+                    block.index
+                );
+                counters::BLOCKS_FETCHED_COUNT.inc();
+                return Some(fetched_contents);
+            }
+            Err(err) => {
+                log::error!(
+                    logger,
+                    "Worker {} failed getting transactions for block {}: {}",
+                    worker_num,
+                    block.index,
+                    err
+                );
+            }
+        }
+
+        // Sleep, with a linearly increasing delay, before retrying.
+        num_attempts += 1;
+        thread::sleep(Duration::from_secs(num_attempts));
+
+        if stop_requested.load(Ordering::SeqCst) {
+            return None;
+        }
+    }
+}
+
+/// Fetches, verifies and hands out blocks for appending, in order, until a block cannot be
+/// fetched or is not safe.
+pub(crate) struct SyncPipeline {
+    /// Verified blocks waiting to be appended, along with their unchecked signatures.
+    safe_block_receiver: Option<Receiver<SafeBlock>>,
+
+    /// The verifier thread handle.
+    join_handle: Option<thread::JoinHandle<Result<(), LedgerSyncError>>>,
+}
+
+impl SyncPipeline {
+    /// Starts fetching and verifying blocks.
+    ///
+    /// # Arguments
+    /// * `config` - Queue depths and parallelism.
+    /// * `ledger` - The local ledger, which the verified blocks are expected to be appended to.
+    /// * `transactions_fetcher` - The mechanism used for fetching block contents.
+    /// * `safe_responder_ids` - ResponderIds that agree with eachother on `blocks`.
+    /// * `blocks` - Potentially safe blocks, in increasing order of block number.
+    /// * `fee_split` - How the network splits block fees, if known.
+    /// * `timeout` - How long to keep retrying a block before giving up on it.
+    pub fn start<L: Ledger + 'static, TF: TransactionsFetcher + 'static>(
+        config: &SyncPipelineConfig,
+        ledger: L,
+        transactions_fetcher: Arc<TF>,
+        safe_responder_ids: &[ResponderId],
+        blocks: Vec<Block>,
+        fee_split: Option<FeeSplit>,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Self {
+        let fetch_queue_depth = max(config.fetch_queue_depth, 1);
+        let fetch_stage = FetchStage::start(
+            transactions_fetcher,
+            safe_responder_ids,
+            min(config.num_fetch_workers, blocks.len()),
+            fetch_queue_depth,
+            timeout,
+            logger,
+        );
+
+        let (safe_block_sender, safe_block_receiver) =
+            crossbeam_channel::bounded(max(config.append_queue_depth, 1));
+
+        let thread_logger = logger.clone();
+        let join_handle = thread::Builder::new()
+            .name("VerifyBlocks".into())
+            .spawn(move || {
+                let result = verify_blocks(
+                    &ledger,
+                    fetch_stage,
+                    fetch_queue_depth,
+                    blocks,
+                    fee_split,
+                    &safe_block_sender,
+                    &thread_logger,
+                );
+
+                counters::FETCH_QUEUE_SIZE.set(0);
+                counters::VERIFY_QUEUE_SIZE.set(0);
+                result
+            })
+            .expect("Failed spawning VerifyBlocks thread!");
+
+        Self {
+            safe_block_receiver: Some(safe_block_receiver),
+            join_handle: Some(join_handle),
+        }
+    }
+
+    /// Waits for the next safe block. Returns None once no further blocks are safe.
+    pub fn next_safe_block(&self) -> Option<SafeBlock> {
+        let safe_block_receiver = self.safe_block_receiver.as_ref()?;
+        let safe_block = safe_block_receiver.recv().ok();
+        counters::APPEND_QUEUE_SIZE.set(safe_block_receiver.len() as i64);
+        safe_block
+    }
+
+    /// Stops the pipeline. Returns `NoTransactionData` if not even the contents of the first block
+    /// could be fetched.
+    pub fn finish(mut self) -> Result<(), LedgerSyncError> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<(), LedgerSyncError> {
+        // Dropping the receiver stops the verifier thread at its next verified block.
+        self.safe_block_receiver = None;
+        counters::APPEND_QUEUE_SIZE.set(0);
+
+        match self.join_handle.take() {
+            Some(join_handle) => join_handle.join().expect("VerifyBlocks join failed"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SyncPipeline {
+    fn drop(&mut self) {
+        let _ = self.stop();
+    }
+}
+
+/// The verify stage: feeds `blocks` to the fetch stage, keeping at most `fetch_queue_depth` of them
+/// in flight, and sends those that are safe to `safe_block_sender` in order.
+fn verify_blocks<L: Ledger>(
+    ledger: &L,
+    mut fetch_stage: FetchStage,
+    fetch_queue_depth: usize,
+    blocks: Vec<Block>,
+    fee_split: Option<FeeSplit>,
+    safe_block_sender: &Sender<SafeBlock>,
+    logger: &Logger,
+) -> Result<(), LedgerSyncError> {
+    let num_blocks = blocks.len();
+    let mut blocks_to_fetch = blocks.into_iter();
+    let mut checker = SafeBlockChecker::new(ledger, fee_split);
+
+    // Indices of blocks handed to the fetch stage and not verified yet, in order.
+    let mut in_flight: VecDeque<BlockIndex> = VecDeque::new();
+
+    // Fetched blocks waiting for the blocks before them.
+    let mut fetched: HashMap<BlockIndex, (Block, Option<FetchedContents>)> = HashMap::default();
+
+    let mut num_fetched_blocks = 0;
+    loop {
+        while in_flight.len() < fetch_queue_depth {
+            match blocks_to_fetch.next() {
+                Some(block) => {
+                    in_flight.push_back(block.index);
+                    fetch_stage.fetch(block);
+                }
+                None => break,
+            }
+        }
+
+        let next_block_index = match in_flight.pop_front() {
+            Some(block_index) => block_index,
+            None => break,
+        };
+
+        while !fetched.contains_key(&next_block_index) {
+            counters::FETCH_QUEUE_SIZE.set((in_flight.len() + 1 - fetched.len()) as i64);
+            match fetch_stage.recv() {
+                Some((block, fetched_contents)) => {
+                    fetched.insert(block.index, (block, fetched_contents));
+                }
+                None => break,
+            }
+        }
+        counters::VERIFY_QUEUE_SIZE.set(fetched.len() as i64);
+
+        let (block, block_contents, signature) = match fetched.remove(&next_block_index) {
+            Some((block, Some((block_contents, signature)))) => (block, block_contents, signature),
+            _ => {
+                log::error!(logger, "No transactions for block {}", next_block_index);
+                break;
+            }
+        };
+        num_fetched_blocks += 1;
+
+        {
+            let _timer = counters::VERIFY_BLOCK_TIME.start_timer();
+            if !checker.is_safe(ledger, &block, &block_contents, logger) {
+                break;
+            }
+        }
+        counters::BLOCKS_VERIFIED_COUNT.inc();
+
+        // Blocks while the ledger is busy appending earlier blocks.
+        if safe_block_sender
+            .send((block, block_contents, signature))
+            .is_err()
+        {
+            break;
+        }
+        counters::APPEND_QUEUE_SIZE.set(safe_block_sender.len() as i64);
+    }
+
+    fetch_stage.stop();
+
+    if num_fetched_blocks == 0 && num_blocks > 0 {
+        log::error!(
+            logger,
+            "Identified {} safe blocks but was unable to get transaction data",
+            num_blocks,
+        );
+        return Err(LedgerSyncError::NoTransactionData);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::MockTransactionsFetcher;
+    use common::logger::test_with_logger;
+    use ledger_db::test_utils::get_mock_ledger;
+
+    #[test_with_logger]
+    // Blocks should be handed out in order, whatever the queue depths, and the ledger they are
+    // appended to should match the one they were fetched from.
+    fn test_sync_pipeline_appends_blocks_in_order(logger: Logger) {
+        let num_blocks = 25;
+        let source_ledger = get_mock_ledger(num_blocks);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(source_ledger.clone()));
+
+        let configs = vec![
+            SyncPipelineConfig::default(),
+            SyncPipelineConfig {
+                num_fetch_workers: 4,
+                fetch_queue_depth: 1,
+                append_queue_depth: 1,
+                append_batch_size: 1,
+            },
+            SyncPipelineConfig {
+                num_fetch_workers: 1,
+                fetch_queue_depth: 3,
+                append_queue_depth: 0,
+                append_batch_size: 0,
+            },
+        ];
+
+        for config in configs {
+            let mut local_ledger = get_mock_ledger(1);
+            let blocks: Vec<Block> = (1..num_blocks as u64)
+                .map(|idx| source_ledger.get_block(idx).unwrap())
+                .collect();
+
+            let pipeline = SyncPipeline::start(
+                &config,
+                local_ledger.clone(),
+                transactions_fetcher.clone(),
+                &[],
+                blocks,
+                None,
+                Duration::from_secs(1),
+                &logger,
+            );
+            while let Some((block, block_contents, signature)) = pipeline.next_safe_block() {
+                local_ledger
+                    .append_block(&block, &block_contents, signature.as_ref())
+                    .unwrap();
+            }
+            pipeline.finish().unwrap();
+
+            assert_eq!(local_ledger.num_blocks().unwrap(), num_blocks as u64);
+            for idx in 0..num_blocks as u64 {
+                assert_eq!(
+                    local_ledger.get_block(idx).unwrap(),
+                    source_ledger.get_block(idx).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test_with_logger]
+    // The pipeline should stop at the first block whose contents do not match it, and hand out the
+    // blocks before it.
+    fn test_sync_pipeline_stops_at_unavailable_block(logger: Logger) {
+        let source_ledger = get_mock_ledger(10);
+        let transactions_fetcher = Arc::new(MockTransactionsFetcher::new(source_ledger.clone()));
+        let local_ledger = get_mock_ledger(1);
+
+        let mut blocks: Vec<Block> = (1..10)
+            .map(|idx| source_ledger.get_block(idx).unwrap())
+            .collect();
+        // Block 4's contents cannot be fetched.
+        blocks[3].contents_hash.0[0] = !blocks[3].contents_hash.0[0];
+
+        let pipeline = SyncPipeline::start(
+            &SyncPipelineConfig::default(),
+            local_ledger.clone(),
+            transactions_fetcher.clone(),
+            &[],
+            blocks.clone(),
+            None,
+            Duration::from_millis(100),
+            &logger,
+        );
+        let mut safe_block_indexes = Vec::new();
+        while let Some((block, _block_contents, _signature)) = pipeline.next_safe_block() {
+            safe_block_indexes.push(block.index);
+        }
+        pipeline.finish().unwrap();
+        assert_eq!(safe_block_indexes, vec![1, 2, 3]);
+
+        // Not being able to fetch the first block is an error.
+        let pipeline = SyncPipeline::start(
+            &SyncPipelineConfig::default(),
+            local_ledger,
+            transactions_fetcher,
+            &[],
+            blocks[3..].to_vec(),
+            None,
+            Duration::from_millis(100),
+            &logger,
+        );
+        assert!(pipeline.next_safe_block().is_none());
+        match pipeline.finish() {
+            Err(LedgerSyncError::NoTransactionData) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -3,7 +3,10 @@
 use crate::{TransactionFetcherError, TransactionsFetcher};
 use common::ResponderId;
 use ledger_db::Ledger;
-use transaction::{Block, BlockContents};
+use mobilecoin_api::{blockchain, conversions::block_num_to_s3block_path};
+use protobuf::Message;
+use std::{fs, path::Path};
+use transaction::{Block, BlockContents, BlockSignature, BLOCK_VERSION};
 
 impl TransactionFetcherError for String {}
 
@@ -30,4 +33,46 @@ impl<L: Ledger + Sync> TransactionsFetcher for MockTransactionsFetcher<L> {
             .get_block_contents(block.index)
             .map_err(|e| format!("Error getting contents of block #{}: {:?}", block.index, e))
     }
+
+    fn get_block_contents_and_signature(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
+        let block_contents = self.get_block_contents(safe_responder_ids, block)?;
+        Ok((
+            block_contents,
+            self.ledger.get_block_signature(block.index).ok(),
+        ))
+    }
+}
+
+/// Writes a block with empty contents the way ledger-distribution does.
+pub fn write_block(dir: &Path, block: &Block) {
+    let mut s3_block = blockchain::S3Block::new();
+    s3_block.set_block(blockchain::Block::from(block));
+    s3_block.set_block_contents(blockchain::BlockContents::from(&BlockContents::new(
+        Vec::new(),
+        Vec::new(),
+    )));
+
+    let path = dir.join(block_num_to_s3block_path(block.index));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, s3_block.write_to_bytes().unwrap()).unwrap();
+}
+
+/// Blocks with empty contents, starting at the origin block.
+pub fn get_blocks(num_blocks: usize) -> Vec<Block> {
+    let mut blocks = vec![Block::new_origin_block(&[])];
+    while blocks.len() < num_blocks {
+        let parent = blocks.last().unwrap();
+        blocks.push(Block::new(
+            BLOCK_VERSION,
+            &parent.id,
+            parent.index + 1,
+            &Default::default(),
+            &BlockContents::new(Vec::new(), Vec::new()),
+        ));
+    }
+    blocks
 }
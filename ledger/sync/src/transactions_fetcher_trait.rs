@@ -5,7 +5,7 @@
 
 use common::ResponderId;
 use std::fmt::Debug;
-use transaction::{Block, BlockContents};
+use transaction::{Block, BlockContents, BlockSignature};
 
 pub trait TransactionFetcherError: Debug + Send + Sync {}
 
@@ -24,4 +24,20 @@ pub trait TransactionsFetcher: Sized + Sync + Send {
         safe_responder_ids: &[ResponderId],
         block: &Block,
     ) -> Result<BlockContents, Self::Error>;
+
+    /// Fetches the contents of a given block, along with the signature of the block if the
+    /// implementer serves one. The signature is not checked. Serves no signature by default.
+    ///
+    /// # Arguments
+    /// * `safe_responder_ids` - List of responder IDs that have been identified as being able to provide a
+    /// consistent copy of the blockchain.
+    /// * `block` - The block we want to fetch contents for.
+    fn get_block_contents_and_signature(
+        &self,
+        safe_responder_ids: &[ResponderId],
+        block: &Block,
+    ) -> Result<(BlockContents, Option<BlockSignature>), Self::Error> {
+        let block_contents = self.get_block_contents(safe_responder_ids, block)?;
+        Ok((block_contents, None))
+    }
 }
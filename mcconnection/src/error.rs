@@ -8,6 +8,7 @@ use crate::traits::AttestationError;
 use failure::Fail;
 use grpcio::Error as GrpcError;
 use mcnoise::CipherError;
+use mcserial::DecodeError;
 use mobilecoin_api::{consensus_common::ProposeTxResult, ConversionError};
 use std::{array::TryFromSliceError, convert::TryInto, result::Result as StdResult};
 use transaction::validation::TransactionValidationError;
@@ -35,6 +36,8 @@ pub enum Error {
     Attestation(Box<dyn AttestationError + 'static>),
     #[fail(display = "Transaction validation failure: {}", _0)]
     TransactionValidation(TransactionValidationError),
+    #[fail(display = "Could not decode enclave response: {}", _0)]
+    Decode(DecodeError),
     #[fail(display = "Other: {}", _0)]
     Other(String),
 }
@@ -62,6 +65,12 @@ impl From<CipherError> for Error {
     }
 }
 
+impl From<DecodeError> for Error {
+    fn from(src: DecodeError) -> Self {
+        Error::Decode(src)
+    }
+}
+
 impl From<GrpcError> for Error {
     fn from(src: GrpcError) -> Self {
         Error::Grpc(src)
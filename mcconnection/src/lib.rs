@@ -17,7 +17,8 @@ pub use self::{
     thick::{ThickClient, ThickClientAttestationError},
     traits::{
         AttestationError, AttestedConnection, BlockchainConnection, Connection,
-        RetryableBlockchainConnection, RetryableUserTxConnection, UserTxConnection,
+        RetryableBlockchainConnection, RetryableUserTxConnection, TxOutProofsConnection,
+        UserTxConnection,
     },
 };
 
@@ -18,7 +18,7 @@ use std::{
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
     time::Duration,
 };
-use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+use transaction::{dust::DustRules, ring_signature::KeyImage, tx::Tx, Block, BlockID, BlockIndex};
 
 /// A synchronous wrapper for a connection object.
 ///
@@ -223,4 +223,32 @@ impl<UTC: UserTxConnection> RetryableUserTxConnection for SyncConnection<UTC> {
     ) -> RetryResult<BlockIndex> {
         impl_sync_connection_retry!(self.write(), self.logger, propose_tx, retry_iterator, tx)
     }
+
+    fn fetch_dust_rules(
+        &self,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<DustRules> {
+        impl_sync_connection_retry!(self.write(), self.logger, fetch_dust_rules, retry_iterator)
+    }
+
+    fn fetch_minimum_fee(
+        &self,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<u64> {
+        impl_sync_connection_retry!(self.write(), self.logger, fetch_minimum_fee, retry_iterator)
+    }
+
+    fn fetch_pending_key_images(
+        &self,
+        key_images: &[KeyImage],
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<Vec<KeyImage>> {
+        impl_sync_connection_retry!(
+            self.write(),
+            self.logger,
+            fetch_pending_key_images,
+            retry_iterator,
+            key_images
+        )
+    }
 }
@@ -7,23 +7,27 @@ use crate::{
     error::{Error, Result},
     grpcio_utils::ConnectionUriGrpcioChannel,
     traits::{
-        AttestationError, AttestedConnection, BlockchainConnection, Connection, UserTxConnection,
+        AttestationError, AttestedConnection, BlockchainConnection, Connection,
+        TxOutProofsConnection, UserTxConnection,
     },
 };
 use aes_gcm::Aes256Gcm;
-use attest::Measurement;
+use attest::{EnclaveIdentity, Measurement, Verifier};
 use attest_ake::{ClientInitiate, Error as AkeError, Ready, Start, Transition};
 use attest_api::{attest::Message, attest_grpc::AttestedApiClient};
 use common::{
     logger::{log, o, Logger},
     trace_time,
 };
+use consensus_enclave_api::{
+    PendingKeyImagesRequest, PendingKeyImagesResponse, TxOutProofsRequest, TxOutProofsResponse,
+};
 use failure::Fail;
-use grpcio::{ChannelBuilder, Environment, Error as GrpcError};
+use grpcio::{ChannelBuilder, Environment, Error as GrpcError, RpcStatusCode};
 use keys::X25519;
 use mcnoise::CipherError;
 use mcrand::McRng;
-use mcserial::encode;
+use mcserial::{decode, encode};
 use mcuri::{ConnectionUri, ConsensusClientUri as ClientUri, UriConversionError};
 use mobilecoin_api::{
     blockchain::BlocksRequest, blockchain_grpc::BlockchainApiClient,
@@ -41,7 +45,7 @@ use std::{
     result::Result as StdResult,
     sync::Arc,
 };
-use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+use transaction::{dust::DustRules, ring_signature::KeyImage, tx::Tx, Block, BlockID, BlockIndex};
 
 // FIXME: MC-530 (better place to store MobileCoin-specific enclave details)
 const MC_NODE_PRODUCT_ID: u16 = 1;
@@ -104,8 +108,8 @@ pub struct ThickClient {
     attested_api_client: AttestedApiClient,
     /// The gRPC API client we will use for legacy transaction submission.
     consensus_client_api_client: ConsensusClientApiClient,
-    /// The expected node enclave measurement value.
-    expected_measurement: Measurement,
+    /// The node enclaves we accept.
+    verifier: Verifier,
     /// The AKE state machine object, if one is available.
     enclave_connection: Option<Ready<Aes256Gcm>>,
 }
@@ -117,6 +121,30 @@ impl ThickClient {
         expected_measurement: impl Into<Measurement>,
         env: Arc<Environment>,
         logger: Logger,
+    ) -> Result<Self> {
+        Self::new_with_verifier(
+            uri,
+            Verifier::new(
+                Self::node_identity(expected_measurement),
+                attest::DEBUG_ENCLAVE,
+            ),
+            env,
+            logger,
+        )
+    }
+
+    /// The identity of a consensus node enclave with the given measurement.
+    pub fn node_identity(measurement: impl Into<Measurement>) -> EnclaveIdentity {
+        EnclaveIdentity::new(measurement, MC_NODE_PRODUCT_ID, MC_SECURITY_VERSION)
+    }
+
+    /// Create a new attested connection to the given consensus node, which accepts any of the
+    /// enclaves the given verifier accepts.
+    pub fn new_with_verifier(
+        uri: ClientUri,
+        verifier: Verifier,
+        env: Arc<Environment>,
+        logger: Logger,
     ) -> Result<Self> {
         let logger = logger.new(o!("mc.cxn" => uri.to_string()));
 
@@ -132,7 +160,7 @@ impl ThickClient {
             blockchain_api_client,
             consensus_client_api_client,
             attested_api_client,
-            expected_measurement: expected_measurement.into(),
+            verifier,
             enclave_connection: None,
         })
     }
@@ -160,13 +188,8 @@ impl AttestedConnection for ThickClient {
 
         let mut csprng = McRng::default();
 
-        let initiator = Start::new(
-            self.uri.responder_id()?.to_string(),
-            self.expected_measurement,
-            MC_NODE_PRODUCT_ID,
-            MC_SECURITY_VERSION,
-            attest::DEBUG_ENCLAVE,
-        );
+        let initiator =
+            Start::new_with_verifier(self.uri.responder_id()?.to_string(), self.verifier.clone());
 
         let init_input = ClientInitiate::<X25519, Aes256Gcm, Sha512>::default();
         let (initiator, auth_request_output) = initiator.try_next(&mut csprng, init_input)?;
@@ -212,10 +235,25 @@ impl BlockchainConnection for ThickClient {
         let limit = u32::try_from(range.end - range.start).or(Err(Error::RequestTooLarge))?;
         request.set_limit(limit);
 
-        self.attested_call(|this| this.blockchain_api_client.get_blocks(&request))?
-            .get_blocks()
+        let block_ids =
+            match self.attested_call(|this| this.blockchain_api_client.get_block_ids(&request)) {
+                Ok(response) => response.get_block_ids().to_vec(),
+                // Nodes that predate GetBlockIds only serve whole blocks.
+                Err(ThickClientAttestationError::Grpc(GrpcError::RpcFailure(status)))
+                    if status.status == RpcStatusCode::UNIMPLEMENTED =>
+                {
+                    self.attested_call(|this| this.blockchain_api_client.get_blocks(&request))?
+                        .get_blocks()
+                        .iter()
+                        .map(|proto_block| proto_block.id.clone())
+                        .collect()
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+        block_ids
             .iter()
-            .map(|proto_block| BlockID::try_from(&proto_block.id[..]).map_err(Error::from))
+            .map(|block_id| BlockID::try_from(&block_id[..]).map_err(Error::from))
             .collect::<Result<Vec<BlockID>>>()
     }
 
@@ -261,6 +299,99 @@ impl UserTxConnection for ThickClient {
             Err(resp.get_result().into())
         }
     }
+
+    fn fetch_dust_rules(&mut self) -> Result<DustRules> {
+        trace_time!(self.logger, "ThickClient::fetch_dust_rules");
+
+        let resp = self.attested_call(|this| {
+            this.blockchain_api_client
+                .get_last_block_info(&Empty::new())
+        })?;
+        Ok(DustRules::from(&resp))
+    }
+
+    fn fetch_minimum_fee(&mut self) -> Result<u64> {
+        trace_time!(self.logger, "ThickClient::fetch_minimum_fee");
+
+        Ok(self
+            .attested_call(|this| {
+                this.blockchain_api_client
+                    .get_last_block_info(&Empty::new())
+            })?
+            .minimum_fee)
+    }
+
+    fn fetch_pending_key_images(&mut self, key_images: &[KeyImage]) -> Result<Vec<KeyImage>> {
+        trace_time!(self.logger, "ThickClient::fetch_pending_key_images");
+
+        if !self.is_attested() {
+            self.attest()?
+        }
+
+        let request = PendingKeyImagesRequest {
+            key_images: key_images.to_vec(),
+        };
+
+        let enclave_connection = self
+            .enclave_connection
+            .as_mut()
+            .expect("no enclave_connection even though attest succeeded");
+
+        let mut msg = Message::new();
+        msg.set_channel_id(Vec::from(enclave_connection.binding()));
+        msg.set_data(enclave_connection.encrypt(&[], &encode(&request))?);
+
+        let resp = self.attested_call(|this| {
+            this.consensus_client_api_client
+                .get_pending_key_images(&msg)
+        })?;
+
+        let enclave_connection = self
+            .enclave_connection
+            .as_mut()
+            .expect("no enclave_connection even though attested call succeeded");
+        let plaintext = enclave_connection.decrypt(resp.get_aad(), resp.get_data())?;
+        let response: PendingKeyImagesResponse = decode(&plaintext)?;
+        Ok(response.pending_key_images)
+    }
+}
+
+impl TxOutProofsConnection for ThickClient {
+    fn fetch_tx_out_proofs(
+        &mut self,
+        indices: &[u64],
+        num_mixins: u64,
+    ) -> Result<TxOutProofsResponse> {
+        trace_time!(self.logger, "ThickClient::fetch_tx_out_proofs");
+
+        if !self.is_attested() {
+            self.attest()?
+        }
+
+        let request = TxOutProofsRequest {
+            indices: indices.to_vec(),
+            num_mixins,
+        };
+
+        let enclave_connection = self
+            .enclave_connection
+            .as_mut()
+            .expect("no enclave_connection even though attest succeeded");
+
+        let mut msg = Message::new();
+        msg.set_channel_id(Vec::from(enclave_connection.binding()));
+        msg.set_data(enclave_connection.encrypt(&[], &encode(&request))?);
+
+        let resp =
+            self.attested_call(|this| this.consensus_client_api_client.get_tx_out_proofs(&msg))?;
+
+        let enclave_connection = self
+            .enclave_connection
+            .as_mut()
+            .expect("no enclave_connection even though attested call succeeded");
+        let plaintext = enclave_connection.decrypt(resp.get_aad(), resp.get_data())?;
+        Ok(decode(&plaintext)?)
+    }
 }
 
 impl Display for ThickClient {
@@ -3,6 +3,7 @@
 //! Traits which connection implementations can implement.
 
 use crate::error::{Result, RetryResult};
+use consensus_enclave_api::TxOutProofsResponse;
 use grpcio::{Error as GrpcError, RpcStatusCode};
 use mcuri::ConnectionUri;
 use std::{
@@ -12,7 +13,7 @@ use std::{
     result::Result as StdResult,
     time::Duration,
 };
-use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+use transaction::{dust::DustRules, ring_signature::KeyImage, tx::Tx, Block, BlockID, BlockIndex};
 
 /// A base connection trait, applicable to all connections.
 pub trait Connection: Display + Eq + Hash + Ord + PartialEq + PartialOrd + Send + Sync {
@@ -58,7 +59,8 @@ pub trait BlockchainConnection: Connection {
     /// Retrieve the block metadata from the blockchain service.
     fn fetch_blocks(&mut self, range: Range<BlockIndex>) -> Result<Vec<Block>>;
 
-    /// Retrieve the BlockIDs (hashes) of the given blocks from the blockchain service.
+    /// Retrieve the BlockIDs (hashes) of the given blocks from the blockchain service, without
+    /// the rest of the blocks.
     fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> Result<Vec<BlockID>>;
 
     /// Retrieve the consensus node's current block height
@@ -70,6 +72,27 @@ pub trait UserTxConnection: Connection {
     /// Propose a transaction over the encrypted channel.
     /// Returns the number of blocks in the ledger at the time the call was received.
     fn propose_tx(&mut self, tx: &Tx) -> Result<BlockIndex>;
+
+    /// Retrieve the rules the node's network applies to zero-value and sub-minimum outputs.
+    fn fetch_dust_rules(&mut self) -> Result<DustRules>;
+
+    /// Retrieve the minimum fee, in picoMOB, the node's network requires of a transaction.
+    fn fetch_minimum_fee(&mut self) -> Result<u64>;
+
+    /// Retrieve those of the given key images that are spent by a transaction the node has
+    /// accepted, but which has not been externalized yet.
+    fn fetch_pending_key_images(&mut self, key_images: &[KeyImage]) -> Result<Vec<KeyImage>>;
+}
+
+/// A trait which supports retrieving TxOut membership proofs over the encrypted channel.
+pub trait TxOutProofsConnection: Connection {
+    /// Retrieve the TxOuts at the given global indices and their membership proofs, along with
+    /// `num_mixins` randomly selected TxOuts (and proofs) for use as ring members.
+    fn fetch_tx_out_proofs(
+        &mut self,
+        indices: &[u64],
+        num_mixins: u64,
+    ) -> Result<TxOutProofsResponse>;
 }
 
 // Retryable connections: these traits exist to allow SyncConnection to extend itself when its
@@ -107,4 +130,24 @@ pub trait RetryableUserTxConnection {
         tx: &Tx,
         retry_iterator: impl IntoIterator<Item = Duration>,
     ) -> RetryResult<BlockIndex>;
+
+    /// Retrieve the rules the node's network applies to zero-value and sub-minimum outputs.
+    fn fetch_dust_rules(
+        &self,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<DustRules>;
+
+    /// Retrieve the minimum fee, in picoMOB, the node's network requires of a transaction.
+    fn fetch_minimum_fee(
+        &self,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<u64>;
+
+    /// Retrieve those of the given key images that are spent by a transaction the node has
+    /// accepted, but which has not been externalized yet.
+    fn fetch_pending_key_images(
+        &self,
+        key_images: &[KeyImage],
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> RetryResult<Vec<KeyImage>>;
 }
@@ -16,7 +16,7 @@ use std::{
     thread,
     time::Duration,
 };
-use transaction::{tx::Tx, Block, BlockID, BlockIndex};
+use transaction::{dust::DustRules, ring_signature::KeyImage, tx::Tx, Block, BlockID, BlockIndex};
 
 #[derive(Clone)]
 pub struct MockBlockchainConnection {
@@ -114,6 +114,21 @@ impl UserTxConnection for MockBlockchainConnection {
     fn propose_tx(&mut self, _tx: &Tx) -> ConnectionResult<BlockIndex> {
         unimplemented!()
     }
+
+    fn fetch_dust_rules(&mut self) -> ConnectionResult<DustRules> {
+        unimplemented!()
+    }
+
+    fn fetch_minimum_fee(&mut self) -> ConnectionResult<u64> {
+        unimplemented!()
+    }
+
+    fn fetch_pending_key_images(
+        &mut self,
+        _key_images: &[KeyImage],
+    ) -> ConnectionResult<Vec<KeyImage>> {
+        unimplemented!()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,494 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! An in-process simulation of a consensus network.
+//!
+//! Transactions proposed to any of the network's connections are checked for double spends and
+//! expired tombstones, and externalized into a new block of a shared ledger. Tests can add latency
+//! to a connection, inject failures, and hold transactions back to control when blocks land.
+
+use grpcio::{Error as GrpcError, RpcStatus, RpcStatusCode};
+use ledger_db::Ledger;
+use mcconnection::{
+    BlockchainConnection, Connection, Error as ConnectionError, Result as ConnectionResult,
+    UserTxConnection,
+};
+use mcuri::{ConnectionUri, ConsensusClientUri};
+use std::{
+    cmp::{min, Ordering},
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard},
+    thread,
+    time::Duration,
+};
+use transaction::{
+    constants::BASE_FEE,
+    dust::DustRules,
+    ring_signature::KeyImage,
+    tx::{Tx, TxOut},
+    validation::{validate_tombstone, TransactionValidationError},
+    Block, BlockContents, BlockID, BlockIndex, BLOCK_VERSION,
+};
+
+/// State shared by all connections of a network.
+struct MockConsensusState<L: Ledger> {
+    /// The ledger blocks are appended to.
+    ledger: L,
+
+    /// Transactions accepted but not yet externalized.
+    pending_txs: Vec<Tx>,
+
+    /// All transactions accepted by the network, in order.
+    submitted_txs: Vec<Tx>,
+
+    /// Whether accepted transactions are externalized right away.
+    auto_externalize: bool,
+
+    /// Number of upcoming proposals that should fail.
+    num_injected_failures: usize,
+
+    /// Number of upcoming proposals that should be accepted, but reported as timed out.
+    num_dropped_responses: usize,
+
+    dust_rules: DustRules,
+    minimum_fee: u64,
+}
+
+/// A simulated consensus network, backed by a ledger.
+pub struct MockConsensusNetwork<L: Ledger> {
+    state: Arc<Mutex<MockConsensusState<L>>>,
+}
+
+impl<L: Ledger> Clone for MockConsensusNetwork<L> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<L: Ledger> MockConsensusNetwork<L> {
+    /// Creates a network that appends blocks to `ledger`. The ledger must contain at least the
+    /// origin block.
+    pub fn new(ledger: L) -> Self {
+        assert!(
+            ledger
+                .num_blocks()
+                .expect("failed getting number of blocks")
+                > 0,
+            "ledger must contain the origin block"
+        );
+
+        Self {
+            state: Arc::new(Mutex::new(MockConsensusState {
+                ledger,
+                pending_txs: Vec::new(),
+                submitted_txs: Vec::new(),
+                auto_externalize: true,
+                num_injected_failures: 0,
+                num_dropped_responses: 0,
+                dust_rules: DustRules::default(),
+                minimum_fee: BASE_FEE,
+            })),
+        }
+    }
+
+    /// Creates a connection to a node of this network.
+    pub fn connection(&self, uri: ConsensusClientUri) -> MockConsensusConnection<L> {
+        MockConsensusConnection {
+            uri,
+            network: self.clone(),
+            latency: Duration::default(),
+        }
+    }
+
+    /// Controls whether accepted transactions are externalized right away. When disabled,
+    /// transactions stay pending until `externalize_pending` is called.
+    pub fn set_auto_externalize(&self, auto_externalize: bool) {
+        self.lock().auto_externalize = auto_externalize;
+    }
+
+    /// Makes the next `num_failures` proposals fail, regardless of the connection they are made
+    /// to.
+    pub fn inject_failures(&self, num_failures: usize) {
+        self.lock().num_injected_failures = num_failures;
+    }
+
+    /// Makes the next `num_dropped` accepted proposals return a timeout error, as if the response
+    /// was lost on its way back to the client.
+    pub fn drop_responses(&self, num_dropped: usize) {
+        self.lock().num_dropped_responses = num_dropped;
+    }
+
+    pub fn set_dust_rules(&self, dust_rules: DustRules) {
+        self.lock().dust_rules = dust_rules;
+    }
+
+    pub fn set_minimum_fee(&self, minimum_fee: u64) {
+        self.lock().minimum_fee = minimum_fee;
+    }
+
+    /// All transactions accepted by the network, in order.
+    pub fn submitted_txs(&self) -> Vec<Tx> {
+        self.lock().submitted_txs.clone()
+    }
+
+    /// Transactions accepted but not yet externalized.
+    pub fn pending_txs(&self) -> Vec<Tx> {
+        self.lock().pending_txs.clone()
+    }
+
+    /// Externalizes all pending transactions into a single block. Returns the index of the new
+    /// block, or `None` if there were no pending transactions.
+    pub fn externalize_pending(&self) -> Option<BlockIndex> {
+        self.lock().externalize_pending()
+    }
+
+    fn lock(&self) -> MutexGuard<MockConsensusState<L>> {
+        self.state.lock().expect("mutex poisoned")
+    }
+}
+
+impl<L: Ledger> MockConsensusState<L> {
+    fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<BlockIndex> {
+        if self.num_injected_failures > 0 {
+            self.num_injected_failures -= 1;
+            return Err(ConnectionError::Other("injected failure".to_string()));
+        }
+
+        let num_blocks = self.num_blocks()?;
+        validate_tombstone(num_blocks, tx.prefix.tombstone_block)
+            .map_err(ConnectionError::TransactionValidation)?;
+
+        // Reject key images that are already spent, or spent by a pending transaction.
+        for key_image in tx.key_images() {
+            let is_spent = self
+                .ledger
+                .contains_key_image(&key_image)
+                .map_err(|err| ConnectionError::Other(err.to_string()))?;
+            let is_pending = self
+                .pending_txs
+                .iter()
+                .any(|pending_tx| pending_tx.key_images().contains(&key_image));
+            if is_spent || is_pending {
+                return Err(ConnectionError::TransactionValidation(
+                    TransactionValidationError::ContainsSpentKeyImage,
+                ));
+            }
+        }
+
+        self.pending_txs.push(tx.clone());
+        self.submitted_txs.push(tx.clone());
+        if self.auto_externalize {
+            self.externalize_pending();
+        }
+
+        if self.num_dropped_responses > 0 {
+            self.num_dropped_responses -= 1;
+            return Err(ConnectionError::Grpc(GrpcError::RpcFailure(
+                RpcStatus::new(RpcStatusCode::DEADLINE_EXCEEDED, None),
+            )));
+        }
+
+        Ok(num_blocks)
+    }
+
+    fn pending_key_images(&self, key_images: &[KeyImage]) -> Vec<KeyImage> {
+        key_images
+            .iter()
+            .filter(|key_image| {
+                self.pending_txs
+                    .iter()
+                    .any(|pending_tx| pending_tx.key_images().contains(key_image))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn externalize_pending(&mut self) -> Option<BlockIndex> {
+        if self.pending_txs.is_empty() {
+            return None;
+        }
+
+        let key_images = self
+            .pending_txs
+            .iter()
+            .flat_map(|tx| tx.key_images())
+            .collect();
+        let outputs: Vec<TxOut> = self
+            .pending_txs
+            .drain(..)
+            .flat_map(|tx| tx.prefix.outputs)
+            .collect();
+        let block_contents = BlockContents::new(key_images, outputs);
+
+        let num_blocks = self.num_blocks().expect("failed getting number of blocks");
+        let parent = self
+            .ledger
+            .get_block(num_blocks - 1)
+            .expect("failed getting parent block");
+        let block = Block::new(
+            BLOCK_VERSION,
+            &parent.id,
+            num_blocks,
+            &Default::default(),
+            &block_contents,
+        );
+        self.ledger
+            .append_block(&block, &block_contents, None)
+            .expect("failed appending block");
+
+        Some(num_blocks)
+    }
+
+    fn num_blocks(&self) -> ConnectionResult<u64> {
+        self.ledger
+            .num_blocks()
+            .map_err(|err| ConnectionError::Other(err.to_string()))
+    }
+}
+
+/// A connection to a node of a `MockConsensusNetwork`.
+pub struct MockConsensusConnection<L: Ledger> {
+    /// The destination uri
+    uri: ConsensusClientUri,
+
+    /// The network this node belongs to.
+    network: MockConsensusNetwork<L>,
+
+    /// Latency added to every request to this node.
+    latency: Duration,
+}
+
+impl<L: Ledger> MockConsensusConnection<L> {
+    /// Adds latency to every request to this node.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    fn delay(&self) {
+        if self.latency > Duration::default() {
+            thread::sleep(self.latency);
+        }
+    }
+}
+
+impl<L: Ledger> Clone for MockConsensusConnection<L> {
+    fn clone(&self) -> Self {
+        Self {
+            uri: self.uri.clone(),
+            network: self.network.clone(),
+            latency: self.latency,
+        }
+    }
+}
+
+impl<L: Ledger> Display for MockConsensusConnection<L> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.uri)
+    }
+}
+
+impl<L: Ledger> Eq for MockConsensusConnection<L> {}
+
+impl<L: Ledger> Hash for MockConsensusConnection<L> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.uri.addr().hash(state);
+    }
+}
+
+impl<L: Ledger> Ord for MockConsensusConnection<L> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.uri.addr().cmp(&other.uri.addr())
+    }
+}
+
+impl<L: Ledger> PartialEq for MockConsensusConnection<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri.addr() == other.uri.addr()
+    }
+}
+
+impl<L: Ledger> PartialOrd for MockConsensusConnection<L> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.uri.addr().partial_cmp(&other.uri.addr())
+    }
+}
+
+impl<L: Ledger> Connection for MockConsensusConnection<L> {
+    type Uri = ConsensusClientUri;
+
+    fn uri(&self) -> Self::Uri {
+        self.uri.clone()
+    }
+}
+
+impl<L: Ledger> BlockchainConnection for MockConsensusConnection<L> {
+    fn fetch_blocks(&mut self, range: Range<BlockIndex>) -> ConnectionResult<Vec<Block>> {
+        self.delay();
+        let state = self.network.lock();
+
+        let num_blocks = state.num_blocks()?;
+        if range.start >= num_blocks {
+            return Err(ConnectionError::NotFound);
+        }
+
+        (range.start..min(range.end, num_blocks))
+            .map(|block_index| {
+                state
+                    .ledger
+                    .get_block(block_index)
+                    .or(Err(ConnectionError::NotFound))
+            })
+            .collect()
+    }
+
+    fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> ConnectionResult<Vec<BlockID>> {
+        Ok(self
+            .fetch_blocks(range)?
+            .into_iter()
+            .map(|block| block.id)
+            .collect())
+    }
+
+    fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
+        self.delay();
+        Ok(self.network.lock().num_blocks()? - 1)
+    }
+}
+
+impl<L: Ledger> UserTxConnection for MockConsensusConnection<L> {
+    fn propose_tx(&mut self, tx: &Tx) -> ConnectionResult<BlockIndex> {
+        self.delay();
+        self.network.lock().propose_tx(tx)
+    }
+
+    fn fetch_dust_rules(&mut self) -> ConnectionResult<DustRules> {
+        self.delay();
+        Ok(self.network.lock().dust_rules)
+    }
+
+    fn fetch_minimum_fee(&mut self) -> ConnectionResult<u64> {
+        self.delay();
+        Ok(self.network.lock().minimum_fee)
+    }
+
+    fn fetch_pending_key_images(
+        &mut self,
+        key_images: &[KeyImage],
+    ) -> ConnectionResult<Vec<KeyImage>> {
+        self.delay();
+        Ok(self.network.lock().pending_key_images(key_images))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_client_uri;
+    use ledger_db::test_utils::{get_mock_ledger, MockLedger};
+    use transaction::tx::TxPrefix;
+
+    /// A transaction that re-sends an output of the ledger. It has no inputs, and hence no key
+    /// images.
+    fn get_tx(ledger: &MockLedger, tx_out_index: u64, tombstone_block: u64) -> Tx {
+        let tx_out = ledger.get_tx_out_by_index(tx_out_index).unwrap();
+        Tx {
+            prefix: TxPrefix::new(Vec::new(), vec![tx_out], BASE_FEE, tombstone_block),
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    // Proposed transactions should be appended to the ledger and visible from every node.
+    fn test_propose_tx_appends_block() {
+        let ledger = get_mock_ledger(5);
+        let network = MockConsensusNetwork::new(ledger.clone());
+        let mut node1 = network.connection(test_client_uri(1));
+        let mut node2 = network.connection(test_client_uri(2));
+
+        assert_eq!(node1.propose_tx(&get_tx(&ledger, 0, 100)).unwrap(), 5);
+        assert_eq!(ledger.num_blocks().unwrap(), 6);
+        assert_eq!(node2.fetch_block_height().unwrap(), 5);
+
+        let blocks = node2.fetch_blocks(4..10).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].parent_id, blocks[0].id);
+        assert_eq!(
+            node2.fetch_block_ids(5..6).unwrap(),
+            vec![ledger.get_block(5).unwrap().id]
+        );
+        assert_eq!(network.submitted_txs().len(), 1);
+    }
+
+    #[test]
+    // Transactions whose tombstone block has passed should be rejected.
+    fn test_propose_tx_rejects_expired_tombstone() {
+        let ledger = get_mock_ledger(5);
+        let network = MockConsensusNetwork::new(ledger.clone());
+        let mut node = network.connection(test_client_uri(1));
+
+        match node.propose_tx(&get_tx(&ledger, 0, 5)) {
+            Err(ConnectionError::TransactionValidation(
+                TransactionValidationError::TombstoneBlockExceeded,
+            )) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(ledger.num_blocks().unwrap(), 5);
+    }
+
+    #[test]
+    // Injected failures should fail the requested number of proposals.
+    fn test_inject_failures() {
+        let ledger = get_mock_ledger(5);
+        let network = MockConsensusNetwork::new(ledger.clone());
+        let mut node = network.connection(test_client_uri(1));
+
+        network.inject_failures(2);
+        assert!(node.propose_tx(&get_tx(&ledger, 0, 100)).is_err());
+        assert!(node.propose_tx(&get_tx(&ledger, 0, 100)).is_err());
+        assert!(node.propose_tx(&get_tx(&ledger, 0, 100)).is_ok());
+        assert_eq!(network.submitted_txs().len(), 1);
+    }
+
+    #[test]
+    // Dropped responses should report a retryable error, even though the transaction landed.
+    fn test_drop_responses() {
+        let ledger = get_mock_ledger(5);
+        let network = MockConsensusNetwork::new(ledger.clone());
+        let mut node = network.connection(test_client_uri(1));
+
+        network.drop_responses(1);
+        match node.propose_tx(&get_tx(&ledger, 0, 100)) {
+            Err(err) => assert!(err.should_retry()),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(ledger.num_blocks().unwrap(), 6);
+        assert_eq!(network.submitted_txs().len(), 1);
+    }
+
+    #[test]
+    // Held transactions should land together once externalized.
+    fn test_externalize_pending() {
+        let ledger = get_mock_ledger(5);
+        let network = MockConsensusNetwork::new(ledger.clone());
+        let mut node = network
+            .connection(test_client_uri(1))
+            .with_latency(Duration::from_millis(10));
+
+        network.set_auto_externalize(false);
+        assert_eq!(network.externalize_pending(), None);
+
+        node.propose_tx(&get_tx(&ledger, 0, 100)).unwrap();
+        node.propose_tx(&get_tx(&ledger, 1, 100)).unwrap();
+        assert_eq!(ledger.num_blocks().unwrap(), 5);
+        assert_eq!(network.pending_txs().len(), 2);
+
+        assert_eq!(network.externalize_pending(), Some(5));
+        assert_eq!(ledger.num_blocks().unwrap(), 6);
+        assert_eq!(ledger.get_block_contents(5).unwrap().outputs.len(), 2);
+        assert!(network.pending_txs().is_empty());
+    }
+}
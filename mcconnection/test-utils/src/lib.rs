@@ -6,6 +6,7 @@ use mcuri::ConsensusClientUri;
 use std::str::FromStr;
 
 mod blockchain;
+mod consensus;
 mod user_tx;
 
 pub fn test_client_uri(node_id: u32) -> ConsensusClientUri {
@@ -13,4 +14,8 @@ pub fn test_client_uri(node_id: u32) -> ConsensusClientUri {
         .expect("Could not construct client uri from string")
 }
 
-pub use self::{blockchain::MockBlockchainConnection, user_tx::MockUserTxConnection};
+pub use self::{
+    blockchain::MockBlockchainConnection,
+    consensus::{MockConsensusConnection, MockConsensusNetwork},
+    user_tx::MockUserTxConnection,
+};
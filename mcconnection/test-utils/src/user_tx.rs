@@ -9,12 +9,16 @@ use std::{
     fmt::{Display, Formatter, Result as FmtResult},
     hash::{Hash, Hasher},
 };
-use transaction::{tx::Tx, BlockIndex};
+use transaction::{
+    constants::BASE_FEE, dust::DustRules, ring_signature::KeyImage, tx::Tx, BlockIndex,
+};
 
 #[derive(Clone)]
 pub struct MockUserTxConnection {
     uri: ConsensusClientUri,
     pub submitted_txs: Vec<Tx>,
+    pub dust_rules: DustRules,
+    pub minimum_fee: u64,
 }
 
 impl MockUserTxConnection {
@@ -22,6 +26,8 @@ impl MockUserTxConnection {
         MockUserTxConnection {
             uri,
             submitted_txs: Vec::new(),
+            dust_rules: DustRules::default(),
+            minimum_fee: BASE_FEE,
         }
     }
 }
@@ -71,4 +77,19 @@ impl UserTxConnection for MockUserTxConnection {
         self.submitted_txs.push(tx.clone());
         Ok(1)
     }
+
+    fn fetch_dust_rules(&mut self) -> ConnectionResult<DustRules> {
+        Ok(self.dust_rules)
+    }
+
+    fn fetch_minimum_fee(&mut self) -> ConnectionResult<u64> {
+        Ok(self.minimum_fee)
+    }
+
+    fn fetch_pending_key_images(
+        &mut self,
+        _key_images: &[KeyImage],
+    ) -> ConnectionResult<Vec<KeyImage>> {
+        Ok(Vec::new())
+    }
 }
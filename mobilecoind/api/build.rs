@@ -3,6 +3,6 @@
 fn main() {
     mc_build_grpc::compile_protos_and_generate_mod_rs(
         &["./proto", "../../consensus/api/proto"],
-        &["mobilecoind_api.proto"],
+        &["mobilecoind_api.proto", "mobilecoind_faucet_api.proto"],
     );
 }
@@ -2,7 +2,7 @@
 
 //! provides conversions between types used in libmobilecoin and types from mobilecoind_api
 
-use crate::mobilecoind_api;
+use crate::{mob_uri::MobUri, mobilecoind_api};
 use mobilecoin_api::external;
 use std::convert::{From, TryFrom};
 use transaction::account_keys;
@@ -119,9 +119,33 @@ impl TryFrom<&mobilecoind_api::PublicAddress> for account_keys::PublicAddress {
     }
 }
 
+impl From<&MobUri> for mobilecoind_api::MobUri {
+    fn from(src: &MobUri) -> Self {
+        let mut dst = mobilecoind_api::MobUri::new();
+        dst.set_public_address((&src.public_address).into());
+        dst.set_amount(src.amount);
+        dst.set_memo(src.memo.clone());
+        dst.set_expiry(src.expiry);
+        dst
+    }
+}
+
+impl TryFrom<&mobilecoind_api::MobUri> for MobUri {
+    type Error = ConversionError;
+
+    fn try_from(src: &mobilecoind_api::MobUri) -> Result<Self, Self::Error> {
+        Ok(Self {
+            public_address: account_keys::PublicAddress::try_from(src.get_public_address())?,
+            amount: src.amount,
+            memo: src.memo.clone(),
+            expiry: src.expiry,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::mobilecoind_api;
+    use crate::{mob_uri::MobUri, mobilecoind_api};
     use common::logger::{test_with_logger, Logger};
     use mobilecoin_api::external;
     use rand::{rngs::StdRng, SeedableRng};
@@ -240,4 +264,33 @@ mod tests {
             assert_eq!(public_address, public_address2);
         }
     }
+
+    // Test converting between mobilecoind_api::MobUri and mob_uri::MobUri
+    #[test_with_logger]
+    fn test_mob_uri_conversion(_logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+
+        // mob_uri -> mobilecoind_api
+        let mut mob_uri =
+            MobUri::new(account_keys::AccountKey::random(&mut rng).default_subaddress());
+        mob_uri.amount = 1234;
+        mob_uri.memo = "coffee".to_string();
+        mob_uri.expiry = 1_600_000_000;
+
+        let proto_mob_uri = mobilecoind_api::MobUri::from(&mob_uri);
+        assert_eq!(
+            account_keys::PublicAddress::try_from(proto_mob_uri.get_public_address()).unwrap(),
+            mob_uri.public_address
+        );
+        assert_eq!(proto_mob_uri.amount, 1234);
+        assert_eq!(proto_mob_uri.memo, String::from("coffee"));
+        assert_eq!(proto_mob_uri.expiry, 1_600_000_000);
+
+        // mobilecoind_api -> mob_uri
+        let mob_uri2 = MobUri::try_from(&proto_mob_uri).unwrap();
+        assert_eq!(mob_uri, mob_uri2);
+
+        // A URI without an address cannot be paid.
+        assert!(MobUri::try_from(&mobilecoind_api::MobUri::new()).is_err());
+    }
 }
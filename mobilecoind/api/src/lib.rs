@@ -20,3 +20,4 @@ pub use crate::conversions::ConversionError;
 pub use autogenerated_code::{mobilecoind_api::*, *};
 
 pub mod conversions;
+pub mod mob_uri;
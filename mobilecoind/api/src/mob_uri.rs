@@ -0,0 +1,251 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! `mob:` URIs, used by mobile wallets and point-of-sale apps to ask for a payment, e.g. in links
+//! and QR codes.
+//!
+//! `mob:<address>?amount=<picoMOB>&memo=<memo>&expires=<unix seconds>`
+//! * The address is a base-58 "MobileCoin Request Code" that only holds the address.
+//! * All parameters are optional. Unknown parameters are ignored, so that later versions can add
+//!   some.
+
+use failure::Fail;
+use keys::RistrettoPublic;
+use mc_b58_payloads::payloads::RequestPayload;
+use mcserial::ReprBytes32;
+use std::convert::TryFrom;
+use transaction::account_keys::PublicAddress;
+use url::Url;
+
+/// The scheme of MobileCoin URIs.
+pub const MOB_URI_SCHEME: &str = "mob";
+
+#[derive(Clone, Debug, Eq, PartialEq, Fail)]
+pub enum MobUriError {
+    #[fail(display = "Url parse error: {}", _0)]
+    UrlParse(url::ParseError),
+
+    #[fail(display = "Unknown scheme: {}", _0)]
+    UnknownScheme(String),
+
+    #[fail(display = "Invalid address: {}", _0)]
+    Address(String),
+
+    #[fail(display = "Invalid parameter {}: {}", _0, _1)]
+    Parameter(String, String),
+
+    #[fail(display = "Duplicate parameter: {}", _0)]
+    DuplicateParameter(String),
+}
+
+impl From<url::ParseError> for MobUriError {
+    fn from(src: url::ParseError) -> Self {
+        Self::UrlParse(src)
+    }
+}
+
+/// A payment request, as carried by a `mob:` URI.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MobUri {
+    /// The address to pay.
+    pub public_address: PublicAddress,
+
+    /// The requested amount in picoMOB, or 0 to let the payer choose.
+    pub amount: u64,
+
+    /// A message for the payer. Empty if there is none.
+    pub memo: String,
+
+    /// The request expires after this time, in seconds since the Unix epoch. 0 if it never
+    /// expires.
+    pub expiry: u64,
+}
+
+impl MobUri {
+    /// A URI for a payment to `public_address`, without parameters.
+    pub fn new(public_address: PublicAddress) -> Self {
+        Self {
+            public_address,
+            amount: 0,
+            memo: String::new(),
+            expiry: 0,
+        }
+    }
+
+    /// Returns true if the request expired at `now`, in seconds since the Unix epoch.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry != 0 && self.expiry <= now
+    }
+
+    /// Encodes the URI. Parameters left to their default are omitted.
+    pub fn encode(&self) -> Result<String, MobUriError> {
+        let view_public_key = self.public_address.view_public_key().to_bytes();
+        let spend_public_key = self.public_address.spend_public_key().to_bytes();
+        let payload = match self.public_address.fog_url() {
+            Some(fog_url) => RequestPayload::new_v1(&view_public_key, &spend_public_key, fog_url),
+            None => RequestPayload::new_v0(&view_public_key, &spend_public_key),
+        }
+        .map_err(|err| MobUriError::Address(err.to_string()))?;
+
+        let mut url = Url::parse(&format!("{}:{}", MOB_URI_SCHEME, payload.encode()))?;
+        if self.amount != 0 || !self.memo.is_empty() || self.expiry != 0 {
+            let mut query = url.query_pairs_mut();
+            if self.amount != 0 {
+                query.append_pair("amount", &self.amount.to_string());
+            }
+            if !self.memo.is_empty() {
+                query.append_pair("memo", &self.memo);
+            }
+            if self.expiry != 0 {
+                query.append_pair("expires", &self.expiry.to_string());
+            }
+        }
+        Ok(url.into_string())
+    }
+
+    /// Decodes a URI. The address must not be a request code asking for a value or holding a
+    /// memo, since these belong in the parameters.
+    pub fn decode(uri: &str) -> Result<Self, MobUriError> {
+        let url = Url::parse(uri)?;
+        if url.scheme() != MOB_URI_SCHEME {
+            return Err(MobUriError::UnknownScheme(url.scheme().to_owned()));
+        }
+
+        let payload = RequestPayload::decode(url.path())
+            .map_err(|err| MobUriError::Address(err.to_string()))?;
+        if payload.value != 0 || !payload.memo.is_empty() {
+            return Err(MobUriError::Address(
+                "request codes with a value or memo are not addresses".to_owned(),
+            ));
+        }
+        let spend_public_key = RistrettoPublic::try_from(&payload.spend_public_key)
+            .map_err(|err| MobUriError::Address(err.to_string()))?;
+        let view_public_key = RistrettoPublic::try_from(&payload.view_public_key)
+            .map_err(|err| MobUriError::Address(err.to_string()))?;
+        let public_address = if payload.fog_url.is_empty() {
+            PublicAddress::new(&spend_public_key, &view_public_key)
+        } else {
+            PublicAddress::new_with_fog(&spend_public_key, &view_public_key, &payload.fog_url)
+        };
+
+        let mut mob_uri = Self::new(public_address);
+        let (mut has_amount, mut has_memo, mut has_expiry) = (false, false, false);
+        for (name, value) in url.query_pairs() {
+            let seen = match name.as_ref() {
+                "amount" => {
+                    mob_uri.amount = parse_u64(&name, &value)?;
+                    &mut has_amount
+                }
+                "memo" => {
+                    mob_uri.memo = value.into_owned();
+                    &mut has_memo
+                }
+                "expires" => {
+                    mob_uri.expiry = parse_u64(&name, &value)?;
+                    &mut has_expiry
+                }
+                _ => continue,
+            };
+            if *seen {
+                return Err(MobUriError::DuplicateParameter(name.into_owned()));
+            }
+            *seen = true;
+        }
+
+        Ok(mob_uri)
+    }
+}
+
+fn parse_u64(name: &str, value: &str) -> Result<u64, MobUriError> {
+    value
+        .parse()
+        .map_err(|_| MobUriError::Parameter(name.to_owned(), value.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+
+    #[test]
+    // URIs should decode to what was encoded.
+    fn test_encode_decode() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+
+        let mut with_params = MobUri::new(AccountKey::random(&mut rng).default_subaddress());
+        with_params.amount = 1234567890;
+        with_params.memo = "coffee & a bagel, 100% paid".to_owned();
+        with_params.expiry = 1_600_000_000;
+
+        let mut with_fog = MobUri::new(AccountKey::random_with_fog(&mut rng).default_subaddress());
+        with_fog.amount = u64::max_value();
+
+        let mob_uris = vec![
+            MobUri::new(AccountKey::random(&mut rng).default_subaddress()),
+            with_params,
+            with_fog,
+        ];
+
+        for mob_uri in mob_uris {
+            let uri = mob_uri.encode().unwrap();
+            assert!(uri.starts_with("mob:"));
+            assert_eq!(MobUri::decode(&uri).unwrap(), mob_uri);
+        }
+    }
+
+    #[test]
+    // Parameters should be optional, and in any order.
+    fn test_decode_parameters() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let public_address = AccountKey::random(&mut rng).default_subaddress();
+        let uri = MobUri::new(public_address.clone()).encode().unwrap();
+        assert!(!uri.contains('?'));
+
+        let mob_uri = MobUri::decode(&format!(
+            "{}?expires=10&future=1&memo=tea+time&amount=5",
+            uri
+        ))
+        .unwrap();
+        assert_eq!(mob_uri.public_address, public_address);
+        assert_eq!(mob_uri.amount, 5);
+        assert_eq!(mob_uri.memo, "tea time");
+        assert_eq!(mob_uri.expiry, 10);
+        assert!(!mob_uri.is_expired(9));
+        assert!(mob_uri.is_expired(10));
+        assert!(!MobUri::new(public_address).is_expired(u64::max_value()));
+    }
+
+    #[test]
+    // Malformed URIs should be rejected.
+    fn test_decode_invalid() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let public_address = AccountKey::random(&mut rng).default_subaddress();
+        let uri = MobUri::new(public_address.clone()).encode().unwrap();
+        let b58_address = uri.trim_start_matches("mob:");
+
+        let request_code = RequestPayload::new_v3(
+            &public_address.view_public_key().to_bytes(),
+            &public_address.spend_public_key().to_bytes(),
+            "",
+            10,
+            "memo",
+        )
+        .unwrap()
+        .encode();
+
+        let invalid = vec![
+            "".to_owned(),
+            format!("http:{}", b58_address),
+            "mob:junk".to_owned(),
+            format!("mob:{}", request_code),
+            format!("{}?amount=-1", uri),
+            format!("{}?amount=1.5", uri),
+            format!("{}?expires=tomorrow", uri),
+            format!("{}?amount=1&amount=2", uri),
+        ];
+
+        for uri in invalid {
+            assert!(MobUri::decode(&uri).is_err(), "{}", uri);
+        }
+    }
+}
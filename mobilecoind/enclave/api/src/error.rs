@@ -0,0 +1,76 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Enclave API Errors
+
+use attest::SgxError;
+use failure::Fail;
+use mcserial::{
+    decode::Error as RmpDecodeError, encode::Error as RmpEncodeError,
+    DecodeError as ProstDecodeError,
+};
+use serde::{Deserialize, Serialize};
+
+/// An enumeration of errors which can occur inside the signing enclave.
+#[derive(Clone, Debug, Deserialize, Fail, PartialEq, PartialOrd, Serialize)]
+pub enum Error {
+    /// A call to the SGX SDK has failed
+    #[fail(display = "Error communicating with SGX: {}", _0)]
+    Sgx(SgxError),
+
+    /// There was an error serializing or deserializing data
+    #[fail(display = "Error while serializing/deserializing")]
+    Serialization,
+
+    /// The account key could not be sealed
+    #[fail(display = "Could not seal the account key")]
+    Seal,
+
+    /// The sealed account key could not be unsealed, e.g. because it was sealed on another
+    /// platform
+    #[fail(display = "Could not unseal the account key")]
+    Unseal,
+
+    /// A ring could not be signed, e.g. because the real input does not belong to the account
+    #[fail(display = "Could not sign a ring")]
+    Signature,
+
+    /// The input at this index is not the account's, or does not match the transaction
+    #[fail(display = "Input {} does not match the transaction", _0)]
+    InputMismatch(usize),
+
+    /// The output at this index does not commit to the claimed value
+    #[fail(display = "Output {} does not match the transaction", _0)]
+    OutputMismatch(usize),
+
+    /// The inputs do not pay for exactly the outputs and the fee
+    #[fail(display = "The transaction does not balance")]
+    Unbalanced,
+
+    /// The change output at this index does not return to the account
+    #[fail(display = "Output {} is not change returned to the account", _0)]
+    ChangeMismatch(usize),
+}
+
+impl From<SgxError> for Error {
+    fn from(src: SgxError) -> Self {
+        Error::Sgx(src)
+    }
+}
+
+impl From<RmpEncodeError> for Error {
+    fn from(_src: RmpEncodeError) -> Error {
+        Error::Serialization
+    }
+}
+
+impl From<RmpDecodeError> for Error {
+    fn from(_src: RmpDecodeError) -> Error {
+        Error::Serialization
+    }
+}
+
+impl From<ProstDecodeError> for Error {
+    fn from(_src: ProstDecodeError) -> Error {
+        Error::Serialization
+    }
+}
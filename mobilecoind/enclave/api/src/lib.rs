@@ -0,0 +1,71 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! APIs for the mobilecoind signing enclave
+
+#![no_std]
+
+extern crate alloc;
+
+mod error;
+mod messages;
+mod signable_tx;
+
+pub use crate::{
+    error::Error,
+    messages::EnclaveCall,
+    signable_tx::{SignableInput, SignableTx},
+};
+
+use alloc::vec::Vec;
+use core::result::Result as StdResult;
+use keys::RistrettoPublic;
+use serde::{Deserialize, Serialize};
+use transaction::{
+    account_keys::AccountKey,
+    ring_signature::{KeyImage, RingMLSAG},
+};
+
+/// A generic result type for enclave calls
+pub type Result<T> = StdResult<T, Error>;
+
+/// An account key sealed by the signing enclave. Only the signing enclave, running on the platform
+/// that sealed it, can unseal it.
+pub type SealedAccountKey = Vec<u8>;
+
+/// An output received by a sealed account, identified by what the enclave needs to compute its
+/// key image.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReceivedOutput {
+    /// The output's transaction public key `R`.
+    pub tx_public_key: RistrettoPublic,
+
+    /// Index of the subaddress the output was sent to.
+    pub subaddress_index: u64,
+}
+
+/// The API of the signing enclave, which holds the spend private keys of monitors.
+pub trait SigningEnclave: Send + Sync {
+    /// Seal an account key to this platform. The returned bytes are all mobilecoind stores of the
+    /// account's spend private key.
+    ///
+    /// The account key crosses untrusted memory on its way in, so provisioning is only as safe as
+    /// the host it runs on. Monitors should be added to a signing mobilecoind from a trusted host,
+    /// e.g. before it is exposed to the network, after which the key only exists sealed.
+    fn seal_account_key(&self, account_key: AccountKey) -> Result<SealedAccountKey>;
+
+    /// Compute the key images of outputs received by a sealed account, in order.
+    fn key_images(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        outputs: Vec<ReceivedOutput>,
+    ) -> Result<Vec<KeyImage>>;
+
+    /// Sign the rings of a transaction's inputs with a sealed account, after checking the
+    /// transaction with `SignableTx::check`. The message signed is the hash of the transaction's
+    /// prefix, as computed by the enclave. Returns a ring signature per input, in order.
+    fn sign_tx(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        tx: SignableTx,
+    ) -> Result<Vec<RingMLSAG>>;
+}
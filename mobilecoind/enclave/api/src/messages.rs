@@ -0,0 +1,27 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The message types used by the mobilecoind_enclave_api.
+
+use crate::{ReceivedOutput, SealedAccountKey, SignableTx};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use transaction::account_keys::AccountKey;
+
+/// An enumeration of API calls and their arguments for use across serialization boundaries.
+#[derive(Clone, Deserialize, Serialize)]
+pub enum EnclaveCall {
+    /// The [SigningEnclave::seal_account_key()] method.
+    ///
+    /// Seals an account key to this platform.
+    SealAccountKey(AccountKey),
+
+    /// The [SigningEnclave::key_images()] method.
+    ///
+    /// Computes the key images of outputs received by a sealed account.
+    KeyImages(SealedAccountKey, Vec<ReceivedOutput>),
+
+    /// The [SigningEnclave::sign_tx()] method.
+    ///
+    /// Checks a transaction, and signs the rings of its inputs with a sealed account.
+    SignTx(SealedAccountKey, SignableTx),
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A transaction to sign, and the checks the signing enclave runs on it before signing.
+
+use crate::{Error, Result};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
+use serde::{Deserialize, Serialize};
+use transaction::{
+    account_keys::AccountKey,
+    mob::PicoMob,
+    onetime_keys::{recover_onetime_private_key, subaddress_for_key},
+    ring_signature::{CurveScalar, Scalar},
+    tx::TxPrefix,
+    CompressedCommitment,
+};
+
+/// The real input of one of a transaction's rings, with the secrets the enclave needs to sign it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SignableInput {
+    /// Index of the real input in the ring.
+    pub real_index: usize,
+
+    /// Index of the subaddress the real input was sent to.
+    pub subaddress_index: u64,
+
+    /// Value of the real input.
+    pub value: u64,
+
+    /// Blinding of the real input's amount commitment.
+    pub blinding: CurveScalar,
+
+    /// Blinding of the pseudo-output the ring signature commits to.
+    pub pseudo_output_blinding: CurveScalar,
+}
+
+/// A transaction whose inputs the signing enclave is asked to sign.
+///
+/// The enclave signs the hash of `prefix`, which it computes itself, and only after checking that
+/// the rest of the request agrees with the prefix. See `SignableTx::check`.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SignableTx {
+    /// The transaction, without its signature.
+    pub prefix: TxPrefix,
+
+    /// The real input of each of `prefix.inputs`, in order.
+    pub inputs: Vec<SignableInput>,
+
+    /// Value and blinding of each of `prefix.outputs`, in order. The fee's are implicit.
+    pub output_values_and_blindings: Vec<(u64, CurveScalar)>,
+
+    /// Indices in `prefix.outputs` of the outputs returning change to the account.
+    pub change_outputs: Vec<usize>,
+
+    /// The account's subaddress the change is returned to.
+    pub change_subaddress: u64,
+}
+
+impl SignableTx {
+    /// Checks the transaction against the policy of the signing enclave, and returns the one-time
+    /// private key of each real input, in order. The transaction must:
+    /// * only spend real inputs owned by `account_key`, with the claimed values,
+    /// * have outputs that commit to the claimed values,
+    /// * balance, so that nothing but the outputs and the fee is paid for by the inputs,
+    /// * return the claimed change to `account_key`'s change subaddress.
+    ///
+    /// The enclave cannot tell who the other outputs pay, which is up to the caller, but it will
+    /// not sign rings that belong to another transaction, nor change that leaves the account.
+    pub fn check(&self, account_key: &AccountKey) -> Result<Vec<RistrettoPrivate>> {
+        if self.inputs.len() != self.prefix.inputs.len() {
+            return Err(Error::InputMismatch(self.inputs.len()));
+        }
+        if self.output_values_and_blindings.len() != self.prefix.outputs.len() {
+            return Err(Error::OutputMismatch(
+                self.output_values_and_blindings.len(),
+            ));
+        }
+
+        // Each real input is the account's, and has the claimed value.
+        let onetime_private_keys = self
+            .inputs
+            .iter()
+            .zip(self.prefix.inputs.iter())
+            .enumerate()
+            .map(|(index, (input, tx_in))| {
+                let real_output = tx_in
+                    .ring
+                    .get(input.real_index)
+                    .ok_or(Error::InputMismatch(index))?;
+                let real_output_public_key = RistrettoPublic::try_from(&real_output.public_key)
+                    .or(Err(Error::InputMismatch(index)))?;
+                let onetime_private_key = recover_onetime_private_key(
+                    &real_output_public_key,
+                    account_key.view_private_key(),
+                    &account_key.subaddress_spend_key(input.subaddress_index),
+                );
+                let target_key =
+                    CompressedRistrettoPublic::from(&RistrettoPublic::from(&onetime_private_key));
+                let commitment = CompressedCommitment::new(input.value, *input.blinding.as_ref());
+                if target_key != real_output.target_key
+                    || commitment != real_output.amount.commitment
+                {
+                    return Err(Error::InputMismatch(index));
+                }
+                Ok(onetime_private_key)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Each output commits to the claimed value.
+        for (index, ((value, blinding), tx_out)) in self
+            .output_values_and_blindings
+            .iter()
+            .zip(self.prefix.outputs.iter())
+            .enumerate()
+        {
+            if CompressedCommitment::new(*value, *blinding.as_ref()) != tx_out.amount.commitment {
+                return Err(Error::OutputMismatch(index));
+            }
+        }
+
+        // The inputs pay for the outputs and the fee, and the pseudo-outputs they are signed with
+        // commit to the same total.
+        let input_total =
+            PicoMob::checked_sum(self.inputs.iter().map(|input| PicoMob::new(input.value)));
+        let output_total = PicoMob::checked_sum(
+            self.output_values_and_blindings
+                .iter()
+                .map(|(value, _)| PicoMob::new(*value))
+                .chain(core::iter::once(PicoMob::new(self.prefix.fee))),
+        );
+        if input_total.is_none() || input_total != output_total {
+            return Err(Error::Unbalanced);
+        }
+        let pseudo_output_blinding_total: Scalar = self
+            .inputs
+            .iter()
+            .map(|input| input.pseudo_output_blinding.as_ref())
+            .sum();
+        let output_blinding_total: Scalar = self
+            .output_values_and_blindings
+            .iter()
+            .map(|(_, blinding)| blinding.as_ref())
+            .sum();
+        if pseudo_output_blinding_total != output_blinding_total {
+            return Err(Error::Unbalanced);
+        }
+
+        // The change goes back to the account.
+        let change_spend_public_key = *account_key
+            .subaddress(self.change_subaddress)
+            .spend_public_key();
+        for index in self.change_outputs.iter() {
+            let tx_out = self
+                .prefix
+                .outputs
+                .get(*index)
+                .ok_or(Error::ChangeMismatch(*index))?;
+            let target_key = RistrettoPublic::try_from(&tx_out.target_key)
+                .or(Err(Error::ChangeMismatch(*index)))?;
+            let public_key = RistrettoPublic::try_from(&tx_out.public_key)
+                .or(Err(Error::ChangeMismatch(*index)))?;
+            if subaddress_for_key(account_key.view_private_key(), &target_key, &public_key)
+                != change_spend_public_key
+            {
+                return Err(Error::ChangeMismatch(*index));
+            }
+        }
+
+        Ok(onetime_private_keys)
+    }
+
+    /// The message the ring signatures sign, i.e. the hash of the prefix.
+    pub fn message(&self) -> [u8; 32] {
+        *self.prefix.hash().as_bytes()
+    }
+
+    /// The ring of each input, as the ring signatures sign it.
+    pub fn rings(&self) -> Vec<Vec<(CompressedRistrettoPublic, CompressedCommitment)>> {
+        self.prefix
+            .inputs
+            .iter()
+            .map(|tx_in| {
+                tx_in
+                    .ring
+                    .iter()
+                    .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
+                    .collect()
+            })
+            .collect()
+    }
+}
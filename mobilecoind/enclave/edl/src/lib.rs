@@ -0,0 +1,3 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+#![no_std]
@@ -0,0 +1,94 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! mobilecoind Signing Enclave Implementation
+//!
+//! This crate implements the inside-the-enclave version of the `SigningEnclave` API. The enclave
+//! keeps no state: every call carries the sealed account key it needs, which is unsealed for the
+//! duration of the call only.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use attest::IntelSealed;
+use attest_trusted::SealAlgo;
+use core::convert::TryFrom;
+use mcrand::McRng;
+use mobilecoind_enclave_api::{
+    Error, ReceivedOutput, Result, SealedAccountKey, SignableTx, SigningEnclave,
+};
+use transaction::{
+    account_keys::AccountKey,
+    onetime_keys::{compute_key_image, recover_onetime_private_key},
+    ring_signature::{KeyImage, RingMLSAG},
+};
+
+/// The signing enclave, as it runs inside SGX.
+#[derive(Default)]
+pub struct SgxSigningEnclave;
+
+impl SgxSigningEnclave {
+    fn unseal_account_key(sealed_account_key: &SealedAccountKey) -> Result<AccountKey> {
+        let sealed = IntelSealed::try_from(sealed_account_key.clone()).or(Err(Error::Unseal))?;
+        let (account_key_bytes, _mac) = sealed.unseal_raw().or(Err(Error::Unseal))?;
+        Ok(mcserial::decode(&account_key_bytes)?)
+    }
+}
+
+impl SigningEnclave for SgxSigningEnclave {
+    fn seal_account_key(&self, account_key: AccountKey) -> Result<SealedAccountKey> {
+        let sealed =
+            IntelSealed::seal_raw(&mcserial::encode(&account_key), &[]).or(Err(Error::Seal))?;
+        Ok(sealed.as_ref().to_vec())
+    }
+
+    fn key_images(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        outputs: Vec<ReceivedOutput>,
+    ) -> Result<Vec<KeyImage>> {
+        let account_key = Self::unseal_account_key(sealed_account_key)?;
+        Ok(outputs
+            .iter()
+            .map(|output| {
+                let onetime_private_key = recover_onetime_private_key(
+                    &output.tx_public_key,
+                    account_key.view_private_key(),
+                    &account_key.subaddress_spend_key(output.subaddress_index),
+                );
+                compute_key_image(&onetime_private_key)
+            })
+            .collect())
+    }
+
+    fn sign_tx(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        tx: SignableTx,
+    ) -> Result<Vec<RingMLSAG>> {
+        let account_key = Self::unseal_account_key(sealed_account_key)?;
+        let onetime_private_keys = tx.check(&account_key)?;
+        let message = tx.message();
+        let rings = tx.rings();
+        let mut rng = McRng::default();
+        tx.inputs
+            .iter()
+            .zip(rings.iter())
+            .zip(onetime_private_keys.iter())
+            .map(|((input, ring), onetime_private_key)| {
+                RingMLSAG::sign(
+                    &message,
+                    ring,
+                    input.real_index,
+                    onetime_private_key,
+                    input.value,
+                    input.blinding.as_ref(),
+                    input.pseudo_output_blinding.as_ref(),
+                    &mut rng,
+                )
+                .or(Err(Error::Signature))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Build script for the mobilecoind_enclave_measurement crate.
+
+use cargo_emit::{rerun_if_env_changed, rustc_cfg};
+use mcbuild_enclave::Builder;
+use mcbuild_sgx_utils::{IasMode, SgxEnvironment, SgxMode, TcsPolicy};
+use mcbuild_utils::Environment;
+use std::{env::var, path::PathBuf};
+
+pub const MOBILECOIND_ENCLAVE_PRODUCT_ID: u16 = 2;
+pub const MOBILECOIND_ENCLAVE_SECURITY_VERSION: u16 = 1;
+pub const MOBILECOIND_ENCLAVE_NAME: &str = "mobilecoind-enclave";
+pub const MOBILECOIND_ENCLAVE_DIR: &str = "../trusted";
+
+// Enclave sizing. The enclave keeps no state, so it only needs room for the calls in flight.
+pub const MOBILECOIND_ENCLAVE_TCS_NUM: usize = 8;
+pub const MOBILECOIND_ENCLAVE_STACK_MAX_SIZE: usize = 256 * 1024;
+pub const MOBILECOIND_ENCLAVE_HEAP_MAX_SIZE: usize = 16 * 1024 * 1024;
+
+fn main() {
+    let env = Environment::default();
+    let sgx = SgxEnvironment::new(&env).expect("Could not read SGX environment");
+
+    if sgx.sgx_mode() == SgxMode::Simulation {
+        rustc_cfg!("feature=\"sgx-sim\"");
+    }
+
+    let mut builder = Builder::new(
+        &env,
+        &sgx,
+        MOBILECOIND_ENCLAVE_NAME,
+        MOBILECOIND_ENCLAVE_DIR.as_ref(),
+    )
+    .expect("Could not construct builder");
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_CSS");
+    if let Ok(value) = var("MOBILECOIND_ENCLAVE_CSS") {
+        builder.css(PathBuf::from(&value));
+    }
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_UNSIGNED");
+    if let Ok(value) = var("MOBILECOIND_ENCLAVE_UNSIGNED") {
+        builder.unsigned_enclave(PathBuf::from(&value));
+    }
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_SIGNED");
+    if let Ok(value) = var("MOBILECOIND_ENCLAVE_SIGNED") {
+        builder.signed_enclave(PathBuf::from(&value));
+    }
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_LDS");
+    if let Ok(value) = var("MOBILECOIND_ENCLAVE_LDS") {
+        builder.lds(PathBuf::from(&value));
+    }
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_PRIVKEY");
+    if let Ok(value) = var("MOBILECOIND_ENCLAVE_PRIVKEY") {
+        builder.privkey(PathBuf::from(&value));
+    }
+
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_GENDATA");
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_PUBKEY");
+    rerun_if_env_changed!("MOBILECOIND_ENCLAVE_SIGNATURE");
+    if let Ok(gendata) = var("MOBILECOIND_ENCLAVE_GENDATA") {
+        if let Ok(pubkey) = var("MOBILECOIND_ENCLAVE_PUBKEY") {
+            if let Ok(signature) = var("MOBILECOIND_ENCLAVE_SIGNATURE") {
+                builder.catsig(gendata.into(), pubkey.into(), signature.into());
+            }
+        }
+    }
+
+    builder
+        .cargo_builder
+        .target_dir(env.target_dir().join(MOBILECOIND_ENCLAVE_NAME));
+
+    builder
+        .config_builder
+        .debug(
+            sgx.sgx_mode() == SgxMode::Simulation
+                || sgx.ias_mode() == IasMode::Development
+                || env.profile() != "release",
+        )
+        .prod_id(MOBILECOIND_ENCLAVE_PRODUCT_ID)
+        .isv_security_version(MOBILECOIND_ENCLAVE_SECURITY_VERSION)
+        .tcs_num(MOBILECOIND_ENCLAVE_TCS_NUM)
+        .tcs_min_pool(1)
+        .tcs_policy(TcsPolicy::Unbound)
+        .stack_max_size(MOBILECOIND_ENCLAVE_STACK_MAX_SIZE)
+        .heap_max_size(MOBILECOIND_ENCLAVE_HEAP_MAX_SIZE);
+
+    let _sig = builder
+        .build()
+        .expect("Failed to extract mobilecoind-enclave signature");
+}
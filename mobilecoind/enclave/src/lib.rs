@@ -0,0 +1,147 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! The mobilecoind Signing Enclave Proxy
+
+pub use mobilecoind_enclave_api::{
+    EnclaveCall, Error, ReceivedOutput, Result, SealedAccountKey, SignableInput, SignableTx,
+    SigningEnclave,
+};
+
+use attest::{SgxError, DEBUG_ENCLAVE};
+use enclave_boundary::untrusted::make_variable_length_ecall;
+use sgx_types::{sgx_enclave_id_t, sgx_status_t, *};
+use sgx_urts::SgxEnclave;
+use std::{path, result::Result as StdResult, sync::Arc};
+use transaction::{
+    account_keys::AccountKey,
+    ring_signature::{KeyImage, RingMLSAG},
+};
+
+#[allow(unused_imports)]
+use sgx_slog;
+
+/// The default filename of mobilecoind's signing enclave binary.
+pub const ENCLAVE_FILE: &str = "libmobilecoind-enclave.signed.so";
+
+#[derive(Clone)]
+pub struct SigningSgxEnclave {
+    /// Hold a reference counter to the enclave to prevent destruction,
+    /// this object is a handle to an enclave rather than having its lifetime tied to the actual enclave.
+    enclave: Arc<SgxEnclave>,
+}
+
+impl SigningSgxEnclave {
+    pub fn new(enclave_path: path::PathBuf) -> SigningSgxEnclave {
+        let mut launch_token: sgx_launch_token_t = [0; 1024];
+        let mut launch_token_updated: i32 = 0;
+        // Receives the misc select and attributes of the launched enclave.
+        let mut misc_attr = sgx_misc_attribute_t {
+            secs_attr: sgx_attributes_t { flags: 0, xfrm: 0 },
+            misc_select: 0,
+        };
+        let enclave = SgxEnclave::create(
+            &enclave_path,
+            DEBUG_ENCLAVE as i32,
+            &mut launch_token,
+            &mut launch_token_updated,
+            &mut misc_attr,
+        )
+        .unwrap_or_else(|_| {
+            panic!(
+                "SgxEnclave::create(file_name={:?}, debug={}) failed",
+                &enclave_path, DEBUG_ENCLAVE as i32
+            )
+        });
+
+        SigningSgxEnclave {
+            enclave: Arc::new(enclave),
+        }
+    }
+
+    /// Takes serialized data, and fires to the corresponding ECALL.
+    fn enclave_call(&self, inbuf: &[u8]) -> StdResult<Vec<u8>, SgxError> {
+        Ok(make_variable_length_ecall(
+            self.enclave.geteid(),
+            mobileenclave_call,
+            &inbuf,
+        )?)
+    }
+}
+
+/// Proxy API for talking to the corresponding implementation inside the enclave.
+impl SigningEnclave for SigningSgxEnclave {
+    fn seal_account_key(&self, account_key: AccountKey) -> Result<SealedAccountKey> {
+        let inbuf = mcserial::serialize(&EnclaveCall::SealAccountKey(account_key))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn key_images(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        outputs: Vec<ReceivedOutput>,
+    ) -> Result<Vec<KeyImage>> {
+        let inbuf =
+            mcserial::serialize(&EnclaveCall::KeyImages(sealed_account_key.clone(), outputs))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+
+    fn sign_tx(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        tx: SignableTx,
+    ) -> Result<Vec<RingMLSAG>> {
+        let inbuf = mcserial::serialize(&EnclaveCall::SignTx(sealed_account_key.clone(), tx))?;
+        let outbuf = self.enclave_call(&inbuf)?;
+        mcserial::deserialize(&outbuf[..])?
+    }
+}
+
+extern "C" {
+    /// Unified Enclave ECALL declaration.
+    ///
+    /// Callers should initialize `outbuf_used` and `outbuf_retry_id`
+    /// to zero before calling the first time. In the event the
+    /// output buffer is not large enough to hold the serialized
+    /// result, the enclave must cache the output buffer keyed by
+    /// a numeric ID, set the `outbuf_used` to the required size,
+    /// update the `outbuf_retry_id` to the numeric ID, and return
+    /// `sgx_status_t::SGX_ERROR_OUT_OF_MEMORY`.
+    ///
+    /// When callers see that return value, they must resize their
+    /// output buffer and repeat the call with the `outbuf_retry_id`
+    /// set to the value returned by the enclave. The enclave will
+    /// copy the cached output into the newly resized outbuf, set
+    /// `outbuf_used` appropriately, reset `outbuf_retry_id` to
+    /// zero, and return `sgx_status_t::SGX_STATUS_SUCCESS`, indicating
+    /// the underlying ECALL was successful.
+    ///
+    /// Other sgx_status_t values are not similarly overloaded.
+    ///
+    /// The implementation of this method is auto-generated by edger8r,
+    /// in two parts. The first part is the literal `mobileenclave_call()` C function,
+    /// which lives in the untrusted code. The second part is a corresponding
+    /// function that will run inside the enclave as an ECALL. The generated
+    /// untrusted function will call the generated trusted function. This
+    /// implicitly depends on a real function inside the enclave that is
+    /// similar, but not identical, in that it does not include the `eid`
+    /// parameter. As a result, the call stack will look something like
+    /// this:
+    ///
+    ///  1. Application Code
+    ///  2. Untrusted generated_enclave_api(eid, retval, inbuf, ...) function
+    ///  3. Trusted, generated_enclave_api(inbuf, ...) ECALL
+    ///  4. Target mobileenclave_call(inbuf, ...) method inside rust in the
+    ///     enclave.
+    pub fn mobileenclave_call(
+        eid: sgx_enclave_id_t,
+        retval: *mut sgx_status_t,
+        inbuf: *const u8,
+        inbuf_len: usize,
+        outbuf: *mut u8,
+        outbuf_len: usize,
+        outbuf_used: *mut usize,
+        outbuf_retry_id: *mut u64,
+    ) -> sgx_status_t;
+}
@@ -0,0 +1,104 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! mobilecoind Signing Enclave
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::slice;
+use enclave_boundary::trusted::RetryBuffer;
+use lazy_static::lazy_static;
+use mcserial::{deserialize, serialize};
+use mobilecoind_enclave_api::{EnclaveCall, SigningEnclave};
+use mobilecoind_enclave_impl::SgxSigningEnclave;
+use sgx_compat::panic::catch_unwind;
+use sgx_types::{c_void, sgx_is_outside_enclave, sgx_status_t};
+
+lazy_static! {
+    /// Storage for ECALL results whose given outbuf was not large enough
+    static ref RETRY_BUFFER: RetryBuffer = { RetryBuffer::new(&ecall_dispatcher) };
+
+    /// Storage for the business logic / implementation state
+    static ref ENCLAVE: SgxSigningEnclave = { Default::default() };
+}
+
+/// Dispatch ecalls with the unified signature
+pub fn ecall_dispatcher(inbuf: &[u8]) -> Result<Vec<u8>, sgx_status_t> {
+    // Figure out what we're trying to do
+    let call_details: EnclaveCall =
+        deserialize(inbuf).or(Err(sgx_status_t::SGX_ERROR_INVALID_PARAMETER))?;
+
+    // And actually do it
+    let outdata = match call_details {
+        EnclaveCall::SealAccountKey(account_key) => {
+            serialize(&ENCLAVE.seal_account_key(account_key))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+        EnclaveCall::KeyImages(sealed_account_key, outputs) => {
+            serialize(&ENCLAVE.key_images(&sealed_account_key, outputs))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+        EnclaveCall::SignTx(sealed_account_key, tx) => {
+            serialize(&ENCLAVE.sign_tx(&sealed_account_key, tx))
+                .or(Err(sgx_status_t::SGX_ERROR_UNEXPECTED))?
+        }
+    };
+
+    Ok(outdata)
+}
+
+/// The entry point implementation for mobilecoind_enclave_api
+///
+/// See mobilecoind_enclave_api::mobileenclave() declaration for more information
+#[no_mangle]
+pub extern "C" fn mobileenclave_call(
+    inbuf: *const u8,
+    inbuf_len: usize,
+    outbuf: *mut u8,
+    outbuf_len: usize,
+    outbuf_used: *mut usize,
+    outbuf_retry_id: *mut u64,
+) -> sgx_status_t {
+    if inbuf.is_null()
+        || outbuf.is_null()
+        || outbuf_used.is_null()
+        || outbuf_retry_id.is_null()
+        || unsafe { sgx_is_outside_enclave(inbuf as *const c_void, inbuf_len) } != 1
+        || unsafe { sgx_is_outside_enclave(outbuf as *const c_void, outbuf_len) } != 1
+        || unsafe {
+            sgx_is_outside_enclave(outbuf_used as *const c_void, core::mem::size_of::<usize>())
+        } != 1
+        || unsafe {
+            sgx_is_outside_enclave(
+                outbuf_retry_id as *const c_void,
+                core::mem::size_of::<u64>(),
+            )
+        } != 1
+    {
+        return sgx_status_t::SGX_ERROR_INVALID_PARAMETER;
+    }
+
+    match catch_unwind(|| {
+        let mut temp_outbuf_used = unsafe { *outbuf_used };
+        let mut temp_outbuf_retry_id = unsafe { *outbuf_retry_id };
+        let res = RETRY_BUFFER.call(
+            unsafe { slice::from_raw_parts(inbuf, inbuf_len) },
+            unsafe { slice::from_raw_parts_mut(outbuf, outbuf_len) },
+            &mut temp_outbuf_used,
+            &mut temp_outbuf_retry_id,
+        );
+        unsafe {
+            *outbuf_used = temp_outbuf_used;
+            *outbuf_retry_id = temp_outbuf_retry_id;
+        }
+        res
+    }) {
+        Ok(x) => match x {
+            Ok(_) => sgx_status_t::SGX_SUCCESS,
+            Err(retval) => retval,
+        },
+        Err(_) => sgx_status_t::SGX_ERROR_ENCLAVE_CRASHED,
+    }
+}
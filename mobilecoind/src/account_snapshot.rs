@@ -0,0 +1,417 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Account snapshots.
+//!
+//! A snapshot holds the unspent outputs of a monitor as of a given block, signed by the
+//! mobilecoind instance that exported it. Importing it on another host creates the monitor already
+//! synced up to that block, instead of rescanning the ledger from the monitor's first block.
+//!
+//! The signature only establishes where a snapshot came from. Every output it holds is still
+//! checked against the ledger and the account key before it is imported.
+
+use crate::{
+    error::Error,
+    monitor_store::{MonitorData, MonitorId},
+    utxo_store::{UnspentTxOut, MAX_UTXO_MEMO_LENGTH},
+};
+use keys::{
+    Ed25519Pair, Ed25519Public, Ed25519Signature, RistrettoPublic, Signature, Signer, Verifier,
+};
+use ledger_db::{Ledger, LedgerDB};
+use mcserial::Message;
+use std::{convert::TryFrom, sync::Arc};
+use transaction::{
+    account_keys::AccountKey,
+    get_tx_out_shared_secret,
+    onetime_keys::{compute_key_image, recover_onetime_private_key},
+};
+
+/// The keys used to sign exported snapshots, and to check the signature of imported ones.
+#[derive(Clone, Default)]
+pub struct SnapshotSigners {
+    /// The key this instance signs the snapshots it exports with. Exporting is disabled without
+    /// one.
+    pub signer: Option<Arc<Ed25519Pair>>,
+
+    /// Keys of other instances whose snapshots are accepted. Snapshots signed by `signer` are
+    /// always accepted.
+    pub trusted_signers: Vec<Ed25519Public>,
+}
+
+impl SnapshotSigners {
+    /// Returns true if snapshots signed by `signer` may be imported.
+    pub fn is_trusted(&self, signer: &Ed25519Public) -> bool {
+        self.trusted_signers.contains(signer)
+            || self
+                .signer
+                .as_ref()
+                .map_or(false, |keypair| keypair.public_key() == *signer)
+    }
+}
+
+/// The state of a monitor as of a given block.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct AccountSnapshotContents {
+    /// The monitor the snapshot was taken of.
+    #[prost(message, required, tag = "1")]
+    pub monitor_id: MonitorId,
+
+    /// The smallest subaddress index in the range the monitor watches.
+    #[prost(uint64, tag = "2")]
+    pub first_subaddress: u64,
+
+    /// The number of subaddresses the monitor was asked to watch.
+    #[prost(uint64, tag = "3")]
+    pub num_subaddresses: u64,
+
+    /// The first block the monitor processed.
+    #[prost(uint64, tag = "4")]
+    pub first_block: u64,
+
+    /// The next block the monitor needs to process. The snapshot reflects every block before it.
+    #[prost(uint64, tag = "5")]
+    pub next_block: u64,
+
+    /// The monitor's gap limit.
+    #[prost(uint64, tag = "6")]
+    pub gap_limit: u64,
+
+    /// The number of subaddresses gap-limit scanning added past the requested range.
+    #[prost(uint64, tag = "7")]
+    pub num_extended_subaddresses: u64,
+
+    /// Id of the block preceding `next_block`, which ties the snapshot to a specific ledger.
+    /// Empty if no block was processed.
+    #[prost(bytes, tag = "8")]
+    pub last_block_id: Vec<u8>,
+
+    /// The monitor's unspent outputs as of `next_block`.
+    #[prost(message, repeated, tag = "9")]
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// Whether the monitor returns change in outputs of standard denominations.
+    #[prost(bool, tag = "10")]
+    pub denominate_change: bool,
+}
+
+impl AccountSnapshotContents {
+    /// Captures the state of a monitor. `utxos` are the monitor's unspent outputs.
+    pub fn new(
+        monitor_data: &MonitorData,
+        utxos: Vec<UnspentTxOut>,
+        ledger_db: &LedgerDB,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            monitor_id: MonitorId::from(monitor_data),
+            first_subaddress: monitor_data.first_subaddress,
+            num_subaddresses: monitor_data.num_subaddresses,
+            first_block: monitor_data.first_block,
+            next_block: monitor_data.next_block,
+            gap_limit: monitor_data.gap_limit,
+            num_extended_subaddresses: monitor_data.num_extended_subaddresses,
+            last_block_id: last_block_id(ledger_db, monitor_data.next_block)?,
+            utxos,
+            denominate_change: monitor_data.denominate_change,
+        })
+    }
+
+    /// Recreates the monitor the snapshot was taken of. Fails if the snapshot was not taken of a
+    /// monitor of `account_key`.
+    pub fn monitor_data(&self, account_key: AccountKey) -> Result<MonitorData, Error> {
+        let mut monitor_data = MonitorData::new(
+            account_key,
+            self.first_subaddress,
+            self.num_subaddresses,
+            self.first_block,
+        )?;
+        monitor_data.next_block = self.next_block;
+        monitor_data.gap_limit = self.gap_limit;
+        monitor_data.num_extended_subaddresses = self.num_extended_subaddresses;
+        monitor_data.denominate_change = self.denominate_change;
+
+        if MonitorId::from(&monitor_data) != self.monitor_id {
+            return Err(Error::AccountSnapshot(
+                "Snapshot was taken of a different account".to_string(),
+            ));
+        }
+        Ok(monitor_data)
+    }
+
+    /// Checks the snapshot against the local ledger:
+    /// * The ledger contains the blocks the snapshot reflects, with matching block ids.
+    /// * Every output is in the ledger and belongs to the account, and its key image and value
+    ///   are the ones derived with the account key.
+    /// * No output was spent before `next_block`. Outputs spent since are removed by the regular
+    ///   sync once it processes the spending block.
+    pub fn verify_against_ledger(
+        &self,
+        monitor_data: &MonitorData,
+        ledger_db: &LedgerDB,
+    ) -> Result<(), Error> {
+        if self.next_block < self.first_block {
+            return Err(Error::AccountSnapshot(
+                "Snapshot ends before its first block".to_string(),
+            ));
+        }
+        if self.next_block > ledger_db.num_blocks()? {
+            return Err(Error::AccountSnapshot(format!(
+                "Ledger has not reached block {} yet",
+                self.next_block
+            )));
+        }
+        if last_block_id(ledger_db, self.next_block)? != self.last_block_id {
+            return Err(Error::AccountSnapshot(format!(
+                "Block {} does not match the ledger",
+                self.next_block - 1
+            )));
+        }
+
+        let account_key = monitor_data.account_key()?;
+        let subaddress_indexes = monitor_data.subaddress_indexes();
+        for utxo in &self.utxos {
+            let invalid_utxo = |reason: &str| {
+                Error::AccountSnapshot(format!(
+                    "Invalid output {}: {}",
+                    hex::encode(utxo.tx_out.public_key.as_bytes()),
+                    reason
+                ))
+            };
+
+            if !subaddress_indexes.contains(&utxo.subaddress_index) {
+                return Err(invalid_utxo("subaddress is not watched by the monitor"));
+            }
+            if utxo.memo.len() > MAX_UTXO_MEMO_LENGTH {
+                return Err(invalid_utxo("memo is too long"));
+            }
+
+            let tx_out_index = match ledger_db.get_tx_out_index_by_hash(&utxo.tx_out.hash()) {
+                Ok(tx_out_index) => tx_out_index,
+                Err(ledger_db::Error::NotFound) => return Err(invalid_utxo("not in the ledger")),
+                Err(err) => return Err(err.into()),
+            };
+            if ledger_db.get_tx_out_by_index(tx_out_index)? != utxo.tx_out {
+                return Err(invalid_utxo("does not match the ledger"));
+            }
+
+            let tx_public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key)?;
+            let onetime_private_key = recover_onetime_private_key(
+                &tx_public_key,
+                account_key.view_private_key(),
+                &account_key.subaddress_spend_key(utxo.subaddress_index),
+            );
+            if RistrettoPublic::from(&onetime_private_key)
+                != RistrettoPublic::try_from(&utxo.tx_out.target_key)?
+            {
+                return Err(invalid_utxo("not sent to the subaddress"));
+            }
+            if compute_key_image(&onetime_private_key) != utxo.key_image {
+                return Err(invalid_utxo("key image mismatch"));
+            }
+
+            let shared_secret =
+                get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+            match utxo.tx_out.amount.get_value(&shared_secret) {
+                Ok((value, _blinding)) if value == utxo.value => {}
+                _ => return Err(invalid_utxo("value mismatch")),
+            }
+
+            if let Some(spent_block) = ledger_db.check_key_image(&utxo.key_image)? {
+                if spent_block < self.next_block {
+                    return Err(invalid_utxo(&format!("spent in block {}", spent_block)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A snapshot, together with a signature over it.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct AccountSnapshot {
+    /// The encoded `AccountSnapshotContents`. This is kept as bytes so that the signature covers
+    /// its exact bytes.
+    #[prost(bytes, tag = "1")]
+    pub contents: Vec<u8>,
+
+    /// Ed25519 signature over `contents`.
+    #[prost(bytes, tag = "2")]
+    pub signature: Vec<u8>,
+
+    /// The public key of the keypair used to generate the signature.
+    #[prost(bytes, tag = "3")]
+    pub signer: Vec<u8>,
+}
+
+impl AccountSnapshot {
+    /// Serializes and signs a snapshot.
+    pub fn sign(contents: &AccountSnapshotContents, signer: &Ed25519Pair) -> Result<Self, Error> {
+        let contents = mcserial::encode(contents);
+        let signature: Ed25519Signature = signer
+            .try_sign(&contents)
+            .map_err(|err| Error::AccountSnapshot(format!("Signing snapshot: {}", err)))?;
+
+        Ok(Self {
+            contents,
+            signature: signature.as_bytes().to_vec(),
+            signer: signer.public_key().as_ref().to_vec(),
+        })
+    }
+
+    /// Verifies the signature and returns the snapshot contents.
+    pub fn verify(&self, signers: &SnapshotSigners) -> Result<AccountSnapshotContents, Error> {
+        let signer = Ed25519Public::try_from(&self.signer[..])
+            .map_err(|err| Error::AccountSnapshot(format!("Invalid signer: {}", err)))?;
+        if !signers.is_trusted(&signer) {
+            return Err(Error::AccountSnapshot(format!(
+                "Snapshot signed by untrusted key {}",
+                hex::encode(&self.signer)
+            )));
+        }
+
+        let signature = Ed25519Signature::from_bytes(&self.signature)
+            .map_err(|err| Error::AccountSnapshot(format!("Invalid signature: {}", err)))?;
+        signer
+            .verify(&self.contents, &signature)
+            .map_err(|_| Error::AccountSnapshot("Snapshot signature mismatch".to_string()))?;
+
+        Ok(mcserial::decode(&self.contents)?)
+    }
+}
+
+/// Id of the block preceding `next_block`, or an empty vector if `next_block` is the origin block.
+pub(crate) fn last_block_id(ledger_db: &LedgerDB, next_block: u64) -> Result<Vec<u8>, Error> {
+    if next_block == 0 {
+        return Ok(Vec::new());
+    }
+    Ok(ledger_db.get_block(next_block - 1)?.id.as_ref().to_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        sync::SyncThread,
+        test_utils::{get_test_databases, wait_for_monitors},
+    };
+    use common::logger::{test_with_logger, Logger};
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::DEFAULT_SUBADDRESS_INDEX;
+
+    #[test_with_logger]
+    // A snapshot exported by one instance should be importable by another that trusts it, and
+    // the imported monitor should hold the same outputs without rescanning the ledger.
+    fn test_export_and_import_snapshot(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([42u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let signer = Arc::new(Ed25519Pair::from_random(&mut rng));
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger.clone(),
+            &mut rng,
+        );
+        let monitor_data =
+            MonitorData::new(account_key.clone(), DEFAULT_SUBADDRESS_INDEX, 1, 0).unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let (synced_data, utxos) = mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap();
+        assert!(!utxos.is_empty());
+        let contents =
+            AccountSnapshotContents::new(&synced_data, utxos.clone(), &ledger_db).unwrap();
+        let snapshot = AccountSnapshot::sign(&contents, &signer).unwrap();
+
+        // Another instance, which only trusts the signer's public key.
+        let signers = SnapshotSigners {
+            signer: None,
+            trusted_signers: vec![signer.public_key()],
+        };
+        let imported = snapshot.verify(&signers).unwrap();
+        let imported_data = imported.monitor_data(account_key.clone()).unwrap();
+        imported
+            .verify_against_ledger(&imported_data, &ledger_db)
+            .unwrap();
+        assert_eq!(imported_data, synced_data);
+        assert_eq!(imported.utxos, utxos);
+
+        // An instance that does not trust the signer refuses the snapshot.
+        assert!(snapshot.verify(&SnapshotSigners::default()).is_err());
+
+        // The snapshot cannot be used to restore another account.
+        assert!(imported.monitor_data(AccountKey::random(&mut rng)).is_err());
+    }
+
+    #[test_with_logger]
+    // Outputs that do not check out against the ledger should be refused, even in a snapshot
+    // with a valid signature.
+    fn test_verify_against_ledger_rejects_bad_outputs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([43u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger.clone(),
+            &mut rng,
+        );
+        let monitor_data =
+            MonitorData::new(account_key.clone(), DEFAULT_SUBADDRESS_INDEX, 1, 0).unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let (synced_data, utxos) = mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap();
+        let contents = AccountSnapshotContents::new(&synced_data, utxos, &ledger_db).unwrap();
+        assert!(contents
+            .verify_against_ledger(&synced_data, &ledger_db)
+            .is_ok());
+
+        // An inflated value.
+        let mut tampered = contents.clone();
+        tampered.utxos[0].value += 1;
+        assert!(tampered
+            .verify_against_ledger(&synced_data, &ledger_db)
+            .is_err());
+
+        // A key image that does not belong to the output.
+        let mut tampered = contents.clone();
+        tampered.utxos[0].key_image = tampered.utxos[1].key_image;
+        assert!(tampered
+            .verify_against_ledger(&synced_data, &ledger_db)
+            .is_err());
+
+        // A block id from another ledger.
+        let mut tampered = contents.clone();
+        tampered.last_block_id[0] ^= 1;
+        assert!(tampered
+            .verify_against_ledger(&synced_data, &ledger_db)
+            .is_err());
+
+        // Blocks the ledger does not have yet.
+        let mut tampered = contents;
+        tampered.next_block += 1;
+        assert!(tampered
+            .verify_against_ledger(&synced_data, &ledger_db)
+            .is_err());
+    }
+}
@@ -0,0 +1,313 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for named accounts.
+//! * An account groups the monitors of one wallet under a name chosen by the client, so that a
+//!   single mobilecoind can host many wallets and tell them apart.
+//! * Accounts are keyed by name. Removing an account removes its monitors too.
+
+use crate::{error::Error, monitor_store::MonitorId};
+
+use common::logger::{log, Logger};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// LMDB Database Names
+pub const ACCOUNT_NAME_TO_ACCOUNT_DATA_DB_NAME: &str =
+    "mobilecoind_db:account_store:account_name_to_account_data";
+
+/// The maximal length of an account name, in bytes.
+pub const MAX_ACCOUNT_NAME_LEN: usize = 64;
+
+/// A named account and the monitors that belong to it.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct AccountData {
+    /// The name of the account, unique in this database.
+    #[prost(string, tag = "1")]
+    pub name: String,
+
+    /// The monitors of the account, in the order they were added.
+    #[prost(message, repeated, tag = "2")]
+    pub monitor_ids: Vec<MonitorId>,
+
+    /// When the account was created, in seconds since the UNIX epoch.
+    #[prost(uint64, tag = "3")]
+    pub created_at: u64,
+
+    /// The tenant that created the account. Empty unless mobilecoind is shared by tenants.
+    #[prost(string, tag = "4")]
+    pub tenant: String,
+}
+
+impl AccountData {
+    /// Create an account without monitors, created now.
+    pub fn new(name: &str) -> Result<Self, Error> {
+        if name.is_empty() || name.len() > MAX_ACCOUNT_NAME_LEN {
+            return Err(Error::InvalidArgument(
+                "name".to_string(),
+                format!("must be 1 to {} bytes long", MAX_ACCOUNT_NAME_LEN),
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            monitor_ids: Vec::new(),
+            created_at: now(),
+            tenant: String::new(),
+        })
+    }
+}
+
+/// The named accounts database.
+#[derive(Clone)]
+pub struct AccountStore {
+    env: Arc<Environment>,
+
+    /// Mapping of account name -> AccountData.
+    account_name_to_account_data: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl AccountStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let account_name_to_account_data = env.create_db(
+            Some(ACCOUNT_NAME_TO_ACCOUNT_DATA_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            account_name_to_account_data,
+            logger,
+        })
+    }
+
+    /// Add a new account. Fails if an account with the same name exists.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        data: &AccountData,
+    ) -> Result<(), Error> {
+        match db_txn.put(
+            self.account_name_to_account_data,
+            &data.name,
+            &mcserial::encode(data),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::AccountNameExists),
+            Err(err) => Err(err.into()),
+        }?;
+
+        log::info!(self.logger, "Added account {}", data.name);
+        Ok(())
+    }
+
+    /// Get an account by name.
+    pub fn get(&self, db_txn: &impl Transaction, name: &str) -> Result<AccountData, Error> {
+        match db_txn.get(self.account_name_to_account_data, &name) {
+            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::AccountNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get all accounts, ordered by name.
+    pub fn list(&self, db_txn: &impl Transaction) -> Result<Vec<AccountData>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.account_name_to_account_data)?;
+        let mut results = Vec::new();
+        for (_key_bytes, value_bytes) in cursor.iter_start() {
+            results.push(mcserial::decode(value_bytes)?);
+        }
+        Ok(results)
+    }
+
+    /// Remove an account, returning its data so that its monitors can be removed as well.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        name: &str,
+    ) -> Result<AccountData, Error> {
+        let data = self.get(db_txn, name)?;
+        db_txn.del(self.account_name_to_account_data, &name, None)?;
+
+        log::info!(self.logger, "Removed account {}", name);
+        Ok(data)
+    }
+
+    /// Add a monitor to an account. Adding a monitor that already belongs to the account does
+    /// nothing.
+    pub fn add_monitor<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        name: &str,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        let mut data = self.get(db_txn, name)?;
+        if data.monitor_ids.contains(monitor_id) {
+            return Ok(());
+        }
+
+        data.monitor_ids.push(*monitor_id);
+        self.put(db_txn, &data)?;
+
+        log::info!(
+            self.logger,
+            "Added monitor {} to account {}",
+            monitor_id,
+            name
+        );
+        Ok(())
+    }
+
+    /// Remove a monitor from every account it belongs to.
+    pub fn remove_monitor<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        for mut data in self.list(db_txn)? {
+            if !data.monitor_ids.contains(monitor_id) {
+                continue;
+            }
+
+            data.monitor_ids.retain(|id| id != monitor_id);
+            self.put(db_txn, &data)?;
+        }
+        Ok(())
+    }
+
+    fn put<'env>(&self, db_txn: &mut RwTransaction<'env>, data: &AccountData) -> Result<(), Error> {
+        db_txn.put(
+            self.account_name_to_account_data,
+            &data.name,
+            &mcserial::encode(data),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+}
+
+/// The current time, in seconds since the UNIX epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn setup_test_account_store(logger: &Logger) -> (Arc<Environment>, AccountStore) {
+        let db_tmp = TempDir::new("account_store_db")
+            .expect("Could not make tempdir for account store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = AccountStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_account_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([5u8; 32]);
+        let (env, store) = setup_test_account_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        // Names must be unique.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store
+            .insert(&mut db_txn, &AccountData::new("savings").unwrap())
+            .unwrap();
+        store
+            .insert(&mut db_txn, &AccountData::new("checking").unwrap())
+            .unwrap();
+        match store.insert(&mut db_txn, &AccountData::new("savings").unwrap()) {
+            Err(Error::AccountNameExists) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Monitors are added once, and only to existing accounts.
+        store
+            .add_monitor(&mut db_txn, "savings", &monitor_id0)
+            .unwrap();
+        store
+            .add_monitor(&mut db_txn, "savings", &monitor_id1)
+            .unwrap();
+        store
+            .add_monitor(&mut db_txn, "savings", &monitor_id0)
+            .unwrap();
+        store
+            .add_monitor(&mut db_txn, "checking", &monitor_id1)
+            .unwrap();
+        match store.add_monitor(&mut db_txn, "spending", &monitor_id0) {
+            Err(Error::AccountNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        db_txn.commit().unwrap();
+
+        // Accounts are listed by name.
+        let db_txn = env.begin_ro_txn().unwrap();
+        let accounts = store.list(&db_txn).unwrap();
+        assert_eq!(
+            accounts
+                .iter()
+                .map(|data| data.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["checking", "savings"]
+        );
+        assert_eq!(accounts[1].monitor_ids, vec![monitor_id0, monitor_id1]);
+        drop(db_txn);
+
+        // Removing a monitor removes it from every account.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.remove_monitor(&mut db_txn, &monitor_id1).unwrap();
+        assert_eq!(
+            store.get(&db_txn, "savings").unwrap().monitor_ids,
+            vec![monitor_id0]
+        );
+        assert!(store
+            .get(&db_txn, "checking")
+            .unwrap()
+            .monitor_ids
+            .is_empty());
+
+        // Removing an account returns its data.
+        let removed = store.remove(&mut db_txn, "savings").unwrap();
+        assert_eq!(removed.monitor_ids, vec![monitor_id0]);
+        match store.get(&db_txn, "savings") {
+            Err(Error::AccountNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match store.remove(&mut db_txn, "savings") {
+            Err(Error::AccountNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_account_name_validation() {
+        assert!(AccountData::new("").is_err());
+        assert!(AccountData::new(&"a".repeat(MAX_ACCOUNT_NAME_LEN)).is_ok());
+        assert!(AccountData::new(&"a".repeat(MAX_ACCOUNT_NAME_LEN + 1)).is_err());
+    }
+}
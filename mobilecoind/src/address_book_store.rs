@@ -0,0 +1,308 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for the address book.
+//! * The address book holds the public addresses a client pays regularly, under a label chosen
+//!   by the client, so that payments can refer to recipients by label.
+//! * Entries are keyed by label.
+
+use crate::error::Error;
+
+use common::logger::{log, Logger};
+use keys::RistrettoPublic;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+use transaction::account_keys::PublicAddress;
+
+// LMDB Database Names
+pub const LABEL_TO_ADDRESS_BOOK_ENTRY_DB_NAME: &str =
+    "mobilecoind_db:address_book_store:label_to_address_book_entry";
+
+/// The maximal length of an address book label, in bytes.
+pub const MAX_ADDRESS_BOOK_LABEL_LEN: usize = 64;
+
+/// The maximal length of the notes of an address book entry, in bytes.
+pub const MAX_ADDRESS_BOOK_NOTES_LEN: usize = 1024;
+
+/// A labeled public address.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct AddressBookEntry {
+    /// The label of the entry, unique in this database.
+    #[prost(string, tag = "1")]
+    pub label: String,
+
+    /// The view public key of the address.
+    #[prost(message, required, tag = "2")]
+    pub view_public_key: RistrettoPublic,
+
+    /// The spend public key of the address.
+    #[prost(message, required, tag = "3")]
+    pub spend_public_key: RistrettoPublic,
+
+    /// The fog url of the address. Empty if the address has no fog service.
+    #[prost(string, tag = "4")]
+    pub fog_url: String,
+
+    /// Free-form notes about the recipient.
+    #[prost(string, tag = "5")]
+    pub notes: String,
+}
+
+impl AddressBookEntry {
+    pub fn new(label: &str, public_address: &PublicAddress, notes: &str) -> Result<Self, Error> {
+        if label.is_empty() || label.len() > MAX_ADDRESS_BOOK_LABEL_LEN {
+            return Err(Error::InvalidArgument(
+                "label".to_string(),
+                format!("must be 1 to {} bytes long", MAX_ADDRESS_BOOK_LABEL_LEN),
+            ));
+        }
+
+        if notes.len() > MAX_ADDRESS_BOOK_NOTES_LEN {
+            return Err(Error::InvalidArgument(
+                "notes".to_string(),
+                format!("must be at most {} bytes long", MAX_ADDRESS_BOOK_NOTES_LEN),
+            ));
+        }
+
+        Ok(Self {
+            label: label.to_string(),
+            view_public_key: *public_address.view_public_key(),
+            spend_public_key: *public_address.spend_public_key(),
+            fog_url: public_address.fog_url().unwrap_or("").to_string(),
+            notes: notes.to_string(),
+        })
+    }
+
+    /// The public address of the entry.
+    pub fn public_address(&self) -> PublicAddress {
+        if self.fog_url.is_empty() {
+            PublicAddress::new(&self.spend_public_key, &self.view_public_key)
+        } else {
+            PublicAddress::new_with_fog(
+                &self.spend_public_key,
+                &self.view_public_key,
+                &self.fog_url,
+            )
+        }
+    }
+}
+
+/// The address book database.
+#[derive(Clone)]
+pub struct AddressBookStore {
+    env: Arc<Environment>,
+
+    /// Mapping of label -> AddressBookEntry.
+    label_to_address_book_entry: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl AddressBookStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let label_to_address_book_entry = env.create_db(
+            Some(LABEL_TO_ADDRESS_BOOK_ENTRY_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            label_to_address_book_entry,
+            logger,
+        })
+    }
+
+    /// Add a new entry. Fails if an entry with the same label exists.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        entry: &AddressBookEntry,
+    ) -> Result<(), Error> {
+        match db_txn.put(
+            self.label_to_address_book_entry,
+            &entry.label,
+            &mcserial::encode(entry),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::AddressBookLabelExists),
+            Err(err) => Err(err.into()),
+        }?;
+
+        log::info!(self.logger, "Added address book entry {}", entry.label);
+        Ok(())
+    }
+
+    /// Replace an existing entry with one of the same label.
+    pub fn update<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        entry: &AddressBookEntry,
+    ) -> Result<(), Error> {
+        // Make sure the entry exists, so that updates never create entries.
+        self.get(db_txn, &entry.label)?;
+
+        db_txn.put(
+            self.label_to_address_book_entry,
+            &entry.label,
+            &mcserial::encode(entry),
+            WriteFlags::empty(),
+        )?;
+
+        log::info!(self.logger, "Updated address book entry {}", entry.label);
+        Ok(())
+    }
+
+    /// Get an entry by label.
+    pub fn get(&self, db_txn: &impl Transaction, label: &str) -> Result<AddressBookEntry, Error> {
+        match db_txn.get(self.label_to_address_book_entry, &label) {
+            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::AddressBookEntryNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get all entries, ordered by label.
+    pub fn list(&self, db_txn: &impl Transaction) -> Result<Vec<AddressBookEntry>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.label_to_address_book_entry)?;
+        let mut results = Vec::new();
+        for (_key_bytes, value_bytes) in cursor.iter_start() {
+            results.push(mcserial::decode(value_bytes)?);
+        }
+        Ok(results)
+    }
+
+    /// Remove an entry by label.
+    pub fn remove<'env>(&self, db_txn: &mut RwTransaction<'env>, label: &str) -> Result<(), Error> {
+        match db_txn.del(self.label_to_address_book_entry, &label, None) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::NotFound) => Err(Error::AddressBookEntryNotFound),
+            Err(err) => Err(err.into()),
+        }?;
+
+        log::info!(self.logger, "Removed address book entry {}", label);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::account_keys::AccountKey;
+
+    fn setup_test_address_book_store(logger: &Logger) -> (Arc<Environment>, AddressBookStore) {
+        let db_tmp = TempDir::new("address_book_store_db")
+            .expect("Could not make tempdir for address book store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = AddressBookStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_address_book_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([6u8; 32]);
+        let (env, store) = setup_test_address_book_store(&logger);
+        let alice = AccountKey::random(&mut rng).default_subaddress();
+        let bob = AccountKey::random_with_fog(&mut rng).default_subaddress();
+
+        // Labels must be unique.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store
+            .insert(
+                &mut db_txn,
+                &AddressBookEntry::new("alice", &alice, "rent").unwrap(),
+            )
+            .unwrap();
+        store
+            .insert(
+                &mut db_txn,
+                &AddressBookEntry::new("bob", &bob, "").unwrap(),
+            )
+            .unwrap();
+        match store.insert(
+            &mut db_txn,
+            &AddressBookEntry::new("alice", &bob, "").unwrap(),
+        ) {
+            Err(Error::AddressBookLabelExists) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        db_txn.commit().unwrap();
+
+        // Entries are listed by label, and keep their addresses, fog included.
+        let db_txn = env.begin_ro_txn().unwrap();
+        let entries = store.list(&db_txn).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.label.as_str())
+                .collect::<Vec<_>>(),
+            vec!["alice", "bob"]
+        );
+        assert_eq!(entries[0].public_address(), alice);
+        assert_eq!(entries[0].notes, "rent");
+        assert_eq!(entries[1].public_address(), bob);
+        drop(db_txn);
+
+        // Only existing entries can be updated.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store
+            .update(
+                &mut db_txn,
+                &AddressBookEntry::new("alice", &bob, "moved").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(store.get(&db_txn, "alice").unwrap().public_address(), bob);
+        match store.update(
+            &mut db_txn,
+            &AddressBookEntry::new("carol", &bob, "").unwrap(),
+        ) {
+            Err(Error::AddressBookEntryNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Removed entries are gone.
+        store.remove(&mut db_txn, "alice").unwrap();
+        match store.get(&db_txn, "alice") {
+            Err(Error::AddressBookEntryNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match store.remove(&mut db_txn, "alice") {
+            Err(Error::AddressBookEntryNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_address_book_entry_validation() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let address = AccountKey::random(&mut rng).default_subaddress();
+
+        assert!(AddressBookEntry::new("", &address, "").is_err());
+        assert!(
+            AddressBookEntry::new(&"a".repeat(MAX_ADDRESS_BOOK_LABEL_LEN), &address, "").is_ok()
+        );
+        assert!(
+            AddressBookEntry::new(&"a".repeat(MAX_ADDRESS_BOOK_LABEL_LEN + 1), &address, "")
+                .is_err()
+        );
+        assert!(AddressBookEntry::new(
+            "alice",
+            &address,
+            &"a".repeat(MAX_ADDRESS_BOOK_NOTES_LEN + 1)
+        )
+        .is_err());
+    }
+}
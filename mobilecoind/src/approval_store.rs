@@ -0,0 +1,426 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for transaction approvals.
+//! * When an approval threshold is configured, GenerateTx records every transaction sending more
+//!   than the threshold as pending approval, keyed by its prefix hash.
+//! * A different API identity than the one that generated the transaction must approve it before
+//!   SubmitTx accepts it. Entries are kept after they are decided, so that the decision and who
+//!   made it remain queryable.
+
+use crate::{
+    error::Error,
+    monitor_store::MonitorId,
+    payments::{Outlay, TxProposal, UnsignedTxProposal},
+};
+
+use common::logger::{log, Logger};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use transaction::{mob::PicoMob, tx::TxPrefix};
+
+// LMDB Database Names
+pub const TX_PREFIX_HASH_TO_TX_APPROVAL_DB_NAME: &str =
+    "mobilecoind_db:approval_store:tx_prefix_hash_to_tx_approval";
+
+/// The status of a transaction awaiting approval.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum TxApprovalStatus {
+    /// Not set. This is never stored in the database.
+    Unknown = 0,
+
+    /// The transaction was generated and is waiting for a decision.
+    Pending = 1,
+
+    /// The transaction was approved and may be submitted.
+    Approved = 2,
+
+    /// The transaction was rejected and may not be submitted.
+    Rejected = 3,
+
+    /// The approved transaction was submitted to the network.
+    Submitted = 4,
+}
+
+/// A transaction that needs to be approved before it is submitted.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct TxApproval {
+    /// Hash of the transaction prefix.
+    #[prost(bytes, tag = "1")]
+    pub tx_prefix_hash: Vec<u8>,
+
+    /// The monitor that is spending funds in this transaction.
+    #[prost(message, required, tag = "2")]
+    pub monitor_id: MonitorId,
+
+    /// Total value sent by the transaction, excluding change and fee.
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+
+    /// The transaction fee.
+    #[prost(uint64, tag = "4")]
+    pub fee: u64,
+
+    /// Current status of the approval.
+    #[prost(enumeration = "TxApprovalStatus", tag = "5")]
+    pub status: i32,
+
+    /// Who generated the transaction, e.g. a fingerprint of the API token used.
+    #[prost(string, tag = "6")]
+    pub requested_by: String,
+
+    /// When the transaction was generated, in seconds since the UNIX epoch.
+    #[prost(uint64, tag = "7")]
+    pub requested_at: u64,
+
+    /// Who approved or rejected the transaction, or an empty string if it is still pending.
+    #[prost(string, tag = "8")]
+    pub decided_by: String,
+
+    /// When the transaction was approved or rejected, in seconds since the UNIX epoch, or zero if
+    /// it is still pending.
+    #[prost(uint64, tag = "9")]
+    pub decided_at: u64,
+}
+
+impl TxApproval {
+    /// Create a pending approval for a transaction proposal, requested now.
+    pub fn new(monitor_id: &MonitorId, tx_proposal: &TxProposal, requested_by: &str) -> Self {
+        Self::with_prefix(
+            monitor_id,
+            &tx_proposal.tx.prefix,
+            &tx_proposal.outlays,
+            requested_by,
+        )
+    }
+
+    /// Create a pending approval for an unsigned transaction proposal, requested now. Signing
+    /// does not change the prefix, so the approval also applies to the signed proposal.
+    pub fn new_unsigned(
+        monitor_id: &MonitorId,
+        unsigned_tx_proposal: &UnsignedTxProposal,
+        requested_by: &str,
+    ) -> Self {
+        Self::with_prefix(
+            monitor_id,
+            &unsigned_tx_proposal.unsigned_tx.prefix,
+            &unsigned_tx_proposal.outlays,
+            requested_by,
+        )
+    }
+
+    fn with_prefix(
+        monitor_id: &MonitorId,
+        tx_prefix: &TxPrefix,
+        outlays: &[Outlay],
+        requested_by: &str,
+    ) -> Self {
+        Self {
+            tx_prefix_hash: tx_prefix.hash().to_vec(),
+            monitor_id: *monitor_id,
+            value: outlays
+                .iter()
+                .fold(0u64, |sum, outlay| sum.saturating_add(outlay.value)),
+            fee: tx_prefix.fee,
+            status: TxApprovalStatus::Pending as i32,
+            requested_by: requested_by.to_string(),
+            requested_at: now(),
+            decided_by: String::new(),
+            decided_at: 0,
+        }
+    }
+
+    /// Returns the status of this approval.
+    pub fn get_status(&self) -> TxApprovalStatus {
+        TxApprovalStatus::from_i32(self.status).unwrap_or(TxApprovalStatus::Unknown)
+    }
+
+    /// Sets the status of this approval.
+    pub fn set_status(&mut self, status: TxApprovalStatus) {
+        self.status = status as i32;
+    }
+
+    /// Approve the transaction. The approver must not be the identity that generated it.
+    pub fn approve(&mut self, approver: &str) -> Result<(), Error> {
+        if approver == self.requested_by {
+            return Err(Error::ApprovalNotPermitted(
+                "a transaction cannot be approved by the identity that generated it".to_owned(),
+            ));
+        }
+        self.decide(TxApprovalStatus::Approved, approver)
+    }
+
+    /// Reject the transaction. Any identity may reject it, including the one that generated it.
+    pub fn reject(&mut self, rejecter: &str) -> Result<(), Error> {
+        self.decide(TxApprovalStatus::Rejected, rejecter)
+    }
+
+    fn decide(&mut self, status: TxApprovalStatus, decided_by: &str) -> Result<(), Error> {
+        if self.get_status() != TxApprovalStatus::Pending {
+            return Err(Error::ApprovalNotPermitted(format!(
+                "the transaction is not pending approval but {:?}",
+                self.get_status()
+            )));
+        }
+
+        self.set_status(status);
+        self.decided_by = decided_by.to_string();
+        self.decided_at = now();
+        Ok(())
+    }
+}
+
+/// The transaction approvals database.
+#[derive(Clone)]
+pub struct ApprovalStore {
+    env: Arc<Environment>,
+
+    /// Mapping of tx prefix hash -> TxApproval.
+    tx_prefix_hash_to_tx_approval: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl ApprovalStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let tx_prefix_hash_to_tx_approval = env.create_db(
+            Some(TX_PREFIX_HASH_TO_TX_APPROVAL_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            tx_prefix_hash_to_tx_approval,
+            logger,
+        })
+    }
+
+    /// Record a new approval.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        tx_approval: &TxApproval,
+    ) -> Result<(), Error> {
+        match db_txn.put(
+            self.tx_prefix_hash_to_tx_approval,
+            &tx_approval.tx_prefix_hash,
+            &mcserial::encode(tx_approval),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::DuplicateTxApproval),
+            Err(err) => Err(err.into()),
+        }?;
+
+        log::info!(
+            self.logger,
+            "Tx {} of monitor {} sending {} requires approval, requested by {}",
+            hex::encode(&tx_approval.tx_prefix_hash),
+            tx_approval.monitor_id,
+            PicoMob::new(tx_approval.value),
+            tx_approval.requested_by,
+        );
+        Ok(())
+    }
+
+    /// Store an updated approval.
+    pub fn update<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        tx_approval: &TxApproval,
+    ) -> Result<(), Error> {
+        // Make sure the approval exists.
+        self.get(db_txn, &tx_approval.tx_prefix_hash)?;
+
+        db_txn.put(
+            self.tx_prefix_hash_to_tx_approval,
+            &tx_approval.tx_prefix_hash,
+            &mcserial::encode(tx_approval),
+            WriteFlags::empty(),
+        )?;
+
+        log::info!(
+            self.logger,
+            "Tx {} of monitor {} is now {:?}",
+            hex::encode(&tx_approval.tx_prefix_hash),
+            tx_approval.monitor_id,
+            tx_approval.get_status(),
+        );
+        Ok(())
+    }
+
+    /// Get the approval of a transaction by the hash of its prefix.
+    pub fn get(
+        &self,
+        db_txn: &impl Transaction,
+        tx_prefix_hash: &[u8],
+    ) -> Result<TxApproval, Error> {
+        match db_txn.get(self.tx_prefix_hash_to_tx_approval, &tx_prefix_hash) {
+            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::TxApprovalNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get approvals, ordered by tx prefix hash.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - If provided, only approvals of this monitor are returned.
+    /// * `status` - If provided, only approvals with this status are returned.
+    pub fn list(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: Option<&MonitorId>,
+        status: Option<TxApprovalStatus>,
+    ) -> Result<Vec<TxApproval>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.tx_prefix_hash_to_tx_approval)?;
+        let mut results = Vec::new();
+        for (_key_bytes, value_bytes) in cursor.iter_start() {
+            let tx_approval: TxApproval = mcserial::decode(value_bytes)?;
+            if monitor_id.map_or(true, |monitor_id| tx_approval.monitor_id == *monitor_id)
+                && status.map_or(true, |status| tx_approval.get_status() == status)
+            {
+                results.push(tx_approval);
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// The current time, in seconds since the UNIX epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn setup_test_approval_store(logger: &Logger) -> (Arc<Environment>, ApprovalStore) {
+        let db_tmp = TempDir::new("approval_store_db")
+            .expect("Could not make tempdir for approval store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = ApprovalStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    fn test_tx_approval(monitor_id: &MonitorId, tx_prefix_hash: u8) -> TxApproval {
+        TxApproval {
+            tx_prefix_hash: vec![tx_prefix_hash; 32],
+            monitor_id: *monitor_id,
+            value: 1000,
+            fee: 10,
+            status: TxApprovalStatus::Pending as i32,
+            requested_by: "alice".to_owned(),
+            requested_at: now(),
+            decided_by: String::new(),
+            decided_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_approve_and_reject() {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        // The identity that generated a transaction cannot approve it.
+        let mut tx_approval = test_tx_approval(&monitor_id, 1);
+        assert!(tx_approval.approve("alice").is_err());
+        assert_eq!(tx_approval.get_status(), TxApprovalStatus::Pending);
+
+        tx_approval.approve("bob").unwrap();
+        assert_eq!(tx_approval.get_status(), TxApprovalStatus::Approved);
+        assert_eq!(tx_approval.decided_by, "bob");
+
+        // Decisions are final.
+        assert!(tx_approval.reject("carol").is_err());
+        assert_eq!(tx_approval.get_status(), TxApprovalStatus::Approved);
+
+        // The identity that generated a transaction may reject it.
+        let mut tx_approval = test_tx_approval(&monitor_id, 2);
+        tx_approval.reject("alice").unwrap();
+        assert_eq!(tx_approval.get_status(), TxApprovalStatus::Rejected);
+        assert!(tx_approval.approve("bob").is_err());
+    }
+
+    #[test_with_logger]
+    fn test_insert_update_and_list(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let (env, store) = setup_test_approval_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let tx_approvals = vec![
+            test_tx_approval(&monitor_id0, 1),
+            test_tx_approval(&monitor_id1, 2),
+            test_tx_approval(&monitor_id0, 3),
+        ];
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for tx_approval in tx_approvals.iter() {
+                store.insert(&mut db_txn, tx_approval).unwrap();
+            }
+
+            // Approvals cannot be recorded twice.
+            match store.insert(&mut db_txn, &tx_approvals[0]) {
+                Err(Error::DuplicateTxApproval) => {}
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            let mut approved = tx_approvals[2].clone();
+            approved.approve("bob").unwrap();
+            store.update(&mut db_txn, &approved).unwrap();
+
+            // Only existing approvals can be updated.
+            match store.update(&mut db_txn, &test_tx_approval(&monitor_id0, 4)) {
+                Err(Error::TxApprovalNotFound) => {}
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(
+            store.get(&db_txn, &[3u8; 32]).unwrap().get_status(),
+            TxApprovalStatus::Approved
+        );
+
+        assert_eq!(store.list(&db_txn, None, None).unwrap().len(), 3);
+        assert_eq!(
+            store.list(&db_txn, Some(&monitor_id0), None).unwrap(),
+            vec![
+                tx_approvals[0].clone(),
+                store.get(&db_txn, &[3u8; 32]).unwrap()
+            ]
+        );
+        assert_eq!(
+            store
+                .list(&db_txn, None, Some(TxApprovalStatus::Pending))
+                .unwrap(),
+            vec![tx_approvals[0].clone(), tx_approvals[1].clone()]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for the monitor audit log.
+//! * An append-only log of every administrative action taken against a monitor, recording when it
+//!   happened and who asked for it.
+//! * Entries are never modified or removed, not even when the monitor they refer to is removed.
+
+use crate::{
+    database::key_bytes::{key_bytes_to_u64, u64_to_key_bytes},
+    error::Error,
+    monitor_store::MonitorId,
+};
+
+use common::logger::{log, Logger};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// LMDB Database Names
+pub const AUDIT_EVENT_INDEX_TO_AUDIT_EVENT_DB_NAME: &str =
+    "mobilecoind_db:audit_log_store:audit_event_index_to_audit_event";
+
+pub const COUNTS_DB_NAME: &str = "mobilecoind_db:audit_log_store:counts";
+
+// Keys used by the `counts` database.
+const NUM_AUDIT_EVENTS_KEY: &str = "num_audit_events";
+
+/// An administrative action taken against a monitor.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum AuditAction {
+    /// Not set. This is never stored in the database.
+    Unknown = 0,
+
+    /// The monitor was added.
+    MonitorAdded = 1,
+
+    /// The monitor was removed.
+    MonitorRemoved = 2,
+
+    /// The monitor's account key was handed out to a client.
+    MonitorExported = 3,
+
+    /// A snapshot of the monitor's unspent outputs was handed out to a client.
+    MonitorSnapshotExported = 4,
+
+    /// The monitor was added from an account snapshot.
+    MonitorSnapshotImported = 5,
+
+    /// The monitor's account key was handed out to a client in a state archive.
+    MonitorStateExported = 6,
+
+    /// The monitor was added from a state archive.
+    MonitorStateImported = 7,
+
+    /// The monitor was rewound to scan blocks again.
+    MonitorRescanned = 8,
+
+    /// The monitor's labels and metadata were replaced.
+    MonitorMetadataUpdated = 9,
+
+    /// The monitor's scanning priority was changed.
+    MonitorPriorityChanged = 10,
+}
+
+/// An entry in the audit log.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct AuditEvent {
+    /// Position of this entry in the log.
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+
+    /// The monitor the action was taken against.
+    #[prost(message, required, tag = "2")]
+    pub monitor_id: MonitorId,
+
+    /// The action taken.
+    #[prost(enumeration = "AuditAction", tag = "3")]
+    pub action: i32,
+
+    /// When the action was taken, in seconds since the UNIX epoch.
+    #[prost(uint64, tag = "4")]
+    pub timestamp: u64,
+
+    /// Who asked for the action, e.g. a fingerprint of the API token used.
+    #[prost(string, tag = "5")]
+    pub caller: String,
+
+    /// The network address the request came from.
+    #[prost(string, tag = "6")]
+    pub peer: String,
+}
+
+impl AuditEvent {
+    /// Create a log entry for an action taken now. Its index is assigned when it is appended to
+    /// the log.
+    pub fn new(monitor_id: &MonitorId, action: AuditAction, caller: &str, peer: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            index: 0,
+            monitor_id: *monitor_id,
+            action: action as i32,
+            timestamp,
+            caller: caller.to_string(),
+            peer: peer.to_string(),
+        }
+    }
+
+    /// Returns the action this entry records.
+    pub fn get_action(&self) -> AuditAction {
+        AuditAction::from_i32(self.action).unwrap_or(AuditAction::Unknown)
+    }
+}
+
+/// The audit log database.
+#[derive(Clone)]
+pub struct AuditLogStore {
+    env: Arc<Environment>,
+
+    /// Mapping of log index -> AuditEvent.
+    audit_event_index_to_audit_event: Database,
+
+    /// Aggregate counts.
+    /// * `NUM_AUDIT_EVENTS_KEY` --> number of entries in the log.
+    counts: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl AuditLogStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let audit_event_index_to_audit_event = env.create_db(
+            Some(AUDIT_EVENT_INDEX_TO_AUDIT_EVENT_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env,
+            audit_event_index_to_audit_event,
+            counts,
+            logger,
+        })
+    }
+
+    /// Append an event to the log. Returns the index it was stored at.
+    pub fn append<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        audit_event: &AuditEvent,
+    ) -> Result<u64, Error> {
+        let index = self.num_audit_events(db_txn)?;
+
+        let mut audit_event = audit_event.clone();
+        audit_event.index = index;
+
+        db_txn.put(
+            self.audit_event_index_to_audit_event,
+            &u64_to_key_bytes(index),
+            &mcserial::encode(&audit_event),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
+        db_txn.put(
+            self.counts,
+            &NUM_AUDIT_EVENTS_KEY,
+            &u64_to_key_bytes(index + 1),
+            WriteFlags::empty(),
+        )?;
+
+        log::info!(
+            self.logger,
+            "Audit: {:?} on monitor {} by {} ({})",
+            audit_event.get_action(),
+            audit_event.monitor_id,
+            audit_event.caller,
+            audit_event.peer,
+        );
+
+        Ok(index)
+    }
+
+    /// Get entries from the log, in the order they were appended.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - If provided, only entries of this monitor are returned.
+    /// * `first_index` - The first log index to look at.
+    /// * `max_entries` - The maximal number of entries to return.
+    pub fn list(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: Option<&MonitorId>,
+        first_index: u64,
+        max_entries: usize,
+    ) -> Result<Vec<AuditEvent>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.audit_event_index_to_audit_event)?;
+        let mut results = Vec::new();
+        for (_index_bytes, value_bytes) in cursor.iter_from(&u64_to_key_bytes(first_index)) {
+            if results.len() >= max_entries {
+                break;
+            }
+
+            let audit_event: AuditEvent = mcserial::decode(value_bytes)?;
+            if monitor_id.map_or(true, |monitor_id| audit_event.monitor_id == *monitor_id) {
+                results.push(audit_event);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Get the number of entries in the log.
+    pub fn num_audit_events(&self, db_txn: &impl Transaction) -> Result<u64, Error> {
+        match db_txn.get(self.counts, &NUM_AUDIT_EVENTS_KEY) {
+            Ok(value_bytes) => Ok(key_bytes_to_u64(value_bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn setup_test_audit_log_store(logger: &Logger) -> (Arc<Environment>, AuditLogStore) {
+        let db_tmp = TempDir::new("audit_log_store_db")
+            .expect("Could not make tempdir for audit log store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = AuditLogStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_append_and_list(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([3u8; 32]);
+        let (env, store) = setup_test_audit_log_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let events = vec![
+            AuditEvent::new(&monitor_id0, AuditAction::MonitorAdded, "alice", "peer0"),
+            AuditEvent::new(&monitor_id1, AuditAction::MonitorAdded, "bob", "peer1"),
+            AuditEvent::new(&monitor_id0, AuditAction::MonitorExported, "bob", "peer1"),
+            AuditEvent::new(&monitor_id0, AuditAction::MonitorRemoved, "alice", "peer0"),
+        ];
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for (i, event) in events.iter().enumerate() {
+                assert_eq!(store.append(&mut db_txn, event).unwrap(), i as u64);
+            }
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(store.num_audit_events(&db_txn).unwrap(), 4);
+
+        let all = store.list(&db_txn, None, 0, 100).unwrap();
+        assert_eq!(all.len(), 4);
+        for (i, (stored, original)) in all.iter().zip(events.iter()).enumerate() {
+            assert_eq!(stored.index, i as u64);
+            assert_eq!(stored.get_action(), original.get_action());
+            assert_eq!(stored.caller, original.caller);
+            assert_eq!(stored.peer, original.peer);
+            assert_eq!(stored.timestamp, original.timestamp);
+        }
+
+        let monitor0 = store.list(&db_txn, Some(&monitor_id0), 0, 100).unwrap();
+        let actions: Vec<AuditAction> = monitor0.iter().map(|event| event.get_action()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::MonitorAdded,
+                AuditAction::MonitorExported,
+                AuditAction::MonitorRemoved
+            ]
+        );
+
+        let page = store.list(&db_txn, None, 1, 2).unwrap();
+        let indexes: Vec<u64> = page.iter().map(|event| event.index).collect();
+        assert_eq!(indexes, vec![1, 2]);
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Standalone HTTP/JSON gateway entry point, for running the gateway on another host than
+//! mobilecoind.
+
+use common::logger::{create_app_logger, log, o};
+use mobilecoind::{config::HttpGatewayConfig, http_gateway::HttpGateway};
+use structopt::StructOpt;
+
+fn main() {
+    let config = HttpGatewayConfig::from_args();
+
+    common::setup_panic_handler();
+    let (logger, _global_logger_guard) = create_app_logger(o!());
+
+    let tls_config = config.tls.load().expect("Invalid TLS configuration");
+    if tls_config.is_none() {
+        log::warn!(
+            logger,
+            "TLS is not configured, requests are forwarded to {} in plaintext",
+            config.mobilecoind_addr
+        );
+    }
+
+    let _http_gateway = HttpGateway::new(
+        config.http_port,
+        &config.mobilecoind_addr,
+        tls_config.as_ref(),
+        config.tls_hostname_override.as_deref(),
+        config
+            .request_signing
+            .load()
+            .expect("Invalid request signing keys"),
+        logger,
+    )
+    .expect("Could not start HTTP gateway");
+
+    loop {
+        std::thread::park();
+    }
+}
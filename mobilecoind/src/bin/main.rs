@@ -2,87 +2,411 @@
 
 //! mobilecoind daemon entry point
 
-use attest::MrSigner;
 use common::logger::{create_app_logger, log, o, Logger};
 use consensus_enclave_measurement::sigstruct;
 use ledger_db::{Ledger, LedgerDB};
-use ledger_sync::{LedgerSyncServiceThread, PollingNetworkState, ReqwestTransactionsFetcher};
+use ledger_sync::{
+    EquivocationDetector, LedgerSyncService, LedgerSyncServiceThread, PollingNetworkState,
+    ReqwestTransactionsFetcher,
+};
 use mobilecoind::{
-    config::Config, database::Database, payments::TransactionsManager, service::Service,
+    config::Config,
+    confirmations::{BlockConfirmations, ConfirmationThread},
+    database::Database,
+    discovery::fetch_network_config,
+    http_gateway::HttpGateway,
+    notifications::Notifier,
+    payments::TransactionsManager,
+    pruning::PruningThread,
+    self_test::run_self_test,
+    service::Service,
+};
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
-use std::{convert::TryFrom, path::Path};
 use structopt::StructOpt;
 
 fn main() {
-    let config = Config::from_args();
+    let mut config = Config::from_args();
 
     common::setup_panic_handler();
     let _sentry_guard = common::sentry::init();
     let (logger, _global_logger_guard) = create_app_logger(o!());
+    let _metrics_reporter = metrics::MetricsReporter::start_from_env(logger.clone());
+
+    // Stop gracefully on SIGINT and SIGTERM, so that the sync thread can store where it stopped.
+    let shutdown_requested = Arc::new(AtomicBool::new(false));
+    for signal in &[signal_hook::SIGINT, signal_hook::SIGTERM] {
+        signal_hook::flag::register(*signal, shutdown_requested.clone())
+            .expect("Could not register signal handler");
+    }
+
+    // Fill in anything not provided on the command line from the discovery endpoint.
+    let mut discovered_minimum_fee = None;
+    if let (Some(discovery_url), Some(discovery_signer)) =
+        (config.discovery_url.clone(), config.discovery_signer)
+    {
+        log::info!(
+            logger,
+            "Fetching network configuration from {}",
+            discovery_url
+        );
+        let network_config = fetch_network_config(&discovery_url, &discovery_signer)
+            .expect("Failed fetching network configuration");
+        config
+            .apply_network_config(&network_config)
+            .expect("Invalid network configuration");
+        discovered_minimum_fee = network_config.minimum_fee;
+    }
+
+    // Make sure we were built against the consensus enclave of the network the operator expects.
+    if let Err(err) = config.check_consensus_measurement(&sigstruct()) {
+        if config.allow_measurement_mismatch {
+            log::warn!(logger, "Ignoring enclave measurement mismatch: {}", err);
+        } else {
+            panic!(
+                "{}. Pass --allow-measurement-mismatch to start anyway.",
+                err
+            );
+        }
+    }
 
     // Create peer manager.
-    let peer_manager = config.peers_config.create_peer_manager(
-        MrSigner::try_from(&sigstruct().mrsigner()[..])
-            .expect("Could not parse validator node MRSIGNER"),
-        &logger,
-    );
+    let verifier = config
+        .consensus_verifier(&sigstruct())
+        .expect("Could not parse validator node MRSIGNER");
+
+    if config.self_test {
+        let report = run_self_test(&config, &verifier, &logger);
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("Failed serializing self-test report")
+        );
+        std::process::exit(if report.passed { 0 } else { 1 });
+    }
+
+    let peer_manager = config.peers_config.create_peer_manager(verifier, &logger);
 
     // Create network state, transactions fetcher and ledger sync.
     let network_state =
         PollingNetworkState::new(config.quorum_set(), peer_manager.clone(), logger.clone());
 
-    let transactions_fetcher =
+    let mut transactions_fetcher =
         ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
             .expect("Failed creating ReqwestTransactionsFetcher");
+    transactions_fetcher
+        .set_retry_policy(config.tx_source_retry_policy())
+        .expect("Failed setting tx source retry policy");
+
+    // Watch the signed blocks we fetch for nodes signing conflicting blocks.
+    let mut equivocation_detector = EquivocationDetector::new(logger.clone());
+    if let Some(evidence_dir) = config.equivocation_evidence_dir.clone() {
+        equivocation_detector.set_evidence_dir(evidence_dir);
+    }
+    if let Some(webhook_url) = config.equivocation_webhook_url.clone() {
+        equivocation_detector.set_webhook_url(webhook_url);
+    }
+    let equivocation_detector = Arc::new(equivocation_detector);
+    transactions_fetcher.set_equivocation_detector(equivocation_detector.clone());
 
     // Create the ledger_db.
-    let ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
-
-    let _ledger_sync_service_thread = LedgerSyncServiceThread::new(
-        ledger_db.clone(),
-        peer_manager.clone(),
-        network_state,
-        transactions_fetcher,
-        config.poll_interval,
-        logger.clone(),
-    );
+    let mut ledger_db = create_or_open_ledger_db(&config, &logger, &transactions_fetcher);
+    if let Some(block_index) = config.force_resync_from {
+        log::warn!(
+            logger,
+            "Removing blocks from {} onwards from the ledger, which currently has {} blocks",
+            block_index,
+            ledger_db
+                .num_blocks()
+                .expect("Failed getting number of blocks")
+        );
+        ledger_db
+            .truncate(block_index)
+            .expect("Could not remove blocks from the ledger");
+    }
+    if config.verify_ledger_on_startup {
+        let report = ledger_db
+            .verify_integrity()
+            .expect("Failed verifying the ledger");
+        if !report.is_ok() {
+            panic!("The ledger is corrupted: {:?}", report.problems);
+        }
+        log::info!(
+            logger,
+            "Verified {} blocks and {} TxOuts in the ledger",
+            report.num_blocks,
+            report.num_tx_outs
+        );
+    }
+    if let Some(snapshot_path) = config.export_ledger_snapshot.as_ref() {
+        let num_blocks = ledger_db
+            .num_blocks()
+            .expect("Failed getting number of blocks");
+        ledger_db
+            .export_snapshot(snapshot_path, num_blocks - 1)
+            .expect("Failed exporting ledger snapshot");
+        log::info!(
+            logger,
+            "Exported {} blocks to ledger snapshot {:?}",
+            num_blocks,
+            snapshot_path
+        );
+        return;
+    }
+
+    // A read-only ledger is synced by another process.
+    let _ledger_sync_service_thread = if config.ledger_read_only {
+        None
+    } else {
+        let mut ledger_sync_service = LedgerSyncService::new(
+            ledger_db.clone(),
+            peer_manager.clone(),
+            transactions_fetcher,
+            logger.clone(),
+        );
+        ledger_sync_service.set_header_first(config.header_first_sync);
+        ledger_sync_service.set_pipeline_config(config.sync_pipeline_config());
+        ledger_sync_service.set_block_signers(config.block_signers.clone());
+        ledger_sync_service.set_fee_split(config.fee_split().expect("Invalid fee recipients"));
+
+        Some(LedgerSyncServiceThread::from_service(
+            ledger_db.clone(),
+            ledger_sync_service,
+            network_state,
+            config.poll_interval,
+            logger.clone(),
+        ))
+    };
+
+    // Open the mobilecoind database, if the API server is enabled.
+    let mobilecoind_db = config.mobilecoind_db.as_ref().map(|mobilecoind_db| {
+        let _ = std::fs::create_dir_all(mobilecoind_db);
+
+        let mobilecoind_db =
+            Database::new(mobilecoind_db, logger.clone()).expect("Could not open mobilecoinddb");
+
+        match config
+            .mobilecoind_db_secret()
+            .expect("Could not read the mobilecoinddb password or keyfile")
+        {
+            Some(secret) if mobilecoind_db.is_encrypted() => mobilecoind_db
+                .unlock(&secret)
+                .expect("Could not unlock mobilecoinddb"),
+            Some(secret) => {
+                log::info!(logger, "Encrypting mobilecoinddb");
+                mobilecoind_db
+                    .reencrypt(&secret)
+                    .expect("Could not encrypt mobilecoinddb")
+            }
+            None if mobilecoind_db.is_locked() => log::warn!(
+                logger,
+                "mobilecoinddb is encrypted, monitors are not synced until it is unlocked"
+            ),
+            None => {}
+        }
+
+        let dry_run = config.mobilecoind_db_migrate_dry_run;
+        let migrations = mobilecoind_db
+            .migrate(dry_run)
+            .expect("Could not migrate mobilecoinddb");
+        if dry_run {
+            log::info!(
+                logger,
+                "{} pending mobilecoinddb migrations would succeed",
+                migrations.len()
+            );
+            std::process::exit(0);
+        }
+
+        mobilecoind_db
+    });
+
+    // Monitors keep what they found in removed blocks, so they have to scan them again.
+    if let (Some(block_index), Some(mobilecoind_db)) = (config.force_resync_from, &mobilecoind_db) {
+        if mobilecoind_db.is_locked() {
+            log::warn!(
+                logger,
+                "mobilecoinddb is locked, could not check which monitors processed removed blocks"
+            );
+        } else {
+            for (monitor_id, monitor_data) in mobilecoind_db
+                .get_monitor_map()
+                .expect("Could not get monitors")
+            {
+                if monitor_data.next_block > block_index {
+                    log::warn!(
+                        logger,
+                        "Monitor {} processed blocks past {}, remove and add it again to rescan them",
+                        monitor_id,
+                        block_index
+                    );
+                }
+            }
+        }
+    }
+
+    // Optionally discard the contents of old blocks the monitors have scanned.
+    let _pruning_thread = config.ledger_prune_keep_blocks.map(|keep_blocks| {
+        PruningThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            keep_blocks,
+            config.poll_interval,
+            logger.clone(),
+        )
+    });
+
+    // Optionally confirm synced blocks by collecting their signatures from every source.
+    let (confirmations, _confirmation_thread) = match config.confirmation_policy() {
+        Some(policy) => {
+            let mut confirmation_fetcher =
+                ReqwestTransactionsFetcher::new(config.tx_source_urls.clone(), logger.clone())
+                    .expect("Failed creating ReqwestTransactionsFetcher");
+            confirmation_fetcher
+                .set_retry_policy(config.tx_source_retry_policy())
+                .expect("Failed setting tx source retry policy");
+            confirmation_fetcher.set_equivocation_detector(equivocation_detector.clone());
+
+            let confirmations = BlockConfirmations::default();
+            let confirmation_thread = ConfirmationThread::start(
+                ledger_db.clone(),
+                mobilecoind_db.clone(),
+                confirmation_fetcher,
+                policy,
+                confirmations.clone(),
+                config.poll_interval,
+                logger.clone(),
+            );
+            (Some(confirmations), Some(confirmation_thread))
+        }
+        None => (None, None),
+    };
 
     // Potentially launch API server
-    match (&config.mobilecoind_db, &config.service_port) {
+    match (mobilecoind_db, &config.service_port) {
         (Some(mobilecoind_db), Some(service_port)) => {
             log::info!(logger, "Launching mobilecoind API services");
 
-            let _ = std::fs::create_dir_all(mobilecoind_db);
+            // Transactions are submitted through a separate peer manager if submission is
+            // restricted to particular enclaves, so that syncing is not affected.
+            let submission_peer_manager = match config.submission_verifier() {
+                Some(verifier) => config.peers_config.create_peer_manager(verifier, &logger),
+                None => peer_manager,
+            };
 
-            let mobilecoind_db = Database::new(mobilecoind_db, logger.clone())
-                .expect("Could not open mobilecoinddb");
+            // Optionally notify webhooks of monitor activity.
+            let webhook_subscriptions = config
+                .webhook_subscriptions()
+                .expect("Invalid webhook subscriptions");
+            let notifier = match (&config.webhook_signer_key, webhook_subscriptions.is_empty()) {
+                (Some(signer), false) => Some(Notifier::start(
+                    webhook_subscriptions,
+                    signer.clone(),
+                    mobilecoind_db.clone(),
+                    config.sync_lag_threshold,
+                    logger.clone(),
+                )),
+                _ => None,
+            };
 
-            let transactions_manager = TransactionsManager::new(
+            let mut transactions_manager = TransactionsManager::new(
                 ledger_db.clone(),
                 mobilecoind_db.clone(),
-                peer_manager,
+                submission_peer_manager,
                 logger.clone(),
             );
+            if let Some(minimum_fee) = discovered_minimum_fee {
+                transactions_manager.set_minimum_fee_floor(minimum_fee);
+            }
+            transactions_manager.set_quorum_set(config.quorum_set());
+            if let Some(notifier) = &notifier {
+                transactions_manager.set_notifier(notifier.clone());
+            }
+
+            #[cfg(feature = "ledger")]
+            {
+                if config.ledger_signer {
+                    use mobilecoind::payments::ledger::{hid::HidTransport, LedgerSigner};
+                    let transport = HidTransport::open().expect("Could not open Ledger device");
+                    transactions_manager.set_tx_signer(Arc::new(LedgerSigner::new(transport)));
+                }
+            }
+
+            #[cfg(feature = "enclave")]
+            {
+                if config.signing_enclave {
+                    use mobilecoind_enclave::{SigningSgxEnclave, ENCLAVE_FILE};
+                    let enclave_path = std::env::current_exe()
+                        .expect("Could not get the path of our executable")
+                        .with_file_name(ENCLAVE_FILE);
+                    transactions_manager
+                        .set_signing_enclave(Arc::new(SigningSgxEnclave::new(enclave_path)));
+                }
+            }
+
+            #[cfg(feature = "dev")]
+            let _faucet_service = if config.faucet_config.faucet_port.is_some() {
+                Some(
+                    mobilecoind::faucet::FaucetService::new(
+                        &config.faucet_config,
+                        ledger_db.clone(),
+                        mobilecoind_db.clone(),
+                        transactions_manager.clone(),
+                        logger.clone(),
+                    )
+                    .expect("Could not start faucet"),
+                )
+            } else {
+                None
+            };
+
+            let tls_config = config.tls.load().expect("Invalid TLS configuration");
+            let tenant_tokens = config.tenant_tokens().expect("Invalid tenants file");
 
             let _api_server = Service::new(
                 ledger_db,
                 mobilecoind_db,
                 transactions_manager,
                 *service_port,
-                config.num_workers,
-                logger,
+                config.worker_pools,
+                confirmations,
+                config.approval_threshold,
+                config.snapshot_signers(),
+                notifier,
+                config.consolidation_policy(),
+                tenant_tokens,
+                tls_config.as_ref(),
+                logger.clone(),
             );
 
-            loop {
-                std::thread::sleep(config.poll_interval);
-            }
+            // The built-in gateway connects over loopback, so the mobilecoind certificate must be
+            // valid for localhost when TLS is enabled.
+            let _http_gateway = config.http_port.map(|http_port| {
+                HttpGateway::new(
+                    http_port,
+                    &format!("127.0.0.1:{}", service_port),
+                    tls_config.as_ref(),
+                    tls_config.as_ref().map(|_| "localhost"),
+                    config
+                        .request_signing
+                        .load()
+                        .expect("Invalid request signing keys"),
+                    logger.clone(),
+                )
+                .expect("Could not start HTTP gateway")
+            });
+
+            wait_for_shutdown(&shutdown_requested, &logger);
         }
 
         (None, None) => {
             // No mobilecoind service, only ledger syncing.
-            loop {
-                std::thread::sleep(config.poll_interval);
-            }
+            wait_for_shutdown(&shutdown_requested, &logger);
         }
 
         _ => {
@@ -93,11 +417,39 @@ fn main() {
     }
 }
 
+/// Block until a shutdown was requested. Services are stopped as they are dropped.
+fn wait_for_shutdown(shutdown_requested: &AtomicBool, logger: &Logger) {
+    while !shutdown_requested.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    log::info!(logger, "Shutting down");
+}
+
 fn create_or_open_ledger_db(
     config: &Config,
     logger: &Logger,
     transactions_fetcher: &ReqwestTransactionsFetcher,
 ) -> LedgerDB {
+    // A read-only ledger cannot be created, it has to be synced by another process first.
+    if config.ledger_read_only {
+        let ledger_db = LedgerDB::open_read_only(config.ledger_db.clone()).unwrap_or_else(|err| {
+            panic!(
+                "Could not open ledger db inside {:?} read-only: {}",
+                config.ledger_db, err
+            )
+        });
+        log::info!(
+            logger,
+            "Ledger DB {:?} opened read-only: num_blocks={} num_txos={}",
+            config.ledger_db,
+            ledger_db
+                .num_blocks()
+                .expect("Failed getting number of blocks"),
+            ledger_db.num_txos().expect("Failed getting number of txos")
+        );
+        return ledger_db;
+    }
+
     // Attempt to open the ledger and see if it has anything in it.
     if let Ok(ledger_db) = LedgerDB::open(config.ledger_db.clone()) {
         if let Ok(num_blocks) = ledger_db.num_blocks() {
@@ -115,10 +467,11 @@ fn create_or_open_ledger_db(
         }
     }
 
-    // Ledger doesn't exist, or is empty. Copy a bootstrapped ledger or try and get it from the network.
+    // Ledger doesn't exist, or is empty. Copy a bootstrapped ledger, import a snapshot or try and
+    // get it from the network.
     let ledger_db_file = Path::new(&config.ledger_db).join("data.mdb");
-    match &config.ledger_db_bootstrap {
-        Some(ledger_db_bootstrap) => {
+    match (&config.ledger_db_bootstrap, &config.ledger_db_snapshot) {
+        (Some(ledger_db_bootstrap), _) => {
             log::debug!(
                 logger,
                 "Ledger DB {:?} does not exist, copying from {}",
@@ -142,7 +495,23 @@ fn create_or_open_ledger_db(
                 )
             });
         }
-        None => {
+        (None, Some(ledger_db_snapshot)) => {
+            log::info!(
+                logger,
+                "Ledger DB {:?} does not exist, importing snapshot {:?}",
+                config.ledger_db,
+                ledger_db_snapshot
+            );
+            std::fs::create_dir_all(config.ledger_db.clone()).expect("Could not create ledger dir");
+            LedgerDB::create(config.ledger_db.clone()).expect("Could not create ledger_db");
+            let mut db =
+                LedgerDB::open(config.ledger_db.clone()).expect("Could not open ledger_db");
+            let num_blocks = db
+                .import_snapshot(ledger_db_snapshot)
+                .expect("Failed importing ledger snapshot");
+            log::info!(logger, "Imported {} blocks from snapshot", num_blocks);
+        }
+        (None, None) => {
             log::info!(
                     logger,
                     "Ledger DB {:?} does not exist, bootstrapping from peer, this may take a few minutes",
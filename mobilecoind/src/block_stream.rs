@@ -0,0 +1,174 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Streams blocks from the local ledger to API clients.
+//! * Each stream is served by its own thread, which reads blocks in order and hands them to the
+//!   GRPC layer through a bounded channel, so a slow client only ever holds back its own stream.
+//! * Once the stream reaches the end of the ledger it keeps polling for new blocks, which lets
+//!   indexers tail the chain without calling GetBlockInfo for every block.
+//! * A stream can follow a monitor, in which case blocks are only sent once the monitor has
+//!   processed them, together with the TxOuts it matched in them. Wallets can then wait for
+//!   incoming funds without polling GetLedgerInfo or GetProcessedBlock.
+
+use crate::{database::Database, error::Error, monitor_store::MonitorId};
+use common::logger::{log, Logger};
+use futures::{sync::mpsc, Future, Sink};
+use ledger_db::{Ledger, LedgerDB};
+use mobilecoind_api::{self, external, BlockStreamFilter};
+use protobuf::RepeatedField;
+use std::{thread, time::Duration};
+
+/// How long to wait before checking again for new blocks once a stream has caught up with the
+/// ledger.
+pub const BLOCK_STREAM_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How many responses a stream may read ahead of what its client has received.
+const BLOCK_STREAM_BUFFER_SIZE: usize = 16;
+
+/// Builds the response for a single block, containing only the parts selected by `filter`, and
+/// the TxOuts `monitor_id` processed in it if set.
+pub fn block_stream_response(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    block_index: u64,
+    filter: BlockStreamFilter,
+    monitor_id: Option<&MonitorId>,
+) -> Result<mobilecoind_api::StreamBlocksResponse, Error> {
+    let block = ledger_db.get_block(block_index)?;
+    let block_contents = ledger_db.get_block_contents(block_index)?;
+
+    let mut response = mobilecoind_api::StreamBlocksResponse::new();
+    response.set_block_index(block.index);
+    response.set_block_id(block.id.as_ref().to_vec());
+
+    if filter != BlockStreamFilter::OutputPublicKeysOnly {
+        response.set_key_images(RepeatedField::from_vec(
+            block_contents
+                .key_images
+                .iter()
+                .map(external::KeyImage::from)
+                .collect(),
+        ));
+    }
+
+    match filter {
+        BlockStreamFilter::FullBlocks => {
+            response.set_tx_outs(RepeatedField::from_vec(
+                block_contents
+                    .outputs
+                    .iter()
+                    .map(external::TxOut::from)
+                    .collect(),
+            ));
+        }
+        BlockStreamFilter::OutputPublicKeysOnly => {
+            response.set_output_public_keys(RepeatedField::from_vec(
+                block_contents
+                    .outputs
+                    .iter()
+                    .map(|tx_out| tx_out.public_key.into())
+                    .collect(),
+            ));
+        }
+        BlockStreamFilter::KeyImagesOnly => {}
+    }
+
+    if let Some(monitor_id) = monitor_id {
+        let processed_tx_outs = mobilecoind_db.get_processed_block(monitor_id, block_index)?;
+        response.set_processed_tx_outs(RepeatedField::from_vec(
+            processed_tx_outs
+                .iter()
+                .map(|processed_tx_out| {
+                    let mut proto = mobilecoind_api::ProcessedTxOut::from(processed_tx_out);
+                    proto.set_monitor_id(monitor_id.to_vec());
+                    proto
+                })
+                .collect(),
+        ));
+    }
+
+    Ok(response)
+}
+
+/// The number of blocks that can be streamed: the blocks in the ledger, or the blocks
+/// `monitor_id` has processed if set.
+fn num_streamable_blocks(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    monitor_id: Option<&MonitorId>,
+) -> Result<u64, Error> {
+    let num_blocks = ledger_db.num_blocks()?;
+    match monitor_id {
+        Some(monitor_id) => {
+            let monitor_data = mobilecoind_db.get_monitor_data(monitor_id)?;
+            Ok(num_blocks.min(monitor_data.next_block))
+        }
+        None => Ok(num_blocks),
+    }
+}
+
+/// Starts streaming blocks, beginning at `first_block`, and returns the receiving end of the
+/// stream. The stream only ends if reading the ledger fails, or if the monitor it follows is
+/// removed. Its thread exits once the receiver is dropped.
+pub fn start_block_stream(
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    first_block: u64,
+    filter: BlockStreamFilter,
+    monitor_id: Option<MonitorId>,
+    logger: Logger,
+) -> mpsc::Receiver<mobilecoind_api::StreamBlocksResponse> {
+    let (mut sender, receiver) = mpsc::channel(BLOCK_STREAM_BUFFER_SIZE);
+
+    thread::Builder::new()
+        .name("block_stream".to_string())
+        .spawn(move || {
+            log::debug!(logger, "Block stream started at block {}", first_block);
+
+            let mut next_block = first_block;
+            while !sender.is_closed() {
+                let num_blocks =
+                    match num_streamable_blocks(&ledger_db, &mobilecoind_db, monitor_id.as_ref()) {
+                        Ok(num_blocks) => num_blocks,
+                        Err(Error::MonitorIdNotFound) => {
+                            log::info!(logger, "Block stream monitor was removed");
+                            break;
+                        }
+                        Err(err) => {
+                            log::error!(logger, "Failed getting number of blocks: {:?}", err);
+                            break;
+                        }
+                    };
+
+                if next_block >= num_blocks {
+                    thread::sleep(BLOCK_STREAM_POLL_INTERVAL);
+                    continue;
+                }
+
+                let response = match block_stream_response(
+                    &ledger_db,
+                    &mobilecoind_db,
+                    next_block,
+                    filter,
+                    monitor_id.as_ref(),
+                ) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        log::error!(logger, "Failed reading block {}: {:?}", next_block, err);
+                        break;
+                    }
+                };
+
+                // Blocks until the client has room for another response.
+                sender = match sender.send(response).wait() {
+                    Ok(sender) => sender,
+                    Err(_) => break,
+                };
+                next_block += 1;
+            }
+
+            log::debug!(logger, "Block stream stopped at block {}", next_block);
+        })
+        .expect("failed starting block stream thread");
+
+    receiver
+}
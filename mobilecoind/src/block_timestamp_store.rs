@@ -0,0 +1,254 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for block timestamps.
+//! * Manages the mapping of block index -> BlockTimestamp.
+//! * Blocks carry no time of their own. The confirmation thread records when it first saw a block
+//!   served with valid signatures, and how many signers vouched for it at that time, so that the
+//!   transaction history can report wall-clock times that do not depend on when a monitor
+//!   happened to process the block.
+
+use crate::error::Error;
+
+use common::logger::Logger;
+use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::sync::Arc;
+use transaction::BlockIndex;
+
+// LMDB Database Names
+pub const BLOCK_INDEX_TO_BLOCK_TIMESTAMP_DB_NAME: &str =
+    "mobilecoind_db:block_timestamp_store:block_index_to_block_timestamp";
+
+/// How far a timestamp can be trusted, ordered from least to most trustworthy.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TimestampConfidence {
+    /// The time mobilecoind processed the block. No signed copy of the block was seen.
+    Local = 0,
+
+    /// The time the block was first seen signed, by fewer signers than the confirmation quorum.
+    SingleSigner = 1,
+
+    /// The time the block was first seen signed by the confirmation quorum.
+    Quorum = 2,
+}
+
+/// When a block was seen signed, as stored in the block timestamp database.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct BlockTimestamp {
+    /// Seconds since the UNIX epoch.
+    #[prost(uint64, tag = "1")]
+    pub timestamp: u64,
+
+    /// How many signers vouched for the block at that time.
+    #[prost(enumeration = "TimestampConfidence", tag = "2")]
+    pub confidence: i32,
+}
+
+impl BlockTimestamp {
+    pub fn new(timestamp: u64, confidence: TimestampConfidence) -> Self {
+        Self {
+            timestamp,
+            confidence: confidence as i32,
+        }
+    }
+
+    /// Returns the confidence of this timestamp.
+    pub fn get_confidence(&self) -> TimestampConfidence {
+        TimestampConfidence::from_i32(self.confidence).unwrap_or(TimestampConfidence::Local)
+    }
+}
+
+/// The block timestamps database.
+#[derive(Clone)]
+pub struct BlockTimestampStore {
+    env: Arc<Environment>,
+
+    /// Mapping of block index -> BlockTimestamp.
+    block_index_to_block_timestamp: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl BlockTimestampStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let block_index_to_block_timestamp = env.create_db(
+            Some(BLOCK_INDEX_TO_BLOCK_TIMESTAMP_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            block_index_to_block_timestamp,
+            logger,
+        })
+    }
+
+    /// Record that a block was seen signed at a given time. The first timestamp of each
+    /// confidence is kept: a block that was already recorded is only updated if the new timestamp
+    /// has a higher confidence. Returns true if the timestamp was recorded.
+    pub fn observe<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        block_index: BlockIndex,
+        block_timestamp: &BlockTimestamp,
+    ) -> Result<bool, Error> {
+        if let Some(existing) = self.get(db_txn, block_index)? {
+            if existing.get_confidence() >= block_timestamp.get_confidence() {
+                return Ok(false);
+            }
+        }
+
+        db_txn.put(
+            self.block_index_to_block_timestamp,
+            &block_index.to_be_bytes(),
+            &mcserial::encode(block_timestamp),
+            WriteFlags::empty(),
+        )?;
+        Ok(true)
+    }
+
+    /// Get the index of the highest block that was seen before a given time, if any. Since a block
+    /// cannot be seen before it was created, that block and all blocks before it were created
+    /// before that time.
+    pub fn get_last_block_seen_before(
+        &self,
+        db_txn: &impl Transaction,
+        timestamp: u64,
+    ) -> Result<Option<BlockIndex>, Error> {
+        let mut last_block_index = None;
+
+        let mut cursor = db_txn.open_ro_cursor(self.block_index_to_block_timestamp)?;
+        for (key_bytes, value_bytes) in cursor.iter_start() {
+            let block_timestamp: BlockTimestamp = mcserial::decode(value_bytes)?;
+            if block_timestamp.timestamp < timestamp {
+                let mut block_index_bytes = [0u8; 8];
+                block_index_bytes.copy_from_slice(key_bytes);
+                last_block_index = Some(BlockIndex::from_be_bytes(block_index_bytes));
+            }
+        }
+
+        Ok(last_block_index)
+    }
+
+    /// Get the timestamp of a block, if it was seen signed.
+    pub fn get(
+        &self,
+        db_txn: &impl Transaction,
+        block_index: BlockIndex,
+    ) -> Result<Option<BlockTimestamp>, Error> {
+        match db_txn.get(
+            self.block_index_to_block_timestamp,
+            &block_index.to_be_bytes(),
+        ) {
+            Ok(value_bytes) => Ok(Some(mcserial::decode(value_bytes)?)),
+            Err(lmdb::Error::NotFound) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::{test_with_logger, Logger};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    // Timestamps should only be replaced by ones of a higher confidence.
+    fn test_observe(logger: Logger) {
+        let db_tmp = TempDir::new("block_timestamp_store_db")
+            .expect("Could not make tempdir for block timestamp store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = BlockTimestampStore::new(env.clone(), logger.clone()).unwrap();
+
+        let single_signer = BlockTimestamp::new(1000, TimestampConfidence::SingleSigner);
+        let quorum = BlockTimestamp::new(2000, TimestampConfidence::Quorum);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_eq!(store.get(&db_txn, 3).unwrap(), None);
+
+        assert!(store.observe(&mut db_txn, 3, &single_signer).unwrap());
+        assert!(!store
+            .observe(
+                &mut db_txn,
+                3,
+                &BlockTimestamp::new(1500, TimestampConfidence::SingleSigner)
+            )
+            .unwrap());
+        assert_eq!(store.get(&db_txn, 3).unwrap(), Some(single_signer.clone()));
+
+        assert!(store.observe(&mut db_txn, 3, &quorum).unwrap());
+        assert!(!store.observe(&mut db_txn, 3, &single_signer).unwrap());
+        assert_eq!(store.get(&db_txn, 3).unwrap(), Some(quorum));
+
+        // Other blocks are not affected.
+        assert_eq!(store.get(&db_txn, 4).unwrap(), None);
+        db_txn.commit().unwrap();
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(
+            store.get(&db_txn, 3).unwrap().unwrap().get_confidence(),
+            TimestampConfidence::Quorum
+        );
+    }
+
+    #[test_with_logger]
+    // The highest block seen before a time should be found, even if not every block was seen.
+    fn test_get_last_block_seen_before(logger: Logger) {
+        let db_tmp = TempDir::new("block_timestamp_store_db")
+            .expect("Could not make tempdir for block timestamp store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = BlockTimestampStore::new(env.clone(), logger.clone()).unwrap();
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        assert_eq!(
+            store.get_last_block_seen_before(&db_txn, 1000).unwrap(),
+            None
+        );
+
+        for (block_index, timestamp) in &[(0, 1000), (1, 2000), (4, 3000), (300, 4000)] {
+            store
+                .observe(
+                    &mut db_txn,
+                    *block_index,
+                    &BlockTimestamp::new(*timestamp, TimestampConfidence::Local),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(
+            store.get_last_block_seen_before(&db_txn, 1000).unwrap(),
+            None
+        );
+        assert_eq!(
+            store.get_last_block_seen_before(&db_txn, 1001).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            store.get_last_block_seen_before(&db_txn, 3500).unwrap(),
+            Some(4)
+        );
+        assert_eq!(
+            store.get_last_block_seen_before(&db_txn, 5000).unwrap(),
+            Some(300)
+        );
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Strategies for selecting the UTXOs a transaction spends.
+
+use crate::{error::Error, payments::total_utxo_value, utxo_store::UnspentTxOut};
+use mcrand::RngCore;
+use rand::seq::SliceRandom;
+use std::cmp::Reverse;
+use transaction::mob::PicoMob;
+
+/// Selects the UTXOs to spend in a transaction.
+pub trait CoinSelection: Send + Sync {
+    /// Returns a subset of `utxos` totalling at least `value`, with at most `max_inputs` UTXOs.
+    ///
+    /// # Arguments
+    /// * `utxos` - The UTXOs that may be spent.
+    /// * `value` - The value that needs to be covered, including the fee.
+    /// * `max_inputs` - The maximal number of UTXOs to select.
+    /// * `rng` - Randomness, for strategies that need it.
+    fn select_utxos(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<UnspentTxOut>, Error>;
+}
+
+/// The built-in coin selection strategies.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CoinSelectionStrategy {
+    /// See `SmallestFirst`.
+    SmallestFirst,
+
+    /// See `LargestFirst`.
+    LargestFirst,
+
+    /// See `RandomSelection`.
+    Random,
+}
+
+impl Default for CoinSelectionStrategy {
+    fn default() -> Self {
+        CoinSelectionStrategy::SmallestFirst
+    }
+}
+
+impl CoinSelection for CoinSelectionStrategy {
+    fn select_utxos(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        match self {
+            CoinSelectionStrategy::SmallestFirst => {
+                SmallestFirst.select_utxos(utxos, value, max_inputs, rng)
+            }
+            CoinSelectionStrategy::LargestFirst => {
+                LargestFirst.select_utxos(utxos, value, max_inputs, rng)
+            }
+            CoinSelectionStrategy::Random => {
+                RandomSelection.select_utxos(utxos, value, max_inputs, rng)
+            }
+        }
+    }
+}
+
+/// Spends the smallest UTXOs that cover the value, sweeping up dust over time. If that takes
+/// too many inputs, the smallest ones are replaced by larger ones.
+// TODO: This should take attempted_spend_height into account.
+pub struct SmallestFirst;
+
+impl CoinSelection for SmallestFirst {
+    fn select_utxos(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        _rng: &mut dyn RngCore,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        // Sort the utxos in descending order by value.
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        check_funds(&sorted_utxos, value, max_inputs)?;
+
+        // Choose utxos to spend.
+        let value = PicoMob::new(value);
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        loop {
+            let total = total_utxo_value(&selected_utxos);
+            if total.map_or(true, |total| total >= value) {
+                break;
+            }
+
+            // Grab the next (smallest utxo)
+            let next_utxo = sorted_utxos.pop().ok_or(Error::InsufficientFunds)?;
+            selected_utxos.push(next_utxo.clone());
+
+            // Cap at maximum allowed inputs.
+            if selected_utxos.len() > max_inputs {
+                // Remove the lowest utxo.
+                selected_utxos.remove(0);
+            }
+        }
+
+        // Sanity.
+        assert!(!selected_utxos.is_empty());
+        assert!(selected_utxos.len() <= max_inputs);
+
+        // Return selected utxos.
+        Ok(selected_utxos)
+    }
+}
+
+/// Spends the largest UTXOs first, using as few inputs as possible.
+pub struct LargestFirst;
+
+impl CoinSelection for LargestFirst {
+    fn select_utxos(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        _rng: &mut dyn RngCore,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        check_funds(&sorted_utxos, value, max_inputs)?;
+
+        let value = PicoMob::new(value);
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        for utxo in sorted_utxos {
+            if total_utxo_value(&selected_utxos).map_or(true, |total| total >= value) {
+                break;
+            }
+            selected_utxos.push(utxo);
+        }
+
+        assert!(!selected_utxos.is_empty());
+        assert!(selected_utxos.len() <= max_inputs);
+        Ok(selected_utxos)
+    }
+}
+
+/// Spends UTXOs picked at random, so that the inputs of a transaction reveal less about the
+/// other UTXOs of the sender. If that takes too many inputs, the smallest ones are replaced by
+/// larger ones.
+pub struct RandomSelection;
+
+impl CoinSelection for RandomSelection {
+    fn select_utxos(
+        &self,
+        utxos: &[UnspentTxOut],
+        value: u64,
+        max_inputs: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let mut sorted_utxos = utxos.to_vec();
+        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        check_funds(&sorted_utxos, value, max_inputs)?;
+
+        let mut shuffled_utxos = sorted_utxos;
+        shuffled_utxos.shuffle(rng);
+
+        // Once capped, the selection holds the largest UTXOs seen so far, so it covers the value
+        // at the latest once every UTXO was seen.
+        let value = PicoMob::new(value);
+        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
+        for utxo in shuffled_utxos {
+            if total_utxo_value(&selected_utxos).map_or(true, |total| total >= value) {
+                break;
+            }
+            selected_utxos.push(utxo);
+
+            if selected_utxos.len() > max_inputs {
+                let (smallest_index, _) = selected_utxos
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, utxo)| utxo.value)
+                    .expect("selected_utxos is not empty");
+                selected_utxos.remove(smallest_index);
+            }
+        }
+
+        assert!(!selected_utxos.is_empty());
+        assert!(selected_utxos.len() <= max_inputs);
+        Ok(selected_utxos)
+    }
+}
+
+/// Checks that `max_inputs` of the UTXOs, sorted in descending order by value, are enough to
+/// cover `value`.
+fn check_funds(sorted_utxos: &[UnspentTxOut], value: u64, max_inputs: usize) -> Result<(), Error> {
+    // The maximum spendable is limited by the maximal number of inputs we can use. Values too
+    // large to add up are certainly enough to pay for `value`.
+    let value = PicoMob::new(value);
+    let max_spendable_amount = total_utxo_value(sorted_utxos.iter().take(max_inputs));
+    if max_spendable_amount.map_or(false, |amount| value > amount) {
+        // See if we merged the UTXOs we would be able to spend this amount.
+        let total_utxos_value = total_utxo_value(sorted_utxos);
+        if total_utxos_value.map_or(true, |total| total >= value) {
+            return Err(Error::InsufficientFundsFragmentedUtxos);
+        } else {
+            return Err(Error::InsufficientFunds);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::{FromRandom, RistrettoPrivate};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::{account_keys::AccountKey, tx::TxOut};
+
+    fn generate_utxos(num_utxos: usize) -> Vec<UnspentTxOut> {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let tx_secret_key_for_txo = RistrettoPrivate::from_random(&mut rng);
+
+        let tx_out = TxOut::new(
+            1,
+            &alice.default_subaddress(),
+            &tx_secret_key_for_txo,
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap();
+
+        // Construct a bunch of utxos.
+        (0..num_utxos as u64)
+            .map(|_| UnspentTxOut {
+                tx_out: tx_out.clone(),
+                subaddress_index: 0,
+                key_image: Default::default(),
+                value: 1,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                memo: String::new(),
+            })
+            .collect()
+    }
+
+    /// UTXOs worth 100, 200, 300, 2000 and 1000.
+    fn generate_utxos_of_varying_value() -> Vec<UnspentTxOut> {
+        let mut utxos = generate_utxos(5);
+        for (utxo, value) in utxos.iter_mut().zip(&[100, 200, 300, 2000, 1000]) {
+            utxo.value = *value;
+        }
+        utxos
+    }
+
+    fn sum(utxos: &[UnspentTxOut]) -> u64 {
+        utxos.iter().map(|utxo| utxo.value).sum()
+    }
+
+    #[test]
+    fn test_smallest_first_selects_smallest_inputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let utxos = generate_utxos_of_varying_value();
+
+        // Sending 300 should select 100 + 200 when 2 inputs are allowed.
+        let selected_utxos = SmallestFirst
+            .select_utxos(&utxos, 300, utxos.len(), &mut rng)
+            .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[1].clone()]);
+
+        // Sending 301 should select 100 + 200 + 300 when 3 inputs are allowed.
+        let selected_utxos = SmallestFirst
+            .select_utxos(&utxos, 301, utxos.len(), &mut rng)
+            .unwrap();
+
+        assert_eq!(
+            selected_utxos,
+            vec![utxos[0].clone(), utxos[1].clone(), utxos[2].clone()]
+        );
+
+        // Sending 301 should select 200 + 300 when only 2  inputs are allowed.
+        let selected_utxos = SmallestFirst
+            .select_utxos(&utxos, 301, 2, &mut rng)
+            .unwrap();
+
+        assert_eq!(selected_utxos, vec![utxos[1].clone(), utxos[2].clone()]);
+    }
+
+    #[test]
+    fn test_largest_first_selects_largest_inputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let utxos = generate_utxos_of_varying_value();
+
+        let selected_utxos = LargestFirst
+            .select_utxos(&utxos, 300, utxos.len(), &mut rng)
+            .unwrap();
+        assert_eq!(selected_utxos, vec![utxos[3].clone()]);
+
+        let selected_utxos = LargestFirst
+            .select_utxos(&utxos, 2001, utxos.len(), &mut rng)
+            .unwrap();
+        assert_eq!(selected_utxos, vec![utxos[3].clone(), utxos[4].clone()]);
+    }
+
+    #[test]
+    fn test_random_selection_covers_value_within_max_inputs() {
+        let utxos = generate_utxos_of_varying_value();
+
+        let mut selections = Vec::new();
+        for seed in 0..20u8 {
+            let mut rng: StdRng = SeedableRng::from_seed([seed; 32]);
+            for (value, max_inputs) in &[(300, 5), (2500, 2), (3300, 3), (3600, 5)] {
+                let selected_utxos = RandomSelection
+                    .select_utxos(&utxos, *value, *max_inputs, &mut rng)
+                    .unwrap();
+                assert!(sum(&selected_utxos) >= *value);
+                assert!(selected_utxos.len() <= *max_inputs);
+                if *value == 300 {
+                    selections.push(selected_utxos);
+                }
+            }
+        }
+
+        // Different randomness picks different inputs.
+        assert!(selections
+            .iter()
+            .any(|selection| *selection != selections[0]));
+    }
+
+    #[test]
+    fn test_strategies_error_if_too_many_inputs_are_needed() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let utxos = generate_utxos(10);
+        for strategy in &[
+            CoinSelectionStrategy::SmallestFirst,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::Random,
+        ] {
+            // While we have enough utxos to sum to 5, if the input limit is 4 we should fail.
+            match strategy.select_utxos(&utxos, 5, 4, &mut rng) {
+                Err(Error::InsufficientFundsFragmentedUtxos) => {
+                    // Expected.
+                }
+                _ => panic!("Did not get expected error"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_strategies_error_if_insufficient_funds() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let utxos = generate_utxos(10);
+        for strategy in &[
+            CoinSelectionStrategy::SmallestFirst,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::Random,
+        ] {
+            match strategy.select_utxos(&utxos, 50, 100, &mut rng) {
+                Err(Error::InsufficientFunds) => {
+                    // Expected.
+                }
+                _ => panic!("Did not get expected error"),
+            };
+        }
+    }
+
+    #[test]
+    fn test_strategies_handle_overflowing_values() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let mut utxos = generate_utxos(3);
+        for utxo in utxos.iter_mut() {
+            utxo.value = u64::max_value() / 2;
+        }
+
+        // The UTXOs add up to more than a u64 can hold, but any two of them are enough.
+        for strategy in &[
+            CoinSelectionStrategy::SmallestFirst,
+            CoinSelectionStrategy::LargestFirst,
+            CoinSelectionStrategy::Random,
+        ] {
+            let selected_utxos = strategy
+                .select_utxos(&utxos, u64::max_value() - 1, utxos.len(), &mut rng)
+                .unwrap();
+            assert_eq!(selected_utxos.len(), 2);
+        }
+    }
+}
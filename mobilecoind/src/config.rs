@@ -2,13 +2,28 @@
 
 //! Configuration parameters for mobilecoind
 
-use attest::Measurement;
+use crate::{
+    account_snapshot::SnapshotSigners, confirmations::ConfirmationPolicy,
+    consolidation::ConsolidationPolicy, discovery::NetworkConfig, encryption::DatabaseSecret,
+    error::Error, notifications::WebhookSubscription, tenants::TenantTokens, tls::MutualTlsConfig,
+};
+use attest::{MrEnclave, MrSigner, Verifier, VerifierBuilder};
 use common::{logger::Logger, ResponderId};
+use keys::{Ed25519Pair, Ed25519Private, Ed25519Public, RistrettoPublic};
+use ledger_sync::{RetryPolicy, SyncPipelineConfig};
+use mc_encodings::FromHex;
 use mcconnection::{ConnectionManager, ThickClient};
 use mcuri::{ConnectionUri, ConsensusClientUri};
 use scp::QuorumSet;
-use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+use sgx_css::Signature;
+use std::{convert::TryFrom, fs, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 use structopt::StructOpt;
+use transaction::{
+    account_keys::PublicAddress,
+    fee_split::{FeeRecipient, FeeSplit},
+    mob::PicoMob,
+};
+use url::Url;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "mobilecoind", about = "The MobileCoin client daemon.")]
@@ -21,6 +36,34 @@ pub struct Config {
     #[structopt(long)]
     pub ledger_db_bootstrap: Option<String>,
 
+    /// Open the existing ledger db read-only and do not sync it, so that it can be shared with
+    /// another process syncing it, e.g. another mobilecoind.
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "ledger-db-bootstrap",
+            "ledger-db-snapshot",
+            "force-resync-from",
+            "ledger-prune-keep-blocks",
+        ]
+    )]
+    pub ledger_read_only: bool,
+
+    /// Path to a ledger snapshot, see `ledger_db::snapshot`, used when initializing new ledger
+    /// dbs. The blocks it holds are validated and appended to the new ledger, and the rest are
+    /// synced from the network.
+    #[structopt(long, parse(from_os_str), conflicts_with = "ledger-db-bootstrap")]
+    pub ledger_db_snapshot: Option<PathBuf>,
+
+    /// Check the ledger for corruption once it is opened, see `LedgerDB::verify_integrity`, and
+    /// refuse to start if any is found. This reads the whole ledger, so it can take a while.
+    #[structopt(long)]
+    pub verify_ledger_on_startup: bool,
+
+    /// Export a snapshot of the ledger to the given path, once it is opened, and exit.
+    #[structopt(long, parse(from_os_str))]
+    pub export_ledger_snapshot: Option<PathBuf>,
+
     #[structopt(flatten)]
     pub peers_config: PeersConfig,
 
@@ -34,9 +77,74 @@ pub struct Config {
     /// URLs to use for transaction data.
     ///
     /// For example: https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/
-    #[structopt(long = "tx-source-url", required = true, min_values = 1)]
+    ///
+    /// A file:// URL reads blocks from a local directory laid out like the archive, e.g.
+    /// file:///mnt/archive/node1.test.mobilecoin.com/
+    #[structopt(
+        long = "tx-source-url",
+        required_unless = "discovery-url",
+        min_values = 1
+    )]
     pub tx_source_urls: Vec<String>,
 
+    /// Check connectivity and attestation to each peer, fetch a block from each tx source and
+    /// open the databases, print a summary and exit. Exits with a non-zero status if any check
+    /// fails.
+    #[structopt(long)]
+    pub self_test: bool,
+
+    /// Establish the block peers agree on from their block IDs before fetching any blocks, and
+    /// then fetch the blocks from a single peer, instead of fetching blocks from every peer.
+    #[structopt(long)]
+    pub header_first_sync: bool,
+
+    /// Remove the blocks from this index onwards from the local ledger on start, so that they are
+    /// synced again. Used to recover once the local ledger diverged from the network.
+    #[structopt(long)]
+    pub force_resync_from: Option<u64>,
+
+    /// Discard the contents of blocks more than this many blocks behind the end of the ledger, to
+    /// save space. Their headers, key images and TxOuts are kept. Blocks a monitor has yet to
+    /// scan are not pruned, and monitors cannot be added or rescanned from pruned blocks.
+    #[structopt(long)]
+    pub ledger_prune_keep_blocks: Option<u64>,
+
+    /// Number of threads fetching the contents of blocks while syncing.
+    #[structopt(long, default_value = "5")]
+    pub sync_fetch_workers: usize,
+
+    /// Maximal number of downloaded blocks held in memory while waiting to be verified.
+    #[structopt(long, default_value = "50")]
+    pub sync_fetch_queue_depth: usize,
+
+    /// Maximal number of verified blocks held in memory while waiting to be written to the
+    /// ledger.
+    #[structopt(long, default_value = "10")]
+    pub sync_append_queue_depth: usize,
+
+    /// Maximal number of blocks written to the ledger at once while catching up with the
+    /// network. Blocks near the network's tip are written one at a time.
+    #[structopt(long, default_value = "100")]
+    pub sync_append_batch_size: usize,
+
+    /// Maximal number of attempts of a request to a tx source that fails with a transient error,
+    /// such as a timeout or a server error, including the first one.
+    #[structopt(long, default_value = "3")]
+    pub tx_source_max_attempts: usize,
+
+    /// How many milliseconds to wait before retrying a request to a tx source. The delay doubles
+    /// with every retry, up to `--tx-source-max-backoff`.
+    #[structopt(long, default_value = "100", parse(try_from_str=parse_duration_in_milliseconds))]
+    pub tx_source_initial_backoff: Duration,
+
+    /// Maximal number of seconds to wait before retrying a request to a tx source.
+    #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
+    pub tx_source_max_backoff: Duration,
+
+    /// How many seconds a request to a tx source may take.
+    #[structopt(long, default_value = "30", parse(try_from_str=parse_duration_in_seconds))]
+    pub tx_source_request_timeout: Duration,
+
     /// How many seconds to wait between polling.
     #[structopt(long, default_value = "5", parse(try_from_str=parse_duration_in_seconds))]
     pub poll_interval: Duration,
@@ -46,26 +154,348 @@ pub struct Config {
     #[structopt(long, parse(from_os_str))]
     pub mobilecoind_db: Option<PathBuf>,
 
+    /// Path to a file holding the password the mobilecoind database is encrypted with, on its
+    /// first line. An unencrypted database is encrypted with it on start.
+    #[structopt(long, parse(from_os_str), conflicts_with = "mobilecoind-db-keyfile")]
+    pub mobilecoind_db_password_file: Option<PathBuf>,
+
+    /// Path to a keyfile of at least 32 random bytes the mobilecoind database is encrypted with.
+    /// An unencrypted database is encrypted with it on start.
+    #[structopt(long, parse(from_os_str))]
+    pub mobilecoind_db_keyfile: Option<PathBuf>,
+
+    /// Check that the pending schema migrations of the mobilecoind database succeed, without
+    /// applying them, and exit.
+    #[structopt(long, requires = "mobilecoind-db")]
+    pub mobilecoind_db_migrate_dry_run: bool,
+
     /// Port to serve mobilecoind requests from
     #[structopt(long)]
     pub service_port: Option<u16>,
 
-    /// Number of worker threads to use for view key scanning.
-    /// Defaults to number of logical CPU cores.
+    /// Port to serve mobilecoind requests from as JSON over HTTP, in addition to gRPC.
+    #[structopt(long, requires = "service-port")]
+    pub http_port: Option<u16>,
+
+    #[structopt(flatten)]
+    pub tls: TlsConfig,
+
+    #[structopt(flatten)]
+    pub request_signing: RequestSigningConfig,
+
+    #[structopt(flatten)]
+    pub worker_pools: WorkerPoolsConfig,
+
+    /// Path to the consensus enclave signature (CSS) file of the network we expect to connect to.
+    /// If provided, it must match the consensus enclave mobilecoind was compiled against.
+    #[structopt(long, parse(from_os_str))]
+    pub expected_consensus_css: Option<PathBuf>,
+
+    /// Hex-encoded MRSIGNER of the consensus enclave of the network we expect to connect to.
+    /// If provided, it must match the consensus enclave mobilecoind was compiled against.
+    #[structopt(long, parse(try_from_str=parse_mr_signer))]
+    pub expected_mr_signer: Option<MrSigner>,
+
+    /// Start even if the compiled-in consensus enclave measurement does not match the expected
+    /// one.
     #[structopt(long)]
-    pub num_workers: Option<usize>,
+    pub allow_measurement_mismatch: bool,
+
+    /// Hex-encoded MRENCLAVE of an additional consensus enclave to accept when attesting to
+    /// peers, e.g. while the network is being upgraded to a new enclave. May be repeated.
+    #[structopt(long = "accepted-mr-enclave", parse(try_from_str=parse_mr_enclave))]
+    pub accepted_mr_enclaves: Vec<MrEnclave>,
+
+    /// Hex-encoded MRSIGNER of an additional consensus enclave to accept when attesting to
+    /// peers, alongside the compiled-in one. May be repeated.
+    #[structopt(long = "accepted-mr-signer", parse(try_from_str=parse_mr_signer))]
+    pub accepted_mr_signers: Vec<MrSigner>,
+
+    /// Hex-encoded MRENCLAVE of a consensus enclave transactions may be submitted to. May be
+    /// repeated. If this or --submit-mr-signer is provided, transactions are only submitted to
+    /// peers attesting as one of the given enclaves, regardless of which peers are accepted for
+    /// syncing.
+    #[structopt(long = "submit-mr-enclave", parse(try_from_str=parse_mr_enclave))]
+    pub submit_mr_enclaves: Vec<MrEnclave>,
+
+    /// Hex-encoded MRSIGNER of a consensus enclave transactions may be submitted to. May be
+    /// repeated. If this or --submit-mr-enclave is provided, transactions are only submitted to
+    /// peers attesting as one of the given enclaves, regardless of which peers are accepted for
+    /// syncing.
+    #[structopt(long = "submit-mr-signer", parse(try_from_str=parse_mr_signer))]
+    pub submit_mr_signers: Vec<MrSigner>,
+
+    /// URL of a signed network configuration (peers, quorum set, tx sources and minimum fee).
+    /// Values provided on the command line take precedence over discovered ones.
+    #[structopt(long, requires = "discovery-signer")]
+    pub discovery_url: Option<String>,
+
+    /// Hex-encoded Ed25519 public key the discovered network configuration must be signed with.
+    #[structopt(long, parse(try_from_str=parse_ed25519_public))]
+    pub discovery_signer: Option<Ed25519Public>,
+
+    /// Directory where evidence of a node signing two different blocks for the same index is
+    /// written to.
+    #[structopt(long, parse(from_os_str))]
+    pub equivocation_evidence_dir: Option<PathBuf>,
+
+    /// URL evidence of a node signing two different blocks for the same index is POSTed to.
+    #[structopt(long, parse(try_from_str=Url::parse))]
+    pub equivocation_webhook_url: Option<Url>,
+
+    /// Only treat a block as final for balance purposes once this many distinct nodes serve it
+    /// with a valid signature from their transaction source archives.
+    #[structopt(long)]
+    pub confirmation_quorum: Option<usize>,
+
+    /// Hex-encoded Ed25519 public key of a node whose block signatures count towards
+    /// --confirmation-quorum. May be repeated. Any signer counts if not provided.
+    #[structopt(
+        long = "confirmation-signer",
+        requires = "confirmation-quorum",
+        parse(try_from_str=parse_ed25519_public)
+    )]
+    pub confirmation_signers: Vec<Ed25519Public>,
+
+    /// Hex-encoded Ed25519 public key of a node whose block signatures are stored in the ledger
+    /// while syncing. May be repeated. Valid signatures by any signer are stored if not provided.
+    #[structopt(long = "block-signer", parse(try_from_str=parse_ed25519_public))]
+    pub block_signers: Vec<Ed25519Public>,
+
+    /// A recipient of the network's block fees, as "<spend public key>:<view public key>:<weight>"
+    /// with hex-encoded keys. May be repeated, in the order of the network's fee recipients. Synced
+    /// blocks whose fee outputs do not follow the split are not appended. Fee outputs are not
+    /// checked if not provided.
+    #[structopt(long = "fee-recipient", parse(try_from_str=parse_fee_recipient))]
+    pub fee_recipients: Vec<FeeRecipient>,
+
+    /// Transactions sending more than this value, e.g. "100 MOB", need to be approved with a
+    /// second API token before SubmitTx accepts them. SendPayment refuses such payments, since it
+    /// submits them right away.
+    #[structopt(long)]
+    pub approval_threshold: Option<PicoMob>,
+
+    /// Hex-encoded Ed25519 private key exported account snapshots are signed with. Exporting
+    /// snapshots is disabled if not provided.
+    #[structopt(long, parse(try_from_str=parse_ed25519_pair))]
+    pub snapshot_signer_key: Option<Arc<Ed25519Pair>>,
+
+    /// Hex-encoded Ed25519 public key of another mobilecoind whose account snapshots may be
+    /// imported. May be repeated.
+    #[structopt(
+        long = "trusted-snapshot-signer",
+        parse(try_from_str=parse_ed25519_public)
+    )]
+    pub trusted_snapshot_signers: Vec<Ed25519Public>,
+
+    /// Path to a JSON file binding API tokens to tenants, which share this mobilecoind while each
+    /// only sees the monitors it added. Every caller then needs a token bound to a tenant.
+    #[structopt(long, parse(from_os_str))]
+    pub tenant_tokens: Option<PathBuf>,
+
+    /// URL a signed JSON event is POSTed to whenever something happens to a monitor, e.g. it
+    /// receives funds or one of its payments is confirmed. May be repeated.
+    #[structopt(
+        long = "webhook-url",
+        parse(try_from_str=Url::parse),
+        requires = "webhook-signer-key"
+    )]
+    pub webhook_urls: Vec<Url>,
+
+    /// Path to a JSON file listing webhooks along with the events they receive, e.g. only
+    /// payments of some monitor above some value.
+    #[structopt(long, parse(from_os_str), requires = "webhook-signer-key")]
+    pub webhook_subscriptions: Option<PathBuf>,
+
+    /// Notify webhooks when a monitor falls at least this many blocks behind the ledger.
+    #[structopt(long)]
+    pub sync_lag_threshold: Option<u64>,
+
+    /// Hex-encoded Ed25519 private key webhook events are signed with.
+    #[structopt(long, parse(try_from_str=parse_ed25519_pair))]
+    pub webhook_signer_key: Option<Arc<Ed25519Pair>>,
+
+    /// Merge the UTXOs of a subaddress in the background once it holds at least this many
+    /// spendable ones, while no payment from it is pending. Disabled if not provided.
+    #[structopt(long)]
+    pub consolidation_min_utxos: Option<usize>,
+
+    /// How many seconds to wait between looking for subaddresses to consolidate.
+    #[structopt(long, default_value = "60", parse(try_from_str=parse_duration_in_seconds))]
+    pub consolidation_interval: Duration,
+
+    /// Sign transactions with the account held by a Ledger device connected over USB, rather
+    /// than with the account keys of the monitors.
+    #[cfg(feature = "ledger")]
+    #[structopt(long)]
+    pub ledger_signer: bool,
+
+    /// Hold the account keys of new monitors in the SGX signing enclave, which seals them to this
+    /// machine. Monitors added this way can only spend while the enclave is used.
+    #[cfg(feature = "enclave")]
+    #[structopt(long)]
+    pub signing_enclave: bool,
+
+    #[cfg(feature = "dev")]
+    #[structopt(flatten)]
+    pub faucet_config: FaucetConfig,
 }
 
 fn parse_duration_in_seconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
     Ok(Duration::from_secs(u64::from_str(src)?))
 }
 
+fn parse_duration_in_milliseconds(src: &str) -> Result<Duration, std::num::ParseIntError> {
+    Ok(Duration::from_millis(u64::from_str(src)?))
+}
+
 fn parse_quorum_set_from_json(src: &str) -> Result<QuorumSet<ResponderId>, String> {
     Ok(serde_json::from_str(src)
         .map_err(|err| format!("Error parsing quorum set {}: {:?}", src, err))?)
 }
 
+/// Extracts the MRENCLAVE and MRSIGNER values from an enclave signature.
+fn sigstruct_measurements(sigstruct: &Signature) -> Result<(MrEnclave, MrSigner), Error> {
+    let mr_enclave = MrEnclave::try_from(&sigstruct.mrenclave()[..])
+        .map_err(|_| Error::MeasurementMismatch("could not parse MRENCLAVE".to_string()))?;
+    let mr_signer = MrSigner::try_from(&sigstruct.mrsigner()[..])
+        .map_err(|_| Error::MeasurementMismatch("could not parse MRSIGNER".to_string()))?;
+    Ok((mr_enclave, mr_signer))
+}
+
+fn parse_mr_enclave(src: &str) -> Result<MrEnclave, String> {
+    MrEnclave::from_hex(src).map_err(|err| format!("Error parsing MRENCLAVE {}: {:?}", src, err))
+}
+
+fn parse_mr_signer(src: &str) -> Result<MrSigner, String> {
+    MrSigner::from_hex(src).map_err(|err| format!("Error parsing MRSIGNER {}: {:?}", src, err))
+}
+
+fn parse_ed25519_public(src: &str) -> Result<Ed25519Public, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Error decoding {}: {}", src, err))?;
+    Ed25519Public::try_from(&bytes[..])
+        .map_err(|err| format!("Error parsing public key {}: {:?}", src, err))
+}
+
+fn parse_fee_recipient(src: &str) -> Result<FeeRecipient, String> {
+    let parts: Vec<&str> = src.split(':').collect();
+    if parts.len() != 3 {
+        return Err(format!(
+            "Error parsing fee recipient {}: expected <spend public key>:<view public key>:<weight>",
+            src
+        ));
+    }
+    let public_key = |hex_key: &str| -> Result<RistrettoPublic, String> {
+        let bytes =
+            hex::decode(hex_key).map_err(|err| format!("Error decoding {}: {}", hex_key, err))?;
+        RistrettoPublic::try_from(&bytes[..])
+            .map_err(|err| format!("Error parsing public key {}: {:?}", hex_key, err))
+    };
+    let address = PublicAddress::new(&public_key(parts[0])?, &public_key(parts[1])?);
+    let weight = u32::from_str(parts[2])
+        .map_err(|err| format!("Error parsing weight {}: {}", parts[2], err))?;
+    Ok(FeeRecipient::new(address, weight))
+}
+
+fn parse_ed25519_pair(src: &str) -> Result<Arc<Ed25519Pair>, String> {
+    let bytes = hex::decode(src).map_err(|err| format!("Error decoding private key: {}", err))?;
+    let private_key = Ed25519Private::try_from(&bytes[..])
+        .map_err(|err| format!("Error parsing private key: {:?}", err))?;
+    Ok(Arc::new(Ed25519Pair::from(private_key)))
+}
+
 impl Config {
+    /// How the network splits block fees, if fee recipients were provided.
+    pub fn fee_split(&self) -> Result<Option<FeeSplit>, Error> {
+        if self.fee_recipients.is_empty() {
+            return Ok(None);
+        }
+        FeeSplit::new(self.fee_recipients.clone())
+            .map(Some)
+            .map_err(|err| Error::InvalidArgument("fee-recipient".to_string(), err.to_string()))
+    }
+
+    /// The keys used to sign and check account snapshots.
+    pub fn snapshot_signers(&self) -> SnapshotSigners {
+        SnapshotSigners {
+            signer: self.snapshot_signer_key.clone(),
+            trusted_signers: self.trusted_snapshot_signers.clone(),
+        }
+    }
+
+    /// The secret the mobilecoind database is encrypted with, if provided. An encrypted database
+    /// stays locked until unlocked over the API otherwise.
+    pub fn mobilecoind_db_secret(&self) -> Result<Option<DatabaseSecret>, Error> {
+        match (
+            &self.mobilecoind_db_password_file,
+            &self.mobilecoind_db_keyfile,
+        ) {
+            (Some(path), _) => Ok(Some(DatabaseSecret::read_password_file(path)?)),
+            (None, Some(path)) => Ok(Some(DatabaseSecret::read_keyfile(path)?)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// The tenants bound to API tokens. Empty unless mobilecoind is shared by tenants.
+    pub fn tenant_tokens(&self) -> Result<TenantTokens, Error> {
+        match &self.tenant_tokens {
+            Some(path) => TenantTokens::load(path),
+            None => Ok(TenantTokens::default()),
+        }
+    }
+
+    /// The webhooks events are delivered to: every `--webhook-url` receives all events, and the
+    /// subscriptions in `--webhook-subscriptions` receive the events matching their criteria.
+    pub fn webhook_subscriptions(&self) -> Result<Vec<WebhookSubscription>, Error> {
+        let mut subscriptions: Vec<WebhookSubscription> = self
+            .webhook_urls
+            .iter()
+            .cloned()
+            .map(WebhookSubscription::all_events)
+            .collect();
+        if let Some(path) = &self.webhook_subscriptions {
+            subscriptions.extend(WebhookSubscription::load(path)?);
+        }
+        Ok(subscriptions)
+    }
+
+    /// Queue depths and parallelism of syncing blocks.
+    pub fn sync_pipeline_config(&self) -> SyncPipelineConfig {
+        SyncPipelineConfig {
+            num_fetch_workers: self.sync_fetch_workers,
+            fetch_queue_depth: self.sync_fetch_queue_depth,
+            append_queue_depth: self.sync_append_queue_depth,
+            append_batch_size: self.sync_append_batch_size,
+        }
+    }
+
+    /// How failed requests to tx sources are retried.
+    pub fn tx_source_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.tx_source_max_attempts,
+            initial_backoff: self.tx_source_initial_backoff,
+            max_backoff: self.tx_source_max_backoff,
+            request_timeout: self.tx_source_request_timeout,
+        }
+    }
+
+    /// The policy for consolidating UTXOs in the background, if enabled.
+    pub fn consolidation_policy(&self) -> Option<ConsolidationPolicy> {
+        self.consolidation_min_utxos
+            .map(|min_utxos| ConsolidationPolicy {
+                min_utxos,
+                interval: self.consolidation_interval,
+            })
+    }
+
+    /// The policy for confirming blocks by signature quorum, if enabled.
+    pub fn confirmation_policy(&self) -> Option<ConfirmationPolicy> {
+        self.confirmation_quorum.map(|quorum| ConfirmationPolicy {
+            quorum,
+            signers: self.confirmation_signers.clone(),
+        })
+    }
+
     pub fn quorum_set(&self) -> QuorumSet<ResponderId> {
         // If we have an explicit quorum set, use that.
         if let Some(quorum_set) = &self.quorum_set {
@@ -89,13 +519,121 @@ impl Config {
             .collect::<Vec<ResponderId>>();
         QuorumSet::new_with_node_ids(node_ids.len() as u32, node_ids)
     }
+
+    /// Fills in any peers, quorum set and tx sources not provided on the command line from a
+    /// discovered network configuration.
+    pub fn apply_network_config(&mut self, network_config: &NetworkConfig) -> Result<(), Error> {
+        if self.peers_config.peers.is_empty() {
+            self.peers_config.peers = network_config.peer_uris()?;
+        }
+        if self.quorum_set.is_none() {
+            self.quorum_set = network_config.quorum_set.clone();
+        }
+        if self.tx_source_urls.is_empty() {
+            self.tx_source_urls = network_config.tx_source_urls.clone();
+        }
+
+        if self.peers_config.peers.is_empty() {
+            return Err(Error::Discovery("No peers configured".to_string()));
+        }
+        if self.tx_source_urls.is_empty() {
+            return Err(Error::Discovery("No tx sources configured".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks the compiled-in consensus enclave signature against the operator-provided
+    /// expectations, if any.
+    ///
+    /// # Arguments
+    /// * `sigstruct` - The consensus enclave signature mobilecoind was compiled against.
+    pub fn check_consensus_measurement(&self, sigstruct: &Signature) -> Result<(), Error> {
+        let (mr_enclave, mr_signer) = sigstruct_measurements(sigstruct)?;
+
+        if let Some(path) = &self.expected_consensus_css {
+            let expected = Signature::try_from(&fs::read(path)?[..])?;
+            let (expected_mr_enclave, expected_mr_signer) = sigstruct_measurements(&expected)?;
+
+            if expected_mr_signer != mr_signer {
+                return Err(Error::MeasurementMismatch(format!(
+                    "{:?} has MRSIGNER {}, compiled-in MRSIGNER is {}",
+                    path, expected_mr_signer, mr_signer
+                )));
+            }
+            if expected_mr_enclave != mr_enclave {
+                return Err(Error::MeasurementMismatch(format!(
+                    "{:?} has MRENCLAVE {}, compiled-in MRENCLAVE is {}",
+                    path, expected_mr_enclave, mr_enclave
+                )));
+            }
+        }
+
+        if let Some(expected_mr_signer) = &self.expected_mr_signer {
+            if *expected_mr_signer != mr_signer {
+                return Err(Error::MeasurementMismatch(format!(
+                    "expected MRSIGNER {}, compiled-in MRSIGNER is {}",
+                    expected_mr_signer, mr_signer
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The verifier used to attest to peers, which accepts the compiled-in consensus enclave
+    /// signer as well as any additionally accepted enclaves.
+    ///
+    /// # Arguments
+    /// * `sigstruct` - The consensus enclave signature mobilecoind was compiled against.
+    pub fn consensus_verifier(&self, sigstruct: &Signature) -> Result<Verifier, Error> {
+        let (_mr_enclave, mr_signer) = sigstruct_measurements(sigstruct)?;
+
+        let mut builder = VerifierBuilder::default();
+        builder
+            .identity(ThickClient::node_identity(mr_signer))
+            .allow_debug(attest::DEBUG_ENCLAVE);
+        for mr_signer in &self.accepted_mr_signers {
+            builder.identity(ThickClient::node_identity(*mr_signer));
+        }
+        for mr_enclave in &self.accepted_mr_enclaves {
+            builder.identity(ThickClient::node_identity(*mr_enclave));
+        }
+
+        Ok(builder
+            .build()
+            .expect("The compiled-in consensus enclave identity is always accepted"))
+    }
+
+    /// The verifier used to attest to peers transactions are submitted to, if submission is
+    /// restricted to particular enclaves. Returns None if transactions may be submitted to any
+    /// peer accepted by `consensus_verifier`.
+    pub fn submission_verifier(&self) -> Option<Verifier> {
+        if self.submit_mr_signers.is_empty() && self.submit_mr_enclaves.is_empty() {
+            return None;
+        }
+
+        let mut builder = VerifierBuilder::default();
+        builder.allow_debug(attest::DEBUG_ENCLAVE);
+        for mr_signer in &self.submit_mr_signers {
+            builder.identity(ThickClient::node_identity(*mr_signer));
+        }
+        for mr_enclave in &self.submit_mr_enclaves {
+            builder.identity(ThickClient::node_identity(*mr_enclave));
+        }
+
+        Some(
+            builder
+                .build()
+                .expect("At least one submission enclave identity is given"),
+        )
+    }
 }
 
 #[derive(Clone, Debug, StructOpt)]
 #[structopt()]
 pub struct PeersConfig {
     /// validator nodes to connect to.
-    #[structopt(long = "peer", required = true, min_values = 1)]
+    #[structopt(long = "peer", required_unless = "discovery-url", min_values = 1)]
     pub peers: Vec<ConsensusClientUri>,
 }
 
@@ -112,18 +650,16 @@ impl PeersConfig {
 
     pub fn create_peers(
         &self,
-        expected_measurement: impl Into<Measurement>,
+        verifier: Verifier,
         grpc_env: Arc<grpcio::Environment>,
         logger: Logger,
     ) -> Vec<ThickClient> {
-        let measurement = expected_measurement.into();
-
         self.peers
             .iter()
             .map(|client_uri| {
-                ThickClient::new(
+                ThickClient::new_with_verifier(
                     client_uri.clone(),
-                    measurement,
+                    verifier.clone(),
                     grpc_env.clone(),
                     logger.clone(),
                 )
@@ -134,7 +670,7 @@ impl PeersConfig {
 
     pub fn create_peer_manager(
         &self,
-        measurement: impl Into<Measurement>,
+        verifier: Verifier,
         logger: &Logger,
     ) -> ConnectionManager<ThickClient> {
         let grpc_env = Arc::new(
@@ -142,8 +678,136 @@ impl PeersConfig {
                 .name_prefix("RPC".to_string())
                 .build(),
         );
-        let peers = self.create_peers(measurement, grpc_env, logger.clone());
+        let peers = self.create_peers(verifier, grpc_env, logger.clone());
 
         ConnectionManager::new(peers, logger.clone())
     }
 }
+
+/// Sizes of mobilecoind's worker pools. Each pool defaults to the number of logical CPU cores.
+#[derive(Clone, Copy, Debug, Default, StructOpt)]
+#[structopt()]
+pub struct WorkerPoolsConfig {
+    /// Number of threads handling API requests.
+    #[structopt(long)]
+    pub num_api_threads: Option<usize>,
+
+    /// Number of worker threads to use for view key scanning.
+    #[structopt(long, alias = "num-workers")]
+    pub num_scan_workers: Option<usize>,
+
+    /// Number of worker threads building transactions, so that building them does not hold up
+    /// the threads handling API requests.
+    #[structopt(long)]
+    pub num_tx_build_workers: Option<usize>,
+}
+
+/// Mutual TLS on the gRPC link between mobilecoind and its HTTP/JSON gateway. The link is
+/// plaintext if not provided.
+#[derive(Clone, Debug, Default, StructOpt)]
+#[structopt()]
+pub struct TlsConfig {
+    /// Path to the PEM certificate chain presented to the other side of the link.
+    #[structopt(long, parse(from_os_str), requires_all = &["tls-key", "tls-ca"])]
+    pub tls_chain: Option<PathBuf>,
+
+    /// Path to the PEM private key of the certificate.
+    #[structopt(long, parse(from_os_str), requires = "tls-chain")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Path to the PEM bundle of CA certificates trusted to sign the other side's certificate.
+    #[structopt(long, parse(from_os_str), requires = "tls-chain")]
+    pub tls_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Load the certificates and key, if TLS is enabled.
+    pub fn load(&self) -> Result<Option<MutualTlsConfig>, Error> {
+        match (&self.tls_chain, &self.tls_key, &self.tls_ca) {
+            (Some(chain), Some(key), Some(ca)) => Ok(Some(MutualTlsConfig::load(chain, key, ca)?)),
+            (None, None, None) => Ok(None),
+            _ => Err(Error::Tls(
+                "--tls-chain, --tls-key and --tls-ca must be provided together".to_string(),
+            )),
+        }
+    }
+}
+
+/// Configuration of the signatures requests to the HTTP/JSON gateway need, see `request_signing`.
+#[derive(Clone, Debug, StructOpt)]
+pub struct RequestSigningConfig {
+    /// Path to a JSON file listing the HMAC keys requests to the HTTP/JSON gateway are signed
+    /// with. Requests to all methods but the read-only ones, e.g. GetBalance, must then be signed.
+    #[structopt(long, parse(from_os_str))]
+    pub request_signing_keys: Option<PathBuf>,
+
+    /// How many seconds the timestamp of a signed request may be off from the gateway's clock.
+    /// Nonces are remembered for as long.
+    #[structopt(long, default_value = "300", parse(try_from_str=parse_duration_in_seconds))]
+    pub request_signing_window: Duration,
+}
+
+impl RequestSigningConfig {
+    /// Load the signing keys, if requests need to be signed.
+    pub fn load(&self) -> Result<Option<RequestVerifier>, Error> {
+        self.request_signing_keys
+            .as_ref()
+            .map(|path| RequestVerifier::load(path, self.request_signing_window))
+            .transpose()
+    }
+}
+
+/// Configuration of a standalone HTTP/JSON gateway, forwarding requests to a mobilecoind that may
+/// run on another host.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "mobilecoind-http-gateway",
+    about = "Serves the API of a mobilecoind as JSON over HTTP."
+)]
+pub struct HttpGatewayConfig {
+    /// Port to serve HTTP requests from.
+    #[structopt(long)]
+    pub http_port: u16,
+
+    /// Address (host:port) of the mobilecoind gRPC API.
+    #[structopt(long, default_value = "127.0.0.1:4444")]
+    pub mobilecoind_addr: String,
+
+    /// Name the mobilecoind certificate is checked against, if it isn't issued for the host in
+    /// --mobilecoind-addr.
+    #[structopt(long, requires = "tls-chain")]
+    pub tls_hostname_override: Option<String>,
+
+    #[structopt(flatten)]
+    pub tls: TlsConfig,
+
+    #[structopt(flatten)]
+    pub request_signing: RequestSigningConfig,
+}
+
+/// Configuration of the developer faucet, which hands out funds from a monitor to any address
+/// that asks for them.
+#[cfg(feature = "dev")]
+#[derive(Clone, Debug, StructOpt)]
+#[structopt()]
+pub struct FaucetConfig {
+    /// Port to serve faucet requests from. The faucet is disabled if not provided.
+    #[structopt(long, requires = "faucet-monitor-id")]
+    pub faucet_port: Option<u16>,
+
+    /// Hex-encoded id of the monitor funds are sent from. The monitor must already exist.
+    #[structopt(long, parse(try_from_str=hex::decode))]
+    pub faucet_monitor_id: Option<Vec<u8>>,
+
+    /// Subaddress funds are sent from.
+    #[structopt(long, default_value = "0")]
+    pub faucet_subaddress: u64,
+
+    /// Maximal value sent per request, e.g. "1.5 MOB" or "1500 pMOB". Bare numbers are in picoMOB.
+    #[structopt(long, default_value = "1 MOB")]
+    pub faucet_max_value: PicoMob,
+
+    /// Minimal number of seconds between two requests for the same address.
+    #[structopt(long, default_value = "60", parse(try_from_str=parse_duration_in_seconds))]
+    pub faucet_rate_limit: Duration,
+}
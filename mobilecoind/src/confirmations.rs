@@ -0,0 +1,305 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Tracks how much of the ledger has been confirmed by a quorum of block signatures.
+//!
+//! Blocks are synced from whichever transaction source is next in line, so a synced block is only
+//! as trustworthy as that one source. A block is confirmed once the archives of enough distinct
+//! nodes serve it with a valid signature.
+//!
+//! The time a block is first seen signed is recorded in the mobilecoind database, and reported in
+//! the transaction history together with whether a quorum or a single signer vouched for it.
+
+use crate::{
+    block_timestamp_store::{BlockTimestamp, TimestampConfidence},
+    database::Database,
+    error::Error,
+    monitor_store::MonitorId,
+    processed_block_store::ProcessedTxOutDirection,
+};
+use common::{
+    logger::{log, Logger},
+    HashMap, HashSet,
+};
+use keys::Ed25519Public;
+use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::ReqwestTransactionsFetcher;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use transaction::BlockSignature;
+
+/// How many signatures a block needs before it is considered confirmed.
+#[derive(Clone, Debug)]
+pub struct ConfirmationPolicy {
+    /// The number of distinct signers required.
+    pub quorum: usize,
+
+    /// The signers whose signatures count towards the quorum. Any signer counts if empty.
+    pub signers: Vec<Ed25519Public>,
+}
+
+impl ConfirmationPolicy {
+    /// Returns true if the given (valid) signatures of a block satisfy this policy.
+    pub fn is_satisfied_by(&self, signatures: &[BlockSignature]) -> bool {
+        let signers: HashSet<&Ed25519Public> = signatures
+            .iter()
+            .map(|signature| signature.signer())
+            .filter(|signer| self.signers.is_empty() || self.signers.contains(signer))
+            .collect();
+        signers.len() >= self.quorum
+    }
+
+    /// The confidence of a timestamp taken when a block was seen with the given (valid)
+    /// signatures, or None if it was not seen signed.
+    pub fn timestamp_confidence(
+        &self,
+        signatures: &[BlockSignature],
+    ) -> Option<TimestampConfidence> {
+        if signatures.is_empty() {
+            None
+        } else if self.is_satisfied_by(signatures) {
+            Some(TimestampConfidence::Quorum)
+        } else {
+            Some(TimestampConfidence::SingleSigner)
+        }
+    }
+}
+
+/// The number of blocks confirmed so far. Shared between the confirmation thread and the API.
+#[derive(Clone, Default)]
+pub struct BlockConfirmations {
+    confirmed_block_count: Arc<AtomicU64>,
+}
+
+impl BlockConfirmations {
+    /// The number of blocks, starting at the origin block, that have been confirmed.
+    pub fn confirmed_block_count(&self) -> u64 {
+        self.confirmed_block_count.load(Ordering::SeqCst)
+    }
+
+    fn set_confirmed_block_count(&self, confirmed_block_count: u64) {
+        self.confirmed_block_count
+            .store(confirmed_block_count, Ordering::SeqCst);
+    }
+
+    /// The balance of a monitor's subaddress as of the last confirmed block. Changes in blocks
+    /// the monitor processed past that point are undone.
+    pub fn confirmed_balance(
+        &self,
+        mobilecoind_db: &Database,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+        synced_balance: u64,
+    ) -> Result<u64, Error> {
+        Ok(self.confirmed_balances(
+            mobilecoind_db,
+            monitor_id,
+            &[(subaddress_index, synced_balance)],
+        )?[0])
+    }
+
+    /// The balances of several subaddresses of a monitor as of the last confirmed block, given
+    /// their (subaddress index, synced balance) pairs. The blocks past that point are only read
+    /// once.
+    pub fn confirmed_balances(
+        &self,
+        mobilecoind_db: &Database,
+        monitor_id: &MonitorId,
+        synced_balances: &[(u64, u64)],
+    ) -> Result<Vec<u64>, Error> {
+        let unconfirmed_tx_outs = mobilecoind_db.get_processed_tx_out_history(
+            monitor_id,
+            self.confirmed_block_count(),
+            u64::max_value(),
+        )?;
+
+        let mut balances: HashMap<u64, i128> = synced_balances
+            .iter()
+            .map(|(subaddress_index, synced_balance)| (*subaddress_index, *synced_balance as i128))
+            .collect();
+        for tx_out in unconfirmed_tx_outs.iter() {
+            if let Some(balance) = balances.get_mut(&tx_out.subaddress_index) {
+                match tx_out.get_direction() {
+                    ProcessedTxOutDirection::Received | ProcessedTxOutDirection::Change => {
+                        *balance -= tx_out.value as i128
+                    }
+                    ProcessedTxOutDirection::Spent => *balance += tx_out.value as i128,
+                    ProcessedTxOutDirection::Invalid => {}
+                }
+            }
+        }
+        Ok(synced_balances
+            .iter()
+            .map(|(subaddress_index, _synced_balance)| balances[subaddress_index].max(0) as u64)
+            .collect())
+    }
+}
+
+/// A thread confirming synced blocks, in order, by fetching their signatures from every
+/// transaction source. If given a mobilecoind database, it also records when each block was
+/// first seen signed.
+pub struct ConfirmationThread {
+    /// The thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ConfirmationThread {
+    pub fn start(
+        ledger_db: LedgerDB,
+        mobilecoind_db: Option<Database>,
+        transactions_fetcher: ReqwestTransactionsFetcher,
+        policy: ConfirmationPolicy,
+        confirmations: BlockConfirmations,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("confirmations".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "ConfirmationThread started.");
+
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        let num_blocks = ledger_db.num_blocks().unwrap_or(0);
+
+                        // The origin block is not signed, and is trusted by construction.
+                        let mut confirmed_block_count =
+                            confirmations.confirmed_block_count().max(num_blocks.min(1));
+
+                        while confirmed_block_count < num_blocks
+                            && !thread_stop_requested.load(Ordering::SeqCst)
+                        {
+                            let block = match ledger_db.get_block(confirmed_block_count) {
+                                Ok(block) => block,
+                                Err(err) => {
+                                    log::error!(
+                                        logger,
+                                        "Failed getting block {}: {:?}",
+                                        confirmed_block_count,
+                                        err
+                                    );
+                                    break;
+                                }
+                            };
+
+                            let signatures = transactions_fetcher.get_block_signatures(&block);
+                            if let (Some(mobilecoind_db), Some(confidence)) = (
+                                mobilecoind_db.as_ref(),
+                                policy.timestamp_confidence(&signatures),
+                            ) {
+                                let now = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|duration| duration.as_secs())
+                                    .unwrap_or(0);
+                                if let Err(err) = mobilecoind_db.observe_block_timestamp(
+                                    block.index,
+                                    &BlockTimestamp::new(now, confidence),
+                                ) {
+                                    log::error!(
+                                        logger,
+                                        "Failed recording timestamp of block {}: {:?}",
+                                        block.index,
+                                        err
+                                    );
+                                }
+                            }
+
+                            if !policy.is_satisfied_by(&signatures) {
+                                log::debug!(
+                                    logger,
+                                    "Block {} has {} of {} signatures needed",
+                                    block.index,
+                                    signatures.len(),
+                                    policy.quorum,
+                                );
+                                break;
+                            }
+                            confirmed_block_count += 1;
+                        }
+
+                        confirmations.set_confirmed_block_count(confirmed_block_count);
+                        thread::sleep(poll_interval);
+                    }
+
+                    log::debug!(logger, "ConfirmationThread stopped.");
+                })
+                .expect("failed starting confirmation thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("ConfirmationThread join failed");
+        }
+    }
+}
+
+impl Drop for ConfirmationThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::{Ed25519Pair, FromRandom};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::Block;
+
+    #[test]
+    // Only distinct, accepted signers should count towards the quorum.
+    fn test_policy_counts_distinct_accepted_signers() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let block = Block::new_origin_block(&[]);
+        let signers: Vec<Ed25519Pair> =
+            (0..3).map(|_| Ed25519Pair::from_random(&mut rng)).collect();
+        let signatures: Vec<BlockSignature> = signers
+            .iter()
+            .map(|signer| BlockSignature::from_block_and_keypair(&block, signer).unwrap())
+            .collect();
+
+        let any_two = ConfirmationPolicy {
+            quorum: 2,
+            signers: Vec::new(),
+        };
+        assert!(!any_two.is_satisfied_by(&signatures[..1]));
+        assert!(!any_two.is_satisfied_by(&[signatures[0].clone(), signatures[0].clone()]));
+        assert!(any_two.is_satisfied_by(&signatures[..2]));
+
+        let two_of_known = ConfirmationPolicy {
+            quorum: 2,
+            signers: vec![signers[0].public_key(), signers[2].public_key()],
+        };
+        assert!(!two_of_known.is_satisfied_by(&signatures[..2]));
+        assert!(two_of_known.is_satisfied_by(&signatures));
+
+        // Timestamps are vouched for by a quorum once the policy is satisfied.
+        assert_eq!(two_of_known.timestamp_confidence(&[]), None);
+        assert_eq!(
+            two_of_known.timestamp_confidence(&signatures[..2]),
+            Some(TimestampConfidence::SingleSigner)
+        );
+        assert_eq!(
+            two_of_known.timestamp_confidence(&signatures),
+            Some(TimestampConfidence::Quorum)
+        );
+    }
+}
@@ -0,0 +1,282 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Background consolidation of UTXOs.
+//!
+//! A subaddress that receives many small payments ends up holding many small UTXOs, and a later
+//! payment might need more of them than a transaction can spend. When enabled, a background thread
+//! periodically looks for subaddresses holding at least a configured number of spendable UTXOs,
+//! and merges the smallest of them into the largest one by paying the subaddress itself, exactly
+//! like `GenerateOptimizationTx`.
+//! Consolidation only happens while a subaddress is idle: its monitor is synced to the end of the
+//! ledger, none of its UTXOs are spent by a pending transaction and no queued payment is waiting
+//! on it. Merges that would cost more in fees than the merged UTXOs are worth are skipped.
+
+use crate::{
+    database::Database,
+    deadline::Deadline,
+    error::Error,
+    monitor_store::MonitorId,
+    payment_queue::{split_pending_utxos, submit_payment, PaymentQueue},
+    payments::TransactionsManager,
+    utxo_store::UnspentTxOut,
+};
+use common::logger::{log, Logger};
+use ledger_db::{Ledger, LedgerDB};
+use mcconnection::UserTxConnection;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use transaction::constants::MAX_INPUTS;
+
+/// How often the consolidation thread checks whether it was asked to stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When UTXOs are consolidated.
+#[derive(Clone, Debug)]
+pub struct ConsolidationPolicy {
+    /// Subaddresses holding at least this many spendable UTXOs are consolidated. Values below 2
+    /// are treated as 2.
+    pub min_utxos: usize,
+
+    /// How often subaddresses are checked.
+    pub interval: Duration,
+}
+
+impl ConsolidationPolicy {
+    /// The number of UTXOs a consolidation transaction for a subaddress holding `utxos` should
+    /// merge, or None if the subaddress should not be consolidated now.
+    pub fn num_inputs_to_merge(&self, utxos: Vec<UnspentTxOut>, num_blocks: u64) -> Option<usize> {
+        let (spendable_utxos, pending_utxos) = split_pending_utxos(utxos, num_blocks);
+
+        // Wait for pending transactions, whose change might be merged as well.
+        if !pending_utxos.is_empty() || spendable_utxos.len() < std::cmp::max(self.min_utxos, 2) {
+            return None;
+        }
+
+        Some(std::cmp::min(spendable_utxos.len(), MAX_INPUTS as usize))
+    }
+}
+
+/// Consolidation thread - holds objects needed to cleanly terminate the thread.
+pub struct ConsolidationThread {
+    /// The thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl ConsolidationThread {
+    pub fn start<T: UserTxConnection + 'static>(
+        policy: ConsolidationPolicy,
+        payment_queue: PaymentQueue,
+        transactions_manager: TransactionsManager<T>,
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("consolidation".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "ConsolidationThread started.");
+
+                    let mut last_run: Option<Instant> = None;
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        if last_run.map_or(true, |last_run| last_run.elapsed() >= policy.interval) {
+                            last_run = Some(Instant::now());
+                            if let Err(err) = consolidate_idle_subaddresses(
+                                &policy,
+                                &payment_queue,
+                                &transactions_manager,
+                                &ledger_db,
+                                &mobilecoind_db,
+                                &logger,
+                            ) {
+                                log::error!(logger, "Failed consolidating UTXOs: {}", err);
+                            }
+                        }
+
+                        thread::sleep(STOP_POLL_INTERVAL);
+                    }
+
+                    log::debug!(logger, "ConsolidationThread stopped.");
+                })
+                .expect("failed starting consolidation thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("ConsolidationThread join failed");
+        }
+    }
+}
+
+impl Drop for ConsolidationThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Submit a consolidation transaction for every idle subaddress holding enough UTXOs.
+fn consolidate_idle_subaddresses<T: UserTxConnection + 'static>(
+    policy: &ConsolidationPolicy,
+    payment_queue: &PaymentQueue,
+    transactions_manager: &TransactionsManager<T>,
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let num_blocks = ledger_db.num_blocks()?;
+
+    for (monitor_id, monitor_data) in mobilecoind_db.get_monitor_map()? {
+        // Funds found in blocks the monitor did not process yet could be pending.
+        if monitor_data.is_watch_only() || monitor_data.next_block < num_blocks {
+            continue;
+        }
+
+        for subaddress_index in monitor_data.subaddress_indexes() {
+            if payment_queue.has_queued_payments(&monitor_id, subaddress_index) {
+                continue;
+            }
+
+            let utxos = mobilecoind_db.get_utxos_for_subaddress(&monitor_id, subaddress_index)?;
+            if let Some(max_inputs) = policy.num_inputs_to_merge(utxos, num_blocks) {
+                consolidate_subaddress(
+                    transactions_manager,
+                    mobilecoind_db,
+                    &monitor_id,
+                    subaddress_index,
+                    max_inputs,
+                    logger,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Merge up to `max_inputs` UTXOs of a subaddress. Failures only affect this subaddress, so they
+/// are logged rather than returned.
+fn consolidate_subaddress<T: UserTxConnection + 'static>(
+    transactions_manager: &TransactionsManager<T>,
+    mobilecoind_db: &Database,
+    monitor_id: &MonitorId,
+    subaddress_index: u64,
+    max_inputs: usize,
+    logger: &Logger,
+) {
+    let tx_proposal = match transactions_manager.generate_optimization_tx_with_max_inputs(
+        monitor_id,
+        subaddress_index,
+        max_inputs,
+        &Deadline::Never,
+    ) {
+        Ok(tx_proposal) => tx_proposal,
+        Err(Error::OptimizationNotBeneficial(reason)) => {
+            log::debug!(
+                logger,
+                "Not consolidating subaddress {} of monitor {}: {}",
+                subaddress_index,
+                monitor_id,
+                reason
+            );
+            return;
+        }
+        Err(err) => {
+            log::error!(
+                logger,
+                "Failed building consolidation tx for subaddress {} of monitor {}: {}",
+                subaddress_index,
+                monitor_id,
+                err
+            );
+            return;
+        }
+    };
+
+    match submit_payment(transactions_manager, mobilecoind_db, &tx_proposal, logger) {
+        Ok(()) => log::info!(
+            logger,
+            "Submitted tx {} merging {} UTXOs of subaddress {} of monitor {}",
+            tx_proposal.tx.tx_hash(),
+            tx_proposal.utxos.len(),
+            subaddress_index,
+            monitor_id
+        ),
+        Err(err) => log::error!(
+            logger,
+            "Failed submitting consolidation tx for subaddress {} of monitor {}: {}",
+            subaddress_index,
+            monitor_id,
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use transaction::{ring_signature::KeyImage, tx::TxOut};
+
+    fn utxos(num_utxos: u64, num_pending: u64) -> Vec<UnspentTxOut> {
+        (0..num_utxos)
+            .map(|idx| UnspentTxOut {
+                tx_out: TxOut::default(),
+                subaddress_index: 0,
+                key_image: KeyImage::from(idx),
+                value: idx + 1,
+                attempted_spend_height: if idx < num_pending { 5 } else { 0 },
+                attempted_spend_tombstone: if idx < num_pending { 20 } else { 0 },
+                memo: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    // Only idle subaddresses holding enough UTXOs should be consolidated, merging as many UTXOs as
+    // a transaction can spend.
+    fn test_num_inputs_to_merge() {
+        let policy = ConsolidationPolicy {
+            min_utxos: 5,
+            interval: Duration::from_secs(60),
+        };
+
+        assert_eq!(policy.num_inputs_to_merge(utxos(4, 0), 10), None);
+        assert_eq!(policy.num_inputs_to_merge(utxos(5, 0), 10), Some(5));
+        assert_eq!(
+            policy.num_inputs_to_merge(utxos(MAX_INPUTS as u64 + 10, 0), 10),
+            Some(MAX_INPUTS as usize)
+        );
+
+        // Subaddresses with pending transactions are not idle.
+        assert_eq!(policy.num_inputs_to_merge(utxos(8, 1), 10), None);
+
+        // Once the pending transaction expired, its inputs can be merged as well.
+        assert_eq!(policy.num_inputs_to_merge(utxos(8, 1), 20), Some(8));
+
+        // A single UTXO cannot be merged.
+        let policy = ConsolidationPolicy {
+            min_utxos: 0,
+            interval: Duration::from_secs(60),
+        };
+        assert_eq!(policy.num_inputs_to_merge(utxos(1, 0), 10), None);
+        assert_eq!(policy.num_inputs_to_merge(utxos(2, 0), 10), Some(2));
+    }
+}
@@ -3,19 +3,33 @@
 //! Utilities for converting between `mobilecoind` and `mobilecoind_api` data types.
 
 use crate::{
-    payments::{Outlay, TxProposal},
+    address_book_store::AddressBookEntry,
+    approval_store::{TxApproval, TxApprovalStatus},
+    audit_log_store::{AuditAction, AuditEvent},
+    block_timestamp_store::TimestampConfidence,
+    coin_selection::CoinSelectionStrategy,
+    incoming_payment_store::IncomingPayment,
+    monitor_store::{MonitorPriority, WatchOnlyKey},
+    outgoing_tx_store::{OutgoingOutlay, OutgoingTx, OutgoingTxStatus, OutlayStatus},
+    payments::{Outlay, TxProposal, UnsignedTxProposal},
+    processed_block_store::{ProcessedTxOut, ProcessedTxOutDirection},
+    spent_txo_store::SpentTxOut,
+    transaction_log_store::TransactionLogEntry,
     utxo_store::UnspentTxOut,
 };
 
 use common::HashMap;
+use keys::{RistrettoPrivate, RistrettoPublic};
 use mobilecoind_api::{self, ConversionError};
 use protobuf::RepeatedField;
 use std::{convert::TryFrom, iter::FromIterator};
 use transaction::{
     account_keys::PublicAddress,
-    ring_signature::KeyImage,
-    tx::{Tx, TxOut},
+    ring_signature::{CurveScalar, KeyImage},
+    tx::{Tx, TxOut, TxPrefix},
+    tx_out_confirmation_number::TxOutConfirmationNumber,
 };
+use transaction_std::{InputToSign, UnsignedTx};
 
 impl From<&UnspentTxOut> for mobilecoind_api::UnspentTxOut {
     fn from(src: &UnspentTxOut) -> Self {
@@ -27,6 +41,7 @@ impl From<&UnspentTxOut> for mobilecoind_api::UnspentTxOut {
         dst.set_value(src.value);
         dst.set_attempted_spend_height(src.attempted_spend_height);
         dst.set_attempted_spend_tombstone(src.attempted_spend_tombstone);
+        dst.set_memo(src.memo.clone());
 
         dst
     }
@@ -42,6 +57,7 @@ impl TryFrom<&mobilecoind_api::UnspentTxOut> for UnspentTxOut {
         let value = src.value;
         let attempted_spend_height = src.attempted_spend_height;
         let attempted_spend_tombstone = src.attempted_spend_tombstone;
+        let memo = src.memo.clone();
 
         Ok(Self {
             tx_out,
@@ -50,10 +66,293 @@ impl TryFrom<&mobilecoind_api::UnspentTxOut> for UnspentTxOut {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            memo,
         })
     }
 }
 
+impl From<&WatchOnlyKey> for mobilecoind_api::WatchOnlyKey {
+    fn from(src: &WatchOnlyKey) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_view_private_key((&src.view_private_key).into());
+        dst.set_spend_public_key((&src.spend_public_key).into());
+
+        dst
+    }
+}
+
+impl TryFrom<&mobilecoind_api::WatchOnlyKey> for WatchOnlyKey {
+    type Error = ConversionError;
+
+    fn try_from(src: &mobilecoind_api::WatchOnlyKey) -> Result<Self, Self::Error> {
+        let view_private_key = RistrettoPrivate::try_from(src.get_view_private_key())?;
+        let spend_public_key = RistrettoPublic::try_from(src.get_spend_public_key())?;
+
+        Ok(Self::new(view_private_key, spend_public_key))
+    }
+}
+
+impl From<&AddressBookEntry> for mobilecoind_api::AddressBookEntry {
+    fn from(src: &AddressBookEntry) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_label(src.label.clone());
+        dst.set_public_address((&src.public_address()).into());
+        dst.set_notes(src.notes.clone());
+
+        dst
+    }
+}
+
+impl From<MonitorPriority> for mobilecoind_api::MonitorPriority {
+    fn from(src: MonitorPriority) -> Self {
+        match src {
+            MonitorPriority::Normal => Self::MonitorPriorityNormal,
+            MonitorPriority::High => Self::MonitorPriorityHigh,
+            MonitorPriority::Low => Self::MonitorPriorityLow,
+        }
+    }
+}
+
+impl From<mobilecoind_api::MonitorPriority> for MonitorPriority {
+    fn from(src: mobilecoind_api::MonitorPriority) -> Self {
+        match src {
+            mobilecoind_api::MonitorPriority::MonitorPriorityNormal => Self::Normal,
+            mobilecoind_api::MonitorPriority::MonitorPriorityHigh => Self::High,
+            mobilecoind_api::MonitorPriority::MonitorPriorityLow => Self::Low,
+        }
+    }
+}
+
+impl From<ProcessedTxOutDirection> for mobilecoind_api::ProcessedTxOutDirection {
+    fn from(src: ProcessedTxOutDirection) -> Self {
+        match src {
+            ProcessedTxOutDirection::Invalid => Self::Invalid,
+            ProcessedTxOutDirection::Received => Self::Received,
+            ProcessedTxOutDirection::Spent => Self::Spent,
+            ProcessedTxOutDirection::Change => Self::Change,
+        }
+    }
+}
+
+impl From<&ProcessedTxOut> for mobilecoind_api::ProcessedTxOut {
+    fn from(src: &ProcessedTxOut) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_block_index(src.block_index);
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_public_key(src.public_key.into());
+        dst.set_key_image((&src.key_image).into());
+        dst.set_value(src.value);
+        dst.set_direction(src.get_direction().into());
+
+        dst
+    }
+}
+
+impl From<&IncomingPayment> for mobilecoind_api::IncomingPayment {
+    fn from(src: &IncomingPayment) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_block_index(src.block_index);
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_tx_out((&src.tx_out).into());
+        dst.set_key_image((&src.key_image).into());
+        dst.set_value(src.value);
+        dst.set_discovered_at(src.discovered_at);
+
+        dst
+    }
+}
+
+impl From<TimestampConfidence> for mobilecoind_api::TimestampConfidence {
+    fn from(src: TimestampConfidence) -> Self {
+        match src {
+            TimestampConfidence::Local => Self::TimestampLocal,
+            TimestampConfidence::SingleSigner => Self::TimestampSingleSigner,
+            TimestampConfidence::Quorum => Self::TimestampQuorum,
+        }
+    }
+}
+
+impl From<&TransactionLogEntry> for mobilecoind_api::TransactionLogEntry {
+    fn from(src: &TransactionLogEntry) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_block_index(src.block_index);
+        dst.set_subaddress_index(src.subaddress_index);
+        dst.set_public_key(src.public_key.into());
+        dst.set_key_image((&src.key_image).into());
+        dst.set_value(src.value);
+        dst.set_direction(src.get_direction().into());
+        dst.set_timestamp(src.timestamp);
+        dst.set_timestamp_confidence(src.get_timestamp_confidence().into());
+
+        dst
+    }
+}
+
+impl From<&SpentTxOut> for mobilecoind_api::SpentTxOut {
+    fn from(src: &SpentTxOut) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_utxo((&src.utxo).into());
+        dst.set_spent_block_index(src.spent_block_index);
+
+        dst
+    }
+}
+
+impl From<mobilecoind_api::CoinSelectionStrategy> for CoinSelectionStrategy {
+    fn from(src: mobilecoind_api::CoinSelectionStrategy) -> Self {
+        match src {
+            mobilecoind_api::CoinSelectionStrategy::CoinSelectionSmallestFirst => {
+                Self::SmallestFirst
+            }
+            mobilecoind_api::CoinSelectionStrategy::CoinSelectionLargestFirst => Self::LargestFirst,
+            mobilecoind_api::CoinSelectionStrategy::CoinSelectionRandom => Self::Random,
+        }
+    }
+}
+
+impl From<OutgoingTxStatus> for mobilecoind_api::OutgoingTxStatus {
+    fn from(src: OutgoingTxStatus) -> Self {
+        match src {
+            OutgoingTxStatus::Unknown => Self::OutgoingTxUnknown,
+            OutgoingTxStatus::Generated => Self::OutgoingTxGenerated,
+            OutgoingTxStatus::Submitted => Self::OutgoingTxSubmitted,
+            OutgoingTxStatus::Verified => Self::OutgoingTxVerified,
+            OutgoingTxStatus::TombstoneBlockExceeded => Self::OutgoingTxTombstoneBlockExceeded,
+        }
+    }
+}
+
+impl From<OutlayStatus> for mobilecoind_api::OutlayStatus {
+    fn from(src: OutlayStatus) -> Self {
+        match src {
+            OutlayStatus::Unknown => Self::OutlayUnknown,
+            OutlayStatus::Pending => Self::OutlayPending,
+            OutlayStatus::Landed => Self::OutlayLanded,
+            OutlayStatus::Failed => Self::OutlayFailed,
+        }
+    }
+}
+
+impl From<&OutgoingOutlay> for mobilecoind_api::OutgoingOutlayStatus {
+    fn from(src: &OutgoingOutlay) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_status(src.get_status().into());
+        if let Some(tx_out_public_key) = src.tx_out_public_key {
+            dst.set_tx_out_public_key(tx_out_public_key.into());
+        }
+
+        dst
+    }
+}
+
+impl From<&OutgoingTx> for mobilecoind_api::OutgoingTx {
+    fn from(src: &OutgoingTx) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_index(src.index);
+        dst.set_monitor_id(src.monitor_id.to_vec());
+        dst.set_tx_prefix_hash(src.tx_prefix_hash.clone());
+        dst.set_outlay_list(RepeatedField::from_vec(
+            src.outlays
+                .iter()
+                .map(|outlay| {
+                    (&Outlay {
+                        value: outlay.value,
+                        receiver: outlay.receiver(),
+                    })
+                        .into()
+                })
+                .collect(),
+        ));
+        dst.set_fee(src.fee);
+        dst.set_key_image_list(RepeatedField::from_vec(
+            src.key_images
+                .iter()
+                .map(|key_image| key_image.into())
+                .collect(),
+        ));
+        dst.set_tombstone(src.tombstone);
+        dst.set_status(src.get_status().into());
+        dst.set_submitted_block_height(src.submitted_block_height);
+        dst.set_final_block_index(src.final_block_index);
+        dst.set_outlay_status_list(RepeatedField::from_vec(
+            src.outlays.iter().map(|outlay| outlay.into()).collect(),
+        ));
+
+        dst
+    }
+}
+
+impl From<AuditAction> for mobilecoind_api::MonitorAuditAction {
+    fn from(src: AuditAction) -> Self {
+        match src {
+            AuditAction::Unknown => Self::MonitorAuditUnknown,
+            AuditAction::MonitorAdded => Self::MonitorAdded,
+            AuditAction::MonitorRemoved => Self::MonitorRemoved,
+            AuditAction::MonitorExported => Self::MonitorExported,
+            AuditAction::MonitorSnapshotExported => Self::MonitorSnapshotExported,
+            AuditAction::MonitorSnapshotImported => Self::MonitorSnapshotImported,
+            AuditAction::MonitorStateExported => Self::MonitorStateExported,
+            AuditAction::MonitorStateImported => Self::MonitorStateImported,
+            AuditAction::MonitorRescanned => Self::MonitorRescanned,
+            AuditAction::MonitorMetadataUpdated => Self::MonitorMetadataUpdated,
+            AuditAction::MonitorPriorityChanged => Self::MonitorPriorityChanged,
+        }
+    }
+}
+
+impl From<&AuditEvent> for mobilecoind_api::MonitorAuditEvent {
+    fn from(src: &AuditEvent) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_index(src.index);
+        dst.set_monitor_id(src.monitor_id.to_vec());
+        dst.set_action(src.get_action().into());
+        dst.set_timestamp(src.timestamp);
+        dst.set_caller(src.caller.clone());
+        dst.set_peer(src.peer.clone());
+
+        dst
+    }
+}
+
+impl From<TxApprovalStatus> for mobilecoind_api::TxApprovalStatus {
+    fn from(src: TxApprovalStatus) -> Self {
+        match src {
+            TxApprovalStatus::Unknown => Self::TxApprovalUnknown,
+            TxApprovalStatus::Pending => Self::TxApprovalPending,
+            TxApprovalStatus::Approved => Self::TxApprovalApproved,
+            TxApprovalStatus::Rejected => Self::TxApprovalRejected,
+            TxApprovalStatus::Submitted => Self::TxApprovalSubmitted,
+        }
+    }
+}
+
+impl From<&TxApproval> for mobilecoind_api::TxApproval {
+    fn from(src: &TxApproval) -> Self {
+        let mut dst = Self::new();
+
+        dst.set_tx_prefix_hash(src.tx_prefix_hash.clone());
+        dst.set_monitor_id(src.monitor_id.to_vec());
+        dst.set_value(src.value);
+        dst.set_fee(src.fee);
+        dst.set_status(src.get_status().into());
+        dst.set_requested_by(src.requested_by.clone());
+        dst.set_requested_at(src.requested_at);
+        dst.set_decided_by(src.decided_by.clone());
+        dst.set_decided_at(src.decided_at);
+
+        dst
+    }
+}
+
 impl From<&Outlay> for mobilecoind_api::Outlay {
     fn from(src: &Outlay) -> Self {
         let mut dst = Self::new();
@@ -93,6 +392,13 @@ impl From<&TxProposal> for mobilecoind_api::TxProposal {
                 .iter()
                 .map(|(key, val)| (*key as u64, *val as u64)),
         ));
+        dst.set_absorbed_change(src.absorbed_change);
+        dst.set_outlay_confirmation_number_list(RepeatedField::from_vec(
+            src.outlay_confirmation_numbers
+                .iter()
+                .map(|confirmation_number| confirmation_number.to_vec())
+                .collect(),
+        ));
 
         dst
     }
@@ -120,39 +426,232 @@ impl TryFrom<&mobilecoind_api::TxProposal> for TxProposal {
 
         let tx = Tx::try_from(src.get_tx())?;
 
-        let outlay_index_to_tx_out_index = HashMap::from_iter(
-            src.get_outlay_index_to_tx_out_index()
+        let outlay_index_to_tx_out_index = outlay_index_to_tx_out_index_from_proto(
+            src.get_outlay_index_to_tx_out_index(),
+            outlays.len(),
+            tx.prefix.outputs.len(),
+        )?;
+
+        // Absorbed change is part of the fee.
+        if src.absorbed_change > src.fee {
+            return Err(ConversionError::FeeMismatch);
+        }
+
+        let outlay_confirmation_numbers = outlay_confirmation_numbers_from_proto(
+            src.get_outlay_confirmation_number_list(),
+            outlays.len(),
+        )?;
+
+        Ok(Self {
+            utxos,
+            outlays,
+            tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+            absorbed_change: src.absorbed_change,
+        })
+    }
+}
+
+impl From<&UnsignedTxProposal> for mobilecoind_api::UnsignedTxProposal {
+    fn from(src: &UnsignedTxProposal) -> mobilecoind_api::UnsignedTxProposal {
+        let mut dst = mobilecoind_api::UnsignedTxProposal::new();
+
+        dst.set_input_list(RepeatedField::from_vec(
+            src.utxos.iter().map(|utxo| utxo.into()).collect(),
+        ));
+        dst.set_outlay_list(RepeatedField::from_vec(
+            src.outlays.iter().map(|outlay| outlay.into()).collect(),
+        ));
+        dst.set_tx_prefix((&src.unsigned_tx.prefix).into());
+        dst.set_input_to_sign_list(RepeatedField::from_vec(
+            src.unsigned_tx
+                .inputs
                 .iter()
-                .map(|(key, val)| (*key as usize, *val as usize)),
-        );
+                .map(|input| {
+                    let mut input_to_sign = mobilecoind_api::InputToSign::new();
+                    input_to_sign.set_real_index(input.real_index as u64);
+                    input_to_sign.set_value(input.value);
+                    input_to_sign.set_blinding((&CurveScalar::from(input.blinding)).into());
+                    input_to_sign
+                })
+                .collect(),
+        ));
+        dst.set_output_value_and_blinding_list(RepeatedField::from_vec(
+            src.unsigned_tx
+                .output_values_and_blindings()
+                .iter()
+                .map(|(value, blinding)| {
+                    let mut value_and_blinding = mobilecoind_api::ValueAndBlinding::new();
+                    value_and_blinding.set_value(*value);
+                    value_and_blinding.set_blinding((&CurveScalar::from(*blinding)).into());
+                    value_and_blinding
+                })
+                .collect(),
+        ));
+        dst.set_outlay_index_to_tx_out_index(std::collections::HashMap::from_iter(
+            src.outlay_index_to_tx_out_index
+                .iter()
+                .map(|(key, val)| (*key as u64, *val as u64)),
+        ));
+        dst.set_absorbed_change(src.absorbed_change);
+        dst.set_outlay_confirmation_number_list(RepeatedField::from_vec(
+            src.outlay_confirmation_numbers
+                .iter()
+                .map(|confirmation_number| confirmation_number.to_vec())
+                .collect(),
+        ));
+
+        dst.set_change_subaddress(src.change_subaddress);
+        dst
+    }
+}
+
+impl TryFrom<&mobilecoind_api::UnsignedTxProposal> for UnsignedTxProposal {
+    type Error = ConversionError;
 
-        // Check that none of the indices are out of bound.
-        if outlay_index_to_tx_out_index.len() != outlays.len() {
+    fn try_from(src: &mobilecoind_api::UnsignedTxProposal) -> Result<Self, Self::Error> {
+        let utxos = src
+            .get_input_list()
+            .iter()
+            .map(UnspentTxOut::try_from)
+            .collect::<Result<Vec<UnspentTxOut>, ConversionError>>()?;
+
+        let outlays = src
+            .get_outlay_list()
+            .iter()
+            .map(Outlay::try_from)
+            .collect::<Result<Vec<Outlay>, ConversionError>>()?;
+
+        let prefix = TxPrefix::try_from(src.get_tx_prefix())?;
+
+        // Each input signs the ring it is spent from in the prefix.
+        if src.get_input_to_sign_list().len() != prefix.inputs.len() {
             return Err(ConversionError::IndexOutOfBounds);
         }
-
-        for (outlay_index, tx_out_index) in outlay_index_to_tx_out_index.iter() {
-            if *outlay_index >= outlays.len() || *tx_out_index >= tx.prefix.outputs.len() {
-                return Err(ConversionError::IndexOutOfBounds);
-            }
+        let inputs = src
+            .get_input_to_sign_list()
+            .iter()
+            .zip(prefix.inputs.iter())
+            .map(|(input_to_sign, tx_in)| {
+                let real_index = input_to_sign.real_index as usize;
+                let real_output = tx_in
+                    .ring
+                    .get(real_index)
+                    .ok_or(ConversionError::IndexOutOfBounds)?;
+                Ok(InputToSign {
+                    ring: tx_in
+                        .ring
+                        .iter()
+                        .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
+                        .collect(),
+                    real_index,
+                    real_output_public_key: RistrettoPublic::try_from(&real_output.public_key)?,
+                    value: input_to_sign.value,
+                    blinding: CurveScalar::try_from(input_to_sign.get_blinding())?.scalar,
+                })
+            })
+            .collect::<Result<Vec<InputToSign>, ConversionError>>()?;
+
+        // There is a value and blinding per output, followed by the fee's.
+        if src.get_output_value_and_blinding_list().len() != prefix.outputs.len() + 1 {
+            return Err(ConversionError::IndexOutOfBounds);
         }
+        let output_values_and_blindings = src
+            .get_output_value_and_blinding_list()
+            .iter()
+            .map(|value_and_blinding| {
+                let blinding = CurveScalar::try_from(value_and_blinding.get_blinding())?;
+                Ok((value_and_blinding.value, blinding.scalar))
+            })
+            .collect::<Result<Vec<_>, ConversionError>>()?;
+
+        let outlay_index_to_tx_out_index = outlay_index_to_tx_out_index_from_proto(
+            src.get_outlay_index_to_tx_out_index(),
+            outlays.len(),
+            prefix.outputs.len(),
+        )?;
+
+        // Absorbed change is part of the fee.
+        if src.absorbed_change > prefix.fee {
+            return Err(ConversionError::FeeMismatch);
+        }
+
+        let outlay_confirmation_numbers = outlay_confirmation_numbers_from_proto(
+            src.get_outlay_confirmation_number_list(),
+            outlays.len(),
+        )?;
+
+        // The lengths were checked above, so only the fee can mismatch.
+        let unsigned_tx = UnsignedTx::new(prefix, inputs, output_values_and_blindings)
+            .map_err(|_| ConversionError::FeeMismatch)?;
 
         Ok(Self {
             utxos,
             outlays,
-            tx,
+            unsigned_tx,
             outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+            absorbed_change: src.absorbed_change,
+            change_subaddress: src.change_subaddress,
         })
     }
 }
 
+/// Checks that the map from each outlay to the TxOut created for it covers every outlay, and that
+/// none of the indices are out of bounds.
+fn outlay_index_to_tx_out_index_from_proto(
+    src: &std::collections::HashMap<u64, u64>,
+    num_outlays: usize,
+    num_outputs: usize,
+) -> Result<HashMap<usize, usize>, ConversionError> {
+    let outlay_index_to_tx_out_index =
+        HashMap::from_iter(src.iter().map(|(key, val)| (*key as usize, *val as usize)));
+
+    if outlay_index_to_tx_out_index.len() != num_outlays {
+        return Err(ConversionError::IndexOutOfBounds);
+    }
+
+    for (outlay_index, tx_out_index) in outlay_index_to_tx_out_index.iter() {
+        if *outlay_index >= num_outlays || *tx_out_index >= num_outputs {
+            return Err(ConversionError::IndexOutOfBounds);
+        }
+    }
+
+    Ok(outlay_index_to_tx_out_index)
+}
+
+/// Proposals created before confirmation numbers were introduced have none, otherwise there is one
+/// per outlay.
+fn outlay_confirmation_numbers_from_proto(
+    src: &[Vec<u8>],
+    num_outlays: usize,
+) -> Result<Vec<TxOutConfirmationNumber>, ConversionError> {
+    let outlay_confirmation_numbers = src
+        .iter()
+        .map(|bytes| {
+            TxOutConfirmationNumber::try_from(&bytes[..])
+                .map_err(|_| ConversionError::IndexOutOfBounds)
+        })
+        .collect::<Result<Vec<TxOutConfirmationNumber>, ConversionError>>()?;
+    if !outlay_confirmation_numbers.is_empty() && outlay_confirmation_numbers.len() != num_outlays {
+        return Err(ConversionError::IndexOutOfBounds);
+    }
+
+    Ok(outlay_confirmation_numbers)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use keys::{FromRandom, RistrettoPublic};
     use ledger_db::Ledger;
     use rand::{rngs::StdRng, SeedableRng};
-    use transaction::{account_keys::AccountKey, amount::Amount, ring_signature::Blinding};
+    use transaction::{
+        account_keys::AccountKey, amount::Amount, ring_signature::Blinding,
+        tx::TxOutMembershipProof,
+    };
+    use transaction_std::TransactionBuilder;
     use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
 
     #[test]
@@ -177,6 +676,7 @@ mod test {
         let value = 789;
         let attempted_spend_height = 1000;
         let attempted_spend_tombstone = 1234;
+        let memo = "customer 42 deposit".to_string();
 
         let rust = UnspentTxOut {
             tx_out: tx_out.clone(),
@@ -185,6 +685,7 @@ mod test {
             value,
             attempted_spend_height,
             attempted_spend_tombstone,
+            memo: memo.clone(),
         };
 
         let proto = mobilecoind_api::UnspentTxOut::from(&rust);
@@ -198,6 +699,7 @@ mod test {
         assert_eq!(value, proto.value);
         assert_eq!(attempted_spend_height, proto.attempted_spend_height);
         assert_eq!(attempted_spend_tombstone, proto.attempted_spend_tombstone);
+        assert_eq!(memo, proto.memo);
 
         // Proto -> Rust
         assert_eq!(rust, UnspentTxOut::try_from(&proto).unwrap());
@@ -274,6 +776,7 @@ mod test {
                 value,
                 attempted_spend_height,
                 attempted_spend_tombstone,
+                memo: String::new(),
             }
         };
 
@@ -293,6 +796,8 @@ mod test {
             outlays: vec![outlay],
             tx,
             outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers: vec![TxOutConfirmationNumber::from([7u8; 32])],
+            absorbed_change: 0,
         };
 
         let proto = mobilecoind_api::TxProposal::from(&rust);
@@ -312,7 +817,121 @@ mod test {
 
         assert_eq!(rust.tx, Tx::try_from(proto.get_tx()).unwrap());
 
+        assert_eq!(
+            proto.get_outlay_confirmation_number_list(),
+            &[vec![7u8; 32]][..]
+        );
+
         // Proto -> Rust
         assert_eq!(rust, TxProposal::try_from(&proto).unwrap());
+
+        // Proposals without confirmation numbers are accepted, but one confirmation number is
+        // needed for every outlay otherwise.
+        let mut legacy_proto = proto.clone();
+        legacy_proto.clear_outlay_confirmation_number_list();
+        assert!(TxProposal::try_from(&legacy_proto)
+            .unwrap()
+            .outlay_confirmation_numbers
+            .is_empty());
+
+        let mut bad_proto = proto;
+        bad_proto
+            .mut_outlay_confirmation_number_list()
+            .push(vec![8u8; 32]);
+        assert!(TxProposal::try_from(&bad_proto).is_err());
+    }
+
+    #[test]
+    fn test_unsigned_tx_proposal_conversion() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let sender = AccountKey::random(&mut rng);
+        let recipient = AccountKey::random(&mut rng);
+
+        let mut ledger = create_ledger();
+        initialize_ledger(&mut ledger, 1, &sender, &mut rng);
+        let ring = ledger.get_block_contents(0).unwrap().outputs;
+        let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+
+        let mut transaction_builder = TransactionBuilder::new();
+        transaction_builder
+            .add_input_without_key(
+                ring,
+                membership_proofs,
+                0,
+                *sender.view_private_key(),
+                &mut rng,
+            )
+            .unwrap();
+        let (_tx_out, confirmation_number) = transaction_builder
+            .add_output(10, &recipient.default_subaddress(), None, &mut rng)
+            .unwrap();
+        let unsigned_tx = transaction_builder.build_unsigned().unwrap();
+
+        let outlay = Outlay {
+            receiver: recipient.default_subaddress(),
+            value: 10,
+        };
+
+        // Rust -> Proto
+        let rust = UnsignedTxProposal {
+            utxos: vec![],
+            outlays: vec![outlay],
+            unsigned_tx,
+            outlay_index_to_tx_out_index: HashMap::from_iter(vec![(0, 0)]),
+            outlay_confirmation_numbers: vec![confirmation_number],
+            absorbed_change: 0,
+            change_subaddress: 3,
+        };
+        let proto = mobilecoind_api::UnsignedTxProposal::from(&rust);
+
+        assert_eq!(
+            TxPrefix::try_from(proto.get_tx_prefix()).unwrap(),
+            rust.unsigned_tx.prefix
+        );
+        assert_eq!(proto.get_input_to_sign_list().len(), 1);
+        assert_eq!(proto.get_output_value_and_blinding_list().len(), 2);
+
+        // Proto -> Rust
+        let converted = UnsignedTxProposal::try_from(&proto).unwrap();
+        assert_eq!(converted.outlays, rust.outlays);
+        assert_eq!(converted.unsigned_tx.prefix, rust.unsigned_tx.prefix);
+        assert_eq!(converted.unsigned_tx.message(), rust.unsigned_tx.message());
+        for (converted_input, input) in converted
+            .unsigned_tx
+            .inputs
+            .iter()
+            .zip(rust.unsigned_tx.inputs.iter())
+        {
+            assert_eq!(converted_input.ring, input.ring);
+            assert_eq!(converted_input.real_index, input.real_index);
+            assert_eq!(
+                converted_input.real_output_public_key,
+                input.real_output_public_key
+            );
+            assert_eq!(converted_input.value, input.value);
+            assert_eq!(converted_input.blinding, input.blinding);
+        }
+        assert_eq!(
+            converted.unsigned_tx.output_values_and_blindings(),
+            rust.unsigned_tx.output_values_and_blindings()
+        );
+        assert_eq!(
+            converted.outlay_index_to_tx_out_index,
+            rust.outlay_index_to_tx_out_index
+        );
+        assert_eq!(
+            converted.outlay_confirmation_numbers,
+            rust.outlay_confirmation_numbers
+        );
+        assert_eq!(converted.change_subaddress, rust.change_subaddress);
+
+        // The inputs and outputs must match the prefix.
+        let mut bad_proto = proto.clone();
+        bad_proto.mut_input_to_sign_list()[0].set_real_index(100);
+        assert!(UnsignedTxProposal::try_from(&bad_proto).is_err());
+
+        let mut bad_proto = proto;
+        bad_proto.mut_output_value_and_blinding_list().clear();
+        assert!(UnsignedTxProposal::try_from(&bad_proto).is_err());
     }
 }
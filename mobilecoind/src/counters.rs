@@ -0,0 +1,8 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use lazy_static;
+use metrics::OpMetrics;
+
+lazy_static::lazy_static! {
+    pub static ref OP_COUNTERS: OpMetrics = OpMetrics::new_and_registered("mobilecoind");
+}
@@ -2,11 +2,29 @@
 
 //! The mobilecoind database
 
+pub mod key_bytes;
+pub mod migrations;
+
 use crate::{
+    account_store::{AccountData, AccountStore},
+    address_book_store::{AddressBookEntry, AddressBookStore},
+    approval_store::{ApprovalStore, TxApproval, TxApprovalStatus},
+    audit_log_store::{AuditEvent, AuditLogStore},
+    block_timestamp_store::{BlockTimestamp, BlockTimestampStore},
+    encryption::{DatabaseCipher, DatabaseSecret},
     error::Error,
-    monitor_store::{MonitorData, MonitorId, MonitorStore},
+    incoming_payment_store::{IncomingPayment, IncomingPaymentStore},
+    monitor_store::{MonitorData, MonitorId, MonitorPriority, MonitorStore},
+    outgoing_tx_store::{OutgoingTx, OutgoingTxStore},
+    processed_block_store::{ProcessedBlockStore, ProcessedTxOut},
+    spent_txo_store::{SpentTxOut, SpentTxoStore},
+    state_archive::MonitorState,
     subaddress_store::{SubaddressId, SubaddressSPKId, SubaddressStore},
-    utxo_store::{UtxoId, UtxoStore},
+    sync_snapshot_store::{SyncSnapshot, SyncSnapshotStore},
+    transaction_log_store::{TransactionLogEntry, TransactionLogStore},
+    transfer_code_store::{TransferCode, TransferCodeStore},
+    utxo_store::{SubaddressBalance, UtxoId, UtxoStore},
+    webhook_delivery_store::{WebhookDelivery, WebhookDeliveryStore},
 };
 
 use crate::utxo_store::UnspentTxOut;
@@ -14,19 +32,54 @@ use common::{
     logger::{log, Logger},
     HashMap,
 };
-use lmdb::{Environment, Transaction};
-use std::{path::Path, sync::Arc};
-use transaction::ring_signature::KeyImage;
+use keys::CompressedRistrettoPublic;
+use lmdb::{Environment, RwTransaction, Transaction};
+use migrations::SchemaVersionStore;
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use transaction::{
+    ring_signature::KeyImage,
+    tx::{TxHash, TxOut},
+    BlockIndex,
+};
 
 // LMDB Constants
 
 const MAX_LMDB_FILE_SIZE: usize = 1_099_511_627_776; // 1 TB
 
+/// What a monitor found in a block it scanned, as stored by `Database::blocks_processed`.
+pub struct BlockScanResult<'a> {
+    /// The monitor that scanned the block.
+    pub monitor_id: MonitorId,
+
+    /// The index of the block, which must be the next block of the monitor.
+    pub block_num: u64,
+
+    /// The UTXOs of the monitor the block pays.
+    pub discovered_utxos: &'a [UnspentTxOut],
+
+    /// The key images the block spends.
+    pub spent_key_images: &'a [KeyImage],
+
+    /// The public keys of the outputs of the block.
+    pub tx_out_public_keys: &'a [CompressedRistrettoPublic],
+
+    /// How the monitor classified the TxOuts it processed in the block.
+    pub processed_tx_outs: &'a [ProcessedTxOut],
+}
+
 #[derive(Clone)]
 pub struct Database {
     // LMDB Environment (database).
     env: Arc<Environment>,
 
+    /// Seals the records of the stores holding secrets, once the database is encrypted.
+    cipher: DatabaseCipher,
+
     /// Monitor store.
     monitor_store: MonitorStore,
 
@@ -36,6 +89,52 @@ pub struct Database {
     /// Utxo store.
     utxo_store: UtxoStore,
 
+    /// Spent TxOuts store.
+    spent_txo_store: SpentTxoStore,
+
+    /// Processed block store.
+    processed_block_store: ProcessedBlockStore,
+
+    /// Outgoing transactions store.
+    outgoing_tx_store: OutgoingTxStore,
+
+    /// Incoming payments store.
+    incoming_payment_store: IncomingPaymentStore,
+
+    /// Monitor audit log store.
+    audit_log_store: AuditLogStore,
+
+    /// Transaction approvals store.
+    approval_store: ApprovalStore,
+
+    /// Named accounts store.
+    account_store: AccountStore,
+
+    /// Address book store.
+    address_book_store: AddressBookStore,
+
+    /// Transaction history store.
+    transaction_log_store: TransactionLogStore,
+
+    /// Block timestamps store.
+    block_timestamp_store: BlockTimestampStore,
+
+    /// Pending webhook deliveries store.
+    webhook_delivery_store: WebhookDeliveryStore,
+
+    /// Transfer codes store.
+    transfer_code_store: TransferCodeStore,
+
+    /// Schema version store.
+    schema_version_store: SchemaVersionStore,
+
+    /// Sync thread shutdown snapshot store.
+    sync_snapshot_store: SyncSnapshotStore,
+
+    /// Held while the ledger is pruned, and while a monitor is stored after checking that the
+    /// blocks it scans were not pruned.
+    pruning_lock: Arc<Mutex<()>>,
+
     /// Logger.
     logger: Logger,
 }
@@ -44,96 +143,959 @@ impl Database {
     pub fn new<P: AsRef<Path>>(path: P, logger: Logger) -> Result<Self, Error> {
         let env = Arc::new(
             Environment::new()
-                .set_max_dbs(10)
+                .set_max_dbs(30)
                 .set_map_size(MAX_LMDB_FILE_SIZE)
                 .open(path.as_ref())?,
         );
 
-        let monitor_store = MonitorStore::new(env.clone(), logger.clone())?;
+        let cipher = DatabaseCipher::new(env.clone())?;
+        let monitor_store = MonitorStore::new(env.clone(), cipher.clone(), logger.clone())?;
         let subaddress_store = SubaddressStore::new(env.clone(), logger.clone())?;
-        let utxo_store = UtxoStore::new(env.clone(), logger.clone())?;
+        let utxo_store = UtxoStore::new(env.clone(), cipher.clone(), logger.clone())?;
+        let spent_txo_store = SpentTxoStore::new(env.clone(), cipher.clone(), logger.clone())?;
+        let processed_block_store = ProcessedBlockStore::new(env.clone(), logger.clone())?;
+        let outgoing_tx_store = OutgoingTxStore::new(env.clone(), logger.clone())?;
+        let incoming_payment_store = IncomingPaymentStore::new(env.clone(), logger.clone())?;
+        let audit_log_store = AuditLogStore::new(env.clone(), logger.clone())?;
+        let approval_store = ApprovalStore::new(env.clone(), logger.clone())?;
+        let account_store = AccountStore::new(env.clone(), logger.clone())?;
+        let address_book_store = AddressBookStore::new(env.clone(), logger.clone())?;
+        let transaction_log_store = TransactionLogStore::new(env.clone(), logger.clone())?;
+        let block_timestamp_store = BlockTimestampStore::new(env.clone(), logger.clone())?;
+        let webhook_delivery_store = WebhookDeliveryStore::new(env.clone(), logger.clone())?;
+        let transfer_code_store =
+            TransferCodeStore::new(env.clone(), cipher.clone(), logger.clone())?;
+        let schema_version_store = SchemaVersionStore::new(&env)?;
+        let sync_snapshot_store = SyncSnapshotStore::new(env.clone(), logger.clone())?;
+
+        Ok(Self {
+            env,
+            cipher,
+            monitor_store,
+            subaddress_store,
+            utxo_store,
+            spent_txo_store,
+            processed_block_store,
+            outgoing_tx_store,
+            incoming_payment_store,
+            audit_log_store,
+            approval_store,
+            account_store,
+            address_book_store,
+            transaction_log_store,
+            block_timestamp_store,
+            webhook_delivery_store,
+            transfer_code_store,
+            schema_version_store,
+            sync_snapshot_store,
+            pruning_lock: Arc::new(Mutex::new(())),
+            logger,
+        })
+    }
+
+    /// Whether the database is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_encrypted()
+    }
+
+    /// Whether the database is encrypted and has not been unlocked yet. Monitors, UTXOs and
+    /// transfer codes cannot be read or written until it is.
+    pub fn is_locked(&self) -> bool {
+        self.cipher.is_locked()
+    }
+
+    /// Keeps the ledger from being pruned while the guard is held. Without it, the ledger could be
+    /// pruned past the first block of a monitor between checking the number of pruned blocks and
+    /// storing the monitor, leaving it with blocks it can never scan.
+    pub fn lock_pruning(&self) -> MutexGuard<()> {
+        self.pruning_lock.lock().expect("mutex poisoned")
+    }
+
+    /// Unlocks an encrypted database with the password or keyfile protecting its key.
+    pub fn unlock(&self, secret: &DatabaseSecret) -> Result<(), Error> {
+        self.cipher.unlock(secret)?;
+        log::info!(self.logger, "Unlocked the database");
+        Ok(())
+    }
+
+    /// Encrypts the database with a new key derived from `secret`, resealing everything sealed
+    /// with the previous key, if any. An encrypted database has to be unlocked first.
+    pub fn reencrypt(&self, secret: &DatabaseSecret) -> Result<(), Error> {
+        common::trace_time!(self.logger, "reencrypt");
+
+        let db_txn = self.env.begin_rw_txn()?;
+        self.cipher.rekey(db_txn, secret, |db_txn, rekeying| {
+            self.monitor_store.reseal(db_txn, rekeying)?;
+            self.utxo_store.reseal(db_txn, rekeying)?;
+            self.spent_txo_store.reseal(db_txn, rekeying)?;
+            self.transfer_code_store.reseal(db_txn, rekeying)
+        })?;
+
+        log::info!(self.logger, "Encrypted the database with a new key");
+        Ok(())
+    }
+
+    pub fn add_monitor(&self, data: &MonitorData) -> Result<MonitorId, Error> {
+        common::trace_time!(self.logger, "add_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let id = self.add_monitor_in_txn(&mut db_txn, data)?;
+
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    fn add_monitor_in_txn<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        data: &MonitorData,
+    ) -> Result<MonitorId, Error> {
+        let id = self.monitor_store.add(db_txn, data)?;
+
+        //for index in 0..data.num_subaddresses {
+        for index in data.subaddress_indexes() {
+            self.subaddress_store.insert(db_txn, &id, data, index)?;
+        }
+
+        Ok(id)
+    }
+
+    pub fn remove_monitor(&self, id: &MonitorId) -> Result<(), Error> {
+        common::trace_time!(self.logger, "remove_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.remove_monitor_in_txn(&mut db_txn, id)?;
+        db_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Remove a monitor along with its history: its incoming payments and transaction log
+    /// entries, which `remove_monitor` keeps.
+    pub fn remove_monitor_with_history(&self, id: &MonitorId) -> Result<(), Error> {
+        common::trace_time!(self.logger, "remove_monitor_with_history");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.remove_monitor_in_txn(&mut db_txn, id)?;
+        self.remove_history_in_txn(&mut db_txn, id)?;
+        db_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Remove a monitor, and its history if `remove_history` is set, after handing its state, as
+    /// exported by `export_state`, to `archive`. The state is read in the same transaction that
+    /// removes it, and nothing is removed if `archive` fails.
+    pub fn remove_monitor_and_archive<T>(
+        &self,
+        id: &MonitorId,
+        remove_history: bool,
+        archive: impl FnOnce(MonitorState) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        common::trace_time!(self.logger, "remove_monitor_and_archive");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let archived = archive(self.get_monitor_state_in_txn(&db_txn, id)?)?;
+
+        self.remove_monitor_in_txn(&mut db_txn, id)?;
+        if remove_history {
+            self.remove_history_in_txn(&mut db_txn, id)?;
+        }
+        db_txn.commit()?;
+
+        Ok(archived)
+    }
+
+    fn remove_history_in_txn<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        id: &MonitorId,
+    ) -> Result<(), Error> {
+        self.incoming_payment_store.remove(db_txn, id)?;
+        self.transaction_log_store.remove(db_txn, id)?;
+        Ok(())
+    }
+
+    fn remove_monitor_in_txn<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        id: &MonitorId,
+    ) -> Result<(), Error> {
+        let data = self.monitor_store.get_data(db_txn, &id)?;
+
+        for index in data.subaddress_indexes() {
+            self.subaddress_store.delete(db_txn, &data, index)?;
+            self.utxo_store.remove_utxos(db_txn, id, index)?;
+        }
+
+        self.spent_txo_store.remove(db_txn, id)?;
+        self.processed_block_store.remove(db_txn, id)?;
+        self.outgoing_tx_store.remove_pending(db_txn, id)?;
+        self.account_store.remove_monitor(db_txn, id)?;
+        self.monitor_store.remove(db_txn, id)?;
+
+        Ok(())
+    }
+
+    /// Create a named account along with its first monitors. Monitors that already exist are
+    /// added to the account as they are. Returns the ids of the account's monitors.
+    pub fn create_account(
+        &self,
+        account: &AccountData,
+        monitors: &[MonitorData],
+    ) -> Result<Vec<MonitorId>, Error> {
+        common::trace_time!(self.logger, "create_account");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.account_store.insert(&mut db_txn, account)?;
+
+        let mut ids = Vec::new();
+        for data in monitors {
+            let id = self.add_account_monitor_in_txn(&mut db_txn, &account.name, data)?;
+            ids.push(id);
+        }
+
+        db_txn.commit()?;
+        Ok(ids)
+    }
+
+    /// Add a monitor to a named account. A monitor that already exists is added to the account as
+    /// it is.
+    pub fn add_account_monitor(&self, name: &str, data: &MonitorData) -> Result<MonitorId, Error> {
+        common::trace_time!(self.logger, "add_account_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let id = self.add_account_monitor_in_txn(&mut db_txn, name, data)?;
+
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    fn add_account_monitor_in_txn<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        name: &str,
+        data: &MonitorData,
+    ) -> Result<MonitorId, Error> {
+        // Make sure the account exists before adding the monitor.
+        self.account_store.get(db_txn, name)?;
+
+        let id = match self.add_monitor_in_txn(db_txn, data) {
+            Ok(id) => id,
+            Err(Error::MonitorIdExists) => MonitorId::from(data),
+            Err(err) => return Err(err),
+        };
+        self.account_store.add_monitor(db_txn, name, &id)?;
+        Ok(id)
+    }
+
+    /// Remove a named account along with all of its monitors and their data. Returns the removed
+    /// account.
+    pub fn remove_account(&self, name: &str) -> Result<AccountData, Error> {
+        common::trace_time!(self.logger, "remove_account");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let account = self.account_store.remove(&mut db_txn, name)?;
+
+        for id in &account.monitor_ids {
+            match self.remove_monitor_in_txn(&mut db_txn, id) {
+                // The monitor might have been shared with another account that was removed.
+                Ok(()) | Err(Error::MonitorIdNotFound) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        db_txn.commit()?;
+        Ok(account)
+    }
+
+    pub fn get_account(&self, name: &str) -> Result<AccountData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.account_store.get(&db_txn, name)
+    }
+
+    /// Get all named accounts, ordered by name.
+    pub fn get_account_list(&self) -> Result<Vec<AccountData>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.account_store.list(&db_txn)
+    }
+
+    /// Add an entry to the address book. Fails if an entry with the same label exists.
+    pub fn add_address_book_entry(&self, entry: &AddressBookEntry) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.address_book_store.insert(&mut db_txn, entry)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Replace an existing address book entry with one of the same label.
+    pub fn update_address_book_entry(&self, entry: &AddressBookEntry) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.address_book_store.update(&mut db_txn, entry)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_address_book_entry(&self, label: &str) -> Result<AddressBookEntry, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.address_book_store.get(&db_txn, label)
+    }
+
+    /// Get all address book entries, ordered by label.
+    pub fn get_address_book(&self) -> Result<Vec<AddressBookEntry>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.address_book_store.list(&db_txn)
+    }
+
+    pub fn remove_address_book_entry(&self, label: &str) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.address_book_store.remove(&mut db_txn, label)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Add a transfer code. Fails if a code funded by the same TxOut exists.
+    pub fn add_transfer_code(&self, transfer_code: &TransferCode) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.transfer_code_store
+            .insert(&mut db_txn, transfer_code)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Get a transfer code by the public key of the TxOut funding it.
+    pub fn get_transfer_code(
+        &self,
+        tx_public_key: &CompressedRistrettoPublic,
+    ) -> Result<TransferCode, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.transfer_code_store.get(&db_txn, tx_public_key)
+    }
+
+    /// Record that a transfer code was redeemed. Codes created by other wallets are added.
+    pub fn transfer_code_redeemed(&self, transfer_code: &TransferCode) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.transfer_code_store.put(&mut db_txn, transfer_code)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Extends the subaddresses a monitor watches so that its gap limit is kept past
+    /// `used_subaddress`. Returns the number of subaddresses added.
+    pub fn extend_monitor_subaddresses(
+        &self,
+        id: &MonitorId,
+        used_subaddress: u64,
+    ) -> Result<u64, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        let new_indexes = data.extend_past(used_subaddress);
+        if new_indexes.start == new_indexes.end {
+            return Ok(0);
+        }
+
+        for index in new_indexes.clone() {
+            self.subaddress_store
+                .insert(&mut db_txn, id, &data, index)?;
+        }
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+
+        db_txn.commit()?;
+        Ok(new_indexes.end - new_indexes.start)
+    }
+
+    /// Sets the scanning priority of a monitor, and records `audit_event` along with the change.
+    pub fn set_monitor_priority(
+        &self,
+        id: &MonitorId,
+        priority: MonitorPriority,
+        audit_event: &AuditEvent,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        data.priority = priority as i32;
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+        self.audit_log_store.append(&mut db_txn, audit_event)?;
+
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Replaces the labels and metadata of a monitor, and records `audit_event` along with the
+    /// change.
+    pub fn set_monitor_metadata(
+        &self,
+        id: &MonitorId,
+        labels: BTreeMap<String, String>,
+        metadata: String,
+        audit_event: &AuditEvent,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        data.set_metadata(labels, metadata)?;
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+        self.audit_log_store.append(&mut db_txn, audit_event)?;
+
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Rewind a monitor so that it scans the ledger again starting at `from_block`, which must be
+    /// between the first block of the monitor and the next block it was going to scan.
+    ///
+    /// The outputs the monitor discovered at or after `from_block` are dropped, and the outputs it
+    /// discovered before but spent at or after `from_block` become unspent again. Both are
+    /// restored once the blocks are scanned again. Incoming payments and the transaction history
+    /// are kept, since scanning a block again does not record its entries twice.
+    ///
+    /// # Arguments
+    /// * `id` - The monitor to rescan.
+    /// * `from_block` - The first block to scan again.
+    /// * `audit_event` - Recorded in the audit log along with the change.
+    /// * `tx_out_block_index` - Looks up the block a TxOut of the monitor appeared in.
+    pub fn rescan_monitor(
+        &self,
+        id: &MonitorId,
+        from_block: u64,
+        audit_event: &AuditEvent,
+        tx_out_block_index: impl Fn(&TxOut) -> Result<BlockIndex, Error>,
+    ) -> Result<(), Error> {
+        common::trace_time!(self.logger, "rescan_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+
+        let mut data = self.monitor_store.get_data(&db_txn, id)?;
+        if from_block < data.first_block || from_block > data.next_block {
+            return Err(Error::InvalidArgument(
+                "from_block".to_string(),
+                format!(
+                    "must be between {} and {}, got {}",
+                    data.first_block, data.next_block, from_block
+                ),
+            ));
+        }
+
+        // Drop the outputs discovered in the blocks that are scanned again.
+        let mut discovered_key_images = Vec::new();
+        for index in data.subaddress_indexes() {
+            for utxo in self.utxo_store.get_utxos(&db_txn, id, index)? {
+                if tx_out_block_index(&utxo.tx_out)? >= from_block {
+                    discovered_key_images.push(utxo.key_image);
+                }
+            }
+        }
+        self.utxo_store
+            .remove_utxos_by_key_images(&mut db_txn, id, &discovered_key_images)?;
+
+        // Outputs spent in the blocks that are scanned again are unspent until then, unless they
+        // were also discovered in them.
+        let spent_txos = self
+            .spent_txo_store
+            .remove_from_block(&mut db_txn, id, from_block)?;
+        for spent_txo in &spent_txos {
+            if tx_out_block_index(&spent_txo.utxo.tx_out)? < from_block {
+                self.utxo_store.append_utxo(
+                    &mut db_txn,
+                    id,
+                    spent_txo.utxo.subaddress_index,
+                    &spent_txo.utxo,
+                )?;
+            }
+        }
+
+        self.processed_block_store
+            .remove_from_block(&mut db_txn, id, from_block)?;
+
+        let previous_next_block = data.next_block;
+        data.next_block = from_block;
+        self.monitor_store.set_data(&mut db_txn, id, &data)?;
+        self.audit_log_store.append(&mut db_txn, audit_event)?;
+
+        db_txn.commit()?;
+        log::info!(
+            self.logger,
+            "Rescanning monitor id {} from block {} instead of block {}",
+            id,
+            from_block,
+            previous_next_block
+        );
+        Ok(())
+    }
+
+    /// Add a monitor restored from an account snapshot, along with the unspent outputs it had as
+    /// of `data.next_block`. The monitor resumes syncing from that block.
+    pub fn import_monitor(
+        &self,
+        data: &MonitorData,
+        utxos: &[UnspentTxOut],
+    ) -> Result<MonitorId, Error> {
+        common::trace_time!(self.logger, "import_monitor");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let id = self.monitor_store.add(&mut db_txn, data)?;
+
+        for index in data.subaddress_indexes() {
+            self.subaddress_store
+                .insert(&mut db_txn, &id, data, index)?;
+        }
+
+        for utxo in utxos {
+            self.utxo_store
+                .append_utxo(&mut db_txn, &id, utxo.subaddress_index, utxo)?;
+        }
+
+        db_txn.commit()?;
+        Ok(id)
+    }
+
+    /// Get the state of every monitor together with the address book, as of the same moment.
+    pub fn export_state(&self) -> Result<(Vec<MonitorState>, Vec<AddressBookEntry>), Error> {
+        common::trace_time!(self.logger, "export_state");
+
+        let db_txn = self.env.begin_ro_txn()?;
+        let mut monitors = Vec::new();
+        for id in self.monitor_store.get_ids(&db_txn)? {
+            monitors.push(self.get_monitor_state_in_txn(&db_txn, &id)?);
+        }
+
+        let address_book = self.address_book_store.list(&db_txn)?;
+        Ok((monitors, address_book))
+    }
+
+    fn get_monitor_state_in_txn(
+        &self,
+        db_txn: &impl Transaction,
+        id: &MonitorId,
+    ) -> Result<MonitorState, Error> {
+        let monitor_data = self.monitor_store.get_data(db_txn, id)?;
+
+        let mut utxos = Vec::new();
+        for index in monitor_data.subaddress_indexes() {
+            utxos.extend(self.utxo_store.get_utxos(db_txn, id, index)?);
+        }
+        let history = self
+            .transaction_log_store
+            .get_history(db_txn, id, None, 0, u64::MAX)?;
+
+        Ok(MonitorState {
+            monitor_data,
+            utxos,
+            history,
+        })
+    }
+
+    /// Restore monitors, along with their unspent outputs and history, and address book entries
+    /// exported by `export_state`. Each monitor resumes syncing from the block it had reached.
+    /// Nothing is imported if one of the monitors or labels already exists.
+    pub fn import_state(
+        &self,
+        monitors: &[MonitorState],
+        address_book: &[AddressBookEntry],
+    ) -> Result<Vec<MonitorId>, Error> {
+        common::trace_time!(self.logger, "import_state");
+
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut ids = Vec::new();
+        for monitor_state in monitors {
+            let id = self.add_monitor_in_txn(&mut db_txn, &monitor_state.monitor_data)?;
+            for utxo in &monitor_state.utxos {
+                self.utxo_store
+                    .append_utxo(&mut db_txn, &id, utxo.subaddress_index, utxo)?;
+            }
+            self.transaction_log_store
+                .insert_entries(&mut db_txn, &id, &monitor_state.history)?;
+            ids.push(id);
+        }
+
+        for entry in address_book {
+            self.address_book_store.insert(&mut db_txn, entry)?;
+        }
+
+        db_txn.commit()?;
+        log::info!(
+            self.logger,
+            "Imported {} monitors and {} address book entries",
+            ids.len(),
+            address_book.len()
+        );
+        Ok(ids)
+    }
+
+    /// Get a monitor's data together with all of its unspent outputs, as of the same block.
+    pub fn get_monitor_utxos(
+        &self,
+        id: &MonitorId,
+    ) -> Result<(MonitorData, Vec<UnspentTxOut>), Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        let data = self.monitor_store.get_data(&db_txn, id)?;
+
+        let mut utxos = Vec::new();
+        for index in data.subaddress_indexes() {
+            utxos.extend(self.utxo_store.get_utxos(&db_txn, id, index)?);
+        }
+        Ok((data, utxos))
+    }
+
+    pub fn get_monitor_data(&self, id: &MonitorId) -> Result<MonitorData, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.monitor_store.get_data(&db_txn, id)
+    }
+
+    pub fn get_monitor_map(&self) -> Result<HashMap<MonitorId, MonitorData>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.monitor_store.get_map(&db_txn)
+    }
+
+    pub fn get_monitor_ids(&self) -> Result<Vec<MonitorId>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.monitor_store.get_ids(&db_txn)
+    }
+
+    /// Store the state of the sync thread at shutdown, replacing any earlier snapshot.
+    pub fn save_sync_snapshot(&self, snapshot: &SyncSnapshot) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.sync_snapshot_store.put(&mut db_txn, snapshot)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Remove the state stored by the sync thread at shutdown and return it, if there was one.
+    pub fn take_sync_snapshot(&self) -> Result<Option<SyncSnapshot>, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let snapshot = self.sync_snapshot_store.take(&mut db_txn)?;
+        db_txn.commit()?;
+        Ok(snapshot)
+    }
+
+    pub fn get_subaddress_id_by_spk(
+        &self,
+        subaddress_spk: &SubaddressSPKId,
+    ) -> Result<SubaddressId, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.subaddress_store
+            .get_index_data(&db_txn, subaddress_spk)
+    }
+
+    pub fn get_subaddress_id_by_utxo_id(&self, utxo_id: &UtxoId) -> Result<SubaddressId, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.utxo_store
+            .get_subaddress_id_by_utxo_id(&db_txn, utxo_id)
+    }
+
+    pub fn get_utxos_for_subaddress(
+        &self,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.utxo_store.get_utxos(&db_txn, monitor_id, index)
+    }
+
+    /// Get the running balance of a subaddress.
+    pub fn get_subaddress_balance(
+        &self,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<SubaddressBalance, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.utxo_store.get_balance(&db_txn, monitor_id, index)
+    }
+
+    /// Get the running balances of several subaddresses of a monitor, in the order of `indexes`.
+    /// The balances are read at once, so they reflect the same synced block.
+    pub fn get_subaddress_balances(
+        &self,
+        monitor_id: &MonitorId,
+        indexes: &[u64],
+    ) -> Result<Vec<SubaddressBalance>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        indexes
+            .iter()
+            .map(|index| self.utxo_store.get_balance(&db_txn, monitor_id, *index))
+            .collect()
+    }
+
+    /// Get a page of the utxos of a subaddress. See `UtxoStore::get_utxos_page`.
+    pub fn get_utxos_page_for_subaddress(
+        &self,
+        monitor_id: &MonitorId,
+        index: u64,
+        after: Option<&UtxoId>,
+        max_entries: usize,
+    ) -> Result<(Vec<UnspentTxOut>, Option<UtxoId>), Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.utxo_store
+            .get_utxos_page(&db_txn, monitor_id, index, after, max_entries)
+    }
+
+    pub fn get_utxos_by_key_images(
+        &self,
+        monitor_id: &MonitorId,
+        key_images: &[KeyImage],
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.utxo_store
+            .get_utxos_by_key_images(&db_txn, monitor_id, key_images)
+    }
+
+    /// Get the TxOuts a monitor spent, in the order they were spent. See
+    /// `SpentTxoStore::get_spent_txos`.
+    pub fn get_spent_txos(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: Option<u64>,
+        first_block: u64,
+        max_blocks: u64,
+    ) -> Result<Vec<SpentTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.spent_txo_store.get_spent_txos(
+            &db_txn,
+            monitor_id,
+            subaddress_index,
+            first_block,
+            max_blocks,
+        )
+    }
 
-        Ok(Self {
-            env,
-            monitor_store,
-            subaddress_store,
-            utxo_store,
-            logger,
-        })
+    pub fn get_processed_block(
+        &self,
+        monitor_id: &MonitorId,
+        block_num: u64,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.processed_block_store
+            .get_processed_block(&db_txn, monitor_id, block_num)
     }
 
-    pub fn add_monitor(&self, data: &MonitorData) -> Result<MonitorId, Error> {
-        common::trace_time!(self.logger, "add_monitor");
+    pub fn get_processed_tx_out_history(
+        &self,
+        monitor_id: &MonitorId,
+        first_block: u64,
+        max_blocks: u64,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.processed_block_store
+            .get_history(&db_txn, monitor_id, first_block, max_blocks)
+    }
 
-        let mut db_txn = self.env.begin_rw_txn()?;
-        let id = self.monitor_store.add(&mut db_txn, data)?;
+    /// Get the TxOuts a monitor received and spent, in block order. Entries of blocks that were
+    /// seen signed carry the time they were seen, instead of the time they were processed.
+    pub fn get_transaction_history(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: Option<u64>,
+        first_block: u64,
+        max_blocks: u64,
+    ) -> Result<Vec<TransactionLogEntry>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        let mut entries = self.transaction_log_store.get_history(
+            &db_txn,
+            monitor_id,
+            subaddress_index,
+            first_block,
+            max_blocks,
+        )?;
 
-        //for index in 0..data.num_subaddresses {
-        for index in data.subaddress_indexes() {
-            self.subaddress_store
-                .insert(&mut db_txn, &id, data, index)?;
+        for entry in entries.iter_mut() {
+            if let Some(block_timestamp) =
+                self.block_timestamp_store.get(&db_txn, entry.block_index)?
+            {
+                entry.timestamp = block_timestamp.timestamp;
+                entry.timestamp_confidence = block_timestamp.confidence;
+            }
         }
 
+        Ok(entries)
+    }
+
+    /// Record when a block was seen signed. Returns true if the timestamp was recorded, see
+    /// `BlockTimestampStore::observe`.
+    pub fn observe_block_timestamp(
+        &self,
+        block_index: u64,
+        block_timestamp: &BlockTimestamp,
+    ) -> Result<bool, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let recorded =
+            self.block_timestamp_store
+                .observe(&mut db_txn, block_index, block_timestamp)?;
         db_txn.commit()?;
-        Ok(id)
+        Ok(recorded)
     }
 
-    pub fn remove_monitor(&self, id: &MonitorId) -> Result<(), Error> {
-        common::trace_time!(self.logger, "remove_monitor");
+    /// Get the index of the first block that may have been created at or after a given time,
+    /// according to when blocks were seen. Returns 0 if no block was seen before that time.
+    pub fn get_first_block_not_seen_before(&self, timestamp: u64) -> Result<u64, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        Ok(self
+            .block_timestamp_store
+            .get_last_block_seen_before(&db_txn, timestamp)?
+            .map_or(0, |block_index| block_index + 1))
+    }
 
+    /// Store deliveries of an event to webhooks, all or none.
+    pub fn add_webhook_deliveries(&self, deliveries: &[WebhookDelivery]) -> Result<(), Error> {
         let mut db_txn = self.env.begin_rw_txn()?;
+        for delivery in deliveries {
+            self.webhook_delivery_store.insert(&mut db_txn, delivery)?;
+        }
+        db_txn.commit()?;
+        Ok(())
+    }
 
-        let data = self.monitor_store.get_data(&db_txn, &id)?;
+    /// Get pending webhook deliveries, in the order the events happened.
+    pub fn get_webhook_deliveries(
+        &self,
+        max_entries: usize,
+    ) -> Result<Vec<WebhookDelivery>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.webhook_delivery_store.list(&db_txn, max_entries)
+    }
 
-        for index in data.subaddress_indexes() {
-            self.subaddress_store.delete(&mut db_txn, &data, index)?;
-            self.utxo_store.remove_utxos(&mut db_txn, id, index)?;
-        }
+    /// Record a failed attempt to deliver an event to a webhook.
+    pub fn update_webhook_delivery(&self, delivery: &WebhookDelivery) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.webhook_delivery_store.update(&mut db_txn, delivery)?;
+        db_txn.commit()?;
+        Ok(())
+    }
 
-        self.monitor_store.remove(&mut db_txn, id)?;
+    /// Forget a webhook delivery, once it succeeded or was given up on.
+    pub fn remove_webhook_delivery(&self, id: u64) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.webhook_delivery_store.remove(&mut db_txn, id)?;
+        db_txn.commit()?;
+        Ok(())
+    }
 
+    /// Append a transaction to the outgoing transaction log. Returns its index in the log.
+    pub fn append_outgoing_tx(&self, outgoing_tx: &OutgoingTx) -> Result<u64, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let index = self.outgoing_tx_store.append(&mut db_txn, outgoing_tx)?;
         db_txn.commit()?;
+        Ok(index)
+    }
 
+    /// Record that a transaction in the outgoing transaction log was submitted to the network.
+    pub fn outgoing_tx_submitted(
+        &self,
+        tx_prefix_hash: &TxHash,
+        submitted_block_height: u64,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.outgoing_tx_store
+            .tx_submitted(&mut db_txn, tx_prefix_hash, submitted_block_height)?;
+        db_txn.commit()?;
         Ok(())
     }
 
-    pub fn get_monitor_data(&self, id: &MonitorId) -> Result<MonitorData, Error> {
+    pub fn get_outgoing_tx_by_tx_prefix_hash(
+        &self,
+        tx_prefix_hash: &TxHash,
+    ) -> Result<OutgoingTx, Error> {
         let db_txn = self.env.begin_ro_txn()?;
-        self.monitor_store.get_data(&db_txn, id)
+        self.outgoing_tx_store
+            .get_by_tx_prefix_hash(&db_txn, tx_prefix_hash)
     }
 
-    pub fn get_monitor_map(&self) -> Result<HashMap<MonitorId, MonitorData>, Error> {
+    pub fn get_outgoing_tx_list(
+        &self,
+        monitor_id: Option<&MonitorId>,
+        first_index: u64,
+        max_entries: usize,
+    ) -> Result<Vec<OutgoingTx>, Error> {
         let db_txn = self.env.begin_ro_txn()?;
-        self.monitor_store.get_map(&db_txn)
+        self.outgoing_tx_store
+            .list(&db_txn, monitor_id, first_index, max_entries)
     }
 
-    pub fn get_monitor_ids(&self) -> Result<Vec<MonitorId>, Error> {
-        let db_txn = self.env.begin_ro_txn()?;
-        self.monitor_store.get_ids(&db_txn)
+    /// Append an event to the monitor audit log. Returns its index in the log.
+    pub fn append_audit_event(&self, audit_event: &AuditEvent) -> Result<u64, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let index = self.audit_log_store.append(&mut db_txn, audit_event)?;
+        db_txn.commit()?;
+        Ok(index)
     }
 
-    pub fn get_subaddress_id_by_spk(
+    pub fn get_audit_log(
         &self,
-        subaddress_spk: &SubaddressSPKId,
-    ) -> Result<SubaddressId, Error> {
+        monitor_id: Option<&MonitorId>,
+        first_index: u64,
+        max_entries: usize,
+    ) -> Result<Vec<AuditEvent>, Error> {
         let db_txn = self.env.begin_ro_txn()?;
-        self.subaddress_store
-            .get_index_data(&db_txn, subaddress_spk)
+        self.audit_log_store
+            .list(&db_txn, monitor_id, first_index, max_entries)
     }
 
-    pub fn get_subaddress_id_by_utxo_id(&self, utxo_id: &UtxoId) -> Result<SubaddressId, Error> {
+    /// Record a transaction as pending approval.
+    pub fn add_tx_approval(&self, tx_approval: &TxApproval) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.approval_store.insert(&mut db_txn, tx_approval)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_tx_approval(&self, tx_prefix_hash: &TxHash) -> Result<TxApproval, Error> {
         let db_txn = self.env.begin_ro_txn()?;
-        self.utxo_store
-            .get_subaddress_id_by_utxo_id(&db_txn, utxo_id)
+        self.approval_store.get(&db_txn, tx_prefix_hash.as_bytes())
     }
 
-    pub fn get_utxos_for_subaddress(
+    pub fn get_tx_approval_list(
+        &self,
+        monitor_id: Option<&MonitorId>,
+        status: Option<TxApprovalStatus>,
+    ) -> Result<Vec<TxApproval>, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.approval_store.list(&db_txn, monitor_id, status)
+    }
+
+    /// Approve a transaction pending approval. Returns the updated approval.
+    pub fn approve_tx(&self, tx_prefix_hash: &TxHash, approver: &str) -> Result<TxApproval, Error> {
+        self.update_tx_approval(tx_prefix_hash, |tx_approval| tx_approval.approve(approver))
+    }
+
+    /// Reject a transaction pending approval. Returns the updated approval.
+    pub fn reject_tx(&self, tx_prefix_hash: &TxHash, rejecter: &str) -> Result<TxApproval, Error> {
+        self.update_tx_approval(tx_prefix_hash, |tx_approval| tx_approval.reject(rejecter))
+    }
+
+    /// Record that an approved transaction was submitted to the network.
+    pub fn tx_approval_submitted(&self, tx_prefix_hash: &TxHash) -> Result<TxApproval, Error> {
+        self.update_tx_approval(tx_prefix_hash, |tx_approval| {
+            tx_approval.set_status(TxApprovalStatus::Submitted);
+            Ok(())
+        })
+    }
+
+    /// Read, modify and write back an approval in a single database transaction.
+    fn update_tx_approval(
+        &self,
+        tx_prefix_hash: &TxHash,
+        f: impl FnOnce(&mut TxApproval) -> Result<(), Error>,
+    ) -> Result<TxApproval, Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        let mut tx_approval = self
+            .approval_store
+            .get(&db_txn, tx_prefix_hash.as_bytes())?;
+        f(&mut tx_approval)?;
+        self.approval_store.update(&mut db_txn, &tx_approval)?;
+        db_txn.commit()?;
+        Ok(tx_approval)
+    }
+
+    pub fn get_incoming_payment_list(
         &self,
         monitor_id: &MonitorId,
-        index: u64,
-    ) -> Result<Vec<UnspentTxOut>, Error> {
+        first_block: u64,
+        max_entries: usize,
+    ) -> Result<Vec<IncomingPayment>, Error> {
         let db_txn = self.env.begin_ro_txn()?;
-        self.utxo_store.get_utxos(&db_txn, monitor_id, index)
+        self.incoming_payment_store
+            .get_list(&db_txn, monitor_id, first_block, max_entries)
     }
 
     pub fn update_attempted_spend(
@@ -156,80 +1118,219 @@ impl Database {
         Ok(())
     }
 
-    /// Feed data processed from a given block into the various stores.
+    /// Replace the memo of an UnspentTxOut belonging to a given monitor.
+    pub fn update_utxo_memo(
+        &self,
+        monitor_id: &MonitorId,
+        key_image: &KeyImage,
+        memo: String,
+    ) -> Result<(), Error> {
+        let mut db_txn = self.env.begin_rw_txn()?;
+        self.utxo_store
+            .update_memo(&mut db_txn, monitor_id, key_image, memo)?;
+        db_txn.commit()?;
+        Ok(())
+    }
+
+    /// Feed data processed from a given block into the various stores. Returns the outgoing
+    /// transactions that reached a final status in this block.
     pub fn block_processed(
         &self,
         monitor_id: &MonitorId,
         block_num: u64,
         discovered_utxos: &[UnspentTxOut],
         spent_key_images: &[KeyImage],
-    ) -> Result<(), Error> {
+        tx_out_public_keys: &[CompressedRistrettoPublic],
+        processed_tx_outs: &[ProcessedTxOut],
+    ) -> Result<Vec<OutgoingTx>, Error> {
+        self.blocks_processed(&[BlockScanResult {
+            monitor_id: *monitor_id,
+            block_num,
+            discovered_utxos,
+            spent_key_images,
+            tx_out_public_keys,
+            processed_tx_outs,
+        }])?
+        .pop()
+        .expect("one result per block")
+    }
+
+    /// Feed what several monitors found in the blocks they scanned into the various stores, in a
+    /// single database transaction. Returns, for each block, the outgoing transactions that
+    /// reached a final status in it.
+    ///
+    /// A block that is not the one its monitor expects, or whose monitor was removed, is skipped
+    /// and gets an error of its own. Any other error fails all of the blocks.
+    pub fn blocks_processed(
+        &self,
+        results: &[BlockScanResult],
+    ) -> Result<Vec<Result<Vec<OutgoingTx>, Error>>, Error> {
         let mut db_txn = self.env.begin_rw_txn()?;
 
-        // Get monitor data.
-        let mut monitor_data = self.monitor_store.get_data(&db_txn, monitor_id)?;
+        let mut outcomes = Vec::with_capacity(results.len());
+        let mut num_spent = vec![0; results.len()];
+        for (result, num_spent) in results.iter().zip(num_spent.iter_mut()) {
+            // Get monitor data.
+            let monitor_data = match self.monitor_store.get_data(&db_txn, &result.monitor_id) {
+                Ok(monitor_data) => monitor_data,
+                Err(err) => {
+                    outcomes.push(Err(err));
+                    continue;
+                }
+            };
 
-        // If the block being handed to us is not the one we expect, error out.
-        if block_num != monitor_data.next_block {
-            return Err(Error::InvalidArgument(
-                "block_num".to_string(),
-                format!(
-                    "Expected block {}, got block {}",
-                    monitor_data.next_block, block_num
-                ),
-            ));
+            // If the block being handed to us is not the one we expect, error out.
+            if result.block_num != monitor_data.next_block {
+                outcomes.push(Err(Error::InvalidArgument(
+                    "block_num".to_string(),
+                    format!(
+                        "Expected block {}, got block {}",
+                        monitor_data.next_block, result.block_num
+                    ),
+                )));
+                continue;
+            }
+
+            let (finalized_outgoing_txs, num_removed) =
+                self.block_processed_in_txn(&mut db_txn, monitor_data, result)?;
+            *num_spent = num_removed;
+            outcomes.push(Ok(finalized_outgoing_txs));
+        }
+
+        // Commit.
+        db_txn.commit()?;
+
+        // Success.
+        for ((result, outcome), num_spent) in results.iter().zip(&outcomes).zip(num_spent) {
+            if let Ok(finalized_outgoing_txs) = outcome {
+                self.log_block_processed(result, finalized_outgoing_txs, num_spent);
+            }
         }
+        Ok(outcomes)
+    }
+
+    /// Stores a block of a monitor that expects it. Returns the outgoing transactions that reached
+    /// a final status in the block, and the number of UTXOs it spent.
+    fn block_processed_in_txn<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        mut monitor_data: MonitorData,
+        result: &BlockScanResult,
+    ) -> Result<(Vec<OutgoingTx>, usize), Error> {
+        let monitor_id = &result.monitor_id;
+        let block_num = result.block_num;
 
         // Store new utxos
-        for utxo in discovered_utxos {
+        for utxo in result.discovered_utxos {
             self.utxo_store
-                .append_utxo(&mut db_txn, &monitor_id, utxo.subaddress_index, &utxo)?;
+                .append_utxo(db_txn, monitor_id, utxo.subaddress_index, utxo)?;
         }
 
-        // Remove spent utxos
+        // Move spent utxos to the spent TxOuts store.
+        let spent_utxos =
+            self.utxo_store
+                .get_utxos_by_key_images(db_txn, monitor_id, result.spent_key_images)?;
+        self.spent_txo_store
+            .block_processed(db_txn, monitor_id, block_num, &spent_utxos)?;
         let removed_key_images = self.utxo_store.remove_utxos_by_key_images(
-            &mut db_txn,
+            db_txn,
             monitor_id,
-            spent_key_images,
+            result.spent_key_images,
+        )?;
+
+        // Record the processed TxOuts.
+        self.processed_block_store.block_processed(
+            db_txn,
+            monitor_id,
+            block_num,
+            result.processed_tx_outs,
+        )?;
+
+        // Record incoming payments and the transaction history. These outlive the utxos, so that
+        // the history of a monitor survives spends and rescans.
+        let processed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.incoming_payment_store.block_processed(
+            db_txn,
+            monitor_id,
+            block_num,
+            result.discovered_utxos,
+            processed_at,
+        )?;
+        self.transaction_log_store.block_processed(
+            db_txn,
+            monitor_id,
+            block_num,
+            result.processed_tx_outs,
+            processed_at,
+        )?;
+
+        // Update the status of our outgoing transactions.
+        let finalized_outgoing_txs = self.outgoing_tx_store.block_processed(
+            db_txn,
+            monitor_id,
+            block_num,
+            result.spent_key_images,
+            result.tx_out_public_keys,
         )?;
 
         // Update monitor data.
         monitor_data.next_block += 1;
         self.monitor_store
-            .set_data(&mut db_txn, monitor_id, &monitor_data)?;
+            .set_data(db_txn, monitor_id, &monitor_data)?;
 
-        // Commit.
-        db_txn.commit()?;
+        Ok((finalized_outgoing_txs, removed_key_images.len()))
+    }
 
-        // Success.
-        if discovered_utxos.is_empty() && removed_key_images.is_empty() {
+    fn log_block_processed(
+        &self,
+        result: &BlockScanResult,
+        finalized_outgoing_txs: &[OutgoingTx],
+        num_spent: usize,
+    ) {
+        for outgoing_tx in finalized_outgoing_txs {
+            log::info!(
+                self.logger,
+                "Outgoing tx {} of monitor id {} is {:?} as of block {}",
+                outgoing_tx.index,
+                result.monitor_id,
+                outgoing_tx.get_status(),
+                result.block_num
+            );
+        }
+        if result.discovered_utxos.is_empty() && num_spent == 0 {
             log::debug!(
                 self.logger,
                 "Processed {} utxos and {} key images in block {} for monitor id {}",
-                discovered_utxos.len(),
-                removed_key_images.len(),
-                block_num,
-                monitor_id
+                result.discovered_utxos.len(),
+                num_spent,
+                result.block_num,
+                result.monitor_id
             )
         } else {
             log::info!(
                 self.logger,
                 "Processed {} utxos and {} key images in block {} for monitor id {}",
-                discovered_utxos.len(),
-                removed_key_images.len(),
-                block_num,
-                monitor_id
+                result.discovered_utxos.len(),
+                num_spent,
+                result.block_num,
+                result.monitor_id
             )
         };
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{error::Error, test_utils::get_test_databases};
+    use crate::{
+        audit_log_store::AuditAction, error::Error, processed_block_store::ProcessedTxOutDirection,
+        test_utils::get_test_databases,
+    };
     use common::logger::{test_with_logger, Logger};
+    use ledger_db::Ledger;
     use rand::{rngs::StdRng, SeedableRng};
     use transaction::account_keys::AccountKey;
 
@@ -326,4 +1427,402 @@ mod test {
             .add_monitor(&initial_data)
             .expect("failed adding monitor");
     }
+
+    // Spent utxos should be moved to the spent TxOuts store, which is cleared along with the
+    // monitor.
+    #[test_with_logger]
+    fn test_block_processed_keeps_spent_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([125u8; 32]);
+
+        // Set up a db with 3 random recipients and 10 blocks.
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0,  // first_subaddress
+            10, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let utxo = UnspentTxOut {
+            tx_out: ledger_db.get_tx_out_by_index(0).unwrap(),
+            subaddress_index: 3,
+            key_image: KeyImage::from(7),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            memo: String::new(),
+        };
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 0, &[utxo.clone()], &[], &[], &[])
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_spent_txos(&monitor_id, None, 0, u64::max_value())
+                .unwrap(),
+            vec![]
+        );
+
+        mobilecoind_db
+            .block_processed(&monitor_id, 1, &[], &[utxo.key_image], &[], &[])
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 3)
+                .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_spent_txos(&monitor_id, Some(3), 0, u64::max_value())
+                .unwrap(),
+            vec![SpentTxOut {
+                utxo,
+                spent_block_index: 1,
+            }]
+        );
+
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_spent_txos(&monitor_id, None, 0, u64::max_value())
+                .unwrap(),
+            vec![]
+        );
+    }
+
+    // Removing a monitor with its history should clear its incoming payments and transaction
+    // log, and archiving it should capture its state before anything is removed.
+    #[test_with_logger]
+    fn test_remove_monitor_with_history_and_archive(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([126u8; 32]);
+
+        // Set up a db with 3 random recipients and 10 blocks.
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let utxo = UnspentTxOut {
+            tx_out: ledger_db.get_tx_out_by_index(0).unwrap(),
+            subaddress_index: 3,
+            key_image: KeyImage::from(7),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            memo: String::new(),
+        };
+        let processed_tx_out = ProcessedTxOut {
+            block_index: 0,
+            subaddress_index: 3,
+            public_key: utxo.tx_out.public_key,
+            key_image: utxo.key_image,
+            value: utxo.value,
+            direction: ProcessedTxOutDirection::Received as i32,
+        };
+
+        let mut add_monitor_with_history = || {
+            let data = MonitorData::new(AccountKey::random(&mut rng), 0, 10, 0).unwrap();
+            let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+            mobilecoind_db
+                .block_processed(
+                    &monitor_id,
+                    0,
+                    &[utxo.clone()],
+                    &[],
+                    &[],
+                    &[processed_tx_out.clone()],
+                )
+                .unwrap();
+            monitor_id
+        };
+        let has_history = |monitor_id: &MonitorId| {
+            let payments = mobilecoind_db
+                .get_incoming_payment_list(monitor_id, 0, usize::max_value())
+                .unwrap();
+            let history = mobilecoind_db
+                .get_transaction_history(monitor_id, None, 0, u64::max_value())
+                .unwrap();
+            assert_eq!(payments.is_empty(), history.is_empty());
+            !history.is_empty()
+        };
+
+        // The history survives a plain removal.
+        let monitor_id = add_monitor_with_history();
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert!(has_history(&monitor_id));
+
+        let monitor_id = add_monitor_with_history();
+        mobilecoind_db
+            .remove_monitor_with_history(&monitor_id)
+            .unwrap();
+        assert!(mobilecoind_db.get_monitor_data(&monitor_id).is_err());
+        assert!(!has_history(&monitor_id));
+
+        // Nothing is removed if archiving fails.
+        let monitor_id = add_monitor_with_history();
+        assert!(mobilecoind_db
+            .remove_monitor_and_archive(&monitor_id, true, |_state| -> Result<(), Error> {
+                Err(Error::StateArchive("failed".to_string()))
+            })
+            .is_err());
+        assert!(mobilecoind_db.get_monitor_data(&monitor_id).is_ok());
+        assert!(has_history(&monitor_id));
+
+        let state = mobilecoind_db
+            .remove_monitor_and_archive(&monitor_id, true, Ok)
+            .unwrap();
+        assert_eq!(MonitorId::from(&state.monitor_data), monitor_id);
+        assert_eq!(state.utxos, vec![utxo]);
+        assert_eq!(state.history.len(), 1);
+        assert!(mobilecoind_db.get_monitor_data(&monitor_id).is_err());
+        assert!(!has_history(&monitor_id));
+    }
+
+    // Rescanning a monitor should drop what it found in the rescanned blocks, and restore the
+    // outputs it spent in them.
+    #[test_with_logger]
+    fn test_rescan_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([127u8; 32]);
+
+        // Set up a db with 3 random recipients and 10 blocks.
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let data = MonitorData::new(AccountKey::random(&mut rng), 0, 10, 0).unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let utxo_in_block = |block_index: u64, key_image: u64| UnspentTxOut {
+            tx_out: ledger_db.get_block_contents(block_index).unwrap().outputs[0].clone(),
+            subaddress_index: 0,
+            key_image: KeyImage::from(key_image),
+            value: 10,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            memo: String::new(),
+        };
+        let utxo0 = utxo_in_block(0, 1);
+        let utxo1 = utxo_in_block(1, 2);
+        let processed_tx_out =
+            |block_index: u64, utxo: &UnspentTxOut, direction: ProcessedTxOutDirection| {
+                ProcessedTxOut {
+                    block_index,
+                    subaddress_index: 0,
+                    public_key: utxo.tx_out.public_key,
+                    key_image: utxo.key_image,
+                    value: utxo.value,
+                    direction: direction as i32,
+                }
+            };
+
+        // Block 0 pays utxo0, block 1 pays utxo1 and block 2 spends utxo0.
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                0,
+                &[utxo0.clone()],
+                &[],
+                &[],
+                &[processed_tx_out(
+                    0,
+                    &utxo0,
+                    ProcessedTxOutDirection::Received,
+                )],
+            )
+            .unwrap();
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                1,
+                &[utxo1.clone()],
+                &[],
+                &[],
+                &[processed_tx_out(
+                    1,
+                    &utxo1,
+                    ProcessedTxOutDirection::Received,
+                )],
+            )
+            .unwrap();
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                2,
+                &[],
+                &[utxo0.key_image],
+                &[],
+                &[processed_tx_out(2, &utxo0, ProcessedTxOutDirection::Spent)],
+            )
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap(),
+            vec![utxo1.clone()]
+        );
+
+        let tx_out_block_index = |tx_out: &TxOut| -> Result<BlockIndex, Error> {
+            let tx_out_index = ledger_db.get_tx_out_index_by_public_key(&tx_out.public_key)?;
+            Ok(ledger_db.get_block_index_by_tx_out_index(tx_out_index)?)
+        };
+
+        // The monitor can only be rewound. A rejected rescan is not audited.
+        let audit_event = AuditEvent::new(&monitor_id, AuditAction::MonitorRescanned, "test", "");
+        assert!(mobilecoind_db
+            .rescan_monitor(&monitor_id, 4, &audit_event, tx_out_block_index)
+            .is_err());
+        assert!(mobilecoind_db
+            .get_audit_log(Some(&monitor_id), 0, usize::MAX)
+            .unwrap()
+            .is_empty());
+
+        mobilecoind_db
+            .rescan_monitor(&monitor_id, 1, &audit_event, tx_out_block_index)
+            .unwrap();
+        let audit_log = mobilecoind_db
+            .get_audit_log(Some(&monitor_id), 0, usize::MAX)
+            .unwrap();
+        assert_eq!(audit_log.len(), 1);
+        assert_eq!(audit_log[0].get_action(), AuditAction::MonitorRescanned);
+
+        assert_eq!(
+            mobilecoind_db
+                .get_monitor_data(&monitor_id)
+                .unwrap()
+                .next_block,
+            1
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap(),
+            vec![utxo0.clone()]
+        );
+        assert!(mobilecoind_db
+            .get_spent_txos(&monitor_id, None, 0, u64::max_value())
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            mobilecoind_db
+                .get_processed_block(&monitor_id, 0)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(mobilecoind_db
+            .get_processed_block(&monitor_id, 1)
+            .unwrap()
+            .is_empty());
+        assert!(mobilecoind_db
+            .get_processed_block(&monitor_id, 2)
+            .unwrap()
+            .is_empty());
+
+        // Scanning the blocks again restores the state the monitor had.
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                1,
+                &[utxo1.clone()],
+                &[],
+                &[],
+                &[processed_tx_out(
+                    1,
+                    &utxo1,
+                    ProcessedTxOutDirection::Received,
+                )],
+            )
+            .unwrap();
+        mobilecoind_db
+            .block_processed(
+                &monitor_id,
+                2,
+                &[],
+                &[utxo0.key_image],
+                &[],
+                &[processed_tx_out(2, &utxo0, ProcessedTxOutDirection::Spent)],
+            )
+            .unwrap();
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap(),
+            vec![utxo1]
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_transaction_history(&monitor_id, None, 0, u64::max_value())
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    // Removing an account should remove its monitors, and removing a monitor should remove it
+    // from its account.
+    #[test_with_logger]
+    fn test_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([124u8; 32]);
+
+        // Set up a db with 3 random recipients and 10 blocks.
+        let (_ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let monitors: Vec<MonitorData> = (0..3)
+            .map(|_| MonitorData::new(AccountKey::random(&mut rng), 0, 10, 0).unwrap())
+            .collect();
+
+        // Create an account with two monitors, and add a third one later.
+        let account = AccountData::new("wallet").unwrap();
+        let mut monitor_ids = mobilecoind_db
+            .create_account(&account, &monitors[0..2])
+            .expect("failed creating account");
+        monitor_ids.push(
+            mobilecoind_db
+                .add_account_monitor("wallet", &monitors[2])
+                .expect("failed adding monitor to account"),
+        );
+        assert_eq!(
+            mobilecoind_db.get_account("wallet").unwrap().monitor_ids,
+            monitor_ids
+        );
+
+        // Adding an existing monitor again should not change the account.
+        assert_eq!(
+            mobilecoind_db
+                .add_account_monitor("wallet", &monitors[2])
+                .unwrap(),
+            monitor_ids[2]
+        );
+        assert_eq!(
+            mobilecoind_db.get_account("wallet").unwrap().monitor_ids,
+            monitor_ids
+        );
+
+        // Account names must be unique.
+        match mobilecoind_db.create_account(&account, &[]) {
+            Err(Error::AccountNameExists) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Removing a monitor removes it from the account.
+        mobilecoind_db.remove_monitor(&monitor_ids[0]).unwrap();
+        assert_eq!(
+            mobilecoind_db.get_account("wallet").unwrap().monitor_ids,
+            &monitor_ids[1..]
+        );
+
+        // Removing the account removes its remaining monitors.
+        let removed = mobilecoind_db.remove_account("wallet").unwrap();
+        assert_eq!(removed.monitor_ids, &monitor_ids[1..]);
+        assert!(mobilecoind_db.get_account_list().unwrap().is_empty());
+        assert!(mobilecoind_db.get_monitor_ids().unwrap().is_empty());
+
+        match mobilecoind_db.remove_account("wallet") {
+            Err(Error::AccountNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
 }
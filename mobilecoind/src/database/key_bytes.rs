@@ -0,0 +1,49 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Encoding of LMDB keys shared by several stores: u64 keys, for stores that key records by a
+//! counter or timestamp, and (MonitorId, BlockIndex) prefixed keys, for stores that key records
+//! of a monitor by block.
+
+use crate::{error::Error, monitor_store::MonitorId};
+use std::convert::TryFrom;
+use transaction::BlockIndex;
+
+/// Length of a key made of a (MonitorId, BlockIndex) prefix followed by a 32 byte suffix.
+pub const MONITOR_BLOCK_KEY_LEN: usize = 72;
+
+/// Converts a u64 to big-endian bytes, so that LMDB keys sort numerically.
+pub fn u64_to_key_bytes(value: u64) -> [u8; 8] {
+    value.to_be_bytes()
+}
+
+/// Interprets bytes produced by `u64_to_key_bytes` as a u64.
+pub fn key_bytes_to_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_be_bytes(buf)
+}
+
+/// Returns the 40 byte (MonitorId, BlockIndex) prefix of a key. The block index is big-endian so
+/// that the keys of a monitor sort by block, and the prefix sorts before all the keys of the given
+/// monitor and block.
+pub fn monitor_block_key_prefix(monitor_id: &MonitorId, block_index: BlockIndex) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(MONITOR_BLOCK_KEY_LEN);
+    buf.extend_from_slice(monitor_id.as_bytes());
+    buf.extend_from_slice(&u64_to_key_bytes(block_index));
+    buf
+}
+
+/// Decodes the monitor id and block index of a key built on `monitor_block_key_prefix`.
+pub fn decode_monitor_block_key_prefix(src: &[u8]) -> Result<(MonitorId, BlockIndex), Error> {
+    if src.len() != MONITOR_BLOCK_KEY_LEN {
+        return Err(Error::InvalidArgument(
+            "src".to_string(),
+            format!("src length must be exactly {}", MONITOR_BLOCK_KEY_LEN),
+        ));
+    }
+
+    let monitor_id = MonitorId::try_from(&src[0..32])?;
+    let block_index = key_bytes_to_u64(&src[32..40]);
+
+    Ok((monitor_id, block_index))
+}
@@ -0,0 +1,280 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Schema migrations of the mobilecoind database.
+//! * The database stores the version of its schema, i.e. of the layout of the records of its
+//!   stores. A database written before versions were stored is at version 0.
+//! * Each change to a layout appends a `Migration` to `MIGRATIONS`, which rewrites the records of
+//!   the previous version. Steps are applied in order on start, each in its own transaction along
+//!   with the version it leads to, so that an interrupted migration resumes where it stopped.
+//! * A database whose version is newer than this mobilecoind knows about was written by a newer
+//!   release, and is refused rather than misread.
+//! * Steps that rewrite records sealed by the database cipher need the database to be unlocked.
+
+use super::Database;
+use crate::error::Error;
+
+use common::logger::log;
+use lmdb::{DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+
+// LMDB Database Names
+pub const SCHEMA_VERSION_DB_NAME: &str = "mobilecoind_db:migrations:schema_version";
+
+/// The key the schema version is stored under.
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// A step from one schema version to the next.
+pub struct Migration {
+    /// The version the database is at once the step is applied.
+    pub version: u64,
+
+    /// What the step changes, for the logs.
+    pub description: &'static str,
+
+    /// Rewrites the records of the previous version.
+    pub apply: fn(&Database, &mut RwTransaction) -> Result<(), Error>,
+}
+
+/// Every migration, ordered by version. Versions start at 1 and increase by one.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Store the schema version",
+        apply: store_schema_version,
+    },
+    Migration {
+        version: 2,
+        description: "Index the balance of every subaddress",
+        apply: index_subaddress_balances,
+    },
+];
+
+/// The schema version this mobilecoind writes.
+pub fn current_schema_version() -> u64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// The first migration only introduces the stored version, which every step records.
+fn store_schema_version(_database: &Database, _db_txn: &mut RwTransaction) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Subaddress balances are kept up to date as UTXOs are added and removed, starting from the
+/// UTXOs already stored.
+fn index_subaddress_balances(database: &Database, db_txn: &mut RwTransaction) -> Result<(), Error> {
+    database.utxo_store.rebuild_balances(db_txn)
+}
+
+/// Database storage for the schema version.
+#[derive(Clone)]
+pub struct SchemaVersionStore {
+    /// Mapping of SCHEMA_VERSION_KEY -> the big-endian schema version.
+    schema_version: lmdb::Database,
+}
+
+impl SchemaVersionStore {
+    pub fn new(env: &Environment) -> Result<Self, Error> {
+        let schema_version = env.create_db(Some(SCHEMA_VERSION_DB_NAME), DatabaseFlags::empty())?;
+        Ok(Self { schema_version })
+    }
+
+    /// The stored schema version, or 0 if none was stored yet.
+    pub fn get(&self, db_txn: &impl Transaction) -> Result<u64, Error> {
+        match db_txn.get(self.schema_version, &SCHEMA_VERSION_KEY) {
+            Ok(value_bytes) if value_bytes.len() == 8 => {
+                let mut version_bytes = [0u8; 8];
+                version_bytes.copy_from_slice(value_bytes);
+                Ok(u64::from_be_bytes(version_bytes))
+            }
+            Ok(_) => Err(Error::InvalidArgument(
+                "schema_version".to_string(),
+                "stored version must be exactly 8 bytes".to_string(),
+            )),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn set<'env>(&self, db_txn: &mut RwTransaction<'env>, version: u64) -> Result<(), Error> {
+        db_txn.put(
+            self.schema_version,
+            &SCHEMA_VERSION_KEY,
+            &version.to_be_bytes(),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+}
+
+impl Database {
+    /// The schema version the database is at.
+    pub fn schema_version(&self) -> Result<u64, Error> {
+        let db_txn = self.env.begin_ro_txn()?;
+        self.schema_version_store.get(&db_txn)
+    }
+
+    /// Applies the migrations the database has not gone through yet, and returns them. With
+    /// `dry_run`, they are applied in a single transaction that is then discarded, which checks
+    /// that they would succeed without changing anything.
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<&'static Migration>, Error> {
+        self.migrate_with(MIGRATIONS, dry_run)
+    }
+
+    fn migrate_with(
+        &self,
+        migrations: &'static [Migration],
+        dry_run: bool,
+    ) -> Result<Vec<&'static Migration>, Error> {
+        let from_version = self.schema_version()?;
+        let to_version = migrations.last().map_or(0, |migration| migration.version);
+        if from_version > to_version {
+            return Err(Error::SchemaVersionTooNew(from_version, to_version));
+        }
+
+        let pending: Vec<&'static Migration> = migrations
+            .iter()
+            .filter(|migration| migration.version > from_version)
+            .collect();
+        if pending.is_empty() {
+            return Ok(pending);
+        }
+
+        if dry_run {
+            let mut db_txn = self.env.begin_rw_txn()?;
+            for migration in &pending {
+                log::info!(
+                    self.logger,
+                    "Checking migration to schema version {}: {}",
+                    migration.version,
+                    migration.description
+                );
+                (migration.apply)(self, &mut db_txn)?;
+            }
+            db_txn.abort();
+            return Ok(pending);
+        }
+
+        for migration in &pending {
+            log::info!(
+                self.logger,
+                "Migrating to schema version {}: {}",
+                migration.version,
+                migration.description
+            );
+            let mut db_txn = self.env.begin_rw_txn()?;
+            (migration.apply)(self, &mut db_txn)?;
+            self.schema_version_store
+                .set(&mut db_txn, migration.version)?;
+            db_txn.commit()?;
+        }
+
+        log::info!(
+            self.logger,
+            "Migrated from schema version {} to {}",
+            from_version,
+            to_version
+        );
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{address_book_store::AddressBookEntry, test_utils::get_test_databases};
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+
+    // Relabels every address book entry, standing in for a change of layout.
+    fn relabel_address_book(database: &Database, db_txn: &mut RwTransaction) -> Result<(), Error> {
+        for entry in database.address_book_store.list(db_txn)? {
+            database.address_book_store.remove(db_txn, &entry.label)?;
+            let mut relabeled = entry.clone();
+            relabeled.label = format!("migrated {}", entry.label);
+            database.address_book_store.insert(db_txn, &relabeled)?;
+        }
+        Ok(())
+    }
+
+    fn fail(_database: &Database, _db_txn: &mut RwTransaction) -> Result<(), Error> {
+        Err(Error::InvalidArgument(
+            "migration".to_string(),
+            "always fails".to_string(),
+        ))
+    }
+
+    static TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "Store the schema version",
+            apply: store_schema_version,
+        },
+        Migration {
+            version: 2,
+            description: "Relabel the address book",
+            apply: relabel_address_book,
+        },
+    ];
+
+    static FAILING_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "Store the schema version",
+            apply: store_schema_version,
+        },
+        Migration {
+            version: 2,
+            description: "Relabel the address book",
+            apply: relabel_address_book,
+        },
+        Migration {
+            version: 3,
+            description: "Fail",
+            apply: fail,
+        },
+    ];
+
+    #[test_with_logger]
+    fn test_migrate(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([45u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let (_ledger_db, mobilecoind_db) = get_test_databases(3, &[], 10, logger, &mut rng);
+        assert_eq!(mobilecoind_db.schema_version().unwrap(), 0);
+
+        let entry = AddressBookEntry::new("alice", &account_key.default_subaddress(), "").unwrap();
+        mobilecoind_db.add_address_book_entry(&entry).unwrap();
+
+        // A dry run applies nothing.
+        let pending = mobilecoind_db.migrate_with(TEST_MIGRATIONS, true).unwrap();
+        assert_eq!(
+            pending.iter().map(|m| m.version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(mobilecoind_db.schema_version().unwrap(), 0);
+        assert!(mobilecoind_db.get_address_book_entry("alice").is_ok());
+
+        // Migrating applies the steps in order, once.
+        let applied = mobilecoind_db.migrate_with(TEST_MIGRATIONS, false).unwrap();
+        assert_eq!(applied.len(), 2);
+        assert_eq!(mobilecoind_db.schema_version().unwrap(), 2);
+        assert!(mobilecoind_db
+            .get_address_book_entry("migrated alice")
+            .is_ok());
+        assert!(mobilecoind_db
+            .migrate_with(TEST_MIGRATIONS, false)
+            .unwrap()
+            .is_empty());
+
+        // A failing step leaves the database at the last version that succeeded.
+        assert!(mobilecoind_db
+            .migrate_with(FAILING_MIGRATIONS, false)
+            .is_err());
+        assert_eq!(mobilecoind_db.schema_version().unwrap(), 2);
+
+        // A database newer than the migrations is refused.
+        match mobilecoind_db.migrate_with(&TEST_MIGRATIONS[..1], false) {
+            Err(Error::SchemaVersionTooNew(2, 1)) => {}
+            result => panic!("unexpected result {:?}", result.map(|m| m.len())),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Deadlines for long running operations.
+//! * API handlers pass the deadline of their RPC down to operations that may take a while, such
+//!   as building a transaction from many inputs.
+//! * These operations check the deadline between steps, and give up with
+//!   `Error::DeadlineExceeded` once the client is no longer waiting for the result.
+
+use crate::error::Error;
+use std::time::Instant;
+
+/// The point in time after which the result of an operation is no longer needed.
+pub enum Deadline {
+    /// The operation always runs to completion, e.g. when started by a background thread.
+    Never,
+
+    /// The operation is abandoned at the given instant.
+    At(Instant),
+
+    /// The operation is abandoned once the deadline set by the client of an RPC passes. RPCs
+    /// without a deadline are never abandoned.
+    Rpc(grpcio::Deadline),
+}
+
+impl Deadline {
+    /// Returns true if the deadline has passed.
+    pub fn exceeded(&self) -> bool {
+        match self {
+            Deadline::Never => false,
+            Deadline::At(instant) => Instant::now() >= *instant,
+            Deadline::Rpc(deadline) => deadline.exceeded(),
+        }
+    }
+
+    /// A cancellation checkpoint. Fails with `Error::DeadlineExceeded` if the deadline has passed.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.exceeded() {
+            Err(Error::DeadlineExceeded)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_check() {
+        assert!(Deadline::Never.check().is_ok());
+
+        let future = Deadline::At(Instant::now() + Duration::from_secs(3600));
+        assert!(future.check().is_ok());
+
+        let past = Deadline::At(Instant::now());
+        match past.check() {
+            Err(Error::DeadlineExceeded) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Bootstrap network discovery.
+//!
+//! A discovery endpoint serves a `SignedNetworkConfig` JSON document, describing the peers, quorum
+//! set, transaction sources and minimum fee of a network. The embedded configuration is signed by
+//! a key the operator trusts, so that end users don't need to maintain node lists by hand.
+
+use crate::error::Error;
+use common::ResponderId;
+use keys::{Ed25519Pair, Ed25519Public, Ed25519Signature, Signature, Signer, Verifier};
+use mcuri::ConsensusClientUri;
+use scp::QuorumSet;
+use serde::{Deserialize, Serialize};
+use std::{fs, str::FromStr};
+use url::Url;
+
+/// The network configuration served by a discovery endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Validator nodes to connect to.
+    pub peers: Vec<String>,
+
+    /// Quorum set for ledger syncing. If not provided, all peers are used.
+    pub quorum_set: Option<QuorumSet<ResponderId>>,
+
+    /// URLs to use for transaction data.
+    pub tx_source_urls: Vec<String>,
+
+    /// The minimum fee, in picoMOB, the network expects transactions to pay.
+    pub minimum_fee: Option<u64>,
+}
+
+impl NetworkConfig {
+    /// Parses the peer URIs in this configuration.
+    pub fn peer_uris(&self) -> Result<Vec<ConsensusClientUri>, Error> {
+        self.peers
+            .iter()
+            .map(|peer| {
+                ConsensusClientUri::from_str(peer)
+                    .map_err(|err| Error::Discovery(format!("Invalid peer {}: {:?}", peer, err)))
+            })
+            .collect()
+    }
+}
+
+/// A `NetworkConfig` together with a signature over it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedNetworkConfig {
+    /// The JSON-encoded `NetworkConfig`. This is kept as a string so that the signature covers
+    /// its exact bytes.
+    pub network_config: String,
+
+    /// Hex-encoded Ed25519 signature over `network_config`.
+    pub signature: String,
+}
+
+impl SignedNetworkConfig {
+    /// Serializes and signs a network configuration.
+    pub fn sign(network_config: &NetworkConfig, signer: &Ed25519Pair) -> Result<Self, Error> {
+        let network_config = serde_json::to_string(network_config)
+            .map_err(|err| Error::Discovery(format!("Serializing network config: {}", err)))?;
+        let signature: Ed25519Signature = signer
+            .try_sign(network_config.as_bytes())
+            .map_err(|err| Error::Discovery(format!("Signing network config: {}", err)))?;
+
+        Ok(Self {
+            network_config,
+            signature: hex::encode(signature.as_bytes()),
+        })
+    }
+
+    /// Verifies the signature and returns the network configuration.
+    ///
+    /// # Arguments
+    /// * `signer` - The public key the configuration is expected to be signed with.
+    pub fn verify(&self, signer: &Ed25519Public) -> Result<NetworkConfig, Error> {
+        let signature_bytes = hex::decode(&self.signature)
+            .map_err(|err| Error::Discovery(format!("Invalid signature encoding: {}", err)))?;
+        let signature = Ed25519Signature::from_bytes(&signature_bytes)
+            .map_err(|err| Error::Discovery(format!("Invalid signature: {}", err)))?;
+
+        signer
+            .verify(self.network_config.as_bytes(), &signature)
+            .map_err(|_| Error::Discovery("Network config signature mismatch".to_string()))?;
+
+        serde_json::from_str(&self.network_config)
+            .map_err(|err| Error::Discovery(format!("Invalid network config: {}", err)))
+    }
+}
+
+/// Fetches the network configuration from a discovery endpoint, and verifies its signature.
+///
+/// # Arguments
+/// * `discovery_url` - http(s) or file URL of a `SignedNetworkConfig` JSON document.
+/// * `signer` - The public key the configuration is expected to be signed with.
+pub fn fetch_network_config(
+    discovery_url: &str,
+    signer: &Ed25519Public,
+) -> Result<NetworkConfig, Error> {
+    let url = Url::parse(discovery_url)
+        .map_err(|err| Error::Discovery(format!("Invalid URL {}: {}", discovery_url, err)))?;
+
+    // Special treatment for file:// to read from a local file.
+    let body = if url.scheme() == "file" {
+        let path = &url[url::Position::BeforeHost..url::Position::AfterPath];
+        fs::read_to_string(path)?
+    } else {
+        reqwest::get(url.as_str())
+            .and_then(|response| response.error_for_status()?.text())
+            .map_err(|err| Error::Discovery(format!("Fetching {}: {}", url, err)))?
+    };
+
+    let signed: SignedNetworkConfig = serde_json::from_str(&body)
+        .map_err(|err| Error::Discovery(format!("Invalid response from {}: {}", url, err)))?;
+
+    signed.verify(signer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    fn test_network_config() -> NetworkConfig {
+        NetworkConfig {
+            peers: vec![
+                "mc://node1.test.mobilecoin.com/".to_string(),
+                "mc://node2.test.mobilecoin.com/".to_string(),
+            ],
+            quorum_set: None,
+            tx_source_urls: vec![
+                "https://s3-us-west-1.amazonaws.com/mobilecoin.chain/node1.test.mobilecoin.com/"
+                    .to_string(),
+            ],
+            minimum_fee: Some(10_000_000_000),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let network_config = test_network_config();
+
+        let signed = SignedNetworkConfig::sign(&network_config, &signer).unwrap();
+        assert_eq!(signed.verify(&signer.public_key()).unwrap(), network_config);
+        assert_eq!(
+            signed
+                .verify(&signer.public_key())
+                .unwrap()
+                .peer_uris()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    // Verification should fail with the wrong key, or if the config was tampered with.
+    fn test_verify_rejects_bad_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let other_signer = Ed25519Pair::from_random(&mut rng);
+
+        let signed = SignedNetworkConfig::sign(&test_network_config(), &signer).unwrap();
+        assert!(signed.verify(&other_signer.public_key()).is_err());
+
+        let mut tampered = signed.clone();
+        tampered.network_config = tampered.network_config.replace("node2", "evil");
+        assert!(tampered.verify(&signer.public_key()).is_err());
+
+        let mut garbled = signed;
+        garbled.signature = "not hex".to_string();
+        assert!(garbled.verify(&signer.public_key()).is_err());
+    }
+
+    #[test]
+    fn test_fetch_network_config_from_file() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let network_config = test_network_config();
+        let signed = SignedNetworkConfig::sign(&network_config, &signer).unwrap();
+
+        let dir = TempDir::new("discovery").unwrap();
+        let path = dir.path().join("network.json");
+        fs::write(&path, serde_json::to_string(&signed).unwrap()).unwrap();
+
+        let url = format!("file://{}", path.to_str().unwrap());
+        assert_eq!(
+            fetch_network_config(&url, &signer.public_key()).unwrap(),
+            network_config
+        );
+    }
+}
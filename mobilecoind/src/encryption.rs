@@ -0,0 +1,552 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Encryption at rest for the mobilecoind database.
+//! * Monitor data, UTXOs and transfer codes are sealed with AES-256-GCM before they are written,
+//!   with a key derived from a password (Argon2id) or from the contents of a keyfile.
+//! * What is needed to derive the key again is stored in plaintext, along with a value sealed
+//!   with the key that tells whether a key offered to unlock the database is the right one.
+//! * An encrypted database is locked when opened. Sealed records can neither be read nor written
+//!   until it is unlocked with its password or keyfile.
+//! * Only values are sealed. Each sealed value is bound to its key, which stays in plaintext,
+//!   e.g. UTXOs remain keyed by their key image.
+
+use crate::error::Error;
+
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, NewAead, Payload},
+    Aes256Gcm,
+};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use rand::RngCore;
+use sha3::{Digest, Sha3_256};
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+// LMDB Database Names
+pub const ENCRYPTION_PARAMS_DB_NAME: &str = "mobilecoind_db:encryption:params";
+
+/// The key the encryption parameters are stored under.
+const ENCRYPTION_PARAMS_KEY: &[u8] = b"params";
+
+/// Sealed with the database key, so that a key can be checked without opening any record.
+const KEY_CHECK_PLAINTEXT: &[u8] = b"mobilecoind database key check";
+const KEY_CHECK_AAD: &[u8] = b"key_check";
+
+/// Domain separator for keys derived from keyfiles.
+const KEYFILE_DOMAIN_TAG: &[u8] = b"mobilecoind database keyfile";
+
+/// The least number of bytes a keyfile holds.
+pub const MIN_KEYFILE_LEN: usize = 32;
+
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Argon2id costs of new password-derived keys.
+const ARGON2_MEM_COST_KIB: u32 = 64 * 1024;
+const ARGON2_TIME_COST: u32 = 3;
+
+/// How the database key is derived from the secret protecting it.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum KeyDerivation {
+    /// Argon2id over a password.
+    Password = 0,
+
+    /// SHA3-256 over the contents of a keyfile.
+    Keyfile = 1,
+}
+
+/// What is needed to derive the database key again. Stored in plaintext.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct EncryptionParams {
+    /// How the key is derived.
+    #[prost(enumeration = "KeyDerivation", tag = "1")]
+    pub key_derivation: i32,
+
+    /// Salt mixed into the key.
+    #[prost(bytes, tag = "2")]
+    pub salt: Vec<u8>,
+
+    /// Argon2id memory cost in KiB. Zero for keyfiles.
+    #[prost(uint32, tag = "3")]
+    pub argon2_mem_cost: u32,
+
+    /// Argon2id number of passes. Zero for keyfiles.
+    #[prost(uint32, tag = "4")]
+    pub argon2_time_cost: u32,
+
+    /// A known value sealed with the key.
+    #[prost(bytes, tag = "5")]
+    pub key_check: Vec<u8>,
+}
+
+impl EncryptionParams {
+    /// Parameters with a fresh salt, for a new key derived from `secret`.
+    fn new(secret: &DatabaseSecret) -> Self {
+        let mut salt = vec![0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let (argon2_mem_cost, argon2_time_cost) = match secret {
+            DatabaseSecret::Password(_) => (ARGON2_MEM_COST_KIB, ARGON2_TIME_COST),
+            DatabaseSecret::Keyfile(_) => (0, 0),
+        };
+
+        Self {
+            key_derivation: secret.key_derivation() as i32,
+            salt,
+            argon2_mem_cost,
+            argon2_time_cost,
+            key_check: Vec::new(),
+        }
+    }
+}
+
+/// A secret protecting the database key.
+#[derive(Clone)]
+pub enum DatabaseSecret {
+    Password(String),
+    Keyfile(Vec<u8>),
+}
+
+impl DatabaseSecret {
+    /// Reads a password from the first line of a file.
+    pub fn read_password_file(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        let password = contents.lines().next().unwrap_or_default();
+        Ok(DatabaseSecret::Password(password.to_string()))
+    }
+
+    /// Reads a keyfile, which holds at least `MIN_KEYFILE_LEN` random bytes.
+    pub fn read_keyfile(path: &Path) -> Result<Self, Error> {
+        Ok(DatabaseSecret::Keyfile(fs::read(path)?))
+    }
+
+    fn key_derivation(&self) -> KeyDerivation {
+        match self {
+            DatabaseSecret::Password(_) => KeyDerivation::Password,
+            DatabaseSecret::Keyfile(_) => KeyDerivation::Keyfile,
+        }
+    }
+
+    /// Derives the database key described by `params`.
+    fn derive_key(&self, params: &EncryptionParams) -> Result<Aes256Gcm, Error> {
+        if self.key_derivation() as i32 != params.key_derivation {
+            return Err(Error::WrongDatabaseKey);
+        }
+
+        let key = match self {
+            DatabaseSecret::Password(password) => {
+                if password.is_empty() {
+                    return Err(Error::InvalidArgument(
+                        "password".to_string(),
+                        "must not be empty".to_string(),
+                    ));
+                }
+                let config = argon2::Config {
+                    variant: argon2::Variant::Argon2id,
+                    mem_cost: params.argon2_mem_cost,
+                    time_cost: params.argon2_time_cost,
+                    hash_length: 32,
+                    ..Default::default()
+                };
+                argon2::hash_raw(password.as_bytes(), &params.salt, &config)
+                    .map_err(|err| Error::DatabaseKeyDerivation(err.to_string()))?
+            }
+            DatabaseSecret::Keyfile(contents) => {
+                if contents.len() < MIN_KEYFILE_LEN {
+                    return Err(Error::DatabaseKeyDerivation(format!(
+                        "keyfiles need to hold at least {} bytes",
+                        MIN_KEYFILE_LEN
+                    )));
+                }
+                let mut hasher = Sha3_256::new();
+                hasher.input(KEYFILE_DOMAIN_TAG);
+                hasher.input(&params.salt);
+                hasher.input(contents);
+                hasher.result().to_vec()
+            }
+        };
+
+        Ok(Aes256Gcm::new(GenericArray::clone_from_slice(&key)))
+    }
+}
+
+/// Seals `plaintext` under a random nonce, which is prepended to the ciphertext.
+fn seal_with(cipher: &Aes256Gcm, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(
+            GenericArray::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("Encrypting into unbounded buffer should not fail");
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    sealed
+}
+
+/// Counterpart to `seal_with`.
+fn open_with(cipher: &Aes256Gcm, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, Error> {
+    if sealed.len() < NONCE_LEN {
+        return Err(Error::DatabaseDecryption);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    cipher
+        .decrypt(
+            GenericArray::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| Error::DatabaseDecryption)
+}
+
+enum CipherState {
+    /// The database is not encrypted.
+    Plaintext,
+
+    /// The database is encrypted, and its key is not known.
+    Locked(EncryptionParams),
+
+    /// The database is encrypted with this key.
+    Unlocked(EncryptionParams, Aes256Gcm),
+}
+
+impl CipherState {
+    fn seal(&self, aad: &[u8], plaintext: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match self {
+            CipherState::Plaintext => Ok(plaintext),
+            CipherState::Locked(_) => Err(Error::DatabaseLocked),
+            CipherState::Unlocked(_, cipher) => Ok(seal_with(cipher, aad, &plaintext)),
+        }
+    }
+
+    fn open(&self, aad: &[u8], bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            CipherState::Plaintext => Ok(bytes.to_vec()),
+            CipherState::Locked(_) => Err(Error::DatabaseLocked),
+            CipherState::Unlocked(_, cipher) => open_with(cipher, aad, bytes),
+        }
+    }
+}
+
+/// Seals and opens the records of the stores holding secrets. Clones share their state, so
+/// unlocking the database through one unlocks it for all.
+#[derive(Clone)]
+pub struct DatabaseCipher {
+    /// Mapping of ENCRYPTION_PARAMS_KEY -> EncryptionParams. Empty while not encrypted.
+    encryption_params: Database,
+
+    state: Arc<RwLock<CipherState>>,
+}
+
+impl DatabaseCipher {
+    pub fn new(env: Arc<Environment>) -> Result<Self, Error> {
+        let encryption_params =
+            env.create_db(Some(ENCRYPTION_PARAMS_DB_NAME), DatabaseFlags::empty())?;
+
+        let db_txn = env.begin_ro_txn()?;
+        let state = match db_txn.get(encryption_params, &ENCRYPTION_PARAMS_KEY) {
+            Ok(value_bytes) => CipherState::Locked(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => CipherState::Plaintext,
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            encryption_params,
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// Whether the database is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        match *self.state.read().expect("lock poisoned") {
+            CipherState::Plaintext => false,
+            CipherState::Locked(_) | CipherState::Unlocked(_, _) => true,
+        }
+    }
+
+    /// Whether the database is encrypted and has not been unlocked.
+    pub fn is_locked(&self) -> bool {
+        match *self.state.read().expect("lock poisoned") {
+            CipherState::Locked(_) => true,
+            CipherState::Plaintext | CipherState::Unlocked(_, _) => false,
+        }
+    }
+
+    /// Unlocks the database with the secret protecting its key. Unlocking an unlocked database
+    /// checks the secret again.
+    pub fn unlock(&self, secret: &DatabaseSecret) -> Result<(), Error> {
+        let mut state = self.state.write().expect("lock poisoned");
+        let params = match &*state {
+            CipherState::Plaintext => return Err(Error::DatabaseNotEncrypted),
+            CipherState::Locked(params) | CipherState::Unlocked(params, _) => params.clone(),
+        };
+
+        let cipher = secret.derive_key(&params)?;
+        open_with(&cipher, KEY_CHECK_AAD, &params.key_check)
+            .map_err(|_| Error::WrongDatabaseKey)?;
+
+        *state = CipherState::Unlocked(params, cipher);
+        Ok(())
+    }
+
+    /// Seals a value stored under `key`. Values pass through unchanged while the database is not
+    /// encrypted.
+    pub fn seal(&self, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        self.state.read().expect("lock poisoned").seal(key, value)
+    }
+
+    /// Opens a value stored under `key`.
+    pub fn open(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        self.state.read().expect("lock poisoned").open(key, value)
+    }
+
+    /// Encrypts the database with a new key derived from `secret`. `reseal` has to reseal every
+    /// sealed record in `db_txn`, which is committed along with the new parameters. Records
+    /// cannot be opened in the meantime, so that no one sees records sealed with the other key.
+    pub fn rekey<'env, F>(
+        &self,
+        mut db_txn: RwTransaction<'env>,
+        secret: &DatabaseSecret,
+        reseal: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut RwTransaction<'env>, &Rekeying) -> Result<(), Error>,
+    {
+        let mut state = self.state.write().expect("lock poisoned");
+        if let CipherState::Locked(_) = *state {
+            return Err(Error::DatabaseLocked);
+        }
+
+        let mut params = EncryptionParams::new(secret);
+        let cipher = secret.derive_key(&params)?;
+        params.key_check = seal_with(&cipher, KEY_CHECK_AAD, KEY_CHECK_PLAINTEXT);
+
+        reseal(
+            &mut db_txn,
+            &Rekeying {
+                old: &*state,
+                new: &cipher,
+            },
+        )?;
+        db_txn.put(
+            self.encryption_params,
+            &ENCRYPTION_PARAMS_KEY,
+            &mcserial::encode(&params),
+            WriteFlags::empty(),
+        )?;
+        db_txn.commit()?;
+
+        *state = CipherState::Unlocked(params, cipher);
+        Ok(())
+    }
+}
+
+/// Reseals records while the database key changes.
+pub struct Rekeying<'a> {
+    old: &'a CipherState,
+    new: &'a Aes256Gcm,
+}
+
+impl Rekeying<'_> {
+    /// Reseals a value stored under `key` with the new key.
+    pub fn reseal(&self, key: &[u8], value: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = self.old.open(key, value)?;
+        Ok(seal_with(self.new, key, &plaintext))
+    }
+
+    /// Reseals every value of `db` with the new key.
+    pub fn reseal_all<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        db: Database,
+    ) -> Result<(), Error> {
+        let records = {
+            let mut cursor = db_txn.open_ro_cursor(db)?;
+            cursor
+                .iter()
+                .map(|(key_bytes, value_bytes)| {
+                    Ok((key_bytes.to_vec(), self.reseal(key_bytes, value_bytes)?))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+
+        for (key_bytes, value_bytes) in records {
+            db_txn.put(db, &key_bytes, &value_bytes, WriteFlags::empty())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn setup_test_env() -> (Arc<Environment>, Database) {
+        let db_tmp = TempDir::new("encryption_db")
+            .expect("Could not make tempdir for encryption db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let records = env
+            .create_db(Some("records"), DatabaseFlags::empty())
+            .unwrap();
+        (env, records)
+    }
+
+    fn put(
+        env: &Environment,
+        cipher: &DatabaseCipher,
+        records: Database,
+        key: &[u8],
+        value: &[u8],
+    ) {
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        let sealed = cipher.seal(key, value.to_vec()).unwrap();
+        db_txn
+            .put(records, &key, &sealed, WriteFlags::empty())
+            .unwrap();
+        db_txn.commit().unwrap();
+    }
+
+    fn get(
+        env: &Environment,
+        cipher: &DatabaseCipher,
+        records: Database,
+        key: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let db_txn = env.begin_ro_txn().unwrap();
+        cipher.open(key, db_txn.get(records, &key).unwrap())
+    }
+
+    fn rekey(
+        env: &Environment,
+        cipher: &DatabaseCipher,
+        records: Database,
+        secret: &DatabaseSecret,
+    ) {
+        let db_txn = env.begin_rw_txn().unwrap();
+        cipher
+            .rekey(db_txn, secret, |db_txn, rekeying| {
+                rekeying.reseal_all(db_txn, records)
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_encrypt_unlock_and_rekey() {
+        let (env, records) = setup_test_env();
+        let password = DatabaseSecret::Password("correct horse battery staple".to_string());
+        let keyfile = DatabaseSecret::Keyfile(vec![7u8; MIN_KEYFILE_LEN]);
+
+        // Records are stored in plaintext until the database is encrypted.
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        assert!(!cipher.is_encrypted());
+        put(&env, &cipher, records, b"a", b"secret a");
+        {
+            let db_txn = env.begin_ro_txn().unwrap();
+            assert_eq!(db_txn.get(records, &b"a").unwrap(), b"secret a");
+        }
+        match cipher.unlock(&password) {
+            Err(Error::DatabaseNotEncrypted) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Encrypting seals the existing records.
+        rekey(&env, &cipher, records, &password);
+        assert!(cipher.is_encrypted());
+        assert!(!cipher.is_locked());
+        put(&env, &cipher, records, b"b", b"secret b");
+        {
+            let db_txn = env.begin_ro_txn().unwrap();
+            assert_ne!(db_txn.get(records, &b"a").unwrap(), b"secret a");
+        }
+        assert_eq!(get(&env, &cipher, records, b"a").unwrap(), b"secret a");
+        assert_eq!(get(&env, &cipher, records, b"b").unwrap(), b"secret b");
+
+        // Sealed values are bound to their keys.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            let sealed_a = db_txn.get(records, &b"a").unwrap().to_vec();
+            db_txn
+                .put(records, &b"c", &sealed_a, WriteFlags::empty())
+                .unwrap();
+            db_txn.commit().unwrap();
+        }
+        match get(&env, &cipher, records, b"c") {
+            Err(Error::DatabaseDecryption) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            db_txn.del(records, &b"c", None).unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        // Reopening the database locks it.
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        assert!(cipher.is_locked());
+        match get(&env, &cipher, records, b"a") {
+            Err(Error::DatabaseLocked) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match cipher.seal(b"d", b"secret d".to_vec()) {
+            Err(Error::DatabaseLocked) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match cipher.unlock(&DatabaseSecret::Password("wrong".to_string())) {
+            Err(Error::WrongDatabaseKey) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match cipher.unlock(&keyfile) {
+            Err(Error::WrongDatabaseKey) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(cipher.is_locked());
+
+        cipher.unlock(&password).unwrap();
+        assert_eq!(get(&env, &cipher, records, b"a").unwrap(), b"secret a");
+
+        // Switching to a keyfile reseals every record.
+        rekey(&env, &cipher, records, &keyfile);
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        match cipher.unlock(&password) {
+            Err(Error::WrongDatabaseKey) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        cipher.unlock(&keyfile).unwrap();
+        assert_eq!(get(&env, &cipher, records, b"a").unwrap(), b"secret a");
+        assert_eq!(get(&env, &cipher, records, b"b").unwrap(), b"secret b");
+
+        // Keyfiles need to hold enough bytes.
+        let db_txn = env.begin_rw_txn().unwrap();
+        match cipher.rekey(
+            db_txn,
+            &DatabaseSecret::Keyfile(vec![1u8; 8]),
+            |_, _| Ok(()),
+        ) {
+            Err(Error::DatabaseKeyDerivation(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
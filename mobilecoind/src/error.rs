@@ -9,8 +9,12 @@ use lmdb::Error as LmdbError;
 use mcconnection::Error as ConnectionError;
 use mcserial::{decode::Error as DecodeError, encode::Error as EncodeError};
 use mobilecoin_api::ConversionError;
+use mobilecoind_enclave_api::Error as EnclaveError;
 use prost::DecodeError as ProstDecodeError;
 use retry::Error as RetryError;
+use sgx_css::Error as SigstructError;
+use transaction::dust::DustError;
+use transaction_std::TxBuilderError;
 
 #[derive(Debug, Fail)]
 pub enum Error {
@@ -74,15 +78,36 @@ pub enum Error {
     #[fail(display = "No matching key was found in UtxoStore:utxo_id_to_utxo")]
     UtxoIdNotFound,
 
+    #[fail(display = "Outgoing transaction already in database")]
+    DuplicateOutgoingTx,
+
+    #[fail(display = "No matching key was found in OutgoingTxStore")]
+    OutgoingTxNotFound,
+
+    #[fail(display = "Transaction approval already in database")]
+    DuplicateTxApproval,
+
+    #[fail(display = "No matching key was found in ApprovalStore")]
+    TxApprovalNotFound,
+
+    #[fail(display = "Approval not permitted: {}", _0)]
+    ApprovalNotPermitted(String),
+
     #[fail(display = "Optimization not beneficial: {}", _0)]
     OptimizationNotBeneficial(String),
 
     #[fail(display = "Tx build error: {}", _0)]
     TxBuildError(String),
 
+    #[fail(display = "Invalid tx proposal: {}", _0)]
+    InvalidTxProposal(String),
+
     #[fail(display = "Insufficient funds")]
     InsufficientFunds,
 
+    #[fail(display = "Value overflow")]
+    ValueOverflow,
+
     #[fail(display = "Insufficient funds due to UTXO fragmentation")]
     InsufficientFundsFragmentedUtxos,
 
@@ -91,6 +116,117 @@ pub enum Error {
 
     #[fail(display = "The ledger does not contain enough tx outs for rings")]
     InsufficientTxOuts,
+
+    #[fail(display = "Output violates the network's dust rules: {}", _0)]
+    Dust(DustError),
+
+    #[fail(display = "Invalid enclave signature: {}", _0)]
+    Sigstruct(SigstructError),
+
+    #[fail(display = "Consensus enclave measurement mismatch: {}", _0)]
+    MeasurementMismatch(String),
+
+    #[fail(display = "Network discovery error: {}", _0)]
+    Discovery(String),
+
+    #[fail(display = "Deadline exceeded")]
+    DeadlineExceeded,
+
+    #[fail(display = "Account snapshot error: {}", _0)]
+    AccountSnapshot(String),
+
+    #[fail(display = "State archive error: {}", _0)]
+    StateArchive(String),
+
+    #[fail(
+        display = "Database schema version {} is newer than the latest known version {}",
+        _0, _1
+    )]
+    SchemaVersionTooNew(u64, u64),
+
+    #[fail(display = "Monitor is watch-only and cannot spend")]
+    WatchOnlyMonitor,
+
+    #[fail(display = "An account with this name already exists")]
+    AccountNameExists,
+
+    #[fail(display = "No account with this name was found")]
+    AccountNotFound,
+
+    #[fail(display = "An address book entry with this label already exists")]
+    AddressBookLabelExists,
+
+    #[fail(display = "No address book entry with this label was found")]
+    AddressBookEntryNotFound,
+
+    #[fail(display = "HTTP server error: {}", _0)]
+    Http(hyper::Error),
+
+    #[fail(display = "TLS configuration error: {}", _0)]
+    Tls(String),
+
+    #[fail(display = "Transaction signer error: {}", _0)]
+    Signer(String),
+
+    #[fail(display = "No matching key was found in WebhookDeliveryStore")]
+    WebhookDeliveryNotFound,
+
+    #[fail(display = "Invalid webhook subscription: {}", _0)]
+    WebhookSubscription(String),
+
+    #[fail(display = "Invalid payment request: {}", _0)]
+    PaymentRequest(String),
+
+    #[fail(display = "Signing enclave error: {}", _0)]
+    Enclave(EnclaveError),
+
+    #[fail(display = "The monitor's account key is sealed, but no signing enclave is available")]
+    NoSigningEnclave,
+
+    #[fail(display = "Invalid tenants file: {}", _0)]
+    TenantTokens(String),
+
+    #[fail(display = "The API token is not bound to a tenant")]
+    UnknownTenantToken,
+
+    #[fail(display = "Transfer code already in database")]
+    DuplicateTransferCode,
+
+    #[fail(display = "No matching key was found in TransferCodeStore")]
+    TransferCodeNotFound,
+
+    #[fail(display = "The funds of the transfer code are not in the ledger")]
+    TransferCodeNotFunded,
+
+    #[fail(display = "The transfer code was already redeemed")]
+    TransferCodeRedeemed,
+
+    #[fail(display = "The database is encrypted and has not been unlocked")]
+    DatabaseLocked,
+
+    #[fail(display = "The database is not encrypted")]
+    DatabaseNotEncrypted,
+
+    #[fail(display = "Wrong database key")]
+    WrongDatabaseKey,
+
+    #[fail(display = "Failed deriving the database key: {}", _0)]
+    DatabaseKeyDerivation(String),
+
+    #[fail(display = "A sealed database record could not be opened")]
+    DatabaseDecryption,
+
+    #[fail(display = "Invalid signing keys: {}", _0)]
+    SigningKeys(String),
+
+    #[fail(display = "Invalid request signature: {}", _0)]
+    RequestSignature(String),
+
+    #[fail(
+        display = "{} of the transaction's key images are already spent by a pending transaction",
+        _0
+    )]
+    AlreadyPending(usize),
 }
 
 impl From<RetryError<ConnectionError>> for Error {
@@ -129,12 +265,24 @@ impl From<ProstDecodeError> for Error {
     }
 }
 
+impl From<hyper::Error> for Error {
+    fn from(e: hyper::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         Error::IO(e)
     }
 }
 
+impl From<SigstructError> for Error {
+    fn from(e: SigstructError) -> Self {
+        Error::Sigstruct(e)
+    }
+}
+
 impl<M> From<crossbeam_channel::SendError<M>> for Error {
     fn from(_e: crossbeam_channel::SendError<M>) -> Self {
         Error::ChannelSend
@@ -152,3 +300,25 @@ impl From<KeyError> for Error {
         Error::Key(e)
     }
 }
+
+impl From<DustError> for Error {
+    fn from(e: DustError) -> Self {
+        Error::Dust(e)
+    }
+}
+
+impl From<EnclaveError> for Error {
+    fn from(e: EnclaveError) -> Self {
+        Error::Enclave(e)
+    }
+}
+
+impl From<TxBuilderError> for Error {
+    fn from(e: TxBuilderError) -> Self {
+        match e {
+            TxBuilderError::InsufficientFunds(_, _) => Error::InsufficientFunds,
+            TxBuilderError::Dust(e) => Error::Dust(e),
+            e => Error::TxBuildError(e.to_string()),
+        }
+    }
+}
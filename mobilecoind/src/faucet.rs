@@ -0,0 +1,457 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A developer faucet, available in builds with the `dev` feature.
+//! * Sends funds from a configured monitor (the "treasury") to any address that asks for them,
+//!   so that SDK tests against local and test networks do not need to manage funded accounts.
+//! * Each address may only receive funds once per rate limit interval.
+//! * Served on its own port, separately from the mobilecoind API.
+
+use crate::{
+    coin_selection::CoinSelectionStrategy,
+    config::FaucetConfig,
+    database::Database,
+    deadline::Deadline,
+    error::Error,
+    monitor_store::MonitorId,
+    payment_queue::{build_payment, submit_payment, QueuedPayment},
+    payments::{Outlay, TransactionsManager},
+    service::get_tx_receipts,
+};
+use common::{
+    logger::{log, Logger},
+    HashMap,
+};
+use grpc_util::{rpc_internal_error, rpc_invalid_arg_error, rpc_logger, send_result};
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
+use ledger_db::LedgerDB;
+use mcconnection::UserTxConnection;
+use mobilecoind_api::{
+    mobilecoind_faucet_api,
+    mobilecoind_faucet_api_grpc::{create_mobilecoind_faucet_api, MobilecoindFaucetApi},
+};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use transaction::{account_keys::PublicAddress, mob::PicoMob};
+
+pub struct FaucetService {
+    /// GRPC server.
+    _server: grpcio::Server,
+}
+
+impl FaucetService {
+    pub fn new<T: UserTxConnection + 'static>(
+        config: &FaucetConfig,
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T>,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let port = config
+            .faucet_port
+            .ok_or_else(|| Error::InvalidArgument("faucet_port".into(), "missing".into()))?;
+        let api = FaucetApi::new(
+            config,
+            ledger_db,
+            mobilecoind_db,
+            transactions_manager,
+            logger,
+        )?;
+        let logger = api.logger.clone();
+
+        let env = Arc::new(
+            grpcio::EnvBuilder::new()
+                .name_prefix("Mobilecoind-Faucet-RPC".to_string())
+                .build(),
+        );
+        let faucet_service = create_mobilecoind_faucet_api(api);
+
+        log::info!(logger, "Starting mobilecoind faucet on port {}", port);
+        let server = grpc_util::run_server(env, vec![faucet_service], port, &logger);
+
+        Ok(Self { _server: server })
+    }
+}
+
+/// Tracks when each address last received funds.
+#[derive(Clone)]
+struct RateLimiter {
+    /// Minimal time between two requests for the same address.
+    interval: Duration,
+
+    /// Time of the last request of each address.
+    last_request: Arc<Mutex<HashMap<PublicAddress, Instant>>>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_request: Arc::new(Mutex::new(HashMap::default())),
+        }
+    }
+
+    /// Records a request for `address` made at `now`. Fails with the time left to wait if the
+    /// previous request for `address` is too recent.
+    fn try_acquire(&self, address: &PublicAddress, now: Instant) -> Result<(), Duration> {
+        let mut last_request = self.last_request.lock().expect("mutex poisoned");
+
+        if let Some(previous) = last_request.get(address) {
+            let elapsed = now.saturating_duration_since(*previous);
+            if elapsed < self.interval {
+                return Err(self.interval - elapsed);
+            }
+        }
+
+        // Forget addresses that are no longer limited, so that the map does not grow forever.
+        let interval = self.interval;
+        last_request.retain(|_, previous| now.saturating_duration_since(*previous) < interval);
+
+        last_request.insert(address.clone(), now);
+        Ok(())
+    }
+
+    /// Forgets a request recorded by `try_acquire`, e.g. because sending the funds failed.
+    fn release(&self, address: &PublicAddress, at: Instant) {
+        let mut last_request = self.last_request.lock().expect("mutex poisoned");
+        if last_request.get(address) == Some(&at) {
+            last_request.remove(address);
+        }
+    }
+}
+
+pub struct FaucetApi<T: UserTxConnection + 'static> {
+    monitor_id: MonitorId,
+    subaddress_index: u64,
+    max_value: PicoMob,
+    rate_limiter: RateLimiter,
+    transactions_manager: TransactionsManager<T>,
+    ledger_db: LedgerDB,
+    mobilecoind_db: Database,
+    logger: Logger,
+}
+
+impl<T: UserTxConnection + 'static> Clone for FaucetApi<T> {
+    fn clone(&self) -> Self {
+        Self {
+            monitor_id: self.monitor_id,
+            subaddress_index: self.subaddress_index,
+            max_value: self.max_value,
+            rate_limiter: self.rate_limiter.clone(),
+            transactions_manager: self.transactions_manager.clone(),
+            ledger_db: self.ledger_db.clone(),
+            mobilecoind_db: self.mobilecoind_db.clone(),
+            logger: self.logger.clone(),
+        }
+    }
+}
+
+impl<T: UserTxConnection + 'static> FaucetApi<T> {
+    pub fn new(
+        config: &FaucetConfig,
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        transactions_manager: TransactionsManager<T>,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let monitor_id_bytes = config
+            .faucet_monitor_id
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArgument("faucet_monitor_id".into(), "missing".into()))?;
+        let monitor_id = MonitorId::try_from(monitor_id_bytes)?;
+
+        // Make sure the monitor exists and covers the subaddress.
+        let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id)?;
+        if !monitor_data
+            .subaddress_indexes()
+            .contains(&config.faucet_subaddress)
+        {
+            return Err(Error::InvalidArgument(
+                "faucet_subaddress".into(),
+                "not covered by the faucet monitor".into(),
+            ));
+        }
+
+        Ok(Self {
+            monitor_id,
+            subaddress_index: config.faucet_subaddress,
+            max_value: config.faucet_max_value,
+            rate_limiter: RateLimiter::new(config.faucet_rate_limit),
+            transactions_manager,
+            ledger_db,
+            mobilecoind_db,
+            logger,
+        })
+    }
+
+    fn faucet_send_impl(
+        &mut self,
+        request: mobilecoind_faucet_api::FaucetSendRequest,
+    ) -> Result<mobilecoind_faucet_api::FaucetSendResponse, RpcStatus> {
+        let receiver = PublicAddress::try_from(request.get_receiver())
+            .map_err(|err| rpc_invalid_arg_error("receiver.try_from", err, &self.logger))?;
+
+        let value = match PicoMob::new(request.value) {
+            PicoMob::ZERO => self.max_value,
+            value if value > self.max_value => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some(format!("value may not exceed {}", self.max_value)),
+                ));
+            }
+            value => value,
+        };
+
+        let now = Instant::now();
+        self.rate_limiter
+            .try_acquire(&receiver, now)
+            .map_err(|wait| {
+                RpcStatus::new(
+                    RpcStatusCode::RESOURCE_EXHAUSTED,
+                    Some(format!(
+                        "address was funded recently, try again in {} seconds",
+                        wait.as_secs() + 1
+                    )),
+                )
+            })?;
+
+        let result = self.send(&receiver, value);
+        if result.is_err() {
+            self.rate_limiter.release(&receiver, now);
+        }
+        result
+    }
+
+    /// Builds and submits a transaction sending `value` to `receiver`.
+    fn send(
+        &self,
+        receiver: &PublicAddress,
+        value: PicoMob,
+    ) -> Result<mobilecoind_faucet_api::FaucetSendResponse, RpcStatus> {
+        let payment = QueuedPayment {
+            sender_monitor_id: self.monitor_id,
+            sender_subaddress: self.subaddress_index,
+            outlays: vec![Outlay {
+                value: value.picomob(),
+                receiver: receiver.clone(),
+            }],
+            fee: 0,
+            coin_selection: CoinSelectionStrategy::default(),
+        };
+
+        // Faucet transactions only spend UTXOs that are not already pending, so that concurrent
+        // requests do not conflict.
+        let tx_proposal = match build_payment(
+            &self.transactions_manager,
+            &self.ledger_db,
+            &self.mobilecoind_db,
+            &payment,
+            &Deadline::Never,
+        ) {
+            Ok(Some(tx_proposal)) => tx_proposal,
+            Ok(None) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::UNAVAILABLE,
+                    Some("faucet funds are pending, try again later".to_string()),
+                ));
+            }
+            Err(Error::InsufficientFunds) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::FAILED_PRECONDITION,
+                    Some("faucet is out of funds".to_string()),
+                ));
+            }
+            Err(err) => return Err(rpc_internal_error("build_payment", err, &self.logger)),
+        };
+
+        submit_payment(
+            &self.transactions_manager,
+            &self.mobilecoind_db,
+            &tx_proposal,
+            &self.logger,
+        )
+        .map_err(|err| rpc_internal_error("submit_payment", err, &self.logger))?;
+
+        log::info!(self.logger, "Faucet sent {} to {:?}", value, receiver);
+
+        let (_sender_tx_receipt, mut receiver_tx_receipts) = get_tx_receipts(&tx_proposal)?;
+        let mut response = mobilecoind_faucet_api::FaucetSendResponse::new();
+        response.set_receiver_tx_receipt(receiver_tx_receipts.remove(0));
+        response.set_value(value.picomob());
+        Ok(response)
+    }
+
+    fn get_faucet_status_impl(
+        &mut self,
+        _request: mobilecoind_api::Empty,
+    ) -> Result<mobilecoind_faucet_api::GetFaucetStatusResponse, RpcStatus> {
+        let utxos = self
+            .mobilecoind_db
+            .get_utxos_for_subaddress(&self.monitor_id, self.subaddress_index)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+            })?;
+
+        let mut response = mobilecoind_faucet_api::GetFaucetStatusResponse::new();
+        response.set_monitor_id(self.monitor_id.to_vec());
+        response.set_subaddress_index(self.subaddress_index);
+        let balance = PicoMob::checked_sum(utxos.iter().map(|utxo| PicoMob::new(utxo.value)))
+            .ok_or_else(|| rpc_internal_error("balance", Error::ValueOverflow, &self.logger))?;
+        response.set_balance(balance.picomob());
+        response.set_max_value(self.max_value.picomob());
+        response.set_rate_limit_seconds(self.rate_limiter.interval.as_secs());
+        Ok(response)
+    }
+}
+
+impl<T: UserTxConnection + 'static> MobilecoindFaucetApi for FaucetApi<T> {
+    fn faucet_send(
+        &mut self,
+        ctx: RpcContext,
+        request: mobilecoind_faucet_api::FaucetSendRequest,
+        sink: UnarySink<mobilecoind_faucet_api::FaucetSendResponse>,
+    ) {
+        let logger = rpc_logger(&ctx, &self.logger);
+        send_result(ctx, sink, self.faucet_send_impl(request), &logger)
+    }
+
+    fn get_faucet_status(
+        &mut self,
+        ctx: RpcContext,
+        request: mobilecoind_api::Empty,
+        sink: UnarySink<mobilecoind_faucet_api::GetFaucetStatusResponse>,
+    ) {
+        let logger = rpc_logger(&ctx, &self.logger);
+        send_result(ctx, sink, self.get_faucet_status_impl(request), &logger)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        monitor_store::MonitorData,
+        test_utils::{get_testing_environment, wait_for_monitors, PER_RECIPIENT_AMOUNT},
+    };
+    use common::logger::test_with_logger;
+    use ledger_db::Ledger;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let address1 = AccountKey::random(&mut rng).default_subaddress();
+        let address2 = AccountKey::random(&mut rng).default_subaddress();
+
+        let rate_limiter = RateLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(rate_limiter.try_acquire(&address1, start), Ok(()));
+        assert_eq!(rate_limiter.try_acquire(&address2, start), Ok(()));
+        assert_eq!(
+            rate_limiter.try_acquire(&address1, start + Duration::from_secs(20)),
+            Err(Duration::from_secs(40))
+        );
+
+        // Released requests do not count.
+        rate_limiter.release(&address2, start);
+        assert_eq!(
+            rate_limiter.try_acquire(&address2, start + Duration::from_secs(20)),
+            Ok(())
+        );
+
+        // Releasing an older request does not forget a newer one.
+        rate_limiter.release(&address2, start);
+        assert!(rate_limiter
+            .try_acquire(&address2, start + Duration::from_secs(30))
+            .is_err());
+
+        // Requests are allowed again once the interval has passed.
+        assert_eq!(
+            rate_limiter.try_acquire(&address1, start + Duration::from_secs(60)),
+            Ok(())
+        );
+    }
+
+    #[test_with_logger]
+    fn test_faucet_send(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let treasury = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            treasury.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db, _client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![treasury.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let config = FaucetConfig {
+            faucet_port: None,
+            faucet_monitor_id: Some(monitor_id.to_vec()),
+            faucet_subaddress: 0,
+            faucet_max_value: PicoMob::new(PER_RECIPIENT_AMOUNT / 10),
+            faucet_rate_limit: Duration::from_secs(60),
+        };
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            server_conn_manager,
+            logger.clone(),
+        );
+        let mut faucet = FaucetApi::new(
+            &config,
+            ledger_db.clone(),
+            mobilecoind_db,
+            transactions_manager,
+            logger,
+        )
+        .unwrap();
+
+        let status = faucet
+            .get_faucet_status_impl(mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(status.get_monitor_id(), &monitor_id.to_vec()[..]);
+        assert_eq!(
+            status.get_balance(),
+            PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+        assert_eq!(status.get_max_value(), PER_RECIPIENT_AMOUNT / 10);
+
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let mut request = mobilecoind_faucet_api::FaucetSendRequest::new();
+        request.set_receiver((&receiver).into());
+
+        // Requesting more than the maximal value should fail.
+        request.set_value(PER_RECIPIENT_AMOUNT / 10 + 1);
+        assert!(faucet.faucet_send_impl(request.clone()).is_err());
+
+        // Not specifying a value sends the maximal value.
+        request.set_value(0);
+        let response = faucet.faucet_send_impl(request.clone()).unwrap();
+        assert_eq!(response.get_value(), PER_RECIPIENT_AMOUNT / 10);
+        assert_eq!(
+            response.get_receiver_tx_receipt().get_receipient(),
+            &(&receiver).into()
+        );
+
+        // The same address cannot be funded again right away.
+        request.set_value(1);
+        let err = faucet.faucet_send_impl(request.clone()).unwrap_err();
+        assert_eq!(err.status, RpcStatusCode::RESOURCE_EXHAUSTED);
+    }
+}
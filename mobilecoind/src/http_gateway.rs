@@ -0,0 +1,687 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! An HTTP/JSON gateway for the mobilecoind API.
+//! * Every method of `MobilecoindAPI` is served at `POST /<MethodName>`, e.g. `POST /GetBalance`.
+//!   The request body and the response are the proto3 JSON mapping of the method's request and
+//!   response messages. Methods that take no arguments accept an empty body.
+//! * Requests are forwarded to the gRPC API, so the gateway behaves exactly like the gRPC API.
+//!   The `authorization` header is forwarded along, so that tenants are told apart as they are by
+//!   the gRPC API, see `tenants`.
+//!   gRPC errors are returned as the closest HTTP status, with a JSON body holding the gRPC status
+//!   code and details.
+//! * The gateway runs either inside mobilecoind, or standalone on another host. The gRPC link is
+//!   then protected with mutual TLS, see `tls`.
+//! * `StreamBlocks` responds with one JSON object per line, for as long as the stream lasts.
+//! * Once signing keys are configured, requests to all but the read-only methods need to be
+//!   signed, see `request_signing`. Requests that are not are rejected with 401 Unauthorized.
+
+use crate::{
+    error::Error,
+    request_signing::{requires_signature, RequestVerifier},
+    tls::MutualTlsConfig,
+};
+use common::logger::{log, Logger};
+use futures::{stream, sync::oneshot, Future, Stream};
+use grpcio::{
+    CallOption, ChannelBuilder, ClientSStreamReceiver, ClientUnaryReceiver, EnvBuilder,
+    MetadataBuilder, RpcStatusCode,
+};
+use hyper::{
+    header, service::service_fn, Body, HeaderMap, Method, Request, Response, Server, StatusCode,
+};
+use mobilecoind_api::mobilecoind_api_grpc::MobilecoindApiClient;
+use protobuf::Message;
+use std::{net::SocketAddr, sync::Arc, thread};
+
+type ResponseFuture = Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+pub struct HttpGateway {
+    /// Signals the server to stop.
+    shutdown_sender: Option<oneshot::Sender<()>>,
+
+    /// The thread running the server.
+    join_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl HttpGateway {
+    /// Serve the mobilecoind API listening on `service_addr` as JSON over HTTP.
+    ///
+    /// # Arguments
+    /// * `http_port` - Port to serve HTTP requests from.
+    /// * `service_addr` - Address (host:port) the mobilecoind gRPC API is served from.
+    /// * `tls_config` - Certificates and key to connect to mobilecoind with mutual TLS, if enabled.
+    /// * `tls_hostname_override` - Name the mobilecoind certificate is checked against, if it isn't
+    ///   the host in `service_addr`.
+    /// * `request_verifier` - Checks the signatures of requests to all but the read-only methods,
+    ///   if they need to be signed.
+    /// * `logger` - Logger.
+    pub fn new(
+        http_port: u16,
+        service_addr: &str,
+        tls_config: Option<&MutualTlsConfig>,
+        tls_hostname_override: Option<&str>,
+        request_verifier: Option<RequestVerifier>,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let env = Arc::new(
+            EnvBuilder::new()
+                .name_prefix("Mobilecoind-HTTP-Gateway".to_string())
+                .build(),
+        );
+        let mut builder = ChannelBuilder::new(env);
+        if let Some(tls_hostname_override) = tls_hostname_override {
+            builder = builder.override_ssl_target(tls_hostname_override);
+        }
+        let ch = match tls_config {
+            Some(tls_config) => {
+                log::info!(
+                    logger,
+                    "Connecting to mobilecoind at {} over TLS",
+                    service_addr
+                );
+                builder.secure_connect(service_addr, tls_config.channel_credentials())
+            }
+            None => builder.connect(service_addr),
+        };
+
+        Self::start(
+            http_port,
+            MobilecoindApiClient::new(ch),
+            request_verifier,
+            logger,
+        )
+    }
+
+    fn start(
+        http_port: u16,
+        client: MobilecoindApiClient,
+        request_verifier: Option<RequestVerifier>,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let addr = SocketAddr::from(([0, 0, 0, 0], http_port));
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+
+        let request_verifier = request_verifier.map(Arc::new);
+        let handler_logger = logger.clone();
+        let server = Server::try_bind(&addr)?
+            .serve(move || {
+                let client = client.clone();
+                let request_verifier = request_verifier.clone();
+                let logger = handler_logger.clone();
+                service_fn(move |request| handle(&client, &request_verifier, &logger, request))
+            })
+            .with_graceful_shutdown(shutdown_receiver.then(|_| Ok::<(), ()>(())));
+
+        log::info!(logger, "HTTP gateway listening on {}", addr);
+        let join_handle = thread::Builder::new()
+            .name("HttpGateway".into())
+            .spawn(move || {
+                hyper::rt::run(server.map_err(move |err| {
+                    log::error!(logger, "HTTP gateway failed: {}", err);
+                }))
+            })?;
+
+        Ok(Self {
+            shutdown_sender: Some(shutdown_sender),
+            join_handle: Some(join_handle),
+        })
+    }
+}
+
+impl Drop for HttpGateway {
+    fn drop(&mut self) {
+        if let Some(shutdown_sender) = self.shutdown_sender.take() {
+            let _ = shutdown_sender.send(());
+        }
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+    }
+}
+
+/// Route a request to the gRPC method named by its path, once its signature is checked if it
+/// needs one.
+fn handle(
+    client: &MobilecoindApiClient,
+    request_verifier: &Option<Arc<RequestVerifier>>,
+    logger: &Logger,
+    request: Request<Body>,
+) -> ResponseFuture {
+    if request.method() != Method::POST {
+        return Box::new(futures::future::ok(error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Only POST is supported",
+        )));
+    }
+
+    let client = client.clone();
+    let request_verifier = request_verifier
+        .clone()
+        .filter(|_| requires_signature(request.uri().path().trim_start_matches('/')));
+    let logger = logger.clone();
+    let method = request.uri().path().trim_start_matches('/').to_string();
+    let (parts, body) = request.into_parts();
+    Box::new(body.concat2().and_then(move |body| {
+        if let Some(request_verifier) = request_verifier {
+            match request_verifier.verify(&method, &parts.headers, &body) {
+                Ok(signed_request) => log::info!(
+                    logger,
+                    "{} request signed with key {} at {}, nonce {}, signature {}",
+                    method,
+                    signed_request.key_id,
+                    signed_request.timestamp,
+                    signed_request.nonce,
+                    signed_request.signature
+                ),
+                Err(err) => {
+                    log::warn!(logger, "Rejected {} request: {}", method, err);
+                    return Box::new(futures::future::ok(error_response(
+                        StatusCode::UNAUTHORIZED,
+                        &err.to_string(),
+                    ))) as ResponseFuture;
+                }
+            }
+        }
+        dispatch(&client, &method, &body, call_option(&parts.headers))
+    }))
+}
+
+/// The options of the gRPC call a request is forwarded with. The API token in the `authorization`
+/// header, if any, is passed on, so that the call is scoped to the caller's tenant.
+fn call_option(headers: &HeaderMap) -> CallOption {
+    let mut metadata = MetadataBuilder::with_capacity(1);
+    if let Some(authorization) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+    {
+        // Values that are not valid metadata are dropped, and the call is then made anonymously.
+        let _ = metadata.add_str("authorization", authorization);
+    }
+    CallOption::default().headers(metadata.build())
+}
+
+// Routes requests to the gRPC method of the same name, and lists the names it routes.
+macro_rules! routes {
+    ($($name:literal => $kind:ident $call:path),* $(,)?) => {
+        /// The names of the gRPC methods served by the gateway.
+        pub const GATEWAY_METHODS: &[&str] = &[$($name),*];
+
+        fn dispatch(
+            client: &MobilecoindApiClient,
+            method: &str,
+            body: &[u8],
+            call_option: CallOption,
+        ) -> ResponseFuture {
+            use MobilecoindApiClient as C;
+
+            match method {
+                $($name => $kind(client, body, call_option, $call),)*
+                _ => Box::new(futures::future::ok(error_response(
+                    StatusCode::NOT_FOUND,
+                    &format!("Unknown method {}", method),
+                ))),
+            }
+        }
+    };
+}
+
+routes! {
+    "AddMonitor" => unary C::add_monitor_async_opt,
+    "RemoveMonitor" => unary C::remove_monitor_async_opt,
+    "GetMonitorList" => unary C::get_monitor_list_async_opt,
+    "GetMonitorStatus" => unary C::get_monitor_status_async_opt,
+    "SetMonitorPriority" => unary C::set_monitor_priority_async_opt,
+    "RescanMonitor" => unary C::rescan_monitor_async_opt,
+    "UpdateMonitorMetadata" => unary C::update_monitor_metadata_async_opt,
+    "GetUnspentTxOutList" => unary C::get_unspent_tx_out_list_async_opt,
+    "UpdateUtxoMemo" => unary C::update_utxo_memo_async_opt,
+    "GetProcessedTxOutHistory" => unary C::get_processed_tx_out_history_async_opt,
+    "GetIncomingPaymentList" => unary C::get_incoming_payment_list_async_opt,
+    "GetTransactionHistory" => unary C::get_transaction_history_async_opt,
+    "GetSpentTxOuts" => unary C::get_spent_tx_outs_async_opt,
+    "GetMonitorAuditLog" => unary C::get_monitor_audit_log_async_opt,
+    "ExportAccountSnapshot" => unary C::export_account_snapshot_async_opt,
+    "ImportAccountSnapshot" => unary C::import_account_snapshot_async_opt,
+    "CreateAccount" => unary C::create_account_async_opt,
+    "ListAccounts" => unary C::list_accounts_async_opt,
+    "RemoveAccount" => unary C::remove_account_async_opt,
+    "AddAddressBookEntry" => unary C::add_address_book_entry_async_opt,
+    "UpdateAddressBookEntry" => unary C::update_address_book_entry_async_opt,
+    "GetAddressBookEntry" => unary C::get_address_book_entry_async_opt,
+    "GetAddressBook" => unary C::get_address_book_async_opt,
+    "RemoveAddressBookEntry" => unary C::remove_address_book_entry_async_opt,
+    "GenerateEntropy" => unary C::generate_entropy_async_opt,
+    "GetAccountKey" => unary C::get_account_key_async_opt,
+    "GetPublicAddress" => unary C::get_public_address_async_opt,
+    "ReadRequestCode" => unary C::read_request_code_async_opt,
+    "GetRequestCode" => unary C::get_request_code_async_opt,
+    "ReadTransferCode" => unary C::read_transfer_code_async_opt,
+    "GetTransferCode" => unary C::get_transfer_code_async_opt,
+    "CreateRequest" => unary C::create_request_async_opt,
+    "ParseRequest" => unary C::parse_request_async_opt,
+    "CreateMobUri" => unary C::create_mob_uri_async_opt,
+    "ParseMobUri" => unary C::parse_mob_uri_async_opt,
+    "GenerateTx" => unary C::generate_tx_async_opt,
+    "GenerateOptimizationTx" => unary C::generate_optimization_tx_async_opt,
+    "GenerateTransferCodeTx" => unary C::generate_transfer_code_tx_async_opt,
+    "SubmitTx" => unary C::submit_tx_async_opt,
+    "VerifyTxProposal" => unary C::verify_tx_proposal_async_opt,
+    "GenerateUnsignedTx" => unary C::generate_unsigned_tx_async_opt,
+    "SignTx" => unary C::sign_tx_async_opt,
+    "ApproveTx" => unary C::approve_tx_async_opt,
+    "RejectTx" => unary C::reject_tx_async_opt,
+    "GetTxApprovalList" => unary C::get_tx_approval_list_async_opt,
+    "GetLedgerInfo" => unary C::get_ledger_info_async_opt,
+    "GetBlockInfo" => unary C::get_block_info_async_opt,
+    "GetBlockSignature" => unary C::get_block_signature_async_opt,
+    "GetProcessedBlock" => unary C::get_processed_block_async_opt,
+    "StreamBlocks" => server_streaming C::stream_blocks_opt,
+    "GetTxStatusAsSender" => unary C::get_tx_status_as_sender_async_opt,
+    "GetTxStatusAsReceiver" => unary C::get_tx_status_as_receiver_async_opt,
+    "GetTxOutResultsByPublicKey" => unary C::get_tx_out_results_by_public_key_async_opt,
+    "GetMembershipProofs" => unary C::get_membership_proofs_async_opt,
+    "GetTxOutsByBlockRange" => unary C::get_tx_outs_by_block_range_async_opt,
+    "GetBlockByKeyImage" => unary C::get_block_by_key_image_async_opt,
+    "GetOutgoingTxList" => unary C::get_outgoing_tx_list_async_opt,
+    "GetOutgoingTx" => unary C::get_outgoing_tx_async_opt,
+    "GetBalance" => unary C::get_balance_async_opt,
+    "GetBalanceBySubaddress" => unary C::get_balance_by_subaddress_async_opt,
+    "GetBalanceBySubaddressList" => unary C::get_balance_by_subaddress_list_async_opt,
+    "SendPayment" => unary C::send_payment_async_opt,
+    "GetQueuedPaymentStatus" => unary C::get_queued_payment_status_async_opt,
+    "CreateTransferCode" => unary C::create_transfer_code_async_opt,
+    "RedeemTransferCode" => unary C::redeem_transfer_code_async_opt,
+    "UnlockDatabase" => unary C::unlock_database_async_opt,
+    "ReencryptDatabase" => unary C::reencrypt_database_async_opt,
+    "ExportState" => unary C::export_state_async_opt,
+    "ImportState" => unary C::import_state_async_opt,
+}
+
+/// Forward a request to a unary gRPC method.
+fn unary<Req: Message, Resp: Message>(
+    client: &MobilecoindApiClient,
+    body: &[u8],
+    call_option: CallOption,
+    call: impl FnOnce(
+        &MobilecoindApiClient,
+        &Req,
+        CallOption,
+    ) -> grpcio::Result<ClientUnaryReceiver<Resp>>,
+) -> ResponseFuture {
+    let request = match parse_request::<Req>(body) {
+        Ok(request) => request,
+        Err(response) => return Box::new(futures::future::ok(response)),
+    };
+
+    match call(client, &request, call_option) {
+        Ok(receiver) => Box::new(receiver.then(|result| {
+            Ok(match result {
+                Ok(response) => message_response(&response),
+                Err(err) => grpc_error_response(&err),
+            })
+        })),
+        Err(err) => Box::new(futures::future::ok(grpc_error_response(&err))),
+    }
+}
+
+/// Forward a request to a server streaming gRPC method. If the stream fails before its first
+/// response, the error is returned like that of a unary method. Otherwise, responses are written
+/// one per line, and a later error ends the response.
+fn server_streaming<Req: Message, Resp: Message>(
+    client: &MobilecoindApiClient,
+    body: &[u8],
+    call_option: CallOption,
+    call: impl FnOnce(
+        &MobilecoindApiClient,
+        &Req,
+        CallOption,
+    ) -> grpcio::Result<ClientSStreamReceiver<Resp>>,
+) -> ResponseFuture {
+    let request = match parse_request::<Req>(body) {
+        Ok(request) => request,
+        Err(response) => return Box::new(futures::future::ok(response)),
+    };
+
+    match call(client, &request, call_option) {
+        Ok(receiver) => Box::new(receiver.into_future().then(|result| {
+            Ok(match result {
+                Ok((first, rest)) => {
+                    let lines = stream::iter_ok(first)
+                        .chain(rest)
+                        .map(|response| format!("{}\n", to_json(&response)));
+                    Response::builder()
+                        .header(header::CONTENT_TYPE, "application/x-ndjson")
+                        .body(Body::wrap_stream(lines))
+                        .expect("Failed building response")
+                }
+                Err((err, _rest)) => grpc_error_response(&err),
+            })
+        })),
+        Err(err) => Box::new(futures::future::ok(grpc_error_response(&err))),
+    }
+}
+
+/// Parse a request message from its JSON mapping. An empty body is an empty message.
+fn parse_request<Req: Message>(body: &[u8]) -> Result<Req, Response<Body>> {
+    let json = std::str::from_utf8(body)
+        .map_err(|_| error_response(StatusCode::BAD_REQUEST, "Request body is not UTF-8"))?;
+    if json.trim().is_empty() {
+        return Ok(Req::new());
+    }
+
+    protobuf::json::parse_from_str(json).map_err(|err| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            &format!("Invalid request: {:?}", err),
+        )
+    })
+}
+
+fn to_json(message: &impl Message) -> String {
+    protobuf::json::print_to_string(message).unwrap_or_else(|err| {
+        serde_json::json!({ "error": format!("Failed encoding response: {:?}", err) }).to_string()
+    })
+}
+
+fn message_response(message: &impl Message) -> Response<Body> {
+    json_response(StatusCode::OK, to_json(message))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, serde_json::json!({ "error": message }).to_string())
+}
+
+fn grpc_error_response(err: &grpcio::Error) -> Response<Body> {
+    match err {
+        grpcio::Error::RpcFailure(rpc_status) => {
+            let code: i32 = rpc_status.status.into();
+            json_response(
+                http_status(rpc_status.status),
+                serde_json::json!({
+                    "error": rpc_status.details.clone().unwrap_or_default(),
+                    "code": code,
+                })
+                .to_string(),
+            )
+        }
+        err => error_response(StatusCode::BAD_GATEWAY, &err.to_string()),
+    }
+}
+
+fn json_response(status: StatusCode, json: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(json))
+        .expect("Failed building response")
+}
+
+/// The HTTP status closest to a gRPC status.
+fn http_status(code: RpcStatusCode) -> StatusCode {
+    match code {
+        RpcStatusCode::OK => StatusCode::OK,
+        RpcStatusCode::INVALID_ARGUMENT | RpcStatusCode::OUT_OF_RANGE => StatusCode::BAD_REQUEST,
+        RpcStatusCode::UNAUTHENTICATED => StatusCode::UNAUTHORIZED,
+        RpcStatusCode::PERMISSION_DENIED => StatusCode::FORBIDDEN,
+        RpcStatusCode::NOT_FOUND => StatusCode::NOT_FOUND,
+        RpcStatusCode::ALREADY_EXISTS | RpcStatusCode::ABORTED => StatusCode::CONFLICT,
+        RpcStatusCode::FAILED_PRECONDITION => StatusCode::PRECONDITION_FAILED,
+        RpcStatusCode::RESOURCE_EXHAUSTED => StatusCode::TOO_MANY_REQUESTS,
+        RpcStatusCode::UNIMPLEMENTED => StatusCode::NOT_IMPLEMENTED,
+        RpcStatusCode::UNAVAILABLE => StatusCode::SERVICE_UNAVAILABLE,
+        RpcStatusCode::DEADLINE_EXCEEDED => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        request_signing::{
+            sign_request, KEY_ID_HEADER, NONCE_HEADER, READ_ONLY_METHODS, SIGNATURE_HEADER,
+            TIMESTAMP_HEADER,
+        },
+        tenants::{token_hash, TenantTokens},
+        test_utils::{
+            get_free_port, get_testing_environment, get_testing_environment_with_tenants,
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+        },
+    };
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::account_keys::AccountKey;
+
+    #[test]
+    // Every method of the API should be served, and only the read-only ones without a signature.
+    fn test_gateway_methods_require_signature() {
+        let mut api_methods: Vec<&str> = include_str!("../api/proto/mobilecoind_api.proto")
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("rpc "))
+            .map(|line| {
+                line[4..]
+                    .split(|c: char| c == '(' || c.is_whitespace())
+                    .next()
+                    .unwrap()
+            })
+            .collect();
+        api_methods.sort();
+        let mut gateway_methods = GATEWAY_METHODS.to_vec();
+        gateway_methods.sort();
+        assert_eq!(api_methods, gateway_methods);
+
+        for method in READ_ONLY_METHODS {
+            assert!(GATEWAY_METHODS.contains(method), "{} is not served", method);
+        }
+
+        for method in GATEWAY_METHODS {
+            assert_eq!(
+                requires_signature(method),
+                !READ_ONLY_METHODS.contains(method),
+                "{}",
+                method
+            );
+        }
+
+        // Methods that return secrets need to be signed even though they do not change anything.
+        for method in &[
+            "GetMonitorStatus",
+            "ExportState",
+            "ExportAccountSnapshot",
+            "GetAccountKey",
+            "SignTx",
+            "GenerateTx",
+            "GenerateOptimizationTx",
+            "GenerateTransferCodeTx",
+            "GenerateUnsignedTx",
+        ] {
+            assert!(requires_signature(method), "{}", method);
+        }
+
+        // And so do methods the gateway does not know about yet.
+        assert!(requires_signature("FaucetSend"));
+    }
+
+    #[test_with_logger]
+    fn test_http_gateway(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let port = get_free_port();
+        let _gateway = HttpGateway::start(port, client, None, logger).unwrap();
+        let http_client = reqwest::Client::new();
+        let url = |method: &str| format!("http://127.0.0.1:{}/{}", port, method);
+
+        // Methods without arguments accept an empty body.
+        let mut response = http_client.post(&url("GetLedgerInfo")).send().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let json: serde_json::Value = response.json().unwrap();
+        assert_eq!(
+            json["blockCount"],
+            ledger_db.num_blocks().unwrap().to_string()
+        );
+
+        // gRPC errors are mapped to HTTP statuses.
+        let mut response = http_client
+            .post(&url("GetBlockInfo"))
+            .body(format!(
+                r#"{{"block": "{}"}}"#,
+                GET_TESTING_ENVIRONMENT_NUM_BLOCKS + 100
+            ))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let json: serde_json::Value = response.json().unwrap();
+        assert_eq!(json["code"], 13);
+
+        // Invalid requests are rejected before reaching the API.
+        let response = http_client
+            .post(&url("GetBlockInfo"))
+            .body("not json")
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = http_client.post(&url("NoSuchMethod")).send().unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = http_client.get(&url("GetLedgerInfo")).send().unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test_with_logger]
+    fn test_http_gateway_request_signing(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let key = [5u8; 32];
+        let request_verifier = RequestVerifier::parse(
+            format!(
+                r#"[{{"key_id": "exchange", "key_hex": "{}"}}]"#,
+                hex::encode(&key)
+            )
+            .as_bytes(),
+            std::time::Duration::from_secs(300),
+        )
+        .unwrap();
+
+        let port = get_free_port();
+        let _gateway = HttpGateway::start(port, client, Some(request_verifier), logger).unwrap();
+        let http_client = reqwest::Client::new();
+        let url = |method: &str| format!("http://127.0.0.1:{}/{}", port, method);
+
+        // Methods that only read do not need to be signed.
+        let response = http_client.post(&url("GetLedgerInfo")).send().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Other methods do.
+        let body = r#"{"monitorId": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}"#;
+        let response = http_client
+            .post(&url("RemoveMonitor"))
+            .body(body)
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // A signed request reaches the API.
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let signed_request = || {
+            http_client
+                .post(&url("RemoveMonitor"))
+                .header(KEY_ID_HEADER, "exchange")
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(NONCE_HEADER, "nonce-1")
+                .header(
+                    SIGNATURE_HEADER,
+                    sign_request(&key, "RemoveMonitor", timestamp, "nonce-1", body.as_bytes()),
+                )
+                .body(body)
+                .send()
+                .unwrap()
+        };
+        let response = signed_request();
+        assert_ne!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Replaying it is not.
+        let response = signed_request();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test_with_logger]
+    // The API token of a request should be forwarded, so that tenants are told apart.
+    fn test_http_gateway_tenants(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([25u8; 32]);
+
+        let tenants_dir = TempDir::new("tenants").unwrap();
+        let tenants_path = tenants_dir.path().join("tenants.json");
+        std::fs::write(
+            &tenants_path,
+            format!(
+                r#"[
+                    {{"tenant": "alice", "token_sha3_256": "{}"}},
+                    {{"tenant": "bob", "token_sha3_256": "{}"}}
+                ]"#,
+                hex::encode(token_hash("alice-token")),
+                hex::encode(token_hash("bob-token")),
+            ),
+        )
+        .unwrap();
+
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment_with_tenants(
+                3,
+                &vec![],
+                &vec![],
+                TenantTokens::load(&tenants_path).unwrap(),
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Alice adds a monitor through the gRPC API.
+        let mut headers = MetadataBuilder::with_capacity(1);
+        headers
+            .add_str("authorization", "Bearer alice-token")
+            .unwrap();
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key((&AccountKey::random(&mut rng)).into());
+        request.set_num_subaddresses(1);
+        client
+            .add_monitor_opt(&request, CallOption::default().headers(headers.build()))
+            .unwrap();
+
+        let port = get_free_port();
+        let _gateway = HttpGateway::start(port, client, None, logger).unwrap();
+        let http_client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/GetMonitorList", port);
+        let num_monitors =
+            |json: &serde_json::Value| json["monitorIdList"].as_array().map(Vec::len).unwrap_or(0);
+
+        // Requests without a token are rejected, like they are by the gRPC API.
+        let response = http_client.post(&url).send().unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Each tenant only sees its own monitors.
+        let mut response = http_client
+            .post(&url)
+            .header(header::AUTHORIZATION, "Bearer alice-token")
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(num_monitors(&response.json().unwrap()), 1);
+
+        let mut response = http_client
+            .post(&url)
+            .header(header::AUTHORIZATION, "Bearer bob-token")
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(num_monitors(&response.json().unwrap()), 0);
+    }
+}
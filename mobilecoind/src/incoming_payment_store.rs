@@ -0,0 +1,354 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for incoming payments.
+//! * Manages the mapping of (monitor id, block index, TxOut public key) -> IncomingPayment.
+//! * Every TxOut a monitor discovers is recorded here, together with where and when it was
+//!   found. Unlike the UTXO set, entries are not removed when the TxOut is spent, nor when the
+//!   monitor is removed, unless its history is removed along with it. A monitor that is added
+//!   again rediscovers the same TxOuts, which keep their original discovery time.
+
+use crate::{
+    database::key_bytes::{decode_monitor_block_key_prefix, monitor_block_key_prefix},
+    error::Error,
+    monitor_store::MonitorId,
+    utxo_store::UnspentTxOut,
+};
+
+use common::logger::Logger;
+use keys::CompressedRistrettoPublic;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+use transaction::{ring_signature::KeyImage, tx::TxOut, BlockIndex};
+
+// LMDB Database Names
+pub const INCOMING_PAYMENT_KEY_TO_INCOMING_PAYMENT_DB_NAME: &str =
+    "mobilecoind_db:incoming_payment_store:incoming_payment_key_to_incoming_payment";
+
+/// A TxOut discovered by a monitor, as stored in the incoming payments database.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct IncomingPayment {
+    /// The block the TxOut was found in.
+    #[prost(uint64, tag = "1")]
+    pub block_index: u64,
+
+    /// Index of the subaddress the TxOut was sent to.
+    #[prost(uint64, tag = "2")]
+    pub subaddress_index: u64,
+
+    /// The TxOut.
+    #[prost(message, required, tag = "3")]
+    pub tx_out: TxOut,
+
+    /// Key image of the TxOut.
+    #[prost(message, required, tag = "4")]
+    pub key_image: KeyImage,
+
+    /// Value of the TxOut.
+    #[prost(uint64, tag = "5")]
+    pub value: u64,
+
+    /// The time the TxOut was first discovered, in seconds since the UNIX epoch.
+    #[prost(uint64, tag = "6")]
+    pub discovered_at: u64,
+}
+
+impl IncomingPayment {
+    pub fn new(block_index: BlockIndex, utxo: &UnspentTxOut, discovered_at: u64) -> Self {
+        Self {
+            block_index,
+            subaddress_index: utxo.subaddress_index,
+            tx_out: utxo.tx_out.clone(),
+            key_image: utxo.key_image,
+            value: utxo.value,
+            discovered_at,
+        }
+    }
+}
+
+/// Type used as the key in the incoming_payment_key_to_incoming_payment database.
+/// Keys are ordered by monitor id, then by block index, so that the payments of a monitor can be
+/// iterated in block order.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct IncomingPaymentKey {
+    pub monitor_id: MonitorId,
+    pub block_index: BlockIndex,
+    pub public_key: CompressedRistrettoPublic,
+}
+
+impl IncomingPaymentKey {
+    pub fn new(
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        public_key: &CompressedRistrettoPublic,
+    ) -> Self {
+        Self {
+            monitor_id: *monitor_id,
+            block_index,
+            public_key: *public_key,
+        }
+    }
+
+    // 72 bytes: 32 for MonitorId, 8 for the big-endian block index (so that keys sort by block),
+    // 32 for the TxOut public key.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = monitor_block_key_prefix(&self.monitor_id, self.block_index);
+        buf.extend_from_slice(self.public_key.as_bytes());
+        buf
+    }
+}
+
+/// The incoming payments database.
+#[derive(Clone)]
+pub struct IncomingPaymentStore {
+    env: Arc<Environment>,
+
+    /// Mapping of IncomingPaymentKey -> IncomingPayment.
+    incoming_payment_key_to_incoming_payment: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl IncomingPaymentStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let incoming_payment_key_to_incoming_payment = env.create_db(
+            Some(INCOMING_PAYMENT_KEY_TO_INCOMING_PAYMENT_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            incoming_payment_key_to_incoming_payment,
+            logger,
+        })
+    }
+
+    /// Record the TxOuts discovered by a monitor in a given block. TxOuts that were already
+    /// recorded are left untouched. Returns the number of newly recorded TxOuts.
+    pub fn block_processed<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        discovered_utxos: &[UnspentTxOut],
+        discovered_at: u64,
+    ) -> Result<usize, Error> {
+        let mut num_recorded = 0;
+
+        for utxo in discovered_utxos {
+            let key = IncomingPaymentKey::new(monitor_id, block_index, &utxo.tx_out.public_key);
+            let incoming_payment = IncomingPayment::new(block_index, utxo, discovered_at);
+            let value_bytes = mcserial::encode(&incoming_payment);
+
+            match db_txn.put(
+                self.incoming_payment_key_to_incoming_payment,
+                &key.to_vec(),
+                &value_bytes,
+                WriteFlags::NO_OVERWRITE,
+            ) {
+                Ok(_) => num_recorded += 1,
+                Err(lmdb::Error::KeyExist) => {}
+                Err(err) => return Err(err.into()),
+            };
+        }
+
+        Ok(num_recorded)
+    }
+
+    /// Get the incoming payments of a monitor, in block order.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor to get the payments of.
+    /// * `first_block` - The first block to include.
+    /// * `max_entries` - The maximal number of payments to return.
+    pub fn get_list(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        first_block: BlockIndex,
+        max_entries: usize,
+    ) -> Result<Vec<IncomingPayment>, Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, first_block);
+
+        let mut cursor = db_txn.open_ro_cursor(self.incoming_payment_key_to_incoming_payment)?;
+        let mut results = Vec::new();
+        for (key_bytes, value_bytes) in cursor.iter_from(&start_key) {
+            let (key_monitor_id, _block_index) = decode_monitor_block_key_prefix(key_bytes)?;
+            if key_monitor_id != *monitor_id || results.len() >= max_entries {
+                break;
+            }
+            results.push(mcserial::decode(value_bytes)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Remove all the incoming payments of a monitor.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, 0);
+
+        // Collect the keys first, since we cannot delete while iterating a read cursor.
+        let mut keys = Vec::new();
+        {
+            let mut cursor =
+                db_txn.open_ro_cursor(self.incoming_payment_key_to_incoming_payment)?;
+            for (key_bytes, _value_bytes) in cursor.iter_from(&start_key) {
+                let (key_monitor_id, _block_index) = decode_monitor_block_key_prefix(key_bytes)?;
+                if key_monitor_id != *monitor_id {
+                    break;
+                }
+                keys.push(key_bytes.to_vec());
+            }
+        }
+
+        for key in keys {
+            db_txn.del(self.incoming_payment_key_to_incoming_payment, &key, None)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{get_test_databases, get_test_monitor_data_and_id};
+    use common::logger::{test_with_logger, Logger};
+    use ledger_db::Ledger;
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_block_processed_and_get_list(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        // Set up a db with 3 random recipients and 10 blocks, and use its TxOuts to construct
+        // UnspentTxOuts.
+        let (ledger_db, _mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+        let utxos: Vec<UnspentTxOut> = (0..5)
+            .map(|idx| UnspentTxOut {
+                tx_out: ledger_db.get_tx_out_by_index(idx).unwrap(),
+                subaddress_index: idx,
+                key_image: KeyImage::from(idx),
+                value: idx,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                memo: String::new(),
+            })
+            .collect();
+
+        let db_tmp = TempDir::new("incoming_payment_store_db")
+            .expect("Could not make tempdir for incoming payment store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = IncomingPaymentStore::new(env.clone(), logger.clone()).unwrap();
+
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 5, &utxos[2..4], 1000)
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 3, &utxos[0..2], 1000)
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id1, 3, &utxos[4..5], 1000)
+                    .unwrap(),
+                1
+            );
+            db_txn.commit().unwrap();
+        }
+
+        // Processing the same block again, e.g. after the monitor was re-added, should not
+        // overwrite the original entries.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 3, &utxos[0..2], 2000)
+                    .unwrap(),
+                0
+            );
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+
+        // Payments should be returned in block order and only contain our monitor's data.
+        let payments = store
+            .get_list(&db_txn, &monitor_id0, 0, usize::max_value())
+            .unwrap();
+        assert_eq!(payments.len(), 4);
+        assert!(payments[0..2]
+            .iter()
+            .all(|payment| payment.block_index == 3));
+        assert!(payments[2..4]
+            .iter()
+            .all(|payment| payment.block_index == 5));
+        assert!(payments.iter().all(|payment| payment.discovered_at == 1000));
+        assert!(payments
+            .iter()
+            .all(|payment| payment.tx_out != utxos[4].tx_out));
+        assert!(payments.contains(&IncomingPayment::new(3, &utxos[1], 1000)));
+
+        // The block range and limit should be respected.
+        let payments = store
+            .get_list(&db_txn, &monitor_id0, 4, usize::max_value())
+            .unwrap();
+        assert_eq!(payments.len(), 2);
+        assert!(payments.iter().all(|payment| payment.block_index == 5));
+
+        let payments = store.get_list(&db_txn, &monitor_id0, 0, 3).unwrap();
+        assert_eq!(payments.len(), 3);
+
+        let payments = store
+            .get_list(&db_txn, &monitor_id0, 6, usize::max_value())
+            .unwrap();
+        assert_eq!(payments.len(), 0);
+
+        let payments = store
+            .get_list(&db_txn, &monitor_id1, 0, usize::max_value())
+            .unwrap();
+        assert_eq!(payments, vec![IncomingPayment::new(3, &utxos[4], 1000)]);
+        drop(db_txn);
+
+        // Removing a monitor's payments should leave the other monitors' untouched.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.remove(&mut db_txn, &monitor_id0).unwrap();
+        assert_eq!(
+            store
+                .get_list(&db_txn, &monitor_id0, 0, usize::max_value())
+                .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            store
+                .get_list(&db_txn, &monitor_id1, 0, usize::max_value())
+                .unwrap(),
+            vec![IncomingPayment::new(3, &utxos[4], 1000)]
+        );
+    }
+}
@@ -4,18 +4,53 @@
 
 extern crate alloc;
 
+pub mod account_snapshot;
+pub mod coin_selection;
 pub mod config;
+pub mod confirmations;
+pub mod consolidation;
 pub mod database;
+pub mod deadline;
+pub mod discovery;
+pub mod encryption;
+#[cfg(feature = "dev")]
+pub mod faucet;
+pub mod http_gateway;
+pub mod notifications;
 pub mod payments;
+pub mod pruning;
+pub mod request_signing;
+pub mod self_test;
 pub mod service;
+pub mod state_archive;
+pub mod tenants;
+pub mod tls;
 
+mod account_store;
+mod address_book_store;
+mod approval_store;
+mod audit_log_store;
+mod block_stream;
+mod block_timestamp_store;
 mod conversions;
+mod counters;
 mod database_key;
 mod error;
+mod incoming_payment_store;
 mod monitor_store;
+mod outgoing_tx_store;
+mod payment_queue;
+mod processed_block_store;
+mod spent_txo_store;
 mod subaddress_store;
 mod sync;
+mod sync_scheduler;
+mod sync_snapshot_store;
+mod transaction_log_store;
+mod transfer_code_store;
 mod utxo_store;
+mod webhook_delivery_store;
+mod worker_pool;
 
 #[cfg(any(test, feature = "test_utils"))]
 mod test_utils;
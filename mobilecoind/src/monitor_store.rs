@@ -4,29 +4,117 @@
 //! * Provides monitor configuration and status from MonitorId.
 //! * MonitorId is a hash of the instantiation parameters.
 
-use crate::{database_key::DatabaseByteArrayKey, error::Error};
+use crate::{
+    database_key::DatabaseByteArrayKey,
+    encryption::{DatabaseCipher, Rekeying},
+    error::Error,
+};
 
 use common::{
     logger::{log, Logger},
     HashMap,
 };
 use digestible::Digestible;
+use keys::{RistrettoPrivate, RistrettoPublic};
 use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
 use mcserial::Message;
+use mobilecoind_enclave_api::SealedAccountKey;
+use prost::Enumeration;
 use sha3::Sha3_256;
-use std::{convert::TryFrom, ops::Range, sync::Arc};
-use transaction::account_keys::{AccountKey, PublicAddress};
+use std::{
+    collections::BTreeMap,
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::Arc,
+};
+use transaction::account_keys::{
+    view_only_subaddress, AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX,
+};
 
 // LMDB Database Names
 pub const MONITOR_ID_TO_MONITOR_DATA_DB_NAME: &str =
     "mobilecoind_db:monitor_store:monitor_id_to_monitor_data";
 
+/// The maximal number of labels a monitor can have.
+pub const MAX_MONITOR_LABELS: usize = 32;
+
+/// The maximal length of a label's key or value, in bytes.
+pub const MAX_MONITOR_LABEL_LENGTH: usize = 256;
+
+/// The maximal length of a monitor's metadata, in bytes.
+pub const MAX_MONITOR_METADATA_LENGTH: usize = 16 * 1024;
+
+/// The keys a watch-only monitor uses to recognize the outputs of an account and read their
+/// values. They are not enough to compute key images, so they can neither spend those outputs
+/// nor tell when they have been spent.
+#[derive(Clone, Message)]
+pub struct WatchOnlyKey {
+    /// The account's view private key `a`.
+    #[prost(message, required, tag = "1")]
+    pub view_private_key: RistrettoPrivate,
+
+    /// The account's spend public key `B`.
+    #[prost(message, required, tag = "2")]
+    pub spend_public_key: RistrettoPublic,
+}
+
+impl WatchOnlyKey {
+    pub fn new(view_private_key: RistrettoPrivate, spend_public_key: RistrettoPublic) -> Self {
+        Self {
+            view_private_key,
+            spend_public_key,
+        }
+    }
+
+    /// The subaddress at `index`, without fog.
+    pub fn subaddress(&self, index: u64) -> PublicAddress {
+        view_only_subaddress(&self.view_private_key, &self.spend_public_key, index)
+    }
+}
+
+impl From<&AccountKey> for WatchOnlyKey {
+    fn from(src: &AccountKey) -> Self {
+        Self::new(*src.view_private_key(), src.spend_public_key())
+    }
+}
+
+// RistrettoPrivate is neither Eq nor Hash, so compare keys by their default subaddress, like
+// AccountKey does.
+impl PartialEq for WatchOnlyKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.subaddress(DEFAULT_SUBADDRESS_INDEX) == other.subaddress(DEFAULT_SUBADDRESS_INDEX)
+    }
+}
+
+impl Eq for WatchOnlyKey {}
+
+impl Hash for WatchOnlyKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.subaddress(DEFAULT_SUBADDRESS_INDEX).hash(state);
+    }
+}
+
+/// How much of the block scanning capacity a monitor gets while other monitors are also behind.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum MonitorPriority {
+    /// The default.
+    Normal = 0,
+
+    /// Scanned ahead of normal priority monitors, e.g. for accounts expecting deposits.
+    High = 1,
+
+    /// Scanned in the background, e.g. for accounts being restored.
+    Low = 2,
+}
+
 /// Type used as the stored data in the monitor_id_to_monitor_data database.
 #[derive(Clone, Eq, Hash, PartialEq, Message)]
 pub struct MonitorData {
-    /// The private key pair for the account this monitor watches.
-    #[prost(message, required, tag = "1")]
-    pub account_key: AccountKey,
+    /// The private key pair for the account this monitor watches. Not set for watch-only
+    /// monitors.
+    #[prost(message, optional, tag = "1")]
+    pub account_key: Option<AccountKey>,
 
     /// The smallest subaddress index in the range this monitor watches.
     #[prost(uint64, tag = "2")]
@@ -43,6 +131,45 @@ pub struct MonitorData {
     /// The next block this monitor needs to process.
     #[prost(uint64, tag = "5")]
     pub next_block: u64,
+
+    /// The number of unused subaddresses to keep watching past the highest subaddress that
+    /// received funds. Zero disables gap-limit scanning.
+    #[prost(uint64, tag = "6")]
+    pub gap_limit: u64,
+
+    /// The number of subaddresses gap-limit scanning added past the requested range.
+    #[prost(uint64, tag = "7")]
+    pub num_extended_subaddresses: u64,
+
+    /// Whether transactions sent by this monitor return change in outputs of standard
+    /// denominations.
+    #[prost(bool, tag = "8")]
+    pub denominate_change: bool,
+
+    /// The keys of a watch-only monitor. Only set when `account_key` is not.
+    #[prost(message, optional, tag = "9")]
+    pub watch_only_key: Option<WatchOnlyKey>,
+
+    /// The scanning priority of this monitor.
+    #[prost(enumeration = "MonitorPriority", tag = "10")]
+    pub priority: i32,
+
+    /// The account key, sealed by the signing enclave. Empty unless the monitor was added while
+    /// mobilecoind ran with the signing enclave, in which case `watch_only_key` is set as well.
+    #[prost(bytes, tag = "11")]
+    pub sealed_account_key: Vec<u8>,
+
+    /// The tenant that added this monitor. Empty unless mobilecoind is shared by tenants.
+    #[prost(string, tag = "12")]
+    pub tenant: String,
+
+    /// Labels supplied by the user, e.g. to tag monitors with the customer they belong to.
+    #[prost(btree_map = "string, string", tag = "13")]
+    pub labels: BTreeMap<String, String>,
+
+    /// JSON metadata supplied by the user. Empty if not set.
+    #[prost(string, tag = "14")]
+    pub metadata: String,
 }
 
 impl MonitorData {
@@ -51,6 +178,58 @@ impl MonitorData {
         first_subaddress: u64,
         num_subaddresses: u64,
         first_block: u64,
+    ) -> Result<Self, Error> {
+        Self::with_keys(
+            Some(account_key),
+            None,
+            first_subaddress,
+            num_subaddresses,
+            first_block,
+        )
+    }
+
+    /// A monitor that tracks the outputs an account receives, without being able to spend them.
+    pub fn new_watch_only(
+        watch_only_key: WatchOnlyKey,
+        first_subaddress: u64,
+        num_subaddresses: u64,
+        first_block: u64,
+    ) -> Result<Self, Error> {
+        Self::with_keys(
+            None,
+            Some(watch_only_key),
+            first_subaddress,
+            num_subaddresses,
+            first_block,
+        )
+    }
+
+    /// A monitor whose account key is held by the signing enclave. `watch_only_key` lets the
+    /// monitor find the account's outputs, while spending them takes the enclave.
+    pub fn new_sealed(
+        watch_only_key: WatchOnlyKey,
+        sealed_account_key: SealedAccountKey,
+        first_subaddress: u64,
+        num_subaddresses: u64,
+        first_block: u64,
+    ) -> Result<Self, Error> {
+        let mut data = Self::with_keys(
+            None,
+            Some(watch_only_key),
+            first_subaddress,
+            num_subaddresses,
+            first_block,
+        )?;
+        data.sealed_account_key = sealed_account_key;
+        Ok(data)
+    }
+
+    fn with_keys(
+        account_key: Option<AccountKey>,
+        watch_only_key: Option<WatchOnlyKey>,
+        first_subaddress: u64,
+        num_subaddresses: u64,
+        first_block: u64,
     ) -> Result<Self, Error> {
         if num_subaddresses == 0 {
             return Err(Error::InvalidArgument(
@@ -66,11 +245,141 @@ impl MonitorData {
             first_block,
             // The next block we need to sync is our first block.
             next_block: first_block,
+            gap_limit: 0,
+            num_extended_subaddresses: 0,
+            denominate_change: false,
+            watch_only_key,
+            priority: MonitorPriority::Normal as i32,
+            sealed_account_key: Vec::new(),
+            tenant: String::new(),
+            labels: BTreeMap::new(),
+            metadata: String::new(),
         })
     }
 
+    /// Returns the scanning priority of this monitor.
+    pub fn get_priority(&self) -> MonitorPriority {
+        MonitorPriority::from_i32(self.priority).unwrap_or(MonitorPriority::Normal)
+    }
+
+    /// Whether this monitor only watches an account, and cannot spend from it.
+    pub fn is_watch_only(&self) -> bool {
+        self.account_key.is_none() && !self.is_sealed()
+    }
+
+    /// Whether this monitor's account key is held by the signing enclave.
+    pub fn is_sealed(&self) -> bool {
+        !self.sealed_account_key.is_empty()
+    }
+
+    /// The account key, which is needed for spending in process. Fails for watch-only and sealed
+    /// monitors.
+    pub fn account_key(&self) -> Result<&AccountKey, Error> {
+        self.account_key.as_ref().ok_or(Error::WatchOnlyMonitor)
+    }
+
+    /// The view private key of the watched account.
+    pub fn view_private_key(&self) -> &RistrettoPrivate {
+        match (&self.account_key, &self.watch_only_key) {
+            (Some(account_key), _) => account_key.view_private_key(),
+            (None, Some(watch_only_key)) => &watch_only_key.view_private_key,
+            (None, None) => panic!("monitor has neither an account key nor a watch-only key"),
+        }
+    }
+
+    /// The subaddress at `index` of the watched account. Only monitors holding the account key
+    /// know the account's fog url.
+    pub fn subaddress(&self, index: u64) -> PublicAddress {
+        match (&self.account_key, &self.watch_only_key) {
+            (Some(account_key), _) => account_key.subaddress(index),
+            (None, Some(watch_only_key)) => watch_only_key.subaddress(index),
+            (None, None) => panic!("monitor has neither an account key nor a watch-only key"),
+        }
+    }
+
+    /// All subaddresses this monitor watches: the requested range, followed by any subaddresses
+    /// added by gap-limit scanning.
     pub fn subaddress_indexes(&self) -> Range<u64> {
-        (self.first_subaddress..self.first_subaddress + self.num_subaddresses)
+        (self.first_subaddress
+            ..self.first_subaddress + self.num_subaddresses + self.num_extended_subaddresses)
+    }
+
+    /// Enables gap-limit scanning. Until some subaddress receives funds, the first `gap_limit`
+    /// subaddresses are watched even if that is more than the requested range.
+    pub fn set_gap_limit(&mut self, gap_limit: u64) {
+        self.gap_limit = gap_limit;
+        self.extend_to(self.first_subaddress.saturating_add(gap_limit));
+    }
+
+    /// Extends the watched range so that `gap_limit` subaddresses past `used_subaddress` are
+    /// watched. Returns the subaddress indexes that were added, which is empty if gap-limit
+    /// scanning is disabled or the range is already large enough.
+    pub fn extend_past(&mut self, used_subaddress: u64) -> Range<u64> {
+        if self.gap_limit == 0 {
+            let end = self.subaddress_indexes().end;
+            return end..end;
+        }
+        self.extend_to(
+            used_subaddress
+                .saturating_add(1)
+                .saturating_add(self.gap_limit),
+        )
+    }
+
+    /// Replaces the labels and metadata of this monitor. Labels must have non-empty keys, and the
+    /// metadata must be empty or a JSON document.
+    pub fn set_metadata(
+        &mut self,
+        labels: BTreeMap<String, String>,
+        metadata: String,
+    ) -> Result<(), Error> {
+        if labels.len() > MAX_MONITOR_LABELS {
+            return Err(Error::InvalidArgument(
+                "labels".to_string(),
+                format!("at most {} labels are allowed", MAX_MONITOR_LABELS),
+            ));
+        }
+        for (key, value) in &labels {
+            if key.is_empty() {
+                return Err(Error::InvalidArgument(
+                    "labels".to_string(),
+                    "keys must not be empty".to_string(),
+                ));
+            }
+            if key.len() > MAX_MONITOR_LABEL_LENGTH || value.len() > MAX_MONITOR_LABEL_LENGTH {
+                return Err(Error::InvalidArgument(
+                    "labels".to_string(),
+                    format!(
+                        "keys and values must be at most {} bytes",
+                        MAX_MONITOR_LABEL_LENGTH
+                    ),
+                ));
+            }
+        }
+
+        if metadata.len() > MAX_MONITOR_METADATA_LENGTH {
+            return Err(Error::InvalidArgument(
+                "metadata".to_string(),
+                format!("must be at most {} bytes", MAX_MONITOR_METADATA_LENGTH),
+            ));
+        }
+        if !metadata.is_empty() {
+            serde_json::from_str::<serde_json::Value>(&metadata)
+                .map_err(|err| Error::InvalidArgument("metadata".to_string(), err.to_string()))?;
+        }
+
+        self.labels = labels;
+        self.metadata = metadata;
+        Ok(())
+    }
+
+    fn extend_to(&mut self, required_end: u64) -> Range<u64> {
+        let end = self.subaddress_indexes().end;
+        if required_end <= end {
+            return end..end;
+        }
+        self.num_extended_subaddresses += required_end - end;
+        end..required_end
     }
 }
 
@@ -79,7 +388,7 @@ pub type MonitorId = DatabaseByteArrayKey;
 
 impl From<&MonitorData> for MonitorId {
     // When constructing a MonitorId from a given MonitorData object we only want to hash the data
-    // that doesn't change over time.
+    // that doesn't change over time. This excludes the gap limit and the subaddresses it adds.
     fn from(src: &MonitorData) -> MonitorId {
         #[derive(Digestible)]
         struct ConstMonitorData {
@@ -90,7 +399,7 @@ impl From<&MonitorData> for MonitorId {
             pub first_block: u64,
         }
         let const_data = ConstMonitorData {
-            address: src.account_key.default_subaddress(),
+            address: src.subaddress(DEFAULT_SUBADDRESS_INDEX),
             first_subaddress: src.first_subaddress,
             num_subaddresses: src.num_subaddresses,
             first_block: src.first_block,
@@ -106,16 +415,23 @@ impl From<&MonitorData> for MonitorId {
 pub struct MonitorStore {
     env: Arc<Environment>,
 
-    /// Mapping of MonitorId -> MonitorData
+    /// Mapping of MonitorId -> MonitorData, sealed by the cipher.
     monitor_id_to_monitor_data: Database,
 
+    /// Seals and opens MonitorData, which holds account keys.
+    cipher: DatabaseCipher,
+
     /// Logger.
     logger: Logger,
 }
 
 /// A DB mapping account IDs to keys
 impl MonitorStore {
-    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+    pub fn new(
+        env: Arc<Environment>,
+        cipher: DatabaseCipher,
+        logger: Logger,
+    ) -> Result<Self, Error> {
         let monitor_id_to_monitor_data = env.create_db(
             Some(MONITOR_ID_TO_MONITOR_DATA_DB_NAME),
             DatabaseFlags::empty(),
@@ -124,6 +440,7 @@ impl MonitorStore {
         Ok(Self {
             env,
             monitor_id_to_monitor_data,
+            cipher,
             logger,
         })
     }
@@ -137,7 +454,7 @@ impl MonitorStore {
         let monitor_id = MonitorId::from(data);
         let key_bytes = monitor_id.as_bytes();
 
-        let value_bytes = mcserial::encode(data);
+        let value_bytes = self.cipher.seal(key_bytes, mcserial::encode(data))?;
 
         log::trace!(self.logger, "adding new monitor {}: {:?}", monitor_id, data);
 
@@ -171,7 +488,8 @@ impl MonitorStore {
     ) -> Result<MonitorData, Error> {
         match db_txn.get(self.monitor_id_to_monitor_data, monitor_id) {
             Ok(value_bytes) => {
-                let data: MonitorData = mcserial::decode(value_bytes)?;
+                let data: MonitorData =
+                    mcserial::decode(&self.cipher.open(monitor_id, value_bytes)?)?;
                 Ok(data)
             }
             Err(lmdb::Error::NotFound) => Err(Error::MonitorIdNotFound),
@@ -190,7 +508,7 @@ impl MonitorStore {
         for (key_bytes, value_bytes) in cursor.iter() {
             let monitor_id =
                 MonitorId::try_from(key_bytes).map_err(|_| Error::KeyDeserializationError)?;
-            let data: MonitorData = mcserial::decode(value_bytes)?;
+            let data: MonitorData = mcserial::decode(&self.cipher.open(key_bytes, value_bytes)?)?;
             results.insert(monitor_id, data);
         }
         Ok(results)
@@ -217,7 +535,7 @@ impl MonitorStore {
         let key_bytes = monitor_id.to_vec();
         match db_txn.get(self.monitor_id_to_monitor_data, &key_bytes) {
             Ok(_value_bytes) => {
-                let new_value_bytes = mcserial::encode(data);
+                let new_value_bytes = self.cipher.seal(&key_bytes, mcserial::encode(data))?;
                 db_txn.put(
                     self.monitor_id_to_monitor_data,
                     &key_bytes,
@@ -231,6 +549,15 @@ impl MonitorStore {
         }
     }
 
+    /// Reseal all MonitorData while the database key changes.
+    pub fn reseal<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        rekeying: &Rekeying,
+    ) -> Result<(), Error> {
+        rekeying.reseal_all(db_txn, self.monitor_id_to_monitor_data)
+    }
+
     /*
     /// Stop collecting new data for a monitor. Used during monitor deletion.
      pub fn stop(&self, monitor_id: &MonitorId) -> Result<(), Error> {
@@ -395,4 +722,43 @@ mod test {
             }
         }
     }
+
+    // Labels and metadata are validated, and do not change the monitor id.
+    #[test]
+    fn test_set_metadata() {
+        let mut rng: StdRng = SeedableRng::from_seed([124u8; 32]);
+        let (mut monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let labels: BTreeMap<String, String> =
+            vec![("customer_id".to_string(), "1234".to_string())]
+                .into_iter()
+                .collect();
+        monitor_data
+            .set_metadata(labels.clone(), r#"{"tier": "gold"}"#.to_string())
+            .unwrap();
+        assert_eq!(monitor_data.labels, labels);
+        assert_eq!(MonitorId::from(&monitor_data), monitor_id);
+
+        // Invalid metadata leaves the monitor as it was.
+        assert!(monitor_data
+            .set_metadata(BTreeMap::new(), "{tier".to_string())
+            .is_err());
+        assert_eq!(monitor_data.labels, labels);
+
+        let empty_key: BTreeMap<String, String> = vec![(String::new(), "1234".to_string())]
+            .into_iter()
+            .collect();
+        assert!(monitor_data.set_metadata(empty_key, String::new()).is_err());
+
+        let too_many: BTreeMap<String, String> = (0..=MAX_MONITOR_LABELS)
+            .map(|index| (index.to_string(), String::new()))
+            .collect();
+        assert!(monitor_data.set_metadata(too_many, String::new()).is_err());
+
+        monitor_data
+            .set_metadata(BTreeMap::new(), String::new())
+            .unwrap();
+        assert!(monitor_data.labels.is_empty());
+        assert!(monitor_data.metadata.is_empty());
+    }
 }
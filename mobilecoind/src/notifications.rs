@@ -0,0 +1,715 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Webhook notifications of monitor activity.
+//! * Whenever something happens to a monitor, e.g. it receives funds or one of its payments is
+//!   confirmed, a JSON event is POSTed to every webhook subscribed to it. Subscriptions may be
+//!   limited to a monitor, a subaddress, payments of at least some value, or some event types.
+//! * The body of each request is signed with an Ed25519 key. The hex-encoded signature is sent in
+//!   the `X-Mobilecoind-Signature` header, so that receivers can check where events came from.
+//! * Events are stored in the database until they were delivered, so that they survive restarts.
+//!   A dedicated thread delivers them, retrying failed deliveries with backoff, so that slow or
+//!   unavailable webhooks never hold up scanning. Each webhook receives events in order.
+
+use crate::{
+    database::Database,
+    error::Error,
+    monitor_store::MonitorId,
+    outgoing_tx_store::{OutgoingTx, OutgoingTxStatus},
+    utxo_store::UnspentTxOut,
+    webhook_delivery_store::WebhookDelivery,
+};
+use common::{
+    logger::{log, Logger},
+    HashSet,
+};
+use crossbeam_channel::RecvTimeoutError;
+use keys::{Ed25519Pair, Ed25519Signature, Signature, Signer};
+use retry::delay::Fibonacci;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use transaction::BlockIndex;
+use url::Url;
+
+/// The header holding the hex-encoded signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-Mobilecoind-Signature";
+
+/// Maximal number of attempts to deliver an event to a webhook.
+const MAX_DELIVERY_ATTEMPTS: u32 = 8;
+
+/// Maximal number of pending deliveries looked at each time the delivery thread wakes up.
+const MAX_DELIVERIES_PER_PASS: usize = 1000;
+
+/// How often the delivery thread looks for deliveries to retry when no new events arrive.
+const DELIVERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Something that happened to a monitor.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A subaddress of a monitor received a TxOut.
+    PaymentReceived {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        subaddress_index: u64,
+        block_index: BlockIndex,
+        value: u64,
+        /// Hex-encoded public key of the received TxOut.
+        tx_public_key: String,
+    },
+
+    /// An outgoing transaction of a monitor landed in a block, or can no longer land.
+    OutgoingTxFinalized {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        /// The index of the transaction in the outgoing transaction log.
+        index: u64,
+        /// Hex-encoded hash of the transaction prefix.
+        tx_prefix_hash: String,
+        /// "Verified" or "TombstoneBlockExceeded".
+        status: String,
+        block_index: BlockIndex,
+    },
+
+    /// A payment of a monitor was submitted to the network.
+    PaymentSubmitted {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        /// The index of the transaction in the outgoing transaction log.
+        index: u64,
+        /// Hex-encoded hash of the transaction prefix.
+        tx_prefix_hash: String,
+        /// The total value sent, excluding change and fee.
+        value: u64,
+        fee: u64,
+        /// The payment fails if it has not landed before this block.
+        tombstone_block: BlockIndex,
+    },
+
+    /// A payment of a monitor landed in a block.
+    PaymentConfirmed {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        /// The index of the transaction in the outgoing transaction log.
+        index: u64,
+        /// Hex-encoded hash of the transaction prefix.
+        tx_prefix_hash: String,
+        /// The total value sent, excluding change and fee.
+        value: u64,
+        block_index: BlockIndex,
+    },
+
+    /// A payment of a monitor reached its tombstone block without landing.
+    PaymentFailed {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        /// The index of the transaction in the outgoing transaction log.
+        index: u64,
+        /// Hex-encoded hash of the transaction prefix.
+        tx_prefix_hash: String,
+        /// The total value that was to be sent, excluding change and fee.
+        value: u64,
+        block_index: BlockIndex,
+    },
+
+    /// A monitor fell behind the ledger by at least the configured number of blocks. This is
+    /// sent again only after the monitor caught up.
+    SyncLagging {
+        /// Hex-encoded monitor id.
+        monitor_id: String,
+        blocks_behind: u64,
+    },
+}
+
+/// The kinds of events, for filtering which ones a webhook receives.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    PaymentReceived,
+    OutgoingTxFinalized,
+    PaymentSubmitted,
+    PaymentConfirmed,
+    PaymentFailed,
+    SyncLagging,
+}
+
+impl Event {
+    pub fn payment_received(
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        utxo: &UnspentTxOut,
+    ) -> Self {
+        Event::PaymentReceived {
+            monitor_id: hex::encode(monitor_id.to_vec()),
+            subaddress_index: utxo.subaddress_index,
+            block_index,
+            value: utxo.value,
+            tx_public_key: hex::encode(utxo.tx_out.public_key.as_bytes()),
+        }
+    }
+
+    pub fn outgoing_tx_finalized(outgoing_tx: &OutgoingTx) -> Self {
+        Event::OutgoingTxFinalized {
+            monitor_id: hex::encode(outgoing_tx.monitor_id.to_vec()),
+            index: outgoing_tx.index,
+            tx_prefix_hash: hex::encode(&outgoing_tx.tx_prefix_hash),
+            status: format!("{:?}", outgoing_tx.get_status()),
+            block_index: outgoing_tx.final_block_index,
+        }
+    }
+
+    pub fn payment_submitted(outgoing_tx: &OutgoingTx) -> Self {
+        Event::PaymentSubmitted {
+            monitor_id: hex::encode(outgoing_tx.monitor_id.to_vec()),
+            index: outgoing_tx.index,
+            tx_prefix_hash: hex::encode(&outgoing_tx.tx_prefix_hash),
+            value: outlays_value(outgoing_tx),
+            fee: outgoing_tx.fee,
+            tombstone_block: outgoing_tx.tombstone,
+        }
+    }
+
+    /// The event for a finalized outgoing transaction, `PaymentConfirmed` or `PaymentFailed`.
+    /// Returns None if the transaction was not finalized.
+    pub fn payment_finalized(outgoing_tx: &OutgoingTx) -> Option<Self> {
+        let monitor_id = hex::encode(outgoing_tx.monitor_id.to_vec());
+        let index = outgoing_tx.index;
+        let tx_prefix_hash = hex::encode(&outgoing_tx.tx_prefix_hash);
+        let value = outlays_value(outgoing_tx);
+        let block_index = outgoing_tx.final_block_index;
+
+        match outgoing_tx.get_status() {
+            OutgoingTxStatus::Verified => Some(Event::PaymentConfirmed {
+                monitor_id,
+                index,
+                tx_prefix_hash,
+                value,
+                block_index,
+            }),
+            OutgoingTxStatus::TombstoneBlockExceeded => Some(Event::PaymentFailed {
+                monitor_id,
+                index,
+                tx_prefix_hash,
+                value,
+                block_index,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn sync_lagging(monitor_id: &MonitorId, blocks_behind: u64) -> Self {
+        Event::SyncLagging {
+            monitor_id: hex::encode(monitor_id.to_vec()),
+            blocks_behind,
+        }
+    }
+
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::PaymentReceived { .. } => EventType::PaymentReceived,
+            Event::OutgoingTxFinalized { .. } => EventType::OutgoingTxFinalized,
+            Event::PaymentSubmitted { .. } => EventType::PaymentSubmitted,
+            Event::PaymentConfirmed { .. } => EventType::PaymentConfirmed,
+            Event::PaymentFailed { .. } => EventType::PaymentFailed,
+            Event::SyncLagging { .. } => EventType::SyncLagging,
+        }
+    }
+
+    /// Hex-encoded id of the monitor the event is about.
+    pub fn monitor_id(&self) -> &str {
+        match self {
+            Event::PaymentReceived { monitor_id, .. }
+            | Event::OutgoingTxFinalized { monitor_id, .. }
+            | Event::PaymentSubmitted { monitor_id, .. }
+            | Event::PaymentConfirmed { monitor_id, .. }
+            | Event::PaymentFailed { monitor_id, .. }
+            | Event::SyncLagging { monitor_id, .. } => monitor_id,
+        }
+    }
+
+    /// The subaddress the event is about, if any.
+    pub fn subaddress_index(&self) -> Option<u64> {
+        match self {
+            Event::PaymentReceived {
+                subaddress_index, ..
+            } => Some(*subaddress_index),
+            _ => None,
+        }
+    }
+
+    /// The value of the payment the event is about, if any.
+    pub fn value(&self) -> Option<u64> {
+        match self {
+            Event::PaymentReceived { value, .. }
+            | Event::PaymentSubmitted { value, .. }
+            | Event::PaymentConfirmed { value, .. }
+            | Event::PaymentFailed { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// The total value an outgoing transaction sends, excluding change and fee.
+fn outlays_value(outgoing_tx: &OutgoingTx) -> u64 {
+    outgoing_tx
+        .outlays
+        .iter()
+        .fold(0, |total, outlay| total.saturating_add(outlay.value))
+}
+
+/// Which events a webhook receives. Unset criteria match every event. Set criteria only match
+/// events that have the attribute, e.g. no outgoing transaction matches a subaddress.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventFilter {
+    /// Hex-encoded monitor id.
+    pub monitor_id: Option<String>,
+    pub subaddress_index: Option<u64>,
+    /// The minimal value of payments.
+    pub min_value: Option<u64>,
+    /// Event types, or empty for all of them.
+    pub event_types: Vec<EventType>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        if let Some(monitor_id) = &self.monitor_id {
+            if event.monitor_id() != monitor_id {
+                return false;
+            }
+        }
+        if let Some(subaddress_index) = self.subaddress_index {
+            if event.subaddress_index() != Some(subaddress_index) {
+                return false;
+            }
+        }
+        if let Some(min_value) = self.min_value {
+            if event.value().map_or(true, |value| value < min_value) {
+                return false;
+            }
+        }
+        self.event_types.is_empty() || self.event_types.contains(&event.event_type())
+    }
+}
+
+/// A webhook, and the events it receives.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WebhookSubscription {
+    pub url: Url,
+    pub filter: EventFilter,
+}
+
+/// A subscription, as written in a subscriptions file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebhookSubscriptionJson {
+    url: String,
+    monitor_id: Option<String>,
+    subaddress_index: Option<u64>,
+    min_value: Option<u64>,
+    #[serde(default)]
+    event_types: Vec<EventType>,
+}
+
+impl WebhookSubscription {
+    /// A subscription to every event.
+    pub fn all_events(url: Url) -> Self {
+        Self {
+            url,
+            filter: EventFilter::default(),
+        }
+    }
+
+    /// Read subscriptions from a JSON file holding a list of objects with a `url`, and optionally
+    /// `monitor_id`, `subaddress_index`, `min_value` and `event_types` criteria.
+    pub fn load(path: &Path) -> Result<Vec<Self>, Error> {
+        Self::parse_list(&fs::read(path)?)
+    }
+
+    fn parse_list(json: &[u8]) -> Result<Vec<Self>, Error> {
+        let subscriptions: Vec<WebhookSubscriptionJson> = serde_json::from_slice(json)
+            .map_err(|err| Error::WebhookSubscription(err.to_string()))?;
+
+        subscriptions
+            .into_iter()
+            .map(|subscription| {
+                let url = Url::parse(&subscription.url).map_err(|err| {
+                    Error::WebhookSubscription(format!("{}: {}", subscription.url, err))
+                })?;
+
+                let monitor_id = match subscription.monitor_id {
+                    Some(monitor_id) => match hex::decode(&monitor_id) {
+                        Ok(bytes) if bytes.len() == 32 => Some(hex::encode(bytes)),
+                        _ => {
+                            return Err(Error::WebhookSubscription(format!(
+                                "{}: invalid monitor id {}",
+                                url, monitor_id
+                            )))
+                        }
+                    },
+                    None => None,
+                };
+
+                Ok(Self {
+                    url,
+                    filter: EventFilter {
+                        monitor_id,
+                        subaddress_index: subscription.subaddress_index,
+                        min_value: subscription.min_value,
+                        event_types: subscription.event_types,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+/// The URLs an event is delivered to. A webhook subscribed more than once receives the event once.
+fn webhook_urls_for<'a>(subscriptions: &'a [WebhookSubscription], event: &Event) -> Vec<&'a Url> {
+    let mut urls: Vec<&Url> = Vec::new();
+    for subscription in subscriptions {
+        if subscription.filter.matches(event) && !urls.contains(&&subscription.url) {
+            urls.push(&subscription.url);
+        }
+    }
+    urls
+}
+
+/// Queues events for delivery to webhooks.
+#[derive(Clone)]
+pub struct Notifier {
+    subscriptions: Arc<Vec<WebhookSubscription>>,
+
+    /// Pending deliveries are stored here.
+    mobilecoind_db: Database,
+
+    /// Monitors at least this many blocks behind the ledger are reported as lagging.
+    sync_lag_threshold: Option<u64>,
+
+    /// Wakes up the delivery thread.
+    wake_sender: crossbeam_channel::Sender<()>,
+
+    logger: Logger,
+}
+
+impl Notifier {
+    /// Start the thread delivering events. The thread stops once every clone of the notifier was
+    /// dropped. Events it did not deliver yet are delivered once mobilecoind starts again.
+    ///
+    /// # Arguments
+    /// * `subscriptions` - The webhooks events are POSTed to.
+    /// * `signer` - The key request bodies are signed with.
+    /// * `mobilecoind_db` - Stores pending deliveries.
+    /// * `sync_lag_threshold` - Report monitors at least this many blocks behind the ledger.
+    /// * `logger` - Logger.
+    pub fn start(
+        subscriptions: Vec<WebhookSubscription>,
+        signer: Arc<Ed25519Pair>,
+        mobilecoind_db: Database,
+        sync_lag_threshold: Option<u64>,
+        logger: Logger,
+    ) -> Self {
+        // A single pending wake-up is enough for the thread to look at every pending delivery.
+        let (wake_sender, wake_receiver) = crossbeam_channel::bounded::<()>(1);
+
+        let webhook_urls: HashSet<String> = subscriptions
+            .iter()
+            .map(|subscription| subscription.url.to_string())
+            .collect();
+        let thread_mobilecoind_db = mobilecoind_db.clone();
+        let thread_logger = logger.clone();
+
+        thread::Builder::new()
+            .name("Notifier".to_string())
+            .spawn(move || {
+                let client = reqwest::Client::new();
+                loop {
+                    if let Err(err) = deliver_pending(
+                        &client,
+                        &thread_mobilecoind_db,
+                        &webhook_urls,
+                        &signer,
+                        &thread_logger,
+                    ) {
+                        log::error!(thread_logger, "Failed delivering webhook events: {}", err);
+                    }
+
+                    match wake_receiver.recv_timeout(DELIVERY_POLL_INTERVAL) {
+                        Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+            .expect("failed starting notifier thread");
+
+        Self {
+            subscriptions: Arc::new(subscriptions),
+            mobilecoind_db,
+            sync_lag_threshold,
+            wake_sender,
+            logger,
+        }
+    }
+
+    /// Queue an event for delivery to every webhook subscribed to it.
+    pub fn notify(&self, event: Event) {
+        let urls = webhook_urls_for(&self.subscriptions, &event);
+        if urls.is_empty() {
+            return;
+        }
+
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(err) => {
+                log::error!(self.logger, "Failed serializing {:?}: {}", event, err);
+                return;
+            }
+        };
+        let deliveries: Vec<WebhookDelivery> = urls
+            .iter()
+            .map(|url| WebhookDelivery::new(url.as_str(), body.clone()))
+            .collect();
+        if let Err(err) = self.mobilecoind_db.add_webhook_deliveries(&deliveries) {
+            log::error!(self.logger, "Failed queueing {:?}: {}", event, err);
+            return;
+        }
+
+        // If a wake-up is already pending, the thread will see these deliveries too.
+        let _ = self.wake_sender.try_send(());
+    }
+
+    /// Monitors at least this many blocks behind the ledger should be reported as lagging.
+    pub fn sync_lag_threshold(&self) -> Option<u64> {
+        self.sync_lag_threshold
+    }
+}
+
+/// Attempt every pending delivery that is due. Deliveries to a webhook are attempted in order, so
+/// a delivery waiting to be retried holds up later ones to the same webhook.
+fn deliver_pending(
+    client: &reqwest::Client,
+    mobilecoind_db: &Database,
+    webhook_urls: &HashSet<String>,
+    signer: &Ed25519Pair,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let now = now_ms();
+    let mut waiting_urls = HashSet::default();
+
+    for mut delivery in mobilecoind_db.get_webhook_deliveries(MAX_DELIVERIES_PER_PASS)? {
+        // The webhook was unsubscribed since the delivery was queued.
+        if !webhook_urls.contains(&delivery.url) {
+            log::warn!(
+                logger,
+                "Dropping event for {}, which is no longer subscribed",
+                delivery.url
+            );
+            mobilecoind_db.remove_webhook_delivery(delivery.id)?;
+            continue;
+        }
+
+        if waiting_urls.contains(&delivery.url) {
+            continue;
+        }
+        if delivery.not_before_ms > now {
+            waiting_urls.insert(delivery.url.clone());
+            continue;
+        }
+
+        match deliver(client, &delivery, signer) {
+            Ok(()) => mobilecoind_db.remove_webhook_delivery(delivery.id)?,
+            Err(err) => {
+                delivery.attempts += 1;
+                if delivery.attempts >= MAX_DELIVERY_ATTEMPTS {
+                    log::error!(
+                        logger,
+                        "Failed delivering {} to {}: {}",
+                        String::from_utf8_lossy(&delivery.body),
+                        delivery.url,
+                        err
+                    );
+                    mobilecoind_db.remove_webhook_delivery(delivery.id)?;
+                } else {
+                    delivery.not_before_ms =
+                        now + retry_delay(delivery.attempts).as_millis() as u64;
+                    mobilecoind_db.update_webhook_delivery(&delivery)?;
+                    waiting_urls.insert(delivery.url.clone());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// POST an event to a webhook once.
+fn deliver(
+    client: &reqwest::Client,
+    delivery: &WebhookDelivery,
+    signer: &Ed25519Pair,
+) -> Result<(), String> {
+    let signature = sign_body(&delivery.body, signer)?;
+
+    client
+        .post(delivery.url.as_str())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(SIGNATURE_HEADER, signature.as_str())
+        .body(delivery.body.clone())
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map(|_response| ())
+        .map_err(|err| format!("{:?}", err))
+}
+
+/// How long to wait before retrying a delivery that failed `attempts` times.
+fn retry_delay(attempts: u32) -> Duration {
+    Fibonacci::from_millis(500)
+        .nth(attempts.saturating_sub(1) as usize)
+        .unwrap_or_default()
+}
+
+/// Milliseconds since the UNIX epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Sign a serialized event. Returns the hex-encoded signature.
+fn sign_body(body: &[u8], signer: &Ed25519Pair) -> Result<String, String> {
+    let signature: Ed25519Signature = signer.try_sign(body).map_err(|err| err.to_string())?;
+    Ok(hex::encode(signature.as_bytes().to_vec()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::{Ed25519Public, FromRandom, Verifier};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    // Events should serialize with their type, and their signature should verify.
+    fn test_sign_event() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let event = Event::PaymentReceived {
+            monitor_id: "00".repeat(32),
+            subaddress_index: 2,
+            block_index: 10,
+            value: 1000,
+            tx_public_key: "11".repeat(32),
+        };
+
+        let body = serde_json::to_vec(&event).unwrap();
+        let signature = sign_body(&body, &signer).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["type"], "payment_received");
+        assert_eq!(json["value"], 1000);
+
+        let public_key: Ed25519Public = signer.public_key();
+        let signature = Ed25519Signature::from_bytes(&hex::decode(&signature).unwrap()).unwrap();
+        assert!(public_key.verify(&body, &signature).is_ok());
+        assert!(public_key.verify(b"other body", &signature).is_err());
+    }
+
+    #[test]
+    // Subscriptions should only receive the events matching all of their criteria.
+    fn test_webhook_urls_for() {
+        let json = format!(
+            r#"[
+                {{ "url": "https://all.example.com" }},
+                {{ "url": "https://monitor.example.com", "monitor_id": "{}" }},
+                {{ "url": "https://large.example.com", "min_value": 1000 }},
+                {{
+                    "url": "https://subaddress.example.com",
+                    "subaddress_index": 2,
+                    "event_types": ["payment_received"]
+                }},
+                {{ "url": "https://lag.example.com", "event_types": ["sync_lagging"] }},
+                {{ "url": "https://all.example.com", "min_value": 1 }}
+            ]"#,
+            "AA".repeat(32)
+        );
+        let subscriptions = WebhookSubscription::parse_list(json.as_bytes()).unwrap();
+        assert_eq!(subscriptions.len(), 6);
+        assert_eq!(
+            subscriptions[1].filter.monitor_id,
+            Some("aa".repeat(32)),
+            "monitor ids should be normalized"
+        );
+
+        let hosts = |event: &Event| -> Vec<String> {
+            webhook_urls_for(&subscriptions, event)
+                .iter()
+                .map(|url| url.host_str().unwrap().to_string())
+                .collect()
+        };
+
+        let received =
+            |monitor_id: &str, subaddress_index: u64, value: u64| Event::PaymentReceived {
+                monitor_id: monitor_id.to_string(),
+                subaddress_index,
+                block_index: 10,
+                value,
+                tx_public_key: "11".repeat(32),
+            };
+        assert_eq!(
+            hosts(&received(&"aa".repeat(32), 2, 1000)),
+            vec![
+                "all.example.com",
+                "monitor.example.com",
+                "large.example.com",
+                "subaddress.example.com"
+            ]
+        );
+        assert_eq!(
+            hosts(&received(&"bb".repeat(32), 3, 999)),
+            vec!["all.example.com"]
+        );
+
+        let confirmed = Event::PaymentConfirmed {
+            monitor_id: "aa".repeat(32),
+            index: 0,
+            tx_prefix_hash: "22".repeat(32),
+            value: 5000,
+            block_index: 12,
+        };
+        assert_eq!(
+            hosts(&confirmed),
+            vec![
+                "all.example.com",
+                "monitor.example.com",
+                "large.example.com"
+            ]
+        );
+
+        // Events without a value never match a minimal value.
+        let lagging = Event::SyncLagging {
+            monitor_id: "bb".repeat(32),
+            blocks_behind: 100,
+        };
+        assert_eq!(hosts(&lagging), vec!["all.example.com", "lag.example.com"]);
+    }
+
+    #[test]
+    // Invalid subscriptions should be rejected.
+    fn test_parse_invalid_subscriptions() {
+        for json in &[
+            r#"{ "url": "https://example.com" }"#,
+            r#"[{ "url": "not a url" }]"#,
+            r#"[{ "url": "https://example.com", "monitor_id": "aabb" }]"#,
+            r#"[{ "url": "https://example.com", "min_vaule": 10 }]"#,
+            r#"[{ "url": "https://example.com", "event_types": ["payment_sent"] }]"#,
+        ] {
+            match WebhookSubscription::parse_list(json.as_bytes()) {
+                Err(Error::WebhookSubscription(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", json, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,814 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for outgoing transactions.
+//! * An append-only log of every transaction mobilecoind generated or submitted, indexed by its
+//!   position in the log and by its prefix hash.
+//! * Entries are never removed, not even when the monitor that created them is removed. Only their
+//!   status is updated, as the sync code observes them landing in the ledger or expiring.
+
+use crate::{
+    database::key_bytes::{key_bytes_to_u64, u64_to_key_bytes},
+    error::Error,
+    monitor_store::MonitorId,
+    payments::TxProposal,
+};
+
+use common::logger::{log, Logger};
+use keys::{CompressedRistrettoPublic, RistrettoPublic};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::sync::Arc;
+use transaction::{account_keys::PublicAddress, ring_signature::KeyImage, tx::TxHash, BlockIndex};
+
+// LMDB Database Names
+pub const OUTGOING_TX_INDEX_TO_OUTGOING_TX_DB_NAME: &str =
+    "mobilecoind_db:outgoing_tx_store:outgoing_tx_index_to_outgoing_tx";
+
+pub const TX_PREFIX_HASH_TO_OUTGOING_TX_INDEX_DB_NAME: &str =
+    "mobilecoind_db:outgoing_tx_store:tx_prefix_hash_to_outgoing_tx_index";
+
+pub const MONITOR_ID_TO_PENDING_OUTGOING_TX_INDEX_DB_NAME: &str =
+    "mobilecoind_db:outgoing_tx_store:monitor_id_to_pending_outgoing_tx_index";
+
+pub const COUNTS_DB_NAME: &str = "mobilecoind_db:outgoing_tx_store:counts";
+
+// Keys used by the `counts` database.
+const NUM_OUTGOING_TXS_KEY: &str = "num_outgoing_txs";
+
+/// The status of an outgoing transaction.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum OutgoingTxStatus {
+    /// Not set. This is never stored in the database.
+    Unknown = 0,
+
+    /// The transaction was generated, but mobilecoind did not submit it.
+    Generated = 1,
+
+    /// The transaction was submitted to the network.
+    Submitted = 2,
+
+    /// The transaction's key images appeared in the ledger.
+    Verified = 3,
+
+    /// The transaction did not appear in the ledger before its tombstone block.
+    TombstoneBlockExceeded = 4,
+}
+
+impl OutgoingTxStatus {
+    /// Returns true if the transaction is not going to change status anymore.
+    pub fn is_final(self) -> bool {
+        match self {
+            OutgoingTxStatus::Verified | OutgoingTxStatus::TombstoneBlockExceeded => true,
+            _ => false,
+        }
+    }
+}
+
+/// The status of a single destination of an outgoing transaction.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum OutlayStatus {
+    /// Not tracked, for entries logged before outlay statuses were recorded.
+    Unknown = 0,
+
+    /// The TxOut paying the outlay has not appeared in the ledger yet.
+    Pending = 1,
+
+    /// The TxOut paying the outlay appeared in the ledger.
+    Landed = 2,
+
+    /// The transaction reached a final status without the TxOut paying the outlay appearing in the
+    /// ledger.
+    Failed = 3,
+}
+
+/// A destination of an outgoing transaction.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct OutgoingOutlay {
+    /// Value being sent.
+    #[prost(uint64, tag = "1")]
+    pub value: u64,
+
+    /// The receiver's view public key.
+    #[prost(message, required, tag = "2")]
+    pub view_public_key: RistrettoPublic,
+
+    /// The receiver's spend public key.
+    #[prost(message, required, tag = "3")]
+    pub spend_public_key: RistrettoPublic,
+
+    /// The receiver's fog url, or an empty string when not in use.
+    #[prost(string, tag = "4")]
+    pub fog_url: String,
+
+    /// Public key of the TxOut paying this outlay, if known.
+    #[prost(message, optional, tag = "5")]
+    pub tx_out_public_key: Option<CompressedRistrettoPublic>,
+
+    /// Current status of this outlay.
+    #[prost(enumeration = "OutlayStatus", tag = "6")]
+    pub status: i32,
+}
+
+impl OutgoingOutlay {
+    pub fn new(
+        value: u64,
+        receiver: &PublicAddress,
+        tx_out_public_key: Option<CompressedRistrettoPublic>,
+    ) -> Self {
+        Self {
+            value,
+            view_public_key: *receiver.view_public_key(),
+            spend_public_key: *receiver.spend_public_key(),
+            fog_url: receiver.fog_url().unwrap_or("").to_string(),
+            tx_out_public_key,
+            status: OutlayStatus::Pending as i32,
+        }
+    }
+
+    /// Returns the status of this outlay.
+    pub fn get_status(&self) -> OutlayStatus {
+        OutlayStatus::from_i32(self.status).unwrap_or(OutlayStatus::Unknown)
+    }
+
+    /// Sets the status of this outlay.
+    pub fn set_status(&mut self, status: OutlayStatus) {
+        self.status = status as i32;
+    }
+
+    /// The receiver of this outlay.
+    pub fn receiver(&self) -> PublicAddress {
+        if self.fog_url.is_empty() {
+            PublicAddress::new(&self.spend_public_key, &self.view_public_key)
+        } else {
+            PublicAddress::new_with_fog(
+                &self.spend_public_key,
+                &self.view_public_key,
+                &self.fog_url,
+            )
+        }
+    }
+}
+
+/// An entry in the outgoing transaction log.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct OutgoingTx {
+    /// Position of this entry in the log.
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+
+    /// The monitor that is spending funds in this transaction.
+    #[prost(message, required, tag = "2")]
+    pub monitor_id: MonitorId,
+
+    /// Hash of the transaction prefix.
+    #[prost(bytes, tag = "3")]
+    pub tx_prefix_hash: Vec<u8>,
+
+    /// Destinations the transaction is sending funds to. This excludes change and fee.
+    #[prost(message, repeated, tag = "4")]
+    pub outlays: Vec<OutgoingOutlay>,
+
+    /// The transaction fee.
+    #[prost(uint64, tag = "5")]
+    pub fee: u64,
+
+    /// Key images of the inputs spent by the transaction.
+    #[prost(message, repeated, tag = "6")]
+    pub key_images: Vec<KeyImage>,
+
+    /// Tombstone block set in the transaction.
+    #[prost(uint64, tag = "7")]
+    pub tombstone: u64,
+
+    /// Current status of the transaction.
+    #[prost(enumeration = "OutgoingTxStatus", tag = "8")]
+    pub status: i32,
+
+    /// The block height reported by the node the transaction was submitted to, or zero if it was
+    /// not submitted.
+    #[prost(uint64, tag = "9")]
+    pub submitted_block_height: u64,
+
+    /// The block in which the transaction reached its final status, or zero if it has not.
+    #[prost(uint64, tag = "10")]
+    pub final_block_index: u64,
+}
+
+impl OutgoingTx {
+    /// Create a log entry for a transaction proposal. Its index is assigned when it is appended
+    /// to the log.
+    pub fn new(monitor_id: &MonitorId, tx_proposal: &TxProposal, status: OutgoingTxStatus) -> Self {
+        Self {
+            index: 0,
+            monitor_id: *monitor_id,
+            tx_prefix_hash: tx_proposal.tx.prefix.hash().to_vec(),
+            outlays: tx_proposal
+                .outlays
+                .iter()
+                .enumerate()
+                .map(|(outlay_index, outlay)| {
+                    let tx_out_public_key = tx_proposal
+                        .outlay_index_to_tx_out_index
+                        .get(&outlay_index)
+                        .and_then(|tx_out_index| tx_proposal.tx.prefix.outputs.get(*tx_out_index))
+                        .map(|tx_out| tx_out.public_key);
+                    OutgoingOutlay::new(outlay.value, &outlay.receiver, tx_out_public_key)
+                })
+                .collect(),
+            fee: tx_proposal.fee(),
+            key_images: tx_proposal.tx.key_images(),
+            tombstone: tx_proposal.tx.prefix.tombstone_block,
+            status: status as i32,
+            submitted_block_height: 0,
+            final_block_index: 0,
+        }
+    }
+
+    /// Returns the status of this transaction.
+    pub fn get_status(&self) -> OutgoingTxStatus {
+        OutgoingTxStatus::from_i32(self.status).unwrap_or(OutgoingTxStatus::Unknown)
+    }
+
+    /// Sets the status of this transaction.
+    pub fn set_status(&mut self, status: OutgoingTxStatus) {
+        self.status = status as i32;
+    }
+}
+
+/// The outgoing transactions database.
+#[derive(Clone)]
+pub struct OutgoingTxStore {
+    env: Arc<Environment>,
+
+    /// Mapping of log index -> OutgoingTx.
+    outgoing_tx_index_to_outgoing_tx: Database,
+
+    /// Mapping of tx prefix hash -> log index.
+    tx_prefix_hash_to_outgoing_tx_index: Database,
+
+    /// Mapping of MonitorId -> [log index], for entries that have not reached a final status.
+    /// This allows the sync code to only look at transactions that can still change status.
+    monitor_id_to_pending_outgoing_tx_index: Database,
+
+    /// Aggregate counts.
+    /// * `NUM_OUTGOING_TXS_KEY` --> number of entries in the log.
+    counts: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl OutgoingTxStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let outgoing_tx_index_to_outgoing_tx = env.create_db(
+            Some(OUTGOING_TX_INDEX_TO_OUTGOING_TX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        let tx_prefix_hash_to_outgoing_tx_index = env.create_db(
+            Some(TX_PREFIX_HASH_TO_OUTGOING_TX_INDEX_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        let monitor_id_to_pending_outgoing_tx_index = env.create_db(
+            Some(MONITOR_ID_TO_PENDING_OUTGOING_TX_INDEX_DB_NAME),
+            // DUP_SORT is needed here since we are storing multiple indexes per MonitorId.
+            DatabaseFlags::DUP_SORT | DatabaseFlags::DUP_FIXED,
+        )?;
+
+        let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env,
+            outgoing_tx_index_to_outgoing_tx,
+            tx_prefix_hash_to_outgoing_tx_index,
+            monitor_id_to_pending_outgoing_tx_index,
+            counts,
+            logger,
+        })
+    }
+
+    /// Append a transaction to the log. Returns the index it was stored at.
+    pub fn append<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        outgoing_tx: &OutgoingTx,
+    ) -> Result<u64, Error> {
+        let index = self.num_outgoing_txs(db_txn)?;
+
+        let mut outgoing_tx = outgoing_tx.clone();
+        outgoing_tx.index = index;
+
+        match db_txn.put(
+            self.tx_prefix_hash_to_outgoing_tx_index,
+            &outgoing_tx.tx_prefix_hash,
+            &u64_to_key_bytes(index),
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::DuplicateOutgoingTx),
+            Err(err) => Err(err.into()),
+        }?;
+
+        self.put_outgoing_tx(db_txn, &outgoing_tx, WriteFlags::NO_OVERWRITE)?;
+
+        if !outgoing_tx.get_status().is_final() {
+            db_txn.put(
+                self.monitor_id_to_pending_outgoing_tx_index,
+                &outgoing_tx.monitor_id,
+                &u64_to_key_bytes(index),
+                WriteFlags::NO_DUP_DATA,
+            )?;
+        }
+
+        db_txn.put(
+            self.counts,
+            &NUM_OUTGOING_TXS_KEY,
+            &u64_to_key_bytes(index + 1),
+            WriteFlags::empty(),
+        )?;
+
+        Ok(index)
+    }
+
+    /// Record that a transaction was submitted to the network.
+    pub fn tx_submitted<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        tx_prefix_hash: &TxHash,
+        submitted_block_height: u64,
+    ) -> Result<(), Error> {
+        let mut outgoing_tx = self.get_by_tx_prefix_hash(db_txn, tx_prefix_hash)?;
+
+        // Don't move backwards if the sync code already saw the transaction land.
+        if !outgoing_tx.get_status().is_final() {
+            outgoing_tx.set_status(OutgoingTxStatus::Submitted);
+        }
+        outgoing_tx.submitted_block_height = submitted_block_height;
+
+        self.put_outgoing_tx(db_txn, &outgoing_tx, WriteFlags::empty())
+    }
+
+    /// Update the status of the pending transactions of a monitor, and of their outlays, based on
+    /// the contents of a block it processed. Returns the transactions that reached a final status.
+    ///
+    /// # Arguments
+    /// * `key_images` - The key images spent in the block.
+    /// * `tx_out_public_keys` - The public keys of the TxOuts created in the block.
+    pub fn block_processed<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        key_images: &[KeyImage],
+        tx_out_public_keys: &[CompressedRistrettoPublic],
+    ) -> Result<Vec<OutgoingTx>, Error> {
+        let mut finalized = Vec::new();
+
+        for index in self.get_pending_indexes(db_txn, monitor_id)? {
+            let mut outgoing_tx = self.get_by_index(db_txn, index)?;
+
+            // A transaction is only valid in blocks before its tombstone block.
+            let status = if outgoing_tx
+                .key_images
+                .iter()
+                .any(|key_image| key_images.contains(key_image))
+            {
+                OutgoingTxStatus::Verified
+            } else if block_index + 1 >= outgoing_tx.tombstone {
+                OutgoingTxStatus::TombstoneBlockExceeded
+            } else {
+                continue;
+            };
+
+            // The outputs of a transaction land in the same block as its key images. Outlays whose
+            // TxOut is not in that block failed, e.g. because another transaction spending the
+            // same inputs landed instead.
+            for outlay in outgoing_tx.outlays.iter_mut() {
+                if outlay.get_status() != OutlayStatus::Pending {
+                    continue;
+                }
+                let landed = outlay
+                    .tx_out_public_key
+                    .map_or(false, |public_key| tx_out_public_keys.contains(&public_key));
+                outlay.set_status(if landed {
+                    OutlayStatus::Landed
+                } else {
+                    OutlayStatus::Failed
+                });
+            }
+
+            outgoing_tx.set_status(status);
+            outgoing_tx.final_block_index = block_index;
+            self.put_outgoing_tx(db_txn, &outgoing_tx, WriteFlags::empty())?;
+
+            db_txn.del(
+                self.monitor_id_to_pending_outgoing_tx_index,
+                monitor_id,
+                Some(&u64_to_key_bytes(index)[..]),
+            )?;
+
+            log::debug!(
+                self.logger,
+                "Outgoing tx {} of monitor {} is now {:?}",
+                index,
+                monitor_id,
+                status,
+            );
+            finalized.push(outgoing_tx);
+        }
+
+        Ok(finalized)
+    }
+
+    /// Stop tracking the pending transactions of a monitor. Their log entries are kept.
+    pub fn remove_pending<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        match db_txn.del(
+            self.monitor_id_to_pending_outgoing_tx_index,
+            monitor_id,
+            None,
+        ) {
+            Ok(_) | Err(lmdb::Error::NotFound) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get a single entry by its index in the log.
+    pub fn get_by_index(&self, db_txn: &impl Transaction, index: u64) -> Result<OutgoingTx, Error> {
+        match db_txn.get(
+            self.outgoing_tx_index_to_outgoing_tx,
+            &u64_to_key_bytes(index),
+        ) {
+            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::OutgoingTxNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get a single entry by the hash of its transaction prefix.
+    pub fn get_by_tx_prefix_hash(
+        &self,
+        db_txn: &impl Transaction,
+        tx_prefix_hash: &TxHash,
+    ) -> Result<OutgoingTx, Error> {
+        match db_txn.get(
+            self.tx_prefix_hash_to_outgoing_tx_index,
+            tx_prefix_hash.as_bytes(),
+        ) {
+            Ok(index_bytes) => self.get_by_index(db_txn, key_bytes_to_u64(index_bytes)),
+            Err(lmdb::Error::NotFound) => Err(Error::OutgoingTxNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get entries from the log, in the order they were appended.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - If provided, only entries of this monitor are returned.
+    /// * `first_index` - The first log index to look at.
+    /// * `max_entries` - The maximal number of entries to return.
+    pub fn list(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: Option<&MonitorId>,
+        first_index: u64,
+        max_entries: usize,
+    ) -> Result<Vec<OutgoingTx>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.outgoing_tx_index_to_outgoing_tx)?;
+        let mut results = Vec::new();
+        for (_index_bytes, value_bytes) in cursor.iter_from(&u64_to_key_bytes(first_index)) {
+            if results.len() >= max_entries {
+                break;
+            }
+
+            let outgoing_tx: OutgoingTx = mcserial::decode(value_bytes)?;
+            if monitor_id.map_or(true, |monitor_id| outgoing_tx.monitor_id == *monitor_id) {
+                results.push(outgoing_tx);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Get the number of entries in the log.
+    pub fn num_outgoing_txs(&self, db_txn: &impl Transaction) -> Result<u64, Error> {
+        match db_txn.get(self.counts, &NUM_OUTGOING_TXS_KEY) {
+            Ok(value_bytes) => Ok(key_bytes_to_u64(value_bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the log indexes of the pending transactions of a monitor.
+    fn get_pending_indexes(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+    ) -> Result<Vec<u64>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.monitor_id_to_pending_outgoing_tx_index)?;
+        match cursor.iter_dup_of(&monitor_id.to_vec()) {
+            Ok(iter) => Ok(iter
+                .map(|(_monitor_id_bytes, index_bytes)| key_bytes_to_u64(index_bytes))
+                .collect()),
+            Err(lmdb::Error::NotFound) => Ok(vec![]),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn put_outgoing_tx<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        outgoing_tx: &OutgoingTx,
+        flags: WriteFlags,
+    ) -> Result<(), Error> {
+        let value_bytes = mcserial::encode(outgoing_tx);
+        db_txn.put(
+            self.outgoing_tx_index_to_outgoing_tx,
+            &u64_to_key_bytes(outgoing_tx.index),
+            &value_bytes,
+            flags,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use keys::FromRandom;
+    use mcrand::{CryptoRng, RngCore};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::account_keys::AccountKey;
+
+    fn random_outgoing_tx(
+        rng: &mut (impl CryptoRng + RngCore),
+        monitor_id: &MonitorId,
+        tombstone: u64,
+    ) -> OutgoingTx {
+        let mut tx_prefix_hash = [0u8; 32];
+        rng.fill_bytes(&mut tx_prefix_hash);
+
+        OutgoingTx {
+            index: 0,
+            monitor_id: *monitor_id,
+            tx_prefix_hash: tx_prefix_hash.to_vec(),
+            outlays: vec![OutgoingOutlay::new(
+                rng.next_u64(),
+                &AccountKey::random(rng).default_subaddress(),
+                Some(CompressedRistrettoPublic::from(
+                    RistrettoPublic::from_random(rng),
+                )),
+            )],
+            fee: rng.next_u64(),
+            key_images: vec![KeyImage::from(rng.next_u64())],
+            tombstone,
+            status: OutgoingTxStatus::Generated as i32,
+            submitted_block_height: 0,
+            final_block_index: 0,
+        }
+    }
+
+    fn setup_test_outgoing_tx_store(logger: &Logger) -> (Arc<Environment>, OutgoingTxStore) {
+        let db_tmp = TempDir::new("outgoing_tx_store_db")
+            .expect("Could not make tempdir for outgoing tx store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = OutgoingTxStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test]
+    fn test_outgoing_outlay_receiver_round_trip() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+        assert_eq!(
+            OutgoingOutlay::new(10, &receiver, None).receiver(),
+            receiver
+        );
+
+        let receiver = PublicAddress::new_with_fog(
+            &RistrettoPublic::from_random(&mut rng),
+            &RistrettoPublic::from_random(&mut rng),
+            "fog.mobilecoin.com",
+        );
+        assert_eq!(
+            OutgoingOutlay::new(10, &receiver, None).receiver(),
+            receiver
+        );
+    }
+
+    #[test_with_logger]
+    fn test_append_and_list(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let (env, store) = setup_test_outgoing_tx_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let txs: Vec<OutgoingTx> = (0..5)
+            .map(|i| {
+                let monitor_id = if i % 2 == 0 {
+                    &monitor_id0
+                } else {
+                    &monitor_id1
+                };
+                random_outgoing_tx(&mut rng, monitor_id, 100)
+            })
+            .collect();
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for (i, tx) in txs.iter().enumerate() {
+                assert_eq!(store.append(&mut db_txn, tx).unwrap(), i as u64);
+            }
+
+            // Appending the same transaction twice should fail.
+            match store.append(&mut db_txn, &txs[0]) {
+                Err(Error::DuplicateOutgoingTx) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(store.num_outgoing_txs(&db_txn).unwrap(), 5);
+
+        let all = store.list(&db_txn, None, 0, 100).unwrap();
+        assert_eq!(all.len(), 5);
+        for (i, (stored, original)) in all.iter().zip(txs.iter()).enumerate() {
+            assert_eq!(stored.index, i as u64);
+            assert_eq!(stored.tx_prefix_hash, original.tx_prefix_hash);
+        }
+
+        let monitor1 = store.list(&db_txn, Some(&monitor_id1), 0, 100).unwrap();
+        let indexes: Vec<u64> = monitor1.iter().map(|tx| tx.index).collect();
+        assert_eq!(indexes, vec![1, 3]);
+
+        let page = store.list(&db_txn, None, 2, 2).unwrap();
+        let indexes: Vec<u64> = page.iter().map(|tx| tx.index).collect();
+        assert_eq!(indexes, vec![2, 3]);
+
+        let mut tx_prefix_hash = TxHash::default();
+        tx_prefix_hash.0.copy_from_slice(&txs[3].tx_prefix_hash);
+        assert_eq!(
+            store
+                .get_by_tx_prefix_hash(&db_txn, &tx_prefix_hash)
+                .unwrap()
+                .index,
+            3
+        );
+        match store.get_by_tx_prefix_hash(&db_txn, &TxHash::default()) {
+            Err(Error::OutgoingTxNotFound) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_status_updates(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([3u8; 32]);
+        let (env, store) = setup_test_outgoing_tx_store(&logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let landed = random_outgoing_tx(&mut rng, &monitor_id, 20);
+        let expired = random_outgoing_tx(&mut rng, &monitor_id, 12);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.append(&mut db_txn, &landed).unwrap();
+        store.append(&mut db_txn, &expired).unwrap();
+
+        let mut tx_prefix_hash = TxHash::default();
+        tx_prefix_hash.0.copy_from_slice(&landed.tx_prefix_hash);
+        store.tx_submitted(&mut db_txn, &tx_prefix_hash, 9).unwrap();
+
+        let stored = store.get_by_index(&db_txn, 0).unwrap();
+        assert_eq!(stored.get_status(), OutgoingTxStatus::Submitted);
+        assert_eq!(stored.submitted_block_height, 9);
+
+        let landed_public_keys: Vec<CompressedRistrettoPublic> = landed
+            .outlays
+            .iter()
+            .filter_map(|outlay| outlay.tx_out_public_key)
+            .collect();
+
+        // Nothing happens in a block that does not contain our key images.
+        let finalized = store
+            .block_processed(
+                &mut db_txn,
+                &monitor_id,
+                10,
+                &[KeyImage::from(1234)],
+                &landed_public_keys,
+            )
+            .unwrap();
+        assert!(finalized.is_empty());
+        let stored = store.get_by_index(&db_txn, 0).unwrap();
+        assert_eq!(stored.outlays[0].get_status(), OutlayStatus::Pending);
+
+        // Block 11 is the last one the second transaction could have landed in.
+        let finalized = store
+            .block_processed(
+                &mut db_txn,
+                &monitor_id,
+                11,
+                &landed.key_images,
+                &landed_public_keys,
+            )
+            .unwrap();
+        assert_eq!(finalized.len(), 2);
+
+        let stored = store.get_by_index(&db_txn, 0).unwrap();
+        assert_eq!(stored.get_status(), OutgoingTxStatus::Verified);
+        assert_eq!(stored.final_block_index, 11);
+        assert_eq!(stored.submitted_block_height, 9);
+        assert_eq!(stored.outlays[0].get_status(), OutlayStatus::Landed);
+
+        let stored = store.get_by_index(&db_txn, 1).unwrap();
+        assert_eq!(
+            stored.get_status(),
+            OutgoingTxStatus::TombstoneBlockExceeded
+        );
+        assert_eq!(stored.final_block_index, 11);
+        assert_eq!(stored.outlays[0].get_status(), OutlayStatus::Failed);
+
+        // Finalized transactions are no longer pending.
+        assert!(store
+            .block_processed(
+                &mut db_txn,
+                &monitor_id,
+                12,
+                &landed.key_images,
+                &landed_public_keys
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test_with_logger]
+    // Outlays should fail when the transaction's key images land without their TxOuts, e.g. when a
+    // conflicting transaction spent the same inputs.
+    fn test_outlay_statuses_of_partially_landed_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let (env, store) = setup_test_outgoing_tx_store(&logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let mut outgoing_tx = random_outgoing_tx(&mut rng, &monitor_id, 20);
+        outgoing_tx.outlays.push(OutgoingOutlay::new(
+            10,
+            &AccountKey::random(&mut rng).default_subaddress(),
+            Some(CompressedRistrettoPublic::from(
+                RistrettoPublic::from_random(&mut rng),
+            )),
+        ));
+        // Entries logged before outlay statuses were recorded are left alone.
+        let mut untracked =
+            OutgoingOutlay::new(20, &AccountKey::random(&mut rng).default_subaddress(), None);
+        untracked.set_status(OutlayStatus::Unknown);
+        outgoing_tx.outlays.push(untracked);
+
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.append(&mut db_txn, &outgoing_tx).unwrap();
+
+        let first_public_key = outgoing_tx.outlays[0].tx_out_public_key.unwrap();
+        let finalized = store
+            .block_processed(
+                &mut db_txn,
+                &monitor_id,
+                10,
+                &outgoing_tx.key_images,
+                &[first_public_key],
+            )
+            .unwrap();
+        assert_eq!(finalized.len(), 1);
+
+        let statuses: Vec<OutlayStatus> = store
+            .get_by_index(&db_txn, 0)
+            .unwrap()
+            .outlays
+            .iter()
+            .map(|outlay| outlay.get_status())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                OutlayStatus::Landed,
+                OutlayStatus::Failed,
+                OutlayStatus::Unknown
+            ]
+        );
+    }
+}
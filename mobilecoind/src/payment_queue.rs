@@ -0,0 +1,439 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A queue of payments waiting for change from pending transactions.
+//!
+//! During bursts of withdrawals, most of the funds of a monitor might be locked up in
+//! transactions that were submitted but did not land in the ledger yet. A payment that can only be
+//! funded once the change of those transactions is returned can be placed in this queue instead
+//! of failing with insufficient funds. A background thread periodically retries the queued
+//! payments, in the order they were queued, and builds and submits each of them once enough funds
+//! are available.
+//! The queue is kept in memory, and does not survive a restart of mobilecoind.
+
+use crate::{
+    coin_selection::CoinSelectionStrategy,
+    database::Database,
+    deadline::Deadline,
+    error::Error,
+    monitor_store::MonitorId,
+    payments::{Outlay, TransactionsManager, TxProposal},
+    utxo_store::{UnspentTxOut, UtxoId},
+};
+use common::{
+    logger::{log, Logger},
+    HashSet,
+};
+use ledger_db::{Ledger, LedgerDB};
+use mcconnection::UserTxConnection;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use transaction::mob::PicoMob;
+
+/// The maximal number of submitted or failed payments whose status is kept around.
+const MAX_FINISHED_PAYMENTS: usize = 10_000;
+
+/// How often the queued payments are retried.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Identifies a payment in the queue.
+pub type QueuedPaymentId = u64;
+
+/// A payment waiting to be built and submitted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedPayment {
+    /// Monitor id sending the funds.
+    pub sender_monitor_id: MonitorId,
+
+    /// Subaddress the funds are coming from. Change is returned to this subaddress.
+    pub sender_subaddress: u64,
+
+    /// Outputs to be generated by the transaction. This excludes change and fee.
+    pub outlays: Vec<Outlay>,
+
+    /// Fee (optional, setting to 0 would cause mobilecoind to choose a fee).
+    pub fee: u64,
+
+    /// How the UTXOs spent by the payment are selected.
+    pub coin_selection: CoinSelectionStrategy,
+}
+
+/// The status of a queued payment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum QueuedPaymentStatus {
+    /// Waiting for change from pending transactions.
+    Queued,
+
+    /// The payment was built and submitted to the network.
+    Submitted(TxProposal),
+
+    /// The payment could not be built or submitted.
+    Failed(String),
+}
+
+/// Splits UTXOs into the ones that can be spent, and the ones that are inputs of a transaction
+/// that was submitted and might still land in the ledger.
+pub fn split_pending_utxos(
+    utxos: Vec<UnspentTxOut>,
+    num_blocks: u64,
+) -> (Vec<UnspentTxOut>, Vec<UnspentTxOut>) {
+    utxos.into_iter().partition(|utxo| {
+        utxo.attempted_spend_height == 0 || utxo.attempted_spend_tombstone <= num_blocks
+    })
+}
+
+/// Attempts to build a transaction for a payment, using only UTXOs that are not part of a
+/// pending transaction.
+/// Returns `None` if the payment cannot be funded yet, but might be once the pending transactions
+/// of the sender land in the ledger or expire.
+pub fn build_payment<T: UserTxConnection + 'static>(
+    transactions_manager: &TransactionsManager<T>,
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    payment: &QueuedPayment,
+    deadline: &Deadline,
+) -> Result<Option<TxProposal>, Error> {
+    let utxos = mobilecoind_db
+        .get_utxos_for_subaddress(&payment.sender_monitor_id, payment.sender_subaddress)?;
+    let (spendable_utxos, pending_utxos) = split_pending_utxos(utxos, ledger_db.num_blocks()?);
+
+    match transactions_manager.build_transaction(
+        &payment.sender_monitor_id,
+        payment.sender_subaddress,
+        &spendable_utxos,
+        &payment.outlays,
+        payment.fee,
+        0,
+        0,
+        &payment.coin_selection,
+        deadline,
+    ) {
+        Ok(tx_proposal) => Ok(Some(tx_proposal)),
+        Err(Error::InsufficientFunds) | Err(Error::InsufficientFundsFragmentedUtxos) => {
+            // The change of the pending transactions can only be smaller than their inputs, so
+            // this is an upper bound on the funds that could become available.
+            // Sums too large to fit count as more than enough.
+            let available_value = PicoMob::checked_sum(
+                spendable_utxos
+                    .iter()
+                    .chain(pending_utxos.iter())
+                    .map(|utxo| PicoMob::new(utxo.value)),
+            );
+            let total_value = PicoMob::checked_sum(
+                payment
+                    .outlays
+                    .iter()
+                    .map(|outlay| PicoMob::new(outlay.value))
+                    .chain(Some(PicoMob::new(payment.fee))),
+            )
+            .ok_or(Error::ValueOverflow)?;
+
+            if !pending_utxos.is_empty()
+                && available_value.map_or(true, |available_value| available_value > total_value)
+            {
+                Ok(None)
+            } else {
+                Err(Error::InsufficientFunds)
+            }
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// The queue of payments. Cloning produces a handle to the same queue.
+#[derive(Clone, Default)]
+pub struct PaymentQueue {
+    /// Queued payments and their statuses, by the order in which they were queued.
+    payments: Arc<Mutex<BTreeMap<QueuedPaymentId, (QueuedPayment, QueuedPaymentStatus)>>>,
+
+    /// The id of the next queued payment.
+    next_id: Arc<AtomicU64>,
+}
+
+impl PaymentQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a payment to the queue.
+    pub fn push(&self, payment: QueuedPayment) -> QueuedPaymentId {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mut payments = self.payments.lock().expect("mutex poisoned");
+        payments.insert(id, (payment, QueuedPaymentStatus::Queued));
+        id
+    }
+
+    /// Returns the status of a payment, or `None` if it is unknown.
+    pub fn get_status(&self, id: QueuedPaymentId) -> Option<QueuedPaymentStatus> {
+        let payments = self.payments.lock().expect("mutex poisoned");
+        payments.get(&id).map(|(_payment, status)| status.clone())
+    }
+
+    /// Returns the monitor a payment is sent from, if it was queued.
+    pub fn get_sender_monitor_id(&self, id: QueuedPaymentId) -> Option<MonitorId> {
+        let payments = self.payments.lock().expect("mutex poisoned");
+        payments
+            .get(&id)
+            .map(|(payment, _status)| payment.sender_monitor_id)
+    }
+
+    /// Returns true if a payment from the given monitor and subaddress is waiting in the queue.
+    /// New payments from the same source should queue behind it, so that they are sent in order.
+    pub fn has_queued_payments(&self, monitor_id: &MonitorId, subaddress_index: u64) -> bool {
+        let payments = self.payments.lock().expect("mutex poisoned");
+        payments.values().any(|(payment, status)| {
+            *status == QueuedPaymentStatus::Queued
+                && payment.sender_monitor_id == *monitor_id
+                && payment.sender_subaddress == subaddress_index
+        })
+    }
+
+    /// Returns the payments that are waiting in the queue, in order.
+    fn queued_payments(&self) -> Vec<(QueuedPaymentId, QueuedPayment)> {
+        let payments = self.payments.lock().expect("mutex poisoned");
+        payments
+            .iter()
+            .filter(|(_id, (_payment, status))| *status == QueuedPaymentStatus::Queued)
+            .map(|(id, (payment, _status))| (*id, payment.clone()))
+            .collect()
+    }
+
+    /// Updates the status of a payment, and forgets the oldest finished payments.
+    fn set_status(&self, id: QueuedPaymentId, status: QueuedPaymentStatus) {
+        let mut payments = self.payments.lock().expect("mutex poisoned");
+        if let Some(entry) = payments.get_mut(&id) {
+            entry.1 = status;
+        }
+
+        let finished_ids: Vec<QueuedPaymentId> = payments
+            .iter()
+            .filter(|(_id, (_payment, status))| *status != QueuedPaymentStatus::Queued)
+            .map(|(id, _)| *id)
+            .collect();
+        if finished_ids.len() > MAX_FINISHED_PAYMENTS {
+            for id in &finished_ids[..finished_ids.len() - MAX_FINISHED_PAYMENTS] {
+                payments.remove(id);
+            }
+        }
+    }
+}
+
+/// Payment queue thread - holds objects needed to cleanly terminate the thread.
+pub struct PaymentQueueThread {
+    /// The thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl PaymentQueueThread {
+    pub fn start<T: UserTxConnection + 'static>(
+        payment_queue: PaymentQueue,
+        transactions_manager: TransactionsManager<T>,
+        ledger_db: LedgerDB,
+        mobilecoind_db: Database,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("payment_queue".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "PaymentQueueThread started.");
+
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        // Sources with a payment that is still waiting. Their later payments are
+                        // skipped, so that payments are sent in the order they were queued.
+                        let mut waiting_sources = HashSet::default();
+
+                        for (id, payment) in payment_queue.queued_payments() {
+                            let source = (payment.sender_monitor_id, payment.sender_subaddress);
+                            if waiting_sources.contains(&source) {
+                                continue;
+                            }
+
+                            match process_payment(
+                                &transactions_manager,
+                                &ledger_db,
+                                &mobilecoind_db,
+                                &payment,
+                                &logger,
+                            ) {
+                                Some(status) => {
+                                    log::info!(logger, "Queued payment {}: {:?}", id, status);
+                                    payment_queue.set_status(id, status);
+                                }
+                                None => {
+                                    waiting_sources.insert(source);
+                                }
+                            }
+                        }
+
+                        thread::sleep(RETRY_INTERVAL);
+                    }
+
+                    log::debug!(logger, "PaymentQueueThread stopped.");
+                })
+                .expect("failed starting payment queue thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("PaymentQueueThread join failed");
+        }
+    }
+}
+
+impl Drop for PaymentQueueThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Attempts to build and submit a queued payment. Returns its new status, or `None` if it needs
+/// to keep waiting.
+fn process_payment<T: UserTxConnection + 'static>(
+    transactions_manager: &TransactionsManager<T>,
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    payment: &QueuedPayment,
+    logger: &Logger,
+) -> Option<QueuedPaymentStatus> {
+    let tx_proposal = match build_payment(
+        transactions_manager,
+        ledger_db,
+        mobilecoind_db,
+        payment,
+        &Deadline::Never,
+    ) {
+        Ok(Some(tx_proposal)) => tx_proposal,
+        Ok(None) => return None,
+        Err(err) => return Some(QueuedPaymentStatus::Failed(err.to_string())),
+    };
+
+    match submit_payment(transactions_manager, mobilecoind_db, &tx_proposal, logger) {
+        Ok(()) => Some(QueuedPaymentStatus::Submitted(tx_proposal)),
+        Err(err) => Some(QueuedPaymentStatus::Failed(err.to_string())),
+    }
+}
+
+/// Submits a transaction built by `build_payment`, and marks its inputs as pending.
+pub fn submit_payment<T: UserTxConnection + 'static>(
+    transactions_manager: &TransactionsManager<T>,
+    mobilecoind_db: &Database,
+    tx_proposal: &TxProposal,
+    logger: &Logger,
+) -> Result<(), Error> {
+    let block_height = transactions_manager.submit_tx_proposal(tx_proposal)?;
+
+    // The transaction was sent to the network, so failing to update the database should not fail
+    // the payment.
+    let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+    if let Err(err) = mobilecoind_db.update_attempted_spend(
+        &utxo_ids,
+        block_height,
+        tx_proposal.tx.prefix.tombstone_block,
+    ) {
+        log::error!(
+            logger,
+            "failed updating attempted_spend_height after submitting tx {}: {:?}",
+            tx_proposal.tx,
+            err
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::{ring_signature::KeyImage, tx::TxOut};
+
+    #[test]
+    fn test_split_pending_utxos() {
+        let utxo =
+            |idx: u64, attempted_spend_height: u64, attempted_spend_tombstone: u64| UnspentTxOut {
+                tx_out: TxOut::default(),
+                subaddress_index: 0,
+                key_image: KeyImage::from(idx),
+                value: idx,
+                attempted_spend_height,
+                attempted_spend_tombstone,
+                memo: String::new(),
+            };
+
+        let never_spent = utxo(1, 0, 0);
+        let expired = utxo(2, 5, 10);
+        let pending = utxo(3, 5, 11);
+
+        let (spendable, pending_utxos) = split_pending_utxos(
+            vec![never_spent.clone(), expired.clone(), pending.clone()],
+            10,
+        );
+        assert_eq!(spendable, vec![never_spent, expired]);
+        assert_eq!(pending_utxos, vec![pending]);
+    }
+
+    #[test]
+    fn test_payment_queue() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let payment = |monitor_id: MonitorId| QueuedPayment {
+            sender_monitor_id: monitor_id,
+            sender_subaddress: 0,
+            outlays: vec![],
+            fee: 0,
+            coin_selection: CoinSelectionStrategy::default(),
+        };
+
+        let queue = PaymentQueue::new();
+        let id0 = queue.push(payment(monitor_id0));
+        let id1 = queue.push(payment(monitor_id0));
+        assert_ne!(id0, id1);
+        assert_eq!(queue.get_status(id0), Some(QueuedPaymentStatus::Queued));
+        assert_eq!(queue.get_status(id1 + 1), None);
+
+        assert!(queue.has_queued_payments(&monitor_id0, 0));
+        assert!(!queue.has_queued_payments(&monitor_id0, 1));
+        assert!(!queue.has_queued_payments(&monitor_id1, 0));
+
+        // Payments are retried in order.
+        let queued_ids: Vec<QueuedPaymentId> = queue
+            .queued_payments()
+            .into_iter()
+            .map(|(id, _payment)| id)
+            .collect();
+        assert_eq!(queued_ids, vec![id0, id1]);
+
+        // Finished payments are no longer queued.
+        queue.set_status(id0, QueuedPaymentStatus::Failed("oops".to_string()));
+        queue.set_status(id1, QueuedPaymentStatus::Failed("oops".to_string()));
+        assert!(!queue.has_queued_payments(&monitor_id0, 0));
+        assert!(queue.queued_payments().is_empty());
+        assert_eq!(
+            queue.get_status(id1),
+            Some(QueuedPaymentStatus::Failed("oops".to_string()))
+        );
+    }
+}
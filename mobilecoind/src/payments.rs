@@ -2,42 +2,79 @@
 
 //! Construct and submit transactions to the validator network.
 
-use crate::{database::Database, error::Error, monitor_store::MonitorId, utxo_store::UnspentTxOut};
+pub mod enclave;
+pub mod ledger;
+pub mod request;
+
+use crate::{
+    coin_selection::CoinSelection,
+    database::Database,
+    deadline::Deadline,
+    error::Error,
+    monitor_store::{MonitorData, MonitorId},
+    notifications::{Event, Notifier},
+    outgoing_tx_store::{OutgoingTx, OutgoingTxStatus},
+    utxo_store::{UnspentTxOut, UtxoId},
+};
 
 use common::{
     logger::{log, o, Logger},
-    HashMap, HashSet,
+    HashMap, HashSet, ResponderId,
 };
-use keys::RistrettoPublic;
+use curve25519_dalek::scalar::Scalar;
+use enclave::EnclaveSigner;
+use keys::{CompressedRistrettoPublic, RistrettoPrivate, RistrettoPublic};
 use ledger_db::{Error as LedgerError, Ledger, LedgerDB};
-use mcconnection::{ConnectionManager, RetryableUserTxConnection, UserTxConnection};
+use mcconnection::{
+    ConnectionManager, Error as ConnectionError, RetryError, RetryableUserTxConnection,
+    SyncConnection, UserTxConnection,
+};
 use mcrand::{CryptoRng, RngCore};
+use mobilecoind_enclave_api::SigningEnclave;
 use rand::Rng;
+use retry::delay::Fixed;
+use scp::QuorumSet;
 use std::{
-    cmp::Reverse,
     convert::TryFrom,
     iter::{empty, FromIterator},
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    thread,
+    time::{Duration, Instant},
 };
 use transaction::{
-    account_keys::{AccountKey, PublicAddress},
+    account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
     constants::{BASE_FEE, MAX_INPUTS, RING_SIZE},
+    dust::DustRules,
+    get_tx_out_shared_secret,
+    mob::PicoMob,
     onetime_keys::{compute_key_image, recover_onetime_private_key},
+    ring_signature::RingMLSAG,
     tx::{Tx, TxOut, TxOutMembershipProof},
+    tx_out_confirmation_number::TxOutConfirmationNumber,
+    validation::{validate_transaction_signature, TransactionValidationError},
     BlockIndex,
 };
-use transaction_std::{InputCredentials, TransactionBuilder};
+use transaction_std::{InputToSign, PaymentBuilder, SpendableTxOut, UnsignedTx};
 
 /// Default number of blocks used for calculating transaction tombstone block number.
 // TODO support for making this configurable
 pub const DEFAULT_NEW_TX_BLOCK_ATTEMPTS: u64 = 50;
 
+/// Number of times submitting a transaction is retried after an ambiguous failure.
+pub const DEFAULT_SUBMIT_RETRIES: usize = 3;
+
+/// Delay between attempts at submitting a transaction.
+pub const DEFAULT_SUBMIT_RETRY_DELAY_MILLIS: u64 = 500;
+
 /// Default ring size
 pub const DEFAULT_RING_SIZE: usize = RING_SIZE;
 
+/// How long the minimum fee and dust rules fetched from the network are reused for, by default.
+pub const DEFAULT_NETWORK_RULES_TTL: Duration = Duration::from_secs(60);
+
 /// An outlay - the API representation of a desired transaction output.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Outlay {
@@ -63,6 +100,14 @@ pub struct TxProposal {
     /// A map of outlay index -> TxOut index in the Tx object.
     /// This is needed to map recipients to their respective TxOuts.
     pub outlay_index_to_tx_out_index: HashMap<usize, usize>,
+
+    /// The confirmation number of the TxOut created for each outlay.
+    /// Empty for proposals created before confirmation numbers were introduced.
+    pub outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+
+    /// Change that was added to the fee instead of being returned to the sender.
+    /// This is already included in the transaction's fee.
+    pub absorbed_change: u64,
 }
 
 impl TxProposal {
@@ -71,6 +116,148 @@ impl TxProposal {
     }
 }
 
+/// A pending transaction whose inputs are not signed yet. It holds no private keys, so it can be
+/// built by a mobilecoind that only knows the sender's view private key, and signed by one that
+/// holds the account key, e.g. on an offline machine.
+#[derive(Clone, Debug)]
+pub struct UnsignedTxProposal {
+    /// UTXOs used as inputs for this transaction.
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// Destinations the transaction is being sent to.
+    pub outlays: Vec<Outlay>,
+
+    /// The transaction, without its signature.
+    pub unsigned_tx: UnsignedTx,
+
+    /// See `TxProposal::outlay_index_to_tx_out_index`.
+    pub outlay_index_to_tx_out_index: HashMap<usize, usize>,
+
+    /// See `TxProposal::outlay_confirmation_numbers`.
+    pub outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+
+    /// See `TxProposal::absorbed_change`.
+    pub absorbed_change: u64,
+
+    /// Index of the sender's subaddress the change is returned to.
+    pub change_subaddress: u64,
+}
+
+impl UnsignedTxProposal {
+    pub fn fee(&self) -> u64 {
+        self.unsigned_tx.prefix.fee
+    }
+}
+
+/// The ring of a transaction input to sign.
+#[derive(Clone, Debug)]
+pub struct RingToSign {
+    /// The input, as built by the transaction builder.
+    pub input: InputToSign,
+
+    /// Index of the sender's subaddress the real input was sent to.
+    pub subaddress_index: u64,
+
+    /// Blinding of the pseudo-output the ring signature commits to.
+    pub pseudo_output_blinding: Scalar,
+}
+
+/// Computes the ring signatures of the transactions built by `TransactionsManager`.
+pub trait TxSigner: Send + Sync {
+    /// Sign the rings of a transaction's inputs. Returns a ring signature per ring, in order.
+    ///
+    /// # Arguments
+    /// * `message` - The message to sign, i.e. the hash of the transaction prefix.
+    /// * `rings` - The rings to sign.
+    fn sign_rings(&self, message: &[u8; 32], rings: &[RingToSign])
+        -> Result<Vec<RingMLSAG>, Error>;
+
+    /// Sign the rings of a whole transaction. Signers that check what they sign, rather than
+    /// trusting the message they are handed, implement this instead of `sign_rings`.
+    ///
+    /// # Arguments
+    /// * `unsigned_tx_proposal` - The transaction the rings belong to.
+    /// * `rings` - The rings to sign.
+    fn sign_tx(
+        &self,
+        unsigned_tx_proposal: &UnsignedTxProposal,
+        rings: &[RingToSign],
+    ) -> Result<Vec<RingMLSAG>, Error> {
+        self.sign_rings(&unsigned_tx_proposal.unsigned_tx.message(), rings)
+    }
+}
+
+/// Signs in process, with the spend private keys of an account.
+pub struct LocalSigner {
+    account_key: AccountKey,
+}
+
+impl LocalSigner {
+    pub fn new(account_key: AccountKey) -> Self {
+        Self { account_key }
+    }
+}
+
+impl TxSigner for LocalSigner {
+    fn sign_rings(
+        &self,
+        message: &[u8; 32],
+        rings: &[RingToSign],
+    ) -> Result<Vec<RingMLSAG>, Error> {
+        let mut rng = rand::thread_rng();
+        rings
+            .iter()
+            .map(|ring| {
+                let onetime_private_key = recover_onetime_private_key(
+                    &ring.input.real_output_public_key,
+                    self.account_key.view_private_key(),
+                    &self.account_key.subaddress_spend_key(ring.subaddress_index),
+                );
+                RingMLSAG::sign(
+                    message,
+                    &ring.input.ring,
+                    ring.input.real_index,
+                    &onetime_private_key,
+                    ring.input.value,
+                    &ring.input.blinding,
+                    &ring.pseudo_output_blinding,
+                    &mut rng,
+                )
+                .map_err(|err| Error::Signer(err.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// How likely a broadcast transaction is to externalize, judged by which peers accepted it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubmissionVerdict {
+    /// Accepted by too few peers to block the quorum set. The transaction may never externalize.
+    Minority,
+
+    /// Accepted by a blocking set, i.e. by at least one node in every quorum slice.
+    BlockingSet,
+
+    /// Accepted by a quorum slice.
+    Quorum,
+}
+
+/// The outcome of broadcasting a transaction to all peers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BroadcastResult {
+    /// Highest block height reported by an accepting peer.
+    pub block_height: u64,
+
+    /// Quorum-aware verdict derived from the accepting peers.
+    pub verdict: SubmissionVerdict,
+
+    /// Peers that accepted the transaction.
+    pub accepting_peers: Vec<ResponderId>,
+
+    /// Peers that rejected the transaction, or could not be reached.
+    pub rejecting_peers: Vec<ResponderId>,
+}
+
 pub struct TransactionsManager<T: UserTxConnection + 'static> {
     /// Ledger database.
     ledger_db: LedgerDB,
@@ -86,6 +273,36 @@ pub struct TransactionsManager<T: UserTxConnection + 'static> {
 
     /// Monotonically increasing counter. This is used for node round-robin selection.
     submit_node_offset: Arc<AtomicUsize>,
+
+    /// Round-robin counter for queries, kept apart from `submit_node_offset` so that queries do
+    /// not skew which node transactions are submitted to.
+    query_node_offset: Arc<AtomicUsize>,
+
+    /// Lower bound on the fee used when none is specified, e.g. as learned from network
+    /// discovery. Never below `BASE_FEE`.
+    minimum_fee_floor: u64,
+
+    /// How long fetched network rules are reused for.
+    network_rules_ttl: Duration,
+
+    /// The network's minimum fee, when last fetched.
+    cached_minimum_fee: CachedNetworkRule<u64>,
+
+    /// The network's dust rules, when last fetched.
+    cached_dust_rules: CachedNetworkRule<DustRules>,
+
+    /// Quorum set used to judge how widely a broadcast transaction was accepted.
+    quorum_set: QuorumSet<ResponderId>,
+
+    /// Signs the transactions of every monitor. When not set, transactions are signed in process
+    /// with the sending monitor's account key.
+    tx_signer: Option<Arc<dyn TxSigner>>,
+
+    /// Holds the account keys of sealed monitors, if set.
+    signing_enclave: Option<Arc<dyn SigningEnclave>>,
+
+    /// Notifies webhooks of submitted payments, if set.
+    notifier: Option<Notifier>,
 }
 
 impl<T: UserTxConnection + 'static> Clone for TransactionsManager<T> {
@@ -96,6 +313,15 @@ impl<T: UserTxConnection + 'static> Clone for TransactionsManager<T> {
             peer_manager: self.peer_manager.clone(),
             logger: self.logger.clone(),
             submit_node_offset: self.submit_node_offset.clone(),
+            query_node_offset: self.query_node_offset.clone(),
+            minimum_fee_floor: self.minimum_fee_floor,
+            network_rules_ttl: self.network_rules_ttl,
+            cached_minimum_fee: self.cached_minimum_fee.clone(),
+            cached_dust_rules: self.cached_dust_rules.clone(),
+            quorum_set: self.quorum_set.clone(),
+            tx_signer: self.tx_signer.clone(),
+            signing_enclave: self.signing_enclave.clone(),
+            notifier: self.notifier.clone(),
         }
     }
 }
@@ -108,12 +334,80 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         logger: Logger,
     ) -> Self {
         let mut rng = rand::thread_rng();
+        let responder_ids = peer_manager.responder_ids();
+        let quorum_set = QuorumSet::new_with_node_ids(responder_ids.len() as u32, responder_ids);
         Self {
             ledger_db,
             mobilecoind_db,
             peer_manager,
             logger,
             submit_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
+            query_node_offset: Arc::new(AtomicUsize::new(rng.next_u64() as usize)),
+            minimum_fee_floor: BASE_FEE,
+            network_rules_ttl: DEFAULT_NETWORK_RULES_TTL,
+            cached_minimum_fee: CachedNetworkRule::default(),
+            cached_dust_rules: CachedNetworkRule::default(),
+            quorum_set,
+            tx_signer: None,
+            signing_enclave: None,
+            notifier: None,
+        }
+    }
+
+    /// Sets a lower bound on the fee used when none is specified. The network-advertised
+    /// minimum fee is used when it is higher. The floor is never below `BASE_FEE`.
+    pub fn set_minimum_fee_floor(&mut self, minimum_fee_floor: u64) {
+        self.minimum_fee_floor = std::cmp::max(minimum_fee_floor, BASE_FEE);
+    }
+
+    /// Sets how long the minimum fee and dust rules fetched from the network are reused for,
+    /// before being fetched again. Defaults to `DEFAULT_NETWORK_RULES_TTL`.
+    pub fn set_network_rules_ttl(&mut self, network_rules_ttl: Duration) {
+        self.network_rules_ttl = network_rules_ttl;
+    }
+
+    /// Sets the quorum set used to judge broadcast transactions. Defaults to requiring all peers.
+    pub fn set_quorum_set(&mut self, quorum_set: QuorumSet<ResponderId>) {
+        self.quorum_set = quorum_set;
+    }
+
+    /// Delegates signing transactions to `tx_signer`, e.g. a hardware wallet.
+    pub fn set_tx_signer(&mut self, tx_signer: Arc<dyn TxSigner>) {
+        self.tx_signer = Some(tx_signer);
+    }
+
+    /// Signs the transactions of sealed monitors with `signing_enclave`, which holds their
+    /// account keys.
+    pub fn set_signing_enclave(&mut self, signing_enclave: Arc<dyn SigningEnclave>) {
+        self.signing_enclave = Some(signing_enclave);
+    }
+
+    /// The signing enclave, if set.
+    pub fn signing_enclave(&self) -> Option<Arc<dyn SigningEnclave>> {
+        self.signing_enclave.clone()
+    }
+
+    /// Notifies webhooks whenever a payment is submitted.
+    pub fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = Some(notifier);
+    }
+
+    /// The signer for transactions sent from the monitor described by `monitor_data`. Fails for
+    /// watch-only monitors, and for sealed monitors when no signing enclave is set.
+    fn tx_signer_for(&self, monitor_data: &MonitorData) -> Result<Arc<dyn TxSigner>, Error> {
+        if monitor_data.is_watch_only() {
+            return Err(Error::WatchOnlyMonitor);
+        }
+        if let Some(tx_signer) = &self.tx_signer {
+            return Ok(tx_signer.clone());
+        }
+        match (&monitor_data.account_key, &self.signing_enclave) {
+            (Some(account_key), _) => Ok(Arc::new(LocalSigner::new(account_key.clone()))),
+            (None, Some(signing_enclave)) => Ok(Arc::new(EnclaveSigner::new(
+                signing_enclave.clone(),
+                monitor_data.sealed_account_key.clone(),
+            ))),
+            (None, None) => Err(Error::NoSigningEnclave),
         }
     }
 
@@ -125,7 +419,65 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         outlays: &[Outlay],
         opt_fee: u64,
         opt_tombstone: u64,
+        opt_change_absorption_threshold: u64,
+        coin_selection: &dyn CoinSelection,
+        deadline: &Deadline,
     ) -> Result<TxProposal, Error> {
+        // Get sender monitor data.
+        let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
+        let tx_signer = self.tx_signer_for(&sender_monitor_data)?;
+
+        let unsigned_tx_proposal = self.build_unsigned_tx(
+            sender_monitor_id,
+            change_subaddress,
+            inputs,
+            outlays,
+            opt_fee,
+            opt_tombstone,
+            opt_change_absorption_threshold,
+            coin_selection,
+            deadline,
+        )?;
+
+        // Sign and return the TxProposal object
+        deadline.check()?;
+        let mut rng = rand::thread_rng();
+        let tx_proposal = Self::sign_tx_proposal(
+            unsigned_tx_proposal,
+            tx_signer.as_ref(),
+            &mut rng,
+            &self.logger,
+        )?;
+        log::trace!(
+            self.logger,
+            "Tx constructed, hash={}",
+            tx_proposal.tx.tx_hash()
+        );
+
+        // Record the transaction in the outgoing transaction log.
+        self.mobilecoind_db.append_outgoing_tx(&OutgoingTx::new(
+            sender_monitor_id,
+            &tx_proposal,
+            OutgoingTxStatus::Generated,
+        ))?;
+
+        Ok(tx_proposal)
+    }
+
+    /// Build a transaction without signing it, see `sign_tx`. Only the view private key of the
+    /// sender is needed, so this also works for watch-only monitors.
+    pub fn build_unsigned_tx(
+        &self,
+        sender_monitor_id: &MonitorId,
+        change_subaddress: u64,
+        inputs: &[UnspentTxOut],
+        outlays: &[Outlay],
+        opt_fee: u64,
+        opt_tombstone: u64,
+        opt_change_absorption_threshold: u64,
+        coin_selection: &dyn CoinSelection,
+        deadline: &Deadline,
+    ) -> Result<UnsignedTxProposal, Error> {
         let logger = self.logger.new(o!("sender_monitor_id" => sender_monitor_id.to_string(), "outlays" => format!("{:?}", outlays)));
         log::trace!(logger, "Building pending transaction...");
 
@@ -145,7 +497,9 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let sender_monitor_data = self.mobilecoind_db.get_monitor_data(sender_monitor_id)?;
 
         // Figure out total amount of transaction (excluding fee).
-        let total_value: u64 = outlays.iter().map(|outlay| outlay.value).sum();
+        let total_value =
+            PicoMob::checked_sum(outlays.iter().map(|outlay| PicoMob::new(outlay.value)))
+                .ok_or(Error::ValueOverflow)?;
         log::trace!(
             logger,
             "Total transaction value excluding fees: {}",
@@ -153,11 +507,23 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         );
 
         // Figure out the fee.
-        let fee = if opt_fee > 0 { opt_fee } else { BASE_FEE };
+        let fee = if opt_fee > 0 {
+            opt_fee
+        } else {
+            self.fetch_minimum_fee()?
+        };
+        let total_value_with_fee = total_value
+            .checked_add(PicoMob::new(fee))
+            .ok_or(Error::ValueOverflow)?;
 
         // Select the UTXOs to be used for this transaction.
-        let selected_utxos =
-            Self::select_utxos_for_value(inputs, total_value + fee, MAX_INPUTS as usize)?;
+        let mut rng = rand::thread_rng();
+        let selected_utxos = coin_selection.select_utxos(
+            inputs,
+            total_value_with_fee.picomob(),
+            MAX_INPUTS as usize,
+            &mut rng,
+        )?;
         log::trace!(
             logger,
             "Selected {} utxos ({:?})",
@@ -166,6 +532,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         );
 
         // Get membership proofs for selected utxos.
+        deadline.check()?;
         let selected_utxos_with_proofs = self.get_membership_proofs(selected_utxos.clone())?;
         log::trace!(logger, "Got membership proofs");
 
@@ -184,6 +551,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             DEFAULT_RING_SIZE,
             selected_utxos_with_proofs.len(),
             &excluded_tx_out_indices,
+            deadline,
         )?;
         log::trace!(logger, "Got {} rings", rings.len());
 
@@ -196,28 +564,74 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         };
         log::trace!(logger, "Tombstone block set to {}", tombstone_block);
 
-        // Build and return the TxProposal object
-        let mut rng = rand::thread_rng();
-        let tx_proposal = Self::build_tx_proposal(
+        // Get the network's dust rules.
+        let dust_rules = self.fetch_dust_rules()?;
+        log::trace!(logger, "Using dust rules {:?}", dust_rules);
+
+        // Build and return the UnsignedTxProposal object
+        deadline.check()?;
+        let unsigned_tx_proposal = Self::build_unsigned_tx_proposal(
             &selected_utxos_with_proofs,
             rings,
             fee,
-            &sender_monitor_data.account_key,
+            sender_monitor_data.view_private_key(),
             change_subaddress,
+            &sender_monitor_data.subaddress(change_subaddress),
             outlays,
             tombstone_block,
+            &dust_rules,
+            opt_change_absorption_threshold,
+            sender_monitor_data.denominate_change,
             &mut rng,
             &self.logger,
         )?;
-        log::trace!(logger, "Tx constructed, hash={}", tx_proposal.tx.tx_hash());
+        log::trace!(logger, "Unsigned tx constructed");
 
-        Ok(tx_proposal)
+        Ok(unsigned_tx_proposal)
+    }
+
+    /// Sign a transaction built by `build_unsigned_tx`, possibly by another mobilecoind. The
+    /// transaction is signed with `account_key`, unless a signer was set with `set_tx_signer`.
+    pub fn sign_tx(
+        &self,
+        unsigned_tx_proposal: UnsignedTxProposal,
+        account_key: &AccountKey,
+    ) -> Result<TxProposal, Error> {
+        let tx_signer: Arc<dyn TxSigner> = match &self.tx_signer {
+            Some(tx_signer) => tx_signer.clone(),
+            None => Arc::new(LocalSigner::new(account_key.clone())),
+        };
+        let mut rng = rand::thread_rng();
+        Self::sign_tx_proposal(
+            unsigned_tx_proposal,
+            tx_signer.as_ref(),
+            &mut rng,
+            &self.logger,
+        )
     }
 
     pub fn generate_optimization_tx(
         &self,
         monitor_id: &MonitorId,
         subaddress_index: u64,
+        deadline: &Deadline,
+    ) -> Result<TxProposal, Error> {
+        self.generate_optimization_tx_with_max_inputs(
+            monitor_id,
+            subaddress_index,
+            MAX_INPUTS as usize,
+            deadline,
+        )
+    }
+
+    /// Like `generate_optimization_tx`, but merges at most `max_inputs` UTXOs, and only once the
+    /// subaddress holds at least that many spendable UTXOs.
+    pub fn generate_optimization_tx_with_max_inputs(
+        &self,
+        monitor_id: &MonitorId,
+        subaddress_index: u64,
+        max_inputs: usize,
+        deadline: &Deadline,
     ) -> Result<TxProposal, Error> {
         let logger = self.logger.new(
             o!("monitor_id" => monitor_id.to_string(), "subaddress_index" => subaddress_index),
@@ -226,6 +640,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
 
         // Get monitor data.
         let monitor_data = self.mobilecoind_db.get_monitor_data(monitor_id)?;
+        let tx_signer = self.tx_signer_for(&monitor_data)?;
 
         // Select UTXOs.
         let num_blocks_in_ledger = self.ledger_db.num_blocks()?;
@@ -236,7 +651,8 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let (selected_utxos, fee) = Self::select_utxos_for_optimization(
             num_blocks_in_ledger,
             &inputs,
-            MAX_INPUTS as usize,
+            std::cmp::min(max_inputs, MAX_INPUTS as usize),
+            self.fetch_minimum_fee()?,
         )?;
 
         log::trace!(
@@ -247,7 +663,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         );
 
         // Figure out total amount of transaction (excluding fee).
-        let total_value: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
+        let total_value = total_utxo_value(&selected_utxos).ok_or(Error::ValueOverflow)?;
         log::trace!(
             logger,
             "Total transaction value excluding fees: {}",
@@ -255,6 +671,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         );
 
         // Get membership proofs for selected utxos.
+        deadline.check()?;
         let selected_utxos_with_proofs = self.get_membership_proofs(selected_utxos.clone())?;
         log::trace!(logger, "Got membership proofs");
 
@@ -273,6 +690,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             DEFAULT_RING_SIZE,
             selected_utxos_with_proofs.len(),
             &excluded_tx_out_indices,
+            deadline,
         )?;
         log::trace!(logger, "Got {} rings", rings.len());
 
@@ -282,20 +700,32 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
 
         // We are paying ourselves the entire amount.
         let outlays = vec![Outlay {
-            receiver: monitor_data.account_key.subaddress(subaddress_index),
-            value: total_value - fee,
+            receiver: monitor_data.subaddress(subaddress_index),
+            value: total_value
+                .checked_sub(PicoMob::new(fee))
+                .ok_or(Error::ValueOverflow)?
+                .picomob(),
         }];
 
+        // Get the network's dust rules.
+        let dust_rules = self.fetch_dust_rules()?;
+
         // Build and return the TxProposal object
+        deadline.check()?;
         let mut rng = rand::thread_rng();
         let tx_proposal = Self::build_tx_proposal(
             &selected_utxos_with_proofs,
             rings,
             fee,
-            &monitor_data.account_key,
+            monitor_data.view_private_key(),
             subaddress_index,
+            &monitor_data.subaddress(subaddress_index),
+            tx_signer.as_ref(),
             &outlays,
             tombstone_block,
+            &dust_rules,
+            0,
+            false,
             &mut rng,
             &self.logger,
         )?;
@@ -305,128 +735,609 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             tx_proposal.tx.tx_hash()
         );
 
+        // Record the transaction in the outgoing transaction log.
+        self.mobilecoind_db.append_outgoing_tx(&OutgoingTx::new(
+            monitor_id,
+            &tx_proposal,
+            OutgoingTxStatus::Generated,
+        ))?;
+
         Ok(tx_proposal)
     }
 
-    /// Submit a previously built tx proposal to the network.
-    pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
-        // Pick a peer to submit to.
-        let idx = self.submit_node_offset.fetch_add(1, Ordering::SeqCst);
-        let responder_ids = self.peer_manager.responder_ids();
-        let responder_id = &responder_ids[idx % responder_ids.len()];
-
-        // Try and submit.
-        let block_height = self
-            .peer_manager
-            .conn(responder_id)
-            .ok_or(Error::NodeNotFound)?
-            .propose_tx(&tx_proposal.tx, empty())
-            .map_err(Error::from)?;
-
-        log::info!(
-            self.logger,
-            "Tx {} submitted at block height {}",
-            tx_proposal.tx,
-            block_height
+    /// Build a transaction sweeping the funds of a transfer code into `receiver`. `account_key`
+    /// is the one-time account derived from the code's entropy, and `tx_out` the output holding
+    /// the funds. The transaction is always signed in process, since the code's account key is not
+    /// held by any signer, and it is not recorded in the outgoing transaction log, since no monitor
+    /// sends it.
+    pub fn build_transfer_code_redemption_tx(
+        &self,
+        account_key: &AccountKey,
+        tx_out: &TxOut,
+        receiver: &PublicAddress,
+        opt_fee: u64,
+        opt_tombstone: u64,
+        deadline: &Deadline,
+    ) -> Result<TxProposal, Error> {
+        let logger = self
+            .logger
+            .new(o!("tx_public_key" => tx_out.public_key.to_string()));
+        log::trace!(logger, "Building transfer code redemption transaction...");
+
+        // The output must belong to the code's account.
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)?;
+        let onetime_private_key = recover_onetime_private_key(
+            &tx_public_key,
+            account_key.view_private_key(),
+            &account_key.subaddress_spend_key(DEFAULT_SUBADDRESS_INDEX),
         );
+        if CompressedRistrettoPublic::from(&RistrettoPublic::from(&onetime_private_key))
+            != tx_out.target_key
+        {
+            return Err(Error::InvalidArgument(
+                "transfer_code".to_string(),
+                "does not own the TxOut it refers to".to_string(),
+            ));
+        }
+        let shared_secret =
+            get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+        let (value, _blinding) = tx_out.amount.get_value(&shared_secret).map_err(|_| {
+            Error::InvalidArgument(
+                "transfer_code".to_string(),
+                "cannot open the amount of its TxOut".to_string(),
+            )
+        })?;
+
+        // The funds can only be swept once they are in the ledger, and only once.
+        let tx_out_index = match self.ledger_db.get_tx_out_index_by_hash(&tx_out.hash()) {
+            Ok(index) => index,
+            Err(LedgerError::NotFound) => return Err(Error::TransferCodeNotFunded),
+            Err(err) => return Err(err.into()),
+        };
+        let key_image = compute_key_image(&onetime_private_key);
+        if self.ledger_db.contains_key_image(&key_image)? {
+            return Err(Error::TransferCodeRedeemed);
+        }
 
-        // Successfully submitted.
-        Ok(block_height)
-    }
+        // Figure out the fee. The receiver gets everything else.
+        let fee = if opt_fee > 0 {
+            opt_fee
+        } else {
+            self.fetch_minimum_fee()?
+        };
+        let outlays = vec![Outlay {
+            receiver: receiver.clone(),
+            value: PicoMob::new(value)
+                .checked_sub(PicoMob::new(fee))
+                .ok_or(Error::InsufficientFunds)?
+                .picomob(),
+        }];
+        log::trace!(logger, "Sweeping {} with a fee of {}", value, fee);
+
+        // Get the membership proof and ring.
+        let utxo = UnspentTxOut {
+            tx_out: tx_out.clone(),
+            subaddress_index: DEFAULT_SUBADDRESS_INDEX,
+            key_image,
+            value,
+            attempted_spend_height: 0,
+            attempted_spend_tombstone: 0,
+            memo: String::new(),
+        };
+        deadline.check()?;
+        let utxos_with_proofs = self.get_membership_proofs(vec![utxo])?;
+        let rings = self.get_rings(DEFAULT_RING_SIZE, 1, &[tx_out_index], deadline)?;
 
-    /// Returns a subset of UTXOs totalling at least the given amount.
-    // TODO: This method should take attempted_spend_height into account.
-    fn select_utxos_for_value(
-        utxos: &[UnspentTxOut],
-        value: u64,
-        max_inputs: usize,
-    ) -> Result<Vec<UnspentTxOut>, Error> {
-        // Sort the utxos in descending order by value.
-        let mut sorted_utxos = utxos.to_vec();
-        sorted_utxos.sort_by_key(|utxo| Reverse(utxo.value));
+        // Come up with tombstone block.
+        let tombstone_block = if opt_tombstone > 0 {
+            opt_tombstone
+        } else {
+            self.ledger_db.num_blocks()? + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
+        };
 
-        // The maximum spendable is limited by the maximal number of inputs we can use.
-        let max_spendable_amount = sorted_utxos
-            .iter()
-            .take(max_inputs)
-            .map(|utxo| utxo.value)
-            .sum();
-        if value > max_spendable_amount {
-            // See if we merged the UTXOs we would be able to spend this amount.
-            let total_utxos_value: u64 = sorted_utxos.iter().map(|utxo| utxo.value).sum();
-            if total_utxos_value >= value {
-                return Err(Error::InsufficientFundsFragmentedUtxos);
-            } else {
-                return Err(Error::InsufficientFunds);
-            }
-        }
+        // Get the network's dust rules.
+        let dust_rules = self.fetch_dust_rules()?;
 
-        // Choose utxos to spend.
-        let mut selected_utxos: Vec<UnspentTxOut> = Vec::new();
-        loop {
-            let total: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
-            if total >= value {
-                break;
-            }
+        // Build and return the TxProposal object
+        deadline.check()?;
+        let mut rng = rand::thread_rng();
+        let tx_proposal = Self::build_tx_proposal(
+            &utxos_with_proofs,
+            rings,
+            fee,
+            account_key.view_private_key(),
+            DEFAULT_SUBADDRESS_INDEX,
+            &account_key.default_subaddress(),
+            &LocalSigner::new(account_key.clone()),
+            &outlays,
+            tombstone_block,
+            &dust_rules,
+            0,
+            false,
+            &mut rng,
+            &self.logger,
+        )?;
+        log::trace!(
+            logger,
+            "Transfer code redemption tx constructed, hash={}",
+            tx_proposal.tx.tx_hash()
+        );
 
-            // Grab the next (smallest utxo)
-            let next_utxo = sorted_utxos.pop().ok_or(Error::InsufficientFunds)?;
-            selected_utxos.push(next_utxo.clone());
+        Ok(tx_proposal)
+    }
 
-            // Cap at maximum allowed inputs.
-            if selected_utxos.len() > max_inputs {
-                // Remove the lowest utxo.
-                selected_utxos.remove(0);
-            }
+    /// Find the most recent output in the ledger with a given public key. This is how the funds of
+    /// transfer codes created by other wallets are found. TxOuts are indexed by public key, so
+    /// this also finds outputs of pruned blocks.
+    pub fn find_tx_out_by_public_key(
+        &self,
+        public_key: &CompressedRistrettoPublic,
+        deadline: &Deadline,
+    ) -> Result<TxOut, Error> {
+        deadline.check()?;
+        match self.ledger_db.get_tx_out_index_by_public_key(public_key) {
+            Ok(tx_out_index) => Ok(self.ledger_db.get_tx_out_by_index(tx_out_index)?),
+            Err(LedgerError::NotFound) => Err(Error::TransferCodeNotFunded),
+            Err(err) => Err(err.into()),
         }
-
-        // Sanity.
-        assert!(!selected_utxos.is_empty());
-        assert!(selected_utxos.len() <= max_inputs);
-
-        // Return selected utxos.
-        Ok(selected_utxos)
     }
 
-    /// Select UTXOs for optimization. The current strategy is to to attempt to add the maximum number
-    /// of small UTXOs into the biggest one, which is the one most likely to be used when spending.
-    /// The assumption is that if we maintain it as the biggest, we're less likely to need multiple
-    /// UTXOs in future transactions.
-    /// 1) Filter out UTXOs which we believe are currently still pending/at an unknown state.
-    /// 2) Sort remaining UTXOs in ascending order (by their value).
-    /// 3) Grab the largest available UTXO, and MAX_INPUTS-1 smallest available UTXOs.
-    /// 4) If the sum of the smallest available UTXOs > fee, we would be able to increase our largest
-    ///    UTXO and we're done. If not, try again without the smallest UTXO.
+    /// Re-runs the checks the network applies to a transaction against the local ledger, along
+    /// with checks of the proposal around it, so that a proposal built by another machine can be
+    /// checked before it is approved or submitted:
+    /// * The transaction is well-formed: its rings, the membership proofs of their elements, its
+    ///   ring signatures and range proofs, its fee and its tombstone block.
+    /// * None of its key images is spent.
+    /// * The proposal's UTXOs are the transaction's inputs, and cover its outlays and fee.
+    /// * Each outlay maps to a distinct output of the transaction.
     ///
-    /// Returns a tuple of (selected UTXOs, fee).
-    fn select_utxos_for_optimization(
-        num_blocks_in_ledger: u64,
-        inputs: &[UnspentTxOut],
-        max_inputs: usize,
-    ) -> Result<(Vec<UnspentTxOut>, u64), Error> {
-        if max_inputs < 2 {
-            return Err(Error::InvalidArgument(
-                "max_inputs".to_owned(),
-                "need at least 2 inputs to be able to merge".to_owned(),
-            ));
+    /// Returns `Error::InvalidTxProposal` if a check fails.
+    pub fn verify_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<(), Error> {
+        let tx = &tx_proposal.tx;
+
+        let root_proofs = match self
+            .ledger_db
+            .get_tx_out_proof_of_memberships(&tx.get_membership_proof_highest_indices())
+        {
+            Ok(root_proofs) => root_proofs,
+            Err(LedgerError::NotFound) | Err(LedgerError::IndexOutOfBounds(_)) => {
+                return Err(Error::InvalidTxProposal(
+                    "membership proofs refer to TxOuts that are not in the ledger".to_string(),
+                ))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let mut rng = rand::thread_rng();
+        transaction::validation::validate(tx, self.ledger_db.num_blocks()?, &root_proofs, &mut rng)
+            .map_err(|err| Error::InvalidTxProposal(err.to_string()))?;
+
+        let key_images = tx.key_images();
+        for key_image in &key_images {
+            if self.ledger_db.contains_key_image(key_image)? {
+                return Err(Error::InvalidTxProposal(
+                    TransactionValidationError::ContainsSpentKeyImage.to_string(),
+                ));
+            }
         }
 
-        let mut spendable_inputs: Vec<&UnspentTxOut> = inputs
+        let utxo_key_images: HashSet<_> = tx_proposal
+            .utxos
             .iter()
-            .filter(|utxo| num_blocks_in_ledger >= utxo.attempted_spend_tombstone)
+            .map(|utxo| utxo.key_image)
             .collect();
+        if utxo_key_images.len() != key_images.len()
+            || key_images
+                .iter()
+                .any(|key_image| !utxo_key_images.contains(key_image))
+        {
+            return Err(Error::InvalidTxProposal(
+                "utxos do not match the transaction's inputs".to_string(),
+            ));
+        }
 
-        // No point in merging if we are able to spend all inputs at once.
-        if spendable_inputs.len() < max_inputs {
-            return Err(Error::OptimizationNotBeneficial(
-                "Not enough spendable UTXOs to require merging".to_owned(),
+        let outlays_and_fee = PicoMob::checked_sum(
+            tx_proposal
+                .outlays
+                .iter()
+                .map(|outlay| PicoMob::new(outlay.value))
+                .chain(std::iter::once(PicoMob::new(tx.prefix.fee))),
+        );
+        match (total_utxo_value(&tx_proposal.utxos), outlays_and_fee) {
+            (Some(inputs), Some(outputs)) if inputs >= outputs => {}
+            _ => {
+                return Err(Error::InvalidTxProposal(
+                    "utxos do not cover the outlays and fee".to_string(),
+                ))
+            }
+        }
+        if tx_proposal.absorbed_change > tx.prefix.fee {
+            return Err(Error::InvalidTxProposal(
+                "absorbed change exceeds the fee".to_string(),
             ));
         }
 
-        spendable_inputs.sort_by_key(|utxo| utxo.value);
+        let mut tx_out_indexes = HashSet::default();
+        for outlay_index in 0..tx_proposal.outlays.len() {
+            match tx_proposal.outlay_index_to_tx_out_index.get(&outlay_index) {
+                Some(tx_out_index)
+                    if *tx_out_index < tx.prefix.outputs.len()
+                        && tx_out_indexes.insert(*tx_out_index) => {}
+                _ => {
+                    return Err(Error::InvalidTxProposal(format!(
+                        "outlay {} does not map to a distinct output",
+                        outlay_index
+                    )))
+                }
+            }
+        }
+        if tx_proposal.outlay_index_to_tx_out_index.len() != tx_proposal.outlays.len() {
+            return Err(Error::InvalidTxProposal(
+                "outputs are mapped to unknown outlays".to_string(),
+            ));
+        }
 
-        let biggest_utxo = spendable_inputs.pop().unwrap();
-        loop {
+        Ok(())
+    }
+
+    /// Submit a previously built tx proposal to the network, retrying on ambiguous failures.
+    pub fn submit_tx_proposal(&self, tx_proposal: &TxProposal) -> Result<u64, Error> {
+        self.submit_tx_proposal_with_retry(
+            tx_proposal,
+            Fixed::from_millis(DEFAULT_SUBMIT_RETRY_DELAY_MILLIS).take(DEFAULT_SUBMIT_RETRIES),
+        )
+    }
+
+    /// Submit a previously built tx proposal to the network.
+    ///
+    /// Failures that leave it unknown whether a peer received the transaction (e.g. a timeout) are
+    /// retried, against the next peer, after each delay yielded by `retry_iterator`. Retrying must
+    /// not double-submit, so:
+    /// * Before re-sending, the ledger is checked for the transaction's key images. If they are
+    ///   all spent, the earlier attempt landed and the transaction is not sent again.
+    /// * If a re-sent transaction is rejected for containing spent key images, these were spent
+    ///   by the earlier attempt, which is in the ledger or in the peers' pending sets.
+    ///
+    /// Before the first attempt, a peer is asked whether any of the key images are already pending,
+    /// in which case this fails with `Error::AlreadyPending`.
+    pub fn submit_tx_proposal_with_retry(
+        &self,
+        tx_proposal: &TxProposal,
+        retry_iterator: impl IntoIterator<Item = Duration>,
+    ) -> Result<u64, Error> {
+        self.check_key_images_not_pending(&tx_proposal.tx)?;
+
+        let mut retry_iterator = retry_iterator.into_iter();
+        let mut is_retry = false;
+
+        let block_height = loop {
+            if is_retry {
+                if let Some(block_index) = self.find_spent_key_images(&tx_proposal.tx)? {
+                    log::info!(
+                        self.logger,
+                        "Tx {} already landed in block {}, not re-sending",
+                        tx_proposal.tx,
+                        block_index
+                    );
+                    break block_index;
+                }
+            }
+
+            let err = match self.next_peer()?.propose_tx(&tx_proposal.tx, empty()) {
+                Ok(block_height) => break block_height,
+                Err(err) => err,
+            };
+
+            let is_ambiguous = match &err {
+                RetryError::Operation {
+                    error:
+                        ConnectionError::TransactionValidation(
+                            TransactionValidationError::ContainsSpentKeyImage,
+                        ),
+                    ..
+                } if is_retry => {
+                    log::info!(
+                        self.logger,
+                        "Tx {} was already accepted by an earlier attempt",
+                        tx_proposal.tx
+                    );
+                    break self.ledger_db.num_blocks()?;
+                }
+                RetryError::Operation { error, .. } => error.should_retry(),
+                RetryError::Internal(_) => false,
+            };
+
+            match retry_iterator.next() {
+                Some(delay) if is_ambiguous => {
+                    log::debug!(
+                        self.logger,
+                        "Submitting tx {} failed ambiguously, retrying in {:?}: {:?}",
+                        tx_proposal.tx,
+                        delay,
+                        err
+                    );
+                    thread::sleep(delay);
+                    is_retry = true;
+                }
+                _ => return Err(err.into()),
+            }
+        };
+
+        log::info!(
+            self.logger,
+            "Tx {} submitted at block height {}",
+            tx_proposal.tx,
+            block_height
+        );
+
+        // The transaction is already on its way, so failing to record it should not fail the
+        // submission.
+        if let Err(err) = self.record_submitted_tx(tx_proposal, block_height) {
+            log::warn!(
+                self.logger,
+                "Failed recording submitted tx {} in the outgoing tx log: {:?}",
+                tx_proposal.tx.tx_hash(),
+                err
+            );
+        }
+
+        // Successfully submitted.
+        Ok(block_height)
+    }
+
+    /// Submit a previously built tx proposal to every peer, and judge from the peers that accepted
+    /// it whether it is likely to externalize. Fails if no peer accepted the transaction, or if
+    /// any of its key images are already pending.
+    pub fn broadcast_tx_proposal(
+        &self,
+        tx_proposal: &TxProposal,
+    ) -> Result<BroadcastResult, Error> {
+        self.check_key_images_not_pending(&tx_proposal.tx)?;
+
+        let mut block_height = None;
+        let mut last_error = Error::NodeNotFound;
+        let mut accepting_peers = Vec::new();
+        let mut rejecting_peers = Vec::new();
+
+        for responder_id in self.peer_manager.responder_ids() {
+            let result = self
+                .peer_manager
+                .conn(&responder_id)
+                .ok_or(Error::NodeNotFound)
+                .and_then(|conn| {
+                    conn.propose_tx(&tx_proposal.tx, empty())
+                        .map_err(Error::from)
+                });
+
+            match result {
+                Ok(peer_block_height) => {
+                    block_height =
+                        Some(std::cmp::max(block_height.unwrap_or(0), peer_block_height));
+                    accepting_peers.push(responder_id);
+                }
+                Err(err) => {
+                    log::debug!(
+                        self.logger,
+                        "Peer {} rejected tx {}: {:?}",
+                        responder_id,
+                        tx_proposal.tx,
+                        err
+                    );
+                    last_error = err;
+                    rejecting_peers.push(responder_id);
+                }
+            }
+        }
+
+        let block_height = block_height.ok_or(last_error)?;
+
+        let accepting_set = HashSet::from_iter(accepting_peers.iter().cloned());
+        let verdict = if self.quorum_set.is_quorum_slice(&accepting_set) {
+            SubmissionVerdict::Quorum
+        } else if self.quorum_set.is_blocking_set(&accepting_set) {
+            SubmissionVerdict::BlockingSet
+        } else {
+            SubmissionVerdict::Minority
+        };
+
+        log::info!(
+            self.logger,
+            "Tx {} broadcast at block height {}: accepted by {} of {} peers ({:?})",
+            tx_proposal.tx,
+            block_height,
+            accepting_peers.len(),
+            accepting_peers.len() + rejecting_peers.len(),
+            verdict,
+        );
+
+        if let Err(err) = self.record_submitted_tx(tx_proposal, block_height) {
+            log::warn!(
+                self.logger,
+                "Failed recording submitted tx {} in the outgoing tx log: {:?}",
+                tx_proposal.tx.tx_hash(),
+                err
+            );
+        }
+
+        Ok(BroadcastResult {
+            block_height,
+            verdict,
+            accepting_peers,
+            rejecting_peers,
+        })
+    }
+
+    /// Mark a submitted transaction in the outgoing transaction log. Transactions that were not
+    /// generated by this instance of mobilecoind are appended to the log.
+    fn record_submitted_tx(
+        &self,
+        tx_proposal: &TxProposal,
+        block_height: u64,
+    ) -> Result<(), Error> {
+        let tx_prefix_hash = tx_proposal.tx.prefix.hash();
+        match self
+            .mobilecoind_db
+            .outgoing_tx_submitted(&tx_prefix_hash, block_height)
+        {
+            Err(Error::OutgoingTxNotFound) => {
+                let utxo = tx_proposal.utxos.get(0).ok_or(Error::OutgoingTxNotFound)?;
+                let subaddress_id = self
+                    .mobilecoind_db
+                    .get_subaddress_id_by_utxo_id(&UtxoId::from(utxo))?;
+
+                let mut outgoing_tx = OutgoingTx::new(
+                    &subaddress_id.monitor_id,
+                    tx_proposal,
+                    OutgoingTxStatus::Submitted,
+                );
+                outgoing_tx.submitted_block_height = block_height;
+                self.mobilecoind_db.append_outgoing_tx(&outgoing_tx)?;
+            }
+            result => result?,
+        };
+
+        if let Some(notifier) = &self.notifier {
+            let outgoing_tx = self
+                .mobilecoind_db
+                .get_outgoing_tx_by_tx_prefix_hash(&tx_prefix_hash)?;
+            notifier.notify(Event::payment_submitted(&outgoing_tx));
+        }
+        Ok(())
+    }
+
+    /// If all of the transaction's key images are spent, returns the index of the block the last
+    /// of them was spent in.
+    fn find_spent_key_images(&self, tx: &Tx) -> Result<Option<BlockIndex>, Error> {
+        let mut block_index = None;
+        for key_image in tx.key_images() {
+            match self.ledger_db.check_key_image(&key_image)? {
+                Some(spent_at) => {
+                    block_index = Some(std::cmp::max(block_index.unwrap_or(0), spent_at));
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(block_index)
+    }
+
+    /// Fails with `Error::AlreadyPending` if a peer reports that any of the transaction's key
+    /// images are spent by a transaction it accepted but has not externalized yet, e.g. one
+    /// submitted by another instance sharing the account. The check is best-effort: if the peer
+    /// cannot be queried, submission goes ahead and the network rejects a conflict anyway.
+    fn check_key_images_not_pending(&self, tx: &Tx) -> Result<(), Error> {
+        match self
+            .next_peer()?
+            .fetch_pending_key_images(&tx.key_images(), empty())
+        {
+            Ok(pending_key_images) if !pending_key_images.is_empty() => {
+                log::info!(
+                    self.logger,
+                    "Not submitting tx {}: key images {:?} are already pending",
+                    tx,
+                    pending_key_images
+                );
+                Err(Error::AlreadyPending(pending_key_images.len()))
+            }
+            Ok(_) => Ok(()),
+            Err(err) => {
+                log::warn!(
+                    self.logger,
+                    "Failed checking tx {} for pending key images: {:?}",
+                    tx,
+                    err
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Pick a peer to query, round-robin.
+    fn next_peer(&self) -> Result<SyncConnection<T>, Error> {
+        let idx = self.submit_node_offset.fetch_add(1, Ordering::SeqCst);
+        let responder_ids = self.peer_manager.responder_ids();
+        if responder_ids.is_empty() {
+            return Err(Error::NodeNotFound);
+        }
+        let responder_id = &responder_ids[idx % responder_ids.len()];
+
+        self.peer_manager
+            .conn(responder_id)
+            .ok_or(Error::NodeNotFound)
+    }
+
+    /// Pick a peer to query, round-robin, without advancing the rotation used for submission.
+    fn query_peer(&self) -> Result<SyncConnection<T>, Error> {
+        let idx = self.query_node_offset.fetch_add(1, Ordering::SeqCst);
+        let responder_ids = self.peer_manager.responder_ids();
+        if responder_ids.is_empty() {
+            return Err(Error::NodeNotFound);
+        }
+        let responder_id = &responder_ids[idx % responder_ids.len()];
+
+        self.peer_manager
+            .conn(responder_id)
+            .ok_or(Error::NodeNotFound)
+    }
+
+    /// Fetch the rules the network applies to zero-value and sub-minimum outputs. The rules are
+    /// reused for `network_rules_ttl`.
+    fn fetch_dust_rules(&self) -> Result<DustRules, Error> {
+        self.cached_dust_rules
+            .get_or_fetch(self.network_rules_ttl, || {
+                Ok(self.query_peer()?.fetch_dust_rules(empty())?)
+            })
+    }
+
+    /// Fetch the minimum fee the network currently requires of a transaction, or the floor if it
+    /// is higher. The network's fee is reused for `network_rules_ttl`.
+    fn fetch_minimum_fee(&self) -> Result<u64, Error> {
+        let minimum_fee = self
+            .cached_minimum_fee
+            .get_or_fetch(self.network_rules_ttl, || {
+                Ok(self.query_peer()?.fetch_minimum_fee(empty())?)
+            })?;
+        Ok(std::cmp::max(minimum_fee, self.minimum_fee_floor))
+    }
+
+    /// Select UTXOs for optimization. The current strategy is to to attempt to add the maximum number
+    /// of small UTXOs into the biggest one, which is the one most likely to be used when spending.
+    /// The assumption is that if we maintain it as the biggest, we're less likely to need multiple
+    /// UTXOs in future transactions.
+    /// 1) Filter out UTXOs which we believe are currently still pending/at an unknown state.
+    /// 2) Sort remaining UTXOs in ascending order (by their value).
+    /// 3) Grab the largest available UTXO, and MAX_INPUTS-1 smallest available UTXOs.
+    /// 4) If the sum of the smallest available UTXOs > fee, we would be able to increase our largest
+    ///    UTXO and we're done. If not, try again without the smallest UTXO.
+    ///
+    /// Returns a tuple of (selected UTXOs, fee).
+    fn select_utxos_for_optimization(
+        num_blocks_in_ledger: u64,
+        inputs: &[UnspentTxOut],
+        max_inputs: usize,
+        fee: u64,
+    ) -> Result<(Vec<UnspentTxOut>, u64), Error> {
+        if max_inputs < 2 {
+            return Err(Error::InvalidArgument(
+                "max_inputs".to_owned(),
+                "need at least 2 inputs to be able to merge".to_owned(),
+            ));
+        }
+
+        let mut spendable_inputs: Vec<&UnspentTxOut> = inputs
+            .iter()
+            .filter(|utxo| num_blocks_in_ledger >= utxo.attempted_spend_tombstone)
+            .collect();
+
+        // No point in merging if we are able to spend all inputs at once.
+        if spendable_inputs.len() < max_inputs {
+            return Err(Error::OptimizationNotBeneficial(
+                "Not enough spendable UTXOs to require merging".to_owned(),
+            ));
+        }
+
+        spendable_inputs.sort_by_key(|utxo| utxo.value);
+
+        let biggest_utxo = spendable_inputs.pop().unwrap();
+        loop {
             // If there are no spendable inputs, we've tried merging all of them and still ended up losing it all to fees.
             if spendable_inputs.is_empty() {
                 return Err(Error::OptimizationNotBeneficial(
@@ -434,25 +1345,27 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
                 ));
             }
 
-            let mut total = 0;
-            let mut selected_utxos = Vec::new();
-            for utxo in spendable_inputs.iter().take(max_inputs - 1).cloned() {
-                selected_utxos.push(utxo);
-                total += utxo.value;
-            }
-
-            // Calculate the fee - right now this is constant.
-            let fee = BASE_FEE;
+            let mut selected_utxos: Vec<&UnspentTxOut> = spendable_inputs
+                .iter()
+                .take(max_inputs - 1)
+                .cloned()
+                .collect();
+            let total =
+                total_utxo_value(selected_utxos.iter().cloned()).ok_or(Error::ValueOverflow)?;
 
             // See if the total amount we are trying to merge into our biggest UTXO is bigger than the fee.
             // If it's smaller, the merge would just lose us money.
-            if total > fee {
+            if total > PicoMob::new(fee) {
                 // Grab the UTXO we are merging into and stop iterating.
                 selected_utxos.push(biggest_utxo);
 
                 // Sanity - the amount we're moving sans the fee needs to increase the value of our biggest UTXO.
-                let total_value: u64 = selected_utxos.iter().map(|utxo| utxo.value).sum();
-                assert!(total_value - fee > biggest_utxo.value);
+                let total_value =
+                    total_utxo_value(selected_utxos.iter().cloned()).ok_or(Error::ValueOverflow)?;
+                assert!(
+                    total_value.checked_sub(PicoMob::new(fee))
+                        > Some(PicoMob::new(biggest_utxo.value))
+                );
 
                 // Return our selected utxos and fee.
                 return Ok((selected_utxos.into_iter().cloned().collect(), fee));
@@ -483,6 +1396,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         ring_size: usize,
         num_rings: usize,
         excluded_tx_out_indices: &[u64],
+        deadline: &Deadline,
     ) -> Result<Vec<Vec<(TxOut, TxOutMembershipProof)>>, Error> {
         let num_requested = ring_size * num_rings;
         let num_txos = self.ledger_db.num_txos()?;
@@ -512,6 +1426,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let sampled_indices_vec: Vec<u64> = sampled_indices.into_iter().collect();
 
         // Get proofs for all of those indexes.
+        deadline.check()?;
         let proofs = self
             .ledger_db
             .get_tx_out_proof_of_memberships(&sampled_indices_vec)?;
@@ -524,6 +1439,7 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
         let mut rings_with_proofs = Vec::new();
 
         for _ in 0..num_rings {
+            deadline.check()?;
             let mut ring = Vec::new();
             for _ in 0..ring_size {
                 let (index, proof) = indexes_and_proofs_iterator.next().unwrap();
@@ -538,17 +1454,63 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
     }
 
     /// Build a TxProposal object.
+    ///
+    /// See `build_unsigned_tx_proposal` for the arguments.
     fn build_tx_proposal(
         inputs: &[(UnspentTxOut, TxOutMembershipProof)],
         rings: Vec<Vec<(TxOut, TxOutMembershipProof)>>,
         fee: u64,
-        from_account_key: &AccountKey,
+        view_private_key: &RistrettoPrivate,
         change_subaddress: u64,
+        change_address: &PublicAddress,
+        tx_signer: &dyn TxSigner,
         destinations: &[Outlay],
         tombstone_block: BlockIndex,
+        dust_rules: &DustRules,
+        change_absorption_threshold: u64,
+        denominate_change: bool,
         rng: &mut (impl RngCore + CryptoRng),
         logger: &Logger,
     ) -> Result<TxProposal, Error> {
+        let unsigned_tx_proposal = Self::build_unsigned_tx_proposal(
+            inputs,
+            rings,
+            fee,
+            view_private_key,
+            change_subaddress,
+            change_address,
+            destinations,
+            tombstone_block,
+            dust_rules,
+            change_absorption_threshold,
+            denominate_change,
+            rng,
+            logger,
+        )?;
+        Self::sign_tx_proposal(unsigned_tx_proposal, tx_signer, rng, logger)
+    }
+
+    /// Build an UnsignedTxProposal object.
+    ///
+    /// If `change_absorption_threshold` is non-zero and the change is below it, the change is added
+    /// to the fee instead of being returned to `change_address`, the sender's subaddress
+    /// `change_subaddress`. If `denominate_change` is set, change is returned in outputs of
+    /// standard denominations.
+    fn build_unsigned_tx_proposal(
+        inputs: &[(UnspentTxOut, TxOutMembershipProof)],
+        rings: Vec<Vec<(TxOut, TxOutMembershipProof)>>,
+        fee: u64,
+        view_private_key: &RistrettoPrivate,
+        change_subaddress: u64,
+        change_address: &PublicAddress,
+        destinations: &[Outlay],
+        tombstone_block: BlockIndex,
+        dust_rules: &DustRules,
+        change_absorption_threshold: u64,
+        denominate_change: bool,
+        rng: &mut (impl RngCore + CryptoRng),
+        logger: &Logger,
+    ) -> Result<UnsignedTxProposal, Error> {
         // Check that number of rings matches number of inputs.
         if rings.len() != inputs.len() {
             let err = format!(
@@ -560,189 +1522,190 @@ impl<T: UserTxConnection + 'static> TransactionsManager<T> {
             return Err(Error::TxBuildError(err));
         }
 
-        // Check that we have at least one destination.
-        if destinations.is_empty() {
-            return Err(Error::TxBuildError(
-                "Must have at least one destination".into(),
-            ));
-        }
-
-        // Create tx_builder.
-        let mut tx_builder = TransactionBuilder::new();
-
-        tx_builder.set_fee(fee);
+        let mut payment_builder = PaymentBuilder::new();
+        payment_builder.set_fee(fee);
+        payment_builder.set_tombstone_block(tombstone_block);
+        payment_builder.set_change_absorption_threshold(change_absorption_threshold);
+        payment_builder.set_denominate_change(denominate_change);
+        payment_builder.set_dust_rules(*dust_rules);
 
-        // Unzip each vec of tuples into a tuple of vecs.
-        let mut rings_and_proofs: Vec<(Vec<TxOut>, Vec<TxOutMembershipProof>)> = rings
-            .into_iter()
-            .map(|tuples| tuples.into_iter().unzip())
-            .collect();
-
-        // Add inputs to the tx.
-        for (utxo, proof) in inputs {
-            let (mut ring, mut membership_proofs) = rings_and_proofs
-                .pop()
-                .ok_or_else(|| Error::TxBuildError("rings_and_proofs was empty".to_string()))?;
-            assert_eq!(
-                ring.len(),
-                membership_proofs.len(),
-                "Each ring element must have a corresponding membership proof."
+        for ((utxo, proof), ring) in inputs.iter().zip(rings.into_iter()) {
+            log::debug!(
+                logger,
+                "Adding input: utxo {:?}, key image {:?}",
+                utxo.tx_out.public_key,
+                utxo.key_image
             );
-
-            // Add the input to the ring.
-            let position_opt = ring.iter().position(|tx_out| *tx_out == utxo.tx_out);
-            let real_key_index = match position_opt {
-                Some(position) => {
-                    // The input is already present in the ring.
-                    // This could happen if ring elements are sampled randomly from the ledger.
-                    position
-                }
-                None => {
-                    // The input is not already in the ring.
-                    if ring.is_empty() {
-                        // Append the input and its proof of membership.
-                        ring.push(utxo.tx_out.clone());
-                        membership_proofs.push(proof.clone());
-                    } else {
-                        // Replace the first element of the ring.
-                        ring[0] = utxo.tx_out.clone();
-                        membership_proofs[0] = proof.clone();
-                    }
-                    // The real input is always the first element. This is safe because TransactionBuilder
-                    // sorts each ring.
-                    0
-                }
-            };
-
-            assert_eq!(
-                ring.len(),
-                membership_proofs.len(),
-                "Each ring element must have a corresponding membership proof."
+            payment_builder.add_input(
+                SpendableTxOut {
+                    tx_out: utxo.tx_out.clone(),
+                    membership_proof: proof.clone(),
+                    subaddress_index: utxo.subaddress_index,
+                    value: utxo.value,
+                },
+                ring,
             );
+        }
 
-            let public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key).unwrap();
-            let onetime_private_key = recover_onetime_private_key(
-                &public_key,
-                from_account_key.view_private_key(),
-                &from_account_key.subaddress_spend_key(utxo.subaddress_index),
-            );
+        for outlay in destinations {
+            payment_builder.add_outlay(outlay.value, &outlay.receiver);
+        }
 
-            let key_image = compute_key_image(&onetime_private_key);
+        let unsigned_payment =
+            payment_builder.build_unsigned_from_view_key(view_private_key, change_address, rng)?;
+        if unsigned_payment.absorbed_change > 0 {
             log::debug!(
                 logger,
-                "Adding input: ring {:?}, utxo index {:?}, key image {:?}, pubkey {:?}",
-                ring,
-                real_key_index,
-                key_image,
-                public_key
-            );
-
-            tx_builder.add_input(
-                InputCredentials::new(
-                    ring,
-                    membership_proofs,
-                    real_key_index,
-                    onetime_private_key,
-                    *from_account_key.view_private_key(),
-                    rng,
-                )
-                .or_else(|_| {
-                    Err(Error::TxBuildError(
-                        "failed creating InputCredentials".into(),
-                    ))
-                })?,
+                "Absorbed change of {} into fee of {}",
+                unsigned_payment.absorbed_change,
+                fee
             );
         }
 
-        // Add outputs to our destinations.
-        let mut total_value = 0;
-        let mut tx_out_to_outlay_index = HashMap::default();
-        for (i, outlay) in destinations.iter().enumerate() {
-            let tx_out = tx_builder
-                .add_output(outlay.value, &outlay.receiver, None, rng)
-                .map_err(|err| Error::TxBuildError(format!("failed adding output: {}", err)))?;
-
-            tx_out_to_outlay_index.insert(tx_out, i);
-
-            total_value += outlay.value;
-        }
+        Ok(UnsignedTxProposal {
+            utxos: inputs
+                .iter()
+                .map(|(utxo, _membership_proof)| utxo.clone())
+                .collect(),
+            outlays: destinations.to_vec(),
+            unsigned_tx: unsigned_payment.unsigned_tx,
+            outlay_index_to_tx_out_index: HashMap::from_iter(
+                unsigned_payment
+                    .outlay_tx_out_indices
+                    .into_iter()
+                    .enumerate(),
+            ),
+            outlay_confirmation_numbers: unsigned_payment.outlay_confirmation_numbers,
+            absorbed_change: unsigned_payment.absorbed_change,
+            change_subaddress,
+        })
+    }
 
-        // Figure out if we have change.
-        let input_value = inputs
+    /// Sign an UnsignedTxProposal object with `tx_signer`.
+    fn sign_tx_proposal(
+        unsigned_tx_proposal: UnsignedTxProposal,
+        tx_signer: &dyn TxSigner,
+        rng: &mut (impl RngCore + CryptoRng),
+        logger: &Logger,
+    ) -> Result<TxProposal, Error> {
+        // Have the signer sign each ring.
+        let unsigned_tx = &unsigned_tx_proposal.unsigned_tx;
+        let pseudo_output_blindings = unsigned_tx.pseudo_output_blindings(rng);
+        let rings_to_sign = unsigned_tx
+            .inputs
             .iter()
-            .fold(0, |acc, (utxo, _proof)| acc + utxo.value);
-        if total_value > input_value {
-            return Err(Error::InsufficientFunds);
-        }
-        let change = input_value - total_value - tx_builder.fee;
-
-        // If we do, add an output for that as well.
-        if change > 0 {
-            tx_builder
-                .add_output(
-                    change,
-                    &from_account_key.subaddress(change_subaddress),
-                    None,
-                    rng,
-                )
-                .map_err(|err| {
-                    Error::TxBuildError(format!("failed adding output (change): {}", err))
-                })?;
-        }
-
-        // Set tombstone block.
-        tx_builder.set_tombstone_block(tombstone_block);
-
-        // Build tx.
-        let tx = tx_builder
-            .build(rng)
-            .map_err(|err| Error::TxBuildError(format!("build tx failed: {}", err)))?;
-
-        // Map each TxOut in the constructed transaction to its respective outlay.
-        let outlay_index_to_tx_out_index =
-            HashMap::from_iter(tx.prefix.outputs.iter().enumerate().filter_map(
-                |(tx_out_index, tx_out)| {
-                    if let Some(outlay_index) = tx_out_to_outlay_index.get(tx_out) {
-                        Some((*outlay_index, tx_out_index))
-                    } else {
-                        None
-                    }
-                },
-            ));
+            .zip(pseudo_output_blindings.iter())
+            .map(|(input, pseudo_output_blinding)| {
+                let public_key = CompressedRistrettoPublic::from(&input.real_output_public_key);
+                let utxo = unsigned_tx_proposal
+                    .utxos
+                    .iter()
+                    .find(|utxo| utxo.tx_out.public_key == public_key)
+                    .ok_or_else(|| Error::TxBuildError("No selected utxo matches input".into()))?;
+                Ok(RingToSign {
+                    input: input.clone(),
+                    subaddress_index: utxo.subaddress_index,
+                    pseudo_output_blinding: *pseudo_output_blinding,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        log::trace!(logger, "Signing {} rings", rings_to_sign.len());
+        let ring_signatures = tx_signer.sign_tx(&unsigned_tx_proposal, &rings_to_sign)?;
 
-        // Sanity check: All of our outlays should have a unique index in the map.
-        assert_eq!(outlay_index_to_tx_out_index.len(), destinations.len());
-        let mut found_tx_out_indices = HashSet::default();
-        for i in 0..destinations.len() {
-            let tx_out_index = outlay_index_to_tx_out_index
-                .get(&i)
-                .expect("index not in map");
-            if !found_tx_out_indices.insert(tx_out_index) {
-                panic!("duplicate index {} found in map", tx_out_index);
-            }
-        }
+        let UnsignedTxProposal {
+            utxos,
+            outlays,
+            unsigned_tx,
+            outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+            absorbed_change,
+            change_subaddress: _,
+        } = unsigned_tx_proposal;
+
+        let tx = unsigned_tx.sign_with_ring_signatures(
+            ring_signatures,
+            &pseudo_output_blindings,
+            rng,
+        )?;
 
-        // Return the TxProposal
-        let selected_utxos = inputs
-            .iter()
-            .map(|(utxo, _membership_proof)| utxo.clone())
-            .collect();
+        // Signers other than the local one may return anything, so check the signature.
+        validate_transaction_signature(&tx, rng)
+            .map_err(|err| Error::Signer(format!("Invalid transaction signature: {:?}", err)))?;
 
         Ok(TxProposal {
-            utxos: selected_utxos,
-            outlays: destinations.to_vec(),
+            utxos,
+            outlays,
             tx,
             outlay_index_to_tx_out_index,
+            outlay_confirmation_numbers,
+            absorbed_change,
         })
     }
 }
 
+/// A rule fetched from the network, along with when it was fetched.
+struct CachedNetworkRule<V: Clone>(Arc<Mutex<Option<(Instant, V)>>>);
+
+impl<V: Clone> CachedNetworkRule<V> {
+    /// Returns the cached value if it was fetched less than `ttl` ago, and otherwise replaces it
+    /// with the result of `fetch`. Concurrent callers wait for a single fetch.
+    fn get_or_fetch(
+        &self,
+        ttl: Duration,
+        fetch: impl FnOnce() -> Result<V, Error>,
+    ) -> Result<V, Error> {
+        let mut cached = self.0.lock().expect("lock poisoned");
+        if let Some((fetched_at, value)) = cached.as_ref() {
+            if fetched_at.elapsed() < ttl {
+                return Ok(value.clone());
+            }
+        }
+        let value = fetch()?;
+        *cached = Some((Instant::now(), value.clone()));
+        Ok(value)
+    }
+}
+
+impl<V: Clone> Clone for CachedNetworkRule<V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<V: Clone> Default for CachedNetworkRule<V> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(None)))
+    }
+}
+
+/// Sums up the values of UTXOs, or returns `None` if the sum does not fit in a u64.
+pub(crate) fn total_utxo_value<'a>(
+    utxos: impl IntoIterator<Item = &'a UnspentTxOut>,
+) -> Option<PicoMob> {
+    PicoMob::checked_sum(utxos.into_iter().map(|utxo| PicoMob::new(utxo.value)))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use keys::{FromRandom, RistrettoPrivate};
+    use crate::{
+        coin_selection::CoinSelectionStrategy,
+        monitor_store::{MonitorData, WatchOnlyKey},
+        sync::SyncThread,
+        test_utils::{
+            get_test_databases, wait_for_monitors, TestSigningEnclave,
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+        },
+    };
+    use common::logger::test_with_logger;
+    use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
     use mcconnection::ThickClient;
+    use mcconnection_tests::{test_client_uri, MockConsensusNetwork};
+    use mcuri::ConnectionUri;
+    use mobilecoind_enclave_api::Error as EnclaveError;
     use rand::{rngs::StdRng, SeedableRng};
+    use std::{convert::TryFrom, time::Instant};
+    use transaction::{dust::DustPolicy, onetime_keys::compute_key_image};
 
     fn generate_utxos(num_utxos: usize) -> Vec<UnspentTxOut> {
         let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
@@ -767,68 +1730,11 @@ mod test {
                 value: 1,
                 attempted_spend_height: 0,
                 attempted_spend_tombstone: 0,
+                memo: String::new(),
             })
             .collect()
     }
 
-    #[test]
-    fn test_select_utxos_for_value_selects_smallest_inputs() {
-        let mut utxos = generate_utxos(5);
-
-        utxos[0].value = 100;
-        utxos[1].value = 200;
-        utxos[2].value = 300;
-        utxos[3].value = 2000;
-        utxos[4].value = 1000;
-
-        // Sending 300 should select 100 + 200 when 2 inputs are allowed.
-        let selected_utxos =
-            TransactionsManager::<ThickClient>::select_utxos_for_value(&utxos, 300, utxos.len())
-                .unwrap();
-
-        assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[1].clone()]);
-
-        // Sending 301 should select 100 + 200 + 300 when 3 inputs are allowed.
-        let selected_utxos =
-            TransactionsManager::<ThickClient>::select_utxos_for_value(&utxos, 301, utxos.len())
-                .unwrap();
-
-        assert_eq!(
-            selected_utxos,
-            vec![utxos[0].clone(), utxos[1].clone(), utxos[2].clone()]
-        );
-
-        // Sending 301 should select 200 + 300 when only 2  inputs are allowed.
-        let selected_utxos =
-            TransactionsManager::<ThickClient>::select_utxos_for_value(&utxos, 301, 2).unwrap();
-
-        assert_eq!(selected_utxos, vec![utxos[1].clone(), utxos[2].clone()]);
-    }
-
-    #[test]
-    fn test_select_utxos_for_value_errors_if_too_many_inputs_are_needed() {
-        let utxos = generate_utxos(10);
-        // While we have enough utxos to sum to 5, if the input limit is 4 we should fail.
-        match TransactionsManager::<ThickClient>::select_utxos_for_value(&utxos, 5, 4) {
-            Err(Error::InsufficientFundsFragmentedUtxos) => {
-                // Expected.
-            }
-            _ => panic!("Did not get expected error"),
-        };
-    }
-
-    #[test]
-    fn test_select_utxos_for_value_errors_if_insufficient_funds() {
-        let utxos = generate_utxos(10);
-        // While we have enough utxos to sum to 5, if the input limit is 4 we should fail.
-        match TransactionsManager::<ThickClient>::select_utxos_for_value(&utxos, 50, 100) {
-            Err(Error::InsufficientFunds) => {
-                // Expected.
-            }
-            _ => panic!("Did not get expected error"),
-        };
-    }
-
     #[test]
     fn test_select_utxos_for_optimization_selects_smallest_inputs() {
         // Optimizing with max_inputs=2 should select 100, 2000
@@ -843,8 +1749,10 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 2)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 2, BASE_FEE,
+                )
+                .unwrap();
 
             assert_eq!(selected_utxos, vec![utxos[0].clone(), utxos[4].clone()]);
             assert_eq!(fee, BASE_FEE);
@@ -862,8 +1770,10 @@ mod test {
             utxos[5].value = 1000;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 3, BASE_FEE,
+                )
+                .unwrap();
 
             assert_eq!(
                 selected_utxos,
@@ -893,7 +1803,7 @@ mod test {
             );
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000, &utxos, 100, BASE_FEE,
             );
             assert!(result.is_err());
         }
@@ -906,7 +1816,7 @@ mod test {
             utxos[1].value = 2000;
 
             let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
-                1000, &utxos, 100,
+                1000, &utxos, 100, BASE_FEE,
             );
             assert!(result.is_err());
         }
@@ -921,8 +1831,10 @@ mod test {
             utxos[3].value = 2;
 
             let (selected_utxos, fee) =
-                TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &utxos, 3)
-                    .unwrap();
+                TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+                    1000, &utxos, 3, BASE_FEE,
+                )
+                .unwrap();
             // Since we're limited to 3 inputs, the lowest input (of value 1) is going to get excluded.
             assert_eq!(
                 selected_utxos,
@@ -940,14 +1852,19 @@ mod test {
         utxos[0].value = 2000;
         utxos[1].value = 2000;
 
-        let result =
-            TransactionsManager::<ThickClient>::select_utxos_for_optimization(1000, &[], 100);
+        let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
+            1000,
+            &[],
+            100,
+            BASE_FEE,
+        );
         assert!(result.is_err());
 
         let result = TransactionsManager::<ThickClient>::select_utxos_for_optimization(
             1000,
             &utxos[0..1],
             100,
+            BASE_FEE,
         );
         assert!(result.is_err());
 
@@ -956,6 +1873,7 @@ mod test {
             1000,
             &utxos[0..2],
             2,
+            BASE_FEE,
         );
         assert!(result.is_ok());
 
@@ -963,7 +1881,730 @@ mod test {
             1000,
             &utxos[0..2],
             3,
+            BASE_FEE,
         );
         assert!(result.is_err());
     }
+
+    #[test_with_logger]
+    // Submitted transactions should land in the ledger, and double spends should be rejected.
+    fn test_submit_tx_proposal_to_mock_consensus(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        let peer_manager = ConnectionManager::new(
+            vec![
+                network.connection(test_client_uri(1)),
+                network.connection(test_client_uri(2)),
+            ],
+            logger.clone(),
+        );
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            peer_manager,
+            logger.clone(),
+        );
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = [Outlay {
+            value: 10,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        // Nothing is built once the deadline has passed.
+        match transactions_manager.build_transaction(
+            &monitor_id,
+            0,
+            &utxos,
+            &outlays,
+            0,
+            0,
+            0,
+            &CoinSelectionStrategy::default(),
+            &Deadline::At(Instant::now()),
+        ) {
+            Err(Error::DeadlineExceeded) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let tx_proposal = transactions_manager
+            .build_transaction(
+                &monitor_id,
+                0,
+                &utxos,
+                &outlays,
+                0,
+                0,
+                0,
+                &CoinSelectionStrategy::default(),
+                &Deadline::Never,
+            )
+            .unwrap();
+
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        assert_eq!(
+            transactions_manager
+                .submit_tx_proposal(&tx_proposal)
+                .unwrap(),
+            num_blocks
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), num_blocks + 1);
+        for utxo in tx_proposal.utxos.iter() {
+            assert!(ledger_db.contains_key_image(&utxo.key_image).unwrap());
+        }
+
+        // Submitting the same transaction again is a double spend.
+        assert!(transactions_manager
+            .submit_tx_proposal(&tx_proposal)
+            .is_err());
+        assert_eq!(network.submitted_txs(), vec![tx_proposal.tx]);
+    }
+
+    #[test_with_logger]
+    // A transaction built by a watch-only monitor should be submittable once signed with the
+    // account key.
+    fn test_build_unsigned_tx_and_sign_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new_watch_only(
+            WatchOnlyKey::from(&sender),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        let peer_manager =
+            ConnectionManager::new(vec![network.connection(test_client_uri(1))], logger.clone());
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            peer_manager,
+            logger.clone(),
+        );
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = [Outlay {
+            value: 10,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        // Watch-only monitors cannot sign.
+        match transactions_manager.build_transaction(
+            &monitor_id,
+            0,
+            &utxos,
+            &outlays,
+            0,
+            0,
+            0,
+            &CoinSelectionStrategy::default(),
+            &Deadline::Never,
+        ) {
+            Err(Error::WatchOnlyMonitor) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let unsigned_tx_proposal = transactions_manager
+            .build_unsigned_tx(
+                &monitor_id,
+                0,
+                &utxos,
+                &outlays,
+                0,
+                0,
+                0,
+                &CoinSelectionStrategy::default(),
+                &Deadline::Never,
+            )
+            .unwrap();
+        assert_eq!(unsigned_tx_proposal.outlays, outlays.to_vec());
+
+        // Only the owner of the inputs can sign them.
+        match transactions_manager
+            .sign_tx(unsigned_tx_proposal.clone(), &AccountKey::random(&mut rng))
+        {
+            Err(Error::Signer(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let tx_proposal = transactions_manager
+            .sign_tx(unsigned_tx_proposal.clone(), &sender)
+            .unwrap();
+        assert_eq!(
+            tx_proposal.tx.prefix,
+            unsigned_tx_proposal.unsigned_tx.prefix
+        );
+        assert_eq!(
+            tx_proposal.outlay_index_to_tx_out_index,
+            unsigned_tx_proposal.outlay_index_to_tx_out_index
+        );
+
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        assert_eq!(
+            transactions_manager
+                .submit_tx_proposal(&tx_proposal)
+                .unwrap(),
+            num_blocks
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), num_blocks + 1);
+    }
+
+    #[test_with_logger]
+    // Sealed monitors should be synced and spent from through the signing enclave.
+    fn test_sealed_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new_sealed(
+            WatchOnlyKey::from(&sender),
+            TestSigningEnclave.seal_account_key(sender.clone()).unwrap(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+        assert!(data.is_sealed());
+        assert!(!data.is_watch_only());
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            Some(Arc::new(TestSigningEnclave)),
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The key images computed by the enclave are those of the account key.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+        for utxo in utxos.iter() {
+            let tx_public_key = RistrettoPublic::try_from(&utxo.tx_out.public_key).unwrap();
+            let onetime_private_key = recover_onetime_private_key(
+                &tx_public_key,
+                sender.view_private_key(),
+                &sender.subaddress_spend_key(0),
+            );
+            assert_eq!(utxo.key_image, compute_key_image(&onetime_private_key));
+        }
+
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        let peer_manager =
+            ConnectionManager::new(vec![network.connection(test_client_uri(1))], logger.clone());
+        let mut transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            peer_manager,
+            logger.clone(),
+        );
+
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = [Outlay {
+            value: 10,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        // Without the enclave, sealed monitors cannot sign.
+        match transactions_manager.build_transaction(
+            &monitor_id,
+            0,
+            &utxos,
+            &outlays,
+            0,
+            0,
+            0,
+            &CoinSelectionStrategy::default(),
+            &Deadline::Never,
+        ) {
+            Err(Error::NoSigningEnclave) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        transactions_manager.set_signing_enclave(Arc::new(TestSigningEnclave));
+        let tx_proposal = transactions_manager
+            .build_transaction(
+                &monitor_id,
+                0,
+                &utxos,
+                &outlays,
+                0,
+                0,
+                0,
+                &CoinSelectionStrategy::default(),
+                &Deadline::Never,
+            )
+            .unwrap();
+
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        assert_eq!(
+            transactions_manager
+                .submit_tx_proposal(&tx_proposal)
+                .unwrap(),
+            num_blocks
+        );
+        assert_eq!(ledger_db.num_blocks().unwrap(), num_blocks + 1);
+
+        // The enclave checks what it signs: it refuses change that does not return to the
+        // account's change subaddress...
+        transactions_manager.set_tx_signer(Arc::new(EnclaveSigner::new(
+            Arc::new(TestSigningEnclave),
+            data.sealed_account_key.clone(),
+        )));
+        let mut unsigned_tx_proposal = transactions_manager
+            .build_unsigned_tx(
+                &monitor_id,
+                0,
+                &utxos,
+                &outlays,
+                0,
+                0,
+                0,
+                &CoinSelectionStrategy::default(),
+                &Deadline::Never,
+            )
+            .unwrap();
+        unsigned_tx_proposal.change_subaddress = 1;
+        match transactions_manager.sign_tx(unsigned_tx_proposal.clone(), &sender) {
+            Err(Error::Enclave(EnclaveError::ChangeMismatch(_))) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // ...inputs that are not the account's...
+        unsigned_tx_proposal.change_subaddress = 0;
+        let input = &mut unsigned_tx_proposal.unsigned_tx.inputs[0];
+        input.real_index = (input.real_index + 1) % input.ring.len();
+        match transactions_manager.sign_tx(unsigned_tx_proposal, &sender) {
+            Err(Error::Enclave(EnclaveError::InputMismatch(0))) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // ...and bare messages.
+        let signer = EnclaveSigner::new(
+            Arc::new(TestSigningEnclave),
+            data.sealed_account_key.clone(),
+        );
+        assert!(signer.sign_rings(&[0u8; 32], &[]).is_err());
+    }
+
+    #[test_with_logger]
+    // The network's minimum fee and dust rules should be reused until they expire, and the fee
+    // should never fall below BASE_FEE.
+    fn test_network_rules_are_cached(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        let peer_manager =
+            ConnectionManager::new(vec![network.connection(test_client_uri(1))], logger.clone());
+        let mut transactions_manager =
+            TransactionsManager::new(ledger_db, mobilecoind_db, peer_manager, logger);
+
+        // A node reporting no minimum fee does not make transactions free.
+        network.set_minimum_fee(0);
+        assert_eq!(transactions_manager.fetch_minimum_fee().unwrap(), BASE_FEE);
+        assert_eq!(
+            transactions_manager.fetch_dust_rules().unwrap(),
+            DustRules::default()
+        );
+
+        // Until the cached rules expire, changes are not picked up.
+        let dust_rules = DustRules::new(DustPolicy::Rejected, 200);
+        network.set_minimum_fee(BASE_FEE * 10);
+        network.set_dust_rules(dust_rules);
+        assert_eq!(transactions_manager.fetch_minimum_fee().unwrap(), BASE_FEE);
+        assert_eq!(
+            transactions_manager.fetch_dust_rules().unwrap(),
+            DustRules::default()
+        );
+
+        transactions_manager.set_network_rules_ttl(Duration::from_secs(0));
+        assert_eq!(
+            transactions_manager.fetch_minimum_fee().unwrap(),
+            BASE_FEE * 10
+        );
+        assert_eq!(transactions_manager.fetch_dust_rules().unwrap(), dust_rules);
+    }
+
+    #[test_with_logger]
+    // Retrying after a lost response should not turn into a double spend error.
+    fn test_submit_tx_proposal_with_retry_is_idempotent(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        let peer_manager = ConnectionManager::new(
+            vec![
+                network.connection(test_client_uri(1)),
+                network.connection(test_client_uri(2)),
+            ],
+            logger.clone(),
+        );
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            peer_manager,
+            logger.clone(),
+        );
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let build_tx = |inputs: &[UnspentTxOut]| {
+            transactions_manager
+                .build_transaction(
+                    &monitor_id,
+                    0,
+                    inputs,
+                    &[Outlay {
+                        value: 10,
+                        receiver: receiver.default_subaddress(),
+                    }],
+                    0,
+                    0,
+                    0,
+                    &CoinSelectionStrategy::default(),
+                    &Deadline::Never,
+                )
+                .unwrap()
+        };
+        let retries = || Fixed::from_millis(10).take(3);
+
+        // The transaction lands, but the response is lost. The retry should find it in the ledger
+        // instead of re-sending it.
+        let tx_proposal = build_tx(&utxos[..1]);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        network.drop_responses(1);
+        assert_eq!(
+            transactions_manager
+                .submit_tx_proposal_with_retry(&tx_proposal, retries())
+                .unwrap(),
+            num_blocks
+        );
+        assert_eq!(network.submitted_txs(), vec![tx_proposal.tx.clone()]);
+
+        // The transaction is accepted into the pending set, but the response is lost. The re-sent
+        // transaction is rejected as a double spend, which means the first attempt went through.
+        let tx_proposal = build_tx(&utxos[1..2]);
+        network.set_auto_externalize(false);
+        network.drop_responses(1);
+        assert!(transactions_manager
+            .submit_tx_proposal_with_retry(&tx_proposal, retries())
+            .is_ok());
+        assert_eq!(network.pending_txs(), vec![tx_proposal.tx.clone()]);
+
+        // A double spend on the first attempt is still an error, caught before sending.
+        match transactions_manager.submit_tx_proposal_with_retry(&tx_proposal, retries()) {
+            Err(Error::AlreadyPending(1)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Failures that are not ambiguous are not retried.
+        let tx_proposal = build_tx(&utxos[2..3]);
+        network.inject_failures(1);
+        assert!(transactions_manager
+            .submit_tx_proposal_with_retry(&tx_proposal, retries())
+            .is_err());
+        assert_eq!(network.submitted_txs().len(), 2);
+    }
+
+    #[test_with_logger]
+    // A transaction conflicting with a pending one, e.g. built by another instance sharing the
+    // account, should be rejected before it is sent.
+    fn test_submit_tx_proposal_already_pending(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let network = MockConsensusNetwork::new(ledger_db.clone());
+        network.set_auto_externalize(false);
+        let peer_manager =
+            ConnectionManager::new(vec![network.connection(test_client_uri(1))], logger.clone());
+        let transactions_manager = TransactionsManager::new(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            peer_manager,
+            logger.clone(),
+        );
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let build_tx = |value: u64| {
+            transactions_manager
+                .build_transaction(
+                    &monitor_id,
+                    0,
+                    &utxos[..1],
+                    &[Outlay {
+                        value,
+                        receiver: receiver.default_subaddress(),
+                    }],
+                    0,
+                    0,
+                    0,
+                    &CoinSelectionStrategy::default(),
+                    &Deadline::Never,
+                )
+                .unwrap()
+        };
+        let tx_proposal1 = build_tx(10);
+        let tx_proposal2 = build_tx(20);
+
+        transactions_manager
+            .submit_tx_proposal(&tx_proposal1)
+            .unwrap();
+
+        match transactions_manager.submit_tx_proposal(&tx_proposal2) {
+            Err(Error::AlreadyPending(1)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        match transactions_manager.broadcast_tx_proposal(&tx_proposal2) {
+            Err(Error::AlreadyPending(1)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(network.submitted_txs(), vec![tx_proposal1.tx]);
+
+        // Once the first transaction is externalized, the key image is spent rather than pending.
+        network.externalize_pending();
+        match transactions_manager.submit_tx_proposal(&tx_proposal2) {
+            Err(Error::AlreadyPending(_)) | Ok(_) => panic!("conflict should be found in ledger"),
+            Err(_) => {}
+        }
+    }
+
+    #[test_with_logger]
+    // Broadcasting should judge how widely a transaction was accepted against the quorum set.
+    fn test_broadcast_tx_proposal_verdict(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        let (ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![sender.default_subaddress()],
+            GET_TESTING_ENVIRONMENT_NUM_BLOCKS,
+            logger.clone(),
+            &mut rng,
+        );
+        let _sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            None,
+            None,
+            None,
+            logger.clone(),
+        );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let responder_ids: Vec<ResponderId> = (1..=4)
+            .map(|node_id| test_client_uri(node_id).responder_id().unwrap())
+            .collect();
+
+        // Broadcasts a transaction to four peers, each of which holds transactions in its own
+        // pending set, after making the first `num_failing` of them fail.
+        let broadcast = |num_failing: usize| -> Result<BroadcastResult, Error> {
+            let networks: Vec<_> = (0..4)
+                .map(|_| MockConsensusNetwork::new(ledger_db.clone()))
+                .collect();
+            for (i, network) in networks.iter().enumerate() {
+                network.set_auto_externalize(false);
+                if i < num_failing {
+                    network.inject_failures(1);
+                }
+            }
+            let peer_manager = ConnectionManager::new(
+                networks
+                    .iter()
+                    .enumerate()
+                    .map(|(i, network)| network.connection(test_client_uri(i as u32 + 1)))
+                    .collect(),
+                logger.clone(),
+            );
+            let mut transactions_manager = TransactionsManager::new(
+                ledger_db.clone(),
+                mobilecoind_db.clone(),
+                peer_manager,
+                logger.clone(),
+            );
+            transactions_manager
+                .set_quorum_set(QuorumSet::new_with_node_ids(3, responder_ids.clone()));
+
+            let tx_proposal = transactions_manager
+                .build_transaction(
+                    &monitor_id,
+                    0,
+                    &utxos,
+                    &[Outlay {
+                        value: 10,
+                        receiver: receiver.default_subaddress(),
+                    }],
+                    0,
+                    0,
+                    0,
+                    &CoinSelectionStrategy::default(),
+                    &Deadline::Never,
+                )
+                .unwrap();
+            transactions_manager.broadcast_tx_proposal(&tx_proposal)
+        };
+
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        let result = broadcast(0).unwrap();
+        assert_eq!(result.block_height, num_blocks);
+        assert_eq!(result.verdict, SubmissionVerdict::Quorum);
+        assert_eq!(result.accepting_peers, responder_ids);
+        assert!(result.rejecting_peers.is_empty());
+
+        let result = broadcast(1).unwrap();
+        assert_eq!(result.verdict, SubmissionVerdict::Quorum);
+        assert_eq!(result.rejecting_peers, responder_ids[..1].to_vec());
+
+        // With a threshold of 3 out of 4, any two peers form a blocking set.
+        let result = broadcast(2).unwrap();
+        assert_eq!(result.verdict, SubmissionVerdict::BlockingSet);
+        assert_eq!(result.accepting_peers, responder_ids[2..].to_vec());
+
+        let result = broadcast(3).unwrap();
+        assert_eq!(result.verdict, SubmissionVerdict::Minority);
+
+        assert!(broadcast(4).is_err());
+    }
 }
@@ -0,0 +1,89 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Signing transactions with the signing enclave.
+//! * Monitors added while mobilecoind runs with the signing enclave only store their account key
+//!   sealed by the enclave. mobilecoind builds their transactions with the view private key, and
+//!   hands the whole transaction to the enclave along with the sealed key.
+//! * The enclave unseals the key, checks that the transaction only spends the account's inputs
+//!   and returns its change to the account, and signs the hash of the prefix it computes itself.
+//!   The spend private key never leaves it, and it cannot be used to sign arbitrary messages.
+
+use super::{RingToSign, TxSigner, UnsignedTxProposal};
+use crate::error::Error;
+use mobilecoind_enclave_api::{SealedAccountKey, SignableInput, SignableTx, SigningEnclave};
+use std::{collections::HashSet, sync::Arc};
+use transaction::ring_signature::{CurveScalar, RingMLSAG};
+
+/// Signs with an account key sealed by the signing enclave.
+pub struct EnclaveSigner {
+    signing_enclave: Arc<dyn SigningEnclave>,
+    sealed_account_key: SealedAccountKey,
+}
+
+impl EnclaveSigner {
+    pub fn new(
+        signing_enclave: Arc<dyn SigningEnclave>,
+        sealed_account_key: SealedAccountKey,
+    ) -> Self {
+        Self {
+            signing_enclave,
+            sealed_account_key,
+        }
+    }
+}
+
+impl TxSigner for EnclaveSigner {
+    fn sign_rings(
+        &self,
+        _message: &[u8; 32],
+        _rings: &[RingToSign],
+    ) -> Result<Vec<RingMLSAG>, Error> {
+        Err(Error::Signer(
+            "The signing enclave only signs whole transactions".into(),
+        ))
+    }
+
+    fn sign_tx(
+        &self,
+        unsigned_tx_proposal: &UnsignedTxProposal,
+        rings: &[RingToSign],
+    ) -> Result<Vec<RingMLSAG>, Error> {
+        let unsigned_tx = &unsigned_tx_proposal.unsigned_tx;
+        let inputs = rings
+            .iter()
+            .map(|ring| SignableInput {
+                real_index: ring.input.real_index,
+                subaddress_index: ring.subaddress_index,
+                value: ring.input.value,
+                blinding: CurveScalar::from(ring.input.blinding),
+                pseudo_output_blinding: CurveScalar::from(ring.pseudo_output_blinding),
+            })
+            .collect();
+
+        // The fee's value and blinding come last, and are implicit in the prefix.
+        let num_outputs = unsigned_tx.prefix.outputs.len();
+        let output_values_and_blindings = unsigned_tx.output_values_and_blindings()[..num_outputs]
+            .iter()
+            .map(|(value, blinding)| (*value, CurveScalar::from(*blinding)))
+            .collect();
+
+        // Every output that does not pay an outlay is change.
+        let outlay_outputs: HashSet<usize> = unsigned_tx_proposal
+            .outlay_index_to_tx_out_index
+            .values()
+            .cloned()
+            .collect();
+        let change_outputs = (0..num_outputs)
+            .filter(|index| !outlay_outputs.contains(index))
+            .collect();
+
+        let tx = SignableTx {
+            prefix: unsigned_tx.prefix.clone(),
+            inputs,
+            output_values_and_blindings,
+            change_outputs,
+            change_subaddress: unsigned_tx_proposal.change_subaddress,
+        };
+        Ok(self.signing_enclave.sign_tx(&self.sealed_account_key, tx)?)
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Signing transactions with a Ledger hardware wallet.
+//! * The device holds the account's private keys. For each ring, the host sends the ring, the
+//!   real input's index, transaction public key and subaddress index, and the amounts to commit
+//!   to. The device derives the input's one-time private key and returns the ring signature, so
+//!   that the spend private key never leaves it.
+//! * Commands are APDUs. Requests that do not fit in a single APDU are sent in chunks, and the
+//!   ring signature is read back in chunks.
+
+use super::{RingToSign, TxSigner};
+use crate::error::Error;
+use keys::CompressedRistrettoPublic;
+use transaction::ring_signature::RingMLSAG;
+
+/// Class of the MobileCoin app's APDUs.
+pub const CLA: u8 = 0xe0;
+
+/// Sends a chunk of a sign ring request.
+pub const INS_SIGN_RING: u8 = 0x10;
+
+/// Reads a chunk of the last ring signature.
+pub const INS_GET_RING_SIGNATURE: u8 = 0x11;
+
+/// P1 of the first chunk of a request.
+pub const P1_FIRST_CHUNK: u8 = 0x00;
+
+/// P1 of the following chunks of a request.
+pub const P1_MORE_CHUNKS: u8 = 0x80;
+
+/// P2 of every chunk of a request but the last one.
+pub const P2_MORE_CHUNKS_FOLLOW: u8 = 0x00;
+
+/// P2 of the last chunk of a request.
+pub const P2_LAST_CHUNK: u8 = 0x01;
+
+/// The largest payload of a command or response APDU.
+pub const MAX_CHUNK_LEN: usize = 255;
+
+/// Status word of a successful command.
+pub const SW_OK: u16 = 0x9000;
+
+/// Status word of a command the user refused on the device.
+pub const SW_DENIED: u16 = 0x6985;
+
+/// Exchanges APDUs with a device.
+pub trait LedgerTransport: Send + Sync {
+    /// Send a command APDU and return the response APDU, status word included.
+    fn exchange(&self, command: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// Signs with the account held by a Ledger device.
+pub struct LedgerSigner<T: LedgerTransport> {
+    transport: T,
+}
+
+impl<T: LedgerTransport> LedgerSigner<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport }
+    }
+
+    /// Send a command and return the response data, failing unless the device reports success.
+    fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut command = vec![CLA, ins, p1, p2, data.len() as u8];
+        command.extend_from_slice(data);
+
+        let mut response = self.transport.exchange(&command)?;
+        if response.len() < 2 {
+            return Err(Error::Signer("Truncated response from device".to_string()));
+        }
+        let sw_index = response.len() - 2;
+        let status_word = u16::from_be_bytes([response[sw_index], response[sw_index + 1]]);
+        response.truncate(sw_index);
+        match status_word {
+            SW_OK => Ok(response),
+            SW_DENIED => Err(Error::Signer(
+                "Signing was denied on the device".to_string(),
+            )),
+            status_word => Err(Error::Signer(format!(
+                "Device returned status word {:04x}",
+                status_word
+            ))),
+        }
+    }
+
+    fn sign_ring(&self, message: &[u8; 32], ring: &RingToSign) -> Result<RingMLSAG, Error> {
+        let request = encode_sign_ring_request(message, ring);
+        let num_chunks = (request.len() + MAX_CHUNK_LEN - 1) / MAX_CHUNK_LEN;
+        let mut response = Vec::new();
+        for (index, chunk) in request.chunks(MAX_CHUNK_LEN).enumerate() {
+            let p1 = if index == 0 {
+                P1_FIRST_CHUNK
+            } else {
+                P1_MORE_CHUNKS
+            };
+            let p2 = if index + 1 == num_chunks {
+                P2_LAST_CHUNK
+            } else {
+                P2_MORE_CHUNKS_FOLLOW
+            };
+            response = self.exchange(INS_SIGN_RING, p1, p2, chunk)?;
+        }
+
+        // The response to the last chunk is the length of the ring signature.
+        if response.len() != 2 {
+            return Err(Error::Signer(
+                "Invalid response to sign ring request".to_string(),
+            ));
+        }
+        let signature_len = u16::from_be_bytes([response[0], response[1]]) as usize;
+
+        let mut signature_bytes = Vec::with_capacity(signature_len);
+        while signature_bytes.len() < signature_len {
+            let offset = signature_bytes.len() as u16;
+            let chunk = self.exchange(INS_GET_RING_SIGNATURE, 0, 0, &offset.to_be_bytes())?;
+            if chunk.is_empty() || chunk.len() > signature_len - signature_bytes.len() {
+                return Err(Error::Signer(
+                    "Invalid ring signature chunk from device".to_string(),
+                ));
+            }
+            signature_bytes.extend_from_slice(&chunk);
+        }
+
+        mcserial::decode(&signature_bytes)
+            .map_err(|err| Error::Signer(format!("Invalid ring signature from device: {}", err)))
+    }
+}
+
+impl<T: LedgerTransport> TxSigner for LedgerSigner<T> {
+    fn sign_rings(
+        &self,
+        message: &[u8; 32],
+        rings: &[RingToSign],
+    ) -> Result<Vec<RingMLSAG>, Error> {
+        rings
+            .iter()
+            .map(|ring| self.sign_ring(message, ring))
+            .collect()
+    }
+}
+
+/// Serializes a sign ring request:
+/// * message (32 bytes)
+/// * subaddress index (u64, little endian)
+/// * real input's transaction public key (32 bytes)
+/// * real index (u32, little endian)
+/// * value (u64, little endian)
+/// * blinding (32 bytes)
+/// * pseudo-output blinding (32 bytes)
+/// * ring size (u32, little endian), followed by each ring member's one-time public key and
+///   amount commitment (32 bytes each)
+pub fn encode_sign_ring_request(message: &[u8; 32], ring: &RingToSign) -> Vec<u8> {
+    let input = &ring.input;
+    let mut request = Vec::with_capacity(152 + 64 * input.ring.len());
+    request.extend_from_slice(message);
+    request.extend_from_slice(&ring.subaddress_index.to_le_bytes());
+    request.extend_from_slice(
+        CompressedRistrettoPublic::from(&input.real_output_public_key).as_bytes(),
+    );
+    request.extend_from_slice(&(input.real_index as u32).to_le_bytes());
+    request.extend_from_slice(&input.value.to_le_bytes());
+    request.extend_from_slice(input.blinding.as_bytes());
+    request.extend_from_slice(ring.pseudo_output_blinding.as_bytes());
+    request.extend_from_slice(&(input.ring.len() as u32).to_le_bytes());
+    for (target_key, commitment) in &input.ring {
+        request.extend_from_slice(target_key.as_bytes());
+        request.extend_from_slice(commitment.as_ref());
+    }
+    request
+}
+
+/// Transport to a Ledger device connected over USB.
+#[cfg(feature = "ledger")]
+pub mod hid {
+    use super::LedgerTransport;
+    use crate::error::Error;
+    use hidapi::{HidApi, HidDevice};
+    use std::sync::Mutex;
+
+    /// USB vendor id of Ledger devices.
+    pub const LEDGER_VENDOR_ID: u16 = 0x2c97;
+
+    /// Size of an HID packet.
+    const PACKET_LEN: usize = 64;
+
+    /// Channel id of the APDU channel.
+    const CHANNEL: u16 = 0x0101;
+
+    /// Tag of APDU packets.
+    const TAG_APDU: u8 = 0x05;
+
+    /// How long to wait for the device to answer, e.g. while the user confirms on it.
+    const READ_TIMEOUT_MILLIS: i32 = 60_000;
+
+    /// Exchanges APDUs with the first Ledger device found on USB.
+    pub struct HidTransport {
+        device: Mutex<HidDevice>,
+    }
+
+    impl HidTransport {
+        pub fn open() -> Result<Self, Error> {
+            let api = HidApi::new().map_err(|err| Error::Signer(err.to_string()))?;
+            let device_info = api
+                .device_list()
+                .find(|device_info| device_info.vendor_id() == LEDGER_VENDOR_ID)
+                .ok_or_else(|| Error::Signer("No Ledger device found".to_string()))?;
+            let device = device_info
+                .open_device(&api)
+                .map_err(|err| Error::Signer(err.to_string()))?;
+            Ok(Self {
+                device: Mutex::new(device),
+            })
+        }
+    }
+
+    impl LedgerTransport for HidTransport {
+        fn exchange(&self, command: &[u8]) -> Result<Vec<u8>, Error> {
+            let device = self.device.lock().expect("mutex poisoned");
+
+            for packet in wrap_apdu(command) {
+                // The first byte is the report id.
+                let mut report = vec![0u8];
+                report.extend_from_slice(&packet);
+                device
+                    .write(&report)
+                    .map_err(|err| Error::Signer(err.to_string()))?;
+            }
+
+            let mut packets = Vec::new();
+            loop {
+                let mut packet = [0u8; PACKET_LEN];
+                let len = device
+                    .read_timeout(&mut packet, READ_TIMEOUT_MILLIS)
+                    .map_err(|err| Error::Signer(err.to_string()))?;
+                if len == 0 {
+                    return Err(Error::Signer(
+                        "Timed out waiting for the device".to_string(),
+                    ));
+                }
+                packets.push(packet);
+                if let Some(response) = unwrap_apdu(&packets)? {
+                    return Ok(response);
+                }
+            }
+        }
+    }
+
+    /// Splits an APDU into HID packets. Each packet starts with the channel, tag and sequence
+    /// number. The first packet then holds the length of the APDU.
+    pub fn wrap_apdu(apdu: &[u8]) -> Vec<[u8; PACKET_LEN]> {
+        let mut data = (apdu.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(apdu);
+
+        data.chunks(PACKET_LEN - 5)
+            .enumerate()
+            .map(|(sequence, chunk)| {
+                let mut packet = [0u8; PACKET_LEN];
+                packet[0..2].copy_from_slice(&CHANNEL.to_be_bytes());
+                packet[2] = TAG_APDU;
+                packet[3..5].copy_from_slice(&(sequence as u16).to_be_bytes());
+                packet[5..5 + chunk.len()].copy_from_slice(chunk);
+                packet
+            })
+            .collect()
+    }
+
+    /// Reassembles an APDU from HID packets. Returns None if more packets are needed.
+    pub fn unwrap_apdu(packets: &[[u8; PACKET_LEN]]) -> Result<Option<Vec<u8>>, Error> {
+        let mut data = Vec::new();
+        for (sequence, packet) in packets.iter().enumerate() {
+            if packet[0..2] != CHANNEL.to_be_bytes()
+                || packet[2] != TAG_APDU
+                || packet[3..5] != (sequence as u16).to_be_bytes()
+            {
+                return Err(Error::Signer("Unexpected packet from device".to_string()));
+            }
+            data.extend_from_slice(&packet[5..]);
+        }
+
+        if data.len() < 2 {
+            return Ok(None);
+        }
+        let apdu_len = u16::from_be_bytes([data[0], data[1]]) as usize;
+        if data.len() < 2 + apdu_len {
+            return Ok(None);
+        }
+        Ok(Some(data[2..2 + apdu_len].to_vec()))
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_wrap_unwrap_apdu() {
+            for apdu_len in &[0, 10, 57, 58, 59, 200, 300] {
+                let apdu: Vec<u8> = (0..*apdu_len).map(|i| i as u8).collect();
+                let packets = wrap_apdu(&apdu);
+                assert_eq!(packets.len(), (apdu.len() + 2 + 58) / 59);
+
+                // The APDU is only complete once every packet is in.
+                for num_packets in 1..packets.len() {
+                    assert_eq!(unwrap_apdu(&packets[..num_packets]).unwrap(), None);
+                }
+                assert_eq!(unwrap_apdu(&packets).unwrap(), Some(apdu));
+            }
+        }
+
+        #[test]
+        fn test_unwrap_apdu_rejects_out_of_order_packets() {
+            let apdu = vec![7u8; 100];
+            let mut packets = wrap_apdu(&apdu);
+            packets.swap(0, 1);
+            assert!(unwrap_apdu(&packets).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::payments::LocalSigner;
+    use curve25519_dalek::scalar::Scalar;
+    use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+    use std::{convert::TryFrom, sync::Mutex};
+    use transaction::{
+        account_keys::AccountKey,
+        onetime_keys::{compute_tx_pubkey, create_onetime_public_key},
+        CompressedCommitment,
+    };
+    use transaction_std::InputToSign;
+
+    /// Emulates the device, signing with an account key.
+    struct MockDevice {
+        signer: LocalSigner,
+        request: Mutex<Vec<u8>>,
+        signature: Mutex<Vec<u8>>,
+        deny: bool,
+    }
+
+    impl MockDevice {
+        fn new(account_key: AccountKey, deny: bool) -> Self {
+            Self {
+                signer: LocalSigner::new(account_key),
+                request: Mutex::new(Vec::new()),
+                signature: Mutex::new(Vec::new()),
+                deny,
+            }
+        }
+
+        fn decode_sign_ring_request(request: &[u8]) -> ([u8; 32], RingToSign) {
+            let bytes32 = |offset: usize| {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&request[offset..offset + 32]);
+                bytes
+            };
+            let u64_at = |offset: usize| {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&request[offset..offset + 8]);
+                u64::from_le_bytes(bytes)
+            };
+            let u32_at = |offset: usize| {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&request[offset..offset + 4]);
+                u32::from_le_bytes(bytes) as usize
+            };
+
+            let ring_size = u32_at(148);
+            let ring = (0..ring_size)
+                .map(|i| {
+                    let offset = 152 + 64 * i;
+                    (
+                        CompressedRistrettoPublic::try_from(&request[offset..offset + 32]).unwrap(),
+                        CompressedCommitment::try_from(&request[offset + 32..offset + 64]).unwrap(),
+                    )
+                })
+                .collect();
+            let ring_to_sign = RingToSign {
+                input: InputToSign {
+                    ring,
+                    real_index: u32_at(72),
+                    real_output_public_key: RistrettoPublic::try_from(
+                        &CompressedRistrettoPublic::try_from(&request[40..72]).unwrap(),
+                    )
+                    .unwrap(),
+                    value: u64_at(76),
+                    blinding: Scalar::from_canonical_bytes(bytes32(84)).unwrap(),
+                },
+                subaddress_index: u64_at(32),
+                pseudo_output_blinding: Scalar::from_canonical_bytes(bytes32(116)).unwrap(),
+            };
+            (bytes32(0), ring_to_sign)
+        }
+    }
+
+    impl LedgerTransport for MockDevice {
+        fn exchange(&self, command: &[u8]) -> Result<Vec<u8>, Error> {
+            assert_eq!(command[0], CLA);
+            assert_eq!(command[4] as usize, command.len() - 5);
+            let data = &command[5..];
+
+            let mut response = match command[1] {
+                INS_SIGN_RING => {
+                    let mut request = self.request.lock().unwrap();
+                    if command[2] == P1_FIRST_CHUNK {
+                        request.clear();
+                    }
+                    request.extend_from_slice(data);
+                    if command[3] != P2_LAST_CHUNK {
+                        Vec::new()
+                    } else if self.deny {
+                        return Ok(SW_DENIED.to_be_bytes().to_vec());
+                    } else {
+                        let (message, ring) = Self::decode_sign_ring_request(&request);
+                        let signature = self.signer.sign_rings(&message, &[ring])?.remove(0);
+                        let signature_bytes = mcserial::encode(&signature);
+                        let response = (signature_bytes.len() as u16).to_be_bytes().to_vec();
+                        *self.signature.lock().unwrap() = signature_bytes;
+                        response
+                    }
+                }
+                INS_GET_RING_SIGNATURE => {
+                    let offset = u16::from_be_bytes([data[0], data[1]]) as usize;
+                    let signature = self.signature.lock().unwrap();
+                    let end = (offset + MAX_CHUNK_LEN).min(signature.len());
+                    signature[offset..end].to_vec()
+                }
+                _ => panic!("unexpected instruction {}", command[1]),
+            };
+            response.extend_from_slice(&SW_OK.to_be_bytes());
+            Ok(response)
+        }
+    }
+
+    /// A ring of `ring_size` members, the real one of which is owned by `account_key`.
+    fn ring_to_sign(account_key: &AccountKey, ring_size: usize, rng: &mut StdRng) -> RingToSign {
+        let value = 1234;
+        let blinding = Scalar::random(rng);
+        let tx_private_key = RistrettoPrivate::from_random(rng);
+        let recipient = account_key.default_subaddress();
+        let real_output_public_key =
+            compute_tx_pubkey(&tx_private_key, recipient.spend_public_key());
+        let target_key = create_onetime_public_key(&recipient, &tx_private_key);
+
+        let real_index = ring_size / 2;
+        let ring = (0..ring_size)
+            .map(|i| {
+                if i == real_index {
+                    (
+                        CompressedRistrettoPublic::from(&target_key),
+                        CompressedCommitment::new(value, blinding),
+                    )
+                } else {
+                    (
+                        CompressedRistrettoPublic::from(RistrettoPublic::from_random(rng)),
+                        CompressedCommitment::new(rng.next_u64(), Scalar::random(rng)),
+                    )
+                }
+            })
+            .collect();
+
+        RingToSign {
+            input: InputToSign {
+                ring,
+                real_index,
+                real_output_public_key,
+                value,
+                blinding,
+            },
+            subaddress_index: 0,
+            pseudo_output_blinding: Scalar::random(rng),
+        }
+    }
+
+    #[test]
+    // Ring signatures computed on the device should verify, whatever the number of chunks needed to
+    // transfer the request and the signature.
+    fn test_ledger_signer() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let signer = LedgerSigner::new(MockDevice::new(account_key.clone(), false));
+
+        let message = [3u8; 32];
+        let rings: Vec<RingToSign> = [1, 3, 11]
+            .iter()
+            .map(|ring_size| ring_to_sign(&account_key, *ring_size, &mut rng))
+            .collect();
+        let signatures = signer.sign_rings(&message, &rings).unwrap();
+        assert_eq!(signatures.len(), rings.len());
+
+        for (signature, ring) in signatures.iter().zip(rings.iter()) {
+            let output_commitment =
+                CompressedCommitment::new(ring.input.value, ring.pseudo_output_blinding);
+            assert!(signature
+                .verify(&message, &ring.input.ring, &output_commitment)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_ledger_signer_denied() {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let signer = LedgerSigner::new(MockDevice::new(account_key.clone(), true));
+
+        let rings = vec![ring_to_sign(&account_key, 11, &mut rng)];
+        match signer.sign_rings(&[3u8; 32], &rings) {
+            Err(Error::Signer(_)) => {}
+            Err(err) => panic!("unexpected error {:?}", err),
+            Ok(_) => panic!("signing should have been denied"),
+        }
+    }
+}
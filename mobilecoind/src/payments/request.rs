@@ -0,0 +1,181 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Payment requests handed out by a recipient to ask for a payment.
+//! * The base-58 encoding is the "MobileCoin Request Code" read by wallets, which is short enough
+//!   to be shown as a QR code.
+//! * The protobuf encoding is a serialized `mobilecoind_api::PaymentRequest`, for services that
+//!   exchange requests over the API.
+
+use crate::error::Error;
+use keys::RistrettoPublic;
+use mc_b58_payloads::payloads::RequestPayload;
+use mcserial::ReprBytes32;
+use protobuf::Message;
+use std::convert::TryFrom;
+use transaction::account_keys::PublicAddress;
+
+/// A request for a payment of `value` to `public_address`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentRequest {
+    /// The address to pay.
+    pub public_address: PublicAddress,
+
+    /// The requested value in picoMOB.
+    pub value: u64,
+
+    /// A message for the payer, at most 255 bytes of UTF-8.
+    pub memo: String,
+}
+
+impl PaymentRequest {
+    pub fn new(public_address: PublicAddress, value: u64, memo: &str) -> Result<Self, Error> {
+        let request = Self {
+            public_address,
+            value,
+            memo: memo.to_owned(),
+        };
+
+        // Make sure every request can be handed out as a request code.
+        request.to_payload()?;
+        Ok(request)
+    }
+
+    /// Encodes the request as a base-58 request code.
+    pub fn encode_b58(&self) -> Result<String, Error> {
+        Ok(self.to_payload()?.encode())
+    }
+
+    /// Decodes a base-58 request code. Codes of earlier payload versions decode with a zero value
+    /// and an empty memo.
+    pub fn decode_b58(b58_code: &str) -> Result<Self, Error> {
+        let payload = RequestPayload::decode(b58_code)
+            .map_err(|err| Error::PaymentRequest(err.to_string()))?;
+
+        let spend_public_key = RistrettoPublic::try_from(&payload.spend_public_key)?;
+        let view_public_key = RistrettoPublic::try_from(&payload.view_public_key)?;
+        let public_address = if payload.fog_url.is_empty() {
+            PublicAddress::new(&spend_public_key, &view_public_key)
+        } else {
+            PublicAddress::new_with_fog(&spend_public_key, &view_public_key, &payload.fog_url)
+        };
+
+        Ok(Self {
+            public_address,
+            value: payload.value,
+            memo: payload.memo,
+        })
+    }
+
+    /// Encodes the request as a serialized `mobilecoind_api::PaymentRequest`.
+    pub fn encode_proto(&self) -> Result<Vec<u8>, Error> {
+        mobilecoind_api::PaymentRequest::from(self)
+            .write_to_bytes()
+            .map_err(|err| Error::PaymentRequest(err.to_string()))
+    }
+
+    /// Decodes a serialized `mobilecoind_api::PaymentRequest`.
+    pub fn decode_proto(bytes: &[u8]) -> Result<Self, Error> {
+        let proto: mobilecoind_api::PaymentRequest = protobuf::parse_from_bytes(bytes)
+            .map_err(|err| Error::PaymentRequest(err.to_string()))?;
+        Self::try_from(&proto)
+    }
+
+    fn to_payload(&self) -> Result<RequestPayload, Error> {
+        RequestPayload::new_v3(
+            &self.public_address.view_public_key().to_bytes(),
+            &self.public_address.spend_public_key().to_bytes(),
+            self.public_address.fog_url().unwrap_or(""),
+            self.value,
+            &self.memo,
+        )
+        .map_err(|err| Error::PaymentRequest(err.to_string()))
+    }
+}
+
+impl From<&PaymentRequest> for mobilecoind_api::PaymentRequest {
+    fn from(src: &PaymentRequest) -> Self {
+        let mut proto = mobilecoind_api::PaymentRequest::new();
+        proto.set_public_address((&src.public_address).into());
+        proto.set_value(src.value);
+        proto.set_memo(src.memo.clone());
+        proto
+    }
+}
+
+impl TryFrom<&mobilecoind_api::PaymentRequest> for PaymentRequest {
+    type Error = Error;
+
+    fn try_from(src: &mobilecoind_api::PaymentRequest) -> Result<Self, Self::Error> {
+        if !src.has_public_address() {
+            return Err(Error::PaymentRequest("missing public address".to_owned()));
+        }
+        let public_address = PublicAddress::try_from(src.get_public_address())?;
+        Self::new(public_address, src.get_value(), src.get_memo())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+
+    #[test]
+    // Requests should survive both encodings unchanged.
+    fn test_encode_decode() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+
+        let requests = vec![
+            PaymentRequest::new(AccountKey::random(&mut rng).default_subaddress(), 0, "").unwrap(),
+            PaymentRequest::new(
+                AccountKey::random(&mut rng).default_subaddress(),
+                1234567890,
+                "coffee",
+            )
+            .unwrap(),
+            PaymentRequest::new(
+                AccountKey::random_with_fog(&mut rng).default_subaddress(),
+                u64::max_value(),
+                "hello there",
+            )
+            .unwrap(),
+        ];
+
+        for request in requests {
+            let b58_code = request.encode_b58().unwrap();
+            assert_eq!(PaymentRequest::decode_b58(&b58_code).unwrap(), request);
+
+            let bytes = request.encode_proto().unwrap();
+            assert_eq!(PaymentRequest::decode_proto(&bytes).unwrap(), request);
+        }
+    }
+
+    #[test]
+    // Requests that cannot be encoded as a request code, and malformed encodings, are rejected.
+    fn test_invalid_requests() {
+        let mut rng: StdRng = SeedableRng::from_seed([7u8; 32]);
+        let public_address = AccountKey::random(&mut rng).default_subaddress();
+
+        match PaymentRequest::new(public_address.clone(), 10, &"x".repeat(256)) {
+            Err(Error::PaymentRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        match PaymentRequest::decode_b58("junk") {
+            Err(Error::PaymentRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        match PaymentRequest::decode_proto(&[0xff, 0xff, 0xff]) {
+            Err(Error::PaymentRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // A request without an address cannot be paid.
+        let proto = mobilecoind_api::PaymentRequest::new();
+        match PaymentRequest::decode_proto(&proto.write_to_bytes().unwrap()) {
+            Err(Error::PaymentRequest(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
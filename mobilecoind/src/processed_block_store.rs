@@ -0,0 +1,453 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for processed blocks.
+//! * Manages the mapping of (monitor id, block index) -> [ProcessedTxOut]s.
+//! * Every TxOut a monitor matched or spent while syncing a block is recorded here, together with
+//!   its direction, so that the history of a monitor can be queried after its UTXOs are spent.
+
+use crate::{error::Error, monitor_store::MonitorId};
+
+use common::logger::Logger;
+use keys::CompressedRistrettoPublic;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::{convert::TryFrom, sync::Arc};
+use transaction::{ring_signature::KeyImage, BlockIndex};
+
+// LMDB Database Names
+pub const PROCESSED_BLOCK_KEY_TO_PROCESSED_TX_OUTS_DB_NAME: &str =
+    "mobilecoind_db:processed_block_store:processed_block_key_to_processed_tx_outs";
+
+/// The direction of a processed TxOut, from the point of view of the monitor that processed it.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum ProcessedTxOutDirection {
+    /// Not set. This is never stored in the database.
+    Invalid = 0,
+
+    /// The TxOut was sent to the monitor by someone else.
+    Received = 1,
+
+    /// The TxOut belonged to the monitor and its key image appeared in the block.
+    Spent = 2,
+
+    /// The TxOut was sent to the monitor by itself, in the same block it spent some of its
+    /// outputs from the same subaddress.
+    Change = 3,
+}
+
+/// A TxOut processed by a monitor, as stored in the processed block database.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct ProcessedTxOut {
+    /// The block the TxOut was processed in.
+    #[prost(uint64, tag = "1")]
+    pub block_index: u64,
+
+    /// Index of the subaddress the TxOut belongs to.
+    #[prost(uint64, tag = "2")]
+    pub subaddress_index: u64,
+
+    /// The public key of the TxOut.
+    #[prost(message, required, tag = "3")]
+    pub public_key: CompressedRistrettoPublic,
+
+    /// Key image of the TxOut.
+    #[prost(message, required, tag = "4")]
+    pub key_image: KeyImage,
+
+    /// Value of the TxOut.
+    #[prost(uint64, tag = "5")]
+    pub value: u64,
+
+    /// Direction of the TxOut.
+    #[prost(enumeration = "ProcessedTxOutDirection", tag = "6")]
+    pub direction: i32,
+}
+
+impl ProcessedTxOut {
+    /// Returns the direction of this TxOut.
+    pub fn get_direction(&self) -> ProcessedTxOutDirection {
+        ProcessedTxOutDirection::from_i32(self.direction)
+            .unwrap_or(ProcessedTxOutDirection::Invalid)
+    }
+}
+
+/// Type used as the key in the processed_block_key_to_processed_tx_outs database.
+/// Keys are ordered by monitor id, then by block index, so that the history of a monitor can be
+/// iterated in block order.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ProcessedBlockKey {
+    pub monitor_id: MonitorId,
+    pub block_index: BlockIndex,
+}
+
+impl ProcessedBlockKey {
+    pub fn new(monitor_id: &MonitorId, block_index: BlockIndex) -> Self {
+        Self {
+            monitor_id: *monitor_id,
+            block_index,
+        }
+    }
+
+    // 40 bytes: 32 for MonitorId, 8 for the big-endian block index (so that keys sort by block).
+    pub fn to_bytes(&self) -> [u8; 40] {
+        let mut buf = [0u8; 40];
+        buf[0..32].copy_from_slice(self.monitor_id.as_bytes());
+        buf[32..40].copy_from_slice(&self.block_index.to_be_bytes());
+        buf
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.to_bytes().to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for ProcessedBlockKey {
+    type Error = Error;
+
+    fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
+        if src.len() != 40 {
+            return Err(Error::InvalidArgument(
+                "src".to_string(),
+                "src length must be exactly 40".to_string(),
+            ));
+        }
+
+        let monitor_id = MonitorId::try_from(&src[0..32])?;
+
+        let mut block_index_bytes = [0u8; 8];
+        block_index_bytes.copy_from_slice(&src[32..40]);
+        let block_index = u64::from_be_bytes(block_index_bytes);
+
+        Ok(Self {
+            monitor_id,
+            block_index,
+        })
+    }
+}
+
+/// The processed blocks database.
+#[derive(Clone)]
+pub struct ProcessedBlockStore {
+    env: Arc<Environment>,
+
+    /// Mapping of ProcessedBlockKey -> [ProcessedTxOut].
+    processed_block_key_to_processed_tx_outs: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl ProcessedBlockStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let processed_block_key_to_processed_tx_outs = env.create_db(
+            Some(PROCESSED_BLOCK_KEY_TO_PROCESSED_TX_OUTS_DB_NAME),
+            // DUP_SORT is needed here since we are storing multiple ProcessedTxOuts per block.
+            // Note that values in a DUP_SORT db must be < 511 bytes!
+            DatabaseFlags::DUP_SORT,
+        )?;
+
+        Ok(Self {
+            env,
+            processed_block_key_to_processed_tx_outs,
+            logger,
+        })
+    }
+
+    /// Store the TxOuts processed by a monitor in a given block.
+    pub fn block_processed<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        processed_tx_outs: &[ProcessedTxOut],
+    ) -> Result<(), Error> {
+        let key = ProcessedBlockKey::new(monitor_id, block_index);
+        let key_bytes = key.to_vec();
+
+        for processed_tx_out in processed_tx_outs {
+            // Sanity test
+            if processed_tx_out.block_index != block_index {
+                return Err(Error::InvalidArgument(
+                    "block_index".to_string(),
+                    "must be equal to processed_tx_out.block_index".to_string(),
+                ));
+            }
+
+            let value_bytes = mcserial::encode(processed_tx_out);
+            db_txn.put(
+                self.processed_block_key_to_processed_tx_outs,
+                &key_bytes,
+                &value_bytes,
+                WriteFlags::NO_DUP_DATA,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the TxOuts processed by a monitor in a given block.
+    pub fn get_processed_block(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let key = ProcessedBlockKey::new(monitor_id, block_index);
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+        match cursor.iter_dup_of(&key.to_vec()) {
+            Ok(iter) => {
+                let mut results = Vec::new();
+                for (_key_bytes, value_bytes) in iter {
+                    results.push(mcserial::decode(value_bytes)?);
+                }
+                Ok(results)
+            }
+            Err(lmdb::Error::NotFound) => Ok(vec![]),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the TxOuts processed by a monitor, in block order.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor to get the history of.
+    /// * `first_block` - The first block to include.
+    /// * `max_blocks` - The maximal number of blocks to look at, starting at `first_block`.
+    pub fn get_history(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        first_block: BlockIndex,
+        max_blocks: u64,
+    ) -> Result<Vec<ProcessedTxOut>, Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, first_block);
+        let end_block = first_block.saturating_add(max_blocks);
+
+        let mut cursor = db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+        let mut results = Vec::new();
+        for (key_bytes, value_bytes) in cursor.iter_from(&start_key.to_vec()) {
+            let key = ProcessedBlockKey::try_from(key_bytes)?;
+            if key.monitor_id != *monitor_id || key.block_index >= end_block {
+                break;
+            }
+            results.push(mcserial::decode(value_bytes)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Remove all processed block data associated with a given monitor.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        self.remove_from_block(db_txn, monitor_id, 0)
+    }
+
+    /// Remove the processed block data of a given monitor, starting at `first_block`.
+    pub fn remove_from_block<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        first_block: BlockIndex,
+    ) -> Result<(), Error> {
+        let start_key = ProcessedBlockKey::new(monitor_id, first_block);
+
+        // Collect the keys first, since we cannot delete while iterating a read cursor.
+        let mut keys = Vec::new();
+        {
+            let mut cursor =
+                db_txn.open_ro_cursor(self.processed_block_key_to_processed_tx_outs)?;
+            for (key_bytes, _value_bytes) in cursor.iter_from(&start_key.to_vec()) {
+                let key = ProcessedBlockKey::try_from(key_bytes)?;
+                if key.monitor_id != *monitor_id {
+                    break;
+                }
+                if keys.last() != Some(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        // Deleting a key without specifying a value removes all of its duplicates.
+        for key in keys {
+            db_txn.del(
+                self.processed_block_key_to_processed_tx_outs,
+                &key.to_vec(),
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::{
+        logger::{test_with_logger, Logger},
+        HashSet,
+    };
+    use keys::{FromRandom, RistrettoPublic};
+    use mcrand::{CryptoRng, RngCore};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::iter::FromIterator;
+    use tempdir::TempDir;
+
+    fn random_processed_tx_out(
+        rng: &mut (impl CryptoRng + RngCore),
+        block_index: BlockIndex,
+        direction: ProcessedTxOutDirection,
+    ) -> ProcessedTxOut {
+        let mut key_image_bytes = [0u8; 32];
+        rng.fill_bytes(&mut key_image_bytes);
+
+        ProcessedTxOut {
+            block_index,
+            subaddress_index: rng.next_u64() % 10,
+            public_key: CompressedRistrettoPublic::from(&RistrettoPublic::from_random(rng)),
+            key_image: KeyImage::from(key_image_bytes),
+            value: rng.next_u64(),
+            direction: direction as i32,
+        }
+    }
+
+    fn setup_test_processed_block_store(
+        logger: &Logger,
+    ) -> (Arc<Environment>, ProcessedBlockStore) {
+        let db_tmp = TempDir::new("processed_block_store_db")
+            .expect("Could not make tempdir for processed block store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = ProcessedBlockStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_block_processed_and_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let (env, store) = setup_test_processed_block_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let block3: Vec<ProcessedTxOut> = vec![
+            random_processed_tx_out(&mut rng, 3, ProcessedTxOutDirection::Received),
+            random_processed_tx_out(&mut rng, 3, ProcessedTxOutDirection::Received),
+        ];
+        let block5: Vec<ProcessedTxOut> = vec![
+            random_processed_tx_out(&mut rng, 5, ProcessedTxOutDirection::Spent),
+            random_processed_tx_out(&mut rng, 5, ProcessedTxOutDirection::Change),
+        ];
+        let other_monitor_block3 = vec![random_processed_tx_out(
+            &mut rng,
+            3,
+            ProcessedTxOutDirection::Received,
+        )];
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id0, 3, &block3)
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id0, 5, &block5)
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id1, 3, &other_monitor_block3)
+                .unwrap();
+
+            // Mismatching block indexes should be rejected.
+            assert!(store
+                .block_processed(&mut db_txn, &monitor_id0, 4, &block3)
+                .is_err());
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+
+        let processed = store.get_processed_block(&db_txn, &monitor_id0, 3).unwrap();
+        assert_eq!(
+            HashSet::from_iter(processed),
+            HashSet::from_iter(block3.clone())
+        );
+
+        assert_eq!(
+            store.get_processed_block(&db_txn, &monitor_id0, 4).unwrap(),
+            vec![]
+        );
+
+        // History should be returned in block order and only contain our monitor's data.
+        let history = store.get_history(&db_txn, &monitor_id0, 0, 100).unwrap();
+        assert_eq!(history.len(), 4);
+        assert!(history[0..2].iter().all(|tx_out| tx_out.block_index == 3));
+        assert!(history[2..4].iter().all(|tx_out| tx_out.block_index == 5));
+        assert!(history
+            .iter()
+            .all(|tx_out| !other_monitor_block3.contains(tx_out)));
+
+        // The block range should be respected.
+        let history = store.get_history(&db_txn, &monitor_id0, 4, 100).unwrap();
+        assert_eq!(history.len(), 2);
+        let history = store.get_history(&db_txn, &monitor_id0, 0, 4).unwrap();
+        assert_eq!(history.len(), 2);
+        let history = store.get_history(&db_txn, &monitor_id0, 6, 100).unwrap();
+        assert_eq!(history.len(), 0);
+    }
+
+    #[test_with_logger]
+    fn test_remove(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let (env, store) = setup_test_processed_block_store(&logger);
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for block_index in 0..3 {
+                for monitor_id in &[monitor_id0, monitor_id1] {
+                    let processed_tx_outs = vec![random_processed_tx_out(
+                        &mut rng,
+                        block_index,
+                        ProcessedTxOutDirection::Received,
+                    )];
+                    store
+                        .block_processed(&mut db_txn, monitor_id, block_index, &processed_tx_outs)
+                        .unwrap();
+                }
+            }
+            db_txn.commit().unwrap();
+        }
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store.remove(&mut db_txn, &monitor_id0).unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(
+            store.get_history(&db_txn, &monitor_id0, 0, 100).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            store
+                .get_history(&db_txn, &monitor_id1, 0, 100)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Keeps the local ledger small by discarding the contents of old blocks.
+//!
+//! Monitors scan the contents of every block from their first block onwards, so the contents of a
+//! block are only discarded once every monitor has scanned it. The headers, key images and TxOuts
+//! of pruned blocks are kept, which is all that is needed to validate new blocks, check key
+//! images and build membership proofs.
+
+use crate::{database::Database, error::Error};
+use common::logger::{log, Logger};
+use ledger_db::{Ledger, LedgerDB};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Discards the contents of the blocks more than `keep_blocks` blocks behind the end of the
+/// ledger that every monitor has scanned. Returns the number of pruned blocks, or None if the
+/// monitors cannot be read because the database is locked.
+pub fn prune_ledger(
+    ledger_db: &mut LedgerDB,
+    mobilecoind_db: Option<&Database>,
+    keep_blocks: u64,
+) -> Result<Option<u64>, Error> {
+    let mut num_pruned_blocks = ledger_db.num_blocks()?.saturating_sub(keep_blocks);
+
+    // Monitors cannot be added while the lock is held, so none of them starts before the blocks
+    // that are pruned.
+    let _pruning_guard = match mobilecoind_db {
+        Some(mobilecoind_db) => {
+            if mobilecoind_db.is_locked() {
+                return Ok(None);
+            }
+            let pruning_guard = mobilecoind_db.lock_pruning();
+            for monitor_data in mobilecoind_db.get_monitor_map()?.values() {
+                num_pruned_blocks = num_pruned_blocks.min(monitor_data.next_block);
+            }
+            Some(pruning_guard)
+        }
+        None => None,
+    };
+
+    ledger_db.prune(num_pruned_blocks)?;
+    Ok(Some(ledger_db.num_pruned_blocks()?))
+}
+
+/// A thread pruning the ledger as it grows.
+pub struct PruningThread {
+    /// The thread handle.
+    join_handle: Option<thread::JoinHandle<()>>,
+
+    /// Stop trigger, used to signal the thread to terminate.
+    stop_requested: Arc<AtomicBool>,
+}
+
+impl PruningThread {
+    pub fn start(
+        mut ledger_db: LedgerDB,
+        mobilecoind_db: Option<Database>,
+        keep_blocks: u64,
+        poll_interval: Duration,
+        logger: Logger,
+    ) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = Some(
+            thread::Builder::new()
+                .name("pruning".to_string())
+                .spawn(move || {
+                    log::debug!(logger, "PruningThread started.");
+
+                    let mut last_num_pruned_blocks = None;
+                    while !thread_stop_requested.load(Ordering::SeqCst) {
+                        match prune_ledger(&mut ledger_db, mobilecoind_db.as_ref(), keep_blocks) {
+                            Ok(Some(num_pruned_blocks)) => {
+                                if last_num_pruned_blocks != Some(num_pruned_blocks) {
+                                    log::debug!(
+                                        logger,
+                                        "The contents of the first {} blocks are pruned",
+                                        num_pruned_blocks
+                                    );
+                                }
+                                last_num_pruned_blocks = Some(num_pruned_blocks);
+                            }
+                            Ok(None) => {}
+                            Err(err) => log::error!(logger, "Failed pruning the ledger: {:?}", err),
+                        }
+                        thread::sleep(poll_interval);
+                    }
+
+                    log::debug!(logger, "PruningThread stopped.");
+                })
+                .expect("failed starting pruning thread"),
+        );
+
+        Self {
+            join_handle,
+            stop_requested,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.join().expect("PruningThread join failed");
+        }
+    }
+}
+
+impl Drop for PruningThread {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{monitor_store::MonitorData, test_utils::get_test_databases};
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::AccountKey;
+
+    #[test_with_logger]
+    // Blocks are pruned once they are old enough and every monitor scanned them.
+    fn test_prune_ledger(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([97u8; 32]);
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        // Without monitors, all but the last blocks are pruned.
+        assert_eq!(
+            prune_ledger(&mut ledger_db, Some(&mobilecoind_db), 7).unwrap(),
+            Some(3)
+        );
+
+        // A monitor that has yet to scan a block holds it back.
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0, // first_subaddress
+            1, // num_subaddresses
+            5, // first_block
+        )
+        .unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        assert_eq!(
+            prune_ledger(&mut ledger_db, Some(&mobilecoind_db), 2).unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            ledger_db.get_block_contents(4),
+            Err(ledger_db::Error::BlockPruned(4))
+        );
+        assert!(ledger_db.get_block_contents(5).is_ok());
+
+        // Once the monitor is removed, the other blocks can be pruned.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        assert_eq!(
+            prune_ledger(&mut ledger_db, Some(&mobilecoind_db), 2).unwrap(),
+            Some(8)
+        );
+    }
+
+    #[test_with_logger]
+    // A monitor stored while the pruning lock is held holds back the blocks it scans.
+    fn test_prune_ledger_waits_for_pruning_lock(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 10, logger.clone(), &mut rng);
+
+        let pruning_guard = mobilecoind_db.lock_pruning();
+
+        let thread_mobilecoind_db = mobilecoind_db.clone();
+        let join_handle = thread::spawn(move || {
+            prune_ledger(&mut ledger_db, Some(&thread_mobilecoind_db), 2).unwrap()
+        });
+
+        // The ledger is not pruned while the lock is held.
+        thread::sleep(Duration::from_millis(100));
+        let monitor_data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            0, // first_subaddress
+            1, // num_subaddresses
+            3, // first_block
+        )
+        .unwrap();
+        mobilecoind_db.add_monitor(&monitor_data).unwrap();
+        drop(pruning_guard);
+
+        assert_eq!(join_handle.join().unwrap(), Some(3));
+    }
+}
@@ -0,0 +1,373 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Signed requests to the HTTP/JSON gateway.
+//! * Once signing keys are configured, requests must be signed with HMAC-SHA256 by one of the
+//!   keys, except for those to the read-only methods in `READ_ONLY_METHODS`, which are served as
+//!   before. Methods that change something, e.g. `SendPayment`, or that return secrets, e.g.
+//!   `ExportState`, are never listed there.
+//! * A signed request carries the id of its key, a unix timestamp and a nonce in headers, along
+//!   with the hex-encoded HMAC of `<method>\n<timestamp>\n<nonce>\n<body>`.
+//! * Requests whose timestamp is further from the gateway's clock than the signing window are
+//!   rejected, and so are nonces already seen within the window, so that a captured request
+//!   cannot be replayed.
+//! * Accepted signed requests are logged along with their key id, nonce and signature, so that
+//!   they can be matched against the records of whoever signed them.
+
+use crate::error::Error;
+use common::HashMap;
+use hmac::{Hmac, Mac};
+use hyper::HeaderMap;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{
+    fs,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Header holding the id of the key a request is signed with.
+pub const KEY_ID_HEADER: &str = "x-mobilecoind-key-id";
+
+/// Header holding the unix timestamp, in seconds, at which a request was signed.
+pub const TIMESTAMP_HEADER: &str = "x-mobilecoind-timestamp";
+
+/// Header holding a value unique to each request signed with a key.
+pub const NONCE_HEADER: &str = "x-mobilecoind-nonce";
+
+/// Header holding the hex-encoded HMAC-SHA256 of a request.
+pub const SIGNATURE_HEADER: &str = "x-mobilecoind-signature";
+
+/// The longest nonce accepted.
+pub const MAX_NONCE_LEN: usize = 128;
+
+/// The shortest signing key accepted.
+pub const MIN_SIGNING_KEY_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The gateway methods that only read, and do not return secrets such as account keys. They are
+/// served without a signature even once signing keys are configured.
+pub const READ_ONLY_METHODS: &[&str] = &[
+    "GetMonitorList",
+    "GetUnspentTxOutList",
+    "GetProcessedTxOutHistory",
+    "GetIncomingPaymentList",
+    "GetTransactionHistory",
+    "GetSpentTxOuts",
+    "GetMonitorAuditLog",
+    "ListAccounts",
+    "GetAddressBookEntry",
+    "GetAddressBook",
+    "GetPublicAddress",
+    "ReadRequestCode",
+    "GetRequestCode",
+    "CreateRequest",
+    "ParseRequest",
+    "CreateMobUri",
+    "ParseMobUri",
+    "VerifyTxProposal",
+    "GetTxApprovalList",
+    "GetLedgerInfo",
+    "GetBlockInfo",
+    "GetBlockSignature",
+    "GetProcessedBlock",
+    "StreamBlocks",
+    "GetTxStatusAsSender",
+    "GetTxStatusAsReceiver",
+    "GetTxOutResultsByPublicKey",
+    "GetMembershipProofs",
+    "GetTxOutsByBlockRange",
+    "GetBlockByKeyImage",
+    "GetOutgoingTxList",
+    "GetOutgoingTx",
+    "GetBalance",
+    "GetBalanceBySubaddress",
+    "GetBalanceBySubaddressList",
+    "GetQueuedPaymentStatus",
+];
+
+/// Whether requests to a gateway method need to be signed once signing keys are configured. All
+/// methods do but those in `READ_ONLY_METHODS`, so that methods added later are signed unless
+/// they are explicitly listed there.
+pub fn requires_signature(method: &str) -> bool {
+    !READ_ONLY_METHODS.contains(&method)
+}
+
+/// The HMAC of a request, as carried in `SIGNATURE_HEADER`.
+pub fn sign_request(key: &[u8], method: &str, timestamp: u64, nonce: &str, body: &[u8]) -> String {
+    hex::encode(
+        request_mac(key, method, timestamp, nonce, body)
+            .result()
+            .code(),
+    )
+}
+
+fn request_mac(key: &[u8], method: &str, timestamp: u64, nonce: &str, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_varkey(key).expect("HMAC accepts keys of any length");
+    mac.input(format!("{}\n{}\n{}\n", method, timestamp, nonce).as_bytes());
+    mac.input(body);
+    mac
+}
+
+/// A signed request that was accepted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SignedRequest {
+    pub key_id: String,
+    pub timestamp: u64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+/// A signing key, as written in a signing keys file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SigningKeyJson {
+    key_id: String,
+    key_hex: String,
+}
+
+/// Checks the signatures of requests, and remembers their nonces for as long as their timestamp
+/// is within the signing window.
+pub struct RequestVerifier {
+    /// Signing keys by id.
+    keys: HashMap<String, Vec<u8>>,
+
+    /// How far the timestamp of a request may be from the gateway's clock.
+    window: Duration,
+
+    /// The timestamp of each (key id, nonce) accepted within the window.
+    seen_nonces: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl RequestVerifier {
+    /// Read signing keys from a JSON file holding a list of objects with a `key_id` and the
+    /// hex-encoded `key_hex` shared with whoever signs requests with it.
+    pub fn load(path: &Path, window: Duration) -> Result<Self, Error> {
+        Self::parse(&fs::read(path)?, window)
+    }
+
+    pub(crate) fn parse(json: &[u8], window: Duration) -> Result<Self, Error> {
+        let entries: Vec<SigningKeyJson> =
+            serde_json::from_slice(json).map_err(|err| Error::SigningKeys(err.to_string()))?;
+        if entries.is_empty() {
+            return Err(Error::SigningKeys("no signing keys".to_string()));
+        }
+
+        let mut keys = HashMap::default();
+        for entry in entries {
+            if entry.key_id.is_empty() {
+                return Err(Error::SigningKeys("empty key id".to_string()));
+            }
+
+            let key = match hex::decode(&entry.key_hex) {
+                Ok(key) if key.len() >= MIN_SIGNING_KEY_LEN => key,
+                _ => {
+                    return Err(Error::SigningKeys(format!(
+                        "{}: keys need to be at least {} hex-encoded bytes",
+                        entry.key_id, MIN_SIGNING_KEY_LEN
+                    )))
+                }
+            };
+
+            if keys.insert(entry.key_id.clone(), key).is_some() {
+                return Err(Error::SigningKeys(format!(
+                    "duplicate key id {}",
+                    entry.key_id
+                )));
+            }
+        }
+
+        Ok(Self {
+            keys,
+            window,
+            seen_nonces: Mutex::new(HashMap::default()),
+        })
+    }
+
+    /// Check the signature of a request to `method`, and that it is not replayed.
+    pub fn verify(
+        &self,
+        method: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<SignedRequest, Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+        self.verify_at(method, headers, body, now)
+    }
+
+    fn verify_at(
+        &self,
+        method: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+        now: u64,
+    ) -> Result<SignedRequest, Error> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| Error::RequestSignature(format!("missing {} header", name)))
+        };
+        let key_id = header(KEY_ID_HEADER)?;
+        let timestamp = header(TIMESTAMP_HEADER)?;
+        let nonce = header(NONCE_HEADER)?;
+        let signature = header(SIGNATURE_HEADER)?;
+
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| Error::RequestSignature(format!("unknown key id {}", key_id)))?;
+        let timestamp: u64 = timestamp
+            .parse()
+            .map_err(|_| Error::RequestSignature("invalid timestamp".to_string()))?;
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(Error::RequestSignature(format!(
+                "nonces need to hold 1 to {} characters",
+                MAX_NONCE_LEN
+            )));
+        }
+        let signature_bytes = hex::decode(signature)
+            .map_err(|_| Error::RequestSignature("invalid signature".to_string()))?;
+
+        request_mac(key, method, timestamp, nonce, body)
+            .verify(&signature_bytes)
+            .map_err(|_| Error::RequestSignature("invalid signature".to_string()))?;
+
+        let window = self.window.as_secs();
+        if timestamp.saturating_add(window) < now || now.saturating_add(window) < timestamp {
+            return Err(Error::RequestSignature(
+                "timestamp is outside of the signing window".to_string(),
+            ));
+        }
+
+        // Nonces older than the window can be forgotten, since their requests would be rejected
+        // for their timestamp anyway.
+        let mut seen_nonces = self.seen_nonces.lock().expect("mutex poisoned");
+        seen_nonces.retain(|_, seen_timestamp| seen_timestamp.saturating_add(window) >= now);
+        let nonce_key = (key_id.to_string(), nonce.to_string());
+        if seen_nonces.contains_key(&nonce_key) {
+            return Err(Error::RequestSignature("replayed nonce".to_string()));
+        }
+        seen_nonces.insert(nonce_key, timestamp);
+
+        Ok(SignedRequest {
+            key_id: key_id.to_string(),
+            timestamp,
+            nonce: nonce.to_string(),
+            signature: signature.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    const KEY_HEX: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+    fn signed_headers(
+        key: &[u8],
+        method: &str,
+        timestamp: u64,
+        nonce: &str,
+        body: &[u8],
+    ) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(KEY_ID_HEADER, HeaderValue::from_static("exchange"));
+        headers.insert(
+            TIMESTAMP_HEADER,
+            HeaderValue::from_str(&timestamp.to_string()).unwrap(),
+        );
+        headers.insert(NONCE_HEADER, HeaderValue::from_str(nonce).unwrap());
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&sign_request(key, method, timestamp, nonce, body)).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    // Signed requests should be accepted once, within the signing window.
+    fn test_verify() {
+        let verifier = RequestVerifier::parse(
+            format!(r#"[{{"key_id": "exchange", "key_hex": "{}"}}]"#, KEY_HEX).as_bytes(),
+            Duration::from_secs(300),
+        )
+        .unwrap();
+        let key = hex::decode(KEY_HEX).unwrap();
+        let body = br#"{"value": "10"}"#;
+        let now = 1_600_000_000;
+
+        let headers = signed_headers(&key, "SendPayment", now, "nonce-1", body);
+        let signed_request = verifier
+            .verify_at("SendPayment", &headers, body, now + 10)
+            .unwrap();
+        assert_eq!(signed_request.key_id, "exchange");
+        assert_eq!(signed_request.nonce, "nonce-1");
+
+        // The same request cannot be replayed.
+        match verifier.verify_at("SendPayment", &headers, body, now + 20) {
+            Err(Error::RequestSignature(reason)) => assert_eq!(reason, "replayed nonce"),
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // The signature covers the method and the body.
+        let headers = signed_headers(&key, "SendPayment", now, "nonce-2", body);
+        assert!(verifier.verify_at("SubmitTx", &headers, body, now).is_err());
+        assert!(verifier
+            .verify_at("SendPayment", &headers, br#"{"value": "11"}"#, now)
+            .is_err());
+
+        // Requests signed with another key are rejected.
+        let headers = signed_headers(&[2u8; 32], "SendPayment", now, "nonce-3", body);
+        assert!(verifier
+            .verify_at("SendPayment", &headers, body, now)
+            .is_err());
+
+        // Requests signed too long ago, or too far in the future, are rejected.
+        let headers = signed_headers(&key, "SendPayment", now - 301, "nonce-4", body);
+        assert!(verifier
+            .verify_at("SendPayment", &headers, body, now)
+            .is_err());
+        let headers = signed_headers(&key, "SendPayment", now + 301, "nonce-5", body);
+        assert!(verifier
+            .verify_at("SendPayment", &headers, body, now)
+            .is_err());
+
+        // Nonces are forgotten once their requests would be rejected anyway.
+        let headers = signed_headers(&key, "SendPayment", now + 400, "nonce-1", body);
+        verifier
+            .verify_at("SendPayment", &headers, body, now + 400)
+            .unwrap();
+        assert_eq!(verifier.seen_nonces.lock().unwrap().len(), 1);
+
+        // Unsigned requests are rejected.
+        assert!(verifier
+            .verify_at("SendPayment", &HeaderMap::new(), body, now)
+            .is_err());
+    }
+
+    #[test]
+    // Invalid signing key files should be rejected.
+    fn test_parse_invalid() {
+        let window = Duration::from_secs(300);
+        assert!(RequestVerifier::parse(b"[]", window).is_err());
+        assert!(
+            RequestVerifier::parse(br#"[{"key_id": "a", "key_hex": "0101"}]"#, window).is_err()
+        );
+        assert!(RequestVerifier::parse(
+            format!(
+                r#"[{{"key_id": "a", "key_hex": "{}"}}, {{"key_id": "a", "key_hex": "{}"}}]"#,
+                KEY_HEX, KEY_HEX
+            )
+            .as_bytes(),
+            window
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A self-test of a mobilecoind deployment, run with `--self-test`.
+//! * Attests to each configured peer, fetches the origin block from each tx source and opens the
+//!   databases, without syncing the ledger or serving any requests.
+//! * Every check is run even if an earlier one failed, so that a single run reports everything
+//!   that is wrong with a deployment.
+
+use crate::{config::Config, database::Database};
+use attest::Verifier;
+use common::logger::{log, Logger};
+use ledger_db::{Ledger, LedgerDB};
+use ledger_sync::ReqwestTransactionsFetcher;
+use mcconnection::{AttestedConnection, BlockchainConnection, ThickClient};
+use mcuri::ConsensusClientUri;
+use serde::Serialize;
+use std::{path::Path, sync::Arc};
+
+/// The outcome of a single check.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct SelfTestCheck {
+    /// What was checked, e.g. "peer mc://node1.test.mobilecoin.com/".
+    pub name: String,
+
+    /// Whether the check passed.
+    pub passed: bool,
+
+    /// What was found, or why the check failed.
+    pub detail: String,
+}
+
+impl SelfTestCheck {
+    fn new<E: ToString>(name: String, result: Result<String, E>) -> Self {
+        match result {
+            Ok(detail) => Self {
+                name,
+                passed: true,
+                detail,
+            },
+            Err(err) => Self {
+                name,
+                passed: false,
+                detail: err.to_string(),
+            },
+        }
+    }
+}
+
+/// The outcome of all checks.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct SelfTestReport {
+    /// Whether every check passed.
+    pub passed: bool,
+
+    /// The checks, in the order they were run.
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn new(checks: Vec<SelfTestCheck>) -> Self {
+        Self {
+            passed: checks.iter().all(|check| check.passed),
+            checks,
+        }
+    }
+}
+
+/// Run every check of a deployment.
+///
+/// # Arguments
+/// * `config` - The configuration of the deployment.
+/// * `verifier` - The verifier peers must attest with.
+/// * `logger` - Logger.
+pub fn run_self_test(config: &Config, verifier: &Verifier, logger: &Logger) -> SelfTestReport {
+    let grpc_env = Arc::new(
+        grpcio::EnvBuilder::new()
+            .name_prefix("SelfTest-RPC".to_string())
+            .build(),
+    );
+
+    let mut checks = Vec::new();
+    for peer in &config.peers_config.peers {
+        checks.push(check_peer(peer, verifier, grpc_env.clone(), logger));
+    }
+    for tx_source_url in &config.tx_source_urls {
+        checks.push(check_tx_source(tx_source_url, logger));
+    }
+    checks.push(check_ledger_db(&config.ledger_db, config.ledger_read_only));
+    if let Some(mobilecoind_db) = &config.mobilecoind_db {
+        checks.push(check_mobilecoind_db(mobilecoind_db, logger));
+    }
+
+    for check in &checks {
+        if check.passed {
+            log::info!(logger, "Self-test passed: {}: {}", check.name, check.detail);
+        } else {
+            log::error!(logger, "Self-test failed: {}: {}", check.name, check.detail);
+        }
+    }
+
+    SelfTestReport::new(checks)
+}
+
+/// Attest to a peer and fetch its block height.
+fn check_peer(
+    peer: &ConsensusClientUri,
+    verifier: &Verifier,
+    grpc_env: Arc<grpcio::Environment>,
+    logger: &Logger,
+) -> SelfTestCheck {
+    let result =
+        ThickClient::new_with_verifier(peer.clone(), verifier.clone(), grpc_env, logger.clone())
+            .map_err(|err| format!("Could not connect: {}", err))
+            .and_then(|mut conn| {
+                conn.attest()
+                    .map_err(|err| format!("Attestation failed: {}", err))?;
+                let block_height = conn
+                    .fetch_block_height()
+                    .map_err(|err| format!("Could not fetch block height: {}", err))?;
+                Ok(format!("Attested, block height {}", block_height))
+            });
+
+    SelfTestCheck::new(format!("peer {}", peer), result)
+}
+
+/// Fetch the origin block from a tx source.
+fn check_tx_source(tx_source_url: &str, logger: &Logger) -> SelfTestCheck {
+    let result = ReqwestTransactionsFetcher::new(vec![tx_source_url.to_string()], logger.clone())
+        .and_then(|fetcher| fetcher.get_origin_block_and_transactions())
+        .map(|(_block, block_contents)| {
+            format!(
+                "Fetched origin block with {} outputs",
+                block_contents.outputs.len()
+            )
+        })
+        .map_err(|err| format!("Could not fetch origin block: {:?}", err));
+
+    SelfTestCheck::new(format!("tx source {}", tx_source_url), result)
+}
+
+/// Open the ledger database, if it was created.
+fn check_ledger_db(path: &Path, read_only: bool) -> SelfTestCheck {
+    let result = if !path.join("data.mdb").exists() {
+        Ok("Not created yet, it will be created on start".to_string())
+    } else if read_only {
+        LedgerDB::open_read_only(path.to_path_buf())
+            .and_then(|ledger_db| ledger_db.num_blocks())
+            .map(|num_blocks| format!("Opened read-only, {} blocks", num_blocks))
+            .map_err(|err| format!("Could not open: {}", err))
+    } else {
+        LedgerDB::open(path.to_path_buf())
+            .and_then(|ledger_db| ledger_db.num_blocks())
+            .map(|num_blocks| format!("Opened, {} blocks", num_blocks))
+            .map_err(|err| format!("Could not open: {}", err))
+    };
+
+    SelfTestCheck::new(format!("ledger db {}", path.display()), result)
+}
+
+/// Open the mobilecoind database, if it was created.
+fn check_mobilecoind_db(path: &Path, logger: &Logger) -> SelfTestCheck {
+    let result = if !path.exists() {
+        Ok("Not created yet, it will be created on start".to_string())
+    } else {
+        Database::new(path, logger.clone())
+            .and_then(|mobilecoind_db| mobilecoind_db.get_monitor_ids())
+            .map(|monitor_ids| format!("Opened, {} monitors", monitor_ids.len()))
+            .map_err(|err| format!("Could not open: {}", err))
+    };
+
+    SelfTestCheck::new(format!("mobilecoind db {}", path.display()), result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::{test_with_logger, Logger};
+    use mobilecoin_api::{blockchain, conversions::block_num_to_s3block_path};
+    use protobuf::Message;
+    use std::fs;
+    use tempdir::TempDir;
+    use transaction::{Block, BlockContents};
+
+    #[test_with_logger]
+    // Tx sources pass if they serve the origin block.
+    fn test_check_tx_source(logger: Logger) {
+        let block = Block::new_origin_block(&[]);
+        let mut s3_block = blockchain::S3Block::new();
+        s3_block.set_block(blockchain::Block::from(&block));
+        s3_block.set_block_contents(blockchain::BlockContents::from(&BlockContents::new(
+            Vec::new(),
+            Vec::new(),
+        )));
+
+        let good_dir = TempDir::new("good_source").unwrap();
+        let path = good_dir.path().join(block_num_to_s3block_path(0));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, s3_block.write_to_bytes().unwrap()).unwrap();
+        let empty_dir = TempDir::new("empty_source").unwrap();
+
+        let good_url = format!("file://{}", good_dir.path().to_str().unwrap());
+        assert!(check_tx_source(&good_url, &logger).passed);
+
+        let empty_url = format!("file://{}", empty_dir.path().to_str().unwrap());
+        assert!(!check_tx_source(&empty_url, &logger).passed);
+    }
+
+    #[test_with_logger]
+    // Databases pass if they can be opened, or have not been created yet.
+    fn test_check_databases(logger: Logger) {
+        let db_dir = TempDir::new("self_test_db").unwrap();
+        let mobilecoind_db_path = db_dir.path().join("mobilecoind_db");
+        assert!(check_mobilecoind_db(&mobilecoind_db_path, &logger).passed);
+        assert!(check_ledger_db(&db_dir.path().join("ledger_db"), false).passed);
+
+        fs::create_dir_all(&mobilecoind_db_path).unwrap();
+        let check = check_mobilecoind_db(&mobilecoind_db_path, &logger);
+        assert!(check.passed);
+        assert_eq!(check.detail, "Opened, 0 monitors");
+
+        // A ledger that is not an LMDB database fails.
+        let ledger_db_path = db_dir.path().join("ledger_db");
+        fs::create_dir_all(&ledger_db_path).unwrap();
+        fs::write(ledger_db_path.join("data.mdb"), b"not a database").unwrap();
+        assert!(!check_ledger_db(&ledger_db_path, false).passed);
+    }
+
+    #[test]
+    fn test_report() {
+        let pass = SelfTestCheck::new::<String>("a".to_string(), Ok("ok".to_string()));
+        let fail = SelfTestCheck::new("b".to_string(), Err("broken"));
+        assert!(!fail.passed);
+        assert_eq!(fail.detail, "broken");
+
+        assert!(SelfTestReport::new(vec![pass.clone()]).passed);
+        assert!(!SelfTestReport::new(vec![pass, fail]).passed);
+        assert!(SelfTestReport::new(vec![]).passed);
+    }
+}
@@ -7,38 +7,88 @@
 //! * writes matching transactions to a local DB, organized by subaddress_id
 
 use crate::{
+    account_snapshot::{AccountSnapshot, AccountSnapshotContents, SnapshotSigners},
+    account_store::AccountData,
+    address_book_store::AddressBookEntry,
+    approval_store::{TxApproval, TxApprovalStatus},
+    audit_log_store::{AuditAction, AuditEvent},
+    block_stream::start_block_stream,
+    coin_selection::CoinSelectionStrategy,
+    config::WorkerPoolsConfig,
+    confirmations::BlockConfirmations,
+    consolidation::{ConsolidationPolicy, ConsolidationThread},
     database::Database,
+    deadline::Deadline,
+    encryption::DatabaseSecret,
     error::Error,
-    monitor_store::{MonitorData, MonitorId},
-    payments::{Outlay, TransactionsManager, TxProposal},
+    monitor_store::{MonitorData, MonitorId, MonitorPriority, WatchOnlyKey},
+    notifications::Notifier,
+    payment_queue::{
+        build_payment, PaymentQueue, PaymentQueueThread, QueuedPayment, QueuedPaymentStatus,
+    },
+    payments::{
+        request::PaymentRequest, Outlay, SubmissionVerdict, TransactionsManager, TxProposal,
+        UnsignedTxProposal,
+    },
+    state_archive::{StateArchive, StateArchiveContents},
     sync::SyncThread,
+    tenants::{token_hash, TenantScope, TenantTokens, TokenHash},
+    tls::MutualTlsConfig,
+    transfer_code_store::{TransferCode, TransferCodeStatus},
     utxo_store::{UnspentTxOut, UtxoId},
+    worker_pool::{PoolMetrics, WorkerPool, API_POOL_NAME, TX_BUILD_POOL_NAME},
 };
 
 use common::{
     logger::{log, Logger},
     HashMap,
 };
-use grpc_util::{rpc_internal_error, rpc_logger, send_result};
-use grpcio::{RpcContext, RpcStatus, RpcStatusCode, UnarySink};
-use keys::RistrettoPublic;
+use futures::{sync::oneshot, Future, Sink, Stream};
+use grpc_util::{rpc_internal_error, rpc_invalid_arg_error, rpc_logger, send_result};
+use grpcio::{RpcContext, RpcStatus, RpcStatusCode, ServerStreamingSink, UnarySink, WriteFlags};
+use keys::{CompressedRistrettoPublic, RistrettoPublic};
 use ledger_db::{Ledger, LedgerDB};
 use mc_b58_payloads::payloads::{RequestPayload, TransferPayload};
 use mcconnection::UserTxConnection;
 use mcserial::ReprBytes32;
-use mobilecoind_api::mobilecoind_api_grpc::{create_mobilecoind_api, MobilecoindApi};
+use mobilecoind_api::{
+    mob_uri::MobUri,
+    mobilecoind_api_grpc::{create_mobilecoind_api, MobilecoindApi},
+};
 use protobuf::RepeatedField;
-use std::{convert::TryFrom, sync::Arc};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use transaction::{
     account_keys::{AccountKey, PublicAddress},
+    mob::PicoMob,
     ring_signature::KeyImage,
+    tx::TxHash,
+    tx_out_confirmation_number::TxOutConfirmationNumber,
 };
 use transaction_std::identity::RootIdentity;
 
+/// Maximal number of public keys looked up by a single GetTxOutResultsByPublicKey request.
+const MAX_TX_OUT_RESULTS_PER_REQUEST: usize = 10_000;
+
+/// Maximal number of membership proofs returned by a single GetMembershipProofs request.
+const MAX_MEMBERSHIP_PROOFS_PER_REQUEST: usize = 1_000;
+
+/// Maximal number of blocks whose TxOuts are returned by a single GetTxOutsByBlockRange request.
+const MAX_BLOCKS_PER_TX_OUTS_REQUEST: u64 = 1_000;
+
 pub struct Service {
     /// Sync thread.
     _sync_thread: SyncThread,
 
+    /// Payment queue thread.
+    _payment_queue_thread: PaymentQueueThread,
+
+    /// UTXO consolidation thread, if enabled.
+    _consolidation_thread: Option<ConsolidationThread>,
+
     /// GRPC server.
     _server: grpcio::Server,
 }
@@ -49,12 +99,23 @@ impl Service {
         mobilecoind_db: Database,
         transactions_manager: TransactionsManager<T>,
         port: u16,
-        num_workers: Option<usize>,
+        worker_pools: WorkerPoolsConfig,
+        confirmations: Option<BlockConfirmations>,
+        approval_threshold: Option<PicoMob>,
+        snapshot_signers: SnapshotSigners,
+        notifier: Option<Notifier>,
+        consolidation_policy: Option<ConsolidationPolicy>,
+        tenant_tokens: TenantTokens,
+        tls_config: Option<&MutualTlsConfig>,
         logger: Logger,
     ) -> Self {
+        let num_api_threads = worker_pools.num_api_threads.unwrap_or_else(num_cpus::get);
+        let api_metrics = PoolMetrics::new(API_POOL_NAME);
+        api_metrics.set_num_workers(num_api_threads);
         let env = Arc::new(
             grpcio::EnvBuilder::new()
                 .name_prefix("Mobilecoind-RPC".to_string())
+                .cq_count(num_api_threads)
                 .build(),
         );
 
@@ -62,14 +123,48 @@ impl Service {
         let sync_thread = SyncThread::start(
             ledger_db.clone(),
             mobilecoind_db.clone(),
-            num_workers,
+            worker_pools.num_scan_workers,
+            notifier,
+            transactions_manager.signing_enclave(),
+            logger.clone(),
+        );
+
+        log::info!(logger, "Starting mobilecoind payment queue thread");
+        let payment_queue = PaymentQueue::new();
+        let payment_queue_thread = PaymentQueueThread::start(
+            payment_queue.clone(),
+            transactions_manager.clone(),
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
             logger.clone(),
         );
 
+        let consolidation_thread = consolidation_policy.map(|consolidation_policy| {
+            log::info!(logger, "Starting mobilecoind UTXO consolidation thread");
+            ConsolidationThread::start(
+                consolidation_policy,
+                payment_queue.clone(),
+                transactions_manager.clone(),
+                ledger_db.clone(),
+                mobilecoind_db.clone(),
+                logger.clone(),
+            )
+        });
+
+        log::info!(logger, "Starting mobilecoind transaction building workers");
+        let tx_build_pool = WorkerPool::new(TX_BUILD_POOL_NAME, worker_pools.num_tx_build_workers);
+
         let api = ServiceApi::new(
             transactions_manager,
             ledger_db,
             mobilecoind_db,
+            payment_queue,
+            tx_build_pool,
+            api_metrics,
+            confirmations,
+            approval_threshold,
+            snapshot_signers,
+            tenant_tokens,
             logger.clone(),
         );
 
@@ -81,16 +176,23 @@ impl Service {
 
         // Package service into grpc server
         log::info!(logger, "Starting mobilecoind API Service on port {}", port);
-        let server = grpc_util::run_server(
-            env,
-            vec![mobilecoind_service, health_service],
-            port,
-            &logger,
-        );
+        let services = vec![mobilecoind_service, health_service];
+        let server = match tls_config {
+            Some(tls_config) => grpc_util::run_secure_server(
+                env,
+                services,
+                port,
+                tls_config.server_credentials(),
+                &logger,
+            ),
+            None => grpc_util::run_server(env, services, port, &logger),
+        };
 
         Self {
             _server: server,
             _sync_thread: sync_thread,
+            _payment_queue_thread: payment_queue_thread,
+            _consolidation_thread: consolidation_thread,
         }
     }
 }
@@ -99,6 +201,13 @@ pub struct ServiceApi<T: UserTxConnection + 'static> {
     transactions_manager: TransactionsManager<T>,
     ledger_db: LedgerDB,
     mobilecoind_db: Database,
+    payment_queue: PaymentQueue,
+    tx_build_pool: WorkerPool,
+    api_metrics: PoolMetrics,
+    confirmations: Option<BlockConfirmations>,
+    approval_threshold: Option<PicoMob>,
+    snapshot_signers: SnapshotSigners,
+    tenant_tokens: TenantTokens,
     logger: Logger,
 }
 
@@ -108,6 +217,13 @@ impl<T: UserTxConnection + 'static> Clone for ServiceApi<T> {
             transactions_manager: self.transactions_manager.clone(),
             ledger_db: self.ledger_db.clone(),
             mobilecoind_db: self.mobilecoind_db.clone(),
+            payment_queue: self.payment_queue.clone(),
+            tx_build_pool: self.tx_build_pool.clone(),
+            api_metrics: self.api_metrics.clone(),
+            confirmations: self.confirmations.clone(),
+            approval_threshold: self.approval_threshold,
+            snapshot_signers: self.snapshot_signers.clone(),
+            tenant_tokens: self.tenant_tokens.clone(),
             logger: self.logger.clone(),
         }
     }
@@ -118,12 +234,26 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
         transactions_manager: TransactionsManager<T>,
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
+        payment_queue: PaymentQueue,
+        tx_build_pool: WorkerPool,
+        api_metrics: PoolMetrics,
+        confirmations: Option<BlockConfirmations>,
+        approval_threshold: Option<PicoMob>,
+        snapshot_signers: SnapshotSigners,
+        tenant_tokens: TenantTokens,
         logger: Logger,
     ) -> Self {
         Self {
             transactions_manager,
             ledger_db,
             mobilecoind_db,
+            payment_queue,
+            tx_build_pool,
+            api_metrics,
+            confirmations,
+            approval_threshold,
+            snapshot_signers,
+            tenant_tokens,
             logger,
         }
     }
@@ -131,33 +261,31 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
     fn add_monitor_impl(
         &mut self,
         request: mobilecoind_api::AddMonitorRequest,
+        caller: &Caller,
     ) -> Result<mobilecoind_api::AddMonitorResponse, RpcStatus> {
-        // Get the AccountKey from the GRPC request.
-        let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
-            RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("account_key".to_string()),
-            )
-        })?;
-        let account_key = AccountKey::try_from(proto_account_key)
-            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
-
-        // Populate a new `MonitorData` instance.
-        let data = MonitorData::new(
-            account_key,
-            request.first_subaddress,
-            request.num_subaddresses,
-            request.first_block,
-        )
-        .map_err(|err| rpc_internal_error("monitor_data.new", err, &self.logger))?;
+        let scope = self.tenant_scope(caller)?;
+        let data = self.monitor_data_from_request(&request, &scope)?;
+        self.authorize_new_monitor(&data, &scope)?;
+        let _pruning_guard = self.check_not_pruned("first_block", data.next_block)?;
 
         // Insert into database. If the monitor already exists, we will simply return its id.
-        let id = match self.mobilecoind_db.add_monitor(&data) {
-            Ok(id) => Ok(id),
-            Err(Error::MonitorIdExists) => Ok(MonitorId::from(&data)),
-            Err(err) => Err(err),
-        }
-        .map_err(|err| rpc_internal_error("mobilecoind_db.add_monitor", err, &self.logger))?;
+        let id = if request.account_name.is_empty() {
+            match self.mobilecoind_db.add_monitor(&data) {
+                Ok(id) => Ok(id),
+                Err(Error::MonitorIdExists) => Ok(MonitorId::from(&data)),
+                Err(err) => Err(err),
+            }
+            .map_err(|err| rpc_internal_error("mobilecoind_db.add_monitor", err, &self.logger))?
+        } else {
+            self.authorize_account(&request.account_name, caller)?;
+            self.mobilecoind_db
+                .add_account_monitor(&request.account_name, &data)
+                .map_err(|err| {
+                    rpc_account_error("mobilecoind_db.add_account_monitor", err, &self.logger)
+                })?
+        };
+
+        self.audit(&id, AuditAction::MonitorAdded, caller)?;
 
         // Return success response.
         let mut response = mobilecoind_api::AddMonitorResponse::new();
@@ -165,38 +293,195 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
         Ok(response)
     }
 
+    /// Populates a new `MonitorData` instance from an AddMonitor request, owned by the tenant of
+    /// `scope`.
+    fn monitor_data_from_request(
+        &self,
+        request: &mobilecoind_api::AddMonitorRequest,
+        scope: &TenantScope,
+    ) -> Result<MonitorData, RpcStatus> {
+        // Blocks seen before the account was created cannot hold any of its outputs.
+        let first_block = if request.account_created_at == 0 {
+            request.first_block
+        } else {
+            let first_block_after_creation = self
+                .mobilecoind_db
+                .get_first_block_not_seen_before(request.account_created_at)
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "mobilecoind_db.get_first_block_not_seen_before",
+                        err,
+                        &self.logger,
+                    )
+                })?;
+            request.first_block.max(first_block_after_creation)
+        };
+
+        // With a gap limit, the range may be left for the monitor to discover. Only the first
+        // subaddress is requested then, and the gap limit extends the range from there.
+        let num_subaddresses = if request.num_subaddresses == 0 && request.gap_limit > 0 {
+            1
+        } else {
+            request.num_subaddresses
+        };
+
+        // Populate a new `MonitorData` instance from either the AccountKey or the WatchOnlyKey in
+        // the GRPC request.
+        let data = match (
+            request.account_key.as_ref(),
+            request.watch_only_key.as_ref(),
+        ) {
+            (Some(proto_account_key), None) => {
+                let account_key = AccountKey::try_from(proto_account_key)
+                    .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
+                match self.transactions_manager.signing_enclave() {
+                    // Only the signing enclave gets to keep the spend private key.
+                    Some(signing_enclave) => {
+                        let watch_only_key = WatchOnlyKey::from(&account_key);
+                        let sealed_account_key = signing_enclave
+                            .seal_account_key(account_key)
+                            .map_err(|err| {
+                                rpc_internal_error(
+                                    "signing_enclave.seal_account_key",
+                                    err,
+                                    &self.logger,
+                                )
+                            })?;
+                        MonitorData::new_sealed(
+                            watch_only_key,
+                            sealed_account_key,
+                            request.first_subaddress,
+                            num_subaddresses,
+                            first_block,
+                        )
+                    }
+                    None => MonitorData::new(
+                        account_key,
+                        request.first_subaddress,
+                        num_subaddresses,
+                        first_block,
+                    ),
+                }
+            }
+            (None, Some(proto_watch_only_key)) => {
+                let watch_only_key =
+                    WatchOnlyKey::try_from(proto_watch_only_key).map_err(|err| {
+                        rpc_internal_error("watch_only_key.try_from", err, &self.logger)
+                    })?;
+                MonitorData::new_watch_only(
+                    watch_only_key,
+                    request.first_subaddress,
+                    num_subaddresses,
+                    first_block,
+                )
+            }
+            _ => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("exactly one of account_key and watch_only_key must be set".to_string()),
+                ));
+            }
+        };
+        let mut data =
+            data.map_err(|err| rpc_internal_error("monitor_data.new", err, &self.logger))?;
+        data.set_gap_limit(request.gap_limit);
+        data.denominate_change = request.denominate_change;
+        data.priority = MonitorPriority::from(request.priority) as i32;
+        data.tenant = scope.owner().to_string();
+        data.set_metadata(
+            request.get_labels().clone().into_iter().collect(),
+            request.metadata.clone(),
+        )
+        .map_err(|err| rpc_monitor_error("monitor_data.set_metadata", err, &self.logger))?;
+        Ok(data)
+    }
+
     fn remove_monitor_impl(
         &mut self,
         request: mobilecoind_api::RemoveMonitorRequest,
-    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::RemoveMonitorResponse, RpcStatus> {
         // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        let mut response = mobilecoind_api::RemoveMonitorResponse::new();
 
         // Remove from database.
-        self.mobilecoind_db
-            .remove_monitor(&monitor_id)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.remove_monitor", err, &self.logger)
-            })?;
+        if request.archive {
+            // The archive holds the account key of the monitor, like the ones ExportState returns.
+            self.forbid_tenants(caller, "mobilecoind state")?;
+            let monitor_data =
+                self.mobilecoind_db
+                    .get_monitor_data(&monitor_id)
+                    .map_err(|err| {
+                        rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                    })?;
+            if monitor_data.is_sealed() {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::FAILED_PRECONDITION,
+                    Some("Monitor is sealed and cannot be archived".to_string()),
+                ));
+            }
+
+            let ledger_db = &self.ledger_db;
+            let archive = self
+                .mobilecoind_db
+                .remove_monitor_and_archive(&monitor_id, request.remove_history, |state| {
+                    let contents = StateArchiveContents::new(vec![state], vec![], ledger_db)?;
+                    Ok(StateArchive::new(&contents))
+                })
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "mobilecoind_db.remove_monitor_and_archive",
+                        err,
+                        &self.logger,
+                    )
+                })?;
+
+            self.audit(&monitor_id, AuditAction::MonitorStateExported, caller)?;
+            response.set_archive(mcserial::encode(&archive));
+        } else if request.remove_history {
+            self.mobilecoind_db
+                .remove_monitor_with_history(&monitor_id)
+                .map_err(|err| {
+                    rpc_internal_error(
+                        "mobilecoind_db.remove_monitor_with_history",
+                        err,
+                        &self.logger,
+                    )
+                })?;
+        } else {
+            self.mobilecoind_db
+                .remove_monitor(&monitor_id)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.remove_monitor", err, &self.logger)
+                })?;
+        }
+
+        self.audit(&monitor_id, AuditAction::MonitorRemoved, caller)?;
 
         // Return success response.
-        let response = mobilecoind_api::Empty::new();
         Ok(response)
     }
 
     fn get_monitor_list_impl(
         &mut self,
         _request: mobilecoind_api::Empty,
+        caller: &Caller,
     ) -> Result<mobilecoind_api::GetMonitorListResponse, RpcStatus> {
+        let scope = self.tenant_scope(caller)?;
         let monitor_map: HashMap<MonitorId, MonitorData> =
             self.mobilecoind_db.get_monitor_map().map_err(|err| {
                 rpc_internal_error("mobilecoind_db.get_monitor_store_map", err, &self.logger)
             })?;
 
         let mut response = mobilecoind_api::GetMonitorListResponse::new();
-        for id in monitor_map.keys() {
-            response.mut_monitor_id_list().push(id.to_vec());
+        for (id, data) in monitor_map.iter() {
+            if scope.contains(&data.tenant) {
+                response.mut_monitor_id_list().push(id.to_vec());
+            }
         }
         Ok(response)
     }
@@ -204,6 +489,7 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
     fn get_monitor_status_impl(
         &mut self,
         request: mobilecoind_api::GetMonitorStatusRequest,
+        caller: &Caller,
     ) -> Result<mobilecoind_api::GetMonitorStatusResponse, RpcStatus> {
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
@@ -214,1042 +500,4841 @@ impl<T: UserTxConnection + 'static> ServiceApi<T> {
             .map_err(|err| {
                 rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
             })?;
+        check_tenant(&self.tenant_scope(caller)?, &data.tenant, "monitor_id")?;
+
+        // The status includes the monitor's account key.
+        self.audit(&monitor_id, AuditAction::MonitorExported, caller)?;
 
         let mut status = mobilecoind_api::MonitorStatus::new();
-        status.set_account_key(mobilecoind_api::AccountKey::from(&data.account_key));
+        if let Some(account_key) = data.account_key.as_ref() {
+            status.set_account_key(account_key.into());
+        }
+        if let Some(watch_only_key) = data.watch_only_key.as_ref() {
+            status.set_watch_only_key(watch_only_key.into());
+        }
+        status.set_sealed(data.is_sealed());
         status.set_first_subaddress(data.first_subaddress);
         status.set_num_subaddresses(data.num_subaddresses);
         status.set_first_block(data.first_block);
         status.set_next_block(data.next_block);
+        status.set_gap_limit(data.gap_limit);
+        status.set_num_extended_subaddresses(data.num_extended_subaddresses);
+        status.set_denominate_change(data.denominate_change);
+        status.set_priority(data.get_priority().into());
+        status.set_labels(data.labels.into_iter().collect());
+        status.set_metadata(data.metadata);
+        if let Some(confirmations) = self.confirmations.as_ref() {
+            status.set_confirmed_next_block(
+                confirmations.confirmed_block_count().min(data.next_block),
+            );
+        }
 
         let mut response = mobilecoind_api::GetMonitorStatusResponse::new();
         response.set_status(status);
         Ok(response)
     }
 
-    fn get_unspent_tx_out_list_impl(
+    fn set_monitor_priority_impl(
         &mut self,
-        request: mobilecoind_api::GetUnspentTxOutListRequest,
-    ) -> Result<mobilecoind_api::GetUnspentTxOutListResponse, RpcStatus> {
-        // Get MonitorId from from the GRPC request.
+        request: mobilecoind_api::SetMonitorPriorityRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
 
-        // Get UnspentTxOuts.
-        let utxos = self
-            .mobilecoind_db
-            .get_utxos_for_subaddress(&monitor_id, request.subaddress_index)
+        // The sync thread picks up the new priority the next time it polls the monitors.
+        let audit_event =
+            self.audit_event(&monitor_id, AuditAction::MonitorPriorityChanged, caller);
+        self.mobilecoind_db
+            .set_monitor_priority(&monitor_id, request.priority.into(), &audit_event)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+                rpc_internal_error("mobilecoind_db.set_monitor_priority", err, &self.logger)
             })?;
 
-        // Convert to protos.
-        let proto_utxos: Vec<mobilecoind_api::UnspentTxOut> =
-            utxos.iter().map(|utxo| utxo.into()).collect();
+        Ok(mobilecoind_api::Empty::new())
+    }
 
-        // Returrn response.
-        let mut response = mobilecoind_api::GetUnspentTxOutListResponse::new();
-        response.set_output_list(RepeatedField::from_vec(proto_utxos));
-        Ok(response)
+    fn update_monitor_metadata_impl(
+        &mut self,
+        request: mobilecoind_api::UpdateMonitorMetadataRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        let audit_event =
+            self.audit_event(&monitor_id, AuditAction::MonitorMetadataUpdated, caller);
+        self.mobilecoind_db
+            .set_monitor_metadata(
+                &monitor_id,
+                request.get_labels().clone().into_iter().collect(),
+                request.metadata,
+                &audit_event,
+            )
+            .map_err(|err| {
+                rpc_monitor_error("mobilecoind_db.set_monitor_metadata", err, &self.logger)
+            })?;
+
+        Ok(mobilecoind_api::Empty::new())
     }
 
-    fn generate_entropy_impl(
+    fn rescan_monitor_impl(
         &mut self,
-        _request: mobilecoind_api::Empty,
-    ) -> Result<mobilecoind_api::GenerateEntropyResponse, RpcStatus> {
-        let mut rng = rand::thread_rng();
-        let root_id = RootIdentity::random(&mut rng, None);
-        let mut response = mobilecoind_api::GenerateEntropyResponse::new();
-        response.set_entropy(root_id.root_entropy.to_vec());
-        Ok(response)
+        request: mobilecoind_api::RescanMonitorRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+        let _pruning_guard = self.check_not_pruned("from_block", request.from_block)?;
+
+        // The sync thread picks up the monitor the next time it polls the monitors. A block it was
+        // processing for the monitor in the meantime is rejected by the database.
+        let audit_event = self.audit_event(&monitor_id, AuditAction::MonitorRescanned, caller);
+        let ledger_db = &self.ledger_db;
+        self.mobilecoind_db
+            .rescan_monitor(&monitor_id, request.from_block, &audit_event, |tx_out| {
+                // Outputs that are no longer in the ledger were found in blocks removed from it by
+                // a resync, which are scanned again.
+                match ledger_db.get_tx_out_index_by_public_key(&tx_out.public_key) {
+                    Ok(tx_out_index) => {
+                        Ok(ledger_db.get_block_index_by_tx_out_index(tx_out_index)?)
+                    }
+                    Err(ledger_db::Error::NotFound) => Ok(u64::MAX),
+                    Err(err) => Err(err.into()),
+                }
+            })
+            .map_err(|err| rpc_monitor_error("mobilecoind_db.rescan_monitor", err, &self.logger))?;
+
+        Ok(mobilecoind_api::Empty::new())
     }
 
-    fn get_account_key_impl(
+    fn get_monitor_audit_log_impl(
         &mut self,
-        request: mobilecoind_api::GetAccountKeyRequest,
-    ) -> Result<mobilecoind_api::GetAccountKeyResponse, RpcStatus> {
-        // Get the entropy.
-        if request.get_entropy().len() != 32 {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("entropy".to_string()),
-            ));
-        }
-        let mut root_entropy = [0u8; 32];
-        root_entropy.copy_from_slice(request.get_entropy());
+        request: mobilecoind_api::GetMonitorAuditLogRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetMonitorAuditLogResponse, RpcStatus> {
+        // An empty monitor_id means entries of all monitors.
+        let opt_monitor_id = if request.monitor_id.is_empty() {
+            None
+        } else {
+            Some(MonitorId::try_from(&request.monitor_id).map_err(|err| {
+                rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger)
+            })?)
+        };
+        self.authorize_optional_monitor(opt_monitor_id.as_ref(), caller)?;
 
-        // Use root entropy to construct AccountKey.
-        let root_id = RootIdentity {
-            root_entropy,
-            fog_url: None,
+        // A max_entries of zero means no limit.
+        let max_entries = if request.max_entries == 0 {
+            usize::max_value()
+        } else {
+            request.max_entries as usize
         };
 
-        // TODO: change to production AccountKey derivation
-        let account_key = AccountKey::from(&root_id);
+        let audit_events = self
+            .mobilecoind_db
+            .get_audit_log(opt_monitor_id.as_ref(), request.first_index, max_entries)
+            .map_err(|err| rpc_internal_error("mobilecoind_db.get_audit_log", err, &self.logger))?;
 
-        // Return response.
-        let mut response = mobilecoind_api::GetAccountKeyResponse::new();
-        response.set_account_key((&account_key).into());
+        let mut response = mobilecoind_api::GetMonitorAuditLogResponse::new();
+        response.set_event_list(RepeatedField::from_vec(
+            audit_events
+                .iter()
+                .map(mobilecoind_api::MonitorAuditEvent::from)
+                .collect(),
+        ));
         Ok(response)
     }
 
-    fn get_public_address_impl(
+    fn export_account_snapshot_impl(
         &mut self,
-        request: mobilecoind_api::GetPublicAddressRequest,
-    ) -> Result<mobilecoind_api::GetPublicAddressResponse, RpcStatus> {
-        // Get MonitorId from from the GRPC request.
+        request: mobilecoind_api::ExportAccountSnapshotRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ExportAccountSnapshotResponse, RpcStatus> {
+        let signer = self.snapshot_signers.signer.clone().ok_or_else(|| {
+            RpcStatus::new(
+                RpcStatusCode::FAILED_PRECONDITION,
+                Some("No snapshot signer key configured".to_string()),
+            )
+        })?;
+
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
 
-        // Get monitor data.
-        let data = self
+        let (data, utxos) = self
             .mobilecoind_db
-            .get_monitor_data(&monitor_id)
+            .get_monitor_utxos(&monitor_id)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                rpc_internal_error("mobilecoind_db.get_monitor_utxos", err, &self.logger)
             })?;
+        check_tenant(&self.tenant_scope(caller)?, &data.tenant, "monitor_id")?;
 
-        // Verify subaddress falls in the range we are monitoring.
-        if !data
-            .subaddress_indexes()
-            .contains(&request.subaddress_index)
-        {
+        // Snapshots restore an account from its account key, which watch-only and sealed monitors
+        // do not hold.
+        if data.account_key.is_none() {
             return Err(RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("subaddress_index".to_string()),
+                RpcStatusCode::FAILED_PRECONDITION,
+                Some("Watch-only and sealed monitors cannot be exported".to_string()),
             ));
         }
 
-        // Get the subaddress.
-        let subaddress = data.account_key.subaddress(request.subaddress_index);
+        let contents =
+            AccountSnapshotContents::new(&data, utxos, &self.ledger_db).map_err(|err| {
+                rpc_internal_error("account_snapshot_contents.new", err, &self.logger)
+            })?;
+        let snapshot = AccountSnapshot::sign(&contents, &signer)
+            .map_err(|err| rpc_internal_error("account_snapshot.sign", err, &self.logger))?;
 
-        // Return response.
-        let mut response = mobilecoind_api::GetPublicAddressResponse::new();
-        response.set_public_address((&subaddress).into());
+        // The snapshot holds the monitor's outputs, which reveal its balance.
+        self.audit(&monitor_id, AuditAction::MonitorSnapshotExported, caller)?;
+
+        let mut response = mobilecoind_api::ExportAccountSnapshotResponse::new();
+        response.set_snapshot(mcserial::encode(&snapshot));
+        response.set_next_block(contents.next_block);
         Ok(response)
     }
 
-    fn read_request_code_impl(
+    fn import_account_snapshot_impl(
         &mut self,
-        request: mobilecoind_api::ReadRequestCodeRequest,
-    ) -> Result<mobilecoind_api::ReadRequestCodeResponse, RpcStatus> {
-        let request_payload = RequestPayload::decode(request.get_b58_code())
-            .map_err(|err| rpc_internal_error("RequestPayload.decode", err, &self.logger))?;
+        request: mobilecoind_api::ImportAccountSnapshotRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ImportAccountSnapshotResponse, RpcStatus> {
+        // Imported monitors hold their account key, which the signing enclave should hold instead.
+        if self.transactions_manager.signing_enclave().is_some() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::FAILED_PRECONDITION,
+                Some("Snapshots cannot be imported while the signing enclave is used".to_string()),
+            ));
+        }
 
-        let mut response = mobilecoind_api::ReadRequestCodeResponse::new();
-        response.set_receiver(mobilecoind_api::PublicAddress::from(&PublicAddress::from(
-            &request_payload,
-        )));
-        response.set_value(request_payload.value);
-        response.set_memo(request_payload.memo);
-        Ok(response)
-    }
+        let scope = self.tenant_scope(caller)?;
 
-    fn get_request_code_impl(
-        &mut self,
-        request: mobilecoind_api::GetRequestCodeRequest,
-    ) -> Result<mobilecoind_api::GetRequestCodeResponse, RpcStatus> {
-        let receiver = PublicAddress::try_from(request.get_receiver())
-            .map_err(|err| rpc_internal_error("PublicAddress.try_from", err, &self.logger))?;
+        // Get the AccountKey from the GRPC request.
+        let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("account_key".to_string()),
+            )
+        })?;
+        let account_key = AccountKey::try_from(proto_account_key)
+            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
 
-        let view_key = receiver.view_public_key().to_bytes();
-        let spend_key = receiver.spend_public_key().to_bytes();
-        let fog_url = receiver.fog_url().unwrap_or("");
+        let snapshot: AccountSnapshot =
+            mcserial::decode(request.get_snapshot()).map_err(|err| {
+                RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some(format!("snapshot: {}", err)),
+                )
+            })?;
+        let contents = snapshot
+            .verify(&self.snapshot_signers)
+            .map_err(|err| rpc_snapshot_error("account_snapshot.verify", err, &self.logger))?;
+        let mut data = contents.monitor_data(account_key).map_err(|err| {
+            rpc_snapshot_error("account_snapshot_contents.monitor_data", err, &self.logger)
+        })?;
+        data.tenant = scope.owner().to_string();
+        let _pruning_guard = self.check_not_pruned("snapshot", data.next_block)?;
+        contents
+            .verify_against_ledger(&data, &self.ledger_db)
+            .map_err(|err| {
+                rpc_snapshot_error(
+                    "account_snapshot_contents.verify_against_ledger",
+                    err,
+                    &self.logger,
+                )
+            })?;
 
-        let payload = RequestPayload::new_v3(
-            &view_key,
-            &spend_key,
-            fog_url,
-            request.get_value(),
-            request.get_memo(),
-        )
-        .map_err(|err| rpc_internal_error("RequestPayload.new_v3", err, &self.logger))?;
-        let b58_code = payload.encode();
+        let monitor_id = self
+            .mobilecoind_db
+            .import_monitor(&data, &contents.utxos)
+            .map_err(|err| match err {
+                Error::MonitorIdExists => RpcStatus::new(
+                    RpcStatusCode::ALREADY_EXISTS,
+                    Some("monitor_id".to_string()),
+                ),
+                err => rpc_internal_error("mobilecoind_db.import_monitor", err, &self.logger),
+            })?;
 
-        let mut response = mobilecoind_api::GetRequestCodeResponse::new();
-        response.set_b58_code(b58_code);
+        self.audit(&monitor_id, AuditAction::MonitorSnapshotImported, caller)?;
+
+        let mut response = mobilecoind_api::ImportAccountSnapshotResponse::new();
+        response.set_monitor_id(monitor_id.to_vec());
+        response.set_next_block(data.next_block);
         Ok(response)
     }
 
-    fn read_transfer_code_impl(
+    fn create_account_impl(
         &mut self,
-        request: mobilecoind_api::ReadTransferCodeRequest,
-    ) -> Result<mobilecoind_api::ReadTransferCodeResponse, RpcStatus> {
-        let transfer_payload = TransferPayload::decode(request.get_b58_code())
-            .map_err(|err| rpc_internal_error("TransferPayload.decode", err, &self.logger))?;
+        request: mobilecoind_api::CreateAccountRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::CreateAccountResponse, RpcStatus> {
+        let scope = self.tenant_scope(caller)?;
+        let mut account = AccountData::new(&request.name)
+            .map_err(|err| rpc_account_error("account_data.new", err, &self.logger))?;
+        account.tenant = scope.owner().to_string();
+        let monitors = request
+            .monitors
+            .iter()
+            .map(|monitor| {
+                let data = self.monitor_data_from_request(monitor, &scope)?;
+                self.authorize_new_monitor(&data, &scope)?;
+                Ok(data)
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+        let first_block = monitors
+            .iter()
+            .map(|data| data.next_block)
+            .min()
+            .unwrap_or(u64::MAX);
+        let _pruning_guard = self.check_not_pruned("monitors", first_block)?;
 
-        let tx_public_key = RistrettoPublic::try_from(&transfer_payload.utxo)
-            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+        let monitor_ids = self
+            .mobilecoind_db
+            .create_account(&account, &monitors)
+            .map_err(|err| rpc_account_error("mobilecoind_db.create_account", err, &self.logger))?;
 
-        let mut response = mobilecoind_api::ReadTransferCodeResponse::new();
-        response.set_entropy(transfer_payload.entropy.to_vec());
-        response.set_tx_public_key((&tx_public_key).into());
-        response.set_memo(transfer_payload.memo);
+        for monitor_id in &monitor_ids {
+            self.audit(monitor_id, AuditAction::MonitorAdded, caller)?;
+        }
+
+        let account = self
+            .mobilecoind_db
+            .get_account(&account.name)
+            .map_err(|err| rpc_internal_error("mobilecoind_db.get_account", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::CreateAccountResponse::new();
+        response.set_account(self.account_with_balance(&account)?);
         Ok(response)
     }
 
-    fn get_transfer_code_impl(
+    fn list_accounts_impl(
         &mut self,
-        request: mobilecoind_api::GetTransferCodeRequest,
-    ) -> Result<mobilecoind_api::GetTransferCodeResponse, RpcStatus> {
-        let mut entropy: [u8; 32] = [0; 32];
-        if request.entropy.len() != entropy.len() {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("entropy".to_string()),
-            ));
+        _request: mobilecoind_api::Empty,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ListAccountsResponse, RpcStatus> {
+        let scope = self.tenant_scope(caller)?;
+        let accounts = self.mobilecoind_db.get_account_list().map_err(|err| {
+            rpc_internal_error("mobilecoind_db.get_account_list", err, &self.logger)
+        })?;
+
+        let mut response = mobilecoind_api::ListAccountsResponse::new();
+        for account in accounts
+            .iter()
+            .filter(|account| scope.contains(&account.tenant))
+        {
+            response
+                .mut_accounts()
+                .push(self.account_with_balance(account)?);
         }
-        entropy.copy_from_slice(request.get_entropy());
+        Ok(response)
+    }
 
-        let tx_public_key = RistrettoPublic::try_from(request.get_tx_public_key())
-            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+    fn remove_account_impl(
+        &mut self,
+        request: mobilecoind_api::RemoveAccountRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.authorize_account(&request.name, caller)?;
+        let account = self
+            .mobilecoind_db
+            .remove_account(&request.name)
+            .map_err(|err| rpc_account_error("mobilecoind_db.remove_account", err, &self.logger))?;
 
-        let payload =
-            TransferPayload::new_v1(&entropy, &tx_public_key.to_bytes(), request.get_memo())
-                .map_err(|err| rpc_internal_error("TransferPayload.new_v1", err, &self.logger))?;
+        for monitor_id in &account.monitor_ids {
+            self.audit(monitor_id, AuditAction::MonitorRemoved, caller)?;
+        }
 
-        let b58_code = payload.encode();
+        Ok(mobilecoind_api::Empty::new())
+    }
 
-        let mut response = mobilecoind_api::GetTransferCodeResponse::new();
-        response.set_b58_code(b58_code);
-        Ok(response)
+    fn add_address_book_entry_impl(
+        &mut self,
+        request: mobilecoind_api::AddAddressBookEntryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.forbid_tenants(caller, "address book")?;
+        let entry = self.address_book_entry_from_request(request.get_entry())?;
+        self.mobilecoind_db
+            .add_address_book_entry(&entry)
+            .map_err(|err| {
+                rpc_address_book_error("mobilecoind_db.add_address_book_entry", err, &self.logger)
+            })?;
+        Ok(mobilecoind_api::Empty::new())
     }
 
-    fn generate_tx_impl(
+    fn update_address_book_entry_impl(
         &mut self,
-        request: mobilecoind_api::GenerateTxRequest,
-    ) -> Result<mobilecoind_api::GenerateTxResponse, RpcStatus> {
-        // Get sender monitor id from request.
-        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
-            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        request: mobilecoind_api::UpdateAddressBookEntryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.forbid_tenants(caller, "address book")?;
+        let entry = self.address_book_entry_from_request(request.get_entry())?;
+        self.mobilecoind_db
+            .update_address_book_entry(&entry)
+            .map_err(|err| {
+                rpc_address_book_error(
+                    "mobilecoind_db.update_address_book_entry",
+                    err,
+                    &self.logger,
+                )
+            })?;
+        Ok(mobilecoind_api::Empty::new())
+    }
 
-        // Get monitor data for this monitor.
-        let sender_monitor_data = self
+    fn get_address_book_entry_impl(
+        &mut self,
+        request: mobilecoind_api::GetAddressBookEntryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetAddressBookEntryResponse, RpcStatus> {
+        self.forbid_tenants(caller, "address book")?;
+        let entry = self
             .mobilecoind_db
-            .get_monitor_data(&sender_monitor_id)
+            .get_address_book_entry(&request.label)
             .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                rpc_address_book_error("mobilecoind_db.get_address_book_entry", err, &self.logger)
             })?;
 
-        // Check that change_subaddress is covered by this monitor.
-        if !sender_monitor_data
-            .subaddress_indexes()
-            .contains(&request.change_subaddress)
-        {
+        let mut response = mobilecoind_api::GetAddressBookEntryResponse::new();
+        response.set_entry((&entry).into());
+        Ok(response)
+    }
+
+    fn get_address_book_impl(
+        &mut self,
+        _request: mobilecoind_api::Empty,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetAddressBookResponse, RpcStatus> {
+        self.forbid_tenants(caller, "address book")?;
+        let entries = self.mobilecoind_db.get_address_book().map_err(|err| {
+            rpc_internal_error("mobilecoind_db.get_address_book", err, &self.logger)
+        })?;
+
+        let mut response = mobilecoind_api::GetAddressBookResponse::new();
+        response.set_entry_list(RepeatedField::from_vec(
+            entries.iter().map(|entry| entry.into()).collect(),
+        ));
+        Ok(response)
+    }
+
+    fn remove_address_book_entry_impl(
+        &mut self,
+        request: mobilecoind_api::RemoveAddressBookEntryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.forbid_tenants(caller, "address book")?;
+        self.mobilecoind_db
+            .remove_address_book_entry(&request.label)
+            .map_err(|err| {
+                rpc_address_book_error(
+                    "mobilecoind_db.remove_address_book_entry",
+                    err,
+                    &self.logger,
+                )
+            })?;
+        Ok(mobilecoind_api::Empty::new())
+    }
+
+    /// Validates an address book entry sent by a client.
+    fn address_book_entry_from_request(
+        &self,
+        entry: &mobilecoind_api::AddressBookEntry,
+    ) -> Result<AddressBookEntry, RpcStatus> {
+        let public_address = PublicAddress::try_from(entry.get_public_address())
+            .map_err(|err| rpc_internal_error("PublicAddress.try_from", err, &self.logger))?;
+
+        AddressBookEntry::new(&entry.label, &public_address, &entry.notes)
+            .map_err(|err| rpc_address_book_error("address_book_entry.new", err, &self.logger))
+    }
+
+    /// Converts an outlay sent by a client, resolving address book labels to their address.
+    fn outlay_from_request(
+        &self,
+        outlay: &mobilecoind_api::Outlay,
+        caller: &Caller,
+    ) -> Result<Outlay, RpcStatus> {
+        if outlay.receiver_label.is_empty() {
+            return Outlay::try_from(outlay)
+                .map_err(|err| rpc_internal_error("outlay.try_from", err, &self.logger));
+        }
+
+        if outlay.has_receiver() {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
-                Some("change_subaddress".to_string()),
+                Some("outlay_list: receiver and receiver_label are mutually exclusive".to_string()),
             ));
         }
+        self.forbid_tenants(caller, "address book")?;
 
-        // Get the list of potential inputs passed to.
-        let input_list: Vec<UnspentTxOut> = request
-            .get_input_list()
-            .iter()
-            .enumerate()
-            .map(|(i, proto_utxo)| {
-                // Proto -> Rust struct conversion.
-                let utxo = UnspentTxOut::try_from(proto_utxo).map_err(|err| {
-                    rpc_internal_error("unspent_tx_out.try_from", err, &self.logger)
-                })?;
+        let entry = self
+            .mobilecoind_db
+            .get_address_book_entry(&outlay.receiver_label)
+            .map_err(|err| {
+                rpc_address_book_error("mobilecoind_db.get_address_book_entry", err, &self.logger)
+            })?;
 
-                // Verify this output belongs to the monitor.
-                let subaddress_id = self
-                    .mobilecoind_db
-                    .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
+        Ok(Outlay {
+            value: outlay.value,
+            receiver: entry.public_address(),
+        })
+    }
+
+    /// Converts an account to its API representation, which includes the balance of all of its
+    /// monitors.
+    fn account_with_balance(
+        &self,
+        account: &AccountData,
+    ) -> Result<mobilecoind_api::Account, RpcStatus> {
+        let mut balance = 0u64;
+        for monitor_id in &account.monitor_ids {
+            let (_data, utxos) =
+                self.mobilecoind_db
+                    .get_monitor_utxos(monitor_id)
                     .map_err(|err| {
-                        rpc_internal_error(
-                            "mobilecoind_db.get_subaddress_id_by_utxo_id",
-                            err,
-                            &self.logger,
-                        )
+                        rpc_internal_error("mobilecoind_db.get_monitor_utxos", err, &self.logger)
                     })?;
+            balance = utxos
+                .iter()
+                .fold(balance, |sum, utxo| sum.saturating_add(utxo.value));
+        }
 
-                if subaddress_id.monitor_id != sender_monitor_id {
-                    return Err(RpcStatus::new(
-                        RpcStatusCode::INVALID_ARGUMENT,
-                        Some(format!("input_list.{}", i)),
-                    ));
-                }
+        let mut dst = mobilecoind_api::Account::new();
+        dst.set_name(account.name.clone());
+        dst.set_monitor_ids(RepeatedField::from_vec(
+            account.monitor_ids.iter().map(|id| id.to_vec()).collect(),
+        ));
+        dst.set_created_at(account.created_at);
+        dst.set_balance(balance);
+        Ok(dst)
+    }
 
-                // Success.
-                Ok(utxo)
-            })
-            .collect::<Result<Vec<UnspentTxOut>, RpcStatus>>()?;
+    /// An audit log entry for an administrative action against a monitor, for database methods
+    /// that record it in the same transaction as the action.
+    fn audit_event(
+        &self,
+        monitor_id: &MonitorId,
+        action: AuditAction,
+        caller: &Caller,
+    ) -> AuditEvent {
+        AuditEvent::new(monitor_id, action, &caller.identity, &caller.peer)
+    }
 
-        // Get the list of outlays.
-        let outlays: Vec<Outlay> = request
-            .get_outlay_list()
-            .iter()
-            .map(|outlay_proto| {
-                Outlay::try_from(outlay_proto)
-                    .map_err(|err| rpc_internal_error("outlay.try_from", err, &self.logger))
-            })
-            .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
+    /// Record an administrative action against a monitor in the audit log.
+    fn audit(
+        &self,
+        monitor_id: &MonitorId,
+        action: AuditAction,
+        caller: &Caller,
+    ) -> Result<(), RpcStatus> {
+        let audit_event = self.audit_event(monitor_id, action, caller);
+        self.mobilecoind_db
+            .append_audit_event(&audit_event)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.append_audit_event", err, &self.logger)
+            })?;
+        Ok(())
+    }
 
-        // Attempt to construct a transaction.
-        let tx_proposal = self
-            .transactions_manager
-            .build_transaction(
-                &sender_monitor_id,
-                request.change_subaddress,
-                &input_list,
-                &outlays,
-                request.fee,
-                request.tombstone,
-            )
+    /// The monitors and accounts the caller has access to.
+    fn tenant_scope(&self, caller: &Caller) -> Result<TenantScope, RpcStatus> {
+        self.tenant_tokens
+            .scope(caller.token_hash.as_ref())
+            .map_err(|err| RpcStatus::new(RpcStatusCode::UNAUTHENTICATED, Some(err.to_string())))
+    }
+
+    /// Makes sure a monitor belongs to the caller's tenant, if mobilecoind is shared by tenants.
+    fn authorize_monitor(&self, monitor_id: &MonitorId, caller: &Caller) -> Result<(), RpcStatus> {
+        let scope = self.tenant_scope(caller)?;
+        if scope == TenantScope::All {
+            return Ok(());
+        }
+
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(monitor_id)
             .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
             })?;
+        check_tenant(&scope, &data.tenant, "monitor_id")
+    }
 
-        // Success.
-        let mut response = mobilecoind_api::GenerateTxResponse::new();
-        response.set_tx_proposal((&tx_proposal).into());
-        Ok(response)
+    /// Listing the records of all monitors is reserved to callers that see every monitor. Tenants
+    /// have to ask for the records of one of their monitors.
+    fn authorize_optional_monitor(
+        &self,
+        opt_monitor_id: Option<&MonitorId>,
+        caller: &Caller,
+    ) -> Result<(), RpcStatus> {
+        match opt_monitor_id {
+            Some(monitor_id) => self.authorize_monitor(monitor_id, caller),
+            None => match self.tenant_scope(caller)? {
+                TenantScope::All => Ok(()),
+                TenantScope::Tenant(_) => Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("monitor_id: required for tenants".to_string()),
+                )),
+            },
+        }
     }
 
-    fn generate_optimization_tx_impl(
+    /// Monitor ids only depend on the monitor's keys, so adding a monitor again returns the
+    /// existing one. Makes sure this does not hand a monitor of another tenant to the caller.
+    fn authorize_new_monitor(
+        &self,
+        data: &MonitorData,
+        scope: &TenantScope,
+    ) -> Result<(), RpcStatus> {
+        match self.mobilecoind_db.get_monitor_data(&MonitorId::from(data)) {
+            Ok(existing) => check_tenant(scope, &existing.tenant, "monitor_id"),
+            Err(Error::MonitorIdNotFound) => Ok(()),
+            Err(err) => Err(rpc_internal_error(
+                "mobilecoind_db.get_monitor_data",
+                err,
+                &self.logger,
+            )),
+        }
+    }
+
+    /// Makes sure a monitor can scan the ledger from `block_index`, since the contents of pruned
+    /// blocks are no longer available. The ledger is not pruned further until the returned guard
+    /// is dropped, so callers hold it until the monitor is stored.
+    fn check_not_pruned(
+        &self,
+        argument: &str,
+        block_index: u64,
+    ) -> Result<MutexGuard<()>, RpcStatus> {
+        let pruning_guard = self.mobilecoind_db.lock_pruning();
+        let num_pruned_blocks = self
+            .ledger_db
+            .num_pruned_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_pruned_blocks", err, &self.logger))?;
+        if block_index < num_pruned_blocks {
+            return Err(RpcStatus::new(
+                RpcStatusCode::FAILED_PRECONDITION,
+                Some(format!(
+                    "{}: the contents of blocks before {} were pruned",
+                    argument, num_pruned_blocks
+                )),
+            ));
+        }
+        Ok(pruning_guard)
+    }
+
+    /// Makes sure an account belongs to the caller's tenant, if mobilecoind is shared by tenants.
+    fn authorize_account(&self, name: &str, caller: &Caller) -> Result<(), RpcStatus> {
+        let scope = self.tenant_scope(caller)?;
+        if scope == TenantScope::All {
+            return Ok(());
+        }
+
+        let account = self
+            .mobilecoind_db
+            .get_account(name)
+            .map_err(|err| rpc_account_error("mobilecoind_db.get_account", err, &self.logger))?;
+        check_tenant(&scope, &account.tenant, "name")
+    }
+
+    /// Makes sure the caller's tenant owns the monitor a transaction awaiting approval spends
+    /// from.
+    fn authorize_tx_approval(
+        &self,
+        tx_prefix_hash: &TxHash,
+        caller: &Caller,
+    ) -> Result<(), RpcStatus> {
+        if self.tenant_scope(caller)? == TenantScope::All {
+            return Ok(());
+        }
+
+        let tx_approval = self
+            .mobilecoind_db
+            .get_tx_approval(tx_prefix_hash)
+            .map_err(|err| {
+                rpc_approval_error("mobilecoind_db.get_tx_approval", err, &self.logger)
+            })?;
+        self.authorize_monitor(&tx_approval.monitor_id, caller)
+    }
+
+    /// Makes sure none of the outputs a transaction spends belong to a monitor of another tenant.
+    /// Outputs no monitor tracks are not checked, since mobilecoind merely relays their spending.
+    fn authorize_utxos(&self, utxos: &[UnspentTxOut], caller: &Caller) -> Result<(), RpcStatus> {
+        if self.tenant_scope(caller)? == TenantScope::All {
+            return Ok(());
+        }
+
+        for (i, utxo) in utxos.iter().enumerate() {
+            match self
+                .mobilecoind_db
+                .get_subaddress_id_by_utxo_id(&UtxoId::from(utxo))
+            {
+                Ok(subaddress_id) => self
+                    .authorize_monitor(&subaddress_id.monitor_id, caller)
+                    .map_err(|_| {
+                        RpcStatus::new(
+                            RpcStatusCode::PERMISSION_DENIED,
+                            Some(format!("tx_proposal.utxos.{}: owned by another tenant", i)),
+                        )
+                    })?,
+                Err(Error::UtxoIdNotFound) => {}
+                Err(err) => {
+                    return Err(rpc_internal_error(
+                        "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                        err,
+                        &self.logger,
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects tenants from features all callers share.
+    fn forbid_tenants(&self, caller: &Caller, feature: &str) -> Result<(), RpcStatus> {
+        match self.tenant_scope(caller)? {
+            TenantScope::All => Ok(()),
+            TenantScope::Tenant(_) => Err(RpcStatus::new(
+                RpcStatusCode::PERMISSION_DENIED,
+                Some(format!("The {} is shared by all tenants", feature)),
+            )),
+        }
+    }
+
+    fn get_unspent_tx_out_list_impl(
         &mut self,
-        request: mobilecoind_api::GenerateOptimizationTxRequest,
-    ) -> Result<mobilecoind_api::GenerateOptimizationTxResponse, RpcStatus> {
-        // Get monitor id from request.
+        request: mobilecoind_api::GetUnspentTxOutListRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetUnspentTxOutListResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
         let monitor_id = MonitorId::try_from(&request.monitor_id)
             .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        // An empty continuation token starts from the first output.
+        let after = if request.continuation_token.is_empty() {
+            None
+        } else {
+            Some(
+                UtxoId::try_from(&request.continuation_token).map_err(|err| {
+                    rpc_internal_error("utxo_id.try_from.bytes", err, &self.logger)
+                })?,
+            )
+        };
 
-        // Generate optimization tx.
-        let tx_proposal = self
-            .transactions_manager
-            .generate_optimization_tx(&monitor_id, request.subaddress)
+        // A max_entries of zero means no limit.
+        let max_entries = if request.max_entries == 0 {
+            usize::max_value()
+        } else {
+            request.max_entries as usize
+        };
+
+        // Get UnspentTxOuts.
+        let (utxos, next) = self
+            .mobilecoind_db
+            .get_utxos_page_for_subaddress(
+                &monitor_id,
+                request.subaddress_index,
+                after.as_ref(),
+                max_entries,
+            )
             .map_err(|err| {
                 rpc_internal_error(
-                    "transactions_manager.generate_optimization_tx",
+                    "mobilecoind_db.get_utxos_page_for_subaddress",
                     err,
                     &self.logger,
                 )
             })?;
 
-        // Success.
-        let mut response = mobilecoind_api::GenerateOptimizationTxResponse::new();
-        response.set_tx_proposal((&tx_proposal).into());
+        // Convert to protos.
+        let proto_utxos: Vec<mobilecoind_api::UnspentTxOut> =
+            utxos.iter().map(|utxo| utxo.into()).collect();
+
+        // Returrn response.
+        let mut response = mobilecoind_api::GetUnspentTxOutListResponse::new();
+        response.set_output_list(RepeatedField::from_vec(proto_utxos));
+        if let Some(next) = next {
+            response.set_continuation_token(next.to_vec());
+        }
         Ok(response)
     }
 
-    fn generate_transfer_code_tx_impl(
+    fn update_utxo_memo_impl(
         &mut self,
-        request: mobilecoind_api::GenerateTransferCodeTxRequest,
-    ) -> Result<mobilecoind_api::GenerateTransferCodeTxResponse, RpcStatus> {
-        // Generate entropy.
-        let entropy_response = self.generate_entropy_impl(mobilecoind_api::Empty::new())?;
-        let entropy = entropy_response.get_entropy().to_vec();
-
-        let mut entropy_bytes = [0; 32];
-        if entropy.len() != entropy_bytes.len() {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INTERNAL,
-                Some("entropy returned was not 32 bytes".to_owned()),
-            ));
-        }
-        entropy_bytes.copy_from_slice(&entropy);
+        request: mobilecoind_api::UpdateUtxoMemoRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
 
-        // Generate a new account using this entropy.
-        let mut account_key_request = mobilecoind_api::GetAccountKeyRequest::new();
-        account_key_request.set_entropy(entropy.clone());
+        let key_image = KeyImage::try_from(request.get_key_image())
+            .map_err(|err| rpc_internal_error("key_image.try_from", err, &self.logger))?;
 
-        let account_key_response = self.get_account_key_impl(account_key_request)?;
-        let account_key = AccountKey::try_from(account_key_response.get_account_key())
-            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
+        self.mobilecoind_db
+            .update_utxo_memo(&monitor_id, &key_image, request.memo)
+            .map_err(|err| {
+                rpc_monitor_error("mobilecoind_db.update_utxo_memo", err, &self.logger)
+            })?;
 
-        // The outlay we are sending the money to.
-        let outlay = Outlay {
-            receiver: account_key.default_subaddress(),
-            value: request.value,
-        };
+        Ok(mobilecoind_api::Empty::new())
+    }
 
-        // Generate transaction.
-        let mut generate_tx_request = mobilecoind_api::GenerateTxRequest::new();
-        generate_tx_request.set_sender_monitor_id(request.get_sender_monitor_id().to_vec());
-        generate_tx_request.set_change_subaddress(request.change_subaddress);
-        generate_tx_request.set_input_list(RepeatedField::from_vec(request.input_list.to_vec()));
-        generate_tx_request.set_outlay_list(RepeatedField::from_vec(vec![(&outlay).into()]));
-        generate_tx_request.set_fee(request.fee);
-        generate_tx_request.set_tombstone(request.tombstone);
+    fn get_processed_tx_out_history_impl(
+        &mut self,
+        request: mobilecoind_api::GetProcessedTxOutHistoryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetProcessedTxOutHistoryResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
 
-        let mut generate_tx_response = self.generate_tx_impl(generate_tx_request)?;
-        let tx_proposal = generate_tx_response.take_tx_proposal();
+        // A max_blocks of zero means no limit.
+        let max_blocks = if request.max_blocks == 0 {
+            u64::max_value()
+        } else {
+            request.max_blocks
+        };
 
-        // Grab the public key of the relevant tx out.
-        let proto_tx_public_key = {
-            // We expect only a single outlay.
-            if tx_proposal.get_outlay_index_to_tx_out_index().len() != 1 {
-                return Err(RpcStatus::new(
-                    RpcStatusCode::INTERNAL,
-                    Some(format!(
-                        "outlay_index_to_tx_out_index contains {} elements, was expecting 1",
-                        tx_proposal.get_outlay_index_to_tx_out_index().len()
-                    )),
-                ));
-            }
+        // Get ProcessedTxOuts.
+        let processed_tx_outs = self
+            .mobilecoind_db
+            .get_processed_tx_out_history(&monitor_id, request.first_block, max_blocks)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "mobilecoind_db.get_processed_tx_out_history",
+                    err,
+                    &self.logger,
+                )
+            })?;
 
-            // Get the TxOut index of our single outlay.
-            let tx_out_index = tx_proposal
-                .get_outlay_index_to_tx_out_index()
-                .get(&0)
-                .ok_or_else(|| {
-                    RpcStatus::new(
-                        RpcStatusCode::INTERNAL,
-                        Some("outlay_index_to_tx_out_index doesn't contain index 0".to_owned()),
-                    )
-                })?;
+        // Convert to protos.
+        let proto_processed_tx_outs = processed_tx_outs
+            .iter()
+            .map(|processed_tx_out| {
+                let mut proto = mobilecoind_api::ProcessedTxOut::from(processed_tx_out);
+                proto.set_monitor_id(monitor_id.to_vec());
+                proto
+            })
+            .collect();
 
-            // Get the TxOut
-            let tx_out = tx_proposal
-                .get_tx()
-                .get_prefix()
-                .get_outputs()
-                .get(*tx_out_index as usize)
-                .ok_or_else(|| {
-                    RpcStatus::new(
-                        RpcStatusCode::INTERNAL,
-                        Some(format!("tx out index {} not found", tx_out_index)),
-                    )
-                })?;
+        // Return response.
+        let mut response = mobilecoind_api::GetProcessedTxOutHistoryResponse::new();
+        response.set_tx_out_list(RepeatedField::from_vec(proto_processed_tx_outs));
+        Ok(response)
+    }
 
-            // Get the public key
-            tx_out.get_public_key().clone()
+    fn get_incoming_payment_list_impl(
+        &mut self,
+        request: mobilecoind_api::GetIncomingPaymentListRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetIncomingPaymentListResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        // A max_entries of zero means no limit.
+        let max_entries = if request.max_entries == 0 {
+            usize::max_value()
+        } else {
+            request.max_entries as usize
         };
 
-        let tx_public_key = RistrettoPublic::try_from(&proto_tx_public_key)
-            .map_err(|err| rpc_internal_error("ristretto_public.try_from", err, &self.logger))?;
+        // Get IncomingPayments.
+        let incoming_payments = self
+            .mobilecoind_db
+            .get_incoming_payment_list(&monitor_id, request.first_block, max_entries)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "mobilecoind_db.get_incoming_payment_list",
+                    err,
+                    &self.logger,
+                )
+            })?;
 
-        // Generate b58 code.
-        let transfer_payload = TransferPayload::new_v1(
-            &entropy_bytes,
-            &tx_public_key.to_bytes(),
-            request.get_memo(),
-        )
-        .map_err(|err| rpc_internal_error("transfer_payload.new_v1", err, &self.logger))?;
-        let b58_code = transfer_payload.encode();
+        // Convert to protos.
+        let proto_incoming_payments = incoming_payments
+            .iter()
+            .map(|incoming_payment| {
+                let mut proto = mobilecoind_api::IncomingPayment::from(incoming_payment);
+                proto.set_monitor_id(monitor_id.to_vec());
+                proto
+            })
+            .collect();
 
-        // Construct response.
-        let mut response = mobilecoind_api::GenerateTransferCodeTxResponse::new();
-        response.set_tx_proposal(tx_proposal);
-        response.set_entropy(entropy);
-        response.set_tx_public_key(proto_tx_public_key);
-        response.set_memo(request.get_memo().to_owned());
-        response.set_b58_code(b58_code);
+        // Return response.
+        let mut response = mobilecoind_api::GetIncomingPaymentListResponse::new();
+        response.set_incoming_payment_list(RepeatedField::from_vec(proto_incoming_payments));
         Ok(response)
     }
 
-    fn submit_tx_impl(
+    fn get_transaction_history_impl(
         &mut self,
-        request: mobilecoind_api::SubmitTxRequest,
-    ) -> Result<mobilecoind_api::SubmitTxResponse, RpcStatus> {
-        // Get TxProposal from request.
-        let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
-            .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
+        request: mobilecoind_api::GetTransactionHistoryRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetTransactionHistoryResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
 
-        // Submit to network.
-        let block_height = self
-            .transactions_manager
-            .submit_tx_proposal(&tx_proposal)
+        let subaddress_index = if request.filter_by_subaddress {
+            Some(request.subaddress_index)
+        } else {
+            None
+        };
+
+        // A max_blocks of zero means no limit.
+        let max_blocks = if request.max_blocks == 0 {
+            u64::max_value()
+        } else {
+            request.max_blocks
+        };
+
+        // Get TransactionLogEntries.
+        let entries = self
+            .mobilecoind_db
+            .get_transaction_history(
+                &monitor_id,
+                subaddress_index,
+                request.first_block,
+                max_blocks,
+            )
             .map_err(|err| {
-                rpc_internal_error("transactions_manager.submit_tx_proposal", err, &self.logger)
+                rpc_internal_error("mobilecoind_db.get_transaction_history", err, &self.logger)
             })?;
 
-        // Update the attempted spend block height in db. Note that we swallow the error here since
-        // our transaction did get sent to the network, and its better to have the user attempt a
-        // double spend by having stale UnspentTxOut data than having them not be aware that the
-        // transaction was submitted.
-        let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
-        if let Err(err) = self.mobilecoind_db.update_attempted_spend(
-            &utxo_ids,
-            block_height,
-            tx_proposal.tx.prefix.tombstone_block,
-        ) {
-            log::error!(
-                self.logger,
-                "failed updating attempted_spend_height after submitting tx {}: {:?}",
-                tx_proposal.tx,
-                err
-            );
-        }
+        // Convert to protos.
+        let proto_entries = entries
+            .iter()
+            .map(|entry| {
+                let mut proto = mobilecoind_api::TransactionLogEntry::from(entry);
+                proto.set_monitor_id(monitor_id.to_vec());
+                proto
+            })
+            .collect();
 
-        // Construct sender receipt.
-        let mut sender_tx_receipt = mobilecoind_api::SenderTxReceipt::new();
-        sender_tx_receipt.set_key_image_list(RepeatedField::from_vec(
-            tx_proposal
-                .utxos
-                .iter()
-                .map(|utxo| (&utxo.key_image).into())
-                .collect(),
-        ));
-        sender_tx_receipt.set_tombstone(tx_proposal.tx.prefix.tombstone_block);
+        // Return response.
+        let mut response = mobilecoind_api::GetTransactionHistoryResponse::new();
+        response.set_entry_list(RepeatedField::from_vec(proto_entries));
+        Ok(response)
+    }
 
-        // Construct receiver receipts.
-        let receiver_tx_receipts: Vec<_> = tx_proposal
-            .outlays
-            .iter()
-            .enumerate()
-            .map(|(outlay_index, outlay)| {
-                let tx_out_index = tx_proposal
-                    .outlay_index_to_tx_out_index
-                    .get(&outlay_index)
-                    .ok_or_else(|| {
-                        RpcStatus::new(
-                            RpcStatusCode::INVALID_ARGUMENT,
-                            Some("outlay_index_to_tx_out_index".to_string()),
-                        )
-                    })?;
+    fn get_spent_tx_outs_impl(
+        &mut self,
+        request: mobilecoind_api::GetSpentTxOutsRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetSpentTxOutsResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
 
-                let tx_out = tx_proposal
-                    .tx
-                    .prefix
-                    .outputs
-                    .get(*tx_out_index)
-                    .ok_or_else(|| {
-                        RpcStatus::new(
-                            RpcStatusCode::INVALID_ARGUMENT,
-                            Some("outlay_index_to_tx_out_index".to_string()),
-                        )
-                    })?;
+        let subaddress_index = if request.filter_by_subaddress {
+            Some(request.subaddress_index)
+        } else {
+            None
+        };
 
-                let mut receiver_tx_receipt = mobilecoind_api::ReceiverTxReceipt::new();
-                receiver_tx_receipt.set_receipient((&outlay.receiver).into());
-                receiver_tx_receipt.set_tx_public_key(tx_out.public_key.into());
-                receiver_tx_receipt.set_tx_out_hash(tx_out.hash().to_vec());
-                receiver_tx_receipt.set_tombstone(tx_proposal.tx.prefix.tombstone_block);
+        // A max_blocks of zero means no limit.
+        let max_blocks = if request.max_blocks == 0 {
+            u64::max_value()
+        } else {
+            request.max_blocks
+        };
 
-                Ok(receiver_tx_receipt)
-            })
-            .collect::<Result<Vec<mobilecoind_api::ReceiverTxReceipt>, RpcStatus>>()?;
+        // Get SpentTxOuts.
+        let spent_txos = self
+            .mobilecoind_db
+            .get_spent_txos(
+                &monitor_id,
+                subaddress_index,
+                request.first_block,
+                max_blocks,
+            )
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_spent_txos", err, &self.logger)
+            })?;
 
         // Return response.
-        let mut response = mobilecoind_api::SubmitTxResponse::new();
-        response.set_sender_tx_receipt(sender_tx_receipt);
-        response.set_receiver_tx_receipt_list(RepeatedField::from_vec(receiver_tx_receipts));
+        let mut response = mobilecoind_api::GetSpentTxOutsResponse::new();
+        response.set_spent_tx_out_list(RepeatedField::from_vec(
+            spent_txos
+                .iter()
+                .map(mobilecoind_api::SpentTxOut::from)
+                .collect(),
+        ));
         Ok(response)
     }
 
-    fn get_ledger_info_impl(
+    fn generate_entropy_impl(
         &mut self,
         _request: mobilecoind_api::Empty,
-    ) -> Result<mobilecoind_api::GetLedgerInfoResponse, RpcStatus> {
-        let num_blocks = self
-            .ledger_db
-            .num_blocks()
-            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
-
-        let num_txos = self
-            .ledger_db
-            .num_txos()
-            .map_err(|err| rpc_internal_error("ledger_db.num_txos", err, &self.logger))?;
-
-        let mut response = mobilecoind_api::GetLedgerInfoResponse::new();
-        response.set_block_count(num_blocks);
-        response.set_txo_count(num_txos);
+    ) -> Result<mobilecoind_api::GenerateEntropyResponse, RpcStatus> {
+        let mut rng = rand::thread_rng();
+        let root_id = RootIdentity::random(&mut rng, None);
+        let mut response = mobilecoind_api::GenerateEntropyResponse::new();
+        response.set_entropy(root_id.root_entropy.to_vec());
         Ok(response)
     }
 
-    fn get_block_info_impl(
+    fn get_account_key_impl(
         &mut self,
-        request: mobilecoind_api::GetBlockInfoRequest,
-    ) -> Result<mobilecoind_api::GetBlockInfoResponse, RpcStatus> {
-        let block_contents = self
-            .ledger_db
-            .get_block_contents(request.block)
-            .map_err(|err| rpc_internal_error("ledger_db.get_block_contents", err, &self.logger))?;
+        request: mobilecoind_api::GetAccountKeyRequest,
+    ) -> Result<mobilecoind_api::GetAccountKeyResponse, RpcStatus> {
+        // Get the entropy.
+        if request.get_entropy().len() != 32 {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("entropy".to_string()),
+            ));
+        }
+        let mut root_entropy = [0u8; 32];
+        root_entropy.copy_from_slice(request.get_entropy());
 
-        let num_tx_outs = block_contents.outputs.len();
-        let num_key_images = block_contents.key_images.len();
+        // Use root entropy to construct AccountKey.
+        let root_id = RootIdentity {
+            root_entropy,
+            fog_url: None,
+        };
+
+        // TODO: change to production AccountKey derivation
+        let account_key = AccountKey::from(&root_id);
 
         // Return response.
-        let mut response = mobilecoind_api::GetBlockInfoResponse::new();
-        response.set_key_image_count(num_key_images as u64);
-        response.set_txo_count(num_tx_outs as u64);
+        let mut response = mobilecoind_api::GetAccountKeyResponse::new();
+        response.set_account_key((&account_key).into());
         Ok(response)
     }
 
-    fn get_tx_status_as_sender_impl(
+    fn get_public_address_impl(
         &mut self,
-        request: mobilecoind_api::GetTxStatusAsSenderRequest,
-    ) -> Result<mobilecoind_api::GetTxStatusAsSenderResponse, RpcStatus> {
-        // Sanity-test the request.
-        if request.get_receipt().get_key_image_list().is_empty() {
+        request: mobilecoind_api::GetPublicAddressRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetPublicAddressResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Get monitor data.
+        let data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+        check_tenant(&self.tenant_scope(caller)?, &data.tenant, "monitor_id")?;
+
+        // Verify subaddress falls in the range we are monitoring.
+        if !data
+            .subaddress_indexes()
+            .contains(&request.subaddress_index)
+        {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
-                Some("receipt.key_image_list".to_string()),
+                Some("subaddress_index".to_string()),
             ));
         }
 
-        if request.get_receipt().tombstone == 0 {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("receipt.tombstone".to_string()),
-            ));
-        }
+        // Get the subaddress.
+        let subaddress = data.subaddress(request.subaddress_index);
 
-        // Get list of key images from request.
-        let key_images: Vec<KeyImage> = request
-            .get_receipt()
-            .get_key_image_list()
-            .iter()
-            .map(|key_image| {
-                KeyImage::try_from(key_image)
-                    .map_err(|err| rpc_internal_error("key_image.try_from", err, &self.logger))
-            })
-            .collect::<Result<Vec<KeyImage>, RpcStatus>>()?;
+        // Return response.
+        let mut response = mobilecoind_api::GetPublicAddressResponse::new();
+        response.set_public_address((&subaddress).into());
+        Ok(response)
+    }
 
-        // Convert key images to a list of booleans indicating whether they were found in the
-        // ledger or not.
-        let key_image_found: Vec<bool> = key_images
-            .iter()
-            .map(|key_image| {
-                self.ledger_db.contains_key_image(key_image).map_err(|err| {
-                    rpc_internal_error("ledger_db.contains_key_image", err, &self.logger)
-                })
-            })
-            .collect::<Result<Vec<bool>, RpcStatus>>()?;
+    fn read_request_code_impl(
+        &mut self,
+        request: mobilecoind_api::ReadRequestCodeRequest,
+    ) -> Result<mobilecoind_api::ReadRequestCodeResponse, RpcStatus> {
+        let request_payload = RequestPayload::decode(request.get_b58_code())
+            .map_err(|err| rpc_internal_error("RequestPayload.decode", err, &self.logger))?;
 
-        // If all key images are in ledger, the transaction was completed.
-        if key_image_found
-            .iter()
-            .all(|key_image_found| *key_image_found)
-        {
-            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
-            response.set_status(mobilecoind_api::TxStatus::Verified);
-            return Ok(response);
-        }
+        let mut response = mobilecoind_api::ReadRequestCodeResponse::new();
+        response.set_receiver(mobilecoind_api::PublicAddress::from(&PublicAddress::from(
+            &request_payload,
+        )));
+        response.set_value(request_payload.value);
+        response.set_memo(request_payload.memo);
+        Ok(response)
+    }
 
-        // If only some key images found their way to the ledger, something is weird.
-        if key_image_found
-            .iter()
-            .any(|key_image_found| *key_image_found)
-        {
-            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
-            response.set_status(mobilecoind_api::TxStatus::Unknown);
-            return Ok(response);
-        }
+    fn get_request_code_impl(
+        &mut self,
+        request: mobilecoind_api::GetRequestCodeRequest,
+    ) -> Result<mobilecoind_api::GetRequestCodeResponse, RpcStatus> {
+        let receiver = PublicAddress::try_from(request.get_receiver())
+            .map_err(|err| rpc_internal_error("PublicAddress.try_from", err, &self.logger))?;
 
-        // Check if the tombstone block was exceeded.
-        let num_blocks = self
-            .ledger_db
-            .num_blocks()
-            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+        let view_key = receiver.view_public_key().to_bytes();
+        let spend_key = receiver.spend_public_key().to_bytes();
+        let fog_url = receiver.fog_url().unwrap_or("");
 
-        if num_blocks >= request.get_receipt().tombstone {
-            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
-            response.set_status(mobilecoind_api::TxStatus::TombstoneBlockExceeded);
-            return Ok(response);
-        }
+        let payload = RequestPayload::new_v3(
+            &view_key,
+            &spend_key,
+            fog_url,
+            request.get_value(),
+            request.get_memo(),
+        )
+        .map_err(|err| rpc_internal_error("RequestPayload.new_v3", err, &self.logger))?;
+        let b58_code = payload.encode();
 
-        // No key images in ledger, tombstone block not yet exceeded.
-        let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
-        response.set_status(mobilecoind_api::TxStatus::Unknown);
+        let mut response = mobilecoind_api::GetRequestCodeResponse::new();
+        response.set_b58_code(b58_code);
         Ok(response)
     }
 
-    fn get_tx_status_as_receiver_impl(
+    fn read_transfer_code_impl(
         &mut self,
-        request: mobilecoind_api::GetTxStatusAsReceiverRequest,
-    ) -> Result<mobilecoind_api::GetTxStatusAsReceiverResponse, RpcStatus> {
-        // Sanity-test the request.
-        if request.get_receipt().get_tx_out_hash().len() != 32 {
-            return Err(RpcStatus::new(
-                RpcStatusCode::INVALID_ARGUMENT,
-                Some("receipt.tx_out_hash".to_string()),
-            ));
-        }
+        request: mobilecoind_api::ReadTransferCodeRequest,
+    ) -> Result<mobilecoind_api::ReadTransferCodeResponse, RpcStatus> {
+        let transfer_payload = TransferPayload::decode(request.get_b58_code())
+            .map_err(|err| rpc_internal_error("TransferPayload.decode", err, &self.logger))?;
 
-        if request.get_receipt().tombstone == 0 {
+        let tx_public_key = RistrettoPublic::try_from(&transfer_payload.utxo)
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::ReadTransferCodeResponse::new();
+        response.set_entropy(transfer_payload.entropy.to_vec());
+        response.set_tx_public_key((&tx_public_key).into());
+        response.set_memo(transfer_payload.memo);
+        Ok(response)
+    }
+
+    fn get_transfer_code_impl(
+        &mut self,
+        request: mobilecoind_api::GetTransferCodeRequest,
+    ) -> Result<mobilecoind_api::GetTransferCodeResponse, RpcStatus> {
+        let mut entropy: [u8; 32] = [0; 32];
+        if request.entropy.len() != entropy.len() {
             return Err(RpcStatus::new(
                 RpcStatusCode::INVALID_ARGUMENT,
-                Some("receipt.tombstone".to_string()),
+                Some("entropy".to_string()),
             ));
         }
-        // Check if the hash landed in the ledger.
-        let mut hash_bytes = [0u8; 32];
-        hash_bytes.copy_from_slice(&request.get_receipt().tx_out_hash);
+        entropy.copy_from_slice(request.get_entropy());
 
-        match self.ledger_db.get_tx_out_index_by_hash(&hash_bytes) {
-            Ok(_) => {
-                // The hash found its way into the ledger, so the transaction succeeded.
-                let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
-                response.set_status(mobilecoind_api::TxStatus::Verified);
-                return Ok(response);
-            }
-            Err(ledger_db::Error::NotFound) => {}
-            Err(err) => {
-                return Err(rpc_internal_error(
-                    "ledger_db.get_tx_out_index_by_hash",
-                    err,
-                    &self.logger,
-                ));
-            }
-        };
+        let tx_public_key = RistrettoPublic::try_from(request.get_tx_public_key())
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
 
-        // Check if the tombstone block was exceeded.
-        let num_blocks = self
-            .ledger_db
-            .num_blocks()
-            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+        let payload =
+            TransferPayload::new_v1(&entropy, &tx_public_key.to_bytes(), request.get_memo())
+                .map_err(|err| rpc_internal_error("TransferPayload.new_v1", err, &self.logger))?;
 
-        if num_blocks >= request.get_receipt().tombstone {
-            let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
-            response.set_status(mobilecoind_api::TxStatus::TombstoneBlockExceeded);
-            return Ok(response);
-        }
+        let b58_code = payload.encode();
 
-        // Tx out not in ledger, tombstone block not yet exceeded.
-        let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
-        response.set_status(mobilecoind_api::TxStatus::Unknown);
+        let mut response = mobilecoind_api::GetTransferCodeResponse::new();
+        response.set_b58_code(b58_code);
         Ok(response)
     }
 
-    fn get_balance_impl(
+    fn create_request_impl(
         &mut self,
-        request: mobilecoind_api::GetBalanceRequest,
-    ) -> Result<mobilecoind_api::GetBalanceResponse, RpcStatus> {
-        // Get MonitorId from from the GRPC request.
-        let monitor_id = MonitorId::try_from(&request.monitor_id)
-            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
-
-        // Get all utxos for this monitor id.
-        let utxos = self
-            .mobilecoind_db
-            .get_utxos_for_subaddress(&monitor_id, request.subaddress_index)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
-            })?;
+        request: mobilecoind_api::CreateRequestRequest,
+    ) -> Result<mobilecoind_api::CreateRequestResponse, RpcStatus> {
+        let payment_request = PaymentRequest::try_from(request.get_request()).map_err(|err| {
+            RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(err.to_string()))
+        })?;
 
-        // Sum them up.
-        let balance = utxos.iter().map(|utxo| utxo.value).sum::<u64>();
+        let b58_code = payment_request
+            .encode_b58()
+            .map_err(|err| rpc_internal_error("PaymentRequest.encode_b58", err, &self.logger))?;
+        let request_bytes = payment_request
+            .encode_proto()
+            .map_err(|err| rpc_internal_error("PaymentRequest.encode_proto", err, &self.logger))?;
 
-        // Return response.
-        let mut response = mobilecoind_api::GetBalanceResponse::new();
-        response.set_balance(balance);
+        let mut response = mobilecoind_api::CreateRequestResponse::new();
+        response.set_b58_code(b58_code);
+        response.set_request_bytes(request_bytes);
         Ok(response)
     }
 
-    fn send_payment_impl(
+    fn parse_request_impl(
         &mut self,
-        request: mobilecoind_api::SendPaymentRequest,
-    ) -> Result<mobilecoind_api::SendPaymentResponse, RpcStatus> {
-        // Get sender monitor id from request.
-        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
-            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
-
-        // Get all utxos for this monitor id.
-        let utxos = self
-            .mobilecoind_db
-            .get_utxos_for_subaddress(&sender_monitor_id, request.sender_subaddress)
-            .map_err(|err| {
-                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
-            })?;
+        request: mobilecoind_api::ParseRequestRequest,
+    ) -> Result<mobilecoind_api::ParseRequestResponse, RpcStatus> {
+        let payment_request = match (
+            request.get_b58_code().is_empty(),
+            request.get_request_bytes().is_empty(),
+        ) {
+            (false, true) => PaymentRequest::decode_b58(request.get_b58_code()),
+            (true, false) => PaymentRequest::decode_proto(request.get_request_bytes()),
+            _ => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("exactly one of b58_code and request_bytes must be set".to_string()),
+                ));
+            }
+        }
+        .map_err(|err| RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(err.to_string())))?;
 
-        // Get the list of outlays.
-        let outlays: Vec<Outlay> = request
-            .get_outlay_list()
-            .iter()
-            .map(|outlay_proto| {
-                Outlay::try_from(outlay_proto)
-                    .map_err(|err| rpc_internal_error("outlay.try_from", err, &self.logger))
-            })
-            .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
+        let mut response = mobilecoind_api::ParseRequestResponse::new();
+        response.set_request((&payment_request).into());
+        Ok(response)
+    }
 
-        // Attempt to construct a transaction.
-        let tx_proposal = self
-            .transactions_manager
-            .build_transaction(
-                &sender_monitor_id,
-                request.sender_subaddress,
-                &utxos,
-                &outlays,
-                request.fee,
-                request.tombstone,
+    fn create_mob_uri_impl(
+        &mut self,
+        request: mobilecoind_api::CreateMobUriRequest,
+    ) -> Result<mobilecoind_api::CreateMobUriResponse, RpcStatus> {
+        let mob_uri = MobUri::try_from(request.get_mob_uri()).map_err(|err| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("mob_uri.public_address: {:?}", err)),
             )
-            .map_err(|err| {
-                rpc_internal_error("transactions_manager.build_transaction", err, &self.logger)
-            })?;
+        })?;
 
-        let proto_tx_proposal = mobilecoind_api::TxProposal::from(&tx_proposal);
+        let uri = mob_uri.encode().map_err(|err| {
+            RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(err.to_string()))
+        })?;
 
-        // Submit transaction.
-        let mut submit_tx_request = mobilecoind_api::SubmitTxRequest::new();
-        submit_tx_request.set_tx_proposal(proto_tx_proposal.clone());
-        let mut submit_tx_response = self.submit_tx_impl(submit_tx_request)?;
+        let mut response = mobilecoind_api::CreateMobUriResponse::new();
+        response.set_uri(uri);
+        Ok(response)
+    }
 
-        // Return response.
-        let mut response = mobilecoind_api::SendPaymentResponse::new();
-        response.set_sender_tx_receipt(submit_tx_response.take_sender_tx_receipt());
-        response.set_receiver_tx_receipt_list(submit_tx_response.take_receiver_tx_receipt_list());
-        response.set_tx_proposal(proto_tx_proposal);
+    fn parse_mob_uri_impl(
+        &mut self,
+        request: mobilecoind_api::ParseMobUriRequest,
+    ) -> Result<mobilecoind_api::ParseMobUriResponse, RpcStatus> {
+        let mob_uri = MobUri::decode(request.get_uri()).map_err(|err| {
+            RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(err.to_string()))
+        })?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let mut response = mobilecoind_api::ParseMobUriResponse::new();
+        response.set_mob_uri((&mob_uri).into());
+        response.set_expired(mob_uri.is_expired(now));
         Ok(response)
     }
-}
 
-macro_rules! build_api {
-    ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident ),+)
-    =>
-    (
-        impl<T: UserTxConnection + 'static> MobilecoindApi for ServiceApi<T> {
-            $(
-                fn $service_function_name(
-                    &mut self,
-                    ctx: RpcContext,
-                    request: mobilecoind_api::$service_request_type,
-                    sink: UnarySink<mobilecoind_api::$service_response_type>,
-                ) {
-                    let logger = rpc_logger(&ctx, &self.logger);
-                    send_result(
-                        ctx,
-                        sink,
-                        self.$service_function_impl(request),
-                        &logger,
+    /// Checks the arguments shared by GenerateTx and GenerateUnsignedTx. Returns the sender monitor
+    /// id, the inputs and the outlays.
+    fn parse_generate_tx_request(
+        &self,
+        sender_monitor_id: &[u8],
+        change_subaddress: u64,
+        input_list: &[mobilecoind_api::UnspentTxOut],
+        outlay_list: &[mobilecoind_api::Outlay],
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<(MonitorId, Vec<UnspentTxOut>, Vec<Outlay>), RpcStatus> {
+        // Get sender monitor id.
+        let sender_monitor_id = MonitorId::try_from(sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Get monitor data for this monitor.
+        let sender_monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&sender_monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+        check_tenant(
+            &self.tenant_scope(caller)?,
+            &sender_monitor_data.tenant,
+            "sender_monitor_id",
+        )?;
+
+        // Check that change_subaddress is covered by this monitor.
+        if !sender_monitor_data
+            .subaddress_indexes()
+            .contains(&change_subaddress)
+        {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("change_subaddress".to_string()),
+            ));
+        }
+
+        // Get the list of potential inputs passed to.
+        let input_list: Vec<UnspentTxOut> = input_list
+            .iter()
+            .enumerate()
+            .map(|(i, proto_utxo)| {
+                deadline
+                    .check()
+                    .map_err(|err| rpc_deadline_error("input_list", err, &self.logger))?;
+
+                // Proto -> Rust struct conversion.
+                let utxo = UnspentTxOut::try_from(proto_utxo).map_err(|err| {
+                    rpc_internal_error("unspent_tx_out.try_from", err, &self.logger)
+                })?;
+
+                // Verify this output belongs to the monitor.
+                let subaddress_id = self
+                    .mobilecoind_db
+                    .get_subaddress_id_by_utxo_id(&UtxoId::from(&utxo))
+                    .map_err(|err| {
+                        rpc_internal_error(
+                            "mobilecoind_db.get_subaddress_id_by_utxo_id",
+                            err,
+                            &self.logger,
+                        )
+                    })?;
+
+                if subaddress_id.monitor_id != sender_monitor_id {
+                    return Err(RpcStatus::new(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        Some(format!("input_list.{}", i)),
+                    ));
+                }
+
+                // Success.
+                Ok(utxo)
+            })
+            .collect::<Result<Vec<UnspentTxOut>, RpcStatus>>()?;
+
+        // Get the list of outlays.
+        let outlays: Vec<Outlay> = outlay_list
+            .iter()
+            .map(|outlay_proto| {
+                Outlay::try_from(outlay_proto)
+                    .map_err(|err| rpc_internal_error("outlay.try_from", err, &self.logger))
+            })
+            .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
+
+        Ok((sender_monitor_id, input_list, outlays))
+    }
+
+    fn generate_tx_impl(
+        &mut self,
+        request: mobilecoind_api::GenerateTxRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GenerateTxResponse, RpcStatus> {
+        let (sender_monitor_id, input_list, outlays) = self.parse_generate_tx_request(
+            &request.sender_monitor_id,
+            request.change_subaddress,
+            request.get_input_list(),
+            request.get_outlay_list(),
+            deadline,
+            caller,
+        )?;
+
+        // Attempt to construct a transaction.
+        let tx_proposal = self
+            .transactions_manager
+            .build_transaction(
+                &sender_monitor_id,
+                request.change_subaddress,
+                &input_list,
+                &outlays,
+                request.fee,
+                request.tombstone,
+                request.change_absorption_threshold,
+                &CoinSelectionStrategy::default(),
+                deadline,
+            )
+            .map_err(|err| {
+                rpc_deadline_error("transactions_manager.build_transaction", err, &self.logger)
+            })?;
+
+        // Transactions above the approval threshold need to be approved before they are submitted.
+        let approval_required = self.approval_required(&outlays);
+        if approval_required {
+            let tx_approval = TxApproval::new(&sender_monitor_id, &tx_proposal, &caller.identity);
+            self.mobilecoind_db
+                .add_tx_approval(&tx_approval)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.add_tx_approval", err, &self.logger)
+                })?;
+        }
+
+        // Success.
+        let mut response = mobilecoind_api::GenerateTxResponse::new();
+        response.set_tx_proposal((&tx_proposal).into());
+        response.set_approval_required(approval_required);
+        Ok(response)
+    }
+
+    fn generate_optimization_tx_impl(
+        &mut self,
+        request: mobilecoind_api::GenerateOptimizationTxRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GenerateOptimizationTxResponse, RpcStatus> {
+        // Get monitor id from request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        // Generate optimization tx.
+        let tx_proposal = self
+            .transactions_manager
+            .generate_optimization_tx(&monitor_id, request.subaddress, deadline)
+            .map_err(|err| {
+                rpc_deadline_error(
+                    "transactions_manager.generate_optimization_tx",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // Success.
+        let mut response = mobilecoind_api::GenerateOptimizationTxResponse::new();
+        response.set_tx_proposal((&tx_proposal).into());
+        Ok(response)
+    }
+
+    fn generate_transfer_code_tx_impl(
+        &mut self,
+        request: mobilecoind_api::GenerateTransferCodeTxRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GenerateTransferCodeTxResponse, RpcStatus> {
+        // Generate entropy.
+        let entropy_response = self.generate_entropy_impl(mobilecoind_api::Empty::new())?;
+        let entropy = entropy_response.get_entropy().to_vec();
+
+        let mut entropy_bytes = [0; 32];
+        if entropy.len() != entropy_bytes.len() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INTERNAL,
+                Some("entropy returned was not 32 bytes".to_owned()),
+            ));
+        }
+        entropy_bytes.copy_from_slice(&entropy);
+
+        // Generate a new account using this entropy.
+        let mut account_key_request = mobilecoind_api::GetAccountKeyRequest::new();
+        account_key_request.set_entropy(entropy.clone());
+
+        let account_key_response = self.get_account_key_impl(account_key_request)?;
+        let account_key = AccountKey::try_from(account_key_response.get_account_key())
+            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
+
+        // The outlay we are sending the money to.
+        let outlay = Outlay {
+            receiver: account_key.default_subaddress(),
+            value: request.value,
+        };
+
+        // Generate transaction.
+        let mut generate_tx_request = mobilecoind_api::GenerateTxRequest::new();
+        generate_tx_request.set_sender_monitor_id(request.get_sender_monitor_id().to_vec());
+        generate_tx_request.set_change_subaddress(request.change_subaddress);
+        generate_tx_request.set_input_list(RepeatedField::from_vec(request.input_list.to_vec()));
+        generate_tx_request.set_outlay_list(RepeatedField::from_vec(vec![(&outlay).into()]));
+        generate_tx_request.set_fee(request.fee);
+        generate_tx_request.set_tombstone(request.tombstone);
+
+        let mut generate_tx_response =
+            self.generate_tx_impl(generate_tx_request, deadline, caller)?;
+        let tx_proposal = generate_tx_response.take_tx_proposal();
+
+        // Grab the public key of the relevant tx out.
+        let proto_tx_public_key = {
+            // We expect only a single outlay.
+            if tx_proposal.get_outlay_index_to_tx_out_index().len() != 1 {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::INTERNAL,
+                    Some(format!(
+                        "outlay_index_to_tx_out_index contains {} elements, was expecting 1",
+                        tx_proposal.get_outlay_index_to_tx_out_index().len()
+                    )),
+                ));
+            }
+
+            // Get the TxOut index of our single outlay.
+            let tx_out_index = tx_proposal
+                .get_outlay_index_to_tx_out_index()
+                .get(&0)
+                .ok_or_else(|| {
+                    RpcStatus::new(
+                        RpcStatusCode::INTERNAL,
+                        Some("outlay_index_to_tx_out_index doesn't contain index 0".to_owned()),
+                    )
+                })?;
+
+            // Get the TxOut
+            let tx_out = tx_proposal
+                .get_tx()
+                .get_prefix()
+                .get_outputs()
+                .get(*tx_out_index as usize)
+                .ok_or_else(|| {
+                    RpcStatus::new(
+                        RpcStatusCode::INTERNAL,
+                        Some(format!("tx out index {} not found", tx_out_index)),
+                    )
+                })?;
+
+            // Get the public key
+            tx_out.get_public_key().clone()
+        };
+
+        let tx_public_key = RistrettoPublic::try_from(&proto_tx_public_key)
+            .map_err(|err| rpc_internal_error("ristretto_public.try_from", err, &self.logger))?;
+
+        // Generate b58 code.
+        let transfer_payload = TransferPayload::new_v1(
+            &entropy_bytes,
+            &tx_public_key.to_bytes(),
+            request.get_memo(),
+        )
+        .map_err(|err| rpc_internal_error("transfer_payload.new_v1", err, &self.logger))?;
+        let b58_code = transfer_payload.encode();
+
+        // Construct response.
+        let mut response = mobilecoind_api::GenerateTransferCodeTxResponse::new();
+        response.set_tx_proposal(tx_proposal);
+        response.set_entropy(entropy);
+        response.set_tx_public_key(proto_tx_public_key);
+        response.set_memo(request.get_memo().to_owned());
+        response.set_b58_code(b58_code);
+        response.set_approval_required(generate_tx_response.get_approval_required());
+        Ok(response)
+    }
+
+    fn submit_tx_impl(
+        &mut self,
+        request: mobilecoind_api::SubmitTxRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::SubmitTxResponse, RpcStatus> {
+        // Get TxProposal from request.
+        let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
+            .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
+        self.authorize_utxos(&tx_proposal.utxos, caller)?;
+
+        // Transactions above the approval threshold may only be submitted once approved. The
+        // outlays are supplied by the client, so transactions recorded as requiring approval are
+        // checked regardless of them.
+        let approval_status = if self.approval_threshold.is_some() {
+            let tx_prefix_hash = tx_proposal.tx.prefix.hash();
+            match self.mobilecoind_db.get_tx_approval(&tx_prefix_hash) {
+                Ok(tx_approval) => Some(tx_approval.get_status()),
+                Err(Error::TxApprovalNotFound) if self.approval_required(&tx_proposal.outlays) => {
+                    Some(TxApprovalStatus::Unknown)
+                }
+                Err(Error::TxApprovalNotFound) => None,
+                Err(err) => {
+                    return Err(rpc_internal_error(
+                        "mobilecoind_db.get_tx_approval",
+                        err,
+                        &self.logger,
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+        if let Some(status) = approval_status {
+            // Submitting an approved transaction again is harmless, since it spends the same inputs.
+            if status != TxApprovalStatus::Approved && status != TxApprovalStatus::Submitted {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::PERMISSION_DENIED,
+                    Some(format!(
+                        "tx_proposal: transaction requires approval, approval status is {:?}",
+                        status
+                    )),
+                ));
+            }
+        }
+
+        let mut response = mobilecoind_api::SubmitTxResponse::new();
+
+        // Submit to network.
+        let block_height = if request.get_broadcast() {
+            let result = self
+                .transactions_manager
+                .broadcast_tx_proposal(&tx_proposal)
+                .map_err(|err| {
+                    rpc_submit_error(
+                        "transactions_manager.broadcast_tx_proposal",
+                        err,
+                        &self.logger,
                     )
+                })?;
+
+            response.set_verdict(match result.verdict {
+                SubmissionVerdict::Minority => {
+                    mobilecoind_api::SubmissionVerdict::SubmissionMinority
+                }
+                SubmissionVerdict::BlockingSet => {
+                    mobilecoind_api::SubmissionVerdict::SubmissionBlockingSet
+                }
+                SubmissionVerdict::Quorum => mobilecoind_api::SubmissionVerdict::SubmissionQuorum,
+            });
+            response.set_accepting_peer_list(RepeatedField::from_vec(
+                result
+                    .accepting_peers
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect(),
+            ));
+            response.set_rejecting_peer_list(RepeatedField::from_vec(
+                result
+                    .rejecting_peers
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect(),
+            ));
+            result.block_height
+        } else {
+            self.transactions_manager
+                .submit_tx_proposal(&tx_proposal)
+                .map_err(|err| {
+                    rpc_submit_error("transactions_manager.submit_tx_proposal", err, &self.logger)
+                })?
+        };
+
+        // Update the attempted spend block height in db. Note that we swallow the error here since
+        // our transaction did get sent to the network, and its better to have the user attempt a
+        // double spend by having stale UnspentTxOut data than having them not be aware that the
+        // transaction was submitted.
+        let utxo_ids: Vec<UtxoId> = tx_proposal.utxos.iter().map(UtxoId::from).collect();
+        if let Err(err) = self.mobilecoind_db.update_attempted_spend(
+            &utxo_ids,
+            block_height,
+            tx_proposal.tx.prefix.tombstone_block,
+        ) {
+            log::error!(
+                self.logger,
+                "failed updating attempted_spend_height after submitting tx {}: {:?}",
+                tx_proposal.tx,
+                err
+            );
+        }
+
+        // Same as above, the transaction was sent so the error is only logged.
+        if approval_status.is_some() {
+            if let Err(err) = self
+                .mobilecoind_db
+                .tx_approval_submitted(&tx_proposal.tx.prefix.hash())
+            {
+                log::error!(
+                    self.logger,
+                    "failed updating approval after submitting tx {}: {:?}",
+                    tx_proposal.tx,
+                    err
+                );
+            }
+        }
+
+        // Construct receipts.
+        let (sender_tx_receipt, receiver_tx_receipts) = get_tx_receipts(&tx_proposal)?;
+
+        // Return response.
+        response.set_sender_tx_receipt(sender_tx_receipt);
+        response.set_receiver_tx_receipt_list(RepeatedField::from_vec(receiver_tx_receipts));
+        Ok(response)
+    }
+
+    fn generate_unsigned_tx_impl(
+        &mut self,
+        request: mobilecoind_api::GenerateUnsignedTxRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GenerateUnsignedTxResponse, RpcStatus> {
+        let (sender_monitor_id, input_list, outlays) = self.parse_generate_tx_request(
+            &request.sender_monitor_id,
+            request.change_subaddress,
+            request.get_input_list(),
+            request.get_outlay_list(),
+            deadline,
+            caller,
+        )?;
+
+        // Attempt to construct a transaction.
+        let unsigned_tx_proposal = self
+            .transactions_manager
+            .build_unsigned_tx(
+                &sender_monitor_id,
+                request.change_subaddress,
+                &input_list,
+                &outlays,
+                request.fee,
+                request.tombstone,
+                request.change_absorption_threshold,
+                &CoinSelectionStrategy::default(),
+                deadline,
+            )
+            .map_err(|err| {
+                rpc_deadline_error("transactions_manager.build_unsigned_tx", err, &self.logger)
+            })?;
+
+        // Signing does not change the prefix, so the approval covers the signed transaction.
+        let approval_required = self.approval_required(&outlays);
+        if approval_required {
+            let tx_approval = TxApproval::new_unsigned(
+                &sender_monitor_id,
+                &unsigned_tx_proposal,
+                &caller.identity,
+            );
+            self.mobilecoind_db
+                .add_tx_approval(&tx_approval)
+                .map_err(|err| {
+                    rpc_internal_error("mobilecoind_db.add_tx_approval", err, &self.logger)
+                })?;
+        }
+
+        // Success.
+        let mut response = mobilecoind_api::GenerateUnsignedTxResponse::new();
+        response.set_unsigned_tx_proposal((&unsigned_tx_proposal).into());
+        response.set_approval_required(approval_required);
+        Ok(response)
+    }
+
+    fn sign_tx_impl(
+        &mut self,
+        request: mobilecoind_api::SignTxRequest,
+    ) -> Result<mobilecoind_api::SignTxResponse, RpcStatus> {
+        let proto_account_key = request.account_key.as_ref().ok_or_else(|| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("account_key".to_string()),
+            )
+        })?;
+        let account_key = AccountKey::try_from(proto_account_key)
+            .map_err(|err| rpc_internal_error("account_key.try_from", err, &self.logger))?;
+
+        let unsigned_tx_proposal = UnsignedTxProposal::try_from(request.get_unsigned_tx_proposal())
+            .map_err(|err| {
+                rpc_internal_error("unsigned_tx_proposal.try_from", err, &self.logger)
+            })?;
+
+        let tx_proposal = self
+            .transactions_manager
+            .sign_tx(unsigned_tx_proposal, &account_key)
+            .map_err(|err| rpc_internal_error("transactions_manager.sign_tx", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::SignTxResponse::new();
+        response.set_tx_proposal((&tx_proposal).into());
+        Ok(response)
+    }
+
+    fn verify_tx_proposal_impl(
+        &mut self,
+        request: mobilecoind_api::VerifyTxProposalRequest,
+    ) -> Result<mobilecoind_api::VerifyTxProposalResponse, RpcStatus> {
+        let tx_proposal = TxProposal::try_from(request.get_tx_proposal())
+            .map_err(|err| rpc_internal_error("tx_proposal.try_from", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::VerifyTxProposalResponse::new();
+        match self.transactions_manager.verify_tx_proposal(&tx_proposal) {
+            Ok(()) => response.set_is_valid(true),
+            Err(Error::InvalidTxProposal(failure)) => response.set_failure(failure),
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "transactions_manager.verify_tx_proposal",
+                    err,
+                    &self.logger,
+                ))
+            }
+        }
+        Ok(response)
+    }
+
+    fn approve_tx_impl(
+        &mut self,
+        request: mobilecoind_api::ApproveTxRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ApproveTxResponse, RpcStatus> {
+        let tx_prefix_hash = TxHash::try_from(&request.tx_prefix_hash[..]).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("tx_prefix_hash".to_string()),
+            )
+        })?;
+
+        // Approving on behalf of nobody in particular would defeat the purpose of approvals.
+        if !caller.is_authenticated() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::UNAUTHENTICATED,
+                Some("approving a transaction requires an API token".to_string()),
+            ));
+        }
+        self.authorize_tx_approval(&tx_prefix_hash, caller)?;
+
+        let tx_approval = self
+            .mobilecoind_db
+            .approve_tx(&tx_prefix_hash, &caller.identity)
+            .map_err(|err| rpc_approval_error("mobilecoind_db.approve_tx", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::ApproveTxResponse::new();
+        response.set_approval((&tx_approval).into());
+        Ok(response)
+    }
+
+    fn reject_tx_impl(
+        &mut self,
+        request: mobilecoind_api::RejectTxRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::RejectTxResponse, RpcStatus> {
+        let tx_prefix_hash = TxHash::try_from(&request.tx_prefix_hash[..]).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("tx_prefix_hash".to_string()),
+            )
+        })?;
+
+        if !caller.is_authenticated() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::UNAUTHENTICATED,
+                Some("rejecting a transaction requires an API token".to_string()),
+            ));
+        }
+        self.authorize_tx_approval(&tx_prefix_hash, caller)?;
+
+        let tx_approval = self
+            .mobilecoind_db
+            .reject_tx(&tx_prefix_hash, &caller.identity)
+            .map_err(|err| rpc_approval_error("mobilecoind_db.reject_tx", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::RejectTxResponse::new();
+        response.set_approval((&tx_approval).into());
+        Ok(response)
+    }
+
+    fn get_tx_approval_list_impl(
+        &mut self,
+        request: mobilecoind_api::GetTxApprovalListRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetTxApprovalListResponse, RpcStatus> {
+        // An empty monitor_id means approvals of all monitors.
+        let opt_monitor_id = if request.monitor_id.is_empty() {
+            None
+        } else {
+            Some(MonitorId::try_from(&request.monitor_id).map_err(|err| {
+                rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger)
+            })?)
+        };
+        self.authorize_optional_monitor(opt_monitor_id.as_ref(), caller)?;
+
+        let opt_status = if request.pending_only {
+            Some(TxApprovalStatus::Pending)
+        } else {
+            None
+        };
+
+        let tx_approvals = self
+            .mobilecoind_db
+            .get_tx_approval_list(opt_monitor_id.as_ref(), opt_status)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_tx_approval_list", err, &self.logger)
+            })?;
+
+        let mut response = mobilecoind_api::GetTxApprovalListResponse::new();
+        response.set_approval_list(RepeatedField::from_vec(
+            tx_approvals
+                .iter()
+                .map(mobilecoind_api::TxApproval::from)
+                .collect(),
+        ));
+        Ok(response)
+    }
+
+    /// Returns true if a transaction sending `outlays` needs to be approved before it is
+    /// submitted.
+    fn approval_required(&self, outlays: &[Outlay]) -> bool {
+        self.approval_threshold.map_or(false, |threshold| {
+            // Values too large to add up are certainly above the threshold.
+            PicoMob::checked_sum(outlays.iter().map(|outlay| PicoMob::new(outlay.value)))
+                .map_or(true, |value| value > threshold)
+        })
+    }
+
+    fn get_ledger_info_impl(
+        &mut self,
+        _request: mobilecoind_api::Empty,
+    ) -> Result<mobilecoind_api::GetLedgerInfoResponse, RpcStatus> {
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        let num_txos = self
+            .ledger_db
+            .num_txos()
+            .map_err(|err| rpc_internal_error("ledger_db.num_txos", err, &self.logger))?;
+
+        let mut response = mobilecoind_api::GetLedgerInfoResponse::new();
+        response.set_block_count(num_blocks);
+        response.set_txo_count(num_txos);
+        if let Some(confirmations) = self.confirmations.as_ref() {
+            response.set_confirmed_block_count(confirmations.confirmed_block_count());
+        }
+        Ok(response)
+    }
+
+    fn get_block_info_impl(
+        &mut self,
+        request: mobilecoind_api::GetBlockInfoRequest,
+    ) -> Result<mobilecoind_api::GetBlockInfoResponse, RpcStatus> {
+        let block_contents = self
+            .ledger_db
+            .get_block_contents(request.block)
+            .map_err(|err| rpc_internal_error("ledger_db.get_block_contents", err, &self.logger))?;
+
+        let num_tx_outs = block_contents.outputs.len();
+        let num_key_images = block_contents.key_images.len();
+
+        // Return response.
+        let mut response = mobilecoind_api::GetBlockInfoResponse::new();
+        response.set_key_image_count(num_key_images as u64);
+        response.set_txo_count(num_tx_outs as u64);
+        Ok(response)
+    }
+
+    fn get_block_signature_impl(
+        &mut self,
+        request: mobilecoind_api::GetBlockSignatureRequest,
+    ) -> Result<mobilecoind_api::GetBlockSignatureResponse, RpcStatus> {
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+        if request.block >= num_blocks {
+            return Err(RpcStatus::new(
+                RpcStatusCode::NOT_FOUND,
+                Some("block".to_string()),
+            ));
+        }
+
+        let mut response = mobilecoind_api::GetBlockSignatureResponse::new();
+        match self.ledger_db.get_block_signature(request.block) {
+            Ok(signature) => {
+                response.set_has_signature(true);
+                response.set_signature(mobilecoind_api::external::Ed25519Signature::from(
+                    signature.signature(),
+                ));
+                response.set_signer(mobilecoind_api::external::Ed25519Public::from(
+                    signature.signer(),
+                ));
+            }
+            Err(ledger_db::Error::NotFound) => {}
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_block_signature",
+                    err,
+                    &self.logger,
+                ))
+            }
+        }
+        Ok(response)
+    }
+
+    fn get_processed_block_impl(
+        &mut self,
+        request: mobilecoind_api::GetProcessedBlockRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetProcessedBlockResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Make sure the monitor has processed the requested block.
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+        check_tenant(
+            &self.tenant_scope(caller)?,
+            &monitor_data.tenant,
+            "monitor_id",
+        )?;
+
+        if request.block < monitor_data.first_block || request.block >= monitor_data.next_block {
+            return Err(RpcStatus::new(
+                RpcStatusCode::OUT_OF_RANGE,
+                Some(format!(
+                    "Block {} was not processed by this monitor (processed blocks {}-{})",
+                    request.block, monitor_data.first_block, monitor_data.next_block
+                )),
+            ));
+        }
+
+        // Get ProcessedTxOuts.
+        let processed_tx_outs = self
+            .mobilecoind_db
+            .get_processed_block(&monitor_id, request.block)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_processed_block", err, &self.logger)
+            })?;
+
+        // Convert to protos.
+        let proto_processed_tx_outs = processed_tx_outs
+            .iter()
+            .map(|processed_tx_out| {
+                let mut proto = mobilecoind_api::ProcessedTxOut::from(processed_tx_out);
+                proto.set_monitor_id(monitor_id.to_vec());
+                proto
+            })
+            .collect();
+
+        // Return response.
+        let mut response = mobilecoind_api::GetProcessedBlockResponse::new();
+        response.set_tx_out_list(RepeatedField::from_vec(proto_processed_tx_outs));
+        Ok(response)
+    }
+
+    fn stream_blocks_impl(
+        &mut self,
+        ctx: RpcContext,
+        request: mobilecoind_api::StreamBlocksRequest,
+        sink: ServerStreamingSink<mobilecoind_api::StreamBlocksResponse>,
+    ) {
+        let logger = rpc_logger(&ctx, &self.logger);
+
+        let opt_monitor_id = match self.stream_blocks_monitor_id(&request, &rpc_caller(&ctx)) {
+            Ok(opt_monitor_id) => opt_monitor_id,
+            Err(status) => {
+                let future = sink
+                    .fail(status)
+                    .map_err(move |err| log::error!(logger, "failed to reply: {:?}", err));
+                ctx.spawn(future);
+                return;
+            }
+        };
+
+        let responses = start_block_stream(
+            self.ledger_db.clone(),
+            self.mobilecoind_db.clone(),
+            request.first_block,
+            request.get_filter(),
+            opt_monitor_id,
+            logger.clone(),
+        )
+        .map(|response| (response, WriteFlags::default()))
+        .map_err(|_| grpcio::Error::RemoteStopped);
+
+        let future = sink
+            .send_all(responses)
+            .map(|_| ())
+            .map_err(move |err| log::debug!(logger, "Block stream ended: {:?}", err));
+
+        ctx.spawn(future);
+    }
+
+    /// The monitor a StreamBlocks request follows, if any.
+    fn stream_blocks_monitor_id(
+        &self,
+        request: &mobilecoind_api::StreamBlocksRequest,
+        caller: &Caller,
+    ) -> Result<Option<MonitorId>, RpcStatus> {
+        // An empty monitor_id means the stream does not follow a monitor.
+        if request.monitor_id.is_empty() {
+            return Ok(None);
+        }
+
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        // Make sure the monitor exists, instead of ending the stream right away.
+        match self.mobilecoind_db.get_monitor_data(&monitor_id) {
+            Ok(data) => {
+                check_tenant(&self.tenant_scope(caller)?, &data.tenant, "monitor_id")?;
+                Ok(Some(monitor_id))
+            }
+            Err(Error::MonitorIdNotFound) => Err(RpcStatus::new(
+                RpcStatusCode::NOT_FOUND,
+                Some("monitor_id".to_string()),
+            )),
+            Err(err) => Err(rpc_internal_error(
+                "mobilecoind_db.get_monitor_data",
+                err,
+                &self.logger,
+            )),
+        }
+    }
+
+    fn get_tx_status_as_sender_impl(
+        &mut self,
+        request: mobilecoind_api::GetTxStatusAsSenderRequest,
+    ) -> Result<mobilecoind_api::GetTxStatusAsSenderResponse, RpcStatus> {
+        // Sanity-test the request.
+        if request.get_receipt().get_key_image_list().is_empty() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("receipt.key_image_list".to_string()),
+            ));
+        }
+
+        if request.get_receipt().tombstone == 0 {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("receipt.tombstone".to_string()),
+            ));
+        }
+
+        // Get list of key images from request.
+        let key_images: Vec<KeyImage> = request
+            .get_receipt()
+            .get_key_image_list()
+            .iter()
+            .map(|key_image| {
+                KeyImage::try_from(key_image)
+                    .map_err(|err| rpc_internal_error("key_image.try_from", err, &self.logger))
+            })
+            .collect::<Result<Vec<KeyImage>, RpcStatus>>()?;
+
+        // Convert key images to a list of booleans indicating whether they were found in the
+        // ledger or not.
+        let key_image_found: Vec<bool> = key_images
+            .iter()
+            .map(|key_image| {
+                self.ledger_db.contains_key_image(key_image).map_err(|err| {
+                    rpc_internal_error("ledger_db.contains_key_image", err, &self.logger)
+                })
+            })
+            .collect::<Result<Vec<bool>, RpcStatus>>()?;
+
+        // If all key images are in ledger, the transaction was completed.
+        if key_image_found
+            .iter()
+            .all(|key_image_found| *key_image_found)
+        {
+            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
+            response.set_status(mobilecoind_api::TxStatus::Verified);
+            return Ok(response);
+        }
+
+        // If only some key images found their way to the ledger, something is weird.
+        if key_image_found
+            .iter()
+            .any(|key_image_found| *key_image_found)
+        {
+            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
+            response.set_status(mobilecoind_api::TxStatus::Unknown);
+            return Ok(response);
+        }
+
+        // Check if the tombstone block was exceeded.
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        if num_blocks >= request.get_receipt().tombstone {
+            let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
+            response.set_status(mobilecoind_api::TxStatus::TombstoneBlockExceeded);
+            return Ok(response);
+        }
+
+        // No key images in ledger, tombstone block not yet exceeded.
+        let mut response = mobilecoind_api::GetTxStatusAsSenderResponse::new();
+        response.set_status(mobilecoind_api::TxStatus::Unknown);
+        Ok(response)
+    }
+
+    fn get_tx_status_as_receiver_impl(
+        &mut self,
+        request: mobilecoind_api::GetTxStatusAsReceiverRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetTxStatusAsReceiverResponse, RpcStatus> {
+        // Sanity-test the request.
+        if request.get_receipt().get_tx_out_hash().len() != 32 {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("receipt.tx_out_hash".to_string()),
+            ));
+        }
+
+        if request.get_receipt().tombstone == 0 {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("receipt.tombstone".to_string()),
+            ));
+        }
+
+        // If the receipient's monitor is given, check that the sender created the TxOut.
+        if !request.monitor_id.is_empty() {
+            let monitor_id = MonitorId::try_from(&request.monitor_id).map_err(|err| {
+                rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger)
+            })?;
+
+            let monitor_data =
+                self.mobilecoind_db
+                    .get_monitor_data(&monitor_id)
+                    .map_err(|err| {
+                        rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+                    })?;
+            check_tenant(
+                &self.tenant_scope(caller)?,
+                &monitor_data.tenant,
+                "monitor_id",
+            )?;
+
+            let tx_public_key = RistrettoPublic::try_from(
+                request.get_receipt().get_tx_public_key(),
+            )
+            .map_err(|err| rpc_internal_error("RistrettoPublic.try_from", err, &self.logger))?;
+
+            let confirmation_number =
+                TxOutConfirmationNumber::try_from(request.get_receipt().get_confirmation_number())
+                    .map_err(|_| {
+                        RpcStatus::new(
+                            RpcStatusCode::INVALID_ARGUMENT,
+                            Some("receipt.confirmation_number".to_string()),
+                        )
+                    })?;
+
+            if !confirmation_number.validate(&tx_public_key, monitor_data.view_private_key()) {
+                let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
+                response.set_status(mobilecoind_api::TxStatus::InvalidConfirmationNumber);
+                return Ok(response);
+            }
+        }
+
+        // Check if the hash landed in the ledger.
+        let mut hash_bytes = [0u8; 32];
+        hash_bytes.copy_from_slice(&request.get_receipt().tx_out_hash);
+
+        match self.ledger_db.get_tx_out_index_by_hash(&hash_bytes) {
+            Ok(_) => {
+                // The hash found its way into the ledger, so the transaction succeeded.
+                let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
+                response.set_status(mobilecoind_api::TxStatus::Verified);
+                return Ok(response);
+            }
+            Err(ledger_db::Error::NotFound) => {}
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_tx_out_index_by_hash",
+                    err,
+                    &self.logger,
+                ));
+            }
+        };
+
+        // Check if the tombstone block was exceeded.
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        if num_blocks >= request.get_receipt().tombstone {
+            let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
+            response.set_status(mobilecoind_api::TxStatus::TombstoneBlockExceeded);
+            return Ok(response);
+        }
+
+        // Tx out not in ledger, tombstone block not yet exceeded.
+        let mut response = mobilecoind_api::GetTxStatusAsReceiverResponse::new();
+        response.set_status(mobilecoind_api::TxStatus::Unknown);
+        Ok(response)
+    }
+
+    fn get_tx_out_results_by_public_key_impl(
+        &mut self,
+        request: mobilecoind_api::GetTxOutResultsByPublicKeyRequest,
+    ) -> Result<mobilecoind_api::GetTxOutResultsByPublicKeyResponse, RpcStatus> {
+        if request.get_tx_out_public_keys().len() > MAX_TX_OUT_RESULTS_PER_REQUEST {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!(
+                    "tx_out_public_keys: at most {} public keys per request",
+                    MAX_TX_OUT_RESULTS_PER_REQUEST
+                )),
+            ));
+        }
+
+        // TxOuts in blocks appended while the request is processed are reported as not found, so
+        // that all results refer to the same ledger.
+        let num_blocks = self
+            .ledger_db
+            .num_blocks()
+            .map_err(|err| rpc_internal_error("ledger_db.num_blocks", err, &self.logger))?;
+
+        let results = request
+            .get_tx_out_public_keys()
+            .iter()
+            .map(|proto_public_key| {
+                let mut result = mobilecoind_api::TxOutResult::new();
+                result.set_tx_out_public_key(proto_public_key.clone());
+
+                let public_key =
+                    match CompressedRistrettoPublic::try_from(proto_public_key.get_data()) {
+                        Ok(public_key) => public_key,
+                        Err(_) => {
+                            result.set_result_code(
+                                mobilecoind_api::TxOutResultCode::MalformedPublicKey,
+                            );
+                            return Ok(result);
+                        }
+                    };
+
+                let global_tx_out_index =
+                    match self.ledger_db.get_tx_out_index_by_public_key(&public_key) {
+                        Ok(index) => index,
+                        Err(ledger_db::Error::NotFound) => {
+                            result.set_result_code(mobilecoind_api::TxOutResultCode::TxOutNotFound);
+                            return Ok(result);
+                        }
+                        Err(err) => {
+                            return Err(rpc_internal_error(
+                                "ledger_db.get_tx_out_index_by_public_key",
+                                err,
+                                &self.logger,
+                            ))
+                        }
+                    };
+
+                let block_index = self
+                    .ledger_db
+                    .get_block_index_by_tx_out_index(global_tx_out_index)
+                    .map_err(|err| {
+                        rpc_internal_error(
+                            "ledger_db.get_block_index_by_tx_out_index",
+                            err,
+                            &self.logger,
+                        )
+                    })?;
+                if block_index >= num_blocks {
+                    result.set_result_code(mobilecoind_api::TxOutResultCode::TxOutNotFound);
+                    return Ok(result);
+                }
+
+                result.set_result_code(mobilecoind_api::TxOutResultCode::TxOutFound);
+                result.set_block_index(block_index);
+                result.set_global_tx_out_index(global_tx_out_index);
+                Ok(result)
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        let mut response = mobilecoind_api::GetTxOutResultsByPublicKeyResponse::new();
+        response.set_results(RepeatedField::from_vec(results));
+        response.set_num_blocks(num_blocks);
+        Ok(response)
+    }
+
+    fn get_membership_proofs_impl(
+        &mut self,
+        request: mobilecoind_api::GetMembershipProofsRequest,
+    ) -> Result<mobilecoind_api::GetMembershipProofsResponse, RpcStatus> {
+        if request.get_tx_out_public_keys().len() > MAX_MEMBERSHIP_PROOFS_PER_REQUEST {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!(
+                    "tx_out_public_keys: at most {} public keys per request",
+                    MAX_MEMBERSHIP_PROOFS_PER_REQUEST
+                )),
+            ));
+        }
+
+        let tx_out_indices = request
+            .get_tx_out_public_keys()
+            .iter()
+            .map(|proto_public_key| {
+                let public_key = CompressedRistrettoPublic::try_from(proto_public_key.get_data())
+                    .map_err(|err| {
+                    rpc_invalid_arg_error("tx_out_public_key.try_from", err, &self.logger)
+                })?;
+                match self.ledger_db.get_tx_out_index_by_public_key(&public_key) {
+                    Ok(index) => Ok(index),
+                    Err(ledger_db::Error::NotFound) => Err(RpcStatus::new(
+                        RpcStatusCode::NOT_FOUND,
+                        Some(format!(
+                            "tx_out_public_key: {} is not in the ledger",
+                            hex::encode(proto_public_key.get_data())
+                        )),
+                    )),
+                    Err(err) => Err(rpc_internal_error(
+                        "ledger_db.get_tx_out_index_by_public_key",
+                        err,
+                        &self.logger,
+                    )),
+                }
+            })
+            .collect::<Result<Vec<u64>, RpcStatus>>()?;
+
+        let outputs = tx_out_indices
+            .iter()
+            .map(|index| {
+                self.ledger_db
+                    .get_tx_out_by_index(*index)
+                    .map(|tx_out| mobilecoind_api::external::TxOut::from(&tx_out))
+                    .map_err(|err| {
+                        rpc_internal_error("ledger_db.get_tx_out_by_index", err, &self.logger)
+                    })
+            })
+            .collect::<Result<Vec<_>, RpcStatus>>()?;
+
+        // The proofs are built in a single transaction, so that they are consistent.
+        let membership_proofs = self
+            .ledger_db
+            .get_tx_out_proof_of_memberships(&tx_out_indices)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "ledger_db.get_tx_out_proof_of_memberships",
+                    err,
+                    &self.logger,
+                )
+            })?
+            .iter()
+            .map(mobilecoind_api::external::TxOutMembershipProof::from)
+            .collect();
+
+        let mut response = mobilecoind_api::GetMembershipProofsResponse::new();
+        response.set_outputs(RepeatedField::from_vec(outputs));
+        response.set_membership_proofs(RepeatedField::from_vec(membership_proofs));
+        Ok(response)
+    }
+
+    fn get_tx_outs_by_block_range_impl(
+        &mut self,
+        request: mobilecoind_api::GetTxOutsByBlockRangeRequest,
+    ) -> Result<mobilecoind_api::GetTxOutsByBlockRangeResponse, RpcStatus> {
+        if request.num_blocks > MAX_BLOCKS_PER_TX_OUTS_REQUEST {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!(
+                    "num_blocks: at most {} blocks per request",
+                    MAX_BLOCKS_PER_TX_OUTS_REQUEST
+                )),
+            ));
+        }
+        let end_block = request
+            .first_block
+            .checked_add(request.num_blocks)
+            .ok_or_else(|| {
+                RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("first_block".to_string()),
+                )
+            })?;
+
+        let tx_outs = match self
+            .ledger_db
+            .get_tx_outs_by_block_range(request.first_block..end_block)
+        {
+            Ok(tx_outs) => tx_outs,
+            Err(ledger_db::Error::IndexOutOfBounds(_)) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::NOT_FOUND,
+                    Some("num_blocks".to_string()),
+                ))
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_tx_outs_by_block_range",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+
+        let mut response = mobilecoind_api::GetTxOutsByBlockRangeResponse::new();
+        response.set_tx_outs(RepeatedField::from_vec(
+            tx_outs
+                .iter()
+                .map(mobilecoind_api::external::TxOut::from)
+                .collect(),
+        ));
+        Ok(response)
+    }
+
+    fn get_block_by_key_image_impl(
+        &mut self,
+        request: mobilecoind_api::GetBlockByKeyImageRequest,
+    ) -> Result<mobilecoind_api::GetBlockByKeyImageResponse, RpcStatus> {
+        let key_image = KeyImage::try_from(request.get_key_image())
+            .map_err(|err| rpc_invalid_arg_error("key_image.try_from", err, &self.logger))?;
+
+        let block = match self.ledger_db.get_block_by_key_image(&key_image) {
+            Ok(block) => block,
+            Err(ledger_db::Error::NotFound) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::NOT_FOUND,
+                    Some("key_image".to_string()),
+                ))
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "ledger_db.get_block_by_key_image",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+
+        let mut response = mobilecoind_api::GetBlockByKeyImageResponse::new();
+        response.set_block_index(block.index);
+        response.set_block_id(block.id.as_ref().to_vec());
+        Ok(response)
+    }
+
+    fn get_outgoing_tx_list_impl(
+        &mut self,
+        request: mobilecoind_api::GetOutgoingTxListRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetOutgoingTxListResponse, RpcStatus> {
+        // An empty monitor_id means transactions of all monitors.
+        let opt_monitor_id = if request.monitor_id.is_empty() {
+            None
+        } else {
+            Some(MonitorId::try_from(&request.monitor_id).map_err(|err| {
+                rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger)
+            })?)
+        };
+        self.authorize_optional_monitor(opt_monitor_id.as_ref(), caller)?;
+
+        // A max_entries of zero means no limit.
+        let max_entries = if request.max_entries == 0 {
+            usize::max_value()
+        } else {
+            request.max_entries as usize
+        };
+
+        // Get OutgoingTxs.
+        let outgoing_txs = self
+            .mobilecoind_db
+            .get_outgoing_tx_list(opt_monitor_id.as_ref(), request.first_index, max_entries)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_outgoing_tx_list", err, &self.logger)
+            })?;
+
+        // Return response.
+        let mut response = mobilecoind_api::GetOutgoingTxListResponse::new();
+        response.set_tx_list(RepeatedField::from_vec(
+            outgoing_txs
+                .iter()
+                .map(mobilecoind_api::OutgoingTx::from)
+                .collect(),
+        ));
+        Ok(response)
+    }
+
+    fn get_outgoing_tx_impl(
+        &mut self,
+        request: mobilecoind_api::GetOutgoingTxRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetOutgoingTxResponse, RpcStatus> {
+        let tx_prefix_hash = TxHash::try_from(&request.tx_prefix_hash[..]).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("tx_prefix_hash".to_string()),
+            )
+        })?;
+
+        let outgoing_tx = self
+            .mobilecoind_db
+            .get_outgoing_tx_by_tx_prefix_hash(&tx_prefix_hash)
+            .map_err(|err| {
+                rpc_internal_error(
+                    "mobilecoind_db.get_outgoing_tx_by_tx_prefix_hash",
+                    err,
+                    &self.logger,
+                )
+            })?;
+        self.authorize_monitor(&outgoing_tx.monitor_id, caller)?;
+
+        // Return response.
+        let mut response = mobilecoind_api::GetOutgoingTxResponse::new();
+        response.set_tx(mobilecoind_api::OutgoingTx::from(&outgoing_tx));
+        Ok(response)
+    }
+
+    fn get_balance_impl(
+        &mut self,
+        request: mobilecoind_api::GetBalanceRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetBalanceResponse, RpcStatus> {
+        // Get MonitorId from from the GRPC request.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        // The running balance is the sum of the subaddress' utxos.
+        let synced_balance = self
+            .mobilecoind_db
+            .get_subaddress_balance(&monitor_id, request.subaddress_index)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_subaddress_balance", err, &self.logger)
+            })?
+            .value;
+
+        // Only count blocks confirmed by a quorum of signatures, if required.
+        let balance = match self.confirmations.as_ref() {
+            Some(confirmations) => confirmations
+                .confirmed_balance(
+                    &self.mobilecoind_db,
+                    &monitor_id,
+                    request.subaddress_index,
+                    synced_balance,
+                )
+                .map_err(|err| {
+                    rpc_internal_error("confirmations.confirmed_balance", err, &self.logger)
+                })?,
+            None => synced_balance,
+        };
+
+        // Return response.
+        let mut response = mobilecoind_api::GetBalanceResponse::new();
+        response.set_balance(balance);
+        response.set_synced_balance(synced_balance);
+        Ok(response)
+    }
+
+    fn get_balance_by_subaddress_impl(
+        &mut self,
+        request: mobilecoind_api::GetBalanceBySubaddressRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetBalanceBySubaddressResponse, RpcStatus> {
+        let (mut balance_list, next_block) =
+            self.subaddress_balances(&request.monitor_id, &[request.subaddress_index], caller)?;
+
+        let mut response = mobilecoind_api::GetBalanceBySubaddressResponse::new();
+        response.set_balance(balance_list.remove(0));
+        response.set_next_block(next_block);
+        Ok(response)
+    }
+
+    fn get_balance_by_subaddress_list_impl(
+        &mut self,
+        request: mobilecoind_api::GetBalanceBySubaddressListRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetBalanceBySubaddressListResponse, RpcStatus> {
+        let (balance_list, next_block) = self.subaddress_balances(
+            &request.monitor_id,
+            request.get_subaddress_index_list(),
+            caller,
+        )?;
+
+        let mut response = mobilecoind_api::GetBalanceBySubaddressListResponse::new();
+        response.set_balance_list(RepeatedField::from_vec(balance_list));
+        response.set_next_block(next_block);
+        Ok(response)
+    }
+
+    /// The balances of subaddresses of a monitor, every subaddress it watches if `indexes` is
+    /// empty, along with the next block the monitor will process.
+    fn subaddress_balances(
+        &self,
+        monitor_id_bytes: &[u8],
+        indexes: &[u64],
+        caller: &Caller,
+    ) -> Result<(Vec<mobilecoind_api::SubaddressBalance>, u64), RpcStatus> {
+        let monitor_id = MonitorId::try_from(monitor_id_bytes)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+
+        let data = match self.mobilecoind_db.get_monitor_data(&monitor_id) {
+            Ok(data) => data,
+            Err(Error::MonitorIdNotFound) => {
+                return Err(RpcStatus::new(
+                    RpcStatusCode::NOT_FOUND,
+                    Some("monitor_id".to_string()),
+                ))
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "mobilecoind_db.get_monitor_data",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+        check_tenant(&self.tenant_scope(caller)?, &data.tenant, "monitor_id")?;
+
+        let subaddress_indexes = data.subaddress_indexes();
+        let indexes: Vec<u64> = if indexes.is_empty() {
+            subaddress_indexes.collect()
+        } else if let Some(index) = indexes
+            .iter()
+            .find(|index| !subaddress_indexes.contains(*index))
+        {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!(
+                    "subaddress_index: {} is not watched by the monitor",
+                    index
+                )),
+            ));
+        } else {
+            indexes.to_vec()
+        };
+
+        let synced_balances = self
+            .mobilecoind_db
+            .get_subaddress_balances(&monitor_id, &indexes)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_subaddress_balances", err, &self.logger)
+            })?;
+
+        // Only count blocks confirmed by a quorum of signatures, if required.
+        let balances: Vec<u64> = match self.confirmations.as_ref() {
+            Some(confirmations) => confirmations
+                .confirmed_balances(
+                    &self.mobilecoind_db,
+                    &monitor_id,
+                    &indexes
+                        .iter()
+                        .zip(synced_balances.iter())
+                        .map(|(index, synced_balance)| (*index, synced_balance.value))
+                        .collect::<Vec<_>>(),
+                )
+                .map_err(|err| {
+                    rpc_internal_error("confirmations.confirmed_balances", err, &self.logger)
+                })?,
+            None => synced_balances
+                .iter()
+                .map(|balance| balance.value)
+                .collect(),
+        };
+
+        let balance_list = indexes
+            .iter()
+            .zip(synced_balances.iter())
+            .zip(balances.iter())
+            .map(|((index, synced_balance), balance)| {
+                let mut subaddress_balance = mobilecoind_api::SubaddressBalance::new();
+                subaddress_balance.set_subaddress_index(*index);
+                subaddress_balance.set_balance(*balance);
+                subaddress_balance.set_synced_balance(synced_balance.value);
+                subaddress_balance.set_num_utxos(synced_balance.num_utxos);
+                subaddress_balance
+            })
+            .collect();
+        Ok((balance_list, data.next_block))
+    }
+
+    fn send_payment_impl(
+        &mut self,
+        request: mobilecoind_api::SendPaymentRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::SendPaymentResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&sender_monitor_id, caller)?;
+
+        // Get all utxos for this monitor id.
+        let utxos = self
+            .mobilecoind_db
+            .get_utxos_for_subaddress(&sender_monitor_id, request.sender_subaddress)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+            })?;
+
+        // Get the list of outlays.
+        let outlays: Vec<Outlay> = request
+            .get_outlay_list()
+            .iter()
+            .map(|outlay_proto| self.outlay_from_request(outlay_proto, caller))
+            .collect::<Result<Vec<Outlay>, RpcStatus>>()?;
+
+        // SendPayment submits right away, leaving no opportunity to approve the transaction.
+        if self.approval_required(&outlays) {
+            return Err(RpcStatus::new(
+                RpcStatusCode::PERMISSION_DENIED,
+                Some(
+                    "outlay_list: payments above the approval threshold must be generated with \
+                     GenerateTx and approved with ApproveTx"
+                        .to_string(),
+                ),
+            ));
+        }
+
+        // Attempt to construct a transaction.
+        let coin_selection = CoinSelectionStrategy::from(request.get_coin_selection_strategy());
+        let tx_proposal = if request.queue_if_change_pending {
+            let payment = QueuedPayment {
+                sender_monitor_id,
+                sender_subaddress: request.sender_subaddress,
+                outlays,
+                fee: request.fee,
+                coin_selection,
+            };
+
+            // Payments queue behind earlier payments from the same subaddress, so that they are
+            // sent in order.
+            let opt_tx_proposal = if self
+                .payment_queue
+                .has_queued_payments(&sender_monitor_id, request.sender_subaddress)
+            {
+                None
+            } else {
+                build_payment(
+                    &self.transactions_manager,
+                    &self.ledger_db,
+                    &self.mobilecoind_db,
+                    &payment,
+                    deadline,
+                )
+                .map_err(|err| rpc_deadline_error("build_payment", err, &self.logger))?
+            };
+
+            match opt_tx_proposal {
+                Some(tx_proposal) => tx_proposal,
+                None => {
+                    let queued_payment_id = self.payment_queue.push(payment);
+                    log::info!(
+                        self.logger,
+                        "Queued payment {} from monitor {} until pending change lands",
+                        queued_payment_id,
+                        sender_monitor_id
+                    );
+
+                    let mut response = mobilecoind_api::SendPaymentResponse::new();
+                    response.set_queued(true);
+                    response.set_queued_payment_id(queued_payment_id);
+                    return Ok(response);
+                }
+            }
+        } else {
+            self.transactions_manager
+                .build_transaction(
+                    &sender_monitor_id,
+                    request.sender_subaddress,
+                    &utxos,
+                    &outlays,
+                    request.fee,
+                    request.tombstone,
+                    0,
+                    &coin_selection,
+                    deadline,
+                )
+                .map_err(|err| {
+                    rpc_deadline_error("transactions_manager.build_transaction", err, &self.logger)
+                })?
+        };
+
+        let proto_tx_proposal = mobilecoind_api::TxProposal::from(&tx_proposal);
+
+        // Submit transaction.
+        let mut submit_tx_request = mobilecoind_api::SubmitTxRequest::new();
+        submit_tx_request.set_tx_proposal(proto_tx_proposal.clone());
+        let mut submit_tx_response = self.submit_tx_impl(submit_tx_request, caller)?;
+
+        // Return response.
+        let mut response = mobilecoind_api::SendPaymentResponse::new();
+        response.set_sender_tx_receipt(submit_tx_response.take_sender_tx_receipt());
+        response.set_receiver_tx_receipt_list(submit_tx_response.take_receiver_tx_receipt_list());
+        response.set_tx_proposal(proto_tx_proposal);
+        Ok(response)
+    }
+
+    fn get_queued_payment_status_impl(
+        &mut self,
+        request: mobilecoind_api::GetQueuedPaymentStatusRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::GetQueuedPaymentStatusResponse, RpcStatus> {
+        if let Some(sender_monitor_id) = self
+            .payment_queue
+            .get_sender_monitor_id(request.queued_payment_id)
+        {
+            self.authorize_monitor(&sender_monitor_id, caller)?;
+        }
+
+        let status = self
+            .payment_queue
+            .get_status(request.queued_payment_id)
+            .ok_or_else(|| {
+                RpcStatus::new(
+                    RpcStatusCode::INVALID_ARGUMENT,
+                    Some("queued_payment_id".to_string()),
+                )
+            })?;
+
+        let mut response = mobilecoind_api::GetQueuedPaymentStatusResponse::new();
+        match status {
+            QueuedPaymentStatus::Queued => {
+                response.set_status(mobilecoind_api::QueuedPaymentStatus::PaymentQueued);
+            }
+            QueuedPaymentStatus::Submitted(tx_proposal) => {
+                let (sender_tx_receipt, receiver_tx_receipts) = get_tx_receipts(&tx_proposal)?;
+                response.set_status(mobilecoind_api::QueuedPaymentStatus::PaymentSubmitted);
+                response.set_sender_tx_receipt(sender_tx_receipt);
+                response
+                    .set_receiver_tx_receipt_list(RepeatedField::from_vec(receiver_tx_receipts));
+                response.set_tx_proposal((&tx_proposal).into());
+            }
+            QueuedPaymentStatus::Failed(error) => {
+                response.set_status(mobilecoind_api::QueuedPaymentStatus::PaymentFailed);
+                response.set_error(error);
+            }
+        }
+        Ok(response)
+    }
+
+    fn create_transfer_code_impl(
+        &mut self,
+        request: mobilecoind_api::CreateTransferCodeRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::CreateTransferCodeResponse, RpcStatus> {
+        // Get sender monitor id from request.
+        let sender_monitor_id = MonitorId::try_from(&request.sender_monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&sender_monitor_id, caller)?;
+
+        // Generate the one-time account holding the funds.
+        let mut rng = rand::thread_rng();
+        let root_id = RootIdentity::random(&mut rng, None);
+        let account_key = AccountKey::from(&root_id);
+        let outlay = Outlay {
+            receiver: account_key.default_subaddress(),
+            value: request.value,
+        };
+
+        // CreateTransferCode submits right away, leaving no opportunity to approve the transaction.
+        if self.approval_required(&[outlay.clone()]) {
+            return Err(RpcStatus::new(
+                RpcStatusCode::PERMISSION_DENIED,
+                Some(
+                    "value: transfer codes above the approval threshold must be generated with \
+                     GenerateTransferCodeTx and approved with ApproveTx"
+                        .to_string(),
+                ),
+            ));
+        }
+
+        // Check the memo before any funds are spent. The TxOut is filled in once it exists.
+        let mut transfer_payload =
+            TransferPayload::new_v1(&root_id.root_entropy, &[0u8; 32], request.get_memo())
+                .map_err(|_| {
+                    RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some("memo".to_string()))
+                })?;
+
+        // Attempt to construct a transaction.
+        let utxos = self
+            .mobilecoind_db
+            .get_utxos_for_subaddress(&sender_monitor_id, request.sender_subaddress)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_utxos_for_subaddress", err, &self.logger)
+            })?;
+        let tx_proposal = self
+            .transactions_manager
+            .build_transaction(
+                &sender_monitor_id,
+                request.sender_subaddress,
+                &utxos,
+                &[outlay],
+                request.fee,
+                request.tombstone,
+                0,
+                &CoinSelectionStrategy::default(),
+                deadline,
+            )
+            .map_err(|err| {
+                rpc_deadline_error("transactions_manager.build_transaction", err, &self.logger)
+            })?;
+
+        let tx_out = tx_proposal
+            .outlay_index_to_tx_out_index
+            .get(&0)
+            .and_then(|tx_out_index| tx_proposal.tx.prefix.outputs.get(*tx_out_index))
+            .cloned()
+            .ok_or_else(|| {
+                RpcStatus::new(
+                    RpcStatusCode::INTERNAL,
+                    Some("tx_proposal does not pay the transfer code".to_owned()),
+                )
+            })?;
+        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)
+            .map_err(|err| rpc_internal_error("ristretto_public.try_from", err, &self.logger))?;
+        transfer_payload.utxo = tx_public_key.to_bytes();
+
+        // The code is stored before its funds are sent, so that they can always be recovered.
+        let transfer_code = TransferCode {
+            entropy: root_id.root_entropy.to_vec(),
+            tx_out,
+            value: request.value,
+            memo: request.get_memo().to_owned(),
+            sender_monitor_id: Some(sender_monitor_id),
+            status: TransferCodeStatus::Created as i32,
+            redeemer_monitor_id: None,
+            redeem_tx_prefix_hash: vec![],
+        };
+        self.mobilecoind_db
+            .add_transfer_code(&transfer_code)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.add_transfer_code", err, &self.logger)
+            })?;
+
+        // Submit transaction.
+        let proto_tx_proposal = mobilecoind_api::TxProposal::from(&tx_proposal);
+        let mut submit_tx_request = mobilecoind_api::SubmitTxRequest::new();
+        submit_tx_request.set_tx_proposal(proto_tx_proposal.clone());
+        let mut submit_tx_response = self.submit_tx_impl(submit_tx_request, caller)?;
+
+        // Return response.
+        let mut response = mobilecoind_api::CreateTransferCodeResponse::new();
+        response.set_b58_code(transfer_payload.encode());
+        response.set_entropy(transfer_code.entropy);
+        response.set_tx_public_key((&tx_public_key).into());
+        response.set_memo(transfer_code.memo);
+        response.set_sender_tx_receipt(submit_tx_response.take_sender_tx_receipt());
+        response.set_tx_proposal(proto_tx_proposal);
+        Ok(response)
+    }
+
+    fn redeem_transfer_code_impl(
+        &mut self,
+        request: mobilecoind_api::RedeemTransferCodeRequest,
+        deadline: &Deadline,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::RedeemTransferCodeResponse, RpcStatus> {
+        // Get the monitor receiving the funds.
+        let monitor_id = MonitorId::try_from(&request.monitor_id)
+            .map_err(|err| rpc_internal_error("monitor_id.try_from.bytes", err, &self.logger))?;
+        self.authorize_monitor(&monitor_id, caller)?;
+
+        let monitor_data = self
+            .mobilecoind_db
+            .get_monitor_data(&monitor_id)
+            .map_err(|err| {
+                rpc_internal_error("mobilecoind_db.get_monitor_data", err, &self.logger)
+            })?;
+        if !monitor_data
+            .subaddress_indexes()
+            .contains(&request.subaddress_index)
+        {
+            return Err(RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("subaddress_index".to_string()),
+            ));
+        }
+
+        // Decode the code.
+        let transfer_payload = TransferPayload::decode(request.get_b58_code()).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("b58_code".to_string()),
+            )
+        })?;
+        let tx_public_key = RistrettoPublic::try_from(&transfer_payload.utxo).map_err(|_| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some("b58_code".to_string()),
+            )
+        })?;
+        let tx_public_key = CompressedRistrettoPublic::from(&tx_public_key);
+
+        // TODO: change to production AccountKey derivation
+        let account_key = AccountKey::from(&RootIdentity {
+            root_entropy: transfer_payload.entropy,
+            fog_url: None,
+        });
+
+        // Codes created by this mobilecoind know their TxOut, others are looked up in the ledger.
+        let mut transfer_code = match self.mobilecoind_db.get_transfer_code(&tx_public_key) {
+            Ok(transfer_code) => transfer_code,
+            Err(Error::TransferCodeNotFound) => {
+                let tx_out = self
+                    .transactions_manager
+                    .find_tx_out_by_public_key(&tx_public_key, deadline)
+                    .map_err(|err| {
+                        rpc_transfer_code_error(
+                            "transactions_manager.find_tx_out_by_public_key",
+                            err,
+                            &self.logger,
+                        )
+                    })?;
+                TransferCode {
+                    entropy: transfer_payload.entropy.to_vec(),
+                    tx_out,
+                    value: 0,
+                    memo: transfer_payload.memo.clone(),
+                    sender_monitor_id: None,
+                    status: TransferCodeStatus::Unknown as i32,
+                    redeemer_monitor_id: None,
+                    redeem_tx_prefix_hash: vec![],
+                }
+            }
+            Err(err) => {
+                return Err(rpc_internal_error(
+                    "mobilecoind_db.get_transfer_code",
+                    err,
+                    &self.logger,
+                ))
+            }
+        };
+
+        // Sweep the funds into the monitor.
+        let tx_proposal = self
+            .transactions_manager
+            .build_transfer_code_redemption_tx(
+                &account_key,
+                &transfer_code.tx_out,
+                &monitor_data.subaddress(request.subaddress_index),
+                request.fee,
+                request.tombstone,
+                deadline,
+            )
+            .map_err(|err| {
+                rpc_transfer_code_error(
+                    "transactions_manager.build_transfer_code_redemption_tx",
+                    err,
+                    &self.logger,
+                )
+            })?;
+
+        // The funds belong to no monitor, so the transaction neither needs approval nor updates
+        // any UnspentTxOut.
+        self.transactions_manager
+            .submit_tx_proposal(&tx_proposal)
+            .map_err(|err| {
+                rpc_submit_error("transactions_manager.submit_tx_proposal", err, &self.logger)
+            })?;
+
+        // The transaction was sent, so the error is only logged.
+        transfer_code.value = tx_proposal.utxos[0].value;
+        transfer_code.set_status(TransferCodeStatus::Redeemed);
+        transfer_code.redeemer_monitor_id = Some(monitor_id);
+        transfer_code.redeem_tx_prefix_hash = tx_proposal.tx.prefix.hash().to_vec();
+        if let Err(err) = self.mobilecoind_db.transfer_code_redeemed(&transfer_code) {
+            log::error!(
+                self.logger,
+                "failed recording redemption of transfer code {}: {:?}",
+                tx_public_key,
+                err
+            );
+        }
+
+        // Construct receipts.
+        let (sender_tx_receipt, mut receiver_tx_receipts) = get_tx_receipts(&tx_proposal)?;
+
+        // Return response.
+        let mut response = mobilecoind_api::RedeemTransferCodeResponse::new();
+        response.set_value(tx_proposal.outlays[0].value);
+        response.set_memo(transfer_payload.memo);
+        response.set_sender_tx_receipt(sender_tx_receipt);
+        response.set_receiver_tx_receipt(receiver_tx_receipts.remove(0));
+        response.set_tx_proposal((&tx_proposal).into());
+        Ok(response)
+    }
+
+    fn unlock_database_impl(
+        &mut self,
+        request: mobilecoind_api::UnlockDatabaseRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.forbid_tenants(caller, "database key")?;
+        let secret = database_secret_from_request(request.get_secret())?;
+
+        self.mobilecoind_db
+            .unlock(&secret)
+            .map_err(|err| rpc_database_key_error("mobilecoind_db.unlock", err, &self.logger))?;
+        Ok(mobilecoind_api::Empty::new())
+    }
+
+    fn reencrypt_database_impl(
+        &mut self,
+        request: mobilecoind_api::ReencryptDatabaseRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::Empty, RpcStatus> {
+        self.forbid_tenants(caller, "database key")?;
+        let secret = database_secret_from_request(request.get_secret())?;
+
+        self.mobilecoind_db
+            .reencrypt(&secret)
+            .map_err(|err| rpc_database_key_error("mobilecoind_db.reencrypt", err, &self.logger))?;
+        Ok(mobilecoind_api::Empty::new())
+    }
+
+    fn export_state_impl(
+        &mut self,
+        _request: mobilecoind_api::Empty,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ExportStateResponse, RpcStatus> {
+        self.forbid_tenants(caller, "mobilecoind state")?;
+
+        let (monitors, address_book) = self
+            .mobilecoind_db
+            .export_state()
+            .map_err(|err| rpc_internal_error("mobilecoind_db.export_state", err, &self.logger))?;
+
+        // Keys sealed by the signing enclave can only be unsealed on this machine.
+        let (skipped_monitors, monitors): (Vec<_>, Vec<_>) = monitors
+            .into_iter()
+            .partition(|monitor_state| monitor_state.monitor_data.is_sealed());
+
+        let contents = StateArchiveContents::new(monitors, address_book, &self.ledger_db)
+            .map_err(|err| rpc_internal_error("state_archive_contents.new", err, &self.logger))?;
+        let archive = StateArchive::new(&contents);
+
+        // The archive holds the account keys of the monitors.
+        for monitor_state in &contents.monitors {
+            let monitor_id = MonitorId::from(&monitor_state.monitor_data);
+            self.audit(&monitor_id, AuditAction::MonitorStateExported, caller)?;
+        }
+
+        let mut response = mobilecoind_api::ExportStateResponse::new();
+        response.set_archive(mcserial::encode(&archive));
+        response.set_num_blocks(contents.num_blocks);
+        response.set_skipped_monitor_ids(RepeatedField::from_vec(
+            skipped_monitors
+                .iter()
+                .map(|monitor_state| MonitorId::from(&monitor_state.monitor_data).to_vec())
+                .collect(),
+        ));
+        Ok(response)
+    }
+
+    fn import_state_impl(
+        &mut self,
+        request: mobilecoind_api::ImportStateRequest,
+        caller: &Caller,
+    ) -> Result<mobilecoind_api::ImportStateResponse, RpcStatus> {
+        self.forbid_tenants(caller, "mobilecoind state")?;
+
+        // Imported monitors hold their account key, which the signing enclave should hold instead.
+        if self.transactions_manager.signing_enclave().is_some() {
+            return Err(RpcStatus::new(
+                RpcStatusCode::FAILED_PRECONDITION,
+                Some(
+                    "State archives cannot be imported while the signing enclave is used"
+                        .to_string(),
+                ),
+            ));
+        }
+
+        let archive: StateArchive = mcserial::decode(request.get_archive()).map_err(|err| {
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("archive: {}", err)),
+            )
+        })?;
+        let contents = archive
+            .open()
+            .map_err(|err| rpc_state_archive_error("state_archive.open", err, &self.logger))?;
+        contents
+            .verify_against_ledger(&self.ledger_db)
+            .map_err(|err| {
+                rpc_state_archive_error(
+                    "state_archive_contents.verify_against_ledger",
+                    err,
+                    &self.logger,
+                )
+            })?;
+        let first_block = contents
+            .monitors
+            .iter()
+            .map(|monitor| monitor.monitor_data.next_block)
+            .min()
+            .unwrap_or(u64::MAX);
+        let _pruning_guard = self.check_not_pruned("archive", first_block)?;
+
+        let monitor_ids = self
+            .mobilecoind_db
+            .import_state(&contents.monitors, &contents.address_book)
+            .map_err(|err| {
+                rpc_state_archive_error("mobilecoind_db.import_state", err, &self.logger)
+            })?;
+
+        for monitor_id in &monitor_ids {
+            self.audit(monitor_id, AuditAction::MonitorStateImported, caller)?;
+        }
+
+        let mut response = mobilecoind_api::ImportStateResponse::new();
+        response.set_monitor_ids(RepeatedField::from_vec(
+            monitor_ids
+                .iter()
+                .map(|monitor_id| monitor_id.to_vec())
+                .collect(),
+        ));
+        response.set_num_address_book_entries(contents.address_book.len() as u64);
+        Ok(response)
+    }
+}
+
+/// The password or keyfile set in a request.
+fn database_secret_from_request(
+    secret: &mobilecoind_api::DatabaseSecret,
+) -> Result<DatabaseSecret, RpcStatus> {
+    match (
+        secret.get_password().is_empty(),
+        secret.get_keyfile().is_empty(),
+    ) {
+        (false, true) => Ok(DatabaseSecret::Password(secret.get_password().to_string())),
+        (true, false) => Ok(DatabaseSecret::Keyfile(secret.get_keyfile().to_vec())),
+        _ => Err(RpcStatus::new(
+            RpcStatusCode::INVALID_ARGUMENT,
+            Some("secret: exactly one of password and keyfile must be set".to_string()),
+        )),
+    }
+}
+
+/// Construct the sender and receiver receipts of a transaction.
+pub(crate) fn get_tx_receipts(
+    tx_proposal: &TxProposal,
+) -> Result<
+    (
+        mobilecoind_api::SenderTxReceipt,
+        Vec<mobilecoind_api::ReceiverTxReceipt>,
+    ),
+    RpcStatus,
+> {
+    // Construct sender receipt.
+    let mut sender_tx_receipt = mobilecoind_api::SenderTxReceipt::new();
+    sender_tx_receipt.set_key_image_list(RepeatedField::from_vec(
+        tx_proposal
+            .utxos
+            .iter()
+            .map(|utxo| (&utxo.key_image).into())
+            .collect(),
+    ));
+    sender_tx_receipt.set_tombstone(tx_proposal.tx.prefix.tombstone_block);
+    sender_tx_receipt.set_output_public_key_list(RepeatedField::from_vec(
+        tx_proposal
+            .tx
+            .prefix
+            .outputs
+            .iter()
+            .map(|tx_out| tx_out.public_key.into())
+            .collect(),
+    ));
+
+    // Construct receiver receipts.
+    let receiver_tx_receipts: Vec<_> = tx_proposal
+        .outlays
+        .iter()
+        .enumerate()
+        .map(|(outlay_index, outlay)| {
+            let tx_out_index = tx_proposal
+                .outlay_index_to_tx_out_index
+                .get(&outlay_index)
+                .ok_or_else(|| {
+                    RpcStatus::new(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        Some("outlay_index_to_tx_out_index".to_string()),
+                    )
+                })?;
+
+            let tx_out = tx_proposal
+                .tx
+                .prefix
+                .outputs
+                .get(*tx_out_index)
+                .ok_or_else(|| {
+                    RpcStatus::new(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        Some("outlay_index_to_tx_out_index".to_string()),
+                    )
+                })?;
+
+            let mut receiver_tx_receipt = mobilecoind_api::ReceiverTxReceipt::new();
+            receiver_tx_receipt.set_receipient((&outlay.receiver).into());
+            receiver_tx_receipt.set_tx_public_key(tx_out.public_key.into());
+            receiver_tx_receipt.set_tx_out_hash(tx_out.hash().to_vec());
+            receiver_tx_receipt.set_tombstone(tx_proposal.tx.prefix.tombstone_block);
+            if let Some(confirmation_number) =
+                tx_proposal.outlay_confirmation_numbers.get(outlay_index)
+            {
+                receiver_tx_receipt.set_confirmation_number(confirmation_number.to_vec());
+            }
+
+            Ok(receiver_tx_receipt)
+        })
+        .collect::<Result<Vec<mobilecoind_api::ReceiverTxReceipt>, RpcStatus>>()?;
+
+    Ok((sender_tx_receipt, receiver_tx_receipts))
+}
+
+/// The deadline the client set for an RPC call.
+fn rpc_deadline(ctx: &RpcContext) -> Deadline {
+    Deadline::Rpc(ctx.deadline())
+}
+
+/// Replies with the result of a request handled on a worker pool, once it is available.
+fn send_pooled_result<T: Send + 'static>(
+    ctx: RpcContext,
+    sink: UnarySink<T>,
+    result: oneshot::Receiver<Result<T, RpcStatus>>,
+    logger: Logger,
+) {
+    ctx.spawn(result.then(move |result| {
+        let result = result.unwrap_or_else(|_canceled| {
+            Err(RpcStatus::new(
+                RpcStatusCode::INTERNAL,
+                Some("worker pool shut down".to_string()),
+            ))
+        });
+        match result {
+            Ok(response) => sink.success(response),
+            Err(status) => sink.fail(status),
+        }
+        .map_err(move |err| log::error!(logger, "failed to reply: {:?}", err))
+    }))
+}
+
+/// Reports an error of an operation that honors a deadline. Running out of time is reported as
+/// DEADLINE_EXCEEDED, spending from a watch-only monitor, or from a sealed monitor without the
+/// signing enclave, as FAILED_PRECONDITION, anything else as an internal error.
+fn rpc_deadline_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::DeadlineExceeded => {
+            log::debug!(logger, "{}: deadline exceeded", context);
+            RpcStatus::new(RpcStatusCode::DEADLINE_EXCEEDED, Some(context.to_string()))
+        }
+        Error::WatchOnlyMonitor => RpcStatus::new(
+            RpcStatusCode::FAILED_PRECONDITION,
+            Some("Monitor is watch-only and cannot spend".to_string()),
+        ),
+        Error::NoSigningEnclave => RpcStatus::new(
+            RpcStatusCode::FAILED_PRECONDITION,
+            Some("Monitor is sealed and the signing enclave is not available".to_string()),
+        ),
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of approving or rejecting a transaction. Unknown transactions are reported
+/// as NOT_FOUND and decisions that are not allowed as PERMISSION_DENIED.
+fn rpc_approval_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::TxApprovalNotFound => {
+            RpcStatus::new(RpcStatusCode::NOT_FOUND, Some("tx_prefix_hash".to_string()))
+        }
+        Error::ApprovalNotPermitted(reason) => {
+            log::debug!(logger, "{}: {}", context, reason);
+            RpcStatus::new(RpcStatusCode::PERMISSION_DENIED, Some(reason))
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of submitting a transaction. Transactions that conflict with a transaction
+/// that is already pending in the network are reported as ABORTED.
+fn rpc_submit_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::AlreadyPending(num_key_images) => {
+            log::debug!(logger, "{}: {} key images pending", context, num_key_images);
+            RpcStatus::new(RpcStatusCode::ABORTED, Some(err.to_string()))
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of importing an account snapshot. Snapshots that fail verification are
+/// reported as INVALID_ARGUMENT.
+fn rpc_snapshot_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::AccountSnapshot(reason) => {
+            log::debug!(logger, "{}: {}", context, reason);
+            RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(reason))
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of importing a state archive. Archives that are corrupted or do not match the
+/// ledger are reported as INVALID_ARGUMENT, and monitors or labels that already exist as
+/// ALREADY_EXISTS.
+fn rpc_state_archive_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::StateArchive(reason) => {
+            log::debug!(logger, "{}: {}", context, reason);
+            RpcStatus::new(RpcStatusCode::INVALID_ARGUMENT, Some(reason))
+        }
+        Error::MonitorIdExists => RpcStatus::new(
+            RpcStatusCode::ALREADY_EXISTS,
+            Some("monitor_id".to_string()),
+        ),
+        Error::AddressBookLabelExists => {
+            RpcStatus::new(RpcStatusCode::ALREADY_EXISTS, Some("label".to_string()))
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of changing a monitor. Invalid arguments are reported as INVALID_ARGUMENT and
+/// unknown outputs as NOT_FOUND.
+fn rpc_monitor_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::UtxoIdNotFound => {
+            RpcStatus::new(RpcStatusCode::NOT_FOUND, Some("key_image".to_string()))
+        }
+        Error::InvalidArgument(argument, reason) => {
+            log::debug!(logger, "{}: {}: {}", context, argument, reason);
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("{}: {}", argument, reason)),
+            )
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of managing named accounts. Unknown accounts are reported as NOT_FOUND, names
+/// in use as ALREADY_EXISTS and invalid names as INVALID_ARGUMENT.
+fn rpc_account_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::AccountNotFound => {
+            RpcStatus::new(RpcStatusCode::NOT_FOUND, Some("name".to_string()))
+        }
+        Error::AccountNameExists => {
+            RpcStatus::new(RpcStatusCode::ALREADY_EXISTS, Some("name".to_string()))
+        }
+        Error::InvalidArgument(argument, reason) => {
+            log::debug!(logger, "{}: {}: {}", context, argument, reason);
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("{}: {}", argument, reason)),
+            )
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of managing the address book. Unknown labels are reported as NOT_FOUND,
+/// labels in use as ALREADY_EXISTS and invalid entries as INVALID_ARGUMENT.
+fn rpc_address_book_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::AddressBookEntryNotFound => {
+            RpcStatus::new(RpcStatusCode::NOT_FOUND, Some("label".to_string()))
+        }
+        Error::AddressBookLabelExists => {
+            RpcStatus::new(RpcStatusCode::ALREADY_EXISTS, Some("label".to_string()))
+        }
+        Error::InvalidArgument(argument, reason) => {
+            log::debug!(logger, "{}: {}: {}", context, argument, reason);
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("{}: {}", argument, reason)),
+            )
+        }
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Reports an error of redeeming a transfer code. Codes whose funds are not in the ledger are
+/// reported as FAILED_PRECONDITION, codes that were already redeemed as ALREADY_EXISTS and codes
+/// that do not hold their funds as INVALID_ARGUMENT.
+fn rpc_transfer_code_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::TransferCodeNotFunded => RpcStatus::new(
+            RpcStatusCode::FAILED_PRECONDITION,
+            Some("The funds of the transfer code are not in the ledger".to_string()),
+        ),
+        Error::TransferCodeRedeemed => {
+            RpcStatus::new(RpcStatusCode::ALREADY_EXISTS, Some("b58_code".to_string()))
+        }
+        Error::InvalidArgument(argument, reason) => {
+            log::debug!(logger, "{}: {}: {}", context, argument, reason);
+            RpcStatus::new(
+                RpcStatusCode::INVALID_ARGUMENT,
+                Some(format!("b58_code: {}", reason)),
+            )
+        }
+        err => rpc_deadline_error(context, err, logger),
+    }
+}
+
+fn rpc_database_key_error(context: &str, err: Error, logger: &Logger) -> RpcStatus {
+    match err {
+        Error::WrongDatabaseKey => RpcStatus::new(
+            RpcStatusCode::PERMISSION_DENIED,
+            Some("secret: wrong password or keyfile".to_string()),
+        ),
+        Error::DatabaseLocked => RpcStatus::new(
+            RpcStatusCode::FAILED_PRECONDITION,
+            Some("The database must be unlocked first".to_string()),
+        ),
+        Error::DatabaseNotEncrypted => RpcStatus::new(
+            RpcStatusCode::FAILED_PRECONDITION,
+            Some("The database is not encrypted".to_string()),
+        ),
+        Error::InvalidArgument(_, reason) | Error::DatabaseKeyDerivation(reason) => RpcStatus::new(
+            RpcStatusCode::INVALID_ARGUMENT,
+            Some(format!("secret: {}", reason)),
+        ),
+        err => rpc_internal_error(context, err, logger),
+    }
+}
+
+/// Rejects access to something owned by a tenant outside of `scope`.
+fn check_tenant(scope: &TenantScope, owner: &str, argument: &str) -> Result<(), RpcStatus> {
+    if scope.contains(owner) {
+        Ok(())
+    } else {
+        Err(RpcStatus::new(
+            RpcStatusCode::PERMISSION_DENIED,
+            Some(format!("{}: owned by another tenant", argument)),
+        ))
+    }
+}
+
+/// The identity of callers that did not send an API token.
+const ANONYMOUS_CALLER: &str = "anonymous";
+
+/// Who made an RPC call, as recorded in the monitor audit log and in transaction approvals.
+struct Caller {
+    /// A fingerprint of the API token sent in the `authorization` header, or "anonymous".
+    identity: String,
+
+    /// The hash of the API token, which binds the caller to a tenant.
+    token_hash: Option<TokenHash>,
+
+    /// The network address the call came from.
+    peer: String,
+}
+
+impl Caller {
+    /// Returns true if the caller sent an API token.
+    fn is_authenticated(&self) -> bool {
+        self.identity != ANONYMOUS_CALLER
+    }
+}
+
+/// Identifies the caller of an RPC. The API token itself is never stored, only the first bytes
+/// of its hash, which is enough to tell tokens apart.
+fn rpc_caller(ctx: &RpcContext) -> Caller {
+    let token = ctx
+        .request_headers()
+        .iter()
+        .find(|(key, _value)| key.eq_ignore_ascii_case("authorization"))
+        .map(|(_key, value)| {
+            let value = String::from_utf8_lossy(value);
+            value.trim_start_matches("Bearer ").trim().to_string()
+        })
+        .filter(|token| !token.is_empty());
+
+    let token_hash = token.map(|token| token_hash(&token));
+    let identity = match token_hash.as_ref() {
+        Some(hash) => format!("token:{}", hex::encode(&hash[..8])),
+        None => ANONYMOUS_CALLER.to_string(),
+    };
+
+    Caller {
+        identity,
+        token_hash,
+        peer: ctx.peer(),
+    }
+}
+
+// Handlers listed with trailing functions are also passed what they extract from the call
+// context, in order: `rpc_caller` tells them who made the call, so that they can record it in the
+// audit log and in transaction approvals and restrict it to its tenant, and `rpc_deadline` lets
+// long running handlers give up once the client stops waiting.
+macro_rules! build_api {
+    // Handles a request on the API thread it arrived on.
+    (@dispatch $api:ident $ctx:ident $request:ident $sink:ident $logger:ident $service_function_impl:ident [$($caller_fn:ident)*]) => {{
+        let result = $api.$service_function_impl($request $(, &$caller_fn(&$ctx))*);
+        send_result($ctx, $sink, result, &$logger)
+    }};
+
+    // Hands a request over to a worker pool, freeing up the API thread. The caller information
+    // is extracted beforehand, since the context stays on the API thread.
+    (@dispatch $api:ident $ctx:ident $request:ident $sink:ident $logger:ident $service_function_impl:ident [$($caller_fn:ident)*] $pool:ident) => {{
+        $( let $caller_fn = $caller_fn(&$ctx); )*
+        let mut api = $api.clone();
+        let result = $api
+            .$pool
+            .spawn(move || api.$service_function_impl($request $(, &$caller_fn)*));
+        send_pooled_result($ctx, $sink, result, $logger)
+    }};
+
+    ($( $service_function_name:ident $service_request_type:ident $service_response_type:ident $service_function_impl:ident $($caller_fn:ident)* $(=> $pool:ident)? ),+)
+    =>
+    (
+        impl<T: UserTxConnection + 'static> MobilecoindApi for ServiceApi<T> {
+            $(
+                fn $service_function_name(
+                    &mut self,
+                    ctx: RpcContext,
+                    request: mobilecoind_api::$service_request_type,
+                    sink: UnarySink<mobilecoind_api::$service_response_type>,
+                ) {
+                    let logger = rpc_logger(&ctx, &self.logger);
+                    let _task_guard = self.api_metrics.start_task();
+                    build_api!(@dispatch self ctx request sink logger $service_function_impl [$($caller_fn)*] $($pool)?)
+                }
+            )+
+
+            // Streaming handlers manage their sink themselves.
+            fn stream_blocks(
+                &mut self,
+                ctx: RpcContext,
+                request: mobilecoind_api::StreamBlocksRequest,
+                sink: ServerStreamingSink<mobilecoind_api::StreamBlocksResponse>,
+            ) {
+                self.stream_blocks_impl(ctx, request, sink)
+            }
+        }
+    );
+}
+
+build_api! {
+    add_monitor AddMonitorRequest AddMonitorResponse add_monitor_impl rpc_caller,
+    remove_monitor RemoveMonitorRequest RemoveMonitorResponse remove_monitor_impl rpc_caller,
+    get_monitor_list Empty GetMonitorListResponse get_monitor_list_impl rpc_caller,
+    get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse get_monitor_status_impl rpc_caller,
+    set_monitor_priority SetMonitorPriorityRequest Empty set_monitor_priority_impl rpc_caller,
+    rescan_monitor RescanMonitorRequest Empty rescan_monitor_impl rpc_caller,
+    update_monitor_metadata UpdateMonitorMetadataRequest Empty update_monitor_metadata_impl rpc_caller,
+    get_monitor_audit_log GetMonitorAuditLogRequest GetMonitorAuditLogResponse get_monitor_audit_log_impl rpc_caller,
+    export_account_snapshot ExportAccountSnapshotRequest ExportAccountSnapshotResponse export_account_snapshot_impl rpc_caller,
+    import_account_snapshot ImportAccountSnapshotRequest ImportAccountSnapshotResponse import_account_snapshot_impl rpc_caller,
+    create_account CreateAccountRequest CreateAccountResponse create_account_impl rpc_caller,
+    list_accounts Empty ListAccountsResponse list_accounts_impl rpc_caller,
+    remove_account RemoveAccountRequest Empty remove_account_impl rpc_caller,
+    add_address_book_entry AddAddressBookEntryRequest Empty add_address_book_entry_impl rpc_caller,
+    update_address_book_entry UpdateAddressBookEntryRequest Empty update_address_book_entry_impl rpc_caller,
+    get_address_book_entry GetAddressBookEntryRequest GetAddressBookEntryResponse get_address_book_entry_impl rpc_caller,
+    get_address_book Empty GetAddressBookResponse get_address_book_impl rpc_caller,
+    remove_address_book_entry RemoveAddressBookEntryRequest Empty remove_address_book_entry_impl rpc_caller,
+    get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse get_unspent_tx_out_list_impl rpc_caller,
+    update_utxo_memo UpdateUtxoMemoRequest Empty update_utxo_memo_impl rpc_caller,
+    get_processed_tx_out_history GetProcessedTxOutHistoryRequest GetProcessedTxOutHistoryResponse get_processed_tx_out_history_impl rpc_caller,
+    get_incoming_payment_list GetIncomingPaymentListRequest GetIncomingPaymentListResponse get_incoming_payment_list_impl rpc_caller,
+    get_transaction_history GetTransactionHistoryRequest GetTransactionHistoryResponse get_transaction_history_impl rpc_caller,
+    get_spent_tx_outs GetSpentTxOutsRequest GetSpentTxOutsResponse get_spent_tx_outs_impl rpc_caller,
+    generate_entropy Empty GenerateEntropyResponse generate_entropy_impl,
+    get_account_key GetAccountKeyRequest GetAccountKeyResponse get_account_key_impl,
+    get_public_address GetPublicAddressRequest GetPublicAddressResponse get_public_address_impl rpc_caller,
+    read_request_code ReadRequestCodeRequest ReadRequestCodeResponse read_request_code_impl,
+    get_request_code GetRequestCodeRequest GetRequestCodeResponse get_request_code_impl,
+    read_transfer_code ReadTransferCodeRequest ReadTransferCodeResponse read_transfer_code_impl,
+    get_transfer_code GetTransferCodeRequest GetTransferCodeResponse get_transfer_code_impl,
+    create_request CreateRequestRequest CreateRequestResponse create_request_impl,
+    parse_request ParseRequestRequest ParseRequestResponse parse_request_impl,
+    create_mob_uri CreateMobUriRequest CreateMobUriResponse create_mob_uri_impl,
+    parse_mob_uri ParseMobUriRequest ParseMobUriResponse parse_mob_uri_impl,
+    generate_tx GenerateTxRequest GenerateTxResponse generate_tx_impl rpc_deadline rpc_caller => tx_build_pool,
+    generate_optimization_tx GenerateOptimizationTxRequest GenerateOptimizationTxResponse generate_optimization_tx_impl rpc_deadline rpc_caller => tx_build_pool,
+    generate_transfer_code_tx GenerateTransferCodeTxRequest GenerateTransferCodeTxResponse generate_transfer_code_tx_impl rpc_deadline rpc_caller => tx_build_pool,
+    submit_tx SubmitTxRequest SubmitTxResponse submit_tx_impl rpc_caller,
+    verify_tx_proposal VerifyTxProposalRequest VerifyTxProposalResponse verify_tx_proposal_impl => tx_build_pool,
+    generate_unsigned_tx GenerateUnsignedTxRequest GenerateUnsignedTxResponse generate_unsigned_tx_impl rpc_deadline rpc_caller => tx_build_pool,
+    sign_tx SignTxRequest SignTxResponse sign_tx_impl => tx_build_pool,
+    approve_tx ApproveTxRequest ApproveTxResponse approve_tx_impl rpc_caller,
+    reject_tx RejectTxRequest RejectTxResponse reject_tx_impl rpc_caller,
+    get_tx_approval_list GetTxApprovalListRequest GetTxApprovalListResponse get_tx_approval_list_impl rpc_caller,
+    get_ledger_info Empty GetLedgerInfoResponse get_ledger_info_impl,
+    get_block_info GetBlockInfoRequest GetBlockInfoResponse get_block_info_impl,
+    get_block_signature GetBlockSignatureRequest GetBlockSignatureResponse get_block_signature_impl,
+    get_processed_block GetProcessedBlockRequest GetProcessedBlockResponse get_processed_block_impl rpc_caller,
+    get_tx_status_as_sender GetTxStatusAsSenderRequest GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
+    get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl rpc_caller,
+    get_tx_out_results_by_public_key GetTxOutResultsByPublicKeyRequest GetTxOutResultsByPublicKeyResponse get_tx_out_results_by_public_key_impl,
+    get_membership_proofs GetMembershipProofsRequest GetMembershipProofsResponse get_membership_proofs_impl,
+    get_tx_outs_by_block_range GetTxOutsByBlockRangeRequest GetTxOutsByBlockRangeResponse get_tx_outs_by_block_range_impl,
+    get_block_by_key_image GetBlockByKeyImageRequest GetBlockByKeyImageResponse get_block_by_key_image_impl,
+    get_outgoing_tx_list GetOutgoingTxListRequest GetOutgoingTxListResponse get_outgoing_tx_list_impl rpc_caller,
+    get_outgoing_tx GetOutgoingTxRequest GetOutgoingTxResponse get_outgoing_tx_impl rpc_caller,
+    get_balance GetBalanceRequest GetBalanceResponse get_balance_impl rpc_caller,
+    get_balance_by_subaddress GetBalanceBySubaddressRequest GetBalanceBySubaddressResponse get_balance_by_subaddress_impl rpc_caller,
+    get_balance_by_subaddress_list GetBalanceBySubaddressListRequest GetBalanceBySubaddressListResponse get_balance_by_subaddress_list_impl rpc_caller,
+    send_payment SendPaymentRequest SendPaymentResponse send_payment_impl rpc_deadline rpc_caller => tx_build_pool,
+    get_queued_payment_status GetQueuedPaymentStatusRequest GetQueuedPaymentStatusResponse get_queued_payment_status_impl rpc_caller,
+    create_transfer_code CreateTransferCodeRequest CreateTransferCodeResponse create_transfer_code_impl rpc_deadline rpc_caller => tx_build_pool,
+    redeem_transfer_code RedeemTransferCodeRequest RedeemTransferCodeResponse redeem_transfer_code_impl rpc_deadline rpc_caller => tx_build_pool,
+    unlock_database UnlockDatabaseRequest Empty unlock_database_impl rpc_caller,
+    reencrypt_database ReencryptDatabaseRequest Empty reencrypt_database_impl rpc_caller,
+    export_state Empty ExportStateResponse export_state_impl rpc_caller,
+    import_state ImportStateRequest ImportStateResponse import_state_impl rpc_caller
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        block_timestamp_store::{BlockTimestamp, TimestampConfidence},
+        payments::DEFAULT_NEW_TX_BLOCK_ATTEMPTS,
+        test_utils::{
+            self, add_block_to_ledger_db, get_testing_environment, wait_for_monitors,
+            PER_RECIPIENT_AMOUNT,
+        },
+        utxo_store::{UnspentTxOut, MAX_UTXO_MEMO_LENGTH},
+    };
+    use common::{logger::test_with_logger, HashSet};
+    use grpcio::{CallOption, MetadataBuilder};
+    use keys::{Ed25519Pair, FromRandom, RistrettoPrivate};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::{convert::TryFrom, iter::FromIterator};
+    use tempdir::TempDir;
+    use transaction::{
+        account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
+        constants::{BASE_FEE, MAX_INPUTS, RING_SIZE},
+        dust::{DustPolicy, DustRules},
+        get_tx_out_shared_secret,
+        onetime_keys::{compute_key_image, recover_onetime_private_key},
+        tx::{Tx, TxOut, TxOutMembershipProof},
+        Block, BlockContents, BlockIndex, BlockSignature, BLOCK_VERSION,
+    };
+
+    #[test_with_logger]
+    fn test_add_monitor_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Three random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Create request for adding a new monitor.
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+            1,                        // num_subaddresses
+            0,                        // first_block
+        )
+        .expect("failed to create data");
+
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key(mobilecoind_api::AccountKey::from(
+            data.account_key().unwrap(),
+        ));
+        request.set_first_subaddress(data.first_subaddress);
+        request.set_num_subaddresses(data.num_subaddresses);
+        request.set_first_block(data.first_block);
+
+        // Send request.
+        let response = client.add_monitor(&request).expect("failed to add monitor");
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Compare the MonitorId we got back to the value we expected.
+        let monitor_id = MonitorId::try_from(&response.monitor_id)
+            .expect("failed to convert response to MonitorId");
+        let expected_monitor_id = MonitorId::from(&data);
+
+        assert_eq!(expected_monitor_id, monitor_id);
+    }
+
+    #[test_with_logger]
+    // Monitors with a gap limit should discover their subaddress range when none is given.
+    fn test_add_monitor_discovering_subaddresses(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Without a gap limit, the range must be given.
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key((&account_key).into());
+        request.set_first_subaddress(DEFAULT_SUBADDRESS_INDEX);
+        assert!(client.add_monitor(&request).is_err());
+
+        request.set_gap_limit(5);
+        let response = client.add_monitor(&request).unwrap();
+        let monitor_id = MonitorId::try_from(&response.monitor_id).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The default subaddress received funds, so the 5 subaddresses past it are watched.
+        let data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(
+            data.subaddress_indexes(),
+            DEFAULT_SUBADDRESS_INDEX..DEFAULT_SUBADDRESS_INDEX + 6
+        );
+    }
+
+    #[test_with_logger]
+    // Monitors of accounts created after some blocks were seen should skip those blocks.
+    fn test_add_monitor_with_account_created_at(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+
+        // Three random recipients and no monitors.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        for (block_index, timestamp) in &[(0, 1000), (1, 2000), (2, 3000)] {
+            mobilecoind_db
+                .observe_block_timestamp(
+                    *block_index,
+                    &BlockTimestamp::new(*timestamp, TimestampConfidence::Quorum),
+                )
+                .unwrap();
+        }
+
+        let add_monitor = |account_created_at: u64, first_block: u64| {
+            let mut request = mobilecoind_api::AddMonitorRequest::new();
+            request.set_account_key(mobilecoind_api::AccountKey::from(&AccountKey::random(
+                &mut StdRng::from_seed([account_created_at as u8; 32]),
+            )));
+            request.set_num_subaddresses(1);
+            request.set_first_block(first_block);
+            request.set_account_created_at(account_created_at);
+            let response = client.add_monitor(&request).expect("failed to add monitor");
+
+            let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+            request.set_monitor_id(response.monitor_id);
+            client
+                .get_monitor_status(&request)
+                .expect("failed to get monitor status")
+                .get_status()
+                .first_block
+        };
+
+        // Blocks 0 and 1 were seen before the account was created.
+        assert_eq!(add_monitor(2500, 0), 2);
+
+        // A later first block is kept.
+        assert_eq!(add_monitor(2600, 3), 3);
+
+        // No block was seen before the account was created.
+        assert_eq!(add_monitor(500, 0), 0);
+    }
+
+    #[test_with_logger]
+    fn test_remove_monitor_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([22u8; 32]);
+
+        // 10 random recipients and no monitors.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
+        assert_eq!(0, monitors_map.len());
+
+        // Add some monitors directly to the database.
+        let monitors_to_add = 10;
+        let monitor_ids: Vec<MonitorId> = (0..monitors_to_add)
+            .map(|_i| {
+                let data = MonitorData::new(
+                    AccountKey::random(&mut rng),
+                    DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+                    1,                        // num_subaddresses
+                    0,                        // first_block
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+
+        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
+        assert_eq!(monitors_to_add, monitors_map.len());
+
+        // Remove all the monitors we added.
+        for id in monitor_ids {
+            let mut request = mobilecoind_api::RemoveMonitorRequest::new();
+            request.set_monitor_id(id.to_vec());
+            client
+                .remove_monitor(&request)
+                .expect("failed to remove monitor");
+        }
+
+        // Check that no monitors remain.
+        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
+        assert_eq!(0, monitors_map.len());
+    }
+
+    #[test_with_logger]
+    fn test_remove_monitor_with_archive(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([22u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(account_key, 0, 1, 0).unwrap())
+            .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let (monitor_data, utxos) = mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap();
+        let history = mobilecoind_db
+            .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+            .unwrap();
+        assert!(!history.is_empty());
+
+        // Remove the monitor along with its history, archiving it first.
+        let mut request = mobilecoind_api::RemoveMonitorRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_remove_history(true);
+        request.set_archive(true);
+        let response = client
+            .remove_monitor(&request)
+            .expect("failed to remove monitor");
+
+        assert!(mobilecoind_db.get_monitor_data(&monitor_id).is_err());
+        assert!(mobilecoind_db
+            .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+            .unwrap()
+            .is_empty());
+        assert!(mobilecoind_db
+            .get_incoming_payment_list(&monitor_id, 0, usize::MAX)
+            .unwrap()
+            .is_empty());
+
+        // The archive restores the monitor as it was.
+        let archive: StateArchive = mcserial::decode(response.get_archive()).unwrap();
+        let contents = archive.open().unwrap();
+        assert_eq!(contents.monitors.len(), 1);
+        assert!(contents.address_book.is_empty());
+
+        let mut request = mobilecoind_api::ImportStateRequest::new();
+        request.set_archive(response.get_archive().to_vec());
+        let response = client.import_state(&request).unwrap();
+        assert_eq!(response.get_monitor_ids(), &[monitor_id.to_vec()][..]);
+        assert_eq!(
+            mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap(),
+            (monitor_data, utxos)
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+                .unwrap(),
+            history
+        );
+
+        // Without an archive, nothing is returned.
+        let mut request = mobilecoind_api::RemoveMonitorRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let response = client
+            .remove_monitor(&request)
+            .expect("failed to remove monitor");
+        assert!(response.get_archive().is_empty());
+    }
+
+    #[test_with_logger]
+    fn test_update_monitor_metadata(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([88u8; 32]);
+
+        // Three random recipients and no monitors.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key((&AccountKey::random(&mut rng)).into());
+        request.set_num_subaddresses(1);
+        request
+            .mut_labels()
+            .insert("customer_id".to_string(), "1234".to_string());
+        request.set_metadata(r#"{"tier": "gold"}"#.to_string());
+        let monitor_id = client
+            .add_monitor(&request)
+            .expect("failed to add monitor")
+            .monitor_id;
+
+        let mut status_request = mobilecoind_api::GetMonitorStatusRequest::new();
+        status_request.set_monitor_id(monitor_id.clone());
+        let status = client
+            .get_monitor_status(&status_request)
+            .expect("failed to get monitor status")
+            .take_status();
+        assert_eq!(status.get_labels().get("customer_id").unwrap(), "1234");
+        assert_eq!(status.metadata, r#"{"tier": "gold"}"#);
+
+        // The labels and metadata are replaced.
+        let mut request = mobilecoind_api::UpdateMonitorMetadataRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        request
+            .mut_labels()
+            .insert("customer_id".to_string(), "5678".to_string());
+        client
+            .update_monitor_metadata(&request)
+            .expect("failed to update monitor metadata");
+
+        let status = client
+            .get_monitor_status(&status_request)
+            .expect("failed to get monitor status")
+            .take_status();
+        assert_eq!(status.get_labels().len(), 1);
+        assert_eq!(status.get_labels().get("customer_id").unwrap(), "5678");
+        assert_eq!(status.metadata, "");
+
+        // The change is audited, along with the monitor being added and its status, which holds
+        // its account key, being read.
+        let monitor_id = MonitorId::try_from(&monitor_id).unwrap();
+        let audit_actions = || -> Vec<AuditAction> {
+            mobilecoind_db
+                .get_audit_log(Some(&monitor_id), 0, usize::MAX)
+                .unwrap()
+                .iter()
+                .map(AuditEvent::get_action)
+                .collect()
+        };
+        let expected_actions = vec![
+            AuditAction::MonitorAdded,
+            AuditAction::MonitorExported,
+            AuditAction::MonitorMetadataUpdated,
+            AuditAction::MonitorExported,
+        ];
+        assert_eq!(audit_actions(), expected_actions);
+
+        // Metadata that is not JSON is rejected, and nothing is audited.
+        request.set_metadata("{tier".to_string());
+        match client.update_monitor_metadata(&request) {
+            Err(grpcio::Error::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.status, RpcStatusCode::INVALID_ARGUMENT);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(audit_actions(), expected_actions);
+    }
+
+    #[test_with_logger]
+    fn test_rescan_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([77u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(account_key, 0, 1, 0).unwrap())
+            .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let (monitor_data, utxos) = mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap();
+        assert!(!utxos.is_empty());
+        let history = mobilecoind_db
+            .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+            .unwrap();
+
+        // A monitor cannot be moved past the blocks it scanned.
+        let mut request = mobilecoind_api::RescanMonitorRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        request.set_from_block(monitor_data.next_block + 1);
+        match client.rescan_monitor(&request) {
+            Err(grpcio::Error::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.status, RpcStatusCode::INVALID_ARGUMENT);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Scanning the whole ledger again ends up with the same outputs and history.
+        request.set_from_block(0);
+        client
+            .rescan_monitor(&request)
+            .expect("failed to rescan monitor");
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        assert_eq!(
+            mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap(),
+            (monitor_data, utxos)
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+                .unwrap(),
+            history
+        );
+
+        let audit_log = mobilecoind_db
+            .get_audit_log(Some(&monitor_id), 0, usize::MAX)
+            .unwrap();
+        assert_eq!(
+            audit_log.last().unwrap().get_action(),
+            AuditAction::MonitorRescanned
+        );
+    }
+
+    #[test_with_logger]
+    fn test_monitor_audit_log(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // Three random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+            1,                        // num_subaddresses
+            0,                        // first_block
+        )
+        .unwrap();
+
+        // Add the monitor with an API token.
+        let mut headers = MetadataBuilder::with_capacity(1);
+        headers
+            .add_str("authorization", "Bearer secret-token")
+            .unwrap();
+        let call_option = CallOption::default().headers(headers.build());
+
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key(mobilecoind_api::AccountKey::from(
+            data.account_key().unwrap(),
+        ));
+        request.set_first_subaddress(data.first_subaddress);
+        request.set_num_subaddresses(data.num_subaddresses);
+        request.set_first_block(data.first_block);
+        let response = client
+            .add_monitor_opt(&request, call_option)
+            .expect("failed to add monitor");
+        let monitor_id = response.monitor_id;
+
+        // Read its status and remove it, anonymously.
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        client
+            .get_monitor_status(&request)
+            .expect("failed to get monitor status");
+
+        let mut request = mobilecoind_api::RemoveMonitorRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        client
+            .remove_monitor(&request)
+            .expect("failed to remove monitor");
+
+        // The audit log outlives the monitor.
+        let mut request = mobilecoind_api::GetMonitorAuditLogRequest::new();
+        request.set_monitor_id(monitor_id.clone());
+        let response = client
+            .get_monitor_audit_log(&request)
+            .expect("failed to get audit log");
+        let events = response.get_event_list();
+
+        let actions: Vec<mobilecoind_api::MonitorAuditAction> =
+            events.iter().map(|event| event.get_action()).collect();
+        assert_eq!(
+            actions,
+            vec![
+                mobilecoind_api::MonitorAuditAction::MonitorAdded,
+                mobilecoind_api::MonitorAuditAction::MonitorExported,
+                mobilecoind_api::MonitorAuditAction::MonitorRemoved,
+            ]
+        );
+        for event in events {
+            assert_eq!(event.get_monitor_id(), &monitor_id[..]);
+            assert!(event.get_timestamp() > 0);
+            assert!(!event.get_peer().is_empty());
+        }
+
+        // The token is identified by a fingerprint, never stored as-is.
+        assert!(events[0].get_caller().starts_with("token:"));
+        assert!(!events[0].get_caller().contains("secret-token"));
+        assert_eq!(events[1].get_caller(), "anonymous");
+        assert_eq!(events[2].get_caller(), "anonymous");
+    }
+
+    #[test_with_logger]
+    fn test_get_monitor_list_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([21u8; 32]);
+
+        // 10 random recipients and no monitors.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Add some new monitors directly to the database.
+        let monitors_to_add = 10;
+        let monitor_ids: Vec<MonitorId> = (0..monitors_to_add)
+            .map(|_i| {
+                let data = MonitorData::new(
+                    AccountKey::random(&mut rng),
+                    DEFAULT_SUBADDRESS_INDEX, // first_subaddress
+                    1,                        // num_subaddresses
+                    0,                        // first_block
+                )
+                .unwrap();
+                let id = mobilecoind_db.add_monitor(&data).unwrap();
+                log::debug!(logger, "adding monitor {}", id,);
+                id
+            })
+            .collect();
+
+        // Ask the api for a list of all monitors.
+        let response = client
+            .get_monitor_list(&mobilecoind_api::Empty::new())
+            .expect("failed to get monitor list");
+
+        let monitor_id_list: Vec<MonitorId> = response
+            .monitor_id_list
+            .iter()
+            .map(|bytes| {
+                let id =
+                    MonitorId::try_from(bytes).expect("failed to convert response to MonitorId");
+                log::debug!(logger, "found monitor {}", id,);
+                id
+            })
+            .collect();
+
+        // Check monitor count.
+        assert_eq!(monitors_to_add, monitor_id_list.len());
+
+        // Check that all new monitors are present.
+        assert_eq!(
+            HashSet::from_iter(monitor_id_list.iter()),
+            HashSet::from_iter(monitor_ids.iter()),
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_monitor_status_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // 10 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let data = MonitorData::new(
+            AccountKey::random(&mut rng),
+            10, // first_subaddress
+            20, // num_subaddresses
+            30, // first_block
+        )
+        .unwrap();
+
+        // Insert into database.
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Query monitor status.
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(id.to_vec());
+
+        let response = client
+            .get_monitor_status(&request)
+            .expect("failed to get monitor status");
+        let status = response.status.as_ref().expect("no status in response");
+
+        // Verify the data we got matches what we expected
+        assert_eq!(
+            data.account_key().unwrap(),
+            &AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap(),
+        );
+        assert_eq!(status.first_subaddress, data.first_subaddress);
+        assert_eq!(status.num_subaddresses, data.num_subaddresses);
+        assert_eq!(status.first_block, data.first_block);
+        assert_eq!(status.next_block, data.next_block);
+        assert_eq!(
+            status.priority,
+            mobilecoind_api::MonitorPriority::MonitorPriorityNormal
+        );
+
+        // The priority can be changed.
+        let mut priority_request = mobilecoind_api::SetMonitorPriorityRequest::new();
+        priority_request.set_monitor_id(id.to_vec());
+        priority_request.set_priority(mobilecoind_api::MonitorPriority::MonitorPriorityHigh);
+        client
+            .set_monitor_priority(&priority_request)
+            .expect("failed to set monitor priority");
+        assert_eq!(
+            mobilecoind_db
+                .get_audit_log(Some(&id), 0, usize::MAX)
+                .unwrap()
+                .last()
+                .unwrap()
+                .get_action(),
+            AuditAction::MonitorPriorityChanged
+        );
+
+        let response = client
+            .get_monitor_status(&request)
+            .expect("failed to get monitor status");
+        assert_eq!(
+            response.get_status().priority,
+            mobilecoind_api::MonitorPriority::MonitorPriorityHigh
+        );
+        assert_eq!(
+            mobilecoind_db.get_monitor_data(&id).unwrap().get_priority(),
+            MonitorPriority::High
+        );
+
+        // Calling get_monitor_status for nonexistent or invalid monitor_id should return an error.
+        mobilecoind_db.remove_monitor(&id).unwrap();
+
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(id.to_vec());
+        assert!(client.get_monitor_status(&request).is_err());
+
+        let request = mobilecoind_api::GetMonitorStatusRequest::new();
+        assert!(client.get_monitor_status(&request).is_err());
+
+        assert!(client.set_monitor_priority(&priority_request).is_err());
+
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(vec![3; 3]);
+        assert!(client.get_monitor_status(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_get_unspent_tx_out_list_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Query for unspent tx outs for a subaddress that did not receive any tx outs.
+        let mut request = mobilecoind_api::GetUnspentTxOutListRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(1);
+
+        let response = client
+            .get_unspent_tx_out_list(&request)
+            .expect("failed to get unspent tx out list");
+
+        assert_eq!(response.output_list.to_vec(), vec![]);
+
+        // Query wit hthe correct subaddress index.
+        let mut request = mobilecoind_api::GetUnspentTxOutListRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+
+        let response = client
+            .get_unspent_tx_out_list(&request)
+            .expect("failed to get unspent tx out list");
+
+        let utxos: Vec<UnspentTxOut> = response
+            .output_list
+            .iter()
+            .map(|proto_utxo| {
+                UnspentTxOut::try_from(proto_utxo).expect("failed converting proto utxo")
+            })
+            .collect();
+
+        // Verify the data we got matches what we expected. This assumes knowledge about how the
+        // test ledger is constructed by the test utils.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let account_tx_outs: Vec<TxOut> = (0..num_blocks)
+            .map(|idx| {
+                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
+                // We grab the 4th tx out in each block since the test ledger had 3 random
+                // recipients, followed by our known recipient.
+                // See the call to `get_testing_environment` at the beginning of the test.
+                block_contents.outputs[3].clone()
+            })
+            .collect();
+
+        let expected_utxos: Vec<UnspentTxOut> = account_tx_outs
+            .iter()
+            .map(|tx_out| {
+                // Calculate the key image for this tx out.
+                let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    account_key.view_private_key(),
+                    &account_key.subaddress_spend_key(0),
+                );
+                let key_image = compute_key_image(&onetime_private_key);
+
+                // Craft the expected UnspentTxOut
+                UnspentTxOut {
+                    tx_out: tx_out.clone(),
+                    subaddress_index: 0,
+                    key_image,
+                    value: test_utils::PER_RECIPIENT_AMOUNT,
+                    attempted_spend_height: 0,
+                    attempted_spend_tombstone: 0,
+                    memo: String::new(),
                 }
-            )+
-        }
-    );
-}
+            })
+            .collect();
 
-build_api! {
-    add_monitor AddMonitorRequest AddMonitorResponse add_monitor_impl,
-    remove_monitor RemoveMonitorRequest Empty remove_monitor_impl,
-    get_monitor_list Empty GetMonitorListResponse get_monitor_list_impl,
-    get_monitor_status GetMonitorStatusRequest GetMonitorStatusResponse get_monitor_status_impl,
-    get_unspent_tx_out_list GetUnspentTxOutListRequest GetUnspentTxOutListResponse get_unspent_tx_out_list_impl,
-    generate_entropy Empty GenerateEntropyResponse generate_entropy_impl,
-    get_account_key GetAccountKeyRequest GetAccountKeyResponse get_account_key_impl,
-    get_public_address GetPublicAddressRequest GetPublicAddressResponse get_public_address_impl,
-    read_request_code ReadRequestCodeRequest ReadRequestCodeResponse read_request_code_impl,
-    get_request_code GetRequestCodeRequest GetRequestCodeResponse get_request_code_impl,
-    read_transfer_code ReadTransferCodeRequest ReadTransferCodeResponse read_transfer_code_impl,
-    get_transfer_code GetTransferCodeRequest GetTransferCodeResponse get_transfer_code_impl,
-    generate_tx GenerateTxRequest GenerateTxResponse generate_tx_impl,
-    generate_optimization_tx GenerateOptimizationTxRequest GenerateOptimizationTxResponse generate_optimization_tx_impl,
-    generate_transfer_code_tx GenerateTransferCodeTxRequest GenerateTransferCodeTxResponse generate_transfer_code_tx_impl,
-    submit_tx SubmitTxRequest SubmitTxResponse submit_tx_impl,
-    get_ledger_info Empty GetLedgerInfoResponse get_ledger_info_impl,
-    get_block_info GetBlockInfoRequest GetBlockInfoResponse get_block_info_impl,
-    get_tx_status_as_sender GetTxStatusAsSenderRequest GetTxStatusAsSenderResponse get_tx_status_as_sender_impl,
-    get_tx_status_as_receiver GetTxStatusAsReceiverRequest GetTxStatusAsReceiverResponse get_tx_status_as_receiver_impl,
-    get_balance GetBalanceRequest GetBalanceResponse get_balance_impl,
-    send_payment SendPaymentRequest SendPaymentResponse send_payment_impl
-}
+        // Compare
+        assert_eq!(utxos.len(), num_blocks as usize);
+        assert_eq!(
+            HashSet::from_iter(utxos.clone()),
+            HashSet::from_iter(expected_utxos)
+        );
+        assert!(response.continuation_token.is_empty());
+
+        // Paging through the list should return the same outputs, in the same order.
+        request.set_max_entries(3);
+        let mut paged_utxos = Vec::new();
+        loop {
+            let response = client
+                .get_unspent_tx_out_list(&request)
+                .expect("failed to get unspent tx out list");
+            assert!(response.output_list.len() <= 3);
+            paged_utxos.extend(response.output_list.iter().map(|proto_utxo| {
+                UnspentTxOut::try_from(proto_utxo).expect("failed converting proto utxo")
+            }));
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::{
-        payments::DEFAULT_NEW_TX_BLOCK_ATTEMPTS,
-        test_utils::{
-            self, add_block_to_ledger_db, get_testing_environment, wait_for_monitors,
-            PER_RECIPIENT_AMOUNT,
-        },
-        utxo_store::UnspentTxOut,
-    };
-    use common::{logger::test_with_logger, HashSet};
-    use keys::FromRandom;
-    use rand::{rngs::StdRng, SeedableRng};
-    use std::{convert::TryFrom, iter::FromIterator};
-    use transaction::{
-        account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
-        constants::{BASE_FEE, MAX_INPUTS, RING_SIZE},
-        get_tx_out_shared_secret,
-        onetime_keys::{compute_key_image, recover_onetime_private_key},
-        tx::{Tx, TxOut},
-        Block, BlockContents, BlockIndex, BLOCK_VERSION,
-    };
+            if response.continuation_token.is_empty() {
+                break;
+            }
+            request.set_continuation_token(response.continuation_token);
+        }
+        assert_eq!(paged_utxos, utxos);
+
+        // Invalid continuation tokens should be rejected.
+        request.set_continuation_token(vec![1; 3]);
+        assert!(client.get_unspent_tx_out_list(&request).is_err());
+    }
 
     #[test_with_logger]
-    fn test_add_monitor_impl(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([20u8; 32]);
+    fn test_update_utxo_memo(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([96u8; 32]);
 
-        // Three random recipients and no monitors.
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0, // first_subaddress
+            1, // num_subaddresses
+            0, // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
         let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let mut list_request = mobilecoind_api::GetUnspentTxOutListRequest::new();
+        list_request.set_monitor_id(id.to_vec());
+        let utxos = client
+            .get_unspent_tx_out_list(&list_request)
+            .expect("failed to get unspent tx out list")
+            .take_output_list();
+        assert!(utxos.iter().all(|utxo| utxo.memo.is_empty()));
+
+        // Attach a memo to the first output.
+        let mut request = mobilecoind_api::UpdateUtxoMemoRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_key_image(utxos[0].get_key_image().clone());
+        request.set_memo("customer 42 deposit".to_string());
+        client
+            .update_utxo_memo(&request)
+            .expect("failed to update utxo memo");
+
+        let updated_utxos = client
+            .get_unspent_tx_out_list(&list_request)
+            .expect("failed to get unspent tx out list")
+            .take_output_list();
+        for utxo in updated_utxos.iter() {
+            if utxo.get_key_image() == utxos[0].get_key_image() {
+                assert_eq!(utxo.memo, "customer 42 deposit");
+            } else {
+                assert_eq!(utxo.memo, "");
+            }
+        }
+
+        // Memos that are too long are rejected.
+        request.set_memo("x".repeat(MAX_UTXO_MEMO_LENGTH + 1));
+        match client.update_utxo_memo(&request) {
+            Err(grpcio::Error::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.status, RpcStatusCode::INVALID_ARGUMENT);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Unknown outputs are reported as not found.
+        request.set_key_image((&KeyImage::from(1234)).into());
+        request.set_memo("memo".to_string());
+        match client.update_utxo_memo(&request) {
+            Err(grpcio::Error::RpcFailure(rpc_status)) => {
+                assert_eq!(rpc_status.status, RpcStatusCode::NOT_FOUND);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_generate_root_entropy_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
-        // Create request for adding a new monitor.
+        // call get entropy
+        let response = client
+            .generate_entropy(&mobilecoind_api::Empty::default())
+            .unwrap();
+        let entropy = response.get_entropy().to_vec();
+        assert_eq!(entropy.len(), 32);
+        assert_ne!(entropy, vec![0; 32]);
+    }
+
+    #[test_with_logger]
+    fn test_get_account_key_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // call get account key
+        let root_entropy = [123u8; 32];
+        let mut request = mobilecoind_api::GetAccountKeyRequest::new();
+        request.set_entropy(root_entropy.to_vec());
+
+        let response = client.get_account_key(&request).unwrap();
+
+        // TODO: change to production AccountKey derivation
+        let root_id = RootIdentity {
+            root_entropy,
+            fog_url: None,
+        };
+        assert_eq!(
+            AccountKey::from(&root_id),
+            AccountKey::try_from(response.get_account_key()).unwrap(),
+        );
+
+        // Calling with no root entropy or invalid root entropy should error.
+        let request = mobilecoind_api::GetAccountKeyRequest::new();
+        assert!(client.get_account_key(&request).is_err());
+
+        let root_entropy = [123u8; 31];
+        let mut request = mobilecoind_api::GetAccountKeyRequest::new();
+        request.set_entropy(root_entropy.to_vec());
+        assert!(client.get_account_key(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_get_public_address_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
         let data = MonitorData::new(
-            AccountKey::random(&mut rng),
-            DEFAULT_SUBADDRESS_INDEX, // first_subaddress
-            1,                        // num_subaddresses
-            0,                        // first_block
+            account_key.clone(),
+            10, // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
         )
-        .expect("failed to create data");
+        .unwrap();
 
-        let mut request = mobilecoind_api::AddMonitorRequest::new();
-        request.set_account_key(mobilecoind_api::AccountKey::from(&data.account_key));
-        request.set_first_subaddress(data.first_subaddress);
-        request.set_num_subaddresses(data.num_subaddresses);
-        request.set_first_block(data.first_block);
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
-        // Send request.
-        let response = client.add_monitor(&request).expect("failed to add monitor");
+        // Insert into database.
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
 
-        // Allow the new monitor to process the ledger.
-        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        // Call get public address.
+        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(10);
+        let response = client.get_public_address(&request).unwrap();
 
-        // Compare the MonitorId we got back to the value we expected.
-        let monitor_id = MonitorId::try_from(&response.monitor_id)
-            .expect("failed to convert response to MonitorId");
-        let expected_monitor_id = MonitorId::from(&data);
+        assert_eq!(
+            PublicAddress::try_from(response.get_public_address()).unwrap(),
+            account_key.subaddress(10)
+        );
 
-        assert_eq!(expected_monitor_id, monitor_id);
+        // Subaddress that is out of index or an invalid monitor id should error.
+        let request = mobilecoind_api::GetPublicAddressRequest::new();
+        assert!(client.get_public_address(&request).is_err());
+
+        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(vec![3; 3]);
+        request.set_subaddress_index(10);
+        assert!(client.get_public_address(&request).is_err());
+
+        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+        assert!(client.get_public_address(&request).is_err());
+
+        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(1000);
+        assert!(client.get_public_address(&request).is_err());
     }
 
     #[test_with_logger]
-    fn test_remove_monitor_impl(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([22u8; 32]);
+    fn test_get_ledger_info_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        // 10 random recipients and no monitors.
-        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
-        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
-        assert_eq!(0, monitors_map.len());
+        // Call get ledger info.
+        let response = client
+            .get_ledger_info(&mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(response.block_count, ledger_db.num_blocks().unwrap());
+        assert_eq!(response.txo_count, ledger_db.num_txos().unwrap());
+    }
 
-        // Add some monitors directly to the database.
-        let monitors_to_add = 10;
-        let monitor_ids: Vec<MonitorId> = (0..monitors_to_add)
-            .map(|_i| {
-                let data = MonitorData::new(
-                    AccountKey::random(&mut rng),
-                    DEFAULT_SUBADDRESS_INDEX, // first_subaddress
-                    1,                        // num_subaddresses
-                    0,                        // first_block
-                )
-                .unwrap();
-                mobilecoind_db.add_monitor(&data).unwrap()
-            })
-            .collect();
+    #[test_with_logger]
+    fn test_get_block_info_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
-        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
-        assert_eq!(monitors_to_add, monitors_map.len());
+        // Call get block info for a valid block.
+        let mut request = mobilecoind_api::GetBlockInfoRequest::new();
+        request.set_block(0);
 
-        // Remove all the monitors we added.
-        for id in monitor_ids {
-            let mut request = mobilecoind_api::RemoveMonitorRequest::new();
-            request.set_monitor_id(id.to_vec());
-            client
-                .remove_monitor(&request)
-                .expect("failed to remove monitor");
-        }
+        let response = client.get_block_info(&request).unwrap();
+        assert_eq!(response.key_image_count, 0); // test code does not generate any key images
+        assert_eq!(response.txo_count, 3); // 3 recipients = 3 tx outs
 
-        // Check that no monitors remain.
-        let monitors_map = mobilecoind_db.get_monitor_map().unwrap();
-        assert_eq!(0, monitors_map.len());
+        // Call with an invalid block number.
+        let mut request = mobilecoind_api::GetBlockInfoRequest::new();
+        request.set_block(ledger_db.num_blocks().unwrap());
+
+        assert!(client.get_block_info(&request).is_err());
     }
 
     #[test_with_logger]
-    fn test_get_monitor_list_impl(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([21u8; 32]);
-
-        // 10 random recipients and no monitors.
-        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
-
-        // Add some new monitors directly to the database.
-        let monitors_to_add = 10;
-        let monitor_ids: Vec<MonitorId> = (0..monitors_to_add)
-            .map(|_i| {
-                let data = MonitorData::new(
-                    AccountKey::random(&mut rng),
-                    DEFAULT_SUBADDRESS_INDEX, // first_subaddress
-                    1,                        // num_subaddresses
-                    0,                        // first_block
-                )
-                .unwrap();
-                let id = mobilecoind_db.add_monitor(&data).unwrap();
-                log::debug!(logger, "adding monitor {}", id,);
-                id
-            })
-            .collect();
+    fn test_get_block_signature(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([99u8; 32]);
 
-        // Ask the api for a list of all monitors.
-        let response = client
-            .get_monitor_list(&mobilecoind_api::Empty::new())
-            .expect("failed to get monitor list");
+        // no known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
 
-        let monitor_id_list: Vec<MonitorId> = response
-            .monitor_id_list
-            .iter()
-            .map(|bytes| {
-                let id =
-                    MonitorId::try_from(bytes).expect("failed to convert response to MonitorId");
-                log::debug!(logger, "found monitor {}", id,);
-                id
-            })
-            .collect();
+        // Blocks are not signed by default.
+        let mut request = mobilecoind_api::GetBlockSignatureRequest::new();
+        request.set_block(0);
+        let response = client.get_block_signature(&request).unwrap();
+        assert!(!response.has_signature);
 
-        // Check monitor count.
-        assert_eq!(monitors_to_add, monitor_id_list.len());
+        // Append a signed block.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let parent = ledger_db.get_block(num_blocks - 1).unwrap();
+        let tx_out = TxOut::new(
+            PER_RECIPIENT_AMOUNT,
+            &AccountKey::random(&mut rng).default_subaddress(),
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap();
+        let block_contents = BlockContents::new(vec![], vec![tx_out]);
+        let block = Block::new(
+            BLOCK_VERSION,
+            &parent.id,
+            num_blocks,
+            &Default::default(),
+            &block_contents,
+        );
+        let signer = Ed25519Pair::from_random(&mut rng);
+        let signature = BlockSignature::from_block_and_keypair(&block, &signer).unwrap();
+        ledger_db
+            .append_block(&block, &block_contents, Some(&signature))
+            .unwrap();
 
-        // Check that all new monitors are present.
+        let mut request = mobilecoind_api::GetBlockSignatureRequest::new();
+        request.set_block(num_blocks);
+        let response = client.get_block_signature(&request).unwrap();
+        assert!(response.has_signature);
         assert_eq!(
-            HashSet::from_iter(monitor_id_list.iter()),
-            HashSet::from_iter(monitor_ids.iter()),
+            response.get_signature(),
+            &mobilecoind_api::external::Ed25519Signature::from(signature.signature())
+        );
+        assert_eq!(
+            response.get_signer(),
+            &mobilecoind_api::external::Ed25519Public::from(&signer.public_key())
         );
+
+        // Blocks that are not in the ledger are not found.
+        let mut request = mobilecoind_api::GetBlockSignatureRequest::new();
+        request.set_block(num_blocks + 1);
+        match client.get_block_signature(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
     }
 
     #[test_with_logger]
-    fn test_get_monitor_status_impl(logger: Logger) {
+    fn test_stream_blocks(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        // 10 random recipients and no monitors.
-        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(10, &vec![], &vec![], logger.clone(), &mut rng);
-
-        let data = MonitorData::new(
-            AccountKey::random(&mut rng),
-            10, // first_subaddress
-            20, // num_subaddresses
-            30, // first_block
-        )
-        .unwrap();
+        // no known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
 
-        // Insert into database.
-        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        // Full blocks match the ledger.
+        let mut request = mobilecoind_api::StreamBlocksRequest::new();
+        request.set_first_block(1);
+        let mut stream = client.stream_blocks(&request).unwrap().wait();
+        for block_index in 1..num_blocks {
+            let response = stream.next().unwrap().unwrap();
+            let block = ledger_db.get_block(block_index).unwrap();
+            let block_contents = ledger_db.get_block_contents(block_index).unwrap();
+            assert_eq!(response.block_index, block_index);
+            assert_eq!(response.get_block_id(), block.id.as_ref());
+            assert_eq!(response.get_tx_outs().len(), block_contents.outputs.len());
+            assert!(response.get_output_public_keys().is_empty());
+        }
 
-        // Allow the new monitor to process the ledger.
-        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        // Streams keep tailing the ledger once they have caught up with it.
+        let mut request = mobilecoind_api::StreamBlocksRequest::new();
+        request.set_first_block(num_blocks);
+        request.set_filter(mobilecoind_api::BlockStreamFilter::KeyImagesOnly);
+        let mut key_images_stream = client.stream_blocks(&request).unwrap().wait();
 
-        // Query monitor status.
-        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
-        request.set_monitor_id(id.to_vec());
+        let mut request = mobilecoind_api::StreamBlocksRequest::new();
+        request.set_first_block(num_blocks);
+        request.set_filter(mobilecoind_api::BlockStreamFilter::OutputPublicKeysOnly);
+        let mut public_keys_stream = client.stream_blocks(&request).unwrap().wait();
 
-        let response = client
-            .get_monitor_status(&request)
-            .expect("failed to get monitor status");
-        let status = response.status.as_ref().expect("no status in response");
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[recipient],
+            &[KeyImage::from(1), KeyImage::from(2)],
+            &mut rng,
+        );
+        let block_contents = ledger_db.get_block_contents(num_blocks).unwrap();
 
-        // Verify the data we got matches what we expected
+        let response = key_images_stream.next().unwrap().unwrap();
+        assert_eq!(response.block_index, num_blocks);
         assert_eq!(
-            data.account_key,
-            AccountKey::try_from(status.account_key.as_ref().unwrap()).unwrap(),
+            response.get_key_images().to_vec(),
+            vec![
+                mobilecoind_api::external::KeyImage::from(&KeyImage::from(1)),
+                mobilecoind_api::external::KeyImage::from(&KeyImage::from(2))
+            ]
         );
-        assert_eq!(status.first_subaddress, data.first_subaddress);
-        assert_eq!(status.num_subaddresses, data.num_subaddresses);
-        assert_eq!(status.first_block, data.first_block);
-        assert_eq!(status.next_block, data.next_block);
-
-        // Calling get_monitor_status for nonexistent or invalid monitor_id should return an error.
-        mobilecoind_db.remove_monitor(&id).unwrap();
-
-        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
-        request.set_monitor_id(id.to_vec());
-        assert!(client.get_monitor_status(&request).is_err());
+        assert!(response.get_tx_outs().is_empty());
+        assert!(response.get_output_public_keys().is_empty());
 
-        let request = mobilecoind_api::GetMonitorStatusRequest::new();
-        assert!(client.get_monitor_status(&request).is_err());
-
-        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
-        request.set_monitor_id(vec![3; 3]);
-        assert!(client.get_monitor_status(&request).is_err());
+        let response = public_keys_stream.next().unwrap().unwrap();
+        assert_eq!(response.block_index, num_blocks);
+        assert!(response.get_key_images().is_empty());
+        assert!(response.get_tx_outs().is_empty());
+        assert_eq!(
+            response.get_output_public_keys().to_vec(),
+            vec![mobilecoind_api::external::RistrettoPublic::from(
+                block_contents.outputs[0].public_key
+            )]
+        );
     }
 
     #[test_with_logger]
-    fn test_get_unspent_tx_out_list_impl(logger: Logger) {
+    fn test_stream_blocks_for_monitor(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
         let account_key = AccountKey::random(&mut rng);
@@ -1262,7 +5347,7 @@ mod test {
         .unwrap();
 
         // 1 known recipient, 3 random recipients and no monitors.
-        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
             get_testing_environment(
                 3,
                 &vec![account_key.default_subaddress()],
@@ -1270,225 +5355,302 @@ mod test {
                 logger.clone(),
                 &mut rng,
             );
+        let num_blocks = ledger_db.num_blocks().unwrap();
 
-        // Insert into database.
-        let id = mobilecoind_db.add_monitor(&data).unwrap();
-
-        // Allow the new monitor to process the ledger.
-        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
-
-        // Query for unspent tx outs for a subaddress that did not receive any tx outs.
-        let mut request = mobilecoind_api::GetUnspentTxOutListRequest::new();
-        request.set_monitor_id(id.to_vec());
-        request.set_subaddress_index(1);
-
-        let response = client
-            .get_unspent_tx_out_list(&request)
-            .expect("failed to get unspent tx out list");
+        // Following an unknown monitor should fail.
+        let mut request = mobilecoind_api::StreamBlocksRequest::new();
+        request.set_monitor_id(vec![3; 32]);
+        let mut stream = client.stream_blocks(&request).unwrap().wait();
+        assert!(stream.next().unwrap().is_err());
 
-        assert_eq!(response.output_list.to_vec(), vec![]);
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
 
-        // Query wit hthe correct subaddress index.
-        let mut request = mobilecoind_api::GetUnspentTxOutListRequest::new();
+        // Every block in the ledger holds a single TxOut received by the monitor.
+        let mut request = mobilecoind_api::StreamBlocksRequest::new();
         request.set_monitor_id(id.to_vec());
-        request.set_subaddress_index(0);
-
-        let response = client
-            .get_unspent_tx_out_list(&request)
-            .expect("failed to get unspent tx out list");
-
-        let utxos: Vec<UnspentTxOut> = response
-            .output_list
-            .iter()
-            .map(|proto_utxo| {
-                UnspentTxOut::try_from(proto_utxo).expect("failed converting proto utxo")
-            })
-            .collect();
-
-        // Verify the data we got matches what we expected. This assumes knowledge about how the
-        // test ledger is constructed by the test utils.
-        let num_blocks = ledger_db.num_blocks().unwrap();
-        let account_tx_outs: Vec<TxOut> = (0..num_blocks)
-            .map(|idx| {
-                let block_contents = ledger_db.get_block_contents(idx as u64).unwrap();
-                // We grab the 4th tx out in each block since the test ledger had 3 random
-                // recipients, followed by our known recipient.
-                // See the call to `get_testing_environment` at the beginning of the test.
-                block_contents.outputs[3].clone()
-            })
-            .collect();
-
-        let expected_utxos: Vec<UnspentTxOut> = account_tx_outs
-            .iter()
-            .map(|tx_out| {
-                // Calculate the key image for this tx out.
-                let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
-                let onetime_private_key = recover_onetime_private_key(
-                    &tx_public_key,
-                    account_key.view_private_key(),
-                    &account_key.subaddress_spend_key(0),
-                );
-                let key_image = compute_key_image(&onetime_private_key);
-
-                // Craft the expected UnspentTxOut
-                UnspentTxOut {
-                    tx_out: tx_out.clone(),
-                    subaddress_index: 0,
-                    key_image,
-                    value: test_utils::PER_RECIPIENT_AMOUNT,
-                    attempted_spend_height: 0,
-                    attempted_spend_tombstone: 0,
-                }
-            })
-            .collect();
+        let mut stream = client.stream_blocks(&request).unwrap().wait();
+        for block_index in 0..num_blocks {
+            let response = stream.next().unwrap().unwrap();
+            assert_eq!(response.block_index, block_index);
+            assert_eq!(response.get_processed_tx_outs().len(), 1);
+
+            let processed_tx_out = &response.get_processed_tx_outs()[0];
+            assert_eq!(processed_tx_out.block_index, block_index);
+            assert_eq!(processed_tx_out.value, PER_RECIPIENT_AMOUNT);
+            assert_eq!(
+                processed_tx_out.direction,
+                mobilecoind_api::ProcessedTxOutDirection::Received
+            );
+            assert_eq!(processed_tx_out.monitor_id, id.to_vec());
+        }
 
-        // Compare
-        assert_eq!(utxos.len(), num_blocks as usize);
-        assert_eq!(
-            HashSet::from_iter(utxos),
-            HashSet::from_iter(expected_utxos)
+        // Blocks appended to the ledger are sent once the monitor has processed them.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[AccountKey::random(&mut rng).default_subaddress()],
+            &[],
+            &mut rng,
         );
+        let response = stream.next().unwrap().unwrap();
+        assert_eq!(response.block_index, num_blocks);
+        assert!(response.get_processed_tx_outs().is_empty());
+        assert!(mobilecoind_db.get_monitor_data(&id).unwrap().next_block > num_blocks);
     }
 
     #[test_with_logger]
-    fn test_generate_root_entropy_impl(logger: Logger) {
+    fn test_get_processed_block_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        // no known recipient, 3 random recipients and no monitors.
-        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
-
-        // call get entropy
-        let response = client
-            .generate_entropy(&mobilecoind_api::Empty::default())
-            .unwrap();
-        let entropy = response.get_entropy().to_vec();
-        assert_eq!(entropy.len(), 32);
-        assert_ne!(entropy, vec![0; 32]);
-    }
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
 
-    #[test_with_logger]
-    fn test_get_account_key_impl(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
 
-        // no known recipient, 3 random recipients and no monitors.
-        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        // Insert into database.
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
 
-        // call get account key
-        let root_entropy = [123u8; 32];
-        let mut request = mobilecoind_api::GetAccountKeyRequest::new();
-        request.set_entropy(root_entropy.to_vec());
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
-        let response = client.get_account_key(&request).unwrap();
+        // Each block should contain a single TxOut received by our monitor.
+        let mut request = mobilecoind_api::GetProcessedBlockRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_block(0);
 
-        // TODO: change to production AccountKey derivation
-        let root_id = RootIdentity {
-            root_entropy,
-            fog_url: None,
-        };
+        let response = client.get_processed_block(&request).unwrap();
+        assert_eq!(response.tx_out_list.len(), 1);
+
+        let processed_tx_out = &response.tx_out_list[0];
+        let tx_out = &ledger_db.get_block_contents(0).unwrap().outputs[3];
+        assert_eq!(processed_tx_out.block_index, 0);
+        assert_eq!(processed_tx_out.subaddress_index, 0);
         assert_eq!(
-            AccountKey::from(&root_id),
-            AccountKey::try_from(response.get_account_key()).unwrap(),
+            processed_tx_out.get_public_key(),
+            &mobilecoind_api::external::RistrettoPublic::from(tx_out.public_key)
+        );
+        assert_eq!(processed_tx_out.value, PER_RECIPIENT_AMOUNT);
+        assert_eq!(
+            processed_tx_out.direction,
+            mobilecoind_api::ProcessedTxOutDirection::Received
         );
+        assert_eq!(processed_tx_out.monitor_id, id.to_vec());
 
-        // Calling with no root entropy or invalid root entropy should error.
-        let request = mobilecoind_api::GetAccountKeyRequest::new();
-        assert!(client.get_account_key(&request).is_err());
+        // The history should contain every processed block.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let mut request = mobilecoind_api::GetProcessedTxOutHistoryRequest::new();
+        request.set_monitor_id(id.to_vec());
 
-        let root_entropy = [123u8; 31];
-        let mut request = mobilecoind_api::GetAccountKeyRequest::new();
-        request.set_entropy(root_entropy.to_vec());
-        assert!(client.get_account_key(&request).is_err());
+        let response = client.get_processed_tx_out_history(&request).unwrap();
+        assert_eq!(response.tx_out_list.len(), num_blocks as usize);
+        assert_eq!(response.tx_out_list[0], *processed_tx_out);
+
+        // Limiting the range should limit the results.
+        request.set_first_block(1);
+        request.set_max_blocks(2);
+        let response = client.get_processed_tx_out_history(&request).unwrap();
+        let block_indexes: Vec<u64> = response
+            .tx_out_list
+            .iter()
+            .map(|tx_out| tx_out.block_index)
+            .collect();
+        assert_eq!(block_indexes, vec![1, 2]);
+
+        // Blocks the monitor did not process yet should be rejected.
+        let mut request = mobilecoind_api::GetProcessedBlockRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_block(num_blocks);
+
+        assert!(client.get_processed_block(&request).is_err());
     }
 
     #[test_with_logger]
-    fn test_get_public_address_impl(logger: Logger) {
+    fn test_get_incoming_payment_list_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
         let account_key = AccountKey::random(&mut rng);
         let data = MonitorData::new(
             account_key.clone(),
-            10, // first_subaddress
+            0,  // first_subaddress
             20, // num_subaddresses
             0,  // first_block
         )
         .unwrap();
 
-        // no known recipient, 3 random recipients and no monitors.
-        let (_ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
 
         // Insert into database.
         let id = mobilecoind_db.add_monitor(&data).unwrap();
 
-        // Call get public address.
-        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Each block should contain a single TxOut received by our monitor.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let mut request = mobilecoind_api::GetIncomingPaymentListRequest::new();
         request.set_monitor_id(id.to_vec());
-        request.set_subaddress_index(10);
-        let response = client.get_public_address(&request).unwrap();
 
+        let response = client.get_incoming_payment_list(&request).unwrap();
+        assert_eq!(response.incoming_payment_list.len(), num_blocks as usize);
+
+        let incoming_payment = response.incoming_payment_list[0].clone();
+        let tx_out = &ledger_db.get_block_contents(0).unwrap().outputs[3];
+        assert_eq!(incoming_payment.block_index, 0);
+        assert_eq!(incoming_payment.subaddress_index, 0);
         assert_eq!(
-            PublicAddress::try_from(response.get_public_address()).unwrap(),
-            account_key.subaddress(10)
+            TxOut::try_from(incoming_payment.get_tx_out()).unwrap(),
+            *tx_out
         );
+        assert_eq!(incoming_payment.value, PER_RECIPIENT_AMOUNT);
+        assert!(incoming_payment.discovered_at > 0);
+        assert_eq!(incoming_payment.monitor_id, id.to_vec());
+
+        // Limiting the range should limit the results.
+        request.set_first_block(1);
+        request.set_max_entries(2);
+        let response = client.get_incoming_payment_list(&request).unwrap();
+        let block_indexes: Vec<u64> = response
+            .incoming_payment_list
+            .iter()
+            .map(|incoming_payment| incoming_payment.block_index)
+            .collect();
+        assert_eq!(block_indexes, vec![1, 2]);
 
-        // Subaddress that is out of index or an invalid monitor id should error.
-        let request = mobilecoind_api::GetPublicAddressRequest::new();
-        assert!(client.get_public_address(&request).is_err());
-
-        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
-        request.set_monitor_id(vec![3; 3]);
-        request.set_subaddress_index(10);
-        assert!(client.get_public_address(&request).is_err());
-
-        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        // The payments should survive removing the monitor, and re-adding it should keep the
+        // original entries.
+        mobilecoind_db.remove_monitor(&id).unwrap();
+        let mut request = mobilecoind_api::GetIncomingPaymentListRequest::new();
         request.set_monitor_id(id.to_vec());
-        request.set_subaddress_index(0);
-        assert!(client.get_public_address(&request).is_err());
+        let response = client.get_incoming_payment_list(&request).unwrap();
+        assert_eq!(response.incoming_payment_list.len(), num_blocks as usize);
 
-        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
-        request.set_monitor_id(id.to_vec());
-        request.set_subaddress_index(1000);
-        assert!(client.get_public_address(&request).is_err());
+        assert_eq!(mobilecoind_db.add_monitor(&data).unwrap(), id);
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let response = client.get_incoming_payment_list(&request).unwrap();
+        assert_eq!(response.incoming_payment_list.len(), num_blocks as usize);
+        assert_eq!(response.incoming_payment_list[0], incoming_payment);
     }
 
     #[test_with_logger]
-    fn test_get_ledger_info_impl(logger: Logger) {
+    fn test_get_transaction_history_impl(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
 
-        // no known recipient, 3 random recipients and no monitors.
-        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
 
-        // Call get ledger info.
-        let response = client
-            .get_ledger_info(&mobilecoind_api::Empty::new())
-            .unwrap();
-        assert_eq!(response.block_count, ledger_db.num_blocks().unwrap());
-        assert_eq!(response.txo_count, ledger_db.num_txos().unwrap());
-    }
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
 
-    #[test_with_logger]
-    fn test_get_block_info_impl(logger: Logger) {
-        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+        // Insert into database.
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
 
-        // no known recipient, 3 random recipients and no monitors.
-        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
-            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
 
-        // Call get block info for a valid block.
-        let mut request = mobilecoind_api::GetBlockInfoRequest::new();
-        request.set_block(0);
+        // Each block should contain a single TxOut received by our monitor.
+        let num_blocks = ledger_db.num_blocks().unwrap();
+        let mut request = mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(id.to_vec());
 
-        let response = client.get_block_info(&request).unwrap();
-        assert_eq!(response.key_image_count, 0); // test code does not generate any key images
-        assert_eq!(response.txo_count, 3); // 3 recipients = 3 tx outs
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.entry_list.len(), num_blocks as usize);
 
-        // Call with an invalid block number.
-        let mut request = mobilecoind_api::GetBlockInfoRequest::new();
-        request.set_block(ledger_db.num_blocks().unwrap());
+        let entry = &response.entry_list[0];
+        let tx_out = &ledger_db.get_block_contents(0).unwrap().outputs[3];
+        assert_eq!(entry.block_index, 0);
+        assert_eq!(entry.subaddress_index, 0);
+        assert_eq!(
+            entry.get_public_key(),
+            &mobilecoind_api::external::RistrettoPublic::from(tx_out.public_key)
+        );
+        assert_eq!(entry.value, PER_RECIPIENT_AMOUNT);
+        assert_eq!(
+            entry.direction,
+            mobilecoind_api::ProcessedTxOutDirection::Received
+        );
+        assert!(entry.timestamp > 0);
+        assert_eq!(
+            entry.timestamp_confidence,
+            mobilecoind_api::TimestampConfidence::TimestampLocal
+        );
+        assert_eq!(entry.monitor_id, id.to_vec());
 
-        assert!(client.get_block_info(&request).is_err());
+        // Blocks that were seen signed should report when they were seen.
+        mobilecoind_db
+            .observe_block_timestamp(0, &BlockTimestamp::new(1234, TimestampConfidence::Quorum))
+            .unwrap();
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.entry_list[0].timestamp, 1234);
+        assert_eq!(
+            response.entry_list[0].timestamp_confidence,
+            mobilecoind_api::TimestampConfidence::TimestampQuorum
+        );
+        assert_eq!(
+            response.entry_list[1].timestamp_confidence,
+            mobilecoind_api::TimestampConfidence::TimestampLocal
+        );
+
+        // Limiting the range should limit the results.
+        request.set_first_block(1);
+        request.set_max_blocks(2);
+        let response = client.get_transaction_history(&request).unwrap();
+        let block_indexes: Vec<u64> = response
+            .entry_list
+            .iter()
+            .map(|entry| entry.block_index)
+            .collect();
+        assert_eq!(block_indexes, vec![1, 2]);
+
+        // Filtering by subaddress should only return that subaddress' entries.
+        request.set_filter_by_subaddress(true);
+        request.set_subaddress_index(0);
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.entry_list.len(), 2);
+
+        request.set_subaddress_index(1);
+        let response = client.get_transaction_history(&request).unwrap();
+        assert!(response.entry_list.is_empty());
+
+        // The history should survive removing the monitor.
+        mobilecoind_db.remove_monitor(&id).unwrap();
+        let mut request = mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(id.to_vec());
+        let response = client.get_transaction_history(&request).unwrap();
+        assert_eq!(response.entry_list.len(), num_blocks as usize);
     }
 
     #[test_with_logger]
@@ -1655,6 +5817,218 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_get_tx_out_results_by_public_key_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let num_tx_outs = ledger_db.num_txos().unwrap();
+        let mut public_keys: Vec<mobilecoind_api::external::RistrettoPublic> = (0..num_tx_outs)
+            .map(|index| {
+                let tx_out = ledger_db.get_tx_out_by_index(index).unwrap();
+                mobilecoind_api::external::RistrettoPublic::from(tx_out.public_key)
+            })
+            .collect();
+
+        let mut unknown_public_key = mobilecoind_api::external::RistrettoPublic::new();
+        unknown_public_key.set_data(vec![7u8; 32]);
+        public_keys.push(unknown_public_key);
+
+        let mut malformed_public_key = mobilecoind_api::external::RistrettoPublic::new();
+        malformed_public_key.set_data(vec![7u8; 3]);
+        public_keys.push(malformed_public_key);
+
+        let mut request = mobilecoind_api::GetTxOutResultsByPublicKeyRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(public_keys.clone()));
+        let response = client.get_tx_out_results_by_public_key(&request).unwrap();
+        assert_eq!(response.num_blocks, ledger_db.num_blocks().unwrap());
+
+        let results = response.get_results();
+        assert_eq!(results.len(), public_keys.len());
+        for (index, result) in results[..num_tx_outs as usize].iter().enumerate() {
+            let index = index as u64;
+            assert_eq!(result.get_tx_out_public_key(), &public_keys[index as usize]);
+            assert_eq!(
+                result.get_result_code(),
+                mobilecoind_api::TxOutResultCode::TxOutFound
+            );
+            assert_eq!(result.global_tx_out_index, index);
+            let block_contents = ledger_db.get_block_contents(result.block_index).unwrap();
+            let tx_out = ledger_db.get_tx_out_by_index(index).unwrap();
+            assert!(block_contents.outputs.contains(&tx_out));
+        }
+        assert_eq!(
+            results[num_tx_outs as usize].get_result_code(),
+            mobilecoind_api::TxOutResultCode::TxOutNotFound
+        );
+        assert_eq!(
+            results[num_tx_outs as usize + 1].get_result_code(),
+            mobilecoind_api::TxOutResultCode::MalformedPublicKey
+        );
+
+        // Requests for too many public keys are rejected.
+        let mut request = mobilecoind_api::GetTxOutResultsByPublicKeyRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(vec![
+            public_keys[0].clone();
+            MAX_TX_OUT_RESULTS_PER_REQUEST
+                + 1
+        ]));
+        assert!(client.get_tx_out_results_by_public_key(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_get_membership_proofs(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        // Request proofs of the TxOuts out of order.
+        let num_tx_outs = ledger_db.num_txos().unwrap();
+        let indices: Vec<u64> = (0..num_tx_outs).rev().step_by(2).collect();
+        let tx_outs: Vec<TxOut> = indices
+            .iter()
+            .map(|index| ledger_db.get_tx_out_by_index(*index).unwrap())
+            .collect();
+        let public_keys: Vec<mobilecoind_api::external::RistrettoPublic> = tx_outs
+            .iter()
+            .map(|tx_out| mobilecoind_api::external::RistrettoPublic::from(tx_out.public_key))
+            .collect();
+
+        let mut request = mobilecoind_api::GetMembershipProofsRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(public_keys.clone()));
+        let response = client.get_membership_proofs(&request).unwrap();
+
+        let expected_proofs = ledger_db.get_tx_out_proof_of_memberships(&indices).unwrap();
+        assert_eq!(response.get_outputs().len(), indices.len());
+        assert_eq!(response.get_membership_proofs().len(), indices.len());
+        for ((output, proof), (tx_out, expected_proof)) in response
+            .get_outputs()
+            .iter()
+            .zip(response.get_membership_proofs())
+            .zip(tx_outs.iter().zip(expected_proofs.iter()))
+        {
+            assert_eq!(&TxOut::try_from(output).unwrap(), tx_out);
+            assert_eq!(
+                &TxOutMembershipProof::try_from(proof).unwrap(),
+                expected_proof
+            );
+        }
+
+        // Requests for TxOuts that are not in the ledger fail.
+        let mut unknown_public_key = mobilecoind_api::external::RistrettoPublic::new();
+        unknown_public_key.set_data(vec![7u8; 32]);
+        let mut request = mobilecoind_api::GetMembershipProofsRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(vec![
+            public_keys[0].clone(),
+            unknown_public_key,
+        ]));
+        match client.get_membership_proofs(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Malformed public keys are rejected.
+        let mut malformed_public_key = mobilecoind_api::external::RistrettoPublic::new();
+        malformed_public_key.set_data(vec![7u8; 3]);
+        let mut request = mobilecoind_api::GetMembershipProofsRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(vec![malformed_public_key]));
+        match client.get_membership_proofs(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Requests for too many proofs are rejected.
+        let mut request = mobilecoind_api::GetMembershipProofsRequest::new();
+        request.set_tx_out_public_keys(RepeatedField::from_vec(vec![
+            public_keys[0].clone();
+            MAX_MEMBERSHIP_PROOFS_PER_REQUEST
+                + 1
+        ]));
+        assert!(client.get_membership_proofs(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_get_tx_outs_by_block_range(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([97u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        let mut request = mobilecoind_api::GetTxOutsByBlockRangeRequest::new();
+        request.set_first_block(1);
+        request.set_num_blocks(num_blocks - 1);
+        let response = client.get_tx_outs_by_block_range(&request).unwrap();
+        let expected_tx_outs: Vec<TxOut> = (1..num_blocks)
+            .flat_map(|block_index| ledger_db.get_block_contents(block_index).unwrap().outputs)
+            .collect();
+        let tx_outs: Vec<TxOut> = response
+            .get_tx_outs()
+            .iter()
+            .map(|tx_out| TxOut::try_from(tx_out).unwrap())
+            .collect();
+        assert_eq!(tx_outs, expected_tx_outs);
+
+        // Blocks that are not in the ledger are not found.
+        request.set_num_blocks(num_blocks);
+        match client.get_tx_outs_by_block_range(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Requests for too many blocks are rejected.
+        request.set_first_block(0);
+        request.set_num_blocks(MAX_BLOCKS_PER_TX_OUTS_REQUEST + 1);
+        match client.get_tx_outs_by_block_range(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_get_block_by_key_image(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([96u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        for block_index in 0..ledger_db.num_blocks().unwrap() {
+            let block = ledger_db.get_block(block_index).unwrap();
+            for key_image in ledger_db.get_key_images_by_block(block_index).unwrap() {
+                let mut request = mobilecoind_api::GetBlockByKeyImageRequest::new();
+                request.set_key_image((&key_image).into());
+                let response = client.get_block_by_key_image(&request).unwrap();
+                assert_eq!(response.block_index, block_index);
+                assert_eq!(response.get_block_id(), block.id.as_ref());
+            }
+        }
+
+        // Key images that are not spent are not found.
+        let mut request = mobilecoind_api::GetBlockByKeyImageRequest::new();
+        request.set_key_image((&KeyImage::from(1234)).into());
+        match client.get_block_by_key_image(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::NOT_FOUND)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
     #[test_with_logger]
     fn test_generate_tx(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -1669,7 +6043,7 @@ mod test {
         .unwrap();
 
         // 1 known recipient, 3 random recipients and no monitors.
-        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
             get_testing_environment(
                 3,
                 &vec![sender.default_subaddress()],
@@ -1769,9 +6143,45 @@ mod test {
             // Sanity test tombstone block
             let num_blocks = ledger_db.num_blocks().unwrap();
             assert_eq!(
-                tx_proposal.get_tx().get_prefix().tombstone_block,
-                num_blocks + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
+                tx_proposal.get_tx().get_prefix().tombstone_block,
+                num_blocks + DEFAULT_NEW_TX_BLOCK_ATTEMPTS
+            );
+        }
+
+        // Change below the absorption threshold should be added to the fee.
+        {
+            let change = test_utils::PER_RECIPIENT_AMOUNT
+                - outlays.iter().map(|outlay| outlay.value).sum::<u64>()
+                - BASE_FEE;
+
+            // Change equal to the threshold is returned to the sender.
+            let mut request = request.clone();
+            request.set_change_absorption_threshold(change);
+            let response = client.generate_tx(&request).unwrap();
+            let tx_proposal = response.get_tx_proposal();
+            assert_eq!(tx_proposal.get_absorbed_change(), 0);
+            assert_eq!(tx_proposal.get_fee(), BASE_FEE);
+            assert_eq!(
+                tx_proposal.get_tx().get_prefix().get_outputs().len(),
+                outlays.len() + 1
+            );
+
+            // Change below the threshold is absorbed, and no change output is created.
+            request.set_change_absorption_threshold(change + 1);
+            let response = client.generate_tx(&request).unwrap();
+            let tx_proposal = response.get_tx_proposal();
+            assert_eq!(tx_proposal.get_absorbed_change(), change);
+            assert_eq!(tx_proposal.get_fee(), BASE_FEE + change);
+            assert_eq!(tx_proposal.get_tx().get_prefix().fee, BASE_FEE + change);
+            assert_eq!(
+                tx_proposal.get_tx().get_prefix().get_outputs().len(),
+                outlays.len()
             );
+
+            // The proposal should survive the round trip through its Rust representation.
+            let rust_tx_proposal = TxProposal::try_from(tx_proposal).unwrap();
+            assert_eq!(rust_tx_proposal.absorbed_change, change);
+            assert_eq!(rust_tx_proposal.fee(), BASE_FEE + change);
         }
 
         // Invalid input scenarios should result in an error.
@@ -1826,6 +6236,28 @@ mod test {
             ]));
             assert!(client.generate_tx(&request).is_err());
         }
+
+        {
+            // Outlays the network considers dust
+            for mock_peer in server_conn_manager.conns() {
+                mock_peer.write().dust_rules = DustRules::new(DustPolicy::Rejected, 200);
+            }
+            assert!(client.generate_tx(&request).is_err());
+
+            for mock_peer in server_conn_manager.conns() {
+                mock_peer.write().dust_rules = DustRules::new(DustPolicy::Rejected, 100);
+            }
+            assert!(client.generate_tx(&request).is_ok());
+        }
+
+        {
+            // With no fee specified, the fee advertised by the network is used.
+            for mock_peer in server_conn_manager.conns() {
+                mock_peer.write().minimum_fee = BASE_FEE * 10;
+            }
+            let response = client.generate_tx(&request).unwrap();
+            assert_eq!(response.get_tx_proposal().get_fee(), BASE_FEE * 10);
+        }
     }
 
     #[test_with_logger]
@@ -2003,10 +6435,7 @@ mod test {
         assert_eq!(tx_proposal.tx.prefix.inputs.len(), expected_num_inputs);
 
         assert_eq!(tx_proposal.outlays.len(), 1);
-        assert_eq!(
-            tx_proposal.outlays[0].receiver,
-            data.account_key.subaddress(0)
-        );
+        assert_eq!(tx_proposal.outlays[0].receiver, data.subaddress(0));
         assert_eq!(
             tx_proposal.outlays[0].value,
             // Each UTXO we have has PER_RECIPIENT_AMOUNT coins. We will be merging MAX_INPUTS of those
@@ -2020,8 +6449,7 @@ mod test {
         assert_eq!(tx_proposal.tx.prefix.outputs.len(), 1);
         let tx_out = &tx_proposal.tx.prefix.outputs[0];
         let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
-        let shared_secret =
-            get_tx_out_shared_secret(data.account_key.view_private_key(), &tx_public_key);
+        let shared_secret = get_tx_out_shared_secret(data.view_private_key(), &tx_public_key);
         let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
         assert_eq!(value, tx_proposal.outlays[0].value);
 
@@ -2037,6 +6465,99 @@ mod test {
         );
     }
 
+    #[test_with_logger]
+    // A transaction generated by a watch-only monitor should be submittable once signed with the
+    // account key, e.g. by another mobilecoind.
+    fn test_generate_unsigned_tx_and_sign_tx(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new_watch_only(
+            WatchOnlyKey::from(&sender),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Get list of unspent tx outs
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert!(!utxos.is_empty());
+
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = vec![Outlay {
+            value: 123,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        // Generate an unsigned tx.
+        let mut request = mobilecoind_api::GenerateUnsignedTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(mobilecoind_api::Outlay::from).collect(),
+        ));
+        let response = client.generate_unsigned_tx(&request).unwrap();
+        let unsigned_tx_proposal = response.get_unsigned_tx_proposal().clone();
+        assert!(!response.get_approval_required());
+        assert_eq!(
+            unsigned_tx_proposal.get_outlay_list(),
+            request.get_outlay_list()
+        );
+
+        // Signing with another account key fails.
+        let mut request = mobilecoind_api::SignTxRequest::new();
+        request.set_account_key((&AccountKey::random(&mut rng)).into());
+        request.set_unsigned_tx_proposal(unsigned_tx_proposal.clone());
+        assert!(client.sign_tx(&request).is_err());
+
+        // Sign with the sender's account key.
+        request.set_account_key((&sender).into());
+        let response = client.sign_tx(&request).unwrap();
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        assert_eq!(
+            mobilecoind_api::external::TxPrefix::from(&tx_proposal.tx.prefix),
+            *unsigned_tx_proposal.get_tx_prefix()
+        );
+        assert_eq!(tx_proposal.outlay_confirmation_numbers.len(), outlays.len());
+
+        // Submit the signed tx.
+        let mut request = mobilecoind_api::SubmitTxRequest::new();
+        request.set_tx_proposal(mobilecoind_api::TxProposal::from(&tx_proposal));
+        client.submit_tx(&request).unwrap();
+
+        let submitted_txs: Vec<Tx> = server_conn_manager
+            .conns()
+            .iter()
+            .flat_map(|mock_peer| mock_peer.read().submitted_txs.clone())
+            .collect();
+        assert_eq!(submitted_txs, vec![tx_proposal.tx]);
+    }
+
     #[test_with_logger]
     fn test_submit_tx(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2154,60 +6675,340 @@ mod test {
                 assert_eq!(subaddress_id.monitor_id, monitor_id);
             }
 
-            assert_eq!(
-                response.get_sender_tx_receipt().tombstone,
-                tx.prefix.tombstone_block
-            );
+            assert_eq!(
+                response.get_sender_tx_receipt().tombstone,
+                tx.prefix.tombstone_block
+            );
+
+            // Sanity the receiver receipts.
+            assert_eq!(response.get_receiver_tx_receipt_list().len(), outlays.len());
+            for (outlay, receipt) in outlays
+                .iter()
+                .zip(response.get_receiver_tx_receipt_list().iter())
+            {
+                assert_eq!(
+                    outlay.receiver,
+                    PublicAddress::try_from(receipt.get_receipient()).unwrap()
+                );
+
+                assert_eq!(receipt.tombstone, tx.prefix.tombstone_block);
+            }
+
+            assert_eq!(
+                response.get_receiver_tx_receipt_list().len() + 1, // There's a change output that is not part of the receipts
+                tx.prefix.outputs.len()
+            );
+            for (tx_out, receipt) in tx
+                .prefix
+                .outputs
+                .iter()
+                .zip(response.get_receiver_tx_receipt_list().iter())
+            {
+                assert_eq!(tx_out.hash(), receipt.get_tx_out_hash(),);
+
+                assert_eq!(
+                    tx_out.public_key.as_bytes(),
+                    receipt.get_tx_public_key().get_data(),
+                );
+            }
+
+            // The sender receipt should list the public keys of all outputs, including change.
+            let output_public_keys: Vec<Vec<u8>> = response
+                .get_sender_tx_receipt()
+                .get_output_public_key_list()
+                .iter()
+                .map(|public_key| public_key.get_data().to_vec())
+                .collect();
+            let expected_output_public_keys: Vec<Vec<u8>> = tx
+                .prefix
+                .outputs
+                .iter()
+                .map(|tx_out| tx_out.public_key.as_bytes().to_vec())
+                .collect();
+            assert_eq!(output_public_keys, expected_output_public_keys);
+
+            // Each receiver should be able to validate the confirmation number of their output.
+            for (receiver, receipt) in [&receiver1, &receiver2]
+                .iter()
+                .zip(response.get_receiver_tx_receipt_list().iter())
+            {
+                let confirmation_number =
+                    TxOutConfirmationNumber::try_from(receipt.get_confirmation_number()).unwrap();
+                let tx_public_key = RistrettoPublic::try_from(receipt.get_tx_public_key()).unwrap();
+                assert!(confirmation_number.validate(&tx_public_key, receiver.view_private_key()));
+            }
+
+            // The receiver can check the receipt against their monitor. The transaction did not
+            // land in the ledger, so a valid receipt is pending, and a forged one is rejected.
+            {
+                let receiver_data = MonitorData::new(
+                    receiver1.clone(),
+                    0, // first_subaddress
+                    1, // num_subaddresses
+                    0, // first_block
+                )
+                .unwrap();
+                let receiver_monitor_id = mobilecoind_db.add_monitor(&receiver_data).unwrap();
+
+                let mut request = mobilecoind_api::GetTxStatusAsReceiverRequest::new();
+                request.set_receipt(response.get_receiver_tx_receipt_list()[0].clone());
+                request.set_monitor_id(receiver_monitor_id.to_vec());
+                let status_response = client.get_tx_status_as_receiver(&request).unwrap();
+                assert_eq!(
+                    status_response.get_status(),
+                    mobilecoind_api::TxStatus::Unknown
+                );
+
+                request.mut_receipt().set_confirmation_number(vec![0u8; 32]);
+                let status_response = client.get_tx_status_as_receiver(&request).unwrap();
+                assert_eq!(
+                    status_response.get_status(),
+                    mobilecoind_api::TxStatus::InvalidConfirmationNumber
+                );
+            }
+
+            // Check that attempted_spend_height got updated for the relevant utxos.
+            let account_utxos = mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap();
+            let tx_proposal_utxo_ids: Vec<UtxoId> =
+                tx_proposal.utxos.iter().map(UtxoId::from).collect();
+            let mut matched_utxos = 0;
+            for utxo in account_utxos.iter() {
+                if tx_proposal_utxo_ids.contains(&UtxoId::from(utxo)) {
+                    assert!(utxo.attempted_spend_height > 0);
+                    matched_utxos += 1;
+                } else {
+                    assert_eq!(utxo.attempted_spend_height, 0);
+                }
+            }
+            assert_eq!(matched_utxos, tx_proposal.utxos.len());
+        }
+    }
+
+    #[test_with_logger]
+    fn test_verify_tx_proposal(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let outlays = vec![Outlay {
+            value: 123,
+            receiver: AccountKey::random(&mut rng).default_subaddress(),
+        }];
+
+        let mut request = mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(mobilecoind_api::Outlay::from).collect(),
+        ));
+        let response = client.generate_tx(&request).unwrap();
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+
+        let verify = |tx_proposal: &TxProposal| {
+            let mut request = mobilecoind_api::VerifyTxProposalRequest::new();
+            request.set_tx_proposal(mobilecoind_api::TxProposal::from(tx_proposal));
+            client.verify_tx_proposal(&request).unwrap()
+        };
+
+        // A proposal built by mobilecoind passes every check.
+        let response = verify(&tx_proposal);
+        assert!(response.get_is_valid());
+        assert_eq!(response.get_failure(), "");
+
+        // A fee that was changed after signing invalidates the signature.
+        let mut tampered = tx_proposal.clone();
+        tampered.tx.prefix.fee += 1;
+        let response = verify(&tampered);
+        assert!(!response.get_is_valid());
+        assert!(!response.get_failure().is_empty());
+
+        // Outlays the inputs cannot cover.
+        let mut tampered = tx_proposal.clone();
+        tampered.outlays[0].value = u64::MAX / 2;
+        let response = verify(&tampered);
+        assert!(!response.get_is_valid());
+        assert_eq!(
+            response.get_failure(),
+            "utxos do not cover the outlays and fee"
+        );
+
+        // Utxos that are not the transaction's inputs.
+        let mut tampered = tx_proposal.clone();
+        tampered.utxos.pop();
+        let response = verify(&tampered);
+        assert!(!response.get_is_valid());
+        assert_eq!(
+            response.get_failure(),
+            "utxos do not match the transaction's inputs"
+        );
+
+        // Outlays that do not map to an output.
+        let mut tampered = tx_proposal;
+        tampered.outlay_index_to_tx_out_index.clear();
+        let response = verify(&tampered);
+        assert!(!response.get_is_valid());
+        assert_eq!(
+            response.get_failure(),
+            "outlay 0 does not map to a distinct output"
+        );
+    }
+
+    #[test_with_logger]
+    fn test_get_outgoing_tx_list(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The log starts out empty.
+        let response = client
+            .get_outgoing_tx_list(&mobilecoind_api::GetOutgoingTxListRequest::new())
+            .unwrap();
+        assert!(response.get_tx_list().is_empty());
+
+        // Generate a transaction.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let receiver = AccountKey::random(&mut rng);
+        let outlays = vec![Outlay {
+            value: 123,
+            receiver: receiver.default_subaddress(),
+        }];
+
+        let mut request = mobilecoind_api::GenerateTxRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_change_subaddress(0);
+        request.set_input_list(RepeatedField::from_vec(
+            utxos
+                .iter()
+                .map(mobilecoind_api::UnspentTxOut::from)
+                .collect(),
+        ));
+        request.set_outlay_list(RepeatedField::from_vec(
+            outlays.iter().map(mobilecoind_api::Outlay::from).collect(),
+        ));
+
+        let response = client.generate_tx(&request).unwrap();
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        let tx_prefix_hash = tx_proposal.tx.prefix.hash();
+
+        // The generated transaction should be in the log.
+        let mut request = mobilecoind_api::GetOutgoingTxRequest::new();
+        request.set_tx_prefix_hash(tx_prefix_hash.to_vec());
+        let response = client.get_outgoing_tx(&request).unwrap();
+        let outgoing_tx = response.get_tx();
+        assert_eq!(outgoing_tx.index, 0);
+        assert_eq!(outgoing_tx.monitor_id, monitor_id.to_vec());
+        assert_eq!(
+            outgoing_tx.get_status(),
+            mobilecoind_api::OutgoingTxStatus::OutgoingTxGenerated
+        );
+        assert_eq!(outgoing_tx.fee, tx_proposal.fee());
+        assert_eq!(outgoing_tx.tombstone, tx_proposal.tx.prefix.tombstone_block);
+        assert_eq!(
+            outgoing_tx.get_key_image_list().len(),
+            tx_proposal.tx.prefix.inputs.len()
+        );
+        assert_eq!(outgoing_tx.get_outlay_list().len(), 1);
+        assert_eq!(
+            Outlay::try_from(&outgoing_tx.get_outlay_list()[0]).unwrap(),
+            outlays[0]
+        );
 
-            // Sanity the receiver receipts.
-            assert_eq!(response.get_receiver_tx_receipt_list().len(), outlays.len());
-            for (outlay, receipt) in outlays
-                .iter()
-                .zip(response.get_receiver_tx_receipt_list().iter())
-            {
-                assert_eq!(
-                    outlay.receiver,
-                    PublicAddress::try_from(receipt.get_receipient()).unwrap()
-                );
+        // The outlay is pending until its TxOut lands.
+        let tx_out_index = tx_proposal.outlay_index_to_tx_out_index[&0];
+        let outlay_status = &outgoing_tx.get_outlay_status_list()[0];
+        assert_eq!(
+            outlay_status.get_status(),
+            mobilecoind_api::OutlayStatus::OutlayPending
+        );
+        assert_eq!(
+            outlay_status.get_tx_out_public_key().get_data(),
+            &tx_proposal.tx.prefix.outputs[tx_out_index]
+                .public_key
+                .as_bytes()[..]
+        );
 
-                assert_eq!(receipt.tombstone, tx.prefix.tombstone_block);
-            }
+        // Submitting it should update its status.
+        let mut request = mobilecoind_api::SubmitTxRequest::new();
+        request.set_tx_proposal(mobilecoind_api::TxProposal::from(&tx_proposal));
+        client.submit_tx(&request).unwrap();
 
-            assert_eq!(
-                response.get_receiver_tx_receipt_list().len() + 1, // There's a change output that is not part of the receipts
-                tx.prefix.outputs.len()
-            );
-            for (tx_out, receipt) in tx
-                .prefix
-                .outputs
-                .iter()
-                .zip(response.get_receiver_tx_receipt_list().iter())
-            {
-                assert_eq!(tx_out.hash(), receipt.get_tx_out_hash(),);
+        let mut request = mobilecoind_api::GetOutgoingTxListRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let response = client.get_outgoing_tx_list(&request).unwrap();
+        assert_eq!(response.get_tx_list().len(), 1);
+        assert_eq!(
+            response.get_tx_list()[0].get_status(),
+            mobilecoind_api::OutgoingTxStatus::OutgoingTxSubmitted
+        );
+        assert_eq!(
+            response.get_tx_list()[0].tx_prefix_hash,
+            tx_prefix_hash.to_vec()
+        );
 
-                assert_eq!(
-                    tx_out.public_key.as_bytes(),
-                    receipt.get_tx_public_key().get_data(),
-                );
-            }
+        // Other monitors have no outgoing transactions.
+        request.set_monitor_id(vec![0u8; 32]);
+        let response = client.get_outgoing_tx_list(&request).unwrap();
+        assert!(response.get_tx_list().is_empty());
 
-            // Check that attempted_spend_height got updated for the relevant utxos.
-            let account_utxos = mobilecoind_db
-                .get_utxos_for_subaddress(&monitor_id, 0)
-                .unwrap();
-            let tx_proposal_utxo_ids: Vec<UtxoId> =
-                tx_proposal.utxos.iter().map(UtxoId::from).collect();
-            let mut matched_utxos = 0;
-            for utxo in account_utxos.iter() {
-                if tx_proposal_utxo_ids.contains(&UtxoId::from(utxo)) {
-                    assert!(utxo.attempted_spend_height > 0);
-                    matched_utxos += 1;
-                } else {
-                    assert_eq!(utxo.attempted_spend_height, 0);
-                }
-            }
-            assert_eq!(matched_utxos, tx_proposal.utxos.len());
-        }
+        // Unknown transactions should produce an error.
+        let mut request = mobilecoind_api::GetOutgoingTxRequest::new();
+        request.set_tx_prefix_hash(vec![0u8; 32]);
+        assert!(client.get_outgoing_tx(&request).is_err());
     }
 
     #[test_with_logger]
@@ -2273,7 +7074,477 @@ mod test {
         request.set_monitor_id(vec![1; 2]);
         request.set_subaddress_index(0);
 
-        assert!(client.get_balance(&request).is_err());
+        assert!(client.get_balance(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_get_balance_by_subaddress_impl(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            account_key.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+        let id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let num_blocks = ledger_db.num_blocks().unwrap();
+
+        // The default subaddress received one output per block.
+        let mut request = mobilecoind_api::GetBalanceBySubaddressRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index(0);
+        let response = client.get_balance_by_subaddress(&request).unwrap();
+        let balance = response.get_balance();
+        assert_eq!(balance.subaddress_index, 0);
+        assert_eq!(
+            balance.balance,
+            test_utils::PER_RECIPIENT_AMOUNT * num_blocks
+        );
+        assert_eq!(balance.synced_balance, balance.balance);
+        assert_eq!(balance.num_utxos, num_blocks);
+        assert_eq!(response.next_block, num_blocks);
+
+        // Subaddresses the monitor does not watch are refused.
+        request.set_subaddress_index(20);
+        assert!(client.get_balance_by_subaddress(&request).is_err());
+
+        // Unknown monitors are refused.
+        request.set_monitor_id(vec![0u8; 32]);
+        request.set_subaddress_index(0);
+        assert!(client.get_balance_by_subaddress(&request).is_err());
+
+        // The list follows the requested order.
+        let mut request = mobilecoind_api::GetBalanceBySubaddressListRequest::new();
+        request.set_monitor_id(id.to_vec());
+        request.set_subaddress_index_list(vec![1, 0]);
+        let response = client.get_balance_by_subaddress_list(&request).unwrap();
+        let balances: Vec<(u64, u64)> = response
+            .get_balance_list()
+            .iter()
+            .map(|balance| (balance.subaddress_index, balance.balance))
+            .collect();
+        assert_eq!(
+            balances,
+            vec![(1, 0), (0, test_utils::PER_RECIPIENT_AMOUNT * num_blocks)]
+        );
+
+        // An empty list covers every subaddress of the monitor.
+        request.clear_subaddress_index_list();
+        let response = client.get_balance_by_subaddress_list(&request).unwrap();
+        assert_eq!(response.get_balance_list().len(), 20);
+
+        // A single unwatched subaddress fails the whole list.
+        request.set_subaddress_index_list(vec![0, 25]);
+        assert!(client.get_balance_by_subaddress_list(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_watch_only_monitor(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Add a watch-only monitor, which only gets the view private key and spend public key.
+        let watch_only_key = WatchOnlyKey::from(&account_key);
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_watch_only_key((&watch_only_key).into());
+        request.set_first_subaddress(0);
+        request.set_num_subaddresses(20);
+        let response = client.add_monitor(&request).unwrap();
+        let id = response.monitor_id;
+
+        // Setting both keys, or neither, should fail.
+        request.set_account_key((&account_key).into());
+        assert!(client.add_monitor(&request).is_err());
+        request.clear_account_key();
+        request.clear_watch_only_key();
+        assert!(client.add_monitor(&request).is_err());
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The monitor should see everything the account received.
+        let mut request = mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(id.clone());
+        request.set_subaddress_index(0);
+        let response = client.get_balance(&request).unwrap();
+        assert_eq!(
+            response.balance,
+            test_utils::PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+
+        // Its subaddresses should match the account's, except for fog.
+        let mut request = mobilecoind_api::GetPublicAddressRequest::new();
+        request.set_monitor_id(id.clone());
+        request.set_subaddress_index(10);
+        let response = client.get_public_address(&request).unwrap();
+        assert_eq!(
+            PublicAddress::try_from(response.get_public_address()).unwrap(),
+            account_key.subaddress(10)
+        );
+
+        // The status should report the watch-only key and no account key.
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(id.clone());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert!(!response.get_status().has_account_key());
+        assert_eq!(
+            WatchOnlyKey::try_from(response.get_status().get_watch_only_key()).unwrap(),
+            watch_only_key
+        );
+
+        // Spending should fail.
+        let mut request = mobilecoind_api::GenerateOptimizationTxRequest::new();
+        request.set_monitor_id(id);
+        request.set_subaddress(0);
+        assert!(client.generate_optimization_tx(&request).is_err());
+    }
+
+    #[test_with_logger]
+    fn test_accounts(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+        let other_account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Create an account with one monitor.
+        let mut monitor_request = mobilecoind_api::AddMonitorRequest::new();
+        monitor_request.set_account_key((&account_key).into());
+        monitor_request.set_first_subaddress(0);
+        monitor_request.set_num_subaddresses(10);
+        let mut request = mobilecoind_api::CreateAccountRequest::new();
+        request.set_name("savings".to_string());
+        request.mut_monitors().push(monitor_request);
+        let response = client.create_account(&request).unwrap();
+        assert_eq!(response.get_account().get_name(), "savings");
+        assert_eq!(response.get_account().get_monitor_ids().len(), 1);
+
+        // Names must be unique and valid.
+        assert!(client.create_account(&request).is_err());
+        request.set_name("".to_string());
+        assert!(client.create_account(&request).is_err());
+
+        // Add a second monitor to the account.
+        let mut request = mobilecoind_api::AddMonitorRequest::new();
+        request.set_account_key((&other_account_key).into());
+        request.set_first_subaddress(0);
+        request.set_num_subaddresses(10);
+        request.set_account_name("savings".to_string());
+        let other_id = client.add_monitor(&request).unwrap().monitor_id;
+
+        // Monitors cannot be added to unknown accounts.
+        request.set_account_name("checking".to_string());
+        assert!(client.add_monitor(&request).is_err());
+
+        // Allow the new monitors to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // The account's balance is that of all of its monitors.
+        let response = client
+            .list_accounts(&mobilecoind_api::Empty::new())
+            .unwrap();
+        assert_eq!(response.get_accounts().len(), 1);
+        let account = &response.get_accounts()[0];
+        assert_eq!(account.get_name(), "savings");
+        assert_eq!(account.get_monitor_ids().len(), 2);
+        assert_eq!(account.get_monitor_ids()[1], other_id);
+        assert_eq!(
+            account.balance,
+            test_utils::PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+
+        // Removing the account removes its monitors.
+        let mut request = mobilecoind_api::RemoveAccountRequest::new();
+        request.set_name("savings".to_string());
+        client.remove_account(&request).unwrap();
+        assert!(client.remove_account(&request).is_err());
+        assert!(client
+            .list_accounts(&mobilecoind_api::Empty::new())
+            .unwrap()
+            .get_accounts()
+            .is_empty());
+        assert!(mobilecoind_db.get_monitor_map().unwrap().is_empty());
+    }
+
+    #[test_with_logger]
+    // Tenants should only see and use the monitors and accounts they added.
+    fn test_tenants(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let alice_key = AccountKey::random(&mut rng);
+        let bob_key = AccountKey::random(&mut rng);
+
+        // Bind a token to each of two tenants.
+        let tenants_dir = TempDir::new("tenants").unwrap();
+        let tenants_path = tenants_dir.path().join("tenants.json");
+        std::fs::write(
+            &tenants_path,
+            format!(
+                r#"[
+                    {{"tenant": "alice", "token_sha3_256": "{}"}},
+                    {{"tenant": "bob", "token_sha3_256": "{}"}}
+                ]"#,
+                hex::encode(token_hash("alice-token")),
+                hex::encode(token_hash("bob-token")),
+            ),
+        )
+        .unwrap();
+        let tenant_tokens = TenantTokens::load(&tenants_path).unwrap();
+
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            test_utils::get_testing_environment_with_tenants(
+                3,
+                &vec![alice_key.default_subaddress(), bob_key.default_subaddress()],
+                &vec![],
+                tenant_tokens,
+                logger.clone(),
+                &mut rng,
+            );
+
+        let call_option = |token: &str| {
+            let mut headers = MetadataBuilder::with_capacity(1);
+            headers
+                .add_str("authorization", &format!("Bearer {}", token))
+                .unwrap();
+            CallOption::default().headers(headers.build())
+        };
+        let add_monitor_request = |account_key: &AccountKey| {
+            let mut request = mobilecoind_api::AddMonitorRequest::new();
+            request.set_account_key(account_key.into());
+            request.set_first_subaddress(0);
+            request.set_num_subaddresses(1);
+            request
+        };
+
+        // Callers need a token bound to a tenant.
+        assert!(client
+            .add_monitor(&add_monitor_request(&alice_key))
+            .is_err());
+        assert!(client
+            .add_monitor_opt(
+                &add_monitor_request(&alice_key),
+                call_option("unknown-token")
+            )
+            .is_err());
+
+        let alice_id = client
+            .add_monitor_opt(&add_monitor_request(&alice_key), call_option("alice-token"))
+            .unwrap()
+            .monitor_id;
+        let bob_id = client
+            .add_monitor_opt(&add_monitor_request(&bob_key), call_option("bob-token"))
+            .unwrap()
+            .monitor_id;
+
+        // Adding a monitor of another tenant again does not hand it over.
+        assert!(client
+            .add_monitor_opt(&add_monitor_request(&alice_key), call_option("bob-token"))
+            .is_err());
+
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Each tenant only lists its own monitors.
+        let response = client
+            .get_monitor_list_opt(&mobilecoind_api::Empty::new(), call_option("alice-token"))
+            .unwrap();
+        assert_eq!(
+            response.get_monitor_id_list().to_vec(),
+            vec![alice_id.clone()]
+        );
+        let response = client
+            .get_monitor_list_opt(&mobilecoind_api::Empty::new(), call_option("bob-token"))
+            .unwrap();
+        assert_eq!(
+            response.get_monitor_id_list().to_vec(),
+            vec![bob_id.clone()]
+        );
+
+        // Balances, history and payments of other tenants' monitors are out of reach.
+        let mut request = mobilecoind_api::GetBalanceRequest::new();
+        request.set_monitor_id(alice_id.clone());
+        let response = client
+            .get_balance_opt(&request, call_option("alice-token"))
+            .unwrap();
+        assert_eq!(
+            response.balance,
+            test_utils::PER_RECIPIENT_AMOUNT * ledger_db.num_blocks().unwrap()
+        );
+        assert!(client
+            .get_balance_opt(&request, call_option("bob-token"))
+            .is_err());
+
+        let mut request = mobilecoind_api::GetTransactionHistoryRequest::new();
+        request.set_monitor_id(alice_id.clone());
+        assert!(client
+            .get_transaction_history_opt(&request, call_option("alice-token"))
+            .is_ok());
+        assert!(client
+            .get_transaction_history_opt(&request, call_option("bob-token"))
+            .is_err());
+
+        let mut request = mobilecoind_api::SendPaymentRequest::new();
+        request.set_sender_monitor_id(alice_id.clone());
+        request.set_outlay_list(RepeatedField::from_vec(vec![(&Outlay {
+            value: 10,
+            receiver: bob_key.default_subaddress(),
+        })
+            .into()]));
+        assert!(client
+            .send_payment_opt(&request, call_option("bob-token"))
+            .is_err());
+
+        // Listing the outgoing transactions of all monitors would reveal those of other tenants.
+        let request = mobilecoind_api::GetOutgoingTxListRequest::new();
+        assert!(client
+            .get_outgoing_tx_list_opt(&request, call_option("alice-token"))
+            .is_err());
+
+        // Neither can other tenants remove the monitor.
+        let mut request = mobilecoind_api::RemoveMonitorRequest::new();
+        request.set_monitor_id(alice_id.clone());
+        assert!(client
+            .remove_monitor_opt(&request, call_option("bob-token"))
+            .is_err());
+        client
+            .remove_monitor_opt(&request, call_option("alice-token"))
+            .unwrap();
+        assert_eq!(mobilecoind_db.get_monitor_map().unwrap().len(), 1);
+
+        // The address book is shared, so tenants cannot use it.
+        assert!(client
+            .get_address_book_opt(&mobilecoind_api::Empty::new(), call_option("alice-token"))
+            .is_err());
+    }
+
+    #[test_with_logger]
+    fn test_address_book(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        let alice = AccountKey::random(&mut rng).default_subaddress();
+        let bob = AccountKey::random(&mut rng).default_subaddress();
+        let entry = |label: &str, address: &PublicAddress, notes: &str| {
+            let mut entry = mobilecoind_api::AddressBookEntry::new();
+            entry.set_label(label.to_string());
+            entry.set_public_address(address.into());
+            entry.set_notes(notes.to_string());
+            entry
+        };
+
+        // Labels must be unique and valid.
+        let mut request = mobilecoind_api::AddAddressBookEntryRequest::new();
+        request.set_entry(entry("alice", &alice, "rent"));
+        client.add_address_book_entry(&request).unwrap();
+        assert!(client.add_address_book_entry(&request).is_err());
+        request.set_entry(entry("", &alice, ""));
+        assert!(client.add_address_book_entry(&request).is_err());
+        request.set_entry(entry("bob", &bob, ""));
+        client.add_address_book_entry(&request).unwrap();
+
+        // Entries are listed by label.
+        let response = client
+            .get_address_book(&mobilecoind_api::Empty::new())
+            .unwrap();
+        let labels: Vec<&str> = response
+            .get_entry_list()
+            .iter()
+            .map(|entry| entry.get_label())
+            .collect();
+        assert_eq!(labels, vec!["alice", "bob"]);
+
+        // Only existing entries can be updated.
+        let mut request = mobilecoind_api::UpdateAddressBookEntryRequest::new();
+        request.set_entry(entry("alice", &alice, "rent and utilities"));
+        client.update_address_book_entry(&request).unwrap();
+        request.set_entry(entry("carol", &alice, ""));
+        assert!(client.update_address_book_entry(&request).is_err());
+
+        let mut request = mobilecoind_api::GetAddressBookEntryRequest::new();
+        request.set_label("alice".to_string());
+        let response = client.get_address_book_entry(&request).unwrap();
+        assert_eq!(
+            PublicAddress::try_from(response.get_entry().get_public_address()).unwrap(),
+            alice
+        );
+        assert_eq!(response.get_entry().get_notes(), "rent and utilities");
+
+        // Payments can refer to their receiver by label.
+        let mut outlay = mobilecoind_api::Outlay::new();
+        outlay.set_value(123);
+        outlay.set_receiver_label("alice".to_string());
+        let mut request = mobilecoind_api::SendPaymentRequest::new();
+        request.set_sender_monitor_id(monitor_id.to_vec());
+        request.set_sender_subaddress(0);
+        request.mut_outlay_list().push(outlay.clone());
+        let response = client.send_payment(&request).unwrap();
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        assert_eq!(tx_proposal.outlays[0].receiver, alice);
+
+        // A label and a receiver cannot be given together.
+        request.mut_outlay_list()[0].set_receiver((&bob).into());
+        assert!(client.send_payment(&request).is_err());
+
+        // Removed entries cannot be paid.
+        let mut remove_request = mobilecoind_api::RemoveAddressBookEntryRequest::new();
+        remove_request.set_label("alice".to_string());
+        client.remove_address_book_entry(&remove_request).unwrap();
+        assert!(client.remove_address_book_entry(&remove_request).is_err());
+        request.set_outlay_list(RepeatedField::from_vec(vec![outlay]));
+        assert!(client.send_payment(&request).is_err());
     }
 
     #[test_with_logger]
@@ -2437,6 +7708,128 @@ mod test {
         assert_eq!(matched_utxos, tx_proposal.utxos.len());
     }
 
+    #[test_with_logger]
+    fn test_send_payment_queue_if_change_pending(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let data = MonitorData::new(
+            sender.clone(),
+            0,  // first_subaddress
+            20, // num_subaddresses
+            0,  // first_block
+        )
+        .unwrap();
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        // Insert into database.
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Allow the new monitor to process the ledger.
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Pretend all of our utxos are inputs of a pending transaction.
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        let utxo_ids: Vec<UtxoId> = utxos.iter().map(UtxoId::from).collect();
+        mobilecoind_db
+            .update_attempted_spend(&utxo_ids, 1, 1000)
+            .unwrap();
+
+        let receiver = AccountKey::random(&mut rng);
+        let payment_request = |value: u64| {
+            let mut request = mobilecoind_api::SendPaymentRequest::new();
+            request.set_sender_monitor_id(monitor_id.to_vec());
+            request.set_sender_subaddress(0);
+            request.set_outlay_list(RepeatedField::from_vec(vec![
+                mobilecoind_api::Outlay::from(&Outlay {
+                    value,
+                    receiver: receiver.default_subaddress(),
+                }),
+            ]));
+            request.set_queue_if_change_pending(true);
+            request
+        };
+
+        // Payments that cannot be funded even once the pending transactions land should fail
+        // right away.
+        let total_value = utxos.iter().map(|utxo| utxo.value).sum();
+        assert!(client.send_payment(&payment_request(total_value)).is_err());
+
+        // Both payments should be queued, since all funds are pending.
+        let response = client
+            .send_payment(&payment_request(PER_RECIPIENT_AMOUNT / 2))
+            .unwrap();
+        assert!(response.queued);
+        let queued_payment_id1 = response.queued_payment_id;
+
+        let response = client
+            .send_payment(&payment_request(PER_RECIPIENT_AMOUNT / 2))
+            .unwrap();
+        assert!(response.queued);
+        let queued_payment_id2 = response.queued_payment_id;
+        assert_ne!(queued_payment_id1, queued_payment_id2);
+
+        let mut request = mobilecoind_api::GetQueuedPaymentStatusRequest::new();
+        request.set_queued_payment_id(queued_payment_id1);
+        let response = client.get_queued_payment_status(&request).unwrap();
+        assert_eq!(
+            response.get_status(),
+            mobilecoind_api::QueuedPaymentStatus::PaymentQueued
+        );
+
+        // Unknown payments should produce an error.
+        request.set_queued_payment_id(queued_payment_id2 + 1);
+        assert!(client.get_queued_payment_status(&request).is_err());
+
+        // Once change lands, the first payment should be submitted.
+        let _ = add_block_to_ledger_db(
+            &mut ledger_db,
+            &vec![sender.default_subaddress()],
+            &vec![],
+            &mut rng,
+        );
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        request.set_queued_payment_id(queued_payment_id1);
+        let mut response = client.get_queued_payment_status(&request).unwrap();
+        for _ in 0..10 {
+            if response.get_status() != mobilecoind_api::QueuedPaymentStatus::PaymentQueued {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            response = client.get_queued_payment_status(&request).unwrap();
+        }
+        assert_eq!(
+            response.get_status(),
+            mobilecoind_api::QueuedPaymentStatus::PaymentSubmitted
+        );
+        assert_eq!(response.get_receiver_tx_receipt_list().len(), 1);
+
+        // The payment should only spend the new utxo.
+        let tx_proposal = TxProposal::try_from(response.get_tx_proposal()).unwrap();
+        assert_eq!(tx_proposal.utxos.len(), 1);
+        assert!(!utxo_ids.contains(&UtxoId::from(&tx_proposal.utxos[0])));
+
+        // The second payment is still waiting, since the new utxo is now pending as well.
+        request.set_queued_payment_id(queued_payment_id2);
+        let response = client.get_queued_payment_status(&request).unwrap();
+        assert_eq!(
+            response.get_status(),
+            mobilecoind_api::QueuedPaymentStatus::PaymentQueued
+        );
+    }
+
     #[test_with_logger]
     fn test_request_code(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2530,6 +7923,125 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    fn test_create_parse_request(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let receiver = AccountKey::random(&mut rng).default_subaddress();
+        let payment_request = PaymentRequest::new(receiver, 1234567890, "invoice 42").unwrap();
+
+        let mut request = mobilecoind_api::CreateRequestRequest::new();
+        request.set_request((&payment_request).into());
+        let response = client.create_request(&request).unwrap();
+
+        // The request code is compatible with ReadRequestCode.
+        {
+            let mut request = mobilecoind_api::ReadRequestCodeRequest::new();
+            request.set_b58_code(response.get_b58_code().to_owned());
+            let read_response = client.read_request_code(&request).unwrap();
+            assert_eq!(read_response.get_value(), 1234567890);
+            assert_eq!(read_response.get_memo(), "invoice 42");
+        }
+
+        // Both encodings parse back to the same request.
+        {
+            let mut request = mobilecoind_api::ParseRequestRequest::new();
+            request.set_b58_code(response.get_b58_code().to_owned());
+            let parse_response = client.parse_request(&request).unwrap();
+            assert_eq!(
+                PaymentRequest::try_from(parse_response.get_request()).unwrap(),
+                payment_request
+            );
+        }
+        {
+            let mut request = mobilecoind_api::ParseRequestRequest::new();
+            request.set_request_bytes(response.get_request_bytes().to_vec());
+            let parse_response = client.parse_request(&request).unwrap();
+            assert_eq!(
+                PaymentRequest::try_from(parse_response.get_request()).unwrap(),
+                payment_request
+            );
+        }
+
+        // Exactly one encoding must be given, and junk does not parse.
+        {
+            let request = mobilecoind_api::ParseRequestRequest::new();
+            assert!(client.parse_request(&request).is_err());
+
+            let mut request = mobilecoind_api::ParseRequestRequest::new();
+            request.set_b58_code(response.get_b58_code().to_owned());
+            request.set_request_bytes(response.get_request_bytes().to_vec());
+            assert!(client.parse_request(&request).is_err());
+
+            let mut request = mobilecoind_api::ParseRequestRequest::new();
+            request.set_b58_code("junk".to_owned());
+            assert!(client.parse_request(&request).is_err());
+        }
+
+        // Requests without a receiver are rejected.
+        {
+            let request = mobilecoind_api::CreateRequestRequest::new();
+            assert!(client.create_request(&request).is_err());
+        }
+    }
+
+    #[test_with_logger]
+    fn test_create_parse_mob_uri(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        // no known recipient, 3 random recipients and no monitors.
+        let (_ledger_db, _mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(3, &vec![], &vec![], logger.clone(), &mut rng);
+
+        let mut mob_uri = MobUri::new(AccountKey::random(&mut rng).default_subaddress());
+        mob_uri.amount = 1234567890;
+        mob_uri.memo = "table 4".to_owned();
+
+        let mut request = mobilecoind_api::CreateMobUriRequest::new();
+        request.set_mob_uri((&mob_uri).into());
+        let response = client.create_mob_uri(&request).unwrap();
+        assert_eq!(response.get_uri(), mob_uri.encode().unwrap());
+
+        // The URI parses back to the same request, which does not expire.
+        {
+            let mut request = mobilecoind_api::ParseMobUriRequest::new();
+            request.set_uri(response.get_uri().to_owned());
+            let parse_response = client.parse_mob_uri(&request).unwrap();
+            assert_eq!(
+                MobUri::try_from(parse_response.get_mob_uri()).unwrap(),
+                mob_uri
+            );
+            assert!(!parse_response.get_expired());
+        }
+
+        // Expired requests are parsed, and flagged.
+        {
+            mob_uri.expiry = 1;
+            let mut request = mobilecoind_api::ParseMobUriRequest::new();
+            request.set_uri(mob_uri.encode().unwrap());
+            let parse_response = client.parse_mob_uri(&request).unwrap();
+            assert_eq!(
+                MobUri::try_from(parse_response.get_mob_uri()).unwrap(),
+                mob_uri
+            );
+            assert!(parse_response.get_expired());
+        }
+
+        // Junk does not parse, and URIs without an address cannot be created.
+        {
+            let mut request = mobilecoind_api::ParseMobUriRequest::new();
+            request.set_uri("mob:junk".to_owned());
+            assert!(client.parse_mob_uri(&request).is_err());
+
+            let request = mobilecoind_api::CreateMobUriRequest::new();
+            assert!(client.create_mob_uri(&request).is_err());
+        }
+    }
+
     #[test_with_logger]
     fn test_transfer_code(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
@@ -2580,4 +8092,276 @@ mod test {
             assert_eq!(response.get_memo(), "test memo");
         }
     }
+
+    #[test_with_logger]
+    fn test_create_and_redeem_transfer_code(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([23u8; 32]);
+
+        let sender = AccountKey::random(&mut rng);
+        let receiver = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (mut ledger_db, mobilecoind_db, client, _server, server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![sender.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let sender_monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(sender, 0, 1, 0).unwrap())
+            .unwrap();
+        let receiver_monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(receiver, 0, 1, 0).unwrap())
+            .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+
+        // Appends the last transaction submitted to the mock peers to the ledger.
+        let append_submitted_tx = |ledger_db: &mut LedgerDB| {
+            let tx = server_conn_manager
+                .conns()
+                .iter()
+                .filter_map(|mock_peer| mock_peer.read().submitted_txs.last().cloned())
+                .last()
+                .expect("no transaction submitted");
+            let block_contents = BlockContents::new(tx.key_images(), tx.prefix.outputs.clone());
+            let num_blocks = ledger_db.num_blocks().unwrap();
+            let parent = ledger_db.get_block(num_blocks - 1).unwrap();
+            let new_block = Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                num_blocks as BlockIndex,
+                &Default::default(),
+                &block_contents,
+            );
+            ledger_db
+                .append_block(&new_block, &block_contents, None)
+                .unwrap();
+        };
+
+        // Create a code.
+        let mut request = mobilecoind_api::CreateTransferCodeRequest::new();
+        request.set_sender_monitor_id(sender_monitor_id.to_vec());
+        request.set_value(1337 * BASE_FEE);
+        request.set_memo("happy birthday".to_owned());
+        let response = client.create_transfer_code(&request).unwrap();
+        let b58_code = response.get_b58_code().to_owned();
+
+        // The code is kept in the database.
+        let tx_public_key = RistrettoPublic::try_from(response.get_tx_public_key()).unwrap();
+        let transfer_code = mobilecoind_db
+            .get_transfer_code(&CompressedRistrettoPublic::from(&tx_public_key))
+            .unwrap();
+        assert_eq!(transfer_code.get_status(), TransferCodeStatus::Created);
+        assert_eq!(transfer_code.entropy, response.get_entropy());
+        assert_eq!(transfer_code.sender_monitor_id, Some(sender_monitor_id));
+
+        // The code cannot be redeemed until its funds are in the ledger.
+        let mut request = mobilecoind_api::RedeemTransferCodeRequest::new();
+        request.set_b58_code(b58_code);
+        request.set_monitor_id(receiver_monitor_id.to_vec());
+        request.set_fee(BASE_FEE);
+        match client.redeem_transfer_code(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::FAILED_PRECONDITION)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Once they are, the receiver gets the funds minus the fee.
+        append_submitted_tx(&mut ledger_db);
+        let response = client.redeem_transfer_code(&request).unwrap();
+        assert_eq!(response.get_value(), 1336 * BASE_FEE);
+        assert_eq!(response.get_memo(), "happy birthday");
+
+        let transfer_code = mobilecoind_db
+            .get_transfer_code(&CompressedRistrettoPublic::from(&tx_public_key))
+            .unwrap();
+        assert_eq!(transfer_code.get_status(), TransferCodeStatus::Redeemed);
+        assert_eq!(transfer_code.redeemer_monitor_id, Some(receiver_monitor_id));
+
+        append_submitted_tx(&mut ledger_db);
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&receiver_monitor_id, 0)
+            .unwrap();
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos[0].value, 1336 * BASE_FEE);
+
+        // A code can only be redeemed once.
+        match client.redeem_transfer_code(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::ALREADY_EXISTS)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    fn test_unlock_and_reencrypt_database(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([24u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(account_key, 0, 1, 0).unwrap())
+            .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 0)
+            .unwrap();
+        assert_eq!(utxos.len(), 1);
+
+        let secret_request = |keyfile: &[u8]| {
+            let mut secret = mobilecoind_api::DatabaseSecret::new();
+            secret.set_keyfile(keyfile.to_vec());
+            secret
+        };
+        fn expect_status(result: grpcio::Result<mobilecoind_api::Empty>, code: RpcStatusCode) {
+            match result {
+                Err(grpcio::Error::RpcFailure(status)) => assert_eq!(status.status, code),
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+
+        // The database is not encrypted yet.
+        let mut request = mobilecoind_api::UnlockDatabaseRequest::new();
+        request.set_secret(secret_request(&[1u8; 32]));
+        expect_status(
+            client.unlock_database(&request),
+            RpcStatusCode::FAILED_PRECONDITION,
+        );
+
+        // Secrets are either a password or a keyfile.
+        let mut request = mobilecoind_api::ReencryptDatabaseRequest::new();
+        let mut secret = secret_request(&[1u8; 32]);
+        secret.set_password("password".to_string());
+        request.set_secret(secret);
+        expect_status(
+            client.reencrypt_database(&request),
+            RpcStatusCode::INVALID_ARGUMENT,
+        );
+
+        // Encrypt the database, and then switch to another keyfile.
+        let mut request = mobilecoind_api::ReencryptDatabaseRequest::new();
+        request.set_secret(secret_request(&[1u8; 32]));
+        client.reencrypt_database(&request).unwrap();
+        assert!(mobilecoind_db.is_encrypted());
+
+        request.set_secret(secret_request(&[2u8; 32]));
+        client.reencrypt_database(&request).unwrap();
+
+        // Only the current keyfile unlocks the database.
+        let mut request = mobilecoind_api::UnlockDatabaseRequest::new();
+        request.set_secret(secret_request(&[1u8; 32]));
+        expect_status(
+            client.unlock_database(&request),
+            RpcStatusCode::PERMISSION_DENIED,
+        );
+        request.set_secret(secret_request(&[2u8; 32]));
+        client.unlock_database(&request).unwrap();
+
+        // Monitors and their UTXOs survive being re-encrypted.
+        let mut request = mobilecoind_api::GetMonitorStatusRequest::new();
+        request.set_monitor_id(monitor_id.to_vec());
+        let response = client.get_monitor_status(&request).unwrap();
+        assert_eq!(response.get_status().get_num_subaddresses(), 1);
+        assert_eq!(
+            mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, 0)
+                .unwrap(),
+            utxos
+        );
+    }
+
+    #[test_with_logger]
+    fn test_export_and_import_state(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([25u8; 32]);
+
+        let account_key = AccountKey::random(&mut rng);
+
+        // 1 known recipient, 3 random recipients and no monitors.
+        let (ledger_db, mobilecoind_db, client, _server, _server_conn_manager) =
+            get_testing_environment(
+                3,
+                &vec![account_key.default_subaddress()],
+                &vec![],
+                logger.clone(),
+                &mut rng,
+            );
+
+        let monitor_id = mobilecoind_db
+            .add_monitor(&MonitorData::new(account_key.clone(), 0, 1, 0).unwrap())
+            .unwrap();
+        wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        let (monitor_data, utxos) = mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap();
+        assert_eq!(utxos.len(), 1);
+        let history = mobilecoind_db
+            .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+            .unwrap();
+        assert!(!history.is_empty());
+
+        let entry =
+            AddressBookEntry::new("alice", &account_key.default_subaddress(), "notes").unwrap();
+        mobilecoind_db.add_address_book_entry(&entry).unwrap();
+
+        let response = client.export_state(&mobilecoind_api::Empty::new()).unwrap();
+        assert_eq!(response.get_num_blocks(), ledger_db.num_blocks().unwrap());
+        assert!(response.get_skipped_monitor_ids().is_empty());
+
+        // The state cannot be imported over itself.
+        let mut request = mobilecoind_api::ImportStateRequest::new();
+        request.set_archive(response.get_archive().to_vec());
+        match client.import_state(&request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::ALREADY_EXISTS)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Archives that were tampered with are refused.
+        let mut archive: StateArchive = mcserial::decode(response.get_archive()).unwrap();
+        archive.contents[0] ^= 1;
+        let mut tampered_request = mobilecoind_api::ImportStateRequest::new();
+        tampered_request.set_archive(mcserial::encode(&archive));
+        match client.import_state(&tampered_request) {
+            Err(grpcio::Error::RpcFailure(status)) => {
+                assert_eq!(status.status, RpcStatusCode::INVALID_ARGUMENT)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        // Once removed, the monitor and address book entry are restored as they were, without
+        // rescanning the ledger.
+        mobilecoind_db.remove_monitor(&monitor_id).unwrap();
+        mobilecoind_db.remove_address_book_entry("alice").unwrap();
+
+        let response = client.import_state(&request).unwrap();
+        assert_eq!(response.get_monitor_ids(), &[monitor_id.to_vec()][..]);
+        assert_eq!(response.get_num_address_book_entries(), 1);
+
+        assert_eq!(
+            mobilecoind_db.get_monitor_utxos(&monitor_id).unwrap(),
+            (monitor_data, utxos)
+        );
+        assert_eq!(
+            mobilecoind_db
+                .get_transaction_history(&monitor_id, None, 0, u64::MAX)
+                .unwrap(),
+            history
+        );
+        assert_eq!(mobilecoind_db.get_address_book().unwrap(), vec![entry]);
+    }
 }
@@ -0,0 +1,352 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for spent outputs.
+//! * Manages the mapping of (monitor id, spent block index, key image) -> SpentTxOut.
+//! * When a monitor's UnspentTxOut is spent, it is removed from the utxo store and kept here
+//!   along with the block it was spent in, so that spends can be reconciled and accounted for.
+//!   Spent TxOuts are removed along with their monitor.
+
+use crate::{
+    database::key_bytes::{decode_monitor_block_key_prefix, monitor_block_key_prefix},
+    encryption::{DatabaseCipher, Rekeying},
+    error::Error,
+    monitor_store::MonitorId,
+    utxo_store::UnspentTxOut,
+};
+
+use common::logger::Logger;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+use transaction::{ring_signature::KeyImage, BlockIndex};
+
+// LMDB Database Names
+pub const SPENT_TXO_KEY_TO_SPENT_TXO_DB_NAME: &str =
+    "mobilecoind_db:spent_txo_store:spent_txo_key_to_spent_txo";
+
+/// An UnspentTxOut of a monitor that has been spent.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct SpentTxOut {
+    /// The UnspentTxOut, as it was when it was spent.
+    #[prost(message, required, tag = "1")]
+    pub utxo: UnspentTxOut,
+
+    /// The block its key image appeared in.
+    #[prost(uint64, tag = "2")]
+    pub spent_block_index: u64,
+}
+
+/// Type used as the key in the spent_txo_key_to_spent_txo database.
+/// Keys are ordered by monitor id, then by spent block index, so that the spent TxOuts of a
+/// monitor can be iterated in the order they were spent.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SpentTxOutKey {
+    pub monitor_id: MonitorId,
+    pub spent_block_index: BlockIndex,
+    pub key_image: KeyImage,
+}
+
+impl SpentTxOutKey {
+    pub fn new(
+        monitor_id: &MonitorId,
+        spent_block_index: BlockIndex,
+        key_image: &KeyImage,
+    ) -> Self {
+        Self {
+            monitor_id: *monitor_id,
+            spent_block_index,
+            key_image: *key_image,
+        }
+    }
+
+    // 72 bytes: 32 for MonitorId, 8 for the big-endian block index (so that keys sort by block),
+    // 32 for the key image.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = monitor_block_key_prefix(&self.monitor_id, self.spent_block_index);
+        buf.extend_from_slice(self.key_image.as_bytes());
+        buf
+    }
+}
+
+/// The spent outputs database.
+#[derive(Clone)]
+pub struct SpentTxoStore {
+    env: Arc<Environment>,
+
+    /// Mapping of SpentTxOutKey -> SpentTxOut, sealed by the cipher.
+    spent_txo_key_to_spent_txo: Database,
+
+    /// Seals and opens SpentTxOuts, which reveal values and key images.
+    cipher: DatabaseCipher,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl SpentTxoStore {
+    pub fn new(
+        env: Arc<Environment>,
+        cipher: DatabaseCipher,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let spent_txo_key_to_spent_txo = env.create_db(
+            Some(SPENT_TXO_KEY_TO_SPENT_TXO_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            spent_txo_key_to_spent_txo,
+            cipher,
+            logger,
+        })
+    }
+
+    /// Record the UnspentTxOuts a monitor spent in a given block.
+    pub fn block_processed<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        spent_block_index: BlockIndex,
+        spent_utxos: &[UnspentTxOut],
+    ) -> Result<(), Error> {
+        for utxo in spent_utxos {
+            let key = SpentTxOutKey::new(monitor_id, spent_block_index, &utxo.key_image).to_vec();
+            let spent_txo = SpentTxOut {
+                utxo: utxo.clone(),
+                spent_block_index,
+            };
+            let value_bytes = self.cipher.seal(&key, mcserial::encode(&spent_txo))?;
+            db_txn.put(
+                self.spent_txo_key_to_spent_txo,
+                &key,
+                &value_bytes,
+                WriteFlags::empty(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the TxOuts a monitor spent, in the order they were spent.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor to get the spent TxOuts of.
+    /// * `subaddress_index` - If set, only TxOuts of this subaddress are returned.
+    /// * `first_block` - The first spent block to include.
+    /// * `max_blocks` - The maximal number of blocks to look at, starting at `first_block`.
+    pub fn get_spent_txos(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        subaddress_index: Option<u64>,
+        first_block: BlockIndex,
+        max_blocks: u64,
+    ) -> Result<Vec<SpentTxOut>, Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, first_block);
+        let end_block = first_block.saturating_add(max_blocks);
+
+        let mut cursor = db_txn.open_ro_cursor(self.spent_txo_key_to_spent_txo)?;
+        let mut results = Vec::new();
+        for (key_bytes, value_bytes) in cursor.iter_from(&start_key) {
+            let (key_monitor_id, spent_block_index) = decode_monitor_block_key_prefix(key_bytes)?;
+            if key_monitor_id != *monitor_id || spent_block_index >= end_block {
+                break;
+            }
+
+            let spent_txo: SpentTxOut =
+                mcserial::decode(&self.cipher.open(key_bytes, value_bytes)?)?;
+            if subaddress_index.map_or(true, |index| index == spent_txo.utxo.subaddress_index) {
+                results.push(spent_txo);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Remove all spent TxOuts of a given monitor.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        self.remove_from_block(db_txn, monitor_id, 0)?;
+        Ok(())
+    }
+
+    /// Remove the TxOuts a given monitor spent at or after `first_block`, returning them in the
+    /// order they were spent.
+    pub fn remove_from_block<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        first_block: BlockIndex,
+    ) -> Result<Vec<SpentTxOut>, Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, first_block);
+
+        // Collect the entries first, since we cannot delete while iterating a read cursor.
+        let mut keys = Vec::new();
+        let mut spent_txos = Vec::new();
+        {
+            let mut cursor = db_txn.open_ro_cursor(self.spent_txo_key_to_spent_txo)?;
+            for (key_bytes, value_bytes) in cursor.iter_from(&start_key) {
+                let (key_monitor_id, _spent_block_index) =
+                    decode_monitor_block_key_prefix(key_bytes)?;
+                if key_monitor_id != *monitor_id {
+                    break;
+                }
+                let spent_txo: SpentTxOut =
+                    mcserial::decode(&self.cipher.open(key_bytes, value_bytes)?)?;
+                keys.push(key_bytes.to_vec());
+                spent_txos.push(spent_txo);
+            }
+        }
+
+        for key in keys {
+            db_txn.del(self.spent_txo_key_to_spent_txo, &key, None)?;
+        }
+
+        Ok(spent_txos)
+    }
+
+    /// Reseal every spent TxOut with a new key.
+    pub fn reseal<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        rekeying: &Rekeying,
+    ) -> Result<(), Error> {
+        rekeying.reseal_all(db_txn, self.spent_txo_key_to_spent_txo)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use keys::{FromRandom, RistrettoPrivate};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::{account_keys::AccountKey, tx::TxOut};
+
+    #[test_with_logger]
+    fn test_block_processed_and_get_spent_txos(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let db_tmp = TempDir::new("spent_txo_store_db")
+            .expect("Could not make tempdir for spent txo store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        let store = SpentTxoStore::new(env.clone(), cipher, logger.clone()).unwrap();
+
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let mut utxos: Vec<UnspentTxOut> = (0..4)
+            .map(|idx| UnspentTxOut {
+                tx_out: TxOut::new(
+                    10,
+                    &recipient,
+                    &RistrettoPrivate::from_random(&mut rng),
+                    Default::default(),
+                    &mut rng,
+                )
+                .unwrap(),
+                subaddress_index: 0,
+                key_image: KeyImage::from(idx),
+                value: 10,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                memo: String::new(),
+            })
+            .collect();
+        utxos[1].subaddress_index = 1;
+
+        let spent_txo = |utxo: &UnspentTxOut, spent_block_index: u64| SpentTxOut {
+            utxo: utxo.clone(),
+            spent_block_index,
+        };
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id0, 7, &utxos[2..3])
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id0, 3, &utxos[0..2])
+                .unwrap();
+            store
+                .block_processed(&mut db_txn, &monitor_id1, 5, &utxos[3..4])
+                .unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+
+        // Spent TxOuts are returned in the order they were spent.
+        let spent_txos = store
+            .get_spent_txos(&db_txn, &monitor_id0, None, 0, u64::MAX)
+            .unwrap();
+        assert_eq!(spent_txos.len(), 3);
+        assert_eq!(spent_txos[2], spent_txo(&utxos[2], 7));
+        assert!(spent_txos[0..2].contains(&spent_txo(&utxos[0], 3)));
+        assert!(spent_txos[0..2].contains(&spent_txo(&utxos[1], 3)));
+
+        // Filtering by block and subaddress.
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id0, None, 4, 10)
+                .unwrap(),
+            vec![spent_txo(&utxos[2], 7)]
+        );
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id0, None, 0, 3)
+                .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id0, Some(1), 0, u64::MAX)
+                .unwrap(),
+            vec![spent_txo(&utxos[1], 3)]
+        );
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id1, None, 0, u64::MAX)
+                .unwrap(),
+            vec![spent_txo(&utxos[3], 5)]
+        );
+        drop(db_txn);
+
+        // Removing a monitor's spent TxOuts leaves the other monitors' untouched.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store.remove(&mut db_txn, &monitor_id0).unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id0, None, 0, u64::MAX)
+                .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            store
+                .get_spent_txos(&db_txn, &monitor_id1, None, 0, u64::MAX)
+                .unwrap(),
+            vec![spent_txo(&utxos[3], 5)]
+        );
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! State archives.
+//!
+//! An archive holds everything mobilecoind knows about its monitors: their data, including the
+//! subaddresses they watch, their unspent outputs and their transaction history, along with the
+//! address book. Importing it on another host restores the monitors already synced up to the
+//! block they had reached, so that moving mobilecoind does not require rescanning the ledger.
+//!
+//! Archives hold the account keys of the monitors in plaintext, and must be kept as secret as the
+//! keys themselves. The digest they carry only detects archives that were truncated or corrupted
+//! on their way, it does not establish where an archive came from.
+
+use crate::{
+    account_snapshot::last_block_id, address_book_store::AddressBookEntry, error::Error,
+    monitor_store::MonitorData, transaction_log_store::TransactionLogEntry,
+    utxo_store::UnspentTxOut,
+};
+use ledger_db::{Ledger, LedgerDB};
+use mcserial::Message;
+use sha3::{Digest, Sha3_256};
+
+/// The version of the archives this mobilecoind exports. Archives of other versions are refused.
+pub const STATE_ARCHIVE_VERSION: u32 = 1;
+
+/// The state of a single monitor.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct MonitorState {
+    /// The monitor's data, as of `next_block`.
+    #[prost(message, required, tag = "1")]
+    pub monitor_data: MonitorData,
+
+    /// The monitor's unspent outputs as of `monitor_data.next_block`.
+    #[prost(message, repeated, tag = "2")]
+    pub utxos: Vec<UnspentTxOut>,
+
+    /// The monitor's transaction history, in block order.
+    #[prost(message, repeated, tag = "3")]
+    pub history: Vec<TransactionLogEntry>,
+}
+
+/// The state of a mobilecoind instance.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct StateArchiveContents {
+    /// The number of blocks in the ledger when the archive was exported. Every monitor's
+    /// `next_block` is at most this.
+    #[prost(uint64, tag = "1")]
+    pub num_blocks: u64,
+
+    /// Id of the last block in the ledger when the archive was exported, which ties the archive
+    /// to a specific ledger. Empty if the ledger had no blocks.
+    #[prost(bytes, tag = "2")]
+    pub last_block_id: Vec<u8>,
+
+    /// The monitors.
+    #[prost(message, repeated, tag = "3")]
+    pub monitors: Vec<MonitorState>,
+
+    /// The address book entries, ordered by label.
+    #[prost(message, repeated, tag = "4")]
+    pub address_book: Vec<AddressBookEntry>,
+}
+
+impl StateArchiveContents {
+    /// Captures the state read from the database. The ledger has to be read after the database,
+    /// so that it has reached every block the monitors processed.
+    pub fn new(
+        monitors: Vec<MonitorState>,
+        address_book: Vec<AddressBookEntry>,
+        ledger_db: &LedgerDB,
+    ) -> Result<Self, Error> {
+        let num_blocks = ledger_db.num_blocks()?;
+        Ok(Self {
+            num_blocks,
+            last_block_id: last_block_id(ledger_db, num_blocks)?,
+            monitors,
+            address_book,
+        })
+    }
+
+    /// Checks the archive against the local ledger, which needs to have reached the blocks the
+    /// archive reflects, with matching block ids.
+    pub fn verify_against_ledger(&self, ledger_db: &LedgerDB) -> Result<(), Error> {
+        if self.num_blocks > ledger_db.num_blocks()? {
+            return Err(Error::StateArchive(format!(
+                "Ledger has not reached block {} yet",
+                self.num_blocks
+            )));
+        }
+        if last_block_id(ledger_db, self.num_blocks)? != self.last_block_id {
+            return Err(Error::StateArchive(format!(
+                "Block {} does not match the ledger",
+                self.num_blocks.saturating_sub(1)
+            )));
+        }
+        if let Some(monitor_state) = self
+            .monitors
+            .iter()
+            .find(|monitor_state| monitor_state.monitor_data.next_block > self.num_blocks)
+        {
+            return Err(Error::StateArchive(format!(
+                "A monitor is ahead of the archive, at block {}",
+                monitor_state.monitor_data.next_block
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// An archive, as handed out to clients.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct StateArchive {
+    /// The version of the archive format.
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+
+    /// The encoded `StateArchiveContents`. This is kept as bytes so that the digest covers its
+    /// exact bytes.
+    #[prost(bytes, tag = "2")]
+    pub contents: Vec<u8>,
+
+    /// SHA3-256 digest of `contents`.
+    #[prost(bytes, tag = "3")]
+    pub digest: Vec<u8>,
+}
+
+impl StateArchive {
+    /// Serializes the contents of an archive.
+    pub fn new(contents: &StateArchiveContents) -> Self {
+        let contents = mcserial::encode(contents);
+        Self {
+            version: STATE_ARCHIVE_VERSION,
+            digest: Sha3_256::digest(&contents).to_vec(),
+            contents,
+        }
+    }
+
+    /// Checks the version and digest of the archive, and returns its contents.
+    pub fn open(&self) -> Result<StateArchiveContents, Error> {
+        if self.version != STATE_ARCHIVE_VERSION {
+            return Err(Error::StateArchive(format!(
+                "Unsupported archive version {}, expected {}",
+                self.version, STATE_ARCHIVE_VERSION
+            )));
+        }
+        if Sha3_256::digest(&self.contents).as_slice() != self.digest.as_slice() {
+            return Err(Error::StateArchive("Archive digest mismatch".to_string()));
+        }
+        Ok(mcserial::decode(&self.contents)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_databases;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::account_keys::{AccountKey, DEFAULT_SUBADDRESS_INDEX};
+
+    #[test_with_logger]
+    // Archives should only open unchanged, and only against the ledger they were exported from.
+    fn test_open_and_verify_archive(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([44u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+        let (ledger_db, _mobilecoind_db) = get_test_databases(
+            3,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger,
+            &mut rng,
+        );
+
+        let mut monitor_data =
+            MonitorData::new(account_key, DEFAULT_SUBADDRESS_INDEX, 1, 0).unwrap();
+        monitor_data.next_block = ledger_db.num_blocks().unwrap();
+        let monitor_state = MonitorState {
+            monitor_data,
+            utxos: vec![],
+            history: vec![],
+        };
+        let contents = StateArchiveContents::new(vec![monitor_state], vec![], &ledger_db).unwrap();
+        let archive = StateArchive::new(&contents);
+
+        let opened: StateArchive = mcserial::decode(&mcserial::encode(&archive)).unwrap();
+        assert_eq!(opened.open().unwrap(), contents);
+        contents.verify_against_ledger(&ledger_db).unwrap();
+
+        // Corrupted contents.
+        let mut tampered = archive.clone();
+        let last = tampered.contents.len() - 1;
+        tampered.contents[last] ^= 1;
+        assert!(tampered.open().is_err());
+
+        // Another version.
+        let mut tampered = archive;
+        tampered.version += 1;
+        assert!(tampered.open().is_err());
+
+        // A block id from another ledger.
+        let mut tampered = contents.clone();
+        tampered.last_block_id[0] ^= 1;
+        assert!(tampered.verify_against_ledger(&ledger_db).is_err());
+
+        // Blocks the ledger does not have yet.
+        let mut tampered = contents.clone();
+        tampered.num_blocks += 1;
+        assert!(tampered.verify_against_ledger(&ledger_db).is_err());
+
+        // A monitor ahead of the archive.
+        let mut tampered = contents;
+        tampered.monitors[0].monitor_data.next_block += 1;
+        assert!(tampered.verify_against_ledger(&ledger_db).is_err());
+    }
+}
@@ -116,8 +116,7 @@ impl SubaddressStore {
         data: &MonitorData,
         index: u64,
     ) -> Result<(), Error> {
-        let subaddress_spk =
-            SubaddressSPKId::from(data.account_key.subaddress(index).spend_public_key());
+        let subaddress_spk = SubaddressSPKId::from(data.subaddress(index).spend_public_key());
         let subaddress_id: SubaddressId = SubaddressId::new(monitor_id, index);
 
         let value_bytes = mcserial::encode(&subaddress_id);
@@ -163,8 +162,7 @@ impl SubaddressStore {
         data: &MonitorData,
         index: u64,
     ) -> Result<(), Error> {
-        let subaddress_spk =
-            SubaddressSPKId::from(data.account_key.subaddress(index).spend_public_key());
+        let subaddress_spk = SubaddressSPKId::from(data.subaddress(index).spend_public_key());
 
         db_txn.del(self.spk_to_index_data, &subaddress_spk, None)?;
 
@@ -2,56 +2,61 @@
 
 //! Manages ledger block scanning for mobilecoind monitors.
 //!
-//! The sync code creates a pool of worker threads, and a main thread to hand off tasks to the
-//! worker threads over a crossbeam channel. Each task is a request to sync block data for a given
-//! monitor id. Each task is limited to a pre-defined amount of blocks - this is useful when the
-//! amount of monitors exceeds the amount of working threads as it ensures monitors are processed
-//! concurrently.
-//! The main thread periodically queries the database for all currently known monitor ids, and
-//! submits new jobs into the queue for each monitor not currently queued. In order to prevent
-//! duplicate queueing, the code also keeps track of the list of already-queued monitor ids inside
-//! a hashset that is shared with the worker threads. When a worker thread is finished with a given
-//! monitor id, it removes it from the hashset, which in turns allows the main thread to queue it
-//! again once the polling interval is exceeded. Since the worker thread processes blocks in
-//! chunks, it is possible that not all available blocks gets processed at once. When that happens,
-//! instead of removing the monitor id from the hashset, it would be placed back into the queue to
-//! be picked up by the next available worker thread.
+//! The sync code creates a main thread that keeps track of which monitors are behind the ledger,
+//! a scanner thread, and a pool of worker threads.
+//! The main thread periodically queries the database for all currently known monitors, and hands
+//! the ones that are behind to a `SyncScheduler`, along with how far behind they are and their
+//! priority. The scanner asks the scheduler for a batch of monitors to process, so that monitors
+//! share the scanning capacity fairly instead of being processed in the order they were added, and
+//! syncs a pre-defined amount of blocks for each of them. Scanning is pipelined per block: the
+//! scanner reads the next block of every monitor in the batch once, the workers match its outputs
+//! against each monitor in parallel, and the scanner stores what all of the monitors found in a
+//! single database transaction. Since blocks are processed in chunks, it is possible that not all
+//! available blocks get processed at once. When that happens, the monitor stays with the
+//! scheduler, to be picked up again in a later batch.
+//! When the sync thread is stopped, it stores where each monitor's scan stopped along with its
+//! scheduling state, and the next start resumes from there instead of starting over.
 
 use crate::{
-    database::Database,
+    database::{BlockScanResult, Database},
     error::Error,
-    monitor_store::{MonitorData, MonitorId},
+    monitor_store::{MonitorData, MonitorId, MonitorPriority},
+    notifications::{Event, Notifier},
+    processed_block_store::{ProcessedTxOut, ProcessedTxOutDirection},
     subaddress_store::SubaddressSPKId,
+    sync_scheduler::SyncScheduler,
+    sync_snapshot_store::{MonitorSyncState, SyncSnapshot},
     utxo_store::UnspentTxOut,
+    worker_pool::{WorkerPool, SCAN_POOL_NAME},
 };
 use common::{
     logger::{log, Logger},
-    HashSet,
+    HashMap, HashSet,
 };
-use keys::RistrettoPublic;
+use futures::Future;
+use keys::{CompressedRistrettoPublic, RistrettoPublic};
 use ledger_db::{Ledger, LedgerDB};
+use mobilecoind_enclave_api::{Error as EnclaveError, ReceivedOutput, SigningEnclave};
 use std::{
     convert::TryFrom,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        Arc,
     },
     thread,
 };
 use transaction::{
     get_tx_out_shared_secret,
     onetime_keys::{compute_key_image, recover_onetime_private_key, subaddress_for_key},
-    tx::TxOut,
+    ring_signature::KeyImage,
+    BlockContents,
 };
 
-///  The maximal number of blocks a worker thread would process at once.
+///  The maximal number of blocks a monitor is processed for at once.
 const MAX_BLOCKS_PROCESSING_CHUNK_SIZE: usize = 5;
 
-/// Message type the our crossbeam channel used to communicate with the worker thread pull.
-enum SyncMsg {
-    SyncMonitor(MonitorId),
-    Stop,
-}
+/// The maximal number of monitors the scanner processes together.
+const MAX_MONITORS_PER_BATCH: usize = 64;
 
 /// Possible return values for the `sync_monitor` function.
 #[derive(Debug, Eq, PartialEq)]
@@ -77,45 +82,40 @@ impl SyncThread {
         ledger_db: LedgerDB,
         mobilecoind_db: Database,
         num_workers: Option<usize>,
+        notifier: Option<Notifier>,
+        signing_enclave: Option<Arc<dyn SigningEnclave>>,
         logger: Logger,
     ) -> Self {
-        // Queue for sending jobs to our worker threads.
-        let (sender, receiver) = crossbeam_channel::unbounded::<SyncMsg>();
-
-        // A hashset to keep track of which MonitorIds were already sent to the queue,
-        // preventing them from being sent again until they are processed.
-        let queued_monitor_ids = Arc::new(Mutex::new(HashSet::<MonitorId>::default()));
-
-        // Create worker threads.
-        let mut worker_join_handles = Vec::new();
-
-        for idx in 0..num_workers.unwrap_or_else(num_cpus::get) {
-            let thread_ledger_db = ledger_db.clone();
-            let thread_mobilecoind_db = mobilecoind_db.clone();
-            let thread_sender = sender.clone();
-            let thread_receiver = receiver.clone();
-            let thread_queued_monitor_ids = queued_monitor_ids.clone();
-            let thread_logger = logger.clone();
-            let join_handle = thread::Builder::new()
-                .name(format!("sync_worker_{}", idx))
-                .spawn(move || {
-                    sync_thread_entry_point(
-                        thread_ledger_db,
-                        thread_mobilecoind_db,
-                        thread_sender,
-                        thread_receiver,
-                        thread_queued_monitor_ids,
-                        thread_logger,
-                    );
-                })
-                .expect("failed starting sync worker thread");
-
-            worker_join_handles.push(join_handle);
-        }
+        // Decides which monitors the scanner processes next.
+        let scheduler = Arc::new(SyncScheduler::new());
+
+        // Matches block outputs against monitors.
+        let pool = WorkerPool::new(SCAN_POOL_NAME, num_workers);
+
+        // Create the scanner thread.
+        let thread_ledger_db = ledger_db.clone();
+        let thread_mobilecoind_db = mobilecoind_db.clone();
+        let thread_scheduler = scheduler.clone();
+        let thread_notifier = notifier.clone();
+        let thread_logger = logger.clone();
+        let scanner_join_handle = thread::Builder::new()
+            .name("sync_scanner".to_string())
+            .spawn(move || {
+                sync_thread_entry_point(
+                    thread_ledger_db,
+                    thread_mobilecoind_db,
+                    thread_scheduler,
+                    pool,
+                    thread_notifier,
+                    signing_enclave,
+                    thread_logger,
+                );
+            })
+            .expect("failed starting sync scanner thread");
 
         // Start the main sync thread.
         // This thread constantly monitors the list of monitor ids we are aware of,
-        // and adds new one into our cyclic queue.
+        // and hands the ones that are behind to the scheduler.
         let stop_requested = Arc::new(AtomicBool::new(false));
         let thread_stop_requested = stop_requested.clone();
 
@@ -125,79 +125,106 @@ impl SyncThread {
                 .spawn(move || {
                     log::debug!(logger, "Syncthread started.");
 
+                    // Monitors that were reported as lagging and have not caught up since.
+                    let mut lagging_monitors =
+                        resume_from_snapshot(&ledger_db, &mobilecoind_db, &scheduler, &logger);
+
+                    // Monitors that were reported as starting before the pruned blocks.
+                    let mut pruned_monitors = HashSet::default();
+
                     loop {
                         if thread_stop_requested.load(Ordering::SeqCst) {
                             log::debug!(logger, "SyncThread stop requested.");
                             break;
                         }
 
+                        // Monitors cannot be read until an encrypted database is unlocked.
+                        if mobilecoind_db.is_locked() {
+                            thread::sleep(std::time::Duration::from_secs(1));
+                            continue;
+                        }
+
                         // Get the current number of blocks in ledger.
                         let num_blocks = ledger_db
                             .num_blocks()
                             .expect("failed getting number of blocks");
 
-                        // A flag to track whether we sent a message to our work queue.
-                        // If we sent a message, that means new blocks have arrived and we can skip sleeping.
-                        // If no new blocks arrived, and we haven't had to sync any monitors, we can sleep for
-                        // a bit so that we do not use 100% cpu.
-                        let mut message_sent = false;
+                        // Monitors cannot scan blocks whose contents were pruned.
+                        let num_pruned_blocks = ledger_db
+                            .num_pruned_blocks()
+                            .expect("failed getting number of pruned blocks");
 
-                        // Go over our list of monitors and see which one needs to process these blocks.
-                        for (monitor_id, monitor_data) in mobilecoind_db
+                        let monitor_map = mobilecoind_db
                             .get_monitor_map()
-                            .expect("failed getting monitor map")
-                        {
-                            // If there are no new blocks for this monitor, don't do anything.
-                            if monitor_data.next_block >= num_blocks {
-                                continue;
-                            }
-
-                            let mut queued_monitor_ids =
-                                queued_monitor_ids.lock().expect("mutex poisoned");
-                            if !queued_monitor_ids.insert(monitor_id.clone()) {
-                                // Already queued, no need to add again to queue at this point.
-                                log::trace!(logger, "{}: skipping, already queued", monitor_id);
-                                continue;
-                            }
-
-                            // This monitor has blocks to process, put it in the queue.
+                            .expect("failed getting monitor map");
+                        report_pruned_monitors(
+                            &monitor_map,
+                            num_pruned_blocks,
+                            &mut pruned_monitors,
+                            &logger,
+                        );
+
+                        // Go over our list of monitors and see which ones need to process blocks.
+                        // Monitors that start before the pruned blocks are left alone until they
+                        // are rescanned from a later block, instead of failing over and over.
+                        let behind_monitors = monitor_map
+                            .into_iter()
+                            .filter(|(_, monitor_data)| {
+                                monitor_data.next_block >= num_pruned_blocks
+                                    && monitor_data.next_block < num_blocks
+                            })
+                            .map(|(monitor_id, monitor_data)| {
+                                (
+                                    monitor_id,
+                                    num_blocks - monitor_data.next_block,
+                                    monitor_data.get_priority(),
+                                )
+                            })
+                            .collect::<Vec<_>>();
+
+                        // Whether some monitor started waiting for a worker. If one did, new blocks
+                        // have arrived and we can skip sleeping. If no new blocks arrived, we can
+                        // sleep for a bit so that we do not use 100% cpu.
+                        let monitors_added = scheduler.update(&behind_monitors);
+                        if monitors_added {
                             log::info!(
                                 logger,
-                                "sync thread noticed monitor {} needs syncing",
-                                monitor_id,
+                                "sync thread noticed {} monitors need syncing",
+                                behind_monitors.len(),
+                            );
+                        }
+
+                        if let Some(notifier) = &notifier {
+                            notify_lagging_monitors(
+                                notifier,
+                                &behind_monitors,
+                                &mut lagging_monitors,
                             );
-                            sender
-                                .send(SyncMsg::SyncMonitor(monitor_id))
-                                .expect("failed sending to queue");
-                            message_sent = true;
                         }
 
                         // If we saw no activity, sleep for a bit.
-                        if !message_sent {
+                        if !monitors_added {
                             thread::sleep(std::time::Duration::from_secs(1));
                         }
                     }
 
                     log::trace!(
                         logger,
-                        "SyncThread attempting to stop all worker threads..."
+                        "SyncThread attempting to stop the scanner thread..."
                     );
-                    for _ in 0..worker_join_handles.len() {
-                        sender
-                            .send(SyncMsg::Stop)
-                            .expect("failed sending stop message");
-                    }
-
-                    let num_workers = worker_join_handles.len();
-                    for (i, join_handle) in worker_join_handles.into_iter().enumerate() {
-                        log::trace!(logger, "Joining worker {}/{}", i + 1, num_workers);
-                        join_handle.join().expect("Failed joining worker thread");
-                        log::debug!(
-                            logger,
-                            "SyncThread worker {}/{} stopped",
-                            i + 1,
-                            num_workers
-                        );
+                    scheduler.stop();
+
+                    // The worker threads exit once the scanner drops the pool.
+                    scanner_join_handle
+                        .join()
+                        .expect("Failed joining scanner thread");
+                    log::debug!(logger, "SyncThread scanner stopped");
+
+                    // The scanner is done, so the scan state no longer changes.
+                    if let Err(err) =
+                        save_snapshot(&ledger_db, &mobilecoind_db, &scheduler, &lagging_monitors)
+                    {
+                        log::error!(logger, "Failed saving sync snapshot: {:?}", err);
                     }
 
                     log::debug!(logger, "SyncThread stopped.");
@@ -224,131 +251,549 @@ impl Drop for SyncThread {
         self.stop();
     }
 }
-/// The entry point of a sync worker thread that processes queue messages.
+/// The entry point of the sync scanner thread that processes the monitors handed out by the
+/// scheduler.
 fn sync_thread_entry_point(
     ledger_db: LedgerDB,
     mobilecoind_db: Database,
-    sender: crossbeam_channel::Sender<SyncMsg>,
-    receiver: crossbeam_channel::Receiver<SyncMsg>,
-    queued_monitor_ids: Arc<Mutex<HashSet<MonitorId>>>,
+    scheduler: Arc<SyncScheduler>,
+    pool: WorkerPool,
+    notifier: Option<Notifier>,
+    signing_enclave: Option<Arc<dyn SigningEnclave>>,
     logger: Logger,
 ) {
-    for msg in receiver.iter() {
-        match msg {
-            SyncMsg::SyncMonitor(monitor_id) => {
-                match sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger) {
-                    // Success - No more blocks are currently available.
-                    Ok(SyncMonitorOk::NoMoreBlocks) => {
-                        // Remove the monitor id from the list of queued ones so that the main thread could
-                        // queue it again if necessary.
-                        log::trace!(logger, "{}: sync_monitor returned NoMoreBlocks", monitor_id);
-
-                        let mut queued_monitor_ids =
-                            queued_monitor_ids.lock().expect("mutex poisoned");
-                        queued_monitor_ids.remove(&monitor_id);
-                    }
+    while let Some(monitor_ids) = scheduler.next_batch(MAX_MONITORS_PER_BATCH) {
+        let results = sync_monitors(
+            &ledger_db,
+            &mobilecoind_db,
+            &pool,
+            &monitor_ids,
+            notifier.as_ref(),
+            signing_enclave.as_ref(),
+            &logger,
+        );
 
-                    // Success - more blocks might be available.
-                    Ok(SyncMonitorOk::MoreBlocksPotentiallyAvailable) => {
-                        // Put the monitor id back in the queue for further processing.
-                        log::trace!(
-                            logger,
-                            "{}: sync_monitor returned MoreBlocksPotentiallyAvailable",
-                            monitor_id,
-                        );
+        for (monitor_id, result) in monitor_ids.iter().zip(results) {
+            let more_blocks = match result {
+                // Success - No more blocks are currently available.
+                Ok(SyncMonitorOk::NoMoreBlocks) => {
+                    // Release the monitor so that the main thread could hand it out again if
+                    // necessary.
+                    log::trace!(
+                        logger,
+                        "{}: sync_monitors returned NoMoreBlocks",
+                        monitor_id
+                    );
+                    false
+                }
 
-                        sender
-                            .send(SyncMsg::SyncMonitor(monitor_id))
-                            .expect("failed sending to channel");
-                    }
+                // Success - more blocks might be available.
+                Ok(SyncMonitorOk::MoreBlocksPotentiallyAvailable) => {
+                    // Keep the monitor with the scheduler for further processing.
+                    log::trace!(
+                        logger,
+                        "{}: sync_monitors returned MoreBlocksPotentiallyAvailable",
+                        monitor_id,
+                    );
+                    true
+                }
 
-                    // Errors that are acceptable - nothing to do.
-                    Err(Error::MonitorIdNotFound) => {}
+                // Errors that are acceptable - nothing to do.
+                Err(Error::MonitorIdNotFound) => false,
 
-                    // Other errors - log.
-                    Err(err) => {
-                        log::error!(logger, "error syncing monitor {}: {:?}", monitor_id, err);
-                    }
-                };
+                // The block was pruned after the main thread handed out the monitor. The main
+                // thread reports it and stops handing it out.
+                Err(Error::LedgerDB(ledger_db::Error::BlockPruned(block_index))) => {
+                    log::error!(
+                        logger,
+                        "{}: cannot scan block {}, its contents were pruned",
+                        monitor_id,
+                        block_index
+                    );
+                    false
+                }
+
+                // Other errors - log, and leave retrying to the main thread.
+                Err(err) => {
+                    log::error!(logger, "error syncing monitor {}: {:?}", monitor_id, err);
+                    false
+                }
+            };
+
+            scheduler.finished(monitor_id, more_blocks);
+        }
+    }
+}
+
+/// Hand the monitors that were behind when the sync thread last stopped to the scheduler, along
+/// with the tokens they had left. Returns the monitors that were reported as lagging.
+fn resume_from_snapshot(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    scheduler: &SyncScheduler,
+    logger: &Logger,
+) -> HashSet<MonitorId> {
+    let snapshot = match mobilecoind_db.take_sync_snapshot() {
+        Ok(Some(snapshot)) => snapshot,
+        Ok(None) => return HashSet::default(),
+        Err(err) => {
+            log::warn!(logger, "Failed reading sync snapshot: {:?}", err);
+            return HashSet::default();
+        }
+    };
+
+    // Monitors cannot be scanned until the database is unlocked, and are picked up from the
+    // database once it is.
+    if mobilecoind_db.is_locked() {
+        log::info!(logger, "Ignoring sync snapshot, the database is locked");
+        return HashSet::default();
+    }
+
+    // Blocks were removed from the ledger since the snapshot was taken.
+    let num_blocks = ledger_db
+        .num_blocks()
+        .expect("failed getting number of blocks");
+    if snapshot.num_blocks > num_blocks {
+        log::warn!(
+            logger,
+            "Ignoring sync snapshot taken at {} blocks, the ledger has {} blocks",
+            snapshot.num_blocks,
+            num_blocks,
+        );
+        return HashSet::default();
+    }
+
+    let behind_monitors = snapshot
+        .monitors
+        .iter()
+        .filter(|state| state.next_block < num_blocks)
+        .map(|state| {
+            (
+                state.monitor_id,
+                num_blocks - state.next_block,
+                state.get_priority(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let tokens = snapshot
+        .monitors
+        .iter()
+        .filter_map(|state| state.tokens.map(|tokens| (state.monitor_id, tokens)))
+        .collect::<HashMap<_, _>>();
+    scheduler.resume(&behind_monitors, &tokens);
+
+    log::info!(
+        logger,
+        "Resumed syncing from snapshot, {} of {} monitors are behind",
+        behind_monitors.len(),
+        snapshot.monitors.len(),
+    );
+
+    snapshot
+        .monitors
+        .iter()
+        .filter(|state| state.lagging)
+        .map(|state| state.monitor_id)
+        .collect()
+}
+
+/// Store where the scan of each monitor stopped, so that the next start resumes from there.
+fn save_snapshot(
+    ledger_db: &LedgerDB,
+    mobilecoind_db: &Database,
+    scheduler: &SyncScheduler,
+    lagging_monitors: &HashSet<MonitorId>,
+) -> Result<(), Error> {
+    if mobilecoind_db.is_locked() {
+        return Ok(());
+    }
+
+    // The ledger is read after the monitors, so that no monitor scanned past it.
+    let monitor_map = mobilecoind_db.get_monitor_map()?;
+    let num_blocks = ledger_db.num_blocks()?;
+
+    let tokens = scheduler.tokens();
+    let monitors = monitor_map
+        .into_iter()
+        .map(|(monitor_id, monitor_data)| MonitorSyncState {
+            monitor_id,
+            next_block: monitor_data.next_block,
+            priority: monitor_data.get_priority() as i32,
+            tokens: tokens.get(&monitor_id).cloned(),
+            lagging: lagging_monitors.contains(&monitor_id),
+        })
+        .collect();
+
+    mobilecoind_db.save_sync_snapshot(&SyncSnapshot {
+        num_blocks,
+        monitors,
+    })
+}
+
+/// Notify about monitors that fell too far behind the ledger. A monitor is reported again only
+/// after it caught up.
+fn notify_lagging_monitors(
+    notifier: &Notifier,
+    behind_monitors: &[(MonitorId, u64, MonitorPriority)],
+    lagging_monitors: &mut HashSet<MonitorId>,
+) {
+    let sync_lag_threshold = match notifier.sync_lag_threshold() {
+        Some(sync_lag_threshold) => sync_lag_threshold,
+        None => return,
+    };
+
+    let mut still_lagging = HashSet::default();
+    for (monitor_id, blocks_behind, _priority) in behind_monitors {
+        if *blocks_behind >= sync_lag_threshold {
+            if !lagging_monitors.contains(monitor_id) {
+                notifier.notify(Event::sync_lagging(monitor_id, *blocks_behind));
             }
+            still_lagging.insert(*monitor_id);
+        }
+    }
+    *lagging_monitors = still_lagging;
+}
 
-            SyncMsg::Stop => {
-                break;
+/// Report the monitors that cannot be scanned because their next block was pruned. A monitor is
+/// reported again only after it was rescanned from a later block.
+fn report_pruned_monitors(
+    monitor_map: &HashMap<MonitorId, MonitorData>,
+    num_pruned_blocks: u64,
+    pruned_monitors: &mut HashSet<MonitorId>,
+    logger: &Logger,
+) {
+    let mut still_pruned = HashSet::default();
+    for (monitor_id, monitor_data) in monitor_map {
+        if monitor_data.next_block < num_pruned_blocks {
+            if !pruned_monitors.contains(monitor_id) {
+                log::error!(
+                    logger,
+                    "{}: cannot scan block {}, the contents of blocks before {} were pruned. \
+                     Rescan the monitor from a later block.",
+                    monitor_id,
+                    monitor_data.next_block,
+                    num_pruned_blocks,
+                );
             }
+            still_pruned.insert(*monitor_id);
         }
     }
+    *pruned_monitors = still_pruned;
 }
 
-/// Sync a single monitor.
-fn sync_monitor(
+/// A block read from the ledger, with the keys of its outputs decompressed once for all of the
+/// monitors that scan it.
+struct ScannedBlock {
+    index: u64,
+    contents: BlockContents,
+    target_keys: Vec<RistrettoPublic>,
+    public_keys: Vec<RistrettoPublic>,
+}
+
+impl ScannedBlock {
+    /// Reads a block from the ledger. Returns None if the ledger does not have it yet.
+    fn read(ledger_db: &LedgerDB, index: u64) -> Result<Option<Self>, Error> {
+        let contents = match ledger_db.get_block_contents(index) {
+            Ok(contents) => contents,
+            Err(ledger_db::Error::NotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let target_keys = contents
+            .outputs
+            .iter()
+            .map(|tx_out| RistrettoPublic::try_from(&tx_out.target_key))
+            .collect::<Result<Vec<_>, _>>()?;
+        let public_keys = contents
+            .outputs
+            .iter()
+            .map(|tx_out| RistrettoPublic::try_from(&tx_out.public_key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(Self {
+            index,
+            target_keys,
+            public_keys,
+            contents,
+        }))
+    }
+
+    fn tx_out_public_keys(&self) -> Vec<CompressedRistrettoPublic> {
+        self.contents
+            .outputs
+            .iter()
+            .map(|tx_out| tx_out.public_key)
+            .collect()
+    }
+}
+
+/// What a monitor found in a block, before it is stored.
+struct BlockScan {
+    block: Arc<ScannedBlock>,
+    discovered_utxos: Vec<UnspentTxOut>,
+    processed_tx_outs: Vec<ProcessedTxOut>,
+}
+
+/// Sync a batch of monitors, notifying about received funds and finalized outgoing transactions.
+/// Returns the result of each monitor, in the order of `monitor_ids`.
+///
+/// Each step scans the next block of every monitor that has not caught up yet. Blocks are read
+/// once, however many monitors scan them, the outputs are matched against each monitor on the
+/// worker pool, and what the monitors found is stored in a single database transaction.
+fn sync_monitors(
     ledger_db: &LedgerDB,
     mobilecoind_db: &Database,
-    monitor_id: &MonitorId,
+    pool: &WorkerPool,
+    monitor_ids: &[MonitorId],
+    notifier: Option<&Notifier>,
+    signing_enclave: Option<&Arc<dyn SigningEnclave>>,
     logger: &Logger,
-) -> Result<SyncMonitorOk, Error> {
+) -> Vec<Result<SyncMonitorOk, Error>> {
+    // The result of each monitor, once it is known.
+    let mut results: Vec<Option<Result<SyncMonitorOk, Error>>> =
+        monitor_ids.iter().map(|_| None).collect();
+
     for _ in 0..MAX_BLOCKS_PROCESSING_CHUNK_SIZE {
-        // Get the monitor data. If it is no longer available, the monitor has been removed and we
-        // can simply return.
-        let monitor_data = mobilecoind_db.get_monitor_data(monitor_id)?;
-        let block_contents = match ledger_db.get_block_contents(monitor_data.next_block) {
-            Ok(block_contents) => block_contents,
-            Err(ledger_db::Error::NotFound) => {
-                return Ok(SyncMonitorOk::NoMoreBlocks);
+        // Hand the next block of each monitor to the worker pool.
+        let mut blocks: HashMap<u64, Arc<ScannedBlock>> = HashMap::default();
+        let mut pending_scans = Vec::new();
+        for (position, monitor_id) in monitor_ids.iter().enumerate() {
+            if results[position].is_some() {
+                continue;
+            }
+
+            // Get the monitor data. If it is no longer available, the monitor has been removed.
+            let monitor_data = match mobilecoind_db.get_monitor_data(monitor_id) {
+                Ok(monitor_data) => monitor_data,
+                Err(err) => {
+                    results[position] = Some(Err(err));
+                    continue;
+                }
+            };
+
+            let block = match blocks.get(&monitor_data.next_block) {
+                Some(block) => block.clone(),
+                None => match ScannedBlock::read(ledger_db, monitor_data.next_block) {
+                    Ok(Some(block)) => {
+                        let block = Arc::new(block);
+                        blocks.insert(block.index, block.clone());
+                        block
+                    }
+                    Ok(None) => {
+                        results[position] = Some(Ok(SyncMonitorOk::NoMoreBlocks));
+                        continue;
+                    }
+                    Err(err) => {
+                        results[position] = Some(Err(err));
+                        continue;
+                    }
+                },
+            };
+
+            log::trace!(
+                logger,
+                "processing {} outputs and {} key images from block {} for monitor_id {}",
+                block.contents.outputs.len(),
+                block.contents.key_images.len(),
+                block.index,
+                monitor_id,
+            );
+
+            let task_mobilecoind_db = mobilecoind_db.clone();
+            let task_monitor_id = *monitor_id;
+            let task_signing_enclave = signing_enclave.cloned();
+            let task_logger = logger.clone();
+            let pending_scan = pool.spawn(move || {
+                scan_block(
+                    &task_mobilecoind_db,
+                    &task_monitor_id,
+                    &monitor_data,
+                    block,
+                    task_signing_enclave.as_deref(),
+                    &task_logger,
+                )
+            });
+            pending_scans.push((position, pending_scan));
+        }
+
+        if pending_scans.is_empty() {
+            break;
+        }
+
+        // Wait for the matches, in the order of the monitors.
+        let mut scans = Vec::new();
+        for (position, pending_scan) in pending_scans {
+            // The task only goes away without a result if it panicked.
+            match pending_scan.wait().unwrap_or(Err(Error::ChannelSend)) {
+                Ok(scan) => scans.push((position, scan)),
+                Err(err) => results[position] = Some(Err(err)),
             }
+        }
+
+        // Update database.
+        let tx_out_public_keys: HashMap<u64, Vec<CompressedRistrettoPublic>> = blocks
+            .iter()
+            .map(|(index, block)| (*index, block.tx_out_public_keys()))
+            .collect();
+        let block_scan_results: Vec<BlockScanResult> = scans
+            .iter()
+            .map(|(position, scan)| BlockScanResult {
+                monitor_id: monitor_ids[*position],
+                block_num: scan.block.index,
+                discovered_utxos: &scan.discovered_utxos,
+                spent_key_images: &scan.block.contents.key_images,
+                tx_out_public_keys: &tx_out_public_keys[&scan.block.index],
+                processed_tx_outs: &scan.processed_tx_outs,
+            })
+            .collect();
+        let outcomes = match mobilecoind_db.blocks_processed(&block_scan_results) {
+            Ok(outcomes) => outcomes,
+            // Store the blocks one by one, so that a monitor that fails does not hold back the
+            // others.
             Err(err) => {
-                return Err(err.into());
+                log::warn!(
+                    logger,
+                    "Failed storing {} blocks together, storing them one by one: {:?}",
+                    block_scan_results.len(),
+                    err,
+                );
+                block_scan_results
+                    .iter()
+                    .map(|result| {
+                        mobilecoind_db
+                            .blocks_processed(std::slice::from_ref(result))
+                            .and_then(|mut outcomes| outcomes.pop().expect("one result per block"))
+                    })
+                    .collect()
             }
         };
 
-        log::trace!(
-            logger,
-            "processing {} outputs and {} key images from block {} for monitor_id {}",
-            block_contents.outputs.len(),
-            block_contents.key_images.len(),
-            monitor_data.next_block,
-            monitor_id,
-        );
+        for ((position, scan), outcome) in scans.iter().zip(outcomes) {
+            let finalized_outgoing_txs = match outcome {
+                Ok(finalized_outgoing_txs) => finalized_outgoing_txs,
+                Err(err) => {
+                    results[*position] = Some(Err(err));
+                    continue;
+                }
+            };
+
+            // Notify only once the block was committed, so that receivers can query the results.
+            if let Some(notifier) = notifier {
+                for utxo in &scan.discovered_utxos {
+                    notifier.notify(Event::payment_received(
+                        &monitor_ids[*position],
+                        scan.block.index,
+                        utxo,
+                    ));
+                }
+                for outgoing_tx in &finalized_outgoing_txs {
+                    notifier.notify(Event::outgoing_tx_finalized(outgoing_tx));
+                    if let Some(event) = Event::payment_finalized(outgoing_tx) {
+                        notifier.notify(event);
+                    }
+                }
+            }
+        }
+    }
 
-        // Match tx outs into UTXOs.
-        let utxos = match_redacted_txs_into_utxos(
-            &mobilecoind_db,
-            &block_contents.outputs,
-            monitor_id,
-            &monitor_data,
-            logger,
-        )?;
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or(Ok(SyncMonitorOk::MoreBlocksPotentiallyAvailable)))
+        .collect()
+}
 
-        // Update database.
-        mobilecoind_db.block_processed(
-            monitor_id,
-            monitor_data.next_block,
-            &utxos,
-            &block_contents.key_images,
-        )?;
+/// Match the outputs of a block against a monitor, and find which of its UTXOs the block spends.
+/// Runs on the worker pool.
+fn scan_block(
+    mobilecoind_db: &Database,
+    monitor_id: &MonitorId,
+    monitor_data: &MonitorData,
+    block: Arc<ScannedBlock>,
+    signing_enclave: Option<&dyn SigningEnclave>,
+    logger: &Logger,
+) -> Result<BlockScan, Error> {
+    // Match tx outs into UTXOs.
+    let mut utxos = match_redacted_txs_into_utxos(
+        mobilecoind_db,
+        &block,
+        monitor_id,
+        monitor_data,
+        signing_enclave,
+        logger,
+    )?;
+
+    // With gap-limit scanning, funds received near the end of the watched range extend it.
+    // The block is then matched again, since it may also pay the subaddresses just added.
+    while let Some(used_subaddress) = utxos.iter().map(|utxo| utxo.subaddress_index).max() {
+        match mobilecoind_db.extend_monitor_subaddresses(monitor_id, used_subaddress) {
+            Ok(0) => break,
+            Ok(num_added) => {
+                log::info!(
+                    logger,
+                    "Watching {} more subaddresses past subaddress {} for monitor_id {}",
+                    num_added,
+                    used_subaddress,
+                    monitor_id,
+                );
+                utxos = match_redacted_txs_into_utxos(
+                    mobilecoind_db,
+                    &block,
+                    monitor_id,
+                    monitor_data,
+                    signing_enclave,
+                    logger,
+                )?;
+            }
+            // The subaddresses past our range are watched by another monitor.
+            Err(Error::SubaddressSPKIdExists) => {
+                log::warn!(
+                    logger,
+                    "Subaddresses past {} of monitor_id {} overlap another monitor",
+                    used_subaddress,
+                    monitor_id,
+                );
+                break;
+            }
+            Err(err) => return Err(err),
+        }
     }
 
-    Ok(SyncMonitorOk::MoreBlocksPotentiallyAvailable)
+    // Find which of our UTXOs got spent in this block.
+    let spent_utxos =
+        mobilecoind_db.get_utxos_by_key_images(monitor_id, &block.contents.key_images)?;
+
+    // Classify everything we processed.
+    let processed_tx_outs = classify_processed_tx_outs(block.index, &utxos, &spent_utxos);
+
+    Ok(BlockScan {
+        block,
+        discovered_utxos: utxos,
+        processed_tx_outs,
+    })
 }
 
-/// Helper function for matching a list of TxOuts to a given monitor.
+/// Helper function for matching the TxOuts of a block to a given monitor.
 fn match_redacted_txs_into_utxos(
     mobilecoind_db: &Database,
-    outputs: &[TxOut],
+    block: &ScannedBlock,
     monitor_id: &MonitorId,
     monitor_data: &MonitorData,
+    signing_enclave: Option<&dyn SigningEnclave>,
     logger: &Logger,
 ) -> Result<Vec<UnspentTxOut>, Error> {
-    let account_key = &monitor_data.account_key;
-    let view_key = account_key.view_key();
+    let view_private_key = monitor_data.view_private_key();
     let mut results = Vec::new();
 
-    for tx_out in outputs {
+    for ((tx_out, &tx_out_target_key), &tx_public_key) in block
+        .contents
+        .outputs
+        .iter()
+        .zip(&block.target_keys)
+        .zip(&block.public_keys)
+    {
         // Calculate the subaddress spend public key for tx_out.
-        let tx_out_target_key = RistrettoPublic::try_from(&tx_out.target_key)?;
-        let tx_public_key = RistrettoPublic::try_from(&tx_out.public_key)?;
-
         let subaddress_spk = SubaddressSPKId::from(&subaddress_for_key(
-            &view_key.view_private_key,
+            view_private_key,
             &tx_out_target_key,
             &tx_public_key,
         ));
@@ -374,21 +819,39 @@ fn match_redacted_txs_into_utxos(
         // Sanity - we should only get a match for our own monitor id.
         assert_eq!(monitor_id, &subaddress_id.monitor_id);
 
-        let shared_secret =
-            get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+        let shared_secret = get_tx_out_shared_secret(view_private_key, &tx_public_key);
 
         let (value, _blinding) = tx_out
             .amount
             .get_value(&shared_secret)
             .expect("Malformed amount"); // TODO
 
-        let onetime_private_key = recover_onetime_private_key(
-            &tx_public_key,
-            account_key.view_private_key(),
-            &account_key.subaddress_spend_key(subaddress_id.index),
-        );
-
-        let key_image = compute_key_image(&onetime_private_key);
+        let key_image = match monitor_data.account_key.as_ref() {
+            Some(account_key) => {
+                let onetime_private_key = recover_onetime_private_key(
+                    &tx_public_key,
+                    view_private_key,
+                    &account_key.subaddress_spend_key(subaddress_id.index),
+                );
+                compute_key_image(&onetime_private_key)
+            }
+            // The spend private key of a sealed monitor never leaves the signing enclave.
+            None if monitor_data.is_sealed() => {
+                let signing_enclave = signing_enclave.ok_or(Error::NoSigningEnclave)?;
+                let output = ReceivedOutput {
+                    tx_public_key,
+                    subaddress_index: subaddress_id.index,
+                };
+                signing_enclave
+                    .key_images(&monitor_data.sealed_account_key, vec![output])?
+                    .pop()
+                    .ok_or(Error::Enclave(EnclaveError::Serialization))?
+            }
+            // Without the spend private key the key image is unknown. Store the output under its
+            // public key instead, which is unique and never shows up as a spent key image, so
+            // the output stays in the balance of a watch-only monitor.
+            None => KeyImage::from(*tx_out.public_key.as_bytes()),
+        };
 
         results.push(UnspentTxOut {
             tx_out: tx_out.clone(),
@@ -397,12 +860,54 @@ fn match_redacted_txs_into_utxos(
             value,
             attempted_spend_height: 0,
             attempted_spend_tombstone: 0,
+            memo: String::new(),
         });
     }
 
     Ok(results)
 }
 
+/// Helper function for classifying the TxOuts a monitor processed in a given block.
+///
+/// Spent UTXOs are classified as spent. A discovered UTXO is classified as change when the block
+/// also spends one of our UTXOs from the same subaddress, since that is where mobilecoind returns
+/// change to. Any other discovered UTXO is classified as received.
+fn classify_processed_tx_outs(
+    block_index: u64,
+    discovered_utxos: &[UnspentTxOut],
+    spent_utxos: &[UnspentTxOut],
+) -> Vec<ProcessedTxOut> {
+    let spending_subaddresses = spent_utxos
+        .iter()
+        .map(|utxo| utxo.subaddress_index)
+        .collect::<HashSet<u64>>();
+
+    let processed_tx_out =
+        |utxo: &UnspentTxOut, direction: ProcessedTxOutDirection| ProcessedTxOut {
+            block_index,
+            subaddress_index: utxo.subaddress_index,
+            public_key: utxo.tx_out.public_key,
+            key_image: utxo.key_image,
+            value: utxo.value,
+            direction: direction as i32,
+        };
+
+    let received = discovered_utxos.iter().map(|utxo| {
+        let direction = if spending_subaddresses.contains(&utxo.subaddress_index) {
+            ProcessedTxOutDirection::Change
+        } else {
+            ProcessedTxOutDirection::Received
+        };
+        processed_tx_out(utxo, direction)
+    });
+
+    let spent = spent_utxos
+        .iter()
+        .map(|utxo| processed_tx_out(utxo, ProcessedTxOutDirection::Spent));
+
+    received.chain(spent).collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -411,13 +916,38 @@ mod test {
         test_utils::{self, add_block_to_ledger_db, get_test_databases},
     };
     use common::logger::{test_with_logger, Logger};
-    use rand::{rngs::StdRng, SeedableRng};
+    use keys::{FromRandom, RistrettoPrivate};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
     use std::iter::FromIterator;
     use transaction::{
         account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
+        ring_signature::KeyImage,
         tx::TxOut,
     };
 
+    /// Sync a single monitor, matching its outputs on a pool of its own.
+    fn sync_monitor(
+        ledger_db: &LedgerDB,
+        mobilecoind_db: &Database,
+        monitor_id: &MonitorId,
+        notifier: Option<&Notifier>,
+        signing_enclave: Option<&Arc<dyn SigningEnclave>>,
+        logger: &Logger,
+    ) -> Result<SyncMonitorOk, Error> {
+        let pool = WorkerPool::new(SCAN_POOL_NAME, Some(1));
+        sync_monitors(
+            ledger_db,
+            mobilecoind_db,
+            &pool,
+            &[*monitor_id],
+            notifier,
+            signing_enclave,
+            logger,
+        )
+        .pop()
+        .unwrap()
+    }
+
     #[test_with_logger]
     fn test_sync_monitor(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([98u8; 32]);
@@ -475,7 +1005,15 @@ mod test {
         assert_eq!(monitor_data.next_block, 0);
 
         // Process the first MAX_BLOCKS_PROCESSING_CHUNK_SIZE blocks.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::MoreBlocksPotentiallyAvailable);
 
         // We should now discover some outputs. Each block has 1 output per recipient, and we
@@ -500,7 +1038,15 @@ mod test {
         }
 
         // Process the second MAX_BLOCKS_PROCESSING_CHUNK_SIZE blocks.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::MoreBlocksPotentiallyAvailable);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -523,7 +1069,15 @@ mod test {
         }
 
         // Process the last remaining block.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -543,7 +1097,15 @@ mod test {
         }
 
         // Calling sync_monitor again should not change the results.
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
@@ -575,7 +1137,15 @@ mod test {
             &mut rng,
         );
 
-        let result = sync_monitor(&ledger_db, &mobilecoind_db, &monitor_id, &logger).unwrap();
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
         assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
 
         let utxos = mobilecoind_db
@@ -584,5 +1154,303 @@ mod test {
         assert_eq!(utxos.len(), num_blocks - 1);
 
         assert!(!utxos.contains(&first_utxo));
+
+        // Every block we received funds in should be recorded as such.
+        let processed = mobilecoind_db.get_processed_block(&monitor_id, 0).unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(
+            processed[0].get_direction(),
+            ProcessedTxOutDirection::Received
+        );
+        assert_eq!(processed[0].value, test_utils::PER_RECIPIENT_AMOUNT);
+
+        // The block that spent our first utxo should record it as spent.
+        let processed = mobilecoind_db
+            .get_processed_block(&monitor_id, num_blocks as u64)
+            .unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].get_direction(), ProcessedTxOutDirection::Spent);
+        assert_eq!(processed[0].key_image, first_utxo.key_image);
+        assert_eq!(processed[0].public_key, first_utxo.tx_out.public_key);
+
+        let history = mobilecoind_db
+            .get_processed_tx_out_history(&monitor_id, 0, u64::max_value())
+            .unwrap();
+        assert_eq!(history.len(), num_blocks + 1);
+    }
+
+    #[test_with_logger]
+    // Funds received near the end of the watched range should extend it by the gap limit.
+    fn test_sync_monitor_gap_limit(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([97u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let (mut ledger_db, mobilecoind_db) =
+            get_test_databases(3, &vec![], 1, logger.clone(), &mut rng);
+
+        // Request a single subaddress with a gap limit of 3, so subaddresses 0-2 are watched.
+        let mut data = MonitorData::new(
+            account_key.clone(),
+            0, // first subaddress
+            1, // number of subaddresses
+            0, // first block
+        )
+        .unwrap();
+        data.set_gap_limit(3);
+        assert_eq!(data.subaddress_indexes(), 0..3);
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        // Subaddress 2 extends the range to 0-5, which reveals subaddress 5 in the same block.
+        // Subaddress 20 stays out of reach.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[
+                account_key.subaddress(2),
+                account_key.subaddress(5),
+                account_key.subaddress(20),
+            ],
+            &[],
+            &mut rng,
+        );
+
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+
+        let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(monitor_data.subaddress_indexes(), 0..9);
+        for (subaddress_index, num_utxos) in &[(2, 1), (5, 1), (20, 0)] {
+            let utxos = mobilecoind_db
+                .get_utxos_for_subaddress(&monitor_id, *subaddress_index)
+                .unwrap();
+            assert_eq!(utxos.len(), *num_utxos);
+        }
+
+        // Subaddresses added by the gap limit are matched like any other, and extend it further.
+        add_block_to_ledger_db(&mut ledger_db, &[account_key.subaddress(7)], &[], &mut rng);
+
+        let result = sync_monitor(
+            &ledger_db,
+            &mobilecoind_db,
+            &monitor_id,
+            None,
+            None,
+            &logger,
+        )
+        .unwrap();
+        assert_eq!(result, SyncMonitorOk::NoMoreBlocks);
+
+        let monitor_data = mobilecoind_db.get_monitor_data(&monitor_id).unwrap();
+        assert_eq!(monitor_data.subaddress_indexes(), 0..11);
+        let utxos = mobilecoind_db
+            .get_utxos_for_subaddress(&monitor_id, 7)
+            .unwrap();
+        assert_eq!(utxos.len(), 1);
+    }
+
+    #[test_with_logger]
+    // Monitors synced together should each get their own outputs, even when they are at different
+    // blocks, and a monitor that was removed should not hold back the others.
+    fn test_sync_monitors(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([95u8; 32]);
+        let account_keys: Vec<_> = (0..3).map(|_i| AccountKey::random(&mut rng)).collect();
+        let recipients: Vec<PublicAddress> = account_keys
+            .iter()
+            .map(AccountKey::default_subaddress)
+            .collect();
+
+        let num_blocks = MAX_BLOCKS_PROCESSING_CHUNK_SIZE + 2;
+        let (ledger_db, mobilecoind_db) =
+            get_test_databases(0, &recipients, num_blocks, logger.clone(), &mut rng);
+
+        // The first monitor starts at the origin block, the second one a block later.
+        let monitor_ids: Vec<MonitorId> = account_keys
+            .iter()
+            .enumerate()
+            .map(|(index, account_key)| {
+                let data = MonitorData::new(
+                    account_key.clone(),
+                    DEFAULT_SUBADDRESS_INDEX,
+                    1,
+                    index as u64,
+                )
+                .unwrap();
+                mobilecoind_db.add_monitor(&data).unwrap()
+            })
+            .collect();
+        mobilecoind_db.remove_monitor(&monitor_ids[2]).unwrap();
+
+        let pool = WorkerPool::new(SCAN_POOL_NAME, Some(2));
+        let results = sync_monitors(
+            &ledger_db,
+            &mobilecoind_db,
+            &pool,
+            &monitor_ids,
+            None,
+            None,
+            &logger,
+        );
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &SyncMonitorOk::MoreBlocksPotentiallyAvailable
+        );
+        assert_eq!(
+            results[1].as_ref().unwrap(),
+            &SyncMonitorOk::MoreBlocksPotentiallyAvailable
+        );
+        match results[2] {
+            Err(Error::MonitorIdNotFound) => {}
+            ref result => panic!("unexpected result {:?}", result),
+        }
+
+        for (index, monitor_id) in monitor_ids[..2].iter().enumerate() {
+            let monitor_data = mobilecoind_db.get_monitor_data(monitor_id).unwrap();
+            assert_eq!(
+                monitor_data.next_block,
+                (index + MAX_BLOCKS_PROCESSING_CHUNK_SIZE) as u64
+            );
+
+            // Each block has one output per recipient, in the order of the recipients.
+            let utxos = mobilecoind_db
+                .get_utxos_for_subaddress(monitor_id, DEFAULT_SUBADDRESS_INDEX)
+                .unwrap();
+            assert_eq!(utxos.len(), MAX_BLOCKS_PROCESSING_CHUNK_SIZE);
+            for block_index in index..index + MAX_BLOCKS_PROCESSING_CHUNK_SIZE {
+                let block_contents = ledger_db.get_block_contents(block_index as u64).unwrap();
+                assert!(utxos
+                    .iter()
+                    .any(|utxo| utxo.tx_out == block_contents.outputs[index]));
+            }
+        }
+
+        // The monitors catch up in the next batch.
+        let results = sync_monitors(
+            &ledger_db,
+            &mobilecoind_db,
+            &pool,
+            &monitor_ids[..2],
+            None,
+            None,
+            &logger,
+        );
+        for (result, monitor_id) in results.into_iter().zip(&monitor_ids) {
+            assert_eq!(result.unwrap(), SyncMonitorOk::NoMoreBlocks);
+            let monitor_data = mobilecoind_db.get_monitor_data(monitor_id).unwrap();
+            assert_eq!(monitor_data.next_block, num_blocks as u64);
+        }
+    }
+
+    #[test_with_logger]
+    // Stopping the sync thread should store where each monitor stopped, and starting it again
+    // should take that snapshot and resume from it.
+    fn test_sync_snapshot(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([96u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let (mut ledger_db, mobilecoind_db) = get_test_databases(
+            3,
+            &vec![account_key.default_subaddress()],
+            10,
+            logger.clone(),
+            &mut rng,
+        );
+        let data = MonitorData::new(account_key.clone(), DEFAULT_SUBADDRESS_INDEX, 1, 0).unwrap();
+        let monitor_id = mobilecoind_db.add_monitor(&data).unwrap();
+
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            Some(1),
+            None,
+            None,
+            logger.clone(),
+        );
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        sync_thread.stop();
+
+        let snapshot = mobilecoind_db.take_sync_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.num_blocks, 10);
+        assert_eq!(snapshot.monitors.len(), 1);
+        assert_eq!(snapshot.monitors[0].monitor_id, monitor_id);
+        assert_eq!(snapshot.monitors[0].next_block, 10);
+        assert_eq!(snapshot.monitors[0].tokens, None);
+        mobilecoind_db.save_sync_snapshot(&snapshot).unwrap();
+
+        // Blocks added while stopped are scanned after restarting.
+        add_block_to_ledger_db(
+            &mut ledger_db,
+            &[account_key.default_subaddress()],
+            &[],
+            &mut rng,
+        );
+        let mut sync_thread = SyncThread::start(
+            ledger_db.clone(),
+            mobilecoind_db.clone(),
+            Some(1),
+            None,
+            None,
+            logger.clone(),
+        );
+        test_utils::wait_for_monitors(&mobilecoind_db, &ledger_db, &logger);
+        assert_eq!(mobilecoind_db.take_sync_snapshot().unwrap(), None);
+        sync_thread.stop();
+
+        let snapshot = mobilecoind_db.take_sync_snapshot().unwrap().unwrap();
+        assert_eq!(snapshot.num_blocks, 11);
+        assert_eq!(snapshot.monitors[0].next_block, 11);
+    }
+
+    #[test]
+    // Outputs sent to a subaddress that spends in the same block should be classified as change.
+    fn test_classify_processed_tx_outs() {
+        let mut rng: StdRng = SeedableRng::from_seed([99u8; 32]);
+        let account_key = AccountKey::random(&mut rng);
+
+        let utxo = |subaddress_index: u64, value: u64, rng: &mut StdRng| {
+            let tx_out = TxOut::new(
+                value,
+                &account_key.subaddress(subaddress_index),
+                &RistrettoPrivate::from_random(rng),
+                Default::default(),
+                rng,
+            )
+            .unwrap();
+            UnspentTxOut {
+                tx_out,
+                subaddress_index,
+                key_image: KeyImage::from(rng.next_u64()),
+                value,
+                attempted_spend_height: 0,
+                attempted_spend_tombstone: 0,
+                memo: String::new(),
+            }
+        };
+
+        let spent = vec![utxo(0, 100, &mut rng)];
+        let discovered = vec![utxo(0, 40, &mut rng), utxo(1, 10, &mut rng)];
+
+        let processed = classify_processed_tx_outs(7, &discovered, &spent);
+        assert_eq!(processed.len(), 3);
+        assert!(processed.iter().all(|tx_out| tx_out.block_index == 7));
+
+        let directions: Vec<(u64, ProcessedTxOutDirection)> = processed
+            .iter()
+            .map(|tx_out| (tx_out.value, tx_out.get_direction()))
+            .collect();
+        assert_eq!(
+            directions,
+            vec![
+                (40, ProcessedTxOutDirection::Change),
+                (10, ProcessedTxOutDirection::Received),
+                (100, ProcessedTxOutDirection::Spent),
+            ]
+        );
     }
 }
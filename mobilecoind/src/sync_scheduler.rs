@@ -0,0 +1,401 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Fair scheduling of block scanning across monitors.
+//!
+//! Each monitor that is behind has a token bucket. Scanning one chunk of blocks for a monitor
+//! costs a token, and a monitor is only scanned while it has tokens left. Once every monitor
+//! waiting to be scanned ran out of tokens, all buckets are refilled at once, each with as many
+//! tokens as its monitor's share. The share grows with the priority of the monitor and,
+//! logarithmically, with how many blocks it is behind. A monitor far behind thus gets more of the
+//! scanning capacity than one close to the tip, but cannot take all of it, so that adding an
+//! account with a long history does not hold up deposit detection for the others.
+
+use crate::monitor_store::{MonitorId, MonitorPriority};
+use common::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex};
+
+/// How many rounds of tokens a bucket holds at most.
+const BUCKET_CAPACITY_ROUNDS: u64 = 2;
+
+/// The number of tokens a monitor gets per round.
+///
+/// # Arguments
+/// * `blocks_behind` - The number of blocks the monitor has yet to scan.
+/// * `priority` - The scanning priority of the monitor.
+pub fn scan_share(blocks_behind: u64, priority: MonitorPriority) -> u64 {
+    let priority_weight = match priority {
+        MonitorPriority::Low => 1,
+        MonitorPriority::Normal => 4,
+        MonitorPriority::High => 16,
+    };
+    // The number of bits in blocks_behind, at least one.
+    let behind_weight = u64::from(64 - blocks_behind.leading_zeros()).max(1);
+    priority_weight * behind_weight
+}
+
+/// Scheduling state of a monitor.
+struct MonitorEntry {
+    /// Tokens added to the bucket each round.
+    share: u64,
+
+    /// Tokens left in the bucket.
+    tokens: u64,
+
+    /// Whether the monitor has blocks to scan.
+    behind: bool,
+
+    /// Whether a worker is currently scanning the monitor.
+    scanning: bool,
+}
+
+impl MonitorEntry {
+    fn is_waiting(&self) -> bool {
+        self.behind && !self.scanning
+    }
+}
+
+#[derive(Default)]
+struct State {
+    monitors: HashMap<MonitorId, MonitorEntry>,
+    stopped: bool,
+}
+
+impl State {
+    /// Take a token from the waiting monitor with the largest fraction of its share left, refilling
+    /// the buckets first if no waiting monitor has tokens left.
+    fn take(&mut self) -> Option<MonitorId> {
+        if !self
+            .monitors
+            .values()
+            .any(|entry| entry.is_waiting() && entry.tokens > 0)
+        {
+            for entry in self.monitors.values_mut().filter(|entry| entry.behind) {
+                entry.tokens =
+                    (entry.tokens + entry.share).min(entry.share * BUCKET_CAPACITY_ROUNDS);
+            }
+        }
+
+        let (monitor_id, entry) = self
+            .monitors
+            .iter_mut()
+            .filter(|(_, entry)| entry.is_waiting() && entry.tokens > 0)
+            // Compare tokens / share without dividing, and break ties by monitor id so that the
+            // order is deterministic.
+            .max_by(|(id_a, a), (id_b, b)| {
+                (a.tokens * b.share)
+                    .cmp(&(b.tokens * a.share))
+                    .then_with(|| id_b.cmp(id_a))
+            })?;
+
+        entry.tokens -= 1;
+        entry.scanning = true;
+        Some(*monitor_id)
+    }
+}
+
+/// Hands out monitors to scan to the sync workers.
+#[derive(Default)]
+pub struct SyncScheduler {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+impl SyncScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the set of monitors that are behind. Monitors that are not in `monitors` are
+    /// dropped once they are no longer being scanned. Returns whether a monitor that wasn't
+    /// waiting to be scanned is now.
+    ///
+    /// # Arguments
+    /// * `monitors` - For each monitor that is behind, the number of blocks it is behind and its
+    ///   priority.
+    pub fn update(&self, monitors: &[(MonitorId, u64, MonitorPriority)]) -> bool {
+        let mut state = self.state.lock().expect("mutex poisoned");
+
+        let behind_ids = monitors
+            .iter()
+            .map(|(monitor_id, _, _)| *monitor_id)
+            .collect::<HashSet<_>>();
+        for (monitor_id, entry) in state.monitors.iter_mut() {
+            entry.behind &= behind_ids.contains(monitor_id);
+        }
+
+        let mut added = false;
+        for (monitor_id, blocks_behind, priority) in monitors {
+            let share = scan_share(*blocks_behind, *priority);
+            // Monitors that just fell behind start with a full share, so that they are scanned
+            // without waiting for the current round to end.
+            let entry = state
+                .monitors
+                .entry(*monitor_id)
+                .or_insert_with(|| MonitorEntry {
+                    share,
+                    tokens: share,
+                    behind: false,
+                    scanning: false,
+                });
+            added |= !entry.is_waiting() && !entry.scanning;
+            entry.share = share;
+            entry.tokens = entry.tokens.min(share * BUCKET_CAPACITY_ROUNDS);
+            entry.behind = true;
+        }
+
+        state
+            .monitors
+            .retain(|_, entry| entry.behind || entry.scanning);
+
+        if added {
+            self.condvar.notify_all();
+        }
+        added
+    }
+
+    /// Like `update`, but monitors that were scheduled before a restart keep the tokens they had
+    /// left, so that the scanning round carries over instead of starting anew.
+    ///
+    /// # Arguments
+    /// * `monitors` - For each monitor that is behind, the number of blocks it is behind and its
+    ///   priority.
+    /// * `tokens` - The tokens returned by `tokens` before the restart.
+    pub fn resume(
+        &self,
+        monitors: &[(MonitorId, u64, MonitorPriority)],
+        tokens: &HashMap<MonitorId, u64>,
+    ) -> bool {
+        let added = self.update(monitors);
+
+        let mut state = self.state.lock().expect("mutex poisoned");
+        for (monitor_id, entry) in state.monitors.iter_mut() {
+            if let Some(tokens) = tokens.get(monitor_id) {
+                entry.tokens = (*tokens).min(entry.share * BUCKET_CAPACITY_ROUNDS);
+            }
+        }
+        added
+    }
+
+    /// The tokens left in the bucket of each monitor that is behind.
+    pub fn tokens(&self) -> HashMap<MonitorId, u64> {
+        let state = self.state.lock().expect("mutex poisoned");
+        state
+            .monitors
+            .iter()
+            .filter(|(_, entry)| entry.behind)
+            .map(|(monitor_id, entry)| (*monitor_id, entry.tokens))
+            .collect()
+    }
+
+    /// Wait for a monitor to scan. Returns None once the scheduler is stopped.
+    pub fn next(&self) -> Option<MonitorId> {
+        self.next_batch(1)
+            .and_then(|mut monitor_ids| monitor_ids.pop())
+    }
+
+    /// Wait for monitors to scan, and take up to `max_monitors` of them, in the order `next` would
+    /// hand them out. Each monitor in the batch costs a token, and is released with `finished`.
+    /// Returns None once the scheduler is stopped.
+    pub fn next_batch(&self, max_monitors: usize) -> Option<Vec<MonitorId>> {
+        let mut state = self.state.lock().expect("mutex poisoned");
+        loop {
+            if state.stopped {
+                return None;
+            }
+            let mut monitor_ids = Vec::new();
+            while monitor_ids.len() < max_monitors {
+                match state.take() {
+                    Some(monitor_id) => monitor_ids.push(monitor_id),
+                    None => break,
+                }
+            }
+            if !monitor_ids.is_empty() {
+                return Some(monitor_ids);
+            }
+            state = self.condvar.wait(state).expect("mutex poisoned");
+        }
+    }
+
+    /// Called once a chunk of blocks was scanned for a monitor.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor that was scanned.
+    /// * `more_blocks` - Whether the monitor may have more blocks to scan right away.
+    pub fn finished(&self, monitor_id: &MonitorId, more_blocks: bool) {
+        let mut state = self.state.lock().expect("mutex poisoned");
+        if let Some(entry) = state.monitors.get_mut(monitor_id) {
+            entry.scanning = false;
+            entry.behind &= more_blocks;
+            if !entry.behind {
+                state.monitors.remove(monitor_id);
+            }
+        }
+        self.condvar.notify_one();
+    }
+
+    /// The number of monitors waiting to be scanned.
+    pub fn queue_size(&self) -> usize {
+        let state = self.state.lock().expect("mutex poisoned");
+        state
+            .monitors
+            .values()
+            .filter(|entry| entry.is_waiting())
+            .count()
+    }
+
+    /// Makes `next` return None from now on.
+    pub fn stop(&self) {
+        let mut state = self.state.lock().expect("mutex poisoned");
+        state.stopped = true;
+        self.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Scans `num_chunks` chunks with a single worker, returning the scanned monitors in order.
+    fn scan(scheduler: &SyncScheduler, num_chunks: usize) -> Vec<MonitorId> {
+        (0..num_chunks)
+            .map(|_| {
+                let monitor_id = scheduler.next().unwrap();
+                scheduler.finished(&monitor_id, true);
+                monitor_id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_share() {
+        assert_eq!(scan_share(0, MonitorPriority::Normal), 4);
+        assert_eq!(scan_share(1, MonitorPriority::Normal), 4);
+        assert_eq!(scan_share(1_000_000, MonitorPriority::Normal), 80);
+        assert!(scan_share(10, MonitorPriority::Low) < scan_share(10, MonitorPriority::Normal));
+        assert!(scan_share(10, MonitorPriority::High) > scan_share(10, MonitorPriority::Normal));
+    }
+
+    #[test]
+    // A monitor far behind should not keep a monitor close to the tip from being scanned.
+    fn test_monitors_share_scanning() {
+        let giant = MonitorId::from([1u8; 32]);
+        let small = MonitorId::from([2u8; 32]);
+
+        let scheduler = SyncScheduler::new();
+        assert!(scheduler.update(&[(giant, 1_000_000, MonitorPriority::Normal)]));
+        scan(&scheduler, 10);
+
+        assert!(scheduler.update(&[
+            (giant, 999_950, MonitorPriority::Normal),
+            (small, 3, MonitorPriority::Normal),
+        ]));
+        let scanned = scan(&scheduler, 10);
+        assert!(scanned.contains(&small));
+
+        // Over a few rounds, scanning is shared according to the shares of the monitors.
+        let scanned = scan(&scheduler, 2 * (80 + 8));
+        let num_small = scanned.iter().filter(|id| **id == small).count();
+        assert!(num_small >= 2 * 8 && num_small <= 3 * 8);
+    }
+
+    #[test]
+    fn test_priorities() {
+        let high = MonitorId::from([1u8; 32]);
+        let low = MonitorId::from([2u8; 32]);
+
+        let scheduler = SyncScheduler::new();
+        scheduler.update(&[
+            (high, 1000, MonitorPriority::High),
+            (low, 1000, MonitorPriority::Low),
+        ]);
+
+        let scanned = scan(&scheduler, 170);
+        let num_high = scanned.iter().filter(|id| **id == high).count();
+        assert_eq!(num_high, 160);
+    }
+
+    #[test]
+    // Resuming should carry over the tokens monitors had left, so that a restart does not hand a
+    // monitor that used up most of its share a full one again.
+    fn test_resume_keeps_tokens() {
+        let giant = MonitorId::from([1u8; 32]);
+        let small = MonitorId::from([2u8; 32]);
+
+        let scheduler = SyncScheduler::new();
+        scheduler.update(&[(small, 3, MonitorPriority::Normal)]);
+        scan(&scheduler, 5);
+        let tokens = scheduler.tokens();
+        assert_eq!(tokens[&small], 3);
+        scheduler.stop();
+
+        // The small monitor only gets scanned again once the giant used up as much of its share.
+        let resumed = SyncScheduler::new();
+        assert!(resumed.resume(
+            &[
+                (giant, 1_000_000, MonitorPriority::Normal),
+                (small, 3, MonitorPriority::Normal),
+            ],
+            &tokens,
+        ));
+        assert_eq!(resumed.tokens()[&small], 3);
+        let scanned = scan(&resumed, 40);
+        assert!(!scanned.contains(&small));
+    }
+
+    #[test]
+    // A batch should take each waiting monitor at most once, in the order they would be handed
+    // out one by one.
+    fn test_next_batch() {
+        let high = MonitorId::from([1u8; 32]);
+        let normal = MonitorId::from([2u8; 32]);
+        let low = MonitorId::from([3u8; 32]);
+
+        let scheduler = SyncScheduler::new();
+        scheduler.update(&[
+            (low, 1000, MonitorPriority::Low),
+            (normal, 1000, MonitorPriority::Normal),
+            (high, 1000, MonitorPriority::High),
+        ]);
+
+        assert_eq!(scheduler.next_batch(2), Some(vec![high, normal]));
+        assert_eq!(scheduler.next_batch(2), Some(vec![low]));
+        assert_eq!(scheduler.queue_size(), 0);
+
+        scheduler.finished(&normal, true);
+        assert_eq!(scheduler.next_batch(64), Some(vec![normal]));
+
+        scheduler.stop();
+        assert_eq!(scheduler.next_batch(64), None);
+    }
+
+    #[test]
+    fn test_monitors_are_scanned_by_one_worker_at_a_time() {
+        let monitor_id = MonitorId::from([1u8; 32]);
+        let other_id = MonitorId::from([2u8; 32]);
+
+        let scheduler = SyncScheduler::new();
+        assert!(scheduler.update(&[(monitor_id, 100, MonitorPriority::Normal)]));
+        // Monitors already waiting are not reported again.
+        assert!(!scheduler.update(&[(monitor_id, 100, MonitorPriority::Normal)]));
+        assert_eq!(scheduler.queue_size(), 1);
+        assert_eq!(scheduler.next(), Some(monitor_id));
+        assert_eq!(scheduler.queue_size(), 0);
+
+        // Updating while the monitor is being scanned does not make it available to another
+        // worker.
+        assert!(!scheduler.update(&[(monitor_id, 95, MonitorPriority::Normal)]));
+        assert!(scheduler.update(&[
+            (monitor_id, 95, MonitorPriority::Normal),
+            (other_id, 100, MonitorPriority::Normal),
+        ]));
+        assert_eq!(scheduler.next(), Some(other_id));
+
+        // Monitors that caught up are dropped.
+        scheduler.finished(&monitor_id, false);
+        scheduler.finished(&other_id, false);
+        assert_eq!(scheduler.queue_size(), 0);
+
+        scheduler.stop();
+        assert_eq!(scheduler.next(), None);
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for the state of the sync thread at shutdown.
+//! * On a graceful shutdown, the sync thread records where each monitor's scan stopped, how much
+//!   of its scanning share it had left and whether it was reported as lagging, along with how far
+//!   the ledger had been synced.
+//! * On start, the sync thread takes the snapshot and resumes the scan from it, instead of
+//!   starting a new scheduling round and reporting lagging monitors again. The snapshot is removed
+//!   when it is taken, so that a later crash does not resume from state that is out of date.
+
+use crate::{
+    error::Error,
+    monitor_store::{MonitorId, MonitorPriority},
+};
+
+use common::logger::Logger;
+use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const SYNC_SNAPSHOT_DB_NAME: &str = "mobilecoind_db:sync_snapshot_store:sync_snapshot";
+
+/// The key the snapshot is stored under.
+const SYNC_SNAPSHOT_KEY: &[u8] = b"sync_snapshot";
+
+/// The scan state of a single monitor.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct MonitorSyncState {
+    /// The monitor.
+    #[prost(message, required, tag = "1")]
+    pub monitor_id: MonitorId,
+
+    /// The next block the monitor was going to scan.
+    #[prost(uint64, tag = "2")]
+    pub next_block: u64,
+
+    /// The scanning priority of the monitor.
+    #[prost(enumeration = "MonitorPriority", tag = "3")]
+    pub priority: i32,
+
+    /// The tokens left in the monitor's bucket, if it was waiting to be scanned.
+    #[prost(uint64, optional, tag = "4")]
+    pub tokens: Option<u64>,
+
+    /// Whether the monitor was reported as lagging behind the ledger.
+    #[prost(bool, tag = "5")]
+    pub lagging: bool,
+}
+
+impl MonitorSyncState {
+    /// Returns the scanning priority of the monitor.
+    pub fn get_priority(&self) -> MonitorPriority {
+        MonitorPriority::from_i32(self.priority).unwrap_or(MonitorPriority::Normal)
+    }
+}
+
+/// The state of the sync thread when it stopped.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct SyncSnapshot {
+    /// The number of blocks in the ledger when the sync thread stopped. No monitor scanned past
+    /// it.
+    #[prost(uint64, tag = "1")]
+    pub num_blocks: u64,
+
+    /// The scan state of every monitor.
+    #[prost(message, repeated, tag = "2")]
+    pub monitors: Vec<MonitorSyncState>,
+}
+
+/// The sync snapshot database.
+#[derive(Clone)]
+pub struct SyncSnapshotStore {
+    env: Arc<Environment>,
+
+    /// Mapping of SYNC_SNAPSHOT_KEY -> SyncSnapshot.
+    sync_snapshot: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl SyncSnapshotStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let sync_snapshot = env.create_db(Some(SYNC_SNAPSHOT_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env,
+            sync_snapshot,
+            logger,
+        })
+    }
+
+    /// Store a snapshot, replacing the previous one, if any.
+    pub fn put<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        snapshot: &SyncSnapshot,
+    ) -> Result<(), Error> {
+        db_txn.put(
+            self.sync_snapshot,
+            &SYNC_SNAPSHOT_KEY,
+            &mcserial::encode(snapshot),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Remove the stored snapshot and return it, if there was one.
+    pub fn take<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+    ) -> Result<Option<SyncSnapshot>, Error> {
+        let snapshot = match db_txn.get(self.sync_snapshot, &SYNC_SNAPSHOT_KEY) {
+            Ok(value_bytes) => mcserial::decode(value_bytes)?,
+            Err(lmdb::Error::NotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        db_txn.del(self.sync_snapshot, &SYNC_SNAPSHOT_KEY, None)?;
+        Ok(Some(snapshot))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::{test_with_logger, Logger};
+    use tempdir::TempDir;
+
+    fn get_store(logger: Logger) -> (TempDir, SyncSnapshotStore) {
+        let temp_dir = TempDir::new("sync_snapshot_store").expect("Could not make tempdir");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(1)
+                .open(temp_dir.path())
+                .unwrap(),
+        );
+        (temp_dir, SyncSnapshotStore::new(env, logger).unwrap())
+    }
+
+    #[test_with_logger]
+    // A snapshot should only be taken once.
+    fn test_put_and_take(logger: Logger) {
+        let (_temp_dir, store) = get_store(logger);
+
+        let snapshot = SyncSnapshot {
+            num_blocks: 10,
+            monitors: vec![MonitorSyncState {
+                monitor_id: MonitorId::from([1u8; 32]),
+                next_block: 7,
+                priority: MonitorPriority::High as i32,
+                tokens: Some(3),
+                lagging: true,
+            }],
+        };
+
+        let mut db_txn = store.env.begin_rw_txn().unwrap();
+        assert_eq!(store.take(&mut db_txn).unwrap(), None);
+        store.put(&mut db_txn, &snapshot).unwrap();
+        db_txn.commit().unwrap();
+
+        let mut db_txn = store.env.begin_rw_txn().unwrap();
+        let taken = store.take(&mut db_txn).unwrap().unwrap();
+        assert_eq!(taken, snapshot);
+        assert_eq!(taken.monitors[0].get_priority(), MonitorPriority::High);
+        assert_eq!(store.take(&mut db_txn).unwrap(), None);
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Tenants sharing a single mobilecoind.
+//! * A tenant is a customer isolated from the others. It only sees the monitors and accounts it
+//!   added, and the payments and history of its monitors.
+//! * Tenants are bound to the API tokens callers send in the `authorization` header. The tenants
+//!   file lists the SHA3-256 hash of each token rather than the token itself.
+//! * Without a tenants file, mobilecoind is not shared and every caller sees everything.
+
+use crate::error::Error;
+use common::HashMap;
+use serde::Deserialize;
+use sha3::{Digest, Sha3_256};
+use std::{fs, path::Path};
+
+/// The SHA3-256 hash of an API token.
+pub type TokenHash = [u8; 32];
+
+/// Hashes an API token.
+pub fn token_hash(token: &str) -> TokenHash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha3_256::digest(token.as_bytes()));
+    hash
+}
+
+/// What a caller has access to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TenantScope {
+    /// Everything, since mobilecoind is not shared by tenants.
+    All,
+
+    /// What a single tenant added.
+    Tenant(String),
+}
+
+impl TenantScope {
+    /// Returns true if something owned by `owner` is visible in this scope. Owners are empty when
+    /// mobilecoind was not shared at the time.
+    pub fn contains(&self, owner: &str) -> bool {
+        match self {
+            TenantScope::All => true,
+            TenantScope::Tenant(tenant) => tenant == owner,
+        }
+    }
+
+    /// The owner recorded for what is added in this scope.
+    pub fn owner(&self) -> &str {
+        match self {
+            TenantScope::All => "",
+            TenantScope::Tenant(tenant) => tenant,
+        }
+    }
+}
+
+/// The tenants bound to each API token.
+#[derive(Clone, Debug, Default)]
+pub struct TenantTokens {
+    tenants: HashMap<TokenHash, String>,
+}
+
+/// A token binding, as written in a tenants file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TenantTokenJson {
+    tenant: String,
+    token_sha3_256: String,
+}
+
+impl TenantTokens {
+    /// Read token bindings from a JSON file holding a list of objects with a `tenant` name and
+    /// the hex-encoded `token_sha3_256` of an API token. A tenant may have several tokens.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    fn parse(json: &[u8]) -> Result<Self, Error> {
+        let bindings: Vec<TenantTokenJson> =
+            serde_json::from_slice(json).map_err(|err| Error::TenantTokens(err.to_string()))?;
+
+        let mut tenants = HashMap::default();
+        for binding in bindings {
+            if binding.tenant.is_empty() {
+                return Err(Error::TenantTokens("empty tenant name".to_string()));
+            }
+
+            let hash = match hex::decode(&binding.token_sha3_256) {
+                Ok(bytes) if bytes.len() == 32 => {
+                    let mut hash = [0u8; 32];
+                    hash.copy_from_slice(&bytes);
+                    hash
+                }
+                _ => {
+                    return Err(Error::TenantTokens(format!(
+                        "{}: invalid token hash {}",
+                        binding.tenant, binding.token_sha3_256
+                    )))
+                }
+            };
+
+            if let Some(tenant) = tenants.insert(hash, binding.tenant.clone()) {
+                return Err(Error::TenantTokens(format!(
+                    "token hash {} is bound to both {} and {}",
+                    binding.token_sha3_256, tenant, binding.tenant
+                )));
+            }
+        }
+
+        Ok(Self { tenants })
+    }
+
+    /// Returns true if mobilecoind is not shared by tenants.
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    /// The scope of a caller, given the hash of the API token it sent, if any. Fails if
+    /// mobilecoind is shared by tenants, and the caller's token is not bound to one.
+    pub fn scope(&self, token_hash: Option<&TokenHash>) -> Result<TenantScope, Error> {
+        if self.is_empty() {
+            return Ok(TenantScope::All);
+        }
+
+        token_hash
+            .and_then(|token_hash| self.tenants.get(token_hash))
+            .map(|tenant| TenantScope::Tenant(tenant.clone()))
+            .ok_or(Error::UnknownTenantToken)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    // Callers should be scoped to the tenant their token is bound to.
+    fn test_scope() {
+        let json = format!(
+            r#"[
+                {{"tenant": "alice", "token_sha3_256": "{}"}},
+                {{"tenant": "alice", "token_sha3_256": "{}"}},
+                {{"tenant": "bob", "token_sha3_256": "{}"}}
+            ]"#,
+            hex::encode(token_hash("alice-1")),
+            hex::encode(token_hash("alice-2")),
+            hex::encode(token_hash("bob")),
+        );
+        let tenant_tokens = TenantTokens::parse(json.as_bytes()).unwrap();
+
+        let alice = TenantScope::Tenant("alice".to_string());
+        assert_eq!(
+            tenant_tokens.scope(Some(&token_hash("alice-1"))).unwrap(),
+            alice
+        );
+        assert_eq!(
+            tenant_tokens.scope(Some(&token_hash("alice-2"))).unwrap(),
+            alice
+        );
+        assert!(alice.contains("alice"));
+        assert!(!alice.contains("bob"));
+        assert!(!alice.contains(""));
+        assert_eq!(alice.owner(), "alice");
+
+        // Callers without a known token have no tenant.
+        match tenant_tokens.scope(Some(&token_hash("mallory"))) {
+            Err(Error::UnknownTenantToken) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match tenant_tokens.scope(None) {
+            Err(Error::UnknownTenantToken) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Without tenants, everyone sees everything.
+        let scope = TenantTokens::default().scope(None).unwrap();
+        assert_eq!(scope, TenantScope::All);
+        assert!(scope.contains("alice"));
+        assert!(scope.contains(""));
+        assert_eq!(scope.owner(), "");
+    }
+
+    #[test]
+    // Malformed tenants files should be rejected.
+    fn test_parse_invalid_tenant_tokens() {
+        let hash = hex::encode(token_hash("token"));
+        let invalid = vec![
+            "{}".to_string(),
+            format!(r#"[{{"tenant": "", "token_sha3_256": "{}"}}]"#, hash),
+            r#"[{"tenant": "alice", "token_sha3_256": "1234"}]"#.to_string(),
+            format!(r#"[{{"tenant": "alice", "token": "{}"}}]"#, hash),
+            format!(
+                r#"[{{"tenant": "alice", "token_sha3_256": "{0}"}}, {{"tenant": "bob", "token_sha3_256": "{0}"}}]"#,
+                hash
+            ),
+        ];
+
+        for json in invalid {
+            match TenantTokens::parse(json.as_bytes()) {
+                Err(Error::TenantTokens(_)) => {}
+                other => panic!("unexpected result for {}: {:?}", json, other),
+            }
+        }
+    }
+}
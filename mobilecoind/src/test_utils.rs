@@ -6,10 +6,13 @@
 #![allow(dead_code)]
 
 use crate::{
+    account_snapshot::SnapshotSigners,
+    config::WorkerPoolsConfig,
     database::Database,
     monitor_store::{MonitorData, MonitorId},
     payments::TransactionsManager,
     service::Service,
+    tenants::TenantTokens,
 };
 use common::logger::{log, Logger};
 use grpcio::{ChannelBuilder, EnvBuilder};
@@ -19,10 +22,15 @@ use mcconnection::ConnectionManager;
 use mcconnection_tests::{test_client_uri, MockUserTxConnection};
 use mcrand::{CryptoRng, RngCore};
 use mobilecoind_api::mobilecoind_api_grpc::MobilecoindApiClient;
+use mobilecoind_enclave_api::{
+    Error as EnclaveError, ReceivedOutput, Result as EnclaveResult, SealedAccountKey, SignableTx,
+    SigningEnclave,
+};
 use tempdir::TempDir;
 use transaction::{
     account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
-    ring_signature::KeyImage,
+    onetime_keys::{compute_key_image, recover_onetime_private_key},
+    ring_signature::{KeyImage, RingMLSAG},
     tx::TxOut,
     Block, BlockContents, BlockIndex, BLOCK_VERSION,
 };
@@ -185,7 +193,7 @@ pub fn add_block_to_ledger_db(
     ledger_db.num_blocks().expect("failed to get block height")
 }
 
-fn get_free_port() -> u16 {
+pub fn get_free_port() -> u16 {
     static PORT_NR: AtomicUsize = AtomicUsize::new(0);
     PORT_NR.fetch_add(1, SeqCst) as u16 + 30100
 }
@@ -194,21 +202,31 @@ fn setup_server(
     logger: Logger,
     ledger_db: LedgerDB,
     mobilecoind_db: Database,
+    tenant_tokens: TenantTokens,
     test_port: u16,
 ) -> (Service, ConnectionManager<MockUserTxConnection>) {
     let conn_manager = get_mock_connection_manager(logger.clone());
-    let transactions_manager = TransactionsManager::new(
+    let mut transactions_manager = TransactionsManager::new(
         ledger_db.clone(),
         mobilecoind_db.clone(),
         conn_manager.clone(),
         logger.clone(),
     );
+    // Tests change the network's rules as they go.
+    transactions_manager.set_network_rules_ttl(std::time::Duration::from_secs(0));
 
     let service = Service::new(
         ledger_db,
         mobilecoind_db,
         transactions_manager,
         test_port,
+        WorkerPoolsConfig::default(),
+        None,
+        None,
+        SnapshotSigners::default(),
+        None,
+        None,
+        tenant_tokens,
         None,
         logger,
     );
@@ -245,6 +263,32 @@ pub fn get_testing_environment(
     recipients: &[PublicAddress],
     monitors: &[MonitorData],
     logger: Logger,
+    rng: &mut (impl CryptoRng + RngCore),
+) -> (
+    LedgerDB,
+    Database,
+    MobilecoindApiClient,
+    Service,
+    ConnectionManager<MockUserTxConnection>,
+) {
+    get_testing_environment_with_tenants(
+        num_random_recipients,
+        recipients,
+        monitors,
+        TenantTokens::default(),
+        logger,
+        rng,
+    )
+}
+
+/// Create a ready test environment, like `get_testing_environment`, whose server is shared by the
+/// tenants bound to `tenant_tokens`.
+pub fn get_testing_environment_with_tenants(
+    num_random_recipients: u32,
+    recipients: &[PublicAddress],
+    monitors: &[MonitorData],
+    tenant_tokens: TenantTokens,
+    logger: Logger,
     mut rng: &mut (impl CryptoRng + RngCore),
 ) -> (
     LedgerDB,
@@ -266,6 +310,7 @@ pub fn get_testing_environment(
         logger.clone(),
         ledger_db.clone(),
         mobilecoind_db.clone(),
+        tenant_tokens,
         port,
     );
     log::debug!(logger, "Setting up client {:?}", port);
@@ -334,3 +379,67 @@ pub fn wait_for_monitors(mobilecoind_db: &Database, ledger_db: &LedgerDB, logger
         );
     }
 }
+
+/// A stand-in for the signing enclave that runs in process. Its "sealed" account keys are simply
+/// serialized.
+pub struct TestSigningEnclave;
+
+impl TestSigningEnclave {
+    fn unseal_account_key(sealed_account_key: &SealedAccountKey) -> EnclaveResult<AccountKey> {
+        mcserial::decode(sealed_account_key).map_err(|_| EnclaveError::Unseal)
+    }
+}
+
+impl SigningEnclave for TestSigningEnclave {
+    fn seal_account_key(&self, account_key: AccountKey) -> EnclaveResult<SealedAccountKey> {
+        Ok(mcserial::encode(&account_key))
+    }
+
+    fn key_images(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        outputs: Vec<ReceivedOutput>,
+    ) -> EnclaveResult<Vec<KeyImage>> {
+        let account_key = Self::unseal_account_key(sealed_account_key)?;
+        Ok(outputs
+            .iter()
+            .map(|output| {
+                compute_key_image(&recover_onetime_private_key(
+                    &output.tx_public_key,
+                    account_key.view_private_key(),
+                    &account_key.subaddress_spend_key(output.subaddress_index),
+                ))
+            })
+            .collect())
+    }
+
+    fn sign_tx(
+        &self,
+        sealed_account_key: &SealedAccountKey,
+        tx: SignableTx,
+    ) -> EnclaveResult<Vec<RingMLSAG>> {
+        let account_key = Self::unseal_account_key(sealed_account_key)?;
+        let onetime_private_keys = tx.check(&account_key)?;
+        let message = tx.message();
+        let rings = tx.rings();
+        let mut rng = rand::thread_rng();
+        tx.inputs
+            .iter()
+            .zip(rings.iter())
+            .zip(onetime_private_keys.iter())
+            .map(|((input, ring), onetime_private_key)| {
+                RingMLSAG::sign(
+                    &message,
+                    ring,
+                    input.real_index,
+                    onetime_private_key,
+                    input.value,
+                    input.blinding.as_ref(),
+                    input.pseudo_output_blinding.as_ref(),
+                    &mut rng,
+                )
+                .map_err(|_| EnclaveError::Signature)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for the transaction history of monitors.
+//! * Manages the mapping of (monitor id, block index, key image) -> TransactionLogEntry.
+//! * Every TxOut a monitor receives or spends is recorded here, together with the time it was
+//!   processed. Like incoming payments, entries survive removing the monitor, unless its history
+//!   is removed along with it. A monitor that is added again keeps its original entries.
+
+use crate::{
+    block_timestamp_store::TimestampConfidence,
+    database::key_bytes::{decode_monitor_block_key_prefix, monitor_block_key_prefix},
+    error::Error,
+    monitor_store::MonitorId,
+    processed_block_store::{ProcessedTxOut, ProcessedTxOutDirection},
+};
+
+use common::logger::Logger;
+use keys::CompressedRistrettoPublic;
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+use transaction::{ring_signature::KeyImage, BlockIndex};
+
+// LMDB Database Names
+pub const TRANSACTION_LOG_KEY_TO_TRANSACTION_LOG_ENTRY_DB_NAME: &str =
+    "mobilecoind_db:transaction_log_store:transaction_log_key_to_transaction_log_entry";
+
+/// A TxOut received or spent by a monitor, as stored in the transaction log database.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct TransactionLogEntry {
+    /// The block the TxOut was received or spent in.
+    #[prost(uint64, tag = "1")]
+    pub block_index: u64,
+
+    /// Index of the subaddress the TxOut was received at, or spent from.
+    #[prost(uint64, tag = "2")]
+    pub subaddress_index: u64,
+
+    /// The public key of the TxOut.
+    #[prost(message, required, tag = "3")]
+    pub public_key: CompressedRistrettoPublic,
+
+    /// Key image of the TxOut.
+    #[prost(message, required, tag = "4")]
+    pub key_image: KeyImage,
+
+    /// Value of the TxOut.
+    #[prost(uint64, tag = "5")]
+    pub value: u64,
+
+    /// Whether the TxOut was received, spent or returned as change.
+    #[prost(enumeration = "ProcessedTxOutDirection", tag = "6")]
+    pub direction: i32,
+
+    /// The time the block was processed, in seconds since the UNIX epoch. Queries replace this
+    /// with the time the block was first seen signed, when known.
+    #[prost(uint64, tag = "7")]
+    pub timestamp: u64,
+
+    /// How far `timestamp` can be trusted.
+    #[prost(enumeration = "TimestampConfidence", tag = "8")]
+    pub timestamp_confidence: i32,
+}
+
+impl TransactionLogEntry {
+    pub fn new(processed_tx_out: &ProcessedTxOut, timestamp: u64) -> Self {
+        Self {
+            block_index: processed_tx_out.block_index,
+            subaddress_index: processed_tx_out.subaddress_index,
+            public_key: processed_tx_out.public_key,
+            key_image: processed_tx_out.key_image,
+            value: processed_tx_out.value,
+            direction: processed_tx_out.direction,
+            timestamp,
+            timestamp_confidence: TimestampConfidence::Local as i32,
+        }
+    }
+
+    /// Returns the direction of this entry.
+    pub fn get_direction(&self) -> ProcessedTxOutDirection {
+        ProcessedTxOutDirection::from_i32(self.direction)
+            .unwrap_or(ProcessedTxOutDirection::Invalid)
+    }
+
+    /// Returns the confidence of this entry's timestamp.
+    pub fn get_timestamp_confidence(&self) -> TimestampConfidence {
+        TimestampConfidence::from_i32(self.timestamp_confidence)
+            .unwrap_or(TimestampConfidence::Local)
+    }
+}
+
+/// Type used as the key in the transaction_log_key_to_transaction_log_entry database.
+/// Keys are ordered by monitor id, then by block index, so that the history of a monitor can be
+/// iterated in block order.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TransactionLogKey {
+    pub monitor_id: MonitorId,
+    pub block_index: BlockIndex,
+    pub key_image: KeyImage,
+}
+
+impl TransactionLogKey {
+    pub fn new(monitor_id: &MonitorId, block_index: BlockIndex, key_image: &KeyImage) -> Self {
+        Self {
+            monitor_id: *monitor_id,
+            block_index,
+            key_image: *key_image,
+        }
+    }
+
+    // 72 bytes: 32 for MonitorId, 8 for the big-endian block index (so that keys sort by block),
+    // 32 for the key image.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = monitor_block_key_prefix(&self.monitor_id, self.block_index);
+        buf.extend_from_slice(self.key_image.as_bytes());
+        buf
+    }
+}
+
+/// The transaction log database.
+#[derive(Clone)]
+pub struct TransactionLogStore {
+    env: Arc<Environment>,
+
+    /// Mapping of TransactionLogKey -> TransactionLogEntry.
+    transaction_log_key_to_transaction_log_entry: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl TransactionLogStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let transaction_log_key_to_transaction_log_entry = env.create_db(
+            Some(TRANSACTION_LOG_KEY_TO_TRANSACTION_LOG_ENTRY_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            transaction_log_key_to_transaction_log_entry,
+            logger,
+        })
+    }
+
+    /// Record the TxOuts a monitor received and spent in a given block. Entries that were already
+    /// recorded are left untouched. Returns the number of newly recorded entries.
+    pub fn block_processed<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        block_index: BlockIndex,
+        processed_tx_outs: &[ProcessedTxOut],
+        timestamp: u64,
+    ) -> Result<usize, Error> {
+        let mut num_recorded = 0;
+
+        for processed_tx_out in processed_tx_outs {
+            let key = TransactionLogKey::new(monitor_id, block_index, &processed_tx_out.key_image);
+            let entry = TransactionLogEntry::new(processed_tx_out, timestamp);
+            let value_bytes = mcserial::encode(&entry);
+
+            match db_txn.put(
+                self.transaction_log_key_to_transaction_log_entry,
+                &key.to_vec(),
+                &value_bytes,
+                WriteFlags::NO_OVERWRITE,
+            ) {
+                Ok(_) => num_recorded += 1,
+                Err(lmdb::Error::KeyExist) => {}
+                Err(err) => return Err(err.into()),
+            };
+        }
+
+        Ok(num_recorded)
+    }
+
+    /// Record entries of a monitor's history as they are, e.g. when restoring it from a state
+    /// archive. Entries that were already recorded are left untouched. Returns the number of
+    /// newly recorded entries.
+    pub fn insert_entries<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        entries: &[TransactionLogEntry],
+    ) -> Result<usize, Error> {
+        let mut num_recorded = 0;
+
+        for entry in entries {
+            let key = TransactionLogKey::new(monitor_id, entry.block_index, &entry.key_image);
+            match db_txn.put(
+                self.transaction_log_key_to_transaction_log_entry,
+                &key.to_vec(),
+                &mcserial::encode(entry),
+                WriteFlags::NO_OVERWRITE,
+            ) {
+                Ok(_) => num_recorded += 1,
+                Err(lmdb::Error::KeyExist) => {}
+                Err(err) => return Err(err.into()),
+            };
+        }
+
+        Ok(num_recorded)
+    }
+
+    /// Get the transaction history of a monitor, in block order.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor to get the history of.
+    /// * `subaddress_index` - If set, only entries of this subaddress are returned.
+    /// * `first_block` - The first block to include.
+    /// * `max_blocks` - The maximal number of blocks to look at, starting at `first_block`.
+    pub fn get_history(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        subaddress_index: Option<u64>,
+        first_block: BlockIndex,
+        max_blocks: u64,
+    ) -> Result<Vec<TransactionLogEntry>, Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, first_block);
+        let end_block = first_block.saturating_add(max_blocks);
+
+        let mut cursor =
+            db_txn.open_ro_cursor(self.transaction_log_key_to_transaction_log_entry)?;
+        let mut results = Vec::new();
+        for (key_bytes, value_bytes) in cursor.iter_from(&start_key) {
+            let (key_monitor_id, block_index) = decode_monitor_block_key_prefix(key_bytes)?;
+            if key_monitor_id != *monitor_id || block_index >= end_block {
+                break;
+            }
+
+            let entry: TransactionLogEntry = mcserial::decode(value_bytes)?;
+            if subaddress_index.map_or(true, |index| index == entry.subaddress_index) {
+                results.push(entry);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Remove the whole history of a monitor.
+    pub fn remove<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+    ) -> Result<(), Error> {
+        let start_key = monitor_block_key_prefix(monitor_id, 0);
+
+        // Collect the keys first, since we cannot delete while iterating a read cursor.
+        let mut keys = Vec::new();
+        {
+            let mut cursor =
+                db_txn.open_ro_cursor(self.transaction_log_key_to_transaction_log_entry)?;
+            for (key_bytes, _value_bytes) in cursor.iter_from(&start_key) {
+                let (key_monitor_id, _block_index) = decode_monitor_block_key_prefix(key_bytes)?;
+                if key_monitor_id != *monitor_id {
+                    break;
+                }
+                keys.push(key_bytes.to_vec());
+            }
+        }
+
+        for key in keys {
+            db_txn.del(
+                self.transaction_log_key_to_transaction_log_entry,
+                &key,
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use keys::{FromRandom, RistrettoPublic};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+
+    #[test_with_logger]
+    fn test_block_processed_and_get_history(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        let db_tmp = TempDir::new("transaction_log_store_db")
+            .expect("Could not make tempdir for transaction log store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = TransactionLogStore::new(env.clone(), logger.clone()).unwrap();
+
+        let (_monitor_data0, monitor_id0) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data1, monitor_id1) = get_test_monitor_data_and_id(&mut rng);
+
+        let mut processed_tx_out =
+            |block_index: u64, subaddress_index: u64, direction: ProcessedTxOutDirection| {
+                ProcessedTxOut {
+                    block_index,
+                    subaddress_index,
+                    public_key: CompressedRistrettoPublic::from(RistrettoPublic::from_random(
+                        &mut rng,
+                    )),
+                    key_image: KeyImage::from(block_index * 10 + subaddress_index),
+                    value: 10,
+                    direction: direction as i32,
+                }
+            };
+
+        let block3 = vec![
+            processed_tx_out(3, 0, ProcessedTxOutDirection::Received),
+            processed_tx_out(3, 1, ProcessedTxOutDirection::Received),
+        ];
+        let block5 = vec![
+            processed_tx_out(5, 0, ProcessedTxOutDirection::Spent),
+            processed_tx_out(5, 2, ProcessedTxOutDirection::Change),
+        ];
+
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 5, &block5, 2000)
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 3, &block3, 1000)
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id1, 3, &block3[0..1], 1000)
+                    .unwrap(),
+                1
+            );
+
+            // Processing the same block again should not overwrite the original entries.
+            assert_eq!(
+                store
+                    .block_processed(&mut db_txn, &monitor_id0, 3, &block3, 3000)
+                    .unwrap(),
+                0
+            );
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+
+        // Entries should be returned in block order and only contain our monitor's data.
+        let history = store
+            .get_history(&db_txn, &monitor_id0, None, 0, u64::max_value())
+            .unwrap();
+        assert_eq!(history.len(), 4);
+        assert!(history[0..2].iter().all(|entry| entry.block_index == 3
+            && entry.timestamp == 1000
+            && entry.get_direction() == ProcessedTxOutDirection::Received));
+        assert!(history[2..4]
+            .iter()
+            .all(|entry| entry.block_index == 5 && entry.timestamp == 2000));
+        assert!(history.contains(&TransactionLogEntry::new(&block5[0], 2000)));
+
+        // The subaddress filter should be respected.
+        let history = store
+            .get_history(&db_txn, &monitor_id0, Some(0), 0, u64::max_value())
+            .unwrap();
+        assert_eq!(
+            history,
+            vec![
+                TransactionLogEntry::new(&block3[0], 1000),
+                TransactionLogEntry::new(&block5[0], 2000)
+            ]
+        );
+
+        // The block range should be respected.
+        let history = store
+            .get_history(&db_txn, &monitor_id0, None, 4, u64::max_value())
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|entry| entry.block_index == 5));
+
+        let history = store
+            .get_history(&db_txn, &monitor_id0, None, 0, 4)
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|entry| entry.block_index == 3));
+
+        let history = store
+            .get_history(&db_txn, &monitor_id1, None, 0, u64::max_value())
+            .unwrap();
+        assert_eq!(history, vec![TransactionLogEntry::new(&block3[0], 1000)]);
+        drop(db_txn);
+
+        // Removing a monitor's history should leave the other monitors' untouched.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.remove(&mut db_txn, &monitor_id0).unwrap();
+        assert_eq!(
+            store
+                .get_history(&db_txn, &monitor_id0, None, 0, u64::max_value())
+                .unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            store
+                .get_history(&db_txn, &monitor_id1, None, 0, u64::max_value())
+                .unwrap(),
+            vec![TransactionLogEntry::new(&block3[0], 1000)]
+        );
+    }
+}
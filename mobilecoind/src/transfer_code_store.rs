@@ -0,0 +1,281 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for transfer codes.
+//! * A transfer code funds a one-time account whose entropy is handed out as a secret, so that
+//!   whoever holds the code can sweep the funds into their own account.
+//! * Codes are keyed by the public key of the TxOut that funds them, which is also what the
+//!   encoded code refers to.
+//! * Entries are never removed, since the entropy of a code that was never redeemed is the only
+//!   way to recover its funds.
+
+use crate::{
+    encryption::{DatabaseCipher, Rekeying},
+    error::Error,
+    monitor_store::MonitorId,
+};
+
+use common::logger::{log, Logger};
+use keys::CompressedRistrettoPublic;
+use lmdb::{Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use prost::Enumeration;
+use std::sync::Arc;
+use transaction::tx::TxOut;
+
+// LMDB Database Names
+pub const TX_PUBLIC_KEY_TO_TRANSFER_CODE_DB_NAME: &str =
+    "mobilecoind_db:transfer_code_store:tx_public_key_to_transfer_code";
+
+/// The status of a transfer code.
+#[derive(Clone, Copy, Debug, Enumeration, Eq, Hash, PartialEq)]
+pub enum TransferCodeStatus {
+    /// Not set. This is never stored in the database.
+    Unknown = 0,
+
+    /// The transaction funding the code was generated by this mobilecoind.
+    Created = 1,
+
+    /// A transaction sweeping the code's funds into a monitor was submitted.
+    Redeemed = 2,
+}
+
+/// A transfer code created or redeemed by this mobilecoind.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct TransferCode {
+    /// The entropy of the one-time account holding the funds.
+    #[prost(bytes, tag = "1")]
+    pub entropy: Vec<u8>,
+
+    /// The TxOut holding the funds.
+    #[prost(message, required, tag = "2")]
+    pub tx_out: TxOut,
+
+    /// Value of the TxOut.
+    #[prost(uint64, tag = "3")]
+    pub value: u64,
+
+    /// Memo encoded in the code.
+    #[prost(string, tag = "4")]
+    pub memo: String,
+
+    /// The monitor that funded the code, if it was created by this mobilecoind.
+    #[prost(message, optional, tag = "5")]
+    pub sender_monitor_id: Option<MonitorId>,
+
+    /// Current status of the code.
+    #[prost(enumeration = "TransferCodeStatus", tag = "6")]
+    pub status: i32,
+
+    /// The monitor the funds were swept into, once redeemed.
+    #[prost(message, optional, tag = "7")]
+    pub redeemer_monitor_id: Option<MonitorId>,
+
+    /// Prefix hash of the transaction sweeping the funds, once redeemed.
+    #[prost(bytes, tag = "8")]
+    pub redeem_tx_prefix_hash: Vec<u8>,
+}
+
+impl TransferCode {
+    /// The public key of the TxOut holding the funds, which identifies the code.
+    pub fn tx_public_key(&self) -> &CompressedRistrettoPublic {
+        &self.tx_out.public_key
+    }
+
+    /// Returns the status of this code.
+    pub fn get_status(&self) -> TransferCodeStatus {
+        TransferCodeStatus::from_i32(self.status).unwrap_or(TransferCodeStatus::Unknown)
+    }
+
+    /// Sets the status of this code.
+    pub fn set_status(&mut self, status: TransferCodeStatus) {
+        self.status = status as i32;
+    }
+}
+
+/// The transfer code database.
+#[derive(Clone)]
+pub struct TransferCodeStore {
+    env: Arc<Environment>,
+
+    /// Mapping of tx public key -> TransferCode, sealed by the cipher.
+    tx_public_key_to_transfer_code: Database,
+
+    /// Seals and opens TransferCodes, which hold the entropy of the codes.
+    cipher: DatabaseCipher,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl TransferCodeStore {
+    pub fn new(
+        env: Arc<Environment>,
+        cipher: DatabaseCipher,
+        logger: Logger,
+    ) -> Result<Self, Error> {
+        let tx_public_key_to_transfer_code = env.create_db(
+            Some(TX_PUBLIC_KEY_TO_TRANSFER_CODE_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        Ok(Self {
+            env,
+            tx_public_key_to_transfer_code,
+            cipher,
+            logger,
+        })
+    }
+
+    /// Add a new transfer code. Fails if a code funded by the same TxOut exists.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        transfer_code: &TransferCode,
+    ) -> Result<(), Error> {
+        let tx_public_key = transfer_code.tx_public_key();
+        match db_txn.put(
+            self.tx_public_key_to_transfer_code,
+            tx_public_key,
+            &self
+                .cipher
+                .seal(tx_public_key.as_ref(), mcserial::encode(transfer_code))?,
+            WriteFlags::NO_OVERWRITE,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::KeyExist) => Err(Error::DuplicateTransferCode),
+            Err(err) => Err(err.into()),
+        }?;
+
+        log::info!(
+            self.logger,
+            "Added transfer code {}",
+            transfer_code.tx_public_key()
+        );
+        Ok(())
+    }
+
+    /// Add a transfer code, or replace the existing code funded by the same TxOut.
+    pub fn put<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        transfer_code: &TransferCode,
+    ) -> Result<(), Error> {
+        let tx_public_key = transfer_code.tx_public_key();
+        db_txn.put(
+            self.tx_public_key_to_transfer_code,
+            tx_public_key,
+            &self
+                .cipher
+                .seal(tx_public_key.as_ref(), mcserial::encode(transfer_code))?,
+            WriteFlags::empty(),
+        )?;
+
+        log::info!(
+            self.logger,
+            "Stored transfer code {} with status {:?}",
+            transfer_code.tx_public_key(),
+            transfer_code.get_status()
+        );
+        Ok(())
+    }
+
+    /// Get a transfer code by the public key of the TxOut funding it.
+    pub fn get(
+        &self,
+        db_txn: &impl Transaction,
+        tx_public_key: &CompressedRistrettoPublic,
+    ) -> Result<TransferCode, Error> {
+        match db_txn.get(self.tx_public_key_to_transfer_code, tx_public_key) {
+            Ok(value_bytes) => Ok(mcserial::decode(
+                &self.cipher.open(tx_public_key.as_ref(), value_bytes)?,
+            )?),
+            Err(lmdb::Error::NotFound) => Err(Error::TransferCodeNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Reseal all TransferCodes while the database key changes.
+    pub fn reseal<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        rekeying: &Rekeying,
+    ) -> Result<(), Error> {
+        rekeying.reseal_all(db_txn, self.tx_public_key_to_transfer_code)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_monitor_data_and_id;
+    use common::logger::{test_with_logger, Logger};
+    use rand::{rngs::StdRng, SeedableRng};
+    use tempdir::TempDir;
+    use transaction::account_keys::AccountKey;
+    use transaction_test_utils::get_outputs;
+
+    fn setup_test_transfer_code_store(logger: &Logger) -> (Arc<Environment>, TransferCodeStore) {
+        let db_tmp = TempDir::new("transfer_code_store_db")
+            .expect("Could not make tempdir for transfer code store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        let store = TransferCodeStore::new(env.clone(), cipher, logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_transfer_code_store(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([8u8; 32]);
+        let (env, store) = setup_test_transfer_code_store(&logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let tx_outs = get_outputs(&[(recipient.clone(), 1337), (recipient, 1)], &mut rng);
+        let mut transfer_code = TransferCode {
+            entropy: vec![7u8; 32],
+            tx_out: tx_outs[0].clone(),
+            value: 1337,
+            memo: "happy birthday".to_string(),
+            sender_monitor_id: Some(monitor_id),
+            status: TransferCodeStatus::Created as i32,
+            redeemer_monitor_id: None,
+            redeem_tx_prefix_hash: vec![],
+        };
+
+        // Codes are unique per TxOut.
+        let mut db_txn = env.begin_rw_txn().unwrap();
+        store.insert(&mut db_txn, &transfer_code).unwrap();
+        match store.insert(&mut db_txn, &transfer_code) {
+            Err(Error::DuplicateTransferCode) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(
+            store.get(&db_txn, transfer_code.tx_public_key()).unwrap(),
+            transfer_code
+        );
+
+        // Redeeming a code replaces it.
+        transfer_code.set_status(TransferCodeStatus::Redeemed);
+        transfer_code.redeemer_monitor_id = Some(monitor_id);
+        transfer_code.redeem_tx_prefix_hash = vec![1u8; 32];
+        store.put(&mut db_txn, &transfer_code).unwrap();
+        let stored = store.get(&db_txn, transfer_code.tx_public_key()).unwrap();
+        assert_eq!(stored.get_status(), TransferCodeStatus::Redeemed);
+        assert_eq!(stored, transfer_code);
+
+        // Unknown codes are not found.
+        match store.get(&db_txn, &tx_outs[1].public_key) {
+            Err(Error::TransferCodeNotFound) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
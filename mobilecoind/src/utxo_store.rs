@@ -2,9 +2,14 @@
 
 //! Database storage for discovered outputs.
 //! * Manages the mapping of (monitor id, subaddress index) -> [UnspentTxOut]s.
+//! * Keeps the running balance of every subaddress up to date as UnspentTxOuts are added and
+//!   removed, so that balances can be read without going over the UnspentTxOuts.
 
 use crate::{
-    database_key::DatabaseByteArrayKey, error::Error, monitor_store::MonitorId,
+    database_key::DatabaseByteArrayKey,
+    encryption::{DatabaseCipher, Rekeying},
+    error::Error,
+    monitor_store::MonitorId,
     subaddress_store::SubaddressId,
 };
 
@@ -23,6 +28,12 @@ pub const KEY_IMAGE_TO_SUBADDRESS_ID_DB_NAME: &str =
 
 pub const UTXO_ID_TO_UTXO_DATA_DB_NAME: &str = "mobilecoind_db:utxo_store:utxo_id_to_utxo";
 
+pub const SUBADDRESS_ID_TO_BALANCE_DB_NAME: &str =
+    "mobilecoind_db:utxo_store:subaddress_id_to_balance";
+
+/// Max length of an UnspentTxOut's memo, in bytes.
+pub const MAX_UTXO_MEMO_LENGTH: usize = 256;
+
 /// An unspent (when discovered) transaction output with some annotations.
 #[derive(Clone, Eq, Hash, PartialEq, Message)]
 pub struct UnspentTxOut {
@@ -52,6 +63,23 @@ pub struct UnspentTxOut {
     /// The tombstone block used when we attempted to spend the UTXO.
     #[prost(uint64, tag = "6")]
     pub attempted_spend_tombstone: u64,
+
+    /// A note the client attached to this UnspentTxOut, e.g. "customer 42 deposit". Empty when
+    /// discovered.
+    #[prost(string, tag = "7")]
+    pub memo: String,
+}
+
+/// The running balance of a subaddress.
+#[derive(Clone, Eq, PartialEq, Message)]
+pub struct SubaddressBalance {
+    /// Sum of the values of the subaddress' UnspentTxOuts.
+    #[prost(uint64, tag = "1")]
+    pub value: u64,
+
+    /// Number of UnspentTxOuts the subaddress holds.
+    #[prost(uint64, tag = "2")]
+    pub num_utxos: u64,
 }
 
 /// Type used as the key in the utxo_id_to_utxo  database.
@@ -85,15 +113,26 @@ pub struct UtxoStore {
     /// as to allow checking of which monitor a given UnspentTxOut belongs to.
     key_image_to_subaddress_id: Database,
 
-    /// Mapping of UtxoId -> UnspentTxOut.
+    /// Mapping of UtxoId -> UnspentTxOut, sealed by the cipher.
     utxo_id_to_utxo: Database,
 
+    /// Mapping of SubaddressId -> SubaddressBalance, sealed by the cipher. Subaddresses that hold
+    /// no UnspentTxOuts have no entry.
+    subaddress_id_to_balance: Database,
+
+    /// Seals and opens UnspentTxOuts and balances, which reveal values and key images.
+    cipher: DatabaseCipher,
+
     /// Logger.
     logger: Logger,
 }
 
 impl UtxoStore {
-    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+    pub fn new(
+        env: Arc<Environment>,
+        cipher: DatabaseCipher,
+        logger: Logger,
+    ) -> Result<Self, Error> {
         let subaddress_id_to_utxo_id = env.create_db(
             Some(SUBADDRESS_ID_TO_UTXO_ID_DB_NAME),
             // DUP_SORT is needed here since we are storing multiple UtxoIds per SubaddressId.
@@ -111,11 +150,18 @@ impl UtxoStore {
             DatabaseFlags::empty(), // here we can store larger values
         )?;
 
+        let subaddress_id_to_balance = env.create_db(
+            Some(SUBADDRESS_ID_TO_BALANCE_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
         Ok(Self {
             env,
             subaddress_id_to_utxo_id,
             key_image_to_subaddress_id,
             utxo_id_to_utxo,
+            subaddress_id_to_balance,
+            cipher,
             logger,
         })
     }
@@ -141,7 +187,7 @@ impl UtxoStore {
         }
 
         // Store the utxo_id -> UnspentTxOut if it is not already in the database.
-        let utxo_bytes = mcserial::encode(utxo);
+        let utxo_bytes = self.cipher.seal(&utxo_id, mcserial::encode(utxo))?;
         match db_txn.put(
             self.utxo_id_to_utxo,
             &utxo_id,
@@ -172,6 +218,15 @@ impl UtxoStore {
             WriteFlags::NO_DUP_DATA,
         )?;
 
+        // Add the utxo to the balance of the subaddress.
+        let mut balance = self.get_balance_by_subaddress_id(db_txn, &subaddress_id)?;
+        balance.value = balance
+            .value
+            .checked_add(utxo.value)
+            .ok_or(Error::ValueOverflow)?;
+        balance.num_utxos += 1;
+        self.put_balance(db_txn, &subaddress_id, &balance)?;
+
         Ok(())
     }
 
@@ -211,6 +266,12 @@ impl UtxoStore {
             Err(err) => Err(err),
         }?;
 
+        // The subaddress is left with no balance.
+        match db_txn.del(self.subaddress_id_to_balance, &subaddress_id_bytes, None) {
+            Ok(_) | Err(lmdb::Error::NotFound) => {}
+            Err(err) => return Err(err.into()),
+        };
+
         // Success.
         Ok(())
     }
@@ -280,14 +341,22 @@ impl UtxoStore {
             drop(cursor);
         }
 
-        // Remove the actual UnspentTxOut data for every key image we successfully removed.
+        // Remove the actual UnspentTxOut data for every key image we successfully removed, and
+        // take it off the balance of its subaddress.
         for key_image in removed_key_images.iter() {
             let utxo_id = UtxoId::from(key_image);
-            match db_txn.del(self.utxo_id_to_utxo, &utxo_id, None) {
-                Ok(_) => Ok(()),
-                Err(lmdb::Error::NotFound) => Ok(()),
-                Err(err) => Err(Error::LMDB(err)),
-            }?;
+            let utxo = match self.get_utxo_by_id(db_txn, &utxo_id) {
+                Ok(utxo) => utxo,
+                Err(Error::UtxoIdNotFound) => continue,
+                Err(err) => return Err(err),
+            };
+            db_txn.del(self.utxo_id_to_utxo, &utxo_id, None)?;
+
+            let subaddress_id = SubaddressId::new(monitor_id, utxo.subaddress_index);
+            let mut balance = self.get_balance_by_subaddress_id(db_txn, &subaddress_id)?;
+            balance.value = balance.value.saturating_sub(utxo.value);
+            balance.num_utxos = balance.num_utxos.saturating_sub(1);
+            self.put_balance(db_txn, &subaddress_id, &balance)?;
         }
 
         // Success.
@@ -310,6 +379,124 @@ impl UtxoStore {
             .collect()
     }
 
+    /// Get the running balance of a given address.
+    pub fn get_balance(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        index: u64,
+    ) -> Result<SubaddressBalance, Error> {
+        self.get_balance_by_subaddress_id(db_txn, &SubaddressId::new(monitor_id, index))
+    }
+
+    /// Recompute the balance of every subaddress from its UnspentTxOuts, replacing the stored
+    /// balances.
+    pub fn rebuild_balances<'env>(&self, db_txn: &mut RwTransaction<'env>) -> Result<(), Error> {
+        db_txn.clear_db(self.subaddress_id_to_balance)?;
+
+        let entries = {
+            let mut cursor = db_txn.open_ro_cursor(self.subaddress_id_to_utxo_id)?;
+            cursor
+                .iter_start()
+                .map(|(subaddress_id_bytes, utxo_id_bytes)| {
+                    Ok((
+                        SubaddressId::try_from(subaddress_id_bytes)?,
+                        UtxoId::try_from(utxo_id_bytes)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?
+        };
+
+        let mut balances = HashMap::<SubaddressId, SubaddressBalance>::default();
+        for (subaddress_id, utxo_id) in entries {
+            let utxo = self.get_utxo_by_id(db_txn, &utxo_id)?;
+            let balance = balances.entry(subaddress_id).or_default();
+            balance.value = balance
+                .value
+                .checked_add(utxo.value)
+                .ok_or(Error::ValueOverflow)?;
+            balance.num_utxos += 1;
+        }
+
+        for (subaddress_id, balance) in balances.iter() {
+            self.put_balance(db_txn, subaddress_id, balance)?;
+        }
+        Ok(())
+    }
+
+    /// Get a page of the UnspentTxOuts belonging to a given subaddress, ordered by UtxoId.
+    ///
+    /// # Arguments
+    /// * `monitor_id` - The monitor the subaddress belongs to.
+    /// * `index` - The subaddress index.
+    /// * `after` - Only return UnspentTxOuts whose id sorts after this one, or all of them if
+    ///   None.
+    /// * `max_entries` - The maximal number of UnspentTxOuts to return.
+    ///
+    /// Returns the UnspentTxOuts, and the id of the last one if more remain.
+    pub fn get_utxos_page(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        index: u64,
+        after: Option<&UtxoId>,
+        max_entries: usize,
+    ) -> Result<(Vec<UnspentTxOut>, Option<UtxoId>), Error> {
+        let subaddress_id = SubaddressId::new(monitor_id, index);
+
+        // Values of a DUP_SORT db are sorted, so the ids of a subaddress are iterated in order.
+        let mut utxo_ids = Vec::new();
+        let mut has_more = false;
+        for utxo_id in self.get_utxo_ids(db_txn, &subaddress_id)? {
+            if after.map_or(false, |after| utxo_id.as_bytes() <= after.as_bytes()) {
+                continue;
+            }
+            if utxo_ids.len() >= max_entries {
+                has_more = true;
+                break;
+            }
+            utxo_ids.push(utxo_id);
+        }
+
+        let utxos = utxo_ids
+            .iter()
+            .map(|utxo_id| self.get_utxo_by_id(db_txn, utxo_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        let next = if has_more {
+            utxo_ids.last().copied()
+        } else {
+            None
+        };
+        Ok((utxos, next))
+    }
+
+    /// Get the UnspentTxOuts belonging to a given monitor that match a list of key images.
+    /// Key images that were not found in the database, or that belong to other monitors, are
+    /// silently ignored.
+    pub fn get_utxos_by_key_images(
+        &self,
+        db_txn: &impl Transaction,
+        monitor_id: &MonitorId,
+        key_images: &[KeyImage],
+    ) -> Result<Vec<UnspentTxOut>, Error> {
+        let mut results = Vec::new();
+        for key_image in key_images.iter() {
+            let utxo_id = UtxoId::from(key_image);
+            match self.get_subaddress_id_by_utxo_id(db_txn, &utxo_id) {
+                Ok(subaddress_id) => {
+                    if subaddress_id.monitor_id == *monitor_id {
+                        results.push(self.get_utxo_by_id(db_txn, &utxo_id)?);
+                    }
+                }
+                Err(Error::UtxoIdNotFound) => {}
+                Err(err) => {
+                    return Err(err);
+                }
+            }
+        }
+        Ok(results)
+    }
+
     /// Get subaddress id by utxo id.
     pub fn get_subaddress_id_by_utxo_id(
         &self,
@@ -350,7 +537,7 @@ impl UtxoStore {
             utxo.attempted_spend_height = attempted_spend_height;
             utxo.attempted_spend_tombstone = attempted_spend_tombstone;
 
-            let utxo_bytes = mcserial::encode(&utxo);
+            let utxo_bytes = self.cipher.seal(utxo_id, mcserial::encode(&utxo))?;
             db_txn.put(
                 self.utxo_id_to_utxo,
                 utxo_id,
@@ -362,6 +549,98 @@ impl UtxoStore {
         Ok(())
     }
 
+    /// Replace the memo of an UnspentTxOut belonging to a given monitor.
+    pub fn update_memo<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        monitor_id: &MonitorId,
+        key_image: &KeyImage,
+        memo: String,
+    ) -> Result<(), Error> {
+        if memo.len() > MAX_UTXO_MEMO_LENGTH {
+            return Err(Error::InvalidArgument(
+                "memo".to_string(),
+                format!("must be at most {} bytes", MAX_UTXO_MEMO_LENGTH),
+            ));
+        }
+
+        // UnspentTxOuts of other monitors are reported as not found.
+        let utxo_id = UtxoId::from(key_image);
+        if self
+            .get_subaddress_id_by_utxo_id(db_txn, &utxo_id)?
+            .monitor_id
+            != *monitor_id
+        {
+            return Err(Error::UtxoIdNotFound);
+        }
+
+        let mut utxo = self.get_utxo_by_id(db_txn, &utxo_id)?;
+        utxo.memo = memo;
+
+        let utxo_bytes = self.cipher.seal(&utxo_id, mcserial::encode(&utxo))?;
+        db_txn.put(
+            self.utxo_id_to_utxo,
+            &utxo_id,
+            &utxo_bytes,
+            WriteFlags::empty(),
+        )?;
+
+        Ok(())
+    }
+
+    /// Reseal all UnspentTxOuts while the database key changes.
+    pub fn reseal<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        rekeying: &Rekeying,
+    ) -> Result<(), Error> {
+        rekeying.reseal_all(db_txn, self.utxo_id_to_utxo)?;
+        rekeying.reseal_all(db_txn, self.subaddress_id_to_balance)
+    }
+
+    /// Get the running balance of a subaddress, which is zero if it holds no UnspentTxOuts.
+    fn get_balance_by_subaddress_id(
+        &self,
+        db_txn: &impl Transaction,
+        subaddress_id: &SubaddressId,
+    ) -> Result<SubaddressBalance, Error> {
+        let subaddress_id_bytes = subaddress_id.to_vec();
+        match db_txn.get(self.subaddress_id_to_balance, &subaddress_id_bytes) {
+            Ok(value_bytes) => Ok(mcserial::decode(
+                &self.cipher.open(&subaddress_id_bytes, value_bytes)?,
+            )?),
+            Err(lmdb::Error::NotFound) => Ok(SubaddressBalance::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Store the running balance of a subaddress, removing it once no UnspentTxOuts are left.
+    fn put_balance<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        subaddress_id: &SubaddressId,
+        balance: &SubaddressBalance,
+    ) -> Result<(), Error> {
+        let subaddress_id_bytes = subaddress_id.to_vec();
+        if balance.num_utxos == 0 {
+            match db_txn.del(self.subaddress_id_to_balance, &subaddress_id_bytes, None) {
+                Ok(_) | Err(lmdb::Error::NotFound) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let balance_bytes = self
+            .cipher
+            .seal(&subaddress_id_bytes, mcserial::encode(balance))?;
+        db_txn.put(
+            self.subaddress_id_to_balance,
+            &subaddress_id_bytes,
+            &balance_bytes,
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
     /// Get all UtxoIds associated with a given subaddress.
     fn get_utxo_ids(
         &self,
@@ -390,7 +669,7 @@ impl UtxoStore {
         utxo_id: &UtxoId,
     ) -> Result<UnspentTxOut, Error> {
         match db_txn.get(self.utxo_id_to_utxo, &utxo_id) {
-            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Ok(value_bytes) => Ok(mcserial::decode(&self.cipher.open(utxo_id, value_bytes)?)?),
             Err(lmdb::Error::NotFound) => Err(Error::UtxoIdNotFound),
             Err(err) => Err(err.into()),
         }
@@ -430,6 +709,7 @@ mod test {
                     value: idx,
                     attempted_spend_height: 0,
                     attempted_spend_tombstone: 0,
+                    memo: String::new(),
                 }
             })
             .collect();
@@ -448,7 +728,8 @@ mod test {
                 .unwrap(),
         );
 
-        let utxo_store = UtxoStore::new(env, logger.clone()).unwrap();
+        let cipher = DatabaseCipher::new(env.clone()).unwrap();
+        let utxo_store = UtxoStore::new(env, cipher, logger.clone()).unwrap();
 
         // Return
         (ledger_db, utxo_store, utxos)
@@ -869,6 +1150,55 @@ mod test {
         }
     }
 
+    #[test_with_logger]
+    // Pages should cover every utxo of a subaddress exactly once, in order.
+    fn test_get_utxos_page(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_ledger_db, utxo_store, utxos) = setup_test_utxo_store(&mut rng, &logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let mut db_txn = utxo_store.env.begin_rw_txn().unwrap();
+        for utxo in utxos.iter() {
+            utxo_store
+                .append_utxo(&mut db_txn, &monitor_id, 0, utxo)
+                .unwrap();
+        }
+
+        let all_utxos = utxo_store.get_utxos(&db_txn, &monitor_id, 0).unwrap();
+        assert_eq!(all_utxos.len(), utxos.len());
+
+        // Everything fits in a single page.
+        let (page, next) = utxo_store
+            .get_utxos_page(&db_txn, &monitor_id, 0, None, utxos.len())
+            .unwrap();
+        assert_eq!(page, all_utxos);
+        assert_eq!(next, None);
+
+        // Pages of two.
+        let mut paged_utxos = Vec::new();
+        let mut after = None;
+        loop {
+            let (page, next) = utxo_store
+                .get_utxos_page(&db_txn, &monitor_id, 0, after.as_ref(), 2)
+                .unwrap();
+            assert!(page.len() <= 2);
+            paged_utxos.extend(page);
+            if next.is_none() {
+                break;
+            }
+            after = next;
+        }
+        assert_eq!(paged_utxos, all_utxos);
+
+        // Other subaddresses have no utxos.
+        assert_eq!(
+            utxo_store
+                .get_utxos_page(&db_txn, &monitor_id, 1, None, 2)
+                .unwrap(),
+            (vec![], None)
+        );
+    }
+
     #[test_with_logger]
     fn test_update_attempted_spend(logger: Logger) {
         let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
@@ -946,4 +1276,133 @@ mod test {
             }
         }
     }
+
+    #[test_with_logger]
+    fn test_update_memo(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_ledger_db, utxo_store, utxos) = setup_test_utxo_store(&mut rng, &logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+        let (_monitor_data, other_monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        let mut db_txn = utxo_store.env.begin_rw_txn().unwrap();
+        for utxo in utxos.iter() {
+            utxo_store
+                .append_utxo(&mut db_txn, &monitor_id, utxo.subaddress_index, utxo)
+                .unwrap();
+        }
+
+        utxo_store
+            .update_memo(
+                &mut db_txn,
+                &monitor_id,
+                &utxos[1].key_image,
+                "customer 42 deposit".to_string(),
+            )
+            .unwrap();
+
+        // Only the memo of the given UnspentTxOut changes.
+        let mut expected_utxos = utxos.clone();
+        expected_utxos[1].memo = "customer 42 deposit".to_string();
+        assert_eq!(
+            HashSet::from_iter(utxo_store.get_utxos(&db_txn, &monitor_id, 0).unwrap()),
+            HashSet::from_iter(expected_utxos)
+        );
+
+        // UnspentTxOuts that do not exist or belong to another monitor are not found.
+        match utxo_store.update_memo(
+            &mut db_txn,
+            &monitor_id,
+            &KeyImage::from(1234567),
+            "memo".to_string(),
+        ) {
+            Err(Error::UtxoIdNotFound) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match utxo_store.update_memo(
+            &mut db_txn,
+            &other_monitor_id,
+            &utxos[1].key_image,
+            "memo".to_string(),
+        ) {
+            Err(Error::UtxoIdNotFound) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Memos are limited in length.
+        match utxo_store.update_memo(
+            &mut db_txn,
+            &monitor_id,
+            &utxos[1].key_image,
+            "x".repeat(MAX_UTXO_MEMO_LENGTH + 1),
+        ) {
+            Err(Error::InvalidArgument(argument, _)) => assert_eq!(argument, "memo"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test_with_logger]
+    // Balances should follow the UnspentTxOuts that are added and removed.
+    fn test_balances(logger: Logger) {
+        let mut rng: StdRng = SeedableRng::from_seed([123u8; 32]);
+        let (_ledger_db, utxo_store, mut utxos) = setup_test_utxo_store(&mut rng, &logger);
+        let (_monitor_data, monitor_id) = get_test_monitor_data_and_id(&mut rng);
+
+        // Values 0, 1 and 2 go to subaddress 0, values 3 and 4 to subaddress 1.
+        for utxo in utxos.iter_mut().skip(3) {
+            utxo.subaddress_index = 1;
+        }
+        let balance = |value, num_utxos| SubaddressBalance { value, num_utxos };
+
+        let mut db_txn = utxo_store.env.begin_rw_txn().unwrap();
+        for utxo in utxos.iter() {
+            utxo_store
+                .append_utxo(&mut db_txn, &monitor_id, utxo.subaddress_index, utxo)
+                .unwrap();
+        }
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 0).unwrap(),
+            balance(3, 3)
+        );
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 1).unwrap(),
+            balance(7, 2)
+        );
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 2).unwrap(),
+            balance(0, 0)
+        );
+
+        // Spending takes the UnspentTxOuts off their subaddress' balance.
+        utxo_store
+            .remove_utxos_by_key_images(
+                &mut db_txn,
+                &monitor_id,
+                &[utxos[2].key_image, utxos[3].key_image, utxos[4].key_image],
+            )
+            .unwrap();
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 0).unwrap(),
+            balance(1, 2)
+        );
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 1).unwrap(),
+            balance(0, 0)
+        );
+
+        // Rebuilding gives the same balances.
+        utxo_store.rebuild_balances(&mut db_txn).unwrap();
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 0).unwrap(),
+            balance(1, 2)
+        );
+
+        // Removing the subaddress' UnspentTxOuts clears its balance.
+        utxo_store
+            .remove_utxos(&mut db_txn, &monitor_id, 0)
+            .unwrap();
+        assert_eq!(
+            utxo_store.get_balance(&db_txn, &monitor_id, 0).unwrap(),
+            balance(0, 0)
+        );
+    }
 }
@@ -0,0 +1,286 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Database storage for webhook deliveries.
+//! * Every event that matches a webhook subscription is stored here until it was delivered, or
+//!   delivery was given up on, so that events are not lost when mobilecoind restarts.
+//! * Deliveries are keyed by a monotonically increasing id, so that listing them yields the order
+//!   in which events happened.
+
+use crate::{
+    database::key_bytes::{key_bytes_to_u64, u64_to_key_bytes},
+    error::Error,
+};
+
+use common::logger::{log, Logger};
+use lmdb::{Cursor, Database, DatabaseFlags, Environment, RwTransaction, Transaction, WriteFlags};
+use mcserial::Message;
+use std::sync::Arc;
+
+// LMDB Database Names
+pub const DELIVERY_ID_TO_WEBHOOK_DELIVERY_DB_NAME: &str =
+    "mobilecoind_db:webhook_delivery_store:delivery_id_to_webhook_delivery";
+
+pub const COUNTS_DB_NAME: &str = "mobilecoind_db:webhook_delivery_store:counts";
+
+// Keys used by the `counts` database.
+const NEXT_DELIVERY_ID_KEY: &str = "next_delivery_id";
+
+/// An event waiting to be delivered to a webhook.
+#[derive(Clone, Eq, Hash, PartialEq, Message)]
+pub struct WebhookDelivery {
+    /// Assigned when the delivery is stored.
+    #[prost(uint64, tag = "1")]
+    pub id: u64,
+
+    /// The URL the event is POSTed to.
+    #[prost(string, tag = "2")]
+    pub url: String,
+
+    /// The JSON-serialized event.
+    #[prost(bytes, tag = "3")]
+    pub body: Vec<u8>,
+
+    /// The number of failed attempts to deliver the event.
+    #[prost(uint32, tag = "4")]
+    pub attempts: u32,
+
+    /// The delivery should not be attempted before this time, in milliseconds since the UNIX
+    /// epoch.
+    #[prost(uint64, tag = "5")]
+    pub not_before_ms: u64,
+}
+
+impl WebhookDelivery {
+    /// Create a delivery that can be attempted right away. Its id is assigned when it is stored.
+    pub fn new(url: &str, body: Vec<u8>) -> Self {
+        Self {
+            id: 0,
+            url: url.to_string(),
+            body,
+            attempts: 0,
+            not_before_ms: 0,
+        }
+    }
+}
+
+/// The webhook delivery database.
+#[derive(Clone)]
+pub struct WebhookDeliveryStore {
+    env: Arc<Environment>,
+
+    /// Mapping of delivery id -> WebhookDelivery.
+    delivery_id_to_webhook_delivery: Database,
+
+    /// Aggregate counts.
+    /// * `NEXT_DELIVERY_ID_KEY` --> the id the next stored delivery gets.
+    counts: Database,
+
+    /// Logger.
+    logger: Logger,
+}
+
+impl WebhookDeliveryStore {
+    pub fn new(env: Arc<Environment>, logger: Logger) -> Result<Self, Error> {
+        let delivery_id_to_webhook_delivery = env.create_db(
+            Some(DELIVERY_ID_TO_WEBHOOK_DELIVERY_DB_NAME),
+            DatabaseFlags::empty(),
+        )?;
+
+        let counts = env.create_db(Some(COUNTS_DB_NAME), DatabaseFlags::empty())?;
+
+        Ok(Self {
+            env,
+            delivery_id_to_webhook_delivery,
+            counts,
+            logger,
+        })
+    }
+
+    /// Store a new delivery. Returns the id it was stored with.
+    pub fn insert<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        delivery: &WebhookDelivery,
+    ) -> Result<u64, Error> {
+        let id = match db_txn.get(self.counts, &NEXT_DELIVERY_ID_KEY) {
+            Ok(value_bytes) => Ok(key_bytes_to_u64(value_bytes)),
+            Err(lmdb::Error::NotFound) => Ok(0),
+            Err(err) => Err(err),
+        }?;
+
+        let mut delivery = delivery.clone();
+        delivery.id = id;
+
+        db_txn.put(
+            self.delivery_id_to_webhook_delivery,
+            &u64_to_key_bytes(id),
+            &mcserial::encode(&delivery),
+            WriteFlags::NO_OVERWRITE,
+        )?;
+
+        db_txn.put(
+            self.counts,
+            &NEXT_DELIVERY_ID_KEY,
+            &u64_to_key_bytes(id + 1),
+            WriteFlags::empty(),
+        )?;
+
+        log::trace!(
+            self.logger,
+            "Queued webhook delivery {} to {}",
+            id,
+            delivery.url
+        );
+        Ok(id)
+    }
+
+    /// Replace an existing delivery with one of the same id.
+    pub fn update<'env>(
+        &self,
+        db_txn: &mut RwTransaction<'env>,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), Error> {
+        // Make sure the delivery exists, so that updates never create deliveries.
+        self.get(db_txn, delivery.id)?;
+
+        db_txn.put(
+            self.delivery_id_to_webhook_delivery,
+            &u64_to_key_bytes(delivery.id),
+            &mcserial::encode(delivery),
+            WriteFlags::empty(),
+        )?;
+        Ok(())
+    }
+
+    /// Get a delivery by id.
+    pub fn get(&self, db_txn: &impl Transaction, id: u64) -> Result<WebhookDelivery, Error> {
+        match db_txn.get(self.delivery_id_to_webhook_delivery, &u64_to_key_bytes(id)) {
+            Ok(value_bytes) => Ok(mcserial::decode(value_bytes)?),
+            Err(lmdb::Error::NotFound) => Err(Error::WebhookDeliveryNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get pending deliveries, in the order they were stored.
+    ///
+    /// # Arguments
+    /// * `max_entries` - The maximal number of deliveries to return.
+    pub fn list(
+        &self,
+        db_txn: &impl Transaction,
+        max_entries: usize,
+    ) -> Result<Vec<WebhookDelivery>, Error> {
+        let mut cursor = db_txn.open_ro_cursor(self.delivery_id_to_webhook_delivery)?;
+        let mut results = Vec::new();
+        for (_id_bytes, value_bytes) in cursor.iter_start() {
+            if results.len() >= max_entries {
+                break;
+            }
+            results.push(mcserial::decode(value_bytes)?);
+        }
+        Ok(results)
+    }
+
+    /// Remove a delivery by id.
+    pub fn remove<'env>(&self, db_txn: &mut RwTransaction<'env>, id: u64) -> Result<(), Error> {
+        match db_txn.del(
+            self.delivery_id_to_webhook_delivery,
+            &u64_to_key_bytes(id),
+            None,
+        ) {
+            Ok(_) => Ok(()),
+            Err(lmdb::Error::NotFound) => Err(Error::WebhookDeliveryNotFound),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::{test_with_logger, Logger};
+    use tempdir::TempDir;
+
+    fn setup_test_webhook_delivery_store(
+        logger: &Logger,
+    ) -> (Arc<Environment>, WebhookDeliveryStore) {
+        let db_tmp = TempDir::new("webhook_delivery_store_db")
+            .expect("Could not make tempdir for webhook delivery store db")
+            .into_path();
+        let db_path = db_tmp.to_str().expect("Could not get path as string");
+
+        let env = Arc::new(
+            Environment::new()
+                .set_max_dbs(10)
+                .set_map_size(10000000)
+                .open(db_path.as_ref())
+                .unwrap(),
+        );
+        let store = WebhookDeliveryStore::new(env.clone(), logger.clone()).unwrap();
+        (env, store)
+    }
+
+    #[test_with_logger]
+    fn test_insert_update_remove(logger: Logger) {
+        let (env, store) = setup_test_webhook_delivery_store(&logger);
+
+        let deliveries: Vec<WebhookDelivery> = (0..3)
+            .map(|i| WebhookDelivery::new("https://example.com/hook", vec![i]))
+            .collect();
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            for (i, delivery) in deliveries.iter().enumerate() {
+                assert_eq!(store.insert(&mut db_txn, delivery).unwrap(), i as u64);
+            }
+            db_txn.commit().unwrap();
+        }
+
+        // Failed attempts are recorded.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            let mut delivery = store.get(&db_txn, 1).unwrap();
+            delivery.attempts = 1;
+            delivery.not_before_ms = 1000;
+            store.update(&mut db_txn, &delivery).unwrap();
+            db_txn.commit().unwrap();
+        }
+
+        {
+            let db_txn = env.begin_ro_txn().unwrap();
+            let pending = store.list(&db_txn, 100).unwrap();
+            let ids: Vec<u64> = pending.iter().map(|delivery| delivery.id).collect();
+            assert_eq!(ids, vec![0, 1, 2]);
+            assert_eq!(pending[1].body, vec![1]);
+            assert_eq!(pending[1].attempts, 1);
+            assert_eq!(pending[1].not_before_ms, 1000);
+            assert_eq!(store.list(&db_txn, 2).unwrap().len(), 2);
+        }
+
+        // Removed deliveries are gone, and ids are never reused.
+        {
+            let mut db_txn = env.begin_rw_txn().unwrap();
+            store.remove(&mut db_txn, 2).unwrap();
+            match store.remove(&mut db_txn, 2) {
+                Err(Error::WebhookDeliveryNotFound) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            let mut unknown = WebhookDelivery::new("https://example.com/hook", vec![]);
+            unknown.id = 7;
+            match store.update(&mut db_txn, &unknown) {
+                Err(Error::WebhookDeliveryNotFound) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert_eq!(store.insert(&mut db_txn, &deliveries[0]).unwrap(), 3);
+            db_txn.commit().unwrap();
+        }
+
+        let db_txn = env.begin_ro_txn().unwrap();
+        let ids: Vec<u64> = store
+            .list(&db_txn, 100)
+            .unwrap()
+            .iter()
+            .map(|delivery| delivery.id)
+            .collect();
+        assert_eq!(ids, vec![0, 1, 3]);
+    }
+}
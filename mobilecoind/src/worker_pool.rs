@@ -0,0 +1,179 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Worker pools and their metrics.
+//!
+//! mobilecoind runs API requests, ledger scanning and transaction building on separate pools of
+//! threads, each of which can be sized on its own. This keeps a backlog of monitors to scan from
+//! delaying wallet RPCs, and a burst of transactions to build from delaying everything else.
+//!
+//! Every pool reports the following metrics, prefixed with the pool name:
+//! * `num_workers` - the number of threads in the pool.
+//! * `queue_size` - the number of tasks waiting for a thread.
+//! * `busy_workers` - the number of threads currently running a task.
+//! * `tasks_completed` - the number of tasks finished since startup.
+//! * `task_duration` - how long tasks take to run, in seconds.
+
+use crate::counters::OP_COUNTERS;
+use futures::sync::oneshot;
+use metrics::{Histogram, IntCounter, IntGauge};
+use std::{thread, time::Instant};
+
+/// Pool handling API requests.
+pub const API_POOL_NAME: &str = "api";
+
+/// Pool scanning the ledger for monitor outputs.
+pub const SCAN_POOL_NAME: &str = "scan";
+
+/// Pool building transactions.
+pub const TX_BUILD_POOL_NAME: &str = "tx_build";
+
+/// Metrics of a worker pool.
+#[derive(Clone)]
+pub struct PoolMetrics {
+    num_workers: IntGauge,
+    queue_size: IntGauge,
+    busy_workers: IntGauge,
+    tasks_completed: IntCounter,
+    task_duration: Histogram,
+}
+
+impl PoolMetrics {
+    pub fn new(pool_name: &str) -> Self {
+        Self {
+            num_workers: OP_COUNTERS.gauge(&format!("{}_num_workers", pool_name)),
+            queue_size: OP_COUNTERS.gauge(&format!("{}_queue_size", pool_name)),
+            busy_workers: OP_COUNTERS.gauge(&format!("{}_busy_workers", pool_name)),
+            tasks_completed: OP_COUNTERS.counter(&format!("{}_tasks_completed", pool_name)),
+            task_duration: OP_COUNTERS.histogram(&format!("{}_task_duration", pool_name)),
+        }
+    }
+
+    pub fn set_num_workers(&self, num_workers: usize) {
+        self.num_workers.set(num_workers as i64);
+    }
+
+    pub fn set_queue_size(&self, queue_size: usize) {
+        self.queue_size.set(queue_size as i64);
+    }
+
+    /// Marks a worker as busy until the returned guard is dropped, at which point the task is
+    /// counted as completed.
+    pub fn start_task(&self) -> TaskGuard {
+        self.busy_workers.inc();
+        TaskGuard {
+            metrics: self.clone(),
+            started_at: Instant::now(),
+        }
+    }
+}
+
+/// Tracks a running task. See `PoolMetrics::start_task`.
+pub struct TaskGuard {
+    metrics: PoolMetrics,
+    started_at: Instant,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.metrics.busy_workers.dec();
+        self.metrics.tasks_completed.inc();
+        self.metrics
+            .task_duration
+            .observe(self.started_at.elapsed().as_secs_f64());
+    }
+}
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// A fixed-size pool of threads running tasks in the order they were submitted.
+///
+/// The threads exit once every clone of the pool has been dropped and the remaining tasks have
+/// run.
+#[derive(Clone)]
+pub struct WorkerPool {
+    sender: crossbeam_channel::Sender<Task>,
+    metrics: PoolMetrics,
+}
+
+impl WorkerPool {
+    /// Starts a pool.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the pool, used for its threads and metrics.
+    /// * `num_workers` - Number of threads. Defaults to the number of logical CPU cores.
+    pub fn new(name: &str, num_workers: Option<usize>) -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded::<Task>();
+        let metrics = PoolMetrics::new(name);
+
+        let num_workers = num_workers.unwrap_or_else(num_cpus::get);
+        metrics.set_num_workers(num_workers);
+
+        for idx in 0..num_workers {
+            let thread_receiver = receiver.clone();
+            let thread_metrics = metrics.clone();
+            thread::Builder::new()
+                .name(format!("{}_worker_{}", name, idx))
+                .spawn(move || {
+                    for task in thread_receiver.iter() {
+                        thread_metrics.set_queue_size(thread_receiver.len());
+                        let _task_guard = thread_metrics.start_task();
+                        task();
+                    }
+                })
+                .expect("failed starting worker thread");
+        }
+
+        Self { sender, metrics }
+    }
+
+    /// Runs `f` on one of the pool's threads. The returned future resolves to its result.
+    pub fn spawn<F, R>(&self, f: F) -> oneshot::Receiver<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.sender
+            .send(Box::new(move || {
+                // The caller might have stopped waiting for the result.
+                let _ = result_sender.send(f());
+            }))
+            .expect("failed sending task to worker pool");
+        self.metrics.set_queue_size(self.sender.len());
+        result_receiver
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::Future;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    // Tasks run on the pool threads and hand back their results.
+    fn test_spawn() {
+        let pool = WorkerPool::new("test_spawn", Some(2));
+        let num_runs = Arc::new(AtomicUsize::new(0));
+
+        let results: Vec<_> = (0..10)
+            .map(|i| {
+                let num_runs = num_runs.clone();
+                pool.spawn(move || {
+                    num_runs.fetch_add(1, Ordering::SeqCst);
+                    (i * 2, thread::current().name().map(String::from))
+                })
+            })
+            .collect();
+
+        for (i, result) in results.into_iter().enumerate() {
+            let (value, thread_name) = result.wait().expect("task result lost");
+            assert_eq!(value, i * 2);
+            assert!(thread_name.unwrap().starts_with("test_spawn_worker_"));
+        }
+        assert_eq!(num_runs.load(Ordering::SeqCst), 10);
+    }
+}
@@ -15,7 +15,7 @@ use common::{
 };
 use consensus_enclave_api::{ConsensusEnclaveProxy, TxContext, WellFormedEncryptedTx};
 use core::fmt::{Display, Formatter, Result as FmtResult};
-use grpcio::{ChannelBuilder, Environment};
+use grpcio::{ChannelBuilder, Environment, Error as GrpcError, RpcStatusCode};
 use mcconnection::{
     AttestedConnection, BlockchainConnection, Connection, ConnectionUriGrpcioChannel,
     Error as ConnectionError, Result as ConnectionResult,
@@ -52,6 +52,9 @@ pub struct PeerConnection<Enclave: ConsensusEnclaveProxy> {
     /// The local node ID
     local_node_id: NodeID,
 
+    /// Fingerprint of the local node's fee split, checked by the remote node.
+    fee_split_fingerprint: [u8; 32],
+
     /// The remote node ID
     remote_responder_id: ResponderId,
 
@@ -73,6 +76,7 @@ impl<Enclave: ConsensusEnclaveProxy> PeerConnection<Enclave> {
     pub fn new(
         enclave: Enclave,
         local_node_id: NodeID,
+        fee_split_fingerprint: [u8; 32],
         uri: PeerUri,
         env: Arc<Environment>,
         logger: Logger,
@@ -96,6 +100,7 @@ impl<Enclave: ConsensusEnclaveProxy> PeerConnection<Enclave> {
         Self {
             enclave,
             local_node_id,
+            fee_split_fingerprint,
             remote_responder_id,
             uri,
             channel_id: None,
@@ -193,7 +198,7 @@ impl<Enclave: ConsensusEnclaveProxy> BlockchainConnection for PeerConnection<Enc
     }
 
     fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> ConnectionResult<Vec<BlockID>> {
-        trace_time!(self.logger, "PeerConnection::get_blocks");
+        trace_time!(self.logger, "PeerConnection::get_block_ids");
 
         let mut request = BlocksRequest::new();
         request.set_offset(range.start);
@@ -201,12 +206,25 @@ impl<Enclave: ConsensusEnclaveProxy> BlockchainConnection for PeerConnection<Enc
             u32::try_from(range.end - range.start).or(Err(ConnectionError::RequestTooLarge))?;
         request.set_limit(limit);
 
-        self.attested_call(|this| this.blockchain_api_client.get_blocks(&request))?
-            .get_blocks()
+        let block_ids =
+            match self.attested_call(|this| this.blockchain_api_client.get_block_ids(&request)) {
+                Ok(response) => response.get_block_ids().to_vec(),
+                // Peers that predate GetBlockIds only serve whole blocks.
+                Err(PeerAttestationError::Grpc(GrpcError::RpcFailure(status)))
+                    if status.status == RpcStatusCode::UNIMPLEMENTED =>
+                {
+                    self.attested_call(|this| this.blockchain_api_client.get_blocks(&request))?
+                        .get_blocks()
+                        .iter()
+                        .map(|proto_block| proto_block.id.clone())
+                        .collect()
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+        block_ids
             .iter()
-            .map(|proto_block| {
-                BlockID::try_from(&proto_block.id[..]).map_err(ConnectionError::from)
-            })
+            .map(|block_id| BlockID::try_from(&block_id[..]).map_err(ConnectionError::from))
             .collect::<ConnectionResult<Vec<BlockID>>>()
     }
 
@@ -235,6 +253,7 @@ impl<Enclave: ConsensusEnclaveProxy> ConsensusConnection for PeerConnection<Encl
         let mut grpc_msg = GrpcConsensusMsg::default();
         grpc_msg.set_from_responder_id(serialize(&self.local_node_id.responder_id)?);
         grpc_msg.set_payload(serialize(&msg)?);
+        grpc_msg.set_fee_split_fingerprint(self.fee_split_fingerprint.to_vec());
 
         self.attested_call(|this| this.consensus_api_client.send_consensus_msg(&grpc_msg))?;
         Ok(())
@@ -165,8 +165,12 @@ impl<L: Ledger + Sync> BlockchainConnection for MockPeerConnection<L> {
             .or(Err(ConnectionError::NotFound))
     }
 
-    fn fetch_block_ids(&mut self, _range: Range<BlockIndex>) -> ConnectionResult<Vec<BlockID>> {
-        unimplemented!()
+    fn fetch_block_ids(&mut self, range: Range<BlockIndex>) -> ConnectionResult<Vec<BlockID>> {
+        Ok(self
+            .fetch_blocks(range)?
+            .into_iter()
+            .map(|block| block.id)
+            .collect())
     }
 
     fn fetch_block_height(&mut self) -> ConnectionResult<BlockIndex> {
@@ -459,6 +459,12 @@ Please enter a payment request code. If you received an email with an allocation
                     println!();
                     break;
                 }
+                mobilecoind_api::TxStatus::InvalidConfirmationNumber => {
+                    // Only reported when checking the status as a receiver.
+                    pb.finish_with_message("Unexpected transaction status!");
+                    println!();
+                    break;
+                }
             }
         }
 
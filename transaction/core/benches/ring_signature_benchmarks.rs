@@ -0,0 +1,91 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Benchmarks of ring signature verification, and of decompressing the ring it starts with.
+//!
+//! Ring members are decompressed one at a time. Decompressing a Ristretto point is dominated by
+//! an inverse square root, i.e. an exponentiation whose result depends on whether its input is
+//! a square, so unlike inversion it cannot be shared across a batch. `curve25519-dalek` does not
+//! expose its field arithmetic either, so batching would mean carrying our own. "decompress ring"
+//! tracks what decompression costs relative to "::verify" in the meantime.
+
+use core::convert::TryFrom;
+use criterion::{criterion_group, criterion_main, Criterion};
+use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate, RistrettoPublic};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use transaction::{
+    ring_signature::{RingMLSAG, Scalar},
+    Commitment, CompressedCommitment,
+};
+
+/// Number of members of a ring, i.e. the real input and its mixins.
+const RING_SIZE: usize = 11;
+
+fn ring_signature_benchmarks(c: &mut Criterion) {
+    let mut rng: StdRng = SeedableRng::from_seed([100u8; 32]);
+    let mut group = c.benchmark_group("RingMLSAG");
+
+    let mut ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)> = (1..RING_SIZE)
+        .map(|_| {
+            (
+                CompressedRistrettoPublic::from(RistrettoPublic::from_random(&mut rng)),
+                CompressedCommitment::new(rng.next_u64(), Scalar::random(&mut rng)),
+            )
+        })
+        .collect();
+
+    let real_index = 0;
+    let onetime_private_key = RistrettoPrivate::from_random(&mut rng);
+    let value = rng.next_u64();
+    let blinding = Scalar::random(&mut rng);
+    let output_blinding = Scalar::random(&mut rng);
+    ring.insert(
+        real_index,
+        (
+            CompressedRistrettoPublic::from(RistrettoPublic::from(&onetime_private_key)),
+            CompressedCommitment::new(value, blinding),
+        ),
+    );
+
+    let mut message = [0u8; 32];
+    rng.fill_bytes(&mut message);
+    let signature = RingMLSAG::sign(
+        &message,
+        &ring,
+        real_index,
+        &onetime_private_key,
+        value,
+        &blinding,
+        &output_blinding,
+        &mut rng,
+    )
+    .unwrap();
+    let output_commitment = CompressedCommitment::new(value, output_blinding);
+
+    // Decompressing the ring is part of verifying it.
+    group.bench_function("decompress ring", |b| {
+        b.iter(|| {
+            for (address, commitment) in &ring {
+                let _address = RistrettoPublic::try_from(address).unwrap();
+                let _commitment = Commitment::try_from(commitment).unwrap();
+            }
+        })
+    });
+
+    group.bench_function("::verify", |b| {
+        b.iter(|| {
+            signature
+                .verify(&message, &ring, &output_commitment)
+                .unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(100);
+    targets = ring_signature_benchmarks
+}
+
+criterion_main!(benches);
@@ -280,6 +280,12 @@ impl AccountKey {
         }
     }
 
+    /// The account's spend public key `B`, which, together with the view private key, allows
+    /// computing subaddresses without being able to spend.
+    pub fn spend_public_key(&self) -> RistrettoPublic {
+        RistrettoPublic::from(&self.spend_private_key)
+    }
+
     /// Compute a subaddress spend key `d` from private AccountKey data
     pub fn default_subaddress_spend_key(&self) -> RistrettoPrivate {
         self.subaddress_spend_key(DEFAULT_SUBADDRESS_INDEX)
@@ -326,6 +332,32 @@ impl AccountKey {
     }
 }
 
+/// Compute a subaddress (without fog) from an account's view private key `a` and spend public key
+/// `B`. This is the same subaddress `AccountKey::subaddress` computes, and lets watch-only clients
+/// recognize outputs without having the spend private key.
+pub fn view_only_subaddress(
+    view_private_key: &RistrettoPrivate,
+    spend_public_key: &RistrettoPublic,
+    index: u64,
+) -> PublicAddress {
+    let a: &Scalar = view_private_key.as_ref();
+
+    // `Hs(a || n)`
+    let Hs: Scalar = {
+        let n = Scalar::from(index);
+        let mut digest = Blake2b::new();
+        digest.input(a.as_bytes());
+        digest.input(n.as_bytes());
+        Scalar::from_hash::<Blake2b>(digest)
+    };
+
+    let M = Hs * G;
+    let D = M + spend_public_key.as_ref();
+    let C = a * D;
+
+    PublicAddress::new(&RistrettoPublic::from(D), &RistrettoPublic::from(C))
+}
+
 #[cfg(test)]
 mod testing {
     use super::*;
@@ -348,4 +380,18 @@ mod testing {
             }
         });
     }
+
+    #[test]
+    // Subaddresses computed without the spend private key should match the account's.
+    fn view_only_subaddress_matches_account_key() {
+        test_helper::run_with_several_seeds(|mut rng| {
+            let acct = AccountKey::random(&mut rng);
+            for index in &[DEFAULT_SUBADDRESS_INDEX, 1, 17, u64::max_value()] {
+                assert_eq!(
+                    view_only_subaddress(acct.view_private_key(), &acct.spend_public_key(), *index),
+                    acct.subaddress(*index)
+                );
+            }
+        });
+    }
 }
@@ -0,0 +1,300 @@
+use crate::{blake2b_256::Blake2b256, Block, BlockID, BlockIndex};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use digestible::{Digest, Digestible};
+use failure::Fail;
+use keys::{
+    DigestSigner, DigestVerifier, Ed25519Pair, Ed25519Public, Ed25519Signature,
+    Ed25519SignatureError,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+
+/// Domain separator for hashing a block id into a leaf of the merkle tree.
+const MERKLE_LEAF_DOMAIN_TAG: &[u8] = b"mc_archive_manifest_leaf";
+
+/// Domain separator for hashing two children into an inner node of the merkle tree.
+const MERKLE_NODE_DOMAIN_TAG: &[u8] = b"mc_archive_manifest_node";
+
+/// Commits to the ids of a range of consecutive blocks, so that the whole range can be checked
+/// against a single signature.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, Digestible)]
+pub struct ArchiveManifest {
+    /// Index of the first block in the range.
+    pub first_block_index: BlockIndex,
+
+    /// Number of blocks in the range.
+    pub num_blocks: u64,
+
+    /// Merkle root of the ids of the blocks in the range, in order.
+    pub merkle_root: [u8; 32],
+}
+
+impl ArchiveManifest {
+    /// Create a manifest for a range of consecutive blocks.
+    pub fn new(blocks: &[Block]) -> Self {
+        let block_ids: Vec<&BlockID> = blocks.iter().map(|block| &block.id).collect();
+        Self {
+            first_block_index: blocks.first().map_or(0, |block| block.index),
+            num_blocks: blocks.len() as u64,
+            merkle_root: merkle_root(&block_ids),
+        }
+    }
+
+    /// Index of the block following the range.
+    pub fn end_block_index(&self) -> BlockIndex {
+        self.first_block_index + self.num_blocks
+    }
+
+    /// Returns true if the range includes the given block index.
+    pub fn contains(&self, block_index: BlockIndex) -> bool {
+        block_index >= self.first_block_index && block_index < self.end_block_index()
+    }
+
+    /// Verify that `blocks` are exactly the blocks this manifest commits to.
+    pub fn verify_blocks(&self, blocks: &[Block]) -> Result<(), ArchiveManifestError> {
+        let indexes_match = blocks.len() as u64 == self.num_blocks
+            && blocks
+                .iter()
+                .zip(self.first_block_index..)
+                .all(|(block, index)| block.index == index);
+        if !indexes_match {
+            return Err(ArchiveManifestError::BlockRangeMismatch);
+        }
+
+        let block_ids: Vec<&BlockID> = blocks.iter().map(|block| &block.id).collect();
+        if merkle_root(&block_ids) != self.merkle_root {
+            return Err(ArchiveManifestError::MerkleRootMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// An `ArchiveManifest`, signed by the archive that published it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedArchiveManifest {
+    /// The manifest.
+    manifest: ArchiveManifest,
+
+    /// Signature over the manifest.
+    signature: Ed25519Signature,
+
+    /// The public key of the keypair used to generate the signature.
+    signer: Ed25519Public,
+}
+
+impl SignedArchiveManifest {
+    /// Create a new SignedArchiveManifest from an existing signature.
+    pub fn new(
+        manifest: ArchiveManifest,
+        signature: Ed25519Signature,
+        signer: Ed25519Public,
+    ) -> Self {
+        Self {
+            manifest,
+            signature,
+            signer,
+        }
+    }
+
+    /// Create a new SignedArchiveManifest by signing a manifest.
+    pub fn from_manifest_and_keypair(
+        manifest: ArchiveManifest,
+        keypair: &Ed25519Pair,
+    ) -> Result<Self, Ed25519SignatureError> {
+        // SHA512 is used for compatibility with Ed25519ph.
+        let mut hasher = Sha512::default();
+        manifest.digest(&mut hasher);
+        let signature = keypair.try_sign_digest(hasher)?;
+
+        Ok(Self {
+            manifest,
+            signature,
+            signer: keypair.public_key(),
+        })
+    }
+
+    /// Get the manifest. Note that it is only trustworthy once `verify` succeeded.
+    pub fn manifest(&self) -> &ArchiveManifest {
+        &self.manifest
+    }
+
+    /// Get the signature.
+    pub fn signature(&self) -> &Ed25519Signature {
+        &self.signature
+    }
+
+    /// Get the signer.
+    pub fn signer(&self) -> &Ed25519Public {
+        &self.signer
+    }
+
+    /// Verify that the signature is over the manifest.
+    pub fn verify(&self) -> Result<(), Ed25519SignatureError> {
+        let mut hasher = Sha512::default();
+        self.manifest.digest(&mut hasher);
+
+        self.signer.verify_digest(hasher, &self.signature)
+    }
+}
+
+impl Debug for SignedArchiveManifest {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "SignedArchiveManifest({:?}, {}:{})",
+            self.manifest,
+            hex_fmt::HexFmt(&self.signature),
+            hex_fmt::HexFmt(&self.signer)
+        )
+    }
+}
+
+/// An error verifying blocks against a manifest.
+#[derive(Clone, Copy, Debug, Eq, Fail, PartialEq)]
+pub enum ArchiveManifestError {
+    /// The blocks are not the range of blocks the manifest covers.
+    #[fail(display = "Blocks do not match the manifest's block range")]
+    BlockRangeMismatch,
+
+    /// The block ids do not hash to the manifest's merkle root.
+    #[fail(display = "Block ids do not match the manifest's merkle root")]
+    MerkleRootMismatch,
+}
+
+/// Computes the merkle root of a list of block ids.
+///
+/// Leaves and inner nodes are hashed with different domain separators, so that an inner node
+/// cannot be passed off as a leaf. A node without a sibling is carried up to the next level
+/// unchanged, rather than being paired with itself, so that no two lists share a root.
+pub fn merkle_root(block_ids: &[&BlockID]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = block_ids
+        .iter()
+        .map(|block_id| hash_with_domain(MERKLE_LEAF_DOMAIN_TAG, &[block_id.as_ref()]))
+        .collect();
+
+    if level.is_empty() {
+        return hash_with_domain(MERKLE_LEAF_DOMAIN_TAG, &[]);
+    }
+
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_with_domain(MERKLE_NODE_DOMAIN_TAG, &[left, right]),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+fn hash_with_domain(domain_tag: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Blake2b256::new();
+    hasher.input(domain_tag);
+    for part in parts {
+        hasher.input(part);
+    }
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.result());
+    result
+}
+
+#[cfg(test)]
+mod archive_manifest_tests {
+    use super::*;
+    use crate::{BlockContents, BLOCK_VERSION};
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn get_blocks(num_blocks: usize) -> Vec<Block> {
+        let mut blocks = vec![Block::new_origin_block(&[])];
+        while blocks.len() < num_blocks {
+            let parent = blocks.last().unwrap();
+            blocks.push(Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                parent.index + 1,
+                &Default::default(),
+                &BlockContents::new(Vec::new(), Vec::new()),
+            ));
+        }
+        blocks
+    }
+
+    #[test]
+    // A manifest should accept exactly the blocks it was created for.
+    fn test_verify_blocks() {
+        let blocks = get_blocks(7);
+
+        for first in 0..blocks.len() {
+            for end in first + 1..=blocks.len() {
+                let manifest = ArchiveManifest::new(&blocks[first..end]);
+                assert_eq!(manifest.first_block_index, first as u64);
+                assert_eq!(manifest.end_block_index(), end as u64);
+                assert_eq!(manifest.verify_blocks(&blocks[first..end]), Ok(()));
+            }
+        }
+
+        let manifest = ArchiveManifest::new(&blocks[1..5]);
+        assert_eq!(
+            manifest.verify_blocks(&blocks[1..4]),
+            Err(ArchiveManifestError::BlockRangeMismatch)
+        );
+        assert_eq!(
+            manifest.verify_blocks(&blocks[2..6]),
+            Err(ArchiveManifestError::BlockRangeMismatch)
+        );
+
+        // Tampering with any block id should be detected.
+        for i in 1..5 {
+            let mut tampered = blocks[1..5].to_vec();
+            tampered[i - 1].id.0[0] ^= 1;
+            assert_eq!(
+                manifest.verify_blocks(&tampered),
+                Err(ArchiveManifestError::MerkleRootMismatch)
+            );
+        }
+    }
+
+    #[test]
+    // Lists of different lengths should not share a root, even when padded with repeated ids.
+    fn test_merkle_root_distinguishes_lists() {
+        let blocks = get_blocks(3);
+        let ids: Vec<&BlockID> = blocks.iter().map(|block| &block.id).collect();
+
+        let root_3 = merkle_root(&ids);
+        let root_4 = merkle_root(&[ids[0], ids[1], ids[2], ids[2]]);
+        let root_2 = merkle_root(&ids[..2]);
+        assert_ne!(root_3, root_4);
+        assert_ne!(root_3, root_2);
+        assert_ne!(merkle_root(&[]), merkle_root(&ids[..1]));
+    }
+
+    #[test]
+    fn test_signed_manifest() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let keypair = Ed25519Pair::from_random(&mut rng);
+        let blocks = get_blocks(5);
+
+        let signed = SignedArchiveManifest::from_manifest_and_keypair(
+            ArchiveManifest::new(&blocks),
+            &keypair,
+        )
+        .unwrap();
+        assert!(signed.verify().is_ok());
+        assert_eq!(signed.signer(), &keypair.public_key());
+
+        // A manifest for a different range should not verify with the same signature.
+        let forged = SignedArchiveManifest::new(
+            ArchiveManifest::new(&blocks[..4]),
+            signed.signature().clone(),
+            signed.signer().clone(),
+        );
+        assert!(forged.verify().is_err());
+    }
+}
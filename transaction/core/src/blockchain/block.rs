@@ -7,8 +7,9 @@ use alloc::vec::Vec;
 use digestible::{Digest, Digestible};
 use serde::{Deserialize, Serialize};
 
-/// Version identifier.
-pub const BLOCK_VERSION: u32 = 0;
+/// Version identifier. Version 1 derives the keys of fee outputs from the block's contents (see
+/// `fee_split`).
+pub const BLOCK_VERSION: u32 = 1;
 
 /// The index of a block in the blockchain.
 pub type BlockIndex = u64;
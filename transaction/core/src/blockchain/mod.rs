@@ -4,11 +4,13 @@
 
 use failure::Fail;
 
+mod archive_manifest;
 mod block;
 mod block_contents;
 mod block_id;
 mod block_signature;
 
+pub use archive_manifest::*;
 pub use block::*;
 pub use block_contents::*;
 pub use block_id::*;
@@ -23,6 +23,9 @@ pub const TOTAL_MOB: u64 = 250_000_000;
 /// Minimum allowed fee, denominated in picoMOB.
 pub const BASE_FEE: u64 = 10;
 
+/// Maximum number of recipients the fees of a block may be split between.
+pub const MAX_FEE_RECIPIENTS: usize = 16;
+
 cfg_if::cfg_if! {
     if #[cfg(any(test, feature="test-net-fee-keys"))] {
         /// Internal testnet fee recipient account, generated via
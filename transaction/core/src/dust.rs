@@ -0,0 +1,169 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Rules governing zero-value and sub-minimum ("dust") outputs.
+//!
+//! Output values are hidden by commitments, so validators cannot enforce these rules
+//! themselves. Instead, each network advertises its rules and transaction builders are expected
+//! to respect them.
+
+use alloc::string::{String, ToString};
+use core::{fmt, str::FromStr};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+/// How a network treats outputs whose value is below its minimum output value.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DustPolicy {
+    /// Dust outputs are never allowed.
+    Rejected,
+
+    /// Dust outputs are allowed only when they return change to the sender.
+    AllowedAsChange,
+
+    /// Dust outputs are always allowed.
+    AllowedAlways,
+}
+
+impl Default for DustPolicy {
+    fn default() -> Self {
+        DustPolicy::AllowedAlways
+    }
+}
+
+impl fmt::Display for DustPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DustPolicy::Rejected => write!(f, "rejected"),
+            DustPolicy::AllowedAsChange => write!(f, "allowed-as-change"),
+            DustPolicy::AllowedAlways => write!(f, "allowed-always"),
+        }
+    }
+}
+
+impl FromStr for DustPolicy {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        match src {
+            "rejected" => Ok(DustPolicy::Rejected),
+            "allowed-as-change" => Ok(DustPolicy::AllowedAsChange),
+            "allowed-always" => Ok(DustPolicy::AllowedAlways),
+            _ => Err("expected one of: rejected, allowed-as-change, allowed-always".to_string()),
+        }
+    }
+}
+
+/// An error returned when an output violates the network's dust rules.
+#[derive(Clone, Debug, Deserialize, Eq, Fail, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum DustError {
+    /// The output's value is below the network minimum.
+    #[fail(display = "Output value {} is below the minimum of {}", _0, _1)]
+    BelowMinimum(u64, u64),
+
+    /// The output's value is zero.
+    #[fail(display = "Zero-value outputs are not allowed")]
+    ZeroValue,
+}
+
+/// The dust rules advertised by a network.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(default)]
+pub struct DustRules {
+    /// How outputs below `minimum_output_value` are treated.
+    pub policy: DustPolicy,
+
+    /// The smallest output value, in picoMOB, that is not considered dust.
+    /// Zero-value outputs are always dust when this is zero.
+    pub minimum_output_value: u64,
+}
+
+impl DustRules {
+    pub fn new(policy: DustPolicy, minimum_output_value: u64) -> Self {
+        Self {
+            policy,
+            minimum_output_value,
+        }
+    }
+
+    /// Returns true if an output of the given value is dust under these rules.
+    pub fn is_dust(&self, value: u64) -> bool {
+        value == 0 || value < self.minimum_output_value
+    }
+
+    /// Checks whether an output of the given value is allowed.
+    ///
+    /// # Arguments
+    /// * `value` - The output's value, in picoMOB.
+    /// * `is_change` - Whether the output returns change to the sender.
+    pub fn check_output(&self, value: u64, is_change: bool) -> Result<(), DustError> {
+        if !self.is_dust(value) {
+            return Ok(());
+        }
+
+        match (self.policy, is_change) {
+            (DustPolicy::AllowedAlways, _) | (DustPolicy::AllowedAsChange, true) => Ok(()),
+            _ if value == 0 => Err(DustError::ZeroValue),
+            _ => Err(DustError::BelowMinimum(value, self.minimum_output_value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    // The default rules should allow any output.
+    fn test_default_allows_everything() {
+        let rules = DustRules::default();
+        assert_eq!(rules.check_output(0, false), Ok(()));
+        assert_eq!(rules.check_output(1, false), Ok(()));
+        assert_eq!(rules.check_output(0, true), Ok(()));
+    }
+
+    #[test]
+    fn test_rejected() {
+        let rules = DustRules::new(DustPolicy::Rejected, 100);
+        assert_eq!(rules.check_output(100, false), Ok(()));
+        assert_eq!(rules.check_output(100, true), Ok(()));
+        assert_eq!(
+            rules.check_output(99, false),
+            Err(DustError::BelowMinimum(99, 100))
+        );
+        assert_eq!(
+            rules.check_output(99, true),
+            Err(DustError::BelowMinimum(99, 100))
+        );
+        assert_eq!(rules.check_output(0, true), Err(DustError::ZeroValue));
+
+        // Zero-value outputs are dust even without a minimum.
+        let rules = DustRules::new(DustPolicy::Rejected, 0);
+        assert_eq!(rules.check_output(1, false), Ok(()));
+        assert_eq!(rules.check_output(0, false), Err(DustError::ZeroValue));
+    }
+
+    #[test]
+    fn test_allowed_as_change() {
+        let rules = DustRules::new(DustPolicy::AllowedAsChange, 100);
+        assert_eq!(rules.check_output(100, false), Ok(()));
+        assert_eq!(rules.check_output(99, true), Ok(()));
+        assert_eq!(rules.check_output(0, true), Ok(()));
+        assert_eq!(
+            rules.check_output(99, false),
+            Err(DustError::BelowMinimum(99, 100))
+        );
+    }
+
+    #[test]
+    fn test_policy_from_str_round_trip() {
+        for policy in &[
+            DustPolicy::Rejected,
+            DustPolicy::AllowedAsChange,
+            DustPolicy::AllowedAlways,
+        ] {
+            assert_eq!(DustPolicy::from_str(&policy.to_string()).unwrap(), *policy);
+        }
+        assert!(DustPolicy::from_str("sometimes").is_err());
+    }
+}
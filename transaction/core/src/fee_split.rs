@@ -0,0 +1,481 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Splitting the fees of a block between several recipients, e.g. a foundation and the node
+//! operators.
+//!
+//! The fees of a block are paid to one fee output per recipient, with a share of the total
+//! proportional to the recipient's weight. Every node must use the same split, otherwise they
+//! form different blocks.
+//!
+//! Since block version 1, the keys of the fee outputs are derived from the rest of the block's
+//! contents, so anyone who knows the split can check that a block pays its fees to the right
+//! recipients, in the right proportions. The amounts of fee outputs are therefore public.
+
+use crate::{
+    account_keys::PublicAddress,
+    amount::{Amount, AmountError},
+    blake2b_256::Blake2b256,
+    constants::{FEE_SPEND_PUBLIC_KEY, FEE_VIEW_PUBLIC_KEY, MAX_FEE_RECIPIENTS},
+    onetime_keys::{compute_shared_secret, compute_tx_pubkey, create_onetime_public_key},
+    ring_signature::{Blinding, KeyImage, Scalar},
+    tx::TxOut,
+    Block, BlockContents,
+};
+use alloc::{vec, vec::Vec};
+use core::convert::{TryFrom, TryInto};
+use digestible::{Digest, Digestible};
+use failure::Fail;
+use keys::{RistrettoPrivate, RistrettoPublic};
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for hashing fee splits.
+const FEE_SPLIT_DOMAIN_TAG: &[u8] = b"mc_fee_split";
+
+/// Domain separator for deriving the transaction private keys of fee outputs.
+const FEE_OUTPUT_KEY_DOMAIN_TAG: &[u8] = b"mc_fee_output_key";
+
+/// Domain separator for deriving the amount blindings of fee outputs.
+const FEE_OUTPUT_BLINDING_DOMAIN_TAG: &[u8] = b"mc_fee_output_blinding";
+
+/// The first block version whose fee outputs can be checked with `FeeSplit::verify_fee_outputs`.
+pub const VERIFIABLE_FEE_OUTPUTS_BLOCK_VERSION: u32 = 1;
+
+/// An error returned when a fee split is invalid.
+#[derive(Clone, Debug, Deserialize, Eq, Fail, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum FeeSplitError {
+    /// A fee split must have at least one recipient.
+    #[fail(display = "A fee split must have at least one recipient")]
+    NoRecipients,
+
+    /// A fee split must not have more than `MAX_FEE_RECIPIENTS` recipients.
+    #[fail(display = "Too many fee recipients: {}", _0)]
+    TooManyRecipients(usize),
+
+    /// Each recipient must have a non-zero weight.
+    #[fail(display = "Fee recipient {} has a zero weight", _0)]
+    ZeroWeight(usize),
+
+    /// Each recipient must appear once.
+    #[fail(display = "Fee recipient {} appears more than once", _0)]
+    DuplicateRecipient(usize),
+
+    /// A block has fewer outputs than there are fee recipients.
+    #[fail(display = "The block is missing fee outputs")]
+    MissingFeeOutputs,
+
+    /// A block's fee output does not pay the recipient at that index.
+    #[fail(display = "Invalid fee output for recipient {}", _0)]
+    InvalidFeeOutput(usize),
+
+    /// The shares of a block's fee outputs are not split according to the weights.
+    #[fail(display = "The fee outputs do not follow the split")]
+    UnevenFeeOutputs,
+}
+
+/// A recipient of a share of the fees of each block.
+#[derive(Clone, Debug, Deserialize, Digestible, Eq, Hash, PartialEq, Serialize)]
+pub struct FeeRecipient {
+    /// The address fee outputs are minted to.
+    pub address: PublicAddress,
+
+    /// The recipient's share of the fees, relative to the weights of the other recipients.
+    pub weight: u32,
+}
+
+impl FeeRecipient {
+    pub fn new(address: PublicAddress, weight: u32) -> Self {
+        Self { address, weight }
+    }
+}
+
+/// How the fees of a block are split between recipients.
+#[derive(Clone, Debug, Deserialize, Digestible, Eq, Hash, PartialEq, Serialize)]
+pub struct FeeSplit {
+    recipients: Vec<FeeRecipient>,
+}
+
+impl Default for FeeSplit {
+    /// All fees go to the network's fee recipient account.
+    fn default() -> Self {
+        let address = PublicAddress::new(
+            &RistrettoPublic::try_from(&FEE_SPEND_PUBLIC_KEY).unwrap(),
+            &RistrettoPublic::try_from(&FEE_VIEW_PUBLIC_KEY).unwrap(),
+        );
+        Self {
+            recipients: vec![FeeRecipient::new(address, 1)],
+        }
+    }
+}
+
+impl FeeSplit {
+    pub fn new(recipients: Vec<FeeRecipient>) -> Result<Self, FeeSplitError> {
+        let fee_split = Self { recipients };
+        fee_split.validate()?;
+        Ok(fee_split)
+    }
+
+    /// The recipients, in the order of their fee outputs.
+    pub fn recipients(&self) -> &[FeeRecipient] {
+        &self.recipients
+    }
+
+    /// Checks that fees can be split between the recipients. Splits received over a
+    /// serialization boundary must be validated before use.
+    pub fn validate(&self) -> Result<(), FeeSplitError> {
+        if self.recipients.is_empty() {
+            return Err(FeeSplitError::NoRecipients);
+        }
+        if self.recipients.len() > MAX_FEE_RECIPIENTS {
+            return Err(FeeSplitError::TooManyRecipients(self.recipients.len()));
+        }
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            if recipient.weight == 0 {
+                return Err(FeeSplitError::ZeroWeight(i));
+            }
+            if self.recipients[..i]
+                .iter()
+                .any(|other| other.address == recipient.address)
+            {
+                return Err(FeeSplitError::DuplicateRecipient(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits `total_fee` between the recipients, in order. Each recipient gets its share rounded
+    /// down, and the picoMOB lost to rounding go to the first recipients, one each. The shares
+    /// always sum to `total_fee`.
+    pub fn split(&self, total_fee: u64) -> Vec<u64> {
+        let total_weight: u128 = self
+            .recipients
+            .iter()
+            .map(|recipient| recipient.weight as u128)
+            .sum();
+        if total_weight == 0 {
+            return vec![0; self.recipients.len()];
+        }
+
+        let mut shares: Vec<u64> = self
+            .recipients
+            .iter()
+            .map(|recipient| (total_fee as u128 * recipient.weight as u128 / total_weight) as u64)
+            .collect();
+
+        // Less than one picoMOB is lost per recipient.
+        let remainder = total_fee - shares.iter().sum::<u64>();
+        for share in shares.iter_mut().take(remainder as usize) {
+            *share += 1;
+        }
+        shares
+    }
+
+    /// Mints the fee outputs of a block, one per recipient and in order, which are appended after
+    /// the outputs of its transactions.
+    ///
+    /// # Arguments
+    /// * `key_images` - The key images of the block.
+    /// * `tx_outputs` - The outputs of the block's transactions.
+    /// * `total_fee` - The sum of the fees of the block's transactions.
+    pub fn fee_outputs(
+        &self,
+        key_images: &[KeyImage],
+        tx_outputs: &[TxOut],
+        total_fee: u64,
+    ) -> Result<Vec<TxOut>, AmountError> {
+        self.recipients
+            .iter()
+            .zip(self.split(total_fee))
+            .enumerate()
+            .map(|(index, (recipient, fee))| {
+                let (tx_private_key, blinding) = fee_output_keys(key_images, tx_outputs, index);
+                mint_fee_output(&recipient.address, &tx_private_key, fee, blinding)
+            })
+            .collect()
+    }
+
+    /// Checks that a block ends with one fee output per recipient, which split the block's fees
+    /// according to the weights. Returns the total fee of the block.
+    ///
+    /// Only blocks of `VERIFIABLE_FEE_OUTPUTS_BLOCK_VERSION` and above can be checked.
+    pub fn verify_fee_outputs(&self, block_contents: &BlockContents) -> Result<u64, FeeSplitError> {
+        let num_tx_outputs = block_contents
+            .outputs
+            .len()
+            .checked_sub(self.recipients.len())
+            .ok_or(FeeSplitError::MissingFeeOutputs)?;
+        let (tx_outputs, fee_outputs) = block_contents.outputs.split_at(num_tx_outputs);
+
+        let mut shares = Vec::with_capacity(fee_outputs.len());
+        for (index, (recipient, fee_output)) in self.recipients.iter().zip(fee_outputs).enumerate()
+        {
+            let (tx_private_key, blinding) =
+                fee_output_keys(&block_contents.key_images, tx_outputs, index);
+            let shared_secret =
+                compute_shared_secret(recipient.address.view_public_key(), &tx_private_key);
+            let (fee, _blinding) = fee_output
+                .amount
+                .get_value(&shared_secret)
+                .map_err(|_| FeeSplitError::InvalidFeeOutput(index))?;
+
+            let expected_fee_output =
+                mint_fee_output(&recipient.address, &tx_private_key, fee, blinding)
+                    .map_err(|_| FeeSplitError::InvalidFeeOutput(index))?;
+            if *fee_output != expected_fee_output {
+                return Err(FeeSplitError::InvalidFeeOutput(index));
+            }
+            shares.push(fee);
+        }
+
+        let total_fee = shares
+            .iter()
+            .try_fold(0u64, |total_fee, fee| total_fee.checked_add(*fee))
+            .ok_or(FeeSplitError::UnevenFeeOutputs)?;
+        if self.split(total_fee) != shares {
+            return Err(FeeSplitError::UnevenFeeOutputs);
+        }
+        Ok(total_fee)
+    }
+
+    /// Whether the fee outputs of `block` can be checked with `verify_fee_outputs`. The origin
+    /// block has no fee outputs.
+    pub fn has_verifiable_fee_outputs(block: &Block) -> bool {
+        block.index > 0 && block.version >= VERIFIABLE_FEE_OUTPUTS_BLOCK_VERSION
+    }
+
+    /// A hash of the split, used by nodes to check that their peers split fees the same way.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Blake2b256::new();
+        hasher.input(FEE_SPLIT_DOMAIN_TAG);
+        Digestible::digest(self, &mut hasher);
+
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&hasher.result());
+        result
+    }
+}
+
+/// Derives the transaction private key and the amount blinding of the fee output at `index` from
+/// the rest of the block's contents. All nodes derive the same fee outputs, and anyone can check
+/// them.
+///
+/// # Arguments:
+/// * `key_images` - The key images of the block.
+/// * `tx_outputs` - The outputs of the block's transactions.
+/// * `index` - The index of the fee output, i.e. of its recipient in the fee split.
+fn fee_output_keys(
+    key_images: &[KeyImage],
+    tx_outputs: &[TxOut],
+    index: usize,
+) -> (RistrettoPrivate, Blinding) {
+    let hash = |domain_tag: &[u8]| -> [u8; 32] {
+        let mut hasher = Blake2b256::new();
+        hasher.input(domain_tag);
+        hasher.input((index as u64).to_le_bytes());
+        key_images.digest(&mut hasher);
+        tx_outputs.digest(&mut hasher);
+        hasher
+            .result()
+            .as_slice()
+            .try_into()
+            .expect("Wrong length.")
+    };
+
+    let tx_private_key = RistrettoPrivate::from(Scalar::from_bytes_mod_order(hash(
+        FEE_OUTPUT_KEY_DOMAIN_TAG,
+    )));
+    let blinding = Blinding::from_bytes_mod_order(hash(FEE_OUTPUT_BLINDING_DOMAIN_TAG));
+    (tx_private_key, blinding)
+}
+
+/// Creates a single output belonging to a fee recipient.
+///
+/// # Arguments:
+/// * `fee_recipient` - The address of the fee recipient.
+/// * `tx_private_key` - Transaction key used to output the fee.
+/// * `fee` - The recipient's share of the fees in the block.
+/// * `blinding` - The` Blinding` value to use for constructing the Amount.
+fn mint_fee_output(
+    fee_recipient: &PublicAddress,
+    tx_private_key: &RistrettoPrivate,
+    fee: u64,
+    blinding: Blinding,
+) -> Result<TxOut, AmountError> {
+    let target_key = create_onetime_public_key(fee_recipient, tx_private_key).into();
+    let public_key = compute_tx_pubkey(&tx_private_key, fee_recipient.spend_public_key()).into();
+    let shared_secret = compute_shared_secret(fee_recipient.view_public_key(), tx_private_key);
+    let amount = Amount::new(fee, blinding, &shared_secret)?;
+
+    Ok(TxOut {
+        amount,
+        target_key,
+        public_key,
+        e_account_hint: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::account_keys::AccountKey;
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn test_split(weights: &[u32]) -> FeeSplit {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        FeeSplit::new(
+            weights
+                .iter()
+                .map(|weight| {
+                    FeeRecipient::new(AccountKey::random(&mut rng).default_subaddress(), *weight)
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    // The default split should pay all fees to the fee recipient account.
+    fn test_default() {
+        let fee_split = FeeSplit::default();
+        assert_eq!(fee_split.validate(), Ok(()));
+        assert_eq!(fee_split.recipients().len(), 1);
+        assert_eq!(
+            fee_split.recipients()[0].address.spend_public_key(),
+            &RistrettoPublic::try_from(&FEE_SPEND_PUBLIC_KEY).unwrap()
+        );
+        assert_eq!(fee_split.split(1234), vec![1234]);
+    }
+
+    #[test]
+    // Shares should be proportional to the weights, and sum to the total fee.
+    fn test_split_shares() {
+        let fee_split = test_split(&[3, 1]);
+        assert_eq!(fee_split.split(0), vec![0, 0]);
+        assert_eq!(fee_split.split(100), vec![75, 25]);
+
+        // The picoMOB lost to rounding go to the first recipients.
+        let fee_split = test_split(&[1, 1, 1]);
+        assert_eq!(fee_split.split(10), vec![4, 3, 3]);
+        assert_eq!(fee_split.split(11), vec![4, 4, 3]);
+
+        let fee_split = test_split(&[u32::max_value(), 1, u32::max_value()]);
+        for total_fee in &[0, 1, 10, 12345, u64::max_value()] {
+            assert_eq!(fee_split.split(*total_fee).iter().sum::<u64>(), *total_fee);
+        }
+    }
+
+    #[test]
+    fn test_invalid_splits() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let address = AccountKey::random(&mut rng).default_subaddress();
+
+        assert_eq!(FeeSplit::new(vec![]), Err(FeeSplitError::NoRecipients));
+        assert_eq!(
+            FeeSplit::new(vec![FeeRecipient::new(address.clone(), 0)]),
+            Err(FeeSplitError::ZeroWeight(0))
+        );
+        assert_eq!(
+            FeeSplit::new(vec![
+                FeeRecipient::new(address.clone(), 1),
+                FeeRecipient::new(address, 2),
+            ]),
+            Err(FeeSplitError::DuplicateRecipient(1))
+        );
+
+        let weights = vec![1; MAX_FEE_RECIPIENTS + 1];
+        let recipients = weights
+            .iter()
+            .map(|weight| {
+                FeeRecipient::new(AccountKey::random(&mut rng).default_subaddress(), *weight)
+            })
+            .collect();
+        assert_eq!(
+            FeeSplit::new(recipients),
+            Err(FeeSplitError::TooManyRecipients(MAX_FEE_RECIPIENTS + 1))
+        );
+    }
+
+    #[test]
+    // Nodes should only agree on identical splits.
+    fn test_fingerprint() {
+        let fingerprint = test_split(&[3, 1]).fingerprint();
+        assert_eq!(fingerprint, test_split(&[3, 1]).fingerprint());
+        assert_ne!(fingerprint, test_split(&[1, 3]).fingerprint());
+        assert_ne!(fingerprint, test_split(&[6, 2]).fingerprint());
+        assert_ne!(
+            test_split(&[1]).fingerprint(),
+            FeeSplit::default().fingerprint()
+        );
+    }
+
+    #[test]
+    // Fee outputs should be checkable by anyone who knows the split.
+    fn test_verify_fee_outputs() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let fee_split = test_split(&[3, 1]);
+
+        let key_images = vec![KeyImage::from(1), KeyImage::from(2)];
+        let recipient = AccountKey::random(&mut rng).default_subaddress();
+        let tx_outputs = vec![TxOut::new(
+            100,
+            &recipient,
+            &RistrettoPrivate::from_random(&mut rng),
+            Default::default(),
+            &mut rng,
+        )
+        .unwrap()];
+
+        let mut outputs = tx_outputs.clone();
+        outputs.extend(fee_split.fee_outputs(&key_images, &tx_outputs, 40).unwrap());
+        let block_contents = BlockContents::new(key_images.clone(), outputs);
+        assert_eq!(fee_split.verify_fee_outputs(&block_contents), Ok(40));
+
+        // Another split does not verify.
+        assert!(test_split(&[1, 3])
+            .verify_fee_outputs(&block_contents)
+            .is_err());
+        assert!(FeeSplit::default()
+            .verify_fee_outputs(&block_contents)
+            .is_err());
+
+        // Fee outputs derived from other contents do not verify.
+        let mut outputs = tx_outputs.clone();
+        outputs.extend(
+            fee_split
+                .fee_outputs(&key_images[..1], &tx_outputs, 40)
+                .unwrap(),
+        );
+        let block_contents = BlockContents::new(key_images.clone(), outputs);
+        assert_eq!(
+            fee_split.verify_fee_outputs(&block_contents),
+            Err(FeeSplitError::InvalidFeeOutput(0))
+        );
+
+        // Fee outputs split according to other weights do not verify.
+        let mut outputs = tx_outputs.clone();
+        outputs.extend(
+            FeeSplit::new(
+                fee_split
+                    .recipients()
+                    .iter()
+                    .map(|recipient| FeeRecipient::new(recipient.address.clone(), 1))
+                    .collect(),
+            )
+            .unwrap()
+            .fee_outputs(&key_images, &tx_outputs, 40)
+            .unwrap(),
+        );
+        let block_contents = BlockContents::new(key_images.clone(), outputs);
+        assert_eq!(
+            fee_split.verify_fee_outputs(&block_contents),
+            Err(FeeSplitError::UnevenFeeOutputs)
+        );
+
+        // A block without enough outputs does not verify.
+        let block_contents = BlockContents::new(key_images, vec![]);
+        assert_eq!(
+            fee_split.verify_fee_outputs(&block_contents),
+            Err(FeeSplitError::MissingFeeOutputs)
+        );
+    }
+}
@@ -23,15 +23,19 @@ mod blockchain;
 mod commitment;
 mod compressed_commitment;
 pub mod constants;
+pub mod dust;
 pub mod encrypted_fog_hint;
+pub mod fee_split;
 pub mod fog_hint;
 pub mod membership_proofs;
+pub mod mob;
 pub mod onetime_keys;
 pub mod range;
 pub mod range_proofs;
 mod redacted_tx;
 pub mod ring_signature;
 pub mod tx;
+pub mod tx_out_confirmation_number;
 pub mod validation;
 pub mod view_key;
 
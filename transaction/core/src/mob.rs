@@ -0,0 +1,244 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Values of MobileCoin, with checked arithmetic.
+//!
+//! Values are counted in picoMOB, the smallest unit of MobileCoin. Keeping them in a `PicoMob`
+//! rather than a bare `u64` keeps MOB and picoMOB values from being mixed up, and turns overflow
+//! into an error instead of a wrap-around or a panic.
+
+use core::{fmt, iter::Sum, str::FromStr};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+
+/// The number of picoMOB in one MOB.
+pub const PICOMOB_PER_MOB: u64 = 1_000_000_000_000;
+
+/// The number of decimal places of a value written in MOB.
+const MOB_DECIMAL_PLACES: usize = 12;
+
+/// A value of MobileCoin, in picoMOB.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
+pub struct PicoMob(u64);
+
+impl PicoMob {
+    /// No MobileCoin.
+    pub const ZERO: PicoMob = PicoMob(0);
+
+    /// A value of `picomob` picoMOB.
+    pub const fn new(picomob: u64) -> Self {
+        PicoMob(picomob)
+    }
+
+    /// A value of `mob` whole MOB, or `None` if that does not fit in a u64 of picoMOB.
+    pub fn from_mob(mob: u64) -> Option<Self> {
+        mob.checked_mul(PICOMOB_PER_MOB).map(PicoMob)
+    }
+
+    /// The value in picoMOB.
+    pub const fn picomob(self) -> u64 {
+        self.0
+    }
+
+    /// Returns `self + other`, or `None` on overflow.
+    pub fn checked_add(self, other: PicoMob) -> Option<Self> {
+        self.0.checked_add(other.0).map(PicoMob)
+    }
+
+    /// Returns `self - other`, or `None` if `other` is larger.
+    pub fn checked_sub(self, other: PicoMob) -> Option<Self> {
+        self.0.checked_sub(other.0).map(PicoMob)
+    }
+
+    /// Returns `self * factor`, or `None` on overflow.
+    pub fn checked_mul(self, factor: u64) -> Option<Self> {
+        self.0.checked_mul(factor).map(PicoMob)
+    }
+
+    /// Sums up values, or returns `None` on overflow.
+    pub fn checked_sum<I: IntoIterator<Item = PicoMob>>(values: I) -> Option<Self> {
+        values
+            .into_iter()
+            .try_fold(PicoMob::ZERO, PicoMob::checked_add)
+    }
+}
+
+impl From<u64> for PicoMob {
+    fn from(picomob: u64) -> Self {
+        PicoMob(picomob)
+    }
+}
+
+impl From<PicoMob> for u64 {
+    fn from(value: PicoMob) -> Self {
+        value.0
+    }
+}
+
+/// Summing panics on overflow, like summing integers does in debug builds. Use
+/// `PicoMob::checked_sum` when the values are not trusted.
+impl Sum for PicoMob {
+    fn sum<I: Iterator<Item = PicoMob>>(iter: I) -> Self {
+        PicoMob::checked_sum(iter).expect("PicoMob sum overflowed")
+    }
+}
+
+/// Writes the value in MOB, without trailing zeros, e.g. "1.5 MOB".
+impl fmt::Display for PicoMob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let whole = self.0 / PICOMOB_PER_MOB;
+        let mut fraction = self.0 % PICOMOB_PER_MOB;
+        if fraction == 0 {
+            return write!(f, "{} MOB", whole);
+        }
+
+        let mut decimal_places = MOB_DECIMAL_PLACES;
+        while fraction % 10 == 0 {
+            fraction /= 10;
+            decimal_places -= 1;
+        }
+        write!(
+            f,
+            "{}.{:0width$} MOB",
+            whole,
+            fraction,
+            width = decimal_places
+        )
+    }
+}
+
+/// An error returned when parsing a `PicoMob` fails.
+#[derive(Clone, Copy, Debug, Eq, Fail, Hash, PartialEq)]
+pub enum ParseMobError {
+    /// The text is not a number followed by a unit.
+    #[fail(display = "Expected a value such as \"1.5 MOB\" or \"1500 pMOB\"")]
+    Invalid,
+
+    /// A MOB value has more decimal places than there are picoMOB in a MOB.
+    #[fail(display = "MOB values have at most 12 decimal places")]
+    TooPrecise,
+
+    /// The value does not fit in a u64 of picoMOB.
+    #[fail(display = "Value is too large")]
+    Overflow,
+}
+
+/// Parses "<decimal> MOB" or "<integer> pMOB". A bare integer is read as picoMOB, which is how
+/// values have always been passed around. A decimal without a unit is rejected as ambiguous.
+impl FromStr for PicoMob {
+    type Err = ParseMobError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let src = src.trim();
+
+        if let Some(picomob) = strip_unit(src, "pMOB") {
+            return parse_digits(picomob).map(PicoMob);
+        }
+
+        let mob = match strip_unit(src, "MOB") {
+            Some(mob) => mob,
+            None => return parse_digits(src).map(PicoMob),
+        };
+
+        let (whole, fraction) = match mob.find('.') {
+            Some(dot) => (&mob[..dot], &mob[dot + 1..]),
+            None => (mob, ""),
+        };
+        if fraction.len() > MOB_DECIMAL_PLACES {
+            return Err(ParseMobError::TooPrecise);
+        }
+
+        let whole = PicoMob::from_mob(parse_digits(whole)?).ok_or(ParseMobError::Overflow)?;
+        let fraction = if fraction.is_empty() {
+            PicoMob::ZERO
+        } else {
+            // The fraction has at most 12 digits, so this cannot overflow.
+            PicoMob(
+                parse_digits(fraction)? * 10u64.pow((MOB_DECIMAL_PLACES - fraction.len()) as u32),
+            )
+        };
+        whole.checked_add(fraction).ok_or(ParseMobError::Overflow)
+    }
+}
+
+/// Returns the number preceding `unit`, if `src` ends with it.
+fn strip_unit<'a>(src: &'a str, unit: &str) -> Option<&'a str> {
+    if src.ends_with(unit) {
+        Some(src[..src.len() - unit.len()].trim_end())
+    } else {
+        None
+    }
+}
+
+/// Parses a non-empty string of decimal digits. Unlike `u64::from_str`, signs are rejected.
+fn parse_digits(src: &str) -> Result<u64, ParseMobError> {
+    if src.is_empty() || !src.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(ParseMobError::Invalid);
+    }
+    src.parse().map_err(|_| ParseMobError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(PicoMob::ZERO.to_string(), "0 MOB");
+        assert_eq!(PicoMob::new(1).to_string(), "0.000000000001 MOB");
+        assert_eq!(PicoMob::new(1_500_000_000_000).to_string(), "1.5 MOB");
+        assert_eq!(PicoMob::from_mob(250).unwrap().to_string(), "250 MOB");
+        assert_eq!(
+            PicoMob::new(u64::max_value()).to_string(),
+            "18446744.073709551615 MOB"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("1.5 MOB".parse(), Ok(PicoMob::new(1_500_000_000_000)));
+        assert_eq!("1.5MOB".parse(), Ok(PicoMob::new(1_500_000_000_000)));
+        assert_eq!(" 2 MOB ".parse(), Ok(PicoMob::from_mob(2).unwrap()));
+        assert_eq!("0.000000000001 MOB".parse(), Ok(PicoMob::new(1)));
+        assert_eq!("1500 pMOB".parse(), Ok(PicoMob::new(1500)));
+        assert_eq!("1500".parse(), Ok(PicoMob::new(1500)));
+
+        assert_eq!("1.5".parse::<PicoMob>(), Err(ParseMobError::Invalid));
+        assert_eq!("-1 MOB".parse::<PicoMob>(), Err(ParseMobError::Invalid));
+        assert_eq!("1.5 pMOB".parse::<PicoMob>(), Err(ParseMobError::Invalid));
+        assert_eq!(". MOB".parse::<PicoMob>(), Err(ParseMobError::Invalid));
+        assert_eq!(
+            "0.0000000000001 MOB".parse::<PicoMob>(),
+            Err(ParseMobError::TooPrecise)
+        );
+        assert_eq!(
+            "18446745 MOB".parse::<PicoMob>(),
+            Err(ParseMobError::Overflow)
+        );
+
+        // Displayed values parse back to themselves.
+        for value in &[0, 1, 1_500_000_000_000, u64::max_value()] {
+            let value = PicoMob::new(*value);
+            assert_eq!(value.to_string().parse(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let max = PicoMob::new(u64::max_value());
+        assert_eq!(
+            PicoMob::new(1).checked_add(PicoMob::new(2)),
+            Some(PicoMob::new(3))
+        );
+        assert_eq!(max.checked_add(PicoMob::new(1)), None);
+        assert_eq!(PicoMob::new(1).checked_sub(PicoMob::new(2)), None);
+        assert_eq!(PicoMob::from_mob(u64::max_value()), None);
+        assert_eq!(
+            PicoMob::checked_sum(vec![PicoMob::new(1), PicoMob::new(2)]),
+            Some(PicoMob::new(3))
+        );
+        assert_eq!(PicoMob::checked_sum(vec![max, PicoMob::new(1)]), None);
+    }
+}
@@ -83,6 +83,81 @@ impl SignatureRctBulletproofs {
         )
     }
 
+    /// Blindings for the pseudo-outputs of a signature. All but the last are random.
+    ///
+    /// Constructing blindings in this way ensures that sum_of_outputs - sum_of_pseudo_outputs = 0
+    /// if the sum of outputs and the sum of pseudo_outputs have equal value.
+    ///
+    /// # Arguments
+    /// * `num_inputs` - The number of inputs, i.e. of pseudo-outputs.
+    /// * `output_values_and_blindings` - Value and blinding for each output amount commitment.
+    pub fn pseudo_output_blindings<CSPRNG: RngCore + CryptoRng>(
+        num_inputs: usize,
+        output_values_and_blindings: &[(u64, Scalar)],
+        rng: &mut CSPRNG,
+    ) -> Vec<Scalar> {
+        if num_inputs == 0 {
+            return Vec::new();
+        }
+
+        let mut pseudo_output_blindings: Vec<Scalar> = Vec::new();
+        for _i in 0..num_inputs - 1 {
+            pseudo_output_blindings.push(Scalar::random(rng));
+        }
+        let sum_of_output_blindings: Scalar = output_values_and_blindings
+            .iter()
+            .map(|(_, blinding)| blinding)
+            .sum();
+
+        let sum_of_pseudo_output_blindings: Scalar = pseudo_output_blindings.iter().sum();
+        let last_blinding: Scalar = sum_of_output_blindings - sum_of_pseudo_output_blindings;
+        pseudo_output_blindings.push(last_blinding);
+        pseudo_output_blindings
+    }
+
+    /// Create a signature from ring signatures computed elsewhere, e.g. by a hardware wallet
+    /// holding the one-time private keys of the inputs.
+    ///
+    /// # Arguments
+    /// * `ring_signatures` - Signature for each input ring. Each must have been signed with the
+    ///   input's pseudo-output blinding as the output blinding.
+    /// * `input_values` - Value of each real input.
+    /// * `pseudo_output_blindings` - Blinding of each pseudo-output, from `pseudo_output_blindings`.
+    /// * `output_values_and_blindings` - Value and blinding for each output amount commitment.
+    /// * `rng` - Randomness.
+    pub fn from_ring_signatures<CSPRNG: RngCore + CryptoRng>(
+        ring_signatures: Vec<RingMLSAG>,
+        input_values: &[u64],
+        pseudo_output_blindings: &[Scalar],
+        output_values_and_blindings: &[(u64, Scalar)],
+        rng: &mut CSPRNG,
+    ) -> Result<Self, Error> {
+        if ring_signatures.is_empty() {
+            return Err(Error::NoInputs);
+        }
+        if input_values.len() != ring_signatures.len() {
+            return Err(Error::LengthMismatch(
+                ring_signatures.len(),
+                input_values.len(),
+            ));
+        }
+        if pseudo_output_blindings.len() != ring_signatures.len() {
+            return Err(Error::LengthMismatch(
+                ring_signatures.len(),
+                pseudo_output_blindings.len(),
+            ));
+        }
+
+        assemble_signature(
+            ring_signatures,
+            input_values,
+            pseudo_output_blindings,
+            output_values_and_blindings,
+            true,
+            rng,
+        )
+    }
+
     /// Verify.
     ///
     /// # Arguments
@@ -241,21 +316,11 @@ fn sign_with_balance_check<CSPRNG: RngCore + CryptoRng>(
         }
     }
 
-    // Blindings for pseudo_outputs. All but the last are random.
-    // Constructing blindings in this way ensures that sum_of_outputs - sum_of_pseudo_outputs = 0
-    // if the sum of outputs and the sum of pseudo_outputs have equal value.
-    let mut pseudo_output_blindings: Vec<Scalar> = Vec::new();
-    for _i in 0..num_inputs - 1 {
-        pseudo_output_blindings.push(Scalar::random(rng));
-    }
-    let sum_of_output_blindings: Scalar = output_values_and_blindings
-        .iter()
-        .map(|(_, blinding)| blinding)
-        .sum();
-
-    let sum_of_pseudo_output_blindings: Scalar = pseudo_output_blindings.iter().sum();
-    let last_blinding: Scalar = sum_of_output_blindings - sum_of_pseudo_output_blindings;
-    pseudo_output_blindings.push(last_blinding);
+    let pseudo_output_blindings = SignatureRctBulletproofs::pseudo_output_blindings(
+        num_inputs,
+        output_values_and_blindings,
+        rng,
+    );
 
     // Prove that the signer is allowed to spend a public key in each ring, and that
     // the input's value equals the value of the pseudo_output.
@@ -276,11 +341,33 @@ fn sign_with_balance_check<CSPRNG: RngCore + CryptoRng>(
         ring_signatures.push(ring_signature);
     }
 
+    let input_values: Vec<u64> = input_secrets.iter().map(|(_, value, _)| *value).collect();
+    assemble_signature(
+        ring_signatures,
+        &input_values,
+        &pseudo_output_blindings,
+        output_values_and_blindings,
+        check_value_is_preserved,
+        rng,
+    )
+}
+
+/// Create range proofs and pseudo-output commitments to go with the ring signatures of each input.
+fn assemble_signature<CSPRNG: RngCore + CryptoRng>(
+    ring_signatures: Vec<RingMLSAG>,
+    input_values: &[u64],
+    pseudo_output_blindings: &[Scalar],
+    output_values_and_blindings: &[(u64, Scalar)],
+    check_value_is_preserved: bool,
+    rng: &mut CSPRNG,
+) -> Result<SignatureRctBulletproofs, Error> {
+    let num_inputs = ring_signatures.len();
+
     // Create Range proofs for outputs and pseudo-outputs.
-    let pseudo_output_values_and_blindings: Vec<(u64, Scalar)> = input_secrets
+    let pseudo_output_values_and_blindings: Vec<(u64, Scalar)> = input_values
         .iter()
         .zip(pseudo_output_blindings.iter())
-        .map(|((_, value, _), blinding)| (*value, *blinding))
+        .map(|(value, blinding)| (*value, *blinding))
         .collect();
 
     let (range_proof, commitments) = {
@@ -338,7 +425,9 @@ mod rct_bulletproofs_tests {
     use crate::{
         proptest_fixtures::*,
         range_proofs::generate_range_proofs,
-        ring_signature::{Blinding, Error, KeyImage, SignatureRctBulletproofs, GENERATORS},
+        ring_signature::{
+            Blinding, Error, KeyImage, RingMLSAG, SignatureRctBulletproofs, GENERATORS,
+        },
     };
 
     use super::sign_with_balance_check;
@@ -547,6 +636,71 @@ mod rct_bulletproofs_tests {
             assert!(result.is_ok());
         }
 
+        #[test]
+        // A signature assembled from ring signatures computed elsewhere should be valid.
+        fn verify_accepts_signatures_from_ring_signatures(
+            num_inputs in 1..8usize,
+            num_mixins in 1..17usize,
+            seed in any::<[u8; 32]>(),
+        ) {
+            let mut rng: StdRng = SeedableRng::from_seed(seed);
+            let params = SignatureParams::random(num_inputs, num_mixins, &mut rng);
+
+            let pseudo_output_blindings = SignatureRctBulletproofs::pseudo_output_blindings(
+                num_inputs,
+                &params.output_values_and_blindings,
+                &mut rng,
+            );
+            let ring_signatures: Vec<RingMLSAG> = (0..num_inputs)
+                .map(|i| {
+                    let (onetime_private_key, value, blinding) = params.input_secrets[i];
+                    RingMLSAG::sign(
+                        &params.message,
+                        &params.rings[i],
+                        params.real_input_indices[i],
+                        &onetime_private_key,
+                        value,
+                        &blinding,
+                        &pseudo_output_blindings[i],
+                        &mut rng,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let input_values: Vec<u64> = params
+                .input_secrets
+                .iter()
+                .map(|(_, value, _)| *value)
+                .collect();
+
+            // The number of ring signatures and inputs must match.
+            assert!(SignatureRctBulletproofs::from_ring_signatures(
+                ring_signatures.clone(),
+                &input_values[1..],
+                &pseudo_output_blindings,
+                &params.output_values_and_blindings,
+                &mut rng,
+            )
+            .is_err());
+
+            let signature = SignatureRctBulletproofs::from_ring_signatures(
+                ring_signatures,
+                &input_values,
+                &pseudo_output_blindings,
+                &params.output_values_and_blindings,
+                &mut rng,
+            )
+            .unwrap();
+
+            let result = signature.verify(
+                &params.message,
+                &params.rings,
+                &params.get_output_commitments(),
+                &mut rng,
+            );
+            assert!(result.is_ok());
+        }
+
         #[test]
         // `verify` should reject a signature that contains an invalid MLSAG signature.
         fn test_verify_rejects_signature_signed_with_invalid_mlsag(
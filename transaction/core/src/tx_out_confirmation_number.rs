@@ -0,0 +1,116 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Confirmation numbers prove that a TxOut was created by whoever presents them.
+//!
+//! The confirmation number of a TxOut is a hash of the shared secret between the sender and the
+//! recipient. Only the sender, who knows the transaction private key, can compute it before the
+//! TxOut lands in the ledger. The recipient can recompute it with their view private key, and so
+//! check a sender's claim that they paid them.
+
+use crate::{blake2b_256::Blake2b256, onetime_keys::compute_shared_secret, ConvertError};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use digest::Input;
+use keys::{RistrettoPrivate, RistrettoPublic};
+use mcserial::ReprBytes32;
+use serde::{Deserialize, Serialize};
+
+/// Domain separator for hashing a shared secret into a confirmation number.
+const CONFIRMATION_NUMBER_DOMAIN_TAG: &[u8] = b"mc_tx_out_confirmation_number";
+
+/// A hash of the shared secret of a TxOut.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct TxOutConfirmationNumber([u8; 32]);
+
+impl TxOutConfirmationNumber {
+    /// Copies the confirmation number into a vector.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Checks that this is the confirmation number of a TxOut sent to the owner of a view key.
+    ///
+    /// # Arguments
+    /// * `tx_public_key` - The public key of the TxOut.
+    /// * `view_private_key` - The recipient's view private key.
+    pub fn validate(
+        &self,
+        tx_public_key: &RistrettoPublic,
+        view_private_key: &RistrettoPrivate,
+    ) -> bool {
+        let shared_secret = compute_shared_secret(tx_public_key, view_private_key);
+        Self::from(&shared_secret) == *self
+    }
+}
+
+impl From<&RistrettoPublic> for TxOutConfirmationNumber {
+    fn from(shared_secret: &RistrettoPublic) -> Self {
+        let mut hasher = Blake2b256::new();
+        hasher.input(CONFIRMATION_NUMBER_DOMAIN_TAG);
+        hasher.input(&shared_secret.to_bytes());
+
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hasher.result());
+        Self(bytes)
+    }
+}
+
+impl From<[u8; 32]> for TxOutConfirmationNumber {
+    fn from(src: [u8; 32]) -> Self {
+        Self(src)
+    }
+}
+
+impl TryFrom<&[u8]> for TxOutConfirmationNumber {
+    type Error = ConvertError;
+
+    fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
+        if src.len() != 32 {
+            return Err(ConvertError::LengthMismatch(32, src.len()));
+        }
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(src);
+        Ok(Self(bytes))
+    }
+}
+
+impl AsRef<[u8]> for TxOutConfirmationNumber {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tx_out_confirmation_number_tests {
+    use super::*;
+    use crate::{account_keys::AccountKey, onetime_keys::compute_tx_pubkey};
+    use keys::FromRandom;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    // The recipient should be able to validate the confirmation number computed by the sender.
+    fn test_validate() {
+        let mut rng: StdRng = SeedableRng::from_seed([3u8; 32]);
+        let recipient = AccountKey::random(&mut rng);
+        let other = AccountKey::random(&mut rng);
+
+        let recipient_address = recipient.default_subaddress();
+        let tx_private_key = RistrettoPrivate::from_random(&mut rng);
+        let tx_public_key =
+            compute_tx_pubkey(&tx_private_key, recipient_address.spend_public_key());
+        let shared_secret =
+            compute_shared_secret(recipient_address.view_public_key(), &tx_private_key);
+        let confirmation_number = TxOutConfirmationNumber::from(&shared_secret);
+
+        assert!(confirmation_number.validate(&tx_public_key, recipient.view_private_key()));
+        assert!(!confirmation_number.validate(&tx_public_key, other.view_private_key()));
+        assert!(!TxOutConfirmationNumber::default()
+            .validate(&tx_public_key, recipient.view_private_key()));
+
+        assert_eq!(
+            TxOutConfirmationNumber::try_from(&confirmation_number.to_vec()[..]).unwrap(),
+            confirmation_number
+        );
+        assert!(TxOutConfirmationNumber::try_from(&[0u8; 31][..]).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Generates large ledgers with realistic shapes, for performance tests of scanning, input
+//! selection and validation.
+//!
+//! Every output is paid to the default subaddress of one of a set of generated accounts, whose
+//! keys are returned along with the blocks, so that tests can scan the ledger with them. Spent
+//! outputs appear in later blocks by their real key images, so that wallets see them as spent.
+//! Blocks are not made of valid transactions: their outputs are minted, and the values of the
+//! outputs a block spends are not carried over to its outputs.
+//!
+//! The ledger only depends on the configuration, including its seed, so that performance numbers
+//! of different runs are comparable.
+
+use crate::{AccountKey, Block, BlockIndex, TxOut, DEFAULT_SUBADDRESS_INDEX};
+use core::convert::TryFrom;
+use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
+use ledger_db::Ledger;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use transaction::{
+    constants::RING_SIZE,
+    get_tx_out_shared_secret,
+    onetime_keys::{compute_key_image, recover_onetime_private_key},
+    ring_signature::KeyImage,
+    BlockContents, BLOCK_VERSION,
+};
+
+/// How a generated quantity is distributed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    /// Always the same value.
+    Constant(u64),
+
+    /// Uniform between `min` and `max`, both included.
+    Uniform { min: u64, max: u64 },
+
+    /// Uniform in orders of magnitude between `min` and `max`, so that small values are as likely
+    /// as large ones, like the amounts of real payments. `min` must be at least 1.
+    LogUniform { min: u64, max: u64 },
+
+    /// Geometric with the given mean, starting at 0, so that small values are the most likely
+    /// but large ones occur, like the number of payments in a block.
+    Geometric { mean: f64 },
+}
+
+impl Distribution {
+    /// Draws a value.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u64 {
+        match *self {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } => {
+                assert!(min <= max, "min must not exceed max");
+                rng.gen_range(min, max.saturating_add(1))
+            }
+            Distribution::LogUniform { min, max } => {
+                assert!(
+                    0 < min && min <= max,
+                    "min must be positive and not exceed max"
+                );
+                if min == max {
+                    return min;
+                }
+                let exponent = rng.gen_range((min as f64).ln(), (max as f64).ln());
+                (exponent.exp().round() as u64).max(min).min(max)
+            }
+            Distribution::Geometric { mean } => {
+                assert!(mean >= 0.0, "mean must not be negative");
+                if mean == 0.0 {
+                    return 0;
+                }
+                // Number of failures before the first success, with success probability p.
+                let p = 1.0 / (mean + 1.0);
+                let u: f64 = rng.gen_range(f64::EPSILON, 1.0);
+                (u.ln() / (1.0 - p).ln()).floor() as u64
+            }
+        }
+    }
+}
+
+/// Which unspent outputs a block spends.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpendPattern {
+    /// Outputs picked uniformly at random.
+    Random,
+
+    /// The oldest outputs first, like a wallet consolidating its dust.
+    OldestFirst,
+
+    /// The newest outputs first, like a hot wallet spending what it just received.
+    NewestFirst,
+}
+
+/// The shape of a generated ledger.
+#[derive(Clone, Debug)]
+pub struct LedgerGeneratorConfig {
+    /// The number of blocks, including the origin block.
+    pub num_blocks: u64,
+
+    /// The number of accounts the outputs are paid to.
+    pub num_accounts: usize,
+
+    /// The number of outputs of each block after the origin block. Blocks have at least one.
+    pub outputs_per_block: Distribution,
+
+    /// The value of each output.
+    pub output_value: Distribution,
+
+    /// The number of outputs each block after the origin block spends. Blocks spend at most the
+    /// outputs that are unspent.
+    pub spends_per_block: Distribution,
+
+    /// Which outputs blocks spend.
+    pub spend_pattern: SpendPattern,
+
+    /// Seeds everything that is generated, including the accounts.
+    pub seed: [u8; 32],
+}
+
+impl Default for LedgerGeneratorConfig {
+    fn default() -> Self {
+        Self {
+            num_blocks: 1000,
+            num_accounts: 10,
+            outputs_per_block: Distribution::Geometric { mean: 4.0 },
+            output_value: Distribution::LogUniform {
+                min: 1_000,
+                max: 1_000_000_000_000,
+            },
+            spends_per_block: Distribution::Geometric { mean: 3.0 },
+            spend_pattern: SpendPattern::Random,
+            seed: [0u8; 32],
+        }
+    }
+}
+
+/// An output of a generated ledger.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GeneratedTxOut {
+    /// The index of the account the output was paid to.
+    pub account_index: usize,
+
+    /// The block the output appears in.
+    pub block_index: BlockIndex,
+
+    pub tx_out: TxOut,
+
+    pub value: u64,
+
+    pub key_image: KeyImage,
+
+    /// The block that spends the output, if any.
+    pub spent_at: Option<BlockIndex>,
+}
+
+/// A generated ledger.
+#[derive(Clone, Debug)]
+pub struct GeneratedLedger {
+    /// The accounts the outputs are paid to.
+    pub account_keys: Vec<AccountKey>,
+
+    /// The blocks, starting with the origin block.
+    pub blocks: Vec<(Block, BlockContents)>,
+
+    /// Every output, in ledger order.
+    pub tx_outs: Vec<GeneratedTxOut>,
+}
+
+impl GeneratedLedger {
+    /// The value of the outputs of an account that are unspent at the end of the ledger.
+    pub fn unspent_value(&self, account_index: usize) -> u64 {
+        self.tx_outs
+            .iter()
+            .filter(|tx_out| tx_out.account_index == account_index && tx_out.spent_at.is_none())
+            .map(|tx_out| tx_out.value)
+            .sum()
+    }
+}
+
+/// Generates a ledger. The origin block holds `RING_SIZE` outputs, so that the ledger has enough
+/// outputs to use as mixins from the start.
+pub fn generate_ledger_blocks(config: &LedgerGeneratorConfig) -> GeneratedLedger {
+    assert!(config.num_blocks > 0, "num_blocks must be positive");
+    assert!(config.num_accounts > 0, "num_accounts must be positive");
+
+    let mut rng: StdRng = SeedableRng::from_seed(config.seed);
+    let account_keys: Vec<AccountKey> = (0..config.num_accounts)
+        .map(|_| AccountKey::random(&mut rng))
+        .collect();
+
+    let mut blocks: Vec<(Block, BlockContents)> = Vec::new();
+    let mut tx_outs: Vec<GeneratedTxOut> = Vec::new();
+    // Indexes into `tx_outs` of the outputs that are unspent, in ledger order.
+    let mut unspent: Vec<usize> = Vec::new();
+
+    for block_index in 0..config.num_blocks {
+        // Spend some of the outputs of earlier blocks.
+        let mut key_images = Vec::new();
+        if block_index > 0 {
+            let num_spends = (config.spends_per_block.sample(&mut rng) as usize).min(unspent.len());
+            for _ in 0..num_spends {
+                let position = match config.spend_pattern {
+                    SpendPattern::Random => rng.gen_range(0, unspent.len()),
+                    SpendPattern::OldestFirst => 0,
+                    SpendPattern::NewestFirst => unspent.len() - 1,
+                };
+                let spent = &mut tx_outs[unspent.remove(position)];
+                spent.spent_at = Some(block_index);
+                key_images.push(spent.key_image);
+            }
+        }
+
+        let num_outputs = if block_index == 0 {
+            RING_SIZE
+        } else {
+            (config.outputs_per_block.sample(&mut rng) as usize).max(1)
+        };
+        let mut outputs = Vec::with_capacity(num_outputs);
+        for _ in 0..num_outputs {
+            let account_index = rng.gen_range(0, account_keys.len());
+            let account_key = &account_keys[account_index];
+            let value = config.output_value.sample(&mut rng);
+            let tx_out = TxOut::new(
+                value,
+                &account_key.default_subaddress(),
+                &RistrettoPrivate::from_random(&mut rng),
+                Default::default(),
+                &mut rng,
+            )
+            .unwrap();
+
+            unspent.push(tx_outs.len());
+            tx_outs.push(GeneratedTxOut {
+                account_index,
+                block_index,
+                key_image: key_image(account_key, &tx_out),
+                tx_out: tx_out.clone(),
+                value,
+                spent_at: None,
+            });
+            outputs.push(tx_out);
+        }
+
+        let block_contents = BlockContents::new(key_images, outputs);
+        let block = match blocks.last() {
+            None => Block::new_origin_block(&block_contents.outputs),
+            Some((parent, _)) => Block::new(
+                BLOCK_VERSION,
+                &parent.id,
+                block_index,
+                &Default::default(),
+                &block_contents,
+            ),
+        };
+        blocks.push((block, block_contents));
+    }
+
+    GeneratedLedger {
+        account_keys,
+        blocks,
+        tx_outs,
+    }
+}
+
+/// Generates a ledger and appends it to an empty `ledger`.
+pub fn generate_ledger<L: Ledger>(
+    ledger: &mut L,
+    config: &LedgerGeneratorConfig,
+) -> GeneratedLedger {
+    assert_eq!(ledger.num_blocks().unwrap(), 0, "ledger must be empty");
+
+    let generated = generate_ledger_blocks(config);
+    for (block, block_contents) in &generated.blocks {
+        ledger
+            .append_block(block, block_contents, None)
+            .expect("failed appending generated block");
+    }
+    generated
+}
+
+/// The key image of an output paid to the default subaddress of an account.
+fn key_image(account_key: &AccountKey, tx_out: &TxOut) -> KeyImage {
+    let tx_out_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+    let onetime_private_key = recover_onetime_private_key(
+        &tx_out_public_key,
+        account_key.view_private_key(),
+        &account_key.subaddress_spend_key(DEFAULT_SUBADDRESS_INDEX),
+    );
+    compute_key_image(&onetime_private_key)
+}
+
+/// The value of an output paid to an account, as the account sees it.
+pub fn tx_out_value(account_key: &AccountKey, tx_out: &TxOut) -> u64 {
+    let tx_out_public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+    let shared_secret =
+        get_tx_out_shared_secret(account_key.view_private_key(), &tx_out_public_key);
+    let (value, _blinding) = tx_out.amount.get_value(&shared_secret).unwrap();
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::create_ledger;
+
+    fn small_config(spend_pattern: SpendPattern) -> LedgerGeneratorConfig {
+        LedgerGeneratorConfig {
+            num_blocks: 20,
+            num_accounts: 3,
+            spend_pattern,
+            seed: [7u8; 32],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    // The same configuration should generate the same ledger.
+    fn test_generate_ledger_is_deterministic() {
+        let config = small_config(SpendPattern::Random);
+        let first = generate_ledger_blocks(&config);
+        let second = generate_ledger_blocks(&config);
+        assert_eq!(first.blocks, second.blocks);
+        assert_eq!(first.tx_outs, second.tx_outs);
+
+        let other = generate_ledger_blocks(&LedgerGeneratorConfig {
+            seed: [8u8; 32],
+            ..config
+        });
+        assert_ne!(first.blocks, other.blocks);
+    }
+
+    #[test]
+    // Generated ledgers should be valid, and their outputs should be spent as configured.
+    fn test_generate_ledger() {
+        for spend_pattern in &[
+            SpendPattern::Random,
+            SpendPattern::OldestFirst,
+            SpendPattern::NewestFirst,
+        ] {
+            let mut ledger = create_ledger();
+            let generated = generate_ledger(&mut ledger, &small_config(*spend_pattern));
+            assert_eq!(ledger.num_blocks().unwrap(), 20);
+            assert_eq!(generated.blocks[0].1.outputs.len(), RING_SIZE);
+
+            for tx_out in &generated.tx_outs {
+                let account_key = &generated.account_keys[tx_out.account_index];
+                assert_eq!(tx_out_value(account_key, &tx_out.tx_out), tx_out.value);
+
+                match tx_out.spent_at {
+                    Some(spent_at) => {
+                        assert!(spent_at > tx_out.block_index);
+                        assert!(ledger.contains_key_image(&tx_out.key_image).unwrap());
+                        assert!(generated.blocks[spent_at as usize]
+                            .1
+                            .key_images
+                            .contains(&tx_out.key_image));
+                    }
+                    None => assert!(!ledger.contains_key_image(&tx_out.key_image).unwrap()),
+                }
+            }
+            assert!(generated
+                .tx_outs
+                .iter()
+                .any(|tx_out| tx_out.spent_at.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_distributions() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+
+        assert_eq!(Distribution::Constant(5).sample(&mut rng), 5);
+        for _ in 0..1000 {
+            let value = Distribution::Uniform { min: 3, max: 6 }.sample(&mut rng);
+            assert!(3 <= value && value <= 6);
+
+            let value = Distribution::LogUniform {
+                min: 10,
+                max: 10_000,
+            }
+            .sample(&mut rng);
+            assert!(10 <= value && value <= 10_000);
+        }
+        assert_eq!(Distribution::Geometric { mean: 0.0 }.sample(&mut rng), 0);
+
+        let mean = (0..10_000)
+            .map(|_| Distribution::Geometric { mean: 4.0 }.sample(&mut rng))
+            .sum::<u64>() as f64
+            / 10_000.0;
+        assert!(3.5 < mean && mean < 4.5, "mean was {}", mean);
+    }
+}
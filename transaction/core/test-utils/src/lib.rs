@@ -18,6 +18,12 @@ pub use transaction::{
 use transaction::{constants::RING_SIZE, BlockContents};
 use transaction_std::{InputCredentials, TransactionBuilder};
 
+mod ledger_generator;
+pub use ledger_generator::{
+    generate_ledger, generate_ledger_blocks, tx_out_value, Distribution, GeneratedLedger,
+    GeneratedTxOut, LedgerGeneratorConfig, SpendPattern,
+};
+
 /// The amount minted by `initialize_ledger`.
 pub const INITIALIZE_LEDGER_AMOUNT: u64 = 1_000_000;
 
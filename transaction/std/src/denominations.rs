@@ -0,0 +1,103 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Standard output denominations.
+//!
+//! Returning change in a single output of whatever value is left over makes that output easy to
+//! tell apart, and to link to the payment it came from once it is spent. Splitting it into
+//! outputs of a few standard values (1, 2 and 5 times a power of ten picoMOB) instead makes most
+//! outputs of an account look alike.
+
+/// Splits `value` into outputs of standard denominations, largest first. The last output holds
+/// the remainder that is left once `max_outputs` is reached, or that cannot be split off without
+/// creating an output below `minimum_output_value`, and is generally not a standard denomination.
+///
+/// # Arguments
+/// * `value` - The value to split, in picoMOB.
+/// * `max_outputs` - The maximal number of outputs to split the value into.
+/// * `minimum_output_value` - No output is smaller than this, unless `value` itself is.
+pub fn denominate(value: u64, max_outputs: usize, minimum_output_value: u64) -> Vec<u64> {
+    let mut outputs = Vec::new();
+    let mut remaining = value;
+
+    while remaining > 0 && outputs.len() + 1 < max_outputs {
+        let denomination = largest_denomination(remaining);
+        let rest = remaining - denomination;
+        if denomination < minimum_output_value || (rest > 0 && rest < minimum_output_value) {
+            break;
+        }
+        outputs.push(denomination);
+        remaining = rest;
+    }
+
+    if remaining > 0 {
+        outputs.push(remaining);
+    }
+    outputs
+}
+
+/// Returns true if `value` is a standard denomination.
+pub fn is_standard_denomination(value: u64) -> bool {
+    value > 0 && largest_denomination(value) == value
+}
+
+/// The largest standard denomination that is not greater than `value`, which must be non-zero.
+fn largest_denomination(value: u64) -> u64 {
+    let mut power = 1u64;
+    while power <= value / 10 {
+        power *= 10;
+    }
+
+    // Here power <= value < 10 * power.
+    [5, 2, 1]
+        .iter()
+        .filter_map(|multiplier| power.checked_mul(*multiplier))
+        .find(|denomination| *denomination <= value)
+        .unwrap_or(power)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_standard_denomination() {
+        for value in &[
+            1,
+            2,
+            5,
+            10,
+            20,
+            50,
+            1_000_000_000_000,
+            5_000_000_000_000_000_000,
+        ] {
+            assert!(is_standard_denomination(*value), "{}", value);
+        }
+        for value in &[0, 3, 4, 6, 9, 11, 25, 999, u64::max_value()] {
+            assert!(!is_standard_denomination(*value), "{}", value);
+        }
+    }
+
+    #[test]
+    // Values should be split greedily, with the remainder in the last output.
+    fn test_denominate() {
+        assert_eq!(denominate(0, 16, 0), Vec::<u64>::new());
+        assert_eq!(
+            denominate(8765, 16, 0),
+            vec![5000, 2000, 1000, 500, 200, 50, 10, 5]
+        );
+        assert_eq!(denominate(8765, 4, 0), vec![5000, 2000, 1000, 765]);
+        assert_eq!(denominate(8765, 1, 0), vec![8765]);
+
+        // No output may be below the minimum.
+        assert_eq!(denominate(8765, 16, 100), vec![5000, 2000, 1000, 500, 265]);
+        assert_eq!(denominate(1005, 16, 10), vec![1005]);
+        assert_eq!(denominate(5, 16, 10), vec![5]);
+
+        // Values near the top of the range should not overflow.
+        let outputs = denominate(u64::max_value(), 16, 0);
+        assert_eq!(outputs[0], 10_000_000_000_000_000_000);
+        assert_eq!(outputs.len(), 16);
+        assert_eq!(outputs.iter().sum::<u64>(), u64::max_value());
+    }
+}
@@ -1,7 +1,7 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
 use failure::Fail;
-use transaction::{amount::AmountError, ring_signature, ring_signature::Error};
+use transaction::{amount::AmountError, dust::DustError, ring_signature, ring_signature::Error};
 
 #[derive(Debug, Fail)]
 pub enum TxBuilderError {
@@ -29,6 +29,18 @@ pub enum TxBuilderError {
     #[fail(display = "No inputs")]
     NoInputs,
 
+    #[fail(display = "No outputs")]
+    NoOutputs,
+
+    #[fail(
+        display = "Insufficient funds: inputs total {}, but {} is needed",
+        _0, _1
+    )]
+    InsufficientFunds(u64, u64),
+
+    #[fail(display = "Output violates the dust rules: {}", _0)]
+    Dust(DustError),
+
     #[fail(
         display = "When building a transaction, a public key was provided for the recipient's fog server, but their public address does not have a Fog server"
     )]
@@ -41,6 +53,12 @@ pub enum TxBuilderError {
 
     #[fail(display = "Key error: {}", _0)]
     KeyError(keys::KeyError),
+
+    #[fail(display = "An input cannot be signed without its one-time private key")]
+    MissingOnetimePrivateKey,
+
+    #[fail(display = "Unsigned transaction does not match its prefix")]
+    UnsignedTxMismatch,
 }
 
 impl From<mcserial::encode::Error> for TxBuilderError {
@@ -67,6 +85,12 @@ impl From<keys::KeyError> for TxBuilderError {
     }
 }
 
+impl From<DustError> for TxBuilderError {
+    fn from(e: DustError) -> Self {
+        TxBuilderError::Dust(e)
+    }
+}
+
 impl From<ring_signature::Error> for TxBuilderError {
     fn from(_: Error) -> Self {
         TxBuilderError::RingSignatureFailed
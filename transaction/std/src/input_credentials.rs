@@ -47,40 +47,67 @@ impl InputCredentials {
         view_private_key: RistrettoPrivate,
         rng: &mut R,
     ) -> Result<Self, TxBuilderError> {
-        debug_assert_eq!(ring.len(), membership_proofs.len());
-
-        let real_tx_out: TxOut = ring
-            .get(real_index)
-            .cloned()
-            .ok_or(TxBuilderError::NoInputs)?;
-        let real_output_public_key = RistrettoPublic::try_from(&real_tx_out.public_key)?;
-
-        // Randomly shuffle the ring and the corresponding proofs. This ensures that the ordering
-        // of mixins in the transaction will not depend on the user's implementation for obtaining
-        // mixins.
-        let (shuffled_ring, shuffled_membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) = {
-            use rand::seq::SliceRandom;
-            let mut zipped: Vec<_> = ring
-                .into_iter()
-                .zip(membership_proofs.into_iter())
-                .collect();
-            let zipped_as_slice = zipped.as_mut_slice();
-            zipped_as_slice.shuffle(rng);
-            zipped.into_iter().unzip()
-        };
-
-        let shuffled_real_index = shuffled_ring
-            .iter()
-            .position(|tx_out| *tx_out == real_tx_out)
-            .expect("The real tx_out must still exist after shuffling.");
+        let (ring, membership_proofs, real_index, real_output_public_key) =
+            shuffle_ring(ring, membership_proofs, real_index, rng)?;
 
         Ok(InputCredentials {
-            ring: shuffled_ring,
-            membership_proofs: shuffled_membership_proofs,
-            real_index: shuffled_real_index,
+            ring,
+            membership_proofs,
+            real_index,
             onetime_private_key,
             real_output_public_key,
             view_private_key,
         })
     }
 }
+
+/// Randomly shuffles a ring and the corresponding proofs. This ensures that the ordering of mixins
+/// in the transaction will not depend on the user's implementation for obtaining mixins.
+///
+/// Returns the shuffled ring and proofs, the index of the real output in the shuffled ring, and the
+/// public key of the transaction that created the real output.
+pub(crate) fn shuffle_ring<R: Rng + CryptoRng>(
+    ring: Vec<TxOut>,
+    membership_proofs: Vec<TxOutMembershipProof>,
+    real_index: usize,
+    rng: &mut R,
+) -> Result<
+    (
+        Vec<TxOut>,
+        Vec<TxOutMembershipProof>,
+        usize,
+        RistrettoPublic,
+    ),
+    TxBuilderError,
+> {
+    debug_assert_eq!(ring.len(), membership_proofs.len());
+
+    let real_tx_out: TxOut = ring
+        .get(real_index)
+        .cloned()
+        .ok_or(TxBuilderError::NoInputs)?;
+    let real_output_public_key = RistrettoPublic::try_from(&real_tx_out.public_key)?;
+
+    let (shuffled_ring, shuffled_membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) = {
+        use rand::seq::SliceRandom;
+        let mut zipped: Vec<_> = ring
+            .into_iter()
+            .zip(membership_proofs.into_iter())
+            .collect();
+        let zipped_as_slice = zipped.as_mut_slice();
+        zipped_as_slice.shuffle(rng);
+        zipped.into_iter().unzip()
+    };
+
+    let shuffled_real_index = shuffled_ring
+        .iter()
+        .position(|tx_out| *tx_out == real_tx_out)
+        .expect("The real tx_out must still exist after shuffling.");
+
+    Ok((
+        shuffled_ring,
+        shuffled_membership_proofs,
+        shuffled_real_index,
+        real_output_public_key,
+    ))
+}
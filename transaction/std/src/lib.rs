@@ -1,10 +1,13 @@
 // Copyright (c) 2018-2020 MobileCoin Inc.
 
+pub mod denominations;
 mod error;
 pub mod identity;
 mod input_credentials;
+mod payment_builder;
 mod transaction_builder;
 
 pub use error::TxBuilderError;
 pub use input_credentials::InputCredentials;
-pub use transaction_builder::TransactionBuilder;
+pub use payment_builder::{Payment, PaymentBuilder, SpendableTxOut, UnsignedPayment};
+pub use transaction_builder::{InputToSign, TransactionBuilder, UnsignedTx};
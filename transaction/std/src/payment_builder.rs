@@ -0,0 +1,592 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Utility for building a payment from outputs owned by the sender.
+//!
+//! This takes care of everything a client needs on top of `TransactionBuilder`: placing each real
+//! input into its ring, creating the outputs to the recipients, returning change to the sender and
+//! applying the network's dust rules to all of them.
+
+use crate::{
+    denominations::denominate, InputCredentials, TransactionBuilder, TxBuilderError, UnsignedTx,
+};
+use curve25519_dalek::scalar::Scalar;
+use keys::{RistrettoPrivate, RistrettoPublic};
+use rand_core::{CryptoRng, RngCore};
+use std::{collections::HashMap, convert::TryFrom};
+use transaction::{
+    account_keys::{AccountKey, PublicAddress, DEFAULT_SUBADDRESS_INDEX},
+    constants::{BASE_FEE, MAX_OUTPUTS},
+    dust::DustRules,
+    onetime_keys::recover_onetime_private_key,
+    ring_signature::RingMLSAG,
+    tx::{Tx, TxOut, TxOutMembershipProof},
+    tx_out_confirmation_number::TxOutConfirmationNumber,
+};
+
+/// An output owned by the sender, to be spent by a payment.
+#[derive(Clone, Debug)]
+pub struct SpendableTxOut {
+    /// The output.
+    pub tx_out: TxOut,
+
+    /// Proof that the output is in the ledger.
+    pub membership_proof: TxOutMembershipProof,
+
+    /// Index of the sender's subaddress the output was sent to.
+    pub subaddress_index: u64,
+
+    /// Value of the output.
+    pub value: u64,
+}
+
+/// A payment built by `PaymentBuilder`.
+#[derive(Clone, Debug)]
+pub struct Payment {
+    /// The signed transaction.
+    pub tx: Tx,
+
+    /// Index in `tx.prefix.outputs` of the output created for each outlay, in the order the
+    /// outlays were added.
+    pub outlay_tx_out_indices: Vec<usize>,
+
+    /// Confirmation number of the output created for each outlay, in the order the outlays were
+    /// added.
+    pub outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+
+    /// Change that was added to the fee instead of being returned to the sender. This is already
+    /// included in the transaction's fee.
+    pub absorbed_change: u64,
+}
+
+/// A payment built by `PaymentBuilder`, whose inputs are not signed yet.
+#[derive(Clone, Debug)]
+pub struct UnsignedPayment {
+    /// The unsigned transaction.
+    pub unsigned_tx: UnsignedTx,
+
+    /// See `Payment::outlay_tx_out_indices`.
+    pub outlay_tx_out_indices: Vec<usize>,
+
+    /// See `Payment::outlay_confirmation_numbers`.
+    pub outlay_confirmation_numbers: Vec<TxOutConfirmationNumber>,
+
+    /// See `Payment::absorbed_change`.
+    pub absorbed_change: u64,
+}
+
+impl UnsignedPayment {
+    /// Sign the inputs with the sender's keys.
+    pub fn sign<RNG: CryptoRng + RngCore>(self, rng: &mut RNG) -> Result<Payment, TxBuilderError> {
+        let tx = self.unsigned_tx.sign(rng)?;
+        Ok(Payment {
+            tx,
+            outlay_tx_out_indices: self.outlay_tx_out_indices,
+            outlay_confirmation_numbers: self.outlay_confirmation_numbers,
+            absorbed_change: self.absorbed_change,
+        })
+    }
+
+    /// Complete the payment with ring signatures computed elsewhere, see
+    /// `UnsignedTx::sign_with_ring_signatures`.
+    pub fn sign_with_ring_signatures<RNG: CryptoRng + RngCore>(
+        self,
+        ring_signatures: Vec<RingMLSAG>,
+        pseudo_output_blindings: &[Scalar],
+        rng: &mut RNG,
+    ) -> Result<Payment, TxBuilderError> {
+        let tx = self.unsigned_tx.sign_with_ring_signatures(
+            ring_signatures,
+            pseudo_output_blindings,
+            rng,
+        )?;
+        Ok(Payment {
+            tx,
+            outlay_tx_out_indices: self.outlay_tx_out_indices,
+            outlay_confirmation_numbers: self.outlay_confirmation_numbers,
+            absorbed_change: self.absorbed_change,
+        })
+    }
+}
+
+/// Helper utility for building a payment.
+#[derive(Clone, Debug)]
+pub struct PaymentBuilder {
+    inputs: Vec<(SpendableTxOut, Vec<(TxOut, TxOutMembershipProof)>)>,
+    outlays: Vec<(u64, PublicAddress)>,
+    fee: u64,
+    tombstone_block: u64,
+    change_subaddress: u64,
+    change_absorption_threshold: u64,
+    denominate_change: bool,
+    dust_rules: DustRules,
+}
+
+impl PaymentBuilder {
+    /// Initializes a new PaymentBuilder.
+    pub fn new() -> Self {
+        Self {
+            inputs: Vec::new(),
+            outlays: Vec::new(),
+            fee: BASE_FEE,
+            tombstone_block: u64::max_value(),
+            change_subaddress: DEFAULT_SUBADDRESS_INDEX,
+            change_absorption_threshold: 0,
+            denominate_change: false,
+            dust_rules: DustRules::default(),
+        }
+    }
+
+    /// Spend an output owned by the sender.
+    ///
+    /// # Arguments
+    /// * `input` - The output to spend.
+    /// * `mixins` - Other outputs from the ledger, and their membership proofs, to hide the input
+    ///   among. The input replaces one of them unless it is already included.
+    pub fn add_input(&mut self, input: SpendableTxOut, mixins: Vec<(TxOut, TxOutMembershipProof)>) {
+        self.inputs.push((input, mixins));
+    }
+
+    /// Pay a recipient. Returns the index of the outlay.
+    ///
+    /// # Arguments
+    /// * `value` - The value to send, in picoMOB.
+    /// * `receiver` - The recipient's public address.
+    pub fn add_outlay(&mut self, value: u64, receiver: &PublicAddress) -> usize {
+        self.outlays.push((value, receiver.clone()));
+        self.outlays.len() - 1
+    }
+
+    /// Sets the transaction fee.
+    ///
+    /// # Arguments
+    /// * `fee` - Transaction fee, in picoMOB.
+    pub fn set_fee(&mut self, fee: u64) {
+        self.fee = fee;
+    }
+
+    /// Sets the tombstone block.
+    ///
+    /// # Arguments
+    /// * `tombstone_block` - Tombstone block number.
+    pub fn set_tombstone_block(&mut self, tombstone_block: u64) {
+        self.tombstone_block = tombstone_block;
+    }
+
+    /// Sets the subaddress of the sender that change is returned to.
+    pub fn set_change_subaddress(&mut self, change_subaddress: u64) {
+        self.change_subaddress = change_subaddress;
+    }
+
+    /// Change below this value is added to the fee instead of being returned to the sender.
+    /// Zero (the default) always returns change.
+    pub fn set_change_absorption_threshold(&mut self, change_absorption_threshold: u64) {
+        self.change_absorption_threshold = change_absorption_threshold;
+    }
+
+    /// Returns change in outputs of standard denominations rather than in a single output, see
+    /// `denominations::denominate`. Outlays are still paid with a single output each.
+    pub fn set_denominate_change(&mut self, denominate_change: bool) {
+        self.denominate_change = denominate_change;
+    }
+
+    /// Sets the dust rules every output must satisfy.
+    pub fn set_dust_rules(&mut self, dust_rules: DustRules) {
+        self.dust_rules = dust_rules;
+    }
+
+    /// Build and sign the payment.
+    ///
+    /// # Arguments
+    /// * `sender` - The account that owns the inputs.
+    /// * `rng` - Randomness.
+    pub fn build<RNG: CryptoRng + RngCore>(
+        &self,
+        sender: &AccountKey,
+        rng: &mut RNG,
+    ) -> Result<Payment, TxBuilderError> {
+        self.build_unsigned(sender, rng)?.sign(rng)
+    }
+
+    /// Build the payment without signing its inputs.
+    ///
+    /// # Arguments
+    /// * `sender` - The account that owns the inputs.
+    /// * `rng` - Randomness.
+    pub fn build_unsigned<RNG: CryptoRng + RngCore>(
+        &self,
+        sender: &AccountKey,
+        rng: &mut RNG,
+    ) -> Result<UnsignedPayment, TxBuilderError> {
+        self.build_unsigned_impl(
+            sender.view_private_key(),
+            &sender.subaddress(self.change_subaddress),
+            Some(sender),
+            rng,
+        )
+    }
+
+    /// Build the payment without signing its inputs, knowing only the sender's view private key.
+    /// The inputs can then only be signed with ring signatures computed by the holder of the spend
+    /// private key, see `UnsignedPayment::sign_with_ring_signatures`.
+    ///
+    /// # Arguments
+    /// * `view_private_key` - The view private key of the account that owns the inputs.
+    /// * `change_address` - The sender's address to return change to. This takes the place of the
+    ///   change subaddress.
+    /// * `rng` - Randomness.
+    pub fn build_unsigned_from_view_key<RNG: CryptoRng + RngCore>(
+        &self,
+        view_private_key: &RistrettoPrivate,
+        change_address: &PublicAddress,
+        rng: &mut RNG,
+    ) -> Result<UnsignedPayment, TxBuilderError> {
+        self.build_unsigned_impl(view_private_key, change_address, None, rng)
+    }
+
+    /// Build the payment without signing its inputs. The one-time private keys of the inputs are
+    /// only derived when `sender` is given.
+    fn build_unsigned_impl<RNG: CryptoRng + RngCore>(
+        &self,
+        view_private_key: &RistrettoPrivate,
+        change_address: &PublicAddress,
+        sender: Option<&AccountKey>,
+        rng: &mut RNG,
+    ) -> Result<UnsignedPayment, TxBuilderError> {
+        if self.outlays.is_empty() {
+            return Err(TxBuilderError::NoOutputs);
+        }
+
+        let mut tx_builder = TransactionBuilder::new();
+        tx_builder.set_tombstone_block(self.tombstone_block);
+
+        for (input, mixins) in &self.inputs {
+            let (ring, membership_proofs, real_index) = place_in_ring(input, mixins.clone());
+            match sender {
+                Some(sender) => {
+                    let public_key = RistrettoPublic::try_from(&input.tx_out.public_key)?;
+                    let onetime_private_key = recover_onetime_private_key(
+                        &public_key,
+                        sender.view_private_key(),
+                        &sender.subaddress_spend_key(input.subaddress_index),
+                    );
+                    tx_builder.add_input(InputCredentials::new(
+                        ring,
+                        membership_proofs,
+                        real_index,
+                        onetime_private_key,
+                        *view_private_key,
+                        rng,
+                    )?);
+                }
+                None => tx_builder.add_input_without_key(
+                    ring,
+                    membership_proofs,
+                    real_index,
+                    *view_private_key,
+                    rng,
+                )?,
+            }
+        }
+
+        // Add outputs to our destinations.
+        let mut tx_out_to_outlay_index = HashMap::new();
+        let mut outlay_confirmation_numbers = Vec::new();
+        for (outlay_index, (value, receiver)) in self.outlays.iter().enumerate() {
+            self.dust_rules.check_output(*value, false)?;
+            let (tx_out, confirmation_number) =
+                tx_builder.add_output(*value, receiver, None, rng)?;
+            tx_out_to_outlay_index.insert(tx_out, outlay_index);
+            outlay_confirmation_numbers.push(confirmation_number);
+        }
+
+        // Figure out if we have change.
+        let input_value: u64 = self.inputs.iter().map(|(input, _)| input.value).sum();
+        let output_value: u64 = self.outlays.iter().map(|(value, _)| value).sum();
+        let change = input_value
+            .checked_sub(output_value)
+            .and_then(|change| change.checked_sub(self.fee))
+            .ok_or_else(|| {
+                TxBuilderError::InsufficientFunds(
+                    input_value,
+                    output_value.saturating_add(self.fee),
+                )
+            })?;
+
+        // Change below the absorption threshold is folded into the fee rather than creating a
+        // (potentially dust) output.
+        let absorbed_change = if change < self.change_absorption_threshold {
+            change
+        } else {
+            0
+        };
+        tx_builder.set_fee(self.fee + absorbed_change);
+
+        let change = change - absorbed_change;
+        let change_values = if self.denominate_change {
+            let max_change_outputs = (MAX_OUTPUTS as usize)
+                .saturating_sub(self.outlays.len())
+                .max(1);
+            denominate(
+                change,
+                max_change_outputs,
+                self.dust_rules.minimum_output_value,
+            )
+        } else if change > 0 {
+            vec![change]
+        } else {
+            Vec::new()
+        };
+        for value in change_values {
+            self.dust_rules.check_output(value, true)?;
+            tx_builder.add_output(value, change_address, None, rng)?;
+        }
+
+        let unsigned_tx = tx_builder.build_unsigned()?;
+
+        // Outputs may be reordered when the transaction is built, so look up where each outlay ended
+        // up.
+        let mut outlay_tx_out_indices = vec![0; self.outlays.len()];
+        for (tx_out_index, tx_out) in unsigned_tx.prefix.outputs.iter().enumerate() {
+            if let Some(outlay_index) = tx_out_to_outlay_index.get(tx_out) {
+                outlay_tx_out_indices[*outlay_index] = tx_out_index;
+            }
+        }
+
+        Ok(UnsignedPayment {
+            unsigned_tx,
+            outlay_tx_out_indices,
+            outlay_confirmation_numbers,
+            absorbed_change,
+        })
+    }
+}
+
+// This appeases clippy's new_without_default rule.
+impl Default for PaymentBuilder {
+    fn default() -> Self {
+        PaymentBuilder::new()
+    }
+}
+
+/// Places an input into its ring. Returns the ring, the membership proofs of its elements, and the
+/// index of the input in it.
+fn place_in_ring(
+    input: &SpendableTxOut,
+    mixins: Vec<(TxOut, TxOutMembershipProof)>,
+) -> (Vec<TxOut>, Vec<TxOutMembershipProof>, usize) {
+    let (mut ring, mut membership_proofs): (Vec<TxOut>, Vec<TxOutMembershipProof>) =
+        mixins.into_iter().unzip();
+
+    let real_index = match ring.iter().position(|tx_out| *tx_out == input.tx_out) {
+        // The input is already present in the ring. This could happen if ring elements are
+        // sampled randomly from the ledger.
+        Some(position) => position,
+
+        // Otherwise the input replaces the first mixin. This is safe because InputCredentials
+        // shuffles each ring.
+        None => {
+            if ring.is_empty() {
+                ring.push(input.tx_out.clone());
+                membership_proofs.push(input.membership_proof.clone());
+            } else {
+                ring[0] = input.tx_out.clone();
+                membership_proofs[0] = input.membership_proof.clone();
+            }
+            0
+        }
+    };
+
+    (ring, membership_proofs, real_index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use keys::{FromRandom, RistrettoPrivate};
+    use rand::{rngs::StdRng, SeedableRng};
+    use transaction::{
+        dust::{DustError, DustPolicy},
+        encrypted_fog_hint::EncryptedFogHint,
+        get_tx_out_shared_secret,
+        validation::validate_transaction_signature,
+    };
+
+    /// An output of the given value sent to `account`, and some mixins to hide it among.
+    fn spendable_tx_out(
+        account: &AccountKey,
+        value: u64,
+        rng: &mut StdRng,
+    ) -> (SpendableTxOut, Vec<(TxOut, TxOutMembershipProof)>) {
+        let new_tx_out = |receiver: &PublicAddress, rng: &mut StdRng| {
+            TxOut::new(
+                value,
+                receiver,
+                &RistrettoPrivate::from_random(rng),
+                EncryptedFogHint::fake_onetime_hint(rng),
+                rng,
+            )
+            .unwrap()
+        };
+
+        let input = SpendableTxOut {
+            tx_out: new_tx_out(&account.default_subaddress(), rng),
+            membership_proof: TxOutMembershipProof::default(),
+            subaddress_index: DEFAULT_SUBADDRESS_INDEX,
+            value,
+        };
+        let mixins = (0..3)
+            .map(|_| {
+                let receiver = AccountKey::random(rng).default_subaddress();
+                (new_tx_out(&receiver, rng), TxOutMembershipProof::default())
+            })
+            .collect();
+        (input, mixins)
+    }
+
+    /// The value of `tx_out`, if it was sent to `account`.
+    fn value_for(account: &AccountKey, tx_out: &TxOut) -> Option<u64> {
+        let public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+        let shared_secret = get_tx_out_shared_secret(account.view_private_key(), &public_key);
+        tx_out
+            .amount
+            .get_value(&shared_secret)
+            .ok()
+            .map(|(value, _blinding)| value)
+    }
+
+    #[test]
+    // Outlays should be paid, change returned, and each outlay mapped to its output.
+    fn test_payment_with_change() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let carol = AccountKey::random(&mut rng);
+
+        let mut builder = PaymentBuilder::new();
+        for value in &[1000, 2000] {
+            let (input, mixins) = spendable_tx_out(&alice, *value, &mut rng);
+            builder.add_input(input, mixins);
+        }
+        assert_eq!(builder.add_outlay(700, &bob.default_subaddress()), 0);
+        assert_eq!(builder.add_outlay(1200, &carol.default_subaddress()), 1);
+        builder.set_fee(100);
+
+        let payment = builder.build(&alice, &mut rng).unwrap();
+        assert_eq!(payment.tx.prefix.fee, 100);
+        assert_eq!(payment.absorbed_change, 0);
+        assert_eq!(payment.tx.prefix.inputs.len(), 2);
+        assert_eq!(payment.tx.prefix.inputs[0].ring.len(), 3);
+        assert_eq!(payment.tx.prefix.outputs.len(), 3);
+
+        let outputs = &payment.tx.prefix.outputs;
+        assert_eq!(
+            value_for(&bob, &outputs[payment.outlay_tx_out_indices[0]]),
+            Some(700)
+        );
+        assert_eq!(
+            value_for(&carol, &outputs[payment.outlay_tx_out_indices[1]]),
+            Some(1200)
+        );
+        let change: Vec<u64> = outputs
+            .iter()
+            .filter_map(|tx_out| value_for(&alice, tx_out))
+            .collect();
+        assert_eq!(change, vec![1000]);
+
+        // Each recipient should be able to validate the confirmation number of their output.
+        for (outlay_index, recipient) in [&bob, &carol].iter().enumerate() {
+            let tx_out = &outputs[payment.outlay_tx_out_indices[outlay_index]];
+            let public_key = RistrettoPublic::try_from(&tx_out.public_key).unwrap();
+            assert!(payment.outlay_confirmation_numbers[outlay_index]
+                .validate(&public_key, recipient.view_private_key()));
+        }
+
+        assert!(validate_transaction_signature(&payment.tx, &mut rng).is_ok());
+    }
+
+    #[test]
+    // Change below the absorption threshold should be added to the fee.
+    fn test_change_absorption() {
+        let mut rng: StdRng = SeedableRng::from_seed([2u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+
+        let mut builder = PaymentBuilder::new();
+        let (input, mixins) = spendable_tx_out(&alice, 1000, &mut rng);
+        builder.add_input(input, mixins);
+        builder.add_outlay(850, &bob.default_subaddress());
+        builder.set_fee(100);
+        builder.set_change_absorption_threshold(60);
+
+        let payment = builder.build(&alice, &mut rng).unwrap();
+        assert_eq!(payment.absorbed_change, 50);
+        assert_eq!(payment.tx.prefix.fee, 150);
+        assert_eq!(payment.tx.prefix.outputs.len(), 1);
+    }
+
+    #[test]
+    // Denominated change should be split into standard denominations, with the remainder in one
+    // more output.
+    fn test_denominated_change() {
+        let mut rng: StdRng = SeedableRng::from_seed([4u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+
+        let mut builder = PaymentBuilder::new();
+        let (input, mixins) = spendable_tx_out(&alice, 10_000, &mut rng);
+        builder.add_input(input, mixins);
+        builder.add_outlay(1_135, &bob.default_subaddress());
+        builder.set_fee(100);
+        builder.set_denominate_change(true);
+        builder.set_dust_rules(DustRules::new(DustPolicy::Rejected, 20));
+
+        let payment = builder.build(&alice, &mut rng).unwrap();
+        let outputs = &payment.tx.prefix.outputs;
+        assert_eq!(
+            value_for(&bob, &outputs[payment.outlay_tx_out_indices[0]]),
+            Some(1_135)
+        );
+
+        let mut change: Vec<u64> = outputs
+            .iter()
+            .filter_map(|tx_out| value_for(&alice, tx_out))
+            .collect();
+        change.sort();
+        assert_eq!(change, vec![65, 200, 500, 1000, 2000, 5000]);
+        assert_eq!(outputs.len(), 7);
+
+        assert!(validate_transaction_signature(&payment.tx, &mut rng).is_ok());
+    }
+
+    #[test]
+    // Building should fail without outlays, without enough funds, or with dust outputs.
+    fn test_invalid_payments() {
+        let mut rng: StdRng = SeedableRng::from_seed([3u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let (input, mixins) = spendable_tx_out(&alice, 1000, &mut rng);
+
+        let mut builder = PaymentBuilder::new();
+        builder.add_input(input, mixins);
+        builder.set_fee(100);
+        match builder.build(&alice, &mut rng) {
+            Err(TxBuilderError::NoOutputs) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let mut too_much = builder.clone();
+        too_much.add_outlay(950, &bob.default_subaddress());
+        match too_much.build(&alice, &mut rng) {
+            Err(TxBuilderError::InsufficientFunds(1000, 1050)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+
+        let mut dust_change = builder.clone();
+        dust_change.add_outlay(890, &bob.default_subaddress());
+        dust_change.set_dust_rules(DustRules::new(DustPolicy::Rejected, 20));
+        match dust_change.build(&alice, &mut rng) {
+            Err(TxBuilderError::Dust(DustError::BelowMinimum(10, 20))) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
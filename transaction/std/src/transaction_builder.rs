@@ -7,7 +7,7 @@
 use keys::{CompressedRistrettoPublic, FromRandom, RistrettoPrivate, RistrettoPublic};
 use std::collections::HashSet;
 
-use crate::{InputCredentials, TxBuilderError};
+use crate::{input_credentials::shuffle_ring, InputCredentials, TxBuilderError};
 use curve25519_dalek::scalar::Scalar;
 use rand_core::{CryptoRng, RngCore};
 use transaction::{
@@ -16,15 +16,16 @@ use transaction::{
     encrypted_fog_hint::EncryptedFogHint,
     fog_hint::FogHint,
     onetime_keys::compute_shared_secret,
-    ring_signature::SignatureRctBulletproofs,
-    tx::{Tx, TxIn, TxOut, TxPrefix},
+    ring_signature::{RingMLSAG, SignatureRctBulletproofs},
+    tx::{Tx, TxIn, TxOut, TxOutMembershipProof, TxPrefix},
+    tx_out_confirmation_number::TxOutConfirmationNumber,
     CompressedCommitment,
 };
 
 /// Helper utility for building and signing a CryptoNote-style transaction.
 #[derive(Debug)]
 pub struct TransactionBuilder {
-    input_credentials: Vec<InputCredentials>,
+    inputs: Vec<BuilderInput>,
     outputs: Vec<TxOut>,
     output_shared_secrets: Vec<RistrettoPublic>,
     tombstone_block: u64,
@@ -35,7 +36,7 @@ impl TransactionBuilder {
     /// Initializes a new TransactionBuilder.
     pub fn new() -> Self {
         TransactionBuilder {
-            input_credentials: Vec::new(),
+            inputs: Vec::new(),
             outputs: Vec::new(),
             output_shared_secrets: Vec::new(),
             tombstone_block: u64::max_value(),
@@ -48,7 +49,45 @@ impl TransactionBuilder {
     /// # Arguments
     /// * `input_credentials` - Credentials required to construct a ring signature for an input.
     pub fn add_input(&mut self, input_credentials: InputCredentials) {
-        self.input_credentials.push(input_credentials);
+        self.inputs.push(BuilderInput {
+            ring: input_credentials.ring,
+            membership_proofs: input_credentials.membership_proofs,
+            real_index: input_credentials.real_index,
+            real_output_public_key: input_credentials.real_output_public_key,
+            view_private_key: input_credentials.view_private_key,
+            onetime_private_key: Some(input_credentials.onetime_private_key),
+        });
+    }
+
+    /// Add an Input whose one-time private key is not known, e.g. because the owner's spend
+    /// private key is kept offline. The transaction can then only be signed with
+    /// `UnsignedTx::sign_with_ring_signatures`.
+    ///
+    /// # Arguments
+    /// * `ring` - A "ring" of transaction outputs.
+    /// * `membership_proofs` - Proof that each TxOut in `ring` is in the ledger.
+    /// * `real_index` - Index in `ring` of the output being spent.
+    /// * `view_private_key` - The view private key belonging to the owner of the real output.
+    /// * `rng` - Randomness.
+    pub fn add_input_without_key<RNG: CryptoRng + RngCore>(
+        &mut self,
+        ring: Vec<TxOut>,
+        membership_proofs: Vec<TxOutMembershipProof>,
+        real_index: usize,
+        view_private_key: RistrettoPrivate,
+        rng: &mut RNG,
+    ) -> Result<(), TxBuilderError> {
+        let (ring, membership_proofs, real_index, real_output_public_key) =
+            shuffle_ring(ring, membership_proofs, real_index, rng)?;
+        self.inputs.push(BuilderInput {
+            ring,
+            membership_proofs,
+            real_index,
+            real_output_public_key,
+            view_private_key,
+            onetime_private_key: None,
+        });
+        Ok(())
     }
 
     /// Add an output to the transaction.
@@ -59,20 +98,22 @@ impl TransactionBuilder {
     /// * `recipient_fog_ingest_key` - The recipient's fog server's public key
     /// * `rng` - RNG used to generate blinding for commitment
     ///
+    /// Returns the output, and its confirmation number.
     pub fn add_output<RNG: CryptoRng + RngCore>(
         &mut self,
         value: u64,
         recipient: &PublicAddress,
         recipient_fog_ingest_key: Option<&RistrettoPublic>,
         rng: &mut RNG,
-    ) -> Result<TxOut, TxBuilderError> {
+    ) -> Result<(TxOut, TxOutConfirmationNumber), TxBuilderError> {
         let (tx_out, shared_secret) =
             create_output(value, recipient, recipient_fog_ingest_key, rng)?;
+        let confirmation_number = TxOutConfirmationNumber::from(&shared_secret);
 
         self.outputs.push(tx_out.clone());
         self.output_shared_secrets.push(shared_secret);
 
-        Ok(tx_out)
+        Ok((tx_out, confirmation_number))
     }
 
     /// Sets the tombstone block.
@@ -93,63 +134,61 @@ impl TransactionBuilder {
 
     /// Consume the builder and return the transaction.
     pub fn build<RNG: CryptoRng + RngCore>(&mut self, rng: &mut RNG) -> Result<Tx, TxBuilderError> {
-        if self.input_credentials.is_empty() {
+        self.build_unsigned()?.sign(rng)
+    }
+
+    /// Consume the builder and return the transaction, without signing its inputs. This lets
+    /// the ring signatures be computed by someone else, e.g. by a hardware wallet.
+    pub fn build_unsigned(&mut self) -> Result<UnsignedTx, TxBuilderError> {
+        if self.inputs.is_empty() {
             return Err(TxBuilderError::NoInputs);
         }
 
         // All inputs must have rings of the same size.
         {
-            let ring_sizes: HashSet<usize> = self
-                .input_credentials
-                .iter()
-                .map(|input| input.ring.len())
-                .collect();
+            let ring_sizes: HashSet<usize> =
+                self.inputs.iter().map(|input| input.ring.len()).collect();
             if ring_sizes.len() > 1 {
                 return Err(TxBuilderError::InvalidRingSize);
             }
         }
 
         let inputs: Vec<TxIn> = self
-            .input_credentials
+            .inputs
             .iter()
-            .map(|input_credential| TxIn {
-                ring: input_credential.ring.clone(),
-                proofs: input_credential.membership_proofs.clone(),
+            .map(|input| TxIn {
+                ring: input.ring.clone(),
+                proofs: input.membership_proofs.clone(),
             })
             .collect();
 
         let tx_prefix = TxPrefix::new(inputs, self.outputs.clone(), self.fee, self.tombstone_block);
 
-        let tx_prefix_hash = tx_prefix.hash();
-        let message = tx_prefix_hash.as_bytes();
-
-        let mut rings: Vec<Vec<(CompressedRistrettoPublic, CompressedCommitment)>> = Vec::new();
-        for input in &tx_prefix.inputs {
+        let mut inputs_to_sign: Vec<InputToSign> = Vec::new();
+        let mut onetime_private_keys: Vec<Option<RistrettoPrivate>> = Vec::new();
+        for (input, builder_input) in tx_prefix.inputs.iter().zip(&self.inputs) {
             let ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)> = input
                 .ring
                 .iter()
                 .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
                 .collect();
-            rings.push(ring);
-        }
 
-        let real_input_indices: Vec<usize> = self
-            .input_credentials
-            .iter()
-            .map(|input_credential| input_credential.real_index)
-            .collect();
-
-        // One-time private key, amount value, and amount blinding for each real input.
-        let mut input_secrets: Vec<(RistrettoPrivate, u64, Scalar)> = Vec::new();
-        for input_credential in &self.input_credentials {
-            let onetime_private_key = input_credential.onetime_private_key;
-            let amount = &input_credential.ring[input_credential.real_index].amount;
+            // Amount value and blinding of the real input.
+            let amount = &builder_input.ring[builder_input.real_index].amount;
             let shared_secret = compute_shared_secret(
-                &input_credential.real_output_public_key,
-                &input_credential.view_private_key,
+                &builder_input.real_output_public_key,
+                &builder_input.view_private_key,
             );
             let (value, blinding) = amount.get_value(&shared_secret)?;
-            input_secrets.push((onetime_private_key, value, blinding.into()));
+
+            inputs_to_sign.push(InputToSign {
+                ring,
+                real_index: builder_input.real_index,
+                real_output_public_key: builder_input.real_output_public_key,
+                value,
+                blinding: blinding.into(),
+            });
+            onetime_private_keys.push(builder_input.onetime_private_key);
         }
 
         let mut output_values_and_blindings: Vec<(u64, Scalar)> = tx_prefix
@@ -169,17 +208,190 @@ impl TransactionBuilder {
         // The fee output is implicit in the tx_prefix.
         output_values_and_blindings.push(tx_prefix.fee_value_and_blinding());
 
+        Ok(UnsignedTx {
+            prefix: tx_prefix,
+            inputs: inputs_to_sign,
+            output_values_and_blindings,
+            onetime_private_keys,
+        })
+    }
+}
+
+/// An input added to a `TransactionBuilder`, with its ring shuffled.
+#[derive(Debug)]
+struct BuilderInput {
+    ring: Vec<TxOut>,
+    membership_proofs: Vec<TxOutMembershipProof>,
+    real_index: usize,
+    real_output_public_key: RistrettoPublic,
+    view_private_key: RistrettoPrivate,
+
+    /// None for inputs that are signed elsewhere.
+    onetime_private_key: Option<RistrettoPrivate>,
+}
+
+/// A real input of an unsigned transaction, along with what is needed to sign its ring.
+#[derive(Clone, Debug)]
+pub struct InputToSign {
+    /// The ring of one-time addresses and amount commitments.
+    pub ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)>,
+
+    /// Index in `ring` of the real input.
+    pub real_index: usize,
+
+    /// Public key of the transaction that created the real input. Together with the owner's
+    /// view private key and subaddress spend private key, it yields the input's one-time private
+    /// key.
+    pub real_output_public_key: RistrettoPublic,
+
+    /// Value of the real input.
+    pub value: u64,
+
+    /// Blinding of the real input's amount commitment.
+    pub blinding: Scalar,
+}
+
+/// A transaction whose contents are final, but whose inputs are not signed yet.
+#[derive(Clone, Debug)]
+pub struct UnsignedTx {
+    /// The prefix of the transaction.
+    pub prefix: TxPrefix,
+
+    /// The inputs, in the order of `prefix.inputs`.
+    pub inputs: Vec<InputToSign>,
+
+    /// Value and blinding of each output amount commitment, followed by the fee's.
+    output_values_and_blindings: Vec<(u64, Scalar)>,
+
+    /// One-time private key of each input, from the input credentials. None for inputs added
+    /// without one, and for all inputs of a transaction created with `UnsignedTx::new`.
+    onetime_private_keys: Vec<Option<RistrettoPrivate>>,
+}
+
+impl UnsignedTx {
+    /// Creates an unsigned transaction from its parts, e.g. after it was sent to another machine
+    /// to be signed there. It holds no one-time private keys, so it can only be signed with
+    /// `sign_with_ring_signatures`.
+    ///
+    /// # Arguments
+    /// * `prefix` - The prefix of the transaction.
+    /// * `inputs` - The inputs, in the order of `prefix.inputs`.
+    /// * `output_values_and_blindings` - Value and blinding of each output amount commitment,
+    ///   followed by the fee's, see `output_values_and_blindings`.
+    pub fn new(
+        prefix: TxPrefix,
+        inputs: Vec<InputToSign>,
+        output_values_and_blindings: Vec<(u64, Scalar)>,
+    ) -> Result<Self, TxBuilderError> {
+        if inputs.len() != prefix.inputs.len()
+            || output_values_and_blindings.len() != prefix.outputs.len() + 1
+            || output_values_and_blindings.last() != Some(&prefix.fee_value_and_blinding())
+        {
+            return Err(TxBuilderError::UnsignedTxMismatch);
+        }
+
+        // Each input must sign the ring it is spent from in the prefix.
+        for (input, tx_in) in inputs.iter().zip(&prefix.inputs) {
+            let ring: Vec<(CompressedRistrettoPublic, CompressedCommitment)> = tx_in
+                .ring
+                .iter()
+                .map(|tx_out| (tx_out.target_key, tx_out.amount.commitment))
+                .collect();
+            if input.ring != ring || input.real_index >= ring.len() {
+                return Err(TxBuilderError::UnsignedTxMismatch);
+            }
+        }
+
+        let onetime_private_keys = vec![None; inputs.len()];
+        Ok(Self {
+            prefix,
+            inputs,
+            output_values_and_blindings,
+            onetime_private_keys,
+        })
+    }
+
+    /// Value and blinding of each output amount commitment, in the order of `prefix.outputs`,
+    /// followed by the value and blinding of the fee.
+    pub fn output_values_and_blindings(&self) -> &[(u64, Scalar)] {
+        &self.output_values_and_blindings
+    }
+
+    /// The message the ring signatures sign, i.e. the hash of the prefix.
+    pub fn message(&self) -> [u8; 32] {
+        *self.prefix.hash().as_bytes()
+    }
+
+    /// Blindings for the pseudo-output of each input. Ring signatures computed elsewhere must
+    /// be signed with these, and passed to `sign_with_ring_signatures` along with them.
+    pub fn pseudo_output_blindings<RNG: CryptoRng + RngCore>(&self, rng: &mut RNG) -> Vec<Scalar> {
+        SignatureRctBulletproofs::pseudo_output_blindings(
+            self.inputs.len(),
+            &self.output_values_and_blindings,
+            rng,
+        )
+    }
+
+    /// Sign the inputs with the one-time private keys of the input credentials. Fails if an input
+    /// was added without one.
+    pub fn sign<RNG: CryptoRng + RngCore>(self, rng: &mut RNG) -> Result<Tx, TxBuilderError> {
+        let rings: Vec<Vec<(CompressedRistrettoPublic, CompressedCommitment)>> =
+            self.inputs.iter().map(|input| input.ring.clone()).collect();
+        let real_input_indices: Vec<usize> =
+            self.inputs.iter().map(|input| input.real_index).collect();
+
+        // One-time private key, amount value, and amount blinding for each real input.
+        let input_secrets: Vec<(RistrettoPrivate, u64, Scalar)> = self
+            .inputs
+            .iter()
+            .zip(&self.onetime_private_keys)
+            .map(|(input, onetime_private_key)| {
+                onetime_private_key
+                    .as_ref()
+                    .map(|onetime_private_key| (*onetime_private_key, input.value, input.blinding))
+                    .ok_or(TxBuilderError::MissingOnetimePrivateKey)
+            })
+            .collect::<Result<_, _>>()?;
+
         let signature = SignatureRctBulletproofs::sign(
-            message,
+            &self.message(),
             &rings,
             &real_input_indices,
             &input_secrets,
-            &output_values_and_blindings,
+            &self.output_values_and_blindings,
             rng,
         )?;
 
         Ok(Tx {
-            prefix: tx_prefix,
+            prefix: self.prefix,
+            signature,
+        })
+    }
+
+    /// Complete the transaction with ring signatures computed elsewhere.
+    ///
+    /// # Arguments
+    /// * `ring_signatures` - The ring signature of each input, in order.
+    /// * `pseudo_output_blindings` - The blindings the ring signatures were signed with, from
+    ///   `pseudo_output_blindings`.
+    /// * `rng` - Randomness for the range proofs.
+    pub fn sign_with_ring_signatures<RNG: CryptoRng + RngCore>(
+        self,
+        ring_signatures: Vec<RingMLSAG>,
+        pseudo_output_blindings: &[Scalar],
+        rng: &mut RNG,
+    ) -> Result<Tx, TxBuilderError> {
+        let input_values: Vec<u64> = self.inputs.iter().map(|input| input.value).collect();
+        let signature = SignatureRctBulletproofs::from_ring_signatures(
+            ring_signatures,
+            &input_values,
+            pseudo_output_blindings,
+            &self.output_values_and_blindings,
+            rng,
+        )?;
+
+        Ok(Tx {
+            prefix: self.prefix,
             signature,
         })
     }
@@ -321,7 +533,7 @@ pub mod transaction_builder_tests {
 
         let mut transaction_builder = TransactionBuilder::new();
         transaction_builder.add_input(input_credentials);
-        transaction_builder
+        let (_tx_out, confirmation_number) = transaction_builder
             .add_output(value - BASE_FEE, &bob.default_subaddress(), None, &mut rng)
             .unwrap();
 
@@ -357,10 +569,163 @@ pub mod transaction_builder_tests {
             assert_eq!(output_value, value - BASE_FEE);
         }
 
+        // Bob should be able to validate the output's confirmation number.
+        {
+            let public_key = RistrettoPublic::try_from(&output.public_key).unwrap();
+            assert!(confirmation_number.validate(&public_key, bob.view_private_key()));
+        }
+
         // The transaction should have a valid signature.
         assert!(validate_transaction_signature(&tx, &mut rng).is_ok());
     }
 
+    #[test]
+    // A transaction built unsigned should be valid once completed with ring signatures computed
+    // from its inputs.
+    fn test_sign_with_ring_signatures() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let value = 1475;
+
+        let (ring, real_index) = get_ring(3, &alice, value, &mut rng);
+        let real_output = ring[real_index].clone();
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&real_output.public_key).unwrap(),
+            &alice.view_private_key(),
+            &alice.subaddress_spend_key(DEFAULT_SUBADDRESS_INDEX),
+        );
+        let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+        let input_credentials = InputCredentials::new(
+            ring,
+            membership_proofs,
+            real_index,
+            onetime_private_key,
+            *alice.view_private_key(),
+            &mut rng,
+        )
+        .unwrap();
+
+        let mut transaction_builder = TransactionBuilder::new();
+        transaction_builder.add_input(input_credentials);
+        transaction_builder
+            .add_output(value - BASE_FEE, &bob.default_subaddress(), None, &mut rng)
+            .unwrap();
+        let unsigned_tx = transaction_builder.build_unsigned().unwrap();
+        assert_eq!(unsigned_tx.inputs.len(), 1);
+        assert_eq!(unsigned_tx.inputs[0].value, value);
+
+        // Sign the rings the way an external signer would, knowing only the one-time private key.
+        let message = unsigned_tx.message();
+        let pseudo_output_blindings = unsigned_tx.pseudo_output_blindings(&mut rng);
+        let ring_signatures: Vec<RingMLSAG> = unsigned_tx
+            .inputs
+            .iter()
+            .zip(&pseudo_output_blindings)
+            .map(|(input, pseudo_output_blinding)| {
+                RingMLSAG::sign(
+                    &message,
+                    &input.ring,
+                    input.real_index,
+                    &onetime_private_key,
+                    input.value,
+                    &input.blinding,
+                    pseudo_output_blinding,
+                    &mut rng,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let tx = unsigned_tx
+            .sign_with_ring_signatures(ring_signatures, &pseudo_output_blindings, &mut rng)
+            .unwrap();
+        assert_eq!(
+            tx.key_images(),
+            vec![compute_key_image(&onetime_private_key)]
+        );
+        assert!(validate_transaction_signature(&tx, &mut rng).is_ok());
+    }
+
+    #[test]
+    // A transaction built from the view private key alone can only be signed with ring signatures,
+    // also after being reassembled from its parts.
+    fn test_input_without_key() {
+        let mut rng: StdRng = SeedableRng::from_seed([1u8; 32]);
+        let alice = AccountKey::random(&mut rng);
+        let bob = AccountKey::random(&mut rng);
+        let value = 1475;
+
+        let (ring, real_index) = get_ring(3, &alice, value, &mut rng);
+        let real_output = ring[real_index].clone();
+        let membership_proofs = vec![TxOutMembershipProof::default(); ring.len()];
+
+        let mut transaction_builder = TransactionBuilder::new();
+        transaction_builder
+            .add_input_without_key(
+                ring,
+                membership_proofs,
+                real_index,
+                *alice.view_private_key(),
+                &mut rng,
+            )
+            .unwrap();
+        transaction_builder
+            .add_output(value - BASE_FEE, &bob.default_subaddress(), None, &mut rng)
+            .unwrap();
+        let unsigned_tx = transaction_builder.build_unsigned().unwrap();
+        assert_eq!(unsigned_tx.inputs[0].value, value);
+
+        match unsigned_tx.clone().sign(&mut rng) {
+            Err(TxBuilderError::MissingOnetimePrivateKey) => {} // Expected.
+            Err(err) => panic!("Unexpected error {:?}", err),
+            Ok(_) => panic!("Signed without a one-time private key"),
+        }
+
+        // The parts must match the prefix.
+        let mut other_inputs = unsigned_tx.inputs.clone();
+        other_inputs[0].ring.reverse();
+        match UnsignedTx::new(
+            unsigned_tx.prefix.clone(),
+            other_inputs,
+            unsigned_tx.output_values_and_blindings().to_vec(),
+        ) {
+            Err(TxBuilderError::UnsignedTxMismatch) => {} // Expected.
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let unsigned_tx = UnsignedTx::new(
+            unsigned_tx.prefix.clone(),
+            unsigned_tx.inputs.clone(),
+            unsigned_tx.output_values_and_blindings().to_vec(),
+        )
+        .unwrap();
+
+        let onetime_private_key = recover_onetime_private_key(
+            &RistrettoPublic::try_from(&real_output.public_key).unwrap(),
+            &alice.view_private_key(),
+            &alice.subaddress_spend_key(DEFAULT_SUBADDRESS_INDEX),
+        );
+        let message = unsigned_tx.message();
+        let pseudo_output_blindings = unsigned_tx.pseudo_output_blindings(&mut rng);
+        let input = &unsigned_tx.inputs[0];
+        let ring_signature = RingMLSAG::sign(
+            &message,
+            &input.ring,
+            input.real_index,
+            &onetime_private_key,
+            input.value,
+            &input.blinding,
+            &pseudo_output_blindings[0],
+            &mut rng,
+        )
+        .unwrap();
+
+        let tx = unsigned_tx
+            .sign_with_ring_signatures(vec![ring_signature], &pseudo_output_blindings, &mut rng)
+            .unwrap();
+        assert!(validate_transaction_signature(&tx, &mut rng).is_ok());
+    }
+
     #[test]
     #[ignore]
     // `build` should return an error if the inputs contain rings of different sizes.
@@ -161,7 +161,30 @@ pub fn run_server(
         server = server.register_service(service);
     }
 
-    let mut server = server.bind("0.0.0.0", port).build().unwrap();
+    start_server(server.bind("0.0.0.0", port), logger)
+}
+
+/// Like `run_server`, but only accepts TLS connections established with `credentials`.
+pub fn run_secure_server(
+    env: std::sync::Arc<grpcio::Environment>,
+    services: Vec<Service>,
+    port: u16,
+    credentials: grpcio::ServerCredentials,
+    logger: &Logger,
+) -> Server {
+    use grpcio::ServerBuilder;
+
+    let mut server = ServerBuilder::new(env);
+
+    for service in services {
+        server = server.register_service(service);
+    }
+
+    start_server(server.bind_with_cred("0.0.0.0", port, credentials), logger)
+}
+
+fn start_server(server: grpcio::ServerBuilder, logger: &Logger) -> Server {
+    let mut server = server.build().unwrap();
     server.start();
     for (host, port) in server.bind_addrs() {
         log::info!(logger, "API listening on {}:{}", host, port);
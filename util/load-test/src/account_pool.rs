@@ -0,0 +1,158 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! A pool of accounts that can be leased by load-test workers.
+//!
+//! An account can only have one transaction in flight at a time, since two concurrent
+//! transactions could attempt to spend the same inputs. Workers lease an account for the duration
+//! of a transaction, and the account is returned to the back of the pool when the lease is
+//! dropped, so that accounts are used round-robin.
+
+use std::{
+    collections::VecDeque,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+struct Inner<A> {
+    available: Mutex<VecDeque<A>>,
+    returned: Condvar,
+}
+
+/// A pool of accounts. Cloning produces a handle to the same pool.
+pub struct AccountPool<A> {
+    inner: Arc<Inner<A>>,
+}
+
+impl<A> Clone for AccountPool<A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<A> AccountPool<A> {
+    pub fn new(accounts: Vec<A>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                available: Mutex::new(accounts.into_iter().collect()),
+                returned: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Leases the least recently used account, if one is available.
+    pub fn try_lease(&self) -> Option<AccountLease<A>> {
+        let mut available = self.inner.available.lock().expect("mutex poisoned");
+        available.pop_front().map(|account| AccountLease {
+            account: Some(account),
+            pool: self.clone(),
+        })
+    }
+
+    /// Leases the least recently used account, waiting up to `timeout` for one to be returned.
+    pub fn lease(&self, timeout: Duration) -> Option<AccountLease<A>> {
+        let deadline = Instant::now() + timeout;
+        let mut available = self.inner.available.lock().expect("mutex poisoned");
+        while available.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            available = self
+                .inner
+                .returned
+                .wait_timeout(available, deadline - now)
+                .expect("mutex poisoned")
+                .0;
+        }
+        available.pop_front().map(|account| AccountLease {
+            account: Some(account),
+            pool: self.clone(),
+        })
+    }
+
+    /// The number of accounts that are not currently leased.
+    pub fn num_available(&self) -> usize {
+        self.inner.available.lock().expect("mutex poisoned").len()
+    }
+
+    fn release(&self, account: A) {
+        let mut available = self.inner.available.lock().expect("mutex poisoned");
+        available.push_back(account);
+        self.inner.returned.notify_one();
+    }
+}
+
+/// An account leased from an `AccountPool`. The account is returned to the pool when the lease is
+/// dropped.
+pub struct AccountLease<A> {
+    account: Option<A>,
+    pool: AccountPool<A>,
+}
+
+impl<A> Deref for AccountLease<A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        self.account.as_ref().expect("lease already released")
+    }
+}
+
+impl<A> DerefMut for AccountLease<A> {
+    fn deref_mut(&mut self) -> &mut A {
+        self.account.as_mut().expect("lease already released")
+    }
+}
+
+impl<A> Drop for AccountLease<A> {
+    fn drop(&mut self) {
+        if let Some(account) = self.account.take() {
+            self.pool.release(account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    // Accounts should be leased round-robin, and returned when the lease is dropped.
+    fn test_lease_round_robin() {
+        let pool = AccountPool::new(vec![1, 2, 3]);
+
+        let lease = pool.try_lease().unwrap();
+        assert_eq!(*lease, 1);
+        assert_eq!(pool.num_available(), 2);
+        drop(lease);
+        assert_eq!(pool.num_available(), 3);
+
+        let leases: Vec<_> = (0..3).map(|_| pool.try_lease().unwrap()).collect();
+        assert_eq!(
+            leases.iter().map(|lease| **lease).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+        assert!(pool.try_lease().is_none());
+        assert!(pool.lease(Duration::from_millis(10)).is_none());
+    }
+
+    #[test]
+    fn test_lease_waits_for_release() {
+        let pool = AccountPool::new(vec![String::from("account")]);
+        let mut lease = pool.try_lease().unwrap();
+        lease.push_str("-modified");
+
+        let thread_pool = pool.clone();
+        let handle = thread::spawn(move || {
+            let lease = thread_pool.lease(Duration::from_secs(10)).unwrap();
+            lease.clone()
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        drop(lease);
+        assert_eq!(handle.join().unwrap(), "account-modified");
+    }
+}
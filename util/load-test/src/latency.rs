@@ -0,0 +1,93 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Latency recording.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Summary statistics over the recorded latencies.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Records the latency of every operation. Cloning produces a handle to the same recorder.
+#[derive(Clone, Default)]
+pub struct LatencyRecorder {
+    samples: Arc<Mutex<Vec<Duration>>>,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, latency: Duration) {
+        self.samples.lock().expect("mutex poisoned").push(latency);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.lock().expect("mutex poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Computes summary statistics over all recorded latencies.
+    pub fn summary(&self) -> LatencySummary {
+        let mut samples = self.samples.lock().expect("mutex poisoned").clone();
+        if samples.is_empty() {
+            return LatencySummary::default();
+        }
+        samples.sort();
+
+        let count = samples.len();
+        let total: Duration = samples.iter().sum();
+        // Nearest-rank percentile.
+        let percentile = |p: usize| samples[((count * p + 99) / 100).max(1) - 1];
+
+        LatencySummary {
+            count,
+            min: samples[0],
+            mean: total / count as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples[count - 1],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let recorder = LatencyRecorder::new();
+        assert_eq!(recorder.summary(), LatencySummary::default());
+
+        // Record 1..=100ms, in reverse order.
+        for ms in (1..=100u64).rev() {
+            recorder.record(Duration::from_millis(ms));
+        }
+
+        let summary = recorder.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.min, Duration::from_millis(1));
+        assert_eq!(summary.mean, Duration::from_micros(50_500));
+        assert_eq!(summary.p50, Duration::from_millis(50));
+        assert_eq!(summary.p90, Duration::from_millis(90));
+        assert_eq!(summary.p99, Duration::from_millis(99));
+        assert_eq!(summary.max, Duration::from_millis(100));
+    }
+}
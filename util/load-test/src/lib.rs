@@ -0,0 +1,26 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Building blocks for load-testing a MobileCoin network.
+//!
+//! A load test repeatedly leases an account from an [`AccountPool`](AccountPool), builds and
+//! submits a transaction with it, and records how long that took. The [`LoadRunner`](LoadRunner)
+//! drives this loop from a pool of worker threads, paced by a [`LoadMode`](LoadMode):
+//! * Open loop - transactions are started at a fixed rate, regardless of how long previous ones
+//!   took. This models independent clients, and exposes queueing delays.
+//! * Closed loop - a fixed number of transactions are kept in flight, and a new one is started as
+//!   soon as one completes. This measures the maximal sustainable throughput.
+//!
+//! The library knows nothing about transactions, so that the same machinery can drive consensus
+//! nodes directly, mobilecoind, or any other harness.
+
+mod account_pool;
+mod latency;
+mod runner;
+mod tps;
+
+pub use crate::{
+    account_pool::{AccountLease, AccountPool},
+    latency::{LatencyRecorder, LatencySummary},
+    runner::{LoadConfig, LoadReport, LoadRunner},
+    tps::{LoadMode, TpsController},
+};
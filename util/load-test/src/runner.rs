@@ -0,0 +1,311 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Drives a load test.
+
+use crate::{
+    latency::{LatencyRecorder, LatencySummary},
+    tps::{LoadMode, TpsController},
+};
+use common::logger::{log, Logger};
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Load test configuration.
+#[derive(Clone, Debug)]
+pub struct LoadConfig {
+    /// How load is generated.
+    pub mode: LoadMode,
+
+    /// Stop after this many operations have been started.
+    pub max_operations: Option<usize>,
+
+    /// Stop starting new operations after this much time has passed.
+    pub max_duration: Option<Duration>,
+}
+
+impl LoadConfig {
+    /// Whether no more operations should be started.
+    fn is_done(&self, start: Instant, num_started: usize) -> bool {
+        self.max_operations
+            .map_or(false, |max_operations| num_started >= max_operations)
+            || self
+                .max_duration
+                .map_or(false, |max_duration| start.elapsed() >= max_duration)
+    }
+}
+
+/// The outcome of a load test.
+#[derive(Clone, Debug)]
+pub struct LoadReport {
+    /// Number of operations that succeeded.
+    pub num_succeeded: usize,
+
+    /// Number of operations that failed.
+    pub num_failed: usize,
+
+    /// Time from the start of the test until the last operation completed.
+    pub elapsed: Duration,
+
+    /// Latency of the operations that succeeded.
+    pub latency: LatencySummary,
+}
+
+impl LoadReport {
+    /// The achieved rate of successful operations, per second.
+    pub fn tps(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        self.num_succeeded as f64 / elapsed
+    }
+}
+
+/// Runs an operation repeatedly according to a `LoadConfig`.
+pub struct LoadRunner {
+    config: LoadConfig,
+    logger: Logger,
+}
+
+impl LoadRunner {
+    pub fn new(config: LoadConfig, logger: Logger) -> Self {
+        assert!(
+            config.max_operations.is_some() || config.max_duration.is_some(),
+            "a load test needs a stopping condition"
+        );
+        Self { config, logger }
+    }
+
+    /// Runs the load test to completion.
+    ///
+    /// # Arguments
+    /// * `operation` - Called with the index of each operation, e.g. to build and submit a
+    ///   transaction. Called concurrently from multiple threads.
+    pub fn run<F, E>(&self, operation: F) -> LoadReport
+    where
+        F: Fn(usize) -> Result<(), E> + Send + Sync + 'static,
+        E: Display + 'static,
+    {
+        let operation = Arc::new(operation);
+        let recorder = LatencyRecorder::new();
+        let num_failed = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+
+        log::info!(self.logger, "Starting load test: {:?}", self.config);
+
+        match self.config.mode {
+            LoadMode::OpenLoop {
+                target_tps,
+                num_workers,
+            } => self.run_open_loop(
+                target_tps,
+                num_workers,
+                start,
+                operation,
+                &recorder,
+                &num_failed,
+            ),
+            LoadMode::ClosedLoop { concurrency } => {
+                self.run_closed_loop(concurrency, start, operation, &recorder, &num_failed)
+            }
+        }
+
+        let report = LoadReport {
+            num_succeeded: recorder.len(),
+            num_failed: num_failed.load(Ordering::SeqCst),
+            elapsed: start.elapsed(),
+            latency: recorder.summary(),
+        };
+        log::info!(
+            self.logger,
+            "Load test done: {} succeeded, {} failed, {:.2} TPS, latency {:?}",
+            report.num_succeeded,
+            report.num_failed,
+            report.tps(),
+            report.latency,
+        );
+        report
+    }
+
+    fn run_open_loop<F, E>(
+        &self,
+        target_tps: f64,
+        num_workers: usize,
+        start: Instant,
+        operation: Arc<F>,
+        recorder: &LatencyRecorder,
+        num_failed: &Arc<AtomicUsize>,
+    ) where
+        F: Fn(usize) -> Result<(), E> + Send + Sync + 'static,
+        E: Display + 'static,
+    {
+        let (sender, receiver) = crossbeam_channel::unbounded::<(usize, Instant)>();
+
+        let workers: Vec<_> = (0..num_workers.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                let operation = operation.clone();
+                let recorder = recorder.clone();
+                let num_failed = num_failed.clone();
+                let logger = self.logger.clone();
+                thread::spawn(move || {
+                    for (index, scheduled_start) in receiver.iter() {
+                        match operation(index) {
+                            Ok(()) => recorder.record(scheduled_start.elapsed()),
+                            Err(err) => {
+                                log::debug!(logger, "Operation {} failed: {}", index, err);
+                                num_failed.fetch_add(1, Ordering::SeqCst);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut controller = TpsController::new(target_tps);
+        let mut num_started = 0;
+        while !self.config.is_done(start, num_started) {
+            let scheduled_start = controller.wait();
+            sender
+                .send((num_started, scheduled_start))
+                .expect("workers exited early");
+            num_started += 1;
+        }
+
+        // Closing the channel lets the workers exit once it has been drained.
+        drop(sender);
+        for worker in workers {
+            worker.join().expect("load test worker panicked");
+        }
+    }
+
+    fn run_closed_loop<F, E>(
+        &self,
+        concurrency: usize,
+        start: Instant,
+        operation: Arc<F>,
+        recorder: &LatencyRecorder,
+        num_failed: &Arc<AtomicUsize>,
+    ) where
+        F: Fn(usize) -> Result<(), E> + Send + Sync + 'static,
+        E: Display + 'static,
+    {
+        let next_index = Arc::new(AtomicUsize::new(0));
+
+        let workers: Vec<_> = (0..concurrency.max(1))
+            .map(|_| {
+                let config = self.config.clone();
+                let next_index = next_index.clone();
+                let operation = operation.clone();
+                let recorder = recorder.clone();
+                let num_failed = num_failed.clone();
+                let logger = self.logger.clone();
+                thread::spawn(move || loop {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    if config.is_done(start, index) {
+                        break;
+                    }
+
+                    let operation_start = Instant::now();
+                    match operation(index) {
+                        Ok(()) => recorder.record(operation_start.elapsed()),
+                        Err(err) => {
+                            log::debug!(logger, "Operation {} failed: {}", index, err);
+                            num_failed.fetch_add(1, Ordering::SeqCst);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            worker.join().expect("load test worker panicked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use common::logger::test_with_logger;
+    use std::{collections::HashSet, sync::Mutex};
+
+    #[test_with_logger]
+    // Closed loop mode should run every operation exactly once, and count failures.
+    fn test_closed_loop(logger: Logger) {
+        let seen = Arc::new(Mutex::new(HashSet::new()));
+
+        let runner = LoadRunner::new(
+            LoadConfig {
+                mode: LoadMode::ClosedLoop { concurrency: 4 },
+                max_operations: Some(100),
+                max_duration: None,
+            },
+            logger,
+        );
+        let thread_seen = seen.clone();
+        let report = runner.run(move |index| {
+            assert!(thread_seen.lock().unwrap().insert(index));
+            if index % 10 == 0 {
+                Err("fail")
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(seen.lock().unwrap().len(), 100);
+        assert_eq!(report.num_succeeded, 90);
+        assert_eq!(report.num_failed, 10);
+        assert_eq!(report.latency.count, 90);
+    }
+
+    #[test_with_logger]
+    // Open loop mode should start operations at the target rate.
+    fn test_open_loop(logger: Logger) {
+        let runner = LoadRunner::new(
+            LoadConfig {
+                mode: LoadMode::OpenLoop {
+                    target_tps: 200.0,
+                    num_workers: 2,
+                },
+                max_operations: Some(21),
+                max_duration: None,
+            },
+            logger,
+        );
+        let report = runner.run(|_index| -> Result<(), String> { Ok(()) });
+
+        assert_eq!(report.num_succeeded, 21);
+        assert_eq!(report.num_failed, 0);
+        // 21 operations at 200 TPS span 100ms.
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(report.tps() <= 210.0);
+    }
+
+    #[test_with_logger]
+    fn test_max_duration(logger: Logger) {
+        let runner = LoadRunner::new(
+            LoadConfig {
+                mode: LoadMode::ClosedLoop { concurrency: 2 },
+                max_operations: None,
+                max_duration: Some(Duration::from_millis(100)),
+            },
+            logger,
+        );
+        let report = runner.run(|_index| -> Result<(), String> {
+            thread::sleep(Duration::from_millis(10));
+            Ok(())
+        });
+
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(report.num_succeeded > 0);
+        assert!(report.num_succeeded <= 22);
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Pacing of load-test operations.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How a load test generates load.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadMode {
+    /// Start `target_tps` operations per second, regardless of how long each operation takes.
+    /// Operations are handed to a pool of `num_workers` threads, and are timed from when they
+    /// were scheduled to start, so that time spent waiting for a free worker is included in the
+    /// measured latency.
+    OpenLoop { target_tps: f64, num_workers: usize },
+
+    /// Keep `concurrency` operations in flight at all times, starting a new operation as soon as
+    /// one completes.
+    ClosedLoop { concurrency: usize },
+}
+
+/// Schedules operations at a fixed rate.
+///
+/// The schedule is kept even if the caller falls behind: operations that are late are released
+/// immediately, so that the average rate stays on target.
+pub struct TpsController {
+    interval: Duration,
+    next_start: Instant,
+}
+
+impl TpsController {
+    pub fn new(target_tps: f64) -> Self {
+        assert!(target_tps > 0.0, "target_tps must be positive");
+        Self {
+            interval: Duration::from_secs_f64(1.0 / target_tps),
+            next_start: Instant::now(),
+        }
+    }
+
+    /// Blocks until the next operation is due, and returns the time it was scheduled to start.
+    pub fn wait(&mut self) -> Instant {
+        let scheduled_start = self.next_start;
+        let now = Instant::now();
+        if scheduled_start > now {
+            thread::sleep(scheduled_start - now);
+        }
+        self.next_start += self.interval;
+        scheduled_start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_wait_paces_operations() {
+        let start = Instant::now();
+        let mut controller = TpsController::new(100.0);
+
+        let scheduled: Vec<Instant> = (0..11).map(|_| controller.wait()).collect();
+
+        // 11 operations at 100 TPS span 100ms.
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        for pair in scheduled.windows(2) {
+            assert_eq!(pair[1] - pair[0], Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_wait_catches_up() {
+        let mut controller = TpsController::new(100.0);
+        thread::sleep(Duration::from_millis(50));
+
+        // Operations that fell behind schedule are released without waiting.
+        let start = Instant::now();
+        for _ in 0..5 {
+            controller.wait();
+        }
+        assert!(start.elapsed() < Duration::from_millis(40));
+    }
+}
@@ -21,45 +21,7 @@ pub struct JsonEncoder;
 
 impl Encoder for JsonEncoder {
     fn encode<W: Write>(&self, metric_familys: &[MetricFamily], writer: &mut W) -> Result<()> {
-        let mut export_me: HashMap<String, f64> = HashMap::default();
-
-        for mf in metric_familys {
-            let name = mf.get_name();
-            let metric_type = mf.get_field_type();
-
-            for m in mf.get_metric() {
-                match metric_type {
-                    MetricType::COUNTER => {
-                        export_me.insert(
-                            flatten_metric_with_labels(name, m),
-                            m.get_counter().get_value(),
-                        );
-                    }
-                    MetricType::GAUGE => {
-                        export_me.insert(
-                            flatten_metric_with_labels(name, m),
-                            m.get_gauge().get_value(),
-                        );
-                    }
-                    MetricType::HISTOGRAM => {
-                        // write the sum and counts
-                        let h = m.get_histogram();
-                        export_me.insert(
-                            flatten_metric_with_labels(&format!("{}_count", name), m),
-                            h.get_sample_count() as f64,
-                        );
-                        export_me.insert(
-                            flatten_metric_with_labels(&format!("{}_sum", name), m),
-                            h.get_sample_sum(),
-                        );
-                    }
-                    _ => {
-                        // do nothing; unimplemented
-                    }
-                }
-            }
-        }
-
+        let export_me = flatten_metric_families(metric_familys);
         writer.write_all(serde_json::to_string_pretty(&export_me).unwrap().as_bytes())?;
         Ok(())
     }
@@ -69,6 +31,51 @@ impl Encoder for JsonEncoder {
     }
 }
 
+/// Flattens metric families into a map of flat metric names to values. Counters and gauges are
+/// exported as is, histograms as their count and sum, and other metrics are skipped.
+pub(crate) fn flatten_metric_families(metric_familys: &[MetricFamily]) -> HashMap<String, f64> {
+    let mut export_me: HashMap<String, f64> = HashMap::default();
+
+    for mf in metric_familys {
+        let name = mf.get_name();
+        let metric_type = mf.get_field_type();
+
+        for m in mf.get_metric() {
+            match metric_type {
+                MetricType::COUNTER => {
+                    export_me.insert(
+                        flatten_metric_with_labels(name, m),
+                        m.get_counter().get_value(),
+                    );
+                }
+                MetricType::GAUGE => {
+                    export_me.insert(
+                        flatten_metric_with_labels(name, m),
+                        m.get_gauge().get_value(),
+                    );
+                }
+                MetricType::HISTOGRAM => {
+                    // write the sum and counts
+                    let h = m.get_histogram();
+                    export_me.insert(
+                        flatten_metric_with_labels(&format!("{}_count", name), m),
+                        h.get_sample_count() as f64,
+                    );
+                    export_me.insert(
+                        flatten_metric_with_labels(&format!("{}_sum", name), m),
+                        h.get_sample_sum(),
+                    );
+                }
+                _ => {
+                    // do nothing; unimplemented
+                }
+            }
+        }
+    }
+
+    export_me
+}
+
 /**
 This method takes Prometheus metrics with dimensions (represented as label:value tags)
 and converts it into a dot-separated string.
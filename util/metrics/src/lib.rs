@@ -11,11 +11,16 @@ pub use serde_json::json;
 mod json_encoder;
 mod op_counters;
 mod service_metrics;
+mod sinks;
 
 pub use json_encoder::JsonEncoder as MetricsJsonEncoder;
 pub use op_counters::OpMetrics;
 pub use prometheus::{Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
 pub use service_metrics::ServiceMetrics;
+pub use sinks::{
+    register_metrics_sink_factory, MetricsReporter, MetricsSink, MetricsSinkFactory, StatsdSink,
+    DEFAULT_METRICS_INTERVAL,
+};
 
 lazy_static! {
     pub static ref SVC_COUNTERS: ServiceMetrics = ServiceMetrics::new_and_registered();
@@ -0,0 +1,291 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Pluggable metrics sinks.
+//!
+//! Besides being scraped, the registered Prometheus metrics can be pushed to other telemetry
+//! stacks. A `MetricsReporter` gathers them at a fixed interval and hands them to every sink.
+//! `MetricsReporter::start_from_env` builds the sinks listed in `MC_METRICS_SINKS`, as
+//! comma-separated `name=argument` pairs, e.g. `MC_METRICS_SINKS=statsd=127.0.0.1:8125`, and
+//! reports every `MC_METRICS_INTERVAL` seconds (10 by default). `statsd` is built in, and binaries
+//! may register factories of their own, e.g. for an OTLP exporter, with
+//! `register_metrics_sink_factory`.
+
+use crate::json_encoder::flatten_metric_families;
+use common::logger::{log, sinks::parse_sink_specs, Logger};
+use lazy_static::lazy_static;
+use prometheus::proto::MetricFamily;
+use std::{
+    collections::HashMap,
+    env, io, net,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The default reporting interval.
+pub const DEFAULT_METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A destination for metrics.
+pub trait MetricsSink: Send + Sync {
+    /// Report the current value of the given metrics.
+    fn report(&self, metric_families: &[MetricFamily]) -> io::Result<()>;
+}
+
+/// Builds a sink from the argument given to it in `MC_METRICS_SINKS`.
+pub type MetricsSinkFactory = fn(&str) -> Result<Box<dyn MetricsSink>, String>;
+
+lazy_static! {
+    static ref METRICS_SINK_FACTORIES: Mutex<HashMap<String, MetricsSinkFactory>> = {
+        let mut factories = HashMap::new();
+        factories.insert(
+            "statsd".to_string(),
+            create_statsd_sink as MetricsSinkFactory,
+        );
+        Mutex::new(factories)
+    };
+}
+
+/// Register a factory that `MC_METRICS_SINKS` can refer to by name, replacing the factory of the
+/// same name, if any.
+pub fn register_metrics_sink_factory(name: &str, factory: MetricsSinkFactory) {
+    METRICS_SINK_FACTORIES
+        .lock()
+        .expect("mutex poisoned")
+        .insert(name.to_string(), factory);
+}
+
+fn create_statsd_sink(destination_host_port: &str) -> Result<Box<dyn MetricsSink>, String> {
+    // Metrics are prefixed with the name of the binary, like log messages are tagged with it.
+    let prefix = env::current_exe()
+        .ok()
+        .and_then(|path| path.file_name()?.to_str().map(|name| format!("{}.", name)))
+        .unwrap_or_default();
+    let sink = StatsdSink::new(destination_host_port, &prefix).map_err(|err| err.to_string())?;
+    Ok(Box::new(sink))
+}
+
+/// Max size of the statsd payload sent in a single UDP packet, which keeps packets within the MTU
+/// of most networks.
+const STATSD_MAX_PACKET_SIZE: usize = 1432;
+
+/// Sends metrics to a statsd server over UDP. Every metric is sent as a gauge, since Prometheus
+/// counters hold running totals rather than increments. Labels are folded into the metric name,
+/// as `MetricsJsonEncoder` does.
+pub struct StatsdSink {
+    socket: net::UdpSocket,
+    destination: net::SocketAddr,
+    prefix: String,
+}
+
+impl StatsdSink {
+    pub fn new(destination_host_port: &str, prefix: &str) -> io::Result<Self> {
+        let destination = destination_host_port
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let socket = net::UdpSocket::bind("0.0.0.0:0")?;
+        Ok(Self {
+            socket,
+            destination,
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// Formats metrics as statsd gauges, sorted by name.
+    fn lines(&self, metric_families: &[MetricFamily]) -> Vec<String> {
+        let mut lines: Vec<String> = flatten_metric_families(metric_families)
+            .into_iter()
+            .map(|(name, value)| {
+                // ':', '|' and '@' separate the fields of a statsd line.
+                let name = name.replace(|c: char| c == ':' || c == '|' || c == '@', "_");
+                format!("{}{}:{}|g", self.prefix, name, value)
+            })
+            .collect();
+        lines.sort();
+        lines
+    }
+}
+
+impl MetricsSink for StatsdSink {
+    fn report(&self, metric_families: &[MetricFamily]) -> io::Result<()> {
+        let mut packet = String::new();
+        for line in self.lines(metric_families) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > STATSD_MAX_PACKET_SIZE {
+                self.socket.send_to(packet.as_bytes(), self.destination)?;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.socket.send_to(packet.as_bytes(), self.destination)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reports the registered Prometheus metrics to a set of sinks from a background thread, until
+/// it is stopped or dropped.
+pub struct MetricsReporter {
+    stop_requested: Arc<AtomicBool>,
+    join_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MetricsReporter {
+    /// Start reporting to the given sinks every `interval`. Sinks that fail are logged and
+    /// retried on the next report.
+    pub fn start(sinks: Vec<Box<dyn MetricsSink>>, interval: Duration, logger: Logger) -> Self {
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let thread_stop_requested = stop_requested.clone();
+
+        let join_handle = thread::Builder::new()
+            .name("metrics-reporter".into())
+            .spawn(move || loop {
+                let deadline = Instant::now() + interval;
+                while !thread_stop_requested.load(Ordering::SeqCst) {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+                if thread_stop_requested.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let metric_families = prometheus::gather();
+                for sink in &sinks {
+                    if let Err(err) = sink.report(&metric_families) {
+                        log::warn!(logger, "Failed reporting metrics: {}", err);
+                    }
+                }
+            })
+            .expect("Failed spawning metrics reporter thread");
+
+        Self {
+            stop_requested,
+            join_handle: Some(join_handle),
+        }
+    }
+
+    /// Start reporting to the sinks listed in `MC_METRICS_SINKS`, if any.
+    pub fn start_from_env(logger: Logger) -> Option<Self> {
+        let specs = env::var("MC_METRICS_SINKS").ok()?;
+        let interval = env::var("MC_METRICS_INTERVAL")
+            .ok()
+            .map(|secs| {
+                secs.parse()
+                    .map(Duration::from_secs)
+                    .unwrap_or_else(|_| panic!("invalid MC_METRICS_INTERVAL: {}", secs))
+            })
+            .unwrap_or(DEFAULT_METRICS_INTERVAL);
+
+        let factories = METRICS_SINK_FACTORIES.lock().expect("mutex poisoned");
+        let sinks: Vec<Box<dyn MetricsSink>> = parse_sink_specs(&specs)
+            .unwrap_or_else(|err| panic!("MC_METRICS_SINKS: {}", err))
+            .into_iter()
+            .map(|(name, arg)| {
+                let factory = factories
+                    .get(&name)
+                    .unwrap_or_else(|| panic!("unknown metrics sink: {}", name));
+                factory(&arg)
+                    .unwrap_or_else(|err| panic!("failed creating metrics sink {}: {}", name, err))
+            })
+            .collect();
+        if sinks.is_empty() {
+            return None;
+        }
+
+        log::info!(
+            logger,
+            "Reporting metrics to {} sinks every {:?}",
+            sinks.len(),
+            interval
+        );
+        Some(Self::start(sinks, interval, logger))
+    }
+
+    /// Stop reporting, and wait for the reporter thread to exit.
+    pub fn stop(&mut self) {
+        self.stop_requested.store(true, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.thread().unpark();
+            join_handle
+                .join()
+                .expect("Failed joining metrics reporter thread");
+        }
+    }
+}
+
+impl Drop for MetricsReporter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::logger::create_null_logger;
+    use prometheus::{core::Collector, IntCounterVec, IntGauge, Opts};
+
+    #[test]
+    fn test_statsd_sink() {
+        let receiver = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let sink = StatsdSink::new(&receiver.local_addr().unwrap().to_string(), "test.").unwrap();
+
+        let counter = IntCounterVec::new(Opts::new("requests", "Requests"), &["method"]).unwrap();
+        counter.with_label_values(&["get:all"]).inc_by(3);
+        let gauge = IntGauge::new("peers", "Peers").unwrap();
+        gauge.set(7);
+
+        let mut metric_families = counter.collect();
+        metric_families.extend(gauge.collect());
+        sink.report(&metric_families).unwrap();
+
+        let mut buf = [0u8; STATSD_MAX_PACKET_SIZE];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf[..len]).unwrap(),
+            "test.peers:7|g\ntest.requests.get_all:3|g"
+        );
+    }
+
+    /// Counts the reports it receives.
+    struct CountingSink(Arc<Mutex<usize>>);
+
+    impl MetricsSink for CountingSink {
+        fn report(&self, _metric_families: &[MetricFamily]) -> io::Result<()> {
+            *self.0.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reporter_reports_until_stopped() {
+        let num_reports = Arc::new(Mutex::new(0));
+        let mut reporter = MetricsReporter::start(
+            vec![Box::new(CountingSink(num_reports.clone()))],
+            Duration::from_millis(10),
+            create_null_logger(),
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while *num_reports.lock().unwrap() < 2 {
+            assert!(Instant::now() < deadline, "no reports");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        reporter.stop();
+        let num_reports_at_stop = *num_reports.lock().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(*num_reports.lock().unwrap(), num_reports_at_stop);
+    }
+}
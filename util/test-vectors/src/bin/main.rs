@@ -0,0 +1,35 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+use std::{fs, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct Config {
+    /// Number of accounts to derive.
+    #[structopt(short, long, default_value = "3")]
+    pub num: usize,
+
+    /// Seed to derive the vectors from (e.g. 1234567812345678123456781234567812345678123456781234567812345678).
+    #[structopt(short, long, parse(try_from_str=hex::FromHex::from_hex))]
+    pub seed: Option<[u8; 32]>,
+
+    /// File to write the vectors to. Defaults to stdout.
+    #[structopt(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+fn main() {
+    let config = Config::from_args();
+    assert_ne!(0, config.num, "At least one account is required");
+
+    let vectors = test_vectors::generate(
+        config.seed.unwrap_or(test_vectors::DEFAULT_SEED),
+        config.num,
+    );
+    let json = serde_json::to_string_pretty(&vectors).expect("failed serializing test vectors");
+
+    match config.output {
+        Some(path) => fs::write(&path, json).expect("failed writing test vectors"),
+        None => println!("{}", json),
+    }
+}
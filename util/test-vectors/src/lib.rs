@@ -0,0 +1,336 @@
+// Copyright (c) 2018-2020 MobileCoin Inc.
+
+//! Canonical test vectors for the MobileCoin cryptosystem.
+//!
+//! Implementations in other languages can check that they derive the same keys, amounts and
+//! transactions as this crate, byte for byte. All vectors are derived from a single seed, so
+//! generating them again with the same seed gives identical output.
+//!
+//! Keys, points and scalars are encoded as 32 bytes of hex. Transactions and outputs are encoded
+//! as hex of their protobuf encoding.
+
+use keys::{FromRandom, RistrettoPrivate, RistrettoPublic};
+use ledger_db::Ledger;
+use mcserial::ReprBytes32;
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_hc::Hc128Rng as FixedRng;
+use serde::Serialize;
+use transaction::{
+    account_keys::{AccountKey, DEFAULT_SUBADDRESS_INDEX},
+    amount::Amount,
+    get_tx_out_shared_secret,
+    onetime_keys::{
+        compute_key_image, compute_tx_pubkey, create_onetime_public_key,
+        recover_onetime_private_key,
+    },
+    ring_signature::Blinding,
+};
+use transaction_std::identity::RootIdentity;
+use transaction_test_utils::{create_ledger, create_transaction, initialize_ledger};
+
+/// Seed used when none is given.
+pub const DEFAULT_SEED: [u8; 32] = [1; 32];
+
+/// Subaddress indexes included for every account.
+pub const SUBADDRESS_INDEXES: [u64; 4] = [DEFAULT_SUBADDRESS_INDEX, 1, 2, u64::max_value()];
+
+/// Values included in the amount vectors.
+pub const AMOUNT_VALUES: [u64; 4] = [0, 1, 1_000_000_000_000, u64::max_value()];
+
+/// Tombstone block of the transaction vectors.
+pub const TOMBSTONE_BLOCK: u64 = 100;
+
+/// All test vectors derived from one seed.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TestVectors {
+    /// The seed of the RNG all vectors were derived with.
+    pub seed: String,
+    pub accounts: Vec<AccountVector>,
+    pub outputs: Vec<OutputVector>,
+    pub amounts: Vec<AmountVector>,
+    pub transactions: Vec<TransactionVector>,
+}
+
+/// An account derived from root entropy, and some of its subaddresses.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AccountVector {
+    pub root_entropy: String,
+    /// `a`
+    pub view_private_key: String,
+    /// `b`
+    pub spend_private_key: String,
+    pub subaddresses: Vec<SubaddressVector>,
+}
+
+/// The keys of a subaddress.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SubaddressVector {
+    pub index: u64,
+    /// `c = a * d`
+    pub view_private_key: String,
+    /// `d = Hs(a || n) + b`
+    pub spend_private_key: String,
+    /// `C = c * G`
+    pub view_public_key: String,
+    /// `D = d * G`
+    pub spend_public_key: String,
+}
+
+/// An output sent to a subaddress, and the keys its owner recovers from it.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OutputVector {
+    /// The receiving account, as an index into `accounts`.
+    pub account_index: usize,
+    pub subaddress_index: u64,
+    /// `r`
+    pub tx_private_key: String,
+    /// `R = r * D`
+    pub tx_public_key: String,
+    /// `P = Hs(r * C) * G + D`
+    pub onetime_public_key: String,
+    /// `a * R`, which equals `r * C`.
+    pub shared_secret: String,
+    /// `x = Hs(a * R) + d`
+    pub onetime_private_key: String,
+    /// `I = x * Hp(P)`
+    pub key_image: String,
+}
+
+/// A commitment to a value, masked with a shared secret.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AmountVector {
+    pub value: u64,
+    pub blinding: String,
+    pub shared_secret: String,
+    pub commitment: String,
+    pub masked_value: u64,
+    pub masked_blinding: String,
+}
+
+/// A complete signed transaction from one account to another.
+///
+/// The sender owns every output of the origin block the transaction spends from, so the ring is
+/// made of the sender's outputs. The transaction spends the first of them, and sends its value,
+/// less the fee, to the recipient's default subaddress.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TransactionVector {
+    /// The sender, as an index into `accounts`.
+    pub sender_account_index: usize,
+    /// The recipient, as an index into `accounts`.
+    pub recipient_account_index: usize,
+    /// The output that is spent.
+    pub spent_tx_out: String,
+    pub tx: String,
+    pub tx_hash: String,
+    pub key_images: Vec<String>,
+    pub fee: u64,
+    pub tombstone_block: u64,
+}
+
+/// Derives all test vectors from `seed`.
+///
+/// # Arguments
+/// * `seed` - Seed of the RNG used for all randomness.
+/// * `num_accounts` - Number of accounts to derive. Each of them sends a transaction to the next.
+pub fn generate(seed: [u8; 32], num_accounts: usize) -> TestVectors {
+    let mut rng: FixedRng = SeedableRng::from_seed(seed);
+
+    let identities: Vec<RootIdentity> = (0..num_accounts)
+        .map(|_| RootIdentity::random(&mut rng, None))
+        .collect();
+    let account_keys: Vec<AccountKey> = identities.iter().map(AccountKey::from).collect();
+
+    let accounts = identities
+        .iter()
+        .zip(&account_keys)
+        .map(|(identity, account_key)| account_vector(identity, account_key))
+        .collect();
+
+    let mut outputs = Vec::new();
+    for (account_index, account_key) in account_keys.iter().enumerate() {
+        for subaddress_index in SUBADDRESS_INDEXES.iter() {
+            outputs.push(output_vector(
+                account_index,
+                account_key,
+                *subaddress_index,
+                &mut rng,
+            ));
+        }
+    }
+
+    let amounts = AMOUNT_VALUES
+        .iter()
+        .map(|value| amount_vector(*value, &mut rng))
+        .collect();
+
+    let mut transactions = Vec::new();
+    for sender_account_index in 0..num_accounts {
+        let recipient_account_index = (sender_account_index + 1) % num_accounts;
+        transactions.push(transaction_vector(
+            &account_keys,
+            sender_account_index,
+            recipient_account_index,
+            &mut rng,
+        ));
+    }
+
+    TestVectors {
+        seed: hex::encode(seed),
+        accounts,
+        outputs,
+        amounts,
+        transactions,
+    }
+}
+
+fn account_vector(identity: &RootIdentity, account_key: &AccountKey) -> AccountVector {
+    let subaddresses = SUBADDRESS_INDEXES
+        .iter()
+        .map(|index| {
+            let subaddress = account_key.subaddress(*index);
+            SubaddressVector {
+                index: *index,
+                view_private_key: to_hex(&account_key.subaddress_view_key(*index)),
+                spend_private_key: to_hex(&account_key.subaddress_spend_key(*index)),
+                view_public_key: to_hex(subaddress.view_public_key()),
+                spend_public_key: to_hex(subaddress.spend_public_key()),
+            }
+        })
+        .collect();
+
+    AccountVector {
+        root_entropy: hex::encode(identity.root_entropy),
+        view_private_key: to_hex(account_key.view_private_key()),
+        spend_private_key: to_hex(account_key.spend_private_key()),
+        subaddresses,
+    }
+}
+
+fn output_vector<R: CryptoRng + RngCore>(
+    account_index: usize,
+    account_key: &AccountKey,
+    subaddress_index: u64,
+    rng: &mut R,
+) -> OutputVector {
+    let recipient = account_key.subaddress(subaddress_index);
+    let tx_private_key = RistrettoPrivate::from_random(rng);
+    let tx_public_key = compute_tx_pubkey(&tx_private_key, recipient.spend_public_key());
+    let onetime_public_key = create_onetime_public_key(&recipient, &tx_private_key);
+
+    let shared_secret = get_tx_out_shared_secret(account_key.view_private_key(), &tx_public_key);
+    let onetime_private_key = recover_onetime_private_key(
+        &tx_public_key,
+        account_key.view_private_key(),
+        &account_key.subaddress_spend_key(subaddress_index),
+    );
+    let key_image = compute_key_image(&onetime_private_key);
+
+    OutputVector {
+        account_index,
+        subaddress_index,
+        tx_private_key: to_hex(&tx_private_key),
+        tx_public_key: to_hex(&tx_public_key),
+        onetime_public_key: to_hex(&onetime_public_key),
+        shared_secret: to_hex(&shared_secret),
+        onetime_private_key: to_hex(&onetime_private_key),
+        key_image: to_hex(&key_image),
+    }
+}
+
+fn amount_vector<R: CryptoRng + RngCore>(value: u64, rng: &mut R) -> AmountVector {
+    let blinding = Blinding::from_random(rng);
+    let shared_secret = RistrettoPublic::from_random(rng);
+    let amount = Amount::new(value, blinding, &shared_secret).expect("failed creating amount");
+
+    AmountVector {
+        value,
+        blinding: to_hex(&blinding),
+        shared_secret: to_hex(&shared_secret),
+        commitment: to_hex(&amount.commitment),
+        masked_value: amount.masked_value,
+        masked_blinding: to_hex(&amount.masked_blinding),
+    }
+}
+
+fn transaction_vector<R: CryptoRng + RngCore>(
+    account_keys: &[AccountKey],
+    sender_account_index: usize,
+    recipient_account_index: usize,
+    rng: &mut R,
+) -> TransactionVector {
+    let sender = &account_keys[sender_account_index];
+    let recipient = account_keys[recipient_account_index].default_subaddress();
+
+    let mut ledger = create_ledger();
+    initialize_ledger(&mut ledger, 1, sender, rng);
+    let spent_tx_out = ledger
+        .get_block_contents(0)
+        .expect("failed reading origin block")
+        .outputs[0]
+        .clone();
+
+    let tx = create_transaction(
+        &mut ledger,
+        &spent_tx_out,
+        sender,
+        &recipient,
+        TOMBSTONE_BLOCK,
+        rng,
+    );
+
+    TransactionVector {
+        sender_account_index,
+        recipient_account_index,
+        spent_tx_out: hex::encode(mcserial::encode(&spent_tx_out)),
+        tx: hex::encode(mcserial::encode(&tx)),
+        tx_hash: hex::encode(tx.tx_hash().as_bytes()),
+        key_images: tx.key_images().iter().map(to_hex).collect(),
+        fee: tx.prefix.fee,
+        tombstone_block: tx.prefix.tombstone_block,
+    }
+}
+
+fn to_hex<T: ReprBytes32>(value: &T) -> String {
+    hex::encode(value.to_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+    use transaction::tx::Tx;
+
+    #[test]
+    // The same seed should give the same vectors, which should agree with each other.
+    fn test_generate() {
+        let vectors = generate([7u8; 32], 2);
+        assert_eq!(vectors, generate([7u8; 32], 2));
+        assert_ne!(vectors, generate([8u8; 32], 2));
+
+        assert_eq!(vectors.accounts.len(), 2);
+        assert_eq!(vectors.outputs.len(), 2 * SUBADDRESS_INDEXES.len());
+        assert_eq!(vectors.amounts.len(), AMOUNT_VALUES.len());
+        assert_eq!(vectors.transactions.len(), 2);
+
+        // An output's target key is the public key of its onetime private key.
+        for output in &vectors.outputs {
+            let onetime_private_key =
+                RistrettoPrivate::try_from(&hex::decode(&output.onetime_private_key).unwrap()[..])
+                    .unwrap();
+            assert_eq!(
+                to_hex(&RistrettoPublic::from(&onetime_private_key)),
+                output.onetime_public_key
+            );
+        }
+
+        // The encoded transaction should decode to one with the listed key images.
+        for transaction in &vectors.transactions {
+            let tx: Tx = mcserial::decode(&hex::decode(&transaction.tx).unwrap()).unwrap();
+            assert_eq!(
+                tx.key_images().iter().map(to_hex).collect::<Vec<_>>(),
+                transaction.key_images
+            );
+            assert_eq!(hex::encode(tx.tx_hash().as_bytes()), transaction.tx_hash);
+        }
+    }
+}